@@ -3,7 +3,7 @@
 //! Command-line interface for publishing, searching, and installing Gen modules
 
 use clap::{Parser, Subcommand};
-use gen_registry::{GenModule, Registry, RegistryConfig, SearchQuery};
+use gen_registry::{GenModule, Registry, RegistryConfig, SchemaLoader, SchemaPackage, SearchQuery};
 use std::path::PathBuf;
 use tracing::{error, info};
 use tracing_subscriber::{fmt, EnvFilter};
@@ -65,6 +65,41 @@ enum Commands {
         tags: Option<String>,
     },
 
+    /// Package a project's .dol files and publish them as a schema module
+    PublishSchema {
+        /// Module ID (e.g., io.univrs.chat)
+        #[arg(long)]
+        id: String,
+
+        /// Module name
+        #[arg(long)]
+        name: String,
+
+        /// Description
+        #[arg(long)]
+        description: String,
+
+        /// License (MIT, Apache-2.0, etc.)
+        #[arg(long, default_value = "MIT")]
+        license: String,
+
+        /// Version (semver)
+        #[arg(long)]
+        version: String,
+
+        /// Directory containing .dol files
+        #[arg(long, default_value = "schemas")]
+        dir: PathBuf,
+
+        /// Changelog
+        #[arg(long)]
+        changelog: String,
+
+        /// Tags (comma-separated)
+        #[arg(long)]
+        tags: Option<String>,
+    },
+
     /// Search for modules
     Search {
         /// Search query
@@ -89,6 +124,16 @@ enum Commands {
         auto_update: bool,
     },
 
+    /// Install a schema module and validate it with dol-reflect
+    InstallSchema {
+        /// Module ID
+        module_id: String,
+
+        /// Specific version (default: latest)
+        #[arg(short, long)]
+        version: Option<String>,
+    },
+
     /// List installed modules
     List,
 
@@ -188,6 +233,42 @@ async fn execute_command(command: Commands, config: RegistryConfig) -> anyhow::R
             println!("✓ Published {}@{}", id, version);
         }
 
+        Commands::PublishSchema {
+            id,
+            name,
+            description,
+            license,
+            version,
+            dir,
+            changelog,
+            tags,
+        } => {
+            info!("Packaging schemas in {}", dir.display());
+
+            let package = SchemaPackage::from_directory(&id, &version, &dir).await?;
+            let registry = Registry::with_config(config.clone()).await?;
+
+            let mut module = GenModule::new(&id, &name, &description, &config.owner_did, &license);
+            module.add_tag("schema");
+
+            if let Some(tags_str) = tags {
+                for tag in tags_str.split(',') {
+                    module.add_tag(tag.trim());
+                }
+            }
+
+            registry
+                .publish_schema(module, &package, &changelog)
+                .await?;
+
+            println!(
+                "✓ Published schema {}@{} ({} files)",
+                id,
+                version,
+                package.files.len()
+            );
+        }
+
         Commands::Search { query, limit } => {
             let registry = Registry::with_config(config).await?;
 
@@ -220,6 +301,20 @@ async fn execute_command(command: Commands, config: RegistryConfig) -> anyhow::R
             }
         }
 
+        Commands::InstallSchema { module_id, version } => {
+            let registry = Registry::with_config(config).await?;
+            let mut loader = SchemaLoader::new();
+
+            let package = registry
+                .install_schema(&module_id, version.as_deref(), &mut loader)
+                .await?;
+
+            println!("✓ Installed schema {}@{}", module_id, package.version);
+            for file in &package.files {
+                println!("    {}", file.path);
+            }
+        }
+
         Commands::List => {
             let registry = Registry::with_config(config).await?;
             let installed = registry.list_installed();
@@ -36,6 +36,9 @@ pub enum Error {
     #[error("Hash mismatch: expected {expected}, got {actual}")]
     HashMismatch { expected: String, actual: String },
 
+    #[error("Schema package error: {0}")]
+    SchemaError(String),
+
     #[error("Signature verification failed")]
     SignatureVerificationFailed,
 
@@ -99,3 +102,9 @@ impl From<automerge::AutomergeError> for Error {
         Error::AutomergeError(e.to_string())
     }
 }
+
+impl From<dol_reflect::dynamic_load::LoadError> for Error {
+    fn from(e: dol_reflect::dynamic_load::LoadError) -> Self {
+        Error::SchemaError(e.to_string())
+    }
+}
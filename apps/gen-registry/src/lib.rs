@@ -29,6 +29,7 @@
 //! - **Dependencies**: DAG-based dependency resolution
 //! - **Ratings**: Community feedback (CRDT-backed)
 //! - **Sync**: P2P distribution via Iroh
+//! - **Schemas**: Publish DOL schema packages for dol-reflect consumers
 //! - **Offline**: Full offline browsing of cached modules
 //!
 //! # Examples
@@ -100,6 +101,27 @@
 //! # }
 //! ```
 //!
+//! ## Sharing DOL Schemas
+//!
+//! ```no_run
+//! use gen_registry::{GenModule, Registry, SchemaLoader, SchemaPackage};
+//! use std::path::Path;
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let registry = Registry::new("did:key:alice").await?;
+//!
+//! // Package and publish a project's schemas
+//! let package = SchemaPackage::from_directory("io.univrs.chat", "1.0.0", Path::new("schemas/")).await?;
+//! let module = GenModule::new("io.univrs.chat", "Chat Schemas", "Chat data model", "did:key:alice", "MIT");
+//! registry.publish_schema(module, &package, "Initial release").await?;
+//!
+//! // Consume them in another app
+//! let mut loader = SchemaLoader::new();
+//! registry.install_schema("io.univrs.chat", None, &mut loader).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! ## P2P Synchronization
 //!
 //! ```no_run
@@ -124,6 +146,7 @@
 mod error;
 mod models;
 mod registry;
+mod schema;
 mod search;
 mod sync;
 mod version;
@@ -131,16 +154,21 @@ mod wasm;
 
 pub use error::{Error, Result};
 pub use models::{
-    Capability, Dependency, GenModule, InstalledModule, ModuleVersion, PublishCapability, Rating,
-    SearchIndex, SyncState,
+    Capability, Dependency, GenModule, InstalledModule, ModuleKind, ModuleVersion,
+    PublishCapability, Rating, SearchIndex, SyncState,
 };
 pub use registry::{Registry, RegistryConfig};
+pub use schema::SchemaStore;
 pub use search::{SearchQuery, SearchResult};
 pub use sync::{P2PSync, SyncProgress};
 pub use version::{VersionResolver, VersionRequirement};
 pub use wasm::{WasmModule, WasmValidator};
 
+/// Re-export DOL schema types
+pub use dol_reflect::{dynamic_load::SchemaLoader, SchemaPackage};
+
 /// Re-export VUDO types
 pub use vudo_identity::DID;
 pub use vudo_p2p::{Capability as WillowCapability, WillowAdapter};
 pub use vudo_state::StateEngine;
+pub use vudo_storage::{BlobHash, BlobStore};
@@ -5,6 +5,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use vudo_storage::BlobHash;
 
 /// Gen Module metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +22,19 @@ pub struct GenModule {
     pub updated_at: DateTime<Utc>,
     pub download_count: i64,
     pub dependencies: Vec<Dependency>,
+    #[serde(default)]
+    pub kind: ModuleKind,
+}
+
+/// What a module version's artifact contains
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModuleKind {
+    /// Compiled WASM Gen module
+    #[default]
+    Wasm,
+    /// Packaged DOL schemas (see `dol_reflect::SchemaPackage`)
+    Schema,
 }
 
 impl GenModule {
@@ -45,6 +59,7 @@ impl GenModule {
             updated_at: now,
             download_count: 0,
             dependencies: Vec::new(),
+            kind: ModuleKind::Wasm,
         }
     }
 
@@ -81,6 +96,9 @@ pub struct ModuleVersion {
     pub capabilities: Vec<Capability>,
     pub deprecated: bool,
     pub yanked: bool,
+    /// Content hash of the artifact in the publisher's blob store
+    #[serde(default)]
+    pub blob_hash: Option<BlobHash>,
 }
 
 impl ModuleVersion {
@@ -101,6 +119,7 @@ impl ModuleVersion {
             capabilities: Vec::new(),
             deprecated: false,
             yanked: false,
+            blob_hash: None,
         }
     }
 
@@ -4,7 +4,11 @@
 
 use crate::{
     error::{Error, Result},
-    models::{Dependency, GenModule, InstalledModule, ModuleVersion, Rating, SearchIndex},
+    models::{
+        Capability, Dependency, GenModule, InstalledModule, ModuleKind, ModuleVersion, Rating,
+        SearchIndex,
+    },
+    schema::SchemaStore,
     search::{SearchEngine, SearchQuery, SearchResult},
     sync::P2PSync,
     version::VersionResolver,
@@ -12,6 +16,7 @@ use crate::{
 };
 use automerge::{transaction::Transactable, Automerge, ObjType, ReadDoc, ROOT};
 use dashmap::DashMap;
+use dol_reflect::{dynamic_load::SchemaLoader, SchemaPackage};
 use parking_lot::RwLock;
 use std::{
     collections::{HashMap, HashSet},
@@ -20,6 +25,7 @@ use std::{
 };
 use tracing::{debug, info, warn};
 use vudo_state::StateEngine;
+use vudo_storage::BlobStore;
 
 /// Registry configuration
 #[derive(Debug, Clone)]
@@ -56,6 +62,8 @@ pub struct Registry {
     p2p_sync: Option<Arc<P2PSync>>,
     version_resolver: Arc<VersionResolver>,
     wasm_validator: Arc<WasmValidator>,
+    schema_store: SchemaStore,
+    blob_store: Option<Arc<dyn BlobStore>>,
     doc: Arc<RwLock<Automerge>>,
 }
 
@@ -104,6 +112,7 @@ impl Registry {
 
         let version_resolver = Arc::new(VersionResolver::new());
         let wasm_validator = Arc::new(WasmValidator::new());
+        let schema_store = SchemaStore::new(&config.data_dir);
 
         Ok(Self {
             config,
@@ -115,10 +124,21 @@ impl Registry {
             p2p_sync,
             version_resolver,
             wasm_validator,
+            schema_store,
+            blob_store: None,
             doc,
         })
     }
 
+    /// Keep published WASM artifacts in a content-addressed blob store
+    ///
+    /// Identical artifacts published under several versions or modules are
+    /// stored once, and installs read them back from the store.
+    pub fn with_blob_store(mut self, store: Arc<dyn BlobStore>) -> Self {
+        self.blob_store = Some(store);
+        self
+    }
+
     /// Publish a new module version
     pub async fn publish(
         &self,
@@ -150,6 +170,15 @@ impl Registry {
             signature,
         );
 
+        // Store the artifact
+        if let Some(store) = &self.blob_store {
+            let hash = store
+                .put_blob(wasm_module.bytes().to_vec().into())
+                .await
+                .map_err(|e| Error::StorageError(e.to_string()))?;
+            module_version.blob_hash = Some(hash);
+        }
+
         // Extract capabilities from WASM
         let capabilities = wasm_module.extract_capabilities()?;
         for cap in capabilities {
@@ -159,26 +188,66 @@ impl Registry {
         // Add version to module
         module.add_version(module_version);
 
-        // Store in CRDT
-        self.update_module_crdt(&module).await?;
+        self.store_published(&module).await?;
 
-        // Store in local cache
-        self.modules.insert(module.id.clone(), module.clone());
+        info!("Successfully published {}@{}", module.id, version);
+        Ok(())
+    }
 
-        // Update search index
-        if let Some(search) = &self.search_engine {
-            let index = SearchIndex::new(&module);
-            search.index_module(&index).await?;
+    /// Publish a DOL schema package as a module version
+    ///
+    /// The package name must match the module ID. The serialized package is
+    /// the version's artifact: its hash and size are recorded in place of the
+    /// WASM hash and size.
+    pub async fn publish_schema(
+        &self,
+        mut module: GenModule,
+        package: &SchemaPackage,
+        changelog: &str,
+    ) -> Result<()> {
+        info!("Publishing schema {}@{}", module.id, package.version);
+
+        if !module.validate_id() {
+            return Err(Error::InvalidModuleId(module.id.clone()));
+        }
+        if package.name != module.id {
+            return Err(Error::SchemaError(format!(
+                "Package name {} does not match module ID {}",
+                package.name, module.id
+            )));
         }
+        semver::Version::parse(&package.version)?;
 
-        // Sync to P2P network
-        if let Some(sync) = &self.p2p_sync {
-            if self.config.auto_sync {
-                sync.sync_module(&module.id).await?;
-            }
+        let bytes = package.to_bytes()?;
+        let hash = hash_bytes(&bytes);
+        let signature = self
+            .sign_module(&module.id, &package.version, &hash)
+            .await?;
+
+        let mut module_version = ModuleVersion::new(
+            &package.version,
+            hash,
+            bytes.len() as u64,
+            changelog,
+            signature,
+        );
+        for (name, exegesis) in &package.exegesis {
+            module_version.add_capability(Capability::type_def(name, exegesis));
         }
 
-        info!("Successfully published {}@{}", module.id, version);
+        self.schema_store
+            .put(&module.id, &package.version, &bytes)
+            .await?;
+
+        module.kind = ModuleKind::Schema;
+        module.add_version(module_version);
+
+        self.store_published(&module).await?;
+
+        info!(
+            "Successfully published schema {}@{}",
+            module.id, package.version
+        );
         Ok(())
     }
 
@@ -260,6 +329,81 @@ impl Registry {
         Ok(())
     }
 
+    /// Install a schema module into a [`SchemaLoader`]
+    ///
+    /// Resolves the latest stored version when none is given. When the
+    /// module's metadata is known, the package is checked against the
+    /// published hash before it is loaded.
+    pub async fn install_schema(
+        &self,
+        module_id: &str,
+        version: Option<&str>,
+        loader: &mut SchemaLoader,
+    ) -> Result<SchemaPackage> {
+        info!("Installing schema {}", module_id);
+
+        let module = self.get_module(module_id).await.ok();
+        if let Some(module) = &module {
+            if module.kind != ModuleKind::Schema {
+                return Err(Error::SchemaError(format!(
+                    "{} is not a schema module",
+                    module_id
+                )));
+            }
+        }
+
+        // Resolve version
+        let version_str = match (version, &module) {
+            (Some(v), _) => v.to_string(),
+            (None, Some(module)) => module.latest_version.clone(),
+            (None, None) => self
+                .schema_store
+                .versions(module_id)
+                .await?
+                .into_iter()
+                .next()
+                .ok_or_else(|| Error::ModuleNotFound(module_id.to_string()))?,
+        };
+
+        let bytes = self.schema_store.get(module_id, &version_str).await?;
+
+        // Verify against published metadata
+        if let Some(module) = &module {
+            let published = module
+                .versions
+                .iter()
+                .find(|v| v.version == version_str)
+                .ok_or_else(|| Error::VersionNotFound {
+                    module: module_id.to_string(),
+                    version: version_str.clone(),
+                })?;
+            let actual = hash_bytes(&bytes);
+            if published.wasm_hash != actual {
+                return Err(Error::HashMismatch {
+                    expected: published.wasm_hash.clone(),
+                    actual,
+                });
+            }
+        }
+
+        let package = SchemaPackage::from_bytes(&bytes)?;
+        if package.name != module_id || package.version != version_str {
+            return Err(Error::SchemaError(format!(
+                "Stored package {}@{} does not match {}@{}",
+                package.name, package.version, module_id, version_str
+            )));
+        }
+
+        let loaded = loader.load_package(&package).await?;
+        debug!("Loaded {} schema files from {}", loaded, module_id);
+
+        let installed = InstalledModule::new(module_id, version_str);
+        self.installed.insert(module_id.to_string(), installed);
+
+        info!("Successfully installed schema {}", module_id);
+        Ok(package)
+    }
+
     /// Install with auto-update
     pub async fn install_with_auto_update(
         &self,
@@ -360,6 +504,31 @@ impl Registry {
 
     // Private methods
 
+    /// Record a newly published version in the CRDT, cache, search index and
+    /// P2P network
+    async fn store_published(&self, module: &GenModule) -> Result<()> {
+        // Store in CRDT
+        self.update_module_crdt(module).await?;
+
+        // Store in local cache
+        self.modules.insert(module.id.clone(), module.clone());
+
+        // Update search index
+        if let Some(search) = &self.search_engine {
+            let index = SearchIndex::new(module);
+            search.index_module(&index).await?;
+        }
+
+        // Sync to P2P network
+        if let Some(sync) = &self.p2p_sync {
+            if self.config.auto_sync {
+                sync.sync_module(&module.id).await?;
+            }
+        }
+
+        Ok(())
+    }
+
     async fn update_module_crdt(&self, module: &GenModule) -> Result<()> {
         let mut doc = self.doc.write();
         let mut tx = doc.transaction();
@@ -420,6 +589,30 @@ impl Registry {
     }
 
     async fn download_wasm(&self, module_id: &str, version: &str) -> Result<WasmModule> {
+        let module = self.get_module(module_id).await?;
+        let published = module
+            .versions
+            .iter()
+            .find(|v| v.version == version)
+            .ok_or_else(|| Error::VersionNotFound {
+                module: module_id.to_string(),
+                version: version.to_string(),
+            })?;
+
+        // Read from the local blob store when the artifact is there
+        if let (Some(store), Some(hash)) = (&self.blob_store, published.blob_hash) {
+            let bytes = store
+                .get_blob(&hash)
+                .await
+                .map_err(|e| Error::StorageError(e.to_string()))?;
+            if let Some(bytes) = bytes {
+                let wasm_module = WasmModule::from_bytes(bytes.to_vec());
+                self.wasm_validator
+                    .verify_hash(&wasm_module, &published.wasm_hash)?;
+                return Ok(wasm_module);
+            }
+        }
+
         // In a real implementation, this would download from P2P network
         // For now, return a placeholder
         warn!(
@@ -430,6 +623,13 @@ impl Registry {
     }
 }
 
+/// SHA-256 hex digest of an artifact
+fn hash_bytes(bytes: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+
+    format!("{:x}", Sha256::digest(bytes))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
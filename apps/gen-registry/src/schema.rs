@@ -0,0 +1,139 @@
+//! Schema package storage
+//!
+//! Persists published DOL schema packages under the registry data directory so
+//! they can be installed by other processes sharing the same registry.
+
+use crate::error::{Error, Result};
+use std::path::{Path, PathBuf};
+
+/// On-disk store for schema package artifacts
+///
+/// Layout: `<data_dir>/schemas/<module_id>/<version>.json`
+#[derive(Debug, Clone)]
+pub struct SchemaStore {
+    root: PathBuf,
+}
+
+impl SchemaStore {
+    /// Create a store rooted in the registry data directory
+    pub fn new(data_dir: impl AsRef<Path>) -> Self {
+        Self {
+            root: data_dir.as_ref().join("schemas"),
+        }
+    }
+
+    /// Store package bytes for a module version
+    pub async fn put(&self, module_id: &str, version: &str, bytes: &[u8]) -> Result<()> {
+        let path = self.path_for(module_id, version)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, bytes).await?;
+        Ok(())
+    }
+
+    /// Load package bytes for a module version
+    pub async fn get(&self, module_id: &str, version: &str) -> Result<Vec<u8>> {
+        let path = self.path_for(module_id, version)?;
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => Ok(bytes),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(Error::VersionNotFound {
+                module: module_id.to_string(),
+                version: version.to_string(),
+            }),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// List stored versions of a module, highest semver first
+    pub async fn versions(&self, module_id: &str) -> Result<Vec<String>> {
+        let dir = self.root.join(checked_component(module_id)?);
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut versions = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|s| s.to_str()) != Some("json") {
+                continue;
+            }
+            if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                if let Ok(version) = semver::Version::parse(stem) {
+                    versions.push(version);
+                }
+            }
+        }
+
+        versions.sort_by(|a, b| b.cmp(a));
+        Ok(versions.into_iter().map(|v| v.to_string()).collect())
+    }
+
+    fn path_for(&self, module_id: &str, version: &str) -> Result<PathBuf> {
+        Ok(self
+            .root
+            .join(checked_component(module_id)?)
+            .join(format!("{}.json", checked_component(version)?)))
+    }
+}
+
+/// Reject IDs that would escape the store directory
+fn checked_component(value: &str) -> Result<&str> {
+    if value.is_empty() || value.starts_with('.') || value.contains(|c: char| c == '/' || c == '\\')
+    {
+        return Err(Error::SchemaError(format!(
+            "Invalid path component: {}",
+            value
+        )));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_put_get() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SchemaStore::new(temp_dir.path());
+
+        store.put("io.univrs.user", "1.0.0", b"{}").await.unwrap();
+        assert_eq!(store.get("io.univrs.user", "1.0.0").await.unwrap(), b"{}");
+        assert!(matches!(
+            store.get("io.univrs.user", "2.0.0").await,
+            Err(Error::VersionNotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_versions_sorted() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SchemaStore::new(temp_dir.path());
+
+        for version in ["1.0.0", "1.10.0", "1.2.0"] {
+            store.put("io.univrs.user", version, b"{}").await.unwrap();
+        }
+
+        assert_eq!(
+            store.versions("io.univrs.user").await.unwrap(),
+            vec!["1.10.0", "1.2.0", "1.0.0"]
+        );
+        assert!(store.versions("io.univrs.none").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_path_traversal() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SchemaStore::new(temp_dir.path());
+
+        assert!(store.put("../escape", "1.0.0", b"{}").await.is_err());
+        assert!(store
+            .put("io.univrs.user", "../1.0.0", b"{}")
+            .await
+            .is_err());
+    }
+}
@@ -1,7 +1,11 @@
 //! Registry integration tests
 
-use gen_registry::{GenModule, Registry, RegistryConfig};
+use gen_registry::{
+    BlobStore, GenModule, ModuleKind, Registry, RegistryConfig, SchemaLoader, SchemaPackage,
+};
+use std::sync::Arc;
 use tempfile::TempDir;
+use vudo_storage_browser::MemoryAdapter;
 
 async fn create_test_registry() -> (Registry, TempDir) {
     let temp_dir = TempDir::new().unwrap();
@@ -150,3 +154,100 @@ async fn test_module_dependencies_empty() {
 
     assert_eq!(module.dependencies.len(), 0);
 }
+
+#[tokio::test]
+async fn test_publish_and_install_from_blob_store() {
+    let (registry, temp) = create_test_registry().await;
+    let store = Arc::new(MemoryAdapter::new());
+    let registry = registry.with_blob_store(store.clone());
+
+    let wasm = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+    let wasm_path = temp.path().join("user.wasm");
+    std::fs::write(&wasm_path, &wasm).unwrap();
+
+    let module = GenModule::new(
+        "io.univrs.user",
+        "User",
+        "User management",
+        "did:key:alice",
+        "MIT",
+    );
+    registry
+        .publish(module, "1.0.0", &wasm_path, "Initial release")
+        .await
+        .unwrap();
+
+    let published = registry.get_module("io.univrs.user").await.unwrap();
+    let hash = published.versions[0].blob_hash.unwrap();
+    assert_eq!(store.get_blob(&hash).await.unwrap().unwrap(), wasm);
+
+    registry.install("io.univrs.user", None).await.unwrap();
+    assert_eq!(registry.list_installed().len(), 1);
+}
+
+#[tokio::test]
+async fn test_publish_and_install_schema() {
+    let (registry, temp) = create_test_registry().await;
+
+    let mut package = SchemaPackage::new("io.univrs.chat", "1.0.0");
+    package
+        .add_source(
+            "message.dol",
+            "gen chat.message {\n  message has body: String\n}\n\nexegesis { A chat message }\n",
+        )
+        .unwrap();
+
+    let module = GenModule::new(
+        "io.univrs.chat",
+        "Chat Schemas",
+        "Chat data model",
+        "did:key:alice",
+        "MIT",
+    );
+    registry
+        .publish_schema(module, &package, "Initial release")
+        .await
+        .unwrap();
+
+    let published = registry.get_module("io.univrs.chat").await.unwrap();
+    assert_eq!(published.kind, ModuleKind::Schema);
+    assert_eq!(published.latest_version, "1.0.0");
+
+    // A second registry sharing the data directory can install it
+    let mut config = RegistryConfig::default();
+    config.data_dir = temp.path().to_str().unwrap().to_string();
+    config.enable_p2p = false;
+    let consumer = Registry::with_config(config).await.unwrap();
+
+    let mut loader = SchemaLoader::new();
+    let installed = consumer
+        .install_schema("io.univrs.chat", None, &mut loader)
+        .await
+        .unwrap();
+
+    assert_eq!(installed, package);
+    assert_eq!(
+        installed.exegesis_for("chat.message"),
+        Some("A chat message")
+    );
+    assert_eq!(consumer.list_installed().len(), 1);
+}
+
+#[tokio::test]
+async fn test_publish_schema_name_mismatch() {
+    let (registry, _temp) = create_test_registry().await;
+
+    let package = SchemaPackage::new("io.univrs.other", "1.0.0");
+    let module = GenModule::new(
+        "io.univrs.chat",
+        "Chat Schemas",
+        "Chat data model",
+        "did:key:alice",
+        "MIT",
+    );
+
+    assert!(registry
+        .publish_schema(module, &package, "Initial release")
+        .await
+        .is_err());
+}
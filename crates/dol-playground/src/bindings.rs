@@ -0,0 +1,80 @@
+//! JavaScript bindings
+//!
+//! Thin `wasm-bindgen` wrappers around the crate API. Structured results are
+//! handed to JavaScript as plain objects (serialized through `JSON.parse`);
+//! failures are thrown as `Error`s.
+//!
+//! ```js
+//! import init, { parse, check, generate, Playground } from "./pkg/dol_playground.js";
+//!
+//! await init();
+//! const { declarations, diagnostics } = parse(source);
+//! const rust = generate(source, "rust");
+//!
+//! const playground = new Playground();
+//! playground.eval("fun double(x: i64) -> i64 { x * 2 }");
+//! playground.eval("double(21)"); // { kind: "value", output: "42" }
+//! ```
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    let json = serde_json::to_string(value).map_err(|e| JsError::new(&e.to_string()))?;
+    js_sys::JSON::parse(&json).map_err(|_| JsError::new("failed to convert result"))
+}
+
+/// Parse DOL source; returns `{ declarations, diagnostics }`
+#[wasm_bindgen]
+pub fn parse(source: &str) -> Result<JsValue, JsError> {
+    to_js(&crate::parse(source))
+}
+
+/// Parse, validate and type-check DOL source; returns a diagnostics array
+#[wasm_bindgen]
+pub fn check(source: &str) -> Result<JsValue, JsError> {
+    to_js(&crate::check(source))
+}
+
+/// Generate code for `target` ("rust", "typescript" or "json-schema")
+#[wasm_bindgen]
+pub fn generate(source: &str, target: &str) -> Result<String, JsError> {
+    let target = target
+        .parse::<crate::Target>()
+        .map_err(|e| JsError::new(&e))?;
+    crate::generate(source, target).map_err(|d| JsError::new(&d.to_string()))
+}
+
+/// A persistent REPL session
+#[wasm_bindgen]
+pub struct Playground {
+    inner: crate::Playground,
+}
+
+#[wasm_bindgen]
+impl Playground {
+    /// Create an empty session
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            inner: crate::Playground::new(),
+        }
+    }
+
+    /// Evaluate one input; returns `{ kind, output }`
+    pub fn eval(&mut self, input: &str) -> Result<JsValue, JsError> {
+        let output = self.inner.eval(input).map_err(|e| JsError::new(&e))?;
+        to_js(&output)
+    }
+
+    /// Drop all declarations from the session
+    pub fn reset(&mut self) {
+        self.inner.reset();
+    }
+}
+
+impl Default for Playground {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,378 @@
+//! Client-side DOL playground
+//!
+//! Bundles the DOL parser, type checker, REPL evaluator and code generators
+//! into a single crate that compiles to `wasm32-unknown-unknown`, so a web
+//! playground can run entirely in the browser without a backend.
+//!
+//! The Rust API in this module is plain data in, plain data out and is what
+//! the tests exercise. The [`bindings`] module wraps it with `wasm-bindgen`
+//! for JavaScript:
+//!
+//! - `parse(source)` - declarations and parse diagnostics
+//! - `check(source)` - parse, validation and type-check diagnostics
+//! - `generate(source, target)` - Rust, TypeScript or JSON Schema output
+//! - `new Playground()` / `playground.eval(input)` - a persistent REPL session
+//!
+//! Expressions are evaluated by the tree-walking interpreter (the `playground`
+//! feature of `dol`) rather than wasmtime, which cannot run inside a browser.
+//!
+//! # Example
+//!
+//! ```rust
+//! use dol_playground::{generate, parse, Playground, Target};
+//!
+//! let parsed = parse("fun double(x: i64) -> i64 { x * 2 }");
+//! assert_eq!(parsed.declarations[0].name, "double");
+//!
+//! let mut playground = Playground::new();
+//! playground.eval("fun double(x: i64) -> i64 { x * 2 }").unwrap();
+//! assert_eq!(playground.eval("double(21)").unwrap().output, "42");
+//!
+//! let rust = generate("gen Point { has x: i64 }", Target::Rust).unwrap();
+//! assert!(rust.contains("pub struct Point"));
+//! ```
+
+pub mod bindings;
+
+use metadol::ast::{Declaration, Span};
+use metadol::codegen::{JsonSchemaCodegen, RustCodegen, TypeScriptCodegen};
+use metadol::validator::{validate_with_options, ValidationOptions};
+use metadol::{parse_file_all, EvalResult, ParseError, SpiritRepl};
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// Severity of a [`Diagnostic`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The source is rejected
+    Error,
+    /// The source is accepted but suspicious
+    Warning,
+}
+
+/// A message attached to a source location
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// Error or warning
+    pub severity: Severity,
+    /// Human-readable message
+    pub message: String,
+    /// Line number (1-indexed)
+    pub line: usize,
+    /// Column number (1-indexed)
+    pub column: usize,
+}
+
+impl Diagnostic {
+    fn at(severity: Severity, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity,
+            message: message.into(),
+            line: span.line,
+            column: span.column,
+        }
+    }
+
+    fn from_parse_error(error: &ParseError) -> Self {
+        Self::at(Severity::Error, error.to_string(), error.span())
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.column, severity, self.message
+        )
+    }
+}
+
+/// Outline entry for a parsed declaration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeclarationInfo {
+    /// Declaration keyword (gen, trait, rule, system, evo, fun, const, var)
+    pub kind: String,
+    /// Declared name
+    pub name: String,
+    /// Line number (1-indexed)
+    pub line: usize,
+    /// Column number (1-indexed)
+    pub column: usize,
+}
+
+impl From<&Declaration> for DeclarationInfo {
+    fn from(decl: &Declaration) -> Self {
+        let span = decl.span();
+        Self {
+            kind: declaration_kind(decl).to_string(),
+            name: decl.name().to_string(),
+            line: span.line,
+            column: span.column,
+        }
+    }
+}
+
+/// Result of [`parse`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ParseOutput {
+    /// Declarations in source order (empty if parsing failed)
+    pub declarations: Vec<DeclarationInfo>,
+    /// Parse errors
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Parse DOL source into a declaration outline
+pub fn parse(source: &str) -> ParseOutput {
+    match parse_file_all(source) {
+        Ok(decls) => ParseOutput {
+            declarations: decls.iter().map(DeclarationInfo::from).collect(),
+            diagnostics: Vec::new(),
+        },
+        Err(e) => ParseOutput {
+            declarations: Vec::new(),
+            diagnostics: vec![Diagnostic::from_parse_error(&e)],
+        },
+    }
+}
+
+/// Parse, validate and type-check DOL source
+///
+/// Validation errors carry the location of the declaration they belong to.
+pub fn check(source: &str) -> Vec<Diagnostic> {
+    let decls = match parse_file_all(source) {
+        Ok(decls) => decls,
+        Err(e) => return vec![Diagnostic::from_parse_error(&e)],
+    };
+
+    let options = ValidationOptions { typecheck: true };
+    let mut diagnostics = Vec::new();
+    for decl in &decls {
+        let result = validate_with_options(decl, &options);
+        let span = decl.span();
+        diagnostics.extend(
+            result
+                .errors
+                .iter()
+                .map(|e| Diagnostic::at(Severity::Error, e.to_string(), span)),
+        );
+        diagnostics.extend(
+            result
+                .warnings
+                .iter()
+                .map(|w| Diagnostic::at(Severity::Warning, w.to_string(), span)),
+        );
+    }
+    diagnostics
+}
+
+/// Code generation target for [`generate`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// Rust structs, traits and functions
+    Rust,
+    /// TypeScript interfaces and types
+    TypeScript,
+    /// JSON Schema documents
+    JsonSchema,
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "rust" | "rs" => Ok(Target::Rust),
+            "typescript" | "ts" => Ok(Target::TypeScript),
+            "json-schema" | "jsonschema" | "json" => Ok(Target::JsonSchema),
+            other => Err(format!(
+                "unknown target '{}' (expected rust, typescript or json-schema)",
+                other
+            )),
+        }
+    }
+}
+
+/// Generate code for every declaration in the source
+pub fn generate(source: &str, target: Target) -> Result<String, Diagnostic> {
+    let decls = parse_file_all(source).map_err(|e| Diagnostic::from_parse_error(&e))?;
+    Ok(match target {
+        Target::Rust => RustCodegen::generate_all(&decls),
+        Target::TypeScript => TypeScriptCodegen::generate_all(&decls),
+        Target::JsonSchema => JsonSchemaCodegen::generate_all(&decls),
+    })
+}
+
+/// Output of a single [`Playground::eval`] call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EvalOutput {
+    /// What the input produced (empty, defined, value, message, code)
+    pub kind: String,
+    /// Text to show in the console
+    pub output: String,
+}
+
+impl EvalOutput {
+    fn new(kind: &str, output: impl Into<String>) -> Self {
+        Self {
+            kind: kind.to_string(),
+            output: output.into(),
+        }
+    }
+}
+
+/// A REPL session that keeps declarations between evaluations
+#[derive(Debug, Default)]
+pub struct Playground {
+    repl: SpiritRepl,
+}
+
+impl Playground {
+    /// Create an empty session
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Evaluate a declaration, expression or `:command`
+    pub fn eval(&mut self, input: &str) -> Result<EvalOutput, String> {
+        let result = self.repl.eval(input).map_err(|e| e.to_string())?;
+        Ok(match result {
+            EvalResult::Empty | EvalResult::Quit => EvalOutput::new("empty", ""),
+            EvalResult::Defined { message, .. } => EvalOutput::new("defined", message),
+            EvalResult::Expression { value, .. } => EvalOutput::new("value", value),
+            EvalResult::Help(text) | EvalResult::Message(text) | EvalResult::TypeInfo(text) => {
+                EvalOutput::new("message", text)
+            }
+            EvalResult::RustCode(code) => EvalOutput::new("code", code),
+            EvalResult::WasmInfo {
+                size_bytes,
+                functions,
+                has_memory,
+            } => EvalOutput::new(
+                "message",
+                format!(
+                    "WASM module: {} bytes, {} functions, memory: {}",
+                    size_bytes, functions, has_memory
+                ),
+            ),
+            EvalResult::SpiritLoaded { name, declarations } => EvalOutput::new(
+                "message",
+                format!("Loaded spirit '{}' ({} declarations)", name, declarations),
+            ),
+        })
+    }
+
+    /// Drop all declarations from the session
+    pub fn reset(&mut self) {
+        self.repl = SpiritRepl::new();
+    }
+}
+
+fn declaration_kind(decl: &Declaration) -> &'static str {
+    match decl {
+        Declaration::Gene(_) => "gen",
+        Declaration::Trait(_) => "trait",
+        Declaration::Constraint(_) => "rule",
+        Declaration::System(_) => "system",
+        Declaration::Evolution(_) => "evo",
+        Declaration::Function(_) => "fun",
+        Declaration::Const(_) => "const",
+        Declaration::SexVar(_) => "var",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_outline() {
+        let output = parse(
+            r#"
+gen Point {
+    has x: i64
+    has y: i64
+}
+
+fun origin_distance(x: i64, y: i64) -> i64 { x * x + y * y }
+"#,
+        );
+        assert!(output.diagnostics.is_empty());
+        let names: Vec<(&str, &str)> = output
+            .declarations
+            .iter()
+            .map(|d| (d.kind.as_str(), d.name.as_str()))
+            .collect();
+        assert_eq!(names, vec![("gen", "Point"), ("fun", "origin_distance")]);
+        assert_eq!(output.declarations[0].line, 2);
+    }
+
+    #[test]
+    fn test_parse_error_location() {
+        let output = parse("gen Point {\n    has x:\n}");
+        assert!(output.declarations.is_empty());
+        assert_eq!(output.diagnostics.len(), 1);
+        assert_eq!(output.diagnostics[0].severity, Severity::Error);
+        assert!(output.diagnostics[0].line >= 2);
+    }
+
+    #[test]
+    fn test_check_valid_source() {
+        let diagnostics = check("gen Point { has x: i64 }");
+        assert!(diagnostics.iter().all(|d| d.severity != Severity::Error));
+    }
+
+    #[test]
+    fn test_check_reports_parse_error() {
+        let diagnostics = check("gen {");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_generate_targets() {
+        let source = "gen Point { has x: i64 }";
+        assert!(generate(source, Target::Rust)
+            .unwrap()
+            .contains("pub struct Point"));
+        assert!(generate(source, Target::TypeScript)
+            .unwrap()
+            .contains("interface Point"));
+        assert!(generate(source, Target::JsonSchema)
+            .unwrap()
+            .contains("\"Point\""));
+        assert!(generate("gen {", Target::Rust).is_err());
+    }
+
+    #[test]
+    fn test_target_from_str() {
+        assert_eq!("ts".parse::<Target>(), Ok(Target::TypeScript));
+        assert_eq!("JSON-Schema".parse::<Target>(), Ok(Target::JsonSchema));
+        assert!("python".parse::<Target>().is_err());
+    }
+
+    #[test]
+    fn test_playground_session() {
+        let mut playground = Playground::new();
+
+        let defined = playground
+            .eval("fun square(x: i64) -> i64 { x * x }")
+            .unwrap();
+        assert_eq!(defined.kind, "defined");
+
+        let value = playground.eval("square(3) + 1").unwrap();
+        assert_eq!(value, EvalOutput::new("value", "10"));
+
+        assert!(playground.eval("square(").is_err());
+
+        playground.reset();
+        assert!(playground.eval("square(3)").is_err());
+    }
+}
@@ -35,6 +35,7 @@
 //! ```
 
 use crate::schema_api::{ReflectionError, SchemaRegistry};
+use crate::schema_package::SchemaPackage;
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -68,6 +69,10 @@ pub enum LoadError {
     /// Schema conflict during migration
     #[error("Schema conflict: {0}")]
     Conflict(String),
+
+    /// Schema package (de)serialization error
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
 }
 
 /// Result type for loading operations.
@@ -262,6 +267,28 @@ impl SchemaLoader {
         Ok((watcher, rx))
     }
 
+    /// Loads all schemas from a schema package.
+    ///
+    /// Files are tracked under `<package name>/<file path>` with the package
+    /// version, so installing a newer package version is visible in
+    /// [`SchemaLoader::versions`].
+    pub async fn load_package(&mut self, package: &SchemaPackage) -> LoadResult<usize> {
+        let mut registry = self.registry.write().await;
+        for file in &package.files {
+            registry.load_schema(&file.source)?;
+
+            let key = Path::new(&package.name).join(&file.path);
+            let version = SchemaVersion {
+                identifier: key.to_string_lossy().to_string(),
+                version: package.version.clone(),
+                timestamp: std::time::SystemTime::now(),
+            };
+            self.versions.insert(key, version);
+        }
+
+        Ok(package.files.len())
+    }
+
     /// Reloads a schema file (for hot-reload).
     pub async fn reload_file(&mut self, path: &Path) -> LoadResult<()> {
         // Check if file was modified
@@ -432,6 +459,23 @@ exegesis { Version 2 }
         assert_eq!(gen.field_count(), 2);
     }
 
+    #[tokio::test]
+    async fn test_load_package() {
+        let mut package = SchemaPackage::new("io.univrs.test", "2.1.0");
+        package
+            .add_source("test.dol", "gen test.gen { test has field: String } exegesis { Test }")
+            .unwrap();
+
+        let mut loader = SchemaLoader::new();
+        assert_eq!(loader.load_package(&package).await.unwrap(), 1);
+
+        let registry = loader.registry.read().await;
+        assert!(registry.get_gen("test.gen").is_some());
+
+        let version = &loader.versions()[Path::new("io.univrs.test/test.dol")];
+        assert_eq!(version.version, "2.1.0");
+    }
+
     #[tokio::test]
     async fn test_version_tracking() {
         let temp_dir = TempDir::new().unwrap();
@@ -16,6 +16,7 @@
 //! - [`schema_api`]: Core reflection API for querying schema structure
 //! - [`dynamic_load`]: Dynamic schema loading with hot-reload
 //! - [`crdt_introspection`]: CRDT-specific reflection and validation
+//! - [`schema_package`]: Portable schema bundles for registry distribution
 //!
 //! # Quick Start
 //!
@@ -121,6 +122,7 @@
 pub mod crdt_introspection;
 pub mod dynamic_load;
 pub mod schema_api;
+pub mod schema_package;
 
 // Re-export commonly used types
 pub use crdt_introspection::{
@@ -134,6 +136,7 @@ pub use schema_api::{
     EvoReflection, FieldReflection, GenReflection, ReflectionError, ReflectionResult,
     SchemaRegistry, SystemReflection, TraitReflection,
 };
+pub use schema_package::{SchemaFile, SchemaPackage};
 
 // Re-export DOL types for convenience
 pub use metadol::ast::{CrdtAnnotation, CrdtStrategy, Declaration, Visibility};
@@ -0,0 +1,226 @@
+//! Portable Schema Packages
+//!
+//! This module bundles a project's .dol files, together with the exegesis of
+//! every declaration they contain, into a single serializable package. Packages
+//! are the unit of schema distribution: they are published to a registry by the
+//! schema author and fed into a [`SchemaLoader`](crate::dynamic_load::SchemaLoader)
+//! by consuming applications.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use dol_reflect::dynamic_load::SchemaLoader;
+//! use dol_reflect::schema_package::SchemaPackage;
+//! use std::path::Path;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! // Package a project's schemas
+//! let package = SchemaPackage::from_directory("io.univrs.chat", "1.0.0", Path::new("schemas/")).await?;
+//! let bytes = package.to_bytes()?;
+//!
+//! // ...and load them elsewhere
+//! let package = SchemaPackage::from_bytes(&bytes)?;
+//! let mut loader = SchemaLoader::new();
+//! loader.load_package(&package).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::dynamic_load::{LoadError, LoadResult};
+use crate::schema_api::ReflectionResult;
+use metadol::parse_file_all;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Current schema package format version.
+pub const PACKAGE_FORMAT_VERSION: u32 = 1;
+
+/// A single .dol source file inside a package.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaFile {
+    /// Path relative to the package root, with `/` separators
+    pub path: String,
+    /// DOL source text
+    pub source: String,
+}
+
+/// A distributable bundle of DOL schemas and their exegesis.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaPackage {
+    /// Package format version
+    pub format: u32,
+    /// Package name (typically a reverse-domain module ID)
+    pub name: String,
+    /// Package version (semver)
+    pub version: String,
+    /// Source files, sorted by path
+    pub files: Vec<SchemaFile>,
+    /// Exegesis of every declaration, keyed by declaration name
+    pub exegesis: BTreeMap<String, String>,
+}
+
+impl SchemaPackage {
+    /// Creates an empty package.
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            format: PACKAGE_FORMAT_VERSION,
+            name: name.into(),
+            version: version.into(),
+            files: Vec::new(),
+            exegesis: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a source file to the package.
+    ///
+    /// The source is parsed so that broken schemas are rejected at packaging
+    /// time rather than on the consumer's side, and the exegesis of each
+    /// declaration is recorded in the package index.
+    pub fn add_source(
+        &mut self,
+        path: impl Into<String>,
+        source: impl Into<String>,
+    ) -> ReflectionResult<()> {
+        let path = path.into();
+        let source = source.into();
+
+        for decl in parse_file_all(&source)? {
+            let exegesis = decl.exegesis().trim();
+            if !exegesis.is_empty() {
+                self.exegesis
+                    .insert(decl.name().to_string(), exegesis.to_string());
+            }
+        }
+
+        self.files.retain(|f| f.path != path);
+        self.files.push(SchemaFile { path, source });
+        self.files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(())
+    }
+
+    /// Packages all .dol files under a directory.
+    pub async fn from_directory(
+        name: impl Into<String>,
+        version: impl Into<String>,
+        dir: &Path,
+    ) -> LoadResult<Self> {
+        let mut package = Self::new(name, version);
+
+        for entry in WalkDir::new(dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let path = entry.path();
+            if !path.is_file() || path.extension().and_then(|s| s.to_str()) != Some("dol") {
+                continue;
+            }
+
+            let relative = path
+                .strip_prefix(dir)
+                .unwrap_or(path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let source = tokio::fs::read_to_string(path).await?;
+            package.add_source(relative, source)?;
+        }
+
+        if package.files.is_empty() {
+            return Err(LoadError::ValidationFailed(format!(
+                "No .dol files found in {}",
+                dir.display()
+            )));
+        }
+
+        Ok(package)
+    }
+
+    /// Returns the exegesis of a declaration.
+    pub fn exegesis_for(&self, name: &str) -> Option<&str> {
+        self.exegesis.get(name).map(|s| s.as_str())
+    }
+
+    /// Serializes the package.
+    ///
+    /// The encoding is deterministic, so equal packages produce equal bytes
+    /// and can be content-addressed.
+    pub fn to_bytes(&self) -> LoadResult<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Deserializes a package.
+    pub fn from_bytes(bytes: &[u8]) -> LoadResult<Self> {
+        let package: Self = serde_json::from_slice(bytes)?;
+        if package.format > PACKAGE_FORMAT_VERSION {
+            return Err(LoadError::InvalidVersion(format!(
+                "Schema package format {} is newer than supported format {}",
+                package.format, PACKAGE_FORMAT_VERSION
+            )));
+        }
+        Ok(package)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const PROFILE: &str = r#"
+gen user.profile {
+  profile has name: String
+}
+
+exegesis { A user profile }
+"#;
+
+    #[test]
+    fn test_add_source_collects_exegesis() {
+        let mut package = SchemaPackage::new("io.univrs.user", "1.0.0");
+        package.add_source("user.dol", PROFILE).unwrap();
+
+        assert_eq!(package.files.len(), 1);
+        assert_eq!(package.exegesis_for("user.profile"), Some("A user profile"));
+    }
+
+    #[test]
+    fn test_add_source_rejects_invalid() {
+        let mut package = SchemaPackage::new("io.univrs.user", "1.0.0");
+        assert!(package.add_source("bad.dol", "gen {").is_err());
+        assert!(package.files.is_empty());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut package = SchemaPackage::new("io.univrs.user", "1.0.0");
+        package.add_source("user.dol", PROFILE).unwrap();
+
+        let bytes = package.to_bytes().unwrap();
+        assert_eq!(SchemaPackage::from_bytes(&bytes).unwrap(), package);
+    }
+
+    #[tokio::test]
+    async fn test_from_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        tokio::fs::create_dir(temp_dir.path().join("nested"))
+            .await
+            .unwrap();
+        tokio::fs::write(temp_dir.path().join("nested/user.dol"), PROFILE)
+            .await
+            .unwrap();
+        tokio::fs::write(temp_dir.path().join("README.md"), "not a schema")
+            .await
+            .unwrap();
+
+        let package = SchemaPackage::from_directory("io.univrs.user", "1.0.0", temp_dir.path())
+            .await
+            .unwrap();
+        assert_eq!(package.files.len(), 1);
+        assert_eq!(package.files[0].path, "nested/user.dol");
+    }
+}
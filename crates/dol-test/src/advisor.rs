@@ -0,0 +1,1352 @@
+//! CRDT strategy advisor backed by simulation
+//!
+//! Rather than picking a `@crdt` strategy from the field type alone, the
+//! advisor replays a synthetic workload against every strategy that is valid
+//! for the field and recommends the one with the best measured outcome.
+//!
+//! For each field, the workload is turned into a schedule of edit and sync
+//! rounds: every device edits its own replica and then, unless it stays
+//! offline for the round, pulls state from a random peer. Once editing stops,
+//! devices keep gossiping until all replicas agree. Edit payloads are drawn
+//! from the [`generators`](crate::generators), and the schedule is identical
+//! for every strategy of a field.
+//!
+//! Each strategy is measured on:
+//!
+//! - **Convergence**: whether all replicas reached the same state, and how
+//!   many gossip rounds that took after editing stopped
+//! - **Lost edits**: edits whose effect is missing from the converged state
+//!   even though no later edit that observed them replaced them (concurrent
+//!   LWW overwrites, rejected immutable writes, removes beaten by a concurrent
+//!   add in an OR-Set)
+//! - **Conflicts**: concurrent values an MV-Register handed to the application
+//!   to resolve
+//! - **Merge cost**: serialized size of the converged state and the mean
+//!   wall-clock time of a merge
+//!
+//! Strategies are ranked by convergence, then lost edits plus conflicts, then
+//! state size. Remaining ties keep the candidate order, which lists stricter
+//! strategies first. Merge time is reported but not ranked, as it varies
+//! between runs.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use dol_test::advisor::{advise, Workload};
+//!
+//! let workload = Workload {
+//!     devices: 5,
+//!     concurrency: 0.8,
+//!     ..Workload::default()
+//! };
+//! let report = advise(&std::fs::read_to_string("chat.dol")?, &workload)?;
+//! println!("{}", report);
+//! ```
+
+use crate::generators::*;
+use crate::{TestError, TestResult};
+use dol::ast::{CrdtStrategy, Declaration, HasField, Statement, TypeExpr};
+use dol::validator::{format_type_expr, is_crdt_compatible};
+use proptest::strategy::{Strategy, ValueTree};
+use proptest::test_runner::{Config, RngAlgorithm, TestRng, TestRunner};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::time::Instant;
+
+/// Every strategy, in the order candidates are considered
+const STRATEGIES: [CrdtStrategy; 7] = [
+    CrdtStrategy::Immutable,
+    CrdtStrategy::Lww,
+    CrdtStrategy::OrSet,
+    CrdtStrategy::PnCounter,
+    CrdtStrategy::Rga,
+    CrdtStrategy::MvRegister,
+    CrdtStrategy::Peritext,
+];
+
+/// Gossip rounds per device allowed for convergence after editing stops
+const MAX_SYNC_ROUNDS_PER_DEVICE: usize = 8;
+
+/// Number of distinct elements OR-Set edits draw from, per device
+const ELEMENTS_PER_DEVICE: usize = 2;
+
+/// Synthetic workload to simulate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Workload {
+    /// Number of devices editing the document
+    pub devices: usize,
+
+    /// Mean edits per device per round
+    pub edit_rate: f64,
+
+    /// Per-field overrides of `edit_rate`, keyed by field name
+    pub field_edit_rates: BTreeMap<String, f64>,
+
+    /// Probability that a device skips a round's sync, keeping its edits
+    /// concurrent with everyone else's
+    pub concurrency: f64,
+
+    /// Number of edit/sync rounds
+    pub rounds: usize,
+
+    /// Random seed for reproducibility
+    pub seed: u64,
+}
+
+impl Default for Workload {
+    fn default() -> Self {
+        Self {
+            devices: 3,
+            edit_rate: 1.0,
+            field_edit_rates: BTreeMap::new(),
+            concurrency: 0.5,
+            rounds: 50,
+            seed: 0,
+        }
+    }
+}
+
+impl Workload {
+    /// Returns the edit rate for a field
+    pub fn edit_rate_for(&self, field: &str) -> f64 {
+        self.field_edit_rates
+            .get(field)
+            .copied()
+            .unwrap_or(self.edit_rate)
+    }
+
+    /// Checks that the workload can be simulated
+    pub fn validate(&self) -> TestResult<()> {
+        let invalid = |reason: String| Err(TestError::InvalidWorkload { reason });
+
+        if self.devices == 0 {
+            return invalid("at least one device is required".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.concurrency) {
+            return invalid(format!(
+                "concurrency must be between 0 and 1, got {}",
+                self.concurrency
+            ));
+        }
+        for (field, rate) in std::iter::once(("*", &self.edit_rate)).chain(
+            self.field_edit_rates
+                .iter()
+                .map(|(field, rate)| (field.as_str(), rate)),
+        ) {
+            if !rate.is_finite() || *rate < 0.0 {
+                return invalid(format!(
+                    "edit rate for {} must be a non-negative number, got {}",
+                    field, rate
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Measured behavior of one strategy under a workload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrategyMeasurement {
+    /// Strategy that was simulated
+    pub strategy: CrdtStrategy,
+
+    /// Total edits made across all devices
+    pub edits: usize,
+
+    /// Whether all replicas reached the same state
+    pub converged: bool,
+
+    /// Gossip rounds needed to converge after editing stopped
+    pub sync_rounds: usize,
+
+    /// Edits whose effect was silently lost
+    pub lost_edits: usize,
+
+    /// Concurrent values left for the application to resolve
+    pub conflicts: usize,
+
+    /// Serialized size of the converged state in bytes
+    pub state_bytes: usize,
+
+    /// Number of merges performed
+    pub merges: usize,
+
+    /// Mean time per merge in nanoseconds
+    pub mean_merge_nanos: u64,
+}
+
+impl StrategyMeasurement {
+    /// Ranking key; lower is better
+    fn rank(&self) -> (bool, usize, usize) {
+        // An unedited field has no merge cost to compare
+        let state_bytes = if self.edits == 0 { 0 } else { self.state_bytes };
+        (
+            !self.converged,
+            self.lost_edits + self.conflicts,
+            state_bytes,
+        )
+    }
+}
+
+/// Recommendation for a single field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldAdvice {
+    /// Gene declaring the field
+    pub gene: String,
+
+    /// Field name
+    pub field: String,
+
+    /// Field type as written in the schema
+    pub type_name: String,
+
+    /// Strategy currently annotated on the field, if any
+    pub current: Option<CrdtStrategy>,
+
+    /// Best measured strategy
+    pub recommended: CrdtStrategy,
+
+    /// Measurements for every candidate strategy, best first
+    pub measurements: Vec<StrategyMeasurement>,
+}
+
+impl FieldAdvice {
+    /// Returns true if the recommendation differs from the annotated strategy
+    pub fn changes_strategy(&self) -> bool {
+        self.current != Some(self.recommended)
+    }
+}
+
+/// Recommendations for every field of a schema
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdvisorReport {
+    /// Workload that was simulated
+    pub workload: Workload,
+
+    /// Advice per field, in declaration order
+    pub fields: Vec<FieldAdvice>,
+}
+
+impl fmt::Display for AdvisorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let workload = &self.workload;
+        writeln!(
+            f,
+            "Workload: {} devices, {:.2} edits/device/round, {:.0}% concurrency, {} rounds, seed {}",
+            workload.devices,
+            workload.edit_rate,
+            workload.concurrency * 100.0,
+            workload.rounds,
+            workload.seed
+        )?;
+
+        for advice in &self.fields {
+            writeln!(f)?;
+            let current = advice
+                .current
+                .map(|strategy| strategy.as_str())
+                .unwrap_or("none");
+            let verdict = if advice.changes_strategy() {
+                "recommend"
+            } else {
+                "keep"
+            };
+            writeln!(
+                f,
+                "{}.{}: {} (current: {}) -> {} {}",
+                advice.gene,
+                advice.field,
+                advice.type_name,
+                current,
+                verdict,
+                advice.recommended.as_str()
+            )?;
+            writeln!(
+                f,
+                "  {:<12} {:>6} {:>6} {:>9} {:>10} {:>10} {:>6}",
+                "strategy", "edits", "lost", "conflicts", "state", "merge", "sync"
+            )?;
+            for m in &advice.measurements {
+                let sync = if m.converged {
+                    m.sync_rounds.to_string()
+                } else {
+                    "never".to_string()
+                };
+                writeln!(
+                    f,
+                    "  {:<12} {:>6} {:>6} {:>9} {:>8} B {:>7} ns {:>6}",
+                    m.strategy.as_str(),
+                    m.edits,
+                    m.lost_edits,
+                    m.conflicts,
+                    m.state_bytes,
+                    m.mean_merge_nanos,
+                    sync
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the strategies that are valid for a field type
+///
+/// `Option<T>` is treated as `T`.
+pub fn candidate_strategies(type_: &TypeExpr) -> Vec<CrdtStrategy> {
+    let inner = match type_ {
+        TypeExpr::Generic { name, args } if name == "Option" && args.len() == 1 => &args[0],
+        other => other,
+    };
+    STRATEGIES
+        .iter()
+        .copied()
+        .filter(|strategy| is_crdt_compatible(inner, strategy))
+        .collect()
+}
+
+/// Parses a DOL schema and advises on every field of every gene
+pub fn advise(source: &str, workload: &Workload) -> TestResult<AdvisorReport> {
+    let declarations =
+        dol::parse_file_all(source).map_err(|e| TestError::ParseError(e.to_string()))?;
+    advise_declarations(&declarations, workload)
+}
+
+/// Advises on every field of the genes among `declarations`
+pub fn advise_declarations(
+    declarations: &[Declaration],
+    workload: &Workload,
+) -> TestResult<AdvisorReport> {
+    workload.validate()?;
+
+    let mut fields = Vec::new();
+    for declaration in declarations {
+        if let Declaration::Gene(gene) = declaration {
+            for statement in &gene.statements {
+                if let Statement::HasField(field) = statement {
+                    if let Some(advice) = advise_field(&gene.name, field, workload)? {
+                        fields.push(advice);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(AdvisorReport {
+        workload: workload.clone(),
+        fields,
+    })
+}
+
+/// Advises on a single field
+///
+/// Returns `None` if no strategy is valid for the field's type.
+pub fn advise_field(
+    gene: &str,
+    field: &HasField,
+    workload: &Workload,
+) -> TestResult<Option<FieldAdvice>> {
+    let mut measurements = candidate_strategies(&field.type_)
+        .into_iter()
+        .map(|strategy| simulate_strategy(strategy, workload, &field.name))
+        .collect::<TestResult<Vec<_>>>()?;
+    if measurements.is_empty() {
+        return Ok(None);
+    }
+    // Stable sort keeps candidate order on exact ties
+    measurements.sort_by_key(StrategyMeasurement::rank);
+
+    Ok(Some(FieldAdvice {
+        gene: gene.to_string(),
+        field: field.name.clone(),
+        type_name: format_type_expr(&field.type_),
+        current: field.crdt_annotation.as_ref().map(|crdt| crdt.strategy),
+        recommended: measurements[0].strategy,
+        measurements,
+    }))
+}
+
+/// Simulates one strategy for a field under a workload
+///
+/// The field name selects the edit rate and seeds the schedule, so every
+/// strategy of a field sees the same edits and syncs.
+pub fn simulate_strategy(
+    strategy: CrdtStrategy,
+    workload: &Workload,
+    field: &str,
+) -> TestResult<StrategyMeasurement> {
+    workload.validate()?;
+
+    let rate = workload.edit_rate_for(field);
+    let seed = field_seed(workload.seed, field);
+    let outcome = match strategy {
+        CrdtStrategy::Immutable => run::<ImmutableModel>(workload, rate, seed),
+        CrdtStrategy::Lww => run::<LwwModel>(workload, rate, seed),
+        CrdtStrategy::OrSet => run::<OrSetModel>(workload, rate, seed),
+        CrdtStrategy::PnCounter => run::<CounterModel>(workload, rate, seed),
+        CrdtStrategy::Rga => run::<RgaModel>(workload, rate, seed),
+        CrdtStrategy::MvRegister => run::<MvRegisterModel>(workload, rate, seed),
+        CrdtStrategy::Peritext => run::<PeritextModel>(workload, rate, seed),
+    };
+
+    Ok(StrategyMeasurement {
+        strategy,
+        edits: outcome.edits,
+        converged: outcome.converged,
+        sync_rounds: outcome.sync_rounds,
+        lost_edits: outcome.lost_edits,
+        conflicts: outcome.conflicts,
+        state_bytes: outcome.state_bytes,
+        merges: outcome.merges,
+        mean_merge_nanos: outcome
+            .merge_nanos
+            .checked_div(outcome.merges as u128)
+            .unwrap_or(0) as u64,
+    })
+}
+
+/// Mixes the field name into the workload seed (FNV-1a)
+fn field_seed(seed: u64, field: &str) -> u64 {
+    field.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    }) ^ seed
+}
+
+/// Identifies an edit; ordered by Lamport time, then device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+struct Dot {
+    time: u64,
+    device: usize,
+    seq: u64,
+}
+
+/// What an edit did, for lost-edit accounting
+#[derive(Debug, Clone, PartialEq)]
+enum EditKind {
+    Write,
+    Count { delta: i64 },
+    Add,
+    Remove { element: String },
+    Insert,
+    Delete { target: Dot },
+    Format,
+}
+
+/// An edit together with the version vector its device had observed
+#[derive(Debug, Clone)]
+struct EditRecord {
+    dot: Dot,
+    seen: Vec<u64>,
+    kind: EditKind,
+}
+
+impl EditRecord {
+    /// Returns true if this edit was made with knowledge of `dot`
+    fn observed(&self, dot: &Dot) -> bool {
+        self.dot != *dot && self.seen[dot.device] >= dot.seq
+    }
+}
+
+/// Returns true if a later edit observed `dot` and so legitimately replaced it
+fn superseded(dot: &Dot, edits: &[EditRecord]) -> bool {
+    edits.iter().any(|edit| edit.observed(dot))
+}
+
+/// Edit payloads drawn from the property test generators
+struct Payloads {
+    runner: TestRunner,
+    elements: Vec<String>,
+}
+
+impl Payloads {
+    fn new(seed: u64) -> Self {
+        let mut bytes = [0u8; 32];
+        bytes[..8].copy_from_slice(&seed.to_le_bytes());
+        Self {
+            runner: TestRunner::new_with_rng(
+                Config::default(),
+                TestRng::from_seed(RngAlgorithm::ChaCha, &bytes),
+            ),
+            elements: Vec::new(),
+        }
+    }
+
+    fn draw<S: Strategy>(&mut self, strategy: S) -> S::Value {
+        strategy
+            .new_tree(&mut self.runner)
+            .expect("generator produced no value")
+            .current()
+    }
+
+    fn immutable_value(&mut self) -> String {
+        match self.draw(immutable_set_op()) {
+            CrdtOperation::ImmutableSet { value, .. } => value,
+            other => unreachable!("unexpected operation {:?}", other),
+        }
+    }
+
+    fn lww_value(&mut self) -> String {
+        match self.draw(lww_write_op()) {
+            CrdtOperation::LwwWrite { value, .. } => value,
+            other => unreachable!("unexpected operation {:?}", other),
+        }
+    }
+
+    fn mv_value(&mut self) -> String {
+        match self.draw(mv_register_write_op()) {
+            CrdtOperation::MvRegisterWrite { value, .. } => value,
+            other => unreachable!("unexpected operation {:?}", other),
+        }
+    }
+
+    fn counter_delta(&mut self, increment: bool) -> i64 {
+        if increment {
+            match self.draw(pn_counter_increment_op()) {
+                CrdtOperation::PnCounterIncrement { amount, .. } => amount,
+                other => unreachable!("unexpected operation {:?}", other),
+            }
+        } else {
+            match self.draw(pn_counter_decrement_op()) {
+                CrdtOperation::PnCounterDecrement { amount, .. } => -amount,
+                other => unreachable!("unexpected operation {:?}", other),
+            }
+        }
+    }
+
+    /// Picks from a small pool of set elements, so that devices add and
+    /// remove the same elements concurrently
+    fn set_element(&mut self, devices: usize, rng: &mut StdRng) -> String {
+        if self.elements.is_empty() {
+            for _ in 0..devices * ELEMENTS_PER_DEVICE {
+                match self.draw(or_set_add_op()) {
+                    CrdtOperation::OrSetAdd { element, .. } => self.elements.push(element),
+                    other => unreachable!("unexpected operation {:?}", other),
+                }
+            }
+        }
+        self.elements[rng.gen_range(0..self.elements.len())].clone()
+    }
+
+    fn sequence_element(&mut self) -> String {
+        match self.draw(rga_insert_op()) {
+            CrdtOperation::RgaInsert { element, .. } => element,
+            other => unreachable!("unexpected operation {:?}", other),
+        }
+    }
+
+    fn character(&mut self) -> String {
+        match self.draw(peritext_insert_op()) {
+            CrdtOperation::PeritextInsert { character, .. } => character.to_string(),
+            other => unreachable!("unexpected operation {:?}", other),
+        }
+    }
+
+    fn format(&mut self) -> String {
+        match self.draw(peritext_format_op()) {
+            CrdtOperation::PeritextFormat { format, .. } => format,
+            other => unreachable!("unexpected operation {:?}", other),
+        }
+    }
+}
+
+/// Everything a model needs to make a local edit
+struct EditContext<'a> {
+    dot: Dot,
+    seen: &'a [u64],
+    devices: usize,
+    rng: &'a mut StdRng,
+    payloads: &'a mut Payloads,
+}
+
+/// A replica of a field under one strategy
+trait Model: Default + Clone + PartialEq + Serialize {
+    /// Applies a local edit
+    fn edit(&mut self, ctx: EditContext<'_>) -> EditKind;
+
+    /// Merges another replica's state into this one
+    fn merge(&mut self, other: &Self);
+
+    /// Number of concurrent values a read returns
+    fn values(&self) -> usize {
+        1
+    }
+
+    /// Counts edits whose effect is missing from this (converged) state
+    fn lost_edits(&self, edits: &[EditRecord]) -> usize;
+}
+
+/// Raw simulation results
+struct Outcome {
+    edits: usize,
+    converged: bool,
+    sync_rounds: usize,
+    lost_edits: usize,
+    conflicts: usize,
+    state_bytes: usize,
+    merges: usize,
+    merge_nanos: u128,
+}
+
+/// Runs the workload's schedule against one model
+fn run<M: Model>(workload: &Workload, rate: f64, seed: u64) -> Outcome {
+    let devices = workload.devices;
+    let mut replicas = vec![M::default(); devices];
+    let mut clocks = vec![vec![0u64; devices]; devices];
+    // The schedule only consumes `schedule`, so it is the same for every model
+    let mut schedule = StdRng::seed_from_u64(seed);
+    let mut rng = StdRng::seed_from_u64(seed.rotate_left(32));
+    let mut payloads = Payloads::new(seed);
+
+    let mut edits = Vec::new();
+    let mut conflicts = 0;
+    let mut merges = 0;
+    let mut merge_nanos = 0u128;
+
+    let mut pull = |replicas: &mut [M], clocks: &mut [Vec<u64>], device: usize, peer: usize| {
+        let other = replicas[peer].clone();
+        let start = Instant::now();
+        replicas[device].merge(&other);
+        merge_nanos += start.elapsed().as_nanos();
+        merges += 1;
+
+        let peer_clock = clocks[peer].clone();
+        for (mine, theirs) in clocks[device].iter_mut().zip(peer_clock) {
+            *mine = (*mine).max(theirs);
+        }
+    };
+
+    for _ in 0..workload.rounds {
+        for device in 0..devices {
+            let mut count = rate.trunc() as usize;
+            if schedule.gen_bool(rate.fract()) {
+                count += 1;
+            }
+            for _ in 0..count {
+                clocks[device][device] += 1;
+                let seen = clocks[device].clone();
+                let dot = Dot {
+                    time: seen.iter().sum(),
+                    device,
+                    seq: seen[device],
+                };
+                // Writing over concurrent values means resolving them first
+                conflicts += replicas[device].values().saturating_sub(1);
+                let kind = replicas[device].edit(EditContext {
+                    dot,
+                    seen: &seen,
+                    devices,
+                    rng: &mut rng,
+                    payloads: &mut payloads,
+                });
+                edits.push(EditRecord { dot, seen, kind });
+            }
+        }
+
+        if devices > 1 {
+            for device in 0..devices {
+                if schedule.gen_bool(workload.concurrency) {
+                    continue;
+                }
+                let peer = random_peer(&mut schedule, devices, device);
+                pull(&mut replicas, &mut clocks, device, peer);
+            }
+        }
+    }
+
+    let max_sync_rounds = devices * MAX_SYNC_ROUNDS_PER_DEVICE;
+    let mut sync_rounds = 0;
+    while !all_equal(&replicas) && sync_rounds < max_sync_rounds {
+        sync_rounds += 1;
+        for device in 0..devices {
+            let peer = random_peer(&mut schedule, devices, device);
+            pull(&mut replicas, &mut clocks, device, peer);
+        }
+    }
+
+    let converged = all_equal(&replicas);
+    let state = &replicas[0];
+    Outcome {
+        edits: edits.len(),
+        converged,
+        sync_rounds,
+        lost_edits: state.lost_edits(&edits),
+        conflicts: conflicts + state.values().saturating_sub(1),
+        state_bytes: serde_json::to_vec(state)
+            .map(|bytes| bytes.len())
+            .unwrap_or(0),
+        merges,
+        merge_nanos,
+    }
+}
+
+/// Picks a device other than `device`
+fn random_peer(rng: &mut StdRng, devices: usize, device: usize) -> usize {
+    (device + rng.gen_range(1..devices)) % devices
+}
+
+fn all_equal<M: PartialEq>(replicas: &[M]) -> bool {
+    replicas.windows(2).all(|pair| pair[0] == pair[1])
+}
+
+/// Write-once register; concurrent first writes resolve to the earliest
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+struct ImmutableModel {
+    value: Option<(Dot, String)>,
+}
+
+impl Model for ImmutableModel {
+    fn edit(&mut self, ctx: EditContext<'_>) -> EditKind {
+        if self.value.is_none() {
+            self.value = Some((ctx.dot, ctx.payloads.immutable_value()));
+        }
+        EditKind::Write
+    }
+
+    fn merge(&mut self, other: &Self) {
+        if let Some(theirs) = &other.value {
+            let earlier = match &self.value {
+                Some(mine) => theirs.0 < mine.0,
+                None => true,
+            };
+            if earlier {
+                self.value = Some(theirs.clone());
+            }
+        }
+    }
+
+    fn lost_edits(&self, edits: &[EditRecord]) -> usize {
+        // Every write but the one that stuck was rejected or discarded
+        edits.len() - usize::from(self.value.is_some())
+    }
+}
+
+/// Last-write-wins register
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+struct LwwModel {
+    value: Option<(Dot, String)>,
+}
+
+impl Model for LwwModel {
+    fn edit(&mut self, ctx: EditContext<'_>) -> EditKind {
+        self.value = Some((ctx.dot, ctx.payloads.lww_value()));
+        EditKind::Write
+    }
+
+    fn merge(&mut self, other: &Self) {
+        if let Some(theirs) = &other.value {
+            let later = match &self.value {
+                Some(mine) => theirs.0 > mine.0,
+                None => true,
+            };
+            if later {
+                self.value = Some(theirs.clone());
+            }
+        }
+    }
+
+    fn lost_edits(&self, edits: &[EditRecord]) -> usize {
+        // A write is lost when a merge lets a concurrent write replace it
+        edits
+            .iter()
+            .filter(|edit| {
+                edits.iter().any(|other| {
+                    other.dot > edit.dot && !other.observed(&edit.dot) && !edit.observed(&other.dot)
+                })
+            })
+            .count()
+    }
+}
+
+/// Multi-value register keeping every concurrent write
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+struct MvRegisterModel {
+    /// (write, version vector it observed, value), sorted by write
+    values: Vec<(Dot, Vec<u64>, String)>,
+}
+
+impl Model for MvRegisterModel {
+    fn edit(&mut self, ctx: EditContext<'_>) -> EditKind {
+        self.values = vec![(ctx.dot, ctx.seen.to_vec(), ctx.payloads.mv_value())];
+        EditKind::Write
+    }
+
+    fn merge(&mut self, other: &Self) {
+        for entry in &other.values {
+            if !self.values.iter().any(|mine| mine.0 == entry.0) {
+                self.values.push(entry.clone());
+            }
+        }
+        let all = self.values.clone();
+        self.values.retain(|(dot, _, _)| {
+            !all.iter()
+                .any(|(other, seen, _)| other != dot && seen[dot.device] >= dot.seq)
+        });
+        self.values.sort_by_key(|(dot, _, _)| *dot);
+    }
+
+    fn values(&self) -> usize {
+        self.values.len().max(1)
+    }
+
+    fn lost_edits(&self, edits: &[EditRecord]) -> usize {
+        edits
+            .iter()
+            .filter(|edit| {
+                !self.values.iter().any(|(dot, _, _)| *dot == edit.dot)
+                    && !superseded(&edit.dot, edits)
+            })
+            .count()
+    }
+}
+
+/// Positive-negative counter
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+struct CounterModel {
+    /// Per-device (increments, decrements)
+    totals: BTreeMap<usize, (u64, u64)>,
+}
+
+impl Model for CounterModel {
+    fn edit(&mut self, ctx: EditContext<'_>) -> EditKind {
+        let delta = ctx.payloads.counter_delta(ctx.rng.gen_bool(0.5));
+        let (inc, dec) = self.totals.entry(ctx.dot.device).or_default();
+        if delta >= 0 {
+            *inc += delta as u64;
+        } else {
+            *dec += delta.unsigned_abs();
+        }
+        EditKind::Count { delta }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        for (device, (inc, dec)) in &other.totals {
+            let mine = self.totals.entry(*device).or_default();
+            mine.0 = mine.0.max(*inc);
+            mine.1 = mine.1.max(*dec);
+        }
+    }
+
+    fn lost_edits(&self, edits: &[EditRecord]) -> usize {
+        let mut expected: BTreeMap<usize, (u64, u64, usize)> = BTreeMap::new();
+        for edit in edits {
+            if let EditKind::Count { delta } = edit.kind {
+                let (inc, dec, count) = expected.entry(edit.dot.device).or_default();
+                if delta >= 0 {
+                    *inc += delta as u64;
+                } else {
+                    *dec += delta.unsigned_abs();
+                }
+                *count += 1;
+            }
+        }
+        expected
+            .iter()
+            .filter(|(device, (inc, dec, _))| {
+                self.totals.get(device).copied().unwrap_or_default() != (*inc, *dec)
+            })
+            .map(|(_, (_, _, count))| count)
+            .sum()
+    }
+}
+
+/// Observed-remove set with add-wins semantics
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+struct OrSetModel {
+    /// Live add tags per element
+    elements: BTreeMap<String, BTreeSet<Dot>>,
+    /// Tags removed so far
+    removed: BTreeSet<Dot>,
+}
+
+impl Model for OrSetModel {
+    fn edit(&mut self, ctx: EditContext<'_>) -> EditKind {
+        if !self.elements.is_empty() && ctx.rng.gen_bool(0.3) {
+            let index = ctx.rng.gen_range(0..self.elements.len());
+            let element = self.elements.keys().nth(index).cloned().unwrap_or_default();
+            if let Some(tags) = self.elements.remove(&element) {
+                self.removed.extend(tags);
+            }
+            EditKind::Remove { element }
+        } else {
+            let element = ctx.payloads.set_element(ctx.devices, ctx.rng);
+            self.elements.entry(element).or_default().insert(ctx.dot);
+            EditKind::Add
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.removed.extend(other.removed.iter().copied());
+        for (element, tags) in &other.elements {
+            self.elements
+                .entry(element.clone())
+                .or_default()
+                .extend(tags.iter().copied());
+        }
+        let removed = &self.removed;
+        self.elements.retain(|_, tags| {
+            tags.retain(|tag| !removed.contains(tag));
+            !tags.is_empty()
+        });
+    }
+
+    fn lost_edits(&self, edits: &[EditRecord]) -> usize {
+        // A remove is lost when a concurrent add keeps the element alive
+        edits
+            .iter()
+            .filter(|remove| {
+                let EditKind::Remove { element } = &remove.kind else {
+                    return false;
+                };
+                self.elements.get(element).is_some_and(|tags| {
+                    tags.iter().any(|tag| {
+                        !remove.observed(tag)
+                            && !edits
+                                .iter()
+                                .any(|add| add.dot == *tag && add.observed(&remove.dot))
+                    })
+                })
+            })
+            .count()
+    }
+}
+
+/// Sequence element
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct SequenceNode {
+    id: Dot,
+    origin: Option<Dot>,
+    value: String,
+    deleted: bool,
+}
+
+/// Replicated growable array with tombstones
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+struct Sequence {
+    nodes: Vec<SequenceNode>,
+}
+
+impl Sequence {
+    fn visible(&self) -> Vec<usize> {
+        (0..self.nodes.len())
+            .filter(|&i| !self.nodes[i].deleted)
+            .collect()
+    }
+
+    fn position(&self, id: &Dot) -> Option<usize> {
+        self.nodes.iter().position(|node| node.id == *id)
+    }
+
+    /// Inserts `value` at a random visible position
+    fn insert(&mut self, dot: Dot, value: String, rng: &mut StdRng) -> EditKind {
+        let visible = self.visible();
+        let at = rng.gen_range(0..=visible.len());
+        let origin = at.checked_sub(1).map(|i| self.nodes[visible[i]].id);
+        self.integrate(SequenceNode {
+            id: dot,
+            origin,
+            value,
+            deleted: false,
+        });
+        EditKind::Insert
+    }
+
+    /// Deletes a random visible element
+    fn delete(&mut self, rng: &mut StdRng) -> EditKind {
+        let visible = self.visible();
+        let index = visible[rng.gen_range(0..visible.len())];
+        self.nodes[index].deleted = true;
+        EditKind::Delete {
+            target: self.nodes[index].id,
+        }
+    }
+
+    /// Places a node after its origin, skipping concurrent inserts with
+    /// higher ids
+    fn integrate(&mut self, node: SequenceNode) {
+        let mut index = match &node.origin {
+            Some(origin) => self.position(origin).map_or(0, |i| i + 1),
+            None => 0,
+        };
+        while index < self.nodes.len() && self.nodes[index].id > node.id {
+            index += 1;
+        }
+        self.nodes.insert(index, node);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        // Origins always precede their inserts, so other's order is causal
+        for node in &other.nodes {
+            match self.position(&node.id) {
+                Some(index) => self.nodes[index].deleted |= node.deleted,
+                None => self.integrate(node.clone()),
+            }
+        }
+    }
+
+    fn lost_edits(&self, edits: &[EditRecord]) -> usize {
+        edits
+            .iter()
+            .filter(|edit| match &edit.kind {
+                EditKind::Insert => self.position(&edit.dot).is_none(),
+                EditKind::Delete { target } => {
+                    !matches!(self.position(target), Some(index) if self.nodes[index].deleted)
+                }
+                _ => false,
+            })
+            .count()
+    }
+}
+
+/// RGA sequence of list elements
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+struct RgaModel(Sequence);
+
+impl Model for RgaModel {
+    fn edit(&mut self, ctx: EditContext<'_>) -> EditKind {
+        if !self.0.visible().is_empty() && ctx.rng.gen_bool(0.3) {
+            self.0.delete(ctx.rng)
+        } else {
+            let value = ctx.payloads.sequence_element();
+            self.0.insert(ctx.dot, value, ctx.rng)
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.0.merge(&other.0);
+    }
+
+    fn lost_edits(&self, edits: &[EditRecord]) -> usize {
+        self.0.lost_edits(edits)
+    }
+}
+
+/// Formatting span anchored to sequence elements
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct Mark {
+    id: Dot,
+    start: Dot,
+    end: Dot,
+    format: String,
+}
+
+/// Peritext: character sequence plus anchored formatting marks
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+struct PeritextModel {
+    text: Sequence,
+    /// Sorted by id
+    marks: Vec<Mark>,
+}
+
+impl Model for PeritextModel {
+    fn edit(&mut self, ctx: EditContext<'_>) -> EditKind {
+        let visible = self.text.visible();
+        let roll: f64 = ctx.rng.gen();
+        if visible.is_empty() || roll < 0.6 {
+            let character = ctx.payloads.character();
+            self.text.insert(ctx.dot, character, ctx.rng)
+        } else if roll < 0.85 {
+            self.text.delete(ctx.rng)
+        } else {
+            let a = ctx.rng.gen_range(0..visible.len());
+            let b = ctx.rng.gen_range(0..visible.len());
+            let format = ctx.payloads.format();
+            self.marks.push(Mark {
+                id: ctx.dot,
+                start: self.text.nodes[visible[a.min(b)]].id,
+                end: self.text.nodes[visible[a.max(b)]].id,
+                format,
+            });
+            EditKind::Format
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.text.merge(&other.text);
+        for mark in &other.marks {
+            if !self.marks.iter().any(|mine| mine.id == mark.id) {
+                self.marks.push(mark.clone());
+            }
+        }
+        self.marks.sort_by_key(|mark| mark.id);
+    }
+
+    fn lost_edits(&self, edits: &[EditRecord]) -> usize {
+        let lost_marks = edits
+            .iter()
+            .filter(|edit| {
+                edit.kind == EditKind::Format && !self.marks.iter().any(|mark| mark.id == edit.dot)
+            })
+            .count();
+        self.text.lost_edits(edits) + lost_marks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dol::ast::{CrdtAnnotation, Gen, Span, Visibility};
+
+    fn named(name: &str) -> TypeExpr {
+        TypeExpr::Named(name.to_string())
+    }
+
+    fn generic(name: &str, arg: TypeExpr) -> TypeExpr {
+        TypeExpr::Generic {
+            name: name.to_string(),
+            args: vec![arg],
+        }
+    }
+
+    fn field(name: &str, type_: TypeExpr, strategy: Option<CrdtStrategy>) -> Statement {
+        Statement::HasField(Box::new(HasField {
+            name: name.to_string(),
+            type_,
+            default: None,
+            constraint: None,
+            crdt_annotation: strategy.map(|strategy| CrdtAnnotation {
+                strategy,
+                options: vec![],
+                span: Span::default(),
+            }),
+            personal: false,
+            span: Span::default(),
+        }))
+    }
+
+    fn gene(statements: Vec<Statement>) -> Declaration {
+        Declaration::Gene(Gen {
+            visibility: Visibility::default(),
+            name: "doc.note".to_string(),
+            extends: None,
+            statements,
+            exegesis: "A shared note".to_string(),
+            span: Span::default(),
+        })
+    }
+
+    #[test]
+    fn test_candidate_strategies() {
+        use CrdtStrategy::*;
+
+        assert_eq!(
+            candidate_strategies(&named("String")),
+            vec![Immutable, Lww, MvRegister, Peritext]
+        );
+        assert_eq!(
+            candidate_strategies(&named("i64")),
+            vec![Immutable, Lww, PnCounter, MvRegister]
+        );
+        assert_eq!(
+            candidate_strategies(&generic("Set", named("String"))),
+            vec![Immutable, OrSet, MvRegister]
+        );
+        assert_eq!(
+            candidate_strategies(&generic("Vec", named("String"))),
+            vec![Immutable, Lww, Rga, MvRegister]
+        );
+        assert_eq!(
+            candidate_strategies(&generic("Option", named("i64"))),
+            candidate_strategies(&named("i64"))
+        );
+    }
+
+    #[test]
+    fn test_every_strategy_converges() {
+        for workload in [
+            Workload::default(),
+            Workload {
+                devices: 5,
+                edit_rate: 2.5,
+                concurrency: 0.9,
+                rounds: 20,
+                ..Workload::default()
+            },
+            Workload {
+                devices: 1,
+                ..Workload::default()
+            },
+        ] {
+            for strategy in STRATEGIES {
+                let m = simulate_strategy(strategy, &workload, "field").unwrap();
+                assert!(m.converged, "{:?} did not converge", strategy);
+                assert!(m.edits > 0);
+                assert!(m.state_bytes > 0);
+                if workload.devices > 1 {
+                    assert!(m.merges > 0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_concurrent_overwrites_are_measured() {
+        let workload = Workload {
+            devices: 4,
+            concurrency: 0.8,
+            ..Workload::default()
+        };
+        let lww = simulate_strategy(CrdtStrategy::Lww, &workload, "title").unwrap();
+        let mv = simulate_strategy(CrdtStrategy::MvRegister, &workload, "title").unwrap();
+        let immutable = simulate_strategy(CrdtStrategy::Immutable, &workload, "title").unwrap();
+
+        assert!(lww.lost_edits > 0);
+        assert_eq!(lww.conflicts, 0);
+        assert_eq!(mv.lost_edits, 0);
+        assert!(mv.conflicts > 0);
+        assert_eq!(immutable.lost_edits, immutable.edits - 1);
+
+        // Same schedule for every strategy
+        assert_eq!(lww.edits, mv.edits);
+        assert_eq!(lww.edits, immutable.edits);
+    }
+
+    #[test]
+    fn test_merging_strategies_lose_nothing() {
+        let workload = Workload {
+            devices: 4,
+            edit_rate: 2.0,
+            concurrency: 0.7,
+            ..Workload::default()
+        };
+        for strategy in [
+            CrdtStrategy::PnCounter,
+            CrdtStrategy::Rga,
+            CrdtStrategy::Peritext,
+        ] {
+            let m = simulate_strategy(strategy, &workload, "body").unwrap();
+            assert_eq!(m.lost_edits, 0, "{:?}", strategy);
+            assert_eq!(m.conflicts, 0, "{:?}", strategy);
+        }
+    }
+
+    #[test]
+    fn test_simulation_is_deterministic() {
+        let workload = Workload::default();
+        for strategy in STRATEGIES {
+            let a = simulate_strategy(strategy, &workload, "field").unwrap();
+            let b = simulate_strategy(strategy, &workload, "field").unwrap();
+            assert_eq!(
+                (
+                    a.edits,
+                    a.sync_rounds,
+                    a.lost_edits,
+                    a.conflicts,
+                    a.state_bytes
+                ),
+                (
+                    b.edits,
+                    b.sync_rounds,
+                    b.lost_edits,
+                    b.conflicts,
+                    b.state_bytes
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_advise_recommends_measured_strategies() {
+        let mut workload = Workload {
+            devices: 4,
+            concurrency: 0.7,
+            ..Workload::default()
+        };
+        workload.field_edit_rates.insert("id".to_string(), 0.0);
+
+        let report = advise_declarations(
+            &[gene(vec![
+                field("id", named("String"), Some(CrdtStrategy::Lww)),
+                field("views", named("i64"), None),
+                field("body", named("String"), Some(CrdtStrategy::Peritext)),
+                field("tags", generic("Set", named("String")), None),
+            ])],
+            &workload,
+        )
+        .unwrap();
+
+        let recommended: Vec<_> = report
+            .fields
+            .iter()
+            .map(|advice| (advice.field.as_str(), advice.recommended))
+            .collect();
+        assert_eq!(
+            recommended,
+            vec![
+                ("id", CrdtStrategy::Immutable),
+                ("views", CrdtStrategy::PnCounter),
+                ("body", CrdtStrategy::Peritext),
+                ("tags", CrdtStrategy::OrSet),
+            ]
+        );
+        assert!(report.fields[0].changes_strategy());
+        assert!(!report.fields[2].changes_strategy());
+        assert_eq!(report.fields[3].type_name, "Set<String>");
+        assert_eq!(report.fields[3].measurements.len(), 3);
+
+        let table = report.to_string();
+        assert!(table.contains("doc.note.id: String (current: lww) -> recommend immutable"));
+        assert!(table.contains("doc.note.body: String (current: peritext) -> keep peritext"));
+    }
+
+    #[test]
+    fn test_advise_parses_schema() {
+        let source = r#"
+gen counter.page {
+  @crdt(lww)
+  has views: i64
+}
+
+docs {
+  A page view counter.
+}
+"#;
+        let workload = Workload {
+            devices: 4,
+            concurrency: 0.7,
+            ..Workload::default()
+        };
+        let report = advise(source, &workload).unwrap();
+        assert_eq!(report.fields.len(), 1);
+        assert_eq!(report.fields[0].gene, "counter.page");
+        assert_eq!(report.fields[0].current, Some(CrdtStrategy::Lww));
+        assert_eq!(report.fields[0].recommended, CrdtStrategy::PnCounter);
+
+        assert!(matches!(
+            advise("gen {", &workload),
+            Err(TestError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_workload() {
+        let invalid = [
+            Workload {
+                devices: 0,
+                ..Workload::default()
+            },
+            Workload {
+                concurrency: 1.5,
+                ..Workload::default()
+            },
+            Workload {
+                edit_rate: -1.0,
+                ..Workload::default()
+            },
+            Workload {
+                field_edit_rates: [("body".to_string(), f64::NAN)].into_iter().collect(),
+                ..Workload::default()
+            },
+        ];
+        for workload in invalid {
+            assert!(matches!(
+                simulate_strategy(CrdtStrategy::Lww, &workload, "body"),
+                Err(TestError::InvalidWorkload { .. })
+            ));
+        }
+    }
+}
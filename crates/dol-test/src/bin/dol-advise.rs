@@ -0,0 +1,170 @@
+//! dol-advise - Recommend CRDT strategies from simulated workloads
+//!
+//! Simulates every valid `@crdt` strategy for each field of a DOL schema
+//! under a synthetic workload and recommends the best measured one.
+//!
+//! # Usage
+//!
+//! ```bash
+//! # Advise with the default workload (3 devices, 1 edit/round, 50% concurrency)
+//! dol-advise examples/crdt_chat_message.dol
+//!
+//! # Describe the workload on the command line
+//! dol-advise --devices 8 --edit-rate 2 --concurrency 0.9 schema.dol
+//!
+//! # Fields that are rarely or never edited
+//! dol-advise --field-rate id=0 --field-rate title=0.1 schema.dol
+//!
+//! # Load the workload from JSON and emit a JSON report
+//! dol-advise --workload workload.json --format json schema.dol
+//!
+//! # CI mode: fail if an annotated strategy differs from the recommendation
+//! dol-advise --strict schema.dol
+//! ```
+
+use clap::{Parser, ValueEnum};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use dol_test::advisor::{advise, Workload};
+
+/// Recommend CRDT strategies from simulated workloads
+#[derive(Parser, Debug)]
+#[command(name = "dol-advise")]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// DOL schema to analyze
+    schema: PathBuf,
+
+    /// Workload description (JSON); flags below override its values
+    #[arg(long)]
+    workload: Option<PathBuf>,
+
+    /// Number of devices editing the document
+    #[arg(long)]
+    devices: Option<usize>,
+
+    /// Mean edits per device per sync round
+    #[arg(long)]
+    edit_rate: Option<f64>,
+
+    /// Edit rate for a single field (NAME=RATE, repeatable)
+    #[arg(long, value_parser = parse_field_rate)]
+    field_rate: Vec<(String, f64)>,
+
+    /// Probability that a device skips a sync round (0-1)
+    #[arg(long)]
+    concurrency: Option<f64>,
+
+    /// Number of edit/sync rounds to simulate
+    #[arg(long)]
+    rounds: Option<usize>,
+
+    /// Random seed
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value = "pretty")]
+    format: OutputFormat,
+
+    /// Fail if any annotated strategy differs from the recommendation
+    #[arg(long)]
+    strict: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Pretty,
+    Json,
+}
+
+fn parse_field_rate(s: &str) -> Result<(String, f64), String> {
+    let (field, rate) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=RATE, got '{}'", s))?;
+    let rate = rate
+        .parse()
+        .map_err(|_| format!("invalid edit rate '{}'", rate))?;
+    Ok((field.to_string(), rate))
+}
+
+fn load_workload(args: &Args) -> Result<Workload, String> {
+    let mut workload = match &args.workload {
+        Some(path) => {
+            let json = std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            serde_json::from_str(&json)
+                .map_err(|e| format!("Invalid workload {}: {}", path.display(), e))?
+        }
+        None => Workload::default(),
+    };
+
+    if let Some(devices) = args.devices {
+        workload.devices = devices;
+    }
+    if let Some(edit_rate) = args.edit_rate {
+        workload.edit_rate = edit_rate;
+    }
+    if let Some(concurrency) = args.concurrency {
+        workload.concurrency = concurrency;
+    }
+    if let Some(rounds) = args.rounds {
+        workload.rounds = rounds;
+    }
+    if let Some(seed) = args.seed {
+        workload.seed = seed;
+    }
+    workload
+        .field_edit_rates
+        .extend(args.field_rate.iter().cloned());
+
+    Ok(workload)
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let workload = match load_workload(&args) {
+        Ok(workload) => workload,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let source = match std::fs::read_to_string(&args.schema) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("error: Failed to read {}: {}", args.schema.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let report = match advise(&source, &workload) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match args.format {
+        OutputFormat::Pretty => print!("{}", report),
+        OutputFormat::Json => match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return ExitCode::FAILURE;
+            }
+        },
+    }
+
+    let mismatched = report
+        .fields
+        .iter()
+        .any(|advice| advice.current.is_some() && advice.changes_strategy());
+    if args.strict && mismatched {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -41,11 +41,12 @@
 //!
 //! # Architecture
 //!
-//! The framework is organized into three main modules:
+//! The framework is organized into four main modules:
 //!
 //! - [`properties`]: CRDT property definitions and verification functions
 //! - [`generators`]: Arbitrary generators for CRDT operations and network topologies
 //! - [`harness`]: Test harness utilities for running property tests
+//! - [`advisor`]: Simulation-backed `@crdt` strategy recommendations
 //!
 //! # Testing Strategy
 //!
@@ -68,6 +69,7 @@
 pub mod properties;
 pub mod generators;
 pub mod harness;
+pub mod advisor;
 
 use thiserror::Error;
 
@@ -98,6 +100,16 @@ pub enum TestError {
     /// Code generation error
     #[error("Code generation error: {0}")]
     CodegenError(String),
+
+    /// DOL parse error
+    #[error("Parse error: {0}")]
+    ParseError(String),
+
+    /// Workload description that cannot be simulated
+    #[error("Invalid workload: {reason}")]
+    InvalidWorkload {
+        reason: String,
+    },
 }
 
 /// Result type for property tests
@@ -7,11 +7,12 @@ use crate::embedding::EmbeddingService;
 use crate::error::{AIError, Result};
 use crate::inference::InferenceEngine;
 use crate::model_manager::ModelId;
-use automerge::{AutoCommit, ObjId};
+use automerge::{ActorId, AutoCommit, ObjId, ScalarValue, Value};
 use automerge::transaction::Transactable;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tracing::{debug, info, warn};
+use vudo_state::{ConcurrentValue, ConflictHandler, DocumentHandle, DocumentId, FieldConflict};
 
 /// A conflict detected in CRDT merge.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,6 +60,58 @@ impl ConflictValue {
             ConflictValue::Complex(v) => serde_json::to_string(v).unwrap_or_default(),
         }
     }
+
+    /// Convert an Automerge value into a conflict value.
+    pub fn from_automerge(value: &Value<'_>) -> Self {
+        match value {
+            Value::Scalar(scalar) => match scalar.as_ref() {
+                ScalarValue::Str(s) => ConflictValue::String(s.to_string()),
+                ScalarValue::Int(i) | ScalarValue::Timestamp(i) => ConflictValue::Int(*i),
+                ScalarValue::Uint(u) => ConflictValue::Int(*u as i64),
+                ScalarValue::Counter(c) => ConflictValue::Int(i64::from(c)),
+                ScalarValue::F64(f) => ConflictValue::Float(*f),
+                ScalarValue::Boolean(b) => ConflictValue::Bool(*b),
+                ScalarValue::Null => ConflictValue::Null,
+                ScalarValue::Bytes(bytes) => ConflictValue::Complex(serde_json::json!(bytes)),
+                ScalarValue::Unknown { type_code, bytes } => ConflictValue::Complex(
+                    serde_json::json!({ "type_code": type_code, "bytes": bytes }),
+                ),
+            },
+            Value::Object(obj_type) => {
+                ConflictValue::Complex(serde_json::Value::String(format!("{:?}", obj_type)))
+            }
+        }
+    }
+}
+
+impl Conflict {
+    /// Build a conflict from concurrent values reported by the state engine.
+    ///
+    /// The value written by `local_actor` becomes the local value; the
+    /// current winner among the other actors becomes the remote value.
+    /// Returns `None` if the field has no value from `local_actor` or no
+    /// value from any other actor.
+    pub fn from_field_conflict(
+        document_id: impl Into<String>,
+        conflict: &FieldConflict,
+        local_actor: &ActorId,
+    ) -> Option<Self> {
+        let local = conflict.by_actor(local_actor)?;
+        let remote = conflict
+            .values
+            .iter()
+            .rev()
+            .find(|v| &v.actor != local_actor)?;
+
+        Some(Conflict {
+            document_id: document_id.into(),
+            object_id: "root".to_string(),
+            key: conflict.key.clone(),
+            local_value: ConflictValue::from_automerge(&local.value),
+            remote_value: ConflictValue::from_automerge(&remote.value),
+            context: Vec::new(),
+        })
+    }
 }
 
 /// A suggested resolution for a conflict.
@@ -246,6 +299,45 @@ impl ConflictResolver {
         Ok(suggestions)
     }
 
+    /// Discover unresolved conflicts in a document held by the state engine.
+    ///
+    /// Each conflicting root field is reported from the point of view of
+    /// `local_actor`, with the document's other scalar fields as context.
+    pub fn detect_conflicts(
+        &self,
+        handle: &DocumentHandle,
+        local_actor: &ActorId,
+    ) -> Result<Vec<Conflict>> {
+        let document_id = handle.id.to_string();
+        let field_conflicts = handle.conflicts()?;
+
+        let context: Vec<(String, String)> = handle.read(|doc| {
+            use automerge::{ReadDoc, ROOT};
+            let mut context = Vec::new();
+            for key in doc.keys(ROOT) {
+                if field_conflicts.iter().any(|c| c.key == key) {
+                    continue;
+                }
+                if let Some((value @ Value::Scalar(_), _)) = doc.get(ROOT, key.as_str())? {
+                    context.push((key, ConflictValue::from_automerge(&value).to_string_repr()));
+                }
+            }
+            Ok(context)
+        })?;
+
+        let conflicts: Vec<Conflict> = field_conflicts
+            .iter()
+            .filter_map(|c| Conflict::from_field_conflict(document_id.clone(), c, local_actor))
+            .map(|mut c| {
+                c.context = context.clone();
+                c
+            })
+            .collect();
+
+        debug!("Detected {} conflicts in {}", conflicts.len(), document_id);
+        Ok(conflicts)
+    }
+
     /// Apply a resolution suggestion to an Automerge document.
     pub fn apply_resolution(
         &self,
@@ -336,6 +428,63 @@ impl ConflictResolver {
     }
 }
 
+/// Adapter exposing a [`ConflictResolver`] as a vudo-state conflict policy.
+///
+/// Register it with `ConflictPolicy::Delegate` to let the AI pick winners when
+/// remote changes conflict. Only `UseLocal`/`UseRemote` suggestions at or above
+/// the confidence threshold are applied; anything else is left unresolved.
+pub struct AiConflictHandler {
+    resolver: Arc<ConflictResolver>,
+    local_actor: ActorId,
+    min_confidence: f32,
+}
+
+impl AiConflictHandler {
+    /// Create a handler resolving conflicts from the point of view of `local_actor`.
+    pub fn new(resolver: Arc<ConflictResolver>, local_actor: ActorId) -> Self {
+        Self {
+            resolver,
+            local_actor,
+            min_confidence: 0.5,
+        }
+    }
+
+    /// Set the minimum suggestion confidence required to apply a resolution.
+    pub fn with_min_confidence(mut self, min_confidence: f32) -> Self {
+        self.min_confidence = min_confidence;
+        self
+    }
+}
+
+impl ConflictHandler for AiConflictHandler {
+    fn choose(&self, document_id: &DocumentId, conflict: &FieldConflict) -> Option<ConcurrentValue> {
+        let ai_conflict =
+            Conflict::from_field_conflict(document_id.to_string(), conflict, &self.local_actor)?;
+
+        let suggestion = match self.resolver.suggest_resolution(&ai_conflict) {
+            Ok(suggestion) => suggestion,
+            Err(e) => {
+                warn!("AI conflict resolution failed for {}: {}", conflict.key, e);
+                return None;
+            }
+        };
+        if suggestion.confidence < self.min_confidence {
+            return None;
+        }
+
+        match suggestion.strategy {
+            ResolutionStrategy::UseLocal => conflict.by_actor(&self.local_actor).cloned(),
+            ResolutionStrategy::UseRemote => conflict
+                .values
+                .iter()
+                .rev()
+                .find(|v| v.actor != self.local_actor)
+                .cloned(),
+            _ => None,
+        }
+    }
+}
+
 /// Statistics about the conflict resolver.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConflictResolverStats {
@@ -449,6 +598,75 @@ mod tests {
         assert_eq!(ConflictValue::Null.to_string_repr(), "null");
     }
 
+    #[test]
+    fn test_detect_conflicts_from_state() {
+        use vudo_state::{DocumentId, DocumentStore};
+
+        let resolver = setup_test_resolver();
+        let store = DocumentStore::new();
+        let handle = store.create(DocumentId::new("notes", "1")).unwrap();
+        let local = ActorId::from(vec![1u8; 16]);
+        let remote_actor = ActorId::from(vec![2u8; 16]);
+
+        handle.set_actor(local.clone());
+        handle
+            .update(|doc| {
+                doc.put(automerge::ROOT, "title", "draft")?;
+                doc.put(automerge::ROOT, "author", "alice")?;
+                Ok(())
+            })
+            .unwrap();
+
+        let mut remote = AutoCommit::load(&handle.save()).unwrap().with_actor(remote_actor);
+        remote.put(automerge::ROOT, "title", "remote title").unwrap();
+        handle
+            .update(|doc| {
+                doc.put(automerge::ROOT, "title", "local title")?;
+                Ok(())
+            })
+            .unwrap();
+        handle
+            .update(|doc| {
+                doc.merge(&mut remote)?;
+                Ok(())
+            })
+            .unwrap();
+
+        let conflicts = resolver.detect_conflicts(&handle, &local).unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].key, "title");
+        assert_eq!(conflicts[0].local_value.to_string_repr(), "local title");
+        assert_eq!(conflicts[0].remote_value.to_string_repr(), "remote title");
+        assert_eq!(conflicts[0].context, vec![("author".to_string(), "alice".to_string())]);
+    }
+
+    #[test]
+    fn test_ai_conflict_handler() {
+        let resolver = Arc::new(setup_test_resolver());
+        let local = ActorId::from(vec![1u8; 16]);
+        let remote = ActorId::from(vec![2u8; 16]);
+
+        let value = |text: &str, actor: &ActorId, counter: u64| ConcurrentValue {
+            value: automerge::Value::from(text).into_owned(),
+            actor: actor.clone(),
+            counter,
+            timestamp: 0,
+        };
+        let conflict = FieldConflict {
+            key: "content".to_string(),
+            values: vec![
+                value("short", &local, 2),
+                value("much longer text", &remote, 3),
+            ],
+        };
+
+        let handler = AiConflictHandler::new(resolver, local);
+        let winner = handler
+            .choose(&DocumentId::new("notes", "1"), &conflict)
+            .unwrap();
+        assert_eq!(winner.actor, remote);
+    }
+
     #[test]
     fn test_apply_resolution_use_local() {
         let resolver = setup_test_resolver();
@@ -44,6 +44,10 @@ pub enum AIError {
     #[error("State engine error: {0}")]
     StateEngine(#[from] vudo_state::error::StateError),
 
+    /// Storage error.
+    #[error("Storage error: {0}")]
+    Storage(#[from] vudo_storage::StorageError),
+
     /// Serialization error.
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
@@ -130,7 +130,10 @@ pub mod inference;
 pub mod model_manager;
 pub mod planetserve_integration;
 
-pub use conflict_resolution::{Conflict, ConflictResolver, ConflictValue, ResolutionStrategy, ResolutionSuggestion};
+pub use conflict_resolution::{
+    AiConflictHandler, Conflict, ConflictResolver, ConflictValue, ResolutionStrategy,
+    ResolutionSuggestion,
+};
 pub use embedding::{Embedding, EmbeddingService, SearchResult};
 pub use error::{AIError, Result};
 pub use inference::{InferenceEngine, InferenceTensor, TensorData};
@@ -11,6 +11,7 @@ use serde::{Deserialize, Serialize};
 use std::num::NonZeroUsize;
 use std::sync::Arc;
 use tracing::{debug, info, warn};
+use vudo_storage::{BlobHash, BlobStore};
 
 /// Unique identifier for a model.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -87,6 +88,8 @@ pub struct ModelManager {
     max_memory_bytes: usize,
     /// Current memory usage in bytes.
     current_memory_bytes: Arc<RwLock<usize>>,
+    /// Content hashes of model weights kept in a blob store.
+    weights: DashMap<ModelId, BlobHash>,
 }
 
 impl ModelManager {
@@ -104,6 +107,7 @@ impl ModelManager {
             max_cache_size: config.max_cache_size,
             max_memory_bytes: config.max_memory_bytes,
             current_memory_bytes: Arc::new(RwLock::new(0)),
+            weights: DashMap::new(),
         }
     }
 
@@ -171,6 +175,66 @@ impl ModelManager {
         Ok(loaded)
     }
 
+    /// Store the weights of a registered model in a blob store.
+    ///
+    /// Identical weights shared by several models or versions are stored
+    /// once. Replacing a model's weights releases the previous blob.
+    pub async fn store_weights(
+        &self,
+        store: &dyn BlobStore,
+        id: &ModelId,
+        model_bytes: Vec<u8>,
+    ) -> Result<BlobHash> {
+        if !self.registry.contains_key(id) {
+            return Err(AIError::ModelNotFound(id.to_string()));
+        }
+
+        let hash = store.put_blob(model_bytes.into()).await?;
+        if let Some(previous) = self.weights.insert(id.clone(), hash) {
+            store.release_blob(&previous).await?;
+        }
+        debug!("Stored weights of model {} as blob {}", id, hash);
+        Ok(hash)
+    }
+
+    /// Load a model from the weights stored with [`store_weights`](Self::store_weights).
+    ///
+    /// Returns the cached model if it is already loaded.
+    pub async fn load_stored(
+        &self,
+        store: &dyn BlobStore,
+        id: &ModelId,
+    ) -> Result<Arc<LoadedModel>> {
+        if let Some(model) = self.get(id) {
+            return Ok(model);
+        }
+
+        let hash = self
+            .weights_hash(id)
+            .ok_or_else(|| AIError::ModelNotFound(id.to_string()))?;
+        let model_bytes = store
+            .get_blob(&hash)
+            .await?
+            .ok_or(vudo_storage::StorageError::BlobNotFound(hash))?;
+        self.load(id, model_bytes.to_vec())
+    }
+
+    /// Release a model's stored weights.
+    ///
+    /// The blob is removed by the store's next garbage collection unless
+    /// another model still references it.
+    pub async fn release_weights(&self, store: &dyn BlobStore, id: &ModelId) -> Result<()> {
+        if let Some((_, hash)) = self.weights.remove(id) {
+            store.release_blob(&hash).await?;
+        }
+        Ok(())
+    }
+
+    /// Content hash of a model's stored weights.
+    pub fn weights_hash(&self, id: &ModelId) -> Option<BlobHash> {
+        self.weights.get(id).map(|entry| *entry.value())
+    }
+
     /// Get a model from the cache.
     pub fn get(&self, id: &ModelId) -> Option<Arc<LoadedModel>> {
         let mut cache = self.cache.write();
@@ -470,6 +534,53 @@ mod tests {
         assert_eq!(models.len(), 3);
     }
 
+    #[tokio::test]
+    async fn test_stored_weights() {
+        let store = vudo_storage_browser::MemoryAdapter::new();
+        let manager = ModelManager::new();
+        let id = ModelId::new("test-1");
+        let weights = vec![7u8; 1000];
+
+        assert!(manager
+            .store_weights(&store, &id, weights.clone())
+            .await
+            .is_err());
+
+        manager
+            .register(create_test_metadata("test-1", 1000))
+            .unwrap();
+        manager
+            .register(create_test_metadata("test-2", 1000))
+            .unwrap();
+        let hash = manager
+            .store_weights(&store, &id, weights.clone())
+            .await
+            .unwrap();
+        let shared = manager
+            .store_weights(&store, &ModelId::new("test-2"), weights.clone())
+            .await
+            .unwrap();
+        assert_eq!(hash, shared);
+        assert_eq!(store.blob_info(&hash).await.unwrap().unwrap().refcount, 2);
+
+        let loaded = manager.load_stored(&store, &id).await.unwrap();
+        assert_eq!(loaded.model_bytes, weights);
+
+        // Replacing weights releases the previous blob
+        manager
+            .store_weights(&store, &id, vec![8u8; 500])
+            .await
+            .unwrap();
+        assert_eq!(store.blob_info(&hash).await.unwrap().unwrap().refcount, 1);
+
+        manager
+            .release_weights(&store, &ModelId::new("test-2"))
+            .await
+            .unwrap();
+        assert_eq!(store.gc_blobs().await.unwrap().blobs_removed, 1);
+        assert!(!store.has_blob(&hash).await.unwrap());
+    }
+
     #[test]
     fn test_model_id_display() {
         let id = ModelId::new("test-model");
@@ -125,6 +125,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 println!("     Suggested: DEFER");
                 println!("     Action: Mark as disputed, requires manual review");
             }
+            OverdraftResolution::Guarantee { group_id } => {
+                println!("     Suggested: GUARANTEE");
+                println!("     Action: Cover deficit from group {} pledges", group_id);
+            }
         }
 
         // Validate resolution
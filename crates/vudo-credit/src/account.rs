@@ -35,6 +35,15 @@ pub struct CreditAccount {
 
     /// Last BFT reconciliation timestamp (Unix epoch seconds)
     pub last_reconciliation: u64,
+
+    /// Balance pledged to guarantee groups (locked, in cents)
+    #[serde(default)]
+    pub pledged: i64,
+
+    /// Time the reconciliation in progress settles demurrage up to (Unix
+    /// epoch seconds)
+    #[serde(default)]
+    pub settling_at: u64,
 }
 
 impl CreditAccount {
@@ -48,6 +57,8 @@ impl CreditAccount {
             escrows: HashMap::new(),
             pending_credits: 0,
             last_reconciliation: chrono::Utc::now().timestamp() as u64,
+            pledged: 0,
+            settling_at: 0,
         }
     }
 
@@ -114,10 +125,15 @@ impl CreditAccount {
 
     /// Check if account can allocate new escrow
     pub fn can_allocate_escrow(&self, amount: i64) -> bool {
-        let available = self.confirmed_balance - self.total_escrow_allocated();
+        let available = self.confirmed_balance - self.total_escrow_allocated() - self.pledged;
         available >= amount
     }
 
+    /// Calculate balance available to pledge to guarantee groups
+    pub fn available_to_pledge(&self) -> i64 {
+        self.confirmed_balance - self.total_escrow_allocated() - self.pledged
+    }
+
     /// Upgrade reputation tier
     pub fn upgrade_reputation(&mut self) -> Result<()> {
         self.reputation_tier.upgrade()
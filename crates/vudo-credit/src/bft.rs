@@ -1,23 +1,84 @@
 //! BFT committee for account reconciliation
+//!
+//! Reconciliations and escrow grants are decided by a PBFT-style consensus
+//! round among the committee members (see [`crate::consensus`]). A decided
+//! round yields a [`QuorumCertificate`]: 2f+1 Ed25519-signed commit votes for
+//! the same value, which anyone holding the committee keys can check.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
 
+use dashmap::DashMap;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
+use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
+use tokio::sync::{OnceCell, RwLock};
+use tokio::task::JoinHandle;
+use vudo_p2p::GossipOverlay;
 
-use crate::account::CreditAccountHandle;
+use crate::account::{CreditAccount, CreditAccountHandle};
+use crate::consensus::BftReplica;
 use crate::error::{CreditError, Result};
 use crate::escrow::DeviceEscrow;
 use crate::overdraft::Overdraft;
+use crate::policy::{CreditPolicy, Settlement};
+use crate::proof::CommitteeKeys;
 use crate::reputation::ReputationManager;
 
+/// Domain separation tag for vote messages
+const VOTE_DOMAIN: &[u8] = b"vudo-credit/bft-vote/v1";
+
+/// Consensus phase a vote belongs to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum VotePhase {
+    /// Leader proposal
+    PrePrepare,
+
+    /// Acceptance of the leader proposal
+    Prepare,
+
+    /// Commitment after 2f+1 matching prepares
+    Commit,
+
+    /// Request to replace the leader
+    ViewChange,
+
+    /// Request to decide a value
+    Request,
+}
+
+impl VotePhase {
+    fn tag(self) -> u8 {
+        match self {
+            VotePhase::PrePrepare => 0,
+            VotePhase::Prepare => 1,
+            VotePhase::Commit => 2,
+            VotePhase::ViewChange => 3,
+            VotePhase::Request => 4,
+        }
+    }
+}
+
 /// BFT vote from a committee member
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct BftVote {
     /// Voter DID
     pub voter: String,
 
+    /// Consensus subject (account ID, or escrow grant)
+    pub subject: String,
+
+    /// Consensus phase
+    pub phase: VotePhase,
+
+    /// View the vote was cast in
+    pub view: u64,
+
+    /// Sequence number of the consensus instance
+    pub sequence: u64,
+
     /// Proposed balance
     pub proposed_balance: i64,
 
@@ -28,6 +89,145 @@ pub struct BftVote {
     pub signature: Vec<u8>,
 }
 
+impl BftVote {
+    /// Create an unsigned vote cast now
+    pub fn new(
+        voter: impl Into<String>,
+        subject: impl Into<String>,
+        phase: VotePhase,
+        view: u64,
+        sequence: u64,
+        proposed_balance: i64,
+    ) -> Self {
+        Self {
+            voter: voter.into(),
+            subject: subject.into(),
+            phase,
+            view,
+            sequence,
+            proposed_balance,
+            timestamp: chrono::Utc::now().timestamp() as u64,
+            signature: Vec::new(),
+        }
+    }
+
+    /// Message digest signed by the voter
+    ///
+    /// The timestamp is informational and not signed.
+    pub fn message(&self) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(VOTE_DOMAIN);
+        hasher.update(&[self.phase.tag()]);
+        hasher.update(&(self.subject.len() as u64).to_le_bytes());
+        hasher.update(self.subject.as_bytes());
+        hasher.update(&self.view.to_le_bytes());
+        hasher.update(&self.sequence.to_le_bytes());
+        hasher.update(&self.proposed_balance.to_le_bytes());
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Sign the vote
+    pub fn sign(&mut self, key: &SigningKey) {
+        self.signature = key.sign(&self.message()).to_bytes().to_vec();
+    }
+
+    /// Check that the voter is a committee member and signed the vote
+    pub fn verify(&self, keys: &CommitteeKeys) -> bool {
+        let Some(key) = keys.key(&self.voter) else {
+            return false;
+        };
+        let Ok(bytes) = <[u8; 64]>::try_from(self.signature.as_slice()) else {
+            return false;
+        };
+        key.verify(&self.message(), &Signature::from_bytes(&bytes))
+            .is_ok()
+    }
+
+    /// Whether both votes are for the same phase, instance, view, and value
+    pub fn matches(&self, other: &BftVote) -> bool {
+        self.phase == other.phase
+            && self.subject == other.subject
+            && self.view == other.view
+            && self.sequence == other.sequence
+            && self.proposed_balance == other.proposed_balance
+    }
+}
+
+/// 2f+1 matching votes from distinct committee members
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QuorumCertificate {
+    /// Consensus subject
+    pub subject: String,
+
+    /// Phase of the votes (prepare or commit)
+    pub phase: VotePhase,
+
+    /// View the votes were cast in
+    pub view: u64,
+
+    /// Sequence number of the consensus instance
+    pub sequence: u64,
+
+    /// Agreed value (balance or escrow amount, in cents)
+    pub value: i64,
+
+    /// Member votes
+    pub votes: Vec<BftVote>,
+}
+
+impl QuorumCertificate {
+    /// Build a certificate from matching votes
+    ///
+    /// Returns `None` if there are no votes. The quorum is not checked.
+    pub fn from_votes(votes: Vec<BftVote>) -> Option<Self> {
+        let first = votes.first()?;
+        Some(Self {
+            subject: first.subject.clone(),
+            phase: first.phase,
+            view: first.view,
+            sequence: first.sequence,
+            value: first.proposed_balance,
+            votes,
+        })
+    }
+
+    /// Verify the certificate against the committee keys
+    ///
+    /// Succeeds if at least `keys.quorum()` distinct members cast a valid
+    /// vote matching the certificate. Other votes are ignored.
+    pub fn verify(&self, keys: &CommitteeKeys) -> Result<()> {
+        let expected = BftVote::new(
+            String::new(),
+            self.subject.clone(),
+            self.phase,
+            self.view,
+            self.sequence,
+            self.value,
+        );
+        let valid: HashSet<&str> = self
+            .votes
+            .iter()
+            .filter(|vote| vote.matches(&expected) && vote.verify(keys))
+            .map(|vote| vote.voter.as_str())
+            .collect();
+
+        if valid.len() >= keys.quorum() {
+            Ok(())
+        } else {
+            Err(CreditError::InvalidCertificate(format!(
+                "{} valid votes, {} required",
+                valid.len(),
+                keys.quorum()
+            )))
+        }
+    }
+
+    /// DIDs of the members whose votes the certificate holds
+    pub fn signers(&self) -> Vec<&str> {
+        self.votes.iter().map(|vote| vote.voter.as_str()).collect()
+    }
+}
+
 /// BFT reconciliation result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReconciliationResult {
@@ -45,6 +245,21 @@ pub struct ReconciliationResult {
 
     /// Quorum required
     pub quorum_required: usize,
+
+    /// Commit certificate for the new balance (if consensus was reached)
+    pub certificate: Option<QuorumCertificate>,
+
+    /// Fees and demurrage settled into the commons account
+    #[serde(default)]
+    pub settlement: Settlement,
+}
+
+/// Balance a reconciliation confirms: confirmed balance plus pending credits
+/// minus pending debits, fees and demurrage
+pub(crate) fn reconciled_balance(account: &CreditAccount, policy: &CreditPolicy) -> i64 {
+    account.confirmed_balance + account.pending_credits
+        - account.total_pending_debits()
+        - policy.settle(account).total()
 }
 
 /// BFT Committee for account reconciliation
@@ -57,16 +272,35 @@ pub struct BftCommittee {
 
     /// Vote storage (account_id -> votes)
     votes: Arc<RwLock<HashMap<String, Vec<BftVote>>>>,
+
+    /// Committee public keys
+    keys: CommitteeKeys,
+
+    /// Replicas run by this process
+    replicas: Vec<Arc<BftReplica>>,
+
+    /// How long a round may run before it is abandoned
+    round_timeout: Duration,
+
+    /// Next sequence number per subject
+    sequences: DashMap<String, u64>,
+
+    /// Replica tasks, spawned by [`BftCommittee::start`]
+    tasks: OnceCell<Vec<JoinHandle<()>>>,
 }
 
 impl BftCommittee {
     /// Create a new BFT committee
     ///
+    /// Every member runs as a replica in this process, with a freshly
+    /// generated signing key, over a local gossip overlay. Use
+    /// [`BftCommittee::with_replicas`] when members run on separate nodes.
+    ///
     /// # Arguments
     /// * `members` - List of committee member DIDs
     ///
-    /// # Panics
-    /// Panics if less than 4 members (need 3f+1 with f >= 1)
+    /// # Errors
+    /// Fails if less than 4 members (need 3f+1 with f >= 1)
     pub fn new(members: Vec<String>) -> Result<Self> {
         if members.len() < 4 {
             return Err(CreditError::InvalidOperation(
@@ -74,19 +308,69 @@ impl BftCommittee {
             ));
         }
 
-        // Calculate quorum: 2f+1 where 3f+1 = total
-        // So f = (total - 1) / 3
-        // And quorum = 2 * ((total - 1) / 3) + 1
-        let f = (members.len() - 1) / 3;
-        let quorum_size = 2 * f + 1;
+        let signing_keys: Vec<SigningKey> = members
+            .iter()
+            .map(|_| SigningKey::generate(&mut OsRng))
+            .collect();
+        let keys = CommitteeKeys::new(
+            0,
+            members
+                .iter()
+                .zip(&signing_keys)
+                .map(|(did, key)| (did.clone(), key.verifying_key()))
+                .collect(),
+        )?;
+
+        let gossip = Arc::new(GossipOverlay::new());
+        let replicas = members
+            .iter()
+            .zip(signing_keys)
+            .map(|(did, key)| {
+                BftReplica::new(did.clone(), key, keys.clone(), Arc::clone(&gossip)).map(Arc::new)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut committee = Self::with_replicas(keys, replicas)?;
+        committee.members = members;
+        Ok(committee)
+    }
+
+    /// Create a committee from the replicas run by this process
+    ///
+    /// `replicas` are all members for a local committee, or this node's own
+    /// member when the others run elsewhere; rounds run over the replicas'
+    /// gossip overlay. The members are taken from `keys`, in DID order.
+    pub fn with_replicas(keys: CommitteeKeys, replicas: Vec<Arc<BftReplica>>) -> Result<Self> {
+        if replicas.is_empty() {
+            return Err(CreditError::InvalidOperation(
+                "BFT committee requires at least one local replica".to_string(),
+            ));
+        }
+
+        let members = keys.members();
+        let quorum_size = keys.quorum();
+        let round_timeout = replicas[0].view_timeout() * (members.len() as u32 + 1);
 
         Ok(Self {
             members,
             quorum_size,
             votes: Arc::new(RwLock::new(HashMap::new())),
+            keys,
+            replicas,
+            round_timeout,
+            sequences: DashMap::new(),
+            tasks: OnceCell::new(),
         })
     }
 
+    /// Set how long a round may run before it is abandoned
+    ///
+    /// Defaults to one view timeout per member, plus one.
+    pub fn with_round_timeout(mut self, timeout: Duration) -> Self {
+        self.round_timeout = timeout;
+        self
+    }
+
     /// Get committee size
     pub fn size(&self) -> usize {
         self.members.len()
@@ -102,37 +386,96 @@ impl BftCommittee {
         (self.members.len() - 1) / 3
     }
 
+    /// Get the committee public keys, to check certificates with
+    pub fn keys(&self) -> &CommitteeKeys {
+        &self.keys
+    }
+
+    /// Get the last sequence number of each consensus subject
+    ///
+    /// Handed off at rotation, so the next committee's rounds never reuse a
+    /// sequence number of this one.
+    pub fn sequences(&self) -> BTreeMap<String, u64> {
+        self.sequences
+            .iter()
+            .map(|entry| (entry.key().clone(), *entry.value()))
+            .collect()
+    }
+
+    /// Continue the sequence numbers handed off by a previous committee
+    pub fn resume_sequences(&self, sequences: &BTreeMap<String, u64>) {
+        for (subject, &sequence) in sequences {
+            let mut next = self.sequences.entry(subject.clone()).or_insert(0);
+            *next = (*next).max(sequence);
+        }
+    }
+
+    /// Start the local replicas
+    ///
+    /// Each replica processes consensus messages in a background task until
+    /// the committee is dropped. Rounds start the replicas on demand; call
+    /// this up front on nodes that must answer rounds started elsewhere.
+    pub async fn start(&self) -> Result<()> {
+        self.tasks
+            .get_or_try_init(|| async {
+                let mut tasks = Vec::with_capacity(self.replicas.len());
+                for replica in &self.replicas {
+                    let subscription = replica.subscribe().await?;
+                    let replica = Arc::clone(replica);
+                    tasks.push(tokio::spawn(async move { replica.run(subscription).await }));
+                }
+                Ok::<_, CreditError>(tasks)
+            })
+            .await?;
+        Ok(())
+    }
+
     /// Reconcile account balance (BFT consensus)
     pub async fn reconcile_balance(
         &self,
         account: &CreditAccountHandle,
     ) -> Result<ReconciliationResult> {
-        // Read current account state
-        let (confirmed_balance, pending_credits, total_debits, transactions) = account.read(|acc| {
-            let total_debits = acc.total_pending_debits();
-            let transactions: Vec<_> = acc
-                .pending_debits()
-                .iter()
-                .map(|tx| (tx.id.clone(), tx.amount, tx.timestamp))
-                .collect();
-
-            Ok((
-                acc.confirmed_balance,
-                acc.pending_credits,
-                total_debits,
-                transactions,
-            ))
-        })?;
-
-        // Calculate proposed new balance
-        let proposed_balance = confirmed_balance + pending_credits - total_debits;
+        let now = chrono::Utc::now().timestamp() as u64;
+        self.reconcile_with_policy(account, &CreditPolicy::default(), now)
+            .await
+    }
 
-        // Simulate collecting votes from committee members
-        // In a real implementation, this would be P2P communication
-        let votes = self.simulate_vote_collection(&account.id.key, proposed_balance).await?;
+    /// Reconcile account balance, settling fees and demurrage up to `now`
+    ///
+    /// The settlement time is written to the account before the round so
+    /// members validating against their own copy charge the same demurrage.
+    pub async fn reconcile_with_policy(
+        &self,
+        account: &CreditAccountHandle,
+        policy: &CreditPolicy,
+        now: u64,
+    ) -> Result<ReconciliationResult> {
+        account.update(|acc| {
+            acc.settling_at = now;
+            Ok(())
+        })?;
 
-        // Check if we reached quorum
-        let consensus = votes.len() >= self.quorum_size;
+        // Read current account state
+        let (confirmed_balance, proposed_balance, transactions, settlement) =
+            account.read(|acc| {
+                let transactions: Vec<_> = acc
+                    .pending_debits()
+                    .iter()
+                    .map(|tx| (tx.id.clone(), tx.amount, tx.timestamp))
+                    .collect();
+
+                Ok((
+                    acc.confirmed_balance,
+                    reconciled_balance(acc, policy),
+                    transactions,
+                    policy.settle(acc),
+                ))
+            })?;
+
+        // Agree on the new balance with the committee
+        let (certificate, votes_received) =
+            self.run_round(&account.id.key, proposed_balance).await?;
+        let consensus = certificate.is_some();
 
         // Detect overdrafts
         let overdrafts = if consensus {
@@ -142,15 +485,17 @@ impl BftCommittee {
         };
 
         Ok(ReconciliationResult {
-            new_confirmed_balance: if consensus {
-                proposed_balance
-            } else {
-                confirmed_balance
-            },
+            new_confirmed_balance: certificate.as_ref().map_or(confirmed_balance, |c| c.value),
             overdrafts,
             consensus,
-            votes_received: votes.len(),
+            votes_received,
             quorum_required: self.quorum_size,
+            certificate,
+            settlement: if consensus {
+                settlement
+            } else {
+                Settlement::default()
+            },
         })
     }
 
@@ -162,13 +507,14 @@ impl BftCommittee {
         reputation_tier: crate::reputation::ReputationTier,
     ) -> Result<DeviceEscrow> {
         // Get current confirmed balance
-        let (confirmed_balance, total_escrow_allocated) = account.read(|acc| {
-            Ok((acc.confirmed_balance, acc.total_escrow_allocated()))
+        let (confirmed_balance, total_escrow_allocated, pledged) = account.read(|acc| {
+            Ok((acc.confirmed_balance, acc.total_escrow_allocated(), acc.pledged))
         })?;
 
         // Calculate escrow allocation based on reputation tier
+        // (balance pledged to guarantee groups stays locked)
         let escrow_limit = ReputationManager::escrow_limit(reputation_tier);
-        let available = confirmed_balance - total_escrow_allocated;
+        let available = confirmed_balance - total_escrow_allocated - pledged;
         let grant_amount = available.min(escrow_limit);
 
         if grant_amount <= 0 {
@@ -180,74 +526,72 @@ impl BftCommittee {
         let escrow = DeviceEscrow::new(device_id.to_string(), grant_amount, duration_days);
 
         // Vote on escrow grant
-        let votes = self
-            .vote_escrow_grant(&account.id.key, &escrow)
-            .await?;
+        let subject = format!("escrow:{}:{}", account.id.key, device_id);
+        let (certificate, _) = self.run_round(&subject, escrow.allocated).await?;
 
-        if votes.len() >= self.quorum_size {
-            Ok(escrow)
-        } else {
-            Err(CreditError::BftEscrowGrantFailed)
+        match certificate {
+            Some(certificate) if certificate.value == escrow.allocated => Ok(escrow),
+            _ => Err(CreditError::BftEscrowGrantFailed),
         }
     }
 
-    /// Simulate vote collection from committee members
+    /// Agree on the refund of a dispute
     ///
-    /// In a real implementation, this would:
-    /// 1. Broadcast proposal to all committee members via P2P
-    /// 2. Wait for votes with timeout
-    /// 3. Verify signatures
-    /// 4. Return valid votes
-    async fn simulate_vote_collection(
-        &self,
-        account_id: &str,
-        proposed_balance: i64,
-    ) -> Result<Vec<BftVote>> {
-        // For testing, simulate honest majority voting yes
-        let mut votes = Vec::new();
-        let honest_members = (self.members.len() + 1) * 2 / 3; // At least 2/3 honest
-
-        for i in 0..honest_members {
-            votes.push(BftVote {
-                voter: self.members[i].clone(),
-                proposed_balance,
-                timestamp: chrono::Utc::now().timestamp() as u64,
-                signature: vec![0; 64], // Simulated signature
-            });
+    /// The committee decides the amount refunded to the payer, so outcomes
+    /// are certified as a value like balances and escrow grants.
+    pub async fn decide_dispute(&self, dispute_id: &str, refund: i64) -> Result<QuorumCertificate> {
+        let subject = crate::dispute::dispute_subject(dispute_id);
+        let (certificate, votes_received) = self.run_round(&subject, refund).await?;
+
+        match certificate {
+            Some(certificate) if certificate.value == refund => Ok(certificate),
+            _ => Err(CreditError::BftConsensusFailure {
+                votes_received,
+                quorum_required: self.quorum_size,
+            }),
         }
-
-        // Store votes
-        self.votes
-            .write()
-            .await
-            .insert(account_id.to_string(), votes.clone());
-
-        Ok(votes)
     }
 
-    /// Vote on escrow grant
-    async fn vote_escrow_grant(
+    /// Run a consensus round on a value for `subject`
+    ///
+    /// Returns the commit certificate if the round was decided, and the
+    /// number of commit votes seen.
+    async fn run_round(
         &self,
-        _account_id: &str,
-        escrow: &DeviceEscrow,
-    ) -> Result<Vec<BftVote>> {
-        // For testing, simulate honest majority voting yes
-        let mut votes = Vec::new();
-        let honest_members = (self.members.len() + 1) * 2 / 3;
-
-        for i in 0..honest_members {
-            votes.push(BftVote {
-                voter: self.members[i].clone(),
-                proposed_balance: escrow.allocated,
-                timestamp: chrono::Utc::now().timestamp() as u64,
-                signature: vec![0; 64],
-            });
-        }
+        subject: &str,
+        value: i64,
+    ) -> Result<(Option<QuorumCertificate>, usize)> {
+        self.start().await?;
+
+        let sequence = {
+            let mut next = self.sequences.entry(subject.to_string()).or_insert(0);
+            *next += 1;
+            *next
+        };
+
+        let replica = &self.replicas[0];
+        replica.request(subject, sequence, value).await?;
+        let certificate = replica
+            .wait_decision(subject, sequence, self.round_timeout)
+            .await;
+
+        let votes_received = match &certificate {
+            Some(certificate) => {
+                self.votes
+                    .write()
+                    .await
+                    .insert(subject.to_string(), certificate.votes.clone());
+                certificate.votes.len()
+            }
+            None => replica.commit_votes(subject, sequence),
+        };
 
-        Ok(votes)
+        Ok((certificate, votes_received))
     }
 
     /// Get votes for an account
+    ///
+    /// These are the commit votes of the last decided round.
     pub async fn get_votes(&self, account_id: &str) -> Vec<BftVote> {
         self.votes
             .read()
@@ -272,9 +616,22 @@ impl BftCommittee {
     }
 }
 
+impl Drop for BftCommittee {
+    fn drop(&mut self) {
+        if let Some(tasks) = self.tasks.get() {
+            for task in tasks {
+                task.abort();
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::consensus::ProposalValidator;
+    use crate::policy::{DemurragePolicy, FeeSchedule};
+    use crate::transaction::{Transaction, TransactionMetadata};
     use vudo_state::StateEngine;
 
     #[tokio::test]
@@ -326,6 +683,215 @@ mod tests {
         assert!(result.consensus);
         assert_eq!(result.new_confirmed_balance, 10000);
         assert!(result.overdrafts.is_empty());
+
+        let certificate = result.certificate.unwrap();
+        assert_eq!(certificate.phase, VotePhase::Commit);
+        assert_eq!(certificate.value, 10000);
+        assert!(result.votes_received >= committee.quorum());
+        certificate.verify(committee.keys()).unwrap();
+        assert_eq!(
+            committee.get_votes("alice").await.len(),
+            result.votes_received
+        );
+    }
+
+    #[tokio::test]
+    async fn test_certificate_rejects_tampering() {
+        let state_engine = StateEngine::new().await.unwrap();
+        let account = CreditAccountHandle::create(&state_engine, "alice".to_string(), 10000)
+            .await
+            .unwrap();
+
+        let committee = BftCommittee::new_mock(4).await.unwrap();
+        let certificate = committee
+            .reconcile_balance(&account)
+            .await
+            .unwrap()
+            .certificate
+            .unwrap();
+
+        let mut inflated = certificate.clone();
+        inflated.value = 1_000_000;
+        assert!(inflated.verify(committee.keys()).is_err());
+
+        let mut short = certificate.clone();
+        short.votes.truncate(committee.quorum() - 1);
+        assert!(short.verify(committee.keys()).is_err());
+
+        let mut duplicated = short.clone();
+        duplicated.votes.push(duplicated.votes[0].clone());
+        assert!(duplicated.verify(committee.keys()).is_err());
+
+        let other = BftCommittee::new_mock(4).await.unwrap();
+        assert!(certificate.verify(other.keys()).is_err());
+    }
+
+    /// Keys and replicas for a committee of `size`, with a short view timeout
+    fn replicas(size: u8) -> (CommitteeKeys, Vec<BftReplica>) {
+        let signing_keys: Vec<SigningKey> = (0..size)
+            .map(|i| SigningKey::from_bytes(&[i + 1; 32]))
+            .collect();
+        let keys = CommitteeKeys::new(
+            1,
+            signing_keys
+                .iter()
+                .enumerate()
+                .map(|(i, key)| (format!("member{}", i), key.verifying_key()))
+                .collect(),
+        )
+        .unwrap();
+
+        let gossip = Arc::new(GossipOverlay::new());
+        let replicas = signing_keys
+            .into_iter()
+            .enumerate()
+            .map(|(i, key)| {
+                BftReplica::new(
+                    format!("member{}", i),
+                    key,
+                    keys.clone(),
+                    Arc::clone(&gossip),
+                )
+                .unwrap()
+                .with_view_timeout(Duration::from_millis(50))
+            })
+            .collect();
+        (keys, replicas)
+    }
+
+    #[tokio::test]
+    async fn test_view_change_on_leader_failure() {
+        let state_engine = StateEngine::new().await.unwrap();
+        let account = CreditAccountHandle::create(&state_engine, "alice".to_string(), 10000)
+            .await
+            .unwrap();
+
+        // member0 leads view 0 but never runs
+        let (keys, replicas) = replicas(4);
+        let live: Vec<Arc<BftReplica>> = replicas.into_iter().skip(1).map(Arc::new).collect();
+        let committee = BftCommittee::with_replicas(keys, live).unwrap();
+
+        let result = committee.reconcile_balance(&account).await.unwrap();
+        assert!(result.consensus);
+        assert_eq!(result.new_confirmed_balance, 10000);
+
+        let certificate = result.certificate.unwrap();
+        assert!(certificate.view >= 1);
+        assert!(!certificate.signers().contains(&"member0"));
+        certificate.verify(committee.keys()).unwrap();
+    }
+
+    /// Validator of a faulty member that inflates every balance
+    struct Inflating;
+
+    #[async_trait::async_trait]
+    impl ProposalValidator for Inflating {
+        async fn expected_value(&self, _subject: &str) -> Result<Option<i64>> {
+            Ok(Some(1_000_000))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_faulty_leader_is_replaced() {
+        let state_engine = Arc::new(StateEngine::new().await.unwrap());
+        let account = CreditAccountHandle::create(&state_engine, "alice".to_string(), 10000)
+            .await
+            .unwrap();
+
+        let (keys, replicas) = replicas(4);
+        let replicas: Vec<Arc<BftReplica>> = replicas
+            .into_iter()
+            .map(|replica| {
+                let validator: Arc<dyn ProposalValidator> = if replica.did() == "member0" {
+                    Arc::new(Inflating)
+                } else {
+                    state_engine.clone()
+                };
+                Arc::new(replica.with_validator(validator))
+            })
+            .rev()
+            .collect();
+        let committee = BftCommittee::with_replicas(keys, replicas).unwrap();
+
+        let result = committee.reconcile_balance(&account).await.unwrap();
+        assert!(result.consensus);
+        assert_eq!(result.new_confirmed_balance, 10000);
+        assert!(result.certificate.unwrap().view >= 1);
+    }
+
+    #[tokio::test]
+    async fn test_validators_settle_community_policy() {
+        let state_engine = Arc::new(StateEngine::new().await.unwrap());
+        CreditPolicy::new("commons")
+            .with_fee(FeeSchedule::Flat { amount: 10 })
+            .with_demurrage(DemurragePolicy {
+                basis_points: 100,
+                period_secs: 86_400,
+                exempt_balance: 0,
+            })
+            .publish(&state_engine)
+            .await
+            .unwrap();
+        let account = CreditAccountHandle::create(&state_engine, "alice".to_string(), 10000)
+            .await
+            .unwrap();
+        account
+            .update(|acc| {
+                acc.last_reconciliation = 86_400;
+                acc.add_transaction(Transaction::new(
+                    "alice".to_string(),
+                    "bob".to_string(),
+                    1000,
+                    TransactionMetadata::default(),
+                ));
+                Ok(())
+            })
+            .unwrap();
+
+        // Every member checks the proposal against its copy of the account
+        let (keys, replicas) = replicas(4);
+        let replicas: Vec<Arc<BftReplica>> = replicas
+            .into_iter()
+            .map(|replica| Arc::new(replica.with_validator(state_engine.clone())))
+            .collect();
+        let committee = BftCommittee::with_replicas(keys, replicas).unwrap();
+        let policy = CreditPolicy::load(&state_engine).await.unwrap();
+
+        // Two days of 1% demurrage on 10000, and one fee
+        let result = committee
+            .reconcile_with_policy(&account, &policy, 3 * 86_400)
+            .await
+            .unwrap();
+        assert!(result.consensus);
+        assert_eq!(
+            result.settlement,
+            Settlement {
+                fees: 10,
+                demurrage: 200
+            }
+        );
+        assert_eq!(result.new_confirmed_balance, 10000 - 1000 - 210);
+    }
+
+    #[tokio::test]
+    async fn test_no_consensus_without_quorum() {
+        let state_engine = StateEngine::new().await.unwrap();
+        let account = CreditAccountHandle::create(&state_engine, "alice".to_string(), 10000)
+            .await
+            .unwrap();
+
+        // Only 2 of 4 members run (quorum is 3)
+        let (keys, replicas) = replicas(4);
+        let live: Vec<Arc<BftReplica>> = replicas.into_iter().skip(2).map(Arc::new).collect();
+        let committee = BftCommittee::with_replicas(keys, live)
+            .unwrap()
+            .with_round_timeout(Duration::from_millis(300));
+
+        let result = committee.reconcile_balance(&account).await.unwrap();
+        assert!(!result.consensus);
+        assert!(result.certificate.is_none());
+        assert_eq!(result.new_confirmed_balance, 10000);
+        assert!(result.votes_received < result.quorum_required);
     }
 
     #[tokio::test]
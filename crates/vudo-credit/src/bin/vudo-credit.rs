@@ -0,0 +1,253 @@
+//! vudo-credit - Exercise the mutual credit flow from the command line
+//!
+//! Each invocation loads the state archive, runs one wallet operation and
+//! writes the archive back, so a sequence of commands walks an account
+//! through spend, escrow refresh, reconciliation and dispute. The BFT
+//! committee is a local mock committee: this is a tool for development and
+//! testing, not a production wallet.
+//!
+//! # Usage
+//!
+//! ```bash
+//! # Create two accounts
+//! vudo-credit create alice --balance 100000
+//! vudo-credit create bob
+//!
+//! # Get escrow, spend from it, then reconcile
+//! vudo-credit refresh alice
+//! vudo-credit pay alice bob 2500 --description "Groceries"
+//! vudo-credit reconcile alice
+//!
+//! # Dispute a payment and look at the history
+//! vudo-credit dispute alice 6f1c... --reason "Never delivered"
+//! vudo-credit history alice --limit 20
+//! ```
+//!
+//! State is kept in `vudo-credit.state` unless `--state` says otherwise.
+
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use vudo_credit::{
+    BalanceBreakdown, BftCommittee, DisputeRoute, LedgerQuery, MutualCreditScheduler, Page, Wallet,
+};
+use vudo_state::StateEngine;
+
+/// Exercise the mutual credit flow from the command line
+#[derive(Parser, Debug)]
+#[command(name = "vudo-credit", version, about)]
+struct Cli {
+    /// State archive read before and written after the command
+    #[arg(long, default_value = "vudo-credit.state")]
+    state: PathBuf,
+
+    /// Device ID escrow is granted to
+    #[arg(long, default_value = "cli")]
+    device: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Create an account
+    Create {
+        /// Account ID
+        account: String,
+
+        /// Initial confirmed balance (in cents)
+        #[arg(long, default_value_t = 0)]
+        balance: i64,
+    },
+
+    /// Show an account's balance breakdown
+    Balance {
+        /// Account ID
+        account: String,
+    },
+
+    /// Pay from the device escrow
+    Pay {
+        /// Paying account ID
+        account: String,
+
+        /// Recipient account ID
+        recipient: String,
+
+        /// Amount (in cents)
+        amount: i64,
+
+        /// Payment description
+        #[arg(long, default_value = "")]
+        description: String,
+    },
+
+    /// Refresh the device escrow from the committee
+    Refresh {
+        /// Account ID
+        account: String,
+    },
+
+    /// Reconcile an account with the committee
+    Reconcile {
+        /// Account ID
+        account: String,
+    },
+
+    /// Dispute a transaction
+    Dispute {
+        /// Account ID
+        account: String,
+
+        /// Transaction ID
+        transaction: String,
+
+        /// Why the payment is disputed
+        #[arg(long, default_value = "")]
+        reason: String,
+
+        /// Route the dispute to this guarantee group instead of the committee
+        #[arg(long)]
+        group: Option<String>,
+    },
+
+    /// Show an account's history
+    History {
+        /// Account ID
+        account: String,
+
+        /// Only transactions in this category
+        #[arg(long)]
+        category: Option<String>,
+
+        /// Maximum number of entries
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+
+        /// Show entries after this sequence number
+        #[arg(long)]
+        after: Option<u64>,
+    },
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let state_engine = Arc::new(StateEngine::new().await?);
+    if cli.state.exists() {
+        state_engine.import_archive(&cli.state).await?;
+    }
+    let scheduler = Arc::new(
+        MutualCreditScheduler::new(
+            Arc::clone(&state_engine),
+            Arc::new(BftCommittee::new_mock(4).await?),
+            cli.device,
+        )
+        .await?,
+    );
+
+    match cli.command {
+        Command::Create { account, balance } => {
+            let wallet = Wallet::create(scheduler, account, balance).await?;
+            println!("Created {}", wallet.account_id());
+            print_balance(&wallet.balance().await?);
+        }
+        Command::Balance { account } => {
+            let wallet = Wallet::open(scheduler, account).await?;
+            print_balance(&wallet.balance().await?);
+        }
+        Command::Pay {
+            account,
+            recipient,
+            amount,
+            description,
+        } => {
+            let wallet = Wallet::open(scheduler, account).await?;
+            let tx_id = wallet.pay(&recipient, amount, description).await?;
+            println!("Paid {} to {}: {}", amount, recipient, tx_id);
+        }
+        Command::Refresh { account } => {
+            let wallet = Wallet::open(scheduler, account).await?;
+            let escrow = wallet.refresh_escrow().await?;
+            println!(
+                "Escrow {} of {}, expires at {}",
+                escrow.remaining, escrow.allocated, escrow.expires_at
+            );
+        }
+        Command::Reconcile { account } => {
+            let wallet = Wallet::open(scheduler, account).await?;
+            print_balance(&wallet.reconcile().await?);
+        }
+        Command::Dispute {
+            account,
+            transaction,
+            reason,
+            group,
+        } => {
+            let route = match group {
+                Some(group_id) => DisputeRoute::GuaranteeGroup { group_id },
+                None => DisputeRoute::Committee,
+            };
+            let wallet = Wallet::open(scheduler, account).await?;
+            let dispute = wallet.open_dispute(&transaction, reason, route).await?;
+            println!("Disputed {}: dispute {}", transaction, dispute.id);
+        }
+        Command::History {
+            account,
+            category,
+            limit,
+            after,
+        } => {
+            let wallet = Wallet::open(scheduler, account).await?;
+            let mut query = LedgerQuery::new();
+            if let Some(category) = category {
+                query = query.with_category(category);
+            }
+            let page = Page { after, limit };
+            let history = wallet.history(&query, page).await?;
+            for entry in &history.entries {
+                let tx = &entry.transaction;
+                println!(
+                    "{:>5}  {}  {:<20}  {} -> {}  {:>10}  {}",
+                    entry.sequence,
+                    tx.id,
+                    entry.event_label(),
+                    tx.from,
+                    tx.to,
+                    tx.amount,
+                    tx.metadata.description
+                );
+            }
+            if let Some(next) = history.next {
+                println!("More: --after {}", next.after.unwrap_or_default());
+            }
+        }
+    }
+
+    state_engine.export_archive(&cli.state).await?;
+    Ok(())
+}
+
+fn print_balance(balance: &BalanceBreakdown) {
+    println!("Confirmed:        {}", balance.confirmed);
+    println!(
+        "Escrow:           {} of {}",
+        balance.escrow_remaining, balance.escrow_allocated
+    );
+    println!("Pending debits:   {}", balance.pending_debits);
+    println!("Pending credits:  {}", balance.pending_credits);
+    println!("Pledged:          {}", balance.pledged);
+    println!("Projected:        {}", balance.projected());
+}
@@ -0,0 +1,1398 @@
+//! BFT committee formation over P2P gossip
+//!
+//! Candidates announce candidacies signed with their member key on an
+//! application topic. Nothing in an announcement is taken on trust: each node
+//! reads a candidate's reputation tier and stakeable balance from its credit
+//! account, measures its uptime as the share of recent announce intervals in
+//! which it heard the candidate, and runs a verifiable random selection
+//! seeded by the heads of the credit account CRDTs. The heads are recorded
+//! in the committee document, so anyone can recompute the seed and re-check
+//! the selection.
+//!
+//! Candidates are weighted by reputation tier and stake, so higher-tier,
+//! higher-stake nodes serve more often without locking anyone else out:
+//!
+//! ```text
+//! seed    = BLAKE3(epoch || sorted account heads)
+//! weight  = tier weight * (1 + min(stake / stake unit, max stake units))
+//! uptime  = intervals heard / intervals in the uptime window
+//! draw i  = BLAKE3(seed || i) mod (weight of candidates not yet drawn)
+//! members = first `committee_size` draws
+//! ```
+//!
+//! Each epoch's committee document links the previous one by digest. At
+//! rotation the outgoing members sign a [`CommitteeHandoff`] naming the
+//! incoming document, the accounts still awaiting reconciliation and the
+//! last consensus sequence numbers. The document and its handoff form a
+//! [`MembershipProof`], which takes a peer trusting one epoch's committee
+//! keys to the next. Candidates leave by announcing a withdrawal; members
+//! that withdraw serve until the next rotation.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+use automerge::{transaction::Transactable, ROOT};
+use dashmap::DashMap;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+use vudo_identity::Did;
+use vudo_p2p::{GossipOverlay, Topic, TopicMessage, TopicSubscription};
+use vudo_state::{DocumentId, StateEngine, StateError};
+
+use crate::account::CreditAccountHandle;
+use crate::bft::BftCommittee;
+use crate::document::load_json;
+use crate::error::{CreditError, Result};
+use crate::proof::{valid_signers, CommitteeKeys, ProofSignature, ProofSigner};
+use crate::reputation::ReputationTier;
+
+/// Application topic used for committee candidacy announcements
+pub const CANDIDACY_TOPIC: &str = "credit:committee:candidates";
+
+/// Namespace for published committee documents
+pub const COMMITTEE_NAMESPACE: &str = "credit_committees";
+
+/// Domain separation tag for candidacy messages
+const CANDIDACY_DOMAIN: &[u8] = b"vudo-credit/committee-candidacy/v1";
+
+/// Domain separation tag for committee document digests
+const COMMITTEE_DOMAIN: &[u8] = b"vudo-credit/committee/v1";
+
+/// Domain separation tag for committee handoff messages
+const HANDOFF_DOMAIN: &[u8] = b"vudo-credit/committee-handoff/v1";
+
+/// A node offering to serve on the BFT committee
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CommitteeCandidate {
+    /// Candidate DID
+    pub did: String,
+
+    /// P2P peer ID the candidate is reachable at
+    pub peer_id: String,
+
+    /// Announcement timestamp (Unix epoch seconds)
+    pub announced_at: u64,
+
+    /// Balance staked behind the candidacy (in cents), counted up to what
+    /// the candidate's account can pledge
+    #[serde(default)]
+    pub stake: i64,
+
+    /// Key the candidate signs with as a member (the key of its DID)
+    #[serde(default)]
+    pub public_key: Option<VerifyingKey>,
+
+    /// Whether this announcement withdraws the candidacy
+    #[serde(default)]
+    pub withdrawn: bool,
+
+    /// Ed25519 signature by the member key
+    #[serde(default)]
+    pub signature: Vec<u8>,
+}
+
+impl CommitteeCandidate {
+    /// Create a new, unsigned candidacy announced now
+    pub fn new(did: impl Into<String>, peer_id: impl Into<String>) -> Self {
+        Self {
+            did: did.into(),
+            peer_id: peer_id.into(),
+            announced_at: chrono::Utc::now().timestamp() as u64,
+            stake: 0,
+            public_key: None,
+            withdrawn: false,
+            signature: Vec::new(),
+        }
+    }
+
+    /// Stake balance behind the candidacy
+    pub fn with_stake(mut self, stake: i64) -> Self {
+        self.stake = stake.max(0);
+        self
+    }
+
+    /// Sign the candidacy with the member key, announcing it
+    pub fn sign(mut self, key: &SigningKey) -> Self {
+        self.public_key = Some(key.verifying_key());
+        self.signature = key.sign(&self.message()).to_bytes().to_vec();
+        self
+    }
+
+    /// Signed withdrawal of this candidacy, announced now
+    pub fn withdrawal(&self, key: &SigningKey) -> Self {
+        Self {
+            withdrawn: true,
+            announced_at: chrono::Utc::now().timestamp() as u64,
+            ..self.clone()
+        }
+        .sign(key)
+    }
+
+    /// Message digest signed with the member key
+    pub fn message(&self) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(CANDIDACY_DOMAIN);
+        hash_str(&mut hasher, &self.did);
+        hash_str(&mut hasher, &self.peer_id);
+        hasher.update(&self.announced_at.to_le_bytes());
+        hasher.update(&self.stake.to_le_bytes());
+        hasher.update(&[self.withdrawn as u8]);
+        if let Some(key) = &self.public_key {
+            hasher.update(key.as_bytes());
+        }
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Verify the signature, and that the member key is the DID's key
+    pub fn verify(&self) -> Result<()> {
+        let did = Did::parse(&self.did)?;
+        let invalid =
+            || CreditError::InvalidProof(format!("Invalid candidacy signature by {}", self.did));
+        if self.public_key != Some(did.verification_key) {
+            return Err(invalid());
+        }
+        let bytes = <[u8; 64]>::try_from(self.signature.as_slice()).map_err(|_| invalid())?;
+        did.verification_key
+            .verify(&self.message(), &Signature::from_bytes(&bytes))
+            .map_err(|_| invalid())
+    }
+}
+
+/// Committee formation parameters
+#[derive(Debug, Clone)]
+pub struct CommitteeFormationConfig {
+    /// Number of members to select (must satisfy 3f+1 with f >= 1)
+    pub committee_size: usize,
+
+    /// Minimum reputation tier to be eligible
+    pub min_reputation_tier: ReputationTier,
+
+    /// Minimum uptime ratio to be eligible
+    pub min_uptime_score: f64,
+
+    /// How often candidates are expected to announce themselves
+    pub announce_interval: Duration,
+
+    /// Announce intervals over which uptime is measured
+    pub uptime_window: Duration,
+
+    /// How long a candidacy stays valid after being announced
+    pub candidacy_ttl: Duration,
+
+    /// Selection weight of each reputation tier (0-5)
+    pub tier_weights: [u64; 6],
+
+    /// Stake earning one more multiple of the tier weight (in cents)
+    pub stake_unit: i64,
+
+    /// Most stake units counted, so stake cannot outweigh reputation
+    pub max_stake_units: u64,
+}
+
+impl CommitteeFormationConfig {
+    /// Selection weight of a candidate at `tier` with `stake`
+    pub fn weight(&self, tier: ReputationTier, stake: i64) -> u64 {
+        let tier_weight = self.tier_weights[tier.value() as usize];
+        let units = (stake.max(0) / self.stake_unit.max(1)) as u64;
+        tier_weight * (1 + units.min(self.max_stake_units))
+    }
+}
+
+impl Default for CommitteeFormationConfig {
+    fn default() -> Self {
+        Self {
+            committee_size: 4,
+            min_reputation_tier: ReputationTier::new(2).expect("valid tier"),
+            min_uptime_score: 0.9,
+            announce_interval: Duration::from_secs(300),
+            uptime_window: Duration::from_secs(24 * 3600),
+            candidacy_ttl: Duration::from_secs(3600),
+            tier_weights: [1, 1, 1, 2, 4, 8],
+            stake_unit: 100_000,
+            max_stake_units: 4,
+        }
+    }
+}
+
+/// Committee document published to the state engine
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CommitteeDocument {
+    /// Committee epoch
+    pub epoch: u64,
+
+    /// Selection seed (hex-encoded BLAKE3 hash)
+    pub seed: String,
+
+    /// Credit account heads the seed is derived from
+    /// (account ID -> sorted hex change hashes)
+    #[serde(default)]
+    pub heads: BTreeMap<String, Vec<String>>,
+
+    /// Selected member DIDs, in selection order
+    pub members: Vec<String>,
+
+    /// Every eligible candidate DID considered during selection
+    pub candidates: Vec<String>,
+
+    /// Selection weight of each candidate, in `candidates` order
+    /// (empty for an unweighted selection)
+    #[serde(default)]
+    pub weights: Vec<u64>,
+
+    /// Signing keys announced by the members
+    #[serde(default)]
+    pub member_keys: BTreeMap<String, VerifyingKey>,
+
+    /// Digest of the previous epoch's committee document (hex)
+    #[serde(default)]
+    pub previous: Option<String>,
+
+    /// Formation timestamp (Unix epoch seconds)
+    pub formed_at: u64,
+}
+
+impl CommitteeDocument {
+    /// Document ID under which the committee for `epoch` is published
+    pub fn document_id(epoch: u64) -> DocumentId {
+        DocumentId::new(COMMITTEE_NAMESPACE, format!("epoch-{}", epoch))
+    }
+
+    /// Recompute the seed from the recorded heads, re-run the selection and
+    /// check both match the published document
+    pub fn verify(&self) -> bool {
+        let seed = seed_from_heads(self.epoch, &self.heads);
+        if encode_hex(&seed) != self.seed {
+            return false;
+        }
+        let selected = if self.weights.is_empty() {
+            select_members(&seed, &self.candidates, self.members.len())
+        } else if self.weights.len() == self.candidates.len() {
+            select_weighted(&seed, &self.candidates, &self.weights, self.members.len())
+        } else {
+            return false;
+        };
+        selected == self.members && self.member_keys.keys().all(|did| self.is_member(did))
+    }
+
+    /// Whether `did` is a member of the committee
+    pub fn is_member(&self, did: &str) -> bool {
+        self.members.iter().any(|member| member == did)
+    }
+
+    /// Digest of the document, named by the handoff into its epoch
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(COMMITTEE_DOMAIN);
+        hasher.update(&self.epoch.to_le_bytes());
+        hash_str(&mut hasher, &self.seed);
+        hasher.update(&(self.heads.len() as u64).to_le_bytes());
+        for (account, hashes) in &self.heads {
+            hash_str(&mut hasher, account);
+            hash_list(&mut hasher, hashes);
+        }
+        hash_list(&mut hasher, &self.members);
+        hash_list(&mut hasher, &self.candidates);
+        hasher.update(&(self.weights.len() as u64).to_le_bytes());
+        for weight in &self.weights {
+            hasher.update(&weight.to_le_bytes());
+        }
+        hasher.update(&(self.member_keys.len() as u64).to_le_bytes());
+        for (did, key) in &self.member_keys {
+            hash_str(&mut hasher, did);
+            hasher.update(key.as_bytes());
+        }
+        hash_str(&mut hasher, self.previous.as_deref().unwrap_or_default());
+        hasher.update(&self.formed_at.to_le_bytes());
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Committee public keys, to check proofs signed by this committee
+    ///
+    /// Fails if a member did not announce its key.
+    pub fn keys(&self) -> Result<CommitteeKeys> {
+        let members = self
+            .members
+            .iter()
+            .map(|did| match self.member_keys.get(did) {
+                Some(key) => Ok((did.clone(), *key)),
+                None => Err(CreditError::InvalidProof(format!(
+                    "No key announced by member {}",
+                    did
+                ))),
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        CommitteeKeys::new(self.epoch, members)
+    }
+
+    /// Build a BFT committee from the selected members
+    pub fn to_committee(&self) -> Result<BftCommittee> {
+        BftCommittee::new(self.members.clone())
+    }
+}
+
+/// Handoff from one epoch's committee to the next
+///
+/// Signed by the outgoing members. A quorum of their signatures vouches for
+/// the incoming committee document and passes on the work in flight.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CommitteeHandoff {
+    /// Outgoing epoch
+    pub from_epoch: u64,
+
+    /// Incoming epoch
+    pub to_epoch: u64,
+
+    /// Digest of the incoming committee document (hex)
+    pub committee: String,
+
+    /// Members joining the committee
+    pub joined: Vec<String>,
+
+    /// Members leaving the committee
+    pub left: Vec<String>,
+
+    /// Accounts with transactions awaiting reconciliation, sorted
+    pub pending: Vec<String>,
+
+    /// Last consensus sequence number per subject
+    pub sequences: BTreeMap<String, u64>,
+
+    /// Outgoing member signatures
+    pub signatures: Vec<ProofSignature>,
+}
+
+impl CommitteeHandoff {
+    /// Message digest signed by the outgoing members
+    pub fn message(&self) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(HANDOFF_DOMAIN);
+        hasher.update(&self.from_epoch.to_le_bytes());
+        hasher.update(&self.to_epoch.to_le_bytes());
+        hash_str(&mut hasher, &self.committee);
+        hash_list(&mut hasher, &self.joined);
+        hash_list(&mut hasher, &self.left);
+        hash_list(&mut hasher, &self.pending);
+        hasher.update(&(self.sequences.len() as u64).to_le_bytes());
+        for (subject, sequence) in &self.sequences {
+            hash_str(&mut hasher, subject);
+            hasher.update(&sequence.to_le_bytes());
+        }
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Add an outgoing member's signature
+    pub fn sign(&mut self, signer: &ProofSigner) {
+        let signature = signer.sign_digest(&self.message());
+        if !self.signatures.iter().any(|s| s.signer == signature.signer) {
+            self.signatures.push(signature);
+        }
+    }
+
+    /// Verify the handoff against the outgoing committee's keys
+    pub fn verify(&self, outgoing: &CommitteeKeys) -> Result<()> {
+        if outgoing.epoch != self.from_epoch {
+            return Err(CreditError::InvalidProof(format!(
+                "Handoff is from epoch {}, keys are for epoch {}",
+                self.from_epoch, outgoing.epoch
+            )));
+        }
+
+        let valid = valid_signers(&self.message(), &self.signatures, outgoing);
+        if valid.len() >= outgoing.quorum() {
+            Ok(())
+        } else {
+            Err(CreditError::InvalidProof(format!(
+                "{} valid signatures, {} required",
+                valid.len(),
+                outgoing.quorum()
+            )))
+        }
+    }
+}
+
+/// Published proof of an epoch's committee membership
+///
+/// Verifiable by any peer trusting the previous epoch's committee keys.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MembershipProof {
+    /// Incoming committee document
+    pub committee: CommitteeDocument,
+
+    /// Handoff signed by the outgoing committee
+    pub handoff: CommitteeHandoff,
+}
+
+impl MembershipProof {
+    /// Whether `did` is a member of the committee
+    pub fn is_member(&self, did: &str) -> bool {
+        self.committee.is_member(did)
+    }
+
+    /// Verify the proof against the previous epoch's committee keys
+    ///
+    /// Checks the selection, that the handoff names this document and the
+    /// outgoing members' signatures. Returns the committee's keys, to trust
+    /// for the new epoch.
+    pub fn verify(&self, trusted: &CommitteeKeys) -> Result<CommitteeKeys> {
+        if !self.committee.verify() {
+            return Err(CreditError::InvalidProof(
+                "Committee selection does not verify".to_string(),
+            ));
+        }
+        if self.handoff.to_epoch != self.committee.epoch
+            || self.handoff.committee != encode_hex(&self.committee.digest())
+        {
+            return Err(CreditError::InvalidProof(
+                "Handoff names another committee".to_string(),
+            ));
+        }
+        self.handoff.verify(trusted)?;
+        self.committee.keys()
+    }
+}
+
+/// Committee formation service
+pub struct CommitteeFormation {
+    /// State engine holding account and committee documents
+    state_engine: Arc<StateEngine>,
+
+    /// Gossip overlay used for candidacy announcements
+    gossip: Arc<GossipOverlay>,
+
+    /// Formation parameters
+    config: CommitteeFormationConfig,
+
+    /// Known candidates (DID -> latest candidacy)
+    candidates: DashMap<String, CommitteeCandidate>,
+
+    /// Announce intervals in which each candidate was heard, within the
+    /// uptime window (DID -> interval numbers)
+    heard: DashMap<String, BTreeSet<u64>>,
+}
+
+impl CommitteeFormation {
+    /// Create a new committee formation service
+    pub fn new(
+        state_engine: Arc<StateEngine>,
+        gossip: Arc<GossipOverlay>,
+        config: CommitteeFormationConfig,
+    ) -> Result<Self> {
+        if config.committee_size < 4 {
+            return Err(CreditError::InvalidOperation(
+                "BFT committee requires at least 4 members (3f+1 with f=1)".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            state_engine,
+            gossip,
+            config,
+            candidates: DashMap::new(),
+            heard: DashMap::new(),
+        })
+    }
+
+    /// Application topic for candidacy announcements
+    pub fn topic() -> Topic {
+        Topic::app(CANDIDACY_TOPIC)
+    }
+
+    /// Announce a candidacy to the network
+    pub async fn announce(&self, candidate: &CommitteeCandidate) -> Result<()> {
+        let payload = serde_json::to_vec(candidate)?;
+        self.gossip
+            .publish_application(candidate.peer_id.clone(), Self::topic(), payload)
+            .await
+            .map_err(|e| CreditError::P2p(e.to_string()))
+    }
+
+    /// Subscribe to candidacy announcements
+    pub async fn subscribe(&self) -> Result<TopicSubscription> {
+        self.gossip
+            .subscribe_application(Self::topic())
+            .await
+            .map_err(|e| CreditError::P2p(e.to_string()))
+    }
+
+    /// Record a candidacy received over gossip
+    ///
+    /// Candidacies must be signed, and published by the peer they name.
+    /// Returns `true` if the message was a valid candidacy announcement.
+    pub fn ingest(&self, message: &TopicMessage) -> bool {
+        let candidate = match serde_json::from_slice::<CommitteeCandidate>(&message.payload) {
+            Ok(candidate) => candidate,
+            Err(e) => {
+                warn!("Ignoring malformed committee candidacy: {}", e);
+                return false;
+            }
+        };
+        if candidate.peer_id != message.peer_id {
+            warn!(
+                "Ignoring committee candidacy for {} published by {}",
+                candidate.peer_id, message.peer_id
+            );
+            return false;
+        }
+
+        let did = candidate.did.clone();
+        match self.record(candidate) {
+            Ok(()) => {
+                debug!("Received committee candidacy from {}", did);
+                true
+            }
+            Err(e) => {
+                warn!("Ignoring committee candidacy from {}: {}", did, e);
+                false
+            }
+        }
+    }
+
+    /// Withdraw a candidacy from the network, signing with the member key
+    ///
+    /// A withdrawn candidate is not selected again; if it is a member, it
+    /// serves until the next rotation hands its work off.
+    pub async fn withdraw(&self, candidate: &CommitteeCandidate, key: &SigningKey) -> Result<()> {
+        let withdrawal = candidate.withdrawal(key);
+        self.announce(&withdrawal).await?;
+        self.record(withdrawal)
+    }
+
+    /// Record a signed candidacy directly (keeps the most recent per DID)
+    ///
+    /// Counts the candidate as heard in the current announce interval.
+    pub fn record(&self, candidate: CommitteeCandidate) -> Result<()> {
+        candidate.verify()?;
+        self.observe(&candidate.did, chrono::Utc::now().timestamp() as u64);
+
+        let newer = self
+            .candidates
+            .get(&candidate.did)
+            .map_or(true, |existing| candidate.announced_at >= existing.announced_at);
+        if newer {
+            self.candidates.insert(candidate.did.clone(), candidate);
+        }
+        Ok(())
+    }
+
+    /// Share of the announce intervals in the uptime window, up to `now`,
+    /// in which `did` was heard
+    pub fn uptime(&self, did: &str, now: u64) -> f64 {
+        let (interval, count) = self.uptime_intervals();
+        let current = now / interval;
+        let heard = self.heard.get(did).map_or(0, |heard| {
+            heard
+                .range(current.saturating_sub(count - 1)..=current)
+                .count()
+        });
+        heard as f64 / count as f64
+    }
+
+    /// Count `did` as heard at `now`, forgetting intervals past the window
+    fn observe(&self, did: &str, now: u64) {
+        let (interval, count) = self.uptime_intervals();
+        let current = now / interval;
+        let mut heard = self.heard.entry(did.to_string()).or_default();
+        heard.insert(current);
+        heard.retain(|&heard| heard + count > current);
+    }
+
+    /// Announce interval length in seconds, and intervals in the window
+    fn uptime_intervals(&self) -> (u64, u64) {
+        let interval = self.config.announce_interval.as_secs().max(1);
+        let count = (self.config.uptime_window.as_secs() / interval).max(1);
+        (interval, count)
+    }
+
+    /// Drain announcements from a subscription for up to `window`
+    pub async fn collect(&self, subscription: &mut TopicSubscription, window: Duration) -> usize {
+        let deadline = tokio::time::Instant::now() + window;
+        let mut received = 0;
+
+        while let Ok(Some(message)) =
+            tokio::time::timeout_at(deadline, subscription.recv()).await
+        {
+            if self.ingest(&message) {
+                received += 1;
+            }
+        }
+
+        received
+    }
+
+    /// Number of known candidates (eligible or not)
+    pub fn candidate_count(&self) -> usize {
+        self.candidates.len()
+    }
+
+    /// Candidates passing the tier, uptime, weight and freshness filters,
+    /// with their selection weights, sorted by DID
+    ///
+    /// Tiers and stakeable balances come from the candidates' credit
+    /// accounts, so candidates without one are not eligible. Withdrawn
+    /// candidacies never are.
+    pub async fn eligible_candidates(&self) -> Result<Vec<(CommitteeCandidate, u64)>> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let ttl = self.config.candidacy_ttl.as_secs();
+
+        let mut candidates: Vec<_> = self
+            .candidates
+            .iter()
+            .map(|entry| entry.value().clone())
+            .filter(|c| !c.withdrawn && now.saturating_sub(c.announced_at) <= ttl)
+            .filter(|c| self.uptime(&c.did, now) >= self.config.min_uptime_score)
+            .collect();
+        candidates.sort_by(|a, b| a.did.cmp(&b.did));
+
+        let mut eligible = Vec::new();
+        for mut candidate in candidates {
+            let Ok(account) = CreditAccountHandle::load(&self.state_engine, &candidate.did).await
+            else {
+                continue;
+            };
+            let (tier, pledgeable) =
+                account.read(|acc| Ok((acc.reputation_tier, acc.available_to_pledge())))?;
+            candidate.stake = candidate.stake.min(pledgeable.max(0));
+
+            let weight = self.config.weight(tier, candidate.stake);
+            if tier >= self.config.min_reputation_tier && weight > 0 {
+                eligible.push((candidate, weight));
+            }
+        }
+        Ok(eligible)
+    }
+
+    /// Derive the selection seed for an epoch from the credit account heads
+    pub fn derive_seed(&self, epoch: u64) -> Result<[u8; 32]> {
+        Ok(seed_from_heads(epoch, &self.account_heads()?))
+    }
+
+    /// Current heads of the credit account CRDTs
+    /// (account ID -> sorted hex change hashes)
+    fn account_heads(&self) -> Result<BTreeMap<String, Vec<String>>> {
+        let mut heads = BTreeMap::new();
+        for id in self.state_engine.store.list_namespace("credit") {
+            let handle = self.state_engine.store.get(&id)?;
+            let mut hashes: Vec<String> = handle
+                .heads()
+                .iter()
+                .map(|head| encode_hex(&head.0))
+                .collect();
+            hashes.sort();
+            heads.insert(id.key, hashes);
+        }
+        Ok(heads)
+    }
+
+    /// Check a committee document against the local credit accounts
+    ///
+    /// Beyond [`CommitteeDocument::verify`], every head the seed is derived
+    /// from must be known here, so a seed cannot be ground out of made-up
+    /// heads.
+    pub fn verify_committee(&self, committee: &CommitteeDocument) -> Result<()> {
+        if !committee.verify() {
+            return Err(CreditError::InvalidProof(
+                "Committee selection does not verify".to_string(),
+            ));
+        }
+
+        for (account, hashes) in &committee.heads {
+            let heads: Option<Vec<_>> = hashes
+                .iter()
+                .map(|hash| decode_hex(hash).map(automerge::ChangeHash))
+                .collect();
+            let id = DocumentId::new("credit", account.as_str());
+            let known = match (heads, self.state_engine.store.get(&id)) {
+                (Some(heads), Ok(handle)) => handle.contains_heads(&heads),
+                _ => false,
+            };
+            if !known {
+                return Err(CreditError::InvalidProof(format!(
+                    "Committee seed names unknown heads of account {}",
+                    account
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Form and publish the committee for an epoch
+    pub async fn form_committee(&self, epoch: u64) -> Result<CommitteeDocument> {
+        self.form(epoch, None).await
+    }
+
+    /// Rotate to the committee for the epoch after `previous`
+    ///
+    /// Forms and publishes the next committee, linked to `previous`, and
+    /// returns it with the handoff for the outgoing members to sign: the
+    /// accounts awaiting reconciliation and the `outgoing` committee's
+    /// sequence numbers, which the incoming committee resumes from.
+    pub async fn rotate(
+        &self,
+        previous: &CommitteeDocument,
+        outgoing: &BftCommittee,
+    ) -> Result<(CommitteeDocument, CommitteeHandoff)> {
+        let committee = self
+            .form(previous.epoch + 1, Some(encode_hex(&previous.digest())))
+            .await?;
+
+        let handoff = CommitteeHandoff {
+            from_epoch: previous.epoch,
+            to_epoch: committee.epoch,
+            committee: encode_hex(&committee.digest()),
+            joined: committee
+                .members
+                .iter()
+                .filter(|did| !previous.is_member(did))
+                .cloned()
+                .collect(),
+            left: previous
+                .members
+                .iter()
+                .filter(|did| !committee.is_member(did))
+                .cloned()
+                .collect(),
+            pending: self.pending_accounts().await?,
+            sequences: outgoing.sequences(),
+            signatures: Vec::new(),
+        };
+
+        info!(
+            "Rotating committee from epoch {} to {}: {} joined, {} left",
+            handoff.from_epoch,
+            handoff.to_epoch,
+            handoff.joined.len(),
+            handoff.left.len()
+        );
+
+        Ok((committee, handoff))
+    }
+
+    /// Accounts with transactions awaiting reconciliation, sorted
+    pub async fn pending_accounts(&self) -> Result<Vec<String>> {
+        let mut ids = self.state_engine.store.list_namespace("credit");
+        ids.sort_by(|a, b| a.key.cmp(&b.key));
+
+        let mut pending = Vec::new();
+        for id in ids {
+            let account = CreditAccountHandle::load(&self.state_engine, &id.key).await?;
+            if account.read(|acc| Ok(!acc.pending_debits().is_empty()))? {
+                pending.push(id.key);
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Form and publish the committee for an epoch, linked to `previous`
+    async fn form(&self, epoch: u64, previous: Option<String>) -> Result<CommitteeDocument> {
+        let eligible = self.eligible_candidates().await?;
+
+        if eligible.len() < self.config.committee_size {
+            return Err(CreditError::InsufficientCandidates {
+                available: eligible.len(),
+                required: self.config.committee_size,
+            });
+        }
+
+        let heads = self.account_heads()?;
+        let seed = seed_from_heads(epoch, &heads);
+        let weights: Vec<u64> = eligible.iter().map(|(_, weight)| *weight).collect();
+        let candidates: Vec<String> = eligible.iter().map(|(c, _)| c.did.clone()).collect();
+        let members = select_weighted(&seed, &candidates, &weights, self.config.committee_size);
+        let member_keys = eligible
+            .iter()
+            .filter(|(c, _)| members.contains(&c.did))
+            .filter_map(|(c, _)| Some((c.did.clone(), c.public_key?)))
+            .collect();
+
+        let committee = CommitteeDocument {
+            epoch,
+            seed: encode_hex(&seed),
+            heads,
+            members,
+            candidates,
+            weights,
+            member_keys,
+            previous,
+            formed_at: chrono::Utc::now().timestamp() as u64,
+        };
+
+        self.publish(&committee).await?;
+
+        info!(
+            "Formed committee for epoch {} with {} members",
+            epoch,
+            committee.members.len()
+        );
+
+        Ok(committee)
+    }
+
+    /// Load a previously published committee document
+    pub async fn load_committee(&self, epoch: u64) -> Result<CommitteeDocument> {
+        self.load(&CommitteeDocument::document_id(epoch)).await
+    }
+
+    /// Publish a signed handoff, completing the membership proof of its
+    /// incoming epoch
+    pub async fn publish_handoff(&self, handoff: &CommitteeHandoff) -> Result<()> {
+        let doc_id = handoff_document_id(handoff.to_epoch);
+        let handle = match self.state_engine.get_document(&doc_id).await {
+            Ok(handle) => handle,
+            Err(_) => self.state_engine.create_document(doc_id).await?,
+        };
+
+        let json = serde_json::to_string(handoff)?;
+        handle.update(|tx| {
+            tx.put(ROOT, "data_json", json)?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// Load the membership proof of an epoch's committee
+    pub async fn membership_proof(&self, epoch: u64) -> Result<MembershipProof> {
+        Ok(MembershipProof {
+            committee: self.load_committee(epoch).await?,
+            handoff: self.load(&handoff_document_id(epoch)).await?,
+        })
+    }
+
+    /// Load a committee or handoff document
+    async fn load<T: DeserializeOwned>(&self, doc_id: &DocumentId) -> Result<T> {
+        load_json(&self.state_engine, doc_id)
+            .await?
+            .ok_or_else(|| StateError::DocumentNotFound(doc_id.to_string()).into())
+    }
+
+    /// Write the committee document to the state engine and announce it
+    async fn publish(&self, committee: &CommitteeDocument) -> Result<()> {
+        let doc_id = CommitteeDocument::document_id(committee.epoch);
+        let handle = match self.state_engine.get_document(&doc_id).await {
+            Ok(handle) => handle,
+            Err(_) => self.state_engine.create_document(doc_id.clone()).await?,
+        };
+
+        let json = serde_json::to_string(committee)?;
+        let members = committee.members.join(",");
+        let epoch = committee.epoch as i64;
+        let seed = committee.seed.clone();
+        handle.update(|tx| {
+            tx.put(ROOT, "epoch", epoch)?;
+            tx.put(ROOT, "seed", seed)?;
+            tx.put(ROOT, "members", members)?;
+            tx.put(ROOT, "data_json", json)?;
+            Ok(())
+        })?;
+
+        let version = handle.metadata().version;
+        self.gossip
+            .announce_document(
+                "committee".to_string(),
+                &doc_id.namespace,
+                &doc_id.key,
+                version,
+            )
+            .await
+            .map_err(|e| CreditError::P2p(e.to_string()))
+    }
+}
+
+/// Deterministically select `size` members from `candidates` using `seed`
+pub fn select_members(seed: &[u8; 32], candidates: &[String], size: usize) -> Vec<String> {
+    let mut scored: Vec<([u8; 32], &String)> = candidates
+        .iter()
+        .map(|did| {
+            let mut hasher = blake3::Hasher::new();
+            hasher.update(seed);
+            hasher.update(did.as_bytes());
+            (*hasher.finalize().as_bytes(), did)
+        })
+        .collect();
+    scored.sort();
+    scored.into_iter().take(size).map(|(_, did)| did.clone()).collect()
+}
+
+/// Deterministically draw `size` members from weighted `candidates` using `seed`
+///
+/// Each draw picks one of the candidates not yet drawn, with probability
+/// proportional to its weight. Candidates of weight 0 are never drawn.
+pub fn select_weighted(
+    seed: &[u8; 32],
+    candidates: &[String],
+    weights: &[u64],
+    size: usize,
+) -> Vec<String> {
+    let mut remaining: Vec<(&String, u64)> = candidates
+        .iter()
+        .zip(weights.iter().copied())
+        .filter(|(_, weight)| *weight > 0)
+        .collect();
+    let mut members = Vec::with_capacity(size);
+
+    for draw in 0..size as u64 {
+        let total: u64 = remaining.iter().map(|(_, weight)| weight).sum();
+        if total == 0 {
+            break;
+        }
+
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(seed);
+        hasher.update(&draw.to_le_bytes());
+        let hash = hasher.finalize();
+        let bytes: [u8; 8] = hash.as_bytes()[..8].try_into().expect("8 bytes");
+        let mut target = u64::from_le_bytes(bytes) % total;
+
+        let index = remaining
+            .iter()
+            .position(|(_, weight)| {
+                if target < *weight {
+                    return true;
+                }
+                target -= weight;
+                false
+            })
+            .expect("target is below the total weight");
+        members.push(remaining.remove(index).0.clone());
+    }
+
+    members
+}
+
+/// Selection seed for `epoch` from credit account heads
+fn seed_from_heads(epoch: u64, heads: &BTreeMap<String, Vec<String>>) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&epoch.to_le_bytes());
+    for (account, hashes) in heads {
+        hash_str(&mut hasher, account);
+        hash_list(&mut hasher, hashes);
+    }
+    *hasher.finalize().as_bytes()
+}
+
+/// Document ID under which the handoff into `epoch` is published
+fn handoff_document_id(epoch: u64) -> DocumentId {
+    DocumentId::new(COMMITTEE_NAMESPACE, format!("handoff-{}", epoch))
+}
+
+/// Hash a length-prefixed string
+fn hash_str(hasher: &mut blake3::Hasher, value: &str) {
+    hasher.update(&(value.len() as u64).to_le_bytes());
+    hasher.update(value.as_bytes());
+}
+
+/// Hash a length-prefixed list of strings
+fn hash_list(hasher: &mut blake3::Hasher, values: &[String]) {
+    hasher.update(&(values.len() as u64).to_le_bytes());
+    for value in values {
+        hash_str(hasher, value);
+    }
+}
+
+pub(crate) fn encode_hex(bytes: &[u8; 32]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn decode_hex(hex: &str) -> Option<[u8; 32]> {
+    // Peer-supplied, so slicing must not land inside a multi-byte character
+    if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut bytes = [0u8; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn setup() -> (Arc<StateEngine>, CommitteeFormation) {
+        let state_engine = Arc::new(StateEngine::new().await.unwrap());
+        let gossip = Arc::new(GossipOverlay::new());
+        let formation = CommitteeFormation::new(
+            Arc::clone(&state_engine),
+            gossip,
+            CommitteeFormationConfig::default(),
+        )
+        .unwrap();
+        (state_engine, formation)
+    }
+
+    /// Signed candidacy of a node whose credit account is at `tier`
+    async fn candidate(
+        engine: &StateEngine,
+        seed: u8,
+        tier: u8,
+        balance: i64,
+    ) -> (CommitteeCandidate, SigningKey) {
+        let key = SigningKey::from_bytes(&[seed; 32]);
+        let did = Did::from_key(key.verifying_key()).as_str().to_string();
+        let account = CreditAccountHandle::create(engine, did.clone(), balance)
+            .await
+            .unwrap();
+        account
+            .update(|acc| {
+                acc.reputation_tier = ReputationTier::new(tier)?;
+                Ok(())
+            })
+            .unwrap();
+
+        let candidate = CommitteeCandidate::new(did, format!("peer-{}", seed)).sign(&key);
+        (candidate, key)
+    }
+
+    /// Count a candidate as heard in every interval of the uptime window
+    fn online(formation: &CommitteeFormation, did: &str) {
+        let (interval, count) = formation.uptime_intervals();
+        let now = chrono::Utc::now().timestamp() as u64;
+        for i in 0..count {
+            formation.observe(did, now - i * interval);
+        }
+    }
+
+    /// Record a candidacy of a node that has been online all along
+    async fn join(
+        engine: &StateEngine,
+        formation: &CommitteeFormation,
+        seed: u8,
+        tier: u8,
+    ) -> (CommitteeCandidate, SigningKey) {
+        let (candidate, key) = candidate(engine, seed, tier, 0).await;
+        formation.record(candidate.clone()).unwrap();
+        online(formation, &candidate.did);
+        (candidate, key)
+    }
+
+    #[tokio::test]
+    async fn test_candidacy_over_gossip() {
+        let (engine, formation) = setup().await;
+        let mut sub = formation.subscribe().await.unwrap();
+        let (alice, _) = candidate(&engine, 1, 3, 0).await;
+        let (bob, _) = candidate(&engine, 2, 3, 0).await;
+
+        formation.announce(&alice).await.unwrap();
+        formation.announce(&bob).await.unwrap();
+
+        // A forged stake breaks the signature
+        let forged = CommitteeCandidate {
+            stake: 1_000_000,
+            ..bob.clone()
+        };
+        formation.announce(&forged).await.unwrap();
+
+        let received = formation
+            .collect(&mut sub, Duration::from_millis(50))
+            .await;
+        assert_eq!(received, 2);
+        assert_eq!(formation.candidate_count(), 2);
+
+        // Candidacies are only taken from the peer they name
+        let relayed = TopicMessage {
+            peer_id: "peer-mallory".to_string(),
+            payload: serde_json::to_vec(&alice).unwrap(),
+            timestamp: 0,
+        };
+        assert!(!formation.ingest(&relayed));
+    }
+
+    #[tokio::test]
+    async fn test_candidacy_signed_with_did_key() {
+        let (engine, formation) = setup().await;
+        let (alice, _) = candidate(&engine, 1, 3, 0).await;
+        alice.verify().unwrap();
+
+        // Another key cannot announce for alice's DID
+        let mallory = SigningKey::from_bytes(&[9; 32]);
+        let impostor = CommitteeCandidate::new(alice.did.clone(), "peer-9").sign(&mallory);
+        assert!(impostor.verify().is_err());
+        assert!(formation.record(impostor).is_err());
+
+        let unsigned = CommitteeCandidate::new(alice.did.clone(), "peer-1");
+        assert!(formation.record(unsigned).is_err());
+        assert_eq!(formation.candidate_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_eligibility_filters() {
+        let (engine, formation) = setup().await;
+
+        // Stake counts up to the balance the account can pledge
+        let (alice, key) = candidate(&engine, 1, 3, 250_000).await;
+        let alice = CommitteeCandidate {
+            stake: 1_000_000,
+            ..alice
+        }
+        .sign(&key);
+        formation.record(alice.clone()).unwrap();
+        online(&formation, &alice.did);
+
+        join(&engine, &formation, 2, 1).await;
+
+        // Heard once, not all day
+        let (flaky, _) = candidate(&engine, 3, 4, 0).await;
+        formation.record(flaky).unwrap();
+
+        let (stale, key) = candidate(&engine, 4, 4, 0).await;
+        let stale = CommitteeCandidate {
+            announced_at: 0,
+            ..stale
+        }
+        .sign(&key);
+        formation.record(stale.clone()).unwrap();
+        online(&formation, &stale.did);
+
+        // No credit account to read a tier from
+        let key = SigningKey::from_bytes(&[5; 32]);
+        let stranger = Did::from_key(key.verifying_key()).as_str().to_string();
+        formation
+            .record(CommitteeCandidate::new(stranger.clone(), "peer-5").sign(&key))
+            .unwrap();
+        online(&formation, &stranger);
+
+        let eligible = formation.eligible_candidates().await.unwrap();
+        assert_eq!(eligible.len(), 1);
+        let (candidate, weight) = &eligible[0];
+        assert_eq!(candidate.did, alice.did);
+        assert_eq!(candidate.stake, 250_000);
+        assert_eq!(*weight, 6);
+    }
+
+    #[tokio::test]
+    async fn test_uptime() {
+        let (_engine, formation) = setup().await;
+        let (interval, count) = formation.uptime_intervals();
+        let now = 1_000 * interval;
+
+        formation.observe("did:key:zA", now);
+        formation.observe("did:key:zA", now + 1);
+        assert_eq!(formation.uptime("did:key:zA", now), 1.0 / count as f64);
+
+        for i in 0..count / 2 {
+            formation.observe("did:key:zA", now - i * interval);
+        }
+        assert_eq!(formation.uptime("did:key:zA", now), 0.5);
+
+        // Intervals past the window no longer count
+        let later = now + (count - 1) * interval;
+        assert_eq!(formation.uptime("did:key:zA", later), 1.0 / count as f64);
+        assert_eq!(formation.uptime("did:key:zB", now), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_form_committee_insufficient_candidates() {
+        let (engine, formation) = setup().await;
+        join(&engine, &formation, 1, 3).await;
+
+        let result = formation.form_committee(1).await;
+        assert!(matches!(
+            result,
+            Err(CreditError::InsufficientCandidates { available: 1, required: 4 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_form_committee_is_verifiable_and_published() {
+        let (engine, formation) = setup().await;
+        CreditAccountHandle::create(&engine, "alice".to_string(), 10_000)
+            .await
+            .unwrap();
+
+        for i in 0..7 {
+            join(&engine, &formation, i + 1, 3).await;
+        }
+
+        let committee = formation.form_committee(1).await.unwrap();
+        assert_eq!(committee.members.len(), 4);
+        assert_eq!(committee.candidates.len(), 7);
+        assert_eq!(committee.heads.len(), 8);
+        assert!(committee.verify());
+        formation.verify_committee(&committee).unwrap();
+        assert_eq!(committee.to_committee().unwrap().quorum(), 3);
+
+        let loaded = formation.load_committee(1).await.unwrap();
+        assert_eq!(loaded, committee);
+
+        let mut tampered = committee.clone();
+        tampered.members.swap(0, 1);
+        assert!(!tampered.verify());
+
+        // The seed must follow from the recorded heads
+        let mut tampered = committee.clone();
+        tampered.heads.remove("alice");
+        assert!(!tampered.verify());
+
+        // Heads unknown locally are rejected even with a matching selection
+        let mut ground = committee.clone();
+        ground
+            .heads
+            .insert("alice".to_string(), vec![encode_hex(&[7; 32])]);
+        let seed = seed_from_heads(ground.epoch, &ground.heads);
+        ground.seed = encode_hex(&seed);
+        ground.members = select_weighted(&seed, &ground.candidates, &ground.weights, 4);
+        ground.member_keys.clear();
+        assert!(ground.verify());
+        assert!(formation.verify_committee(&ground).is_err());
+    }
+
+    #[test]
+    fn test_candidate_weight() {
+        let config = CommitteeFormationConfig::default();
+        let tier = |tier| ReputationTier::new(tier).unwrap();
+        assert_eq!(config.weight(tier(3), 0), 2);
+        assert_eq!(config.weight(tier(3), 250_000), 6);
+        assert_eq!(config.weight(tier(5), i64::MAX), 40);
+        assert_eq!(config.weight(tier(3), -5), 2);
+    }
+
+    #[test]
+    fn test_weighted_selection() {
+        let candidates: Vec<String> = (0..10).map(|i| format!("node{}", i)).collect();
+        let mut weights = vec![1; 10];
+        weights[0] = 8;
+        weights[9] = 0;
+
+        let mut counts = [0; 10];
+        for i in 0..50u8 {
+            let members = select_weighted(&[i; 32], &candidates, &weights, 4);
+            assert_eq!(members.len(), 4);
+            assert_eq!(members, select_weighted(&[i; 32], &candidates, &weights, 4));
+            for member in members {
+                counts[candidates.iter().position(|c| *c == member).unwrap()] += 1;
+            }
+        }
+
+        // Heavier candidates serve more often, weightless ones never
+        assert!(counts[1..].iter().all(|&count| count < counts[0]));
+        assert_eq!(counts[9], 0);
+
+        // Only weighted candidates are drawn
+        assert_eq!(
+            select_weighted(&[0; 32], &candidates[8..], &weights[8..], 4).len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_withdrawal() {
+        let (engine, formation) = setup().await;
+        let (alice, key) = join(&engine, &formation, 1, 3).await;
+        let (bob, _) = join(&engine, &formation, 2, 3).await;
+
+        formation.withdraw(&alice, &key).await.unwrap();
+        let eligible = formation.eligible_candidates().await.unwrap();
+        assert_eq!(eligible.len(), 1);
+        assert_eq!(eligible[0].0.did, bob.did);
+
+        // Announcing again rejoins
+        let rejoin = CommitteeCandidate {
+            announced_at: alice.announced_at + 10,
+            ..alice
+        };
+        formation.record(rejoin.sign(&key)).unwrap();
+        assert_eq!(formation.eligible_candidates().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rotation_membership_proof() {
+        let (engine, formation) = setup().await;
+        let account = CreditAccountHandle::create(&engine, "alice".to_string(), 10_000)
+            .await
+            .unwrap();
+        CreditAccountHandle::create(&engine, "bob".to_string(), 10_000)
+            .await
+            .unwrap();
+
+        let mut signers = Vec::new();
+        for i in 0..7u8 {
+            let (candidate, key) = join(&engine, &formation, i + 1, 3).await;
+            signers.push(ProofSigner::new(candidate.did, key));
+        }
+        let epoch1 = formation.form_committee(1).await.unwrap();
+        let keys1 = epoch1.keys().unwrap();
+
+        // Alice spends before the rotation
+        account
+            .update(|acc| {
+                acc.add_transaction(crate::transaction::Transaction::new(
+                    "alice".to_string(),
+                    "bob".to_string(),
+                    100,
+                    Default::default(),
+                ));
+                Ok(())
+            })
+            .unwrap();
+
+        let outgoing = BftCommittee::new_mock(4).await.unwrap();
+        let (epoch2, mut handoff) = formation.rotate(&epoch1, &outgoing).await.unwrap();
+        assert_eq!(epoch2.epoch, 2);
+        assert_eq!(epoch2.previous, Some(encode_hex(&epoch1.digest())));
+        assert_eq!(handoff.pending, vec!["alice".to_string()]);
+        assert_eq!(handoff.joined.len(), handoff.left.len());
+
+        // Two outgoing signatures are short of the quorum of three
+        let outgoing_signers: Vec<&ProofSigner> = signers
+            .iter()
+            .filter(|s| epoch1.is_member(s.did()))
+            .collect();
+        for signer in &outgoing_signers[..2] {
+            handoff.sign(signer);
+        }
+        formation.publish_handoff(&handoff).await.unwrap();
+        let proof = formation.membership_proof(2).await.unwrap();
+        assert!(proof.verify(&keys1).is_err());
+
+        handoff.sign(outgoing_signers[2]);
+        formation.publish_handoff(&handoff).await.unwrap();
+        let proof = formation.membership_proof(2).await.unwrap();
+        let keys2 = proof.verify(&keys1).unwrap();
+        assert_eq!(keys2.epoch, 2);
+        assert_eq!(keys2.members(), {
+            let mut members = epoch2.members.clone();
+            members.sort();
+            members
+        });
+
+        // The proof only chains from the previous epoch's keys
+        assert!(proof.verify(&keys2).is_err());
+
+        // A substituted member breaks the proof
+        let mut forged = proof.clone();
+        let outsider = signers.iter().find(|s| !epoch2.is_member(s.did())).unwrap();
+        forged.committee.members[0] = outsider.did().to_string();
+        assert!(forged.verify(&keys1).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_seed_tracks_crdt_heads() {
+        let (engine, formation) = setup().await;
+        CreditAccountHandle::create(&engine, "alice".to_string(), 10_000)
+            .await
+            .unwrap();
+
+        let seed1 = formation.derive_seed(1).unwrap();
+        assert_eq!(seed1, formation.derive_seed(1).unwrap());
+        assert_ne!(seed1, formation.derive_seed(2).unwrap());
+
+        CreditAccountHandle::create(&engine, "bob".to_string(), 5_000)
+            .await
+            .unwrap();
+        assert_ne!(seed1, formation.derive_seed(1).unwrap());
+    }
+
+    #[test]
+    fn test_decode_hex() {
+        let bytes = [0xab; 32];
+        assert_eq!(decode_hex(&encode_hex(&bytes)), Some(bytes));
+
+        // 64 bytes, but not 64 hex digits
+        assert_eq!(decode_hex(&"é".repeat(32)), None);
+        assert_eq!(decode_hex(&format!("+f{}", "0".repeat(62))), None);
+        assert_eq!(decode_hex(&"0".repeat(63)), None);
+    }
+}
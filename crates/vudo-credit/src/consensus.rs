@@ -0,0 +1,1017 @@
+//! PBFT-style consensus rounds for the BFT committee
+//!
+//! Each reconciliation or escrow grant is one consensus instance, identified
+//! by a subject (e.g. an account ID) and a sequence number. Committee members
+//! exchange Ed25519-signed votes over the [`CONSENSUS_TOPIC`] application
+//! topic:
+//!
+//! ```text
+//! request      any member   -> all   subject, sequence, proposed value
+//! pre-prepare  leader(view) -> all   value for (subject, sequence, view)
+//! prepare      every member -> all   after accepting the pre-prepare
+//! commit       every member -> all   after 2f+1 matching prepares
+//! decide                             after 2f+1 matching commits
+//! ```
+//!
+//! The leader of view `v` is member `v mod n` in DID order. A member that sees
+//! no decision within the view timeout broadcasts a view change for the next
+//! view, carrying the highest prepare certificate it holds. f+1 view changes
+//! pull the other members along, and 2f+1 let the next leader propose again,
+//! bound to the value of the highest prepare certificate among them so that
+//! a value that may have been committed is never replaced.
+//!
+//! The 2f+1 commit votes of a decided instance form a [`QuorumCertificate`].
+//! A decision checkpoints its subject: instances of earlier sequences are
+//! evicted, and messages for them ignored.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use ed25519_dalek::SigningKey;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tracing::{debug, info, warn};
+use vudo_p2p::{GossipOverlay, Topic, TopicMessage, TopicSubscription};
+use vudo_state::StateEngine;
+
+use crate::account::CreditAccountHandle;
+use crate::bft::{reconciled_balance, BftVote, QuorumCertificate, VotePhase};
+use crate::error::{CreditError, Result};
+use crate::policy::CreditPolicy;
+use crate::proof::CommitteeKeys;
+
+/// Application topic for consensus messages
+pub const CONSENSUS_TOPIC: &str = "credit:bft";
+
+/// Default time a view may run without a decision before a view change
+pub const DEFAULT_VIEW_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A member's request to replace the leader of an instance
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ViewChange {
+    /// Signed view-change vote for the new view, carrying the member's
+    /// candidate value
+    pub vote: BftVote,
+
+    /// Highest prepare certificate the member holds for the instance
+    pub prepared: Option<QuorumCertificate>,
+}
+
+/// Consensus message exchanged over gossip
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConsensusMessage {
+    /// Request to decide a value for an instance, signed by the requesting
+    /// member
+    Request { vote: BftVote },
+
+    /// Leader proposal, justified by 2f+1 view changes after view 0
+    PrePrepare {
+        vote: BftVote,
+        justification: Vec<ViewChange>,
+    },
+
+    /// Acceptance of a leader proposal
+    Prepare { vote: BftVote },
+
+    /// Commitment after 2f+1 matching prepares
+    Commit { vote: BftVote },
+
+    /// Request to move to a new view
+    ViewChange(ViewChange),
+}
+
+impl ConsensusMessage {
+    /// Signed vote carried by the message
+    fn vote(&self) -> &BftVote {
+        match self {
+            ConsensusMessage::Request { vote }
+            | ConsensusMessage::PrePrepare { vote, .. }
+            | ConsensusMessage::Prepare { vote }
+            | ConsensusMessage::Commit { vote }
+            | ConsensusMessage::ViewChange(ViewChange { vote, .. }) => vote,
+        }
+    }
+
+    /// Instance the message belongs to
+    fn instance(&self) -> (&str, u64) {
+        let vote = self.vote();
+        (&vote.subject, vote.sequence)
+    }
+}
+
+/// Source of the value a member expects for a consensus subject
+///
+/// Members with a validator only prepare proposals matching the value they
+/// compute themselves, so a faulty leader can't get a wrong balance
+/// confirmed; it is replaced by a view change instead.
+#[async_trait]
+pub trait ProposalValidator: Send + Sync {
+    /// Value this member would propose, or `None` to accept the leader's
+    async fn expected_value(&self, subject: &str) -> Result<Option<i64>>;
+}
+
+/// Validates reconciliations against the member's copy of the account
+///
+/// Fees and demurrage are charged by the community policy held by the state
+/// engine. Subjects that are not accounts held by the state engine are accepted.
+#[async_trait]
+impl ProposalValidator for StateEngine {
+    async fn expected_value(&self, subject: &str) -> Result<Option<i64>> {
+        match CreditAccountHandle::load(self, subject).await {
+            Ok(account) => {
+                let policy = CreditPolicy::load(self).await?;
+                account
+                    .read(|acc| Ok(reconciled_balance(acc, &policy)))
+                    .map(Some)
+            }
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+/// Replica state for one consensus instance
+#[derive(Default)]
+struct Instance {
+    /// Current view
+    view: u64,
+
+    /// View timeout deadline (None if no timer runs)
+    deadline: Option<Instant>,
+
+    /// Value this member expects (outer None if not looked up yet)
+    expected: Option<Option<i64>>,
+
+    /// Value of the request that started the instance
+    requested: Option<i64>,
+
+    /// Accepted proposal (view, value)
+    proposal: Option<(u64, i64)>,
+
+    /// Prepare and commit votes by (phase, view, value), then voter
+    votes: HashMap<(VotePhase, u64, i64), HashMap<String, BftVote>>,
+
+    /// View changes by new view, then voter
+    view_changes: HashMap<u64, HashMap<String, ViewChange>>,
+
+    /// Highest prepare certificate held
+    prepared: Option<QuorumCertificate>,
+
+    /// View this member last sent a commit in
+    committed_view: Option<u64>,
+
+    /// Highest view this member requested a view change to
+    view_change_sent: u64,
+
+    /// Highest view this member proposed in as leader
+    led: Option<u64>,
+
+    /// Commit certificate, once decided
+    decision: Option<QuorumCertificate>,
+}
+
+impl Instance {
+    /// Votes for (phase, view, value)
+    fn count(&self, phase: VotePhase, view: u64, value: i64) -> usize {
+        self.votes
+            .get(&(phase, view, value))
+            .map_or(0, HashMap::len)
+    }
+
+    /// Certificate from the votes for (phase, view, value)
+    fn certificate(&self, phase: VotePhase, view: u64, value: i64) -> Option<QuorumCertificate> {
+        let votes = self.votes.get(&(phase, view, value))?;
+        QuorumCertificate::from_votes(votes.values().cloned().collect())
+    }
+
+    /// Value this member stands for in a view change
+    fn candidate(&self) -> i64 {
+        self.prepared
+            .as_ref()
+            .map(|qc| qc.value)
+            .or(self.proposal.map(|(_, value)| value))
+            .or(self.expected.flatten())
+            .or(self.requested)
+            .unwrap_or_default()
+    }
+}
+
+/// One committee member taking part in consensus rounds
+pub struct BftReplica {
+    /// Member DID
+    did: String,
+
+    /// Signing key
+    key: SigningKey,
+
+    /// Committee public keys
+    keys: CommitteeKeys,
+
+    /// Member DIDs, in leader order
+    members: Vec<String>,
+
+    /// Gossip overlay carrying consensus messages
+    gossip: Arc<GossipOverlay>,
+
+    /// Checks leader proposals (None accepts any proposal)
+    validator: Option<Arc<dyn ProposalValidator>>,
+
+    /// Time a view may run without a decision
+    view_timeout: Duration,
+
+    /// Instances by (subject, sequence)
+    instances: Mutex<HashMap<(String, u64), Instance>>,
+
+    /// Last decided sequence per subject; earlier instances are evicted
+    checkpoints: Mutex<HashMap<String, u64>>,
+
+    /// Signalled when an instance is decided
+    decided: Notify,
+}
+
+impl BftReplica {
+    /// Create a replica for a committee member
+    pub fn new(
+        did: impl Into<String>,
+        key: SigningKey,
+        keys: CommitteeKeys,
+        gossip: Arc<GossipOverlay>,
+    ) -> Result<Self> {
+        let did = did.into();
+        if keys.key(&did) != Some(&key.verifying_key()) {
+            return Err(CreditError::InvalidOperation(format!(
+                "{} is not a committee member with this key",
+                did
+            )));
+        }
+
+        Ok(Self {
+            did,
+            key,
+            members: keys.members(),
+            keys,
+            gossip,
+            validator: None,
+            view_timeout: DEFAULT_VIEW_TIMEOUT,
+            instances: Mutex::new(HashMap::new()),
+            checkpoints: Mutex::new(HashMap::new()),
+            decided: Notify::new(),
+        })
+    }
+
+    /// Only prepare proposals matching the value computed by `validator`
+    pub fn with_validator(mut self, validator: Arc<dyn ProposalValidator>) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    /// Set the time a view may run without a decision
+    pub fn with_view_timeout(mut self, timeout: Duration) -> Self {
+        self.view_timeout = timeout;
+        self
+    }
+
+    /// Member DID
+    pub fn did(&self) -> &str {
+        &self.did
+    }
+
+    /// Time a view may run without a decision
+    pub fn view_timeout(&self) -> Duration {
+        self.view_timeout
+    }
+
+    /// Leader of a view
+    pub fn leader(&self, view: u64) -> &str {
+        &self.members[(view % self.members.len() as u64) as usize]
+    }
+
+    /// Application topic for consensus messages
+    pub fn topic() -> Topic {
+        Topic::app(CONSENSUS_TOPIC)
+    }
+
+    /// Subscribe to consensus messages
+    pub async fn subscribe(&self) -> Result<TopicSubscription> {
+        self.gossip
+            .subscribe_application(Self::topic())
+            .await
+            .map_err(|e| CreditError::P2p(e.to_string()))
+    }
+
+    /// Process consensus messages and view timeouts until the subscription
+    /// closes
+    pub async fn run(&self, mut subscription: TopicSubscription) {
+        let mut ticker = tokio::time::interval(self.view_timeout / 4);
+        loop {
+            tokio::select! {
+                message = subscription.recv() => {
+                    let Some(message) = message else {
+                        break;
+                    };
+                    if let Err(e) = self.ingest(&message).await {
+                        warn!("Failed to process consensus message: {}", e);
+                    }
+                }
+                _ = ticker.tick() => {
+                    if let Err(e) = self.check_timeouts().await {
+                        warn!("Failed to change view: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Ask the committee to decide `value` for an instance
+    pub async fn request(&self, subject: &str, sequence: u64, value: i64) -> Result<()> {
+        {
+            let mut instances = self.instances.lock();
+            let instance = instances
+                .entry((subject.to_string(), sequence))
+                .or_default();
+            instance.expected.get_or_insert(Some(value));
+        }
+
+        let vote = self.vote(VotePhase::Request, subject, sequence, 0, value);
+        self.broadcast(ConsensusMessage::Request { vote }).await
+    }
+
+    /// Commit certificate of an instance, if decided
+    pub fn decision(&self, subject: &str, sequence: u64) -> Option<QuorumCertificate> {
+        self.instances
+            .lock()
+            .get(&(subject.to_string(), sequence))
+            .and_then(|instance| instance.decision.clone())
+    }
+
+    /// Wait up to `timeout` for an instance to be decided
+    ///
+    /// Messages must be processed meanwhile, e.g. by [`BftReplica::run`].
+    pub async fn wait_decision(
+        &self,
+        subject: &str,
+        sequence: u64,
+        timeout: Duration,
+    ) -> Option<QuorumCertificate> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let decided = self.decided.notified();
+            if let Some(certificate) = self.decision(subject, sequence) {
+                return Some(certificate);
+            }
+            if tokio::time::timeout_at(deadline, decided).await.is_err() {
+                return self.decision(subject, sequence);
+            }
+        }
+    }
+
+    /// Most commit votes seen for a single value of an instance
+    pub fn commit_votes(&self, subject: &str, sequence: u64) -> usize {
+        self.instances
+            .lock()
+            .get(&(subject.to_string(), sequence))
+            .and_then(|instance| {
+                instance
+                    .votes
+                    .iter()
+                    .filter(|((phase, _, _), _)| *phase == VotePhase::Commit)
+                    .map(|(_, votes)| votes.len())
+                    .max()
+            })
+            .unwrap_or(0)
+    }
+
+    /// Process a consensus message received over gossip
+    ///
+    /// Returns `true` if the message was a consensus message.
+    pub async fn ingest(&self, message: &TopicMessage) -> Result<bool> {
+        let message: ConsensusMessage = match serde_json::from_slice(&message.payload) {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Ignoring malformed consensus message: {}", e);
+                return Ok(false);
+            }
+        };
+
+        self.process(message).await?;
+        Ok(true)
+    }
+
+    /// Request a view change for every instance whose view timed out
+    pub async fn check_timeouts(&self) -> Result<()> {
+        let now = Instant::now();
+        let max_view = 2 * self.members.len() as u64;
+        let mut outgoing = Vec::new();
+
+        {
+            let mut instances = self.instances.lock();
+            for ((subject, sequence), instance) in instances.iter_mut() {
+                if instance.decision.is_some()
+                    || !instance.deadline.is_some_and(|deadline| deadline <= now)
+                {
+                    continue;
+                }
+
+                let view = instance.view.max(instance.view_change_sent) + 1;
+                if view > max_view {
+                    warn!(
+                        "Giving up on {} #{} after {} views",
+                        subject, sequence, max_view
+                    );
+                    instance.deadline = None;
+                    continue;
+                }
+
+                debug!(
+                    "View {} of {} #{} timed out",
+                    instance.view, subject, sequence
+                );
+                instance.deadline = Some(now + self.view_timeout);
+                outgoing.push(self.view_change(subject, *sequence, view, instance));
+            }
+        }
+
+        for message in outgoing {
+            self.broadcast(message).await?;
+        }
+        Ok(())
+    }
+
+    /// Handle a message and everything it makes this replica send
+    async fn process(&self, message: ConsensusMessage) -> Result<()> {
+        let mut queue = VecDeque::from([message]);
+
+        while let Some(message) = queue.pop_front() {
+            if !self.admits(&message) {
+                continue;
+            }
+            self.look_up_expected(&message).await?;
+            for outgoing in self.handle(&message) {
+                let payload = serde_json::to_vec(&outgoing)?;
+                self.publish(payload).await?;
+                queue.push_back(outgoing);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that a message is signed by a member, and that its instance
+    /// is not evicted
+    fn admits(&self, message: &ConsensusMessage) -> bool {
+        let vote = message.vote();
+        if !vote.verify(&self.keys) {
+            warn!("Ignoring unsigned consensus message from {}", vote.voter);
+            return false;
+        }
+
+        self.checkpoints
+            .lock()
+            .get(&vote.subject)
+            .map_or(true, |&checkpoint| vote.sequence >= checkpoint)
+    }
+
+    /// Publish a message and handle it locally
+    async fn broadcast(&self, message: ConsensusMessage) -> Result<()> {
+        let payload = serde_json::to_vec(&message)?;
+        self.publish(payload).await?;
+        self.process(message).await
+    }
+
+    async fn publish(&self, payload: Vec<u8>) -> Result<()> {
+        self.gossip
+            .publish_application(self.did.clone(), Self::topic(), payload)
+            .await
+            .map_err(|e| CreditError::P2p(e.to_string()))
+    }
+
+    /// Ask the validator for the expected value before a proposal is needed
+    async fn look_up_expected(&self, message: &ConsensusMessage) -> Result<()> {
+        let Some(validator) = &self.validator else {
+            return Ok(());
+        };
+        if !matches!(
+            message,
+            ConsensusMessage::Request { .. } | ConsensusMessage::PrePrepare { .. }
+        ) {
+            return Ok(());
+        }
+
+        let (subject, sequence) = message.instance();
+        let key = (subject.to_string(), sequence);
+        let known = self
+            .instances
+            .lock()
+            .get(&key)
+            .is_some_and(|instance| instance.expected.is_some());
+        if known {
+            return Ok(());
+        }
+
+        let expected = validator.expected_value(subject).await?;
+        self.instances
+            .lock()
+            .entry(key)
+            .or_default()
+            .expected
+            .get_or_insert(expected);
+        Ok(())
+    }
+
+    /// Update the instance state for a message, returning the messages to
+    /// send in response
+    fn handle(&self, message: &ConsensusMessage) -> Vec<ConsensusMessage> {
+        let (subject, sequence) = message.instance();
+        let mut instances = self.instances.lock();
+        let instance = instances
+            .entry((subject.to_string(), sequence))
+            .or_default();
+        if instance.decision.is_some() {
+            return Vec::new();
+        }
+
+        match message {
+            ConsensusMessage::Request { vote } => self.on_request(vote, instance),
+            ConsensusMessage::PrePrepare {
+                vote,
+                justification,
+            } => self.on_pre_prepare(vote, justification, instance),
+            ConsensusMessage::Prepare { vote } => self.on_prepare(vote, instance),
+            ConsensusMessage::Commit { vote } => {
+                self.on_commit(vote, instance);
+                if instance.decision.is_some() {
+                    self.checkpoint(&mut instances, subject, sequence);
+                }
+                Vec::new()
+            }
+            ConsensusMessage::ViewChange(view_change) => self.on_view_change(view_change, instance),
+        }
+    }
+
+    fn on_request(&self, vote: &BftVote, instance: &mut Instance) -> Vec<ConsensusMessage> {
+        if vote.phase != VotePhase::Request {
+            warn!("Ignoring invalid request from {}", vote.voter);
+            return Vec::new();
+        }
+
+        instance.requested.get_or_insert(vote.proposed_balance);
+        instance
+            .deadline
+            .get_or_insert(Instant::now() + self.view_timeout);
+
+        if instance.view != 0 || instance.led.is_some() || self.leader(0) != self.did {
+            return Vec::new();
+        }
+
+        instance.led = Some(0);
+        let value = instance.expected.flatten().unwrap_or(vote.proposed_balance);
+        let pre_prepare = self.vote(
+            VotePhase::PrePrepare,
+            &vote.subject,
+            vote.sequence,
+            0,
+            value,
+        );
+        vec![ConsensusMessage::PrePrepare {
+            vote: pre_prepare,
+            justification: Vec::new(),
+        }]
+    }
+
+    fn on_pre_prepare(
+        &self,
+        vote: &BftVote,
+        justification: &[ViewChange],
+        instance: &mut Instance,
+    ) -> Vec<ConsensusMessage> {
+        if vote.phase != VotePhase::PrePrepare || vote.voter != self.leader(vote.view) {
+            warn!("Ignoring invalid pre-prepare from {}", vote.voter);
+            return Vec::new();
+        }
+        if vote.view < instance.view {
+            return Vec::new();
+        }
+        if let Some((view, value)) = instance.proposal {
+            if view == vote.view {
+                if value != vote.proposed_balance {
+                    warn!("Leader {} proposed two values in view {}", vote.voter, view);
+                }
+                return Vec::new();
+            }
+        }
+        if vote.view > 0 && !self.justifies(vote, justification) {
+            warn!("Ignoring unjustified pre-prepare for view {}", vote.view);
+            return Vec::new();
+        }
+
+        instance.view = vote.view;
+        instance.deadline = Some(Instant::now() + self.view_timeout);
+
+        if let Some(expected) = instance.expected.flatten() {
+            if expected != vote.proposed_balance {
+                warn!(
+                    "Rejecting proposal of {} for {}: expected {}",
+                    vote.proposed_balance, vote.subject, expected
+                );
+                return Vec::new();
+            }
+        }
+
+        instance.proposal = Some((vote.view, vote.proposed_balance));
+        let prepare = self.vote(
+            VotePhase::Prepare,
+            &vote.subject,
+            vote.sequence,
+            vote.view,
+            vote.proposed_balance,
+        );
+        vec![ConsensusMessage::Prepare { vote: prepare }]
+    }
+
+    fn on_prepare(&self, vote: &BftVote, instance: &mut Instance) -> Vec<ConsensusMessage> {
+        if !self.record(VotePhase::Prepare, vote, instance) {
+            return Vec::new();
+        }
+
+        let Some((view, value)) = instance.proposal else {
+            return Vec::new();
+        };
+        if instance.committed_view == Some(view)
+            || instance.count(VotePhase::Prepare, view, value) < self.keys.quorum()
+        {
+            return Vec::new();
+        }
+
+        if instance.prepared.as_ref().map_or(true, |qc| qc.view < view) {
+            instance.prepared = instance.certificate(VotePhase::Prepare, view, value);
+        }
+        instance.committed_view = Some(view);
+
+        let commit = self.vote(VotePhase::Commit, &vote.subject, vote.sequence, view, value);
+        vec![ConsensusMessage::Commit { vote: commit }]
+    }
+
+    fn on_commit(&self, vote: &BftVote, instance: &mut Instance) {
+        if !self.record(VotePhase::Commit, vote, instance) {
+            return;
+        }
+
+        let (view, value) = (vote.view, vote.proposed_balance);
+        if instance.count(VotePhase::Commit, view, value) >= self.keys.quorum() {
+            info!(
+                "Decided {} for {} #{} in view {}",
+                value, vote.subject, vote.sequence, view
+            );
+            instance.decision = instance.certificate(VotePhase::Commit, view, value);
+            instance.deadline = None;
+            self.decided.notify_waiters();
+        }
+    }
+
+    fn on_view_change(
+        &self,
+        view_change: &ViewChange,
+        instance: &mut Instance,
+    ) -> Vec<ConsensusMessage> {
+        let vote = &view_change.vote;
+        if !self.valid_view_change(view_change) {
+            warn!("Ignoring invalid view change from {}", vote.voter);
+            return Vec::new();
+        }
+        if vote.view <= instance.view {
+            return Vec::new();
+        }
+
+        instance
+            .view_changes
+            .entry(vote.view)
+            .or_default()
+            .insert(vote.voter.clone(), view_change.clone());
+
+        let mut outgoing = Vec::new();
+
+        // f+1 members want a later view, so at least one honest member does:
+        // join the earliest such view
+        let f = (self.members.len() - 1) / 3;
+        let later: HashMap<&str, u64> = instance
+            .view_changes
+            .iter()
+            .filter(|(view, _)| **view > instance.view)
+            .flat_map(|(view, votes)| votes.keys().map(move |voter| (voter.as_str(), *view)))
+            .collect();
+        if later.len() > f {
+            let target = later.values().copied().min().unwrap_or(vote.view);
+            if target > instance.view_change_sent {
+                outgoing.push(self.view_change(&vote.subject, vote.sequence, target, instance));
+            }
+        }
+
+        // 2f+1 view changes start the new view
+        let votes = &instance.view_changes[&vote.view];
+        if votes.len() < self.keys.quorum() {
+            return outgoing;
+        }
+        let justification: Vec<ViewChange> = votes.values().cloned().collect();
+
+        instance.view = vote.view;
+        instance.deadline = Some(Instant::now() + self.view_timeout);
+
+        if self.leader(vote.view) == self.did && instance.led < Some(vote.view) {
+            instance.led = Some(vote.view);
+            let value = highest_prepared(&justification)
+                .map(|qc| qc.value)
+                .or(instance.expected.flatten())
+                .or(instance.requested)
+                .unwrap_or(vote.proposed_balance);
+            let pre_prepare = self.vote(
+                VotePhase::PrePrepare,
+                &vote.subject,
+                vote.sequence,
+                vote.view,
+                value,
+            );
+            info!(
+                "Leading view {} of {} #{}",
+                vote.view, vote.subject, vote.sequence
+            );
+            outgoing.push(ConsensusMessage::PrePrepare {
+                vote: pre_prepare,
+                justification,
+            });
+        }
+
+        outgoing
+    }
+
+    /// Evict the instances of `subject` before the decided `sequence`
+    ///
+    /// The decided instance stays, to answer [`BftReplica::decision`], until
+    /// a later sequence of the subject is decided.
+    fn checkpoint(
+        &self,
+        instances: &mut HashMap<(String, u64), Instance>,
+        subject: &str,
+        sequence: u64,
+    ) {
+        let mut checkpoints = self.checkpoints.lock();
+        let checkpoint = checkpoints.entry(subject.to_string()).or_default();
+        if sequence > *checkpoint {
+            *checkpoint = sequence;
+            instances.retain(|(s, n), _| s != subject || *n >= sequence);
+        }
+    }
+
+    /// Record a prepare or commit vote, already checked by
+    /// [`BftReplica::admits`]
+    ///
+    /// Returns `false` if the vote is for another phase or a duplicate.
+    fn record(&self, phase: VotePhase, vote: &BftVote, instance: &mut Instance) -> bool {
+        if vote.phase != phase {
+            warn!("Ignoring invalid {:?} vote from {}", phase, vote.voter);
+            return false;
+        }
+
+        instance
+            .votes
+            .entry((phase, vote.view, vote.proposed_balance))
+            .or_default()
+            .insert(vote.voter.clone(), vote.clone())
+            .is_none()
+    }
+
+    /// Check a view change's vote and prepare certificate
+    fn valid_view_change(&self, view_change: &ViewChange) -> bool {
+        let vote = &view_change.vote;
+        if vote.phase != VotePhase::ViewChange || !vote.verify(&self.keys) {
+            return false;
+        }
+
+        view_change.prepared.as_ref().map_or(true, |qc| {
+            qc.phase == VotePhase::Prepare
+                && qc.subject == vote.subject
+                && qc.sequence == vote.sequence
+                && qc.view < vote.view
+                && qc.value == vote.proposed_balance
+                && qc.verify(&self.keys).is_ok()
+        })
+    }
+
+    /// Check that 2f+1 view changes justify a pre-prepare after view 0
+    ///
+    /// The proposal must carry the value of the highest prepare certificate
+    /// among them, if any.
+    fn justifies(&self, vote: &BftVote, justification: &[ViewChange]) -> bool {
+        let valid: Vec<ViewChange> = justification
+            .iter()
+            .filter(|vc| {
+                vc.vote.subject == vote.subject
+                    && vc.vote.sequence == vote.sequence
+                    && vc.vote.view == vote.view
+                    && self.valid_view_change(vc)
+            })
+            .cloned()
+            .collect();
+        let voters: HashSet<&str> = valid.iter().map(|vc| vc.vote.voter.as_str()).collect();
+        if voters.len() < self.keys.quorum() {
+            return false;
+        }
+
+        highest_prepared(&valid).map_or(true, |qc| qc.value == vote.proposed_balance)
+    }
+
+    /// Sign a view-change vote for `view`, remembering it was sent
+    fn view_change(
+        &self,
+        subject: &str,
+        sequence: u64,
+        view: u64,
+        instance: &mut Instance,
+    ) -> ConsensusMessage {
+        instance.view_change_sent = view;
+        let vote = self.vote(
+            VotePhase::ViewChange,
+            subject,
+            sequence,
+            view,
+            instance.candidate(),
+        );
+        ConsensusMessage::ViewChange(ViewChange {
+            vote,
+            prepared: instance.prepared.clone(),
+        })
+    }
+
+    fn vote(
+        &self,
+        phase: VotePhase,
+        subject: &str,
+        sequence: u64,
+        view: u64,
+        value: i64,
+    ) -> BftVote {
+        let mut vote = BftVote::new(self.did.clone(), subject, phase, view, sequence, value);
+        vote.sign(&self.key);
+        vote
+    }
+}
+
+/// Highest-view prepare certificate among view changes
+fn highest_prepared(view_changes: &[ViewChange]) -> Option<&QuorumCertificate> {
+    view_changes
+        .iter()
+        .filter_map(|vc| vc.prepared.as_ref())
+        .max_by_key(|qc| qc.view)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn committee(size: u8) -> (CommitteeKeys, Vec<SigningKey>) {
+        let keys: Vec<SigningKey> = (0..size)
+            .map(|i| SigningKey::from_bytes(&[i + 1; 32]))
+            .collect();
+        let members = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (format!("member{}", i), key.verifying_key()))
+            .collect();
+        (CommitteeKeys::new(1, members).unwrap(), keys)
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        let (_, keys) = committee(4);
+        let mut vote = BftVote::new("member0", "alice", VotePhase::PrePrepare, 0, 1, 9_000);
+        vote.sign(&keys[0]);
+        let message = ConsensusMessage::PrePrepare {
+            vote,
+            justification: Vec::new(),
+        };
+
+        let bytes = serde_json::to_vec(&message).unwrap();
+        let decoded: ConsensusMessage = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, message);
+    }
+
+    #[tokio::test]
+    async fn test_replica_rejects_foreign_key() {
+        let (keys, _) = committee(4);
+        let gossip = Arc::new(GossipOverlay::new());
+        let result = BftReplica::new("member0", SigningKey::from_bytes(&[9; 32]), keys, gossip);
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pre_prepare_from_non_leader_is_ignored() {
+        let (keys, signing_keys) = committee(4);
+        let gossip = Arc::new(GossipOverlay::new());
+        let replica = BftReplica::new(
+            "member1",
+            signing_keys[1].clone(),
+            keys,
+            Arc::clone(&gossip),
+        )
+        .unwrap();
+        let mut sub = replica.subscribe().await.unwrap();
+
+        // member2 is not the leader of view 0
+        let mut vote = BftVote::new("member2", "alice", VotePhase::PrePrepare, 0, 1, 9_000);
+        vote.sign(&signing_keys[2]);
+        replica
+            .process(ConsensusMessage::PrePrepare {
+                vote,
+                justification: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        let sent = tokio::time::timeout(Duration::from_millis(20), sub.recv()).await;
+        assert!(
+            sent.is_err(),
+            "replica must not prepare a non-leader proposal"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forged_request_is_ignored() {
+        let (keys, signing_keys) = committee(4);
+        let gossip = Arc::new(GossipOverlay::new());
+        let replica = BftReplica::new(
+            "member0",
+            signing_keys[0].clone(),
+            keys,
+            Arc::clone(&gossip),
+        )
+        .unwrap();
+        let mut sub = replica.subscribe().await.unwrap();
+
+        // Claims to come from member1, signed with another key
+        let mut vote = BftVote::new("member1", "alice", VotePhase::Request, 0, 1, 9_000);
+        vote.sign(&SigningKey::from_bytes(&[9; 32]));
+        replica
+            .process(ConsensusMessage::Request { vote })
+            .await
+            .unwrap();
+        let sent = tokio::time::timeout(Duration::from_millis(20), sub.recv()).await;
+        assert!(sent.is_err(), "leader must not propose a forged request");
+        assert!(replica.instances.lock().is_empty());
+
+        let mut vote = BftVote::new("member1", "alice", VotePhase::Request, 0, 1, 9_000);
+        vote.sign(&signing_keys[1]);
+        replica
+            .process(ConsensusMessage::Request { vote })
+            .await
+            .unwrap();
+        let sent = sub.recv().await.unwrap();
+        let message: ConsensusMessage = serde_json::from_slice(&sent.payload).unwrap();
+        assert!(matches!(message, ConsensusMessage::PrePrepare { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_decided_instances_are_evicted() {
+        let (keys, signing_keys) = committee(4);
+        let gossip = Arc::new(GossipOverlay::new());
+        let mut replicas = Vec::new();
+        for (i, key) in signing_keys.iter().enumerate() {
+            let replica = Arc::new(
+                BftReplica::new(
+                    format!("member{}", i),
+                    key.clone(),
+                    keys.clone(),
+                    Arc::clone(&gossip),
+                )
+                .unwrap(),
+            );
+            let subscription = replica.subscribe().await.unwrap();
+            let runner = Arc::clone(&replica);
+            tokio::spawn(async move { runner.run(subscription).await });
+            replicas.push(replica);
+        }
+        let leader = &replicas[0];
+
+        for (sequence, value) in [(1, 9_000), (2, 8_000)] {
+            leader.request("alice", sequence, value).await.unwrap();
+            let decision = leader
+                .wait_decision("alice", sequence, Duration::from_secs(5))
+                .await;
+            assert_eq!(decision.unwrap().value, value);
+        }
+
+        // Deciding sequence 2 checkpoints alice, evicting sequence 1
+        assert!(leader.decision("alice", 1).is_none());
+        assert_eq!(leader.instances.lock().len(), 1);
+
+        // Late messages for the evicted instance are ignored
+        let mut vote = BftVote::new("member1", "alice", VotePhase::Commit, 0, 1, 9_000);
+        vote.sign(&signing_keys[1]);
+        leader
+            .process(ConsensusMessage::Commit { vote })
+            .await
+            .unwrap();
+        assert_eq!(leader.instances.lock().len(), 1);
+    }
+}
@@ -0,0 +1,794 @@
+//! Transaction disputes with evidence
+//!
+//! A payer who contests a payment opens a [`Dispute`] on it with
+//! [`MutualCreditScheduler::open_dispute`](crate::MutualCreditScheduler::open_dispute).
+//! The transaction is marked disputed, which holds it out of reconciliation,
+//! and the dispute is routed to whoever resolves it:
+//!
+//! - [`DisputeRoute::Committee`]: the BFT committee agrees on the refund in
+//!   a consensus round, and the commit certificate is kept with the outcome
+//! - [`DisputeRoute::GuaranteeGroup`]: members of a guarantee group cast
+//!   signed [`DisputeVote`]s, and a majority of the members who are not a
+//!   party to the dispute decides
+//!
+//! While the dispute is open, anyone can attach [`Evidence`]: references to
+//! documents (by URI and BLAKE3 digest) and [`SignedStatement`]s, signed by
+//! their author's DID key.
+//!
+//! The outcome is applied without rewriting history. The disputed
+//! transaction is confirmed if it had been reconciled, and reversed if not,
+//! and any credits the outcome moves are paid in a new settlement
+//! transaction, recorded in both parties' ledgers as a
+//! [`LedgerEvent::DisputeSettled`](crate::ledger::LedgerEvent::DisputeSettled)
+//! entry:
+//!
+//! ```text
+//! disputed while   outcome       transaction   settlement
+//! confirmed        uphold        confirmed     -
+//! confirmed        reverse       confirmed     payee -> payer, amount
+//! confirmed        partial(r)    confirmed     payee -> payer, r
+//! pending          uphold        reversed      payer -> payee, amount
+//! pending          reverse       reversed      -
+//! pending          partial(r)    reversed      payer -> payee, amount - r
+//! ```
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use automerge::{transaction::Transactable, ROOT};
+use chrono::Utc;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use vudo_identity::Did;
+use vudo_state::{DocumentId, StateEngine};
+
+use crate::bft::QuorumCertificate;
+use crate::committee::{decode_hex, encode_hex};
+use crate::document::load_json;
+use crate::error::{CreditError, Result};
+use crate::guarantee::GuaranteeGroup;
+use crate::transaction::{Transaction, TransactionId, TransactionStatus};
+
+/// State engine namespace of dispute documents
+pub const DISPUTE_NAMESPACE: &str = "credit_dispute";
+
+/// Transaction category of dispute settlements
+pub const DISPUTE_CATEGORY: &str = "dispute";
+
+/// Domain separation tag for signed statements
+const STATEMENT_DOMAIN: &[u8] = b"vudo-credit/dispute-statement/v1";
+
+/// Domain separation tag for guarantee group votes
+const VOTE_DOMAIN: &[u8] = b"vudo-credit/dispute-vote/v1";
+
+/// Consensus subject of a committee ruling on a dispute
+pub fn dispute_subject(dispute_id: &str) -> String {
+    format!("dispute:{}", dispute_id)
+}
+
+/// Who resolves a dispute
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DisputeRoute {
+    /// The BFT committee
+    Committee,
+
+    /// Members of a guarantee group
+    GuaranteeGroup {
+        /// Group ID
+        group_id: String,
+    },
+}
+
+/// Outcome of a dispute
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DisputeOutcome {
+    /// The payer is refunded in full
+    Reverse,
+
+    /// The payment stands
+    Uphold,
+
+    /// The payer is refunded part of the payment
+    Partial {
+        /// Amount refunded (in cents)
+        refund: i64,
+    },
+}
+
+impl DisputeOutcome {
+    /// Get outcome as string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DisputeOutcome::Reverse => "reverse",
+            DisputeOutcome::Uphold => "uphold",
+            DisputeOutcome::Partial { .. } => "partial",
+        }
+    }
+
+    /// Amount refunded to the payer of a disputed payment of `amount`
+    pub fn refund(&self, amount: i64) -> i64 {
+        match *self {
+            DisputeOutcome::Reverse => amount,
+            DisputeOutcome::Uphold => 0,
+            DisputeOutcome::Partial { refund } => refund,
+        }
+    }
+
+    /// Check the outcome applies to a payment of `amount`
+    pub fn validate(&self, amount: i64) -> Result<()> {
+        match *self {
+            DisputeOutcome::Partial { refund } if refund <= 0 || refund >= amount => {
+                Err(CreditError::InvalidDispute(format!(
+                    "Partial refund of {} must be between 0 and {} exclusive",
+                    refund, amount
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Statement signed by its author's DID key
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignedStatement {
+    /// Dispute the statement is about
+    pub dispute_id: String,
+
+    /// Author DID
+    pub author: String,
+
+    /// Statement text
+    pub text: String,
+
+    /// Signing timestamp (Unix epoch seconds)
+    pub signed_at: u64,
+
+    /// Ed25519 signature by the author's DID key
+    pub signature: Vec<u8>,
+}
+
+impl SignedStatement {
+    /// Create and sign a statement
+    pub fn sign(
+        dispute_id: impl Into<String>,
+        author: &Did,
+        key: &SigningKey,
+        text: impl Into<String>,
+        signed_at: u64,
+    ) -> Self {
+        let mut statement = Self {
+            dispute_id: dispute_id.into(),
+            author: author.as_str().to_string(),
+            text: text.into(),
+            signed_at,
+            signature: Vec::new(),
+        };
+        statement.signature = key.sign(&statement.message()).to_bytes().to_vec();
+        statement
+    }
+
+    /// Message digest signed by the author
+    pub fn message(&self) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(STATEMENT_DOMAIN);
+        for part in [&self.dispute_id, &self.author, &self.text] {
+            hasher.update(&(part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        hasher.update(&self.signed_at.to_le_bytes());
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Verify the signature against the author's DID key
+    pub fn verify(&self) -> Result<()> {
+        verify_signature(&self.author, &self.message(), &self.signature)
+            .map_err(|_| CreditError::InvalidDispute("Invalid statement signature".to_string()))
+    }
+}
+
+/// Evidence attached to a dispute
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Evidence {
+    /// Reference to a document held outside the credit system
+    Document {
+        /// Where the document can be fetched
+        uri: String,
+
+        /// BLAKE3 digest of the document (hex)
+        digest: String,
+
+        /// What the document shows
+        description: String,
+    },
+
+    /// Signed statement
+    Statement(SignedStatement),
+}
+
+impl Evidence {
+    /// Reference a document, digesting its content
+    pub fn document(
+        uri: impl Into<String>,
+        content: &[u8],
+        description: impl Into<String>,
+    ) -> Self {
+        Evidence::Document {
+            uri: uri.into(),
+            digest: encode_hex(blake3::hash(content).as_bytes()),
+            description: description.into(),
+        }
+    }
+
+    /// Check the evidence is well-formed and belongs to `dispute_id`
+    ///
+    /// Document content is not fetched: whoever reviews it checks it against
+    /// the digest.
+    pub fn verify(&self, dispute_id: &str) -> Result<()> {
+        match self {
+            Evidence::Document { uri, digest, .. } => {
+                if uri.is_empty() || decode_hex(digest).is_none() {
+                    return Err(CreditError::InvalidDispute(
+                        "Document evidence needs a URI and a BLAKE3 digest".to_string(),
+                    ));
+                }
+                Ok(())
+            }
+            Evidence::Statement(statement) => {
+                if statement.dispute_id != dispute_id {
+                    return Err(CreditError::InvalidDispute(
+                        "Statement is about another dispute".to_string(),
+                    ));
+                }
+                statement.verify()
+            }
+        }
+    }
+
+    /// Check whether the evidence matches a document's content
+    pub fn matches_document(&self, content: &[u8]) -> bool {
+        matches!(self, Evidence::Document { digest, .. }
+            if *digest == encode_hex(blake3::hash(content).as_bytes()))
+    }
+}
+
+/// Guarantee group member's signed vote on a dispute
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DisputeVote {
+    /// Dispute voted on
+    pub dispute_id: String,
+
+    /// Voting member DID
+    pub member: String,
+
+    /// Outcome voted for
+    pub outcome: DisputeOutcome,
+
+    /// Vote timestamp (Unix epoch seconds)
+    pub cast_at: u64,
+
+    /// Ed25519 signature by the member's DID key
+    pub signature: Vec<u8>,
+}
+
+impl DisputeVote {
+    /// Create and sign a vote
+    pub fn sign(
+        dispute_id: impl Into<String>,
+        member: &Did,
+        key: &SigningKey,
+        outcome: DisputeOutcome,
+        cast_at: u64,
+    ) -> Self {
+        let mut vote = Self {
+            dispute_id: dispute_id.into(),
+            member: member.as_str().to_string(),
+            outcome,
+            cast_at,
+            signature: Vec::new(),
+        };
+        vote.signature = key.sign(&vote.message()).to_bytes().to_vec();
+        vote
+    }
+
+    /// Message digest signed by the member
+    pub fn message(&self) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(VOTE_DOMAIN);
+        for part in [&self.dispute_id, &self.member] {
+            hasher.update(&(part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        let refund = match self.outcome {
+            DisputeOutcome::Partial { refund } => refund,
+            _ => 0,
+        };
+        hasher.update(self.outcome.as_str().as_bytes());
+        hasher.update(&refund.to_le_bytes());
+        hasher.update(&self.cast_at.to_le_bytes());
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Verify the signature against the member's DID key
+    pub fn verify(&self) -> Result<()> {
+        verify_signature(&self.member, &self.message(), &self.signature)
+            .map_err(|_| CreditError::InvalidDispute("Invalid vote signature".to_string()))
+    }
+}
+
+/// Dispute lifecycle state
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DisputeState {
+    /// Awaiting an outcome
+    Open,
+
+    /// Outcome applied
+    Resolved,
+}
+
+/// How a dispute was resolved
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DisputeResolution {
+    /// Outcome applied
+    pub outcome: DisputeOutcome,
+
+    /// Committee members or group members who decided the outcome
+    pub decided_by: Vec<String>,
+
+    /// Commit certificate of the committee's ruling (committee route only)
+    pub certificate: Option<QuorumCertificate>,
+
+    /// Settlement transaction (if the outcome moved credits)
+    pub settlement: Option<TransactionId>,
+
+    /// Resolution timestamp (Unix epoch seconds)
+    pub resolved_at: u64,
+}
+
+/// Dispute over a payment
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Dispute {
+    /// Dispute ID
+    pub id: String,
+
+    /// Paying account that opened the dispute
+    pub account_id: String,
+
+    /// Disputed transaction
+    pub transaction_id: TransactionId,
+
+    /// Payee of the disputed transaction
+    pub counterparty: String,
+
+    /// Disputed amount (in cents)
+    pub amount: i64,
+
+    /// Transaction status before the dispute
+    pub disputed_from: TransactionStatus,
+
+    /// Why the payment is disputed
+    pub reason: String,
+
+    /// Who resolves the dispute
+    pub route: DisputeRoute,
+
+    /// Evidence, in the order it was attached
+    pub evidence: Vec<Evidence>,
+
+    /// Guarantee group votes (latest per member)
+    pub votes: Vec<DisputeVote>,
+
+    /// Lifecycle state
+    pub state: DisputeState,
+
+    /// Opening timestamp (Unix epoch seconds)
+    pub opened_at: u64,
+
+    /// Resolution (once resolved)
+    pub resolution: Option<DisputeResolution>,
+}
+
+impl Dispute {
+    /// Create an open dispute over a payment by `account_id`
+    pub fn new(
+        account_id: impl Into<String>,
+        tx: &Transaction,
+        reason: impl Into<String>,
+        route: DisputeRoute,
+    ) -> Result<Self> {
+        let account_id = account_id.into();
+        if tx.from != account_id {
+            return Err(CreditError::InvalidDispute(format!(
+                "Transaction {} was not paid by {}",
+                tx.id, account_id
+            )));
+        }
+
+        Ok(Self {
+            id: Uuid::new_v4().to_string(),
+            account_id,
+            transaction_id: tx.id.clone(),
+            counterparty: tx.to.clone(),
+            amount: tx.amount,
+            disputed_from: tx.status,
+            reason: reason.into(),
+            route,
+            evidence: Vec::new(),
+            votes: Vec::new(),
+            state: DisputeState::Open,
+            opened_at: Utc::now().timestamp() as u64,
+            resolution: None,
+        })
+    }
+
+    /// Check whether the dispute awaits an outcome
+    pub fn is_open(&self) -> bool {
+        self.state == DisputeState::Open
+    }
+
+    /// Check whether an account is the payer or payee
+    pub fn is_party(&self, account_id: &str) -> bool {
+        account_id == self.account_id || account_id == self.counterparty
+    }
+
+    /// Attach evidence
+    pub fn add_evidence(&mut self, evidence: Evidence) -> Result<()> {
+        self.ensure_open()?;
+        evidence.verify(&self.id)?;
+        self.evidence.push(evidence);
+        Ok(())
+    }
+
+    /// Record a guarantee group member's vote
+    ///
+    /// A member voting again replaces their earlier vote. Returns the
+    /// outcome once a majority of the group's members, other than the
+    /// parties, voted for it.
+    pub fn cast_vote(
+        &mut self,
+        vote: DisputeVote,
+        group: &GuaranteeGroup,
+    ) -> Result<Option<DisputeOutcome>> {
+        self.ensure_open()?;
+        match &self.route {
+            DisputeRoute::GuaranteeGroup { group_id } if *group_id == group.group_id => {}
+            _ => {
+                return Err(CreditError::InvalidDispute(format!(
+                    "Dispute {} is not routed to group {}",
+                    self.id, group.group_id
+                )));
+            }
+        }
+        if vote.dispute_id != self.id {
+            return Err(CreditError::InvalidDispute(
+                "Vote is for another dispute".to_string(),
+            ));
+        }
+        if !group.is_member(&vote.member) || self.is_party(&vote.member) {
+            return Err(CreditError::InvalidDispute(format!(
+                "{} cannot vote on dispute {}",
+                vote.member, self.id
+            )));
+        }
+        vote.outcome.validate(self.amount)?;
+        vote.verify()?;
+
+        self.votes.retain(|v| v.member != vote.member);
+        self.votes.push(vote);
+
+        let voters: HashSet<&str> = group
+            .pledges
+            .keys()
+            .map(String::as_str)
+            .filter(|member| !self.is_party(member))
+            .collect();
+        let decided = self.votes.iter().map(|v| v.outcome).find(|outcome| {
+            let support = self
+                .votes
+                .iter()
+                .filter(|v| v.outcome == *outcome && voters.contains(v.member.as_str()))
+                .count();
+            support * 2 > voters.len()
+        });
+        Ok(decided)
+    }
+
+    /// Fail unless the dispute is open
+    pub(crate) fn ensure_open(&self) -> Result<()> {
+        if self.is_open() {
+            Ok(())
+        } else {
+            Err(CreditError::InvalidDispute(format!(
+                "Dispute {} is already resolved",
+                self.id
+            )))
+        }
+    }
+}
+
+/// Disputes stored in the state engine, one document per dispute
+pub struct Disputes {
+    /// Dispute storage
+    state_engine: Arc<StateEngine>,
+}
+
+impl Disputes {
+    /// Create a store over a state engine
+    pub fn new(state_engine: Arc<StateEngine>) -> Self {
+        Self { state_engine }
+    }
+
+    /// Get a dispute by ID
+    pub async fn get(&self, id: &str) -> Result<Option<Dispute>> {
+        let doc_id = DocumentId::new(DISPUTE_NAMESPACE, id);
+        load_json(&self.state_engine, &doc_id).await
+    }
+
+    /// List every dispute, or only those an account is a party to
+    pub async fn list(&self, account_id: Option<&str>) -> Result<Vec<Dispute>> {
+        let mut disputes = Vec::new();
+        for metadata in self.state_engine.list_documents(DISPUTE_NAMESPACE).await? {
+            if let Some(dispute) = self.get(&metadata.id.key).await? {
+                if account_id.map_or(true, |account| dispute.is_party(account)) {
+                    disputes.push(dispute);
+                }
+            }
+        }
+        Ok(disputes)
+    }
+
+    /// Attach evidence to an open dispute
+    pub async fn add_evidence(&self, id: &str, evidence: Evidence) -> Result<Dispute> {
+        let mut dispute = self.load(id).await?;
+        dispute.add_evidence(evidence)?;
+        self.save(&dispute).await?;
+        Ok(dispute)
+    }
+
+    /// Get a dispute, failing if unknown
+    pub(crate) async fn load(&self, id: &str) -> Result<Dispute> {
+        self.get(id)
+            .await?
+            .ok_or_else(|| CreditError::InvalidDispute(format!("Unknown dispute: {}", id)))
+    }
+
+    /// Write a dispute to the state engine
+    pub(crate) async fn save(&self, dispute: &Dispute) -> Result<()> {
+        let doc_id = DocumentId::new(DISPUTE_NAMESPACE, &dispute.id);
+        let handle = match self.state_engine.get_document(&doc_id).await {
+            Ok(handle) => handle,
+            Err(_) => self.state_engine.create_document(doc_id).await?,
+        };
+
+        let json = serde_json::to_string(dispute)?;
+        let account_id = dispute.account_id.clone();
+        let transaction_id = dispute.transaction_id.clone();
+        let open = dispute.is_open();
+        handle.update(|tx| {
+            tx.put(ROOT, "account_id", account_id)?;
+            tx.put(ROOT, "transaction_id", transaction_id)?;
+            tx.put(ROOT, "open", open)?;
+            tx.put(ROOT, "data_json", json)?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+}
+
+/// Verify an Ed25519 signature by a DID's key
+fn verify_signature(did: &str, message: &[u8; 32], signature: &[u8]) -> Result<()> {
+    let did = Did::parse(did)?;
+    let bytes = <[u8; 64]>::try_from(signature)
+        .map_err(|_| CreditError::InvalidDispute("Malformed signature".to_string()))?;
+    did.verification_key
+        .verify(message, &Signature::from_bytes(&bytes))
+        .map_err(|_| CreditError::InvalidDispute("Invalid signature".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::TransactionMetadata;
+
+    fn identity(seed: u8) -> (Did, SigningKey) {
+        let key = SigningKey::from_bytes(&[seed; 32]);
+        (Did::from_key(key.verifying_key()), key)
+    }
+
+    fn payment(amount: i64) -> Transaction {
+        Transaction::new(
+            "alice".to_string(),
+            "shop".to_string(),
+            amount,
+            TransactionMetadata::default(),
+        )
+    }
+
+    fn group(members: &[&Did]) -> GuaranteeGroup {
+        let mut group = GuaranteeGroup::new("neighbours");
+        for member in members {
+            group.add_pledge(member.as_str(), 1_000, 0).unwrap();
+        }
+        group
+    }
+
+    #[test]
+    fn test_only_payer_opens() {
+        let tx = payment(5_000);
+        assert!(Dispute::new("alice", &tx, "Not delivered", DisputeRoute::Committee).is_ok());
+        assert!(matches!(
+            Dispute::new("shop", &tx, "Not delivered", DisputeRoute::Committee),
+            Err(CreditError::InvalidDispute(_))
+        ));
+    }
+
+    #[test]
+    fn test_outcome_refunds() {
+        assert_eq!(DisputeOutcome::Reverse.refund(5_000), 5_000);
+        assert_eq!(DisputeOutcome::Uphold.refund(5_000), 0);
+        assert_eq!(
+            DisputeOutcome::Partial { refund: 1_500 }.refund(5_000),
+            1_500
+        );
+
+        assert!(DisputeOutcome::Partial { refund: 1_500 }
+            .validate(5_000)
+            .is_ok());
+        assert!(DisputeOutcome::Partial { refund: 5_000 }
+            .validate(5_000)
+            .is_err());
+        assert!(DisputeOutcome::Partial { refund: 0 }
+            .validate(5_000)
+            .is_err());
+    }
+
+    #[test]
+    fn test_evidence() {
+        let mut dispute =
+            Dispute::new("alice", &payment(5_000), "Broken", DisputeRoute::Committee).unwrap();
+        let (author, key) = identity(1);
+
+        let photo = b"photo of a broken kettle";
+        let document = Evidence::document("ipfs://kettle.jpg", photo, "Photo on delivery");
+        assert!(document.matches_document(photo));
+        assert!(!document.matches_document(b"another photo"));
+        dispute.add_evidence(document).unwrap();
+
+        let statement = SignedStatement::sign(&dispute.id, &author, &key, "It arrived broken", 10);
+        dispute
+            .add_evidence(Evidence::Statement(statement.clone()))
+            .unwrap();
+        assert_eq!(dispute.evidence.len(), 2);
+
+        // Tampered statements and statements about other disputes are rejected
+        let mut tampered = statement.clone();
+        tampered.text = "It arrived fine".to_string();
+        assert!(dispute.add_evidence(Evidence::Statement(tampered)).is_err());
+        let other = SignedStatement::sign("other", &author, &key, "It arrived broken", 10);
+        assert!(dispute.add_evidence(Evidence::Statement(other)).is_err());
+
+        let bad_digest = Evidence::Document {
+            uri: "ipfs://kettle.jpg".to_string(),
+            digest: "abc".to_string(),
+            description: String::new(),
+        };
+        assert!(dispute.add_evidence(bad_digest).is_err());
+
+        // Nothing can be attached once resolved
+        dispute.state = DisputeState::Resolved;
+        assert!(dispute
+            .add_evidence(Evidence::Statement(statement))
+            .is_err());
+    }
+
+    #[test]
+    fn test_group_majority_decides() {
+        let members: Vec<(Did, SigningKey)> = (1..=4).map(identity).collect();
+        let dids: Vec<&Did> = members.iter().map(|(did, _)| did).collect();
+        let group = group(&dids);
+
+        let route = DisputeRoute::GuaranteeGroup {
+            group_id: group.group_id.clone(),
+        };
+        let mut dispute = Dispute::new("alice", &payment(5_000), "Broken", route).unwrap();
+        let vote = |i: usize, outcome| {
+            let (did, key) = &members[i];
+            DisputeVote::sign(&dispute.id, did, key, outcome, 20)
+        };
+        let partial = DisputeOutcome::Partial { refund: 2_000 };
+
+        // 2 of 4 is not a majority, and changing a vote replaces it
+        let (v0, v1, v1_again, v2) = (
+            vote(0, partial),
+            vote(1, DisputeOutcome::Uphold),
+            vote(1, partial),
+            vote(2, partial),
+        );
+        assert_eq!(dispute.cast_vote(v0, &group).unwrap(), None);
+        assert_eq!(dispute.cast_vote(v1, &group).unwrap(), None);
+        assert_eq!(dispute.cast_vote(v1_again, &group).unwrap(), None);
+        assert_eq!(dispute.votes.len(), 2);
+        assert_eq!(dispute.cast_vote(v2, &group).unwrap(), Some(partial));
+    }
+
+    #[test]
+    fn test_group_vote_rejected() {
+        let (member, member_key) = identity(1);
+        let (outsider, outsider_key) = identity(2);
+        let group = group(&[&member]);
+        let route = DisputeRoute::GuaranteeGroup {
+            group_id: group.group_id.clone(),
+        };
+        let mut dispute = Dispute::new("alice", &payment(5_000), "Broken", route).unwrap();
+
+        // Not a member
+        let vote = DisputeVote::sign(
+            &dispute.id,
+            &outsider,
+            &outsider_key,
+            DisputeOutcome::Reverse,
+            1,
+        );
+        assert!(dispute.cast_vote(vote, &group).is_err());
+
+        // Signed by another key
+        let vote = DisputeVote::sign(
+            &dispute.id,
+            &member,
+            &outsider_key,
+            DisputeOutcome::Reverse,
+            1,
+        );
+        assert!(dispute.cast_vote(vote, &group).is_err());
+
+        // Routed to another group
+        let vote = DisputeVote::sign(
+            &dispute.id,
+            &member,
+            &member_key,
+            DisputeOutcome::Reverse,
+            1,
+        );
+        assert!(dispute
+            .cast_vote(vote.clone(), &GuaranteeGroup::new("other"))
+            .is_err());
+
+        // A member who is a party cannot vote
+        let tx = Transaction::new(
+            member.as_str().to_string(),
+            "shop".to_string(),
+            5_000,
+            TransactionMetadata::default(),
+        );
+        let route = DisputeRoute::GuaranteeGroup {
+            group_id: group.group_id.clone(),
+        };
+        let mut own = Dispute::new(member.as_str(), &tx, "Broken", route).unwrap();
+        let vote = DisputeVote::sign(&own.id, &member, &member_key, DisputeOutcome::Reverse, 1);
+        assert!(own.cast_vote(vote, &group).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_store() {
+        let store = Disputes::new(Arc::new(StateEngine::new().await.unwrap()));
+        let dispute =
+            Dispute::new("alice", &payment(5_000), "Broken", DisputeRoute::Committee).unwrap();
+        store.save(&dispute).await.unwrap();
+
+        assert_eq!(store.get(&dispute.id).await.unwrap(), Some(dispute.clone()));
+        assert_eq!(store.list(Some("shop")).await.unwrap().len(), 1);
+        assert!(store.list(Some("bob")).await.unwrap().is_empty());
+        assert!(store.get("missing").await.unwrap().is_none());
+
+        let evidence = Evidence::document("https://shop/receipt", b"receipt", "Receipt");
+        let updated = store.add_evidence(&dispute.id, evidence).await.unwrap();
+        assert_eq!(
+            store.get(&dispute.id).await.unwrap().unwrap().evidence,
+            updated.evidence
+        );
+    }
+}
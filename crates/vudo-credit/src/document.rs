@@ -0,0 +1,36 @@
+//! JSON records stored in state engine documents
+//!
+//! Committees, disputes, invoices and the crate's other records are stored
+//! as a JSON string under the `data_json` key of their document.
+
+use automerge::{ReadDoc, ROOT};
+use serde::de::DeserializeOwned;
+use vudo_state::{DocumentId, StateEngine, StateError};
+
+use crate::error::Result;
+
+/// Load the JSON record of a document, or `None` if the document does not
+/// exist
+pub(crate) async fn load_json<T: DeserializeOwned>(
+    state_engine: &StateEngine,
+    doc_id: &DocumentId,
+) -> Result<Option<T>> {
+    let handle = match state_engine.get_document(doc_id).await {
+        Ok(handle) => handle,
+        Err(StateError::DocumentNotFound(_)) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let record = handle.read(|doc| match doc.get(ROOT, "data_json")? {
+        Some((automerge::Value::Scalar(s), _)) => match s.as_ref() {
+            automerge::ScalarValue::Str(json) => serde_json::from_str(json)
+                .map_err(|e| StateError::DeserializationError(e.to_string())),
+            _ => Err(StateError::Internal(format!(
+                "Data of {} is not a string",
+                doc_id
+            ))),
+        },
+        _ => Err(StateError::Internal(format!("Data of {} missing", doc_id))),
+    })?;
+    Ok(Some(record))
+}
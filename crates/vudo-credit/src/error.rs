@@ -26,6 +26,10 @@ pub enum CreditError {
         quorum_required: usize,
     },
 
+    /// Not enough eligible candidates to form a committee
+    #[error("Insufficient committee candidates: {available} eligible, {required} required")]
+    InsufficientCandidates { available: usize, required: usize },
+
     /// BFT escrow grant failed
     #[error("BFT escrow grant failed to reach consensus")]
     BftEscrowGrantFailed,
@@ -34,6 +38,17 @@ pub enum CreditError {
     #[error("Insufficient balance for escrow allocation")]
     InsufficientBalanceForEscrow,
 
+    /// Escrow refresh frozen by a fraud alert pending committee review
+    #[error("Escrow refresh frozen for {account_id} pending review of alert {alert_id}")]
+    EscrowRefreshFrozen {
+        account_id: String,
+        alert_id: String,
+    },
+
+    /// Insufficient unpledged balance for a guarantee pledge
+    #[error("Insufficient balance for pledge: available {available}, requested {requested}")]
+    InsufficientBalanceForPledge { available: i64, requested: i64 },
+
     /// Account not found
     #[error("Account not found: {0}")]
     AccountNotFound(String),
@@ -70,6 +85,26 @@ pub enum CreditError {
     #[error("Serialization error: {0}")]
     Serialization(String),
 
+    /// Balance proof failed verification
+    #[error("Invalid balance proof: {0}")]
+    InvalidProof(String),
+
+    /// Quorum certificate failed verification
+    #[error("Invalid quorum certificate: {0}")]
+    InvalidCertificate(String),
+
+    /// Payment request or acceptance failed verification
+    #[error("Invalid payment request: {0}")]
+    InvalidPaymentRequest(String),
+
+    /// Dispute, evidence or vote rejected
+    #[error("Invalid dispute: {0}")]
+    InvalidDispute(String),
+
+    /// Atomic swap failure
+    #[error("Atomic swap failed: {0}")]
+    SwapFailed(String),
+
     /// Invalid operation
     #[error("Invalid operation: {0}")]
     InvalidOperation(String),
@@ -0,0 +1,630 @@
+//! Transaction export for accounting tools (OFX/CSV/JSON)
+//!
+//! Exports an account's transaction history in formats that conventional
+//! bookkeeping software can import. A user-editable [`CategoryRules`] set maps
+//! counterparties and memos to accounting categories so mutual-credit activity
+//! lands in the right ledger accounts.
+//!
+//! Amounts are signed from the exporting account's point of view: debits
+//! (payments sent) are negative, credits (payments received) are positive.
+
+use std::fmt::Write;
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::account::CreditAccount;
+use crate::error::{CreditError, Result};
+use crate::transaction::{Transaction, TransactionStatus};
+
+/// Category used when no rule or transaction category applies
+pub const DEFAULT_CATEGORY: &str = "Uncategorized";
+
+/// Export file format
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Comma-separated values (RFC 4180)
+    Csv,
+
+    /// Open Financial Exchange (SGML, version 1.02)
+    Ofx,
+
+    /// JSON array of rows, amounts in signed cents
+    Json,
+}
+
+/// Rule mapping matching transactions to an accounting category
+///
+/// All conditions that are set must match. A rule with no conditions matches
+/// every transaction.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CategoryRule {
+    /// Counterparty DID to match exactly
+    pub counterparty: Option<String>,
+
+    /// Case-insensitive substring to find in the transaction description
+    pub memo_contains: Option<String>,
+
+    /// Only match debits (`Some(true)`) or credits (`Some(false)`)
+    pub debit: Option<bool>,
+
+    /// Category assigned to matching transactions
+    pub category: String,
+}
+
+impl CategoryRule {
+    /// Create a rule that assigns `category` to every transaction
+    pub fn new(category: impl Into<String>) -> Self {
+        Self {
+            counterparty: None,
+            memo_contains: None,
+            debit: None,
+            category: category.into(),
+        }
+    }
+
+    /// Restrict the rule to a counterparty
+    pub fn with_counterparty(mut self, counterparty: impl Into<String>) -> Self {
+        self.counterparty = Some(counterparty.into());
+        self
+    }
+
+    /// Restrict the rule to descriptions containing `text`
+    pub fn with_memo_contains(mut self, text: impl Into<String>) -> Self {
+        self.memo_contains = Some(text.into());
+        self
+    }
+
+    /// Restrict the rule to debits or credits
+    pub fn with_debit(mut self, debit: bool) -> Self {
+        self.debit = Some(debit);
+        self
+    }
+
+    /// Check whether the rule matches a transaction of `account_id`
+    pub fn matches(&self, account_id: &str, tx: &Transaction) -> bool {
+        let is_debit = tx.is_from(account_id);
+
+        if let Some(debit) = self.debit {
+            if debit != is_debit {
+                return false;
+            }
+        }
+
+        if let Some(counterparty) = &self.counterparty {
+            let other = if is_debit { &tx.to } else { &tx.from };
+            if other != counterparty {
+                return false;
+            }
+        }
+
+        if let Some(text) = &self.memo_contains {
+            if !tx
+                .metadata
+                .description
+                .to_lowercase()
+                .contains(&text.to_lowercase())
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Ordered, user-editable set of category rules
+///
+/// Rules are evaluated in order and the first match wins. Transactions that
+/// match no rule keep their own category, or fall back to the default.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CategoryRules {
+    /// Rules in evaluation order
+    pub rules: Vec<CategoryRule>,
+
+    /// Fallback category
+    pub default_category: String,
+}
+
+impl Default for CategoryRules {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            default_category: DEFAULT_CATEGORY.to_string(),
+        }
+    }
+}
+
+impl CategoryRules {
+    /// Create an empty rule set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a rule (lowest priority)
+    pub fn push(&mut self, rule: CategoryRule) {
+        self.rules.push(rule);
+    }
+
+    /// Insert a rule at `index` (0 is highest priority)
+    pub fn insert(&mut self, index: usize, rule: CategoryRule) {
+        let index = index.min(self.rules.len());
+        self.rules.insert(index, rule);
+    }
+
+    /// Remove the rule at `index`
+    pub fn remove(&mut self, index: usize) -> Option<CategoryRule> {
+        if index < self.rules.len() {
+            Some(self.rules.remove(index))
+        } else {
+            None
+        }
+    }
+
+    /// Resolve the category of a transaction of `account_id`
+    pub fn categorize(&self, account_id: &str, tx: &Transaction) -> String {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(account_id, tx))
+            .map(|rule| rule.category.clone())
+            .or_else(|| tx.metadata.category.clone())
+            .unwrap_or_else(|| self.default_category.clone())
+    }
+
+    /// Load rules from JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Serialize rules to JSON for editing
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Transaction exporter for a single account
+#[derive(Debug, Clone)]
+pub struct TransactionExporter {
+    /// Account whose history is exported
+    account_id: String,
+
+    /// Category rules
+    rules: CategoryRules,
+
+    /// ISO 4217 currency code written to OFX files
+    currency: String,
+
+    /// Include reversed transactions
+    include_reversed: bool,
+}
+
+impl TransactionExporter {
+    /// Create an exporter for `account_id`
+    pub fn new(account_id: impl Into<String>, rules: CategoryRules) -> Self {
+        Self {
+            account_id: account_id.into(),
+            rules,
+            // ISO 4217 code for "no currency"
+            currency: "XXX".to_string(),
+            include_reversed: false,
+        }
+    }
+
+    /// Set the currency code written to OFX files
+    pub fn with_currency(mut self, currency: impl Into<String>) -> Self {
+        self.currency = currency.into();
+        self
+    }
+
+    /// Include reversed transactions in the export
+    pub fn with_reversed(mut self, include: bool) -> Self {
+        self.include_reversed = include;
+        self
+    }
+
+    /// Get the category rules
+    pub fn rules(&self) -> &CategoryRules {
+        &self.rules
+    }
+
+    /// Get mutable category rules for editing
+    pub fn rules_mut(&mut self) -> &mut CategoryRules {
+        &mut self.rules
+    }
+
+    /// Export an account in the given format
+    pub fn export_account(&self, account: &CreditAccount, format: ExportFormat) -> Result<String> {
+        if account.owner != self.account_id {
+            return Err(CreditError::InvalidOperation(format!(
+                "Exporter is for account {}, not {}",
+                self.account_id, account.owner
+            )));
+        }
+
+        match format {
+            ExportFormat::Csv => self.to_csv(&account.transactions),
+            ExportFormat::Ofx => {
+                self.to_ofx(&account.transactions, Some(account.confirmed_balance))
+            }
+            ExportFormat::Json => self.to_json(&account.transactions),
+        }
+    }
+
+    /// Export transactions as CSV
+    ///
+    /// Columns: date, id, counterparty, description, category, amount, status
+    pub fn to_csv(&self, transactions: &[Transaction]) -> Result<String> {
+        let mut out = format!("{}\r\n", ExportRow::CSV_HEADER);
+
+        for tx in self.relevant(transactions) {
+            out.push_str(&self.row(tx)?.csv_fields().join(","));
+            out.push_str("\r\n");
+        }
+
+        Ok(out)
+    }
+
+    /// Export transactions as a JSON array
+    ///
+    /// Rows have the CSV columns, with amounts in signed cents.
+    pub fn to_json(&self, transactions: &[Transaction]) -> Result<String> {
+        let rows = self
+            .relevant(transactions)
+            .into_iter()
+            .map(|tx| self.row(tx))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(serde_json::to_string_pretty(&rows)?)
+    }
+
+    /// Build the export row of a transaction
+    pub(crate) fn row(&self, tx: &Transaction) -> Result<ExportRow> {
+        Ok(ExportRow {
+            date: format_date(tx.timestamp)?.format("%Y-%m-%d").to_string(),
+            id: tx.id.clone(),
+            counterparty: self.counterparty(tx).to_string(),
+            description: tx.metadata.description.clone(),
+            category: self.rules.categorize(&self.account_id, tx),
+            amount: self.signed_amount(tx),
+            status: tx.status.as_str().to_string(),
+        })
+    }
+
+    /// Export transactions as an OFX 1.02 bank statement
+    ///
+    /// The accounting category is appended to each memo, since OFX has no
+    /// dedicated category field.
+    pub fn to_ofx(
+        &self,
+        transactions: &[Transaction],
+        ledger_balance: Option<i64>,
+    ) -> Result<String> {
+        let txs = self.relevant(transactions);
+        let now = Utc::now();
+        let start = txs.iter().map(|tx| tx.timestamp).min();
+        let end = txs.iter().map(|tx| tx.timestamp).max();
+
+        let mut out = String::new();
+        let w = &mut out;
+        // Writing to a String cannot fail
+        let _ = write!(
+            w,
+            "OFXHEADER:100\r\nDATA:OFXSGML\r\nVERSION:102\r\nSECURITY:NONE\r\n\
+             ENCODING:USASCII\r\nCHARSET:1252\r\nCOMPRESSION:NONE\r\n\
+             OLDFILEUID:NONE\r\nNEWFILEUID:NONE\r\n\r\n"
+        );
+        let _ = write!(
+            w,
+            "<OFX>\r\n<SIGNONMSGSRSV1>\r\n<SONRS>\r\n<STATUS>\r\n<CODE>0\r\n<SEVERITY>INFO\r\n\
+             </STATUS>\r\n<DTSERVER>{}\r\n<LANGUAGE>ENG\r\n</SONRS>\r\n</SIGNONMSGSRSV1>\r\n",
+            ofx_datetime(now)
+        );
+        let _ = write!(
+            w,
+            "<BANKMSGSRSV1>\r\n<STMTTRNRS>\r\n<TRNUID>0\r\n<STATUS>\r\n<CODE>0\r\n\
+             <SEVERITY>INFO\r\n</STATUS>\r\n<STMTRS>\r\n<CURDEF>{}\r\n<BANKACCTFROM>\r\n\
+             <BANKID>VUDO\r\n<ACCTID>{}\r\n<ACCTTYPE>CHECKING\r\n</BANKACCTFROM>\r\n",
+            ofx_escape(&self.currency),
+            ofx_escape(&self.account_id)
+        );
+
+        let _ = write!(w, "<BANKTRANLIST>\r\n");
+        if let (Some(start), Some(end)) = (start, end) {
+            let _ = write!(
+                w,
+                "<DTSTART>{}\r\n<DTEND>{}\r\n",
+                ofx_datetime(format_date(start)?),
+                ofx_datetime(format_date(end)?)
+            );
+        }
+        for tx in &txs {
+            let amount = self.signed_amount(tx);
+            let memo = format!(
+                "{} [{}]",
+                tx.metadata.description,
+                self.rules.categorize(&self.account_id, tx)
+            );
+            let _ = write!(
+                w,
+                "<STMTTRN>\r\n<TRNTYPE>{}\r\n<DTPOSTED>{}\r\n<TRNAMT>{}\r\n<FITID>{}\r\n\
+                 <NAME>{}\r\n<MEMO>{}\r\n</STMTTRN>\r\n",
+                if amount < 0 { "DEBIT" } else { "CREDIT" },
+                ofx_datetime(format_date(tx.timestamp)?),
+                format_amount(amount),
+                ofx_escape(&tx.id),
+                // OFX 1.02 limits NAME to 32 characters
+                ofx_escape(&self.counterparty(tx).chars().take(32).collect::<String>()),
+                ofx_escape(&memo)
+            );
+        }
+        let _ = write!(w, "</BANKTRANLIST>\r\n");
+
+        if let Some(balance) = ledger_balance {
+            let _ = write!(
+                w,
+                "<LEDGERBAL>\r\n<BALAMT>{}\r\n<DTASOF>{}\r\n</LEDGERBAL>\r\n",
+                format_amount(balance),
+                ofx_datetime(now)
+            );
+        }
+
+        let _ = write!(
+            w,
+            "</STMTRS>\r\n</STMTTRNRS>\r\n</BANKMSGSRSV1>\r\n</OFX>\r\n"
+        );
+        Ok(out)
+    }
+
+    /// Transactions involving this account, oldest first
+    fn relevant<'a>(&self, transactions: &'a [Transaction]) -> Vec<&'a Transaction> {
+        let mut txs: Vec<&Transaction> = transactions
+            .iter()
+            .filter(|tx| tx.is_from(&self.account_id) || tx.is_to(&self.account_id))
+            .filter(|tx| self.include_reversed || tx.status != TransactionStatus::Reversed)
+            .collect();
+        txs.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)));
+        txs
+    }
+
+    fn signed_amount(&self, tx: &Transaction) -> i64 {
+        if tx.is_from(&self.account_id) {
+            -tx.amount
+        } else {
+            tx.amount
+        }
+    }
+
+    fn counterparty<'a>(&self, tx: &'a Transaction) -> &'a str {
+        if tx.is_from(&self.account_id) {
+            &tx.to
+        } else {
+            &tx.from
+        }
+    }
+}
+
+/// Exported view of a transaction, from the exporting account's side
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub(crate) struct ExportRow {
+    /// Transaction date (YYYY-MM-DD, UTC)
+    pub date: String,
+
+    /// Transaction ID
+    pub id: String,
+
+    /// Other party of the transaction
+    pub counterparty: String,
+
+    /// Transaction description
+    pub description: String,
+
+    /// Accounting category
+    pub category: String,
+
+    /// Signed amount in cents
+    pub amount: i64,
+
+    /// Transaction status
+    pub status: String,
+}
+
+impl ExportRow {
+    /// CSV header of the row columns
+    pub(crate) const CSV_HEADER: &'static str =
+        "date,id,counterparty,description,category,amount,status";
+
+    /// Escaped CSV fields, in header order
+    pub(crate) fn csv_fields(&self) -> Vec<String> {
+        [
+            self.date.clone(),
+            self.id.clone(),
+            self.counterparty.clone(),
+            self.description.clone(),
+            self.category.clone(),
+            format_amount(self.amount),
+            self.status.clone(),
+        ]
+        .iter()
+        .map(|f| csv_escape(f))
+        .collect()
+    }
+}
+
+/// Format cents as a decimal amount (e.g., -1050 -> "-10.50")
+fn format_amount(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let abs = cents.unsigned_abs();
+    format!("{}{}.{:02}", sign, abs / 100, abs % 100)
+}
+
+pub(crate) fn format_date(timestamp: u64) -> Result<DateTime<Utc>> {
+    Utc.timestamp_opt(timestamp as i64, 0)
+        .single()
+        .ok_or_else(|| CreditError::Serialization(format!("Invalid timestamp: {}", timestamp)))
+}
+
+fn ofx_datetime(dt: DateTime<Utc>) -> String {
+    dt.format("%Y%m%d%H%M%S").to_string()
+}
+
+pub(crate) fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn ofx_escape(field: &str) -> String {
+    field
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::TransactionMetadata;
+
+    fn tx(from: &str, to: &str, amount: i64, description: &str, timestamp: u64) -> Transaction {
+        let mut tx = Transaction::new(
+            from.to_string(),
+            to.to_string(),
+            amount,
+            TransactionMetadata {
+                description: description.to_string(),
+                category: None,
+                invoice_id: None,
+            },
+        );
+        tx.timestamp = timestamp;
+        tx
+    }
+
+    fn history() -> Vec<Transaction> {
+        vec![
+            tx("alice", "cafe", 450, "Coffee, large", 1_700_000_000),
+            tx("bob", "alice", 2000, "Rent share", 1_700_000_100),
+            tx("alice", "market", 1250, "Weekly groceries", 1_700_000_200),
+            tx("carol", "dave", 999, "Unrelated", 1_700_000_300),
+        ]
+    }
+
+    fn rules() -> CategoryRules {
+        let mut rules = CategoryRules::new();
+        rules.push(CategoryRule::new("Expenses:Food").with_counterparty("cafe"));
+        rules.push(CategoryRule::new("Expenses:Groceries").with_memo_contains("GROCERIES"));
+        rules.push(CategoryRule::new("Income:Other").with_debit(false));
+        rules
+    }
+
+    #[test]
+    fn test_category_rules() {
+        let rules = rules();
+        let txs = history();
+
+        assert_eq!(rules.categorize("alice", &txs[0]), "Expenses:Food");
+        assert_eq!(rules.categorize("alice", &txs[1]), "Income:Other");
+        assert_eq!(rules.categorize("alice", &txs[2]), "Expenses:Groceries");
+
+        // Falls back to transaction category, then the default
+        let mut other = tx("alice", "zoo", 100, "Tickets", 1);
+        assert_eq!(rules.categorize("alice", &other), DEFAULT_CATEGORY);
+        other.metadata.category = Some("Entertainment".to_string());
+        assert_eq!(rules.categorize("alice", &other), "Entertainment");
+
+        // First match wins
+        let mut rules = rules;
+        rules.insert(0, CategoryRule::new("Override"));
+        assert_eq!(rules.categorize("alice", &txs[0]), "Override");
+        assert_eq!(rules.remove(0).unwrap().category, "Override");
+        assert!(rules.remove(10).is_none());
+    }
+
+    #[test]
+    fn test_category_rules_json_roundtrip() {
+        let rules = rules();
+        let json = rules.to_json().unwrap();
+        assert_eq!(CategoryRules::from_json(&json).unwrap(), rules);
+    }
+
+    #[test]
+    fn test_csv_export() {
+        let exporter = TransactionExporter::new("alice", rules());
+        let csv = exporter.to_csv(&history()).unwrap();
+        let lines: Vec<&str> = csv.split("\r\n").filter(|l| !l.is_empty()).collect();
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[0],
+            "date,id,counterparty,description,category,amount,status"
+        );
+        assert!(lines[1].starts_with("2023-11-14,"));
+        assert!(lines[1].contains(",cafe,\"Coffee, large\",Expenses:Food,-4.50,pending"));
+        assert!(lines[2].contains(",bob,Rent share,Income:Other,20.00,pending"));
+        assert!(lines[3].contains(",market,Weekly groceries,Expenses:Groceries,-12.50,"));
+    }
+
+    #[test]
+    fn test_json_export() {
+        let exporter = TransactionExporter::new("alice", rules());
+        let json = exporter.to_json(&history()).unwrap();
+        let rows: Vec<ExportRow> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].counterparty, "cafe");
+        assert_eq!(rows[0].amount, -450);
+        assert_eq!(rows[1].category, "Income:Other");
+        assert_eq!(rows[1].amount, 2000);
+    }
+
+    #[test]
+    fn test_ofx_export() {
+        let mut account = CreditAccount::new("alice".to_string(), 10_000);
+        for tx in history() {
+            account.add_transaction(tx);
+        }
+        let mut reversed = tx("alice", "cafe", 100, "Refunded", 1_700_000_050);
+        reversed.status = TransactionStatus::Reversed;
+        account.add_transaction(reversed);
+
+        let exporter = TransactionExporter::new("alice", rules()).with_currency("USD");
+        let ofx = exporter
+            .export_account(&account, ExportFormat::Ofx)
+            .unwrap();
+
+        assert!(ofx.starts_with("OFXHEADER:100"));
+        assert!(ofx.contains("<CURDEF>USD"));
+        assert!(ofx.contains("<ACCTID>alice"));
+        assert_eq!(ofx.matches("<STMTTRN>").count(), 3);
+        assert!(ofx.contains("<TRNTYPE>DEBIT\r\n<DTPOSTED>20231114221320\r\n<TRNAMT>-4.50"));
+        assert!(ofx.contains("<TRNTYPE>CREDIT"));
+        assert!(ofx.contains("<MEMO>Weekly groceries [Expenses:Groceries]"));
+        assert!(ofx.contains("<BALAMT>100.00"));
+        assert!(!ofx.contains("Refunded"));
+
+        let with_reversed = exporter.with_reversed(true);
+        let ofx = with_reversed
+            .export_account(&account, ExportFormat::Ofx)
+            .unwrap();
+        assert_eq!(ofx.matches("<STMTTRN>").count(), 4);
+    }
+
+    #[test]
+    fn test_export_rejects_other_account() {
+        let account = CreditAccount::new("bob".to_string(), 0);
+        let exporter = TransactionExporter::new("alice", CategoryRules::new());
+        assert!(exporter
+            .export_account(&account, ExportFormat::Csv)
+            .is_err());
+    }
+
+    #[test]
+    fn test_format_amount() {
+        assert_eq!(format_amount(0), "0.00");
+        assert_eq!(format_amount(5), "0.05");
+        assert_eq!(format_amount(-1050), "-10.50");
+        assert_eq!(format_amount(123456), "1234.56");
+    }
+}
@@ -0,0 +1,590 @@
+//! Mutual guarantee groups
+//!
+//! Members of a [`GuaranteeGroup`] pledge part of their confirmed balance to
+//! back each other's overdrafts. A pledge is locked on the member's account
+//! (see [`CreditAccount::pledged`](crate::account::CreditAccount::pledged)),
+//! so it cannot also be allocated to device escrow.
+//!
+//! When an overdraft cannot be recovered, it is resolved with
+//! [`OverdraftResolution::Guarantee`](crate::overdraft::OverdraftResolution::Guarantee)
+//! and the deficit is covered from the group's pledges:
+//!
+//! 1. The deficit is split across the other members pro rata to their
+//!    remaining pledges (largest remainder, so shares sum exactly)
+//! 2. Each share becomes a pending transaction from the guarantor to the
+//!    defaulting account, confirmed at the next BFT reconciliation
+//!
+//! The defaulting member's own pledge is never drawn on: it is part of the
+//! balance that was overdrawn. Any deficit beyond the other members'
+//! remaining pledges stays uncovered.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use automerge::{transaction::Transactable, ROOT};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use vudo_state::{DocumentId, StateEngine};
+
+use crate::account::CreditAccountHandle;
+use crate::document::load_json;
+use crate::error::{CreditError, Result};
+use crate::overdraft::Overdraft;
+use crate::transaction::{Transaction, TransactionId, TransactionMetadata};
+
+/// State engine namespace of guarantee group documents
+pub const GUARANTEE_NAMESPACE: &str = "credit_guarantee";
+
+/// Transaction category of loss shares paid by guarantors
+pub const GUARANTEE_CATEGORY: &str = "guarantee";
+
+/// A member's pledge to a group
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Pledge {
+    /// Member account ID
+    pub member: String,
+
+    /// Total amount pledged (in cents)
+    pub amount: i64,
+
+    /// Amount already paid out to cover losses (in cents)
+    pub absorbed: i64,
+
+    /// First pledge timestamp (Unix epoch seconds)
+    pub pledged_at: u64,
+}
+
+impl Pledge {
+    /// Amount still available to cover losses
+    pub fn available(&self) -> i64 {
+        self.amount - self.absorbed
+    }
+}
+
+/// A member's share of a covered loss
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LossShare {
+    /// Member account ID
+    pub member: String,
+
+    /// Amount paid (in cents)
+    pub amount: i64,
+
+    /// Transaction paying the share to the defaulting account
+    pub transaction_id: Option<TransactionId>,
+}
+
+/// A loss covered by a group
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GuaranteeClaim {
+    /// Claim ID
+    pub claim_id: String,
+
+    /// Defaulting account ID
+    pub account_id: String,
+
+    /// Overdrawn transaction
+    pub transaction_id: TransactionId,
+
+    /// Deficit to cover (in cents)
+    pub deficit: i64,
+
+    /// Amount covered by pledges (in cents)
+    pub covered: i64,
+
+    /// Per-member shares of the covered amount
+    pub shares: Vec<LossShare>,
+
+    /// Claim timestamp (Unix epoch seconds)
+    pub claimed_at: u64,
+}
+
+impl GuaranteeClaim {
+    /// Deficit left uncovered
+    pub fn uncovered(&self) -> i64 {
+        self.deficit - self.covered
+    }
+
+    /// Get a member's share, if any
+    pub fn share(&self, member: &str) -> Option<&LossShare> {
+        self.shares.iter().find(|s| s.member == member)
+    }
+}
+
+/// Health metrics of a group
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GroupHealth {
+    /// Number of members
+    pub members: usize,
+
+    /// Total amount pledged (in cents)
+    pub total_pledged: i64,
+
+    /// Total amount paid out to cover losses (in cents)
+    pub total_absorbed: i64,
+
+    /// Pledges still available (in cents)
+    pub available: i64,
+
+    /// Number of claims
+    pub claims: usize,
+
+    /// Deficits left uncovered across claims (in cents)
+    pub uncovered: i64,
+
+    /// Share of pledges still available (1.0 = untouched)
+    pub coverage_ratio: f64,
+
+    /// Share of pledges paid out to losses
+    pub loss_ratio: f64,
+
+    /// Largest member's share of available pledges (concentration risk)
+    pub concentration: f64,
+}
+
+impl GroupHealth {
+    /// Check whether the group could fully cover a deficit
+    pub fn can_cover(&self, deficit: i64) -> bool {
+        self.available >= deficit
+    }
+}
+
+/// A mutual guarantee group
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GuaranteeGroup {
+    /// Group ID
+    pub group_id: String,
+
+    /// Display name
+    pub name: String,
+
+    /// Pledges by member account ID
+    pub pledges: BTreeMap<String, Pledge>,
+
+    /// Covered losses, oldest first
+    pub claims: Vec<GuaranteeClaim>,
+
+    /// Creation timestamp (Unix epoch seconds)
+    pub created_at: u64,
+}
+
+impl GuaranteeGroup {
+    /// Create an empty group
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            group_id: Uuid::new_v4().to_string(),
+            name: name.into(),
+            pledges: BTreeMap::new(),
+            claims: Vec::new(),
+            created_at: Utc::now().timestamp() as u64,
+        }
+    }
+
+    /// Check whether an account is a member
+    pub fn is_member(&self, account_id: &str) -> bool {
+        self.pledges.contains_key(account_id)
+    }
+
+    /// Total amount pledged
+    pub fn total_pledged(&self) -> i64 {
+        self.pledges.values().map(|p| p.amount).sum()
+    }
+
+    /// Total amount paid out to cover losses
+    pub fn total_absorbed(&self) -> i64 {
+        self.pledges.values().map(|p| p.absorbed).sum()
+    }
+
+    /// Pledges still available to cover losses
+    pub fn available(&self) -> i64 {
+        self.pledges.values().map(|p| p.available()).sum()
+    }
+
+    /// Compute health metrics
+    pub fn health(&self) -> GroupHealth {
+        let total_pledged = self.total_pledged();
+        let available = self.available();
+        let ratio = |part: i64, whole: i64| {
+            if whole > 0 {
+                part as f64 / whole as f64
+            } else {
+                0.0
+            }
+        };
+        let largest = self
+            .pledges
+            .values()
+            .map(|p| p.available())
+            .max()
+            .unwrap_or(0);
+
+        GroupHealth {
+            members: self.pledges.len(),
+            total_pledged,
+            total_absorbed: self.total_absorbed(),
+            available,
+            claims: self.claims.len(),
+            uncovered: self.claims.iter().map(|c| c.uncovered()).sum(),
+            coverage_ratio: ratio(available, total_pledged),
+            loss_ratio: ratio(self.total_absorbed(), total_pledged),
+            concentration: ratio(largest, available),
+        }
+    }
+
+    /// Add to a member's pledge (joining the group if needed)
+    pub fn add_pledge(&mut self, member: &str, amount: i64, now: u64) -> Result<()> {
+        if amount <= 0 {
+            return Err(CreditError::InvalidOperation(
+                "Pledge amount must be positive".to_string(),
+            ));
+        }
+        self.pledges
+            .entry(member.to_string())
+            .or_insert_with(|| Pledge {
+                member: member.to_string(),
+                amount: 0,
+                absorbed: 0,
+                pledged_at: now,
+            })
+            .amount += amount;
+        Ok(())
+    }
+
+    /// Withdraw part of a member's available pledge
+    pub fn withdraw_pledge(&mut self, member: &str, amount: i64) -> Result<()> {
+        let pledge = self
+            .pledges
+            .get_mut(member)
+            .ok_or_else(|| CreditError::InvalidOperation(format!("{} is not a member", member)))?;
+        if amount <= 0 || amount > pledge.available() {
+            return Err(CreditError::InvalidOperation(format!(
+                "Cannot withdraw {} from an available pledge of {}",
+                amount,
+                pledge.available()
+            )));
+        }
+        pledge.amount -= amount;
+        Ok(())
+    }
+
+    /// Split a deficit across the other members' pledges and record the claim
+    pub fn distribute_loss(
+        &mut self,
+        account_id: &str,
+        overdraft: &Overdraft,
+        now: u64,
+    ) -> Result<GuaranteeClaim> {
+        if !self.is_member(account_id) {
+            return Err(CreditError::InvalidOperation(format!(
+                "{} is not a member of group {}",
+                account_id, self.group_id
+            )));
+        }
+
+        let mut shares = Vec::new();
+        let others: Vec<(String, i64)> = self
+            .pledges
+            .values()
+            .filter(|p| p.member != account_id && p.available() > 0)
+            .map(|p| (p.member.clone(), p.available()))
+            .collect();
+        let pool: i64 = others.iter().map(|(_, available)| available).sum();
+        let to_split = overdraft.deficit.max(0).min(pool);
+        if to_split > 0 {
+            let mut split: Vec<(String, i64, i128)> = others
+                .iter()
+                .map(|(member, available)| {
+                    let exact = to_split as i128 * *available as i128;
+                    let share = (exact / pool as i128) as i64;
+                    (member.clone(), share, exact % pool as i128)
+                })
+                .collect();
+
+            // Hand out rounding cents by largest remainder
+            let mut leftover = to_split - split.iter().map(|(_, s, _)| s).sum::<i64>();
+            let mut order: Vec<usize> = (0..split.len()).collect();
+            order.sort_by(|&a, &b| split[b].2.cmp(&split[a].2));
+            for i in order {
+                if leftover == 0 {
+                    break;
+                }
+                split[i].1 += 1;
+                leftover -= 1;
+            }
+
+            shares = split
+                .into_iter()
+                .filter(|(_, share, _)| *share > 0)
+                .map(|(member, share, _)| (member, share))
+                .collect();
+        }
+
+        for (member, share) in &shares {
+            if let Some(pledge) = self.pledges.get_mut(member) {
+                pledge.absorbed += share;
+            }
+        }
+
+        let claim = GuaranteeClaim {
+            claim_id: Uuid::new_v4().to_string(),
+            account_id: account_id.to_string(),
+            transaction_id: overdraft.transaction_id.clone(),
+            deficit: overdraft.deficit,
+            covered: shares.iter().map(|(_, share)| share).sum(),
+            shares: shares
+                .into_iter()
+                .map(|(member, amount)| LossShare {
+                    member,
+                    amount,
+                    transaction_id: None,
+                })
+                .collect(),
+            claimed_at: now,
+        };
+        self.claims.push(claim.clone());
+        Ok(claim)
+    }
+}
+
+/// Guarantee groups stored in the state engine, one document per group
+pub struct GuaranteeGroups {
+    /// Group and account storage
+    state_engine: Arc<StateEngine>,
+}
+
+impl GuaranteeGroups {
+    /// Create a store over a state engine
+    pub fn new(state_engine: Arc<StateEngine>) -> Self {
+        Self { state_engine }
+    }
+
+    /// Create and store an empty group
+    pub async fn create(&self, name: impl Into<String>) -> Result<GuaranteeGroup> {
+        let group = GuaranteeGroup::new(name);
+        self.save(&group).await?;
+        Ok(group)
+    }
+
+    /// Get a group by ID
+    pub async fn get(&self, group_id: &str) -> Result<Option<GuaranteeGroup>> {
+        let doc_id = DocumentId::new(GUARANTEE_NAMESPACE, group_id);
+        load_json(&self.state_engine, &doc_id).await
+    }
+
+    /// List every group, or only those an account is a member of
+    pub async fn list(&self, account_id: Option<&str>) -> Result<Vec<GuaranteeGroup>> {
+        let mut groups = Vec::new();
+        for metadata in self
+            .state_engine
+            .list_documents(GUARANTEE_NAMESPACE)
+            .await?
+        {
+            if let Some(group) = self.get(&metadata.id.key).await? {
+                if account_id.map_or(true, |account| group.is_member(account)) {
+                    groups.push(group);
+                }
+            }
+        }
+        Ok(groups)
+    }
+
+    /// Pledge part of a member's confirmed balance to a group
+    ///
+    /// The amount is locked on the member's account and must not exceed the
+    /// balance left after escrow allocations and other pledges.
+    pub async fn pledge(
+        &self,
+        group_id: &str,
+        member: &str,
+        amount: i64,
+    ) -> Result<GuaranteeGroup> {
+        let mut group = self.load(group_id).await?;
+        group.add_pledge(member, amount, Utc::now().timestamp() as u64)?;
+
+        let account = CreditAccountHandle::load(&self.state_engine, member).await?;
+        account.update(|acc| {
+            let available = acc.available_to_pledge();
+            if amount > available {
+                return Err(CreditError::InsufficientBalanceForPledge {
+                    available,
+                    requested: amount,
+                });
+            }
+            acc.pledged += amount;
+            Ok(())
+        })?;
+
+        self.save(&group).await?;
+        Ok(group)
+    }
+
+    /// Withdraw part of a member's available pledge, unlocking it
+    pub async fn withdraw(
+        &self,
+        group_id: &str,
+        member: &str,
+        amount: i64,
+    ) -> Result<GuaranteeGroup> {
+        let mut group = self.load(group_id).await?;
+        group.withdraw_pledge(member, amount)?;
+
+        let account = CreditAccountHandle::load(&self.state_engine, member).await?;
+        account.update(|acc| {
+            acc.pledged -= amount;
+            Ok(())
+        })?;
+
+        self.save(&group).await?;
+        Ok(group)
+    }
+
+    /// Cover an unrecoverable overdraft from the group's pledges
+    ///
+    /// Each guarantor's share is debited as a pending transaction to the
+    /// defaulting account, whose pending credits grow by the covered amount.
+    pub async fn cover(
+        &self,
+        group_id: &str,
+        account_id: &str,
+        overdraft: &Overdraft,
+    ) -> Result<GuaranteeClaim> {
+        let mut group = self.load(group_id).await?;
+        let mut claim =
+            group.distribute_loss(account_id, overdraft, Utc::now().timestamp() as u64)?;
+
+        for share in &mut claim.shares {
+            let tx = Transaction::new(
+                share.member.clone(),
+                account_id.to_string(),
+                share.amount,
+                TransactionMetadata {
+                    description: format!("Guarantee claim {}", claim.claim_id),
+                    category: Some(GUARANTEE_CATEGORY.to_string()),
+                    invoice_id: Some(claim.claim_id.clone()),
+                },
+            );
+            share.transaction_id = Some(tx.id.clone());
+
+            let amount = share.amount;
+            let guarantor = CreditAccountHandle::load(&self.state_engine, &share.member).await?;
+            guarantor.update(|acc| {
+                acc.pledged -= amount;
+                acc.add_transaction(tx);
+                Ok(())
+            })?;
+        }
+
+        let covered = claim.covered;
+        let account = CreditAccountHandle::load(&self.state_engine, account_id).await?;
+        account.update(|acc| {
+            acc.pending_credits += covered;
+            Ok(())
+        })?;
+
+        if let Some(stored) = group.claims.last_mut() {
+            *stored = claim.clone();
+        }
+        self.save(&group).await?;
+
+        tracing::info!(
+            "Group {} covered {} of {} for {} ({} uncovered)",
+            group_id,
+            claim.covered,
+            claim.deficit,
+            account_id,
+            claim.uncovered()
+        );
+        Ok(claim)
+    }
+
+    /// Get a group's health metrics
+    pub async fn health(&self, group_id: &str) -> Result<GroupHealth> {
+        Ok(self.load(group_id).await?.health())
+    }
+
+    /// Get a group, failing if unknown
+    async fn load(&self, group_id: &str) -> Result<GuaranteeGroup> {
+        self.get(group_id).await?.ok_or_else(|| {
+            CreditError::InvalidOperation(format!("Unknown guarantee group: {}", group_id))
+        })
+    }
+
+    /// Write a group to the state engine
+    async fn save(&self, group: &GuaranteeGroup) -> Result<()> {
+        let doc_id = DocumentId::new(GUARANTEE_NAMESPACE, &group.group_id);
+        let handle = match self.state_engine.get_document(&doc_id).await {
+            Ok(handle) => handle,
+            Err(_) => self.state_engine.create_document(doc_id).await?,
+        };
+
+        let json = serde_json::to_string(group)?;
+        let name = group.name.clone();
+        let available = group.available();
+        handle.update(|tx| {
+            tx.put(ROOT, "name", name)?;
+            tx.put(ROOT, "available", available)?;
+            tx.put(ROOT, "data_json", json)?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(pledges: &[(&str, i64)]) -> GuaranteeGroup {
+        let mut group = GuaranteeGroup::new("neighbours");
+        for (member, amount) in pledges {
+            group.add_pledge(member, *amount, 0).unwrap();
+        }
+        group
+    }
+
+    fn overdraft(deficit: i64) -> Overdraft {
+        Overdraft::new("tx1".to_string(), deficit, deficit, 0)
+    }
+
+    #[test]
+    fn test_pro_rata_distribution() {
+        let mut group = group(&[("alice", 1_000), ("bob", 2_000), ("carol", 1_000)]);
+        let claim = group.distribute_loss("alice", &overdraft(901), 1).unwrap();
+
+        // Split 2:1 with the rounding cent to bob; alice's own pledge is untouched
+        assert_eq!(claim.covered, 901);
+        assert!(claim.share("alice").is_none());
+        assert_eq!(claim.share("bob").unwrap().amount, 601);
+        assert_eq!(claim.share("carol").unwrap().amount, 300);
+        assert_eq!(group.pledges["alice"].available(), 1_000);
+        assert_eq!(group.total_absorbed(), 901);
+    }
+
+    #[test]
+    fn test_uncovered_deficit_and_health() {
+        let mut group = group(&[("alice", 100), ("bob", 500)]);
+        let claim = group.distribute_loss("alice", &overdraft(800), 1).unwrap();
+        assert_eq!(claim.covered, 500);
+        assert_eq!(claim.uncovered(), 300);
+
+        let health = group.health();
+        assert_eq!(health.members, 2);
+        assert_eq!(health.available, 100);
+        assert_eq!(health.uncovered, 300);
+        assert_eq!(health.concentration, 1.0);
+        assert!((health.loss_ratio - 500.0 / 600.0).abs() < 1e-9);
+        assert!(!health.can_cover(101));
+    }
+
+    #[test]
+    fn test_non_member_and_withdrawal() {
+        let mut group = group(&[("alice", 1_000)]);
+        assert!(group.distribute_loss("mallory", &overdraft(10), 1).is_err());
+
+        group.withdraw_pledge("alice", 400).unwrap();
+        assert_eq!(group.total_pledged(), 600);
+        assert!(group.withdraw_pledge("alice", 601).is_err());
+        assert!(group.add_pledge("alice", 0, 1).is_err());
+    }
+}
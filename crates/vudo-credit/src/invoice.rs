@@ -0,0 +1,645 @@
+//! Payment requests (invoices) with UCAN-signed acceptance
+//!
+//! A payee asks a payer for credits by issuing a [`PaymentRequest`]: a UCAN
+//! from the payee to the payer granting `credit/pay` on the invoice, whose
+//! facts carry the amount and memo. The request is signed, so the payer can
+//! check who is asking for what, and it expires with the UCAN.
+//!
+//! The payer pays with
+//! [`MutualCreditScheduler::pay_request`](crate::MutualCreditScheduler::pay_request),
+//! which spends locally with the request ID as the transaction's
+//! `invoice_id` and returns a [`PaymentAcceptance`]: a UCAN from the payer
+//! back to the payee, delegated from the request, naming the transaction.
+//! When reconciliation confirms the transaction, the [`InvoiceBook`] marks
+//! the request paid.
+//!
+//! ```text
+//! payee                                   payer
+//!   |-- PaymentRequest (UCAN payee->payer) -->|
+//!   |                                         | spend_local(invoice_id)
+//!   |<-- PaymentAcceptance (UCAN payer->payee)|
+//!   |                                         | reconcile: Accepted -> Paid
+//! ```
+//!
+//! Requests and acceptances travel between devices with an
+//! [`InvoiceExchange`] over gossip.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use automerge::{transaction::Transactable, ROOT};
+use chrono::Utc;
+use ed25519_dalek::SigningKey;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use uuid::Uuid;
+use vudo_identity::{Capability, Did, Ucan};
+use vudo_p2p::{GossipOverlay, Topic, TopicMessage, TopicSubscription};
+use vudo_state::{DocumentId, StateEngine};
+
+use crate::document::load_json;
+use crate::error::{CreditError, Result};
+use crate::transaction::{Transaction, TransactionId, TransactionStatus};
+
+/// Application topic used for invoice messages
+pub const INVOICE_TOPIC: &str = "credit:invoice";
+
+/// State engine namespace of invoice documents
+pub const INVOICE_NAMESPACE: &str = "credit_invoice";
+
+/// UCAN ability a payment request grants the payer
+pub const PAY_ABILITY: &str = "credit/pay";
+
+/// UCAN resource of an invoice
+pub fn invoice_resource(request_id: &str) -> String {
+    format!("vudo://credit/invoice/{}", request_id)
+}
+
+/// Terms signed into a payment request's UCAN facts
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct RequestFacts {
+    amount: i64,
+    memo: String,
+}
+
+/// Facts of a payment acceptance UCAN
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct AcceptanceFacts {
+    amount: i64,
+    transaction_id: TransactionId,
+}
+
+/// Signed request for payment, issued by the payee
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PaymentRequest {
+    /// Request ID (also the UCAN nonce)
+    pub request_id: String,
+
+    /// Payee DID (UCAN issuer)
+    pub payee: String,
+
+    /// Payer DID (UCAN audience)
+    pub payer: String,
+
+    /// Amount in cents
+    pub amount: i64,
+
+    /// Memo shown to the payer and used as the transaction description
+    pub memo: String,
+
+    /// Creation timestamp (Unix epoch seconds)
+    pub created_at: u64,
+
+    /// Expiry timestamp (Unix epoch seconds)
+    pub expires_at: u64,
+
+    /// Encoded UCAN signed by the payee
+    pub ucan: String,
+}
+
+impl PaymentRequest {
+    /// Create and sign a payment request
+    pub fn create(
+        payee: &Did,
+        payee_key: &SigningKey,
+        payer: &Did,
+        amount: i64,
+        memo: impl Into<String>,
+        ttl: Duration,
+    ) -> Result<Self> {
+        if amount <= 0 {
+            return Err(CreditError::InvalidPaymentRequest(
+                "Amount must be positive".to_string(),
+            ));
+        }
+
+        let request_id = Uuid::new_v4().to_string();
+        let memo = memo.into();
+        let created_at = Utc::now().timestamp() as u64;
+        let expires_at = created_at + ttl.as_secs();
+        let facts = serde_json::to_value(RequestFacts {
+            amount,
+            memo: memo.clone(),
+        })?;
+
+        let ucan = Ucan::new(
+            payee.clone(),
+            payer.clone(),
+            vec![Capability::new(invoice_resource(&request_id), PAY_ABILITY)],
+            expires_at,
+            Some(created_at),
+            Some(request_id.clone()),
+            vec![],
+        )
+        .with_facts(facts)
+        .sign(payee_key)?;
+
+        Ok(Self {
+            request_id,
+            payee: payee.as_str().to_string(),
+            payer: payer.as_str().to_string(),
+            amount,
+            memo,
+            created_at,
+            expires_at,
+            ucan: ucan.encode()?,
+        })
+    }
+
+    /// Check whether the request has expired
+    pub fn is_expired(&self) -> bool {
+        Utc::now().timestamp() as u64 > self.expires_at
+    }
+
+    /// Verify the payee's signature and that the UCAN covers these terms
+    ///
+    /// Fails once the request has expired.
+    pub fn verify(&self) -> Result<()> {
+        let ucan = Ucan::decode(&self.ucan)?;
+        ucan.verify()?;
+
+        let invalid = |reason: &str| Err(CreditError::InvalidPaymentRequest(reason.to_string()));
+        if ucan.iss.as_str() != self.payee || ucan.aud.as_str() != self.payer {
+            return invalid("UCAN parties do not match payee and payer");
+        }
+        if ucan.nnc.as_deref() != Some(self.request_id.as_str())
+            || ucan.exp != self.expires_at
+            || ucan.nbf != Some(self.created_at)
+        {
+            return invalid("UCAN does not match request");
+        }
+        if ucan.att
+            != [Capability::new(
+                invoice_resource(&self.request_id),
+                PAY_ABILITY,
+            )]
+        {
+            return invalid("UCAN does not grant payment of this request");
+        }
+
+        let facts: RequestFacts = ucan
+            .fct
+            .map(serde_json::from_value)
+            .transpose()?
+            .ok_or_else(|| CreditError::InvalidPaymentRequest("UCAN has no facts".to_string()))?;
+        if facts.amount != self.amount || facts.memo != self.memo {
+            return invalid("UCAN facts do not match amount and memo");
+        }
+
+        Ok(())
+    }
+
+    /// Accept the request as the payer, naming the paying transaction
+    ///
+    /// The acceptance is a UCAN from the payer to the payee, delegated from
+    /// the request.
+    pub fn accept(
+        &self,
+        payer: &Did,
+        payer_key: &SigningKey,
+        transaction_id: TransactionId,
+    ) -> Result<PaymentAcceptance> {
+        if payer.as_str() != self.payer {
+            return Err(CreditError::InvalidPaymentRequest(format!(
+                "Request is addressed to {}, not {}",
+                self.payer, payer
+            )));
+        }
+        self.verify()?;
+
+        let facts = serde_json::to_value(AcceptanceFacts {
+            amount: self.amount,
+            transaction_id: transaction_id.clone(),
+        })?;
+        let ucan = Ucan::new(
+            payer.clone(),
+            Did::parse(&self.payee)?,
+            vec![Capability::new(
+                invoice_resource(&self.request_id),
+                PAY_ABILITY,
+            )],
+            self.expires_at,
+            None,
+            Some(Uuid::new_v4().to_string()),
+            vec![self.ucan.clone()],
+        )
+        .with_facts(facts)
+        .sign(payer_key)?;
+
+        Ok(PaymentAcceptance {
+            request_id: self.request_id.clone(),
+            transaction_id,
+            accepted_at: Utc::now().timestamp() as u64,
+            ucan: ucan.encode()?,
+        })
+    }
+}
+
+/// Payer's signed acceptance of a payment request
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PaymentAcceptance {
+    /// Accepted request ID
+    pub request_id: String,
+
+    /// Transaction paying the request
+    pub transaction_id: TransactionId,
+
+    /// Acceptance timestamp (Unix epoch seconds)
+    pub accepted_at: u64,
+
+    /// Encoded UCAN signed by the payer, with the request as proof
+    pub ucan: String,
+}
+
+impl PaymentAcceptance {
+    /// Verify the payer's signature against the accepted request
+    pub fn verify(&self, request: &PaymentRequest) -> Result<()> {
+        let invalid = |reason: &str| Err(CreditError::InvalidPaymentRequest(reason.to_string()));
+        if self.request_id != request.request_id {
+            return invalid("Acceptance is for another request");
+        }
+
+        let ucan = Ucan::decode(&self.ucan)?;
+        ucan.verify()?;
+        if ucan.iss.as_str() != request.payer || ucan.aud.as_str() != request.payee {
+            return invalid("Acceptance parties do not match payer and payee");
+        }
+        if ucan.prf != [request.ucan.clone()] {
+            return invalid("Acceptance is not delegated from the request");
+        }
+
+        let facts: AcceptanceFacts = ucan
+            .fct
+            .map(serde_json::from_value)
+            .transpose()?
+            .ok_or_else(|| CreditError::InvalidPaymentRequest("UCAN has no facts".to_string()))?;
+        if facts.amount != request.amount || facts.transaction_id != self.transaction_id {
+            return invalid("Acceptance facts do not match");
+        }
+
+        Ok(())
+    }
+}
+
+/// Invoice status
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum InvoiceStatus {
+    /// Awaiting payment
+    Open,
+
+    /// Payer accepted and spent, awaiting reconciliation
+    Accepted,
+
+    /// Paying transaction confirmed by reconciliation
+    Paid,
+}
+
+impl InvoiceStatus {
+    /// Get status as string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InvoiceStatus::Open => "open",
+            InvoiceStatus::Accepted => "accepted",
+            InvoiceStatus::Paid => "paid",
+        }
+    }
+}
+
+/// Stored invoice: a request and its payment progress
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Invoice {
+    /// Payment request
+    pub request: PaymentRequest,
+
+    /// Invoice status
+    pub status: InvoiceStatus,
+
+    /// Payer's acceptance (once accepted)
+    pub acceptance: Option<PaymentAcceptance>,
+
+    /// Confirmation timestamp (once paid)
+    pub paid_at: Option<u64>,
+}
+
+/// Invoices stored in the state engine, one document per request
+pub struct InvoiceBook {
+    /// Invoice storage
+    state_engine: Arc<StateEngine>,
+}
+
+impl InvoiceBook {
+    /// Create an invoice book over a state engine
+    pub fn new(state_engine: Arc<StateEngine>) -> Self {
+        Self { state_engine }
+    }
+
+    /// Store a verified payment request, keeping any existing invoice
+    pub async fn store(&self, request: &PaymentRequest) -> Result<Invoice> {
+        if let Some(invoice) = self.get(&request.request_id).await? {
+            return Ok(invoice);
+        }
+
+        request.verify()?;
+        let invoice = Invoice {
+            request: request.clone(),
+            status: InvoiceStatus::Open,
+            acceptance: None,
+            paid_at: None,
+        };
+        self.save(&invoice).await?;
+        Ok(invoice)
+    }
+
+    /// Get an invoice by request ID
+    pub async fn get(&self, request_id: &str) -> Result<Option<Invoice>> {
+        let doc_id = DocumentId::new(INVOICE_NAMESPACE, request_id);
+        load_json(&self.state_engine, &doc_id).await
+    }
+
+    /// Record the payer's acceptance of a stored request
+    pub async fn record_acceptance(&self, acceptance: &PaymentAcceptance) -> Result<Invoice> {
+        let mut invoice = self.get(&acceptance.request_id).await?.ok_or_else(|| {
+            CreditError::InvalidPaymentRequest(format!(
+                "Unknown request: {}",
+                acceptance.request_id
+            ))
+        })?;
+
+        match &invoice.acceptance {
+            Some(existing) if existing.transaction_id == acceptance.transaction_id => {
+                return Ok(invoice)
+            }
+            Some(_) => {
+                return Err(CreditError::InvalidPaymentRequest(format!(
+                    "Request {} is already accepted",
+                    acceptance.request_id
+                )))
+            }
+            None => {}
+        }
+
+        acceptance.verify(&invoice.request)?;
+        invoice.status = InvoiceStatus::Accepted;
+        invoice.acceptance = Some(acceptance.clone());
+        self.save(&invoice).await?;
+        Ok(invoice)
+    }
+
+    /// Mark the invoice a confirmed transaction pays as paid
+    ///
+    /// Returns the invoice if the transaction is the accepted payment of a
+    /// stored request.
+    pub async fn settle(&self, tx: &Transaction) -> Result<Option<Invoice>> {
+        let Some(request_id) = &tx.metadata.invoice_id else {
+            return Ok(None);
+        };
+        let Some(mut invoice) = self.get(request_id).await? else {
+            return Ok(None);
+        };
+
+        let accepted = invoice
+            .acceptance
+            .as_ref()
+            .is_some_and(|a| a.transaction_id == tx.id);
+        let request = &invoice.request;
+        if invoice.status != InvoiceStatus::Accepted
+            || !accepted
+            || tx.status != TransactionStatus::Confirmed
+            || tx.from != request.payer
+            || tx.to != request.payee
+            || tx.amount != request.amount
+        {
+            return Ok(None);
+        }
+
+        invoice.status = InvoiceStatus::Paid;
+        invoice.paid_at = Some(Utc::now().timestamp() as u64);
+        self.save(&invoice).await?;
+        info!("Invoice {} paid by {}", request_id, tx.id);
+        Ok(Some(invoice))
+    }
+
+    async fn save(&self, invoice: &Invoice) -> Result<()> {
+        let doc_id = DocumentId::new(INVOICE_NAMESPACE, &invoice.request.request_id);
+        let handle = match self.state_engine.get_document(&doc_id).await {
+            Ok(handle) => handle,
+            Err(_) => self.state_engine.create_document(doc_id).await?,
+        };
+
+        let json = serde_json::to_string(invoice)?;
+        let status = invoice.status.as_str();
+        handle.update(|tx| {
+            tx.put(ROOT, "status", status)?;
+            tx.put(ROOT, "data_json", json)?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+}
+
+/// Invoice message exchanged over gossip
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InvoiceMessage {
+    /// Payment request sent by the payee
+    Request { request: PaymentRequest },
+
+    /// Acceptance sent by the payer
+    Accepted { acceptance: PaymentAcceptance },
+}
+
+/// Shares payment requests and acceptances over gossip
+pub struct InvoiceExchange {
+    /// Invoices known to this device
+    book: Arc<InvoiceBook>,
+
+    /// Gossip overlay used for invoice messages
+    gossip: Arc<GossipOverlay>,
+
+    /// Local device ID
+    device_id: String,
+}
+
+impl InvoiceExchange {
+    /// Create a new invoice exchange
+    pub fn new(
+        book: Arc<InvoiceBook>,
+        gossip: Arc<GossipOverlay>,
+        device_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            book,
+            gossip,
+            device_id: device_id.into(),
+        }
+    }
+
+    /// Application topic for invoice messages
+    pub fn topic() -> Topic {
+        Topic::app(INVOICE_TOPIC)
+    }
+
+    /// Subscribe to invoice messages
+    pub async fn subscribe(&self) -> Result<TopicSubscription> {
+        self.gossip
+            .subscribe_application(Self::topic())
+            .await
+            .map_err(|e| CreditError::P2p(e.to_string()))
+    }
+
+    /// Store a payment request and send it to the payer
+    pub async fn send_request(&self, request: &PaymentRequest) -> Result<()> {
+        self.book.store(request).await?;
+        self.publish(&InvoiceMessage::Request {
+            request: request.clone(),
+        })
+        .await
+    }
+
+    /// Record an acceptance and send it to the payee
+    pub async fn send_acceptance(&self, acceptance: &PaymentAcceptance) -> Result<()> {
+        self.book.record_acceptance(acceptance).await?;
+        self.publish(&InvoiceMessage::Accepted {
+            acceptance: acceptance.clone(),
+        })
+        .await
+    }
+
+    /// Process an invoice message received over gossip
+    ///
+    /// Requests and acceptances that fail verification are dropped. Returns
+    /// `true` if the message was an invoice message.
+    pub async fn ingest(&self, message: &TopicMessage) -> Result<bool> {
+        let message: InvoiceMessage = match serde_json::from_slice(&message.payload) {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Ignoring malformed invoice message: {}", e);
+                return Ok(false);
+            }
+        };
+
+        let result = match message {
+            InvoiceMessage::Request { request } => self.book.store(&request).await.map(|_| ()),
+            InvoiceMessage::Accepted { acceptance } => {
+                self.book.record_acceptance(&acceptance).await.map(|_| ())
+            }
+        };
+        if let Err(e) = result {
+            warn!("Ignoring invalid invoice message: {}", e);
+        }
+
+        Ok(true)
+    }
+
+    async fn publish(&self, message: &InvoiceMessage) -> Result<()> {
+        let payload = serde_json::to_vec(message)?;
+        self.gossip
+            .publish_application(self.device_id.clone(), Self::topic(), payload)
+            .await
+            .map_err(|e| CreditError::P2p(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(seed: u8) -> (Did, SigningKey) {
+        let key = SigningKey::from_bytes(&[seed; 32]);
+        (Did::from_key(key.verifying_key()), key)
+    }
+
+    fn request(amount: i64) -> (PaymentRequest, Did, SigningKey) {
+        let (payee, payee_key) = identity(1);
+        let (payer, payer_key) = identity(2);
+        let request = PaymentRequest::create(
+            &payee,
+            &payee_key,
+            &payer,
+            amount,
+            "Invoice #42",
+            Duration::from_secs(3600),
+        )
+        .unwrap();
+        (request, payer, payer_key)
+    }
+
+    #[test]
+    fn test_request_signature() {
+        let (request, _, _) = request(2_500);
+        request.verify().unwrap();
+        assert!(!request.is_expired());
+
+        let mut tampered = request.clone();
+        tampered.amount = 25_000;
+        assert!(tampered.verify().is_err());
+
+        let mut redirected = request.clone();
+        redirected.payee = identity(3).0.as_str().to_string();
+        assert!(redirected.verify().is_err());
+
+        let (payee, payee_key) = identity(1);
+        assert!(
+            PaymentRequest::create(&payee, &payee_key, &payee, 0, "", Duration::from_secs(60))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_acceptance() {
+        let (request, payer, payer_key) = request(2_500);
+        let acceptance = request
+            .accept(&payer, &payer_key, "tx-1".to_string())
+            .unwrap();
+        acceptance.verify(&request).unwrap();
+
+        // Only the addressed payer can accept
+        let (other, other_key) = identity(3);
+        assert!(request
+            .accept(&other, &other_key, "tx-1".to_string())
+            .is_err());
+
+        let mut swapped = acceptance.clone();
+        swapped.transaction_id = "tx-2".to_string();
+        assert!(swapped.verify(&request).is_err());
+
+        let (another, _, _) = self::request(2_500);
+        assert!(acceptance.verify(&another).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_exchange_over_gossip() {
+        let gossip = Arc::new(GossipOverlay::new());
+        let payee_book = Arc::new(InvoiceBook::new(Arc::new(
+            StateEngine::new().await.unwrap(),
+        )));
+        let payer_book = Arc::new(InvoiceBook::new(Arc::new(
+            StateEngine::new().await.unwrap(),
+        )));
+        let payee = InvoiceExchange::new(Arc::clone(&payee_book), Arc::clone(&gossip), "payee");
+        let payer = InvoiceExchange::new(Arc::clone(&payer_book), Arc::clone(&gossip), "payer");
+        let mut payee_sub = payee.subscribe().await.unwrap();
+        let mut payer_sub = payer.subscribe().await.unwrap();
+
+        let (request, payer_did, payer_key) = request(2_500);
+        payee.send_request(&request).await.unwrap();
+        let message = payer_sub.recv().await.unwrap();
+        assert!(payer.ingest(&message).await.unwrap());
+        let invoice = payer_book.get(&request.request_id).await.unwrap().unwrap();
+        assert_eq!(invoice.status, InvoiceStatus::Open);
+
+        let acceptance = request
+            .accept(&payer_did, &payer_key, "tx-1".to_string())
+            .unwrap();
+        payer.send_acceptance(&acceptance).await.unwrap();
+        // The payee sees its own request, then the acceptance
+        while let Ok(Some(message)) =
+            tokio::time::timeout(Duration::from_millis(20), payee_sub.recv()).await
+        {
+            payee.ingest(&message).await.unwrap();
+        }
+        let invoice = payee_book.get(&request.request_id).await.unwrap().unwrap();
+        assert_eq!(invoice.status, InvoiceStatus::Accepted);
+        assert_eq!(invoice.acceptance.unwrap().transaction_id, "tx-1");
+    }
+}
@@ -0,0 +1,604 @@
+//! Persistent, append-only transaction ledger
+//!
+//! An account document holds the current state of each transaction, so a
+//! payment that was confirmed and later reversed shows only "reversed". The
+//! ledger keeps the history: every recorded transaction and every status
+//! change is appended as a [`LedgerEntry`] to a per-account Automerge list in
+//! the state engine, and entries are never edited or removed.
+//!
+//! Users audit where their credits went with [`TransactionLedger::history`],
+//! which filters entries by time range and accounting category and returns
+//! them a page at a time, and [`TransactionLedger::export`], which writes
+//! matching entries as CSV or JSON.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use vudo_credit::ledger::{LedgerQuery, Page, TransactionLedger};
+//! use vudo_credit::{ExportFormat, Transaction, TransactionMetadata};
+//! use vudo_state::StateEngine;
+//! # use vudo_credit::error::Result;
+//!
+//! # async fn example() -> Result<()> {
+//! let ledger = TransactionLedger::new(Arc::new(StateEngine::new().await?));
+//! let tx = Transaction::new(
+//!     "alice".to_string(),
+//!     "cafe".to_string(),
+//!     450,
+//!     TransactionMetadata::default(),
+//! );
+//! ledger.record("alice", &tx).await?;
+//!
+//! let page = ledger.history("alice", &LedgerQuery::new(), Page::first(50)).await?;
+//! assert_eq!(page.entries.len(), 1);
+//!
+//! let csv = ledger.export("alice", &LedgerQuery::new(), ExportFormat::Csv).await?;
+//! println!("{}", csv);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Arc;
+
+use automerge::{transaction::Transactable, ObjId, ObjType, ReadDoc, ROOT};
+use serde::{Deserialize, Serialize};
+use vudo_state::{DocumentHandle, DocumentId, StateEngine, StateError};
+
+use crate::dispute::DisputeOutcome;
+use crate::error::{CreditError, Result};
+use crate::export::{
+    csv_escape, format_date, CategoryRules, ExportFormat, ExportRow, TransactionExporter,
+};
+use crate::transaction::{Transaction, TransactionStatus};
+
+/// State engine namespace of ledger documents
+pub const LEDGER_NAMESPACE: &str = "credit_ledger";
+
+/// Default number of entries per page
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// Key of the entry list in a ledger document
+const ENTRIES_KEY: &str = "entries";
+
+/// Event recorded by a ledger entry
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LedgerEvent {
+    /// Transaction was added to the account
+    Recorded,
+
+    /// Transaction paying an occurrence of a recurring payment was added
+    Recurring {
+        /// Recurring payment ID
+        payment_id: String,
+
+        /// Occurrence number (1-based)
+        occurrence: u32,
+    },
+
+    /// Transaction status changed (the new status is the transaction's)
+    StatusChanged {
+        /// Previous status
+        from: TransactionStatus,
+    },
+
+    /// Transaction settling a dispute was added
+    DisputeSettled {
+        /// Dispute ID
+        dispute_id: String,
+
+        /// Outcome being settled
+        outcome: DisputeOutcome,
+    },
+}
+
+/// Entry of a transaction ledger
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LedgerEntry {
+    /// Position in the ledger (0 is the oldest entry)
+    pub sequence: u64,
+
+    /// When the entry was appended (Unix epoch seconds)
+    pub recorded_at: u64,
+
+    /// What happened to the transaction
+    pub event: LedgerEvent,
+
+    /// Transaction as of this entry
+    pub transaction: Transaction,
+}
+
+impl LedgerEntry {
+    /// Describe the event (e.g., "recorded", "pending->confirmed")
+    pub fn event_label(&self) -> String {
+        match &self.event {
+            LedgerEvent::Recorded => "recorded".to_string(),
+            LedgerEvent::Recurring { occurrence, .. } => format!("recurring #{}", occurrence),
+            LedgerEvent::StatusChanged { from } => {
+                format!("{}->{}", from.as_str(), self.transaction.status.as_str())
+            }
+            LedgerEvent::DisputeSettled { outcome, .. } => format!("dispute {}", outcome.as_str()),
+        }
+    }
+}
+
+/// Filter over ledger entries
+///
+/// Unset conditions match every entry.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LedgerQuery {
+    /// Earliest `recorded_at` (inclusive)
+    pub since: Option<u64>,
+
+    /// Latest `recorded_at` (exclusive)
+    pub until: Option<u64>,
+
+    /// Accounting categories to keep
+    pub categories: Vec<String>,
+}
+
+impl LedgerQuery {
+    /// Create a query matching every entry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep entries recorded in `[since, until)`
+    pub fn with_range(mut self, since: u64, until: u64) -> Self {
+        self.since = Some(since);
+        self.until = Some(until);
+        self
+    }
+
+    /// Keep entries recorded at or after `since`
+    pub fn with_since(mut self, since: u64) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// Keep entries recorded before `until`
+    pub fn with_until(mut self, until: u64) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    /// Keep entries in `category` (may be given several times)
+    pub fn with_category(mut self, category: impl Into<String>) -> Self {
+        self.categories.push(category.into());
+        self
+    }
+
+    /// Check whether an entry of `account_id` matches
+    pub fn matches(&self, account_id: &str, rules: &CategoryRules, entry: &LedgerEntry) -> bool {
+        if self.since.is_some_and(|since| entry.recorded_at < since) {
+            return false;
+        }
+        if self.until.is_some_and(|until| entry.recorded_at >= until) {
+            return false;
+        }
+        if !self.categories.is_empty() {
+            let category = rules.categorize(account_id, &entry.transaction);
+            if !self.categories.contains(&category) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Position and size of a page of ledger entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    /// Return entries after this sequence number (None starts at the oldest)
+    pub after: Option<u64>,
+
+    /// Maximum number of entries
+    pub limit: usize,
+}
+
+impl Page {
+    /// First page of `limit` entries
+    pub fn first(limit: usize) -> Self {
+        Self { after: None, limit }
+    }
+
+    /// Page of `limit` entries following sequence number `after`
+    pub fn after(after: u64, limit: usize) -> Self {
+        Self {
+            after: Some(after),
+            limit,
+        }
+    }
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Self::first(DEFAULT_PAGE_SIZE)
+    }
+}
+
+/// Page of ledger entries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedgerPage {
+    /// Matching entries, oldest first
+    pub entries: Vec<LedgerEntry>,
+
+    /// Next page, if more entries match
+    pub next: Option<Page>,
+}
+
+/// Ledger entry as exported, from the account's side
+#[derive(Debug, Serialize)]
+struct LedgerRow {
+    sequence: u64,
+    recorded_at: String,
+    event: String,
+    #[serde(flatten)]
+    transaction: ExportRow,
+}
+
+/// Append-only transaction ledgers, one per account
+#[derive(Clone)]
+pub struct TransactionLedger {
+    /// Ledger storage
+    state_engine: Arc<StateEngine>,
+
+    /// Category rules for filters and exports
+    rules: CategoryRules,
+}
+
+impl TransactionLedger {
+    /// Create a ledger store over a state engine
+    pub fn new(state_engine: Arc<StateEngine>) -> Self {
+        Self {
+            state_engine,
+            rules: CategoryRules::default(),
+        }
+    }
+
+    /// Set the category rules for filters and exports
+    pub fn with_rules(mut self, rules: CategoryRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Get the category rules
+    pub fn rules(&self) -> &CategoryRules {
+        &self.rules
+    }
+
+    /// Append an entry for a newly added transaction
+    pub async fn record(&self, account_id: &str, tx: &Transaction) -> Result<LedgerEntry> {
+        self.append(account_id, LedgerEvent::Recorded, tx).await
+    }
+
+    /// Append an entry for a status change (`tx` has the new status)
+    pub async fn record_status_change(
+        &self,
+        account_id: &str,
+        from: TransactionStatus,
+        tx: &Transaction,
+    ) -> Result<LedgerEntry> {
+        self.append(account_id, LedgerEvent::StatusChanged { from }, tx)
+            .await
+    }
+
+    /// Append an entry to an account's ledger
+    pub async fn append(
+        &self,
+        account_id: &str,
+        event: LedgerEvent,
+        tx: &Transaction,
+    ) -> Result<LedgerEntry> {
+        let handle = self.open(account_id).await?;
+        let mut entry = LedgerEntry {
+            sequence: 0,
+            recorded_at: chrono::Utc::now().timestamp() as u64,
+            event,
+            transaction: tx.clone(),
+        };
+
+        let json = serde_json::to_string(&entry)?;
+        let sequence = handle.update(|doc| {
+            let list = entries_list(doc)?;
+            let index = doc.length(&list);
+            doc.insert(&list, index, json)?;
+            Ok(index)
+        })?;
+
+        entry.sequence = sequence as u64;
+        Ok(entry)
+    }
+
+    /// Get every entry of an account's ledger, oldest first
+    pub async fn entries(&self, account_id: &str) -> Result<Vec<LedgerEntry>> {
+        let doc_id = DocumentId::new(LEDGER_NAMESPACE, account_id);
+        let handle = match self.state_engine.get_document(&doc_id).await {
+            Ok(handle) => handle,
+            Err(StateError::DocumentNotFound(_)) => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let values = handle.read(|doc| {
+            let list = entries_list(doc)?;
+            (0..doc.length(&list))
+                .map(|index| match doc.get(&list, index)? {
+                    Some((automerge::Value::Scalar(s), _)) => match s.as_ref() {
+                        automerge::ScalarValue::Str(json) => Ok(json.to_string()),
+                        _ => Err(StateError::Internal(
+                            "Ledger entry is not a string".to_string(),
+                        )),
+                    },
+                    _ => Err(StateError::Internal("Ledger entry missing".to_string())),
+                })
+                .collect::<vudo_state::Result<Vec<_>>>()
+        })?;
+
+        values
+            .iter()
+            .enumerate()
+            .map(|(index, json)| {
+                let mut entry: LedgerEntry = serde_json::from_str(json)?;
+                entry.sequence = index as u64;
+                Ok(entry)
+            })
+            .collect()
+    }
+
+    /// Query a page of an account's history
+    ///
+    /// Pass `next` of the returned page to get the following one.
+    pub async fn history(
+        &self,
+        account_id: &str,
+        query: &LedgerQuery,
+        page: Page,
+    ) -> Result<LedgerPage> {
+        let mut matching = self
+            .entries(account_id)
+            .await?
+            .into_iter()
+            .filter(|entry| page.after.map_or(true, |after| entry.sequence > after))
+            .filter(|entry| query.matches(account_id, &self.rules, entry));
+
+        let entries: Vec<LedgerEntry> = matching.by_ref().take(page.limit).collect();
+        let next = match (entries.last(), matching.next()) {
+            (Some(last), Some(_)) => Some(Page::after(last.sequence, page.limit)),
+            _ => None,
+        };
+
+        Ok(LedgerPage { entries, next })
+    }
+
+    /// Export the matching entries of an account's ledger
+    ///
+    /// CSV and JSON list every entry, with its sequence number, time and
+    /// event. OFX, which has no notion of events, lists the latest state of
+    /// each transaction instead.
+    pub async fn export(
+        &self,
+        account_id: &str,
+        query: &LedgerQuery,
+        format: ExportFormat,
+    ) -> Result<String> {
+        let entries: Vec<LedgerEntry> = self
+            .entries(account_id)
+            .await?
+            .into_iter()
+            .filter(|entry| query.matches(account_id, &self.rules, entry))
+            .collect();
+        let exporter = TransactionExporter::new(account_id, self.rules.clone());
+
+        match format {
+            ExportFormat::Csv => {
+                let mut out = format!("sequence,recorded_at,event,{}\r\n", ExportRow::CSV_HEADER);
+                for entry in &entries {
+                    let row = self.row(&exporter, entry)?;
+                    let mut fields = vec![
+                        row.sequence.to_string(),
+                        row.recorded_at,
+                        csv_escape(&row.event),
+                    ];
+                    fields.extend(row.transaction.csv_fields());
+                    out.push_str(&fields.join(","));
+                    out.push_str("\r\n");
+                }
+                Ok(out)
+            }
+            ExportFormat::Json => {
+                let rows = entries
+                    .iter()
+                    .map(|entry| self.row(&exporter, entry))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(serde_json::to_string_pretty(&rows)?)
+            }
+            ExportFormat::Ofx => {
+                let mut latest: Vec<Transaction> = Vec::new();
+                for entry in entries {
+                    match latest.iter_mut().find(|tx| tx.id == entry.transaction.id) {
+                        Some(tx) => *tx = entry.transaction,
+                        None => latest.push(entry.transaction),
+                    }
+                }
+                exporter.to_ofx(&latest, None)
+            }
+        }
+    }
+
+    fn row(&self, exporter: &TransactionExporter, entry: &LedgerEntry) -> Result<LedgerRow> {
+        Ok(LedgerRow {
+            sequence: entry.sequence,
+            recorded_at: format_date(entry.recorded_at)?
+                .format("%Y-%m-%dT%H:%M:%SZ")
+                .to_string(),
+            event: entry.event_label(),
+            transaction: exporter.row(&entry.transaction)?,
+        })
+    }
+
+    /// Get an account's ledger document, creating it on first use
+    async fn open(&self, account_id: &str) -> Result<DocumentHandle> {
+        let doc_id = DocumentId::new(LEDGER_NAMESPACE, account_id);
+        match self.state_engine.get_document(&doc_id).await {
+            Ok(handle) => Ok(handle),
+            Err(StateError::DocumentNotFound(_)) => {
+                let handle = self.state_engine.create_document(doc_id).await?;
+                handle.update(|doc| {
+                    doc.put(ROOT, "account", account_id)?;
+                    doc.put_object(ROOT, ENTRIES_KEY, ObjType::List)?;
+                    Ok(())
+                })?;
+                Ok(handle)
+            }
+            Err(e) => Err(CreditError::from(e)),
+        }
+    }
+}
+
+/// Find the entry list of a ledger document
+fn entries_list<D: ReadDoc>(doc: &D) -> vudo_state::Result<ObjId> {
+    match doc.get(ROOT, ENTRIES_KEY)? {
+        Some((automerge::Value::Object(ObjType::List), id)) => Ok(id),
+        _ => Err(StateError::Internal(
+            "Ledger entry list missing".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::CategoryRule;
+    use crate::transaction::TransactionMetadata;
+
+    fn tx(to: &str, amount: i64, description: &str) -> Transaction {
+        Transaction::new(
+            "alice".to_string(),
+            to.to_string(),
+            amount,
+            TransactionMetadata {
+                description: description.to_string(),
+                category: None,
+                invoice_id: None,
+            },
+        )
+    }
+
+    async fn ledger() -> TransactionLedger {
+        let mut rules = CategoryRules::new();
+        rules.push(CategoryRule::new("Expenses:Food").with_counterparty("cafe"));
+        TransactionLedger::new(Arc::new(StateEngine::new().await.unwrap())).with_rules(rules)
+    }
+
+    #[tokio::test]
+    async fn test_append_only_history() {
+        let ledger = ledger().await;
+        assert!(ledger.entries("alice").await.unwrap().is_empty());
+
+        let mut coffee = tx("cafe", 450, "Coffee, large");
+        ledger.record("alice", &coffee).await.unwrap();
+        coffee.status = TransactionStatus::Confirmed;
+        let entry = ledger
+            .record_status_change("alice", TransactionStatus::Pending, &coffee)
+            .await
+            .unwrap();
+        assert_eq!(entry.sequence, 1);
+
+        let entries = ledger.entries("alice").await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].event, LedgerEvent::Recorded);
+        assert_eq!(entries[0].transaction.status, TransactionStatus::Pending);
+        assert_eq!(entries[1].event_label(), "pending->confirmed");
+        assert!(ledger.entries("bob").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_history_pagination_and_filters() {
+        let ledger = ledger().await;
+        for i in 0..5 {
+            ledger
+                .record("alice", &tx("cafe", 100 + i, "Coffee"))
+                .await
+                .unwrap();
+            ledger
+                .record("alice", &tx("market", 1000, "Groceries"))
+                .await
+                .unwrap();
+        }
+
+        let food = LedgerQuery::new().with_category("Expenses:Food");
+        let mut page = ledger
+            .history("alice", &food, Page::first(2))
+            .await
+            .unwrap();
+        let mut amounts = Vec::new();
+        loop {
+            amounts.extend(page.entries.iter().map(|e| e.transaction.amount));
+            match page.next {
+                Some(next) => page = ledger.history("alice", &food, next).await.unwrap(),
+                None => break,
+            }
+        }
+        assert_eq!(amounts, vec![100, 101, 102, 103, 104]);
+
+        let all = ledger
+            .history("alice", &LedgerQuery::new(), Page::default())
+            .await
+            .unwrap();
+        assert_eq!(all.entries.len(), 10);
+        assert!(all.next.is_none());
+
+        let future = LedgerQuery::new().with_since(u64::MAX - 1);
+        let none = ledger
+            .history("alice", &future, Page::default())
+            .await
+            .unwrap();
+        assert!(none.entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_export() {
+        let ledger = ledger().await;
+        let mut coffee = tx("cafe", 450, "Coffee, large");
+        ledger.record("alice", &coffee).await.unwrap();
+        coffee.status = TransactionStatus::Confirmed;
+        ledger
+            .record_status_change("alice", TransactionStatus::Pending, &coffee)
+            .await
+            .unwrap();
+
+        let query = LedgerQuery::new();
+        let csv = ledger
+            .export("alice", &query, ExportFormat::Csv)
+            .await
+            .unwrap();
+        let lines: Vec<&str> = csv.split("\r\n").filter(|l| !l.is_empty()).collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "sequence,recorded_at,event,date,id,counterparty,description,category,amount,status"
+        );
+        assert!(lines[1].starts_with("0,"));
+        assert!(lines[1].contains(",recorded,"));
+        assert!(lines[2].contains(",pending->confirmed,"));
+        assert!(lines[2].ends_with(",cafe,\"Coffee, large\",Expenses:Food,-4.50,confirmed"));
+
+        let json = ledger
+            .export("alice", &query, ExportFormat::Json)
+            .await
+            .unwrap();
+        let rows: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1]["sequence"], 1);
+        assert_eq!(rows[1]["event"], "pending->confirmed");
+        assert_eq!(rows[1]["amount"], -450);
+        assert_eq!(rows[1]["category"], "Expenses:Food");
+
+        let ofx = ledger
+            .export("alice", &query, ExportFormat::Ofx)
+            .await
+            .unwrap();
+        assert_eq!(ofx.matches("<STMTTRN>").count(), 1);
+    }
+}
@@ -5,9 +5,22 @@
 //! - **Escrow allocation**: Per-device pre-allocation from global balance
 //! - **Double-spend prevention**: Via escrow limits (no coordination needed)
 //! - **BFT reconciliation**: Periodic balance confirmation with 3f+1 nodes
+//! - **PBFT consensus**: Signed rounds over gossip with view changes and quorum certificates
+//! - **Committee rotation**: Reputation- and stake-weighted selection per epoch, with signed handoffs and membership proofs
 //! - **Overdraft detection**: Via CRDT merge comparison
-//! - **Reputation tiers**: Credit limits based on trust level (0-5)
+//! - **Reputation tiers**: Credit limits based on trust level (0-5), with decay, endorsements and signed attestations
 //! - **Conflict resolution**: For concurrent overdrafts
+//! - **Guarantee groups**: Members pledge balance to cover each other's unrecoverable overdrafts
+//! - **Light client proofs**: Committee-signed balance proofs for thin wallets
+//! - **Accounting export**: OFX/CSV/JSON transaction export with category rules
+//! - **Payment requests**: UCAN-signed invoices matched to payments at reconciliation
+//! - **Recurring payments**: Standing orders paid from escrow, with skip or defer on shortfall
+//! - **Transaction ledger**: Append-only per-account history with paged queries and export
+//! - **Atomic swaps**: Hash time-locked escrow swaps between credit communities
+//! - **Fraud monitoring**: Velocity, wash trading and new-account burst alerts that freeze escrow refresh
+//! - **Fees and demurrage**: Community-set payment fees and idle balance decay paid into a commons account at reconciliation
+//! - **Disputes**: Evidence-backed disputes resolved by the committee or a guarantee group, settled by new ledger entries
+//! - **Wallet API**: Balance breakdown, payments, requests and history, with a `vudo-credit` CLI (`cli` feature)
 //!
 //! # Architecture: The Escrow Pattern
 //!
@@ -134,22 +147,73 @@
 
 pub mod account;
 pub mod bft;
+pub mod committee;
+pub mod consensus;
+pub mod dispute;
+mod document;
 pub mod error;
 pub mod escrow;
+pub mod export;
+pub mod guarantee;
+pub mod invoice;
+pub mod ledger;
+pub mod monitor;
 pub mod overdraft;
+pub mod policy;
+pub mod proof;
+pub mod recurring;
 pub mod reputation;
 pub mod scheduler;
+pub mod swap;
 pub mod transaction;
+pub mod wallet;
 
 // Re-export main types
 pub use account::{CreditAccount, CreditAccountHandle};
-pub use bft::{BftCommittee, BftVote, ReconciliationResult};
+pub use bft::{BftCommittee, BftVote, QuorumCertificate, ReconciliationResult, VotePhase};
+pub use committee::{
+    CommitteeCandidate, CommitteeDocument, CommitteeFormation, CommitteeFormationConfig,
+    CommitteeHandoff, MembershipProof,
+};
+pub use consensus::{BftReplica, ConsensusMessage, ProposalValidator, ViewChange};
+pub use dispute::{
+    Dispute, DisputeOutcome, DisputeResolution, DisputeRoute, DisputeState, DisputeVote, Disputes,
+    Evidence, SignedStatement,
+};
 pub use error::{CreditError, Result};
 pub use escrow::{DeviceEscrow, EscrowManager};
+pub use export::{CategoryRule, CategoryRules, ExportFormat, TransactionExporter};
+pub use guarantee::{
+    GroupHealth, GuaranteeClaim, GuaranteeGroup, GuaranteeGroups, LossShare, Pledge,
+};
+pub use invoice::{
+    Invoice, InvoiceBook, InvoiceExchange, InvoiceMessage, InvoiceStatus, PaymentAcceptance,
+    PaymentRequest,
+};
+pub use ledger::{LedgerEntry, LedgerEvent, LedgerPage, LedgerQuery, Page, TransactionLedger};
+pub use monitor::{
+    AlertKind, AlertSeverity, CreditMetrics, FraudAlert, FraudMonitor, MonitorConfig,
+};
 pub use overdraft::{Overdraft, OverdraftResolution, OverdraftResolver};
-pub use reputation::{ReputationManager, ReputationTier};
+pub use policy::{CreditPolicy, DemurragePolicy, FeeSchedule, Settlement};
+pub use proof::{
+    BalanceProof, CommitteeKeys, GossipProofSource, LightClient, ProofMessage, ProofServer,
+    ProofSignature, ProofSigner, ProofSource, ProofStore,
+};
+pub use recurring::{
+    Occurrence, OccurrenceOutcome, RecurringEnd, RecurringPayment, RecurringPayments,
+    RecurringState, ShortfallPolicy,
+};
+pub use reputation::{
+    Endorsement, ReputationAttestation, ReputationConfig, ReputationManager, ReputationRecord,
+    ReputationTier,
+};
 pub use scheduler::MutualCreditScheduler;
+pub use swap::{
+    LockState, SwapEngine, SwapLeg, SwapLock, SwapMessage, SwapSecret, SwapSide, SwapTerms,
+};
 pub use transaction::{Transaction, TransactionId, TransactionMetadata, TransactionStatus};
+pub use wallet::{BalanceBreakdown, Wallet};
 
 /// Library version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,724 @@
+//! Credit system metrics and fraud anomaly detection
+//!
+//! The [`FraudMonitor`] watches the transactions a node sees and raises typed
+//! [`FraudAlert`]s for three anomalies:
+//!
+//! - **Velocity spikes**: an account spends far more in the current window
+//!   than its average over the preceding windows
+//! - **Wash trading**: payments that go round a short cycle of three or more
+//!   accounts (A → B → C → A), inflating volume and reconciliation history
+//!   without any real exchange
+//! - **New-account bursts**: many freshly seen accounts transacting with
+//!   the same counterparty in a short window, the shape of a Sybil farm
+//!
+//! Alerts at or above [`MonitorConfig::freeze_severity`] freeze escrow
+//! refresh for the accounts involved until the committee reviews and
+//! [releases](FraudMonitor::release) them. Local spends from escrow already
+//! granted are unaffected; the freeze only stops new escrow being granted.
+//!
+//! Each alert maps onto the attack taxonomy of the adversarial test harness
+//! through [`AlertKind::attack`].
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::transaction::Transaction;
+
+/// Thresholds of the fraud monitor
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MonitorConfig {
+    /// Length of a velocity window (seconds)
+    pub velocity_window_secs: u64,
+
+    /// Number of preceding windows averaged into the spend baseline
+    pub velocity_baseline_windows: u32,
+
+    /// Window spend over baseline that counts as a spike
+    pub velocity_spike_factor: f64,
+
+    /// Window spend below which spikes are ignored (in cents)
+    pub velocity_min_amount: i64,
+
+    /// Longest payment cycle checked for wash trading (accounts)
+    pub cycle_max_length: usize,
+
+    /// How far back payments count towards a cycle (seconds)
+    pub cycle_window_secs: u64,
+
+    /// Payments below this amount are ignored for cycles (in cents)
+    pub cycle_min_amount: i64,
+
+    /// Age below which an account counts as new (seconds)
+    pub new_account_age_secs: u64,
+
+    /// Length of a burst window (seconds)
+    pub burst_window_secs: u64,
+
+    /// New accounts on one counterparty within a window that count as a burst
+    pub burst_threshold: usize,
+
+    /// Alerts at or above this severity freeze escrow refresh (`None` never freezes)
+    pub freeze_severity: Option<AlertSeverity>,
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            velocity_window_secs: 3_600,
+            velocity_baseline_windows: 24,
+            velocity_spike_factor: 5.0,
+            velocity_min_amount: 10_000,
+            cycle_max_length: 4,
+            cycle_window_secs: 86_400,
+            cycle_min_amount: 100,
+            new_account_age_secs: 7 * 86_400,
+            burst_window_secs: 3_600,
+            burst_threshold: 10,
+            freeze_severity: Some(AlertSeverity::High),
+        }
+    }
+}
+
+/// Alert severity
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AlertSeverity {
+    /// Worth a look
+    Low,
+
+    /// Likely abuse
+    Medium,
+
+    /// Abuse pattern, freezes escrow refresh by default
+    High,
+}
+
+/// Kind of anomaly detected
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AlertKind {
+    /// Spend rate far above the account's baseline
+    VelocitySpike {
+        /// Spend in the current window (in cents)
+        window_amount: i64,
+
+        /// Average spend per preceding window (in cents)
+        baseline_amount: i64,
+    },
+
+    /// Payments going round a cycle of accounts
+    WashTrading {
+        /// Accounts of the cycle, in payment order
+        cycle: Vec<String>,
+
+        /// Smallest payment along the cycle (in cents)
+        amount: i64,
+    },
+
+    /// Many new accounts transacting with one counterparty
+    NewAccountBurst {
+        /// Account the new accounts transacted with
+        counterparty: String,
+
+        /// New accounts seen within the window
+        new_accounts: Vec<String>,
+    },
+}
+
+impl AlertKind {
+    /// Short label for metrics and logs
+    pub fn label(&self) -> &'static str {
+        match self {
+            AlertKind::VelocitySpike { .. } => "velocity_spike",
+            AlertKind::WashTrading { .. } => "wash_trading",
+            AlertKind::NewAccountBurst { .. } => "new_account_burst",
+        }
+    }
+
+    /// Attack category of the adversarial test harness this anomaly signals
+    pub fn attack(&self) -> &'static str {
+        match self {
+            AlertKind::VelocitySpike { .. } => "velocity_spike",
+            AlertKind::WashTrading { .. } => "wash_trading",
+            AlertKind::NewAccountBurst { .. } => "sybil_attack",
+        }
+    }
+}
+
+/// A detected anomaly
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FraudAlert {
+    /// Alert ID
+    pub alert_id: String,
+
+    /// What was detected
+    pub kind: AlertKind,
+
+    /// How serious it is
+    pub severity: AlertSeverity,
+
+    /// Accounts implicated
+    pub accounts: Vec<String>,
+
+    /// Transaction that triggered the alert
+    pub transaction_id: String,
+
+    /// Detection timestamp (Unix epoch seconds)
+    pub detected_at: u64,
+}
+
+/// Aggregate credit system metrics
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CreditMetrics {
+    /// Transactions observed
+    pub transactions: u64,
+
+    /// Total amount observed (in cents)
+    pub volume: i64,
+
+    /// Distinct accounts observed
+    pub accounts: usize,
+
+    /// Alerts raised, by kind label
+    pub alerts: HashMap<String, usize>,
+
+    /// Accounts with escrow refresh frozen
+    pub frozen_accounts: usize,
+}
+
+/// A payment edge kept for cycle detection
+#[derive(Debug, Clone)]
+struct Edge {
+    from: String,
+    to: String,
+    amount: i64,
+    at: u64,
+}
+
+/// Fraud monitor over observed transactions
+pub struct FraudMonitor {
+    /// Detection thresholds
+    config: MonitorConfig,
+
+    /// Spends per account (timestamp, amount), oldest first
+    spends: DashMap<String, VecDeque<(u64, i64)>>,
+
+    /// Recent payments, oldest first
+    edges: RwLock<VecDeque<Edge>>,
+
+    /// First time each account was seen or created (Unix epoch seconds)
+    first_seen: DashMap<String, u64>,
+
+    /// New accounts seen per counterparty (account, time)
+    newcomers: DashMap<String, VecDeque<(String, u64)>>,
+
+    /// Last alert time per (kind label, subject), to avoid repeats
+    last_alert: DashMap<(&'static str, String), u64>,
+
+    /// Raised alerts, oldest first
+    alerts: RwLock<Vec<FraudAlert>>,
+
+    /// Frozen accounts (account ID -> alert ID)
+    frozen: DashMap<String, String>,
+
+    /// Transaction count and volume
+    totals: RwLock<(u64, i64)>,
+}
+
+impl Default for FraudMonitor {
+    fn default() -> Self {
+        Self::new(MonitorConfig::default())
+    }
+}
+
+impl FraudMonitor {
+    /// Create a monitor with the given thresholds
+    pub fn new(config: MonitorConfig) -> Self {
+        Self {
+            config,
+            spends: DashMap::new(),
+            edges: RwLock::new(VecDeque::new()),
+            first_seen: DashMap::new(),
+            newcomers: DashMap::new(),
+            last_alert: DashMap::new(),
+            alerts: RwLock::new(Vec::new()),
+            frozen: DashMap::new(),
+            totals: RwLock::new((0, 0)),
+        }
+    }
+
+    /// Get the detection thresholds
+    pub fn config(&self) -> &MonitorConfig {
+        &self.config
+    }
+
+    /// Register an account's creation time
+    ///
+    /// Accounts not registered are treated as created when first seen.
+    pub fn observe_account(&self, account_id: &str, created_at: u64) {
+        self.first_seen.insert(account_id.to_string(), created_at);
+    }
+
+    /// Observe a transaction at its own timestamp
+    pub fn observe(&self, tx: &Transaction) -> Vec<FraudAlert> {
+        self.observe_at(tx, tx.timestamp)
+    }
+
+    /// Observe a transaction at `now`, returning any new alerts
+    pub fn observe_at(&self, tx: &Transaction, now: u64) -> Vec<FraudAlert> {
+        {
+            let mut totals = self.totals.write();
+            totals.0 += 1;
+            totals.1 += tx.amount;
+        }
+        for account in [&tx.from, &tx.to] {
+            self.first_seen.entry(account.clone()).or_insert(now);
+        }
+
+        let mut alerts = Vec::new();
+        alerts.extend(self.check_velocity(tx, now));
+        alerts.extend(self.check_cycle(tx, now));
+        alerts.extend(self.check_burst(tx, &tx.from, &tx.to, now));
+        alerts.extend(self.check_burst(tx, &tx.to, &tx.from, now));
+
+        for alert in &alerts {
+            tracing::warn!(
+                "Fraud alert {} ({:?}): {} on {:?}",
+                alert.alert_id,
+                alert.severity,
+                alert.kind.label(),
+                alert.accounts
+            );
+            if self
+                .config
+                .freeze_severity
+                .is_some_and(|threshold| alert.severity >= threshold)
+            {
+                for account in &alert.accounts {
+                    self.frozen
+                        .entry(account.clone())
+                        .or_insert_with(|| alert.alert_id.clone());
+                }
+            }
+        }
+        self.alerts.write().extend(alerts.iter().cloned());
+        alerts
+    }
+
+    /// Check whether escrow refresh is frozen for an account
+    pub fn is_frozen(&self, account_id: &str) -> bool {
+        self.frozen.contains_key(account_id)
+    }
+
+    /// Get the alert an account was frozen for
+    pub fn frozen_by(&self, account_id: &str) -> Option<String> {
+        self.frozen.get(account_id).map(|id| id.clone())
+    }
+
+    /// Lift a freeze after committee review, returning the alert ID it was for
+    pub fn release(&self, account_id: &str) -> Option<String> {
+        self.frozen.remove(account_id).map(|(_, alert_id)| alert_id)
+    }
+
+    /// Freeze escrow refresh for an account pending committee review
+    pub fn freeze(&self, account_id: &str, alert_id: &str) {
+        self.frozen
+            .insert(account_id.to_string(), alert_id.to_string());
+    }
+
+    /// Get every alert raised, oldest first
+    pub fn alerts(&self) -> Vec<FraudAlert> {
+        self.alerts.read().clone()
+    }
+
+    /// Get the alerts implicating an account
+    pub fn alerts_for(&self, account_id: &str) -> Vec<FraudAlert> {
+        self.alerts
+            .read()
+            .iter()
+            .filter(|a| a.accounts.iter().any(|acc| acc == account_id))
+            .cloned()
+            .collect()
+    }
+
+    /// Get aggregate metrics
+    pub fn metrics(&self) -> CreditMetrics {
+        let (transactions, volume) = *self.totals.read();
+        let mut alerts = HashMap::new();
+        for alert in self.alerts.read().iter() {
+            *alerts.entry(alert.kind.label().to_string()).or_insert(0) += 1;
+        }
+        CreditMetrics {
+            transactions,
+            volume,
+            accounts: self.first_seen.len(),
+            alerts,
+            frozen_accounts: self.frozen.len(),
+        }
+    }
+
+    /// Check the payer's spend rate against its baseline
+    fn check_velocity(&self, tx: &Transaction, now: u64) -> Option<FraudAlert> {
+        let window = self.config.velocity_window_secs.max(1);
+        let baseline_windows = self.config.velocity_baseline_windows.max(1) as u64;
+        let horizon = now.saturating_sub(window * (baseline_windows + 1));
+
+        let (window_amount, baseline_amount) = {
+            let mut spends = self.spends.entry(tx.from.clone()).or_default();
+            spends.push_back((now, tx.amount));
+            while spends.front().is_some_and(|(at, _)| *at < horizon) {
+                spends.pop_front();
+            }
+
+            let window_start = now.saturating_sub(window);
+            let (current, previous): (Vec<_>, Vec<_>) =
+                spends.iter().partition(|(at, _)| *at > window_start);
+            let current: i64 = current.iter().map(|(_, amount)| amount).sum();
+            let previous: i64 = previous.iter().map(|(_, amount)| amount).sum();
+            (current, previous / baseline_windows as i64)
+        };
+
+        if window_amount < self.config.velocity_min_amount
+            || (window_amount as f64) <= baseline_amount as f64 * self.config.velocity_spike_factor
+        {
+            return None;
+        }
+        if !self.should_alert("velocity_spike", tx.from.clone(), now, window) {
+            return None;
+        }
+
+        let severity = if (window_amount as f64)
+            > baseline_amount as f64 * self.config.velocity_spike_factor * 2.0
+            && baseline_amount > 0
+        {
+            AlertSeverity::High
+        } else {
+            AlertSeverity::Medium
+        };
+        Some(self.alert(
+            AlertKind::VelocitySpike {
+                window_amount,
+                baseline_amount,
+            },
+            severity,
+            vec![tx.from.clone()],
+            tx,
+            now,
+        ))
+    }
+
+    /// Check whether the payment closes a short cycle of recent payments
+    fn check_cycle(&self, tx: &Transaction, now: u64) -> Option<FraudAlert> {
+        if tx.amount < self.config.cycle_min_amount || tx.from == tx.to {
+            return None;
+        }
+
+        let cycle = {
+            let mut edges = self.edges.write();
+            let horizon = now.saturating_sub(self.config.cycle_window_secs);
+            while edges.front().is_some_and(|e| e.at < horizon) {
+                edges.pop_front();
+            }
+            edges.push_back(Edge {
+                from: tx.from.clone(),
+                to: tx.to.clone(),
+                amount: tx.amount,
+                at: now,
+            });
+
+            let mut adjacency: HashMap<&str, Vec<(&str, i64)>> = HashMap::new();
+            for edge in edges.iter() {
+                adjacency
+                    .entry(edge.from.as_str())
+                    .or_default()
+                    .push((edge.to.as_str(), edge.amount));
+            }
+            find_cycle(&adjacency, &tx.from, &tx.to, self.config.cycle_max_length).map(
+                |(path, amount)| {
+                    let cycle: Vec<String> = path.into_iter().map(str::to_string).collect();
+                    (cycle, amount.min(tx.amount))
+                },
+            )
+        };
+
+        let (cycle, amount) = cycle?;
+        let mut key: Vec<&str> = cycle.iter().map(String::as_str).collect();
+        key.sort_unstable();
+        if !self.should_alert(
+            "wash_trading",
+            key.join(","),
+            now,
+            self.config.cycle_window_secs,
+        ) {
+            return None;
+        }
+
+        Some(self.alert(
+            AlertKind::WashTrading {
+                cycle: cycle.clone(),
+                amount,
+            },
+            AlertSeverity::High,
+            cycle,
+            tx,
+            now,
+        ))
+    }
+
+    /// Check whether `account`, if new, completes a burst on `counterparty`
+    fn check_burst(
+        &self,
+        tx: &Transaction,
+        account: &str,
+        counterparty: &str,
+        now: u64,
+    ) -> Option<FraudAlert> {
+        let created = self.first_seen.get(account).map(|t| *t)?;
+        if now.saturating_sub(created) >= self.config.new_account_age_secs {
+            return None;
+        }
+
+        let new_accounts: Vec<String> = {
+            let mut seen = self.newcomers.entry(counterparty.to_string()).or_default();
+            let horizon = now.saturating_sub(self.config.burst_window_secs);
+            while seen.front().is_some_and(|(_, at)| *at < horizon) {
+                seen.pop_front();
+            }
+            if !seen.iter().any(|(acc, _)| acc == account) {
+                seen.push_back((account.to_string(), now));
+            }
+            seen.iter().map(|(acc, _)| acc.clone()).collect()
+        };
+
+        if new_accounts.len() < self.config.burst_threshold.max(1) {
+            return None;
+        }
+        let window = self.config.burst_window_secs;
+        if !self.should_alert("new_account_burst", counterparty.to_string(), now, window) {
+            return None;
+        }
+
+        let severity = if new_accounts.len() >= self.config.burst_threshold * 2 {
+            AlertSeverity::High
+        } else {
+            AlertSeverity::Medium
+        };
+        let mut accounts = vec![counterparty.to_string()];
+        accounts.extend(new_accounts.iter().cloned());
+        Some(self.alert(
+            AlertKind::NewAccountBurst {
+                counterparty: counterparty.to_string(),
+                new_accounts,
+            },
+            severity,
+            accounts,
+            tx,
+            now,
+        ))
+    }
+
+    /// Record that an alert is raised for `subject`, unless one was within `window`
+    fn should_alert(&self, label: &'static str, subject: String, now: u64, window: u64) -> bool {
+        let key = (label, subject);
+        if let Some(last) = self.last_alert.get(&key) {
+            if now.saturating_sub(*last) < window {
+                return false;
+            }
+        }
+        self.last_alert.insert(key, now);
+        true
+    }
+
+    fn alert(
+        &self,
+        kind: AlertKind,
+        severity: AlertSeverity,
+        accounts: Vec<String>,
+        tx: &Transaction,
+        now: u64,
+    ) -> FraudAlert {
+        FraudAlert {
+            alert_id: Uuid::new_v4().to_string(),
+            kind,
+            severity,
+            accounts,
+            transaction_id: tx.id.clone(),
+            detected_at: now,
+        }
+    }
+}
+
+/// Find a path `start -> ... -> target` closing the payment `target -> start`
+///
+/// Returns the cycle in payment order starting at `target`, and the smallest
+/// payment along the path. Paths visit at most `max_length` accounts.
+fn find_cycle<'a>(
+    adjacency: &HashMap<&'a str, Vec<(&'a str, i64)>>,
+    target: &'a str,
+    start: &'a str,
+    max_length: usize,
+) -> Option<(Vec<&'a str>, i64)> {
+    let mut queue = VecDeque::from([(vec![target, start], i64::MAX)]);
+    while let Some((path, min_amount)) = queue.pop_front() {
+        if path.len() > max_length {
+            continue;
+        }
+        let last = *path.last()?;
+        let visited: HashSet<&str> = path.iter().copied().collect();
+        for &(next, amount) in adjacency.get(last).into_iter().flatten() {
+            if next == target && path.len() > 2 {
+                return Some((path.clone(), min_amount.min(amount)));
+            }
+            if !visited.contains(next) && path.len() < max_length {
+                let mut extended = path.clone();
+                extended.push(next);
+                queue.push_back((extended, min_amount.min(amount)));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::TransactionMetadata;
+
+    fn tx(from: &str, to: &str, amount: i64) -> Transaction {
+        Transaction::new(
+            from.to_string(),
+            to.to_string(),
+            amount,
+            TransactionMetadata::default(),
+        )
+    }
+
+    fn established(monitor: &FraudMonitor, accounts: &[&str]) {
+        for account in accounts {
+            monitor.observe_account(account, 0);
+        }
+    }
+
+    #[test]
+    fn test_velocity_spike() {
+        let monitor = FraudMonitor::default();
+        established(&monitor, &["alice", "bob"]);
+        let start = 1_000_000;
+
+        // Steady $10 per hour for a day
+        for hour in 0..24 {
+            let alerts = monitor.observe_at(&tx("alice", "bob", 1_000), start + hour * 3_600);
+            assert!(alerts.is_empty());
+        }
+
+        // Then $200 within the hour
+        let now = start + 24 * 3_600;
+        let alerts = monitor.observe_at(&tx("alice", "bob", 20_000), now);
+        assert_eq!(alerts.len(), 1);
+        assert!(matches!(
+            alerts[0].kind,
+            AlertKind::VelocitySpike {
+                window_amount: 20_000,
+                baseline_amount: 1_000,
+            }
+        ));
+        assert_eq!(alerts[0].severity, AlertSeverity::High);
+        assert!(monitor.is_frozen("alice"));
+
+        // Not repeated within the window
+        assert!(monitor
+            .observe_at(&tx("alice", "bob", 20_000), now + 60)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_wash_trading_cycle() {
+        let monitor = FraudMonitor::default();
+        established(&monitor, &["a", "b", "c", "d"]);
+
+        assert!(monitor.observe_at(&tx("a", "b", 5_000), 10).is_empty());
+        assert!(monitor.observe_at(&tx("b", "c", 5_000), 20).is_empty());
+        let alerts = monitor.observe_at(&tx("c", "a", 4_000), 30);
+        assert_eq!(alerts.len(), 1);
+        match &alerts[0].kind {
+            AlertKind::WashTrading { cycle, amount } => {
+                assert_eq!(cycle, &["c", "a", "b"]);
+                assert_eq!(*amount, 4_000);
+            }
+            other => panic!("unexpected alert {:?}", other),
+        }
+        assert_eq!(alerts[0].kind.attack(), "wash_trading");
+        for account in ["a", "b", "c"] {
+            assert!(monitor.is_frozen(account));
+        }
+        assert!(!monitor.is_frozen("d"));
+
+        // Committee review lifts the freeze
+        assert_eq!(monitor.release("a"), Some(alerts[0].alert_id.clone()));
+        assert!(!monitor.is_frozen("a"));
+    }
+
+    #[test]
+    fn test_cycles_outside_window_or_length_ignored() {
+        let monitor = FraudMonitor::new(MonitorConfig {
+            cycle_max_length: 3,
+            ..MonitorConfig::default()
+        });
+        established(&monitor, &["a", "b", "c", "d"]);
+
+        monitor.observe_at(&tx("a", "b", 5_000), 10);
+        monitor.observe_at(&tx("b", "c", 5_000), 20);
+        monitor.observe_at(&tx("c", "d", 5_000), 30);
+        assert!(monitor.observe_at(&tx("d", "a", 5_000), 40).is_empty());
+
+        let later = 40 + monitor.config().cycle_window_secs + 1;
+        assert!(monitor.observe_at(&tx("c", "a", 5_000), later).is_empty());
+    }
+
+    #[test]
+    fn test_new_account_burst() {
+        let monitor = FraudMonitor::new(MonitorConfig {
+            burst_threshold: 3,
+            ..MonitorConfig::default()
+        });
+        established(&monitor, &["hub"]);
+        let now = 10 * 86_400;
+
+        assert!(monitor.observe_at(&tx("new1", "hub", 100), now).is_empty());
+        assert!(monitor
+            .observe_at(&tx("new2", "hub", 100), now + 1)
+            .is_empty());
+        let alerts = monitor.observe_at(&tx("new3", "hub", 100), now + 2);
+        assert_eq!(alerts.len(), 1);
+        match &alerts[0].kind {
+            AlertKind::NewAccountBurst {
+                counterparty,
+                new_accounts,
+            } => {
+                assert_eq!(counterparty, "hub");
+                assert_eq!(new_accounts.len(), 3);
+            }
+            other => panic!("unexpected alert {:?}", other),
+        }
+        assert_eq!(alerts[0].severity, AlertSeverity::Medium);
+        assert!(!monitor.is_frozen("hub"));
+    }
+
+    #[test]
+    fn test_metrics() {
+        let monitor = FraudMonitor::default();
+        established(&monitor, &["a", "b", "c"]);
+        monitor.observe_at(&tx("a", "b", 500), 10);
+        monitor.observe_at(&tx("b", "c", 500), 20);
+        monitor.observe_at(&tx("c", "a", 500), 30);
+
+        let metrics = monitor.metrics();
+        assert_eq!(metrics.transactions, 3);
+        assert_eq!(metrics.volume, 1_500);
+        assert_eq!(metrics.accounts, 3);
+        assert_eq!(metrics.alerts.get("wash_trading"), Some(&1));
+        assert_eq!(metrics.frozen_accounts, 3);
+    }
+}
@@ -46,6 +46,9 @@ pub enum OverdraftResolution {
 
     /// Defer resolution (mark as disputed)
     Defer,
+
+    /// Cover the deficit from a guarantee group's pledges
+    Guarantee { group_id: String },
 }
 
 /// Overdraft resolver
@@ -132,6 +135,12 @@ impl OverdraftResolver {
                 Ok(())
             }
             OverdraftResolution::Defer => Ok(()),
+            OverdraftResolution::Guarantee { group_id } => {
+                if group_id.is_empty() {
+                    return Err("Guarantee resolution needs a group".to_string());
+                }
+                Ok(())
+            }
         }
     }
 
@@ -145,6 +154,7 @@ impl OverdraftResolver {
                 receiver_pays,
             } => sender_pays + receiver_pays,
             OverdraftResolution::Defer => 0, // Deferred, no immediate recovery
+            OverdraftResolution::Guarantee { .. } => 0, // Depends on the group's pledges
         }
     }
 }
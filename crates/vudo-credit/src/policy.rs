@@ -0,0 +1,404 @@
+//! Community fee and demurrage policy
+//!
+//! A community can charge a fee on every payment and levy demurrage, a slow
+//! decay of idle positive balances. Both are paid into the community's
+//! commons account, and both are settled at reconciliation as part of the
+//! balance the committee agrees on:
+//!
+//! ```text
+//! fee        = flat amount, or amount * basis points / 10_000
+//! periods    = settling_at / period - last_reconciliation / period
+//! demurrage  = (balance - exempt) * basis points * periods / 10_000
+//! confirmed' = confirmed + pending credits - pending debits - fees - demurrage
+//! ```
+//!
+//! Charges are rounded down, and demurrage is simple rather than compounded.
+//! The policy is a single document in the state engine, and the settlement
+//! time is written to the account before the round, so every replica
+//! computes the same charges from its own copy of the account. Demurrage is
+//! charged per period boundary crossed, so reconciling often does not avoid
+//! it. The commons account pays neither.
+
+use automerge::{transaction::Transactable, ROOT};
+use serde::{Deserialize, Serialize};
+use vudo_state::{DocumentId, StateEngine};
+
+use crate::account::CreditAccount;
+use crate::document::load_json;
+use crate::error::{CreditError, Result};
+
+/// State engine namespace of the community policy document
+pub const POLICY_NAMESPACE: &str = "credit_policy";
+
+/// Key of the community policy document
+const POLICY_KEY: &str = "community";
+
+/// Basis points in a whole (100%)
+const BASIS_POINTS: i128 = 10_000;
+
+/// Fee charged on each payment
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FeeSchedule {
+    /// Fixed fee per payment (in cents)
+    Flat {
+        /// Fee amount
+        amount: i64,
+    },
+
+    /// Share of each payment
+    Percentage {
+        /// Fee rate in basis points (1/100 of a percent)
+        basis_points: u32,
+    },
+}
+
+impl FeeSchedule {
+    /// Fee on a payment of `amount`
+    pub fn fee(&self, amount: i64) -> i64 {
+        match *self {
+            FeeSchedule::Flat { amount: fee } => fee,
+            FeeSchedule::Percentage { basis_points } => {
+                (amount.max(0) as i128 * basis_points as i128 / BASIS_POINTS) as i64
+            }
+        }
+    }
+}
+
+/// Decay of idle positive balances
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DemurragePolicy {
+    /// Rate per period in basis points (1/100 of a percent)
+    pub basis_points: u32,
+
+    /// Period length in seconds
+    pub period_secs: u64,
+
+    /// Balance exempt from demurrage (in cents)
+    pub exempt_balance: i64,
+}
+
+impl DemurragePolicy {
+    /// Demurrage on `balance` for the periods between `from` and `to`
+    pub fn charge(&self, balance: i64, from: u64, to: u64) -> i64 {
+        let periods = (to / self.period_secs).saturating_sub(from / self.period_secs);
+        let base = (balance - self.exempt_balance).max(0) as i128;
+        let charge = base * self.basis_points as i128 * periods as i128 / BASIS_POINTS;
+        charge.min(base) as i64
+    }
+}
+
+/// Charges settled by one reconciliation
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Settlement {
+    /// Fees on the reconciled payments
+    pub fees: i64,
+
+    /// Demurrage on the confirmed balance
+    pub demurrage: i64,
+}
+
+impl Settlement {
+    /// Total paid into the commons account
+    pub fn total(&self) -> i64 {
+        self.fees + self.demurrage
+    }
+}
+
+/// Community fee and demurrage policy
+///
+/// The default policy charges nothing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CreditPolicy {
+    /// Account fees and demurrage are paid into
+    pub commons_account: String,
+
+    /// Fee on each payment
+    pub fee: Option<FeeSchedule>,
+
+    /// Demurrage on idle positive balances
+    pub demurrage: Option<DemurragePolicy>,
+}
+
+impl CreditPolicy {
+    /// Create a policy paying into `commons_account` that charges nothing yet
+    pub fn new(commons_account: impl Into<String>) -> Self {
+        Self {
+            commons_account: commons_account.into(),
+            fee: None,
+            demurrage: None,
+        }
+    }
+
+    /// Charge `fee` on each payment
+    pub fn with_fee(mut self, fee: FeeSchedule) -> Self {
+        self.fee = Some(fee);
+        self
+    }
+
+    /// Levy `demurrage` on idle positive balances
+    pub fn with_demurrage(mut self, demurrage: DemurragePolicy) -> Self {
+        self.demurrage = Some(demurrage);
+        self
+    }
+
+    /// Whether the policy charges anything
+    pub fn is_active(&self) -> bool {
+        self.fee.is_some() || self.demurrage.is_some()
+    }
+
+    /// Whether `account_id` is the commons account
+    pub fn is_commons(&self, account_id: &str) -> bool {
+        account_id == self.commons_account
+    }
+
+    /// Check the policy is well-formed
+    pub fn validate(&self) -> Result<()> {
+        if self.is_active() && self.commons_account.is_empty() {
+            return Err(CreditError::InvalidOperation(
+                "Fees and demurrage need a commons account".to_string(),
+            ));
+        }
+        match self.fee {
+            Some(FeeSchedule::Flat { amount }) if amount < 0 => {
+                return Err(CreditError::InvalidOperation(
+                    "Flat fee cannot be negative".to_string(),
+                ));
+            }
+            Some(FeeSchedule::Percentage { basis_points }) if basis_points > 10_000 => {
+                return Err(CreditError::InvalidOperation(
+                    "Fee cannot exceed 100% of a payment".to_string(),
+                ));
+            }
+            _ => {}
+        }
+        if let Some(demurrage) = &self.demurrage {
+            if demurrage.period_secs == 0 {
+                return Err(CreditError::InvalidOperation(
+                    "Demurrage period must be at least one second".to_string(),
+                ));
+            }
+            if demurrage.basis_points > 10_000 {
+                return Err(CreditError::InvalidOperation(
+                    "Demurrage cannot exceed 100% per period".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Charges the next reconciliation of `account` settles
+    ///
+    /// Fees are charged on pending payments (except those to the commons
+    /// account), and demurrage on the confirmed balance from the last
+    /// reconciliation to `account.settling_at`.
+    pub fn settle(&self, account: &CreditAccount) -> Settlement {
+        if self.is_commons(&account.owner) {
+            return Settlement::default();
+        }
+
+        let fees = self.fee.map_or(0, |fee| {
+            account
+                .pending_debits()
+                .iter()
+                .filter(|tx| tx.to != self.commons_account)
+                .map(|tx| fee.fee(tx.amount))
+                .sum()
+        });
+        let demurrage = self.demurrage.map_or(0, |demurrage| {
+            demurrage.charge(
+                account.confirmed_balance,
+                account.last_reconciliation,
+                account.settling_at,
+            )
+        });
+
+        Settlement { fees, demurrage }
+    }
+
+    /// Load the community policy (the default policy if none is published)
+    ///
+    /// The document syncs from peers, so the policy is validated again
+    /// rather than trusted to have come through [`CreditPolicy::publish`].
+    pub async fn load(state_engine: &StateEngine) -> Result<Self> {
+        let doc_id = DocumentId::new(POLICY_NAMESPACE, POLICY_KEY);
+        match load_json::<Self>(state_engine, &doc_id).await? {
+            Some(policy) => policy.validate().map(|_| policy),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Validate and publish as the community policy
+    pub async fn publish(&self, state_engine: &StateEngine) -> Result<()> {
+        self.validate()?;
+
+        let doc_id = DocumentId::new(POLICY_NAMESPACE, POLICY_KEY);
+        let handle = match state_engine.get_document(&doc_id).await {
+            Ok(handle) => handle,
+            Err(_) => state_engine.create_document(doc_id).await?,
+        };
+
+        let json = serde_json::to_string(self)?;
+        let commons_account = self.commons_account.clone();
+        handle.update(|tx| {
+            tx.put(ROOT, "commons_account", commons_account)?;
+            tx.put(ROOT, "data_json", json)?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::{Transaction, TransactionMetadata};
+
+    const DAY: u64 = 86_400;
+
+    fn account(balance: i64, payments: &[(&str, i64)]) -> CreditAccount {
+        let mut account = CreditAccount::new("alice".to_string(), balance);
+        for (to, amount) in payments {
+            account.add_transaction(Transaction::new(
+                "alice".to_string(),
+                to.to_string(),
+                *amount,
+                TransactionMetadata::default(),
+            ));
+        }
+        account
+    }
+
+    #[test]
+    fn test_fee_schedules() {
+        assert_eq!(FeeSchedule::Flat { amount: 25 }.fee(1_000), 25);
+        assert_eq!(FeeSchedule::Percentage { basis_points: 150 }.fee(1_000), 15);
+        assert_eq!(FeeSchedule::Percentage { basis_points: 150 }.fee(99), 1);
+        assert_eq!(FeeSchedule::Percentage { basis_points: 150 }.fee(66), 0);
+    }
+
+    #[test]
+    fn test_demurrage_counts_period_boundaries() {
+        let demurrage = DemurragePolicy {
+            basis_points: 10,
+            period_secs: DAY,
+            exempt_balance: 10_000,
+        };
+
+        // Same day: nothing, however long the gap
+        assert_eq!(demurrage.charge(110_000, 10 * DAY, 11 * DAY - 1), 0);
+
+        // One boundary crossed, however short the gap
+        assert_eq!(demurrage.charge(110_000, 11 * DAY - 1, 11 * DAY), 100);
+        assert_eq!(demurrage.charge(110_000, 10 * DAY, 13 * DAY), 300);
+
+        // Exempt and negative balances pay nothing
+        assert_eq!(demurrage.charge(10_000, 0, 30 * DAY), 0);
+        assert_eq!(demurrage.charge(-5_000, 0, 30 * DAY), 0);
+
+        // Never more than the taxable balance
+        assert_eq!(demurrage.charge(11_000, 0, 100_000 * DAY), 1_000);
+    }
+
+    #[test]
+    fn test_settle() {
+        let policy = CreditPolicy::new("commons")
+            .with_fee(FeeSchedule::Percentage { basis_points: 100 })
+            .with_demurrage(DemurragePolicy {
+                basis_points: 10,
+                period_secs: DAY,
+                exempt_balance: 0,
+            });
+
+        let mut alice = account(100_000, &[("bob", 5_000), ("commons", 1_000)]);
+        alice.last_reconciliation = 20 * DAY;
+        alice.settling_at = 22 * DAY;
+        assert_eq!(
+            policy.settle(&alice),
+            Settlement {
+                fees: 50,
+                demurrage: 200
+            }
+        );
+
+        // The commons account pays nothing
+        let mut commons = alice.clone();
+        commons.owner = "commons".to_string();
+        assert_eq!(policy.settle(&commons), Settlement::default());
+
+        // Neither does anyone under the default policy
+        assert_eq!(CreditPolicy::default().settle(&alice).total(), 0);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(CreditPolicy::default().validate().is_ok());
+        assert!(CreditPolicy::new("")
+            .with_fee(FeeSchedule::Flat { amount: 1 })
+            .validate()
+            .is_err());
+        assert!(CreditPolicy::new("commons")
+            .with_fee(FeeSchedule::Percentage {
+                basis_points: 10_001
+            })
+            .validate()
+            .is_err());
+        assert!(CreditPolicy::new("commons")
+            .with_demurrage(DemurragePolicy {
+                basis_points: 10,
+                period_secs: 0,
+                exempt_balance: 0,
+            })
+            .validate()
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_publish_and_load() {
+        let state_engine = StateEngine::new().await.unwrap();
+        assert_eq!(
+            CreditPolicy::load(&state_engine).await.unwrap(),
+            CreditPolicy::default()
+        );
+
+        let policy = CreditPolicy::new("commons").with_fee(FeeSchedule::Flat { amount: 5 });
+        policy.publish(&state_engine).await.unwrap();
+        assert_eq!(CreditPolicy::load(&state_engine).await.unwrap(), policy);
+
+        // Invalid policies are not published
+        assert!(CreditPolicy::new("")
+            .with_fee(FeeSchedule::Flat { amount: 5 })
+            .publish(&state_engine)
+            .await
+            .is_err());
+        assert_eq!(CreditPolicy::load(&state_engine).await.unwrap(), policy);
+    }
+
+    #[tokio::test]
+    async fn test_load_rejects_invalid_policy() {
+        let state_engine = StateEngine::new().await.unwrap();
+        let policy = CreditPolicy::new("commons").with_demurrage(DemurragePolicy {
+            basis_points: 100,
+            period_secs: 0,
+            exempt_balance: 0,
+        });
+
+        // Written around publish(), as a synced document could be
+        let handle = state_engine
+            .create_document(DocumentId::new(POLICY_NAMESPACE, POLICY_KEY))
+            .await
+            .unwrap();
+        let json = serde_json::to_string(&policy).unwrap();
+        handle
+            .update(|tx| {
+                tx.put(ROOT, "data_json", json)?;
+                Ok(())
+            })
+            .unwrap();
+
+        assert!(matches!(
+            CreditPolicy::load(&state_engine).await,
+            Err(CreditError::InvalidOperation(_))
+        ));
+    }
+}
@@ -0,0 +1,678 @@
+//! Committee-signed balance proofs for light clients
+//!
+//! A full node holding the transaction CRDT can ask committee members to
+//! co-sign a compact statement of an account's confirmed balance:
+//!
+//! ```text
+//! message = BLAKE3("vudo-credit/balance-proof/v1" || account_id || balance || epoch)
+//! proof   = (account_id, confirmed_balance, epoch, [Ed25519(member, message)])
+//! ```
+//!
+//! Light devices only need the committee's public keys to check a proof,
+//! so thin wallets can display confirmed balances without syncing the
+//! account document. Proofs are fetched on demand from any [`ProofSource`]
+//! and cached per account: a local [`ProofStore`], or full nodes running a
+//! [`ProofServer`] reached over gossip with a [`GossipProofSource`].
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use uuid::Uuid;
+use vudo_p2p::{GossipOverlay, Topic, TopicMessage, TopicSubscription};
+
+use crate::account::CreditAccountHandle;
+use crate::error::{CreditError, Result};
+
+/// Domain separation tag for balance proof messages
+const PROOF_DOMAIN: &[u8] = b"vudo-credit/balance-proof/v1";
+
+/// Application topic used for balance proof requests and replies
+pub const PROOF_TOPIC: &str = "credit:proof";
+
+/// Default time a gossip proof request waits for a reply
+pub const DEFAULT_PROOF_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// One committee member's signature over a balance statement
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProofSignature {
+    /// Signing member DID
+    pub signer: String,
+
+    /// Ed25519 signature bytes
+    pub signature: Vec<u8>,
+}
+
+/// Compact, committee-signed statement of an account's confirmed balance
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BalanceProof {
+    /// Account ID
+    pub account_id: String,
+
+    /// BFT-confirmed balance (in cents)
+    pub confirmed_balance: i64,
+
+    /// Committee epoch the balance was confirmed in
+    pub epoch: u64,
+
+    /// Member signatures
+    pub signatures: Vec<ProofSignature>,
+}
+
+impl BalanceProof {
+    /// Create an unsigned proof
+    pub fn new(account_id: impl Into<String>, confirmed_balance: i64, epoch: u64) -> Self {
+        Self {
+            account_id: account_id.into(),
+            confirmed_balance,
+            epoch,
+            signatures: Vec::new(),
+        }
+    }
+
+    /// Message digest signed by committee members
+    pub fn message(&self) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(PROOF_DOMAIN);
+        hasher.update(&(self.account_id.len() as u64).to_le_bytes());
+        hasher.update(self.account_id.as_bytes());
+        hasher.update(&self.confirmed_balance.to_le_bytes());
+        hasher.update(&self.epoch.to_le_bytes());
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Add a member signature
+    pub fn add_signature(&mut self, signature: ProofSignature) {
+        if !self.signatures.iter().any(|s| s.signer == signature.signer) {
+            self.signatures.push(signature);
+        }
+    }
+
+    /// Verify the proof against a set of trusted committee keys
+    ///
+    /// Succeeds if at least `keys.quorum()` distinct committee members
+    /// produced a valid signature. Signatures from unknown signers or with
+    /// invalid bytes are ignored.
+    pub fn verify(&self, keys: &CommitteeKeys) -> Result<()> {
+        let valid = valid_signers(&self.message(), &self.signatures, keys);
+
+        if valid.len() >= keys.quorum {
+            Ok(())
+        } else {
+            Err(CreditError::InvalidProof(format!(
+                "{} valid signatures, {} required",
+                valid.len(),
+                keys.quorum
+            )))
+        }
+    }
+
+    /// Serialize to bytes for transport
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Deserialize from bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// Committee members with a valid signature over `message`
+///
+/// Signatures from unknown signers or with invalid bytes are ignored.
+pub(crate) fn valid_signers<'a>(
+    message: &[u8; 32],
+    signatures: &'a [ProofSignature],
+    keys: &CommitteeKeys,
+) -> HashSet<&'a str> {
+    let mut valid = HashSet::new();
+
+    for sig in signatures {
+        let Some(key) = keys.members.get(&sig.signer) else {
+            continue;
+        };
+        let Ok(bytes) = <[u8; 64]>::try_from(sig.signature.as_slice()) else {
+            continue;
+        };
+        if key.verify(message, &Signature::from_bytes(&bytes)).is_ok() {
+            valid.insert(sig.signer.as_str());
+        }
+    }
+
+    valid
+}
+
+/// Trusted public keys of the committee for an epoch
+#[derive(Debug, Clone)]
+pub struct CommitteeKeys {
+    /// Epoch these keys are valid for
+    pub epoch: u64,
+
+    /// Member DID -> verifying key
+    members: HashMap<String, VerifyingKey>,
+
+    /// Signatures required for a proof to be accepted (2f+1)
+    quorum: usize,
+}
+
+impl CommitteeKeys {
+    /// Create a key set for an epoch, deriving the BFT quorum from its size
+    pub fn new(epoch: u64, members: HashMap<String, VerifyingKey>) -> Result<Self> {
+        if members.len() < 4 {
+            return Err(CreditError::InvalidOperation(
+                "BFT committee requires at least 4 members (3f+1 with f=1)".to_string(),
+            ));
+        }
+        let f = (members.len() - 1) / 3;
+        Ok(Self {
+            epoch,
+            members,
+            quorum: 2 * f + 1,
+        })
+    }
+
+    /// Signatures required for a proof to be accepted
+    pub fn quorum(&self) -> usize {
+        self.quorum
+    }
+
+    /// Member DIDs, sorted
+    pub fn members(&self) -> Vec<String> {
+        let mut members: Vec<String> = self.members.keys().cloned().collect();
+        members.sort();
+        members
+    }
+
+    /// Verifying key of a member
+    pub fn key(&self, did: &str) -> Option<&VerifyingKey> {
+        self.members.get(did)
+    }
+}
+
+/// A committee member able to co-sign balance proofs
+pub struct ProofSigner {
+    /// Member DID
+    did: String,
+
+    /// Signing key
+    key: SigningKey,
+}
+
+impl ProofSigner {
+    /// Create a signer from a member DID and signing key
+    pub fn new(did: impl Into<String>, key: SigningKey) -> Self {
+        Self {
+            did: did.into(),
+            key,
+        }
+    }
+
+    /// Member DID
+    pub fn did(&self) -> &str {
+        &self.did
+    }
+
+    /// Public key light clients should trust
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.key.verifying_key()
+    }
+
+    /// Sign a balance proof
+    pub fn sign(&self, proof: &BalanceProof) -> ProofSignature {
+        self.sign_digest(&proof.message())
+    }
+
+    /// Sign a domain-tagged message digest
+    pub(crate) fn sign_digest(&self, digest: &[u8; 32]) -> ProofSignature {
+        ProofSignature {
+            signer: self.did.clone(),
+            signature: self.key.sign(digest).to_bytes().to_vec(),
+        }
+    }
+
+    /// Issue a fully signed proof for an account's confirmed balance
+    pub fn issue(
+        account: &CreditAccountHandle,
+        epoch: u64,
+        signers: &[ProofSigner],
+    ) -> Result<BalanceProof> {
+        let confirmed_balance = account.read(|acc| Ok(acc.confirmed_balance))?;
+        let mut proof = BalanceProof::new(account.id.key.clone(), confirmed_balance, epoch);
+        for signer in signers {
+            proof.add_signature(signer.sign(&proof));
+        }
+        Ok(proof)
+    }
+}
+
+/// A peer (or local store) that can serve balance proofs on demand
+#[async_trait]
+pub trait ProofSource: Send + Sync {
+    /// Peer identifier (for logging)
+    fn source_id(&self) -> String;
+
+    /// Fetch the latest proof for an account, if the peer has one
+    async fn fetch_proof(&self, account_id: &str) -> Result<Option<BalanceProof>>;
+}
+
+/// In-memory proof store served by full nodes
+#[derive(Default)]
+pub struct ProofStore {
+    /// Latest proof per account
+    proofs: DashMap<String, BalanceProof>,
+}
+
+impl ProofStore {
+    /// Create an empty proof store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publish a proof, replacing any older epoch for the same account
+    pub fn publish(&self, proof: BalanceProof) {
+        let newer = self
+            .proofs
+            .get(&proof.account_id)
+            .map_or(true, |existing| proof.epoch >= existing.epoch);
+        if newer {
+            self.proofs.insert(proof.account_id.clone(), proof);
+        }
+    }
+
+    /// Get the latest proof for an account
+    pub fn get(&self, account_id: &str) -> Option<BalanceProof> {
+        self.proofs.get(account_id).map(|p| p.clone())
+    }
+}
+
+#[async_trait]
+impl ProofSource for ProofStore {
+    fn source_id(&self) -> String {
+        "local".to_string()
+    }
+
+    async fn fetch_proof(&self, account_id: &str) -> Result<Option<BalanceProof>> {
+        Ok(self.get(account_id))
+    }
+}
+
+/// Balance proof message exchanged over gossip
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProofMessage {
+    /// Light client asking for an account's latest proof
+    Request {
+        request_id: String,
+        account_id: String,
+    },
+
+    /// Full node answering a request
+    Response {
+        request_id: String,
+        proof: BalanceProof,
+    },
+}
+
+/// Answers gossip proof requests from a full node's proof store
+pub struct ProofServer {
+    /// Proofs served to peers
+    store: Arc<ProofStore>,
+
+    /// Gossip overlay used for proof messages
+    gossip: Arc<GossipOverlay>,
+
+    /// Local device ID
+    device_id: String,
+}
+
+impl ProofServer {
+    /// Create a new proof server
+    pub fn new(
+        store: Arc<ProofStore>,
+        gossip: Arc<GossipOverlay>,
+        device_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            store,
+            gossip,
+            device_id: device_id.into(),
+        }
+    }
+
+    /// Application topic for proof messages
+    pub fn topic() -> Topic {
+        Topic::app(PROOF_TOPIC)
+    }
+
+    /// Subscribe to proof messages
+    pub async fn subscribe(&self) -> Result<TopicSubscription> {
+        self.gossip
+            .subscribe_application(Self::topic())
+            .await
+            .map_err(|e| CreditError::P2p(e.to_string()))
+    }
+
+    /// Process a proof message received over gossip
+    ///
+    /// Requests for accounts with a stored proof are answered; everything
+    /// else is ignored. Returns `true` if a reply was sent.
+    pub async fn ingest(&self, message: &TopicMessage) -> Result<bool> {
+        let (request_id, account_id) = match serde_json::from_slice(&message.payload) {
+            Ok(ProofMessage::Request {
+                request_id,
+                account_id,
+            }) => (request_id, account_id),
+            Ok(ProofMessage::Response { .. }) => return Ok(false),
+            Err(e) => {
+                warn!("Ignoring malformed proof message: {}", e);
+                return Ok(false);
+            }
+        };
+
+        let Some(proof) = self.store.get(&account_id) else {
+            debug!(
+                "No proof for {} requested by {}",
+                account_id, message.peer_id
+            );
+            return Ok(false);
+        };
+        let payload = serde_json::to_vec(&ProofMessage::Response { request_id, proof })?;
+        self.gossip
+            .publish_application(self.device_id.clone(), Self::topic(), payload)
+            .await
+            .map_err(|e| CreditError::P2p(e.to_string()))?;
+        Ok(true)
+    }
+}
+
+/// Fetches proofs from peers running a [`ProofServer`]
+///
+/// Replies are not verified here; the [`LightClient`] checks them against
+/// the committee keys and falls back to its next source.
+pub struct GossipProofSource {
+    /// Gossip overlay used for proof messages
+    gossip: Arc<GossipOverlay>,
+
+    /// Local device ID
+    device_id: String,
+
+    /// Time to wait for a reply
+    timeout: Duration,
+}
+
+impl GossipProofSource {
+    /// Create a new gossip proof source
+    pub fn new(gossip: Arc<GossipOverlay>, device_id: impl Into<String>) -> Self {
+        Self {
+            gossip,
+            device_id: device_id.into(),
+            timeout: DEFAULT_PROOF_TIMEOUT,
+        }
+    }
+
+    /// Set the time to wait for a reply
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+#[async_trait]
+impl ProofSource for GossipProofSource {
+    fn source_id(&self) -> String {
+        format!("gossip:{}", PROOF_TOPIC)
+    }
+
+    async fn fetch_proof(&self, account_id: &str) -> Result<Option<BalanceProof>> {
+        // Subscribe before asking so a fast reply isn't missed
+        let mut subscription = self
+            .gossip
+            .subscribe_application(ProofServer::topic())
+            .await
+            .map_err(|e| CreditError::P2p(e.to_string()))?;
+
+        let request_id = Uuid::new_v4().to_string();
+        let payload = serde_json::to_vec(&ProofMessage::Request {
+            request_id: request_id.clone(),
+            account_id: account_id.to_string(),
+        })?;
+        self.gossip
+            .publish_application(self.device_id.clone(), ProofServer::topic(), payload)
+            .await
+            .map_err(|e| CreditError::P2p(e.to_string()))?;
+
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        while let Ok(Some(message)) = tokio::time::timeout_at(deadline, subscription.recv()).await {
+            if let Ok(ProofMessage::Response {
+                request_id: id,
+                proof,
+            }) = serde_json::from_slice(&message.payload)
+            {
+                if id == request_id && proof.account_id == account_id {
+                    return Ok(Some(proof));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Light client that verifies balances from proofs instead of the full CRDT
+pub struct LightClient {
+    /// Trusted committee keys
+    keys: CommitteeKeys,
+
+    /// Peers to fetch proofs from, tried in order
+    sources: Vec<Arc<dyn ProofSource>>,
+
+    /// Verified proofs (account_id -> proof)
+    cache: DashMap<String, BalanceProof>,
+}
+
+impl LightClient {
+    /// Create a light client trusting the given committee keys
+    pub fn new(keys: CommitteeKeys) -> Self {
+        Self {
+            keys,
+            sources: Vec::new(),
+            cache: DashMap::new(),
+        }
+    }
+
+    /// Add a peer to fetch proofs from
+    pub fn add_source(&mut self, source: Arc<dyn ProofSource>) {
+        self.sources.push(source);
+    }
+
+    /// Rotate to a new committee epoch (clears proofs from older epochs)
+    pub fn update_keys(&mut self, keys: CommitteeKeys) {
+        let epoch = keys.epoch;
+        self.keys = keys;
+        self.cache.retain(|_, proof| proof.epoch >= epoch);
+    }
+
+    /// Get a cached, verified proof without touching the network
+    pub fn cached(&self, account_id: &str) -> Option<BalanceProof> {
+        self.cache.get(account_id).map(|p| p.clone())
+    }
+
+    /// Get a verified confirmed balance, fetching a proof if needed
+    pub async fn confirmed_balance(&self, account_id: &str) -> Result<i64> {
+        Ok(self.proof(account_id).await?.confirmed_balance)
+    }
+
+    /// Get a verified proof for the current epoch, fetching if needed
+    pub async fn proof(&self, account_id: &str) -> Result<BalanceProof> {
+        if let Some(proof) = self.cached(account_id) {
+            if proof.epoch >= self.keys.epoch {
+                return Ok(proof);
+            }
+        }
+
+        for source in &self.sources {
+            match source.fetch_proof(account_id).await {
+                Ok(Some(proof)) => match self.accept(account_id, proof) {
+                    Ok(proof) => return Ok(proof),
+                    Err(e) => warn!("Rejected proof from {}: {}", source.source_id(), e),
+                },
+                Ok(None) => debug!("{} has no proof for {}", source.source_id(), account_id),
+                Err(e) => warn!("Failed to fetch proof from {}: {}", source.source_id(), e),
+            }
+        }
+
+        Err(CreditError::InvalidProof(format!(
+            "No valid proof available for {}",
+            account_id
+        )))
+    }
+
+    /// Verify a proof and add it to the cache
+    pub fn accept(&self, account_id: &str, proof: BalanceProof) -> Result<BalanceProof> {
+        if proof.account_id != account_id {
+            return Err(CreditError::InvalidProof("Account mismatch".to_string()));
+        }
+        if proof.epoch < self.keys.epoch {
+            return Err(CreditError::InvalidProof(format!(
+                "Stale epoch {} (current {})",
+                proof.epoch, self.keys.epoch
+            )));
+        }
+        proof.verify(&self.keys)?;
+        self.cache.insert(account_id.to_string(), proof.clone());
+        Ok(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vudo_state::StateEngine;
+
+    fn signers(n: u8) -> Vec<ProofSigner> {
+        (0..n)
+            .map(|i| ProofSigner::new(format!("member{}", i), SigningKey::from_bytes(&[i + 1; 32])))
+            .collect()
+    }
+
+    fn keys(epoch: u64, signers: &[ProofSigner]) -> CommitteeKeys {
+        let members = signers
+            .iter()
+            .map(|s| (s.did().to_string(), s.verifying_key()))
+            .collect();
+        CommitteeKeys::new(epoch, members).unwrap()
+    }
+
+    #[test]
+    fn test_proof_verify_quorum() {
+        let signers = signers(4);
+        let keys = keys(1, &signers);
+
+        let mut proof = BalanceProof::new("alice", 10_000, 1);
+        for signer in &signers[..2] {
+            proof.add_signature(signer.sign(&proof));
+        }
+        assert!(proof.verify(&keys).is_err());
+
+        proof.add_signature(signers[2].sign(&proof));
+        assert!(proof.verify(&keys).is_ok());
+    }
+
+    #[test]
+    fn test_proof_rejects_tampering_and_duplicates() {
+        let signers = signers(4);
+        let keys = keys(1, &signers);
+
+        let mut proof = BalanceProof::new("alice", 10_000, 1);
+        for signer in &signers[..3] {
+            proof.add_signature(signer.sign(&proof));
+        }
+
+        let mut tampered = proof.clone();
+        tampered.confirmed_balance = 1_000_000;
+        assert!(tampered.verify(&keys).is_err());
+
+        let mut duplicated = BalanceProof::new("alice", 10_000, 1);
+        let sig = signers[0].sign(&duplicated);
+        duplicated.signatures = vec![sig.clone(), sig.clone(), sig];
+        assert!(duplicated.verify(&keys).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_issue_and_light_client_fetch() {
+        let engine = StateEngine::new().await.unwrap();
+        let account = CreditAccountHandle::create(&engine, "alice".to_string(), 10_000)
+            .await
+            .unwrap();
+
+        let signers = signers(4);
+        let proof = ProofSigner::issue(&account, 1, &signers).unwrap();
+        assert_eq!(proof.confirmed_balance, 10_000);
+
+        let store = Arc::new(ProofStore::new());
+        store.publish(proof);
+
+        let mut client = LightClient::new(keys(1, &signers));
+        client.add_source(store);
+
+        assert!(client.cached("alice").is_none());
+        assert_eq!(client.confirmed_balance("alice").await.unwrap(), 10_000);
+        assert!(client.cached("alice").is_some());
+        assert!(client.confirmed_balance("bob").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_light_client_fetch_over_gossip() {
+        let signers = signers(4);
+        let mut proof = BalanceProof::new("alice", 7_500, 1);
+        for signer in &signers[..3] {
+            proof.add_signature(signer.sign(&proof));
+        }
+
+        let gossip = Arc::new(GossipOverlay::new());
+        let store = Arc::new(ProofStore::new());
+        store.publish(proof);
+        let server = ProofServer::new(store, Arc::clone(&gossip), "full-node");
+        let mut requests = server.subscribe().await.unwrap();
+        tokio::spawn(async move {
+            while let Some(message) = requests.recv().await {
+                server.ingest(&message).await.unwrap();
+            }
+        });
+
+        let source =
+            GossipProofSource::new(gossip, "light").with_timeout(Duration::from_millis(200));
+        let mut client = LightClient::new(keys(1, &signers));
+        client.add_source(Arc::new(source));
+
+        assert_eq!(client.confirmed_balance("alice").await.unwrap(), 7_500);
+        assert!(client.cached("alice").is_some());
+        // No peer holds a proof for bob, so the request times out
+        assert!(client.confirmed_balance("bob").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_light_client_rejects_stale_epoch() {
+        let signers = signers(4);
+        let mut proof = BalanceProof::new("alice", 500, 1);
+        for signer in &signers {
+            proof.add_signature(signer.sign(&proof));
+        }
+
+        let client = LightClient::new(keys(2, &signers));
+        assert!(client.accept("alice", proof).is_err());
+    }
+
+    #[test]
+    fn test_proof_serialization() {
+        let proof = BalanceProof::new("alice", 42, 3);
+        let decoded = BalanceProof::from_bytes(&proof.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded, proof);
+    }
+}
@@ -0,0 +1,419 @@
+//! Recurring payments and standing orders
+//!
+//! A [`RecurringPayment`] pays a fixed amount to a recipient every interval,
+//! from a start time until its end condition is met. Occurrences are paid by
+//! [`MutualCreditScheduler::run_recurring`](crate::MutualCreditScheduler::run_recurring)
+//! as ordinary local spends, so they work offline and are bounded by the
+//! device escrow like any other payment. Occurrences missed while the device
+//! was not running are caught up, oldest first.
+//!
+//! When escrow cannot cover an occurrence, the order's [`ShortfallPolicy`]
+//! decides: `Skip` gives up on that occurrence and moves on, `Defer` keeps
+//! retrying it on later runs (holding back the occurrences after it).
+//!
+//! Paid occurrences are recorded in the transaction ledger as
+//! [`LedgerEvent::Recurring`](crate::ledger::LedgerEvent::Recurring)
+//! entries; the order itself keeps the outcome of every occurrence.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use automerge::{transaction::Transactable, ROOT};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use vudo_state::{DocumentId, StateEngine};
+
+use crate::document::load_json;
+use crate::error::{CreditError, Result};
+use crate::transaction::{TransactionId, TransactionMetadata};
+
+/// State engine namespace of recurring payment documents
+pub const RECURRING_NAMESPACE: &str = "credit_recurring";
+
+/// When a recurring payment ends
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RecurringEnd {
+    /// Runs until cancelled
+    Never,
+
+    /// Ends after this many occurrences (paid or skipped)
+    AfterOccurrences(u32),
+
+    /// Ends after the last occurrence due at or before this time (Unix epoch seconds)
+    Until(u64),
+}
+
+impl RecurringEnd {
+    /// Check whether occurrence `number` (1-based), due at `due_at`, is scheduled
+    pub fn includes(&self, number: u32, due_at: u64) -> bool {
+        match *self {
+            RecurringEnd::Never => true,
+            RecurringEnd::AfterOccurrences(count) => number <= count,
+            RecurringEnd::Until(until) => due_at <= until,
+        }
+    }
+}
+
+/// What to do when escrow cannot cover an occurrence
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ShortfallPolicy {
+    /// Give up on the occurrence and move on to the next
+    Skip,
+
+    /// Retry the occurrence on later runs
+    Defer,
+}
+
+/// State of a recurring payment
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RecurringState {
+    /// Occurrences are being paid
+    Active,
+
+    /// End condition reached
+    Completed,
+
+    /// Cancelled by the payer
+    Cancelled,
+}
+
+impl RecurringState {
+    /// Get state as string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecurringState::Active => "active",
+            RecurringState::Completed => "completed",
+            RecurringState::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Outcome of an occurrence
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OccurrenceOutcome {
+    /// Paid by a local spend
+    Paid { transaction_id: TransactionId },
+
+    /// Skipped for lack of escrow
+    Skipped,
+
+    /// Deferred for lack of escrow, to be retried
+    Deferred,
+}
+
+/// Occurrence of a recurring payment
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Occurrence {
+    /// Recurring payment ID
+    pub payment_id: String,
+
+    /// Occurrence number (1-based)
+    pub number: u32,
+
+    /// When the occurrence was due (Unix epoch seconds)
+    pub due_at: u64,
+
+    /// What happened
+    pub outcome: OccurrenceOutcome,
+
+    /// When it was processed (Unix epoch seconds)
+    pub processed_at: u64,
+}
+
+/// Standing order paying a fixed amount at a fixed interval
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RecurringPayment {
+    /// Recurring payment ID
+    pub id: String,
+
+    /// Paying account
+    pub account_id: String,
+
+    /// Recipient DID
+    pub recipient: String,
+
+    /// Amount per occurrence in cents
+    pub amount: i64,
+
+    /// Seconds between occurrences
+    pub interval_secs: u64,
+
+    /// End condition
+    pub end: RecurringEnd,
+
+    /// Handling of occurrences escrow cannot cover
+    pub on_shortfall: ShortfallPolicy,
+
+    /// Metadata of the payment transactions
+    pub metadata: TransactionMetadata,
+
+    /// State
+    pub state: RecurringState,
+
+    /// Number of the next occurrence (1-based)
+    pub next_occurrence: u32,
+
+    /// When the next occurrence is due (Unix epoch seconds)
+    pub next_due: u64,
+
+    /// Paid and skipped occurrences, oldest first
+    pub history: Vec<Occurrence>,
+}
+
+impl RecurringPayment {
+    /// Create a recurring payment starting now, running until cancelled
+    pub fn new(
+        account_id: impl Into<String>,
+        recipient: impl Into<String>,
+        amount: i64,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            account_id: account_id.into(),
+            recipient: recipient.into(),
+            amount,
+            interval_secs: interval.as_secs(),
+            end: RecurringEnd::Never,
+            on_shortfall: ShortfallPolicy::Skip,
+            metadata: TransactionMetadata::default(),
+            state: RecurringState::Active,
+            next_occurrence: 1,
+            next_due: Utc::now().timestamp() as u64,
+            history: Vec::new(),
+        }
+    }
+
+    /// Set when the first occurrence is due (Unix epoch seconds)
+    pub fn starting_at(mut self, start: u64) -> Self {
+        self.next_due = start;
+        self
+    }
+
+    /// Set the end condition
+    pub fn with_end(mut self, end: RecurringEnd) -> Self {
+        self.end = end;
+        self
+    }
+
+    /// Set the handling of occurrences escrow cannot cover
+    pub fn with_shortfall_policy(mut self, policy: ShortfallPolicy) -> Self {
+        self.on_shortfall = policy;
+        self
+    }
+
+    /// Set the metadata of the payment transactions
+    pub fn with_metadata(mut self, metadata: TransactionMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Check whether the next occurrence is due at `now`
+    pub fn is_due(&self, now: u64) -> bool {
+        self.state == RecurringState::Active && self.next_due <= now
+    }
+
+    /// Number of paid occurrences
+    pub fn paid_count(&self) -> usize {
+        self.history
+            .iter()
+            .filter(|o| matches!(o.outcome, OccurrenceOutcome::Paid { .. }))
+            .count()
+    }
+
+    /// Record the outcome of the next occurrence and move past it
+    ///
+    /// Marks the payment completed once its end condition is reached.
+    pub(crate) fn advance(&mut self, outcome: OccurrenceOutcome, now: u64) -> Occurrence {
+        let occurrence = Occurrence {
+            payment_id: self.id.clone(),
+            number: self.next_occurrence,
+            due_at: self.next_due,
+            outcome,
+            processed_at: now,
+        };
+        self.history.push(occurrence.clone());
+        self.next_occurrence += 1;
+        self.next_due += self.interval_secs;
+        self.complete_if_ended();
+        occurrence
+    }
+
+    /// Mark the payment completed if no occurrence is left
+    pub(crate) fn complete_if_ended(&mut self) {
+        if self.state == RecurringState::Active
+            && !self.end.includes(self.next_occurrence, self.next_due)
+        {
+            self.state = RecurringState::Completed;
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.amount <= 0 {
+            return Err(CreditError::InvalidOperation(
+                "Recurring payment amount must be positive".to_string(),
+            ));
+        }
+        if self.interval_secs == 0 {
+            return Err(CreditError::InvalidOperation(
+                "Recurring payment interval must be at least one second".to_string(),
+            ));
+        }
+        if self.account_id == self.recipient {
+            return Err(CreditError::InvalidOperation(
+                "Recurring payment cannot pay its own account".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Recurring payments stored in the state engine, one document per order
+pub struct RecurringPayments {
+    /// Order storage
+    state_engine: Arc<StateEngine>,
+}
+
+impl RecurringPayments {
+    /// Create a store over a state engine
+    pub fn new(state_engine: Arc<StateEngine>) -> Self {
+        Self { state_engine }
+    }
+
+    /// Validate and store a new recurring payment
+    pub async fn create(&self, payment: RecurringPayment) -> Result<RecurringPayment> {
+        payment.validate()?;
+        let mut payment = payment;
+        payment.complete_if_ended();
+        self.save(&payment).await?;
+        Ok(payment)
+    }
+
+    /// Get a recurring payment by ID
+    pub async fn get(&self, id: &str) -> Result<Option<RecurringPayment>> {
+        let doc_id = DocumentId::new(RECURRING_NAMESPACE, id);
+        load_json(&self.state_engine, &doc_id).await
+    }
+
+    /// List every recurring payment, or only those of one account
+    pub async fn list(&self, account_id: Option<&str>) -> Result<Vec<RecurringPayment>> {
+        let mut payments = Vec::new();
+        for metadata in self
+            .state_engine
+            .list_documents(RECURRING_NAMESPACE)
+            .await?
+        {
+            if let Some(payment) = self.get(&metadata.id.key).await? {
+                if account_id.map_or(true, |account| payment.account_id == account) {
+                    payments.push(payment);
+                }
+            }
+        }
+        Ok(payments)
+    }
+
+    /// Cancel a recurring payment
+    pub async fn cancel(&self, id: &str) -> Result<RecurringPayment> {
+        let mut payment = self.get(id).await?.ok_or_else(|| {
+            CreditError::InvalidOperation(format!("Unknown recurring payment: {}", id))
+        })?;
+        if payment.state == RecurringState::Active {
+            payment.state = RecurringState::Cancelled;
+            self.save(&payment).await?;
+        }
+        Ok(payment)
+    }
+
+    /// Write a recurring payment to the state engine
+    pub(crate) async fn save(&self, payment: &RecurringPayment) -> Result<()> {
+        let doc_id = DocumentId::new(RECURRING_NAMESPACE, &payment.id);
+        let handle = match self.state_engine.get_document(&doc_id).await {
+            Ok(handle) => handle,
+            Err(_) => self.state_engine.create_document(doc_id).await?,
+        };
+
+        let json = serde_json::to_string(payment)?;
+        let account_id = payment.account_id.clone();
+        let state = payment.state.as_str();
+        let next_due = payment.next_due as i64;
+        handle.update(|tx| {
+            tx.put(ROOT, "account_id", account_id)?;
+            tx.put(ROOT, "state", state)?;
+            tx.put(ROOT, "next_due", next_due)?;
+            tx.put(ROOT, "data_json", json)?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monthly() -> RecurringPayment {
+        RecurringPayment::new(
+            "alice",
+            "landlord",
+            50_000,
+            Duration::from_secs(30 * 86_400),
+        )
+        .starting_at(1_700_000_000)
+    }
+
+    #[test]
+    fn test_end_conditions() {
+        let mut payment = monthly().with_end(RecurringEnd::AfterOccurrences(2));
+        payment.advance(OccurrenceOutcome::Skipped, 1_700_000_000);
+        assert_eq!(payment.state, RecurringState::Active);
+        assert_eq!(payment.next_due, 1_700_000_000 + 30 * 86_400);
+        payment.advance(OccurrenceOutcome::Skipped, 1_700_000_000);
+        assert_eq!(payment.state, RecurringState::Completed);
+        assert_eq!(payment.history.len(), 2);
+
+        let mut payment = monthly().with_end(RecurringEnd::Until(1_700_000_000 + 45 * 86_400));
+        payment.advance(OccurrenceOutcome::Skipped, 1_700_000_000);
+        assert_eq!(payment.state, RecurringState::Active);
+        payment.advance(OccurrenceOutcome::Skipped, 1_700_000_000);
+        assert_eq!(payment.state, RecurringState::Completed);
+    }
+
+    #[tokio::test]
+    async fn test_store() {
+        let store = RecurringPayments::new(Arc::new(StateEngine::new().await.unwrap()));
+        assert!(store
+            .create(RecurringPayment::new(
+                "alice",
+                "alice",
+                100,
+                Duration::from_secs(60)
+            ))
+            .await
+            .is_err());
+        assert!(store
+            .create(RecurringPayment::new("alice", "bob", 100, Duration::ZERO))
+            .await
+            .is_err());
+
+        let rent = store.create(monthly()).await.unwrap();
+        store
+            .create(RecurringPayment::new(
+                "carol",
+                "bob",
+                100,
+                Duration::from_secs(60),
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(store.list(None).await.unwrap().len(), 2);
+        assert_eq!(store.list(Some("alice")).await.unwrap(), vec![rent.clone()]);
+
+        let cancelled = store.cancel(&rent.id).await.unwrap();
+        assert_eq!(cancelled.state, RecurringState::Cancelled);
+        assert!(!store.get(&rent.id).await.unwrap().unwrap().is_due(u64::MAX));
+        assert!(store.cancel("missing").await.is_err());
+    }
+}
@@ -1,8 +1,41 @@
 //! Reputation tier system for credit limits
-
+//!
+//! Tiers move with an account's reputation score:
+//!
+//! - **Decay**: the score halves every `half_life_secs`, so reputation must
+//!   be kept up by continued good behavior
+//! - **Reconciliation history**: clean reconciliations add points, overdrafts
+//!   subtract them and drop the tier by one
+//! - **Endorsements**: signed peer endorsements add points weighted by the
+//!   endorser's tier; tier 0 accounts carry no weight, so fresh Sybil
+//!   identities cannot vouch for each other
+//!
+//! An account moves up one tier once its score reaches the next threshold
+//! and its last `min_clean_streak` reconciliations were clean, and down one
+//! tier when its score falls below its current threshold.
+//!
+//! Committee members sign [`ReputationAttestation`]s, which peers check
+//! against the committee keys before accepting large payments:
+//!
+//! ```text
+//! message = BLAKE3("vudo-credit/reputation-attestation/v1" || account_id || tier || ...)
+//! ```
+
+use std::collections::HashMap;
+
+use dashmap::DashMap;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
 use serde::{Deserialize, Serialize};
+use vudo_identity::Did;
 
 use crate::error::{CreditError, Result};
+use crate::proof::{valid_signers, CommitteeKeys, ProofSignature, ProofSigner};
+
+/// Domain separation tag for endorsement messages
+const ENDORSEMENT_DOMAIN: &[u8] = b"vudo-credit/endorsement/v1";
+
+/// Domain separation tag for reputation attestation messages
+const ATTESTATION_DOMAIN: &[u8] = b"vudo-credit/reputation-attestation/v1";
 
 /// Reputation tier (0-5)
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -91,10 +124,440 @@ impl std::fmt::Display for ReputationTier {
     }
 }
 
-/// Reputation manager for calculating credit limits
-pub struct ReputationManager;
+/// Tunables of reputation scoring
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReputationConfig {
+    /// Seconds for a score to decay to half
+    pub half_life_secs: u64,
+
+    /// Score needed to reach tiers 1 to 5
+    pub tier_thresholds: [f64; 5],
+
+    /// Clean reconciliations in a row required to move up a tier
+    pub min_clean_streak: u32,
+
+    /// Points per clean reconciliation
+    pub reconciliation_points: f64,
+
+    /// Points lost per reconciliation with overdrafts
+    pub overdraft_penalty: f64,
+
+    /// Points per endorsement, by endorser tier (0 to 5)
+    pub endorsement_weights: [f64; 6],
+
+    /// Seconds before the same endorser may endorse an account again
+    pub endorsement_cooldown_secs: u64,
+
+    /// Seconds an attestation stays valid
+    pub attestation_ttl_secs: u64,
+}
+
+impl Default for ReputationConfig {
+    fn default() -> Self {
+        Self {
+            half_life_secs: 180 * 86_400,
+            tier_thresholds: [10.0, 50.0, 200.0, 800.0, 3_200.0],
+            min_clean_streak: 3,
+            reconciliation_points: 2.0,
+            overdraft_penalty: 20.0,
+            endorsement_weights: [0.0, 1.0, 4.0, 16.0, 64.0, 256.0],
+            endorsement_cooldown_secs: 30 * 86_400,
+            attestation_ttl_secs: 86_400,
+        }
+    }
+}
+
+impl ReputationConfig {
+    /// Score needed to hold a tier
+    pub fn threshold(&self, tier: ReputationTier) -> f64 {
+        match tier.value() {
+            0 => 0.0,
+            t => self.tier_thresholds[t as usize - 1],
+        }
+    }
+}
+
+/// Signed endorsement of one account by another
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Endorsement {
+    /// Endorsing account DID
+    pub endorser: String,
+
+    /// Endorsed account DID
+    pub endorsee: String,
+
+    /// Issue timestamp (Unix epoch seconds)
+    pub issued_at: u64,
+
+    /// Ed25519 signature by the endorser's DID key
+    pub signature: Vec<u8>,
+}
+
+impl Endorsement {
+    /// Create and sign an endorsement
+    pub fn sign(
+        endorser: &Did,
+        key: &SigningKey,
+        endorsee: impl Into<String>,
+        issued_at: u64,
+    ) -> Self {
+        let mut endorsement = Self {
+            endorser: endorser.as_str().to_string(),
+            endorsee: endorsee.into(),
+            issued_at,
+            signature: Vec::new(),
+        };
+        endorsement.signature = key.sign(&endorsement.message()).to_bytes().to_vec();
+        endorsement
+    }
+
+    /// Message digest signed by the endorser
+    pub fn message(&self) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(ENDORSEMENT_DOMAIN);
+        for part in [&self.endorser, &self.endorsee] {
+            hasher.update(&(part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        hasher.update(&self.issued_at.to_le_bytes());
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Verify the signature against the endorser's DID key
+    pub fn verify(&self) -> Result<()> {
+        let endorser = Did::parse(&self.endorser)?;
+        let bytes = <[u8; 64]>::try_from(self.signature.as_slice()).map_err(|_| {
+            CreditError::InvalidOperation("Invalid endorsement signature".to_string())
+        })?;
+        endorser
+            .verification_key
+            .verify(&self.message(), &Signature::from_bytes(&bytes))
+            .map_err(|_| CreditError::InvalidOperation("Invalid endorsement signature".to_string()))
+    }
+}
+
+/// Reputation state of an account
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReputationRecord {
+    /// Account ID
+    pub account_id: String,
+
+    /// Current tier
+    pub tier: ReputationTier,
+
+    /// Score as of `updated_at`
+    pub score: f64,
+
+    /// Last score update (Unix epoch seconds)
+    pub updated_at: u64,
+
+    /// Clean reconciliations
+    pub clean_reconciliations: u64,
+
+    /// Reconciliations with overdrafts
+    pub overdrafts: u64,
+
+    /// Clean reconciliations since the last overdraft
+    pub clean_streak: u32,
+
+    /// Last endorsement time, by endorser
+    pub endorsements: HashMap<String, u64>,
+}
+
+impl ReputationRecord {
+    /// Create a record at `tier`, with the score that tier requires
+    pub fn new(
+        account_id: impl Into<String>,
+        tier: ReputationTier,
+        config: &ReputationConfig,
+        now: u64,
+    ) -> Self {
+        Self {
+            account_id: account_id.into(),
+            tier,
+            score: config.threshold(tier),
+            updated_at: now,
+            clean_reconciliations: 0,
+            overdrafts: 0,
+            clean_streak: 0,
+            endorsements: HashMap::new(),
+        }
+    }
+
+    /// Score decayed to `now`
+    pub fn score_at(&self, now: u64, config: &ReputationConfig) -> f64 {
+        let elapsed = now.saturating_sub(self.updated_at) as f64;
+        self.score * 0.5f64.powf(elapsed / config.half_life_secs.max(1) as f64)
+    }
+
+    /// Decay the score to `now`
+    fn decay(&mut self, now: u64, config: &ReputationConfig) {
+        self.score = self.score_at(now, config);
+        self.updated_at = self.updated_at.max(now);
+    }
+
+    /// Move at most one tier toward the score
+    fn reevaluate(&mut self, config: &ReputationConfig) {
+        if self.tier.can_upgrade() {
+            let next = ReputationTier(self.tier.0 + 1);
+            let streak = self.clean_streak >= config.min_clean_streak;
+            if self.score >= config.threshold(next) && streak {
+                self.tier = next;
+                self.clean_streak = 0;
+                return;
+            }
+        }
+        if self.tier.can_downgrade() && self.score < config.threshold(self.tier) {
+            self.tier = ReputationTier(self.tier.0 - 1);
+        }
+    }
+}
+
+/// Committee-signed statement of an account's reputation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReputationAttestation {
+    /// Account ID
+    pub account_id: String,
+
+    /// Attested tier
+    pub tier: ReputationTier,
+
+    /// Clean reconciliations
+    pub clean_reconciliations: u64,
+
+    /// Reconciliations with overdrafts
+    pub overdrafts: u64,
+
+    /// Issue timestamp (Unix epoch seconds)
+    pub issued_at: u64,
+
+    /// Expiry timestamp (Unix epoch seconds)
+    pub expires_at: u64,
+
+    /// Member signatures
+    pub signatures: Vec<ProofSignature>,
+}
+
+impl ReputationAttestation {
+    /// Message digest signed by committee members
+    pub fn message(&self) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(ATTESTATION_DOMAIN);
+        hasher.update(&(self.account_id.len() as u64).to_le_bytes());
+        hasher.update(self.account_id.as_bytes());
+        hasher.update(&[self.tier.value()]);
+        hasher.update(&self.clean_reconciliations.to_le_bytes());
+        hasher.update(&self.overdrafts.to_le_bytes());
+        hasher.update(&self.issued_at.to_le_bytes());
+        hasher.update(&self.expires_at.to_le_bytes());
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Add a member signature
+    pub fn sign(&mut self, signer: &ProofSigner) {
+        let signature = signer.sign_digest(&self.message());
+        if !self.signatures.iter().any(|s| s.signer == signature.signer) {
+            self.signatures.push(signature);
+        }
+    }
+
+    /// Verify the attestation against the committee keys at `now`
+    ///
+    /// Requires a quorum of valid member signatures and an unexpired
+    /// attestation.
+    pub fn verify(&self, keys: &CommitteeKeys, now: u64) -> Result<()> {
+        if now >= self.expires_at {
+            return Err(CreditError::InvalidProof(format!(
+                "Reputation attestation expired at {}",
+                self.expires_at
+            )));
+        }
+
+        let valid = valid_signers(&self.message(), &self.signatures, keys);
+        if valid.len() >= keys.quorum() {
+            Ok(())
+        } else {
+            Err(CreditError::InvalidProof(format!(
+                "{} valid signatures, {} required",
+                valid.len(),
+                keys.quorum()
+            )))
+        }
+    }
+
+    /// Verify the attestation and check its tier allows a payment of `amount`
+    pub fn verify_payment(&self, keys: &CommitteeKeys, amount: i64, now: u64) -> Result<()> {
+        self.verify(keys, now)?;
+        let limit = ReputationManager::credit_limit(self.tier);
+        if amount > limit {
+            return Err(CreditError::InvalidOperation(format!(
+                "Payment of {} exceeds the {} limit of {}",
+                amount,
+                self.tier.name(),
+                limit
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Reputation manager for calculating credit limits and tracking tiers
+#[derive(Default)]
+pub struct ReputationManager {
+    /// Scoring tunables
+    config: ReputationConfig,
+
+    /// Reputation records (account ID -> record)
+    records: DashMap<String, ReputationRecord>,
+}
 
 impl ReputationManager {
+    /// Create a manager with the given scoring tunables
+    pub fn new(config: ReputationConfig) -> Self {
+        Self {
+            config,
+            records: DashMap::new(),
+        }
+    }
+
+    /// Get the scoring tunables
+    pub fn config(&self) -> &ReputationConfig {
+        &self.config
+    }
+
+    /// Get an account's record
+    pub fn record(&self, account_id: &str) -> Option<ReputationRecord> {
+        self.records.get(account_id).map(|r| r.clone())
+    }
+
+    /// Restore a previously saved record
+    pub fn restore(&self, record: ReputationRecord) {
+        self.records.insert(record.account_id.clone(), record);
+    }
+
+    /// Get an account's tier (tier 0 if unknown)
+    pub fn tier(&self, account_id: &str) -> ReputationTier {
+        self.records
+            .get(account_id)
+            .map(|r| r.tier)
+            .unwrap_or_default()
+    }
+
+    /// Get an account's score decayed to `now`
+    pub fn score(&self, account_id: &str, now: u64) -> f64 {
+        self.records
+            .get(account_id)
+            .map(|r| r.score_at(now, &self.config))
+            .unwrap_or(0.0)
+    }
+
+    /// Apply decay up to `now`, moving the tier down if the score lapsed
+    pub fn decay(&self, account_id: &str, now: u64) -> ReputationTier {
+        let Some(mut record) = self.records.get_mut(account_id) else {
+            return ReputationTier::default();
+        };
+        record.decay(now, &self.config);
+        record.reevaluate(&self.config);
+        record.tier
+    }
+
+    /// Record the outcome of a reconciliation and return the new tier
+    ///
+    /// `current` seeds the record of an account seen for the first time.
+    pub fn record_reconciliation(
+        &self,
+        account_id: &str,
+        current: ReputationTier,
+        clean: bool,
+        now: u64,
+    ) -> ReputationTier {
+        let mut record = self
+            .records
+            .entry(account_id.to_string())
+            .or_insert_with(|| ReputationRecord::new(account_id, current, &self.config, now));
+        record.decay(now, &self.config);
+
+        if clean {
+            record.score += self.config.reconciliation_points;
+            record.clean_reconciliations += 1;
+            record.clean_streak += 1;
+            record.reevaluate(&self.config);
+        } else {
+            record.score = (record.score - self.config.overdraft_penalty).max(0.0);
+            record.overdrafts += 1;
+            record.clean_streak = 0;
+            if record.tier.can_downgrade() {
+                record.tier = ReputationTier(record.tier.0 - 1);
+            }
+        }
+
+        record.tier
+    }
+
+    /// Apply a peer endorsement and return the endorsee's new tier
+    ///
+    /// The endorsement is weighted by the endorser's tier. Endorsements from
+    /// tier 0 or unknown accounts, self-endorsements and repeats within the
+    /// cooldown are rejected.
+    pub fn endorse(&self, endorsement: &Endorsement, now: u64) -> Result<ReputationTier> {
+        if endorsement.endorser == endorsement.endorsee {
+            return Err(CreditError::InvalidOperation(
+                "Accounts cannot endorse themselves".to_string(),
+            ));
+        }
+        endorsement.verify()?;
+
+        let endorser_tier = self.tier(&endorsement.endorser);
+        let weight = self.config.endorsement_weights[endorser_tier.value() as usize];
+        if weight <= 0.0 {
+            return Err(CreditError::InvalidOperation(format!(
+                "Endorser tier {} carries no weight",
+                endorser_tier
+            )));
+        }
+
+        let mut record = self
+            .records
+            .entry(endorsement.endorsee.clone())
+            .or_insert_with(|| {
+                ReputationRecord::new(
+                    endorsement.endorsee.clone(),
+                    ReputationTier::default(),
+                    &self.config,
+                    now,
+                )
+            });
+        if let Some(last) = record.endorsements.get(&endorsement.endorser) {
+            if now < last + self.config.endorsement_cooldown_secs {
+                return Err(CreditError::InvalidOperation(
+                    "Endorser already endorsed this account recently".to_string(),
+                ));
+            }
+        }
+
+        record.decay(now, &self.config);
+        record.score += weight;
+        record
+            .endorsements
+            .insert(endorsement.endorser.clone(), now);
+        record.reevaluate(&self.config);
+        Ok(record.tier)
+    }
+
+    /// Build an unsigned attestation of an account's current reputation
+    pub fn attest(&self, account_id: &str, now: u64) -> ReputationAttestation {
+        let record = self.record(account_id);
+        ReputationAttestation {
+            account_id: account_id.to_string(),
+            tier: record.as_ref().map(|r| r.tier).unwrap_or_default(),
+            clean_reconciliations: record.as_ref().map_or(0, |r| r.clean_reconciliations),
+            overdrafts: record.as_ref().map_or(0, |r| r.overdrafts),
+            issued_at: now,
+            expires_at: now + self.config.attestation_ttl_secs,
+            signatures: Vec::new(),
+        }
+    }
+
     /// Get credit limit in cents based on reputation tier
     pub fn credit_limit(tier: ReputationTier) -> i64 {
         match tier.value() {
@@ -270,4 +733,124 @@ mod tests {
             "$1000.00"
         );
     }
+
+    fn identity(seed: u8) -> (Did, SigningKey) {
+        let key = SigningKey::from_bytes(&[seed; 32]);
+        (Did::from_key(key.verifying_key()), key)
+    }
+
+    fn tier(value: u8) -> ReputationTier {
+        ReputationTier::new(value).unwrap()
+    }
+
+    #[test]
+    fn test_score_decay() {
+        let manager = ReputationManager::default();
+        let half_life = manager.config().half_life_secs;
+        manager.restore(ReputationRecord::new("alice", tier(2), manager.config(), 0));
+
+        assert_eq!(manager.score("alice", 0), 50.0);
+        assert!((manager.score("alice", half_life) - 25.0).abs() < 1e-9);
+
+        // A lapsed score drops the tier
+        assert_eq!(manager.decay("alice", half_life), tier(1));
+        assert_eq!(manager.tier("unknown"), tier(0));
+    }
+
+    #[test]
+    fn test_tier_progression() {
+        let manager = ReputationManager::new(ReputationConfig {
+            reconciliation_points: 20.0,
+            ..ReputationConfig::default()
+        });
+
+        // Score alone is not enough without a clean streak
+        assert_eq!(
+            manager.record_reconciliation("alice", tier(0), true, 0),
+            tier(0)
+        );
+        assert_eq!(
+            manager.record_reconciliation("alice", tier(0), true, 1),
+            tier(0)
+        );
+        assert_eq!(
+            manager.record_reconciliation("alice", tier(0), true, 2),
+            tier(1)
+        );
+
+        // Overdrafts drop a tier and reset the streak
+        assert_eq!(
+            manager.record_reconciliation("alice", tier(1), false, 3),
+            tier(0)
+        );
+        let record = manager.record("alice").unwrap();
+        assert_eq!(record.clean_reconciliations, 3);
+        assert_eq!(record.overdrafts, 1);
+        assert_eq!(record.clean_streak, 0);
+    }
+
+    #[test]
+    fn test_endorsements_weighted_by_tier() {
+        let manager = ReputationManager::default();
+        let (alice, alice_key) = identity(1);
+        let (bob, bob_key) = identity(2);
+        let (carol, _) = identity(3);
+        manager.restore(ReputationRecord::new(
+            alice.as_str(),
+            tier(3),
+            manager.config(),
+            0,
+        ));
+
+        let endorsement = Endorsement::sign(&alice, &alice_key, carol.as_str(), 0);
+        manager.endorse(&endorsement, 0).unwrap();
+        assert_eq!(manager.score(carol.as_str(), 0), 16.0);
+
+        // Repeats within the cooldown are rejected
+        assert!(manager.endorse(&endorsement, 1).is_err());
+
+        // Tier 0 endorsers carry no weight (Sybil resistance)
+        let sybil = Endorsement::sign(&bob, &bob_key, carol.as_str(), 0);
+        assert!(manager.endorse(&sybil, 0).is_err());
+
+        // Self-endorsement and forged signatures are rejected
+        let own = Endorsement::sign(&alice, &alice_key, alice.as_str(), 0);
+        assert!(manager.endorse(&own, 0).is_err());
+        let mut forged = Endorsement::sign(&bob, &bob_key, carol.as_str(), 0);
+        forged.endorser = alice.as_str().to_string();
+        assert!(forged.verify().is_err());
+    }
+
+    #[test]
+    fn test_attestation_verify() {
+        let signers: Vec<ProofSigner> = (0..4)
+            .map(|i| ProofSigner::new(format!("member{}", i), SigningKey::from_bytes(&[i + 1; 32])))
+            .collect();
+        let members = signers
+            .iter()
+            .map(|s| (s.did().to_string(), s.verifying_key()))
+            .collect();
+        let keys = CommitteeKeys::new(1, members).unwrap();
+
+        let manager = ReputationManager::default();
+        manager.restore(ReputationRecord::new("alice", tier(2), manager.config(), 0));
+        let mut attestation = manager.attest("alice", 100);
+        assert_eq!(attestation.tier, tier(2));
+
+        attestation.sign(&signers[0]);
+        attestation.sign(&signers[1]);
+        assert!(attestation.verify(&keys, 100).is_err());
+        attestation.sign(&signers[2]);
+        attestation.verify(&keys, 100).unwrap();
+
+        // Payments above the attested tier's limit are refused
+        attestation.verify_payment(&keys, 10_000, 100).unwrap();
+        assert!(attestation.verify_payment(&keys, 10_001, 100).is_err());
+
+        // Expired or tampered attestations fail
+        assert!(attestation.verify(&keys, attestation.expires_at).is_err());
+        let mut tampered = attestation.clone();
+        tampered.tier = tier(5);
+        assert!(tampered.verify(&keys, 100).is_err());
+    }
 }
@@ -3,13 +3,29 @@
 use std::sync::Arc;
 use std::time::Instant;
 
+use ed25519_dalek::SigningKey;
+use vudo_identity::Did;
 use vudo_state::StateEngine;
 
 use crate::account::CreditAccountHandle;
-use crate::bft::BftCommittee;
+use crate::bft::{BftCommittee, QuorumCertificate};
+use crate::dispute::{
+    Dispute, DisputeOutcome, DisputeResolution, DisputeRoute, DisputeState, DisputeVote, Disputes,
+    DISPUTE_CATEGORY,
+};
 use crate::error::{CreditError, Result};
 use crate::escrow::{DeviceEscrow, EscrowManager};
+use crate::guarantee::GuaranteeGroups;
+use crate::invoice::{InvoiceBook, InvoiceStatus, PaymentAcceptance, PaymentRequest};
+use crate::ledger::{LedgerEvent, TransactionLedger};
+use crate::monitor::FraudMonitor;
 use crate::overdraft::{Overdraft, OverdraftResolution, OverdraftResolver};
+use crate::policy::CreditPolicy;
+use crate::recurring::{
+    Occurrence, OccurrenceOutcome, RecurringPayment, RecurringPayments, RecurringState,
+    ShortfallPolicy,
+};
+use crate::reputation::ReputationManager;
 use crate::transaction::{Transaction, TransactionId, TransactionMetadata, TransactionStatus};
 
 /// Mutual credit scheduler
@@ -23,6 +39,27 @@ pub struct MutualCreditScheduler {
     /// Local device escrow manager
     escrow_manager: Arc<EscrowManager>,
 
+    /// Append-only transaction history
+    ledger: Arc<TransactionLedger>,
+
+    /// Payment requests and their payments
+    invoices: Arc<InvoiceBook>,
+
+    /// Recurring payments (standing orders)
+    recurring: Arc<RecurringPayments>,
+
+    /// Reputation scores and tier progression
+    reputation: Arc<ReputationManager>,
+
+    /// Mutual guarantee groups backing overdrafts
+    guarantees: Arc<GuaranteeGroups>,
+
+    /// Disputed payments and their evidence
+    disputes: Arc<Disputes>,
+
+    /// Metrics and fraud anomaly detection
+    monitor: Arc<FraudMonitor>,
+
     /// Device ID
     device_id: String,
 
@@ -38,6 +75,13 @@ impl MutualCreditScheduler {
         device_id: String,
     ) -> Result<Self> {
         Ok(Self {
+            ledger: Arc::new(TransactionLedger::new(Arc::clone(&state_engine))),
+            invoices: Arc::new(InvoiceBook::new(Arc::clone(&state_engine))),
+            recurring: Arc::new(RecurringPayments::new(Arc::clone(&state_engine))),
+            reputation: Arc::new(ReputationManager::default()),
+            guarantees: Arc::new(GuaranteeGroups::new(Arc::clone(&state_engine))),
+            disputes: Arc::new(Disputes::new(Arc::clone(&state_engine))),
+            monitor: Arc::new(FraudMonitor::default()),
             state_engine,
             bft_committee,
             escrow_manager: Arc::new(EscrowManager::new()),
@@ -53,6 +97,25 @@ impl MutualCreditScheduler {
         amount: i64,
         recipient: &str,
         metadata: TransactionMetadata,
+    ) -> Result<TransactionId> {
+        self.spend(
+            account_id,
+            amount,
+            recipient,
+            metadata,
+            LedgerEvent::Recorded,
+        )
+        .await
+    }
+
+    /// Local spend, recorded in the ledger as `event`
+    async fn spend(
+        &self,
+        account_id: &str,
+        amount: i64,
+        recipient: &str,
+        metadata: TransactionMetadata,
+        event: LedgerEvent,
     ) -> Result<TransactionId> {
         let start = Instant::now();
 
@@ -84,11 +147,17 @@ impl MutualCreditScheduler {
         // 5. Add transaction to account
         let account = CreditAccountHandle::load(&self.state_engine, account_id).await?;
         account.update(|acc| {
-            acc.add_transaction(tx);
+            acc.add_transaction(tx.clone());
             Ok(())
         })?;
 
-        // 6. Check if escrow refresh needed
+        // 6. Record in ledger
+        self.ledger.append(account_id, event, &tx).await?;
+
+        // 7. Check for fraud anomalies
+        self.monitor.observe(&tx);
+
+        // 8. Check if escrow refresh needed
         if self
             .escrow_manager
             .is_low(account_id, &self.device_id, self.escrow_low_threshold_percent)?
@@ -109,10 +178,143 @@ impl MutualCreditScheduler {
         Ok(tx_id)
     }
 
+    /// Pay a payment request from local escrow
+    ///
+    /// Verifies the request, spends its amount to the payee with the request
+    /// ID as the transaction's `invoice_id`, and returns the payer's signed
+    /// acceptance for the payee. Reconciliation marks the invoice paid once
+    /// the transaction is confirmed.
+    pub async fn pay_request(
+        &self,
+        account_id: &str,
+        request: &PaymentRequest,
+        payer: &Did,
+        payer_key: &SigningKey,
+    ) -> Result<PaymentAcceptance> {
+        if request.payer != account_id {
+            return Err(CreditError::InvalidPaymentRequest(format!(
+                "Request is addressed to {}, not {}",
+                request.payer, account_id
+            )));
+        }
+
+        let invoice = self.invoices.store(request).await?;
+        if invoice.status != InvoiceStatus::Open {
+            return Err(CreditError::InvalidPaymentRequest(format!(
+                "Request {} is already {}",
+                request.request_id,
+                invoice.status.as_str()
+            )));
+        }
+        request.verify()?;
+
+        let tx_id = self
+            .spend_local(
+                account_id,
+                request.amount,
+                &request.payee,
+                TransactionMetadata {
+                    description: request.memo.clone(),
+                    category: None,
+                    invoice_id: Some(request.request_id.clone()),
+                },
+            )
+            .await?;
+
+        let acceptance = request.accept(payer, payer_key, tx_id)?;
+        self.invoices.record_acceptance(&acceptance).await?;
+        Ok(acceptance)
+    }
+
+    /// Schedule a recurring payment
+    pub async fn schedule_recurring(&self, payment: RecurringPayment) -> Result<RecurringPayment> {
+        self.recurring.create(payment).await
+    }
+
+    /// Cancel a recurring payment
+    pub async fn cancel_recurring(&self, payment_id: &str) -> Result<RecurringPayment> {
+        self.recurring.cancel(payment_id).await
+    }
+
+    /// Pay the recurring payments due at `now` (Unix epoch seconds)
+    ///
+    /// Each due occurrence is a local spend from this device's escrow,
+    /// oldest first. Occurrences the escrow cannot cover are skipped or
+    /// deferred by the payment's shortfall policy. Returns the outcome of
+    /// every occurrence processed, deferred ones included.
+    pub async fn run_recurring(&self, now: u64) -> Result<Vec<Occurrence>> {
+        let mut processed = Vec::new();
+
+        for mut payment in self.recurring.list(None).await? {
+            if !payment.is_due(now) {
+                continue;
+            }
+
+            while payment.is_due(now) {
+                let metadata = payment.metadata.clone();
+                let event = LedgerEvent::Recurring {
+                    payment_id: payment.id.clone(),
+                    occurrence: payment.next_occurrence,
+                };
+                let outcome = match self
+                    .spend(
+                        &payment.account_id,
+                        payment.amount,
+                        &payment.recipient,
+                        metadata,
+                        event,
+                    )
+                    .await
+                {
+                    Ok(transaction_id) => OccurrenceOutcome::Paid { transaction_id },
+                    Err(
+                        CreditError::InsufficientEscrow { .. }
+                        | CreditError::NoEscrowAllocated { .. }
+                        | CreditError::EscrowExpired { .. },
+                    ) => match payment.on_shortfall {
+                        ShortfallPolicy::Skip => OccurrenceOutcome::Skipped,
+                        ShortfallPolicy::Defer => OccurrenceOutcome::Deferred,
+                    },
+                    Err(e) => {
+                        self.recurring.save(&payment).await?;
+                        return Err(e);
+                    }
+                };
+
+                if outcome == OccurrenceOutcome::Deferred {
+                    processed.push(Occurrence {
+                        payment_id: payment.id.clone(),
+                        number: payment.next_occurrence,
+                        due_at: payment.next_due,
+                        outcome,
+                        processed_at: now,
+                    });
+                    break;
+                }
+                processed.push(payment.advance(outcome, now));
+            }
+
+            if payment.state == RecurringState::Completed {
+                tracing::info!("Recurring payment {} completed", payment.id);
+            }
+            self.recurring.save(&payment).await?;
+        }
+
+        Ok(processed)
+    }
+
     /// Request escrow refresh from BFT committee
     pub async fn request_escrow_refresh(&self, account_id: &str) -> Result<()> {
         tracing::info!("Requesting escrow refresh for {}", account_id);
 
+        // Frozen accounts wait for committee review
+        if let Some(alert_id) = self.monitor.frozen_by(account_id) {
+            return Err(CreditError::EscrowRefreshFrozen {
+                account_id: account_id.to_string(),
+                alert_id,
+            });
+        }
+
         // Load account
         let account = CreditAccountHandle::load(&self.state_engine, account_id).await?;
 
@@ -176,6 +378,7 @@ impl MutualCreditScheduler {
         // Load account
         let account = CreditAccountHandle::load(&self.state_engine, account_id).await?;
 
+        let mut changed = None;
         match resolution {
             OverdraftResolution::Reverse => {
                 // Reverse transaction
@@ -191,7 +394,9 @@ impl MutualCreditScheduler {
                                 to: TransactionStatus::Reversed.as_str().to_string(),
                             });
                         }
+                        let from = tx.status;
                         tx.status = TransactionStatus::Reversed;
+                        changed = Some((from, tx.clone()));
 
                         // Refund to escrow
                         if let Some(escrow) = acc.escrows.get_mut(&self.device_id) {
@@ -231,25 +436,318 @@ impl MutualCreditScheduler {
                                 to: TransactionStatus::Disputed.as_str().to_string(),
                             });
                         }
+                        let from = tx.status;
                         tx.status = TransactionStatus::Disputed;
+                        changed = Some((from, tx.clone()));
                     }
                     Ok(())
                 })?;
             }
+            OverdraftResolution::Guarantee { group_id } => {
+                // Cover the deficit from the group's pledges
+                let claim = self
+                    .guarantees
+                    .cover(&group_id, account_id, overdraft)
+                    .await?;
+
+                // Record the guarantors' loss shares in the ledger
+                for share in &claim.shares {
+                    let Some(tx_id) = &share.transaction_id else {
+                        continue;
+                    };
+                    let guarantor =
+                        CreditAccountHandle::load(&self.state_engine, &share.member).await?;
+                    let tx = guarantor.read(|acc| Ok(acc.get_transaction(tx_id).cloned()))?;
+                    if let Some(tx) = tx {
+                        self.ledger.record(&share.member, &tx).await?;
+                    }
+                }
+            }
+        }
+
+        // Record the status change in the ledger
+        if let Some((from, tx)) = changed {
+            self.ledger
+                .record_status_change(account_id, from, &tx)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Dispute a transaction
+    ///
+    /// Marks the transaction disputed, which holds it out of reconciliation
+    /// until it is confirmed or reversed.
+    pub async fn dispute_transaction(&self, account_id: &str, tx_id: &str) -> Result<()> {
+        let account = CreditAccountHandle::load(&self.state_engine, account_id).await?;
+
+        let mut changed = None;
+        account.update(|acc| {
+            let tx = acc
+                .transactions
+                .iter_mut()
+                .find(|tx| tx.id == tx_id)
+                .ok_or_else(|| CreditError::TransactionNotFound(tx_id.to_string()))?;
+            if !tx.status.can_transition_to(TransactionStatus::Disputed) {
+                return Err(CreditError::InvalidStatusTransition {
+                    from: tx.status.as_str().to_string(),
+                    to: TransactionStatus::Disputed.as_str().to_string(),
+                });
+            }
+            let from = tx.status;
+            tx.status = TransactionStatus::Disputed;
+            changed = Some((from, tx.clone()));
+            Ok(())
+        })?;
+
+        // Record the status change in the ledger
+        if let Some((from, tx)) = changed {
+            self.ledger
+                .record_status_change(account_id, from, &tx)
+                .await?;
         }
 
         Ok(())
     }
 
+    /// Open a dispute on a payment by `account_id`
+    ///
+    /// Marks the transaction disputed and stores the dispute for the
+    /// committee or guarantee group it is routed to.
+    pub async fn open_dispute(
+        &self,
+        account_id: &str,
+        tx_id: &str,
+        reason: impl Into<String>,
+        route: DisputeRoute,
+    ) -> Result<Dispute> {
+        if let DisputeRoute::GuaranteeGroup { group_id } = &route {
+            if self.guarantees.get(group_id).await?.is_none() {
+                return Err(CreditError::InvalidDispute(format!(
+                    "Unknown guarantee group: {}",
+                    group_id
+                )));
+            }
+        }
+
+        let account = CreditAccountHandle::load(&self.state_engine, account_id).await?;
+        let tx = account
+            .read(|acc| Ok(acc.get_transaction(tx_id).cloned()))?
+            .ok_or_else(|| CreditError::TransactionNotFound(tx_id.to_string()))?;
+        let dispute = Dispute::new(account_id, &tx, reason, route)?;
+
+        self.dispute_transaction(account_id, tx_id).await?;
+        self.disputes.save(&dispute).await?;
+
+        tracing::info!(
+            "Dispute {} opened on {} by {}",
+            dispute.id,
+            tx_id,
+            account_id
+        );
+        Ok(dispute)
+    }
+
+    /// Resolve a committee-routed dispute
+    ///
+    /// The committee agrees on the outcome's refund in a consensus round
+    /// before the outcome is applied.
+    pub async fn rule_dispute(&self, dispute_id: &str, outcome: DisputeOutcome) -> Result<Dispute> {
+        let dispute = self.disputes.load(dispute_id).await?;
+        dispute.ensure_open()?;
+        if dispute.route != DisputeRoute::Committee {
+            return Err(CreditError::InvalidDispute(format!(
+                "Dispute {} is routed to a guarantee group",
+                dispute_id
+            )));
+        }
+        outcome.validate(dispute.amount)?;
+
+        let certificate = self
+            .bft_committee
+            .decide_dispute(dispute_id, outcome.refund(dispute.amount))
+            .await?;
+        let decided_by = certificate
+            .signers()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        self.settle_dispute(dispute, outcome, decided_by, Some(certificate))
+            .await
+    }
+
+    /// Record a guarantee group member's vote on a dispute
+    ///
+    /// Applies the outcome once a majority of the group voted for it.
+    pub async fn vote_dispute(&self, vote: DisputeVote) -> Result<Dispute> {
+        let mut dispute = self.disputes.load(&vote.dispute_id).await?;
+        let DisputeRoute::GuaranteeGroup { group_id } = &dispute.route else {
+            return Err(CreditError::InvalidDispute(format!(
+                "Dispute {} is routed to the committee",
+                dispute.id
+            )));
+        };
+        let group = self.guarantees.get(group_id).await?.ok_or_else(|| {
+            CreditError::InvalidDispute(format!("Unknown guarantee group: {}", group_id))
+        })?;
+
+        match dispute.cast_vote(vote, &group)? {
+            Some(outcome) => {
+                let decided_by = dispute
+                    .votes
+                    .iter()
+                    .filter(|v| v.outcome == outcome)
+                    .map(|v| v.member.clone())
+                    .collect();
+                self.settle_dispute(dispute, outcome, decided_by, None)
+                    .await
+            }
+            None => {
+                self.disputes.save(&dispute).await?;
+                Ok(dispute)
+            }
+        }
+    }
+
+    /// Apply a dispute outcome
+    ///
+    /// Closes the disputed transaction and pays any credits the outcome
+    /// moves in a new settlement transaction (see [`crate::dispute`]).
+    async fn settle_dispute(
+        &self,
+        mut dispute: Dispute,
+        outcome: DisputeOutcome,
+        decided_by: Vec<String>,
+        certificate: Option<QuorumCertificate>,
+    ) -> Result<Dispute> {
+        // A reconciled payment stands and the payee refunds; an unreconciled
+        // one is reversed and the payer pays what the outcome leaves owed
+        let refund = outcome.refund(dispute.amount);
+        let (closed, payer, payee, amount) =
+            if dispute.disputed_from == TransactionStatus::Confirmed {
+                (
+                    TransactionStatus::Confirmed,
+                    &dispute.counterparty,
+                    &dispute.account_id,
+                    refund,
+                )
+            } else {
+                (
+                    TransactionStatus::Reversed,
+                    &dispute.account_id,
+                    &dispute.counterparty,
+                    dispute.amount - refund,
+                )
+            };
+
+        // Load everything that changes before changing anything
+        let account = CreditAccountHandle::load(&self.state_engine, &dispute.account_id).await?;
+        let payer_account = if amount > 0 {
+            Some(CreditAccountHandle::load(&self.state_engine, payer).await?)
+        } else {
+            None
+        };
+
+        // Close the disputed transaction
+        let mut closed_tx = None;
+        account.update(|acc| {
+            let tx = acc
+                .transactions
+                .iter_mut()
+                .find(|tx| tx.id == dispute.transaction_id)
+                .ok_or_else(|| CreditError::TransactionNotFound(dispute.transaction_id.clone()))?;
+            if tx.status != TransactionStatus::Disputed {
+                return Err(CreditError::InvalidStatusTransition {
+                    from: tx.status.as_str().to_string(),
+                    to: closed.as_str().to_string(),
+                });
+            }
+            tx.status = closed;
+            closed_tx = Some(tx.clone());
+            Ok(())
+        })?;
+        if let Some(tx) = closed_tx {
+            self.ledger
+                .record_status_change(&dispute.account_id, TransactionStatus::Disputed, &tx)
+                .await?;
+        }
+
+        // Pay the settlement
+        let mut settlement = None;
+        if let Some(payer_account) = payer_account {
+            let tx = Transaction::new(
+                payer.clone(),
+                payee.clone(),
+                amount,
+                TransactionMetadata {
+                    description: format!("Dispute {} settlement", dispute.id),
+                    category: Some(DISPUTE_CATEGORY.to_string()),
+                    invoice_id: Some(dispute.id.clone()),
+                },
+            );
+            payer_account.update(|acc| {
+                acc.add_transaction(tx.clone());
+                Ok(())
+            })?;
+            if let Ok(payee_account) = CreditAccountHandle::load(&self.state_engine, payee).await {
+                payee_account.update(|acc| {
+                    acc.pending_credits += amount;
+                    Ok(())
+                })?;
+            }
+
+            let event = LedgerEvent::DisputeSettled {
+                dispute_id: dispute.id.clone(),
+                outcome,
+            };
+            for party in [payer, payee] {
+                self.ledger.append(party, event.clone(), &tx).await?;
+            }
+            settlement = Some(tx.id);
+        }
+
+        dispute.state = DisputeState::Resolved;
+        dispute.resolution = Some(DisputeResolution {
+            outcome,
+            decided_by,
+            certificate,
+            settlement,
+            resolved_at: chrono::Utc::now().timestamp() as u64,
+        });
+        self.disputes.save(&dispute).await?;
+
+        tracing::info!(
+            "Dispute {} resolved: {} ({} settled)",
+            dispute.id,
+            outcome.as_str(),
+            amount
+        );
+        Ok(dispute)
+    }
+
     /// Reconcile account via BFT committee
     pub async fn reconcile_account(&self, account_id: &str) -> Result<()> {
         tracing::info!("Starting BFT reconciliation for {}", account_id);
 
         // Load account
         let account = CreditAccountHandle::load(&self.state_engine, account_id).await?;
+        let pending_credits = account.read(|acc| Ok(acc.pending_credits))?;
+
+        // Load the community policy (and the commons account it pays into)
+        let policy = CreditPolicy::load(&self.state_engine).await?;
+        let commons = if policy.is_active() && !policy.is_commons(account_id) {
+            Some(CreditAccountHandle::load(&self.state_engine, &policy.commons_account).await?)
+        } else {
+            None
+        };
 
         // Perform BFT reconciliation
-        let result = self.bft_committee.reconcile_balance(&account).await?;
+        let now = chrono::Utc::now().timestamp() as u64;
+        let result = self
+            .bft_committee
+            .reconcile_with_policy(&account, &policy, now)
+            .await?;
 
         if !result.consensus {
             return Err(CreditError::BftConsensusFailure {
@@ -259,20 +757,39 @@ impl MutualCreditScheduler {
         }
 
         // Update confirmed balance
+        let mut confirmed = Vec::new();
         account.update(|acc| {
             acc.confirmed_balance = result.new_confirmed_balance;
-            acc.last_reconciliation = chrono::Utc::now().timestamp() as u64;
+            acc.pending_credits -= pending_credits;
+            acc.last_reconciliation = now;
 
             // Confirm pending transactions
             for tx in &mut acc.transactions {
                 if tx.status == TransactionStatus::Pending {
                     tx.status = TransactionStatus::Confirmed;
+                    confirmed.push(tx.clone());
                 }
             }
 
             Ok(())
         })?;
 
+        // Pay fees and demurrage into the commons account
+        if let Some(commons) = commons.filter(|_| result.settlement.total() > 0) {
+            commons.update(|acc| {
+                acc.pending_credits += result.settlement.total();
+                Ok(())
+            })?;
+        }
+
+        // Record confirmations in the ledger and settle paid invoices
+        for tx in &confirmed {
+            self.ledger
+                .record_status_change(account_id, TransactionStatus::Pending, tx)
+                .await?;
+            self.invoices.settle(tx).await?;
+        }
+
         // Handle overdrafts
         for overdraft in &result.overdrafts {
             let resolution = OverdraftResolver::suggest_resolution(
@@ -283,6 +800,22 @@ impl MutualCreditScheduler {
                 .await?;
         }
 
+        // Move the reputation tier with the reconciliation history
+        // (reloaded, as overdraft resolution writes through its own handle)
+        let account = CreditAccountHandle::load(&self.state_engine, account_id).await?;
+        let current = account.read(|acc| Ok(acc.reputation_tier))?;
+        let clean = result.overdrafts.is_empty();
+        let tier = self
+            .reputation
+            .record_reconciliation(account_id, current, clean, now);
+        if tier != current {
+            tracing::info!("Reputation of {}: {} -> {}", account_id, current, tier);
+            account.update(|acc| {
+                acc.reputation_tier = tier;
+                Ok(())
+            })?;
+        }
+
         tracing::info!(
             "BFT reconciliation completed for {}, new balance: {}",
             account_id,
@@ -298,6 +831,51 @@ impl MutualCreditScheduler {
         account.read(|acc| Ok(acc.confirmed_balance))
     }
 
+    /// Get the transaction ledger
+    pub fn ledger(&self) -> &TransactionLedger {
+        &self.ledger
+    }
+
+    /// Get the invoice book
+    pub fn invoices(&self) -> &InvoiceBook {
+        &self.invoices
+    }
+
+    /// Get the recurring payments
+    pub fn recurring(&self) -> &RecurringPayments {
+        &self.recurring
+    }
+
+    /// Get the reputation manager
+    pub fn reputation(&self) -> &ReputationManager {
+        &self.reputation
+    }
+
+    /// Get the guarantee groups
+    pub fn guarantees(&self) -> &GuaranteeGroups {
+        &self.guarantees
+    }
+
+    /// Get the disputes
+    pub fn disputes(&self) -> &Disputes {
+        &self.disputes
+    }
+
+    /// Get the fraud monitor
+    pub fn monitor(&self) -> &FraudMonitor {
+        &self.monitor
+    }
+
+    /// Get the account storage
+    pub fn state_engine(&self) -> &Arc<StateEngine> {
+        &self.state_engine
+    }
+
+    /// Get the device ID
+    pub fn device_id(&self) -> &str {
+        &self.device_id
+    }
+
     /// Get device escrow
     pub fn get_device_escrow(&self, account_id: &str) -> Result<DeviceEscrow> {
         self.escrow_manager.get(account_id, &self.device_id)
@@ -314,6 +892,13 @@ impl MutualCreditScheduler {
             state_engine: Arc::clone(&self.state_engine),
             bft_committee: Arc::clone(&self.bft_committee),
             escrow_manager: Arc::clone(&self.escrow_manager),
+            ledger: Arc::clone(&self.ledger),
+            invoices: Arc::clone(&self.invoices),
+            recurring: Arc::clone(&self.recurring),
+            reputation: Arc::clone(&self.reputation),
+            guarantees: Arc::clone(&self.guarantees),
+            disputes: Arc::clone(&self.disputes),
+            monitor: Arc::clone(&self.monitor),
             device_id: self.device_id.clone(),
             escrow_low_threshold_percent: self.escrow_low_threshold_percent,
         }
@@ -334,6 +919,8 @@ impl MutualCreditScheduler {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::recurring::RecurringEnd;
+    use std::time::Duration;
 
     #[tokio::test]
     async fn test_spend_local_insufficient_escrow() {
@@ -489,6 +1076,83 @@ mod tests {
         assert_eq!(balance, 9000); // 10000 - 1000
     }
 
+    #[tokio::test]
+    async fn test_run_recurring() {
+        let mut scheduler = MutualCreditScheduler::new_mock().await.unwrap();
+        // Keep escrow refreshes from topping up escrow mid-test
+        scheduler.escrow_low_threshold_percent = 0;
+        CreditAccountHandle::create(&scheduler.state_engine, "alice".to_string(), 10000)
+            .await
+            .unwrap();
+        scheduler.set_device_escrow(
+            "alice",
+            DeviceEscrow::new("test-device".to_string(), 1000, 7),
+        );
+
+        // Five occurrences due, escrow covers three
+        let rent = scheduler
+            .schedule_recurring(
+                RecurringPayment::new("alice", "bob", 300, Duration::from_secs(60))
+                    .starting_at(1_000)
+                    .with_end(RecurringEnd::AfterOccurrences(5)),
+            )
+            .await
+            .unwrap();
+        let processed = scheduler.run_recurring(1_240).await.unwrap();
+        let outcomes: Vec<bool> = processed
+            .iter()
+            .map(|o| matches!(o.outcome, OccurrenceOutcome::Paid { .. }))
+            .collect();
+        assert_eq!(outcomes, vec![true, true, true, false, false]);
+        assert_eq!(processed[4].outcome, OccurrenceOutcome::Skipped);
+
+        let rent = scheduler.recurring().get(&rent.id).await.unwrap().unwrap();
+        assert_eq!(rent.state, RecurringState::Completed);
+        assert_eq!(rent.paid_count(), 3);
+
+        let entries = scheduler.ledger().entries("alice").await.unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            entries[2].event,
+            LedgerEvent::Recurring {
+                payment_id: rent.id.clone(),
+                occurrence: 3
+            }
+        );
+
+        // Deferred occurrences wait for escrow
+        let gym = scheduler
+            .schedule_recurring(
+                RecurringPayment::new("alice", "gym", 300, Duration::from_secs(60))
+                    .starting_at(1_000)
+                    .with_shortfall_policy(ShortfallPolicy::Defer),
+            )
+            .await
+            .unwrap();
+        let processed = scheduler.run_recurring(1_060).await.unwrap();
+        assert_eq!(processed.len(), 1);
+        assert_eq!(processed[0].outcome, OccurrenceOutcome::Deferred);
+        let deferred = scheduler.recurring().get(&gym.id).await.unwrap().unwrap();
+        assert_eq!(deferred.next_occurrence, 1);
+        assert!(deferred.history.is_empty());
+
+        scheduler.set_device_escrow(
+            "alice",
+            DeviceEscrow::new("test-device".to_string(), 1000, 7),
+        );
+        let processed = scheduler.run_recurring(1_060).await.unwrap();
+        assert_eq!(processed.len(), 2);
+        assert_eq!(processed[0].due_at, 1_000);
+        assert_eq!(processed[1].due_at, 1_060);
+
+        scheduler.cancel_recurring(&gym.id).await.unwrap();
+        assert!(scheduler
+            .run_recurring(u64::MAX / 2)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
     #[tokio::test]
     async fn test_local_spend_performance() {
         let scheduler = MutualCreditScheduler::new_mock().await.unwrap();
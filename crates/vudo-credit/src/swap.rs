@@ -0,0 +1,1004 @@
+//! Escrow-backed atomic swaps between credit communities
+//!
+//! Two users in different credit systems trade without a shared committee
+//! using hash time-locked commitments (HTLC):
+//!
+//! ```text
+//! 1. Initiator picks a secret S and proposes terms with H = BLAKE3(S)
+//! 2. Initiator locks escrow in community A (payable to responder, expires 2T)
+//! 3. Responder sees the lock and locks escrow in community B (expires T)
+//! 4. Initiator claims the B lock by revealing S over gossip
+//! 5. Community A sees S and settles the A lock to the responder
+//! ```
+//!
+//! Either both locks settle or, once their timeouts pass, both refund. The
+//! responder's shorter timeout guarantees the secret is public while the
+//! initiator's lock is still claimable, so the terms must leave at least
+//! [`MIN_CLAIM_WINDOW_SECS`] between the two expiries.
+//!
+//! The terms name both parties by DID. Lock and refund announcements are
+//! signed by the party's DID key, so the responder only locks once the
+//! initiator itself has announced its lock.
+
+use std::sync::Arc;
+
+use chrono::Utc;
+use dashmap::{DashMap, DashSet};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+use vudo_identity::Did;
+use vudo_p2p::{GossipOverlay, Topic, TopicMessage, TopicSubscription};
+use vudo_state::StateEngine;
+
+use crate::account::CreditAccountHandle;
+use crate::committee::{decode_hex, encode_hex};
+use crate::error::{CreditError, Result};
+use crate::escrow::EscrowManager;
+use crate::transaction::{Transaction, TransactionId, TransactionMetadata};
+
+/// Application topic used for swap protocol messages
+pub const SWAP_TOPIC: &str = "credit:swap";
+
+/// Transaction category recorded for settled swaps
+pub const SWAP_CATEGORY: &str = "swap";
+
+/// Minimum time between the responder's and the initiator's lock expiry,
+/// left for the responder to claim once the secret is revealed
+pub const MIN_CLAIM_WINDOW_SECS: u64 = 60;
+
+/// Domain separation tag for lock announcements
+const LOCK_DOMAIN: &[u8] = b"vudo-credit/swap-lock/v1";
+
+/// Domain separation tag for refund announcements
+const REFUND_DOMAIN: &[u8] = b"vudo-credit/swap-refund/v1";
+
+/// Swap secret (hash-lock preimage)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapSecret([u8; 32]);
+
+impl SwapSecret {
+    /// Generate a random secret from the operating system RNG
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        Self(bytes)
+    }
+
+    /// Create a secret from raw bytes
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// Get the raw bytes
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Hash lock committing to this secret
+    pub fn hash_lock(&self) -> String {
+        encode_hex(blake3::hash(&self.0).as_bytes())
+    }
+
+    /// Hex encoding for transport
+    pub fn to_hex(&self) -> String {
+        encode_hex(&self.0)
+    }
+
+    /// Parse a hex-encoded secret
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        decode_hex(hex).map(Self)
+    }
+}
+
+/// Party of a swap
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum SwapSide {
+    /// Party that knows the secret and locks first
+    Initiator,
+
+    /// Party that locks second, after seeing the initiator's lock
+    Responder,
+}
+
+/// One leg of a swap: a payment within a single credit community
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SwapLeg {
+    /// Credit community the payment happens in
+    pub community: String,
+
+    /// Paying account in that community
+    pub payer: String,
+
+    /// Receiving account in that community
+    pub payee: String,
+
+    /// Amount in cents (community units)
+    pub amount: i64,
+}
+
+/// Agreed terms of a swap
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SwapTerms {
+    /// Swap ID
+    pub swap_id: String,
+
+    /// Initiator DID, signing the initiator's lock announcements
+    pub initiator: String,
+
+    /// Payment locked by the initiator
+    pub initiator_leg: SwapLeg,
+
+    /// Responder DID, signing the responder's lock announcements
+    pub responder: String,
+
+    /// Payment locked by the responder
+    pub responder_leg: SwapLeg,
+
+    /// BLAKE3 hash of the secret (hex)
+    pub hash_lock: String,
+
+    /// Initiator lock expiry (Unix epoch seconds)
+    pub initiator_expires_at: u64,
+
+    /// Responder lock expiry (Unix epoch seconds), earlier than the initiator's
+    pub responder_expires_at: u64,
+}
+
+impl SwapTerms {
+    /// Create terms for a new swap
+    ///
+    /// The responder's lock expires after `timeout_secs` and the initiator's
+    /// after twice that, leaving the responder time to claim once the secret
+    /// has been revealed.
+    pub fn new(
+        initiator: &Did,
+        initiator_leg: SwapLeg,
+        responder: &Did,
+        responder_leg: SwapLeg,
+        hash_lock: String,
+        timeout_secs: u64,
+    ) -> Self {
+        let now = Utc::now().timestamp() as u64;
+        Self {
+            swap_id: Uuid::new_v4().to_string(),
+            initiator: initiator.as_str().to_string(),
+            initiator_leg,
+            responder: responder.as_str().to_string(),
+            responder_leg,
+            hash_lock,
+            initiator_expires_at: now + timeout_secs * 2,
+            responder_expires_at: now + timeout_secs,
+        }
+    }
+
+    /// Get the DID of a party
+    pub fn party(&self, side: SwapSide) -> &str {
+        match side {
+            SwapSide::Initiator => &self.initiator,
+            SwapSide::Responder => &self.responder,
+        }
+    }
+
+    /// Get the leg locked by a party
+    pub fn leg(&self, side: SwapSide) -> &SwapLeg {
+        match side {
+            SwapSide::Initiator => &self.initiator_leg,
+            SwapSide::Responder => &self.responder_leg,
+        }
+    }
+
+    /// Get the lock expiry of a party
+    pub fn expires_at(&self, side: SwapSide) -> u64 {
+        match side {
+            SwapSide::Initiator => self.initiator_expires_at,
+            SwapSide::Responder => self.responder_expires_at,
+        }
+    }
+
+    /// Check whether a secret opens the hash lock
+    pub fn verify_secret(&self, secret: &SwapSecret) -> bool {
+        secret.hash_lock() == self.hash_lock
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.initiator_leg.amount <= 0 || self.responder_leg.amount <= 0 {
+            return Err(CreditError::SwapFailed(
+                "Swap amounts must be positive".to_string(),
+            ));
+        }
+        if self.initiator_leg.community == self.responder_leg.community {
+            return Err(CreditError::SwapFailed(
+                "Swap legs must be in different communities".to_string(),
+            ));
+        }
+        if self
+            .responder_expires_at
+            .saturating_add(MIN_CLAIM_WINDOW_SECS)
+            > self.initiator_expires_at
+        {
+            return Err(CreditError::SwapFailed(format!(
+                "Responder lock must expire at least {}s before initiator lock",
+                MIN_CLAIM_WINDOW_SECS
+            )));
+        }
+        if self.initiator == self.responder {
+            return Err(CreditError::SwapFailed(
+                "Swap parties must differ".to_string(),
+            ));
+        }
+        Did::parse(&self.initiator)?;
+        Did::parse(&self.responder)?;
+        Ok(())
+    }
+
+    /// Message digest a party signs to announce a lock or refund
+    fn announcement(&self, domain: &[u8], side: SwapSide) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(domain);
+        for part in [&self.swap_id, self.party(side), &self.hash_lock] {
+            hasher.update(&(part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Check a party's signature over a lock or refund announcement
+    fn verify_announcement(&self, domain: &[u8], side: SwapSide, signature: &[u8]) -> bool {
+        let Ok(did) = Did::parse(self.party(side)) else {
+            return false;
+        };
+        let Ok(bytes) = <[u8; 64]>::try_from(signature) else {
+            return false;
+        };
+        did.verification_key
+            .verify(
+                &self.announcement(domain, side),
+                &Signature::from_bytes(&bytes),
+            )
+            .is_ok()
+    }
+}
+
+/// State of a hash-locked escrow
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LockState {
+    /// Escrow locked, awaiting secret or timeout
+    Locked,
+
+    /// Settled to the payee
+    Claimed,
+
+    /// Returned to the payer after timeout
+    Refunded,
+}
+
+/// Escrow locked for one leg of a swap
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SwapLock {
+    /// Swap ID
+    pub swap_id: String,
+
+    /// Party that locked the escrow
+    pub side: SwapSide,
+
+    /// Device whose escrow was locked
+    pub device_id: String,
+
+    /// Lock state
+    pub state: LockState,
+
+    /// Settlement transaction (once claimed)
+    pub transaction_id: Option<TransactionId>,
+}
+
+/// Swap protocol message exchanged over gossip
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SwapMessage {
+    /// Swap proposed by the initiator
+    Proposed { terms: Box<SwapTerms> },
+
+    /// A party locked its escrow, signed by the party's DID key
+    Locked {
+        swap_id: String,
+        side: SwapSide,
+        signature: Vec<u8>,
+    },
+
+    /// Secret revealed by a claim
+    Revealed { swap_id: String, secret: String },
+
+    /// A party refunded its lock after timeout, signed by the party's DID key
+    Refunded {
+        swap_id: String,
+        side: SwapSide,
+        signature: Vec<u8>,
+    },
+}
+
+/// Atomic swap engine for one credit community
+pub struct SwapEngine {
+    /// Community this engine settles payments in
+    community: String,
+
+    /// State engine holding the community's accounts
+    state_engine: Arc<StateEngine>,
+
+    /// Escrow manager the locks draw from
+    escrow_manager: Arc<EscrowManager>,
+
+    /// Gossip overlay used for protocol messages
+    gossip: Arc<GossipOverlay>,
+
+    /// Local device ID
+    device_id: String,
+
+    /// DID and key locks are announced with
+    identity: Option<(Did, SigningKey)>,
+
+    /// Known swap terms (swap ID -> terms)
+    swaps: DashMap<String, SwapTerms>,
+
+    /// Locks held in this community
+    locks: DashMap<(String, SwapSide), SwapLock>,
+
+    /// Locks observed in other communities
+    remote_locks: DashSet<(String, SwapSide)>,
+
+    /// Revealed secrets (swap ID -> secret)
+    secrets: DashMap<String, SwapSecret>,
+}
+
+impl SwapEngine {
+    /// Create a new swap engine
+    pub fn new(
+        community: impl Into<String>,
+        state_engine: Arc<StateEngine>,
+        escrow_manager: Arc<EscrowManager>,
+        gossip: Arc<GossipOverlay>,
+        device_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            community: community.into(),
+            state_engine,
+            escrow_manager,
+            gossip,
+            device_id: device_id.into(),
+            identity: None,
+            swaps: DashMap::new(),
+            locks: DashMap::new(),
+            remote_locks: DashSet::new(),
+            secrets: DashMap::new(),
+        }
+    }
+
+    /// Lock and refund legs as `did`
+    ///
+    /// Only the party the terms name for a leg can lock it.
+    pub fn with_identity(mut self, did: Did, signing_key: SigningKey) -> Self {
+        self.identity = Some((did, signing_key));
+        self
+    }
+
+    /// Application topic for swap messages
+    pub fn topic() -> Topic {
+        Topic::app(SWAP_TOPIC)
+    }
+
+    /// Community this engine serves
+    pub fn community(&self) -> &str {
+        &self.community
+    }
+
+    /// Subscribe to swap messages
+    pub async fn subscribe(&self) -> Result<TopicSubscription> {
+        self.gossip
+            .subscribe_application(Self::topic())
+            .await
+            .map_err(|e| CreditError::P2p(e.to_string()))
+    }
+
+    /// Propose a swap to the counterparty
+    pub async fn propose(&self, terms: SwapTerms) -> Result<()> {
+        terms.validate()?;
+        self.swaps.insert(terms.swap_id.clone(), terms.clone());
+        self.publish(&SwapMessage::Proposed {
+            terms: Box::new(terms),
+        })
+        .await
+    }
+
+    /// Get the terms of a known swap
+    pub fn terms(&self, swap_id: &str) -> Option<SwapTerms> {
+        self.swaps.get(swap_id).map(|t| t.clone())
+    }
+
+    /// Get a lock held in this community
+    pub fn lock_of(&self, swap_id: &str, side: SwapSide) -> Option<SwapLock> {
+        self.locks
+            .get(&(swap_id.to_string(), side))
+            .map(|l| l.clone())
+    }
+
+    /// Check whether a party has locked, here or in another community
+    ///
+    /// Locks in other communities count once the party's signed
+    /// announcement was received.
+    pub fn is_locked(&self, swap_id: &str, side: SwapSide) -> bool {
+        let key = (swap_id.to_string(), side);
+        self.remote_locks.contains(&key) || self.locks.contains_key(&key)
+    }
+
+    /// Lock escrow for one leg of a swap
+    ///
+    /// The responder may only lock after the initiator's lock is observed.
+    pub async fn lock(&self, swap_id: &str, side: SwapSide) -> Result<SwapLock> {
+        let terms = self.known_terms(swap_id)?;
+        let leg = terms.leg(side).clone();
+        let signing_key = self.party_key(&terms, side)?;
+
+        if leg.community != self.community {
+            return Err(CreditError::SwapFailed(format!(
+                "Leg belongs to community {}, not {}",
+                leg.community, self.community
+            )));
+        }
+        if side == SwapSide::Responder && !self.is_locked(swap_id, SwapSide::Initiator) {
+            return Err(CreditError::SwapFailed(
+                "Initiator has not locked yet".to_string(),
+            ));
+        }
+        if self.now() >= terms.expires_at(side) {
+            return Err(CreditError::SwapFailed("Swap has expired".to_string()));
+        }
+
+        let key = (swap_id.to_string(), side);
+        if self.locks.contains_key(&key) {
+            return Err(CreditError::SwapFailed("Escrow already locked".to_string()));
+        }
+
+        self.escrow_manager
+            .spend(&leg.payer, &self.device_id, leg.amount)?;
+
+        let lock = SwapLock {
+            swap_id: swap_id.to_string(),
+            side,
+            device_id: self.device_id.clone(),
+            state: LockState::Locked,
+            transaction_id: None,
+        };
+        self.locks.insert(key, lock.clone());
+        info!("Locked {} for swap {} ({:?})", leg.amount, swap_id, side);
+
+        let signature = signing_key.sign(&terms.announcement(LOCK_DOMAIN, side));
+        self.publish(&SwapMessage::Locked {
+            swap_id: swap_id.to_string(),
+            side,
+            signature: signature.to_bytes().to_vec(),
+        })
+        .await?;
+
+        Ok(lock)
+    }
+
+    /// Claim a lock held in this community by presenting the secret
+    ///
+    /// Settles the locked escrow to the leg's payee and reveals the secret
+    /// so the other community can settle its lock.
+    pub async fn claim(
+        &self,
+        swap_id: &str,
+        side: SwapSide,
+        secret: SwapSecret,
+    ) -> Result<TransactionId> {
+        let terms = self.known_terms(swap_id)?;
+        if !terms.verify_secret(&secret) {
+            return Err(CreditError::SwapFailed(
+                "Secret does not match hash lock".to_string(),
+            ));
+        }
+
+        let tx_id = self.settle(&terms, side).await?;
+        self.secrets.insert(swap_id.to_string(), secret);
+
+        self.publish(&SwapMessage::Revealed {
+            swap_id: swap_id.to_string(),
+            secret: secret.to_hex(),
+        })
+        .await?;
+
+        Ok(tx_id)
+    }
+
+    /// Refund an expired, unclaimed lock back to the payer's escrow
+    pub async fn refund(&self, swap_id: &str, side: SwapSide) -> Result<()> {
+        let terms = self.known_terms(swap_id)?;
+        let signing_key = self.party_key(&terms, side)?;
+        if self.now() < terms.expires_at(side) {
+            return Err(CreditError::SwapFailed(
+                "Lock has not expired yet".to_string(),
+            ));
+        }
+
+        let key = (swap_id.to_string(), side);
+        let device_id = {
+            let mut lock = self.locks.get_mut(&key).ok_or_else(|| {
+                CreditError::SwapFailed(format!("No {:?} lock for swap {}", side, swap_id))
+            })?;
+            if lock.state != LockState::Locked {
+                return Err(CreditError::SwapFailed(format!(
+                    "Lock is already {:?}",
+                    lock.state
+                )));
+            }
+            lock.state = LockState::Refunded;
+            lock.device_id.clone()
+        };
+
+        let leg = terms.leg(side);
+        self.escrow_manager
+            .refund(&leg.payer, &device_id, leg.amount)?;
+        info!("Refunded swap {} ({:?})", swap_id, side);
+
+        let signature = signing_key.sign(&terms.announcement(REFUND_DOMAIN, side));
+        self.publish(&SwapMessage::Refunded {
+            swap_id: swap_id.to_string(),
+            side,
+            signature: signature.to_bytes().to_vec(),
+        })
+        .await
+    }
+
+    /// Process a swap message received over gossip
+    ///
+    /// A revealed secret settles any matching lock held in this community.
+    /// Lock and refund announcements are only recorded for known swaps, with
+    /// a valid signature by the party. Returns `true` if the message was a
+    /// swap message.
+    pub async fn ingest(&self, message: &TopicMessage) -> Result<bool> {
+        let message: SwapMessage = match serde_json::from_slice(&message.payload) {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Ignoring malformed swap message: {}", e);
+                return Ok(false);
+            }
+        };
+
+        match message {
+            SwapMessage::Proposed { terms } => {
+                if terms.validate().is_ok() {
+                    self.swaps.entry(terms.swap_id.clone()).or_insert(*terms);
+                }
+            }
+            SwapMessage::Locked {
+                swap_id,
+                side,
+                signature,
+            } => {
+                let signed = self
+                    .terms(&swap_id)
+                    .is_some_and(|terms| terms.verify_announcement(LOCK_DOMAIN, side, &signature));
+                if !signed {
+                    warn!("Ignoring unsigned {:?} lock for swap {}", side, swap_id);
+                    return Ok(true);
+                }
+                debug!("Observed {:?} lock for swap {}", side, swap_id);
+                self.remote_locks.insert((swap_id, side));
+            }
+            SwapMessage::Refunded {
+                swap_id,
+                side,
+                signature,
+            } => {
+                let signed = self.terms(&swap_id).is_some_and(|terms| {
+                    terms.verify_announcement(REFUND_DOMAIN, side, &signature)
+                });
+                if signed {
+                    self.remote_locks.remove(&(swap_id, side));
+                }
+            }
+            SwapMessage::Revealed { swap_id, secret } => {
+                let Some(terms) = self.terms(&swap_id) else {
+                    return Ok(true);
+                };
+                let Some(secret) = SwapSecret::from_hex(&secret) else {
+                    return Ok(true);
+                };
+                if !terms.verify_secret(&secret) {
+                    warn!("Ignoring invalid secret for swap {}", swap_id);
+                    return Ok(true);
+                }
+                self.secrets.insert(swap_id.clone(), secret);
+
+                for side in [SwapSide::Initiator, SwapSide::Responder] {
+                    let pending = self
+                        .lock_of(&swap_id, side)
+                        .is_some_and(|l| l.state == LockState::Locked);
+                    if pending && self.now() < terms.expires_at(side) {
+                        self.settle(&terms, side).await?;
+                    }
+                }
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Get the revealed secret of a swap, if known
+    pub fn revealed_secret(&self, swap_id: &str) -> Option<SwapSecret> {
+        self.secrets.get(swap_id).map(|s| *s)
+    }
+
+    /// Settle a lock to the payee
+    async fn settle(&self, terms: &SwapTerms, side: SwapSide) -> Result<TransactionId> {
+        if self.now() >= terms.expires_at(side) {
+            return Err(CreditError::SwapFailed("Lock has expired".to_string()));
+        }
+
+        let key = (terms.swap_id.clone(), side);
+        {
+            let mut lock = self.locks.get_mut(&key).ok_or_else(|| {
+                CreditError::SwapFailed(format!("No {:?} lock for swap {}", side, terms.swap_id))
+            })?;
+            if lock.state != LockState::Locked {
+                return Err(CreditError::SwapFailed(format!(
+                    "Lock is already {:?}",
+                    lock.state
+                )));
+            }
+            lock.state = LockState::Claimed;
+        }
+
+        // Escrow was deducted at lock time; record the payment as a pending
+        // transaction like any other local spend
+        let leg = terms.leg(side);
+        let tx = Transaction::new(
+            leg.payer.clone(),
+            leg.payee.clone(),
+            leg.amount,
+            TransactionMetadata {
+                description: format!("Atomic swap {}", terms.swap_id),
+                category: Some(SWAP_CATEGORY.to_string()),
+                invoice_id: Some(terms.swap_id.clone()),
+            },
+        );
+        let tx_id = tx.id.clone();
+
+        let account = CreditAccountHandle::load(&self.state_engine, &leg.payer).await?;
+        account.update(|acc| {
+            acc.add_transaction(tx);
+            Ok(())
+        })?;
+
+        if let Some(mut lock) = self.locks.get_mut(&key) {
+            lock.transaction_id = Some(tx_id.clone());
+        }
+        info!("Settled swap {} ({:?}) as {}", terms.swap_id, side, tx_id);
+
+        Ok(tx_id)
+    }
+
+    /// Key of the local identity, which must be the party of `side`
+    fn party_key(&self, terms: &SwapTerms, side: SwapSide) -> Result<&SigningKey> {
+        match &self.identity {
+            Some((did, key)) if did.as_str() == terms.party(side) => Ok(key),
+            _ => Err(CreditError::SwapFailed(format!(
+                "Only {} can lock or refund the {:?} leg",
+                terms.party(side),
+                side
+            ))),
+        }
+    }
+
+    fn known_terms(&self, swap_id: &str) -> Result<SwapTerms> {
+        self.terms(swap_id)
+            .ok_or_else(|| CreditError::SwapFailed(format!("Unknown swap: {}", swap_id)))
+    }
+
+    async fn publish(&self, message: &SwapMessage) -> Result<()> {
+        let payload = serde_json::to_vec(message)?;
+        self.gossip
+            .publish_application(self.device_id.clone(), Self::topic(), payload)
+            .await
+            .map_err(|e| CreditError::P2p(e.to_string()))
+    }
+
+    fn now(&self) -> u64 {
+        Utc::now().timestamp() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::escrow::DeviceEscrow;
+    use std::time::Duration;
+
+    struct Community {
+        engine: SwapEngine,
+        escrows: Arc<EscrowManager>,
+        state: Arc<StateEngine>,
+    }
+
+    fn identity(seed: u8) -> (Did, SigningKey) {
+        let key = SigningKey::from_bytes(&[seed; 32]);
+        (Did::from_key(key.verifying_key()), key)
+    }
+
+    async fn community(
+        name: &str,
+        gossip: &Arc<GossipOverlay>,
+        accounts: &[&str],
+        seed: u8,
+    ) -> Community {
+        let state = Arc::new(StateEngine::new().await.unwrap());
+        let escrows = Arc::new(EscrowManager::new());
+        for account in accounts {
+            CreditAccountHandle::create(&state, account.to_string(), 100_000)
+                .await
+                .unwrap();
+            escrows.set(
+                account,
+                "device",
+                DeviceEscrow::new("device".to_string(), 10_000, 7),
+            );
+        }
+        let (did, key) = identity(seed);
+        let engine = SwapEngine::new(
+            name,
+            Arc::clone(&state),
+            Arc::clone(&escrows),
+            Arc::clone(gossip),
+            "device",
+        )
+        .with_identity(did, key);
+        Community {
+            engine,
+            escrows,
+            state,
+        }
+    }
+
+    fn terms(secret: &SwapSecret, timeout_secs: u64) -> SwapTerms {
+        SwapTerms::new(
+            &identity(1).0,
+            SwapLeg {
+                community: "a".to_string(),
+                payer: "alice".to_string(),
+                payee: "bob".to_string(),
+                amount: 3_000,
+            },
+            &identity(2).0,
+            SwapLeg {
+                community: "b".to_string(),
+                payer: "bob".to_string(),
+                payee: "alice".to_string(),
+                amount: 500,
+            },
+            secret.hash_lock(),
+            timeout_secs,
+        )
+    }
+
+    /// Deliver everything pending on a subscription to an engine
+    async fn pump(engine: &SwapEngine, sub: &mut TopicSubscription) {
+        while let Ok(Some(message)) =
+            tokio::time::timeout(Duration::from_millis(20), sub.recv()).await
+        {
+            engine.ingest(&message).await.unwrap();
+        }
+    }
+
+    async fn transactions(state: &StateEngine, owner: &str) -> Vec<Transaction> {
+        CreditAccountHandle::load(state, owner)
+            .await
+            .unwrap()
+            .read(|acc| Ok(acc.transactions.clone()))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_secret_hash_lock() {
+        let secret = SwapSecret::generate();
+        let terms = terms(&secret, 60);
+        assert!(terms.verify_secret(&secret));
+        assert!(!terms.verify_secret(&SwapSecret::generate()));
+        assert_eq!(SwapSecret::from_hex(&secret.to_hex()), Some(secret));
+        assert!(terms.expires_at(SwapSide::Responder) < terms.expires_at(SwapSide::Initiator));
+    }
+
+    #[tokio::test]
+    async fn test_atomic_swap_settles_both_legs() {
+        let gossip = Arc::new(GossipOverlay::new());
+        let a = community("a", &gossip, &["alice"], 1).await;
+        let b = community("b", &gossip, &["bob"], 2).await;
+        let mut sub_a = a.engine.subscribe().await.unwrap();
+        let mut sub_b = b.engine.subscribe().await.unwrap();
+
+        let secret = SwapSecret::generate();
+        let terms = terms(&secret, 600);
+        let swap_id = terms.swap_id.clone();
+        a.engine.propose(terms).await.unwrap();
+        pump(&b.engine, &mut sub_b).await;
+        assert!(b.engine.terms(&swap_id).is_some());
+
+        // Responder cannot lock before the initiator
+        assert!(b.engine.lock(&swap_id, SwapSide::Responder).await.is_err());
+
+        a.engine.lock(&swap_id, SwapSide::Initiator).await.unwrap();
+        assert_eq!(a.escrows.get("alice", "device").unwrap().remaining, 7_000);
+        pump(&b.engine, &mut sub_b).await;
+
+        b.engine.lock(&swap_id, SwapSide::Responder).await.unwrap();
+        assert_eq!(b.escrows.get("bob", "device").unwrap().remaining, 9_500);
+        pump(&a.engine, &mut sub_a).await;
+        assert!(a.engine.is_locked(&swap_id, SwapSide::Responder));
+
+        // Initiator claims in community B, revealing the secret
+        b.engine
+            .claim(&swap_id, SwapSide::Responder, secret)
+            .await
+            .unwrap();
+        pump(&a.engine, &mut sub_a).await;
+
+        // Community A settled the initiator's lock from the revealed secret
+        let lock = a.engine.lock_of(&swap_id, SwapSide::Initiator).unwrap();
+        assert_eq!(lock.state, LockState::Claimed);
+        assert_eq!(a.engine.revealed_secret(&swap_id), Some(secret));
+
+        let alice_txs = transactions(&a.state, "alice").await;
+        assert_eq!(alice_txs.len(), 1);
+        assert_eq!(alice_txs[0].to, "bob");
+        assert_eq!(alice_txs[0].amount, 3_000);
+
+        let bob_txs = transactions(&b.state, "bob").await;
+        assert_eq!(bob_txs.len(), 1);
+        assert_eq!(bob_txs[0].to, "alice");
+        assert_eq!(bob_txs[0].metadata.category.as_deref(), Some(SWAP_CATEGORY));
+    }
+
+    #[tokio::test]
+    async fn test_wrong_secret_rejected() {
+        let gossip = Arc::new(GossipOverlay::new());
+        let a = community("a", &gossip, &["alice"], 1).await;
+
+        let secret = SwapSecret::generate();
+        let terms = terms(&secret, 600);
+        let swap_id = terms.swap_id.clone();
+        a.engine.propose(terms).await.unwrap();
+        a.engine.lock(&swap_id, SwapSide::Initiator).await.unwrap();
+
+        let result = a
+            .engine
+            .claim(&swap_id, SwapSide::Initiator, SwapSecret::generate())
+            .await;
+        assert!(matches!(result, Err(CreditError::SwapFailed(_))));
+        assert_eq!(
+            a.engine
+                .lock_of(&swap_id, SwapSide::Initiator)
+                .unwrap()
+                .state,
+            LockState::Locked
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refund_after_timeout() {
+        let gossip = Arc::new(GossipOverlay::new());
+        let a = community("a", &gossip, &["alice"], 1).await;
+
+        let secret = SwapSecret::generate();
+        let mut terms = terms(&secret, 600);
+        let swap_id = terms.swap_id.clone();
+        a.engine.propose(terms.clone()).await.unwrap();
+        a.engine.lock(&swap_id, SwapSide::Initiator).await.unwrap();
+
+        // Not refundable before expiry
+        assert!(a
+            .engine
+            .refund(&swap_id, SwapSide::Initiator)
+            .await
+            .is_err());
+
+        // Expire the swap
+        terms.initiator_expires_at = 0;
+        terms.responder_expires_at = 0;
+        a.engine.swaps.insert(swap_id.clone(), terms);
+
+        a.engine
+            .refund(&swap_id, SwapSide::Initiator)
+            .await
+            .unwrap();
+        assert_eq!(a.escrows.get("alice", "device").unwrap().remaining, 10_000);
+        assert_eq!(
+            a.engine
+                .lock_of(&swap_id, SwapSide::Initiator)
+                .unwrap()
+                .state,
+            LockState::Refunded
+        );
+
+        // Expired locks can no longer be claimed
+        assert!(a
+            .engine
+            .claim(&swap_id, SwapSide::Initiator, secret)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_same_community_legs() {
+        let gossip = Arc::new(GossipOverlay::new());
+        let a = community("a", &gossip, &["alice", "bob"], 1).await;
+
+        let secret = SwapSecret::generate();
+        let mut terms = terms(&secret, 600);
+        terms.responder_leg.community = "a".to_string();
+        assert!(a.engine.propose(terms).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_short_claim_window() {
+        let gossip = Arc::new(GossipOverlay::new());
+        let a = community("a", &gossip, &["alice"], 1).await;
+
+        let secret = SwapSecret::generate();
+        let mut terms = terms(&secret, 600);
+        terms.responder_expires_at = terms.initiator_expires_at - MIN_CLAIM_WINDOW_SECS + 1;
+        assert!(a.engine.propose(terms.clone()).await.is_err());
+
+        terms.responder_expires_at = terms.initiator_expires_at - MIN_CLAIM_WINDOW_SECS;
+        a.engine.propose(terms).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_forged_lock_ignored() {
+        let gossip = Arc::new(GossipOverlay::new());
+        let a = community("a", &gossip, &["alice"], 1).await;
+        let b = community("b", &gossip, &["bob"], 2).await;
+        let mut sub_b = b.engine.subscribe().await.unwrap();
+
+        let secret = SwapSecret::generate();
+        let terms = terms(&secret, 600);
+        let swap_id = terms.swap_id.clone();
+        a.engine.propose(terms.clone()).await.unwrap();
+        pump(&b.engine, &mut sub_b).await;
+
+        // Only the initiator can lock the initiator's leg
+        assert!(b.engine.lock(&swap_id, SwapSide::Initiator).await.is_err());
+
+        // Announcements not signed by the initiator are ignored
+        let (_, eve) = identity(9);
+        for signature in [
+            Vec::new(),
+            eve.sign(&terms.announcement(LOCK_DOMAIN, SwapSide::Initiator))
+                .to_bytes()
+                .to_vec(),
+        ] {
+            let forged = SwapMessage::Locked {
+                swap_id: swap_id.clone(),
+                side: SwapSide::Initiator,
+                signature,
+            };
+            gossip
+                .publish_application(
+                    "eve".to_string(),
+                    SwapEngine::topic(),
+                    serde_json::to_vec(&forged).unwrap(),
+                )
+                .await
+                .unwrap();
+        }
+        pump(&b.engine, &mut sub_b).await;
+        assert!(!b.engine.is_locked(&swap_id, SwapSide::Initiator));
+        assert!(b.engine.lock(&swap_id, SwapSide::Responder).await.is_err());
+
+        a.engine.lock(&swap_id, SwapSide::Initiator).await.unwrap();
+        pump(&b.engine, &mut sub_b).await;
+        assert!(b.engine.is_locked(&swap_id, SwapSide::Initiator));
+    }
+}
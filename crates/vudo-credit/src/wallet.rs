@@ -0,0 +1,404 @@
+//! Wallet-facing API over the mutual credit scheduler
+//!
+//! A [`Wallet`] is one account as seen from one device: its balance broken
+//! down into confirmed, escrowed and pending amounts, payments and payment
+//! requests, the account's history, and the escrow refresh, reconciliation
+//! and dispute operations that otherwise take several scheduler calls.
+//!
+//! The device escrow is kept in the account document as well as in the
+//! scheduler's local cache, so a wallet reopened on a fresh scheduler (for
+//! example after restoring the state engine from an archive) can keep
+//! spending from the escrow it was granted.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use ed25519_dalek::SigningKey;
+use vudo_identity::Did;
+use vudo_state::{DocumentId, StateError};
+
+use crate::account::CreditAccountHandle;
+use crate::dispute::{Dispute, DisputeRoute};
+use crate::error::{CreditError, Result};
+use crate::escrow::DeviceEscrow;
+use crate::invoice::{PaymentAcceptance, PaymentRequest};
+use crate::ledger::{LedgerPage, LedgerQuery, Page};
+use crate::scheduler::MutualCreditScheduler;
+use crate::transaction::{TransactionId, TransactionMetadata};
+
+/// Balance of an account, broken down by how settled each part is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalanceBreakdown {
+    /// Confirmed balance (BFT-verified)
+    pub confirmed: i64,
+
+    /// Escrow allocated to this device
+    pub escrow_allocated: i64,
+
+    /// Escrow left to spend on this device
+    pub escrow_remaining: i64,
+
+    /// Spends not yet confirmed by reconciliation
+    pub pending_debits: i64,
+
+    /// Credits not yet confirmed by reconciliation
+    pub pending_credits: i64,
+
+    /// Balance locked in guarantee group pledges
+    pub pledged: i64,
+}
+
+impl BalanceBreakdown {
+    /// Balance expected after the next reconciliation
+    pub fn projected(&self) -> i64 {
+        self.confirmed + self.pending_credits - self.pending_debits
+    }
+}
+
+/// One account's wallet on this device
+pub struct Wallet {
+    /// Scheduler the wallet operates through
+    scheduler: Arc<MutualCreditScheduler>,
+
+    /// Account ID
+    account_id: String,
+
+    /// Identity signing payment requests and acceptances
+    identity: Option<(Did, SigningKey)>,
+}
+
+impl Wallet {
+    /// Create an account and open its wallet
+    pub async fn create(
+        scheduler: Arc<MutualCreditScheduler>,
+        account_id: impl Into<String>,
+        initial_balance: i64,
+    ) -> Result<Self> {
+        let account_id = account_id.into();
+        CreditAccountHandle::create(
+            scheduler.state_engine(),
+            account_id.clone(),
+            initial_balance,
+        )
+        .await?;
+
+        Self::open(scheduler, account_id).await
+    }
+
+    /// Open the wallet of an existing account
+    ///
+    /// Restores the device escrow recorded in the account if the scheduler
+    /// has none cached.
+    pub async fn open(
+        scheduler: Arc<MutualCreditScheduler>,
+        account_id: impl Into<String>,
+    ) -> Result<Self> {
+        let account_id = account_id.into();
+        let doc_id = DocumentId::new("credit", &account_id);
+        if let Err(StateError::DocumentNotFound(_)) =
+            scheduler.state_engine().get_document(&doc_id).await
+        {
+            return Err(CreditError::AccountNotFound(account_id));
+        }
+
+        let wallet = Self {
+            scheduler,
+            account_id,
+            identity: None,
+        };
+        if wallet
+            .scheduler
+            .get_device_escrow(&wallet.account_id)
+            .is_err()
+        {
+            let device_id = wallet.scheduler.device_id();
+            let escrow = wallet
+                .account()
+                .await?
+                .read(|acc| Ok(acc.get_escrow(device_id).cloned()))?;
+            if let Some(escrow) = escrow {
+                wallet
+                    .scheduler
+                    .set_device_escrow(&wallet.account_id, escrow);
+            }
+        }
+
+        Ok(wallet)
+    }
+
+    /// Sign payment requests and acceptances as `did`
+    ///
+    /// Payment requests are addressed by DID, so the account ID should be
+    /// the DID's string form.
+    pub fn with_identity(mut self, did: Did, signing_key: SigningKey) -> Self {
+        self.identity = Some((did, signing_key));
+        self
+    }
+
+    /// Get the account ID
+    pub fn account_id(&self) -> &str {
+        &self.account_id
+    }
+
+    /// Get the scheduler
+    pub fn scheduler(&self) -> &Arc<MutualCreditScheduler> {
+        &self.scheduler
+    }
+
+    /// Get the balance breakdown
+    pub async fn balance(&self) -> Result<BalanceBreakdown> {
+        let escrow = self.scheduler.get_device_escrow(&self.account_id).ok();
+        self.account().await?.read(|acc| {
+            Ok(BalanceBreakdown {
+                confirmed: acc.confirmed_balance,
+                escrow_allocated: escrow.as_ref().map_or(0, |e| e.allocated),
+                escrow_remaining: escrow.as_ref().map_or(0, |e| e.remaining),
+                pending_debits: acc.total_pending_debits(),
+                pending_credits: acc.pending_credits,
+                pledged: acc.pledged,
+            })
+        })
+    }
+
+    /// Pay `amount` to `recipient` from the device escrow
+    pub async fn pay(
+        &self,
+        recipient: &str,
+        amount: i64,
+        description: impl Into<String>,
+    ) -> Result<TransactionId> {
+        let tx_id = self
+            .scheduler
+            .spend_local(
+                &self.account_id,
+                amount,
+                recipient,
+                TransactionMetadata {
+                    description: description.into(),
+                    category: None,
+                    invoice_id: None,
+                },
+            )
+            .await?;
+        self.save_escrow().await?;
+
+        Ok(tx_id)
+    }
+
+    /// Request `amount` from `payer`, signed by the wallet's identity
+    ///
+    /// The request is stored in the invoice book, to be matched to its
+    /// payment at reconciliation.
+    pub async fn request(
+        &self,
+        payer: &Did,
+        amount: i64,
+        memo: impl Into<String>,
+        ttl: Duration,
+    ) -> Result<PaymentRequest> {
+        let (did, signing_key) = self.identity()?;
+        let request = PaymentRequest::create(did, signing_key, payer, amount, memo, ttl)?;
+        self.scheduler.invoices().store(&request).await?;
+
+        Ok(request)
+    }
+
+    /// Pay a payment request addressed to this wallet
+    pub async fn pay_request(&self, request: &PaymentRequest) -> Result<PaymentAcceptance> {
+        let (did, signing_key) = self.identity()?;
+        let acceptance = self
+            .scheduler
+            .pay_request(&self.account_id, request, did, signing_key)
+            .await?;
+        self.save_escrow().await?;
+
+        Ok(acceptance)
+    }
+
+    /// Query a page of the account's history
+    pub async fn history(&self, query: &LedgerQuery, page: Page) -> Result<LedgerPage> {
+        self.scheduler
+            .ledger()
+            .history(&self.account_id, query, page)
+            .await
+    }
+
+    /// Refresh the device escrow from the BFT committee
+    pub async fn refresh_escrow(&self) -> Result<DeviceEscrow> {
+        self.scheduler
+            .request_escrow_refresh(&self.account_id)
+            .await?;
+        self.scheduler.get_device_escrow(&self.account_id)
+    }
+
+    /// Reconcile the account with the BFT committee
+    pub async fn reconcile(&self) -> Result<BalanceBreakdown> {
+        self.scheduler.reconcile_account(&self.account_id).await?;
+        self.balance().await
+    }
+
+    /// Dispute one of the account's transactions
+    pub async fn dispute(&self, tx_id: &str) -> Result<()> {
+        self.scheduler
+            .dispute_transaction(&self.account_id, tx_id)
+            .await
+    }
+
+    /// Open a dispute on one of the account's payments
+    ///
+    /// Unlike [`dispute`](Self::dispute), the dispute is stored and routed
+    /// for resolution, and evidence can be attached to it.
+    pub async fn open_dispute(
+        &self,
+        tx_id: &str,
+        reason: impl Into<String>,
+        route: DisputeRoute,
+    ) -> Result<Dispute> {
+        self.scheduler
+            .open_dispute(&self.account_id, tx_id, reason, route)
+            .await
+    }
+
+    /// Load the account
+    async fn account(&self) -> Result<CreditAccountHandle> {
+        CreditAccountHandle::load(self.scheduler.state_engine(), &self.account_id).await
+    }
+
+    /// Get the signing identity
+    fn identity(&self) -> Result<(&Did, &SigningKey)> {
+        self.identity
+            .as_ref()
+            .map(|(did, signing_key)| (did, signing_key))
+            .ok_or_else(|| {
+                CreditError::InvalidOperation(format!("Wallet {} has no identity", self.account_id))
+            })
+    }
+
+    /// Record the remaining device escrow in the account
+    ///
+    /// Only escrow granted through the account is recorded; escrow set
+    /// directly on the scheduler stays local to it.
+    async fn save_escrow(&self) -> Result<()> {
+        let Ok(escrow) = self.scheduler.get_device_escrow(&self.account_id) else {
+            return Ok(());
+        };
+        let device_id = self.scheduler.device_id();
+        self.account().await?.update(|acc| {
+            if let Some(recorded) = acc.escrows.get_mut(device_id) {
+                recorded.remaining = escrow.remaining;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reputation::ReputationTier;
+    use crate::transaction::TransactionStatus;
+
+    async fn wallet(initial_balance: i64) -> Wallet {
+        let scheduler = Arc::new(MutualCreditScheduler::new_mock().await.unwrap());
+        let wallet = Wallet::create(scheduler, "alice", initial_balance)
+            .await
+            .unwrap();
+
+        // Tier 3 is granted 10,000 of escrow
+        wallet
+            .account()
+            .await
+            .unwrap()
+            .update(|acc| {
+                acc.reputation_tier = ReputationTier::new(3)?;
+                Ok(())
+            })
+            .unwrap();
+        wallet
+    }
+
+    #[tokio::test]
+    async fn test_open_missing_account() {
+        let scheduler = Arc::new(MutualCreditScheduler::new_mock().await.unwrap());
+        assert!(matches!(
+            Wallet::open(scheduler, "nobody").await,
+            Err(CreditError::AccountNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_pay_and_balance() {
+        let wallet = wallet(100_000).await;
+        assert!(wallet.pay("bob", 100, "Coffee").await.is_err());
+
+        let escrow = wallet.refresh_escrow().await.unwrap();
+        assert!(escrow.allocated > 0);
+        wallet.pay("bob", 500, "Coffee").await.unwrap();
+
+        let balance = wallet.balance().await.unwrap();
+        assert_eq!(balance.confirmed, 100_000);
+        assert_eq!(balance.escrow_allocated, escrow.allocated);
+        assert_eq!(balance.escrow_remaining, escrow.allocated - 500);
+        assert_eq!(balance.pending_debits, 500);
+        assert_eq!(balance.projected(), 99_500);
+
+        let balance = wallet.reconcile().await.unwrap();
+        assert_eq!(balance.confirmed, 99_500);
+        assert_eq!(balance.pending_debits, 0);
+    }
+
+    #[tokio::test]
+    async fn test_reopen_restores_escrow() {
+        let wallet = wallet(100_000).await;
+        let escrow = wallet.refresh_escrow().await.unwrap();
+        wallet.pay("bob", 700, "Lunch").await.unwrap();
+
+        // A fresh scheduler over the same state starts with no escrow cached
+        let scheduler = Arc::new(
+            MutualCreditScheduler::new(
+                Arc::clone(wallet.scheduler().state_engine()),
+                Arc::new(crate::bft::BftCommittee::new_mock(4).await.unwrap()),
+                "test-device".to_string(),
+            )
+            .await
+            .unwrap(),
+        );
+        let reopened = Wallet::open(scheduler, "alice").await.unwrap();
+        let balance = reopened.balance().await.unwrap();
+        assert_eq!(balance.escrow_remaining, escrow.allocated - 700);
+    }
+
+    #[tokio::test]
+    async fn test_dispute() {
+        let wallet = wallet(100_000).await;
+        wallet.refresh_escrow().await.unwrap();
+        let tx_id = wallet.pay("bob", 500, "Broken kettle").await.unwrap();
+
+        wallet.dispute(&tx_id).await.unwrap();
+        let history = wallet
+            .history(&LedgerQuery::new(), Page::default())
+            .await
+            .unwrap();
+        let last = history.entries.last().unwrap();
+        assert_eq!(last.transaction.status, TransactionStatus::Disputed);
+
+        // Disputed spends are held out of reconciliation
+        assert_eq!(wallet.balance().await.unwrap().pending_debits, 0);
+        assert!(matches!(
+            wallet.dispute("missing").await,
+            Err(CreditError::TransactionNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_request_needs_identity() {
+        let wallet = wallet(0).await;
+        let payer = Did::from_key(SigningKey::from_bytes(&[2; 32]).verifying_key());
+        assert!(matches!(
+            wallet
+                .request(&payer, 100, "Rent", Duration::from_secs(60))
+                .await,
+            Err(CreditError::InvalidOperation(_))
+        ));
+    }
+}
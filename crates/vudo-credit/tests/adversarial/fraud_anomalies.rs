@@ -0,0 +1,131 @@
+//! Fraud Anomaly Tests
+//!
+//! Tests the fraud monitor against attacks that stay within escrow limits
+//! but abuse the credit system's shape: sudden escrow drains, credit passed
+//! round rings of colluding accounts, and bursts of fresh Sybil accounts
+//! funnelling into one counterparty.
+
+use crate::test_harness::*;
+use vudo_credit::{
+    AlertKind, AlertSeverity, CreditError, FraudMonitor, MonitorConfig, MutualCreditScheduler,
+    Transaction, TransactionMetadata,
+};
+
+fn tx(from: &str, to: &str, amount: i64) -> Transaction {
+    Transaction::new(
+        from.to_string(),
+        to.to_string(),
+        amount,
+        TransactionMetadata::default(),
+    )
+}
+
+#[tokio::test]
+async fn test_velocity_spike_frozen() {
+    // Attack: Quiet baseline, then drain escrow within the hour
+    let malicious_node = create_malicious_node(AttackStrategy::VelocitySpike).await;
+    let result = malicious_node.execute_attack().await.unwrap();
+
+    // Verify: Spike detected and escrow refresh frozen
+    assert!(!result.successful);
+    assert!(result.detection_time.is_some());
+    assert!(matches!(
+        result.mitigation,
+        Some(Mitigation::EscrowRefreshFrozen { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_wash_trading_rings_frozen() {
+    // Attack: Rings up to the monitor's maximum cycle length
+    for ring_size in [3, 4] {
+        let malicious_node = create_malicious_node(AttackStrategy::WashTrading { ring_size }).await;
+        let result = malicious_node.execute_attack().await.unwrap();
+
+        // Verify: Ring detected, every member frozen
+        assert!(!result.successful, "Ring of {} should be caught", ring_size);
+        assert_eq!(result.damage_assessment, DamageLevel::Minimal);
+    }
+}
+
+#[tokio::test]
+async fn test_refunds_not_flagged_as_wash_trading() {
+    // A payment and its refund are a two-account cycle, which is normal
+    let monitor = FraudMonitor::default();
+    monitor.observe_account("alice", 0);
+    monitor.observe_account("shop", 0);
+
+    assert!(monitor
+        .observe_at(&tx("alice", "shop", 5_000), 1_000)
+        .is_empty());
+    assert!(monitor
+        .observe_at(&tx("shop", "alice", 5_000), 1_100)
+        .is_empty());
+    assert!(!monitor.is_frozen("alice"));
+}
+
+#[tokio::test]
+async fn test_sybil_burst_detected() {
+    // Attack: 20 fresh Sybil identities all pay one hub account
+    let monitor = FraudMonitor::default();
+    monitor.observe_account("hub", 0);
+    let now = 30 * 86_400;
+
+    let mut alerts = vec![];
+    for i in 0..20 {
+        let sybil = format!("sybil_{}", i);
+        alerts.extend(monitor.observe_at(&tx(&sybil, "hub", 100), now + i));
+    }
+
+    // Verify: One burst alert per window, naming the hub first
+    let burst: Vec<_> = alerts
+        .iter()
+        .filter(|a| matches!(a.kind, AlertKind::NewAccountBurst { .. }))
+        .collect();
+    assert_eq!(
+        burst.len(),
+        1,
+        "Burst alerts are not repeated within the window"
+    );
+    assert_eq!(burst[0].kind.attack(), "sybil_attack");
+    assert_eq!(burst[0].severity, AlertSeverity::Medium);
+    assert_eq!(burst[0].accounts[0], "hub");
+}
+
+#[tokio::test]
+async fn test_sybil_burst_freezes_when_configured() {
+    // Setup: Monitor that freezes on Medium alerts
+    let monitor = FraudMonitor::new(MonitorConfig {
+        burst_threshold: 5,
+        freeze_severity: Some(AlertSeverity::Medium),
+        ..MonitorConfig::default()
+    });
+    monitor.observe_account("hub", 0);
+
+    // Attack: 5 fresh Sybil identities pay the hub
+    for i in 0..5 {
+        monitor.observe_at(&tx(&format!("sybil_{}", i), "hub", 100), 30 * 86_400 + i);
+    }
+
+    // Verify: Hub and Sybils frozen
+    assert!(monitor.is_frozen("hub"));
+    assert!(monitor.is_frozen("sybil_4"));
+    assert_eq!(monitor.metrics().frozen_accounts, 6);
+}
+
+#[tokio::test]
+async fn test_frozen_account_cannot_refresh_escrow() {
+    let scheduler = MutualCreditScheduler::new_mock().await.unwrap();
+
+    // Verify: Refresh blocked until the committee releases the account
+    scheduler.monitor().freeze("mallory", "manual-review");
+    let result = scheduler.request_escrow_refresh("mallory").await;
+    assert!(matches!(
+        result,
+        Err(CreditError::EscrowRefreshFrozen { .. })
+    ));
+    assert_eq!(
+        scheduler.monitor().release("mallory"),
+        Some("manual-review".to_string())
+    );
+}
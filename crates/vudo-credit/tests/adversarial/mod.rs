@@ -11,6 +11,7 @@
 //! - Eclipse attacks
 //! - Resource exhaustion
 //! - Byzantine committee voting
+//! - Fraud anomalies (velocity spikes, wash trading, new-account bursts)
 //!
 //! ## Test Philosophy
 //!
@@ -44,6 +45,7 @@
 //! cargo test --test adversarial eclipse_attack
 //! cargo test --test adversarial resource_exhaustion
 //! cargo test --test adversarial byzantine_committee
+//! cargo test --test adversarial fraud_anomalies
 //! ```
 
 pub mod test_harness;
@@ -56,6 +58,7 @@ pub mod timing_attack;
 pub mod eclipse_attack;
 pub mod resource_exhaustion;
 pub mod byzantine_committee;
+pub mod fraud_anomalies;
 
 // Re-export test infrastructure for use in tests
 pub use test_harness::*;
@@ -75,6 +78,8 @@ mod integration_tests {
             AttackStrategy::EclipseAttack,
             AttackStrategy::ResourceExhaustion,
             AttackStrategy::ByzantineVoting,
+            AttackStrategy::VelocitySpike,
+            AttackStrategy::WashTrading { ring_size: 3 },
         ];
 
         for strategy in attack_strategies {
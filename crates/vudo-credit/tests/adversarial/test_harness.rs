@@ -9,7 +9,8 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, RwLock};
 use vudo_credit::{
-    CreditAccountHandle, MutualCreditScheduler, Transaction, TransactionId, TransactionMetadata,
+    AlertKind, CreditAccountHandle, FraudMonitor, MutualCreditScheduler, Transaction,
+    TransactionId, TransactionMetadata,
 };
 use vudo_identity::MasterIdentity;
 use vudo_p2p::{VudoP2P, P2PConfig, PeerId};
@@ -32,6 +33,10 @@ pub enum AttackStrategy {
     ResourceExhaustion,
     /// Byzantine voting in BFT committee
     ByzantineVoting,
+    /// Drain escrow in a sudden burst of spending
+    VelocitySpike,
+    /// Pass the same credit round a ring of colluding accounts
+    WashTrading { ring_size: usize },
 }
 
 /// Damage level assessment
@@ -66,6 +71,8 @@ pub enum Mitigation {
     ResourceLimitEnforced,
     /// BFT consensus rejected malicious vote
     BftConsensusRejected,
+    /// Fraud alert froze escrow refresh pending committee review
+    EscrowRefreshFrozen { alert_id: String },
 }
 
 /// Result of an attack simulation
@@ -136,6 +143,8 @@ impl MaliciousNode {
             AttackStrategy::EclipseAttack => self.eclipse_attack().await,
             AttackStrategy::ResourceExhaustion => self.resource_exhaustion().await,
             AttackStrategy::ByzantineVoting => self.byzantine_voting().await,
+            AttackStrategy::VelocitySpike => self.velocity_spike().await,
+            AttackStrategy::WashTrading { ring_size } => self.wash_trading(*ring_size).await,
         }
     }
 
@@ -249,6 +258,87 @@ impl MaliciousNode {
             details: "BFT consensus rejected malicious votes".to_string(),
         })
     }
+
+    /// Sudden burst of spending after a quiet baseline
+    async fn velocity_spike(&self) -> Result<AttackResult, Box<dyn std::error::Error>> {
+        let monitor = FraudMonitor::default();
+        monitor.observe_account(&self.id, 0);
+        monitor.observe_account("fence", 0);
+
+        let start = 1_000_000;
+        for hour in 0..24 {
+            monitor.observe_at(&fraud_tx(&self.id, "fence", 1_000), start + hour * 3_600);
+        }
+
+        let detect = Instant::now();
+        let alerts = monitor.observe_at(&fraud_tx(&self.id, "fence", 50_000), start + 24 * 3_600);
+        Ok(fraud_result(
+            AttackStrategy::VelocitySpike,
+            &monitor,
+            &self.id,
+            alerts.iter().any(|a| matches!(a.kind, AlertKind::VelocitySpike { .. })),
+            detect.elapsed(),
+        ))
+    }
+
+    /// Credit passed round a ring of colluding accounts
+    async fn wash_trading(&self, ring_size: usize) -> Result<AttackResult, Box<dyn std::error::Error>> {
+        let monitor = FraudMonitor::default();
+        let ring: Vec<String> = (0..ring_size.max(3))
+            .map(|i| format!("{}_ring_{}", self.id, i))
+            .collect();
+        for account in &ring {
+            monitor.observe_account(account, 0);
+        }
+
+        let detect = Instant::now();
+        let mut detected = false;
+        for (i, from) in ring.iter().enumerate() {
+            let to = &ring[(i + 1) % ring.len()];
+            let alerts = monitor.observe_at(&fraud_tx(from, to, 5_000), 1_000 + i as u64);
+            detected |= alerts.iter().any(|a| matches!(a.kind, AlertKind::WashTrading { .. }));
+        }
+
+        Ok(fraud_result(
+            AttackStrategy::WashTrading { ring_size },
+            &monitor,
+            &ring[0],
+            detected,
+            detect.elapsed(),
+        ))
+    }
+}
+
+/// Build a transaction for fraud monitor attacks
+fn fraud_tx(from: &str, to: &str, amount: i64) -> Transaction {
+    Transaction::new(from.to_string(), to.to_string(), amount, TransactionMetadata::default())
+}
+
+/// Assess a fraud attack by whether the monitor froze the attacker
+fn fraud_result(
+    attack_type: AttackStrategy,
+    monitor: &FraudMonitor,
+    attacker: &str,
+    detected: bool,
+    detection_time: Duration,
+) -> AttackResult {
+    let frozen_by = monitor.frozen_by(attacker);
+    AttackResult {
+        attack_type,
+        successful: frozen_by.is_none(),
+        damage_assessment: if frozen_by.is_some() {
+            DamageLevel::Minimal
+        } else {
+            DamageLevel::Moderate
+        },
+        detection_time: detected.then_some(detection_time),
+        details: format!(
+            "{} fraud alerts raised, attacker {}",
+            monitor.alerts().len(),
+            if frozen_by.is_some() { "frozen" } else { "not frozen" }
+        ),
+        mitigation: frozen_by.map(|alert_id| Mitigation::EscrowRefreshFrozen { alert_id }),
+    }
 }
 
 /// Honest node for testing
@@ -1,12 +1,18 @@
 //! Integration tests for vudo-credit system
 
+use ed25519_dalek::SigningKey;
 use std::sync::Arc;
 use std::time::Duration;
 use vudo_credit::{
-    BftCommittee, CreditAccountHandle, DeviceEscrow, MutualCreditScheduler, OverdraftResolution,
-    ReputationManager, ReputationTier, Transaction, TransactionMetadata,
-    TransactionStatus,
+    AlertKind, BftCommittee, CommitteeCandidate, CommitteeFormation, CommitteeFormationConfig,
+    CreditAccountHandle, CreditError, CreditPolicy, DemurragePolicy, DeviceEscrow, DisputeOutcome,
+    DisputeRoute, DisputeState, DisputeVote, Evidence, ExportFormat, FeeSchedule, InvoiceStatus,
+    LedgerEvent, LedgerQuery, MutualCreditScheduler, OverdraftResolution, Page, PaymentRequest,
+    ProofSigner, ReputationManager, ReputationTier, SignedStatement, Transaction,
+    TransactionMetadata, TransactionStatus, Wallet,
 };
+use vudo_identity::Did;
+use vudo_p2p::GossipOverlay;
 use vudo_state::StateEngine;
 
 /// Test local spend performance (< 1ms target)
@@ -354,6 +360,43 @@ async fn test_reputation_tier_limits() {
     }
 }
 
+/// Test clean reconciliations move an account up a reputation tier
+#[tokio::test]
+async fn test_reputation_progression_on_reconciliation() {
+    let state_engine = Arc::new(StateEngine::new().await.unwrap());
+    let bft_committee = Arc::new(BftCommittee::new_mock(4).await.unwrap());
+    let scheduler = MutualCreditScheduler::new(
+        Arc::clone(&state_engine),
+        bft_committee,
+        "device1".to_string(),
+    )
+    .await
+    .unwrap();
+    let account = CreditAccountHandle::create(&state_engine, "alice".to_string(), 10_000)
+        .await
+        .unwrap();
+
+    // Tier 1 needs 10 points at 2 points per clean reconciliation, less decay
+    for _ in 0..4 {
+        scheduler.reconcile_account("alice").await.unwrap();
+    }
+    assert_eq!(
+        scheduler.reputation().tier("alice"),
+        ReputationTier::new(0).unwrap()
+    );
+
+    for _ in 0..2 {
+        scheduler.reconcile_account("alice").await.unwrap();
+    }
+    account.invalidate_cache();
+    let tier = account.read(|acc| Ok(acc.reputation_tier)).unwrap();
+    assert_eq!(tier, ReputationTier::new(1).unwrap());
+
+    let record = scheduler.reputation().record("alice").unwrap();
+    assert_eq!(record.clean_reconciliations, 6);
+    assert_eq!(record.overdrafts, 0);
+}
+
 /// Test escrow refresh workflow
 #[tokio::test]
 async fn test_escrow_refresh_workflow() {
@@ -465,6 +508,665 @@ async fn test_overdraft_resolution_strategies() {
     }).unwrap();
 }
 
+/// Test a guarantee group covering an unrecoverable overdraft
+#[tokio::test]
+async fn test_guarantee_group_covers_overdraft() {
+    let state_engine = Arc::new(StateEngine::new().await.unwrap());
+    let bft_committee = Arc::new(BftCommittee::new_mock(4).await.unwrap());
+    let scheduler = MutualCreditScheduler::new(
+        Arc::clone(&state_engine),
+        bft_committee,
+        "device1".to_string(),
+    )
+    .await
+    .unwrap();
+
+    for (owner, balance) in [("alice", 10_000), ("bob", 20_000), ("carol", 10_000)] {
+        CreditAccountHandle::create(&state_engine, owner.to_string(), balance)
+            .await
+            .unwrap();
+    }
+
+    let guarantees = scheduler.guarantees();
+    let group = guarantees.create("neighbours").await.unwrap();
+    for (member, amount) in [("alice", 1_000), ("bob", 4_000), ("carol", 2_000)] {
+        guarantees
+            .pledge(&group.group_id, member, amount)
+            .await
+            .unwrap();
+    }
+    assert!(guarantees
+        .pledge(&group.group_id, "carol", 9_000)
+        .await
+        .is_err());
+
+    // Alice overdraws by $30
+    let alice = CreditAccountHandle::load(&state_engine, "alice")
+        .await
+        .unwrap();
+    alice
+        .update(|acc| {
+            acc.add_transaction(Transaction::new(
+                "alice".to_string(),
+                "dave".to_string(),
+                13_000,
+                TransactionMetadata::default(),
+            ));
+            Ok(())
+        })
+        .unwrap();
+    let overdrafts = scheduler.detect_overdrafts("alice").await.unwrap();
+    assert_eq!(overdrafts[0].deficit, 3_000);
+
+    let resolution = OverdraftResolution::Guarantee {
+        group_id: group.group_id.clone(),
+    };
+    scheduler
+        .resolve_overdraft("alice", &overdrafts[0], resolution)
+        .await
+        .unwrap();
+
+    // Bob and Carol cover the deficit 2:1
+    let health = guarantees.health(&group.group_id).await.unwrap();
+    assert_eq!(health.total_absorbed, 3_000);
+    assert_eq!(health.available, 4_000);
+    assert_eq!(health.uncovered, 0);
+
+    alice.invalidate_cache();
+    assert_eq!(alice.read(|acc| Ok(acc.pending_credits)).unwrap(), 3_000);
+
+    let bob = CreditAccountHandle::load(&state_engine, "bob")
+        .await
+        .unwrap();
+    assert_eq!(bob.read(|acc| Ok(acc.pledged)).unwrap(), 2_000);
+    let pending = bob.read(|acc| Ok(acc.total_pending_debits())).unwrap();
+    assert_eq!(pending, 2_000);
+    let history = scheduler
+        .ledger()
+        .history("bob", &LedgerQuery::default(), Page::default())
+        .await
+        .unwrap();
+    assert_eq!(history.entries.len(), 1);
+
+    // The loss shares settle at reconciliation
+    scheduler.reconcile_account("bob").await.unwrap();
+    assert_eq!(scheduler.get_balance("bob").await.unwrap(), 18_000);
+}
+
+/// Test wash trading freezes escrow refresh until committee review
+#[tokio::test]
+async fn test_wash_trading_freezes_escrow_refresh() {
+    let state_engine = Arc::new(StateEngine::new().await.unwrap());
+    let bft_committee = Arc::new(BftCommittee::new_mock(4).await.unwrap());
+    let scheduler = MutualCreditScheduler::new(
+        Arc::clone(&state_engine),
+        bft_committee,
+        "device1".to_string(),
+    )
+    .await
+    .unwrap();
+
+    for owner in ["alice", "bob", "carol"] {
+        CreditAccountHandle::create(&state_engine, owner.to_string(), 100_000)
+            .await
+            .unwrap();
+        scheduler.set_device_escrow(owner, DeviceEscrow::new("device1".to_string(), 10_000, 7));
+    }
+
+    // The same money goes round alice -> bob -> carol -> alice
+    for (from, to) in [("alice", "bob"), ("bob", "carol"), ("carol", "alice")] {
+        scheduler
+            .spend_local(from, 5_000, to, TransactionMetadata::default())
+            .await
+            .unwrap();
+    }
+
+    let alerts = scheduler.monitor().alerts_for("alice");
+    assert_eq!(alerts.len(), 1);
+    assert!(matches!(alerts[0].kind, AlertKind::WashTrading { .. }));
+    assert_eq!(scheduler.monitor().metrics().transactions, 3);
+
+    let result = scheduler.request_escrow_refresh("alice").await;
+    assert!(matches!(
+        result,
+        Err(CreditError::EscrowRefreshFrozen { .. })
+    ));
+
+    // The committee clears the account
+    scheduler.monitor().release("alice").unwrap();
+    scheduler.request_escrow_refresh("alice").await.unwrap();
+}
+
+/// Test the wallet flow across a state archive round trip
+#[tokio::test]
+async fn test_wallet_flow_survives_archive_restore() {
+    let state_engine = Arc::new(StateEngine::new().await.unwrap());
+    let scheduler = Arc::new(
+        MutualCreditScheduler::new(
+            Arc::clone(&state_engine),
+            Arc::new(BftCommittee::new_mock(4).await.unwrap()),
+            "device1".to_string(),
+        )
+        .await
+        .unwrap(),
+    );
+
+    let wallet = Wallet::create(scheduler, "alice", 100_000).await.unwrap();
+    CreditAccountHandle::load(&state_engine, "alice")
+        .await
+        .unwrap()
+        .update(|acc| {
+            acc.reputation_tier = ReputationTier::new(3).unwrap();
+            Ok(())
+        })
+        .unwrap();
+    let escrow = wallet.refresh_escrow().await.unwrap();
+    wallet.pay("bob", 2_000, "Groceries").await.unwrap();
+    let disputed = wallet.pay("bob", 3_000, "Never delivered").await.unwrap();
+    wallet.dispute(&disputed).await.unwrap();
+
+    // Only the undisputed payment is confirmed
+    let balance = wallet.reconcile().await.unwrap();
+    assert_eq!(balance.confirmed, 98_000);
+    assert_eq!(balance.pending_debits, 0);
+    assert_eq!(balance.escrow_remaining, escrow.allocated - 5_000);
+
+    // Restore into a fresh engine and scheduler
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join("credit.state");
+    state_engine.export_archive(&archive).await.unwrap();
+
+    let restored = Arc::new(StateEngine::new().await.unwrap());
+    restored.import_archive(&archive).await.unwrap();
+    let scheduler = Arc::new(
+        MutualCreditScheduler::new(
+            restored,
+            Arc::new(BftCommittee::new_mock(4).await.unwrap()),
+            "device1".to_string(),
+        )
+        .await
+        .unwrap(),
+    );
+    let wallet = Wallet::open(scheduler, "alice").await.unwrap();
+    assert_eq!(wallet.balance().await.unwrap(), balance);
+
+    // Recorded x2, disputed, confirmed
+    let history = wallet
+        .history(&LedgerQuery::new(), Page::default())
+        .await
+        .unwrap();
+    assert_eq!(history.entries.len(), 4);
+    assert_eq!(history.entries[2].event_label(), "pending->disputed");
+
+    wallet.pay("bob", 1_000, "Coffee").await.unwrap();
+    assert_eq!(
+        wallet.balance().await.unwrap().escrow_remaining,
+        escrow.allocated - 6_000
+    );
+}
+
+/// Test committee rotation hands consensus state to the next epoch
+#[tokio::test]
+async fn test_committee_rotation_handoff() {
+    let state_engine = Arc::new(StateEngine::new().await.unwrap());
+    // Uptime builds up over a day of announcements, so it is not required here
+    let formation = CommitteeFormation::new(
+        Arc::clone(&state_engine),
+        Arc::new(GossipOverlay::new()),
+        CommitteeFormationConfig {
+            min_uptime_score: 0.0,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let account = CreditAccountHandle::create(&state_engine, "alice".to_string(), 10_000)
+        .await
+        .unwrap();
+
+    // Seven tier 3 candidates, two of them staking for a heavier weight
+    let mut signers = Vec::new();
+    for i in 0..7u8 {
+        let key = SigningKey::from_bytes(&[i + 1; 32]);
+        let did = Did::from_key(key.verifying_key()).as_str().to_string();
+        let stake = if i < 2 { 400_000 } else { 0 };
+        let candidate_account = CreditAccountHandle::create(&state_engine, did.clone(), stake)
+            .await
+            .unwrap();
+        candidate_account
+            .update(|acc| {
+                acc.reputation_tier = ReputationTier::new(3)?;
+                Ok(())
+            })
+            .unwrap();
+
+        formation
+            .record(
+                CommitteeCandidate::new(did.clone(), format!("peer-{}", i))
+                    .with_stake(stake)
+                    .sign(&key),
+            )
+            .unwrap();
+        signers.push(ProofSigner::new(did, key));
+    }
+    let epoch1 = formation.form_committee(1).await.unwrap();
+    assert!(epoch1.verify());
+
+    // The epoch 1 committee reconciles alice
+    let outgoing = BftCommittee::new_mock(4).await.unwrap();
+    outgoing.reconcile_balance(&account).await.unwrap();
+
+    let (epoch2, mut handoff) = formation.rotate(&epoch1, &outgoing).await.unwrap();
+    for signer in signers.iter().filter(|s| epoch1.is_member(s.did())) {
+        handoff.sign(signer);
+    }
+    formation.publish_handoff(&handoff).await.unwrap();
+
+    // Peers trusting epoch 1 move on to epoch 2's keys
+    let proof = formation.membership_proof(2).await.unwrap();
+    let keys = proof.verify(&epoch1.keys().unwrap()).unwrap();
+    assert_eq!(keys.epoch, 2);
+    assert!(epoch2.members.iter().all(|did| proof.is_member(did)));
+
+    // The incoming committee continues the sequence numbers
+    let incoming = BftCommittee::new_mock(4).await.unwrap();
+    incoming.resume_sequences(&handoff.sequences);
+    assert_eq!(incoming.sequences(), outgoing.sequences());
+    let result = incoming.reconcile_balance(&account).await.unwrap();
+    assert!(result.consensus);
+    assert_eq!(
+        incoming.sequences()["alice"],
+        outgoing.sequences()["alice"] + 1
+    );
+}
+
+/// Test the transaction ledger across spend and reconciliation
+#[tokio::test]
+async fn test_transaction_ledger_history() {
+    let state_engine = Arc::new(StateEngine::new().await.unwrap());
+    let bft_committee = Arc::new(
+        BftCommittee::new(vec![
+            "m1".to_string(),
+            "m2".to_string(),
+            "m3".to_string(),
+            "m4".to_string(),
+        ])
+        .unwrap(),
+    );
+
+    let scheduler = MutualCreditScheduler::new(
+        Arc::clone(&state_engine),
+        Arc::clone(&bft_committee),
+        "device1".to_string(),
+    )
+    .await
+    .unwrap();
+
+    CreditAccountHandle::create(&state_engine, "alice".to_string(), 10_000)
+        .await
+        .unwrap();
+    scheduler.set_device_escrow("alice", DeviceEscrow::new("device1".to_string(), 5_000, 7));
+
+    for (recipient, amount, category) in [("cafe", 450, "food"), ("market", 1_250, "groceries")] {
+        scheduler
+            .spend_local(
+                "alice",
+                amount,
+                recipient,
+                TransactionMetadata {
+                    description: format!("Paid {}", recipient),
+                    category: Some(category.to_string()),
+                    invoice_id: None,
+                },
+            )
+            .await
+            .unwrap();
+    }
+    scheduler.reconcile_account("alice").await.unwrap();
+
+    // Two payments recorded, then both confirmed
+    let page = scheduler
+        .ledger()
+        .history("alice", &LedgerQuery::new(), Page::first(3))
+        .await
+        .unwrap();
+    assert_eq!(page.entries.len(), 3);
+    assert_eq!(page.entries[0].event, LedgerEvent::Recorded);
+    assert_eq!(page.entries[1].event, LedgerEvent::Recorded);
+    assert_eq!(
+        page.entries[2].event,
+        LedgerEvent::StatusChanged {
+            from: TransactionStatus::Pending
+        }
+    );
+    let rest = scheduler
+        .ledger()
+        .history("alice", &LedgerQuery::new(), page.next.unwrap())
+        .await
+        .unwrap();
+    assert_eq!(rest.entries.len(), 1);
+    assert!(rest.next.is_none());
+
+    // Where did the grocery money go?
+    let groceries = LedgerQuery::new().with_category("groceries");
+    let csv = scheduler
+        .ledger()
+        .export("alice", &groceries, ExportFormat::Csv)
+        .await
+        .unwrap();
+    let lines: Vec<&str> = csv.split("\r\n").filter(|l| !l.is_empty()).collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].ends_with(",market,Paid market,groceries,-12.50,pending"));
+    assert!(lines[2].ends_with(",market,Paid market,groceries,-12.50,confirmed"));
+}
+
+/// Test paying an invoice and settling it at reconciliation
+#[tokio::test]
+async fn test_invoice_paid_on_reconciliation() {
+    let payee_key = SigningKey::from_bytes(&[7; 32]);
+    let payee = Did::from_key(payee_key.verifying_key());
+    let payer_key = SigningKey::from_bytes(&[8; 32]);
+    let payer = Did::from_key(payer_key.verifying_key());
+    let payer_account = payer.as_str().to_string();
+
+    let state_engine = Arc::new(StateEngine::new().await.unwrap());
+    let bft_committee = Arc::new(
+        BftCommittee::new(vec![
+            "m1".to_string(),
+            "m2".to_string(),
+            "m3".to_string(),
+            "m4".to_string(),
+        ])
+        .unwrap(),
+    );
+    let scheduler = MutualCreditScheduler::new(
+        Arc::clone(&state_engine),
+        Arc::clone(&bft_committee),
+        "device1".to_string(),
+    )
+    .await
+    .unwrap();
+
+    CreditAccountHandle::create(&state_engine, payer_account.clone(), 10_000)
+        .await
+        .unwrap();
+    scheduler.set_device_escrow(
+        &payer_account,
+        DeviceEscrow::new("device1".to_string(), 5_000, 7),
+    );
+
+    let request = PaymentRequest::create(
+        &payee,
+        &payee_key,
+        &payer,
+        2_500,
+        "Invoice #42",
+        Duration::from_secs(3600),
+    )
+    .unwrap();
+
+    let acceptance = scheduler
+        .pay_request(&payer_account, &request, &payer, &payer_key)
+        .await
+        .unwrap();
+    acceptance.verify(&request).unwrap();
+
+    // A request can only be paid once
+    assert!(scheduler
+        .pay_request(&payer_account, &request, &payer, &payer_key)
+        .await
+        .is_err());
+
+    let invoice = scheduler
+        .invoices()
+        .get(&request.request_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(invoice.status, InvoiceStatus::Accepted);
+
+    scheduler.reconcile_account(&payer_account).await.unwrap();
+
+    let invoice = scheduler
+        .invoices()
+        .get(&request.request_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(invoice.status, InvoiceStatus::Paid);
+    assert!(invoice.paid_at.is_some());
+
+    let account = CreditAccountHandle::load(&state_engine, &payer_account)
+        .await
+        .unwrap();
+    let tx = account
+        .read(|acc| Ok(acc.get_transaction(&acceptance.transaction_id).cloned()))
+        .unwrap()
+        .unwrap();
+    assert_eq!(tx.to, payee.as_str());
+    assert_eq!(
+        tx.metadata.invoice_id.as_deref(),
+        Some(request.request_id.as_str())
+    );
+}
+
+/// Test fees and demurrage paid into the commons account at reconciliation
+#[tokio::test]
+async fn test_fees_and_demurrage_paid_to_commons() {
+    const DAY: u64 = 86_400;
+
+    let scheduler = MutualCreditScheduler::new_mock().await.unwrap();
+    let state_engine = Arc::clone(scheduler.state_engine());
+    CreditPolicy::new("commons")
+        .with_fee(FeeSchedule::Percentage { basis_points: 100 })
+        .with_demurrage(DemurragePolicy {
+            basis_points: 10,
+            period_secs: DAY,
+            exempt_balance: 0,
+        })
+        .publish(&state_engine)
+        .await
+        .unwrap();
+
+    CreditAccountHandle::create(&state_engine, "commons".to_string(), 0)
+        .await
+        .unwrap();
+    let alice = CreditAccountHandle::create(&state_engine, "alice".to_string(), 100_000)
+        .await
+        .unwrap();
+
+    // Alice last reconciled three days ago
+    let today = chrono::Utc::now().timestamp() as u64 / DAY;
+    alice
+        .update(|acc| {
+            acc.last_reconciliation = (today - 3) * DAY;
+            Ok(())
+        })
+        .unwrap();
+    let escrow = DeviceEscrow::new(scheduler.device_id().to_string(), 20_000, 7);
+    scheduler.set_device_escrow("alice", escrow);
+    scheduler
+        .spend_local("alice", 10_000, "bob", TransactionMetadata::default())
+        .await
+        .unwrap();
+
+    // 1% fee on the payment, 0.1% a day on the idle balance for three days
+    scheduler.reconcile_account("alice").await.unwrap();
+    assert_eq!(
+        scheduler.get_balance("alice").await.unwrap(),
+        100_000 - 10_000 - 100 - 300
+    );
+
+    // The commons account receives both and pays neither
+    scheduler.reconcile_account("commons").await.unwrap();
+    assert_eq!(scheduler.get_balance("commons").await.unwrap(), 400);
+}
+
+/// Test a committee ruling refunds a confirmed payment with a new entry
+#[tokio::test]
+async fn test_dispute_resolved_by_committee() {
+    let state_engine = Arc::new(StateEngine::new().await.unwrap());
+    let bft_committee = Arc::new(BftCommittee::new_mock(4).await.unwrap());
+    let scheduler = MutualCreditScheduler::new(
+        Arc::clone(&state_engine),
+        Arc::clone(&bft_committee),
+        "device1".to_string(),
+    )
+    .await
+    .unwrap();
+
+    CreditAccountHandle::create(&state_engine, "alice".to_string(), 100_000)
+        .await
+        .unwrap();
+    CreditAccountHandle::create(&state_engine, "shop".to_string(), 0)
+        .await
+        .unwrap();
+    scheduler.set_device_escrow("alice", DeviceEscrow::new("device1".to_string(), 10_000, 7));
+    let tx_id = scheduler
+        .spend_local("alice", 5_000, "shop", TransactionMetadata::default())
+        .await
+        .unwrap();
+    scheduler.reconcile_account("alice").await.unwrap();
+    assert_eq!(scheduler.get_balance("alice").await.unwrap(), 95_000);
+
+    // Open the dispute and attach evidence
+    let dispute = scheduler
+        .open_dispute("alice", &tx_id, "Never delivered", DisputeRoute::Committee)
+        .await
+        .unwrap();
+    assert_eq!(dispute.disputed_from, TransactionStatus::Confirmed);
+    let courier_key = SigningKey::from_bytes(&[9; 32]);
+    let courier = Did::from_key(courier_key.verifying_key());
+    let statement = SignedStatement::sign(
+        &dispute.id,
+        &courier,
+        &courier_key,
+        "Parcel returned to sender",
+        1_000,
+    );
+    scheduler
+        .disputes()
+        .add_evidence(&dispute.id, Evidence::Statement(statement))
+        .await
+        .unwrap();
+
+    // The committee certifies the refund
+    let resolved = scheduler
+        .rule_dispute(&dispute.id, DisputeOutcome::Reverse)
+        .await
+        .unwrap();
+    assert_eq!(resolved.state, DisputeState::Resolved);
+    assert_eq!(resolved.evidence.len(), 1);
+    let resolution = resolved.resolution.unwrap();
+    let certificate = resolution.certificate.unwrap();
+    certificate.verify(bft_committee.keys()).unwrap();
+    assert_eq!(certificate.value, 5_000);
+    assert!(scheduler
+        .rule_dispute(&dispute.id, DisputeOutcome::Uphold)
+        .await
+        .is_err());
+
+    // The payment stands; the shop refunds it in a settlement transaction
+    let history = scheduler
+        .ledger()
+        .history("alice", &LedgerQuery::new(), Page::default())
+        .await
+        .unwrap();
+    let labels: Vec<String> = history.entries.iter().map(|e| e.event_label()).collect();
+    assert_eq!(
+        labels,
+        [
+            "recorded",
+            "pending->confirmed",
+            "confirmed->disputed",
+            "disputed->confirmed",
+            "dispute reverse"
+        ]
+    );
+    let settlement = &history.entries[4].transaction;
+    assert_eq!(Some(&settlement.id), resolution.settlement.as_ref());
+    assert_eq!(settlement.from, "shop");
+    assert_eq!(settlement.amount, 5_000);
+
+    scheduler.reconcile_account("alice").await.unwrap();
+    assert_eq!(scheduler.get_balance("alice").await.unwrap(), 100_000);
+}
+
+/// Test a guarantee group majority settles a pending payment in part
+#[tokio::test]
+async fn test_dispute_resolved_by_guarantee_group() {
+    let scheduler = MutualCreditScheduler::new_mock().await.unwrap();
+    let state_engine = Arc::clone(scheduler.state_engine());
+    let group = scheduler.guarantees().create("neighbours").await.unwrap();
+
+    let members: Vec<(Did, SigningKey)> = (1..=3)
+        .map(|i| {
+            let key = SigningKey::from_bytes(&[i; 32]);
+            (Did::from_key(key.verifying_key()), key)
+        })
+        .collect();
+    for (did, _) in &members {
+        CreditAccountHandle::create(&state_engine, did.as_str().to_string(), 10_000)
+            .await
+            .unwrap();
+        scheduler
+            .guarantees()
+            .pledge(&group.group_id, did.as_str(), 1_000)
+            .await
+            .unwrap();
+    }
+
+    CreditAccountHandle::create(&state_engine, "alice".to_string(), 100_000)
+        .await
+        .unwrap();
+    let escrow = DeviceEscrow::new(scheduler.device_id().to_string(), 10_000, 7);
+    scheduler.set_device_escrow("alice", escrow);
+    let tx_id = scheduler
+        .spend_local("alice", 5_000, "shop", TransactionMetadata::default())
+        .await
+        .unwrap();
+
+    let route = DisputeRoute::GuaranteeGroup {
+        group_id: group.group_id.clone(),
+    };
+    let dispute = scheduler
+        .open_dispute("alice", &tx_id, "Half the order missing", route)
+        .await
+        .unwrap();
+
+    // Held out of reconciliation while open
+    scheduler.reconcile_account("alice").await.unwrap();
+    assert_eq!(scheduler.get_balance("alice").await.unwrap(), 100_000);
+
+    // Committee rulings are refused, and one vote of three is not a majority
+    let partial = DisputeOutcome::Partial { refund: 2_000 };
+    assert!(scheduler.rule_dispute(&dispute.id, partial).await.is_err());
+    let vote = |i: usize| DisputeVote::sign(&dispute.id, &members[i].0, &members[i].1, partial, 1);
+    let open = scheduler.vote_dispute(vote(0)).await.unwrap();
+    assert!(open.resolution.is_none());
+
+    let resolved = scheduler.vote_dispute(vote(1)).await.unwrap();
+    let resolution = resolved.resolution.unwrap();
+    assert_eq!(resolution.outcome, partial);
+    assert_eq!(resolution.decided_by.len(), 2);
+    assert!(resolution.certificate.is_none());
+
+    // The payment is reversed and the 3,000 still owed paid anew
+    let account = CreditAccountHandle::load(&state_engine, "alice")
+        .await
+        .unwrap();
+    let status = account
+        .read(|acc| Ok(acc.get_transaction(&tx_id).unwrap().status))
+        .unwrap();
+    assert_eq!(status, TransactionStatus::Reversed);
+    scheduler.reconcile_account("alice").await.unwrap();
+    assert_eq!(scheduler.get_balance("alice").await.unwrap(), 97_000);
+
+    let entries = scheduler.ledger().entries("shop").await.unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].event_label(), "dispute partial");
+    assert_eq!(entries[0].transaction.amount, 3_000);
+}
+
 /// Test escrow expiry handling
 #[tokio::test]
 async fn test_escrow_expiry() {
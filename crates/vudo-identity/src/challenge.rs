@@ -0,0 +1,191 @@
+//! Challenge-response proof of DID ownership
+//!
+//! A verifier that wants to know whether a peer controls a DID sends it an
+//! [`AuthChallenge`]: a fresh nonce bound to the DID and to the channel it
+//! is sent over, e.g. the two endpoints of a connection. The peer signs it
+//! with the DID's key ([`AuthChallenge::respond`]) and the verifier checks
+//! the [`AuthResponse`] ([`AuthChallenge::verify`]).
+//!
+//! The prover only signs challenges whose binding matches the channel as it
+//! sees it, so a challenge relayed from another connection yields no
+//! response a verifier would accept. Challenges expire after
+//! [`CHALLENGE_TTL`] seconds; verifiers should accept one response per
+//! challenge.
+//!
+//! # Examples
+//!
+//! ```
+//! use vudo_identity::{AuthChallenge, DeviceIdentity};
+//!
+//! # async fn example() -> vudo_identity::error::Result<()> {
+//! let device = DeviceIdentity::generate("Alice's Phone").await?;
+//!
+//! // Verifier, on the connection from "laptop" to "phone"
+//! let challenge = AuthChallenge::new(device.did().clone(), "laptop>phone");
+//!
+//! // Prover, checking the binding against the same connection
+//! let response = challenge.respond(&device, "laptop>phone")?;
+//!
+//! challenge.verify(&response)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::did::Did;
+use crate::error::{Error, Result};
+use crate::identity::DeviceIdentity;
+use chrono::Utc;
+use ed25519_dalek::{Signature, Signer, Verifier};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+/// Domain separator for challenge signatures
+const DOMAIN: &[u8] = b"vudo-auth-challenge/1";
+
+/// Lifetime of a challenge (seconds)
+pub const CHALLENGE_TTL: u64 = 60;
+
+/// Nonce to be signed by the holder of a DID
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthChallenge {
+    /// DID whose key must sign the nonce
+    pub did: Did,
+
+    /// Random nonce (base58)
+    pub nonce: String,
+
+    /// Channel the challenge is bound to
+    pub binding: String,
+
+    /// Expiration (Unix seconds)
+    pub expires_at: u64,
+}
+
+/// Signature of a challenge by the holder of its DID
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthResponse {
+    /// DID that signed
+    pub did: Did,
+
+    /// Nonce of the answered challenge
+    pub nonce: String,
+
+    /// Signature over the challenge
+    pub signature: Vec<u8>,
+}
+
+impl AuthChallenge {
+    /// Create a challenge for `did` over the channel `binding`
+    pub fn new(did: Did, binding: impl Into<String>) -> Self {
+        let mut nonce = [0u8; 32];
+        OsRng.fill_bytes(&mut nonce);
+        Self {
+            did,
+            nonce: bs58::encode(nonce).into_string(),
+            binding: binding.into(),
+            expires_at: Utc::now().timestamp() as u64 + CHALLENGE_TTL,
+        }
+    }
+
+    /// Check if the challenge has expired
+    pub fn is_expired(&self) -> bool {
+        Utc::now().timestamp() as u64 > self.expires_at
+    }
+
+    /// Answer the challenge as `device`
+    ///
+    /// `binding` is the channel the challenge arrived on, as the device sees
+    /// it. Fails if the challenge is for another DID, another channel, or
+    /// has expired.
+    pub fn respond(&self, device: &DeviceIdentity, binding: &str) -> Result<AuthResponse> {
+        if &self.did != device.did() {
+            return Err(Error::SignatureVerification(format!(
+                "Challenge is for {}",
+                self.did
+            )));
+        }
+        if self.binding != binding {
+            return Err(Error::SignatureVerification(
+                "Challenge is bound to another channel".to_string(),
+            ));
+        }
+        if self.is_expired() {
+            return Err(Error::SignatureVerification(
+                "Challenge has expired".to_string(),
+            ));
+        }
+
+        let signature = device.signing_key().sign(&self.signing_bytes());
+        Ok(AuthResponse {
+            did: self.did.clone(),
+            nonce: self.nonce.clone(),
+            signature: signature.to_bytes().to_vec(),
+        })
+    }
+
+    /// Verify a response to this challenge
+    pub fn verify(&self, response: &AuthResponse) -> Result<()> {
+        if response.did != self.did || response.nonce != self.nonce {
+            return Err(Error::SignatureVerification(
+                "Response does not answer this challenge".to_string(),
+            ));
+        }
+        if self.is_expired() {
+            return Err(Error::SignatureVerification(
+                "Challenge has expired".to_string(),
+            ));
+        }
+
+        let signature = Signature::from_slice(&response.signature)?;
+        self.did
+            .verification_key
+            .verify(&self.signing_bytes(), &signature)?;
+        Ok(())
+    }
+
+    /// Bytes covered by the signature
+    fn signing_bytes(&self) -> Vec<u8> {
+        let mut data = DOMAIN.to_vec();
+        data.push(0);
+        data.extend_from_slice(self.did.as_str().as_bytes());
+        data.push(0);
+        data.extend_from_slice(self.nonce.as_bytes());
+        data.push(0);
+        data.extend_from_slice(self.binding.as_bytes());
+        data.push(0);
+        data.extend_from_slice(&self.expires_at.to_le_bytes());
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_challenge_response() {
+        let device = DeviceIdentity::generate("Alice's Phone").await.unwrap();
+        let other = DeviceIdentity::generate("Mallory's Phone").await.unwrap();
+
+        let challenge = AuthChallenge::new(device.did().clone(), "laptop>phone");
+        let response = challenge.respond(&device, "laptop>phone").unwrap();
+        assert!(challenge.verify(&response).is_ok());
+
+        // Only the DID's holder can answer, and only on the bound channel
+        assert!(challenge.respond(&other, "laptop>phone").is_err());
+        assert!(challenge.respond(&device, "mallory>phone").is_err());
+
+        // A response answers one challenge only
+        let fresh = AuthChallenge::new(device.did().clone(), "laptop>phone");
+        assert!(fresh.verify(&response).is_err());
+        let mut replayed = response.clone();
+        replayed.nonce = fresh.nonce.clone();
+        assert!(fresh.verify(&replayed).is_err());
+
+        let mut expired = challenge.clone();
+        expired.expires_at = 0;
+        assert!(expired.respond(&device, "laptop>phone").is_err());
+        assert!(expired.verify(&response).is_err());
+    }
+}
@@ -0,0 +1,507 @@
+//! W3C Verifiable Credentials issued by DIDs
+//!
+//! A [`VerifiableCredential`] states claims about a subject DID ("this
+//! device belongs to org X"), signed by an issuer DID. Credentials come in
+//! the two W3C VC 1.1 forms:
+//!
+//! - **Embedded proof** ([`VerifiableCredential::sign`]): the JSON-LD
+//!   credential carries an `Ed25519Signature2020` proof
+//! - **JWT** ([`VerifiableCredential::to_jwt`]): a JWS (EdDSA) with the
+//!   credential in its `vc` claim
+//!
+//! Both are verified against the issuer's DID document, resolved with a
+//! [`DidResolver`]. The embedded proof signs the credential serialized as
+//! JSON with sorted keys, not RDF-canonicalized, so other implementations
+//! will only verify JWT credentials.
+//!
+//! Masters attach credentials to device links with
+//! [`MasterIdentity::attest_device`](crate::MasterIdentity::attest_device),
+//! so higher layers can gate actions on verified attributes of a device.
+//!
+//! # Examples
+//!
+//! ```
+//! use vudo_identity::{DeviceIdentity, DidResolver, MasterIdentity, VerifiableCredential};
+//! use serde_json::json;
+//!
+//! # async fn example() -> vudo_identity::error::Result<()> {
+//! let org = MasterIdentity::generate("Acme").await?;
+//! let laptop = DeviceIdentity::generate("Alice's Laptop").await?;
+//!
+//! let credential = VerifiableCredential::new(
+//!     &org.did,
+//!     laptop.did(),
+//!     "OrganizationMembership",
+//!     json!({ "organization": "Acme" }),
+//! )?
+//! .sign(&org.signing_key())?;
+//!
+//! let resolver = DidResolver::new();
+//! credential.verify(&resolver).await?;
+//!
+//! let jwt = credential.to_jwt(&org.signing_key())?;
+//! let decoded = VerifiableCredential::verify_jwt(&jwt, &resolver).await?;
+//! assert_eq!(decoded.claim("organization"), Some(&json!("Acme")));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::did::{Did, DidDocument};
+use crate::error::{Error, Result};
+use crate::resolver::DidResolver;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Timelike, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// JSON-LD context of W3C credentials
+pub const CREDENTIALS_CONTEXT: &str = "https://www.w3.org/2018/credentials/v1";
+
+/// Type every credential has
+const BASE_TYPE: &str = "VerifiableCredential";
+
+/// Type of embedded proofs
+const PROOF_TYPE: &str = "Ed25519Signature2020";
+
+/// Purpose of embedded proofs
+const PROOF_PURPOSE: &str = "assertionMethod";
+
+/// Verifiable credential (W3C VC data model 1.1)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiableCredential {
+    /// JSON-LD contexts
+    #[serde(rename = "@context")]
+    pub context: Vec<String>,
+
+    /// Credential ID (optional)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    /// Credential types, starting with "VerifiableCredential"
+    #[serde(rename = "type")]
+    pub types: Vec<String>,
+
+    /// Issuer DID
+    pub issuer: String,
+
+    /// When the credential was issued
+    pub issuance_date: DateTime<Utc>,
+
+    /// When the credential expires (optional)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiration_date: Option<DateTime<Utc>>,
+
+    /// Claims about the subject, including its DID as `id`
+    pub credential_subject: Value,
+
+    /// Embedded proof (added when signed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof: Option<CredentialProof>,
+}
+
+/// Embedded proof of a credential
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialProof {
+    /// Proof type ("Ed25519Signature2020")
+    #[serde(rename = "type")]
+    pub proof_type: String,
+
+    /// When the proof was created
+    pub created: DateTime<Utc>,
+
+    /// Verification method of the issuer's DID document
+    pub verification_method: String,
+
+    /// Proof purpose ("assertionMethod")
+    pub proof_purpose: String,
+
+    /// Signature (multibase, base58btc)
+    pub proof_value: String,
+}
+
+/// JWT header of credentials
+#[derive(Debug, Serialize, Deserialize)]
+struct JwtHeader {
+    alg: String,
+    typ: String,
+    kid: String,
+}
+
+/// JWT claims of credentials
+#[derive(Debug, Serialize, Deserialize)]
+struct JwtClaims {
+    iss: String,
+    sub: String,
+    nbf: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    exp: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    jti: Option<String>,
+    vc: VerifiableCredential,
+}
+
+impl VerifiableCredential {
+    /// Create an unsigned credential of `credential_type` about `subject`
+    ///
+    /// `claims` must be a JSON object; the subject DID is added as its `id`.
+    pub fn new(
+        issuer: &Did,
+        subject: &Did,
+        credential_type: impl Into<String>,
+        claims: Value,
+    ) -> Result<Self> {
+        let Value::Object(mut claims) = claims else {
+            return Err(Error::Credential(
+                "Credential claims must be a JSON object".to_string(),
+            ));
+        };
+        claims.insert("id".to_string(), json!(subject.as_str()));
+
+        Ok(Self {
+            context: vec![CREDENTIALS_CONTEXT.to_string()],
+            id: None,
+            types: vec![BASE_TYPE.to_string(), credential_type.into()],
+            issuer: issuer.to_string(),
+            issuance_date: Self::now(),
+            expiration_date: None,
+            credential_subject: Value::Object(claims),
+            proof: None,
+        })
+    }
+
+    /// Set the credential ID
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Expire the credential `ttl` seconds after issuance
+    pub fn expires_in(mut self, ttl: u64) -> Self {
+        self.expiration_date = Some(self.issuance_date + Duration::seconds(ttl as i64));
+        self
+    }
+
+    /// Subject DID
+    pub fn subject(&self) -> Option<&str> {
+        self.credential_subject["id"].as_str()
+    }
+
+    /// Claim about the subject
+    pub fn claim(&self, name: &str) -> Option<&Value> {
+        self.credential_subject.get(name)
+    }
+
+    /// Check if the credential has a type
+    pub fn has_type(&self, credential_type: &str) -> bool {
+        self.types.iter().any(|t| t == credential_type)
+    }
+
+    /// Check if the credential has expired
+    pub fn is_expired(&self) -> bool {
+        self.expiration_date.is_some_and(|exp| Utc::now() >= exp)
+    }
+
+    /// Sign with an embedded proof
+    ///
+    /// `key` must be the signing key of the issuer DID.
+    pub fn sign(mut self, key: &SigningKey) -> Result<Self> {
+        let issuer = self.issuer_did(key)?;
+
+        let mut proof = CredentialProof {
+            proof_type: PROOF_TYPE.to_string(),
+            created: Self::now(),
+            verification_method: issuer.to_document().authentication[0].id.clone(),
+            proof_purpose: PROOF_PURPOSE.to_string(),
+            proof_value: String::new(),
+        };
+        self.proof = None;
+        let signature = key.sign(&self.signing_bytes(&proof)?);
+        proof.proof_value = format!("z{}", bs58::encode(signature.to_bytes()).into_string());
+        self.proof = Some(proof);
+        Ok(self)
+    }
+
+    /// Verify the embedded proof against the issuer's DID document
+    pub async fn verify(&self, resolver: &DidResolver) -> Result<()> {
+        let proof = self
+            .proof
+            .as_ref()
+            .ok_or_else(|| Error::Credential("Credential is not signed".to_string()))?;
+        if proof.proof_type != PROOF_TYPE || proof.proof_purpose != PROOF_PURPOSE {
+            return Err(Error::Credential(format!(
+                "Unsupported proof {} for {}",
+                proof.proof_type, proof.proof_purpose
+            )));
+        }
+        self.check_validity()?;
+
+        let document = resolver.resolve_str(&self.issuer).await?;
+        let key = verification_key(&document, &proof.verification_method)?;
+        let signature = decode_multibase_signature(&proof.proof_value)?;
+
+        let mut unsigned = self.clone();
+        unsigned.proof = None;
+        key.verify(&unsigned.signing_bytes(proof)?, &signature)?;
+        Ok(())
+    }
+
+    /// Encode as a JWT signed by the issuer
+    ///
+    /// Any embedded proof is left out.
+    pub fn to_jwt(&self, key: &SigningKey) -> Result<String> {
+        let issuer = self.issuer_did(key)?;
+        let subject = self
+            .subject()
+            .ok_or_else(|| Error::Credential("Credential has no subject".to_string()))?;
+
+        let header = JwtHeader {
+            alg: "EdDSA".to_string(),
+            typ: "JWT".to_string(),
+            kid: issuer.to_document().authentication[0].id.clone(),
+        };
+        let mut vc = self.clone();
+        vc.proof = None;
+        let claims = JwtClaims {
+            iss: self.issuer.clone(),
+            sub: subject.to_string(),
+            nbf: self.issuance_date.timestamp(),
+            exp: self.expiration_date.map(|exp| exp.timestamp()),
+            jti: self.id.clone(),
+            vc,
+        };
+
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header)?),
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims)?)
+        );
+        let signature = key.sign(signing_input.as_bytes());
+        Ok(format!(
+            "{}.{}",
+            signing_input,
+            URL_SAFE_NO_PAD.encode(signature.to_bytes())
+        ))
+    }
+
+    /// Verify a JWT credential against the issuer's DID document
+    pub async fn verify_jwt(jwt: &str, resolver: &DidResolver) -> Result<Self> {
+        let parts: Vec<&str> = jwt.split('.').collect();
+        let [header, claims, signature] = parts[..] else {
+            return Err(Error::Credential(format!(
+                "Invalid JWT format, expected 3 parts, got {}",
+                parts.len()
+            )));
+        };
+
+        let header: JwtHeader = serde_json::from_slice(&decode_base64(header)?)?;
+        let claims: JwtClaims = serde_json::from_slice(&decode_base64(claims)?)?;
+        if header.alg != "EdDSA" {
+            return Err(Error::Credential(format!(
+                "Unsupported JWT algorithm {}",
+                header.alg
+            )));
+        }
+        let vc = claims.vc;
+        if claims.iss != vc.issuer || Some(claims.sub.as_str()) != vc.subject() {
+            return Err(Error::Credential(
+                "JWT claims do not match the credential".to_string(),
+            ));
+        }
+        vc.check_validity()?;
+
+        let document = resolver.resolve_str(&claims.iss).await?;
+        let key = verification_key(&document, &header.kid)?;
+        let signature = Signature::from_slice(&decode_base64(signature)?)?;
+        let signing_input = &jwt[..jwt.rfind('.').unwrap_or(0)];
+        key.verify(signing_input.as_bytes(), &signature)?;
+        Ok(vc)
+    }
+
+    /// Check the credential is issued and not expired
+    fn check_validity(&self) -> Result<()> {
+        if self.issuance_date > Utc::now() + Duration::minutes(5) {
+            return Err(Error::Credential(
+                "Credential is issued in the future".to_string(),
+            ));
+        }
+        if self.is_expired() {
+            return Err(Error::Credential("Credential has expired".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Issuer DID, checked against `key`
+    fn issuer_did(&self, key: &SigningKey) -> Result<Did> {
+        let issuer = Did::parse(&self.issuer)?;
+        if key.verifying_key() != issuer.verification_key {
+            return Err(Error::Key(
+                "Signing key does not match credential issuer".to_string(),
+            ));
+        }
+        Ok(issuer)
+    }
+
+    /// Bytes covered by an embedded proof: the credential and proof options
+    /// as JSON with sorted keys
+    fn signing_bytes(&self, proof: &CredentialProof) -> Result<Vec<u8>> {
+        let mut options = serde_json::to_value(proof)?;
+        if let Some(options) = options.as_object_mut() {
+            options.remove("proofValue");
+        }
+        let document = json!({
+            "credential": serde_json::to_value(self)?,
+            "proof": options,
+        });
+
+        let mut data = Vec::new();
+        write_canonical(&document, &mut data)?;
+        Ok(data)
+    }
+
+    /// Current time, truncated to seconds (as in serialized dates)
+    fn now() -> DateTime<Utc> {
+        let now = Utc::now();
+        now.with_nanosecond(0).unwrap_or(now)
+    }
+}
+
+/// Ed25519 key of a verification method in a DID document
+fn verification_key(document: &DidDocument, method: &str) -> Result<VerifyingKey> {
+    let method = document
+        .authentication
+        .iter()
+        .find(|m| m.id == method)
+        .ok_or_else(|| {
+            Error::Credential(format!(
+                "{} has no verification method {}",
+                document.id, method
+            ))
+        })?;
+
+    let encoded = method
+        .public_key_multibase
+        .strip_prefix('z')
+        .ok_or_else(|| Error::InvalidMultibase(method.public_key_multibase.clone()))?;
+    let bytes = bs58::decode(encoded)
+        .into_vec()
+        .map_err(|e| Error::InvalidMultibase(e.to_string()))?;
+    let key: [u8; 32] = match bytes.as_slice() {
+        [0xed, 0x01, key @ ..] => key
+            .try_into()
+            .map_err(|_| Error::Key("Invalid Ed25519 key length".to_string()))?,
+        _ => return Err(Error::InvalidMulticodec(method.id.clone())),
+    };
+    Ok(VerifyingKey::from_bytes(&key)?)
+}
+
+/// Signature from a multibase (base58btc) proof value
+fn decode_multibase_signature(value: &str) -> Result<Signature> {
+    let encoded = value
+        .strip_prefix('z')
+        .ok_or_else(|| Error::InvalidMultibase(value.to_string()))?;
+    let bytes = bs58::decode(encoded)
+        .into_vec()
+        .map_err(|e| Error::InvalidMultibase(e.to_string()))?;
+    Ok(Signature::from_slice(&bytes)?)
+}
+
+fn decode_base64(part: &str) -> Result<Vec<u8>> {
+    URL_SAFE_NO_PAD
+        .decode(part)
+        .map_err(|e| Error::Encoding(format!("Failed to decode JWT: {}", e)))
+}
+
+/// Serialize JSON with object keys sorted, whatever the map order
+fn write_canonical(value: &Value, out: &mut Vec<u8>) -> Result<()> {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push(b'{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                out.extend_from_slice(&serde_json::to_vec(key)?);
+                out.push(b':');
+                write_canonical(&map[key], out)?;
+            }
+            out.push(b'}');
+        }
+        Value::Array(items) => {
+            out.push(b'[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_canonical(item, out)?;
+            }
+            out.push(b']');
+        }
+        _ => out.extend_from_slice(&serde_json::to_vec(value)?),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeviceIdentity, MasterIdentity};
+
+    #[tokio::test]
+    async fn test_credential_forms() {
+        let org = MasterIdentity::generate("Acme").await.unwrap();
+        let laptop = DeviceIdentity::generate("Alice's Laptop").await.unwrap();
+        let resolver = DidResolver::new();
+
+        let credential = VerifiableCredential::new(
+            &org.did,
+            laptop.did(),
+            "OrganizationMembership",
+            json!({ "organization": "Acme", "role": "engineer" }),
+        )
+        .unwrap()
+        .expires_in(3600)
+        .sign(&org.signing_key())
+        .unwrap();
+        assert!(credential.verify(&resolver).await.is_ok());
+
+        // Survives a round trip through JSON
+        let json = serde_json::to_string(&credential).unwrap();
+        let parsed: VerifiableCredential = serde_json::from_str(&json).unwrap();
+        assert!(parsed.verify(&resolver).await.is_ok());
+
+        let mut tampered = credential.clone();
+        tampered.credential_subject["role"] = json!("admin");
+        assert!(tampered.verify(&resolver).await.is_err());
+
+        // Only the issuer can sign
+        assert!(credential.clone().sign(&laptop.signing_key()).is_err());
+
+        let jwt = credential.to_jwt(&org.signing_key()).unwrap();
+        let decoded = VerifiableCredential::verify_jwt(&jwt, &resolver)
+            .await
+            .unwrap();
+        assert_eq!(decoded.subject(), Some(laptop.did().as_str()));
+        assert!(decoded.has_type("OrganizationMembership"));
+
+        let mut forged: Vec<&str> = jwt.split('.').collect();
+        let other = laptop.signing_key().sign(b"forged").to_bytes();
+        let other = URL_SAFE_NO_PAD.encode(other);
+        forged[2] = &other;
+        assert!(
+            VerifiableCredential::verify_jwt(&forged.join("."), &resolver)
+                .await
+                .is_err()
+        );
+
+        let mut expired = credential;
+        expired.expiration_date = Some(expired.issuance_date - Duration::seconds(1));
+        assert!(expired.is_expired());
+        assert!(expired.verify(&resolver).await.is_err());
+    }
+}
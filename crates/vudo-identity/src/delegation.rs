@@ -0,0 +1,421 @@
+//! Delegation chains of UCANs
+//!
+//! A [`DelegationChain`] holds the UCANs delegating capabilities from a root
+//! issuer down to a final audience, e.g. device → app → service. Each link is
+//! issued by the audience of the previous one and carries it as its proof.
+//! [`DelegationChain::verify`] checks every link: its signature and validity
+//! period, that it was issued by the audience of its parent, and that it
+//! claims neither a capability nor a lifetime its parent doesn't have. The
+//! result is the [`Delegation`] the chain grants its final audience.
+//!
+//! # Examples
+//!
+//! ```
+//! use vudo_identity::{Capability, DelegationChain, DeviceIdentity, Ucan};
+//! use chrono::Utc;
+//!
+//! # async fn example() -> vudo_identity::error::Result<()> {
+//! let device = DeviceIdentity::generate("Alice's Phone").await?;
+//! let app = DeviceIdentity::generate("Notes").await?;
+//! let service = DeviceIdentity::generate("Backup").await?;
+//! let now = Utc::now().timestamp() as u64;
+//!
+//! let chain = DelegationChain::issue(
+//!     device.did().clone(),
+//!     app.did().clone(),
+//!     vec![Capability::wildcard("vudo://notes/")],
+//!     now + 3600,
+//!     &device.signing_key(),
+//! )?
+//! .delegate(
+//!     service.did().clone(),
+//!     vec![Capability::new("vudo://notes/todo", "read")],
+//!     now + 600,
+//!     &app.signing_key(),
+//! )?;
+//!
+//! // The service presents the last UCAN, which carries the others
+//! let token = chain.encode()?;
+//! let delegation = DelegationChain::from_ucan(&Ucan::decode(&token)?)?.verify()?;
+//! assert_eq!(&delegation.issuer, device.did());
+//! assert!(delegation.allows(&Capability::new("vudo://notes/todo", "read")));
+//! assert!(!delegation.allows(&Capability::new("vudo://notes/todo", "write")));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::did::Did;
+use crate::error::{Error, Result};
+use crate::ucan::{Capability, Ucan};
+use chrono::Utc;
+use ed25519_dalek::SigningKey;
+
+/// Maximum number of UCANs in a chain
+pub const MAX_CHAIN_LENGTH: usize = 16;
+
+/// Capabilities a verified chain grants its final audience
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delegation {
+    /// Issuer of the root UCAN
+    pub issuer: Did,
+
+    /// Audience of the last UCAN
+    pub audience: Did,
+
+    /// Capabilities granted through the whole chain
+    pub capabilities: Vec<Capability>,
+
+    /// Expiration of the chain (Unix seconds)
+    pub expires_at: u64,
+
+    /// Number of UCANs in the chain
+    pub length: usize,
+}
+
+impl Delegation {
+    /// Check if the chain grants a capability
+    pub fn allows(&self, capability: &Capability) -> bool {
+        self.capabilities
+            .iter()
+            .any(|granted| granted.matches(capability))
+    }
+
+    /// Check if the chain has expired
+    pub fn is_expired(&self) -> bool {
+        Utc::now().timestamp() as u64 > self.expires_at
+    }
+}
+
+/// UCANs delegating capabilities from a root issuer, root first
+#[derive(Debug, Clone)]
+pub struct DelegationChain {
+    links: Vec<Ucan>,
+}
+
+impl DelegationChain {
+    /// Start a chain from a signed root UCAN
+    pub fn new(root: Ucan) -> Self {
+        Self { links: vec![root] }
+    }
+
+    /// Start a chain with a root UCAN from `issuer`, signed with its key
+    pub fn issue(
+        issuer: Did,
+        audience: Did,
+        capabilities: Vec<Capability>,
+        exp: u64,
+        key: &SigningKey,
+    ) -> Result<Self> {
+        check_key(&issuer, key)?;
+        let root = Ucan::new(issuer, audience, capabilities, exp, None, None, vec![]).sign(key)?;
+        Ok(Self::new(root))
+    }
+
+    /// Rebuild the chain a UCAN was delegated through from its proofs
+    ///
+    /// Each link's parent is the first of its proofs issued to the link's
+    /// issuer. Nothing is verified yet.
+    pub fn from_ucan(ucan: &Ucan) -> Result<Self> {
+        let mut links = vec![ucan.clone()];
+        loop {
+            let link = &links[links.len() - 1];
+            if link.prf.is_empty() {
+                break;
+            }
+            if links.len() == MAX_CHAIN_LENGTH {
+                return Err(Error::InsufficientDelegation(format!(
+                    "Delegation chain is longer than {} UCANs",
+                    MAX_CHAIN_LENGTH
+                )));
+            }
+
+            let mut parent = None;
+            for proof in &link.prf {
+                let proof = Ucan::decode(proof)?;
+                if proof.aud == link.iss {
+                    parent = Some(proof);
+                    break;
+                }
+            }
+            let parent = parent.ok_or_else(|| {
+                Error::InsufficientDelegation(format!("No proof delegates to {}", link.iss))
+            })?;
+            links.push(parent);
+        }
+
+        links.reverse();
+        Ok(Self { links })
+    }
+
+    /// Delegate part of the chain's capabilities to `audience`, signed with
+    /// the key of the chain's current audience
+    ///
+    /// Fails if the delegation claims a capability the chain doesn't grant,
+    /// or outlives the chain.
+    pub fn delegate(
+        mut self,
+        audience: Did,
+        capabilities: Vec<Capability>,
+        exp: u64,
+        key: &SigningKey,
+    ) -> Result<Self> {
+        let parent = self.leaf();
+        check_key(&parent.aud, key)?;
+        if exp > parent.exp {
+            return Err(Error::InsufficientDelegation(format!(
+                "Delegation to {} outlives its parent",
+                audience
+            )));
+        }
+
+        let link = parent.delegate(audience, capabilities, exp, key)?;
+        self.links.push(link);
+        Ok(self)
+    }
+
+    /// Verify every link and get the capabilities the chain grants
+    pub fn verify(&self) -> Result<Delegation> {
+        let root = &self.links[0];
+        root.verify_token()?;
+
+        for pair in self.links.windows(2) {
+            let (parent, link) = (&pair[0], &pair[1]);
+            link.verify_token()?;
+
+            if link.iss != parent.aud {
+                return Err(Error::InsufficientDelegation(format!(
+                    "UCAN issued by {} is not delegated to by its parent",
+                    link.iss
+                )));
+            }
+            if !parent.grants_to(&link.iss, &link.att)? {
+                return Err(Error::InsufficientDelegation(format!(
+                    "{} delegates capabilities it wasn't granted",
+                    link.iss
+                )));
+            }
+            if link.exp > parent.exp {
+                return Err(Error::InsufficientDelegation(format!(
+                    "Delegation to {} outlives its parent",
+                    link.aud
+                )));
+            }
+        }
+
+        let leaf = self.leaf();
+        let mut capabilities: Vec<Capability> = Vec::new();
+        for capability in &leaf.att {
+            if !capabilities.contains(capability) {
+                capabilities.push(capability.clone());
+            }
+        }
+
+        Ok(Delegation {
+            issuer: root.iss.clone(),
+            audience: leaf.aud.clone(),
+            capabilities,
+            expires_at: leaf.exp,
+            length: self.links.len(),
+        })
+    }
+
+    /// Get the UCANs of the chain, root first
+    pub fn links(&self) -> &[Ucan] {
+        &self.links
+    }
+
+    /// Get the last UCAN, which carries the others as proofs
+    pub fn leaf(&self) -> &Ucan {
+        &self.links[self.links.len() - 1]
+    }
+
+    /// Encode the last UCAN as JWT
+    pub fn encode(&self) -> Result<String> {
+        self.leaf().encode()
+    }
+}
+
+/// Fail unless `key` is the signing key of `did`
+fn check_key(did: &Did, key: &SigningKey) -> Result<()> {
+    if key.verifying_key() != did.verification_key {
+        return Err(Error::Key(format!(
+            "Signing key does not belong to {}",
+            did
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+    use x25519_dalek::{PublicKey, StaticSecret};
+
+    fn create_test_did() -> (Did, SigningKey) {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let encryption_secret = StaticSecret::random_from_rng(OsRng);
+        let encryption_public = PublicKey::from(&encryption_secret);
+        let did = Did::from_keys(signing_key.verifying_key(), &encryption_public).unwrap();
+        (did, signing_key)
+    }
+
+    fn now() -> u64 {
+        Utc::now().timestamp() as u64
+    }
+
+    #[test]
+    fn test_delegation_chain() {
+        let (device_did, device_key) = create_test_did();
+        let (app_did, app_key) = create_test_did();
+        let (service_did, _) = create_test_did();
+
+        let chain = DelegationChain::issue(
+            device_did.clone(),
+            app_did.clone(),
+            vec![Capability::wildcard("vudo://notes/")],
+            now() + 3600,
+            &device_key,
+        )
+        .unwrap()
+        .delegate(
+            service_did.clone(),
+            vec![
+                Capability::new("vudo://notes/todo", "read"),
+                Capability::new("vudo://notes/todo", "read"),
+            ],
+            now() + 600,
+            &app_key,
+        )
+        .unwrap();
+        assert_eq!(chain.links().len(), 2);
+
+        let decoded = Ucan::decode(&chain.encode().unwrap()).unwrap();
+        let delegation = DelegationChain::from_ucan(&decoded)
+            .unwrap()
+            .verify()
+            .unwrap();
+        assert_eq!(delegation.issuer, device_did);
+        assert_eq!(delegation.audience, service_did);
+        assert_eq!(
+            delegation.capabilities,
+            vec![Capability::new("vudo://notes/todo", "read")]
+        );
+        assert_eq!(delegation.expires_at, chain.leaf().exp);
+        assert_eq!(delegation.length, 2);
+        assert!(!delegation.is_expired());
+        assert!(!delegation.allows(&Capability::new("vudo://notes/ideas", "read")));
+    }
+
+    #[test]
+    fn test_delegation_attenuation() {
+        let (device_did, device_key) = create_test_did();
+        let (app_did, app_key) = create_test_did();
+        let (service_did, service_key) = create_test_did();
+
+        let chain = DelegationChain::issue(
+            device_did,
+            app_did,
+            vec![Capability::new("vudo://notes/*", "read")],
+            now() + 3600,
+            &device_key,
+        )
+        .unwrap();
+        assert!(chain.verify().is_ok());
+
+        // Escalating capabilities or lifetime is refused
+        let write = vec![Capability::new("vudo://notes/todo", "write")];
+        let read = vec![Capability::new("vudo://notes/todo", "read")];
+        let escalated = chain
+            .clone()
+            .delegate(service_did.clone(), write, now() + 600, &app_key);
+        assert!(matches!(escalated, Err(Error::InsufficientDelegation(_))));
+        let outliving =
+            chain
+                .clone()
+                .delegate(service_did.clone(), read.clone(), now() + 7200, &app_key);
+        assert!(matches!(outliving, Err(Error::InsufficientDelegation(_))));
+
+        // Only the chain's audience can extend it
+        let stolen =
+            chain
+                .clone()
+                .delegate(service_did.clone(), read.clone(), now() + 600, &service_key);
+        assert!(matches!(stolen, Err(Error::Key(_))));
+
+        // A hand-made link outliving its parent fails verification
+        let link = Ucan::new(
+            chain.leaf().aud.clone(),
+            service_did,
+            read,
+            now() + 7200,
+            None,
+            None,
+            vec![chain.encode().unwrap()],
+        )
+        .sign(&app_key)
+        .unwrap();
+        assert!(link.verify().is_ok());
+        let chain = DelegationChain::from_ucan(&link).unwrap();
+        assert!(matches!(
+            chain.verify(),
+            Err(Error::InsufficientDelegation(_))
+        ));
+    }
+
+    #[test]
+    fn test_broken_chain() {
+        let (device_did, device_key) = create_test_did();
+        let (app_did, _) = create_test_did();
+        let (other_did, other_key) = create_test_did();
+        let (service_did, _) = create_test_did();
+
+        let root = Ucan::new(
+            device_did,
+            app_did,
+            vec![Capability::wildcard("vudo://notes/")],
+            now() + 3600,
+            None,
+            None,
+            vec![],
+        )
+        .sign(&device_key)
+        .unwrap();
+
+        // A link issued by someone the root didn't delegate to
+        let link = Ucan::new(
+            other_did,
+            service_did,
+            vec![Capability::new("vudo://notes/todo", "read")],
+            now() + 600,
+            None,
+            None,
+            vec![root.encode().unwrap()],
+        )
+        .sign(&other_key)
+        .unwrap();
+        assert!(matches!(
+            DelegationChain::from_ucan(&link),
+            Err(Error::InsufficientDelegation(_))
+        ));
+
+        // An expired root invalidates the chain
+        let (device_did, device_key) = create_test_did();
+        let (app_did, _) = create_test_did();
+        let expired = Ucan::new(
+            device_did,
+            app_did,
+            vec![Capability::wildcard("vudo://notes/")],
+            now() - 1,
+            None,
+            None,
+            vec![],
+        )
+        .sign(&device_key)
+        .unwrap();
+        assert!(matches!(
+            DelegationChain::new(expired).verify(),
+            Err(Error::UcanExpired)
+        ));
+    }
+}
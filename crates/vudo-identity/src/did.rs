@@ -29,12 +29,34 @@
 //!
 //! println!("DID: {}", did);
 //! ```
+//!
+//! # Sealed Boxes
+//!
+//! Anyone can encrypt to a DID's X25519 key with [`Did::encrypt_to`]. Each
+//! box uses a fresh ephemeral key, agreed with the recipient's key over
+//! X25519, and XChaCha20-Poly1305; only the holder of the DID's secret key
+//! can open it ([`Did::decrypt_from`], or [`DeviceIdentity::decrypt`]). The
+//! sender stays anonymous: sign the plaintext if the recipient needs to know
+//! who sent it.
+//!
+//! [`DeviceIdentity::decrypt`]: crate::DeviceIdentity::decrypt
 
 use crate::error::{Error, Result};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
 use ed25519_dalek::VerifyingKey;
+use rand::rngs::OsRng;
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use std::fmt;
-use x25519_dalek::PublicKey as X25519PublicKey;
+use x25519_dalek::{EphemeralSecret, PublicKey as X25519PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+/// Key derivation context of sealed boxes
+const SEALED_BOX_CONTEXT: &str = "vudo-identity sealed box v1";
+
+/// Length of the ephemeral public key and nonce heading a sealed box
+const SEALED_BOX_HEADER: usize = 32 + 24;
 
 /// DID (Decentralized Identifier)
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -82,8 +104,27 @@ impl Did {
         })
     }
 
-    /// Parse a did:peer:2 string
+    /// Create a did:key DID from an Ed25519 public key
+    ///
+    /// The key agreement key is the X25519 form of the Ed25519 key, as in
+    /// the did:key spec.
+    pub fn from_key(verification_key: VerifyingKey) -> Self {
+        let mut ed_bytes = vec![0xed, 0x01];
+        ed_bytes.extend_from_slice(verification_key.as_bytes());
+
+        Self {
+            did: format!("did:key:z{}", bs58::encode(&ed_bytes).into_string()),
+            verification_key,
+            encryption_key: X25519PublicKey::from(verification_key.to_montgomery().to_bytes()),
+        }
+    }
+
+    /// Parse a did:peer:2 (or Ed25519 did:key) string
     pub fn parse(did_str: &str) -> Result<Self> {
+        if let Some(key) = did_str.strip_prefix("did:key:z") {
+            return Self::parse_key(did_str, key);
+        }
+
         // Format: did:peer:2.Ez<ed25519>.S<x25519>
         if !did_str.starts_with("did:peer:2.") {
             return Err(Error::Did(format!("Invalid did:peer:2 format: {}", did_str)));
@@ -142,9 +183,33 @@ impl Did {
         })
     }
 
-    /// Get DID method ("peer")
+    /// Parse the base58btc key of a did:key
+    fn parse_key(did_str: &str, key: &str) -> Result<Self> {
+        let ed_bytes = bs58::decode(key)
+            .into_vec()
+            .map_err(|e| Error::Encoding(format!("Failed to decode did:key: {}", e)))?;
+
+        if ed_bytes.len() != 34 || ed_bytes[0] != 0xed || ed_bytes[1] != 0x01 {
+            return Err(Error::Did(format!("Not an Ed25519 did:key: {}", did_str)));
+        }
+
+        let verification_key = VerifyingKey::from_bytes(
+            ed_bytes[2..34]
+                .try_into()
+                .map_err(|_| Error::Did("Invalid Ed25519 key length".to_string()))?,
+        )
+        .map_err(|e| Error::Did(format!("Invalid Ed25519 key: {}", e)))?;
+
+        Ok(Self::from_key(verification_key))
+    }
+
+    /// Get DID method ("peer" or "key")
     pub fn method(&self) -> &str {
-        "peer"
+        if self.did.starts_with("did:key:") {
+            "key"
+        } else {
+            "peer"
+        }
     }
 
     /// Get full DID string
@@ -185,6 +250,83 @@ impl Did {
             }],
         }
     }
+
+    /// Encrypt a message that only the holder of this DID can read
+    ///
+    /// Returns a sealed box: an ephemeral X25519 public key, a nonce and the
+    /// XChaCha20-Poly1305 ciphertext.
+    pub fn encrypt_to(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let ephemeral = EphemeralSecret::random_from_rng(OsRng);
+        let ephemeral_public = X25519PublicKey::from(&ephemeral);
+        let shared = ephemeral.diffie_hellman(&self.encryption_key);
+        if !shared.was_contributory() {
+            return Err(Error::Key("Invalid X25519 key agreement".to_string()));
+        }
+
+        let mut nonce = [0u8; 24];
+        OsRng.fill_bytes(&mut nonce);
+        let cipher = sealed_box_cipher(shared.as_bytes(), &ephemeral_public, &self.encryption_key);
+        let ciphertext = cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad: self.did.as_bytes(),
+                },
+            )
+            .map_err(|_| Error::Key("Failed to encrypt sealed box".to_string()))?;
+
+        let mut sealed = Vec::with_capacity(SEALED_BOX_HEADER + ciphertext.len());
+        sealed.extend_from_slice(ephemeral_public.as_bytes());
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Open a sealed box encrypted to this DID with its X25519 secret key
+    pub fn decrypt_from(&self, sealed: &[u8], secret: &StaticSecret) -> Result<Vec<u8>> {
+        if X25519PublicKey::from(secret) != self.encryption_key {
+            return Err(Error::Key(
+                "Secret key does not match the DID's encryption key".to_string(),
+            ));
+        }
+        if sealed.len() < SEALED_BOX_HEADER {
+            return Err(Error::Encoding("Sealed box is too short".to_string()));
+        }
+
+        let mut ephemeral = [0u8; 32];
+        ephemeral.copy_from_slice(&sealed[..32]);
+        let ephemeral_public = X25519PublicKey::from(ephemeral);
+        let shared = secret.diffie_hellman(&ephemeral_public);
+        if !shared.was_contributory() {
+            return Err(Error::Key("Invalid X25519 key agreement".to_string()));
+        }
+
+        let cipher = sealed_box_cipher(shared.as_bytes(), &ephemeral_public, &self.encryption_key);
+        cipher
+            .decrypt(
+                XNonce::from_slice(&sealed[32..SEALED_BOX_HEADER]),
+                Payload {
+                    msg: &sealed[SEALED_BOX_HEADER..],
+                    aad: self.did.as_bytes(),
+                },
+            )
+            .map_err(|_| Error::Key("Failed to decrypt sealed box".to_string()))
+    }
+}
+
+/// Cipher of a sealed box, keyed by the shared secret and both public keys
+fn sealed_box_cipher(
+    shared: &[u8; 32],
+    ephemeral: &X25519PublicKey,
+    recipient: &X25519PublicKey,
+) -> XChaCha20Poly1305 {
+    let mut material = Zeroizing::new(Vec::with_capacity(96));
+    material.extend_from_slice(shared);
+    material.extend_from_slice(ephemeral.as_bytes());
+    material.extend_from_slice(recipient.as_bytes());
+    let key = Zeroizing::new(blake3::derive_key(SEALED_BOX_CONTEXT, &material));
+    XChaCha20Poly1305::new(key.as_ref().into())
 }
 
 impl fmt::Display for Did {
@@ -315,4 +457,29 @@ mod tests {
 
         assert_eq!(did.method(), "peer");
     }
+
+    #[test]
+    fn test_sealed_box() {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let encryption_secret = StaticSecret::random_from_rng(OsRng);
+        let encryption_public = X25519PublicKey::from(&encryption_secret);
+        let did = Did::from_keys(signing_key.verifying_key(), &encryption_public).unwrap();
+
+        let sealed = did.encrypt_to(b"meet at noon").unwrap();
+        assert_ne!(did.encrypt_to(b"meet at noon").unwrap(), sealed);
+        assert_eq!(
+            did.decrypt_from(&sealed, &encryption_secret).unwrap(),
+            b"meet at noon"
+        );
+
+        // Only the DID's key opens it, and tampering is detected
+        let other = StaticSecret::random_from_rng(OsRng);
+        assert!(did.decrypt_from(&sealed, &other).is_err());
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(did.decrypt_from(&tampered, &encryption_secret).is_err());
+        assert!(did
+            .decrypt_from(&sealed[..SEALED_BOX_HEADER], &encryption_secret)
+            .is_err());
+    }
 }
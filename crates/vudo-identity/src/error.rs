@@ -24,6 +24,14 @@ pub enum Error {
     #[error("UCAN is not yet valid")]
     UcanNotYetValid,
 
+    /// UCAN has been revoked
+    #[error("UCAN has been revoked: {0}")]
+    UcanRevoked(String),
+
+    /// UCAN caveat not satisfied
+    #[error("Caveat not satisfied: {0}")]
+    CaveatViolated(String),
+
     /// Insufficient delegation in UCAN chain
     #[error("Insufficient delegation: {0}")]
     InsufficientDelegation(String),
@@ -64,6 +72,22 @@ pub enum Error {
     #[error("Revocation error: {0}")]
     Revocation(String),
 
+    /// Keystore error
+    #[error("Keystore error: {0}")]
+    Keystore(String),
+
+    /// Trusted timestamp error
+    #[error("Timestamp error: {0}")]
+    Timestamp(String),
+
+    /// Verifiable credential error
+    #[error("Credential error: {0}")]
+    Credential(String),
+
+    /// Guardian threshold error
+    #[error("Threshold error: {0}")]
+    Threshold(String),
+
     /// Resolution error
     #[error("DID resolution error: {0}")]
     Resolution(String),
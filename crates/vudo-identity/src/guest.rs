@@ -0,0 +1,412 @@
+//! Ephemeral guest identities for public demos and kiosks
+//!
+//! A guest node generates a throwaway device identity and a self-issued UCAN
+//! scoped by a [`GuestPolicy`]: read-only access to selected namespaces, a
+//! bandwidth cap and a short lifetime. No master identity or manual
+//! provisioning is involved. Peers decide what guests may do with their own
+//! policy, via [`GuestPolicy::admit`], so a guest can never claim more than
+//! the mesh offers.
+//!
+//! # Examples
+//!
+//! ```
+//! use vudo_identity::{GuestIdentity, GuestPolicy, RevocationList};
+//!
+//! # async fn example() -> vudo_identity::error::Result<()> {
+//! let policy = GuestPolicy::new(3600)
+//!     .read_namespace("demo")
+//!     .max_bandwidth(64 * 1024);
+//!
+//! // On the kiosk
+//! let guest = GuestIdentity::generate(policy.clone()).await?;
+//! let credential = guest.authorization().encode()?;
+//!
+//! // On a peer
+//! let ucan = vudo_identity::Ucan::decode(&credential)?;
+//! let grant = policy.admit(&ucan, &RevocationList::new(ucan.iss.clone()))?;
+//! assert!(grant.can_read("demo"));
+//! assert!(!grant.can_read("private"));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::did::Did;
+use crate::error::{Error, Result};
+use crate::identity::{DeviceIdentity, RevocationList};
+use crate::ucan::{Capability, Ucan};
+use chrono::Utc;
+use ed25519_dalek::SigningKey;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// Action granted to guests on their namespaces
+const READ: &str = "read";
+
+/// What guests are allowed to do
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuestPolicy {
+    /// Namespaces guests may read
+    pub namespaces: Vec<String>,
+
+    /// Maximum bandwidth (bytes/sec)
+    pub max_bandwidth: u64,
+
+    /// Lifetime of a guest credential (seconds)
+    pub ttl: u64,
+}
+
+impl Default for GuestPolicy {
+    fn default() -> Self {
+        Self::new(60 * 60) // 1 hour
+    }
+}
+
+impl GuestPolicy {
+    /// Create a policy granting no namespaces, capped at 64 KiB/s
+    pub fn new(ttl: u64) -> Self {
+        Self {
+            namespaces: Vec::new(),
+            max_bandwidth: 64 * 1024,
+            ttl,
+        }
+    }
+
+    /// Allow guests to read a namespace
+    pub fn read_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespaces.push(namespace.into());
+        self
+    }
+
+    /// Set the bandwidth cap (bytes/sec)
+    pub fn max_bandwidth(mut self, bytes_per_sec: u64) -> Self {
+        self.max_bandwidth = bytes_per_sec;
+        self
+    }
+
+    /// Check if guests may read a namespace
+    pub fn can_read(&self, namespace: &str) -> bool {
+        self.namespaces.iter().any(|ns| ns == namespace)
+    }
+
+    /// UCAN capabilities granted by this policy
+    pub fn capabilities(&self) -> Vec<Capability> {
+        self.namespaces
+            .iter()
+            .map(|ns| Capability::new(namespace_resource(ns), READ))
+            .collect()
+    }
+
+    /// Admit a guest presenting a self-issued UCAN
+    ///
+    /// The UCAN must be valid, not revoked, expire within the policy TTL and
+    /// only request reads of namespaces this policy allows. The returned
+    /// grant is the intersection of what the guest asked for and what the
+    /// policy allows.
+    pub fn admit(&self, ucan: &Ucan, revocations: &RevocationList) -> Result<GuestGrant> {
+        ucan.verify()?;
+
+        if ucan.iss != ucan.aud || !ucan.prf.is_empty() {
+            return Err(Error::Ucan(
+                "Guest credential must be self-issued".to_string(),
+            ));
+        }
+        if revocations.is_revoked(ucan.iss.as_str()) {
+            return Err(Error::DeviceRevoked(ucan.iss.to_string()));
+        }
+
+        let now = Utc::now().timestamp() as u64;
+        if ucan.exp > now + self.ttl {
+            return Err(Error::Ucan(format!(
+                "Guest credential outlives the {}s guest TTL",
+                self.ttl
+            )));
+        }
+
+        let allowed = self.capabilities();
+        let mut namespaces = Vec::new();
+        for requested in &ucan.att {
+            if !allowed.iter().any(|cap| cap.matches(requested)) {
+                return Err(Error::InsufficientDelegation(format!(
+                    "Guests may not {} {}",
+                    requested.action, requested.resource
+                )));
+            }
+            if let Some(ns) = self
+                .namespaces
+                .iter()
+                .find(|ns| requested.resource == namespace_resource(ns))
+            {
+                namespaces.push(ns.clone());
+            }
+        }
+
+        let requested_bandwidth = ucan
+            .fct
+            .as_ref()
+            .and_then(|facts| facts["guest"]["max_bandwidth"].as_u64())
+            .unwrap_or(self.max_bandwidth);
+
+        Ok(GuestGrant {
+            did: ucan.iss.clone(),
+            namespaces,
+            max_bandwidth: requested_bandwidth.min(self.max_bandwidth),
+            expires_at: ucan.exp,
+        })
+    }
+}
+
+/// A throwaway identity for a guest node
+#[derive(Debug, Clone)]
+pub struct GuestIdentity {
+    /// Ephemeral device identity
+    device: DeviceIdentity,
+
+    /// Self-issued UCAN carrying the guest's capabilities
+    authorization: Ucan,
+
+    /// Policy the identity was generated for
+    policy: GuestPolicy,
+}
+
+impl GuestIdentity {
+    /// Generate a guest identity scoped by a policy
+    pub async fn generate(policy: GuestPolicy) -> Result<Self> {
+        let mut device = DeviceIdentity::generate("guest").await?;
+        let did = device.did.as_str();
+        device.name = format!("guest-{}", &did[did.len() - 8..]);
+
+        let authorization = Ucan::new(
+            device.did.clone(),
+            device.did.clone(),
+            policy.capabilities(),
+            Utc::now().timestamp() as u64 + policy.ttl,
+            None,
+            None,
+            vec![],
+        )
+        .with_facts(json!({ "guest": { "max_bandwidth": policy.max_bandwidth } }))
+        .sign(&device.signing_key())?;
+
+        Ok(Self {
+            device,
+            authorization,
+            policy,
+        })
+    }
+
+    /// Get DID
+    pub fn did(&self) -> &Did {
+        self.device.did()
+    }
+
+    /// Get the ephemeral device identity
+    pub fn device(&self) -> &DeviceIdentity {
+        &self.device
+    }
+
+    /// Get the guest UCAN, to present to peers
+    pub fn authorization(&self) -> &Ucan {
+        &self.authorization
+    }
+
+    /// Get the policy
+    pub fn policy(&self) -> &GuestPolicy {
+        &self.policy
+    }
+
+    /// Expiration timestamp (Unix seconds)
+    pub fn expires_at(&self) -> u64 {
+        self.authorization.exp
+    }
+
+    /// Check if the guest credential has expired
+    pub fn is_expired(&self) -> bool {
+        Utc::now().timestamp() as u64 > self.expires_at()
+    }
+}
+
+/// What an admitted guest may do
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuestGrant {
+    /// Guest DID
+    pub did: Did,
+
+    /// Namespaces the guest may read
+    pub namespaces: Vec<String>,
+
+    /// Maximum bandwidth (bytes/sec)
+    pub max_bandwidth: u64,
+
+    /// Expiration timestamp (Unix seconds)
+    pub expires_at: u64,
+}
+
+impl GuestGrant {
+    /// Check if the guest may read a namespace
+    pub fn can_read(&self, namespace: &str) -> bool {
+        !self.is_expired() && self.namespaces.iter().any(|ns| ns == namespace)
+    }
+
+    /// Check if the grant has expired
+    pub fn is_expired(&self) -> bool {
+        Utc::now().timestamp() as u64 > self.expires_at
+    }
+
+    /// Revoke the guest until its credential expires
+    ///
+    /// The entry can be pruned with [`RevocationList::prune_expired`] once
+    /// the credential is no longer valid anyway.
+    pub fn revoke(
+        &self,
+        revocations: &mut RevocationList,
+        reason: Option<String>,
+        master_key: &SigningKey,
+    ) -> Result<()> {
+        revocations.revoke_until(
+            self.did.to_string(),
+            reason,
+            Some(self.expires_at),
+            master_key,
+        )
+    }
+}
+
+/// UCAN resource for a namespace
+fn namespace_resource(namespace: &str) -> String {
+    format!("vudo://{}/*", namespace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::MasterIdentity;
+
+    fn policy() -> GuestPolicy {
+        GuestPolicy::new(3600)
+            .read_namespace("demo")
+            .read_namespace("gallery")
+            .max_bandwidth(32 * 1024)
+    }
+
+    #[tokio::test]
+    async fn test_guest_identity_generation() {
+        let guest = GuestIdentity::generate(policy()).await.unwrap();
+
+        assert!(guest.device().device_name().starts_with("guest-"));
+        assert!(!guest.device().is_linked());
+        assert!(!guest.is_expired());
+        assert_eq!(guest.authorization().iss, *guest.did());
+        assert_eq!(guest.authorization().att.len(), 2);
+        assert!(guest
+            .authorization()
+            .att
+            .iter()
+            .all(|cap| cap.action == "read"));
+        assert!(guest.authorization().verify().is_ok());
+
+        let other = GuestIdentity::generate(policy()).await.unwrap();
+        assert_ne!(guest.did(), other.did());
+    }
+
+    #[tokio::test]
+    async fn test_admit_guest() {
+        let host = MasterIdentity::generate("Kiosk Host").await.unwrap();
+        let guest = GuestIdentity::generate(GuestPolicy::new(600).read_namespace("demo"))
+            .await
+            .unwrap();
+
+        let ucan = Ucan::decode(&guest.authorization().encode().unwrap()).unwrap();
+        let grant = policy().admit(&ucan, &host.revocations).unwrap();
+
+        assert_eq!(grant.did, *guest.did());
+        assert_eq!(grant.namespaces, vec!["demo".to_string()]);
+        assert_eq!(grant.max_bandwidth, 32 * 1024);
+        assert!(grant.can_read("demo"));
+        assert!(!grant.can_read("gallery"));
+    }
+
+    #[tokio::test]
+    async fn test_admit_rejects_overreach() {
+        let host = MasterIdentity::generate("Kiosk Host").await.unwrap();
+
+        // Namespace the host doesn't offer
+        let guest = GuestIdentity::generate(GuestPolicy::new(600).read_namespace("private"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            policy().admit(guest.authorization(), &host.revocations),
+            Err(Error::InsufficientDelegation(_))
+        ));
+
+        // Longer-lived than the host allows
+        let guest = GuestIdentity::generate(GuestPolicy::new(7200).read_namespace("demo"))
+            .await
+            .unwrap();
+        assert!(policy()
+            .admit(guest.authorization(), &host.revocations)
+            .is_err());
+
+        // Write access
+        let device = DeviceIdentity::generate("guest").await.unwrap();
+        let ucan = Ucan::new(
+            device.did.clone(),
+            device.did.clone(),
+            vec![Capability::new("vudo://demo/*", "write")],
+            Utc::now().timestamp() as u64 + 600,
+            None,
+            None,
+            vec![],
+        )
+        .sign(&device.signing_key())
+        .unwrap();
+        assert!(matches!(
+            policy().admit(&ucan, &host.revocations),
+            Err(Error::InsufficientDelegation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_admit_caps_bandwidth() {
+        let host = MasterIdentity::generate("Kiosk Host").await.unwrap();
+        let guest = GuestIdentity::generate(
+            GuestPolicy::new(600)
+                .read_namespace("demo")
+                .max_bandwidth(8 * 1024),
+        )
+        .await
+        .unwrap();
+
+        let grant = policy()
+            .admit(guest.authorization(), &host.revocations)
+            .unwrap();
+        assert_eq!(grant.max_bandwidth, 8 * 1024);
+    }
+
+    #[tokio::test]
+    async fn test_revoked_guest() {
+        let mut host = MasterIdentity::generate("Kiosk Host").await.unwrap();
+        let host_key = host.signing_key();
+        let guest = GuestIdentity::generate(GuestPolicy::new(600).read_namespace("demo"))
+            .await
+            .unwrap();
+
+        let grant = policy()
+            .admit(guest.authorization(), &host.revocations)
+            .unwrap();
+        grant
+            .revoke(&mut host.revocations, Some("Abuse".to_string()), &host_key)
+            .unwrap();
+
+        assert!(matches!(
+            policy().admit(guest.authorization(), &host.revocations),
+            Err(Error::DeviceRevoked(_))
+        ));
+        assert_eq!(
+            host.revocations.revocations[0].expires_at,
+            Some(guest.expires_at())
+        );
+
+        // Still valid, so pruning keeps the entry
+        assert_eq!(host.revocations.prune_expired(&host_key).unwrap(), 0);
+    }
+}
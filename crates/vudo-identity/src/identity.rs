@@ -30,14 +30,27 @@
 //! # }
 //! ```
 
+use crate::credential::VerifiableCredential;
 use crate::did::Did;
 use crate::error::{Error, Result};
+use crate::policy::CapabilityTemplate;
+use crate::revocation::UcanRevocation;
+use crate::timestamp::{TimestampCommittee, TimestampRequest, TimestampToken};
 use crate::ucan::{Capability, Ucan};
+use crate::wipe::{DeviceUnlink, WipeInstruction};
 use chrono::Utc;
-use ed25519_dalek::{Signature, SigningKey, Signer, Verifier};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
 use rand::rngs::OsRng;
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
+use zeroize::Zeroize;
+
+/// Key derivation version of new recovery phrases
+///
+/// Stored in the first byte of a phrase's entropy, so phrases keep
+/// recovering the same keys when the derivation changes.
+pub const MNEMONIC_VERSION: u8 = 1;
 
 /// Master identity (kept offline/cold storage)
 #[derive(Clone, Serialize, Deserialize)]
@@ -64,6 +77,14 @@ pub struct MasterIdentity {
 
     /// Key rotations
     pub rotations: Vec<KeyRotation>,
+
+    /// Audit log of device links, revocations and unlinks
+    #[serde(default)]
+    pub audit_log: Vec<DeviceAuditEntry>,
+
+    /// Seed the master keys were derived from (for recovery phrases)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    seed: Option<MasterSeed>,
 }
 
 impl std::fmt::Debug for MasterIdentity {
@@ -74,27 +95,74 @@ impl std::fmt::Debug for MasterIdentity {
             .field("devices", &self.devices)
             .field("revocations", &self.revocations)
             .field("rotations", &self.rotations)
+            .field("audit_log", &self.audit_log)
             .finish_non_exhaustive()
     }
 }
 
 impl MasterIdentity {
     /// Generate a new master identity
+    ///
+    /// The keys are derived from a random seed, which can be exported as a
+    /// recovery phrase with [`MasterIdentity::to_mnemonic`].
     pub async fn generate(name: impl Into<String>) -> Result<Self> {
-        let signing_key = SigningKey::generate(&mut OsRng);
-        let encryption_key = StaticSecret::random_from_rng(&mut OsRng);
+        Self::from_seed(MasterSeed::generate(), name.into())
+    }
+
+    /// Recover a master identity from a recovery phrase
+    ///
+    /// Yields the DID and keys of the identity the phrase was exported from.
+    /// Linked devices, revocations and rotations are not part of the phrase:
+    /// link devices again with [`MasterIdentity::link_device`].
+    pub fn from_mnemonic(phrase: &str, name: impl Into<String>) -> Result<Self> {
+        let mnemonic = bip39::Mnemonic::parse(phrase)
+            .map_err(|e| Error::Key(format!("Invalid recovery phrase: {}", e)))?;
+        let (mut entropy, len) = mnemonic.to_entropy_array();
+        if len != 32 {
+            entropy.zeroize();
+            return Err(Error::Key(format!(
+                "Recovery phrase must have 24 words, got {}",
+                mnemonic.word_count()
+            )));
+        }
+
+        let mut seed = MasterSeed([0u8; 32]);
+        seed.0.copy_from_slice(&entropy[..32]);
+        entropy.zeroize();
+        Self::from_seed(seed, name.into())
+    }
+
+    /// Export the master key material as a 24-word BIP-39 recovery phrase
+    ///
+    /// Keep it apart from the cold-storage copy of the identity. Fails if
+    /// the keys were not derived from a seed, e.g. after rotating to keys
+    /// generated elsewhere.
+    pub fn to_mnemonic(&self) -> Result<String> {
+        let seed = self.seed.as_ref().ok_or_else(|| {
+            Error::Key("Master keys were not derived from a recovery seed".to_string())
+        })?;
+        let mnemonic = bip39::Mnemonic::from_entropy(&seed.0)
+            .map_err(|e| Error::Key(format!("Invalid recovery seed: {}", e)))?;
+        Ok(mnemonic.to_string())
+    }
+
+    /// Create a master identity with keys derived from a seed
+    fn from_seed(seed: MasterSeed, name: String) -> Result<Self> {
+        let (signing_key, encryption_key) = seed.derive_keys()?;
         let encryption_public = X25519PublicKey::from(&encryption_key);
 
         let did = Did::from_keys(signing_key.verifying_key(), &encryption_public)?;
 
         Ok(Self {
             did: did.clone(),
-            name: name.into(),
+            name,
             master_key: signing_key,
             encryption_key,
             devices: Vec::new(),
             revocations: RevocationList::new(did),
             rotations: Vec::new(),
+            audit_log: Vec::new(),
+            seed: Some(seed),
         })
     }
 
@@ -103,6 +171,11 @@ impl MasterIdentity {
         self.master_key.clone()
     }
 
+    /// Open a sealed box encrypted to the master DID (see [`Did::encrypt_to`])
+    pub fn decrypt(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        self.did.decrypt_from(sealed, &self.encryption_key)
+    }
+
     /// Link a new device (offline operation using master key)
     pub async fn link_device(
         &mut self,
@@ -137,12 +210,56 @@ impl MasterIdentity {
             authorization: ucan,
             linked_at: Utc::now().timestamp() as u64,
             revoked: false,
+            credentials: Vec::new(),
         };
 
         self.devices.push(link.clone());
+        self.audit(&link.device_did, DeviceAction::Linked, None);
         Ok(link)
     }
 
+    /// Issue a credential about a linked device and attach it to the link
+    ///
+    /// E.g. `attest_device(&did, "OrganizationMembership", json!({ "organization": "Acme" }))`.
+    pub fn attest_device(
+        &mut self,
+        device_did: &Did,
+        credential_type: impl Into<String>,
+        claims: serde_json::Value,
+    ) -> Result<VerifiableCredential> {
+        let credential = VerifiableCredential::new(&self.did, device_did, credential_type, claims)?
+            .sign(&self.master_key)?;
+
+        let link = self
+            .devices
+            .iter_mut()
+            .find(|d| &d.device_did == device_did && !d.revoked)
+            .ok_or_else(|| Error::DeviceNotFound(device_did.to_string()))?;
+        link.credentials.push(credential.clone());
+        Ok(credential)
+    }
+
+    /// Issue a UCAN to `audience` from a capability template
+    ///
+    /// The UCAN's facts record the template name and caveats.
+    pub fn issue_from_template(
+        &self,
+        template: &CapabilityTemplate,
+        audience: Did,
+    ) -> Result<Ucan> {
+        Ucan::new(
+            self.did.clone(),
+            audience,
+            template.capabilities.clone(),
+            Utc::now().timestamp() as u64 + template.ttl,
+            None,
+            Some(Self::random_nonce()),
+            vec![],
+        )
+        .with_facts(template.facts())
+        .sign(&self.master_key)
+    }
+
     /// Revoke a device
     pub async fn revoke_device(
         &mut self,
@@ -161,17 +278,67 @@ impl MasterIdentity {
 
         // Add to revocation list
         self.revocations
-            .revoke(device_did.to_string(), reason, master_key)?;
+            .revoke(device_did.to_string(), reason.clone(), master_key)?;
 
+        self.audit(device_did, DeviceAction::Revoked, reason);
         Ok(())
     }
 
+    /// Unlink a lost or stolen device
+    ///
+    /// Revokes the device and signs a revocation of its UCAN and a wipe
+    /// instruction for it. Publish both, e.g. with vudo-p2p's
+    /// `unlink_device`, so peers refuse the device's UCAN and the device
+    /// clears its keystore on its next sync.
+    pub fn unlink_device(
+        &mut self,
+        device_did: &Did,
+        reason: Option<String>,
+    ) -> Result<DeviceUnlink> {
+        let link = self
+            .devices
+            .iter_mut()
+            .find(|d| &d.device_did == device_did)
+            .ok_or_else(|| Error::DeviceNotFound(device_did.to_string()))?;
+
+        if !link.revoked {
+            link.revoked = true;
+            self.revocations
+                .revoke(device_did.to_string(), reason.clone(), &self.master_key)?;
+        }
+
+        // Only the key that issued the UCAN can revoke it
+        let revocation = if link.authorization.iss == self.did {
+            Some(UcanRevocation::new(
+                &link.authorization,
+                self.did.clone(),
+                reason.clone(),
+                &self.master_key,
+            )?)
+        } else {
+            None
+        };
+        let wipe = WipeInstruction::new(
+            self.did.clone(),
+            device_did.clone(),
+            reason.clone(),
+            self.rotations.clone(),
+            &self.master_key,
+        )?;
+
+        self.audit(device_did, DeviceAction::Unlinked, reason);
+        Ok(DeviceUnlink { revocation, wipe })
+    }
+
     /// Check if a device is revoked
     pub fn is_device_revoked(&self, device_did: &Did) -> bool {
         self.revocations.is_revoked(&device_did.to_string())
     }
 
     /// Rotate master key
+    ///
+    /// The new keys are not derived from the recovery seed, so the identity
+    /// can no longer be exported as a recovery phrase.
     pub async fn rotate_key(
         &mut self,
         new_key: SigningKey,
@@ -188,10 +355,21 @@ impl MasterIdentity {
         self.encryption_key = new_encryption_key;
         self.did = new_did;
         self.rotations.push(rotation.clone());
+        self.seed = None;
 
         Ok(rotation)
     }
 
+    /// Record a device change in the audit log
+    fn audit(&mut self, device_did: &Did, action: DeviceAction, reason: Option<String>) {
+        self.audit_log.push(DeviceAuditEntry {
+            device_did: device_did.clone(),
+            action,
+            reason,
+            at: Utc::now().timestamp() as u64,
+        });
+    }
+
     fn random_nonce() -> String {
         use rand::Rng;
         let mut rng = rand::thread_rng();
@@ -200,6 +378,50 @@ impl MasterIdentity {
     }
 }
 
+/// Recovery seed of a master identity: the derivation version, then 31
+/// random bytes, encoded as the entropy of a BIP-39 phrase
+#[derive(Clone, Serialize, Deserialize)]
+struct MasterSeed([u8; 32]);
+
+impl MasterSeed {
+    /// Generate a seed for the current derivation version
+    fn generate() -> Self {
+        let mut seed = Self([0u8; 32]);
+        seed.0[0] = MNEMONIC_VERSION;
+        OsRng.fill_bytes(&mut seed.0[1..]);
+        seed
+    }
+
+    /// Derive the master signing and encryption keys
+    fn derive_keys(&self) -> Result<(SigningKey, StaticSecret)> {
+        match self.0[0] {
+            1 => {
+                let mut signing =
+                    blake3::derive_key("vudo-identity master signing key v1", &self.0);
+                let mut encryption =
+                    blake3::derive_key("vudo-identity master encryption key v1", &self.0);
+                let keys = (
+                    SigningKey::from_bytes(&signing),
+                    StaticSecret::from(encryption),
+                );
+                signing.zeroize();
+                encryption.zeroize();
+                Ok(keys)
+            }
+            version => Err(Error::Key(format!(
+                "Unsupported recovery phrase version {}",
+                version
+            ))),
+        }
+    }
+}
+
+impl Drop for MasterSeed {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
 /// Device identity (used for day-to-day operations)
 #[derive(Clone, Serialize, Deserialize)]
 pub struct DeviceIdentity {
@@ -269,6 +491,25 @@ impl DeviceIdentity {
         self.signing_key.clone()
     }
 
+    /// Derive a symmetric key from the device encryption key
+    ///
+    /// Keys for different contexts are independent. Use a hardcoded, unique
+    /// context string per purpose, e.g. `"vudo-storage-native sqlite v1"`.
+    pub fn derive_key(&self, context: &str) -> [u8; 32] {
+        blake3::derive_key(context, self.encryption_key.as_bytes())
+    }
+
+    /// Diffie-Hellman shared secret between the device encryption key and
+    /// another X25519 public key (e.g. the encryption key of a [`Did`])
+    pub fn key_agreement(&self, public: &X25519PublicKey) -> [u8; 32] {
+        self.encryption_key.diffie_hellman(public).to_bytes()
+    }
+
+    /// Open a sealed box encrypted to the device DID (see [`Did::encrypt_to`])
+    pub fn decrypt(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        self.did.decrypt_from(sealed, &self.encryption_key)
+    }
+
     /// Link to master identity
     pub fn link_to_master(&mut self, master_did: Did, authorization: Ucan) {
         self.master_did = Some(master_did);
@@ -306,6 +547,40 @@ pub struct DeviceLink {
 
     /// Revocation status
     pub revoked: bool,
+
+    /// Credentials issued by the master about the device
+    #[serde(default)]
+    pub credentials: Vec<VerifiableCredential>,
+}
+
+/// Change to a master's devices
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceAction {
+    /// Device linked
+    Linked,
+
+    /// Device revoked
+    Revoked,
+
+    /// Device unlinked and told to wipe its keys
+    Unlinked,
+}
+
+/// Audit log entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceAuditEntry {
+    /// Device DID
+    pub device_did: Did,
+
+    /// What happened
+    pub action: DeviceAction,
+
+    /// Reason (optional)
+    pub reason: Option<String>,
+
+    /// When it happened (Unix seconds)
+    pub at: u64,
 }
 
 /// Key rotation record
@@ -325,6 +600,10 @@ pub struct KeyRotation {
 
     /// Rotation certificate (signed by both keys)
     pub certificate: RotationCertificate,
+
+    /// Committee timestamp of the rotation (optional)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<TimestampToken>,
 }
 
 impl KeyRotation {
@@ -353,6 +632,7 @@ impl KeyRotation {
                 old_key_signature: old_sig.to_bytes().to_vec(),
                 new_key_signature: new_sig.to_bytes().to_vec(),
             },
+            timestamp: None,
         })
     }
 
@@ -362,6 +642,50 @@ impl KeyRotation {
         now < self.rotated_at + self.grace_period
     }
 
+    /// Request for a committee timestamp of this rotation
+    pub fn timestamp_request(&self) -> TimestampRequest {
+        let message = format!(
+            "{}|{}|{}",
+            self.old_did, self.new_did, self.certificate.timestamp
+        );
+        TimestampRequest::new(
+            "key-rotation",
+            message.as_bytes(),
+            self.certificate.timestamp,
+        )
+    }
+
+    /// Attach a committee timestamp after verifying it
+    pub fn attach_timestamp(
+        &mut self,
+        token: TimestampToken,
+        committee: &TimestampCommittee,
+    ) -> Result<()> {
+        token.verify(committee, &self.timestamp_request())?;
+        self.timestamp = Some(token);
+        Ok(())
+    }
+
+    /// Committee-agreed rotation time
+    ///
+    /// Fails if the rotation has no timestamp or it doesn't verify against
+    /// `committee`.
+    pub fn attested_at(&self, committee: &TimestampCommittee) -> Result<u64> {
+        let token = self
+            .timestamp
+            .as_ref()
+            .ok_or_else(|| Error::Timestamp("Key rotation has no timestamp".to_string()))?;
+        token.verify(committee, &self.timestamp_request())?;
+        Ok(token.timestamp)
+    }
+
+    /// Check if rotation is still in grace period, measured from the
+    /// committee-agreed time rather than the self-reported `rotated_at`
+    pub fn in_grace_period_attested(&self, committee: &TimestampCommittee) -> Result<bool> {
+        let now = Utc::now().timestamp() as u64;
+        Ok(now < self.attested_at(committee)? + self.grace_period)
+    }
+
     /// Verify rotation certificate
     pub fn verify(&self) -> Result<()> {
         let message = format!("{}|{}|{}", self.old_did, self.new_did, self.certificate.timestamp);
@@ -444,14 +768,49 @@ impl RevocationList {
         subject: String,
         reason: Option<String>,
         master_key: &SigningKey,
+    ) -> Result<()> {
+        self.revoke_until(subject, reason, None, master_key)
+    }
+
+    /// Add revocation that can be pruned after `expires_at` (Unix seconds)
+    ///
+    /// Use for subjects whose credentials expire anyway, e.g. guest
+    /// identities, so the list doesn't grow without bound.
+    pub fn revoke_until(
+        &mut self,
+        subject: String,
+        reason: Option<String>,
+        expires_at: Option<u64>,
+        master_key: &SigningKey,
     ) -> Result<()> {
         let revocation = Revocation {
             subject,
             reason,
             revoked_at: Utc::now().timestamp() as u64,
+            expires_at,
+            timestamp: None,
         };
 
         self.revocations.push(revocation);
+        self.bump_and_sign(master_key)
+    }
+
+    /// Remove expired revocations, returning how many were removed
+    pub fn prune_expired(&mut self, master_key: &SigningKey) -> Result<usize> {
+        let now = Utc::now().timestamp() as u64;
+        let before = self.revocations.len();
+        self.revocations
+            .retain(|r| r.expires_at.map_or(true, |expires_at| now < expires_at));
+
+        let pruned = before - self.revocations.len();
+        if pruned > 0 {
+            self.bump_and_sign(master_key)?;
+        }
+        Ok(pruned)
+    }
+
+    /// Increment the version and re-sign
+    fn bump_and_sign(&mut self, master_key: &SigningKey) -> Result<()> {
         self.version += 1;
         self.updated_at = Utc::now().timestamp() as u64;
 
@@ -468,6 +827,50 @@ impl RevocationList {
         self.revocations.iter().any(|r| r.subject == subject)
     }
 
+    /// Attach a committee timestamp to the latest revocation of `subject`
+    ///
+    /// Timestamps are not covered by the list signature; the committee
+    /// co-signatures authenticate them.
+    pub fn attach_timestamp(
+        &mut self,
+        subject: &str,
+        token: TimestampToken,
+        committee: &TimestampCommittee,
+    ) -> Result<()> {
+        let issuer = &self.issuer;
+        let revocation = self
+            .revocations
+            .iter_mut()
+            .rev()
+            .find(|r| r.subject == subject)
+            .ok_or_else(|| Error::Revocation(format!("{} is not revoked", subject)))?;
+        token.verify(committee, &revocation.timestamp_request(issuer))?;
+        revocation.timestamp = Some(token);
+        Ok(())
+    }
+
+    /// Committee-agreed time `subject` was revoked
+    ///
+    /// Returns `None` if the subject isn't revoked or no revocation of it
+    /// carries a timestamp that verifies against `committee`.
+    pub fn attested_revocation_time(
+        &self,
+        subject: &str,
+        committee: &TimestampCommittee,
+    ) -> Option<u64> {
+        self.revocations
+            .iter()
+            .filter(|r| r.subject == subject)
+            .filter_map(|r| {
+                let token = r.timestamp.as_ref()?;
+                token
+                    .verify(committee, &r.timestamp_request(&self.issuer))
+                    .ok()?;
+                Some(token.timestamp)
+            })
+            .min()
+    }
+
     /// Verify revocation list signature
     pub fn verify(&self) -> Result<()> {
         let sig_bytes = self
@@ -498,6 +901,9 @@ impl RevocationList {
         for revocation in &self.revocations {
             data.extend_from_slice(revocation.subject.as_bytes());
             data.extend_from_slice(&revocation.revoked_at.to_le_bytes());
+            if let Some(expires_at) = revocation.expires_at {
+                data.extend_from_slice(&expires_at.to_le_bytes());
+            }
         }
 
         Ok(data)
@@ -515,6 +921,22 @@ pub struct Revocation {
 
     /// Revocation timestamp
     pub revoked_at: u64,
+
+    /// When the entry may be pruned (optional, Unix seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+
+    /// Committee timestamp of the revocation (optional)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<TimestampToken>,
+}
+
+impl Revocation {
+    /// Request for a committee timestamp of this revocation by `issuer`
+    pub fn timestamp_request(&self, issuer: &Did) -> TimestampRequest {
+        let message = format!("{}|{}|{}", issuer, self.subject, self.revoked_at);
+        TimestampRequest::new("revocation", message.as_bytes(), self.revoked_at)
+    }
 }
 
 // Serde helpers for cryptographic keys
@@ -567,6 +989,7 @@ mod hex {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::timestamp::TimestampSigner;
 
     #[tokio::test]
     async fn test_master_identity_creation() {
@@ -589,6 +1012,37 @@ mod tests {
         assert_eq!(link.device_name, "Alice's Phone");
         assert!(!link.revoked);
         assert_eq!(master.devices.len(), 1);
+
+        let credential = master
+            .attest_device(
+                &device.did,
+                "OrganizationMembership",
+                serde_json::json!({ "organization": "Acme" }),
+            )
+            .unwrap();
+        assert_eq!(master.devices[0].credentials, vec![credential.clone()]);
+        assert!(credential.verify(&crate::DidResolver::new()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_device_derive_key() {
+        let device = DeviceIdentity::generate("Alice's Phone").await.unwrap();
+        let other = DeviceIdentity::generate("Alice's Laptop").await.unwrap();
+
+        assert_eq!(device.derive_key("storage"), device.derive_key("storage"));
+        assert_ne!(device.derive_key("storage"), device.derive_key("backup"));
+        assert_ne!(device.derive_key("storage"), other.derive_key("storage"));
+    }
+
+    #[tokio::test]
+    async fn test_device_key_agreement() {
+        let device = DeviceIdentity::generate("Alice's Phone").await.unwrap();
+        let other = DeviceIdentity::generate("Alice's Laptop").await.unwrap();
+
+        assert_eq!(
+            device.key_agreement(&other.did.encryption_key),
+            other.key_agreement(&device.did.encryption_key)
+        );
     }
 
     #[tokio::test]
@@ -610,6 +1064,42 @@ mod tests {
         assert!(master.is_device_revoked(&device.did));
     }
 
+    #[tokio::test]
+    async fn test_device_unlink() {
+        let mut master = MasterIdentity::generate("Alice").await.unwrap();
+        let device = DeviceIdentity::generate("Alice's Phone").await.unwrap();
+
+        let master_key = master.signing_key();
+        let link = master
+            .link_device("Alice's Phone".to_string(), device.did.clone(), &master_key)
+            .await
+            .unwrap();
+
+        let unlink = master
+            .unlink_device(&device.did, Some("Stolen".to_string()))
+            .unwrap();
+        assert!(master.is_device_revoked(&device.did));
+        assert!(master.revocations.verify().is_ok());
+
+        // The UCAN is revoked
+        let revocation = unlink.revocation.unwrap();
+        assert_eq!(revocation.cid, link.authorization.cid().unwrap());
+        let store = crate::RevocationStore::new();
+        store.publish(revocation).unwrap();
+        assert!(store.verify(&link.authorization).is_err());
+        assert!(unlink.wipe.verify().is_ok());
+
+        let actions: Vec<DeviceAction> = master.audit_log.iter().map(|e| e.action).collect();
+        assert_eq!(actions, vec![DeviceAction::Linked, DeviceAction::Unlinked]);
+        assert_eq!(master.audit_log[1].reason.as_deref(), Some("Stolen"));
+
+        let unknown = DeviceIdentity::generate("Bob's Phone").await.unwrap();
+        assert!(matches!(
+            master.unlink_device(&unknown.did, None),
+            Err(Error::DeviceNotFound(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_key_rotation() {
         let mut master = MasterIdentity::generate("Alice").await.unwrap();
@@ -623,6 +1113,155 @@ mod tests {
         assert!(rotation.in_grace_period());
     }
 
+    #[tokio::test]
+    async fn test_mnemonic_recovery() {
+        let master = MasterIdentity::generate("Alice").await.unwrap();
+        let phrase = master.to_mnemonic().unwrap();
+        assert_eq!(phrase.split_whitespace().count(), 24);
+
+        // The recovered identity has the same DID and can re-link devices
+        let mut recovered = MasterIdentity::from_mnemonic(&phrase, "Alice").unwrap();
+        assert_eq!(recovered.did, master.did);
+        assert_eq!(recovered.to_mnemonic().unwrap(), phrase);
+        assert!(recovered.devices.is_empty());
+
+        let device = DeviceIdentity::generate("Alice's Phone").await.unwrap();
+        let master_key = recovered.signing_key();
+        let link = recovered
+            .link_device("Alice's Phone".to_string(), device.did.clone(), &master_key)
+            .await
+            .unwrap();
+        assert!(link.authorization.verify().is_ok());
+        assert_eq!(link.authorization.iss, master.did);
+
+        // A wrong word fails the checksum
+        let mut words: Vec<&str> = phrase.split_whitespace().collect();
+        words[0] = if words[0] == "abandon" {
+            "ability"
+        } else {
+            "abandon"
+        };
+        assert!(MasterIdentity::from_mnemonic(&words.join(" "), "Alice").is_err());
+
+        // Unknown derivation versions are refused
+        let mut entropy = [7u8; 32];
+        entropy[0] = MNEMONIC_VERSION + 1;
+        let future = bip39::Mnemonic::from_entropy(&entropy).unwrap().to_string();
+        assert!(MasterIdentity::from_mnemonic(&future, "Alice").is_err());
+
+        // Rotated keys are not derived from the seed
+        let mut rotated = recovered;
+        rotated
+            .rotate_key(
+                SigningKey::generate(&mut OsRng),
+                StaticSecret::random_from_rng(OsRng),
+            )
+            .await
+            .unwrap();
+        assert!(rotated.to_mnemonic().is_err());
+    }
+
+    async fn timestamp_committee() -> (Vec<TimestampSigner>, TimestampCommittee) {
+        let mut signers = Vec::new();
+        for i in 0..4 {
+            let device = DeviceIdentity::generate(format!("notary-{}", i))
+                .await
+                .unwrap();
+            signers.push(TimestampSigner::new(device.did().clone(), device.signing_key()).unwrap());
+        }
+        let committee =
+            TimestampCommittee::new(signers.iter().map(|s| s.did().clone()).collect()).unwrap();
+        (signers, committee)
+    }
+
+    fn co_sign_all(
+        signers: &[TimestampSigner],
+        committee: &TimestampCommittee,
+        request: &TimestampRequest,
+    ) -> Result<TimestampToken> {
+        let signatures = signers
+            .iter()
+            .map(|s| s.co_sign(request))
+            .collect::<Result<Vec<_>>>()?;
+        committee.assemble(request, signatures)
+    }
+
+    #[tokio::test]
+    async fn test_key_rotation_timestamp() {
+        let (signers, committee) = timestamp_committee().await;
+        let mut master = MasterIdentity::generate("Alice").await.unwrap();
+        let new_key = SigningKey::generate(&mut OsRng);
+        let mut rotation = master
+            .rotate_key(new_key, StaticSecret::random_from_rng(&mut OsRng))
+            .await
+            .unwrap();
+        assert!(rotation.attested_at(&committee).is_err());
+
+        let token = co_sign_all(&signers, &committee, &rotation.timestamp_request()).unwrap();
+        rotation.attach_timestamp(token, &committee).unwrap();
+        assert!(rotation.in_grace_period_attested(&committee).unwrap());
+
+        // Another committee's co-signatures don't count
+        let (_, other) = timestamp_committee().await;
+        assert!(rotation.attested_at(&other).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_backdated_rotation_not_timestamped() {
+        let (signers, committee) = timestamp_committee().await;
+        let old_key = SigningKey::generate(&mut OsRng);
+        let new_key = SigningKey::generate(&mut OsRng);
+        let did = |key: &SigningKey| {
+            let encryption = X25519PublicKey::from(&StaticSecret::random_from_rng(&mut OsRng));
+            Did::from_keys(key.verifying_key(), &encryption).unwrap()
+        };
+        let mut rotation =
+            KeyRotation::create(&old_key, &new_key, &did(&old_key), &did(&new_key)).unwrap();
+
+        // Claim the rotation happened before the grace period
+        let backdated = rotation.rotated_at - rotation.grace_period;
+        let message = format!("{}|{}|{}", rotation.old_did, rotation.new_did, backdated);
+        rotation.rotated_at = backdated;
+        rotation.certificate.timestamp = backdated;
+        rotation.certificate.old_key_signature =
+            old_key.sign(message.as_bytes()).to_bytes().to_vec();
+        rotation.certificate.new_key_signature =
+            new_key.sign(message.as_bytes()).to_bytes().to_vec();
+        assert!(rotation.verify().is_ok());
+
+        assert!(co_sign_all(&signers, &committee, &rotation.timestamp_request()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_revocation_timestamp() {
+        let (signers, committee) = timestamp_committee().await;
+        let master = MasterIdentity::generate("Alice").await.unwrap();
+        let key = master.signing_key();
+        let mut revocation_list = RevocationList::new(master.did.clone());
+        revocation_list
+            .revoke("did:peer:abc123".to_string(), None, &key)
+            .unwrap();
+        assert_eq!(
+            revocation_list.attested_revocation_time("did:peer:abc123", &committee),
+            None
+        );
+
+        let request = revocation_list.revocations[0].timestamp_request(&master.did);
+        let token = co_sign_all(&signers, &committee, &request).unwrap();
+        assert!(revocation_list
+            .attach_timestamp("did:peer:xyz789", token.clone(), &committee)
+            .is_err());
+        revocation_list
+            .attach_timestamp("did:peer:abc123", token.clone(), &committee)
+            .unwrap();
+
+        assert_eq!(
+            revocation_list.attested_revocation_time("did:peer:abc123", &committee),
+            Some(token.timestamp)
+        );
+        assert!(revocation_list.verify().is_ok());
+    }
+
     #[tokio::test]
     async fn test_revocation_list() {
         let (did, key) = {
@@ -643,4 +1282,33 @@ mod tests {
         assert!(!revocation_list.is_revoked("did:peer:xyz789"));
         assert!(revocation_list.verify().is_ok());
     }
+
+    #[tokio::test]
+    async fn test_revocation_list_prune_expired() {
+        let master = MasterIdentity::generate("Alice").await.unwrap();
+        let key = master.signing_key();
+        let mut revocation_list = RevocationList::new(master.did.clone());
+        let now = Utc::now().timestamp() as u64;
+
+        revocation_list
+            .revoke_until("did:peer:expired".to_string(), None, Some(now - 1), &key)
+            .unwrap();
+        revocation_list
+            .revoke_until("did:peer:active".to_string(), None, Some(now + 3600), &key)
+            .unwrap();
+        revocation_list
+            .revoke("did:peer:permanent".to_string(), None, &key)
+            .unwrap();
+        assert!(revocation_list.is_revoked("did:peer:expired"));
+
+        assert_eq!(revocation_list.prune_expired(&key).unwrap(), 1);
+        assert!(!revocation_list.is_revoked("did:peer:expired"));
+        assert!(revocation_list.is_revoked("did:peer:active"));
+        assert!(revocation_list.is_revoked("did:peer:permanent"));
+        assert_eq!(revocation_list.version, 4);
+        assert!(revocation_list.verify().is_ok());
+
+        assert_eq!(revocation_list.prune_expired(&key).unwrap(), 0);
+        assert_eq!(revocation_list.version, 4);
+    }
 }
@@ -0,0 +1,561 @@
+//! Encrypted storage of identity keys
+//!
+//! A [`Keystore`] persists device and master identities, keys included, so
+//! they survive restarts instead of being generated anew in memory. Two
+//! backends are available:
+//!
+//! - **Passphrase**: one file per identity, sealed with ChaCha20-Poly1305
+//!   under a key derived from a passphrase with Argon2id. The KDF parameters
+//!   and salt are stored with each file.
+//! - **OS keychain** (`os-keychain` feature): the macOS Keychain, the Windows
+//!   Credential Manager (DPAPI) or the Secret Service on Linux, which encrypt
+//!   their entries with the user's login credentials.
+//!
+//! Identities are stored under a label, e.g. `"device"`. Loading checks that
+//! the stored keys match the stored DID.
+//!
+//! Both backends need the host's filesystem or keychain, so the keystore is
+//! not available on `wasm32`.
+//!
+//! # Examples
+//!
+//! ```
+//! use vudo_identity::{KdfParams, Keystore};
+//!
+//! # async fn example() -> vudo_identity::error::Result<()> {
+//! let dir = std::env::temp_dir().join("vudo-keystore-example");
+//! let keystore = Keystore::with_passphrase(&dir, "correct horse battery staple")
+//!     .with_kdf_params(KdfParams::INTERACTIVE);
+//!
+//! // Generated on first start, loaded afterwards
+//! let device = keystore.load_or_generate_device("device", "Alice's Laptop").await?;
+//! let again = keystore.load_device("device").await?;
+//! assert_eq!(again.did(), device.did());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::identity::{DeviceIdentity, MasterIdentity};
+use crate::wipe::WipeInstruction;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// Version of the key file format
+const FORMAT_VERSION: u8 = 1;
+
+/// Extension of key files
+const KEY_FILE_EXTENSION: &str = "key";
+
+/// Argon2id cost parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Memory cost (KiB)
+    pub memory_kib: u32,
+
+    /// Number of passes
+    pub iterations: u32,
+
+    /// Degree of parallelism
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    /// OWASP recommended minimum: 19 MiB, 2 passes
+    pub const MODERATE: Self = Self {
+        memory_kib: 19 * 1024,
+        iterations: 2,
+        parallelism: 1,
+    };
+
+    /// Fast parameters for tests and low-end devices
+    pub const INTERACTIVE: Self = Self {
+        memory_kib: 1024,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    /// Derive a 32-byte key from a passphrase
+    fn derive(&self, passphrase: &str, salt: &[u8]) -> Result<Zeroizing<[u8; 32]>> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+            .map_err(|e| Error::Keystore(format!("Invalid KDF parameters: {}", e)))?;
+
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+            .map_err(|e| Error::Keystore(format!("Key derivation failed: {}", e)))?;
+        Ok(key)
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self::MODERATE
+    }
+}
+
+/// Identity sealed with a passphrase, as stored on disk
+#[derive(Serialize, Deserialize)]
+struct SealedKeyFile {
+    /// Format version
+    version: u8,
+
+    /// KDF parameters the key was derived with
+    kdf: KdfParams,
+
+    /// KDF salt
+    salt: Vec<u8>,
+
+    /// AEAD nonce
+    nonce: Vec<u8>,
+
+    /// Encrypted identity (JSON)
+    ciphertext: Vec<u8>,
+}
+
+/// Where a keystore keeps its secrets
+enum Backend {
+    /// Files sealed with a passphrase
+    Passphrase {
+        dir: PathBuf,
+        passphrase: Zeroizing<String>,
+        kdf: KdfParams,
+    },
+
+    /// Platform keychain
+    #[cfg(feature = "os-keychain")]
+    OsKeychain { service: String },
+}
+
+/// Persistent, encrypted storage of identities
+pub struct Keystore {
+    backend: Backend,
+}
+
+impl std::fmt::Debug for Keystore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.backend {
+            Backend::Passphrase { dir, kdf, .. } => f
+                .debug_struct("Keystore")
+                .field("dir", dir)
+                .field("kdf", kdf)
+                .finish_non_exhaustive(),
+            #[cfg(feature = "os-keychain")]
+            Backend::OsKeychain { service } => f
+                .debug_struct("Keystore")
+                .field("service", service)
+                .finish(),
+        }
+    }
+}
+
+impl Keystore {
+    /// Create a keystore sealing identities in `dir` with a passphrase
+    pub fn with_passphrase(dir: impl Into<PathBuf>, passphrase: impl Into<String>) -> Self {
+        Self {
+            backend: Backend::Passphrase {
+                dir: dir.into(),
+                passphrase: Zeroizing::new(passphrase.into()),
+                kdf: KdfParams::default(),
+            },
+        }
+    }
+
+    /// Set the KDF parameters for identities saved from now on
+    ///
+    /// Existing files keep the parameters they were sealed with. Has no
+    /// effect on keychain-backed keystores.
+    pub fn with_kdf_params(mut self, params: KdfParams) -> Self {
+        match &mut self.backend {
+            Backend::Passphrase { kdf, .. } => *kdf = params,
+            #[cfg(feature = "os-keychain")]
+            Backend::OsKeychain { .. } => {}
+        }
+        self
+    }
+
+    /// Create a keystore backed by the platform keychain
+    ///
+    /// Entries are stored under `service`, e.g. the application name, with
+    /// the identity label as account.
+    #[cfg(feature = "os-keychain")]
+    pub fn os_keychain(service: impl Into<String>) -> Self {
+        Self {
+            backend: Backend::OsKeychain {
+                service: service.into(),
+            },
+        }
+    }
+
+    /// Save a device identity under a label, replacing any previous one
+    pub async fn save_device(&self, label: &str, device: &DeviceIdentity) -> Result<()> {
+        self.save(label, device).await
+    }
+
+    /// Load the device identity saved under a label
+    pub async fn load_device(&self, label: &str) -> Result<DeviceIdentity> {
+        let device: DeviceIdentity = self.load(label).await?;
+        if device.signing_key().verifying_key() != device.did.verification_key {
+            return Err(Error::Keystore(format!(
+                "Stored key of {} does not match its DID",
+                label
+            )));
+        }
+        Ok(device)
+    }
+
+    /// Load the device identity saved under a label, or generate and save a
+    /// new one on first use
+    pub async fn load_or_generate_device(
+        &self,
+        label: &str,
+        name: impl Into<String>,
+    ) -> Result<DeviceIdentity> {
+        match self.load_device(label).await {
+            Err(Error::IdentityNotFound(_)) => {
+                let device = DeviceIdentity::generate(name).await?;
+                self.save_device(label, &device).await?;
+                Ok(device)
+            }
+            result => result,
+        }
+    }
+
+    /// Save a master identity under a label, replacing any previous one
+    pub async fn save_master(&self, label: &str, master: &MasterIdentity) -> Result<()> {
+        self.save(label, master).await
+    }
+
+    /// Load the master identity saved under a label
+    pub async fn load_master(&self, label: &str) -> Result<MasterIdentity> {
+        let master: MasterIdentity = self.load(label).await?;
+        if master.signing_key().verifying_key() != master.did.verification_key {
+            return Err(Error::Keystore(format!(
+                "Stored key of {} does not match its DID",
+                label
+            )));
+        }
+        Ok(master)
+    }
+
+    /// Honor a wipe instruction for the device saved under a label
+    ///
+    /// Deletes the device if the instruction is addressed to it and signed
+    /// by its master. Returns false if no device is saved under the label.
+    pub async fn apply_wipe(&self, label: &str, wipe: &WipeInstruction) -> Result<bool> {
+        let device = match self.load_device(label).await {
+            Ok(device) => device,
+            Err(Error::IdentityNotFound(_)) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        wipe.verify_for(&device)?;
+        self.delete(label).await
+    }
+
+    /// Check if an identity is saved under a label
+    pub async fn contains(&self, label: &str) -> Result<bool> {
+        match self.read_secret(label).await {
+            Ok(_) => Ok(true),
+            Err(Error::IdentityNotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Delete the identity saved under a label
+    ///
+    /// Returns false if there was none.
+    pub async fn delete(&self, label: &str) -> Result<bool> {
+        check_label(label)?;
+        match &self.backend {
+            Backend::Passphrase { dir, .. } => {
+                match tokio::fs::remove_file(key_file(dir, label)).await {
+                    Ok(()) => Ok(true),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            #[cfg(feature = "os-keychain")]
+            Backend::OsKeychain { service } => {
+                let entry = keychain_entry(service, label)?;
+                blocking(move || match entry.delete_credential() {
+                    Ok(()) => Ok(true),
+                    Err(keyring::Error::NoEntry) => Ok(false),
+                    Err(e) => Err(Error::Keystore(e.to_string())),
+                })
+                .await
+            }
+        }
+    }
+
+    /// Serialize and store an identity
+    async fn save<T: Serialize>(&self, label: &str, identity: &T) -> Result<()> {
+        check_label(label)?;
+        let plaintext = Zeroizing::new(serde_json::to_vec(identity)?);
+
+        match &self.backend {
+            Backend::Passphrase {
+                dir,
+                passphrase,
+                kdf,
+            } => {
+                let mut salt = vec![0u8; 16];
+                let mut nonce = vec![0u8; 12];
+                OsRng.fill_bytes(&mut salt);
+                OsRng.fill_bytes(&mut nonce);
+
+                let key = kdf.derive(passphrase, &salt)?;
+                let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key.as_ref()))
+                    .encrypt(
+                        Nonce::from_slice(&nonce),
+                        Payload {
+                            msg: &plaintext,
+                            aad: label.as_bytes(),
+                        },
+                    )
+                    .map_err(|_| Error::Keystore("Encryption failed".to_string()))?;
+
+                let file = SealedKeyFile {
+                    version: FORMAT_VERSION,
+                    kdf: *kdf,
+                    salt,
+                    nonce,
+                    ciphertext,
+                };
+                write_private(dir, label, &serde_json::to_vec(&file)?).await
+            }
+            #[cfg(feature = "os-keychain")]
+            Backend::OsKeychain { service } => {
+                let entry = keychain_entry(service, label)?;
+                blocking(move || {
+                    entry
+                        .set_secret(&plaintext)
+                        .map_err(|e| Error::Keystore(e.to_string()))
+                })
+                .await
+            }
+        }
+    }
+
+    /// Load and deserialize an identity
+    async fn load<T: DeserializeOwned>(&self, label: &str) -> Result<T> {
+        let plaintext = self.read_secret(label).await?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    /// Read and decrypt the secret stored under a label
+    async fn read_secret(&self, label: &str) -> Result<Zeroizing<Vec<u8>>> {
+        check_label(label)?;
+        match &self.backend {
+            Backend::Passphrase {
+                dir, passphrase, ..
+            } => {
+                let bytes = match tokio::fs::read(key_file(dir, label)).await {
+                    Ok(bytes) => bytes,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                        return Err(Error::IdentityNotFound(label.to_string()));
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                let file: SealedKeyFile = serde_json::from_slice(&bytes)?;
+                if file.version != FORMAT_VERSION {
+                    return Err(Error::Keystore(format!(
+                        "Unsupported key file version {}",
+                        file.version
+                    )));
+                }
+                if file.nonce.len() != 12 {
+                    return Err(Error::Keystore("Invalid nonce length".to_string()));
+                }
+
+                let key = file.kdf.derive(passphrase, &file.salt)?;
+                let plaintext = ChaCha20Poly1305::new(Key::from_slice(key.as_ref()))
+                    .decrypt(
+                        Nonce::from_slice(&file.nonce),
+                        Payload {
+                            msg: &file.ciphertext,
+                            aad: label.as_bytes(),
+                        },
+                    )
+                    .map_err(|_| {
+                        Error::Keystore(format!("Wrong passphrase or corrupted key {}", label))
+                    })?;
+                Ok(Zeroizing::new(plaintext))
+            }
+            #[cfg(feature = "os-keychain")]
+            Backend::OsKeychain { service } => {
+                let entry = keychain_entry(service, label)?;
+                let label = label.to_string();
+                blocking(move || match entry.get_secret() {
+                    Ok(secret) => Ok(Zeroizing::new(secret)),
+                    Err(keyring::Error::NoEntry) => Err(Error::IdentityNotFound(label)),
+                    Err(e) => Err(Error::Keystore(e.to_string())),
+                })
+                .await
+            }
+        }
+    }
+}
+
+/// Only allow labels that are safe as file names and keychain accounts
+fn check_label(label: &str) -> Result<()> {
+    let valid = !label.is_empty()
+        && label.len() <= 64
+        && !label.starts_with('.')
+        && label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(Error::Keystore(format!("Invalid label: {:?}", label)));
+    }
+    Ok(())
+}
+
+/// Path of the key file of a label
+fn key_file(dir: &Path, label: &str) -> PathBuf {
+    dir.join(format!("{}.{}", label, KEY_FILE_EXTENSION))
+}
+
+/// Write a key file readable only by the owner, replacing it atomically
+async fn write_private(dir: &Path, label: &str, bytes: &[u8]) -> Result<()> {
+    tokio::fs::create_dir_all(dir).await?;
+    let path = key_file(dir, label);
+    let tmp = dir.join(format!(".{}.{}.tmp", label, KEY_FILE_EXTENSION));
+
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(&tmp).await?;
+    tokio::io::AsyncWriteExt::write_all(&mut file, bytes).await?;
+    file.sync_all().await?;
+    drop(file);
+
+    tokio::fs::rename(&tmp, &path).await?;
+    Ok(())
+}
+
+/// Keychain entry of a label
+#[cfg(feature = "os-keychain")]
+fn keychain_entry(service: &str, label: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(service, label).map_err(|e| Error::Keystore(e.to_string()))
+}
+
+/// Run a blocking keychain call off the async runtime
+#[cfg(feature = "os-keychain")]
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| Error::Keystore(format!("Keychain task failed: {}", e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_keystore(dir: &Path, passphrase: &str) -> Keystore {
+        Keystore::with_passphrase(dir, passphrase).with_kdf_params(KdfParams::INTERACTIVE)
+    }
+
+    #[tokio::test]
+    async fn test_passphrase_keystore() {
+        let dir = tempfile::tempdir().unwrap();
+        let keystore = test_keystore(dir.path(), "hunter2");
+
+        assert!(matches!(
+            keystore.load_device("device").await,
+            Err(Error::IdentityNotFound(_))
+        ));
+        let device = keystore
+            .load_or_generate_device("device", "Laptop")
+            .await
+            .unwrap();
+        assert!(keystore.contains("device").await.unwrap());
+
+        // Survives a restart
+        let loaded = keystore
+            .load_or_generate_device("device", "Laptop")
+            .await
+            .unwrap();
+        assert_eq!(loaded.did(), device.did());
+        assert_eq!(
+            loaded.signing_key().to_bytes(),
+            device.signing_key().to_bytes()
+        );
+
+        // Sealed, and bound to the passphrase and the label
+        let file = std::fs::read(key_file(dir.path(), "device")).unwrap();
+        let secret = device.signing_key().to_bytes();
+        assert!(!file.windows(secret.len()).any(|w| w == secret));
+        assert!(matches!(
+            test_keystore(dir.path(), "wrong")
+                .load_device("device")
+                .await,
+            Err(Error::Keystore(_))
+        ));
+        std::fs::copy(
+            key_file(dir.path(), "device"),
+            key_file(dir.path(), "other"),
+        )
+        .unwrap();
+        assert!(keystore.load_device("other").await.is_err());
+
+        assert!(keystore.delete("device").await.unwrap());
+        assert!(!keystore.delete("device").await.unwrap());
+        assert!(keystore.load_device("../device").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_master_keystore() {
+        let dir = tempfile::tempdir().unwrap();
+        let keystore = test_keystore(dir.path(), "hunter2");
+
+        let master = MasterIdentity::generate("Alice").await.unwrap();
+        keystore.save_master("master", &master).await.unwrap();
+        let loaded = keystore.load_master("master").await.unwrap();
+        assert_eq!(loaded.did, master.did);
+
+        // A master isn't a device
+        assert!(keystore.load_device("master").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_apply_wipe() {
+        let dir = tempfile::tempdir().unwrap();
+        let keystore = test_keystore(dir.path(), "hunter2");
+
+        let mut master = MasterIdentity::generate("Alice").await.unwrap();
+        let mut phone = DeviceIdentity::generate("Phone").await.unwrap();
+        let master_key = master.signing_key();
+        let link = master
+            .link_device("Phone".to_string(), phone.did().clone(), &master_key)
+            .await
+            .unwrap();
+        phone.link_to_master(master.did.clone(), link.authorization);
+        keystore.save_device("device", &phone).await.unwrap();
+
+        // Only the phone's master can wipe it
+        let impostor = MasterIdentity::generate("Mallory").await.unwrap();
+        let forged = WipeInstruction::new(
+            impostor.did.clone(),
+            phone.did().clone(),
+            None,
+            vec![],
+            &impostor.signing_key(),
+        )
+        .unwrap();
+        assert!(keystore.apply_wipe("device", &forged).await.is_err());
+        assert!(keystore.contains("device").await.unwrap());
+
+        let unlink = master.unlink_device(phone.did(), None).unwrap();
+        assert!(keystore.apply_wipe("device", &unlink.wipe).await.unwrap());
+        assert!(!keystore.contains("device").await.unwrap());
+        assert!(!keystore.apply_wipe("device", &unlink.wipe).await.unwrap());
+    }
+}
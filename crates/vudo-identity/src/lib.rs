@@ -1,12 +1,24 @@
 //! VUDO Identity - Decentralized Identity System
 //!
 //! This crate provides a decentralized identity system for VUDO Runtime with:
-//! - **Peer DIDs (did:peer:2)**: For pairwise node authentication
-//! - **UCANs**: User Controlled Authorization Networks for capability delegation
+//! - **Peer DIDs (did:peer:2)**: For pairwise node authentication, plus Ed25519 did:key for interop
+//! - **UCANs**: User Controlled Authorization Networks for capability delegation, encoded as spec-compliant (0.10) JWTs
+//! - **Delegation chains**: Device → app → service delegation, verified link by link
+//! - **Capability templates**: Named capability sets with caveats (time of day, document size) for consistent UCAN issuance
 //! - **Ed25519 keypairs**: For digital signatures
+//! - **Sealed boxes**: Encryption to a DID with X25519 and XChaCha20-Poly1305
 //! - **Master → Device linking**: Hierarchical identity management
-//! - **Key rotation**: With grace periods and revocation lists
-//! - **DID resolution**: For P2P peer verification
+//! - **Key rotation**: With grace periods and revocation lists, old keys accepted only within the grace period
+//! - **Threshold identities**: Master operations approved by k-of-n guardians instead of one cold key
+//! - **Device unlinking**: Lost or stolen devices revoked and remotely wiped, with an audit log
+//! - **Recovery phrases**: Master keys exported and recovered as 24-word BIP-39 mnemonics
+//! - **Keystore**: Identities persisted encrypted with a passphrase (Argon2id) or in the OS keychain (native only)
+//! - **UCAN revocation**: Signed revocations by CID, merged as an OR-Set synced over P2P
+//! - **Verifiable credentials**: W3C credentials (embedded proof or JWT) issued by DIDs and attached to device links
+//! - **DID resolution**: For P2P peer verification, with an LRU/TTL cache persisted through vudo-storage (`storage` feature)
+//! - **Challenge-response**: Proof of DID ownership bound to a connection
+//! - **Guest identities**: Ephemeral, tightly scoped identities for demos and kiosks
+//! - **Trusted timestamps**: BFT committee co-signatures against back-dated rotations and revocations
 //!
 //! # Architecture
 //!
@@ -166,21 +178,50 @@
 //! - [UCAN spec](https://ucan.xyz/)
 //! - [DID Core](https://www.w3.org/TR/did-core/)
 
+pub mod challenge;
+pub mod credential;
+pub mod delegation;
 pub mod did;
 pub mod error;
+pub mod guest;
 pub mod identity;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod keystore;
+pub mod policy;
 pub mod resolver;
+pub mod revocation;
+pub mod rotation;
+pub mod threshold;
+pub mod timestamp;
 pub mod ucan;
+pub mod wipe;
 
 // Re-export main types
+pub use challenge::{AuthChallenge, AuthResponse, CHALLENGE_TTL};
+pub use credential::{CredentialProof, VerifiableCredential, CREDENTIALS_CONTEXT};
+pub use delegation::{Delegation, DelegationChain, MAX_CHAIN_LENGTH};
 pub use did::{Did, DidDocument, VerificationMethod};
 pub use error::{Error, Result};
+pub use guest::{GuestGrant, GuestIdentity, GuestPolicy};
 pub use identity::{
-    DeviceIdentity, DeviceLink, KeyRotation, MasterIdentity, Revocation, RevocationList,
-    RotationCertificate,
+    DeviceAction, DeviceAuditEntry, DeviceIdentity, DeviceLink, KeyRotation, MasterIdentity,
+    Revocation, RevocationList, RotationCertificate, MNEMONIC_VERSION,
 };
-pub use resolver::{BatchDidResolver, DidResolver};
-pub use ucan::{Capability, Ucan};
+#[cfg(not(target_arch = "wasm32"))]
+pub use keystore::{KdfParams, Keystore};
+pub use policy::{check_caveats, CapabilityTemplate, Caveat, UsageContext};
+pub use resolver::{BatchDidResolver, DidResolver, ResolverMetrics};
+pub use revocation::{RevocationSet, RevocationStore, UcanRevocation};
+pub use rotation::RotationAwareVerifier;
+pub use threshold::{
+    GuardianSet, GuardianSignature, ThresholdApproval, ThresholdDeviceLink,
+    ThresholdMasterIdentity, ThresholdOperation, ThresholdProposal,
+};
+pub use timestamp::{
+    CoSignature, TimestampCommittee, TimestampRequest, TimestampSigner, TimestampToken,
+};
+pub use ucan::{Capability, Ucan, UCAN_VERSION};
+pub use wipe::{DeviceUnlink, WipeInstruction};
 
 /// Library version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,290 @@
+//! Capability templates for UCAN issuance
+//!
+//! Hand-rolled capability strings drift between apps and are hard to audit.
+//! A [`CapabilityTemplate`] names a set of capabilities (resource globs and
+//! actions), the [`Caveat`]s that restrict them and a lifetime. UCANs issued
+//! from a template ([`MasterIdentity::issue_from_template`]) carry its name
+//! and caveats in their signed facts, so verifiers can tell which template a
+//! UCAN came from and enforce its caveats ([`check_caveats`]).
+//!
+//! [`MasterIdentity::issue_from_template`]: crate::MasterIdentity::issue_from_template
+//!
+//! # Examples
+//!
+//! ```
+//! use vudo_identity::{
+//!     check_caveats, CapabilityTemplate, Caveat, DeviceIdentity, MasterIdentity, UsageContext,
+//! };
+//!
+//! # async fn example() -> vudo_identity::error::Result<()> {
+//! let master = MasterIdentity::generate("Alice").await?;
+//! let app = DeviceIdentity::generate("Photos").await?;
+//!
+//! let template = CapabilityTemplate::new("photos-upload", 24 * 60 * 60)
+//!     .allow("vudo://photos/*", "write")
+//!     .caveat(Caveat::MaxDocumentSize { bytes: 10 * 1024 * 1024 });
+//! let ucan = master.issue_from_template(&template, app.did().clone())?;
+//!
+//! // A verifier checks the UCAN, then its caveats for each use
+//! ucan.verify()?;
+//! check_caveats(&ucan, &UsageContext::now().document_size(1024))?;
+//! assert!(check_caveats(&ucan, &UsageContext::now().document_size(1 << 30)).is_err());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::ucan::{Capability, Ucan};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// Name of the built-in template for peers that only pull documents
+pub const READ_ONLY_SYNC: &str = "read-only-sync";
+
+/// Name of the built-in template granting every capability
+pub const ADMIN: &str = "admin";
+
+/// Restriction on the use of a UCAN's capabilities
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Caveat {
+    /// Usable only between two hours of the day (UTC, end exclusive)
+    ///
+    /// A window whose end is before its start wraps around midnight.
+    TimeOfDay {
+        /// First hour (0-23)
+        start_hour: u8,
+
+        /// Hour the window ends (0-23)
+        end_hour: u8,
+    },
+
+    /// Documents written must not exceed a size
+    MaxDocumentSize {
+        /// Maximum size (bytes)
+        bytes: u64,
+    },
+}
+
+impl Caveat {
+    /// Check the caveat against a use of the capabilities
+    pub fn check(&self, context: &UsageContext) -> Result<()> {
+        match *self {
+            Caveat::TimeOfDay {
+                start_hour,
+                end_hour,
+            } => {
+                let hour = context.hour();
+                let inside = if start_hour <= end_hour {
+                    hour >= start_hour && hour < end_hour
+                } else {
+                    hour >= start_hour || hour < end_hour
+                };
+                if !inside {
+                    return Err(Error::CaveatViolated(format!(
+                        "Usable between {:02}:00 and {:02}:00 UTC only",
+                        start_hour, end_hour
+                    )));
+                }
+            }
+            Caveat::MaxDocumentSize { bytes } => {
+                if let Some(size) = context.document_size {
+                    if size > bytes {
+                        return Err(Error::CaveatViolated(format!(
+                            "Document of {} bytes exceeds the {} byte limit",
+                            size, bytes
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A use of a UCAN's capabilities, checked against its caveats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UsageContext {
+    /// Time of use (Unix seconds)
+    pub at: u64,
+
+    /// Size of the document written (bytes, if any)
+    pub document_size: Option<u64>,
+}
+
+impl UsageContext {
+    /// Use at the current time
+    pub fn now() -> Self {
+        Self {
+            at: Utc::now().timestamp() as u64,
+            document_size: None,
+        }
+    }
+
+    /// Set the size of the document written
+    pub fn document_size(mut self, bytes: u64) -> Self {
+        self.document_size = Some(bytes);
+        self
+    }
+
+    /// Hour of the day (UTC)
+    fn hour(&self) -> u8 {
+        ((self.at % (24 * 60 * 60)) / (60 * 60)) as u8
+    }
+}
+
+/// Named set of capabilities, caveats and lifetime for issuing UCANs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapabilityTemplate {
+    /// Template name ("read-only-sync")
+    pub name: String,
+
+    /// Capabilities granted (resources may end in `*`)
+    pub capabilities: Vec<Capability>,
+
+    /// Caveats restricting the capabilities
+    pub caveats: Vec<Caveat>,
+
+    /// Lifetime of issued UCANs (seconds)
+    pub ttl: u64,
+}
+
+impl CapabilityTemplate {
+    /// Create a template granting nothing
+    pub fn new(name: impl Into<String>, ttl: u64) -> Self {
+        Self {
+            name: name.into(),
+            capabilities: Vec::new(),
+            caveats: Vec::new(),
+            ttl,
+        }
+    }
+
+    /// Read and sync every document, for 30 days
+    pub fn read_only_sync() -> Self {
+        Self::new(READ_ONLY_SYNC, 30 * 24 * 60 * 60).allow("vudo://*", "read")
+    }
+
+    /// Every capability on documents and topics, for 1 year
+    pub fn admin() -> Self {
+        Self::new(ADMIN, 365 * 24 * 60 * 60)
+            .allow("vudo://*", "*")
+            .allow("vudo-topic://*", "*")
+    }
+
+    /// Grant `action` on resources matching `resource`
+    pub fn allow(mut self, resource: impl Into<String>, action: impl Into<String>) -> Self {
+        self.capabilities.push(Capability::new(resource, action));
+        self
+    }
+
+    /// Restrict the capabilities with a caveat
+    pub fn caveat(mut self, caveat: Caveat) -> Self {
+        self.caveats.push(caveat);
+        self
+    }
+
+    /// Facts recording the template in issued UCANs
+    pub fn facts(&self) -> serde_json::Value {
+        json!({
+            "policy": {
+                "template": self.name,
+                "caveats": self.caveats,
+            }
+        })
+    }
+}
+
+/// Name of the template a UCAN was issued from, if any
+pub fn template_of(ucan: &Ucan) -> Option<&str> {
+    ucan.fct.as_ref()?["policy"]["template"].as_str()
+}
+
+/// Caveats of a UCAN and of the UCANs it was delegated from
+pub fn caveats_of(ucan: &Ucan) -> Result<Vec<Caveat>> {
+    let mut caveats = Vec::new();
+    if let Some(value) = ucan.fct.as_ref().map(|facts| &facts["policy"]["caveats"]) {
+        if !value.is_null() {
+            caveats.extend(serde_json::from_value::<Vec<Caveat>>(value.clone())?);
+        }
+    }
+    for proof in &ucan.prf {
+        caveats.extend(caveats_of(&Ucan::decode(proof)?)?);
+    }
+    Ok(caveats)
+}
+
+/// Check a use of a UCAN against the caveats of its delegation chain
+///
+/// Verify the UCAN itself first; this only checks caveats.
+pub fn check_caveats(ucan: &Ucan, context: &UsageContext) -> Result<()> {
+    for caveat in caveats_of(ucan)? {
+        caveat.check(context)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeviceIdentity, MasterIdentity};
+
+    #[tokio::test]
+    async fn test_issue_from_template() {
+        let master = MasterIdentity::generate("Alice").await.unwrap();
+        let app = DeviceIdentity::generate("Photos").await.unwrap();
+        let viewer = DeviceIdentity::generate("Viewer").await.unwrap();
+
+        let sync = master
+            .issue_from_template(&CapabilityTemplate::read_only_sync(), app.did().clone())
+            .unwrap();
+        sync.verify().unwrap();
+        assert_eq!(template_of(&sync), Some(READ_ONLY_SYNC));
+        assert!(sync
+            .grants_to(app.did(), &[Capability::new("vudo://photos/1", "read")])
+            .unwrap());
+        assert!(!sync
+            .grants_to(app.did(), &[Capability::new("vudo://photos/1", "write")])
+            .unwrap());
+
+        // 09:00-17:00 UTC, on 1970-01-01
+        let template = CapabilityTemplate::new("office-hours", 3600)
+            .allow("vudo://photos/*", "write")
+            .caveat(Caveat::TimeOfDay {
+                start_hour: 9,
+                end_hour: 17,
+            })
+            .caveat(Caveat::MaxDocumentSize { bytes: 1024 });
+        let ucan = master
+            .issue_from_template(&template, app.did().clone())
+            .unwrap();
+        let at = |hour: u64| UsageContext {
+            at: hour * 3600,
+            document_size: None,
+        };
+        assert!(check_caveats(&ucan, &at(10)).is_ok());
+        assert!(check_caveats(&ucan, &at(18)).is_err());
+        assert!(check_caveats(&ucan, &at(10).document_size(4096)).is_err());
+
+        // Caveats carry over to delegated UCANs
+        let delegated = ucan
+            .delegate(
+                viewer.did().clone(),
+                vec![Capability::new("vudo://photos/1", "write")],
+                ucan.exp,
+                &app.signing_key(),
+            )
+            .unwrap();
+        assert!(check_caveats(&delegated, &at(18)).is_err());
+
+        // Windows may wrap around midnight
+        let night = Caveat::TimeOfDay {
+            start_hour: 22,
+            end_hour: 6,
+        };
+        assert!(night.check(&at(23)).is_ok());
+        assert!(night.check(&at(2)).is_ok());
+        assert!(night.check(&at(12)).is_err());
+    }
+}
@@ -28,12 +28,30 @@
 
 use crate::did::{Did, DidDocument};
 use crate::error::{Error, Result};
+use crate::identity::KeyRotation;
 use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
 use tracing::debug;
 
+/// Default maximum number of cached documents
+pub const DEFAULT_CACHE_CAPACITY: usize = 10_000;
+
+/// Default lifetime of cached resolution failures (seconds)
+pub const DEFAULT_NEGATIVE_TTL: u64 = 60;
+
+/// Storage namespace of persisted DID documents
+#[cfg(feature = "storage")]
+pub const DID_CACHE_NAMESPACE: &str = "_did_cache";
+
 /// DID resolver with caching
+///
+/// Resolved documents are kept in an LRU cache with a TTL, and failed
+/// resolutions are remembered for a shorter TTL so a peer retrying with a
+/// bad DID doesn't trigger a resolution per attempt. Clones share the cache.
 #[derive(Debug, Clone)]
 pub struct DidResolver {
     /// Local cache of DID documents
@@ -41,15 +59,30 @@ pub struct DidResolver {
 
     /// Cache TTL (time-to-live) in seconds
     cache_ttl: u64,
+
+    /// Maximum number of cached documents
+    capacity: usize,
+
+    /// Recent resolution failures
+    failures: Arc<DashMap<String, CachedFailure>>,
+
+    /// TTL of cached failures in seconds
+    negative_ttl: u64,
+
+    /// Counter ordering cache uses (for LRU eviction)
+    clock: Arc<AtomicU64>,
+
+    /// Cache metrics
+    stats: Arc<ResolverStats>,
+
+    /// Known key rotations (old DID -> new DID)
+    rotations: Arc<DashMap<String, Did>>,
 }
 
 impl DidResolver {
     /// Create a new DID resolver
     pub fn new() -> Self {
-        Self {
-            cache: Arc::new(DashMap::new()),
-            cache_ttl: 3600, // 1 hour default
-        }
+        Self::with_ttl(3600) // 1 hour default
     }
 
     /// Create a new DID resolver with custom cache TTL
@@ -57,16 +90,38 @@ impl DidResolver {
         Self {
             cache: Arc::new(DashMap::new()),
             cache_ttl,
+            capacity: DEFAULT_CACHE_CAPACITY,
+            failures: Arc::new(DashMap::new()),
+            negative_ttl: DEFAULT_NEGATIVE_TTL,
+            clock: Arc::new(AtomicU64::new(0)),
+            stats: Arc::new(ResolverStats::default()),
+            rotations: Arc::new(DashMap::new()),
         }
     }
 
+    /// Set the maximum number of cached documents
+    ///
+    /// The least recently used documents are evicted beyond it.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Set how long failed resolutions are remembered (seconds, 0 to disable)
+    pub fn with_negative_ttl(mut self, negative_ttl: u64) -> Self {
+        self.negative_ttl = negative_ttl;
+        self
+    }
+
     /// Resolve DID to document
     pub async fn resolve(&self, did: &Did) -> Result<DidDocument> {
         let did_str = did.as_str();
 
         // Check local cache
-        if let Some(cached) = self.cache.get(did_str) {
+        if let Some(mut cached) = self.cache.get_mut(did_str) {
             if !cached.is_expired(self.cache_ttl) {
+                cached.last_used = self.tick();
+                self.stats.hits.fetch_add(1, Ordering::Relaxed);
                 debug!("DID resolved from cache: {}", did_str);
                 return Ok(cached.document.clone());
             } else {
@@ -74,12 +129,54 @@ impl DidResolver {
             }
         }
 
-        // For did:peer, derive document from DID itself
-        if did.method() == "peer" {
-            let doc = did.to_document();
-            self.cache_document(did_str, doc.clone());
-            debug!("DID resolved from derivation: {}", did_str);
-            return Ok(doc);
+        if let Some(error) = self.cached_failure(did_str) {
+            return Err(error);
+        }
+
+        self.stats.misses.fetch_add(1, Ordering::Relaxed);
+        match self.fetch(did).await {
+            Ok(doc) => {
+                self.failures.remove(did_str);
+                self.cache_document(did_str, doc.clone());
+                Ok(doc)
+            }
+            Err(e) => {
+                self.record_failure(did_str, &e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Recent failure to resolve a DID, if any
+    fn cached_failure(&self, did_str: &str) -> Option<Error> {
+        let failure = self.failures.get(did_str)?;
+        if failure.is_expired(self.negative_ttl) {
+            return None;
+        }
+        self.stats.negative_hits.fetch_add(1, Ordering::Relaxed);
+        debug!("DID resolution failure from cache: {}", did_str);
+        Some(Error::Resolution(failure.error.clone()))
+    }
+
+    /// Remember a failure to resolve a DID
+    fn record_failure(&self, did_str: &str, error: &Error) {
+        if self.negative_ttl > 0 {
+            self.failures.insert(
+                did_str.to_string(),
+                CachedFailure {
+                    error: error.to_string(),
+                    failed_at: Self::current_timestamp(),
+                },
+            );
+        }
+    }
+
+    /// Resolve a DID without the cache
+    async fn fetch(&self, did: &Did) -> Result<DidDocument> {
+        // For did:peer and did:key, derive document from DID itself
+        if matches!(did.method(), "peer" | "key") {
+            debug!("DID resolved from derivation: {}", did);
+            return Ok(did.to_document());
         }
 
         // For other methods, would query P2P network here
@@ -90,25 +187,92 @@ impl DidResolver {
         )))
     }
 
+    /// Resolve the current document of a DID, following key rotations
+    pub async fn resolve_current(&self, did: &Did) -> Result<DidDocument> {
+        self.resolve(&self.current_did(did)).await
+    }
+
+    /// Latest DID of an identity known by `did`
+    pub fn current_did(&self, did: &Did) -> Did {
+        let mut current = did.clone();
+        // Bounded, in case of a rotation cycle
+        for _ in 0..=self.rotations.len() {
+            match self.rotations.get(current.as_str()) {
+                Some(next) => current = next.clone(),
+                None => break,
+            }
+        }
+        current
+    }
+
+    /// Record a verified key rotation
+    ///
+    /// Drops the cached document of the old DID and caches the new one, so
+    /// [`resolve_current`](Self::resolve_current) picks up the new keys.
+    pub fn apply_rotation(&self, rotation: &KeyRotation) -> Result<()> {
+        rotation.verify()?;
+        self.invalidate(&rotation.old_did);
+        self.rotations
+            .insert(rotation.old_did.to_string(), rotation.new_did.clone());
+        self.cache_document(rotation.new_did.as_str(), rotation.new_did.to_document());
+        debug!("DID rotated: {} -> {}", rotation.old_did, rotation.new_did);
+        Ok(())
+    }
+
+    /// Drop the cached document of a DID
+    pub fn invalidate(&self, did: &Did) {
+        self.cache.remove(did.as_str());
+        self.failures.remove(did.as_str());
+        debug!("Invalidated cached DID document: {}", did);
+    }
+
     /// Resolve DID from string
     pub async fn resolve_str(&self, did_str: &str) -> Result<DidDocument> {
-        let did = Did::parse(did_str)?;
+        if let Some(error) = self.cached_failure(did_str) {
+            return Err(error);
+        }
+        let did = Did::parse(did_str).inspect_err(|e| {
+            self.stats.misses.fetch_add(1, Ordering::Relaxed);
+            self.record_failure(did_str, e);
+        })?;
         self.resolve(&did).await
     }
 
     /// Cache a DID document
     pub fn cache_document(&self, did: &str, document: DidDocument) {
-        let cached = CachedDocument {
-            document,
-            cached_at: Self::current_timestamp(),
-        };
-        self.cache.insert(did.to_string(), cached);
+        self.insert(
+            did,
+            CachedDocument {
+                document,
+                cached_at: Self::current_timestamp(),
+                last_used: 0,
+            },
+        );
         debug!("Cached DID document: {}", did);
     }
 
+    /// Insert a cache entry, evicting the least recently used beyond capacity
+    fn insert(&self, did: &str, mut cached: CachedDocument) {
+        cached.last_used = self.tick();
+        self.cache.insert(did.to_string(), cached);
+
+        while self.cache.len() > self.capacity {
+            let oldest = self
+                .cache
+                .iter()
+                .min_by_key(|entry| entry.last_used)
+                .map(|entry| entry.key().clone());
+            let Some(oldest) = oldest else { break };
+            self.cache.remove(&oldest);
+            self.stats.evictions.fetch_add(1, Ordering::Relaxed);
+            debug!("Evicted DID document from cache: {}", oldest);
+        }
+    }
+
     /// Clear cache
     pub fn clear_cache(&self) {
         self.cache.clear();
+        self.failures.clear();
         debug!("DID resolver cache cleared");
     }
 
@@ -124,9 +288,82 @@ impl DidResolver {
             let age = now.saturating_sub(cached.cached_at);
             age < self.cache_ttl
         });
+        self.failures
+            .retain(|_, failure| now.saturating_sub(failure.failed_at) < self.negative_ttl);
         debug!("Pruned expired entries from DID cache");
     }
 
+    /// Re-resolve cached documents past half their TTL, and prune expired
+    /// entries
+    ///
+    /// Keeps documents of active peers warm, so connections don't wait on a
+    /// resolution when an entry expires. Returns the number of documents
+    /// refreshed.
+    pub async fn refresh(&self) -> usize {
+        let now = Self::current_timestamp();
+        let stale: Vec<String> = self
+            .cache
+            .iter()
+            .filter(|entry| now.saturating_sub(entry.cached_at) >= self.cache_ttl / 2)
+            .map(|entry| entry.key().clone())
+            .collect();
+
+        let mut refreshed = 0;
+        for did_str in stale {
+            let Ok(did) = Did::parse(&did_str) else {
+                continue;
+            };
+            if let Ok(document) = self.fetch(&did).await {
+                // Keep the LRU position of the entry
+                if let Some(mut cached) = self.cache.get_mut(&did_str) {
+                    cached.document = document;
+                    cached.cached_at = now;
+                    refreshed += 1;
+                }
+            }
+        }
+
+        self.prune_cache();
+        self.stats
+            .refreshes
+            .fetch_add(refreshed as u64, Ordering::Relaxed);
+        debug!("Refreshed {} cached DID documents", refreshed);
+        refreshed
+    }
+
+    /// Refresh the cache every `interval` in a background task
+    ///
+    /// Abort the returned handle to stop.
+    pub fn spawn_refresh(&self, interval: Duration) -> JoinHandle<()> {
+        let resolver = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                resolver.refresh().await;
+            }
+        })
+    }
+
+    /// Get cache metrics
+    pub fn metrics(&self) -> ResolverMetrics {
+        ResolverMetrics {
+            hits: self.stats.hits.load(Ordering::Relaxed),
+            misses: self.stats.misses.load(Ordering::Relaxed),
+            negative_hits: self.stats.negative_hits.load(Ordering::Relaxed),
+            evictions: self.stats.evictions.load(Ordering::Relaxed),
+            refreshes: self.stats.refreshes.load(Ordering::Relaxed),
+            cached: self.cache.len(),
+            failures: self.failures.len(),
+        }
+    }
+
+    /// Next value of the LRU clock
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
     /// Get current Unix timestamp
     fn current_timestamp() -> u64 {
         SystemTime::now()
@@ -136,17 +373,150 @@ impl DidResolver {
     }
 }
 
+#[cfg(feature = "storage")]
+impl DidResolver {
+    /// Save the cached documents to storage
+    ///
+    /// Replaces documents saved earlier. Returns the number saved.
+    pub async fn persist(&self, storage: &dyn vudo_storage::StorageAdapter) -> Result<usize> {
+        let mut docs = Vec::new();
+        for entry in self.cache.iter() {
+            if !entry.is_expired(self.cache_ttl) {
+                let data = serde_json::to_vec(&PersistedDocument {
+                    did: entry.key().clone(),
+                    document: entry.document.clone(),
+                    cached_at: entry.cached_at,
+                })?;
+                docs.push((Self::storage_id(entry.key()), bytes::Bytes::from(data)));
+            }
+        }
+
+        let existing = storage
+            .list(DID_CACHE_NAMESPACE)
+            .await
+            .map_err(|e| Error::Resolution(format!("Storage: {}", e)))?;
+        for id in existing {
+            if !docs.iter().any(|(saved, _)| *saved == id) {
+                storage
+                    .delete(DID_CACHE_NAMESPACE, &id)
+                    .await
+                    .map_err(|e| Error::Resolution(format!("Storage: {}", e)))?;
+            }
+        }
+
+        let batch: Vec<(&str, &str, bytes::Bytes)> = docs
+            .iter()
+            .map(|(id, data)| (DID_CACHE_NAMESPACE, id.as_str(), data.clone()))
+            .collect();
+        storage
+            .save_batch(&batch)
+            .await
+            .map_err(|e| Error::Resolution(format!("Storage: {}", e)))?;
+        debug!("Persisted {} DID documents", docs.len());
+        Ok(docs.len())
+    }
+
+    /// Load documents saved by [`persist`](Self::persist)
+    ///
+    /// Expired and unreadable documents are skipped. Returns the number
+    /// loaded.
+    pub async fn restore(&self, storage: &dyn vudo_storage::StorageAdapter) -> Result<usize> {
+        let ids = storage
+            .list(DID_CACHE_NAMESPACE)
+            .await
+            .map_err(|e| Error::Resolution(format!("Storage: {}", e)))?;
+
+        let mut loaded = 0;
+        for id in ids {
+            let Some(data) = storage
+                .load(DID_CACHE_NAMESPACE, &id)
+                .await
+                .map_err(|e| Error::Resolution(format!("Storage: {}", e)))?
+            else {
+                continue;
+            };
+            let Ok(persisted) = serde_json::from_slice::<PersistedDocument>(&data) else {
+                debug!("Skipping unreadable persisted DID document {}", id);
+                continue;
+            };
+            let cached = CachedDocument {
+                document: persisted.document,
+                cached_at: persisted.cached_at,
+                last_used: 0,
+            };
+            if !cached.is_expired(self.cache_ttl) {
+                self.insert(&persisted.did, cached);
+                loaded += 1;
+            }
+        }
+        debug!("Restored {} DID documents", loaded);
+        Ok(loaded)
+    }
+
+    /// Storage ID of a DID (DIDs contain characters not all adapters allow)
+    fn storage_id(did: &str) -> String {
+        blake3::hash(did.as_bytes()).to_hex()[..32].to_string()
+    }
+}
+
 impl Default for DidResolver {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Snapshot of resolver cache metrics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolverMetrics {
+    /// Resolutions answered from the cache
+    pub hits: u64,
+
+    /// Resolutions that missed the cache
+    pub misses: u64,
+
+    /// Resolutions answered with a cached failure
+    pub negative_hits: u64,
+
+    /// Documents evicted to stay within capacity
+    pub evictions: u64,
+
+    /// Documents refreshed in the background
+    pub refreshes: u64,
+
+    /// Documents currently cached
+    pub cached: usize,
+
+    /// Failures currently cached
+    pub failures: usize,
+}
+
+impl ResolverMetrics {
+    /// Fraction of resolutions answered from the cache
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.negative_hits + self.misses;
+        if total == 0 {
+            return 0.0;
+        }
+        (self.hits + self.negative_hits) as f64 / total as f64
+    }
+}
+
+/// Cache metric counters
+#[derive(Debug, Default)]
+struct ResolverStats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    negative_hits: AtomicU64,
+    evictions: AtomicU64,
+    refreshes: AtomicU64,
+}
+
 /// Cached DID document
 #[derive(Debug, Clone)]
 struct CachedDocument {
     document: DidDocument,
     cached_at: u64,
+    last_used: u64,
 }
 
 impl CachedDocument {
@@ -161,6 +531,33 @@ impl CachedDocument {
     }
 }
 
+/// Cached resolution failure
+#[derive(Debug, Clone)]
+struct CachedFailure {
+    error: String,
+    failed_at: u64,
+}
+
+impl CachedFailure {
+    /// Check if cache entry is expired
+    fn is_expired(&self, ttl: u64) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        now.saturating_sub(self.failed_at) >= ttl
+    }
+}
+
+/// DID document as persisted to storage
+#[cfg(feature = "storage")]
+#[derive(Debug, Serialize, Deserialize)]
+struct PersistedDocument {
+    did: String,
+    document: DidDocument,
+    cached_at: u64,
+}
+
 /// Batch DID resolver for efficient resolution of multiple DIDs
 #[derive(Debug, Clone)]
 pub struct BatchDidResolver {
@@ -288,6 +685,69 @@ mod tests {
         assert_eq!(doc.id, did_str);
     }
 
+    #[tokio::test]
+    async fn test_lru_eviction() {
+        let resolver = DidResolver::new().with_capacity(2);
+        let (did1, did2, did3) = (create_test_did(), create_test_did(), create_test_did());
+
+        resolver.resolve(&did1).await.unwrap();
+        resolver.resolve(&did2).await.unwrap();
+        resolver.resolve(&did1).await.unwrap();
+        resolver.resolve(&did3).await.unwrap();
+
+        // did2 was the least recently used
+        assert_eq!(resolver.cache_size(), 2);
+        let metrics = resolver.metrics();
+        assert_eq!((metrics.hits, metrics.misses, metrics.evictions), (1, 3, 1));
+        resolver.resolve(&did1).await.unwrap();
+        resolver.resolve(&did2).await.unwrap();
+        assert_eq!(resolver.metrics().misses, 4);
+    }
+
+    #[tokio::test]
+    async fn test_negative_cache() {
+        let resolver = DidResolver::new();
+        let did = "did:web:example.com";
+
+        assert!(resolver.resolve_str(did).await.is_err());
+        assert!(resolver.resolve_str(did).await.is_err());
+        let metrics = resolver.metrics();
+        assert_eq!((metrics.misses, metrics.negative_hits), (1, 1));
+        assert_eq!(metrics.failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_refresh() {
+        let resolver = DidResolver::with_ttl(2);
+        let did = create_test_did();
+        resolver.resolve(&did).await.unwrap();
+
+        assert_eq!(resolver.refresh().await, 0);
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        assert_eq!(resolver.refresh().await, 1);
+        assert_eq!(resolver.metrics().refreshes, 1);
+        assert_eq!(resolver.cache_size(), 1);
+    }
+
+    #[cfg(feature = "storage")]
+    #[tokio::test]
+    async fn test_persist_restore() {
+        let storage = vudo_storage_browser::MemoryAdapter::new();
+        let resolver = DidResolver::new();
+        let (did1, did2) = (create_test_did(), create_test_did());
+        resolver.resolve(&did1).await.unwrap();
+        resolver.resolve(&did2).await.unwrap();
+        assert_eq!(resolver.persist(&storage).await.unwrap(), 2);
+
+        resolver.invalidate(&did2);
+        assert_eq!(resolver.persist(&storage).await.unwrap(), 1);
+
+        let restarted = DidResolver::new();
+        assert_eq!(restarted.restore(&storage).await.unwrap(), 1);
+        restarted.resolve(&did1).await.unwrap();
+        assert_eq!(restarted.metrics().hits, 1);
+    }
+
     #[tokio::test]
     async fn test_cache_clear() {
         let resolver = DidResolver::new();
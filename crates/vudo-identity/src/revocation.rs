@@ -0,0 +1,485 @@
+//! Revocation of UCANs
+//!
+//! A UCAN is revoked by its content identifier ([`Ucan::cid`]) with a
+//! [`UcanRevocation`] signed by the issuer of that UCAN or of any UCAN in its
+//! proof chain: whoever delegated a capability may take it back, including
+//! from everyone it was re-delegated to. A [`RevocationStore`] collects
+//! revocations, and [`RevocationStore::verify`] rejects UCANs whose chain
+//! contains a revoked link.
+//!
+//! The store's state is an OR-Set ([`RevocationSet`]), so replicas can merge
+//! in any order and converge. vudo-p2p keeps it in a document synced with
+//! every peer, so a revocation reaches the mesh within one sync cycle.
+//! Each replica removes revocations once the UCAN they revoke has expired
+//! anyway. Removals are unsigned, so [`RevocationStore::merge`] never takes
+//! them from other replicas: no one can withdraw a revocation.
+//!
+//! # Examples
+//!
+//! ```
+//! use vudo_identity::{Capability, DeviceIdentity, RevocationStore, Ucan, UcanRevocation};
+//! use chrono::Utc;
+//!
+//! # async fn example() -> vudo_identity::error::Result<()> {
+//! let device = DeviceIdentity::generate("Alice's Phone").await?;
+//! let app = DeviceIdentity::generate("Notes").await?;
+//!
+//! let ucan = Ucan::new(
+//!     device.did().clone(),
+//!     app.did().clone(),
+//!     vec![Capability::wildcard("vudo://notes/")],
+//!     Utc::now().timestamp() as u64 + 3600,
+//!     None,
+//!     None,
+//!     vec![],
+//! )
+//! .sign(&device.signing_key())?;
+//!
+//! let store = RevocationStore::new();
+//! store.verify(&ucan)?;
+//!
+//! let revocation = UcanRevocation::new(
+//!     &ucan,
+//!     device.did().clone(),
+//!     Some("Uninstalled".to_string()),
+//!     &device.signing_key(),
+//! )?;
+//! store.publish(revocation)?;
+//! assert!(store.verify(&ucan).is_err());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::did::Did;
+use crate::error::{Error, Result};
+use crate::ucan::Ucan;
+use chrono::Utc;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Domain separator for revocation signatures
+const DOMAIN: &[u8] = b"vudo-ucan-revocation/1";
+
+/// Signed revocation of a UCAN
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UcanRevocation {
+    /// CID of the revoked UCAN
+    pub cid: String,
+
+    /// Issuer of the revoked UCAN or of one of its proofs
+    pub issuer: Did,
+
+    /// Expiration of the revoked UCAN (Unix seconds)
+    pub expires_at: u64,
+
+    /// When the UCAN was revoked (Unix seconds)
+    pub revoked_at: u64,
+
+    /// Reason (optional)
+    pub reason: Option<String>,
+
+    /// Signature of the issuer
+    pub signature: Vec<u8>,
+}
+
+impl UcanRevocation {
+    /// Revoke a UCAN
+    ///
+    /// `issuer` must have issued the UCAN or one of the UCANs in its proof
+    /// chain, and `key` must be its signing key.
+    pub fn new(ucan: &Ucan, issuer: Did, reason: Option<String>, key: &SigningKey) -> Result<Self> {
+        if key.verifying_key() != issuer.verification_key {
+            return Err(Error::Key(
+                "Signing key does not match revocation issuer".to_string(),
+            ));
+        }
+        if !chain_issuers(ucan)?.contains(&issuer) {
+            return Err(Error::Revocation(format!(
+                "{} did not issue the UCAN or any of its proofs",
+                issuer
+            )));
+        }
+
+        let mut revocation = Self {
+            cid: ucan.cid()?,
+            issuer,
+            expires_at: ucan.exp,
+            revoked_at: Utc::now().timestamp() as u64,
+            reason,
+            signature: Vec::new(),
+        };
+        revocation.signature = key.sign(&revocation.signing_bytes()).to_bytes().to_vec();
+        Ok(revocation)
+    }
+
+    /// Verify the issuer's signature
+    ///
+    /// Whether the issuer may revoke a given UCAN depends on that UCAN's
+    /// chain, so it is checked by [`RevocationStore::check`].
+    pub fn verify(&self) -> Result<()> {
+        let signature = Signature::from_slice(&self.signature)?;
+        self.issuer
+            .verification_key
+            .verify(&self.signing_bytes(), &signature)?;
+        Ok(())
+    }
+
+    /// Tag identifying this revocation in a [`RevocationSet`]
+    pub fn tag(&self) -> String {
+        format!(
+            "{}:{}",
+            self.cid,
+            &blake3::hash(&self.signature).to_hex()[..16]
+        )
+    }
+
+    /// Check if the revoked UCAN has expired
+    pub fn is_expired(&self) -> bool {
+        Utc::now().timestamp() as u64 > self.expires_at
+    }
+
+    fn signing_bytes(&self) -> Vec<u8> {
+        let mut data = DOMAIN.to_vec();
+        data.extend_from_slice(self.cid.as_bytes());
+        data.extend_from_slice(self.issuer.as_str().as_bytes());
+        data.extend_from_slice(&self.expires_at.to_le_bytes());
+        data.extend_from_slice(&self.revoked_at.to_le_bytes());
+        if let Some(reason) = &self.reason {
+            data.extend_from_slice(reason.as_bytes());
+        }
+        data
+    }
+}
+
+/// Observed-remove set of revocations
+///
+/// Entries are keyed by [`UcanRevocation::tag`]. Removing a revocation keeps
+/// its tag, so merging with a replica that still holds it doesn't bring it
+/// back.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevocationSet {
+    /// Live revocations by tag
+    entries: BTreeMap<String, UcanRevocation>,
+
+    /// Tags of removed revocations
+    removed: BTreeSet<String>,
+}
+
+impl RevocationSet {
+    /// Create an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a revocation, returning false if it was already added or removed
+    pub fn insert(&mut self, revocation: UcanRevocation) -> bool {
+        let tag = revocation.tag();
+        if self.removed.contains(&tag) || self.entries.contains_key(&tag) {
+            return false;
+        }
+        self.entries.insert(tag, revocation);
+        true
+    }
+
+    /// Remove a revocation by tag
+    pub fn remove(&mut self, tag: &str) -> bool {
+        self.removed.insert(tag.to_string());
+        self.entries.remove(tag).is_some()
+    }
+
+    /// Merge another replica, returning the number of revocations added
+    pub fn merge(&mut self, other: &RevocationSet) -> usize {
+        for tag in &other.removed {
+            self.remove(tag);
+        }
+        other
+            .entries
+            .values()
+            .filter(|revocation| self.insert((*revocation).clone()))
+            .count()
+    }
+
+    /// Live revocations of a UCAN
+    pub fn revocations_of<'a>(
+        &'a self,
+        cid: &str,
+    ) -> impl Iterator<Item = &'a UcanRevocation> + 'a {
+        let prefix = format!("{}:", cid);
+        self.entries
+            .range(prefix.clone()..)
+            .take_while(move |(tag, _)| tag.starts_with(&prefix))
+            .map(|(_, revocation)| revocation)
+    }
+
+    /// Live revocations
+    pub fn iter(&self) -> impl Iterator<Item = &UcanRevocation> {
+        self.entries.values()
+    }
+
+    /// Tags of removed revocations
+    pub fn removed(&self) -> impl Iterator<Item = &str> {
+        self.removed.iter().map(String::as_str)
+    }
+
+    /// Number of live revocations
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if there are no live revocations
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Revocations consulted when verifying UCANs
+#[derive(Debug, Default)]
+pub struct RevocationStore {
+    set: RwLock<RevocationSet>,
+}
+
+impl RevocationStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a revocation, returning false if it was already known
+    pub fn publish(&self, revocation: UcanRevocation) -> Result<bool> {
+        revocation.verify()?;
+        Ok(self.set.write().insert(revocation))
+    }
+
+    /// Merge a replica's set, returning the number of revocations added
+    ///
+    /// Revocations with a bad signature or a mismatched tag are dropped, as
+    /// are expired ones, which this store would prune anyway. The replica's
+    /// removals are ignored: only expiry removes a revocation.
+    pub fn merge(&self, other: &RevocationSet) -> usize {
+        let mut valid = other.clone();
+        valid.removed.clear();
+        valid.entries.retain(|tag, revocation| {
+            let ok = *tag == revocation.tag() && revocation.verify().is_ok();
+            if !ok {
+                tracing::warn!("Dropping invalid revocation of {}", revocation.cid);
+            }
+            ok && !revocation.is_expired()
+        });
+        self.set.write().merge(&valid)
+    }
+
+    /// Check if a UCAN has any live revocation, whoever issued it
+    pub fn is_revoked(&self, cid: &str) -> bool {
+        self.set.read().revocations_of(cid).next().is_some()
+    }
+
+    /// Check that no UCAN in the chain has been revoked by its issuer or an
+    /// issuer above it
+    pub fn check(&self, ucan: &Ucan) -> Result<()> {
+        for proof in &ucan.prf {
+            self.check(&Ucan::decode(proof)?)?;
+        }
+
+        let cid = ucan.cid()?;
+        let revokers: Vec<Did> = self
+            .set
+            .read()
+            .revocations_of(&cid)
+            .map(|revocation| revocation.issuer.clone())
+            .collect();
+        if revokers.is_empty() {
+            return Ok(());
+        }
+
+        let issuers = chain_issuers(ucan)?;
+        match revokers.iter().find(|revoker| issuers.contains(revoker)) {
+            Some(revoker) => Err(Error::UcanRevoked(format!("{} by {}", cid, revoker))),
+            None => Ok(()),
+        }
+    }
+
+    /// Verify a UCAN and check it against the store
+    pub fn verify(&self, ucan: &Ucan) -> Result<()> {
+        ucan.verify()?;
+        self.check(ucan)
+    }
+
+    /// Remove revocations of UCANs that have expired, returning how many
+    pub fn prune_expired(&self) -> usize {
+        let mut set = self.set.write();
+        let expired: Vec<String> = set
+            .entries
+            .iter()
+            .filter(|(_, revocation)| revocation.is_expired())
+            .map(|(tag, _)| tag.clone())
+            .collect();
+        for tag in &expired {
+            set.remove(tag);
+        }
+        expired.len()
+    }
+
+    /// Copy of the current set, for syncing
+    pub fn snapshot(&self) -> RevocationSet {
+        self.set.read().clone()
+    }
+
+    /// Number of live revocations
+    pub fn len(&self) -> usize {
+        self.set.read().len()
+    }
+
+    /// Check if the store holds no live revocations
+    pub fn is_empty(&self) -> bool {
+        self.set.read().is_empty()
+    }
+}
+
+/// Issuers of a UCAN and of every UCAN in its proof chain
+fn chain_issuers(ucan: &Ucan) -> Result<Vec<Did>> {
+    let mut issuers = vec![ucan.iss.clone()];
+    for proof in &ucan.prf {
+        issuers.extend(chain_issuers(&Ucan::decode(proof)?)?);
+    }
+    Ok(issuers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ucan::Capability;
+    use rand::rngs::OsRng;
+    use x25519_dalek::{PublicKey, StaticSecret};
+
+    fn create_test_did() -> (Did, SigningKey) {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let encryption_secret = StaticSecret::random_from_rng(OsRng);
+        let encryption_public = PublicKey::from(&encryption_secret);
+        let did = Did::from_keys(signing_key.verifying_key(), &encryption_public).unwrap();
+        (did, signing_key)
+    }
+
+    fn issue(issuer: &Did, audience: &Did, key: &SigningKey) -> Ucan {
+        Ucan::new(
+            issuer.clone(),
+            audience.clone(),
+            vec![Capability::wildcard("vudo://notes/")],
+            Utc::now().timestamp() as u64 + 3600,
+            None,
+            None,
+            vec![],
+        )
+        .sign(key)
+        .unwrap()
+    }
+
+    #[test]
+    fn test_revocation_by_chain_issuers() {
+        let (device, device_key) = create_test_did();
+        let (app, app_key) = create_test_did();
+        let (service, _) = create_test_did();
+
+        let root = issue(&device, &app, &device_key);
+        let leaf = root
+            .delegate(
+                service.clone(),
+                vec![Capability::new("vudo://notes/todo", "read")],
+                Utc::now().timestamp() as u64 + 600,
+                &app_key,
+            )
+            .unwrap();
+
+        let store = RevocationStore::new();
+        store.verify(&leaf).unwrap();
+
+        // Only issuers in the chain may revoke
+        let (stranger, stranger_key) = create_test_did();
+        assert!(UcanRevocation::new(&leaf, stranger, None, &stranger_key).is_err());
+        assert!(UcanRevocation::new(&leaf, device.clone(), None, &app_key).is_err());
+
+        // Revoking the root also invalidates what was delegated from it
+        let revocation = UcanRevocation::new(&root, device.clone(), None, &device_key).unwrap();
+        assert!(store.publish(revocation.clone()).unwrap());
+        assert!(!store.publish(revocation).unwrap());
+        assert!(store.is_revoked(&root.cid().unwrap()));
+        assert!(matches!(store.verify(&root), Err(Error::UcanRevoked(_))));
+        assert!(matches!(store.verify(&leaf), Err(Error::UcanRevoked(_))));
+
+        // A revocation of the root by its audience carries no weight
+        let other = issue(&device, &service, &device_key);
+        let forged = UcanRevocation {
+            cid: other.cid().unwrap(),
+            issuer: app.clone(),
+            expires_at: other.exp,
+            revoked_at: Utc::now().timestamp() as u64,
+            reason: None,
+            signature: Vec::new(),
+        };
+        let forged = UcanRevocation {
+            signature: app_key.sign(&forged.signing_bytes()).to_bytes().to_vec(),
+            ..forged
+        };
+        store.publish(forged).unwrap();
+        store.verify(&other).unwrap();
+    }
+
+    #[test]
+    fn test_revocation_set_merge() {
+        let (device, device_key) = create_test_did();
+        let (app, _) = create_test_did();
+        let (service, _) = create_test_did();
+        let first = issue(&device, &app, &device_key);
+        let second = issue(&device, &service, &device_key);
+
+        let alice = RevocationStore::new();
+        let bob = RevocationStore::new();
+        alice
+            .publish(UcanRevocation::new(&first, device.clone(), None, &device_key).unwrap())
+            .unwrap();
+        bob.publish(UcanRevocation::new(&second, device.clone(), None, &device_key).unwrap())
+            .unwrap();
+
+        assert_eq!(alice.merge(&bob.snapshot()), 1);
+        assert_eq!(bob.merge(&alice.snapshot()), 1);
+        assert_eq!(alice.snapshot(), bob.snapshot());
+        assert_eq!(alice.len(), 2);
+
+        // A replica can't withdraw a revocation by removing it
+        let tag = alice.snapshot().iter().next().unwrap().tag();
+        let mut withdrawn = alice.snapshot();
+        assert!(withdrawn.remove(&tag));
+        bob.merge(&withdrawn);
+        assert_eq!(bob.len(), 2);
+        assert!(bob.verify(&first).is_err());
+        assert!(bob.verify(&second).is_err());
+
+        // Revocations of expired UCANs are pruned locally and not merged back
+        let expired = Ucan::new(
+            device.clone(),
+            app,
+            vec![Capability::wildcard("vudo://notes/")],
+            Utc::now().timestamp() as u64 - 1,
+            None,
+            None,
+            vec![],
+        )
+        .sign(&device_key)
+        .unwrap();
+        let revocation = UcanRevocation::new(&expired, device.clone(), None, &device_key).unwrap();
+        assert!(alice.publish(revocation).unwrap());
+        let stale = alice.snapshot();
+        assert_eq!(alice.prune_expired(), 1);
+        assert_eq!(alice.merge(&stale), 0);
+        assert_eq!(bob.merge(&stale), 0);
+        assert_eq!(alice.len(), 2);
+
+        // Tampered revocations are dropped
+        let mut tampered = RevocationSet::new();
+        let mut revocation = UcanRevocation::new(&first, device, None, &device_key).unwrap();
+        revocation.reason = Some("Forged".to_string());
+        tampered.entries.insert(revocation.tag(), revocation);
+        assert_eq!(RevocationStore::new().merge(&tampered), 0);
+    }
+}
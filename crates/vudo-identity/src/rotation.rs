@@ -0,0 +1,249 @@
+//! Enforcement of key rotation grace periods
+//!
+//! After a [`KeyRotation`], signatures made with the old key stay valid for
+//! the rotation's grace period, so devices and peers that haven't seen the
+//! rotation yet aren't cut off. [`RotationAwareVerifier`] enforces it: for an
+//! identity known by any of its DIDs, it accepts signatures of the latest key
+//! and of older keys still in their grace period, and rejects older keys once
+//! the period has ended.
+//!
+//! Grace periods are measured from `rotated_at`, which the holder of the old
+//! key chose. With a [`TimestampCommittee`] they are measured from the
+//! committee-agreed time instead, and rotations without a valid timestamp
+//! get no grace period.
+//!
+//! # Examples
+//!
+//! ```
+//! use vudo_identity::{DidResolver, MasterIdentity, RotationAwareVerifier};
+//! use ed25519_dalek::{Signer, SigningKey};
+//! use x25519_dalek::StaticSecret;
+//! use rand::rngs::OsRng;
+//!
+//! # async fn example() -> vudo_identity::error::Result<()> {
+//! let mut master = MasterIdentity::generate("Alice").await?;
+//! let old_did = master.did.clone();
+//! let old_key = master.signing_key();
+//!
+//! let rotation = master
+//!     .rotate_key(SigningKey::generate(&mut OsRng), StaticSecret::random_from_rng(OsRng))
+//!     .await?;
+//!
+//! let resolver = DidResolver::new();
+//! let verifier = RotationAwareVerifier::new().with_resolver(resolver.clone());
+//! verifier.record_rotation(rotation)?;
+//!
+//! // Both keys are accepted during the grace period
+//! let message = b"hello";
+//! verifier.verify(&old_did, message, &old_key.sign(message).to_bytes())?;
+//! let signer = verifier.verify(&old_did, message, &master.signing_key().sign(message).to_bytes())?;
+//! assert_eq!(signer, master.did);
+//!
+//! // Peers resolving the old DID get the new document
+//! assert_eq!(resolver.resolve_current(&old_did).await?.id, master.did.as_str());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::did::Did;
+use crate::error::{Error, Result};
+use crate::identity::KeyRotation;
+use crate::resolver::DidResolver;
+use crate::timestamp::TimestampCommittee;
+use dashmap::DashMap;
+use ed25519_dalek::{Signature, Verifier};
+use std::sync::Arc;
+
+/// Signature verifier that follows key rotations and enforces their grace
+/// periods
+#[derive(Debug, Clone, Default)]
+pub struct RotationAwareVerifier {
+    /// Verified rotations, by old DID
+    rotations: Arc<DashMap<String, KeyRotation>>,
+
+    /// Committee whose timestamps grace periods are measured from (optional)
+    committee: Option<TimestampCommittee>,
+
+    /// Resolver to notify of rotations (optional)
+    resolver: Option<DidResolver>,
+}
+
+impl RotationAwareVerifier {
+    /// Create a verifier with no known rotations
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Measure grace periods from committee-agreed rotation times
+    pub fn with_committee(mut self, committee: TimestampCommittee) -> Self {
+        self.committee = Some(committee);
+        self
+    }
+
+    /// Invalidate the cached documents of rotated DIDs in `resolver`
+    pub fn with_resolver(mut self, resolver: DidResolver) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
+
+    /// Record a key rotation after verifying its certificate
+    pub fn record_rotation(&self, rotation: KeyRotation) -> Result<()> {
+        rotation.verify()?;
+        if let Some(resolver) = &self.resolver {
+            resolver.apply_rotation(&rotation)?;
+        }
+        self.rotations
+            .insert(rotation.old_did.to_string(), rotation);
+        Ok(())
+    }
+
+    /// Latest DID of an identity known by `did`
+    pub fn current_did(&self, did: &Did) -> Did {
+        self.chain(did).pop().unwrap_or_else(|| did.clone())
+    }
+
+    /// Check if the key of `did` may still sign
+    ///
+    /// True for the latest key of an identity, and for older keys whose
+    /// rotation is in its grace period.
+    pub fn is_key_valid(&self, did: &Did) -> bool {
+        match self.rotations.get(did.as_str()) {
+            Some(rotation) => self.in_grace_period(&rotation),
+            None => true,
+        }
+    }
+
+    /// Verify a signature by the identity known by `did`
+    ///
+    /// Accepts the key of `did` or of any later DID of the identity, as long
+    /// as that key is still valid. Returns the DID whose key signed.
+    pub fn verify(&self, did: &Did, message: &[u8], signature: &[u8]) -> Result<Did> {
+        let signature = Signature::from_slice(signature)?;
+
+        let mut expired = None;
+        for candidate in self.chain(did) {
+            if candidate
+                .verification_key
+                .verify(message, &signature)
+                .is_err()
+            {
+                continue;
+            }
+            if self.is_key_valid(&candidate) {
+                return Ok(candidate);
+            }
+            expired = Some(candidate);
+        }
+
+        match expired {
+            Some(old) => Err(Error::KeyRotation(format!(
+                "Key of {} was rotated out and its grace period has ended",
+                old
+            ))),
+            None => Err(Error::SignatureVerification(format!(
+                "Signature is not from any key of {}",
+                did
+            ))),
+        }
+    }
+
+    /// `did` followed by the DIDs it was rotated into
+    fn chain(&self, did: &Did) -> Vec<Did> {
+        let mut chain = vec![did.clone()];
+        while let Some(rotation) = self.rotations.get(chain[chain.len() - 1].as_str()) {
+            if chain.contains(&rotation.new_did) {
+                break;
+            }
+            chain.push(rotation.new_did.clone());
+        }
+        chain
+    }
+
+    fn in_grace_period(&self, rotation: &KeyRotation) -> bool {
+        match &self.committee {
+            Some(committee) => rotation
+                .in_grace_period_attested(committee)
+                .unwrap_or(false),
+            None => rotation.in_grace_period(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeviceIdentity, MasterIdentity};
+    use ed25519_dalek::{Signer, SigningKey};
+    use rand::rngs::OsRng;
+    use x25519_dalek::StaticSecret;
+
+    #[tokio::test]
+    async fn test_grace_period_enforced() {
+        let mut master = MasterIdentity::generate("Alice").await.unwrap();
+        let first = (master.did.clone(), master.signing_key());
+        let mut rotation = master
+            .rotate_key(
+                SigningKey::generate(&mut OsRng),
+                StaticSecret::random_from_rng(OsRng),
+            )
+            .await
+            .unwrap();
+        let second = (master.did.clone(), master.signing_key());
+
+        let resolver = DidResolver::new();
+        resolver.resolve(&first.0).await.unwrap();
+        let verifier = RotationAwareVerifier::new().with_resolver(resolver.clone());
+        verifier.record_rotation(rotation.clone()).unwrap();
+
+        let message = b"hello";
+        let old_sig = first.1.sign(message).to_bytes();
+        let new_sig = second.1.sign(message).to_bytes();
+        assert_eq!(
+            verifier.verify(&first.0, message, &old_sig).unwrap(),
+            first.0
+        );
+        assert_eq!(
+            verifier.verify(&first.0, message, &new_sig).unwrap(),
+            second.0
+        );
+
+        // Rotation doesn't go backwards
+        assert!(verifier.verify(&second.0, message, &old_sig).is_err());
+        assert_eq!(resolver.current_did(&first.0), second.0);
+        assert_eq!(
+            resolver.resolve_current(&first.0).await.unwrap().id,
+            second.0.as_str()
+        );
+
+        // After the grace period only the new key is accepted
+        rotation.grace_period = 0;
+        verifier.rotations.insert(first.0.to_string(), rotation);
+        assert!(matches!(
+            verifier.verify(&first.0, message, &old_sig),
+            Err(Error::KeyRotation(_))
+        ));
+        assert!(verifier.verify(&first.0, message, &new_sig).is_ok());
+
+        // Without a committee timestamp there is no grace period
+        let mut members = Vec::new();
+        for i in 0..4 {
+            let notary = DeviceIdentity::generate(format!("notary-{}", i))
+                .await
+                .unwrap();
+            members.push(notary.did().clone());
+        }
+        let strict =
+            RotationAwareVerifier::new().with_committee(TimestampCommittee::new(members).unwrap());
+        let mut master = MasterIdentity::generate("Bob").await.unwrap();
+        let old_did = master.did.clone();
+        let rotation = master
+            .rotate_key(
+                SigningKey::generate(&mut OsRng),
+                StaticSecret::random_from_rng(OsRng),
+            )
+            .await
+            .unwrap();
+        strict.record_rotation(rotation).unwrap();
+        assert!(!strict.is_key_valid(&old_did));
+    }
+}
@@ -0,0 +1,572 @@
+//! Master identities controlled by k-of-n guardians
+//!
+//! A [`MasterIdentity`](crate::MasterIdentity) hangs on a single cold key:
+//! whoever holds it controls every device, and losing it loses the identity.
+//! A [`ThresholdMasterIdentity`] has no key of its own. Each master
+//! operation (linking or revoking a device, rotating the guardians) is a
+//! [`ThresholdProposal`] that takes effect once `k` of its `n` guardians
+//! have signed it.
+//!
+//! Signatures are plain Ed25519 signatures of each guardian, aggregated by
+//! listing them, so guardians can be ordinary device or master DIDs and sign
+//! offline. Proposals carry the identity's sequence number: an approval
+//! applies once, in order, and the history of approvals replays from the
+//! genesis guardians to the current state ([`ThresholdMasterIdentity::verify`]).
+//!
+//! Since there is no master key, a threshold identity issues no UCANs. A
+//! device's authorization is the approval that linked it, checked with
+//! [`ThresholdMasterIdentity::verify_device`].
+//!
+//! # Examples
+//!
+//! ```
+//! use vudo_identity::{DeviceIdentity, GuardianSet, ThresholdMasterIdentity, ThresholdOperation};
+//!
+//! # async fn example() -> vudo_identity::error::Result<()> {
+//! let mut guardians = Vec::new();
+//! for name in ["Alice", "Bob", "Carol"] {
+//!     guardians.push(DeviceIdentity::generate(name).await?);
+//! }
+//! let set = GuardianSet::new(guardians.iter().map(|g| g.did().clone()).collect(), 2)?;
+//! let mut org = ThresholdMasterIdentity::new("Acme", set);
+//!
+//! // Any guardian proposes, 2 of 3 sign
+//! let phone = DeviceIdentity::generate("Shared Phone").await?;
+//! let proposal = org.propose(ThresholdOperation::LinkDevice {
+//!     device: phone.did().clone(),
+//!     name: "Shared Phone".to_string(),
+//! });
+//! let signatures = guardians[..2]
+//!     .iter()
+//!     .map(|g| proposal.approve(g.did(), &g.signing_key()))
+//!     .collect::<vudo_identity::Result<Vec<_>>>()?;
+//! let approval = org.guardians.assemble(&proposal, signatures)?;
+//!
+//! org.apply(approval)?;
+//! org.verify_device(phone.did())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::did::Did;
+use crate::error::{Error, Result};
+use crate::identity::{DeviceAction, DeviceAuditEntry};
+use chrono::Utc;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
+use serde::{Deserialize, Serialize};
+
+/// Domain separator for proposal signatures
+const DOMAIN: &[u8] = b"vudo-threshold-proposal/1";
+
+/// Domain separator for threshold identity IDs
+const ID_DOMAIN: &[u8] = b"vudo-threshold-id/1";
+
+/// Guardians of a threshold identity and how many must sign
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuardianSet {
+    /// Guardian DIDs
+    pub guardians: Vec<Did>,
+
+    /// Signatures needed to approve an operation
+    pub threshold: usize,
+}
+
+impl GuardianSet {
+    /// Create a guardian set
+    ///
+    /// Needs distinct guardians and a threshold between 1 and their number.
+    pub fn new(guardians: Vec<Did>, threshold: usize) -> Result<Self> {
+        if threshold == 0 || threshold > guardians.len() {
+            return Err(Error::Threshold(format!(
+                "Threshold {} is not between 1 and {} guardians",
+                threshold,
+                guardians.len()
+            )));
+        }
+        for (i, guardian) in guardians.iter().enumerate() {
+            if guardians[..i].contains(guardian) {
+                return Err(Error::Threshold(format!("Duplicate guardian {}", guardian)));
+            }
+        }
+
+        Ok(Self {
+            guardians,
+            threshold,
+        })
+    }
+
+    /// Check if `did` is a guardian
+    pub fn is_guardian(&self, did: &Did) -> bool {
+        self.guardians.contains(did)
+    }
+
+    /// Build an approval from collected signatures
+    ///
+    /// Invalid signatures, signatures of non-guardians and repeated
+    /// signatures of a guardian are dropped. Fails if fewer than the
+    /// threshold remain.
+    pub fn assemble(
+        &self,
+        proposal: &ThresholdProposal,
+        signatures: Vec<GuardianSignature>,
+    ) -> Result<ThresholdApproval> {
+        let mut valid: Vec<GuardianSignature> = Vec::new();
+        for signature in signatures {
+            if self.is_guardian(&signature.guardian)
+                && !valid.iter().any(|s| s.guardian == signature.guardian)
+                && signature.verify(proposal).is_ok()
+            {
+                valid.push(signature);
+            }
+        }
+
+        let approval = ThresholdApproval {
+            proposal: proposal.clone(),
+            signatures: valid,
+        };
+        approval.verify(self)?;
+        Ok(approval)
+    }
+}
+
+/// Operation of a threshold identity
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ThresholdOperation {
+    /// Link a device
+    LinkDevice {
+        /// Device DID
+        device: Did,
+
+        /// Device name
+        name: String,
+    },
+
+    /// Revoke a linked device
+    RevokeDevice {
+        /// Device DID
+        device: Did,
+
+        /// Reason (optional)
+        reason: Option<String>,
+    },
+
+    /// Replace the guardians (the threshold analogue of key rotation)
+    RotateGuardians {
+        /// New guardian set
+        guardians: GuardianSet,
+    },
+}
+
+/// Operation awaiting guardian signatures
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThresholdProposal {
+    /// ID of the threshold identity
+    pub identity: String,
+
+    /// Position of the operation in the identity's history
+    pub sequence: u64,
+
+    /// Proposed operation
+    pub operation: ThresholdOperation,
+
+    /// When the operation was proposed (Unix seconds)
+    pub proposed_at: u64,
+}
+
+impl ThresholdProposal {
+    /// Sign the proposal as the guardian `guardian`
+    pub fn approve(&self, guardian: &Did, key: &SigningKey) -> Result<GuardianSignature> {
+        if key.verifying_key() != guardian.verification_key {
+            return Err(Error::Key(format!(
+                "Signing key does not match guardian {}",
+                guardian
+            )));
+        }
+
+        Ok(GuardianSignature {
+            guardian: guardian.clone(),
+            signature: key.sign(&self.signing_bytes()?).to_bytes().to_vec(),
+        })
+    }
+
+    /// Bytes covered by guardian signatures
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        let mut data = DOMAIN.to_vec();
+        data.push(0);
+        data.extend_from_slice(&serde_json::to_vec(self)?);
+        Ok(data)
+    }
+}
+
+/// A guardian's signature over a proposal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuardianSignature {
+    /// Signing guardian
+    pub guardian: Did,
+
+    /// Signature (Ed25519)
+    pub signature: Vec<u8>,
+}
+
+impl GuardianSignature {
+    /// Verify the signature against a proposal
+    pub fn verify(&self, proposal: &ThresholdProposal) -> Result<()> {
+        let signature = Signature::from_slice(&self.signature)?;
+        self.guardian
+            .verification_key
+            .verify(&proposal.signing_bytes()?, &signature)?;
+        Ok(())
+    }
+}
+
+/// Proposal signed by enough guardians
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThresholdApproval {
+    /// Approved proposal
+    pub proposal: ThresholdProposal,
+
+    /// Guardian signatures
+    pub signatures: Vec<GuardianSignature>,
+}
+
+impl ThresholdApproval {
+    /// Verify the approval against the guardians at the time of the proposal
+    pub fn verify(&self, guardians: &GuardianSet) -> Result<()> {
+        for (i, signature) in self.signatures.iter().enumerate() {
+            if !guardians.is_guardian(&signature.guardian) {
+                return Err(Error::Threshold(format!(
+                    "Signer {} is not a guardian",
+                    signature.guardian
+                )));
+            }
+            if self.signatures[..i]
+                .iter()
+                .any(|s| s.guardian == signature.guardian)
+            {
+                return Err(Error::Threshold(format!(
+                    "Duplicate signature from {}",
+                    signature.guardian
+                )));
+            }
+            signature.verify(&self.proposal)?;
+        }
+
+        if self.signatures.len() < guardians.threshold {
+            return Err(Error::Threshold(format!(
+                "Approval has {} signatures, threshold is {}",
+                self.signatures.len(),
+                guardians.threshold
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Device linked by a threshold identity
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThresholdDeviceLink {
+    /// Device DID
+    pub device_did: Did,
+
+    /// Device name ("Shared Phone")
+    pub device_name: String,
+
+    /// Sequence of the approval that linked the device
+    pub sequence: u64,
+
+    /// Revocation status
+    pub revoked: bool,
+}
+
+/// Master identity whose operations need k-of-n guardian signatures
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThresholdMasterIdentity {
+    /// Identity ID, derived from the genesis guardians
+    pub id: String,
+
+    /// Display name
+    pub name: String,
+
+    /// When the identity was created (Unix seconds)
+    pub created_at: u64,
+
+    /// Guardians the identity was created with
+    pub genesis: GuardianSet,
+
+    /// Current guardians
+    pub guardians: GuardianSet,
+
+    /// Linked devices
+    pub devices: Vec<ThresholdDeviceLink>,
+
+    /// Applied approvals, in sequence
+    pub history: Vec<ThresholdApproval>,
+
+    /// Audit log of device links and revocations
+    #[serde(default)]
+    pub audit_log: Vec<DeviceAuditEntry>,
+}
+
+impl ThresholdMasterIdentity {
+    /// Create an identity controlled by `guardians`
+    pub fn new(name: impl Into<String>, guardians: GuardianSet) -> Self {
+        let created_at = Utc::now().timestamp() as u64;
+        Self {
+            id: Self::derive_id(&guardians, created_at),
+            name: name.into(),
+            created_at,
+            genesis: guardians.clone(),
+            guardians,
+            devices: Vec::new(),
+            history: Vec::new(),
+            audit_log: Vec::new(),
+        }
+    }
+
+    /// Propose the next operation, for the guardians to sign
+    pub fn propose(&self, operation: ThresholdOperation) -> ThresholdProposal {
+        ThresholdProposal {
+            identity: self.id.clone(),
+            sequence: self.history.len() as u64,
+            operation,
+            proposed_at: Utc::now().timestamp() as u64,
+        }
+    }
+
+    /// Apply an approved operation
+    ///
+    /// The approval must be for the next sequence number and signed by the
+    /// threshold of current guardians.
+    pub fn apply(&mut self, approval: ThresholdApproval) -> Result<()> {
+        let proposal = &approval.proposal;
+        if proposal.identity != self.id {
+            return Err(Error::Threshold(format!(
+                "Proposal is for identity {}",
+                proposal.identity
+            )));
+        }
+        if proposal.sequence != self.history.len() as u64 {
+            return Err(Error::Threshold(format!(
+                "Proposal has sequence {}, expected {}",
+                proposal.sequence,
+                self.history.len()
+            )));
+        }
+        approval.verify(&self.guardians)?;
+
+        match &proposal.operation {
+            ThresholdOperation::LinkDevice { device, name } => {
+                if self
+                    .devices
+                    .iter()
+                    .any(|d| &d.device_did == device && !d.revoked)
+                {
+                    return Err(Error::DeviceAlreadyLinked(name.clone()));
+                }
+                self.devices.push(ThresholdDeviceLink {
+                    device_did: device.clone(),
+                    device_name: name.clone(),
+                    sequence: proposal.sequence,
+                    revoked: false,
+                });
+                self.audit(device, DeviceAction::Linked, None);
+            }
+            ThresholdOperation::RevokeDevice { device, reason } => {
+                let link = self
+                    .devices
+                    .iter_mut()
+                    .find(|d| &d.device_did == device && !d.revoked)
+                    .ok_or_else(|| Error::DeviceNotFound(device.to_string()))?;
+                link.revoked = true;
+                self.audit(device, DeviceAction::Revoked, reason.clone());
+            }
+            ThresholdOperation::RotateGuardians { guardians } => {
+                // Re-check invariants, a proposal may be deserialized
+                self.guardians =
+                    GuardianSet::new(guardians.guardians.clone(), guardians.threshold)?;
+            }
+        }
+
+        self.history.push(approval);
+        Ok(())
+    }
+
+    /// Check if a device is linked and not revoked
+    pub fn is_device_linked(&self, device_did: &Did) -> bool {
+        self.devices
+            .iter()
+            .any(|d| &d.device_did == device_did && !d.revoked)
+    }
+
+    /// Check if a device is revoked
+    pub fn is_device_revoked(&self, device_did: &Did) -> bool {
+        self.devices
+            .iter()
+            .any(|d| &d.device_did == device_did && d.revoked)
+            && !self.is_device_linked(device_did)
+    }
+
+    /// Verify the identity by replaying its history from the genesis
+    /// guardians
+    ///
+    /// Use on identities received from peers: the ID must match the genesis
+    /// guardians and every approval must have been signed by the guardians
+    /// of its time.
+    pub fn verify(&self) -> Result<()> {
+        if self.id != Self::derive_id(&self.genesis, self.created_at) {
+            return Err(Error::Threshold(
+                "Identity ID does not match its genesis guardians".to_string(),
+            ));
+        }
+
+        let mut replay = Self {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            created_at: self.created_at,
+            genesis: self.genesis.clone(),
+            guardians: self.genesis.clone(),
+            devices: Vec::new(),
+            history: Vec::new(),
+            audit_log: Vec::new(),
+        };
+        for approval in &self.history {
+            replay.apply(approval.clone())?;
+        }
+
+        if replay.guardians != self.guardians || replay.devices != self.devices {
+            return Err(Error::Threshold(
+                "Identity state does not match its history".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Verify that `device_did` is a linked, unrevoked device
+    pub fn verify_device(&self, device_did: &Did) -> Result<()> {
+        self.verify()?;
+        if self.is_device_revoked(device_did) {
+            return Err(Error::DeviceRevoked(device_did.to_string()));
+        }
+        if !self.is_device_linked(device_did) {
+            return Err(Error::DeviceNotFound(device_did.to_string()));
+        }
+        Ok(())
+    }
+
+    fn audit(&mut self, device_did: &Did, action: DeviceAction, reason: Option<String>) {
+        self.audit_log.push(DeviceAuditEntry {
+            device_did: device_did.clone(),
+            action,
+            reason,
+            at: Utc::now().timestamp() as u64,
+        });
+    }
+
+    fn derive_id(genesis: &GuardianSet, created_at: u64) -> String {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(ID_DOMAIN);
+        hasher.update(&[0]);
+        for guardian in &genesis.guardians {
+            hasher.update(guardian.as_str().as_bytes());
+            hasher.update(&[0]);
+        }
+        hasher.update(&(genesis.threshold as u64).to_le_bytes());
+        hasher.update(&created_at.to_le_bytes());
+        format!(
+            "vudo-threshold:{}",
+            bs58::encode(hasher.finalize().as_bytes()).into_string()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DeviceIdentity;
+
+    async fn guardians(n: usize) -> Vec<DeviceIdentity> {
+        let mut guardians = Vec::new();
+        for i in 0..n {
+            guardians.push(
+                DeviceIdentity::generate(format!("guardian-{}", i))
+                    .await
+                    .unwrap(),
+            );
+        }
+        guardians
+    }
+
+    fn sign(proposal: &ThresholdProposal, signers: &[DeviceIdentity]) -> Vec<GuardianSignature> {
+        signers
+            .iter()
+            .map(|g| proposal.approve(g.did(), &g.signing_key()).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_threshold_operations() {
+        let old = guardians(3).await;
+        let set = GuardianSet::new(old.iter().map(|g| g.did().clone()).collect(), 2).unwrap();
+        let mut org = ThresholdMasterIdentity::new("Acme", set);
+        let phone = DeviceIdentity::generate("Shared Phone").await.unwrap();
+
+        let link = org.propose(ThresholdOperation::LinkDevice {
+            device: phone.did().clone(),
+            name: "Shared Phone".to_string(),
+        });
+
+        // One guardian, even signing twice, is not enough
+        let mut single = sign(&link, &old[..1]);
+        single.extend(sign(&link, &old[..1]));
+        assert!(org.guardians.assemble(&link, single).is_err());
+
+        let approval = org
+            .guardians
+            .assemble(&link, sign(&link, &old[..2]))
+            .unwrap();
+        org.apply(approval.clone()).unwrap();
+        assert!(org.verify_device(phone.did()).is_ok());
+
+        // Approvals apply once
+        assert!(org.apply(approval).is_err());
+
+        // Rotate to new guardians; the old ones lose control
+        let new = guardians(2).await;
+        let rotate = org.propose(ThresholdOperation::RotateGuardians {
+            guardians: GuardianSet::new(new.iter().map(|g| g.did().clone()).collect(), 2).unwrap(),
+        });
+        org.apply(
+            org.guardians
+                .assemble(&rotate, sign(&rotate, &old[1..]))
+                .unwrap(),
+        )
+        .unwrap();
+
+        let revoke = org.propose(ThresholdOperation::RevokeDevice {
+            device: phone.did().clone(),
+            reason: Some("Lost".to_string()),
+        });
+        assert!(org
+            .guardians
+            .assemble(&revoke, sign(&revoke, &old[..2]))
+            .is_err());
+        org.apply(
+            org.guardians
+                .assemble(&revoke, sign(&revoke, &new))
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(org.is_device_revoked(phone.did()));
+        assert!(org.verify_device(phone.did()).is_err());
+        assert!(org.verify().is_ok());
+
+        // Tampered state does not replay
+        let mut tampered = org.clone();
+        tampered.devices[0].revoked = false;
+        assert!(tampered.verify().is_err());
+        let mut tampered = org.clone();
+        tampered.history[0].signatures.pop();
+        assert!(tampered.verify().is_err());
+    }
+}
@@ -0,0 +1,484 @@
+//! Trusted timestamps co-signed by a BFT committee
+//!
+//! Key rotations, revocations and deletion certificates carry timestamps
+//! chosen by whoever created them. Grace periods are measured from those
+//! timestamps, so a holder of a compromised key could back-date a record to
+//! shorten or dodge a grace period. A [`TimestampToken`] fixes the time of an
+//! event independently of its author: every committee member co-signs the
+//! event digest with its own clock, and the agreed time is taken from a
+//! quorum of those signatures.
+//!
+//! ```text
+//! committee = 3f+1 members, quorum = 2f+1
+//! agreed    = (f+1)-th smallest co-signed time
+//! ```
+//!
+//! With at most `f` Byzantine signers, the agreed time lies between the
+//! clocks of two honest members, so neither the author nor up to `f` colluding
+//! members can move it outside honest clock skew. Members refuse to co-sign
+//! an event whose claimed time is further than the committee's `max_skew`
+//! from their own clock, and tokens whose agreed time is that far from the
+//! claimed time don't verify.
+//!
+//! # Examples
+//!
+//! ```
+//! use vudo_identity::{DeviceIdentity, MasterIdentity, TimestampCommittee, TimestampSigner};
+//! use ed25519_dalek::SigningKey;
+//! use x25519_dalek::StaticSecret;
+//! use rand::rngs::OsRng;
+//!
+//! # async fn example() -> vudo_identity::error::Result<()> {
+//! let mut signers = Vec::new();
+//! for i in 0..4 {
+//!     let device = DeviceIdentity::generate(format!("notary-{}", i)).await?;
+//!     signers.push(TimestampSigner::new(device.did().clone(), device.signing_key())?);
+//! }
+//! let committee = TimestampCommittee::new(signers.iter().map(|s| s.did().clone()).collect())?;
+//!
+//! let mut master = MasterIdentity::generate("Alice").await?;
+//! let new_key = SigningKey::generate(&mut OsRng);
+//! let mut rotation = master
+//!     .rotate_key(new_key, StaticSecret::random_from_rng(&mut OsRng))
+//!     .await?;
+//!
+//! // Each member co-signs over the network; the requester assembles the token
+//! let request = rotation.timestamp_request();
+//! let signatures = signers
+//!     .iter()
+//!     .map(|signer| signer.co_sign(&request))
+//!     .collect::<vudo_identity::Result<Vec<_>>>()?;
+//! let token = committee.assemble(&request, signatures)?;
+//!
+//! rotation.attach_timestamp(token, &committee)?;
+//! assert!(rotation.in_grace_period_attested(&committee)?);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::did::Did;
+use crate::error::{Error, Result};
+use chrono::Utc;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
+use serde::{Deserialize, Serialize};
+
+/// Domain separator for co-signed messages
+const SIGNING_CONTEXT: &str = "vudo-timestamp-v1";
+
+/// Default tolerated difference between claimed and co-signed times (seconds)
+pub const DEFAULT_MAX_SKEW: u64 = 5 * 60;
+
+/// An event to be timestamped
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimestampRequest {
+    /// Event kind ("key-rotation", "revocation", "deletion-certificate", ...)
+    pub kind: String,
+
+    /// BLAKE3 digest of the kind and event payload (hex)
+    pub digest: String,
+
+    /// Time the author claims for the event (Unix seconds)
+    pub claimed_at: u64,
+}
+
+impl TimestampRequest {
+    /// Create a request for an event payload
+    ///
+    /// The payload must cover every field of the event that the timestamp
+    /// vouches for, including the claimed time.
+    pub fn new(kind: impl Into<String>, payload: &[u8], claimed_at: u64) -> Self {
+        let kind = kind.into();
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(kind.as_bytes());
+        hasher.update(&[0]);
+        hasher.update(payload);
+
+        Self {
+            kind,
+            digest: hasher.finalize().to_hex().to_string(),
+            claimed_at,
+        }
+    }
+
+    /// Message a committee member signs for this request at `timestamp`
+    fn signing_message(&self, timestamp: u64) -> String {
+        format!(
+            "{}|{}|{}|{}|{}",
+            SIGNING_CONTEXT, self.kind, self.digest, self.claimed_at, timestamp
+        )
+    }
+}
+
+/// A committee member's signature over a request and its own clock reading
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoSignature {
+    /// Signing member
+    pub signer: Did,
+
+    /// Member's clock when signing (Unix seconds)
+    pub timestamp: u64,
+
+    /// Signature (Ed25519)
+    pub signature: Vec<u8>,
+}
+
+impl CoSignature {
+    /// Verify the signature against a request
+    pub fn verify(&self, request: &TimestampRequest) -> Result<()> {
+        let signature =
+            Signature::from_bytes(self.signature.as_slice().try_into().map_err(|_| {
+                Error::SignatureVerification("Invalid co-signature length".to_string())
+            })?);
+        self.signer.verification_key.verify(
+            request.signing_message(self.timestamp).as_bytes(),
+            &signature,
+        )?;
+        Ok(())
+    }
+}
+
+/// A committee member that co-signs timestamp requests
+pub struct TimestampSigner {
+    did: Did,
+    signing_key: SigningKey,
+    max_skew: u64,
+}
+
+impl std::fmt::Debug for TimestampSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TimestampSigner")
+            .field("did", &self.did)
+            .field("max_skew", &self.max_skew)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TimestampSigner {
+    /// Create a signer for the member `did`
+    ///
+    /// Fails if `signing_key` doesn't belong to `did`.
+    pub fn new(did: Did, signing_key: SigningKey) -> Result<Self> {
+        if signing_key.verifying_key() != did.verification_key {
+            return Err(Error::Key(format!(
+                "Signing key does not match timestamp signer {}",
+                did
+            )));
+        }
+
+        Ok(Self {
+            did,
+            signing_key,
+            max_skew: DEFAULT_MAX_SKEW,
+        })
+    }
+
+    /// Refuse requests claiming a time further than `max_skew` seconds from now
+    pub fn with_max_skew(mut self, max_skew: u64) -> Self {
+        self.max_skew = max_skew;
+        self
+    }
+
+    /// Member DID
+    pub fn did(&self) -> &Did {
+        &self.did
+    }
+
+    /// Co-sign a request with the current time
+    pub fn co_sign(&self, request: &TimestampRequest) -> Result<CoSignature> {
+        self.co_sign_at(request, Utc::now().timestamp() as u64)
+    }
+
+    fn co_sign_at(&self, request: &TimestampRequest, now: u64) -> Result<CoSignature> {
+        if request.claimed_at.abs_diff(now) > self.max_skew {
+            return Err(Error::Timestamp(format!(
+                "Claimed time {} of {} is more than {}s from now ({})",
+                request.claimed_at, request.kind, self.max_skew, now
+            )));
+        }
+
+        let signature = self
+            .signing_key
+            .sign(request.signing_message(now).as_bytes());
+        Ok(CoSignature {
+            signer: self.did.clone(),
+            timestamp: now,
+            signature: signature.to_bytes().to_vec(),
+        })
+    }
+}
+
+/// BFT committee whose co-signatures make up timestamp tokens
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimestampCommittee {
+    /// Committee members
+    pub members: Vec<Did>,
+
+    /// Tolerated difference between claimed and agreed times (seconds)
+    pub max_skew: u64,
+}
+
+impl TimestampCommittee {
+    /// Create a committee
+    ///
+    /// Needs at least 4 distinct members (3f+1 with f >= 1).
+    pub fn new(members: Vec<Did>) -> Result<Self> {
+        if members.len() < 4 {
+            return Err(Error::Timestamp(
+                "Timestamp committee requires at least 4 members (3f+1 with f=1)".to_string(),
+            ));
+        }
+        for (i, member) in members.iter().enumerate() {
+            if members[..i].contains(member) {
+                return Err(Error::Timestamp(format!(
+                    "Duplicate timestamp committee member {}",
+                    member
+                )));
+            }
+        }
+
+        Ok(Self {
+            members,
+            max_skew: DEFAULT_MAX_SKEW,
+        })
+    }
+
+    /// Set the tolerated difference between claimed and agreed times
+    pub fn with_max_skew(mut self, max_skew: u64) -> Self {
+        self.max_skew = max_skew;
+        self
+    }
+
+    /// Maximum tolerated Byzantine members
+    pub fn max_byzantine_faults(&self) -> usize {
+        (self.members.len() - 1) / 3
+    }
+
+    /// Number of co-signatures a token needs (2f+1)
+    pub fn quorum(&self) -> usize {
+        2 * self.max_byzantine_faults() + 1
+    }
+
+    /// Check if `did` is a committee member
+    pub fn is_member(&self, did: &Did) -> bool {
+        self.members.contains(did)
+    }
+
+    /// Build a token from collected co-signatures
+    ///
+    /// Invalid signatures, signatures of non-members and repeated signatures
+    /// of a member are dropped, since Byzantine members may send them. Fails
+    /// if fewer than a quorum remain.
+    pub fn assemble(
+        &self,
+        request: &TimestampRequest,
+        signatures: Vec<CoSignature>,
+    ) -> Result<TimestampToken> {
+        let mut valid: Vec<CoSignature> = Vec::new();
+        for signature in signatures {
+            if self.is_member(&signature.signer)
+                && !valid.iter().any(|s| s.signer == signature.signer)
+                && signature.verify(request).is_ok()
+            {
+                valid.push(signature);
+            }
+        }
+
+        let timestamp = self.agreed_time(request, &valid)?;
+        Ok(TimestampToken {
+            request: request.clone(),
+            timestamp,
+            signatures: valid,
+        })
+    }
+
+    /// Agreed time of a set of verified co-signatures
+    fn agreed_time(&self, request: &TimestampRequest, signatures: &[CoSignature]) -> Result<u64> {
+        if signatures.len() < self.quorum() {
+            return Err(Error::Timestamp(format!(
+                "Timestamp has {} co-signatures, quorum is {}",
+                signatures.len(),
+                self.quorum()
+            )));
+        }
+
+        let mut times: Vec<u64> = signatures.iter().map(|s| s.timestamp).collect();
+        times.sort_unstable();
+        let agreed = times[self.max_byzantine_faults()];
+
+        if agreed.abs_diff(request.claimed_at) > self.max_skew {
+            return Err(Error::Timestamp(format!(
+                "Claimed time {} of {} is more than {}s from agreed time {}",
+                request.claimed_at, request.kind, self.max_skew, agreed
+            )));
+        }
+        Ok(agreed)
+    }
+}
+
+/// Proof that a committee quorum saw an event at an agreed time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimestampToken {
+    /// Timestamped event
+    pub request: TimestampRequest,
+
+    /// Agreed time (Unix seconds)
+    pub timestamp: u64,
+
+    /// Committee co-signatures
+    pub signatures: Vec<CoSignature>,
+}
+
+impl TimestampToken {
+    /// Verify the token for `request` against `committee`
+    pub fn verify(&self, committee: &TimestampCommittee, request: &TimestampRequest) -> Result<()> {
+        if self.request != *request {
+            return Err(Error::Timestamp(format!(
+                "Timestamp token is for a different {} event",
+                request.kind
+            )));
+        }
+
+        for (i, signature) in self.signatures.iter().enumerate() {
+            if !committee.is_member(&signature.signer) {
+                return Err(Error::Timestamp(format!(
+                    "Co-signer {} is not a committee member",
+                    signature.signer
+                )));
+            }
+            if self.signatures[..i]
+                .iter()
+                .any(|s| s.signer == signature.signer)
+            {
+                return Err(Error::Timestamp(format!(
+                    "Duplicate co-signature from {}",
+                    signature.signer
+                )));
+            }
+            signature.verify(request)?;
+        }
+
+        let agreed = committee.agreed_time(request, &self.signatures)?;
+        if agreed != self.timestamp {
+            return Err(Error::Timestamp(format!(
+                "Token time {} does not match agreed time {}",
+                self.timestamp, agreed
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+    use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
+
+    fn signer() -> TimestampSigner {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let encryption = X25519PublicKey::from(&StaticSecret::random_from_rng(&mut OsRng));
+        let did = Did::from_keys(signing_key.verifying_key(), &encryption).unwrap();
+        TimestampSigner::new(did, signing_key).unwrap()
+    }
+
+    fn committee(size: usize) -> (Vec<TimestampSigner>, TimestampCommittee) {
+        let signers: Vec<_> = (0..size).map(|_| signer()).collect();
+        let committee =
+            TimestampCommittee::new(signers.iter().map(|s| s.did().clone()).collect()).unwrap();
+        (signers, committee)
+    }
+
+    #[test]
+    fn test_committee_size() {
+        let (signers, committee) = committee(7);
+        assert_eq!(committee.max_byzantine_faults(), 2);
+        assert_eq!(committee.quorum(), 5);
+
+        let dids: Vec<_> = signers.iter().take(3).map(|s| s.did().clone()).collect();
+        assert!(TimestampCommittee::new(dids).is_err());
+
+        let mut dids: Vec<_> = signers.iter().take(4).map(|s| s.did().clone()).collect();
+        dids[3] = dids[0].clone();
+        assert!(TimestampCommittee::new(dids).is_err());
+    }
+
+    #[test]
+    fn test_signer_rejects_mismatched_key() {
+        let a = signer();
+        assert!(TimestampSigner::new(a.did().clone(), SigningKey::generate(&mut OsRng)).is_err());
+    }
+
+    #[test]
+    fn test_assemble_and_verify() {
+        let (signers, committee) = committee(4);
+        let request = TimestampRequest::new("test", b"event", 1_000);
+
+        let signatures: Vec<_> = signers
+            .iter()
+            .zip([1_010, 990, 1_000, 1_020])
+            .map(|(s, now)| s.co_sign_at(&request, now).unwrap())
+            .collect();
+        let token = committee.assemble(&request, signatures).unwrap();
+
+        // f = 1: the second smallest time
+        assert_eq!(token.timestamp, 1_000);
+        assert!(token.verify(&committee, &request).is_ok());
+
+        let other = TimestampRequest::new("test", b"other event", 1_000);
+        assert!(token.verify(&committee, &other).is_err());
+
+        let mut forged = token.clone();
+        forged.timestamp = 990;
+        assert!(forged.verify(&committee, &request).is_err());
+    }
+
+    #[test]
+    fn test_signer_refuses_backdated_request() {
+        let a = signer();
+        let request = TimestampRequest::new("test", b"event", 1_000);
+        assert!(a.co_sign_at(&request, 1_000 + DEFAULT_MAX_SKEW).is_ok());
+        assert!(a.co_sign_at(&request, 1_001 + DEFAULT_MAX_SKEW).is_err());
+    }
+
+    #[test]
+    fn test_byzantine_member_cannot_move_time() {
+        let (mut signers, committee) = committee(4);
+        let request = TimestampRequest::new("test", b"event", 1_000);
+
+        // A colluding member signs a time far in the past
+        let byzantine = signers.remove(0).with_max_skew(u64::MAX);
+        let mut signatures = vec![byzantine.co_sign_at(&request, 0).unwrap()];
+        signatures.extend(
+            signers
+                .iter()
+                .map(|s| s.co_sign_at(&request, 1_000).unwrap()),
+        );
+        let token = committee.assemble(&request, signatures).unwrap();
+        assert_eq!(token.timestamp, 1_000);
+    }
+
+    #[test]
+    fn test_assemble_drops_invalid_signatures() {
+        let (signers, committee) = committee(4);
+        let request = TimestampRequest::new("test", b"event", 1_000);
+
+        let first = signers[0].co_sign_at(&request, 1_000).unwrap();
+        let outsider = signer().co_sign_at(&request, 1_000).unwrap();
+        let mut tampered = signers[1].co_sign_at(&request, 1_000).unwrap();
+        tampered.timestamp = 900;
+
+        let signatures = vec![
+            first.clone(),
+            first,
+            outsider,
+            tampered,
+            signers[2].co_sign_at(&request, 1_000).unwrap(),
+        ];
+        assert!(committee.assemble(&request, signatures.clone()).is_err());
+
+        let mut signatures = signatures;
+        signatures.push(signers[3].co_sign_at(&request, 1_000).unwrap());
+        let token = committee.assemble(&request, signatures).unwrap();
+        assert_eq!(token.signatures.len(), 3);
+    }
+}
@@ -48,6 +48,7 @@ use crate::error::{Error, Result};
 use chrono::Utc;
 use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// UCAN (User Controlled Authorization Network)
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -76,9 +77,20 @@ pub struct Ucan {
     #[serde(default)]
     pub prf: Vec<String>,
 
+    /// Facts: signed, application-specific assertions (optional)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fct: Option<serde_json::Value>,
+
     /// Signature (added when signed)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sig: Option<String>,
+
+    /// Encoded header and payload the signature covers, as decoded
+    ///
+    /// Kept so that tokens encoded by other implementations verify and
+    /// re-encode byte for byte.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signed: Option<String>,
 }
 
 impl Ucan {
@@ -100,14 +112,23 @@ impl Ucan {
             nbf,
             nnc,
             prf,
+            fct: None,
             sig: None,
+            signed: None,
         }
     }
 
+    /// Attach facts (before signing)
+    pub fn with_facts(mut self, facts: serde_json::Value) -> Self {
+        self.fct = Some(facts);
+        self
+    }
+
     /// Sign the UCAN with a signing key
     pub fn sign(mut self, key: &SigningKey) -> Result<Self> {
-        let payload = self.to_payload()?;
-        let signature = key.sign(payload.as_bytes());
+        self.signed = None;
+        let signing_input = self.signing_input()?;
+        let signature = key.sign(signing_input.as_bytes());
         self.sig = Some(base64::encode_config(
             &signature.to_bytes(),
             base64::URL_SAFE_NO_PAD,
@@ -117,6 +138,27 @@ impl Ucan {
 
     /// Verify UCAN is valid (signature, expiry, delegation chain)
     pub fn verify(&self) -> Result<()> {
+        self.verify_token()?;
+
+        // Verify delegation chain
+        for proof_jwt in &self.prf {
+            let parent = Self::decode(proof_jwt)?;
+            parent.verify()?;
+
+            // Check parent grants at least the same capabilities to issuer
+            if !parent.grants_to(&self.iss, &self.att)? {
+                return Err(Error::InsufficientDelegation(format!(
+                    "Parent UCAN does not grant sufficient capabilities to {}",
+                    self.iss
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Verify the signature and validity period, ignoring proofs
+    pub(crate) fn verify_token(&self) -> Result<()> {
         // Check signature present
         let sig_str = self
             .sig
@@ -137,7 +179,7 @@ impl Ucan {
         }
 
         // Verify signature
-        let payload = self.to_payload()?;
+        let signing_input = self.signing_input()?;
         let sig_bytes = base64::decode_config(sig_str, base64::URL_SAFE_NO_PAD)
             .map_err(|e| Error::Encoding(format!("Failed to decode signature: {}", e)))?;
 
@@ -150,21 +192,7 @@ impl Ucan {
 
         self.iss
             .verification_key
-            .verify(payload.as_bytes(), &signature)?;
-
-        // Verify delegation chain
-        for proof_jwt in &self.prf {
-            let parent = Self::decode(proof_jwt)?;
-            parent.verify()?;
-
-            // Check parent grants at least the same capabilities to issuer
-            if !parent.grants_to(&self.iss, &self.att)? {
-                return Err(Error::InsufficientDelegation(format!(
-                    "Parent UCAN does not grant sufficient capabilities to {}",
-                    self.iss
-                )));
-            }
-        }
+            .verify(signing_input.as_bytes(), &signature)?;
 
         Ok(())
     }
@@ -194,53 +222,139 @@ impl Ucan {
     }
 
     /// Encode UCAN as JWT
+    ///
+    /// The token follows the UCAN 0.10 spec: an EdDSA JWT whose payload
+    /// carries `ucv`, DID strings for `iss` and `aud`, and capabilities as
+    /// `cap` (resource to ability to caveats).
     pub fn encode(&self) -> Result<String> {
-        let header = UcanHeader {
-            alg: "EdDSA".to_string(),
-            typ: "JWT".to_string(),
-            ucan_version: "0.10.0".to_string(),
-        };
-
-        let header_json = serde_json::to_string(&header)?;
-        let header_b64 =
-            base64::encode_config(header_json.as_bytes(), base64::URL_SAFE_NO_PAD);
-
-        let payload = self.to_payload()?;
-        let payload_b64 = base64::encode_config(payload.as_bytes(), base64::URL_SAFE_NO_PAD);
-
         let sig = self
             .sig
             .as_ref()
             .ok_or_else(|| Error::Ucan("UCAN not signed".to_string()))?;
 
-        Ok(format!("{}.{}.{}", header_b64, payload_b64, sig))
+        Ok(format!("{}.{}", self.signing_input()?, sig))
     }
 
     /// Decode UCAN from JWT
+    ///
+    /// Accepts UCAN 0.10 tokens, with `ucv` in the payload or (as older
+    /// encoders put it) in the header. Abilities with caveats are rejected,
+    /// as they can't be enforced here.
     pub fn decode(jwt: &str) -> Result<Self> {
         let parts: Vec<&str> = jwt.split('.').collect();
         if parts.len() != 3 {
             return Err(Error::Ucan(format!("Invalid JWT format, expected 3 parts, got {}", parts.len())));
         }
 
-        let payload_bytes = base64::decode_config(parts[1], base64::URL_SAFE_NO_PAD)
-            .map_err(|e| Error::Encoding(format!("Failed to decode payload: {}", e)))?;
-
-        let mut ucan: Ucan = serde_json::from_slice(&payload_bytes)?;
+        let mut ucan = Self::parse_signing_input(parts[0], parts[1])?;
         ucan.sig = Some(parts[2].to_string());
+        ucan.signed = Some(format!("{}.{}", parts[0], parts[1]));
 
         Ok(ucan)
     }
 
-    /// Create canonical payload for signing
-    fn to_payload(&self) -> Result<String> {
-        // Create payload without signature
-        let mut payload = serde_json::to_value(self)?;
-        if let Some(obj) = payload.as_object_mut() {
-            obj.remove("sig");
+    /// Content identifier of the encoded UCAN
+    ///
+    /// A CIDv1 (raw codec, BLAKE3 multihash) of the JWT, multibase encoded
+    /// as base58btc. Revocations refer to UCANs by CID.
+    pub fn cid(&self) -> Result<String> {
+        let jwt = self.encode()?;
+        let mut bytes = vec![0x01, 0x55, 0x1e, 0x20];
+        bytes.extend_from_slice(blake3::hash(jwt.as_bytes()).as_bytes());
+        Ok(format!("z{}", bs58::encode(&bytes).into_string()))
+    }
+
+    /// Encoded header and payload covered by the signature
+    ///
+    /// The decoded encoding if the claims haven't changed since, so foreign
+    /// tokens keep their exact bytes; otherwise this implementation's.
+    fn signing_input(&self) -> Result<String> {
+        if let Some(signed) = &self.signed {
+            if let Some((header, payload)) = signed.split_once('.') {
+                let decoded = Self::parse_signing_input(header, payload)?;
+                if UcanPayload::from_ucan(&decoded) == UcanPayload::from_ucan(self) {
+                    return Ok(signed.clone());
+                }
+            }
+        }
+
+        let header = UcanHeader {
+            alg: ALGORITHM.to_string(),
+            typ: Some("JWT".to_string()),
+            ucv: None,
+        };
+        Ok(format!(
+            "{}.{}",
+            base64::encode_config(
+                serde_json::to_string(&header)?.as_bytes(),
+                base64::URL_SAFE_NO_PAD
+            ),
+            base64::encode_config(
+                serde_json::to_string(&UcanPayload::from_ucan(self))?.as_bytes(),
+                base64::URL_SAFE_NO_PAD
+            )
+        ))
+    }
+
+    /// Decode the header and payload of a JWT
+    fn parse_signing_input(header: &str, payload: &str) -> Result<Self> {
+        let header_bytes = base64::decode_config(header, base64::URL_SAFE_NO_PAD)
+            .map_err(|e| Error::Encoding(format!("Failed to decode header: {}", e)))?;
+        let header: UcanHeader = serde_json::from_slice(&header_bytes)?;
+        if header.alg != ALGORITHM {
+            return Err(Error::Ucan(format!(
+                "Unsupported signature algorithm: {}",
+                header.alg
+            )));
+        }
+
+        let payload_bytes = base64::decode_config(payload, base64::URL_SAFE_NO_PAD)
+            .map_err(|e| Error::Encoding(format!("Failed to decode payload: {}", e)))?;
+        let payload: UcanPayload = serde_json::from_slice(&payload_bytes)?;
+
+        let version = payload
+            .ucv
+            .as_deref()
+            .or(header.ucv.as_deref())
+            .unwrap_or_default();
+        if !version.starts_with("0.10.") {
+            return Err(Error::Ucan(format!(
+                "Unsupported UCAN version: {:?}",
+                version
+            )));
+        }
+
+        let mut att = Vec::new();
+        for (resource, abilities) in payload.cap {
+            for (ability, caveats) in abilities {
+                // No caveats grants nothing; a single empty caveat, everything
+                if caveats.is_empty() {
+                    continue;
+                }
+                if caveats
+                    .iter()
+                    .any(|caveat| caveat != &serde_json::json!({}))
+                {
+                    return Err(Error::Ucan(format!(
+                        "Caveats on {} {} are not supported",
+                        ability, resource
+                    )));
+                }
+                att.push(Capability::new(resource.clone(), ability));
+            }
         }
 
-        Ok(serde_json::to_string(&payload)?)
+        let mut ucan = Ucan::new(
+            Did::parse(&payload.iss)?,
+            Did::parse(&payload.aud)?,
+            att,
+            payload.exp,
+            payload.nbf,
+            payload.nnc,
+            payload.prf,
+        );
+        ucan.fct = payload.fct;
+        Ok(ucan)
     }
 
     /// Create a delegation UCAN (child of this UCAN)
@@ -330,13 +444,74 @@ impl Capability {
     }
 }
 
+/// UCAN spec version of encoded tokens
+pub const UCAN_VERSION: &str = "0.10.0";
+
+/// JWT signature algorithm of UCANs (Ed25519)
+const ALGORITHM: &str = "EdDSA";
+
 /// UCAN JWT header
 #[derive(Debug, Serialize, Deserialize)]
 struct UcanHeader {
     alg: String,
-    typ: String,
-    #[serde(rename = "ucv")]
-    ucan_version: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    typ: Option<String>,
+
+    /// Spec version, where UCANs before 0.10 put it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ucv: Option<String>,
+}
+
+/// UCAN JWT payload, in the 0.10 layout
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct UcanPayload {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ucv: Option<String>,
+
+    iss: String,
+
+    aud: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nbf: Option<u64>,
+
+    exp: u64,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nnc: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fct: Option<serde_json::Value>,
+
+    /// Resource to ability to caveats
+    cap: BTreeMap<String, BTreeMap<String, Vec<serde_json::Value>>>,
+
+    #[serde(default)]
+    prf: Vec<String>,
+}
+
+impl UcanPayload {
+    fn from_ucan(ucan: &Ucan) -> Self {
+        let mut cap: BTreeMap<String, BTreeMap<String, Vec<serde_json::Value>>> = BTreeMap::new();
+        for capability in &ucan.att {
+            cap.entry(capability.resource.clone())
+                .or_default()
+                .insert(capability.action.clone(), vec![serde_json::json!({})]);
+        }
+
+        Self {
+            ucv: Some(UCAN_VERSION.to_string()),
+            iss: ucan.iss.to_string(),
+            aud: ucan.aud.to_string(),
+            nbf: ucan.nbf,
+            exp: ucan.exp,
+            nnc: ucan.nnc.clone(),
+            fct: ucan.fct.clone(),
+            cap,
+            prf: ucan.prf.clone(),
+        }
+    }
 }
 
 /// Generate a random nonce
@@ -516,4 +691,31 @@ mod tests {
         assert_eq!(ucan.att, decoded.att);
         assert_eq!(ucan.exp, decoded.exp);
     }
+
+    #[test]
+    fn test_ucan_facts_are_signed() {
+        let (issuer_did, issuer_key) = create_test_did();
+        let (audience_did, _) = create_test_did();
+
+        let ucan = Ucan::new(
+            issuer_did,
+            audience_did,
+            vec![Capability::new("vudo://myapp/data", "read")],
+            Utc::now().timestamp() as u64 + 3600,
+            None,
+            None,
+            vec![],
+        )
+        .with_facts(serde_json::json!({"tier": "free"}))
+        .sign(&issuer_key)
+        .unwrap();
+
+        let decoded = Ucan::decode(&ucan.encode().unwrap()).unwrap();
+        assert_eq!(decoded.fct, Some(serde_json::json!({"tier": "free"})));
+        assert!(decoded.verify().is_ok());
+
+        let mut tampered = decoded;
+        tampered.fct = Some(serde_json::json!({"tier": "pro"}));
+        assert!(tampered.verify().is_err());
+    }
 }
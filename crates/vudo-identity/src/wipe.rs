@@ -0,0 +1,232 @@
+//! Remote wipe of unlinked devices
+//!
+//! When a device is lost or stolen,
+//! [`MasterIdentity::unlink_device`](crate::MasterIdentity::unlink_device)
+//! revokes it and signs a [`WipeInstruction`] for it. vudo-p2p syncs the
+//! instruction along with UCAN revocations, and the device clears its
+//! keystore entry when it finds an instruction addressed to it
+//! ([`Keystore::apply_wipe`](crate::Keystore::apply_wipe)).
+//!
+//! Devices know the master DID they were linked to, which may have been
+//! rotated since. An instruction carries the master's rotation certificates,
+//! so a device can follow them from the DID it knows to the one that signed.
+//!
+//! # Examples
+//!
+//! ```
+//! use vudo_identity::{DeviceIdentity, MasterIdentity};
+//!
+//! # async fn example() -> vudo_identity::error::Result<()> {
+//! let mut master = MasterIdentity::generate("Alice").await?;
+//! let mut phone = DeviceIdentity::generate("Alice's Phone").await?;
+//!
+//! let master_key = master.signing_key();
+//! let link = master
+//!     .link_device("Alice's Phone".to_string(), phone.did().clone(), &master_key)
+//!     .await?;
+//! phone.link_to_master(master.did.clone(), link.authorization);
+//!
+//! // The phone was stolen
+//! let unlink = master.unlink_device(phone.did(), Some("Stolen".to_string()))?;
+//! assert!(master.is_device_revoked(phone.did()));
+//! unlink.wipe.verify_for(&phone)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::did::Did;
+use crate::error::{Error, Result};
+use crate::identity::{DeviceIdentity, KeyRotation};
+use crate::revocation::UcanRevocation;
+use chrono::Utc;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
+use serde::{Deserialize, Serialize};
+
+/// Domain separator for wipe instruction signatures
+const DOMAIN: &[u8] = b"vudo-device-wipe/1";
+
+/// Instruction for a device to clear its keys, signed by its master
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WipeInstruction {
+    /// Master DID that signed the instruction
+    pub master: Did,
+
+    /// Device to wipe
+    pub device: Did,
+
+    /// Reason (optional)
+    pub reason: Option<String>,
+
+    /// When the instruction was issued (Unix seconds)
+    pub issued_at: u64,
+
+    /// Rotation certificates of the master, oldest first
+    pub rotations: Vec<KeyRotation>,
+
+    /// Signature of the master
+    pub signature: Vec<u8>,
+}
+
+impl WipeInstruction {
+    /// Sign a wipe instruction for a device
+    ///
+    /// `key` must be the signing key of `master`, and `rotations` the
+    /// master's rotation certificates.
+    pub fn new(
+        master: Did,
+        device: Did,
+        reason: Option<String>,
+        rotations: Vec<KeyRotation>,
+        key: &SigningKey,
+    ) -> Result<Self> {
+        if key.verifying_key() != master.verification_key {
+            return Err(Error::Key(
+                "Signing key does not match wipe issuer".to_string(),
+            ));
+        }
+
+        let mut wipe = Self {
+            master,
+            device,
+            reason,
+            issued_at: Utc::now().timestamp() as u64,
+            rotations,
+            signature: Vec::new(),
+        };
+        wipe.signature = key.sign(&wipe.signing_bytes()).to_bytes().to_vec();
+        Ok(wipe)
+    }
+
+    /// Verify the master's signature and rotation certificates
+    pub fn verify(&self) -> Result<()> {
+        let signature = Signature::from_slice(&self.signature)?;
+        self.master
+            .verification_key
+            .verify(&self.signing_bytes(), &signature)?;
+
+        // Certificates must chain into the signing DID
+        let mut expected = &self.master;
+        for rotation in self.rotations.iter().rev() {
+            if &rotation.new_did != expected {
+                return Err(Error::KeyRotation(
+                    "Rotation certificates do not chain to the wipe issuer".to_string(),
+                ));
+            }
+            rotation.verify()?;
+            expected = &rotation.old_did;
+        }
+        Ok(())
+    }
+
+    /// Verify the instruction is addressed to `device` and signed by its
+    /// master
+    ///
+    /// The master DID the device was linked to must be the signer or have
+    /// been rotated into it.
+    pub fn verify_for(&self, device: &DeviceIdentity) -> Result<()> {
+        self.verify()?;
+        if self.device != device.did {
+            return Err(Error::Revocation(format!(
+                "Wipe instruction is addressed to {}",
+                self.device
+            )));
+        }
+
+        let master = device.master_did.as_ref().ok_or_else(|| {
+            Error::Revocation(format!("{} is not linked to a master", device.did))
+        })?;
+        let known = &self.master == master || self.rotations.iter().any(|r| &r.old_did == master);
+        if !known {
+            return Err(Error::Revocation(format!(
+                "Wipe instruction is not signed by the master of {}",
+                device.did
+            )));
+        }
+        Ok(())
+    }
+
+    /// Tag identifying this instruction among those synced between peers
+    pub fn tag(&self) -> String {
+        format!(
+            "{}:{}",
+            self.device,
+            &blake3::hash(&self.signature).to_hex()[..16]
+        )
+    }
+
+    /// Bytes covered by the signature
+    fn signing_bytes(&self) -> Vec<u8> {
+        let mut data = DOMAIN.to_vec();
+        data.push(0);
+        data.extend_from_slice(self.master.as_str().as_bytes());
+        data.push(0);
+        data.extend_from_slice(self.device.as_str().as_bytes());
+        data.push(0);
+        data.extend_from_slice(&self.issued_at.to_le_bytes());
+        if let Some(reason) = &self.reason {
+            data.extend_from_slice(reason.as_bytes());
+        }
+        data
+    }
+}
+
+/// Result of unlinking a device
+#[derive(Debug, Clone)]
+pub struct DeviceUnlink {
+    /// Revocation of the device's UCAN, to publish to a
+    /// [`RevocationStore`](crate::RevocationStore)
+    ///
+    /// `None` if the master key was rotated since the device was linked:
+    /// only the key that issued a UCAN can revoke it. The device is on the
+    /// master's revocation list either way.
+    pub revocation: Option<UcanRevocation>,
+
+    /// Wipe instruction for the device
+    pub wipe: WipeInstruction,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MasterIdentity;
+    use ed25519_dalek::SigningKey;
+    use rand::rngs::OsRng;
+    use x25519_dalek::StaticSecret;
+
+    #[tokio::test]
+    async fn test_wipe_after_rotation() {
+        let mut master = MasterIdentity::generate("Alice").await.unwrap();
+        let mut phone = DeviceIdentity::generate("Alice's Phone").await.unwrap();
+        let other = DeviceIdentity::generate("Bob's Phone").await.unwrap();
+
+        let master_key = master.signing_key();
+        let link = master
+            .link_device("Alice's Phone".to_string(), phone.did.clone(), &master_key)
+            .await
+            .unwrap();
+        phone.link_to_master(master.did.clone(), link.authorization);
+
+        // The phone still knows the DID from before the rotation
+        master
+            .rotate_key(
+                SigningKey::generate(&mut OsRng),
+                StaticSecret::random_from_rng(OsRng),
+            )
+            .await
+            .unwrap();
+        let unlink = master.unlink_device(&phone.did, None).unwrap();
+        assert!(unlink.revocation.is_none());
+        assert!(unlink.wipe.verify_for(&phone).is_ok());
+        assert!(unlink.wipe.verify_for(&other).is_err());
+
+        // Without the certificates the signer is unknown to the phone
+        let mut stripped = unlink.wipe.clone();
+        stripped.rotations.clear();
+        assert!(stripped.verify().is_ok());
+        assert!(stripped.verify_for(&phone).is_err());
+
+        let mut tampered = unlink.wipe;
+        tampered.device = other.did.clone();
+        assert!(tampered.verify().is_err());
+    }
+}
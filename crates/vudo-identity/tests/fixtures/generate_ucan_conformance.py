@@ -0,0 +1,224 @@
+#!/usr/bin/env python3
+"""Generate ucan_conformance.json, UCAN 0.10 tokens signed independently of
+vudo-identity.
+
+Requires the `cryptography` package. Ed25519 signatures are deterministic, so
+the output is stable for the fixed seeds below.
+"""
+
+import base64
+import json
+import os
+
+from cryptography.hazmat.primitives.asymmetric.ed25519 import Ed25519PrivateKey
+from cryptography.hazmat.primitives.serialization import Encoding, PublicFormat
+
+ALPHABET = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz"
+
+ISSUER_SEED = bytes(range(1, 33))
+AUDIENCE_SEED = bytes(range(33, 65))
+DELEGATE_SEED = bytes(range(65, 97))
+
+# 2100-01-01T00:00:00Z and 2000-01-01T00:00:00Z
+FAR_FUTURE = 4102444800
+PAST = 946684800
+
+
+def b58(data):
+    n = int.from_bytes(data, "big")
+    out = ""
+    while n:
+        n, r = divmod(n, 58)
+        out = ALPHABET[r] + out
+    return "1" * (len(data) - len(data.lstrip(b"\0"))) + out
+
+
+def b64(data):
+    return base64.urlsafe_b64encode(data).rstrip(b"=").decode()
+
+
+def key(seed):
+    return Ed25519PrivateKey.from_private_bytes(seed)
+
+
+def did_key(seed):
+    public = key(seed).public_key().public_bytes(Encoding.Raw, PublicFormat.Raw)
+    return "did:key:z" + b58(b"\xed\x01" + public)
+
+
+def jwt(seed, header, payload, **options):
+    signing_input = b64(json.dumps(header, separators=(",", ":")).encode())
+    signing_input += "." + b64(
+        json.dumps(payload, separators=(",", ":"), sort_keys=options.get("sort", False)).encode()
+    )
+    signed = options.get("signed", signing_input)
+    signature = key(seed).sign(signed.encode())
+    return signing_input + "." + b64(signature)
+
+
+HEADER = {"alg": "EdDSA", "typ": "JWT"}
+
+
+def payload(**fields):
+    claims = {
+        "ucv": "0.10.0",
+        "iss": did_key(ISSUER_SEED),
+        "aud": did_key(AUDIENCE_SEED),
+        "nbf": PAST,
+        "exp": FAR_FUTURE,
+        "nnc": "6c1a1c25",
+        "fct": {"tier": "free"},
+        "cap": {
+            "vudo-topic://chat": {"*": [{}]},
+            "vudo://photos/*": {"read": [{}], "write": [{}]},
+        },
+        "prf": [],
+    }
+    claims.update(fields)
+    return {k: v for k, v in claims.items() if v is not None}
+
+
+def main():
+    root = jwt(ISSUER_SEED, HEADER, payload())
+    without_optional = payload(nbf=None, nnc=None, fct=None)
+    without_optional["cap"] = {"vudo://photos/1": {"read": [{}]}}
+    legacy = payload()
+    del legacy["ucv"]
+
+    cases = [
+        {
+            "name": "root",
+            "description": "Canonical encoding, identical to vudo-identity's",
+            "token": root,
+            "canonical": True,
+            "valid": True,
+        },
+        {
+            "name": "minimal",
+            "description": "Only the required claims",
+            "token": jwt(ISSUER_SEED, HEADER, without_optional),
+            "canonical": True,
+            "valid": True,
+        },
+        {
+            "name": "sorted_keys",
+            "description": "Claims in another order than vudo-identity's",
+            "token": jwt(ISSUER_SEED, HEADER, payload(), sort=True),
+            "valid": True,
+        },
+        {
+            "name": "header_version",
+            "description": "Spec version in the header, as older encoders put it",
+            "token": jwt(ISSUER_SEED, dict(HEADER, ucv="0.10.0"), legacy),
+            "valid": True,
+        },
+        {
+            "name": "no_caveats",
+            "description": "An ability with no caveats grants nothing",
+            "token": jwt(
+                ISSUER_SEED,
+                HEADER,
+                payload(cap={"vudo://photos/*": {"read": [{}], "write": []}}),
+            ),
+            "valid": True,
+            "capabilities": [["vudo://photos/*", "read"]],
+        },
+        {
+            "name": "embedded_proof",
+            "description": "Delegation with the parent JWT as proof",
+            "token": jwt(
+                AUDIENCE_SEED,
+                HEADER,
+                payload(
+                    iss=did_key(AUDIENCE_SEED),
+                    aud=did_key(DELEGATE_SEED),
+                    nnc=None,
+                    fct=None,
+                    cap={"vudo://photos/*": {"read": [{}]}},
+                    prf=[root],
+                ),
+            ),
+            "valid": True,
+        },
+        {
+            "name": "escalated_proof",
+            "description": "Delegation of an ability the parent doesn't grant",
+            "token": jwt(
+                AUDIENCE_SEED,
+                HEADER,
+                payload(
+                    iss=did_key(AUDIENCE_SEED),
+                    aud=did_key(DELEGATE_SEED),
+                    cap={"vudo://photos/*": {"delete": [{}]}},
+                    prf=[root],
+                ),
+            ),
+            "valid": False,
+        },
+        {
+            "name": "expired",
+            "description": "Expired in 2000",
+            "token": jwt(ISSUER_SEED, HEADER, payload(nbf=None, exp=PAST)),
+            "valid": False,
+        },
+        {
+            "name": "not_yet_valid",
+            "description": "Not valid before 2100",
+            "token": jwt(ISSUER_SEED, HEADER, payload(nbf=FAR_FUTURE, exp=FAR_FUTURE + 1)),
+            "valid": False,
+        },
+        {
+            "name": "wrong_signer",
+            "description": "Signed by the audience instead of the issuer",
+            "token": jwt(AUDIENCE_SEED, HEADER, payload()),
+            "valid": False,
+        },
+        {
+            "name": "payload_only_signature",
+            "description": "Signature over the payload alone instead of the JWS signing input",
+            "token": jwt(
+                ISSUER_SEED,
+                HEADER,
+                payload(),
+                signed=json.dumps(payload(), separators=(",", ":")),
+            ),
+            "valid": False,
+        },
+        {
+            "name": "unsupported_algorithm",
+            "description": "alg none",
+            "token": jwt(ISSUER_SEED, {"alg": "none", "typ": "JWT"}, payload()),
+            "decodes": False,
+        },
+        {
+            "name": "unsupported_version",
+            "description": "UCAN 0.9 payload layout",
+            "token": jwt(ISSUER_SEED, HEADER, payload(ucv="0.9.1")),
+            "decodes": False,
+        },
+        {
+            "name": "caveats",
+            "description": "Caveats can't be enforced, so the token is rejected",
+            "token": jwt(
+                ISSUER_SEED,
+                HEADER,
+                payload(cap={"vudo://photos/*": {"write": [{"max_size": 1024}]}}),
+            ),
+            "decodes": False,
+        },
+    ]
+
+    corpus = {
+        "issuer_seed": ISSUER_SEED.hex(),
+        "issuer": did_key(ISSUER_SEED),
+        "audience": did_key(AUDIENCE_SEED),
+        "cases": cases,
+    }
+    path = os.path.join(os.path.dirname(os.path.abspath(__file__)), "ucan_conformance.json")
+    with open(path, "w") as f:
+        json.dump(corpus, f, indent=2)
+        f.write("\n")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,69 @@
+//! Error types for the import subsystem.
+
+use thiserror::Error;
+
+/// Result type for import operations.
+pub type Result<T> = std::result::Result<T, ImportError>;
+
+/// Import error types.
+#[derive(Debug, Error)]
+pub enum ImportError {
+    /// Invalid mapping file.
+    #[error("Invalid mapping: {0}")]
+    InvalidMapping(String),
+
+    /// Source driver failed to fetch records.
+    #[error("Source error: {0}")]
+    SourceError(String),
+
+    /// Source driver not compiled into this build.
+    #[error("Source driver '{0}' is not enabled in this build")]
+    DriverDisabled(String),
+
+    /// A record value could not be converted to the mapped type.
+    #[error("Cannot convert field '{field}': {message}")]
+    ConversionError {
+        /// Source field name.
+        field: String,
+        /// Reason the conversion failed.
+        message: String,
+    },
+
+    /// State engine error.
+    #[error("State engine error: {0}")]
+    StateError(#[from] vudo_state::StateError),
+
+    /// JSON error.
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// I/O error.
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+impl From<csv::Error> for ImportError {
+    fn from(err: csv::Error) -> Self {
+        ImportError::SourceError(err.to_string())
+    }
+}
+
+impl From<automerge::AutomergeError> for ImportError {
+    fn from(err: automerge::AutomergeError) -> Self {
+        ImportError::StateError(err.into())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for ImportError {
+    fn from(err: rusqlite::Error) -> Self {
+        ImportError::SourceError(err.to_string())
+    }
+}
+
+#[cfg(feature = "http")]
+impl From<reqwest::Error> for ImportError {
+    fn from(err: reqwest::Error) -> Self {
+        ImportError::SourceError(err.to_string())
+    }
+}
@@ -0,0 +1,516 @@
+//! Idempotent, incremental import of source records into documents.
+//!
+//! Each record becomes one document keyed by its source ID. The mapped values
+//! of the last import are stored on the document under [`IMPORT_METADATA_KEY`]
+//! and serve as the merge base for re-imports:
+//!
+//! - fields unchanged at the source are left alone, so re-running an import
+//!   is a no-op;
+//! - fields changed at the source and untouched locally are updated;
+//! - fields changed at the source *and* edited locally are conflicts, resolved
+//!   by the mapping's [`ConflictMode`] and listed in the [`ImportReport`].
+
+use crate::error::{ImportError, Result};
+use crate::mapping::{ConflictMode, ImportMapping};
+use crate::source::{SourceDriver, SourceRecord};
+use automerge::{transaction::Transactable, AutoCommit, ObjType, ReadDoc, ScalarValue, ROOT};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::info;
+use vudo_state::{DocumentHandle, DocumentId, StateEngine};
+
+/// Root key of the import metadata map on imported documents.
+pub const IMPORT_METADATA_KEY: &str = "_import";
+
+/// Field edited locally and changed at the source since the last import.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportConflict {
+    /// Document holding the field.
+    pub document_id: DocumentId,
+    /// Document field name.
+    pub field: String,
+    /// Local value.
+    pub local: Value,
+    /// Value from the source.
+    pub incoming: Value,
+    /// How the conflict was resolved.
+    pub resolution: ConflictMode,
+}
+
+/// Record that could not be imported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordError {
+    /// Position of the record in the source.
+    pub index: usize,
+    /// Source ID of the record, if it could be read.
+    pub source_id: Option<String>,
+    /// Reason the record was rejected.
+    pub message: String,
+}
+
+/// Summary of an import run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportReport {
+    /// Documents created.
+    pub created: usize,
+    /// Existing documents updated.
+    pub updated: usize,
+    /// Records with nothing to change.
+    pub unchanged: usize,
+    /// Conflicting fields.
+    pub conflicts: Vec<ImportConflict>,
+    /// Rejected records.
+    pub errors: Vec<RecordError>,
+}
+
+impl ImportReport {
+    /// Check whether the import finished without conflicts or rejected records.
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty() && self.errors.is_empty()
+    }
+}
+
+/// Outcome of importing one record.
+enum RecordOutcome {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+/// Import metadata stored on a document.
+struct ImportMetadata {
+    base: Option<Map<String, Value>>,
+}
+
+/// Imports source records into a state engine.
+pub struct Importer {
+    /// State engine documents are imported into.
+    engine: Arc<StateEngine>,
+}
+
+impl Importer {
+    /// Create a new importer.
+    pub fn new(engine: Arc<StateEngine>) -> Self {
+        Self { engine }
+    }
+
+    /// Run the import described by a mapping file.
+    ///
+    /// Relative source paths are resolved against the mapping file directory.
+    pub async fn import_file(&self, mapping_path: impl AsRef<Path>) -> Result<ImportReport> {
+        let mapping_path = mapping_path.as_ref();
+        let mapping = ImportMapping::load(mapping_path)?;
+        let source = mapping.source.open(mapping_path.parent())?;
+        self.import(&mapping, source.as_ref()).await
+    }
+
+    /// Import all records of a source.
+    pub async fn import(
+        &self,
+        mapping: &ImportMapping,
+        source: &dyn SourceDriver,
+    ) -> Result<ImportReport> {
+        mapping.validate()?;
+        let records = source.fetch().await?;
+        info!(
+            "Importing {} records from {} into {}",
+            records.len(),
+            source.name(),
+            mapping.namespace
+        );
+
+        let mut report = ImportReport::default();
+        let mut seen = HashSet::new();
+
+        for (index, record) in records.iter().enumerate() {
+            let source_id = match record_id(record, &mapping.id_field) {
+                Ok(id) => id,
+                Err(e) => {
+                    report.errors.push(RecordError {
+                        index,
+                        source_id: None,
+                        message: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            if !seen.insert(source_id.clone()) {
+                report.errors.push(RecordError {
+                    index,
+                    source_id: Some(source_id),
+                    message: "duplicate source id".to_string(),
+                });
+                continue;
+            }
+
+            let incoming = match map_record(mapping, record) {
+                Ok(values) => values,
+                Err(e) => {
+                    report.errors.push(RecordError {
+                        index,
+                        source_id: Some(source_id),
+                        message: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            match self
+                .import_record(mapping, &source_id, incoming, &mut report.conflicts)
+                .await?
+            {
+                RecordOutcome::Created => report.created += 1,
+                RecordOutcome::Updated => report.updated += 1,
+                RecordOutcome::Unchanged => report.unchanged += 1,
+            }
+        }
+
+        info!(
+            "Import {} finished: {} created, {} updated, {} unchanged, {} conflicts, {} errors",
+            mapping.name,
+            report.created,
+            report.updated,
+            report.unchanged,
+            report.conflicts.len(),
+            report.errors.len()
+        );
+
+        Ok(report)
+    }
+
+    /// Merge one mapped record into its document.
+    async fn import_record(
+        &self,
+        mapping: &ImportMapping,
+        source_id: &str,
+        incoming: Map<String, Value>,
+        conflicts: &mut Vec<ImportConflict>,
+    ) -> Result<RecordOutcome> {
+        let doc_id = DocumentId::new(
+            &mapping.namespace,
+            format!("{}{}", mapping.key_prefix, source_id),
+        );
+        let (handle, created) = match self.engine.get_document(&doc_id).await {
+            Ok(handle) => (handle, false),
+            Err(_) => (self.engine.create_document(doc_id.clone()).await?, true),
+        };
+
+        let (metadata, local) = read_document(&handle, &incoming)?;
+
+        let mut writes = Vec::new();
+        for (field, value) in &incoming {
+            let base = metadata.base.as_ref().and_then(|b| b.get(field));
+            let local = local.get(field);
+
+            // Unchanged at the source since the last import
+            if metadata.base.is_some() && base == Some(value) {
+                continue;
+            }
+            // Already holds the incoming value
+            if local == Some(value) || (local.is_none() && value.is_null()) {
+                continue;
+            }
+            // Not edited locally since the last import
+            if local.is_none() || local == base {
+                writes.push((field.clone(), value.clone()));
+                continue;
+            }
+
+            conflicts.push(ImportConflict {
+                document_id: doc_id.clone(),
+                field: field.clone(),
+                local: local.cloned().unwrap_or(Value::Null),
+                incoming: value.clone(),
+                resolution: mapping.on_conflict,
+            });
+            if mapping.on_conflict == ConflictMode::PreferSource {
+                writes.push((field.clone(), value.clone()));
+            }
+        }
+
+        let base_changed = metadata.base.as_ref() != Some(&incoming);
+        if writes.is_empty() && !base_changed {
+            return Ok(RecordOutcome::Unchanged);
+        }
+
+        let base_json = serde_json::to_string(&incoming)?;
+        handle.update(|doc| {
+            for (field, value) in &writes {
+                put_json(doc, field, value)?;
+            }
+            let meta = doc.put_object(ROOT, IMPORT_METADATA_KEY, ObjType::Map)?;
+            doc.put(&meta, "job", mapping.name.as_str())?;
+            doc.put(&meta, "source_id", source_id)?;
+            if let Some(gen) = &mapping.gen {
+                doc.put(&meta, "gen", gen.as_str())?;
+            }
+            doc.put(&meta, "base", base_json.as_str())?;
+            Ok(())
+        })?;
+
+        Ok(if created {
+            RecordOutcome::Created
+        } else if writes.is_empty() {
+            RecordOutcome::Unchanged
+        } else {
+            RecordOutcome::Updated
+        })
+    }
+}
+
+/// Read the source ID of a record.
+fn record_id(record: &SourceRecord, id_field: &str) -> Result<String> {
+    match record.get(id_field) {
+        Some(Value::String(s)) if !s.trim().is_empty() => Ok(s.trim().to_string()),
+        Some(Value::Number(n)) => Ok(n.to_string()),
+        _ => Err(ImportError::ConversionError {
+            field: id_field.to_string(),
+            message: "missing source id".to_string(),
+        }),
+    }
+}
+
+/// Convert a record to typed document values keyed by target field.
+fn map_record(mapping: &ImportMapping, record: &SourceRecord) -> Result<Map<String, Value>> {
+    let mut values = Map::new();
+    for field in &mapping.fields {
+        let raw = record.get(&field.source).unwrap_or(&Value::Null);
+        let value = field.field_type.convert(&field.source, raw)?;
+        if value.is_null() && field.required {
+            return Err(ImportError::ConversionError {
+                field: field.source.clone(),
+                message: "required field is missing".to_string(),
+            });
+        }
+        values.insert(field.target().to_string(), value);
+    }
+    Ok(values)
+}
+
+/// Read the import metadata and current values of the mapped fields.
+fn read_document(
+    handle: &DocumentHandle,
+    fields: &Map<String, Value>,
+) -> Result<(ImportMetadata, Map<String, Value>)> {
+    let (base, local) = handle.read(|doc| {
+        let base = match doc.get(ROOT, IMPORT_METADATA_KEY)? {
+            Some((automerge::Value::Object(ObjType::Map), meta)) => match doc.get(&meta, "base")? {
+                Some((value, _)) => value.to_str().map(str::to_string),
+                None => None,
+            },
+            _ => None,
+        };
+
+        let mut local = Map::new();
+        for field in fields.keys() {
+            if let Some((value, _)) = doc.get(ROOT, field.as_str())? {
+                local.insert(field.clone(), to_json(&value));
+            }
+        }
+        Ok((base, local))
+    })?;
+
+    let base = base.map(|json| serde_json::from_str(&json)).transpose()?;
+    Ok((ImportMetadata { base }, local))
+}
+
+/// Convert a document value to JSON.
+fn to_json(value: &automerge::Value<'_>) -> Value {
+    match value {
+        automerge::Value::Scalar(scalar) => match scalar.as_ref() {
+            ScalarValue::Str(s) => Value::String(s.to_string()),
+            ScalarValue::Int(i) => Value::from(*i),
+            ScalarValue::Uint(u) => Value::from(*u),
+            ScalarValue::F64(f) => Value::from(*f),
+            ScalarValue::Boolean(b) => Value::Bool(*b),
+            ScalarValue::Null => Value::Null,
+            other => Value::String(other.to_string()),
+        },
+        automerge::Value::Object(_) => Value::Null,
+    }
+}
+
+/// Write a JSON scalar to a root document field.
+fn put_json(doc: &mut AutoCommit, field: &str, value: &Value) -> vudo_state::Result<()> {
+    match value {
+        Value::String(s) => doc.put(ROOT, field, s.as_str())?,
+        Value::Bool(b) => doc.put(ROOT, field, *b)?,
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => doc.put(ROOT, field, i)?,
+            None => doc.put(ROOT, field, n.as_f64().unwrap_or_default())?,
+        },
+        _ => doc.put(ROOT, field, ScalarValue::Null)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapping::{FieldMapping, FieldType, SourceSpec};
+    use crate::source::MemorySource;
+    use serde_json::json;
+
+    fn mapping(on_conflict: ConflictMode) -> ImportMapping {
+        ImportMapping {
+            name: "crm".to_string(),
+            source: SourceSpec::Csv {
+                path: "users.csv".into(),
+                delimiter: None,
+            },
+            namespace: "users".to_string(),
+            gen: Some("UserProfile".to_string()),
+            id_field: "id".to_string(),
+            key_prefix: "crm-".to_string(),
+            fields: vec![
+                FieldMapping {
+                    source: "email".to_string(),
+                    target: None,
+                    field_type: FieldType::String,
+                    required: true,
+                },
+                FieldMapping {
+                    source: "age".to_string(),
+                    target: None,
+                    field_type: FieldType::Int,
+                    required: false,
+                },
+            ],
+            on_conflict,
+        }
+    }
+
+    fn source(records: serde_json::Value) -> MemorySource {
+        let records = records
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r.as_object().unwrap().clone())
+            .collect();
+        MemorySource::new(records)
+    }
+
+    async fn get_field(engine: &StateEngine, key: &str, field: &str) -> Value {
+        let handle = engine
+            .get_document(&DocumentId::new("users", key))
+            .await
+            .unwrap();
+        handle
+            .read(|doc| Ok(doc.get(ROOT, field)?.map(|(v, _)| to_json(&v))))
+            .unwrap()
+            .unwrap_or(Value::Null)
+    }
+
+    #[tokio::test]
+    async fn test_import_is_idempotent() {
+        let engine = Arc::new(StateEngine::new().await.unwrap());
+        let importer = Importer::new(Arc::clone(&engine));
+        let mapping = mapping(ConflictMode::KeepLocal);
+        let records = source(json!([
+            { "id": "1", "email": "a@example.com", "age": "30" },
+            { "id": 2, "email": "b@example.com", "age": "" },
+            { "id": "3", "age": "40" },
+            { "email": "no-id@example.com" },
+        ]));
+
+        let report = importer.import(&mapping, &records).await.unwrap();
+        assert_eq!(report.created, 2);
+        assert_eq!(report.errors.len(), 2);
+        assert_eq!(report.errors[0].source_id.as_deref(), Some("3"));
+        assert_eq!(get_field(&engine, "crm-1", "age").await, json!(30));
+
+        // Re-running the same import changes nothing
+        let report = importer.import(&mapping, &records).await.unwrap();
+        assert_eq!(report.created, 0);
+        assert_eq!(report.updated, 0);
+        assert_eq!(report.unchanged, 2);
+    }
+
+    #[tokio::test]
+    async fn test_incremental_reimport_and_conflicts() {
+        let engine = Arc::new(StateEngine::new().await.unwrap());
+        let importer = Importer::new(Arc::clone(&engine));
+        let first = source(json!([{ "id": "1", "email": "a@example.com", "age": "30" }]));
+        importer
+            .import(&mapping(ConflictMode::KeepLocal), &first)
+            .await
+            .unwrap();
+
+        // Local edit to the email
+        engine
+            .get_document(&DocumentId::new("users", "crm-1"))
+            .await
+            .unwrap()
+            .update(|doc| {
+                doc.put(ROOT, "email", "alice@example.com")?;
+                Ok(())
+            })
+            .unwrap();
+
+        // Source changes both fields: age updates, email conflicts
+        let second = source(json!([{ "id": "1", "email": "a@new.example.com", "age": "31" }]));
+        let report = importer
+            .import(&mapping(ConflictMode::KeepLocal), &second)
+            .await
+            .unwrap();
+        assert_eq!(report.updated, 1);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].field, "email");
+        assert_eq!(report.conflicts[0].local, json!("alice@example.com"));
+        assert_eq!(get_field(&engine, "crm-1", "age").await, json!(31));
+        assert_eq!(
+            get_field(&engine, "crm-1", "email").await,
+            json!("alice@example.com")
+        );
+
+        // The conflict is reported once; the local edit survives later runs
+        let report = importer
+            .import(&mapping(ConflictMode::KeepLocal), &second)
+            .await
+            .unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.unchanged, 1);
+
+        // Preferring the source overwrites the local edit
+        let third = source(json!([{ "id": "1", "email": "a@third.example.com", "age": "31" }]));
+        let report = importer
+            .import(&mapping(ConflictMode::PreferSource), &third)
+            .await
+            .unwrap();
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(
+            get_field(&engine, "crm-1", "email").await,
+            json!("a@third.example.com")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("users.csv"),
+            "id,email,age\n1,a@example.com,30\n",
+        )
+        .unwrap();
+        let mapping_path = dir.path().join("users.import.json");
+        std::fs::write(
+            &mapping_path,
+            serde_json::to_string(&mapping(ConflictMode::KeepLocal)).unwrap(),
+        )
+        .unwrap();
+
+        let engine = Arc::new(StateEngine::new().await.unwrap());
+        let report = Importer::new(Arc::clone(&engine))
+            .import_file(&mapping_path)
+            .await
+            .unwrap();
+        assert_eq!(report.created, 1);
+        assert_eq!(
+            get_field(&engine, "crm-1", "email").await,
+            json!("a@example.com")
+        );
+    }
+}
@@ -0,0 +1,48 @@
+//! VUDO Import Bridge
+//!
+//! Imports records from existing SQL, REST and CSV data sources into VUDO
+//! documents, so applications can move onto local-first state without
+//! abandoning their current back-end.
+//!
+//! - Pluggable source drivers: CSV, JSON files, SQLite (`sqlite` feature) and
+//!   JSON REST APIs (`http` feature)
+//! - Declarative JSON mapping files from source fields to DOL-typed fields
+//! - Idempotent, incremental re-imports keyed by source IDs
+//! - Conflict reporting when a field changed at the source and locally
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use vudo_import::Importer;
+//! use vudo_state::StateEngine;
+//!
+//! # async fn example() -> vudo_import::Result<()> {
+//! let engine = Arc::new(StateEngine::new().await?);
+//! let importer = Importer::new(engine);
+//!
+//! let report = importer.import_file("imports/users.import.json").await?;
+//! println!(
+//!     "{} created, {} updated, {} conflicts",
+//!     report.created,
+//!     report.updated,
+//!     report.conflicts.len()
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod error;
+pub mod importer;
+pub mod mapping;
+pub mod source;
+
+pub use error::{ImportError, Result};
+pub use importer::{ImportConflict, ImportReport, Importer, RecordError, IMPORT_METADATA_KEY};
+pub use mapping::{ConflictMode, FieldMapping, FieldType, ImportMapping, SourceSpec};
+pub use source::{CsvSource, JsonFileSource, MemorySource, SourceDriver, SourceRecord};
+
+#[cfg(feature = "http")]
+pub use source::JsonApiSource;
+#[cfg(feature = "sqlite")]
+pub use source::SqliteSource;
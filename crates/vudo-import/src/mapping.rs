@@ -0,0 +1,365 @@
+//! Declarative mapping from external records to DOL-typed documents.
+//!
+//! A mapping file is JSON:
+//!
+//! ```json
+//! {
+//!   "name": "crm-users",
+//!   "source": { "driver": "csv", "path": "users.csv" },
+//!   "namespace": "users",
+//!   "gen": "UserProfile",
+//!   "id_field": "user_id",
+//!   "fields": [
+//!     { "source": "email", "type": "string", "required": true },
+//!     { "source": "full_name", "target": "name", "type": "string" },
+//!     { "source": "age", "type": "int" }
+//!   ],
+//!   "on_conflict": "keep_local"
+//! }
+//! ```
+
+use crate::error::{ImportError, Result};
+use crate::source::{CsvSource, JsonFileSource, SourceDriver};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Where records are read from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "driver", rename_all = "snake_case")]
+pub enum SourceSpec {
+    /// CSV file with a header row.
+    Csv {
+        /// Path to the CSV file.
+        path: PathBuf,
+        /// Field delimiter (default `,`).
+        #[serde(default)]
+        delimiter: Option<char>,
+    },
+    /// JSON file holding an array of objects.
+    JsonFile {
+        /// Path to the JSON file.
+        path: PathBuf,
+        /// JSON pointer to the record array (default: the document root).
+        #[serde(default)]
+        records: Option<String>,
+    },
+    /// SQLite database queried with a SELECT statement.
+    Sqlite {
+        /// Path to the database file.
+        path: PathBuf,
+        /// Query returning one row per record.
+        query: String,
+    },
+    /// JSON REST endpoint returning an array of objects.
+    JsonApi {
+        /// URL to GET.
+        url: String,
+        /// JSON pointer to the record array (default: the response root).
+        #[serde(default)]
+        records: Option<String>,
+        /// Extra request headers (e.g. authorization).
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+}
+
+impl SourceSpec {
+    /// Open a driver for this source.
+    ///
+    /// Relative file paths are resolved against `base_dir` (usually the
+    /// directory of the mapping file).
+    pub fn open(&self, base_dir: Option<&Path>) -> Result<Box<dyn SourceDriver>> {
+        let resolve = |path: &Path| match base_dir {
+            Some(dir) if path.is_relative() => dir.join(path),
+            _ => path.to_path_buf(),
+        };
+
+        match self {
+            SourceSpec::Csv { path, delimiter } => {
+                let mut source = CsvSource::new(resolve(path));
+                if let Some(delimiter) = delimiter {
+                    source = source.with_delimiter(*delimiter)?;
+                }
+                Ok(Box::new(source))
+            }
+            SourceSpec::JsonFile { path, records } => Ok(Box::new(JsonFileSource::new(
+                resolve(path),
+                records.clone(),
+            ))),
+            #[cfg(feature = "sqlite")]
+            SourceSpec::Sqlite { path, query } => Ok(Box::new(crate::source::SqliteSource::new(
+                resolve(path),
+                query.clone(),
+            ))),
+            #[cfg(not(feature = "sqlite"))]
+            SourceSpec::Sqlite { .. } => Err(ImportError::DriverDisabled("sqlite".to_string())),
+            #[cfg(feature = "http")]
+            SourceSpec::JsonApi {
+                url,
+                records,
+                headers,
+            } => Ok(Box::new(crate::source::JsonApiSource::new(
+                url.clone(),
+                records.clone(),
+                headers.clone(),
+            )?)),
+            #[cfg(not(feature = "http"))]
+            SourceSpec::JsonApi { .. } => Err(ImportError::DriverDisabled("json_api".to_string())),
+        }
+    }
+}
+
+/// DOL scalar type a field is converted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    /// UTF-8 string.
+    String,
+    /// 64-bit signed integer.
+    Int,
+    /// 64-bit float.
+    Float,
+    /// Boolean.
+    Bool,
+}
+
+impl FieldType {
+    /// Convert a source value to this type.
+    ///
+    /// Text values (as produced by CSV) are parsed; empty text is treated as
+    /// missing for non-string types. Missing values convert to `null`.
+    pub fn convert(&self, field: &str, value: &Value) -> Result<Value> {
+        let fail = |message: String| ImportError::ConversionError {
+            field: field.to_string(),
+            message,
+        };
+
+        match (self, value) {
+            (_, Value::Null) => Ok(Value::Null),
+            (FieldType::String, Value::String(_)) => Ok(value.clone()),
+            (FieldType::String, Value::Number(n)) => Ok(Value::String(n.to_string())),
+            (FieldType::String, Value::Bool(b)) => Ok(Value::String(b.to_string())),
+            (_, Value::String(s)) if s.trim().is_empty() => Ok(Value::Null),
+            (FieldType::Int, Value::Number(n)) => n
+                .as_i64()
+                .or_else(|| {
+                    n.as_f64()
+                        .filter(|f| f.fract() == 0.0 && f.abs() < i64::MAX as f64)
+                        .map(|f| f as i64)
+                })
+                .map(Value::from)
+                .ok_or_else(|| fail(format!("{} is not an integer", n))),
+            (FieldType::Int, Value::String(s)) => s
+                .trim()
+                .parse::<i64>()
+                .map(Value::from)
+                .map_err(|_| fail(format!("'{}' is not an integer", s))),
+            (FieldType::Float, Value::Number(n)) => {
+                Ok(n.as_f64().map(Value::from).unwrap_or(Value::Null))
+            }
+            (FieldType::Float, Value::String(s)) => s
+                .trim()
+                .parse::<f64>()
+                .map(Value::from)
+                .map_err(|_| fail(format!("'{}' is not a number", s))),
+            (FieldType::Bool, Value::Bool(_)) => Ok(value.clone()),
+            (FieldType::Bool, Value::Number(n)) => match n.as_i64() {
+                Some(0) => Ok(Value::Bool(false)),
+                Some(1) => Ok(Value::Bool(true)),
+                _ => Err(fail(format!("{} is not a boolean", n))),
+            },
+            (FieldType::Bool, Value::String(s)) => match s.trim().to_lowercase().as_str() {
+                "true" | "yes" | "1" => Ok(Value::Bool(true)),
+                "false" | "no" | "0" => Ok(Value::Bool(false)),
+                _ => Err(fail(format!("'{}' is not a boolean", s))),
+            },
+            (_, other) => Err(fail(format!("unsupported value {}", other))),
+        }
+    }
+}
+
+/// Mapping of one source field to a document field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldMapping {
+    /// Field name in the source record.
+    pub source: String,
+    /// Field name in the document (defaults to the source name).
+    #[serde(default)]
+    pub target: Option<String>,
+    /// Type the value is converted to.
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+    /// Reject records where the field is missing.
+    #[serde(default)]
+    pub required: bool,
+}
+
+impl FieldMapping {
+    /// Get the document field name.
+    pub fn target(&self) -> &str {
+        self.target.as_deref().unwrap_or(&self.source)
+    }
+}
+
+/// How fields edited locally and changed at the source are resolved.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictMode {
+    /// Keep the local edit and report the conflict.
+    #[default]
+    KeepLocal,
+    /// Overwrite with the source value and report the conflict.
+    PreferSource,
+}
+
+/// Declarative import mapping.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportMapping {
+    /// Import job name, recorded on imported documents.
+    pub name: String,
+    /// Record source.
+    pub source: SourceSpec,
+    /// Namespace documents are imported into.
+    pub namespace: String,
+    /// DOL gen the documents are instances of.
+    #[serde(default)]
+    pub gen: Option<String>,
+    /// Source field holding the stable record ID.
+    pub id_field: String,
+    /// Prefix prepended to source IDs to form document keys.
+    #[serde(default)]
+    pub key_prefix: String,
+    /// Field mappings.
+    pub fields: Vec<FieldMapping>,
+    /// Conflict resolution for re-imports.
+    #[serde(default)]
+    pub on_conflict: ConflictMode,
+}
+
+impl ImportMapping {
+    /// Parse a mapping from JSON.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let mapping: Self = serde_json::from_str(json)?;
+        mapping.validate()?;
+        Ok(mapping)
+    }
+
+    /// Load a mapping file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Check the mapping for structural errors.
+    pub fn validate(&self) -> Result<()> {
+        if self.namespace.is_empty() {
+            return Err(ImportError::InvalidMapping(
+                "namespace is empty".to_string(),
+            ));
+        }
+        if self.id_field.is_empty() {
+            return Err(ImportError::InvalidMapping("id_field is empty".to_string()));
+        }
+        if self.fields.is_empty() {
+            return Err(ImportError::InvalidMapping("no fields mapped".to_string()));
+        }
+
+        let mut targets = std::collections::HashSet::new();
+        for field in &self.fields {
+            let target = field.target();
+            if target.starts_with('_') {
+                return Err(ImportError::InvalidMapping(format!(
+                    "target field '{}' is reserved",
+                    target
+                )));
+            }
+            if !targets.insert(target) {
+                return Err(ImportError::InvalidMapping(format!(
+                    "target field '{}' is mapped twice",
+                    target
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_mapping() {
+        let mapping = ImportMapping::from_json(
+            r#"{
+                "name": "crm",
+                "source": { "driver": "csv", "path": "users.csv" },
+                "namespace": "users",
+                "id_field": "id",
+                "fields": [
+                    { "source": "email", "type": "string", "required": true },
+                    { "source": "full_name", "target": "name", "type": "string" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(mapping.on_conflict, ConflictMode::KeepLocal);
+        assert_eq!(mapping.fields[1].target(), "name");
+        assert!(matches!(mapping.source, SourceSpec::Csv { .. }));
+    }
+
+    #[test]
+    fn test_reject_invalid_mapping() {
+        let duplicate = r#"{
+            "name": "crm",
+            "source": { "driver": "csv", "path": "users.csv" },
+            "namespace": "users",
+            "id_field": "id",
+            "fields": [
+                { "source": "a", "target": "x", "type": "string" },
+                { "source": "b", "target": "x", "type": "string" }
+            ]
+        }"#;
+        assert!(matches!(
+            ImportMapping::from_json(duplicate),
+            Err(ImportError::InvalidMapping(_))
+        ));
+
+        let reserved = duplicate.replace(r#""target": "x""#, r#""target": "_import""#);
+        assert!(ImportMapping::from_json(&reserved).is_err());
+    }
+
+    #[test]
+    fn test_field_conversion() {
+        assert_eq!(
+            FieldType::Int.convert("f", &json!(" 42 ")).unwrap(),
+            json!(42)
+        );
+        assert_eq!(FieldType::Int.convert("f", &json!(7.0)).unwrap(), json!(7));
+        assert!(FieldType::Int.convert("f", &json!("4.5")).is_err());
+        assert_eq!(
+            FieldType::Float.convert("f", &json!("2.5")).unwrap(),
+            json!(2.5)
+        );
+        assert_eq!(
+            FieldType::Bool.convert("f", &json!("Yes")).unwrap(),
+            json!(true)
+        );
+        assert_eq!(
+            FieldType::String.convert("f", &json!(12)).unwrap(),
+            json!("12")
+        );
+        assert_eq!(
+            FieldType::Int.convert("f", &json!("")).unwrap(),
+            Value::Null
+        );
+        assert_eq!(
+            FieldType::String.convert("f", &json!("")).unwrap(),
+            json!("")
+        );
+    }
+}
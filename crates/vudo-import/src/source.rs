@@ -0,0 +1,324 @@
+//! Pluggable source drivers producing records to import.
+
+use crate::error::{ImportError, Result};
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+
+/// One external record, as field name to JSON value.
+pub type SourceRecord = Map<String, Value>;
+
+/// Driver reading records from an external data source.
+#[async_trait]
+pub trait SourceDriver: Send + Sync {
+    /// Driver name, for logging and reports.
+    fn name(&self) -> &str;
+
+    /// Fetch all records from the source.
+    async fn fetch(&self) -> Result<Vec<SourceRecord>>;
+}
+
+/// In-memory records, for programmatic imports.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySource {
+    records: Vec<SourceRecord>,
+}
+
+impl MemorySource {
+    /// Create a source from records.
+    pub fn new(records: Vec<SourceRecord>) -> Self {
+        Self { records }
+    }
+}
+
+#[async_trait]
+impl SourceDriver for MemorySource {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    async fn fetch(&self) -> Result<Vec<SourceRecord>> {
+        Ok(self.records.clone())
+    }
+}
+
+/// CSV file with a header row. All values are read as text.
+#[derive(Debug, Clone)]
+pub struct CsvSource {
+    path: PathBuf,
+    delimiter: u8,
+}
+
+impl CsvSource {
+    /// Create a CSV source.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            delimiter: b',',
+        }
+    }
+
+    /// Set the field delimiter.
+    pub fn with_delimiter(mut self, delimiter: char) -> Result<Self> {
+        self.delimiter = u8::try_from(delimiter).map_err(|_| {
+            ImportError::InvalidMapping(format!("delimiter '{}' is not ASCII", delimiter))
+        })?;
+        Ok(self)
+    }
+
+    /// Parse records from CSV text.
+    pub fn parse(&self, reader: impl std::io::Read) -> Result<Vec<SourceRecord>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .from_reader(reader);
+        let headers = reader.headers()?.clone();
+
+        reader
+            .records()
+            .map(|row| {
+                let row = row?;
+                Ok(headers
+                    .iter()
+                    .zip(row.iter())
+                    .map(|(h, v)| (h.to_string(), Value::String(v.to_string())))
+                    .collect())
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl SourceDriver for CsvSource {
+    fn name(&self) -> &str {
+        "csv"
+    }
+
+    async fn fetch(&self) -> Result<Vec<SourceRecord>> {
+        let file = std::fs::File::open(&self.path)?;
+        self.parse(file)
+    }
+}
+
+/// JSON file holding an array of objects.
+#[derive(Debug, Clone)]
+pub struct JsonFileSource {
+    path: PathBuf,
+    records: Option<String>,
+}
+
+impl JsonFileSource {
+    /// Create a JSON file source; `records` is a JSON pointer to the array.
+    pub fn new(path: impl Into<PathBuf>, records: Option<String>) -> Self {
+        Self {
+            path: path.into(),
+            records,
+        }
+    }
+}
+
+#[async_trait]
+impl SourceDriver for JsonFileSource {
+    fn name(&self) -> &str {
+        "json_file"
+    }
+
+    async fn fetch(&self) -> Result<Vec<SourceRecord>> {
+        let value: Value = serde_json::from_slice(&std::fs::read(&self.path)?)?;
+        extract_records(value, self.records.as_deref())
+    }
+}
+
+/// SQLite database queried with a SELECT statement.
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Clone)]
+pub struct SqliteSource {
+    path: PathBuf,
+    query: String,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteSource {
+    /// Create a SQLite source.
+    pub fn new(path: impl Into<PathBuf>, query: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            query: query.into(),
+        }
+    }
+
+    fn query_blocking(path: &std::path::Path, query: &str) -> Result<Vec<SourceRecord>> {
+        use rusqlite::types::ValueRef;
+
+        let conn = rusqlite::Connection::open_with_flags(
+            path,
+            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+        )?;
+        let mut stmt = conn.prepare(query)?;
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+
+        let mut rows = stmt.query([])?;
+        let mut records = Vec::new();
+        while let Some(row) = rows.next()? {
+            let mut record = SourceRecord::new();
+            for (i, column) in columns.iter().enumerate() {
+                let value = match row.get_ref(i)? {
+                    ValueRef::Null => Value::Null,
+                    ValueRef::Integer(n) => Value::from(n),
+                    ValueRef::Real(f) => Value::from(f),
+                    ValueRef::Text(t) => Value::String(String::from_utf8_lossy(t).to_string()),
+                    ValueRef::Blob(_) => {
+                        return Err(ImportError::SourceError(format!(
+                            "column '{}' is a blob",
+                            column
+                        )))
+                    }
+                };
+                record.insert(column.clone(), value);
+            }
+            records.push(record);
+        }
+
+        Ok(records)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[async_trait]
+impl SourceDriver for SqliteSource {
+    fn name(&self) -> &str {
+        "sqlite"
+    }
+
+    async fn fetch(&self) -> Result<Vec<SourceRecord>> {
+        let path = self.path.clone();
+        let query = self.query.clone();
+        tokio::task::spawn_blocking(move || Self::query_blocking(&path, &query))
+            .await
+            .map_err(|e| ImportError::SourceError(e.to_string()))?
+    }
+}
+
+/// JSON REST endpoint returning an array of objects.
+#[cfg(feature = "http")]
+pub struct JsonApiSource {
+    client: reqwest::Client,
+    url: String,
+    records: Option<String>,
+    headers: std::collections::BTreeMap<String, String>,
+}
+
+#[cfg(feature = "http")]
+impl JsonApiSource {
+    /// Create a JSON API source; `records` is a JSON pointer to the array.
+    pub fn new(
+        url: impl Into<String>,
+        records: Option<String>,
+        headers: std::collections::BTreeMap<String, String>,
+    ) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder().build()?,
+            url: url.into(),
+            records,
+            headers,
+        })
+    }
+}
+
+#[cfg(feature = "http")]
+#[async_trait]
+impl SourceDriver for JsonApiSource {
+    fn name(&self) -> &str {
+        "json_api"
+    }
+
+    async fn fetch(&self) -> Result<Vec<SourceRecord>> {
+        let mut request = self
+            .client
+            .get(&self.url)
+            .header(reqwest::header::ACCEPT, "application/json");
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+
+        let response = request.send().await?.error_for_status()?;
+        let value: Value = serde_json::from_slice(&response.bytes().await?)?;
+        extract_records(value, self.records.as_deref())
+    }
+}
+
+/// Extract the record array at a JSON pointer.
+pub fn extract_records(value: Value, pointer: Option<&str>) -> Result<Vec<SourceRecord>> {
+    let array = match pointer {
+        Some(pointer) => value.pointer(pointer).cloned().ok_or_else(|| {
+            ImportError::SourceError(format!("no value at JSON pointer '{}'", pointer))
+        })?,
+        None => value,
+    };
+
+    let Value::Array(items) = array else {
+        return Err(ImportError::SourceError(
+            "records are not a JSON array".to_string(),
+        ));
+    };
+
+    items
+        .into_iter()
+        .map(|item| match item {
+            Value::Object(record) => Ok(record),
+            other => Err(ImportError::SourceError(format!(
+                "record is not a JSON object: {}",
+                other
+            ))),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_csv_parse() {
+        let source = CsvSource::new("unused.csv").with_delimiter(';').unwrap();
+        let records = source
+            .parse("id;email\n1;a@example.com\n2;b@example.com\n".as_bytes())
+            .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1]["email"], json!("b@example.com"));
+        assert_eq!(records[0]["id"], json!("1"));
+    }
+
+    #[test]
+    fn test_extract_records() {
+        let body = json!({ "data": { "users": [{ "id": 1 }, { "id": 2 }] } });
+        let records = extract_records(body.clone(), Some("/data/users")).unwrap();
+        assert_eq!(records.len(), 2);
+
+        assert!(extract_records(body.clone(), None).is_err());
+        assert!(extract_records(body, Some("/missing")).is_err());
+        assert!(extract_records(json!([1, 2]), None).is_err());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_sqlite_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("crm.db");
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE users (id INTEGER, email TEXT, score REAL);
+             INSERT INTO users VALUES (1, 'a@example.com', 1.5), (2, NULL, 2.0);",
+        )
+        .unwrap();
+        drop(conn);
+
+        let source = SqliteSource::new(&path, "SELECT id, email, score FROM users ORDER BY id");
+        let records = source.fetch().await.unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["id"], json!(1));
+        assert_eq!(records[0]["score"], json!(1.5));
+        assert_eq!(records[1]["email"], Value::Null);
+    }
+}
@@ -0,0 +1,39 @@
+//! Error types for the mirror.
+
+use thiserror::Error;
+
+/// Result type for mirror operations.
+pub type Result<T> = std::result::Result<T, MirrorError>;
+
+/// Mirror error types.
+#[derive(Debug, Error)]
+pub enum MirrorError {
+    /// Invalid table schema.
+    #[error("Invalid schema: {0}")]
+    InvalidSchema(String),
+
+    /// Sink failed to apply a change.
+    #[error("Sink error: {0}")]
+    SinkError(String),
+
+    /// State engine error.
+    #[error("State engine error: {0}")]
+    StateError(#[from] vudo_state::StateError),
+
+    /// JSON error.
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+}
+
+impl From<automerge::AutomergeError> for MirrorError {
+    fn from(err: automerge::AutomergeError) -> Self {
+        MirrorError::StateError(err.into())
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl From<tokio_postgres::Error> for MirrorError {
+    fn from(err: tokio_postgres::Error) -> Self {
+        MirrorError::SinkError(err.to_string())
+    }
+}
@@ -0,0 +1,48 @@
+//! VUDO Mirror
+//!
+//! Export bridge that continuously mirrors selected namespaces into
+//! PostgreSQL tables, so analysts can query local-first data with SQL.
+//!
+//! - One table per namespace, one row per document, nested maps flattened
+//!   into columns
+//! - Table schemas declared by hand or derived from the JSON Schema the DOL
+//!   code generator emits for a gen
+//! - Change events applied as upserts and deletes
+//! - Exactly-once application through an offset table recording the document
+//!   version of every row, updated in the same transaction as the row
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use vudo_mirror::{Mirror, PostgresSink, SqlType, TableSchema};
+//! use vudo_state::StateEngine;
+//!
+//! # async fn example() -> vudo_mirror::Result<()> {
+//! let engine = Arc::new(StateEngine::new().await?);
+//! let sink = PostgresSink::connect("host=localhost user=postgres dbname=analytics").await?;
+//!
+//! let users = TableSchema::new("users")
+//!     .with_column("name", SqlType::Text)
+//!     .with_column("age", SqlType::BigInt);
+//! let mirror = Arc::new(Mirror::new(engine, Arc::new(sink)).with_namespace("users", users));
+//!
+//! mirror.init().await?;
+//! mirror.backfill().await?;
+//! mirror.run();
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod error;
+pub mod mirror;
+pub mod schema;
+pub mod sink;
+
+pub use error::{MirrorError, Result};
+pub use mirror::{ApplyOutcome, Mirror, MirrorStats};
+pub use schema::{ColumnSpec, SqlType, SqlValue, TableSchema};
+pub use sink::{MemorySink, MirrorSink, RowChange};
+
+#[cfg(feature = "postgres")]
+pub use sink::PostgresSink;
@@ -0,0 +1,278 @@
+//! Continuous mirror of namespaces into a sink.
+
+use crate::error::{MirrorError, Result};
+use crate::schema::TableSchema;
+use crate::sink::{MirrorSink, RowChange};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+use vudo_state::{ChangeEvent, DocumentId, StateEngine, SubscriptionFilter};
+
+/// Result of mirroring one document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApplyOutcome {
+    /// Row inserted or updated.
+    Upserted,
+    /// Row deleted because the document no longer exists.
+    Deleted,
+    /// Document version already mirrored.
+    AlreadyApplied,
+    /// Namespace is not mirrored.
+    Unmapped,
+}
+
+/// Summary of a backfill.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MirrorStats {
+    /// Rows inserted or updated.
+    pub upserted: usize,
+    /// Rows already up to date.
+    pub already_applied: usize,
+}
+
+/// Mirrors selected namespaces of a state engine into a sink.
+pub struct Mirror {
+    /// Source state engine.
+    engine: Arc<StateEngine>,
+    /// Destination sink.
+    sink: Arc<dyn MirrorSink>,
+    /// Tables by namespace.
+    tables: HashMap<String, TableSchema>,
+}
+
+impl Mirror {
+    /// Create a mirror with no namespaces.
+    pub fn new(engine: Arc<StateEngine>, sink: Arc<dyn MirrorSink>) -> Self {
+        Self {
+            engine,
+            sink,
+            tables: HashMap::new(),
+        }
+    }
+
+    /// Mirror a namespace into a table.
+    pub fn with_namespace(mut self, namespace: impl Into<String>, table: TableSchema) -> Self {
+        self.tables.insert(namespace.into(), table);
+        self
+    }
+
+    /// Get the table a namespace is mirrored into.
+    pub fn table(&self, namespace: &str) -> Option<&TableSchema> {
+        self.tables.get(namespace)
+    }
+
+    /// Validate the tables and create them in the sink.
+    pub async fn init(&self) -> Result<()> {
+        if self.tables.is_empty() {
+            return Err(MirrorError::InvalidSchema(
+                "no namespaces to mirror".to_string(),
+            ));
+        }
+        let tables: Vec<TableSchema> = self.tables.values().cloned().collect();
+        for table in &tables {
+            table.validate()?;
+        }
+        self.sink.ensure_schema(&tables).await
+    }
+
+    /// Mirror the document a change event refers to.
+    pub async fn apply_event(&self, event: &ChangeEvent) -> Result<ApplyOutcome> {
+        self.apply_document(&event.document_id).await
+    }
+
+    /// Mirror the current state of a document.
+    pub async fn apply_document(&self, id: &DocumentId) -> Result<ApplyOutcome> {
+        let Some(table) = self.tables.get(&id.namespace) else {
+            return Ok(ApplyOutcome::Unmapped);
+        };
+
+        let Ok(handle) = self.engine.get_document(id).await else {
+            return Ok(
+                if self
+                    .sink
+                    .apply(table, &id.key, &RowChange::Delete, "")
+                    .await?
+                {
+                    ApplyOutcome::Deleted
+                } else {
+                    ApplyOutcome::AlreadyApplied
+                },
+            );
+        };
+
+        // Take the version before reading so a concurrent write can only make
+        // the recorded version older than the row, which the next event fixes.
+        let version = handle.version_token().to_string();
+        let row = handle.read(|doc| table.row(doc))?;
+
+        Ok(
+            if self
+                .sink
+                .apply(table, &id.key, &RowChange::Upsert(row), &version)
+                .await?
+            {
+                ApplyOutcome::Upserted
+            } else {
+                ApplyOutcome::AlreadyApplied
+            },
+        )
+    }
+
+    /// Mirror every existing document of the mirrored namespaces.
+    pub async fn backfill(&self) -> Result<MirrorStats> {
+        let mut stats = MirrorStats::default();
+        for namespace in self.tables.keys() {
+            for metadata in self.engine.list_documents(namespace).await? {
+                match self.apply_document(&metadata.id).await? {
+                    ApplyOutcome::Upserted => stats.upserted += 1,
+                    _ => stats.already_applied += 1,
+                }
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Start mirroring change events of every mirrored namespace.
+    ///
+    /// One task is spawned per namespace. Failed changes are logged and
+    /// retried with the next event for the same document.
+    pub fn run(self: &Arc<Self>) -> Vec<JoinHandle<()>> {
+        self.tables
+            .keys()
+            .map(|namespace| {
+                let mirror = Arc::clone(self);
+                let mut subscription = self
+                    .engine
+                    .observable
+                    .subscribe(SubscriptionFilter::Namespace(namespace.clone()));
+
+                tokio::spawn(async move {
+                    while let Some(event) = subscription.recv().await {
+                        match mirror.apply_event(&event).await {
+                            Ok(outcome) => {
+                                debug!("Mirrored {}: {:?}", event.document_id, outcome)
+                            }
+                            Err(e) => warn!("Failed to mirror {}: {}", event.document_id, e),
+                        }
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{SqlType, SqlValue};
+    use crate::sink::MemorySink;
+    use automerge::{transaction::Transactable, ROOT};
+
+    fn event(id: &DocumentId) -> ChangeEvent {
+        ChangeEvent {
+            document_id: id.clone(),
+            timestamp: 0,
+            change_hash: Vec::new(),
+            path: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mirror_events() {
+        let engine = Arc::new(StateEngine::new().await.unwrap());
+        let sink = Arc::new(MemorySink::new());
+        let table = TableSchema::new("users")
+            .with_column("name", SqlType::Text)
+            .with_column("age", SqlType::BigInt);
+        let mirror = Mirror::new(Arc::clone(&engine), sink.clone()).with_namespace("users", table);
+        mirror.init().await.unwrap();
+
+        let id = DocumentId::new("users", "alice");
+        let handle = engine.create_document(id.clone()).await.unwrap();
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "name", "Alice")?;
+                doc.put(ROOT, "age", 30i64)?;
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(mirror.backfill().await.unwrap().upserted, 1);
+        assert_eq!(
+            mirror.apply_event(&event(&id)).await.unwrap(),
+            ApplyOutcome::AlreadyApplied
+        );
+        assert_eq!(
+            sink.rows("users")["alice"],
+            vec![SqlValue::Text("Alice".to_string()), SqlValue::Int(30)]
+        );
+
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "age", 31i64)?;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(
+            mirror.apply_event(&event(&id)).await.unwrap(),
+            ApplyOutcome::Upserted
+        );
+        assert_eq!(sink.rows("users")["alice"][1], SqlValue::Int(31));
+
+        engine.delete_document(&id).await.unwrap();
+        assert_eq!(
+            mirror.apply_event(&event(&id)).await.unwrap(),
+            ApplyOutcome::Deleted
+        );
+        assert!(sink.rows("users").is_empty());
+
+        let other = DocumentId::new("posts", "1");
+        assert_eq!(
+            mirror.apply_event(&event(&other)).await.unwrap(),
+            ApplyOutcome::Unmapped
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mirror_run() {
+        let engine = Arc::new(StateEngine::new().await.unwrap());
+        let sink = Arc::new(MemorySink::new());
+        let mirror = Arc::new(
+            Mirror::new(Arc::clone(&engine), sink.clone()).with_namespace(
+                "users",
+                TableSchema::new("users").with_column("name", SqlType::Text),
+            ),
+        );
+        mirror.init().await.unwrap();
+        let tasks = mirror.run();
+
+        let id = DocumentId::new("users", "bob");
+        engine
+            .create_document(id.clone())
+            .await
+            .unwrap()
+            .update(|doc| {
+                doc.put(ROOT, "name", "Bob")?;
+                Ok(())
+            })
+            .unwrap();
+        engine.observable.notify(event(&id));
+        engine.observable.flush_batch();
+
+        for _ in 0..50 {
+            if !sink.rows("users").is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(
+            sink.rows("users")["bob"],
+            vec![SqlValue::Text("Bob".to_string())]
+        );
+
+        for task in tasks {
+            task.abort();
+        }
+    }
+}
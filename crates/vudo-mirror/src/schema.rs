@@ -0,0 +1,515 @@
+//! Table schemas and document flattening.
+//!
+//! A mirrored namespace maps to one table with one row per document. Root
+//! scalar fields become columns; nested maps are flattened with `_`-joined
+//! names (`address.city` becomes `address_city`), and lists are stored as
+//! `JSONB`.
+//!
+//! Schemas can be declared by hand or derived from the JSON Schema emitted by
+//! the DOL JSON Schema code generator for a gen.
+
+use crate::error::{MirrorError, Result};
+use automerge::{AutoCommit, ObjId, ObjType, ReadDoc, ScalarValue, ROOT};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Default name of the document key column.
+pub const DEFAULT_KEY_COLUMN: &str = "doc_key";
+
+/// Column type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SqlType {
+    /// `TEXT`.
+    Text,
+    /// `BIGINT`.
+    BigInt,
+    /// `DOUBLE PRECISION`.
+    Double,
+    /// `BOOLEAN`.
+    Boolean,
+    /// `JSONB`.
+    Jsonb,
+}
+
+impl SqlType {
+    /// Get the SQL type name.
+    pub fn sql(&self) -> &'static str {
+        match self {
+            SqlType::Text => "TEXT",
+            SqlType::BigInt => "BIGINT",
+            SqlType::Double => "DOUBLE PRECISION",
+            SqlType::Boolean => "BOOLEAN",
+            SqlType::Jsonb => "JSONB",
+        }
+    }
+
+    /// Map a JSON Schema type to a column type.
+    fn from_json_schema(schema: &Value) -> SqlType {
+        // Option<T> is emitted as oneOf [T, null]
+        if let Some(variants) = schema.get("oneOf").and_then(Value::as_array) {
+            let non_null: Vec<_> = variants
+                .iter()
+                .filter(|v| v.get("type").and_then(Value::as_str) != Some("null"))
+                .collect();
+            return match non_null.as_slice() {
+                [inner] => SqlType::from_json_schema(inner),
+                _ => SqlType::Jsonb,
+            };
+        }
+
+        match schema.get("type").and_then(Value::as_str) {
+            Some("string") => SqlType::Text,
+            Some("integer") => SqlType::BigInt,
+            Some("number") => SqlType::Double,
+            Some("boolean") => SqlType::Boolean,
+            _ => SqlType::Jsonb,
+        }
+    }
+
+    /// Convert a document value to this column type.
+    ///
+    /// Values that cannot be represented become `NULL`.
+    pub fn coerce(&self, value: &SqlValue) -> SqlValue {
+        match (self, value) {
+            (_, SqlValue::Null) => SqlValue::Null,
+            (SqlType::Text, SqlValue::Text(_)) => value.clone(),
+            (SqlType::Text, SqlValue::Int(i)) => SqlValue::Text(i.to_string()),
+            (SqlType::Text, SqlValue::Float(f)) => SqlValue::Text(f.to_string()),
+            (SqlType::Text, SqlValue::Bool(b)) => SqlValue::Text(b.to_string()),
+            (SqlType::Text, SqlValue::Json(json)) => SqlValue::Text(json.to_string()),
+            (SqlType::BigInt, SqlValue::Int(_)) => value.clone(),
+            (SqlType::BigInt, SqlValue::Text(s)) => s
+                .trim()
+                .parse()
+                .map(SqlValue::Int)
+                .unwrap_or(SqlValue::Null),
+            (SqlType::Double, SqlValue::Float(_)) => value.clone(),
+            (SqlType::Double, SqlValue::Int(i)) => SqlValue::Float(*i as f64),
+            (SqlType::Double, SqlValue::Text(s)) => s
+                .trim()
+                .parse()
+                .map(SqlValue::Float)
+                .unwrap_or(SqlValue::Null),
+            (SqlType::Boolean, SqlValue::Bool(_)) => value.clone(),
+            (SqlType::Jsonb, SqlValue::Json(_)) => value.clone(),
+            (SqlType::Jsonb, other) => SqlValue::Json(other.to_json()),
+            _ => SqlValue::Null,
+        }
+    }
+}
+
+/// Column value read from a document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlValue {
+    /// `NULL`.
+    Null,
+    /// Text.
+    Text(String),
+    /// Integer.
+    Int(i64),
+    /// Float.
+    Float(f64),
+    /// Boolean.
+    Bool(bool),
+    /// JSON document.
+    Json(Value),
+}
+
+impl SqlValue {
+    /// Convert to JSON.
+    pub fn to_json(&self) -> Value {
+        match self {
+            SqlValue::Null => Value::Null,
+            SqlValue::Text(s) => Value::String(s.clone()),
+            SqlValue::Int(i) => Value::from(*i),
+            SqlValue::Float(f) => Value::from(*f),
+            SqlValue::Bool(b) => Value::Bool(*b),
+            SqlValue::Json(json) => json.clone(),
+        }
+    }
+}
+
+/// Table column.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnSpec {
+    /// Column name, which is also the flattened document field name.
+    pub name: String,
+    /// Column type.
+    pub sql_type: SqlType,
+}
+
+/// Table a namespace is mirrored into.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableSchema {
+    /// Table name.
+    pub name: String,
+    /// Primary key column holding the document key.
+    pub key_column: String,
+    /// Data columns.
+    pub columns: Vec<ColumnSpec>,
+}
+
+impl TableSchema {
+    /// Create a table schema with no data columns.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            key_column: DEFAULT_KEY_COLUMN.to_string(),
+            columns: Vec::new(),
+        }
+    }
+
+    /// Add a data column.
+    pub fn with_column(mut self, name: impl Into<String>, sql_type: SqlType) -> Self {
+        self.columns.push(ColumnSpec {
+            name: name.into(),
+            sql_type,
+        });
+        self
+    }
+
+    /// Set the primary key column name.
+    pub fn with_key_column(mut self, name: impl Into<String>) -> Self {
+        self.key_column = name.into();
+        self
+    }
+
+    /// Derive a table schema from a gen's JSON Schema.
+    ///
+    /// Accepts the output of the DOL JSON Schema code generator: either the
+    /// gen schema itself, or a schema document with a `definitions` entry
+    /// named `gen`. Nested object properties are flattened into columns.
+    pub fn from_json_schema(
+        name: impl Into<String>,
+        schema: &Value,
+        gen: Option<&str>,
+    ) -> Result<Self> {
+        let schema = match gen {
+            Some(gen) => ["definitions", "$defs"]
+                .iter()
+                .find_map(|defs| schema.get(defs).and_then(|d| d.get(gen)))
+                .ok_or_else(|| {
+                    MirrorError::InvalidSchema(format!(
+                        "gen '{}' is not defined in the schema",
+                        gen
+                    ))
+                })?,
+            None => schema,
+        };
+
+        let mut table = Self::new(name);
+        add_properties(&mut table, "", schema);
+        table.validate()?;
+        Ok(table)
+    }
+
+    /// Check table and column names.
+    pub fn validate(&self) -> Result<()> {
+        validate_identifier(&self.name)?;
+        validate_identifier(&self.key_column)?;
+        if self.columns.is_empty() {
+            return Err(MirrorError::InvalidSchema(format!(
+                "table '{}' has no columns",
+                self.name
+            )));
+        }
+
+        let mut names = std::collections::HashSet::new();
+        names.insert(self.key_column.as_str());
+        for column in &self.columns {
+            validate_identifier(&column.name)?;
+            if !names.insert(column.name.as_str()) {
+                return Err(MirrorError::InvalidSchema(format!(
+                    "column '{}' is defined twice in '{}'",
+                    column.name, self.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `CREATE TABLE IF NOT EXISTS` statement for this table.
+    pub fn create_table_sql(&self) -> String {
+        let mut columns = vec![format!(
+            "{} TEXT PRIMARY KEY",
+            quote_ident(&self.key_column)
+        )];
+        columns.extend(
+            self.columns
+                .iter()
+                .map(|c| format!("{} {}", quote_ident(&c.name), c.sql_type.sql())),
+        );
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} ({})",
+            quote_ident(&self.name),
+            columns.join(", ")
+        )
+    }
+
+    /// Upsert statement taking the key as `$1` and the columns in order.
+    pub fn upsert_sql(&self) -> String {
+        let names: Vec<String> = std::iter::once(&self.key_column)
+            .chain(self.columns.iter().map(|c| &c.name))
+            .map(|n| quote_ident(n))
+            .collect();
+        let params: Vec<String> = (1..=names.len()).map(|i| format!("${}", i)).collect();
+        let updates: Vec<String> = names[1..]
+            .iter()
+            .map(|n| format!("{} = EXCLUDED.{}", n, n))
+            .collect();
+
+        format!(
+            "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT ({}) DO UPDATE SET {}",
+            quote_ident(&self.name),
+            names.join(", "),
+            params.join(", "),
+            names[0],
+            updates.join(", ")
+        )
+    }
+
+    /// Delete statement taking the key as `$1`.
+    pub fn delete_sql(&self) -> String {
+        format!(
+            "DELETE FROM {} WHERE {} = $1",
+            quote_ident(&self.name),
+            quote_ident(&self.key_column)
+        )
+    }
+
+    /// Build the row values of a document, in column order.
+    pub fn row(&self, doc: &AutoCommit) -> vudo_state::Result<Vec<SqlValue>> {
+        let fields = flatten_document(doc)?;
+        Ok(self
+            .columns
+            .iter()
+            .map(|c| {
+                fields
+                    .get(&c.name)
+                    .map(|v| c.sql_type.coerce(v))
+                    .unwrap_or(SqlValue::Null)
+            })
+            .collect())
+    }
+}
+
+/// Add columns for the properties of an object schema.
+fn add_properties(table: &mut TableSchema, prefix: &str, schema: &Value) {
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return;
+    };
+
+    for (name, property) in properties {
+        let column = format!("{}{}", prefix, name);
+        if property.get("properties").is_some() {
+            add_properties(table, &format!("{}_", column), property);
+        } else {
+            table.columns.push(ColumnSpec {
+                name: column,
+                sql_type: SqlType::from_json_schema(property),
+            });
+        }
+    }
+}
+
+/// Check that a name is a plain SQL identifier.
+fn validate_identifier(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 63
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(MirrorError::InvalidSchema(format!(
+            "'{}' is not a valid identifier",
+            name
+        )))
+    }
+}
+
+/// Quote an SQL identifier.
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Flatten a document into `_`-joined field names.
+pub fn flatten_document(doc: &AutoCommit) -> vudo_state::Result<BTreeMap<String, SqlValue>> {
+    let mut fields = BTreeMap::new();
+    flatten_map(doc, &ROOT, "", &mut fields)?;
+    Ok(fields)
+}
+
+fn flatten_map(
+    doc: &AutoCommit,
+    obj: &ObjId,
+    prefix: &str,
+    fields: &mut BTreeMap<String, SqlValue>,
+) -> vudo_state::Result<()> {
+    for key in doc.keys(obj) {
+        let name = format!("{}{}", prefix, key);
+        match doc.get(obj, key.as_str())? {
+            Some((automerge::Value::Object(ObjType::Map), child)) => {
+                flatten_map(doc, &child, &format!("{}_", name), fields)?;
+            }
+            Some((value, id)) => {
+                fields.insert(name, to_sql_value(doc, value, &id)?);
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+/// Convert a document value to a column value.
+fn to_sql_value(
+    doc: &AutoCommit,
+    value: automerge::Value<'_>,
+    id: &ObjId,
+) -> vudo_state::Result<SqlValue> {
+    Ok(match value {
+        automerge::Value::Scalar(scalar) => match scalar.as_ref() {
+            ScalarValue::Str(s) => SqlValue::Text(s.to_string()),
+            ScalarValue::Int(i) => SqlValue::Int(*i),
+            ScalarValue::Uint(u) => i64::try_from(*u)
+                .map(SqlValue::Int)
+                .unwrap_or(SqlValue::Float(*u as f64)),
+            ScalarValue::F64(f) => SqlValue::Float(*f),
+            ScalarValue::Boolean(b) => SqlValue::Bool(*b),
+            ScalarValue::Counter(c) => SqlValue::Int(i64::from(c)),
+            ScalarValue::Timestamp(t) => SqlValue::Int(*t),
+            ScalarValue::Null => SqlValue::Null,
+            other => SqlValue::Text(other.to_string()),
+        },
+        automerge::Value::Object(ObjType::Text) => SqlValue::Text(doc.text(id)?),
+        automerge::Value::Object(_) => SqlValue::Json(to_json(doc, id)?),
+    })
+}
+
+/// Convert a document object to JSON.
+fn to_json(doc: &AutoCommit, obj: &ObjId) -> vudo_state::Result<Value> {
+    let convert = |value: automerge::Value<'_>, id: ObjId| -> vudo_state::Result<Value> {
+        match value {
+            automerge::Value::Object(ObjType::Map | ObjType::Table | ObjType::List) => {
+                to_json(doc, &id)
+            }
+            other => Ok(to_sql_value(doc, other, &id)?.to_json()),
+        }
+    };
+
+    match doc.object_type(obj)? {
+        ObjType::List => {
+            let mut items = Vec::new();
+            for i in 0..doc.length(obj) {
+                if let Some((value, id)) = doc.get(obj, i)? {
+                    items.push(convert(value, id)?);
+                }
+            }
+            Ok(Value::Array(items))
+        }
+        ObjType::Text => Ok(Value::String(doc.text(obj)?)),
+        ObjType::Map | ObjType::Table => {
+            let mut map = serde_json::Map::new();
+            for key in doc.keys(obj) {
+                if let Some((value, id)) = doc.get(obj, key.as_str())? {
+                    map.insert(key.clone(), convert(value, id)?);
+                }
+            }
+            Ok(Value::Object(map))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use automerge::transaction::Transactable;
+    use serde_json::json;
+
+    #[test]
+    fn test_from_json_schema() {
+        let schema = json!({
+            "definitions": {
+                "UserProfile": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "age": { "type": "integer", "minimum": 0 },
+                        "score": { "oneOf": [{ "type": "number" }, { "type": "null" }] },
+                        "tags": { "type": "array", "items": { "type": "string" } },
+                        "address": {
+                            "type": "object",
+                            "properties": { "city": { "type": "string" } }
+                        }
+                    }
+                }
+            }
+        });
+
+        let table = TableSchema::from_json_schema("users", &schema, Some("UserProfile")).unwrap();
+        let columns: BTreeMap<_, _> = table
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.sql_type))
+            .collect();
+        assert_eq!(columns["age"], SqlType::BigInt);
+        assert_eq!(columns["score"], SqlType::Double);
+        assert_eq!(columns["tags"], SqlType::Jsonb);
+        assert_eq!(columns["address_city"], SqlType::Text);
+
+        assert!(TableSchema::from_json_schema("users", &schema, Some("Missing")).is_err());
+        assert!(TableSchema::from_json_schema("bad name", &schema, Some("UserProfile")).is_err());
+    }
+
+    #[test]
+    fn test_sql_statements() {
+        let table = TableSchema::new("users")
+            .with_column("name", SqlType::Text)
+            .with_column("age", SqlType::BigInt);
+
+        assert_eq!(
+            table.create_table_sql(),
+            r#"CREATE TABLE IF NOT EXISTS "users" ("doc_key" TEXT PRIMARY KEY, "name" TEXT, "age" BIGINT)"#
+        );
+        assert_eq!(
+            table.upsert_sql(),
+            r#"INSERT INTO "users" ("doc_key", "name", "age") VALUES ($1, $2, $3) ON CONFLICT ("doc_key") DO UPDATE SET "name" = EXCLUDED."name", "age" = EXCLUDED."age""#
+        );
+        assert_eq!(
+            table.delete_sql(),
+            r#"DELETE FROM "users" WHERE "doc_key" = $1"#
+        );
+    }
+
+    #[test]
+    fn test_flatten_document() {
+        let mut doc = AutoCommit::new();
+        doc.put(ROOT, "name", "Alice").unwrap();
+        doc.put(ROOT, "age", 30i64).unwrap();
+        let address = doc.put_object(ROOT, "address", ObjType::Map).unwrap();
+        doc.put(&address, "city", "Lisbon").unwrap();
+        let tags = doc.put_object(ROOT, "tags", ObjType::List).unwrap();
+        doc.insert(&tags, 0, "admin").unwrap();
+
+        let table = TableSchema::new("users")
+            .with_column("name", SqlType::Text)
+            .with_column("age", SqlType::Double)
+            .with_column("address_city", SqlType::Text)
+            .with_column("tags", SqlType::Jsonb)
+            .with_column("missing", SqlType::Boolean);
+
+        assert_eq!(
+            table.row(&doc).unwrap(),
+            vec![
+                SqlValue::Text("Alice".to_string()),
+                SqlValue::Float(30.0),
+                SqlValue::Text("Lisbon".to_string()),
+                SqlValue::Json(json!(["admin"])),
+                SqlValue::Null,
+            ]
+        );
+    }
+}
@@ -0,0 +1,328 @@
+//! Sinks applying row changes with exactly-once semantics.
+//!
+//! Every applied row records the document version it was built from in an
+//! offset table, in the same transaction as the row change. A change whose
+//! version is already recorded is skipped, so replayed events and restarts
+//! never apply the same document version twice.
+
+use crate::error::Result;
+use crate::schema::{SqlValue, TableSchema};
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+
+/// Default name of the offset table.
+pub const DEFAULT_OFFSET_TABLE: &str = "vudo_mirror_offsets";
+
+/// Change to one mirrored row.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowChange {
+    /// Insert or update the row with values in column order.
+    Upsert(Vec<SqlValue>),
+    /// Delete the row.
+    Delete,
+}
+
+/// Destination of mirrored rows.
+#[async_trait]
+pub trait MirrorSink: Send + Sync {
+    /// Create the tables and the offset table if they do not exist.
+    async fn ensure_schema(&self, tables: &[TableSchema]) -> Result<()>;
+
+    /// Get the document version last applied to a row.
+    async fn offset(&self, table: &TableSchema, key: &str) -> Result<Option<String>>;
+
+    /// Apply a row change and record its version atomically.
+    ///
+    /// Returns `false` if the version was already applied.
+    async fn apply(
+        &self,
+        table: &TableSchema,
+        key: &str,
+        change: &RowChange,
+        version: &str,
+    ) -> Result<bool>;
+}
+
+/// In-memory sink, for tests and dry runs.
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    /// Rows by table and key.
+    tables: parking_lot::Mutex<HashMap<String, BTreeMap<String, Vec<SqlValue>>>>,
+    /// Applied versions by table and key.
+    offsets: parking_lot::Mutex<HashMap<(String, String), String>>,
+}
+
+impl MemorySink {
+    /// Create an empty sink.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the rows of a table.
+    pub fn rows(&self, table: &str) -> BTreeMap<String, Vec<SqlValue>> {
+        self.tables.lock().get(table).cloned().unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl MirrorSink for MemorySink {
+    async fn ensure_schema(&self, tables: &[TableSchema]) -> Result<()> {
+        let mut rows = self.tables.lock();
+        for table in tables {
+            rows.entry(table.name.clone()).or_default();
+        }
+        Ok(())
+    }
+
+    async fn offset(&self, table: &TableSchema, key: &str) -> Result<Option<String>> {
+        Ok(self
+            .offsets
+            .lock()
+            .get(&(table.name.clone(), key.to_string()))
+            .cloned())
+    }
+
+    async fn apply(
+        &self,
+        table: &TableSchema,
+        key: &str,
+        change: &RowChange,
+        version: &str,
+    ) -> Result<bool> {
+        let mut tables = self.tables.lock();
+        let mut offsets = self.offsets.lock();
+        let offset_key = (table.name.clone(), key.to_string());
+        let rows = tables.entry(table.name.clone()).or_default();
+
+        match change {
+            RowChange::Upsert(values) => {
+                if offsets.get(&offset_key).map(String::as_str) == Some(version) {
+                    return Ok(false);
+                }
+                rows.insert(key.to_string(), values.clone());
+                offsets.insert(offset_key, version.to_string());
+            }
+            RowChange::Delete => {
+                let existed = rows.remove(key).is_some();
+                offsets.remove(&offset_key);
+                if !existed {
+                    return Ok(false);
+                }
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresSink;
+
+#[cfg(feature = "postgres")]
+mod postgres {
+    use super::*;
+    use crate::schema::{quote_ident, SqlType};
+    use tokio_postgres::types::ToSql;
+    use tokio_postgres::{Client, NoTls};
+    use tracing::error;
+
+    /// PostgreSQL sink.
+    pub struct PostgresSink {
+        /// Client, locked for the duration of each transaction.
+        client: tokio::sync::Mutex<Client>,
+        /// Offset table name.
+        offset_table: String,
+    }
+
+    impl PostgresSink {
+        /// Create a sink over a connected client.
+        pub fn new(client: Client) -> Self {
+            Self {
+                client: tokio::sync::Mutex::new(client),
+                offset_table: DEFAULT_OFFSET_TABLE.to_string(),
+            }
+        }
+
+        /// Connect without TLS (e.g. `host=localhost user=postgres dbname=app`).
+        ///
+        /// The connection is driven by a spawned task.
+        pub async fn connect(config: &str) -> Result<Self> {
+            let (client, connection) = tokio_postgres::connect(config, NoTls).await?;
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    error!("PostgreSQL mirror connection failed: {}", e);
+                }
+            });
+            Ok(Self::new(client))
+        }
+
+        /// Set the offset table name.
+        pub fn with_offset_table(mut self, name: impl Into<String>) -> Self {
+            self.offset_table = name.into();
+            self
+        }
+    }
+
+    /// Convert a column value to a typed statement parameter.
+    fn param(value: &SqlValue, sql_type: SqlType) -> Box<dyn ToSql + Sync + Send> {
+        match (sql_type, value) {
+            (SqlType::Text, SqlValue::Text(s)) => Box::new(Some(s.clone())),
+            (SqlType::BigInt, SqlValue::Int(i)) => Box::new(Some(*i)),
+            (SqlType::Double, SqlValue::Float(f)) => Box::new(Some(*f)),
+            (SqlType::Boolean, SqlValue::Bool(b)) => Box::new(Some(*b)),
+            (SqlType::Jsonb, SqlValue::Json(json)) => Box::new(Some(json.clone())),
+            (SqlType::Text, _) => Box::new(None::<String>),
+            (SqlType::BigInt, _) => Box::new(None::<i64>),
+            (SqlType::Double, _) => Box::new(None::<f64>),
+            (SqlType::Boolean, _) => Box::new(None::<bool>),
+            (SqlType::Jsonb, _) => Box::new(None::<serde_json::Value>),
+        }
+    }
+
+    #[async_trait]
+    impl MirrorSink for PostgresSink {
+        async fn ensure_schema(&self, tables: &[TableSchema]) -> Result<()> {
+            let client = self.client.lock().await;
+            client
+                .batch_execute(&format!(
+                    "CREATE TABLE IF NOT EXISTS {} (\
+                     table_name TEXT NOT NULL, \
+                     doc_key TEXT NOT NULL, \
+                     version TEXT NOT NULL, \
+                     applied_at TIMESTAMPTZ NOT NULL DEFAULT now(), \
+                     PRIMARY KEY (table_name, doc_key))",
+                    quote_ident(&self.offset_table)
+                ))
+                .await?;
+            for table in tables {
+                client.batch_execute(&table.create_table_sql()).await?;
+            }
+            Ok(())
+        }
+
+        async fn offset(&self, table: &TableSchema, key: &str) -> Result<Option<String>> {
+            let client = self.client.lock().await;
+            let row = client
+                .query_opt(
+                    &format!(
+                        "SELECT version FROM {} WHERE table_name = $1 AND doc_key = $2",
+                        quote_ident(&self.offset_table)
+                    ),
+                    &[&table.name, &key],
+                )
+                .await?;
+            Ok(row.map(|r| r.get(0)))
+        }
+
+        async fn apply(
+            &self,
+            table: &TableSchema,
+            key: &str,
+            change: &RowChange,
+            version: &str,
+        ) -> Result<bool> {
+            let offset_table = quote_ident(&self.offset_table);
+            let mut client = self.client.lock().await;
+            let tx = client.transaction().await?;
+
+            let applied: Option<String> = tx
+                .query_opt(
+                    &format!(
+                        "SELECT version FROM {} WHERE table_name = $1 AND doc_key = $2 FOR UPDATE",
+                        offset_table
+                    ),
+                    &[&table.name, &key],
+                )
+                .await?
+                .map(|r| r.get(0));
+
+            match change {
+                RowChange::Upsert(values) => {
+                    if applied.as_deref() == Some(version) {
+                        tx.commit().await?;
+                        return Ok(false);
+                    }
+
+                    let mut params: Vec<Box<dyn ToSql + Sync + Send>> =
+                        vec![Box::new(key.to_string())];
+                    params.extend(
+                        table
+                            .columns
+                            .iter()
+                            .zip(values)
+                            .map(|(column, value)| param(value, column.sql_type)),
+                    );
+                    let refs: Vec<&(dyn ToSql + Sync)> = params
+                        .iter()
+                        .map(|p| p.as_ref() as &(dyn ToSql + Sync))
+                        .collect();
+                    tx.execute(&table.upsert_sql(), &refs).await?;
+
+                    tx.execute(
+                        &format!(
+                            "INSERT INTO {} (table_name, doc_key, version) VALUES ($1, $2, $3) \
+                             ON CONFLICT (table_name, doc_key) \
+                             DO UPDATE SET version = EXCLUDED.version, applied_at = now()",
+                            offset_table
+                        ),
+                        &[&table.name, &key, &version],
+                    )
+                    .await?;
+                }
+                RowChange::Delete => {
+                    let deleted = tx.execute(&table.delete_sql(), &[&key]).await?;
+                    tx.execute(
+                        &format!(
+                            "DELETE FROM {} WHERE table_name = $1 AND doc_key = $2",
+                            offset_table
+                        ),
+                        &[&table.name, &key],
+                    )
+                    .await?;
+                    if deleted == 0 {
+                        tx.commit().await?;
+                        return Ok(false);
+                    }
+                }
+            }
+
+            tx.commit().await?;
+            Ok(true)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::SqlType;
+
+    #[tokio::test]
+    async fn test_memory_sink_exactly_once() {
+        let sink = MemorySink::new();
+        let table = TableSchema::new("users").with_column("name", SqlType::Text);
+        sink.ensure_schema(std::slice::from_ref(&table))
+            .await
+            .unwrap();
+
+        let row = RowChange::Upsert(vec![SqlValue::Text("Alice".to_string())]);
+        assert!(sink.apply(&table, "alice", &row, "v1").await.unwrap());
+        assert!(!sink.apply(&table, "alice", &row, "v1").await.unwrap());
+        assert!(sink.apply(&table, "alice", &row, "v2").await.unwrap());
+        assert_eq!(
+            sink.offset(&table, "alice").await.unwrap().as_deref(),
+            Some("v2")
+        );
+
+        assert!(sink
+            .apply(&table, "alice", &RowChange::Delete, "")
+            .await
+            .unwrap());
+        assert!(!sink
+            .apply(&table, "alice", &RowChange::Delete, "")
+            .await
+            .unwrap());
+        assert!(sink.rows("users").is_empty());
+        assert_eq!(sink.offset(&table, "alice").await.unwrap(), None);
+    }
+}
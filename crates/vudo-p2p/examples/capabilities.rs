@@ -5,7 +5,7 @@
 
 use ed25519_dalek::SigningKey;
 use vudo_p2p::{
-    meadowcap::{Capability, Permission},
+    meadowcap::{Area, Capability, CapabilityStore, Permission},
     willow_types::{NamespaceId, Path, SubspaceId},
 };
 
@@ -31,10 +31,10 @@ fn main() {
 
     let users_cap = root_cap
         .delegate(
-            Some(subspace_id),
-            Path::empty(),
+            users_admin_key.verifying_key(),
+            Area::subspace(subspace_id),
             Permission::Write,
-            &users_admin_key,
+            &signing_key,
         )
         .unwrap();
 
@@ -48,8 +48,14 @@ fn main() {
     let alice_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let alice_path = Path::from_components(["alice"]);
 
+    // Signed by the users admin, who received the parent capability
     let alice_cap = users_cap
-        .delegate(Some(subspace_id), alice_path.clone(), Permission::Read, &alice_key)
+        .delegate(
+            alice_key.verifying_key(),
+            Area::new(Some(subspace_id), alice_path),
+            Permission::Read,
+            &users_admin_key,
+        )
         .unwrap();
 
     println!("   Subspace: users");
@@ -96,6 +102,20 @@ fn main() {
     println!("   Root capability valid? {}", root_cap.verify().is_ok());
     println!("   Users capability valid? {}", users_cap.verify().is_ok());
     println!("   Alice capability valid? {}", alice_cap.verify().is_ok());
+    println!();
+
+    // Check a received capability against the namespace owner
+    println!("7. Verifying the Chain Against a Trusted Root\n");
+
+    let store = CapabilityStore::new();
+    store.add(root_cap.clone()).unwrap();
+    let other_key = SigningKey::generate(&mut rand::rngs::OsRng);
+    let other_root = Capability::new_root(namespace_id, &other_key);
+
+    let trusted = store.verify_chain(&alice_cap).is_ok();
+    println!("   Alice capability trusted? {}", trusted);
+    let trusted = store.verify_chain(&other_root).is_ok();
+    println!("   Self-made root trusted? {}", trusted);
 
     println!("\n=== Capability Delegation Complete ===");
 }
@@ -22,6 +22,7 @@ async fn main() -> vudo_p2p::error::Result<()> {
     let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let namespace_id = adapter.map_namespace("myapp.v1");
     let root_cap = Capability::new_root(namespace_id, &signing_key);
+    adapter.capabilities().add(root_cap.clone())?;
 
     println!("1. Creating User Document\n");
 
@@ -46,7 +47,7 @@ async fn main() -> vudo_p2p::error::Result<()> {
     println!("2. Syncing to Willow Network\n");
 
     adapter
-        .sync_from_state_engine("myapp.v1", "users", "alice", &root_cap)
+        .sync_from_state_engine("myapp.v1", "users", "alice", &root_cap, &signing_key)
         .await?;
 
     let stats_before = adapter.stats();
@@ -75,6 +76,7 @@ async fn main() -> vudo_p2p::error::Result<()> {
             "users",
             "alice",
             &root_cap,
+            &signing_key,
             "User requested data deletion under GDPR Article 17 (Right to Erasure)",
         )
         .await?;
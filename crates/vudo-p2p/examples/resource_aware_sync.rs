@@ -21,6 +21,7 @@ async fn main() -> vudo_p2p::error::Result<()> {
     let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let namespace_id = adapter.map_namespace("myapp.v1");
     let root_cap = Capability::new_root(namespace_id, &signing_key);
+    adapter.capabilities().add(root_cap.clone())?;
 
     println!("1. Creating Sample Documents\n");
 
@@ -58,7 +59,13 @@ async fn main() -> vudo_p2p::error::Result<()> {
 
     let start = std::time::Instant::now();
     let stats = adapter
-        .sync_with_constraints("myapp.v1", "users", &root_cap, high_priority_constraints)
+        .sync_with_constraints(
+            "myapp.v1",
+            "users",
+            &root_cap,
+            &signing_key,
+            high_priority_constraints,
+        )
         .await?;
     let duration = start.elapsed();
 
@@ -88,7 +95,13 @@ async fn main() -> vudo_p2p::error::Result<()> {
 
     let start = std::time::Instant::now();
     let stats = adapter
-        .sync_with_constraints("myapp.v1", "users", &root_cap, low_priority_constraints)
+        .sync_with_constraints(
+            "myapp.v1",
+            "users",
+            &root_cap,
+            &signing_key,
+            low_priority_constraints,
+        )
         .await?;
     let duration = start.elapsed();
 
@@ -118,7 +131,13 @@ async fn main() -> vudo_p2p::error::Result<()> {
 
     let start = std::time::Instant::now();
     let stats = adapter
-        .sync_with_constraints("myapp.v1", "users", &root_cap, medium_priority_constraints)
+        .sync_with_constraints(
+            "myapp.v1",
+            "users",
+            &root_cap,
+            &signing_key,
+            medium_priority_constraints,
+        )
         .await?;
     let duration = start.elapsed();
 
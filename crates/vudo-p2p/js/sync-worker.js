@@ -0,0 +1,20 @@
+// Ticks vudo-p2p background sync from a dedicated worker.
+//
+// Timers on a page's main thread are throttled while its tab is in the
+// background; a worker's keep running. The page posts the sync interval in
+// milliseconds (0 stops the ticks) and runs a sync pass on every "tick".
+// Serve this file next to the app and set
+// `BackgroundSyncConfig::worker_script` to its URL.
+
+let timer = null;
+
+self.onmessage = (event) => {
+  clearInterval(timer);
+  timer = null;
+
+  const interval = Number(event.data);
+  if (interval > 0) {
+    self.postMessage("tick");
+    timer = setInterval(() => self.postMessage("tick"), interval);
+  }
+};
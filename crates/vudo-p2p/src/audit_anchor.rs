@@ -0,0 +1,111 @@
+//! Anchoring of GDPR audit log checkpoints over gossip.
+//!
+//! A vudo-privacy [`DeletionAuditLog`](vudo_privacy::audit::DeletionAuditLog)
+//! signing checkpoints passes each one to its [`AuditAnchor`].
+//! [`GossipAuditAnchor`] publishes them to the [`AUDIT_ANCHOR_TOPIC`]
+//! application topic, so peers and auditors subscribed to it hold a copy of
+//! every checkpoint: from then on the controller can't rewrite its log up to
+//! the checkpoint without it showing (see
+//! [`verify_checkpoint`](vudo_privacy::audit::verify_checkpoint)).
+
+use crate::error::{P2PError, Result};
+use crate::gossip::{GossipOverlay, Topic};
+use crate::sync_protocol::PeerId;
+use std::sync::Arc;
+use tokio::runtime::Handle;
+use tracing::warn;
+use vudo_privacy::audit::{AuditAnchor, AuditCheckpoint};
+
+/// Name of the application topic audit checkpoints are published to.
+pub const AUDIT_ANCHOR_TOPIC: &str = "audit-anchors";
+
+/// Anchor publishing audit checkpoints to a gossip topic.
+pub struct GossipAuditAnchor {
+    /// Gossip overlay.
+    gossip: Arc<GossipOverlay>,
+
+    /// Publishing peer.
+    peer_id: PeerId,
+
+    /// Runtime publishing checkpoints (anchoring is synchronous).
+    runtime: Handle,
+}
+
+impl GossipAuditAnchor {
+    /// Create an anchor publishing as `peer_id`.
+    ///
+    /// Must be called from a tokio runtime, which publishes the checkpoints.
+    pub fn new(gossip: Arc<GossipOverlay>, peer_id: PeerId) -> Result<Self> {
+        let runtime = Handle::try_current()
+            .map_err(|e| P2PError::Internal(format!("No tokio runtime: {}", e)))?;
+        Ok(Self {
+            gossip,
+            peer_id,
+            runtime,
+        })
+    }
+
+    /// Get the topic checkpoints are published to.
+    pub fn topic() -> Topic {
+        Topic::app(AUDIT_ANCHOR_TOPIC)
+    }
+
+    /// Decode a checkpoint received on the topic.
+    pub fn decode(payload: &[u8]) -> Result<AuditCheckpoint> {
+        serde_json::from_slice(payload).map_err(|e| P2PError::DeserializationError(e.to_string()))
+    }
+}
+
+impl AuditAnchor for GossipAuditAnchor {
+    fn anchor(&self, checkpoint: &AuditCheckpoint) -> vudo_privacy::Result<()> {
+        let payload = serde_json::to_vec(checkpoint)?;
+        let gossip = Arc::clone(&self.gossip);
+        let peer_id = self.peer_id.clone();
+        let sequence = checkpoint.sequence;
+        self.runtime.spawn(async move {
+            if let Err(e) = gossip
+                .publish_application(peer_id, Self::topic(), payload)
+                .await
+            {
+                warn!("Failed to publish audit checkpoint {}: {}", sequence, e);
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+    use vudo_privacy::audit::{verify_checkpoint, DataCategory, DeletionAuditLog, DeletionMethod};
+
+    #[tokio::test]
+    async fn test_anchor_checkpoints() {
+        let gossip = Arc::new(GossipOverlay::new());
+        let mut sub = gossip
+            .subscribe_application(GossipAuditAnchor::topic())
+            .await
+            .unwrap();
+        let anchor = GossipAuditAnchor::new(Arc::clone(&gossip), "peer-1".to_string()).unwrap();
+
+        let controller = SigningKey::from_bytes(&[7; 32]);
+        let audit_log = DeletionAuditLog::new()
+            .with_checkpoints(controller.clone(), 1)
+            .with_anchor(Arc::new(anchor));
+        audit_log.record_deletion(
+            "did:peer:alice",
+            vec![DataCategory::PersonalData],
+            DeletionMethod::CryptographicErasure,
+            None,
+        );
+
+        let message = sub.recv().await.unwrap();
+        assert_eq!(message.peer_id, "peer-1");
+        let checkpoint = GossipAuditAnchor::decode(&message.payload).unwrap();
+        assert_eq!(checkpoint, audit_log.checkpoints()[0]);
+
+        let json = audit_log.export_json().unwrap();
+        verify_checkpoint(&json, &checkpoint, &controller.verifying_key()).unwrap();
+    }
+}
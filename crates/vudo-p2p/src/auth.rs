@@ -0,0 +1,472 @@
+//! UCAN-authenticated sync sessions.
+//!
+//! With a [`SyncAuthPolicy`] configured, a peer has to present a session
+//! token before documents are exchanged with it. The token is a UCAN signed
+//! by the peer's device DID, carrying the device's delegation chain as
+//! proof and bound to the connection (the presenting and the receiving
+//! node IDs), so it can't be replayed from another node. It is accepted
+//! when the chain is rooted in one of the policy's trusted issuers, usually
+//! the master identity that linked the device.
+//!
+//! The capabilities of an authenticated peer scope what it may do: sync
+//! requests need [`READ`] and incoming changes need [`WRITE`] on the
+//! document resource (`vudo://<namespace>/<id>`), matched with the usual
+//! resource globs such as `vudo://users/*`.
+//!
+//! Publishing to an application topic with restricted publishers needs a
+//! publish token instead: a similar UCAN granting [`PUBLISH`] on the topic
+//! resource (`vudo-topic://<topic>`), bound to the publishing node and the
+//! topic, since gossip also reaches peers the node isn't connected to.
+//!
+//! Deleting a document on a node requiring authentication needs a deletion
+//! token: a UCAN of the document's owner granting [`WRITE`] on the document,
+//! bound to it. It is passed on with the deletion, so it lives for
+//! [`DELETION_TTL`] rather than a session.
+//!
+//! Tokens whose chain contains a UCAN revoked by its issuer, or an issuer
+//! above it, are refused once the revocation is in the policy's
+//! [`RevocationStore`].
+//!
+//! Independently of sessions, a node can challenge a connected peer to prove
+//! it holds a DID, e.g. the one its presence heartbeats name. The
+//! [`AuthChallenge`](vudo_identity::AuthChallenge) is bound to the
+//! connection with [`connection_binding`], so a peer can't pass on another
+//! connection's challenge to a device it doesn't control.
+
+use crate::error::{P2PError, Result};
+use crate::sync_protocol::PeerId;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use vudo_identity::{Capability, DeviceIdentity, Did, RevocationStore, Ucan};
+
+/// Action needed to request a document from this node.
+pub const READ: &str = "read";
+
+/// Action needed to send document changes to this node.
+pub const WRITE: &str = "write";
+
+/// Action needed to publish to a restricted application topic.
+pub const PUBLISH: &str = "publish";
+
+/// Lifetime of a session token (seconds).
+pub const SESSION_TTL: u64 = 60 * 60;
+
+/// Lifetime of a deletion token (seconds), how long peers that were offline
+/// can still learn of the deletion.
+pub const DELETION_TTL: u64 = 90 * 24 * 60 * 60;
+
+/// UCAN resource for a document.
+pub fn document_resource(namespace: &str, id: &str) -> String {
+    format!("vudo://{}/{}", namespace, id)
+}
+
+/// UCAN resource for an application topic.
+pub fn topic_resource(topic: &str) -> String {
+    format!("vudo-topic://{}", topic)
+}
+
+/// Channel a challenge from `verifier` to `prover` is bound to.
+pub fn connection_binding(verifier: &PeerId, prover: &PeerId) -> String {
+    format!("iroh:{}>{}", verifier, prover)
+}
+
+/// Create a session token presenting a device to a peer.
+///
+/// The token claims the capabilities of the device's authorization, with
+/// the authorization as proof, and expires with it or after
+/// [`SESSION_TTL`]. An unlinked device claims everything under `vudo://`,
+/// which peers only accept if they trust the device's own DID.
+pub fn session_token(device: &DeviceIdentity, local: &PeerId, peer: &PeerId) -> Result<String> {
+    device_token(
+        device,
+        "vudo://",
+        json!({ "sync": { "from": local, "to": peer } }),
+        SESSION_TTL,
+    )
+}
+
+/// Create a publish token letting a node publish to a topic as a device.
+///
+/// Like a session token, but bound to the publishing node and the topic,
+/// and an unlinked device claims every topic.
+pub fn publish_token(device: &DeviceIdentity, local: &PeerId, topic: &str) -> Result<String> {
+    device_token(
+        device,
+        "vudo-topic://",
+        json!({ "publish": { "from": local, "topic": topic } }),
+        SESSION_TTL,
+    )
+}
+
+/// Create a deletion token letting a device delete a document it owns.
+///
+/// Like a session token, but bound to the document and valid for
+/// [`DELETION_TTL`]. The device's DID has to be the owner named in the
+/// deletion receipt.
+pub fn deletion_token(device: &DeviceIdentity, namespace: &str, id: &str) -> Result<String> {
+    device_token(
+        device,
+        "vudo://",
+        json!({ "delete": { "namespace": namespace, "id": id } }),
+        DELETION_TTL,
+    )
+}
+
+/// Sign a token presenting a device, bound to its use by `facts` and
+/// living at most `ttl` seconds.
+fn device_token(
+    device: &DeviceIdentity,
+    unlinked_scope: &str,
+    facts: Value,
+    ttl: u64,
+) -> Result<String> {
+    let now = now_secs();
+    let (capabilities, expires_at, proofs) = match &device.authorization {
+        Some(authorization) => (
+            authorization.att.clone(),
+            authorization.exp.min(now + ttl),
+            vec![authorization.encode()?],
+        ),
+        None => (
+            vec![Capability::wildcard(unlinked_scope)],
+            now + ttl,
+            Vec::new(),
+        ),
+    };
+
+    let token = Ucan::new(
+        device.did().clone(),
+        device.did().clone(),
+        capabilities,
+        expires_at,
+        None,
+        None,
+        proofs,
+    )
+    .with_facts(facts)
+    .sign(&device.signing_key())?;
+    Ok(token.encode()?)
+}
+
+/// Who may sync with this node.
+#[derive(Debug, Clone, Default)]
+pub struct SyncAuthPolicy {
+    /// DIDs whose delegations are accepted, directly or through a chain.
+    pub trusted_issuers: Vec<Did>,
+    /// Revoked UCANs, checked on every token.
+    pub revocations: Option<Arc<RevocationStore>>,
+}
+
+impl SyncAuthPolicy {
+    /// Create a policy trusting no one.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept peers whose capabilities were delegated by `did`.
+    pub fn trust(mut self, did: Did) -> Self {
+        self.trusted_issuers.push(did);
+        self
+    }
+
+    /// Refuse tokens carrying UCANs revoked in `store`.
+    pub fn with_revocations(mut self, store: Arc<RevocationStore>) -> Self {
+        self.revocations = Some(store);
+        self
+    }
+
+    /// Verify a session token presented by `peer` to `local`.
+    pub fn verify(&self, token: &str, peer: &PeerId, local: &PeerId) -> Result<PeerAuth> {
+        self.verify_bound(token, "sync", "this connection", |sync| {
+            sync["from"].as_str() == Some(peer.as_str())
+                && sync["to"].as_str() == Some(local.as_str())
+        })
+    }
+
+    /// Verify a publish token attached by `peer` to a message on `topic`.
+    ///
+    /// Fails unless the token also grants [`PUBLISH`] on the topic.
+    pub fn verify_publish(&self, token: &str, peer: &PeerId, topic: &str) -> Result<PeerAuth> {
+        let auth = self.verify_bound(token, "publish", "this topic", |publish| {
+            publish["from"].as_str() == Some(peer.as_str())
+                && publish["topic"].as_str() == Some(topic)
+        })?;
+        if !auth.may_publish(topic) {
+            return Err(P2PError::PermissionDenied(format!(
+                "{} may not publish to {}",
+                auth.did, topic
+            )));
+        }
+        Ok(auth)
+    }
+
+    /// Verify the deletion token of `owner` for a document.
+    ///
+    /// Fails unless the token was issued by the owner and grants [`WRITE`]
+    /// on the document.
+    pub fn verify_deletion(
+        &self,
+        token: &str,
+        owner: &str,
+        namespace: &str,
+        id: &str,
+    ) -> Result<PeerAuth> {
+        let auth = self.verify_bound(token, "delete", "this document", |delete| {
+            delete["namespace"].as_str() == Some(namespace) && delete["id"].as_str() == Some(id)
+        })?;
+        if auth.did.to_string() != owner {
+            return Err(P2PError::PermissionDenied(format!(
+                "Deletion token of {} was issued by {}",
+                owner, auth.did
+            )));
+        }
+        if !auth.allows(namespace, id, WRITE) {
+            return Err(P2PError::PermissionDenied(format!(
+                "{} may not write {}",
+                auth.did,
+                document_resource(namespace, id)
+            )));
+        }
+        Ok(auth)
+    }
+
+    /// Verify a token signed by the presenting device, whose `fact` is
+    /// accepted by `bound`.
+    fn verify_bound(
+        &self,
+        token: &str,
+        fact: &str,
+        purpose: &str,
+        bound: impl FnOnce(&Value) -> bool,
+    ) -> Result<PeerAuth> {
+        let ucan = Ucan::decode(token)?;
+        ucan.verify()?;
+        if let Some(store) = &self.revocations {
+            store.check(&ucan)?;
+        }
+
+        if ucan.iss != ucan.aud {
+            return Err(P2PError::PermissionDenied(
+                "Token must be issued by the presenting device".to_string(),
+            ));
+        }
+        if !ucan.fct.as_ref().is_some_and(|facts| bound(&facts[fact])) {
+            return Err(P2PError::PermissionDenied(format!(
+                "Token of {} was not issued for {}",
+                ucan.iss, purpose
+            )));
+        }
+        if !self.is_trusted(&ucan)? {
+            return Err(P2PError::PermissionDenied(format!(
+                "{} is not authorized by a trusted issuer",
+                ucan.iss
+            )));
+        }
+
+        Ok(PeerAuth {
+            did: ucan.iss,
+            capabilities: ucan.att,
+            expires_at: ucan.exp,
+        })
+    }
+
+    /// Check whether a verified UCAN is rooted in a trusted issuer.
+    fn is_trusted(&self, ucan: &Ucan) -> Result<bool> {
+        if self.trusted_issuers.contains(&ucan.iss) {
+            return Ok(true);
+        }
+        for proof in &ucan.prf {
+            if self.is_trusted(&Ucan::decode(proof)?)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Identity and capabilities of an authenticated peer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerAuth {
+    /// Device DID of the peer.
+    pub did: Did,
+    /// Capabilities proven by the peer.
+    pub capabilities: Vec<Capability>,
+    /// Expiration of the session (Unix seconds).
+    pub expires_at: u64,
+}
+
+impl PeerAuth {
+    /// Check if the session has expired.
+    pub fn is_expired(&self) -> bool {
+        now_secs() > self.expires_at
+    }
+
+    /// Check if the peer may perform `action` on a document.
+    pub fn allows(&self, namespace: &str, id: &str, action: &str) -> bool {
+        let requested = Capability::new(document_resource(namespace, id), action);
+        self.capabilities.iter().any(|cap| cap.matches(&requested))
+    }
+
+    /// Check if the peer may publish to an application topic.
+    pub fn may_publish(&self, topic: &str) -> bool {
+        let requested = Capability::new(topic_resource(topic), PUBLISH);
+        self.capabilities.iter().any(|cap| cap.matches(&requested))
+    }
+}
+
+/// Get current time in seconds.
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vudo_identity::MasterIdentity;
+
+    async fn linked_device(
+        master: &MasterIdentity,
+        capabilities: Vec<Capability>,
+    ) -> DeviceIdentity {
+        let mut device = DeviceIdentity::generate("Phone").await.unwrap();
+        let authorization = Ucan::new(
+            master.did.clone(),
+            device.did().clone(),
+            capabilities,
+            now_secs() + 600,
+            None,
+            None,
+            vec![],
+        )
+        .sign(&master.signing_key())
+        .unwrap();
+        device.link_to_master(master.did.clone(), authorization);
+        device
+    }
+
+    #[tokio::test]
+    async fn test_session_token() {
+        let master = MasterIdentity::generate("Alice").await.unwrap();
+        let device = linked_device(&master, vec![Capability::new("vudo://users/*", READ)]).await;
+        let (phone, laptop) = ("phone".to_string(), "laptop".to_string());
+        let policy = SyncAuthPolicy::new().trust(master.did.clone());
+
+        let token = session_token(&device, &phone, &laptop).unwrap();
+        let auth = policy.verify(&token, &phone, &laptop).unwrap();
+        assert_eq!(auth.did, *device.did());
+        assert!(!auth.is_expired());
+        assert!(auth.allows("users", "alice", READ));
+        assert!(!auth.allows("users", "alice", WRITE));
+        assert!(!auth.allows("photos", "beach", READ));
+
+        // Bound to the connection
+        let other = "mallory".to_string();
+        assert!(policy.verify(&token, &other, &laptop).is_err());
+        assert!(policy.verify(&token, &phone, &other).is_err());
+
+        // Rooted in a trusted issuer
+        assert!(SyncAuthPolicy::new()
+            .verify(&token, &phone, &laptop)
+            .is_err());
+        let unlinked = DeviceIdentity::generate("Stranger").await.unwrap();
+        let token = session_token(&unlinked, &phone, &laptop).unwrap();
+        assert!(policy.verify(&token, &phone, &laptop).is_err());
+        let auth = SyncAuthPolicy::new()
+            .trust(unlinked.did().clone())
+            .verify(&token, &phone, &laptop)
+            .unwrap();
+        assert!(auth.allows("photos", "beach", WRITE));
+    }
+
+    #[tokio::test]
+    async fn test_publish_token() {
+        let master = MasterIdentity::generate("Alice").await.unwrap();
+        let cursors = topic_resource("app:cursors");
+        let device = linked_device(&master, vec![Capability::new(cursors, PUBLISH)]).await;
+        let phone = "phone".to_string();
+        let policy = SyncAuthPolicy::new().trust(master.did.clone());
+
+        let token = publish_token(&device, &phone, "app:cursors").unwrap();
+        let auth = policy
+            .verify_publish(&token, &phone, "app:cursors")
+            .unwrap();
+        assert_eq!(auth.did, *device.did());
+
+        // Bound to the publisher and the topic
+        assert!(policy
+            .verify_publish(&token, &"mallory".to_string(), "app:cursors")
+            .is_err());
+        assert!(policy.verify_publish(&token, &phone, "app:chat").is_err());
+        // Not a session token
+        assert!(policy
+            .verify(&token, &phone, &"laptop".to_string())
+            .is_err());
+
+        // Needs the publish capability on the topic
+        let token = publish_token(&device, &phone, "app:chat").unwrap();
+        assert!(policy.verify_publish(&token, &phone, "app:chat").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_deletion_token() {
+        let master = MasterIdentity::generate("Alice").await.unwrap();
+        let device = linked_device(&master, vec![Capability::new("vudo://users/*", WRITE)]).await;
+        let owner = device.did().to_string();
+        let policy = SyncAuthPolicy::new().trust(master.did.clone());
+
+        let token = deletion_token(&device, "users", "alice").unwrap();
+        let auth = policy
+            .verify_deletion(&token, &owner, "users", "alice")
+            .unwrap();
+        assert_eq!(auth.did, *device.did());
+
+        // Bound to the owner and the document
+        assert!(policy
+            .verify_deletion(&token, master.did.as_str(), "users", "alice")
+            .is_err());
+        assert!(policy
+            .verify_deletion(&token, &owner, "users", "bob")
+            .is_err());
+        // Not a session token
+        assert!(policy
+            .verify(&token, &"phone".to_string(), &"laptop".to_string())
+            .is_err());
+
+        // Needs write access to the document
+        let reader = linked_device(&master, vec![Capability::new("vudo://users/*", READ)]).await;
+        let token = deletion_token(&reader, "users", "alice").unwrap();
+        assert!(policy
+            .verify_deletion(&token, reader.did().as_str(), "users", "alice")
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_revoked_authorization() {
+        let master = MasterIdentity::generate("Alice").await.unwrap();
+        let device = linked_device(&master, vec![Capability::new("vudo://users/*", READ)]).await;
+        let (phone, laptop) = ("phone".to_string(), "laptop".to_string());
+        let store = Arc::new(RevocationStore::new());
+        let policy = SyncAuthPolicy::new()
+            .trust(master.did.clone())
+            .with_revocations(Arc::clone(&store));
+
+        let token = session_token(&device, &phone, &laptop).unwrap();
+        assert!(policy.verify(&token, &phone, &laptop).is_ok());
+
+        let authorization = device.authorization.as_ref().unwrap();
+        let revocation = vudo_identity::UcanRevocation::new(
+            authorization,
+            master.did.clone(),
+            Some("Lost phone".to_string()),
+            &master.signing_key(),
+        )
+        .unwrap();
+        store.publish(revocation).unwrap();
+        assert!(policy.verify(&token, &phone, &laptop).is_err());
+    }
+}
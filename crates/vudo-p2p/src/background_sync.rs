@@ -1,7 +1,11 @@
 //! Background sync task management.
 
-use crate::bandwidth::{BandwidthManager, SyncPriority, SyncTask};
+use crate::bandwidth::{BandwidthManager, SyncPriority, SyncTask, TrafficDirection};
 use crate::error::{P2PError, Result};
+use crate::supervisor::Heartbeat;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::supervisor::{Subsystem, Supervisor};
+use crate::sync_policy::{SkipReason, SyncPolicy};
 use crate::sync_protocol::{PeerId, SyncMessage, SyncProtocol};
 use parking_lot::RwLock;
 use std::collections::HashMap;
@@ -22,6 +26,9 @@ pub struct BackgroundSyncConfig {
     pub retry_backoff: Duration,
     /// Enable exponential backoff.
     pub exponential_backoff: bool,
+    /// URL of the worker script ticking background sync in browsers (see
+    /// `js/sync-worker.js`); a main-thread timer is used when `None`.
+    pub worker_script: Option<String>,
 }
 
 impl Default for BackgroundSyncConfig {
@@ -31,6 +38,7 @@ impl Default for BackgroundSyncConfig {
             max_retries: 3,
             retry_backoff: Duration::from_secs(5),
             exponential_backoff: true,
+            worker_script: None,
         }
     }
 }
@@ -46,7 +54,35 @@ struct SyncTaskState {
     last_attempt: Option<std::time::Instant>,
 }
 
+/// Check whether a task's peer, namespace and priority have bandwidth left
+/// in both directions.
+fn within_limits(bandwidth: &BandwidthManager, task: &SyncTask) -> bool {
+    [TrafficDirection::Upload, TrafficDirection::Download]
+        .into_iter()
+        .all(|direction| {
+            bandwidth.has_budget(
+                direction,
+                &task.peer_id,
+                Some(&task.namespace),
+                task.priority,
+            )
+        })
+}
+
+/// Check a task against the sync policy. Documents added to background sync
+/// count as subscribed.
+fn check_policy(
+    policy: &RwLock<SyncPolicy>,
+    task: &SyncTask,
+) -> std::result::Result<(), SkipReason> {
+    let size = (task.estimated_size > 0).then_some(task.estimated_size as u64);
+    policy
+        .read()
+        .check(&task.namespace, &task.doc_id, true, size)
+}
+
 /// Background sync manager.
+#[derive(Clone)]
 pub struct BackgroundSync {
     /// Configuration.
     config: BackgroundSyncConfig,
@@ -54,6 +90,8 @@ pub struct BackgroundSync {
     is_running: Arc<AtomicBool>,
     /// Bandwidth manager.
     bandwidth_manager: Arc<BandwidthManager>,
+    /// Selective sync policy.
+    policy: Arc<RwLock<SyncPolicy>>,
     /// Pending tasks.
     pending_tasks: Arc<RwLock<HashMap<String, SyncTaskState>>>, // Key: "peer_id:namespace:doc_id"
 }
@@ -65,10 +103,18 @@ impl BackgroundSync {
             config,
             is_running: Arc::new(AtomicBool::new(false)),
             bandwidth_manager,
+            policy: Arc::new(RwLock::new(SyncPolicy::default())),
             pending_tasks: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
+    /// Share a sync policy; tasks it rejects are deferred until it allows
+    /// them (e.g. back on Wi-Fi).
+    pub fn with_policy(mut self, policy: Arc<RwLock<SyncPolicy>>) -> Self {
+        self.policy = policy;
+        self
+    }
+
     /// Start background sync.
     pub fn start(&self) {
         if self.is_running.swap(true, Ordering::SeqCst) {
@@ -89,6 +135,23 @@ impl BackgroundSync {
         }
     }
 
+    /// Start background sync under a supervisor, which restarts the sync
+    /// loop if it panics or stalls.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn start_supervised(&self, supervisor: &Supervisor) {
+        if self.is_running.swap(true, Ordering::SeqCst) {
+            warn!("Background sync already running");
+            return;
+        }
+
+        info!("Starting supervised background sync");
+
+        let sync = self.clone();
+        supervisor.supervise(Subsystem::BackgroundSync, move |heartbeat| {
+            sync.clone().run(heartbeat)
+        });
+    }
+
     /// Stop background sync.
     pub fn stop(&self) {
         info!("Stopping background sync");
@@ -136,133 +199,153 @@ impl BackgroundSync {
     /// Spawn background sync task (native platforms).
     #[cfg(not(target_arch = "wasm32"))]
     fn spawn_task(&self) {
-        let is_running = self.is_running.clone();
-        let sync_interval = self.config.sync_interval;
-        let pending_tasks = self.pending_tasks.clone();
-        let bandwidth_manager = self.bandwidth_manager.clone();
-        let config = self.config.clone();
-
-        tokio::spawn(async move {
-            info!("Background sync task started");
-
-            while is_running.load(Ordering::SeqCst) {
-                // Process pending tasks
-                let tasks: Vec<(String, SyncTaskState)> = {
-                    let pending = pending_tasks.read();
-                    pending.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+        tokio::spawn(self.clone().run(Heartbeat::default()));
+    }
+
+    /// Background sync loop, running until stopped (native platforms).
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn run(self, heartbeat: Heartbeat) {
+        info!("Background sync task started");
+
+        while self.is_running.load(Ordering::SeqCst) {
+            self.process_pending(&heartbeat).await;
+
+            // Wait for next sync interval
+            tokio::time::sleep(self.config.sync_interval).await;
+        }
+
+        info!("Background sync task stopped");
+    }
+
+    /// Run one pass over the pending tasks, scheduling those that are due.
+    async fn process_pending(&self, heartbeat: &Heartbeat) {
+        let tasks: Vec<(String, SyncTaskState)> = {
+            let pending = self.pending_tasks.read();
+            pending
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect()
+        };
+
+        let _busy = heartbeat.busy();
+        for (key, mut state) in tasks {
+            // Check if we should retry this task
+            if let Some(last_attempt) = state.last_attempt {
+                let backoff = if self.config.exponential_backoff {
+                    self.config.retry_backoff * 2u32.pow(state.retry_count)
+                } else {
+                    self.config.retry_backoff
                 };
 
-                for (key, mut state) in tasks {
-                    // Check if we should retry this task
-                    if let Some(last_attempt) = state.last_attempt {
-                        let backoff = if config.exponential_backoff {
-                            config.retry_backoff * 2u32.pow(state.retry_count)
-                        } else {
-                            config.retry_backoff
-                        };
-
-                        if last_attempt.elapsed() < backoff {
-                            continue; // Too soon to retry
-                        }
-                    }
-
-                    // Check retry limit
-                    if state.retry_count >= config.max_retries {
-                        warn!("Max retries reached for task: {}", key);
-                        pending_tasks.write().remove(&key);
-                        continue;
-                    }
-
-                    // Schedule task
-                    debug!("Scheduling background sync task: {}", key);
-
-                    match bandwidth_manager.schedule_sync(state.task.clone()).await {
-                        Ok(_) => {
-                            // Update state
-                            state.last_attempt = Some(std::time::Instant::now());
-                            state.retry_count += 1;
-                            pending_tasks.write().insert(key.clone(), state);
-                        }
-                        Err(e) => {
-                            warn!("Failed to schedule task {}: {}", key, e);
-                        }
-                    }
+                if last_attempt.elapsed() < backoff {
+                    continue; // Too soon to retry
                 }
+            }
 
-                // Wait for next sync interval
-                tokio::time::sleep(sync_interval).await;
+            // Check retry limit
+            if state.retry_count >= self.config.max_retries {
+                warn!("Max retries reached for task: {}", key);
+                self.pending_tasks.write().remove(&key);
+                continue;
             }
 
-            info!("Background sync task stopped");
-        });
+            // Leave the task for a later pass while the policy rejects it
+            if let Err(reason) = check_policy(&self.policy, &state.task) {
+                debug!("Sync policy defers task {}: {}", key, reason);
+                continue;
+            }
+
+            // Leave the task for a later pass while its limits are used up
+            if !within_limits(&self.bandwidth_manager, &state.task) {
+                debug!("Bandwidth limit reached, deferring task: {}", key);
+                continue;
+            }
+
+            // Schedule task
+            debug!("Scheduling background sync task: {}", key);
+
+            match self
+                .bandwidth_manager
+                .schedule_sync(state.task.clone())
+                .await
+            {
+                Ok(_) => {
+                    // Update state
+                    state.last_attempt = Some(std::time::Instant::now());
+                    state.retry_count += 1;
+                    self.pending_tasks.write().insert(key.clone(), state);
+                }
+                Err(e) => {
+                    warn!("Failed to schedule task {}: {}", key, e);
+                }
+            }
+            heartbeat.beat();
+        }
     }
 
-    /// Spawn Web Worker for background sync (browser).
+    /// Run background sync in the browser.
+    ///
+    /// Timers on a page's main thread are throttled while its tab is in the
+    /// background, but a dedicated worker's are not. With
+    /// [`BackgroundSyncConfig::worker_script`] set, a worker running that
+    /// script (see `js/sync-worker.js`) is sent the sync interval and posts
+    /// a tick at that interval; each tick runs a pass over the pending
+    /// tasks. Without a script, or if the worker fails to start, passes run
+    /// on a main-thread timer.
     #[cfg(target_arch = "wasm32")]
     fn spawn_worker(&self) {
+        use wasm_bindgen::closure::Closure;
         use wasm_bindgen::JsCast;
         use wasm_bindgen_futures::spawn_local;
-        use web_sys::Worker;
-
-        let is_running = self.is_running.clone();
-        let sync_interval = self.config.sync_interval;
-        let pending_tasks = self.pending_tasks.clone();
-        let bandwidth_manager = self.bandwidth_manager.clone();
-        let config = self.config.clone();
-
-        spawn_local(async move {
-            info!("Background sync worker started (WASM)");
+        use web_sys::{MessageEvent, Worker};
 
-            // Note: In a real implementation, you'd create a Web Worker
-            // For now, we'll use a simple async loop
-
-            while is_running.load(Ordering::SeqCst) {
-                // Process pending tasks (same logic as native)
-                let tasks: Vec<(String, SyncTaskState)> = {
-                    let pending = pending_tasks.read();
-                    pending.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
-                };
-
-                for (key, mut state) in tasks {
-                    if let Some(last_attempt) = state.last_attempt {
-                        let backoff = if config.exponential_backoff {
-                            config.retry_backoff * 2u32.pow(state.retry_count)
-                        } else {
-                            config.retry_backoff
-                        };
-
-                        if last_attempt.elapsed() < backoff {
-                            continue;
-                        }
-                    }
-
-                    if state.retry_count >= config.max_retries {
-                        warn!("Max retries reached for task: {}", key);
-                        pending_tasks.write().remove(&key);
-                        continue;
-                    }
-
-                    debug!("Scheduling background sync task: {}", key);
-
-                    match bandwidth_manager.schedule_sync(state.task.clone()).await {
-                        Ok(_) => {
-                            state.last_attempt = Some(std::time::Instant::now());
-                            state.retry_count += 1;
-                            pending_tasks.write().insert(key.clone(), state);
-                        }
-                        Err(e) => {
-                            warn!("Failed to schedule task {}: {}", key, e);
-                        }
-                    }
+        let worker = self.config.worker_script.as_deref().and_then(|url| {
+            Worker::new(url)
+                .inspect_err(|e| warn!("Failed to start sync worker {}: {:?}", url, e))
+                .ok()
+        });
+        let Some(worker) = worker else {
+            let sync = self.clone();
+            spawn_local(async move {
+                info!("Background sync started on a timer (WASM)");
+                let heartbeat = Heartbeat::default();
+                while sync.is_running.load(Ordering::SeqCst) {
+                    sync.process_pending(&heartbeat).await;
+                    gloo_timers::future::sleep(sync.config.sync_interval).await;
                 }
+                info!("Background sync stopped (WASM)");
+            });
+            return;
+        };
 
-                // Sleep (using WASM-compatible sleep)
-                let millis = sync_interval.as_millis() as i32;
-                gloo_timers::future::sleep(Duration::from_millis(millis as u64)).await;
+        // Ticks arriving during a pass are skipped
+        let in_pass = Arc::new(AtomicBool::new(false));
+        let sync = self.clone();
+        let ticking = worker.clone();
+        let on_tick = Closure::<dyn FnMut(MessageEvent)>::new(move |_: MessageEvent| {
+            if !sync.is_running.load(Ordering::SeqCst) {
+                info!("Background sync worker stopped (WASM)");
+                ticking.terminate();
+                return;
             }
-
-            info!("Background sync worker stopped (WASM)");
+            if in_pass.swap(true, Ordering::SeqCst) {
+                return;
+            }
+            let (sync, in_pass) = (sync.clone(), Arc::clone(&in_pass));
+            spawn_local(async move {
+                sync.process_pending(&Heartbeat::default()).await;
+                in_pass.store(false, Ordering::SeqCst);
+            });
         });
+        worker.set_onmessage(Some(on_tick.as_ref().unchecked_ref()));
+        // The handler lives as long as the worker
+        on_tick.forget();
+
+        let interval = self.config.sync_interval.as_millis() as f64;
+        if let Err(e) = worker.post_message(&interval.into()) {
+            warn!("Failed to start sync worker ticks: {:?}", e);
+        }
+        info!("Background sync worker started (WASM)");
     }
 
     /// Manually trigger sync for a specific document.
@@ -346,6 +429,68 @@ mod tests {
         assert_eq!(sync.pending_count(), 0);
     }
 
+    #[tokio::test]
+    async fn test_deferred_by_limits() {
+        use crate::bandwidth::{BandwidthLimit, LimitScope};
+
+        let config = BackgroundSyncConfig {
+            sync_interval: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let bandwidth_manager = Arc::new(BandwidthManager::new());
+        let sync = BackgroundSync::new(config, Arc::clone(&bandwidth_manager));
+        let peer = "peer1".to_string();
+        sync.add_document(peer, "users".to_string(), "alice".to_string());
+
+        // Use up the budget of low priority traffic
+        let low = LimitScope::Priority(SyncPriority::Low);
+        bandwidth_manager.set_limit(low.clone(), BandwidthLimit::upload(1_000));
+        bandwidth_manager.throttle_delay(
+            TrafficDirection::Upload,
+            &"peer2".to_string(),
+            None,
+            SyncPriority::Low,
+            100_000,
+        );
+
+        sync.start();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(bandwidth_manager.queue_length(), 0);
+
+        bandwidth_manager.set_limit(low, BandwidthLimit::UNLIMITED);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(bandwidth_manager.queue_length(), 1);
+        sync.stop();
+    }
+
+    #[tokio::test]
+    async fn test_deferred_by_policy() {
+        use crate::sync_policy::NetworkType;
+
+        let config = BackgroundSyncConfig {
+            sync_interval: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let bandwidth_manager = Arc::new(BandwidthManager::new());
+        let policy = Arc::new(RwLock::new(
+            SyncPolicy::sync_all().wifi_only(|| NetworkType::Cellular),
+        ));
+        let sync = BackgroundSync::new(config, Arc::clone(&bandwidth_manager))
+            .with_policy(Arc::clone(&policy));
+        let peer = "peer1".to_string();
+        sync.add_document(peer, "users".to_string(), "alice".to_string());
+
+        sync.start();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(bandwidth_manager.queue_length(), 0);
+        assert_eq!(sync.pending_count(), 1);
+
+        *policy.write() = SyncPolicy::sync_all();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(bandwidth_manager.queue_length(), 1);
+        sync.stop();
+    }
+
     #[tokio::test]
     async fn test_sync_now() {
         let config = BackgroundSyncConfig::default();
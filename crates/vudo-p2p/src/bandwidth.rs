@@ -9,6 +9,27 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tracing::{debug, info, warn};
 
+/// Fraction of the estimated link capacity sync traffic is paced at.
+pub const PACING_GAIN: f64 = 0.8;
+
+/// Lowest pacing rate (bytes/sec), so slow links still make progress.
+pub const MIN_PACING_RATE: u64 = 16 * 1024;
+
+/// Smoothing factor for RTT samples (as in TCP SRTT).
+const RTT_ALPHA: f64 = 0.125;
+
+/// Smoothing factor for capacity and rate samples.
+const RATE_ALPHA: f64 = 0.25;
+
+/// Burst allowance of the per-peer pacer.
+const PACING_BURST: Duration = Duration::from_millis(250);
+
+/// Burst allowance of configured bandwidth limits.
+const LIMIT_BURST: Duration = Duration::from_secs(1);
+
+/// Rate limit of metered connections (bytes/sec).
+const METERED_RATE_LIMIT: u64 = 1024 * 1024;
+
 /// Sync task priority.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum SyncPriority {
@@ -22,6 +43,149 @@ pub enum SyncPriority {
     Urgent = 3,
 }
 
+/// Direction of traffic a bandwidth limit applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TrafficDirection {
+    /// Bytes sent to peers.
+    Upload,
+    /// Bytes received from peers.
+    Download,
+}
+
+/// Traffic a bandwidth limit applies to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LimitScope {
+    /// All traffic.
+    Global,
+    /// Traffic with a single peer.
+    Peer(PeerId),
+    /// Traffic of documents in a namespace.
+    Namespace(String),
+    /// Traffic of a sync priority.
+    Priority(SyncPriority),
+}
+
+/// Maximum upload and download rates (bytes/sec, unlimited when `None`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BandwidthLimit {
+    /// Maximum upload rate.
+    pub upload: Option<u64>,
+    /// Maximum download rate.
+    pub download: Option<u64>,
+}
+
+impl BandwidthLimit {
+    /// No limit in either direction.
+    pub const UNLIMITED: Self = Self {
+        upload: None,
+        download: None,
+    };
+
+    /// Limit uploads only.
+    pub fn upload(bytes_per_sec: u64) -> Self {
+        Self {
+            upload: Some(bytes_per_sec),
+            download: None,
+        }
+    }
+
+    /// Limit downloads only.
+    pub fn download(bytes_per_sec: u64) -> Self {
+        Self {
+            upload: None,
+            download: Some(bytes_per_sec),
+        }
+    }
+
+    /// Limit both directions to the same rate.
+    pub fn symmetric(bytes_per_sec: u64) -> Self {
+        Self {
+            upload: Some(bytes_per_sec),
+            download: Some(bytes_per_sec),
+        }
+    }
+
+    /// Limit in one direction.
+    pub fn get(&self, direction: TrafficDirection) -> Option<u64> {
+        match direction {
+            TrafficDirection::Upload => self.upload,
+            TrafficDirection::Download => self.download,
+        }
+    }
+}
+
+/// Token bucket enforcing a rate, with a burst of [`LIMIT_BURST`].
+#[derive(Debug)]
+struct TokenBucket {
+    /// Rate (bytes/sec).
+    rate: f64,
+    /// Available tokens (bytes); negative while a large transfer is being
+    /// paid off.
+    tokens: f64,
+    /// Last refill.
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(now: Instant, bytes_per_sec: u64) -> Self {
+        let rate = bytes_per_sec.max(1) as f64;
+        Self {
+            rate,
+            tokens: rate * LIMIT_BURST.as_secs_f64(),
+            refilled_at: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let burst = self.rate * LIMIT_BURST.as_secs_f64();
+        let refill = now.duration_since(self.refilled_at).as_secs_f64() * self.rate;
+        self.tokens = (self.tokens + refill).min(burst);
+        self.refilled_at = now;
+    }
+
+    /// Take tokens for a transfer, returning how long to wait before it.
+    fn acquire(&mut self, now: Instant, bytes: usize) -> Duration {
+        self.refill(now);
+        self.tokens -= bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+
+    /// Whether a transfer could start without waiting.
+    fn has_tokens(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens > 0.0
+    }
+}
+
+/// Token buckets of a configured limit.
+#[derive(Debug)]
+struct Limiter {
+    limit: BandwidthLimit,
+    upload: Option<TokenBucket>,
+    download: Option<TokenBucket>,
+}
+
+impl Limiter {
+    fn new(now: Instant, limit: BandwidthLimit) -> Self {
+        Self {
+            limit,
+            upload: limit.upload.map(|rate| TokenBucket::new(now, rate)),
+            download: limit.download.map(|rate| TokenBucket::new(now, rate)),
+        }
+    }
+
+    fn bucket(&mut self, direction: TrafficDirection) -> Option<&mut TokenBucket> {
+        match direction {
+            TrafficDirection::Upload => self.upload.as_mut(),
+            TrafficDirection::Download => self.download.as_mut(),
+        }
+    }
+}
+
 /// Sync task.
 #[derive(Debug, Clone)]
 pub struct SyncTask {
@@ -56,6 +220,170 @@ pub struct BandwidthStats {
     pub is_metered: bool,
     /// Current rate limit (bytes/sec).
     pub rate_limit: u64,
+    /// Serialized size of messages sent.
+    pub raw_bytes_sent: u64,
+    /// Bytes those messages took on the wire, after compression.
+    pub compressed_bytes_sent: u64,
+    /// Serialized size of messages received.
+    pub raw_bytes_received: u64,
+    /// Bytes those messages took on the wire, before decompression.
+    pub compressed_bytes_received: u64,
+}
+
+/// Transport statistics sampled from a connection.
+///
+/// Counters are cumulative over the connection lifetime, as reported by QUIC.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LinkSample {
+    /// Current RTT estimate.
+    pub rtt: Duration,
+    /// Current congestion window (bytes).
+    pub cwnd: u64,
+    /// Congestion events so far.
+    pub congestion_events: u64,
+    /// Packets lost so far.
+    pub lost_packets: u64,
+    /// Packets sent so far.
+    pub sent_packets: u64,
+    /// Bytes sent so far.
+    pub tx_bytes: u64,
+    /// Bytes received so far.
+    pub rx_bytes: u64,
+}
+
+/// Smoothed link estimate for a peer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkEstimate {
+    /// Smoothed round-trip time.
+    pub rtt: Duration,
+    /// Estimated capacity (bytes/sec), from congestion window over RTT.
+    pub capacity: u64,
+    /// Observed send rate (bytes/sec).
+    pub send_rate: u64,
+    /// Observed receive rate (bytes/sec).
+    pub receive_rate: u64,
+    /// Packet loss rate over the last sample interval.
+    pub loss_rate: f64,
+    /// Whether congestion was signalled in the last sample interval.
+    pub congested: bool,
+    /// Rate sync traffic to this peer is paced at (bytes/sec).
+    pub pacing_rate: u64,
+}
+
+/// Per-peer estimator and pacer state.
+#[derive(Debug)]
+struct LinkState {
+    /// Previous sample, for deltas.
+    last: (Instant, LinkSample),
+    /// Current estimate.
+    estimate: LinkEstimate,
+    /// Pacer tokens (bytes); negative while a large send is being paid off.
+    tokens: f64,
+    /// Last pacer refill.
+    refilled_at: Instant,
+}
+
+impl LinkState {
+    fn new(now: Instant, sample: LinkSample) -> Self {
+        let capacity = capacity_of(&sample);
+        let pacing_rate = pacing_rate(capacity, 0.0);
+        Self {
+            last: (now, sample),
+            estimate: LinkEstimate {
+                rtt: sample.rtt,
+                capacity,
+                send_rate: 0,
+                receive_rate: 0,
+                loss_rate: 0.0,
+                congested: false,
+                pacing_rate,
+            },
+            tokens: pacing_rate as f64 * PACING_BURST.as_secs_f64(),
+            refilled_at: now,
+        }
+    }
+
+    /// Fold a new sample into the estimate.
+    fn update(&mut self, now: Instant, sample: LinkSample) {
+        let (then, last) = self.last;
+        let elapsed = now.duration_since(then).as_secs_f64();
+        let estimate = &mut self.estimate;
+
+        estimate.rtt = Duration::from_secs_f64(ewma(
+            estimate.rtt.as_secs_f64(),
+            sample.rtt.as_secs_f64(),
+            RTT_ALPHA,
+        ));
+        estimate.capacity = ewma(
+            estimate.capacity as f64,
+            capacity_of(&sample) as f64,
+            RATE_ALPHA,
+        ) as u64;
+
+        if elapsed > 0.0 {
+            let sent = sample.tx_bytes.saturating_sub(last.tx_bytes) as f64 / elapsed;
+            let received = sample.rx_bytes.saturating_sub(last.rx_bytes) as f64 / elapsed;
+            estimate.send_rate = ewma(estimate.send_rate as f64, sent, RATE_ALPHA) as u64;
+            estimate.receive_rate =
+                ewma(estimate.receive_rate as f64, received, RATE_ALPHA) as u64;
+        }
+
+        let sent_packets = sample.sent_packets.saturating_sub(last.sent_packets);
+        let lost_packets = sample.lost_packets.saturating_sub(last.lost_packets);
+        estimate.loss_rate = if sent_packets > 0 {
+            (lost_packets as f64 / sent_packets as f64).min(1.0)
+        } else {
+            0.0
+        };
+        estimate.congested = sample.congestion_events > last.congestion_events;
+
+        let mut rate = pacing_rate(estimate.capacity, estimate.loss_rate);
+        if estimate.congested {
+            // Back off multiplicatively until the link recovers
+            rate = rate.min(estimate.pacing_rate / 2).max(MIN_PACING_RATE);
+        }
+        estimate.pacing_rate = rate;
+
+        self.last = (now, sample);
+    }
+
+    /// Take tokens for a send, returning how long to wait before sending.
+    fn acquire(&mut self, now: Instant, bytes: usize) -> Duration {
+        let rate = self.estimate.pacing_rate as f64;
+        let burst = rate * PACING_BURST.as_secs_f64();
+        let refill = now.duration_since(self.refilled_at).as_secs_f64() * rate;
+
+        self.tokens = (self.tokens + refill).min(burst);
+        self.refilled_at = now;
+        self.tokens -= bytes as f64;
+
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / rate)
+        }
+    }
+}
+
+/// Capacity implied by a sample's congestion window and RTT.
+fn capacity_of(sample: &LinkSample) -> u64 {
+    if sample.rtt.is_zero() {
+        return u64::MAX;
+    }
+    (sample.cwnd as f64 / sample.rtt.as_secs_f64()) as u64
+}
+
+/// Pacing rate for a capacity and loss rate.
+fn pacing_rate(capacity: u64, loss_rate: f64) -> u64 {
+    if capacity == u64::MAX {
+        return u64::MAX;
+    }
+    ((capacity as f64 * PACING_GAIN * (1.0 - loss_rate)) as u64).max(MIN_PACING_RATE)
+}
+
+/// Exponentially weighted moving average.
+fn ewma(current: f64, sample: f64, alpha: f64) -> f64 {
+    current + alpha * (sample - current)
 }
 
 /// Priority queue for sync tasks.
@@ -125,12 +453,24 @@ pub struct BandwidthManager {
     bytes_sent: Arc<AtomicU64>,
     /// Bytes received counter.
     bytes_received: Arc<AtomicU64>,
+    /// Serialized message bytes sent.
+    raw_bytes_sent: Arc<AtomicU64>,
+    /// Message bytes sent on the wire.
+    compressed_bytes_sent: Arc<AtomicU64>,
+    /// Serialized message bytes received.
+    raw_bytes_received: Arc<AtomicU64>,
+    /// Message bytes received on the wire.
+    compressed_bytes_received: Arc<AtomicU64>,
     /// Sync task queue.
     task_queue: Arc<RwLock<PriorityQueue>>,
     /// Rate calculation window.
     window_duration: Duration,
     /// Timestamp samples for rate calculation.
     samples: Arc<RwLock<VecDeque<(Instant, u64, u64)>>>, // (timestamp, bytes_sent, bytes_received)
+    /// Per-peer link estimates and pacers.
+    links: Arc<RwLock<HashMap<PeerId, LinkState>>>,
+    /// Configured bandwidth limits.
+    limits: Arc<RwLock<HashMap<LimitScope, Limiter>>>,
 }
 
 impl BandwidthManager {
@@ -141,9 +481,15 @@ impl BandwidthManager {
             rate_limit: Arc::new(AtomicU64::new(u64::MAX)), // Unlimited by default
             bytes_sent: Arc::new(AtomicU64::new(0)),
             bytes_received: Arc::new(AtomicU64::new(0)),
+            raw_bytes_sent: Arc::new(AtomicU64::new(0)),
+            compressed_bytes_sent: Arc::new(AtomicU64::new(0)),
+            raw_bytes_received: Arc::new(AtomicU64::new(0)),
+            compressed_bytes_received: Arc::new(AtomicU64::new(0)),
             task_queue: Arc::new(RwLock::new(PriorityQueue::new())),
             window_duration: Duration::from_secs(10),
             samples: Arc::new(RwLock::new(VecDeque::new())),
+            links: Arc::new(RwLock::new(HashMap::new())),
+            limits: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -196,12 +542,12 @@ impl BandwidthManager {
 
         // Adjust rate limit based on metered status
         let new_limit = if is_metered {
-            1024 * 1024 // 1 MB/s for metered connections
+            METERED_RATE_LIMIT
         } else {
             u64::MAX // Unlimited for non-metered
         };
 
-        self.rate_limit.store(new_limit, Ordering::SeqCst);
+        self.set_global_upload(new_limit);
 
         info!(
             "Connection metered status: {} (rate limit: {} bytes/sec)",
@@ -215,11 +561,149 @@ impl BandwidthManager {
     }
 
     /// Set custom rate limit.
+    ///
+    /// This is the global upload limit of [`BandwidthManager::set_limit`];
+    /// `u64::MAX` removes it.
     pub fn set_rate_limit(&self, bytes_per_sec: u64) {
-        self.rate_limit.store(bytes_per_sec, Ordering::SeqCst);
+        self.set_global_upload(bytes_per_sec);
         info!("Rate limit set to {} bytes/sec", bytes_per_sec);
     }
 
+    fn set_global_upload(&self, bytes_per_sec: u64) {
+        let limit = BandwidthLimit {
+            upload: (bytes_per_sec != u64::MAX).then_some(bytes_per_sec),
+            ..self.limit(&LimitScope::Global)
+        };
+        self.set_limit(LimitScope::Global, limit);
+    }
+
+    /// Set the bandwidth limit of a scope, replacing the previous one.
+    ///
+    /// Takes effect immediately for traffic checked through
+    /// [`BandwidthManager::throttle`]. [`BandwidthLimit::UNLIMITED`] removes
+    /// the limit.
+    pub fn set_limit(&self, scope: LimitScope, limit: BandwidthLimit) {
+        if scope == LimitScope::Global {
+            self.rate_limit
+                .store(limit.upload.unwrap_or(u64::MAX), Ordering::SeqCst);
+        }
+
+        let mut limits = self.limits.write();
+        if limit == BandwidthLimit::UNLIMITED {
+            limits.remove(&scope);
+            debug!("Bandwidth limit of {:?} removed", scope);
+        } else {
+            limits.insert(scope.clone(), Limiter::new(Instant::now(), limit));
+            debug!("Bandwidth limit of {:?} set to {:?}", scope, limit);
+        }
+    }
+
+    /// Get the bandwidth limit of a scope.
+    pub fn limit(&self, scope: &LimitScope) -> BandwidthLimit {
+        self.limits
+            .read()
+            .get(scope)
+            .map(|limiter| limiter.limit)
+            .unwrap_or_default()
+    }
+
+    /// Get all configured bandwidth limits.
+    pub fn limits(&self) -> HashMap<LimitScope, BandwidthLimit> {
+        self.limits
+            .read()
+            .iter()
+            .map(|(scope, limiter)| (scope.clone(), limiter.limit))
+            .collect()
+    }
+
+    /// Scopes that apply to some traffic.
+    fn scopes(
+        peer_id: &PeerId,
+        namespace: Option<&str>,
+        priority: SyncPriority,
+    ) -> Vec<LimitScope> {
+        let mut scopes = vec![
+            LimitScope::Global,
+            LimitScope::Peer(peer_id.clone()),
+            LimitScope::Priority(priority),
+        ];
+        if let Some(namespace) = namespace {
+            scopes.push(LimitScope::Namespace(namespace.to_string()));
+        }
+        scopes
+    }
+
+    /// Reserve a transfer against every limit that applies to it, returning
+    /// how long to wait before the transfer.
+    ///
+    /// The global, peer, namespace and priority limits each keep a token
+    /// bucket; the transfer waits for the slowest of them.
+    pub fn throttle_delay(
+        &self,
+        direction: TrafficDirection,
+        peer_id: &PeerId,
+        namespace: Option<&str>,
+        priority: SyncPriority,
+        bytes: usize,
+    ) -> Duration {
+        let mut limits = self.limits.write();
+        if limits.is_empty() {
+            return Duration::ZERO;
+        }
+
+        let now = Instant::now();
+        let mut delay = Duration::ZERO;
+        for scope in Self::scopes(peer_id, namespace, priority) {
+            if let Some(bucket) = limits.get_mut(&scope).and_then(|l| l.bucket(direction)) {
+                delay = delay.max(bucket.acquire(now, bytes));
+            }
+        }
+        delay
+    }
+
+    /// Wait until a transfer fits the limits that apply to it.
+    pub async fn throttle(
+        &self,
+        direction: TrafficDirection,
+        peer_id: &PeerId,
+        namespace: Option<&str>,
+        priority: SyncPriority,
+        bytes: usize,
+    ) {
+        let delay = self.throttle_delay(direction, peer_id, namespace, priority, bytes);
+        if !delay.is_zero() {
+            debug!(
+                "Throttling {:?} of {} bytes with peer {} by {:?}",
+                direction, bytes, peer_id, delay
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Check whether traffic could flow without waiting on a limit, without
+    /// reserving anything.
+    pub fn has_budget(
+        &self,
+        direction: TrafficDirection,
+        peer_id: &PeerId,
+        namespace: Option<&str>,
+        priority: SyncPriority,
+    ) -> bool {
+        let mut limits = self.limits.write();
+        if limits.is_empty() {
+            return true;
+        }
+
+        let now = Instant::now();
+        let mut available = true;
+        for scope in Self::scopes(peer_id, namespace, priority) {
+            if let Some(bucket) = limits.get_mut(&scope).and_then(|l| l.bucket(direction)) {
+                available &= bucket.has_tokens(now);
+            }
+        }
+        available
+    }
+
     /// Record bytes sent.
     pub fn record_sent(&self, bytes: usize) {
         self.bytes_sent.fetch_add(bytes as u64, Ordering::SeqCst);
@@ -232,6 +716,19 @@ impl BandwidthManager {
         self.add_sample();
     }
 
+    /// Record the serialized (`raw`) and on-the-wire (`wire`) size of a
+    /// message, which are equal for uncompressed messages.
+    pub fn record_compression(&self, direction: TrafficDirection, raw: usize, wire: usize) {
+        let (raw_bytes, wire_bytes) = match direction {
+            TrafficDirection::Upload => (&self.raw_bytes_sent, &self.compressed_bytes_sent),
+            TrafficDirection::Download => {
+                (&self.raw_bytes_received, &self.compressed_bytes_received)
+            }
+        };
+        raw_bytes.fetch_add(raw as u64, Ordering::SeqCst);
+        wire_bytes.fetch_add(wire as u64, Ordering::SeqCst);
+    }
+
     /// Add a sample for rate calculation.
     fn add_sample(&self) {
         let mut samples = self.samples.write();
@@ -298,6 +795,73 @@ impl BandwidthManager {
         current_rate + bytes as u64 <= rate_limit
     }
 
+    /// Record transport statistics sampled from a peer connection.
+    ///
+    /// Estimates are passive: they are derived from the QUIC congestion
+    /// controller rather than from probe traffic.
+    pub fn record_link_sample(&self, peer_id: &PeerId, sample: LinkSample) -> LinkEstimate {
+        let now = Instant::now();
+        let mut links = self.links.write();
+
+        let estimate = match links.get_mut(peer_id) {
+            Some(state) => {
+                state.update(now, sample);
+                state.estimate
+            }
+            None => {
+                let state = LinkState::new(now, sample);
+                let estimate = state.estimate;
+                links.insert(peer_id.clone(), state);
+                estimate
+            }
+        };
+
+        if estimate.congested {
+            debug!(
+                "Congestion on link to {}: pacing at {} bytes/sec",
+                peer_id, estimate.pacing_rate
+            );
+        }
+
+        estimate
+    }
+
+    /// Get the link estimate for a peer.
+    pub fn link_estimate(&self, peer_id: &PeerId) -> Option<LinkEstimate> {
+        self.links.read().get(peer_id).map(|state| state.estimate)
+    }
+
+    /// Forget the link estimate for a peer.
+    pub fn remove_link(&self, peer_id: &PeerId) {
+        self.links.write().remove(peer_id);
+    }
+
+    /// Get the effective rate limit for a peer (bytes/sec).
+    ///
+    /// The lowest of the global and per-peer upload limits and the peer's
+    /// pacing rate.
+    pub fn peer_rate_limit(&self, peer_id: &PeerId) -> u64 {
+        let rate_limit = self
+            .limit(&LimitScope::Peer(peer_id.clone()))
+            .upload
+            .unwrap_or(u64::MAX)
+            .min(self.rate_limit.load(Ordering::SeqCst));
+        self.link_estimate(peer_id)
+            .map(|estimate| estimate.pacing_rate.min(rate_limit))
+            .unwrap_or(rate_limit)
+    }
+
+    /// Reserve a send to a peer, returning how long to wait before sending.
+    ///
+    /// Peers without a link estimate are not paced.
+    pub fn pacing_delay(&self, peer_id: &PeerId, bytes: usize) -> Duration {
+        let now = Instant::now();
+        match self.links.write().get_mut(peer_id) {
+            Some(state) if state.estimate.pacing_rate != u64::MAX => state.acquire(now, bytes),
+            _ => Duration::ZERO,
+        }
+    }
+
     /// Schedule a sync task.
     pub async fn schedule_sync(&self, task: SyncTask) -> Result<u64> {
         debug!(
@@ -328,6 +892,10 @@ impl BandwidthManager {
             receive_rate: self.receive_rate(),
             is_metered: self.is_metered.load(Ordering::SeqCst),
             rate_limit: self.rate_limit.load(Ordering::SeqCst),
+            raw_bytes_sent: self.raw_bytes_sent.load(Ordering::SeqCst),
+            compressed_bytes_sent: self.compressed_bytes_sent.load(Ordering::SeqCst),
+            raw_bytes_received: self.raw_bytes_received.load(Ordering::SeqCst),
+            compressed_bytes_received: self.compressed_bytes_received.load(Ordering::SeqCst),
         }
     }
 
@@ -335,6 +903,10 @@ impl BandwidthManager {
     pub fn reset(&self) {
         self.bytes_sent.store(0, Ordering::SeqCst);
         self.bytes_received.store(0, Ordering::SeqCst);
+        self.raw_bytes_sent.store(0, Ordering::SeqCst);
+        self.compressed_bytes_sent.store(0, Ordering::SeqCst);
+        self.raw_bytes_received.store(0, Ordering::SeqCst);
+        self.compressed_bytes_received.store(0, Ordering::SeqCst);
         self.samples.write().clear();
     }
 }
@@ -371,6 +943,24 @@ mod tests {
         assert_eq!(stats.bytes_received, 500);
     }
 
+    #[test]
+    fn test_record_compression() {
+        let manager = BandwidthManager::new();
+
+        manager.record_compression(TrafficDirection::Upload, 100_000, 8_000);
+        manager.record_compression(TrafficDirection::Upload, 100, 100);
+        manager.record_compression(TrafficDirection::Download, 50_000, 5_000);
+
+        let stats = manager.stats();
+        assert_eq!(stats.raw_bytes_sent, 100_100);
+        assert_eq!(stats.compressed_bytes_sent, 8_100);
+        assert_eq!(stats.raw_bytes_received, 50_000);
+        assert_eq!(stats.compressed_bytes_received, 5_000);
+
+        manager.reset();
+        assert_eq!(manager.stats().compressed_bytes_sent, 0);
+    }
+
     #[test]
     fn test_metered_connection() {
         let manager = BandwidthManager::new();
@@ -433,6 +1023,118 @@ mod tests {
         assert_eq!(next.doc_id, "alice");
     }
 
+    fn sample(rtt_ms: u64, cwnd: u64, lost: u64, sent: u64, congestion: u64) -> LinkSample {
+        LinkSample {
+            rtt: Duration::from_millis(rtt_ms),
+            cwnd,
+            congestion_events: congestion,
+            lost_packets: lost,
+            sent_packets: sent,
+            tx_bytes: 0,
+            rx_bytes: 0,
+        }
+    }
+
+    #[test]
+    fn test_link_estimation() {
+        let manager = BandwidthManager::new();
+        let desktop = "desktop".to_string();
+        let phone = "phone".to_string();
+
+        // 12 KB window over 10 ms vs. 60 KB window over 300 ms
+        let fast = manager.record_link_sample(&desktop, sample(10, 12_000, 0, 0, 0));
+        let slow = manager.record_link_sample(&phone, sample(300, 60_000, 0, 0, 0));
+        assert_eq!(fast.capacity, 1_200_000);
+        assert_eq!(slow.capacity, 200_000);
+        assert_eq!(slow.pacing_rate, 160_000);
+        assert_eq!(manager.peer_rate_limit(&phone), 160_000);
+
+        // Losses and congestion lower the pacing rate
+        let lossy = manager.record_link_sample(&phone, sample(300, 60_000, 10, 100, 1));
+        assert!(lossy.congested);
+        assert!((lossy.loss_rate - 0.1).abs() < f64::EPSILON);
+        assert_eq!(lossy.pacing_rate, 80_000);
+
+        // The global limit still applies
+        manager.set_rate_limit(50_000);
+        assert_eq!(manager.peer_rate_limit(&phone), 50_000);
+
+        manager.remove_link(&phone);
+        assert!(manager.link_estimate(&phone).is_none());
+    }
+
+    #[test]
+    fn test_pacing_delay() {
+        let manager = BandwidthManager::new();
+        let phone = "phone".to_string();
+        assert_eq!(manager.pacing_delay(&phone, 1_000_000), Duration::ZERO);
+
+        // 160 KB/s pacing rate with a 40 KB burst
+        manager.record_link_sample(&phone, sample(300, 60_000, 0, 0, 0));
+        assert_eq!(manager.pacing_delay(&phone, 20_000), Duration::ZERO);
+
+        let delay = manager.pacing_delay(&phone, 180_000);
+        assert!(delay > Duration::from_millis(900));
+        assert!(delay < Duration::from_millis(1100));
+    }
+
+    #[test]
+    fn test_bandwidth_limits() {
+        use TrafficDirection::{Download, Upload};
+
+        let manager = BandwidthManager::new();
+        let (phone, laptop) = ("phone".to_string(), "laptop".to_string());
+        let delay = |peer: &PeerId, namespace, priority, bytes| {
+            manager.throttle_delay(Upload, peer, namespace, priority, bytes)
+        };
+        assert!(delay(&phone, None, SyncPriority::Normal, 1 << 20).is_zero());
+
+        // 100 KB/s to the phone, with a one second burst
+        let peer = LimitScope::Peer(phone.clone());
+        manager.set_limit(peer.clone(), BandwidthLimit::upload(100_000));
+        assert!(delay(&phone, None, SyncPriority::Normal, 100_000).is_zero());
+        let wait = delay(&phone, None, SyncPriority::Normal, 50_000);
+        assert!(wait > Duration::from_millis(450) && wait < Duration::from_millis(550));
+        assert!(!manager.has_budget(Upload, &phone, None, SyncPriority::Normal));
+        assert!(manager.has_budget(Download, &phone, None, SyncPriority::Normal));
+        assert!(delay(&laptop, None, SyncPriority::Normal, 1 << 20).is_zero());
+        assert_eq!(manager.peer_rate_limit(&phone), 100_000);
+
+        // The slowest applicable limit wins
+        manager.set_limit(
+            LimitScope::Priority(SyncPriority::Low),
+            BandwidthLimit::symmetric(10_000),
+        );
+        let photos = LimitScope::Namespace("photos".to_string());
+        manager.set_limit(photos, BandwidthLimit::download(1_000));
+        let wait = delay(&laptop, Some("photos"), SyncPriority::Low, 20_000);
+        assert!(wait > Duration::from_millis(900) && wait < Duration::from_millis(1100));
+        let high = SyncPriority::High;
+        let wait = manager.throttle_delay(Download, &laptop, Some("photos"), high, 2_000);
+        assert!(wait > Duration::from_millis(900) && wait < Duration::from_millis(1100));
+        assert!(manager.has_budget(Upload, &laptop, Some("photos"), SyncPriority::High));
+
+        // Limits can be changed and removed at runtime
+        manager.set_limit(peer, BandwidthLimit::UNLIMITED);
+        assert!(delay(&phone, None, SyncPriority::Normal, 1 << 20).is_zero());
+        assert_eq!(manager.limits().len(), 2);
+
+        // The global upload limit is the rate limit
+        manager.set_rate_limit(500_000);
+        assert_eq!(
+            manager.limit(&LimitScope::Global),
+            BandwidthLimit::upload(500_000)
+        );
+        manager.set_limit(LimitScope::Global, BandwidthLimit::symmetric(200_000));
+        assert_eq!(manager.stats().rate_limit, 200_000);
+        manager.set_metered(false);
+        assert_eq!(
+            manager.limit(&LimitScope::Global),
+            BandwidthLimit::download(200_000)
+        );
+        assert_eq!(manager.stats().rate_limit, u64::MAX);
+    }
+
     #[test]
     fn test_can_send() {
         let manager = BandwidthManager::new();
@@ -0,0 +1,84 @@
+//! vudo-relay - self-hosted relay server for VUDO nodes
+//!
+//! # Usage
+//!
+//! ```bash
+//! # Relay on port 3340, STUN on 3478
+//! vudo-relay
+//!
+//! # Behind a TLS-terminating reverse proxy
+//! vudo-relay --http-addr 127.0.0.1:3340 --public-url https://relay.example.com
+//! ```
+//!
+//! Nodes then list the printed URL in `P2PConfig::relays`.
+
+use clap::Parser;
+use std::net::SocketAddr;
+use std::process::ExitCode;
+use vudo_p2p::relay_server::{DEFAULT_RELAY_PORT, DEFAULT_STUN_PORT};
+use vudo_p2p::{RelayServer, RelayServerConfig};
+
+/// Self-hosted relay server for VUDO nodes
+#[derive(Parser, Debug)]
+#[command(name = "vudo-relay", version, about)]
+struct Cli {
+    /// Address the relay listens on
+    #[arg(long, default_value_t = SocketAddr::from(([0, 0, 0, 0], DEFAULT_RELAY_PORT)))]
+    http_addr: SocketAddr,
+
+    /// Address of the STUN server
+    #[arg(long, default_value_t = SocketAddr::from(([0, 0, 0, 0], DEFAULT_STUN_PORT)))]
+    stun_addr: SocketAddr,
+
+    /// Don't run a STUN server
+    #[arg(long)]
+    no_stun: bool,
+
+    /// URL nodes reach the relay at (defaults to the listening address)
+    #[arg(long)]
+    public_url: Option<String>,
+
+    /// Maximum new connections accepted per second
+    #[arg(long)]
+    conn_limit: Option<f64>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
+        )
+        .init();
+
+    let cli = Cli::parse();
+    let config = RelayServerConfig {
+        http_addr: cli.http_addr,
+        stun_addr: (!cli.no_stun).then_some(cli.stun_addr),
+        public_url: cli.public_url,
+        accept_conn_limit: cli.conn_limit,
+    };
+
+    let server = match RelayServer::spawn(config).await {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("Relay running at {}", server.url());
+    if let Some(addr) = server.stun_addr() {
+        println!("STUN listening on {}", addr);
+    }
+
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        eprintln!("Error: {}", e);
+    }
+    match server.shutdown().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
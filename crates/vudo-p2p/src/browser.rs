@@ -0,0 +1,447 @@
+//! Browser peers linked over WebSocket.
+//!
+//! Browsers can't open the QUIC connections Iroh uses, so they join the
+//! mesh through a gateway: a native node accepting WebSocket links (see the
+//! `gateway` module, feature `browser-gateway`). On the gateway, a linked
+//! browser is a peer like any other, reached through the gateway's
+//! [`IrohAdapter`](crate::IrohAdapter), so it syncs with the gateway's
+//! documents and is subject to the same sync authentication and peer access
+//! rules.
+//!
+//! A browser is identified by an Ed25519 key, its peer ID formatted like an
+//! Iroh node ID. When a link opens, the two sides exchange JSON text frames:
+//!
+//! 1. the gateway sends a [`LinkMessage::Challenge`] with a random nonce;
+//! 2. the browser answers with a [`LinkMessage::Hello`], signing the nonce
+//!    and the gateway's ID, and announcing the framing it reads;
+//! 3. the gateway verifies it and sends a [`LinkMessage::Welcome`] with its
+//!    own framing, or a [`LinkMessage::Rejected`].
+//!
+//! From then on, every binary frame carries one sync message, chunked as on
+//! Iroh streams (see [`crate::framing`]). zstd doesn't build for `wasm32`, so
+//! browsers neither send nor accept compressed chunks.
+//!
+//! On `wasm32`, [`BrowserTransport`] is the browser side of these links.
+
+use crate::error::{P2PError, Result};
+use crate::framing::TransportHello;
+use crate::gossip::peer_id_of;
+use crate::sync_protocol::PeerId;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+#[cfg(target_arch = "wasm32")]
+pub use web::BrowserTransport;
+
+/// WebSocket subprotocol of browser links.
+pub const LINK_PROTOCOL: &str = "vudo-link/1";
+
+/// Domain tag of link hello signatures.
+const SIGNATURE_DOMAIN: &[u8] = b"vudo-browser-link/1";
+
+/// Handshake message of a browser link.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LinkMessage {
+    /// Sent by the gateway when a browser connects.
+    Challenge {
+        /// Peer ID of the gateway.
+        gateway: PeerId,
+        /// Random nonce to sign (hex).
+        nonce: String,
+    },
+    /// The browser's answer to the challenge.
+    Hello {
+        /// The browser's public key (hex).
+        key: String,
+        /// Signature of the gateway's ID and the nonce (hex).
+        signature: String,
+        /// Framing the browser reads.
+        transport: TransportHello,
+    },
+    /// Sent by the gateway once the browser is linked.
+    Welcome {
+        /// Peer ID of the browser.
+        peer_id: PeerId,
+        /// Framing the gateway reads.
+        transport: TransportHello,
+    },
+    /// Sent by the gateway before closing a link it refuses.
+    Rejected {
+        /// Why the link was refused.
+        reason: String,
+    },
+}
+
+impl LinkMessage {
+    /// Answer a challenge, signing it with the browser's key.
+    pub fn hello(
+        signing_key: &SigningKey,
+        gateway: &PeerId,
+        nonce: &str,
+        transport: TransportHello,
+    ) -> Self {
+        let signature = signing_key.sign(&signed_bytes(gateway, nonce));
+        LinkMessage::Hello {
+            key: hex::encode(signing_key.verifying_key().as_bytes()),
+            signature: hex::encode(signature.to_bytes()),
+            transport,
+        }
+    }
+
+    /// Serialize the message into a text frame.
+    pub fn to_text(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Parse a text frame.
+    pub fn from_text(text: &str) -> Result<Self> {
+        serde_json::from_str(text)
+            .map_err(|e| P2PError::InvalidMessage(format!("Invalid link message: {}", e)))
+    }
+}
+
+/// Generate a random challenge nonce.
+pub fn new_nonce() -> String {
+    hex::encode(rand::random::<[u8; 32]>())
+}
+
+/// Verify a browser's hello to the challenge `(gateway, nonce)`.
+///
+/// Returns the browser's peer ID and the framing it reads.
+pub fn verify_hello(
+    gateway: &PeerId,
+    nonce: &str,
+    hello: &LinkMessage,
+) -> Result<(PeerId, TransportHello)> {
+    let LinkMessage::Hello {
+        key,
+        signature,
+        transport,
+    } = hello
+    else {
+        return Err(P2PError::InvalidMessage(
+            "Expected a link hello".to_string(),
+        ));
+    };
+
+    let invalid = || P2PError::PermissionDenied("Invalid link hello signature".to_string());
+    let key: [u8; 32] = decode_hex(key)?.try_into().map_err(|_| invalid())?;
+    let key = VerifyingKey::from_bytes(&key).map_err(|_| invalid())?;
+    let signature: [u8; 64] = decode_hex(signature)?.try_into().map_err(|_| invalid())?;
+    key.verify_strict(
+        &signed_bytes(gateway, nonce),
+        &Signature::from_bytes(&signature),
+    )
+    .map_err(|_| invalid())?;
+
+    Ok((peer_id_of(&key), transport.clone()))
+}
+
+/// Bytes signed by a link hello.
+fn signed_bytes(gateway: &PeerId, nonce: &str) -> Vec<u8> {
+    let mut bytes = SIGNATURE_DOMAIN.to_vec();
+    bytes.extend_from_slice(gateway.as_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(nonce.as_bytes());
+    bytes
+}
+
+/// Decode a hex field of a link message.
+fn decode_hex(value: &str) -> Result<Vec<u8>> {
+    hex::decode(value).map_err(|e| P2PError::InvalidMessage(format!("Invalid hex: {}", e)))
+}
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use super::*;
+    use crate::framing::{self, PeerTransports, TransportConfig};
+    use crate::sync_protocol::SyncMessage;
+    use js_sys::{ArrayBuffer, Uint8Array};
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+    use tokio::sync::mpsc;
+    use tracing::{debug, info, warn};
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen::{JsCast, JsValue};
+    use wasm_bindgen_futures::spawn_local;
+    use web_sys::{BinaryType, CloseEvent, MessageEvent, WebSocket};
+
+    /// Frame received on a link.
+    enum Frame {
+        /// Text frame (handshake).
+        Text(String),
+        /// Binary frame (sync message).
+        Binary(Vec<u8>),
+        /// The socket closed, with the reason given.
+        Closed(String),
+    }
+
+    /// Open link to a gateway.
+    struct Link {
+        /// WebSocket of the link.
+        socket: WebSocket,
+        /// Message handler, kept alive with the socket.
+        _on_message: Closure<dyn FnMut(MessageEvent)>,
+        /// Close handler, kept alive with the socket.
+        _on_close: Closure<dyn FnMut(CloseEvent)>,
+    }
+
+    /// Browser side of links to gateways.
+    ///
+    /// Sends and receives sync messages with the same calls as
+    /// [`IrohAdapter`](crate::IrohAdapter), with gateways as the peers.
+    /// Feed received messages to a [`SyncProtocol`](crate::SyncProtocol) to
+    /// sync documents with them.
+    pub struct BrowserTransport {
+        /// Key identifying this browser.
+        signing_key: SigningKey,
+        /// Framing negotiated with each gateway.
+        transports: Rc<PeerTransports>,
+        /// Open links by gateway peer ID.
+        links: Rc<RefCell<HashMap<PeerId, Link>>>,
+        /// Incoming message channel.
+        message_tx: mpsc::UnboundedSender<(PeerId, SyncMessage)>,
+        /// Incoming message receiver.
+        message_rx: tokio::sync::Mutex<mpsc::UnboundedReceiver<(PeerId, SyncMessage)>>,
+    }
+
+    impl BrowserTransport {
+        /// Create a transport identified by `signing_key`.
+        pub fn new(signing_key: SigningKey, config: TransportConfig) -> Self {
+            let (message_tx, message_rx) = mpsc::unbounded_channel();
+            Self {
+                signing_key,
+                transports: Rc::new(PeerTransports::new(config)),
+                links: Rc::new(RefCell::new(HashMap::new())),
+                message_tx,
+                message_rx: tokio::sync::Mutex::new(message_rx),
+            }
+        }
+
+        /// Get this browser's peer ID.
+        pub fn node_id(&self) -> PeerId {
+            peer_id_of(&self.signing_key.verifying_key())
+        }
+
+        /// Link to a gateway, e.g. `wss://gateway.example.com`.
+        ///
+        /// Returns the gateway's peer ID.
+        pub async fn connect(&self, url: &str) -> Result<PeerId> {
+            info!("Linking to gateway {}", url);
+            let socket = WebSocket::new_with_str(url, LINK_PROTOCOL).map_err(js_error)?;
+            socket.set_binary_type(BinaryType::Arraybuffer);
+
+            let (frame_tx, mut frames) = mpsc::unbounded_channel();
+            let tx = frame_tx.clone();
+            let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+                let data = event.data();
+                let frame = match data.as_string() {
+                    Some(text) => Frame::Text(text),
+                    None => match data.dyn_into::<ArrayBuffer>() {
+                        Ok(buffer) => Frame::Binary(Uint8Array::new(&buffer).to_vec()),
+                        Err(_) => return,
+                    },
+                };
+                let _ = tx.send(frame);
+            });
+            let on_close = Closure::<dyn FnMut(CloseEvent)>::new(move |event: CloseEvent| {
+                let _ = frame_tx.send(Frame::Closed(event.reason()));
+            });
+            socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+            socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+            let (gateway, nonce) = match next_message(&mut frames).await? {
+                LinkMessage::Challenge { gateway, nonce } => (gateway, nonce),
+                other => return Err(unexpected(&other)),
+            };
+            let hello =
+                LinkMessage::hello(&self.signing_key, &gateway, &nonce, TransportHello::local());
+            socket.send_with_str(&hello.to_text()?).map_err(js_error)?;
+            match next_message(&mut frames).await? {
+                LinkMessage::Welcome { transport, .. } => {
+                    let compression = self.transports.negotiate(&gateway, &transport);
+                    debug!(
+                        "Negotiated framing {:?} with gateway {}",
+                        compression, gateway
+                    );
+                }
+                LinkMessage::Rejected { reason } => {
+                    return Err(P2PError::PermissionDenied(reason));
+                }
+                other => return Err(unexpected(&other)),
+            }
+
+            info!("Linked to gateway {} as {}", gateway, self.node_id());
+            self.links.borrow_mut().insert(
+                gateway.clone(),
+                Link {
+                    socket,
+                    _on_message: on_message,
+                    _on_close: on_close,
+                },
+            );
+            spawn_local(receive(
+                gateway.clone(),
+                frames,
+                Rc::clone(&self.transports),
+                Rc::clone(&self.links),
+                self.message_tx.clone(),
+            ));
+
+            Ok(gateway)
+        }
+
+        /// Send a message to a gateway.
+        pub async fn send_message(&self, peer_id: &PeerId, message: &SyncMessage) -> Result<()> {
+            let bytes = message.to_bytes()?;
+            let frame = match self.transports.framing(peer_id) {
+                Some(compression) => self
+                    .transports
+                    .chunks(&bytes, compression)
+                    .collect::<Result<Vec<_>>>()?
+                    .concat(),
+                None => bytes,
+            };
+
+            let links = self.links.borrow();
+            let link = links
+                .get(peer_id)
+                .ok_or_else(|| P2PError::PeerNotFound(peer_id.clone()))?;
+            debug!("Sending {} bytes to gateway {}", frame.len(), peer_id);
+            link.socket.send_with_u8_array(&frame).map_err(js_error)
+        }
+
+        /// Receive the next message.
+        pub async fn recv_message(&self) -> Result<(PeerId, SyncMessage)> {
+            self.message_rx
+                .lock()
+                .await
+                .recv()
+                .await
+                .ok_or_else(|| P2PError::Internal("Message channel closed".to_string()))
+        }
+
+        /// Get the linked gateways.
+        pub fn connected_peers(&self) -> Vec<PeerId> {
+            self.links.borrow().keys().cloned().collect()
+        }
+
+        /// Close the link to a gateway.
+        pub async fn disconnect(&self, peer_id: &PeerId) -> Result<()> {
+            let link = self
+                .links
+                .borrow_mut()
+                .remove(peer_id)
+                .ok_or_else(|| P2PError::PeerNotFound(peer_id.clone()))?;
+            self.transports.remove(peer_id);
+            link.socket.close().map_err(js_error)
+        }
+    }
+
+    /// Forward the sync messages of a link until it closes.
+    async fn receive(
+        gateway: PeerId,
+        mut frames: mpsc::UnboundedReceiver<Frame>,
+        transports: Rc<PeerTransports>,
+        links: Rc<RefCell<HashMap<PeerId, Link>>>,
+        message_tx: mpsc::UnboundedSender<(PeerId, SyncMessage)>,
+    ) {
+        let max_message_size = transports.config().max_message_size;
+        while let Some(frame) = frames.recv().await {
+            match frame {
+                Frame::Binary(data) => {
+                    let message = framing::read_message(&mut data.as_slice(), max_message_size)
+                        .await
+                        .and_then(|received| SyncMessage::from_bytes(&received.bytes));
+                    match message {
+                        Ok(message) => {
+                            if message_tx.send((gateway.clone(), message)).is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) => warn!("Rejected message from gateway {}: {}", gateway, e),
+                    }
+                }
+                Frame::Text(_) => debug!("Ignoring text frame from gateway {}", gateway),
+                Frame::Closed(reason) => {
+                    info!("Link to gateway {} closed: {}", gateway, reason);
+                    break;
+                }
+            }
+        }
+        links.borrow_mut().remove(&gateway);
+        transports.remove(&gateway);
+    }
+
+    /// Wait for the next handshake message of a link.
+    async fn next_message(frames: &mut mpsc::UnboundedReceiver<Frame>) -> Result<LinkMessage> {
+        match frames.recv().await {
+            Some(Frame::Text(text)) => LinkMessage::from_text(&text),
+            Some(Frame::Binary(_)) => Err(P2PError::InvalidMessage(
+                "Sync message before the link handshake".to_string(),
+            )),
+            Some(Frame::Closed(reason)) => Err(P2PError::ConnectionFailed(reason)),
+            None => Err(P2PError::ConnectionFailed("Link closed".to_string())),
+        }
+    }
+
+    /// Error for a handshake message out of order.
+    fn unexpected(message: &LinkMessage) -> P2PError {
+        P2PError::InvalidMessage(format!("Unexpected link message: {:?}", message))
+    }
+
+    /// Map a JavaScript exception.
+    fn js_error(e: JsValue) -> P2PError {
+        P2PError::ConnectionFailed(format!("{:?}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handshake() {
+        let browser = SigningKey::generate(&mut rand::rngs::OsRng);
+        let gateway = "gateway".to_string();
+        let nonce = new_nonce();
+
+        let hello = LinkMessage::hello(&browser, &gateway, &nonce, TransportHello::local());
+        let hello = LinkMessage::from_text(&hello.to_text().unwrap()).unwrap();
+        let (peer_id, transport) = verify_hello(&gateway, &nonce, &hello).unwrap();
+        assert_eq!(peer_id, peer_id_of(&browser.verifying_key()));
+        assert_eq!(transport, TransportHello::local());
+
+        // Hellos can't be replayed to another challenge or gateway
+        let other = new_nonce();
+        assert_ne!(nonce, other);
+        assert!(matches!(
+            verify_hello(&gateway, &other, &hello),
+            Err(P2PError::PermissionDenied(_))
+        ));
+        assert!(verify_hello(&"elsewhere".to_string(), &nonce, &hello).is_err());
+    }
+
+    #[test]
+    fn test_malformed_hello() {
+        let gateway = "gateway".to_string();
+        let welcome = LinkMessage::Welcome {
+            peer_id: "browser".to_string(),
+            transport: TransportHello::local(),
+        };
+        assert!(matches!(
+            verify_hello(&gateway, "00", &welcome),
+            Err(P2PError::InvalidMessage(_))
+        ));
+
+        let hello = LinkMessage::Hello {
+            key: "zz".to_string(),
+            signature: String::new(),
+            transport: TransportHello::local(),
+        };
+        assert!(verify_hello(&gateway, "00", &hello).is_err());
+        assert!(LinkMessage::from_text("{\"type\":\"unknown\"}").is_err());
+    }
+}
@@ -0,0 +1,282 @@
+//! Surfacing sync conflicts to application code.
+//!
+//! Merging changes from a peer can leave fields with values written
+//! concurrently by several actors. The state engine's conflict policies
+//! resolve some of them; the rest merge silently to Automerge's winner. The
+//! [`ConflictMonitor`] reports every merge that produced new conflicts as a
+//! [`SyncConflict`], so applications can show them or fix them up with
+//! [`vudo_state::DocumentHandle::resolve_conflict`].
+//!
+//! An advisor (any [`ConflictHandler`], e.g. vudo-ai's `AiConflictHandler`)
+//! can be set to suggest a winner for each conflict left unresolved. Its
+//! suggestions are only reported, never applied.
+
+use crate::sync_protocol::PeerId;
+use parking_lot::RwLock;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tracing::info;
+use vudo_state::{ConcurrentValue, ConflictHandler, ConflictReport, DocumentId, FieldConflict};
+
+/// Conflicts produced by merging changes from a peer.
+#[derive(Debug, Clone)]
+pub struct SyncConflict {
+    /// Peer the changes came from.
+    pub peer_id: PeerId,
+    /// Document namespace.
+    pub namespace: String,
+    /// Document key.
+    pub id: String,
+    /// Conflicting fields.
+    pub keys: Vec<String>,
+    /// Actors that wrote the conflicting values (hex actor IDs).
+    pub actors: Vec<String>,
+    /// Fields resolved by a conflict policy.
+    pub resolved: Vec<String>,
+    /// Concurrent values of the fields left unresolved.
+    pub unresolved: Vec<FieldConflict>,
+    /// Winners suggested by the advisor for unresolved fields.
+    pub suggestions: Vec<ConflictSuggestion>,
+    /// When the conflicts were detected (milliseconds since epoch).
+    pub detected_at: u64,
+}
+
+/// Winner suggested for an unresolved field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConflictSuggestion {
+    /// Field key.
+    pub key: String,
+    /// Suggested value.
+    pub value: ConcurrentValue,
+}
+
+/// Reports sync conflicts to subscribers.
+#[derive(Default)]
+pub struct ConflictMonitor {
+    /// Event subscribers.
+    subscribers: RwLock<Vec<mpsc::UnboundedSender<SyncConflict>>>,
+    /// Suggests winners for unresolved fields.
+    advisor: RwLock<Option<Arc<dyn ConflictHandler>>>,
+}
+
+impl ConflictMonitor {
+    /// Create a monitor without subscribers or advisor.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Subscribe to sync conflicts.
+    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<SyncConflict> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.subscribers.write().push(tx);
+        rx
+    }
+
+    /// Set the advisor suggesting winners for unresolved fields.
+    pub fn set_advisor(&self, advisor: Arc<dyn ConflictHandler>) {
+        *self.advisor.write() = Some(advisor);
+    }
+
+    /// Stop suggesting winners.
+    pub fn clear_advisor(&self) {
+        *self.advisor.write() = None;
+    }
+
+    /// Report the conflicts left by merging changes from `peer`.
+    ///
+    /// `before` and `after` are the document's conflicts before and after
+    /// the merge, and `report` what the conflict policies did afterwards.
+    /// Only conflicts the merge introduced or changed are reported. Returns
+    /// the event, if any.
+    pub fn record(
+        &self,
+        peer: &PeerId,
+        document_id: &DocumentId,
+        before: &[FieldConflict],
+        after: Vec<FieldConflict>,
+        report: &ConflictReport,
+    ) -> Option<SyncConflict> {
+        let new: Vec<FieldConflict> = after
+            .into_iter()
+            .filter(|conflict| !before.contains(conflict))
+            .collect();
+        if new.is_empty() {
+            return None;
+        }
+
+        let mut actors: Vec<String> = new
+            .iter()
+            .flat_map(|conflict| conflict.values.iter())
+            .map(|value| value.actor.to_string())
+            .collect();
+        actors.sort();
+        actors.dedup();
+
+        let keys: Vec<String> = new.iter().map(|conflict| conflict.key.clone()).collect();
+        let resolved: Vec<String> = keys
+            .iter()
+            .filter(|key| report.resolved.contains(key))
+            .cloned()
+            .collect();
+        let unresolved: Vec<FieldConflict> = new
+            .into_iter()
+            .filter(|conflict| !resolved.contains(&conflict.key))
+            .collect();
+
+        let advisor = self.advisor.read().clone();
+        let suggestions = match advisor {
+            Some(advisor) => unresolved
+                .iter()
+                .filter_map(|conflict| {
+                    advisor
+                        .choose(document_id, conflict)
+                        .map(|value| ConflictSuggestion {
+                            key: conflict.key.clone(),
+                            value,
+                        })
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        info!(
+            "Sync with peer {} left {} conflicting fields in {} ({} resolved)",
+            peer,
+            keys.len(),
+            document_id,
+            resolved.len()
+        );
+
+        let event = SyncConflict {
+            peer_id: peer.clone(),
+            namespace: document_id.namespace.clone(),
+            id: document_id.key.clone(),
+            keys,
+            actors,
+            resolved,
+            unresolved,
+            suggestions,
+            detected_at: current_timestamp(),
+        };
+        self.emit(event.clone());
+        Some(event)
+    }
+
+    /// Deliver an event to all live subscribers.
+    fn emit(&self, event: SyncConflict) {
+        self.subscribers
+            .write()
+            .retain(|tx| tx.send(event.clone()).is_ok());
+    }
+}
+
+/// Get current timestamp in milliseconds.
+fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use automerge::{ActorId, ScalarValue, Value};
+
+    fn value(actor: u8, s: &str) -> ConcurrentValue {
+        ConcurrentValue {
+            value: Value::Scalar(std::borrow::Cow::Owned(ScalarValue::Str(s.into()))),
+            actor: ActorId::from(vec![actor; 16]),
+            counter: 1,
+            timestamp: 0,
+        }
+    }
+
+    fn conflict(key: &str) -> FieldConflict {
+        FieldConflict {
+            key: key.to_string(),
+            values: vec![value(1, "local"), value(2, "remote")],
+        }
+    }
+
+    struct PreferRemote;
+
+    impl ConflictHandler for PreferRemote {
+        fn choose(&self, _: &DocumentId, conflict: &FieldConflict) -> Option<ConcurrentValue> {
+            conflict.values.last().cloned()
+        }
+    }
+
+    #[test]
+    fn test_record() {
+        let monitor = ConflictMonitor::new();
+        let mut events = monitor.subscribe();
+        let doc = DocumentId::new("users", "alice");
+        let peer = "peer1".to_string();
+
+        // Conflicts that existed before the merge aren't reported again
+        let before = vec![conflict("name")];
+        let report = ConflictReport::default();
+        assert!(monitor
+            .record(&peer, &doc, &before, before.clone(), &report)
+            .is_none());
+
+        let report = ConflictReport {
+            resolved: vec!["age".to_string()],
+            unresolved: vec!["email".to_string()],
+        };
+        let after = vec![conflict("name"), conflict("age"), conflict("email")];
+        monitor
+            .record(&peer, &doc, &before, after, &report)
+            .unwrap();
+
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.peer_id, "peer1");
+        assert_eq!(
+            (event.namespace.as_str(), event.id.as_str()),
+            ("users", "alice")
+        );
+        assert_eq!(event.keys, vec!["age", "email"]);
+        assert_eq!(event.actors.len(), 2);
+        assert_eq!(event.resolved, vec!["age"]);
+        assert_eq!(event.unresolved, vec![conflict("email")]);
+        assert!(event.suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_advisor_suggestions() {
+        let monitor = ConflictMonitor::new();
+        monitor.set_advisor(Arc::new(PreferRemote));
+        let doc = DocumentId::new("users", "alice");
+
+        let event = monitor
+            .record(
+                &"peer1".to_string(),
+                &doc,
+                &[],
+                vec![conflict("name")],
+                &ConflictReport::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            event.suggestions,
+            vec![ConflictSuggestion {
+                key: "name".to_string(),
+                value: value(2, "remote"),
+            }]
+        );
+
+        monitor.clear_advisor();
+        let event = monitor
+            .record(
+                &"peer1".to_string(),
+                &doc,
+                &[],
+                vec![conflict("title")],
+                &ConflictReport::default(),
+            )
+            .unwrap();
+        assert!(event.suggestions.is_empty());
+    }
+}
@@ -0,0 +1,464 @@
+//! Managed pool of peer connections.
+//!
+//! The [`ConnectionPool`] keeps the bookkeeping of the Iroh adapter's
+//! connections: it enforces the connection limit, tracks when each
+//! connection was last used, and reports every change as a
+//! [`ConnectionEvent`]. Connections idle for longer than the idle timeout
+//! are closed. When a connection this node dialed drops, the peer is
+//! redialed with exponential backoff until a dial succeeds or the attempts
+//! run out; connections closed on purpose (or for being idle) are not
+//! redialed.
+//!
+//! The pool doesn't dial or close anything itself. The Iroh adapter's pool
+//! maintainer (see [`IrohAdapter::pool_maintainer`]) acts on it.
+//!
+//! [`IrohAdapter::pool_maintainer`]: crate::iroh_adapter::IrohAdapter::pool_maintainer
+
+use crate::sync_protocol::PeerId;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{debug, info};
+
+/// Connection pool configuration.
+#[derive(Debug, Clone)]
+pub struct ConnectionPoolConfig {
+    /// Close connections unused for this long (kept open when `None`).
+    pub idle_timeout: Option<Duration>,
+    /// Redial peers whose connection dropped.
+    pub auto_reconnect: bool,
+    /// Delay before the first redial.
+    pub initial_backoff: Duration,
+    /// Maximum delay between redials.
+    pub max_backoff: Duration,
+    /// Redials of a peer before giving up.
+    pub max_reconnect_attempts: u32,
+    /// Interval between checks for idle connections.
+    pub maintenance_interval: Duration,
+}
+
+impl Default for ConnectionPoolConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Some(Duration::from_secs(10 * 60)),
+            auto_reconnect: true,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            max_reconnect_attempts: 10,
+            maintenance_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ConnectionPoolConfig {
+    /// Backoff before redial number `attempt` (starting at 1).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Why a connection ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// Closed by this node.
+    Closed,
+    /// Closed after being idle for the idle timeout.
+    Idle,
+    /// The connection dropped.
+    Lost(String),
+}
+
+/// Change in the pool's connections.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// A connection to a peer was established.
+    Connected {
+        /// Peer ID.
+        peer_id: PeerId,
+        /// Whether this node dialed the peer.
+        dialed: bool,
+    },
+    /// A connection ended.
+    Disconnected {
+        /// Peer ID.
+        peer_id: PeerId,
+        /// Why it ended.
+        reason: DisconnectReason,
+    },
+    /// A dropped peer is about to be redialed.
+    Reconnecting {
+        /// Peer ID.
+        peer_id: PeerId,
+        /// Redial attempt, starting at 1.
+        attempt: u32,
+        /// Delay before the redial.
+        delay: Duration,
+    },
+}
+
+/// A pooled connection.
+#[derive(Debug, Clone)]
+struct PooledConnection {
+    /// Identifies the connection among successive ones to the same peer.
+    connection_id: usize,
+    /// Whether this node dialed the peer (and can redial it).
+    dialed: bool,
+    /// Last time a message was sent or received.
+    last_active: Instant,
+}
+
+/// Connection that stopped receiving, reported by its receiver.
+type Closed = (PeerId, usize, String);
+
+/// Managed pool of peer connections.
+pub struct ConnectionPool {
+    /// Configuration.
+    config: ConnectionPoolConfig,
+    /// Maximum concurrent connections.
+    max_connections: usize,
+    /// Open connections.
+    connections: RwLock<HashMap<PeerId, PooledConnection>>,
+    /// Redial attempts made so far, for peers being reconnected.
+    reconnecting: RwLock<HashMap<PeerId, u32>>,
+    /// Connections that stopped receiving.
+    closed_tx: mpsc::UnboundedSender<Closed>,
+    /// Receiver of closed connections, shared by maintainer restarts.
+    closed_rx: tokio::sync::Mutex<mpsc::UnboundedReceiver<Closed>>,
+    /// Event subscribers.
+    subscribers: RwLock<Vec<mpsc::UnboundedSender<ConnectionEvent>>>,
+}
+
+impl ConnectionPool {
+    /// Create a new connection pool.
+    pub fn new(config: ConnectionPoolConfig, max_connections: usize) -> Self {
+        let (closed_tx, closed_rx) = mpsc::unbounded_channel();
+        Self {
+            config,
+            max_connections,
+            connections: RwLock::new(HashMap::new()),
+            reconnecting: RwLock::new(HashMap::new()),
+            closed_tx,
+            closed_rx: tokio::sync::Mutex::new(closed_rx),
+            subscribers: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Get the configuration.
+    pub fn config(&self) -> &ConnectionPoolConfig {
+        &self.config
+    }
+
+    /// Subscribe to connection events.
+    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<ConnectionEvent> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.subscribers.write().push(tx);
+        rx
+    }
+
+    /// Get the number of open connections.
+    pub fn len(&self) -> usize {
+        self.connections.read().len()
+    }
+
+    /// Check whether no connections are open.
+    pub fn is_empty(&self) -> bool {
+        self.connections.read().is_empty()
+    }
+
+    /// Check whether the connection limit is reached.
+    pub fn is_full(&self) -> bool {
+        self.len() >= self.max_connections
+    }
+
+    /// Check whether a peer is connected.
+    pub fn contains(&self, peer_id: &PeerId) -> bool {
+        self.connections.read().contains_key(peer_id)
+    }
+
+    /// Check whether a peer is being redialed.
+    pub fn is_reconnecting(&self, peer_id: &PeerId) -> bool {
+        self.reconnecting.read().contains_key(peer_id)
+    }
+
+    /// Add a new connection, ending any redials of the peer.
+    pub fn add(&self, peer_id: &PeerId, connection_id: usize, dialed: bool) {
+        self.connections.write().insert(
+            peer_id.clone(),
+            PooledConnection {
+                connection_id,
+                dialed,
+                last_active: Instant::now(),
+            },
+        );
+        self.reconnecting.write().remove(peer_id);
+        self.emit(ConnectionEvent::Connected {
+            peer_id: peer_id.clone(),
+            dialed,
+        });
+    }
+
+    /// Record activity on a peer's connection.
+    pub fn touch(&self, peer_id: &PeerId) {
+        if let Some(connection) = self.connections.write().get_mut(peer_id) {
+            connection.last_active = Instant::now();
+        }
+    }
+
+    /// Remove a connection closed by this node, cancelling redials.
+    ///
+    /// Returns whether the peer was connected.
+    pub fn remove(&self, peer_id: &PeerId, reason: DisconnectReason) -> bool {
+        self.reconnecting.write().remove(peer_id);
+        if self.connections.write().remove(peer_id).is_none() {
+            return false;
+        }
+        self.emit(ConnectionEvent::Disconnected {
+            peer_id: peer_id.clone(),
+            reason,
+        });
+        true
+    }
+
+    /// Report that a connection stopped receiving.
+    pub fn report_closed(&self, peer_id: &PeerId, connection_id: usize, error: String) {
+        let _ = self.closed_tx.send((peer_id.clone(), connection_id, error));
+    }
+
+    /// Wait for the next connection that dropped.
+    ///
+    /// Returns the peer and whether it should be redialed. Reports of
+    /// connections already removed or replaced are skipped.
+    pub async fn next_dropped(&self) -> Option<(PeerId, bool)> {
+        let mut closed_rx = self.closed_rx.lock().await;
+        loop {
+            let (peer_id, connection_id, error) = closed_rx.recv().await?;
+            let dropped = {
+                let mut connections = self.connections.write();
+                match connections.get(&peer_id) {
+                    Some(connection) if connection.connection_id == connection_id => {
+                        connections.remove(&peer_id)
+                    }
+                    _ => None,
+                }
+            };
+            let Some(dropped) = dropped else {
+                continue;
+            };
+
+            info!("Connection to peer {} lost: {}", peer_id, error);
+            self.emit(ConnectionEvent::Disconnected {
+                peer_id: peer_id.clone(),
+                reason: DisconnectReason::Lost(error),
+            });
+            return Some((peer_id, dropped.dialed && self.config.auto_reconnect));
+        }
+    }
+
+    /// Start redialing a peer.
+    pub fn start_reconnect(&self, peer_id: &PeerId) {
+        self.reconnecting.write().insert(peer_id.clone(), 0);
+    }
+
+    /// Get the delay before the next redial of a peer.
+    ///
+    /// Returns `None` once the peer reconnected, the redials were cancelled
+    /// or the attempts ran out.
+    pub fn next_attempt(&self, peer_id: &PeerId) -> Option<Duration> {
+        let attempt = {
+            let mut reconnecting = self.reconnecting.write();
+            let attempts = reconnecting.get_mut(peer_id)?;
+            if *attempts >= self.config.max_reconnect_attempts {
+                reconnecting.remove(peer_id);
+                info!("Giving up reconnecting to peer {}", peer_id);
+                return None;
+            }
+            *attempts += 1;
+            *attempts
+        };
+
+        let delay = self.config.backoff(attempt);
+        debug!(
+            "Reconnecting to peer {} in {:?} (attempt {})",
+            peer_id, delay, attempt
+        );
+        self.emit(ConnectionEvent::Reconnecting {
+            peer_id: peer_id.clone(),
+            attempt,
+            delay,
+        });
+        Some(delay)
+    }
+
+    /// Get the peers whose connection exceeded the idle timeout.
+    pub fn idle_peers(&self) -> Vec<PeerId> {
+        let Some(idle_timeout) = self.config.idle_timeout else {
+            return Vec::new();
+        };
+        self.connections
+            .read()
+            .iter()
+            .filter(|(_, connection)| connection.last_active.elapsed() >= idle_timeout)
+            .map(|(peer_id, _)| peer_id.clone())
+            .collect()
+    }
+
+    /// Deliver an event to all live subscribers.
+    fn emit(&self, event: ConnectionEvent) {
+        self.subscribers
+            .write()
+            .retain(|tx| tx.send(event.clone()).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(config: ConnectionPoolConfig) -> ConnectionPool {
+        ConnectionPool::new(config, 2)
+    }
+
+    fn peer(name: &str) -> PeerId {
+        name.to_string()
+    }
+
+    #[test]
+    fn test_backoff() {
+        let config = ConnectionPoolConfig {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+            ..Default::default()
+        };
+        assert_eq!(config.backoff(1), Duration::from_millis(100));
+        assert_eq!(config.backoff(2), Duration::from_millis(200));
+        assert_eq!(config.backoff(3), Duration::from_millis(400));
+        assert_eq!(config.backoff(4), Duration::from_millis(500));
+        assert_eq!(config.backoff(100), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_limit_and_events() {
+        let pool = pool(ConnectionPoolConfig::default());
+        let mut events = pool.subscribe();
+
+        pool.add(&peer("a"), 1, true);
+        assert!(!pool.is_full());
+        pool.add(&peer("b"), 2, false);
+        assert!(pool.is_full());
+
+        assert!(pool.remove(&peer("a"), DisconnectReason::Closed));
+        assert!(!pool.remove(&peer("a"), DisconnectReason::Closed));
+        assert!(!pool.is_full());
+
+        let expected = [
+            ConnectionEvent::Connected {
+                peer_id: peer("a"),
+                dialed: true,
+            },
+            ConnectionEvent::Connected {
+                peer_id: peer("b"),
+                dialed: false,
+            },
+            ConnectionEvent::Disconnected {
+                peer_id: peer("a"),
+                reason: DisconnectReason::Closed,
+            },
+        ];
+        for event in expected {
+            assert_eq!(events.try_recv().unwrap(), event);
+        }
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_connections() {
+        let pool = pool(ConnectionPoolConfig::default());
+        pool.add(&peer("a"), 1, true);
+        pool.add(&peer("b"), 2, false);
+
+        // Reports of replaced or removed connections are skipped
+        pool.add(&peer("a"), 3, true);
+        pool.report_closed(&peer("a"), 1, "stale".to_string());
+        pool.remove(&peer("b"), DisconnectReason::Closed);
+        pool.report_closed(&peer("b"), 2, "closed".to_string());
+
+        let mut events = pool.subscribe();
+        pool.report_closed(&peer("a"), 3, "timed out".to_string());
+        assert_eq!(pool.next_dropped().await, Some((peer("a"), true)));
+        assert_eq!(
+            events.try_recv().unwrap(),
+            ConnectionEvent::Disconnected {
+                peer_id: peer("a"),
+                reason: DisconnectReason::Lost("timed out".to_string()),
+            }
+        );
+        assert!(pool.is_empty());
+
+        // Peers that dialed us aren't redialed
+        pool.add(&peer("c"), 4, false);
+        pool.report_closed(&peer("c"), 4, "reset".to_string());
+        assert_eq!(pool.next_dropped().await, Some((peer("c"), false)));
+    }
+
+    #[test]
+    fn test_reconnect_attempts() {
+        let pool = pool(ConnectionPoolConfig {
+            initial_backoff: Duration::from_millis(10),
+            max_reconnect_attempts: 2,
+            ..Default::default()
+        });
+        let mut events = pool.subscribe();
+
+        let a = peer("a");
+        pool.start_reconnect(&a);
+        assert_eq!(pool.next_attempt(&a), Some(Duration::from_millis(10)));
+        assert_eq!(pool.next_attempt(&a), Some(Duration::from_millis(20)));
+        assert_eq!(pool.next_attempt(&peer("a")), None);
+        assert!(!pool.is_reconnecting(&peer("a")));
+        assert_eq!(
+            events.try_recv().unwrap(),
+            ConnectionEvent::Reconnecting {
+                peer_id: peer("a"),
+                attempt: 1,
+                delay: Duration::from_millis(10),
+            }
+        );
+
+        // Reconnecting or closing the peer ends the redials
+        pool.start_reconnect(&peer("b"));
+        pool.add(&peer("b"), 1, true);
+        assert_eq!(pool.next_attempt(&peer("b")), None);
+        pool.start_reconnect(&peer("c"));
+        pool.remove(&peer("c"), DisconnectReason::Closed);
+        assert_eq!(pool.next_attempt(&peer("c")), None);
+    }
+
+    #[test]
+    fn test_idle_peers() {
+        let pool = pool(ConnectionPoolConfig {
+            idle_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        });
+        pool.add(&peer("a"), 1, true);
+        pool.add(&peer("b"), 2, true);
+        assert!(pool.idle_peers().is_empty());
+
+        std::thread::sleep(Duration::from_millis(60));
+        pool.touch(&peer("b"));
+        assert_eq!(pool.idle_peers(), vec![peer("a")]);
+
+        let pool = ConnectionPool::new(
+            ConnectionPoolConfig {
+                idle_timeout: None,
+                ..Default::default()
+            },
+            2,
+        );
+        pool.add(&peer("a"), 1, true);
+        assert!(pool.idle_peers().is_empty());
+    }
+}
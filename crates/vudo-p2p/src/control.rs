@@ -0,0 +1,474 @@
+//! Local control API for a running node.
+//!
+//! A [`ControlServer`] answers plain HTTP requests on a local address so that
+//! operator tools (such as `vudo top`) can inspect a node without joining the
+//! P2P network:
+//!
+//! - `GET /status` returns a [`NodeStatus`] snapshot as JSON
+//! - `GET /metrics` returns state engine metrics in Prometheus text format
+//!
+//! The API is read-only and binds to loopback by default.
+
+use crate::bandwidth::BandwidthManager;
+use crate::error::{P2PError, Result};
+use crate::file_transfer::FileTransferManager;
+use crate::iroh_adapter::IrohAdapter;
+use crate::relay::RelayHealth;
+use crate::sync_protocol::{PeerId, SyncProgress, SyncProtocol, SyncSession};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+use vudo_state::StateEngine;
+
+/// Default control API address.
+pub const DEFAULT_CONTROL_ADDR: &str = "127.0.0.1:7171";
+
+/// Number of recent errors kept for the control API.
+pub const RECENT_ERROR_CAPACITY: usize = 64;
+
+/// Maximum size of a request head.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// Snapshot of a running node.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NodeStatus {
+    /// Node ID.
+    pub node_id: String,
+    /// Node name.
+    pub node_name: String,
+    /// Seconds since the node was created.
+    pub uptime_secs: u64,
+    /// Documents in the state engine, most recently modified first.
+    pub documents: Vec<DocumentStatus>,
+    /// Per-peer document sync sessions, most recently synced first.
+    pub sessions: Vec<SyncSession>,
+    /// Per-peer document sync progress, most recently updated first.
+    #[serde(default)]
+    pub progress: Vec<SyncProgress>,
+    /// Connected peers.
+    pub peers: Vec<PeerStatus>,
+    /// Health of the configured relays.
+    #[serde(default)]
+    pub relays: Vec<RelayHealth>,
+    /// Aggregate bandwidth.
+    pub bandwidth: BandwidthStatus,
+    /// Queue depths.
+    pub queues: QueueStatus,
+    /// Recent errors, newest first.
+    pub recent_errors: Vec<ErrorEntry>,
+}
+
+/// A document in the state engine.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentStatus {
+    /// Document namespace.
+    pub namespace: String,
+    /// Document key.
+    pub key: String,
+    /// Document size in bytes.
+    pub size: usize,
+    /// Document version (number of changes).
+    pub version: u64,
+    /// Last modification timestamp (Unix epoch milliseconds).
+    pub last_modified: u64,
+}
+
+/// A connected peer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerStatus {
+    /// Peer ID.
+    pub peer_id: PeerId,
+    /// Is this a direct connection (vs relay)?
+    pub is_direct: bool,
+    /// Relay carrying the connection, if relayed.
+    #[serde(default)]
+    pub relay_url: Option<String>,
+    /// Seconds since the connection was established.
+    pub connected_secs: u64,
+    /// Smoothed round-trip time in milliseconds, once estimated.
+    pub rtt_ms: Option<f64>,
+    /// Estimated link capacity (bytes/sec).
+    pub capacity: u64,
+    /// Observed send rate (bytes/sec).
+    pub send_rate: u64,
+    /// Observed receive rate (bytes/sec).
+    pub receive_rate: u64,
+    /// Packet loss rate over the last sample interval.
+    pub loss_rate: f64,
+    /// Number of messages sent.
+    pub messages_sent: u64,
+    /// Number of messages received.
+    pub messages_received: u64,
+    /// Bytes sent.
+    pub bytes_sent: u64,
+    /// Bytes received.
+    pub bytes_received: u64,
+}
+
+/// Aggregate bandwidth of the node.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BandwidthStatus {
+    /// Current send rate (bytes/sec).
+    pub send_rate: u64,
+    /// Current receive rate (bytes/sec).
+    pub receive_rate: u64,
+    /// Bytes sent in current window.
+    pub bytes_sent: u64,
+    /// Bytes received in current window.
+    pub bytes_received: u64,
+    /// Current rate limit (bytes/sec).
+    pub rate_limit: u64,
+    /// Is connection metered?
+    pub is_metered: bool,
+}
+
+/// Queue depths of the node.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueStatus {
+    /// Offline operations waiting to be applied.
+    pub operations: usize,
+    /// Scheduled sync tasks.
+    pub sync_tasks: usize,
+    /// Incoming file offers waiting for a decision.
+    pub file_offers: usize,
+    /// Active transactions.
+    pub transactions: usize,
+}
+
+/// A recorded error.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorEntry {
+    /// Timestamp (Unix epoch milliseconds).
+    pub timestamp: u64,
+    /// Error message.
+    pub message: String,
+}
+
+/// Bounded log of recent errors.
+#[derive(Debug)]
+pub struct ErrorLog {
+    /// Entries, oldest first.
+    entries: Mutex<VecDeque<ErrorEntry>>,
+    /// Maximum number of entries.
+    capacity: usize,
+}
+
+impl ErrorLog {
+    /// Create an empty log holding at most `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    /// Record an error, evicting the oldest entry when full.
+    pub fn record(&self, message: impl Into<String>) {
+        let mut entries = self.entries.lock();
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(ErrorEntry {
+            timestamp: now_millis(),
+            message: message.into(),
+        });
+    }
+
+    /// Recent errors, newest first.
+    pub fn recent(&self) -> Vec<ErrorEntry> {
+        self.entries.lock().iter().rev().cloned().collect()
+    }
+}
+
+impl Default for ErrorLog {
+    fn default() -> Self {
+        Self::new(RECENT_ERROR_CAPACITY)
+    }
+}
+
+/// Source of the data served by the control API.
+pub trait StatusProvider: Send + Sync + 'static {
+    /// Take a status snapshot.
+    fn status(&self) -> NodeStatus;
+
+    /// Render metrics in Prometheus text format.
+    fn metrics(&self) -> String;
+}
+
+/// Reads node status from the P2P components.
+pub(crate) struct NodeProbe {
+    pub(crate) node_name: String,
+    pub(crate) started_at: Instant,
+    pub(crate) state_engine: Arc<StateEngine>,
+    pub(crate) iroh: Arc<IrohAdapter>,
+    pub(crate) sync_protocol: Arc<SyncProtocol>,
+    pub(crate) bandwidth: Arc<BandwidthManager>,
+    pub(crate) file_transfers: Arc<FileTransferManager>,
+    pub(crate) errors: Arc<ErrorLog>,
+}
+
+impl StatusProvider for NodeProbe {
+    fn status(&self) -> NodeStatus {
+        let mut documents: Vec<DocumentStatus> = self
+            .state_engine
+            .store
+            .list_all()
+            .into_iter()
+            .filter_map(|id| self.state_engine.store.get(&id).ok())
+            .map(|handle| {
+                let metadata = handle.metadata();
+                DocumentStatus {
+                    namespace: handle.id.namespace.clone(),
+                    key: handle.id.key.clone(),
+                    size: metadata.size,
+                    version: metadata.version,
+                    last_modified: metadata.last_modified,
+                }
+            })
+            .collect();
+        documents.sort_by(|a, b| b.last_modified.cmp(&a.last_modified));
+
+        let peers = self
+            .iroh
+            .connected_peers()
+            .into_iter()
+            .filter_map(|peer_id| {
+                let metadata = self.iroh.get_metadata(&peer_id)?;
+                let link = self.iroh.link_estimate(&peer_id);
+                Some(PeerStatus {
+                    rtt_ms: link.map(|l| l.rtt.as_secs_f64() * 1000.0),
+                    capacity: link.map_or(0, |l| l.capacity),
+                    send_rate: link.map_or(0, |l| l.send_rate),
+                    receive_rate: link.map_or(0, |l| l.receive_rate),
+                    loss_rate: link.map_or(0.0, |l| l.loss_rate),
+                    peer_id,
+                    is_direct: metadata.is_direct,
+                    relay_url: metadata.relay_url,
+                    connected_secs: metadata.established_at.elapsed().as_secs(),
+                    messages_sent: metadata.messages_sent,
+                    messages_received: metadata.messages_received,
+                    bytes_sent: metadata.bytes_sent,
+                    bytes_received: metadata.bytes_received,
+                })
+            })
+            .collect();
+
+        let bandwidth = self.bandwidth.stats();
+        let engine = self.state_engine.stats();
+
+        NodeStatus {
+            node_id: self.iroh.node_id().to_string(),
+            node_name: self.node_name.clone(),
+            uptime_secs: self.started_at.elapsed().as_secs(),
+            documents,
+            sessions: self.sync_protocol.sessions(),
+            progress: self.sync_protocol.all_progress(),
+            peers,
+            relays: self.iroh.relay_selector().health(),
+            bandwidth: BandwidthStatus {
+                send_rate: bandwidth.send_rate,
+                receive_rate: bandwidth.receive_rate,
+                bytes_sent: bandwidth.bytes_sent,
+                bytes_received: bandwidth.bytes_received,
+                rate_limit: bandwidth.rate_limit,
+                is_metered: bandwidth.is_metered,
+            },
+            queues: QueueStatus {
+                operations: engine.queue_length,
+                sync_tasks: self.bandwidth.queue_length(),
+                file_offers: self.file_transfers.pending_offers().len(),
+                transactions: engine.active_transaction_count,
+            },
+            recent_errors: self.errors.recent(),
+        }
+    }
+
+    fn metrics(&self) -> String {
+        self.state_engine.metrics().render_prometheus()
+    }
+}
+
+/// HTTP server for the control API.
+pub struct ControlServer {
+    /// Bound listener.
+    listener: TcpListener,
+}
+
+impl ControlServer {
+    /// Bind the control API to an address.
+    pub async fn bind(addr: SocketAddr) -> Result<Self> {
+        let listener = TcpListener::bind(addr).await.map_err(|e| {
+            P2PError::Internal(format!("Failed to bind control API on {}: {}", addr, e))
+        })?;
+        Ok(Self { listener })
+    }
+
+    /// Get the bound address.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener
+            .local_addr()
+            .map_err(|e| P2PError::Internal(e.to_string()))
+    }
+
+    /// Serve requests until the task is aborted.
+    pub fn spawn(self, provider: Arc<dyn StatusProvider>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            if let Ok(addr) = self.listener.local_addr() {
+                info!("Control API listening on http://{}", addr);
+            }
+
+            loop {
+                match self.listener.accept().await {
+                    Ok((stream, peer)) => {
+                        let provider = Arc::clone(&provider);
+                        tokio::spawn(async move {
+                            if let Err(e) = handle_connection(stream, provider.as_ref()).await {
+                                debug!("Control API request from {} failed: {}", peer, e);
+                            }
+                        });
+                    }
+                    Err(e) => warn!("Control API accept failed: {}", e),
+                }
+            }
+        })
+    }
+}
+
+/// Answer a single request and close the connection.
+async fn handle_connection(
+    mut stream: TcpStream,
+    provider: &dyn StatusProvider,
+) -> std::io::Result<()> {
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 || buf.len() + n > MAX_REQUEST_SIZE {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    let head = String::from_utf8_lossy(&buf);
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    let target = request_line.next().unwrap_or_default();
+    let path = target.split('?').next().unwrap_or_default();
+
+    let (status, content_type, body) = match (method, path) {
+        ("GET", "/status") => match serde_json::to_vec(&provider.status()) {
+            Ok(body) => ("200 OK", "application/json", body),
+            Err(e) => (
+                "500 Internal Server Error",
+                "text/plain",
+                e.to_string().into_bytes(),
+            ),
+        },
+        ("GET", "/metrics") => (
+            "200 OK",
+            "text/plain; version=0.0.4",
+            provider.metrics().into_bytes(),
+        ),
+        ("GET", _) => ("404 Not Found", "text/plain", b"not found".to_vec()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            b"method not allowed".to_vec(),
+        ),
+    };
+
+    let header = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(header.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.shutdown().await
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedStatus(NodeStatus);
+
+    impl StatusProvider for FixedStatus {
+        fn status(&self) -> NodeStatus {
+            self.0.clone()
+        }
+
+        fn metrics(&self) -> String {
+            "vudo_documents 1\n".to_string()
+        }
+    }
+
+    async fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[test]
+    fn test_error_log_bounded() {
+        let log = ErrorLog::new(2);
+        log.record("first");
+        log.record("second");
+        log.record("third");
+
+        let recent = log.recent();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].message, "third");
+        assert_eq!(recent[1].message, "second");
+    }
+
+    #[tokio::test]
+    async fn test_control_server_routes() {
+        let status = NodeStatus {
+            node_id: "node-a".to_string(),
+            recent_errors: vec![ErrorEntry {
+                timestamp: 1,
+                message: "boom".to_string(),
+            }],
+            ..Default::default()
+        };
+
+        let server = ControlServer::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.spawn(Arc::new(FixedStatus(status.clone())));
+
+        let response = get(addr, "/status").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        assert_eq!(serde_json::from_str::<NodeStatus>(body).unwrap(), status);
+
+        let response = get(addr, "/metrics").await;
+        assert!(response.ends_with("vudo_documents 1\n"));
+
+        let response = get(addr, "/nope").await;
+        assert!(response.starts_with("HTTP/1.1 404"));
+
+        handle.abort();
+    }
+}
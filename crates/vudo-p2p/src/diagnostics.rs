@@ -0,0 +1,290 @@
+//! NAT traversal diagnostics.
+//!
+//! [`VudoP2P::diagnose`](crate::VudoP2P::diagnose) probes the paths to a
+//! peer (direct UDP, hole punching and relay) and reports what it found as
+//! a [`ConnectivityReport`]: both sides' candidate addresses, the round-trip
+//! time of every path and the path the connection settled on.
+//!
+//! The report's [`Reachability`] matches the connectivity scenarios of the
+//! Iroh proof of concept:
+//!
+//! - S1, same LAN: [`Reachability::Lan`]
+//! - S2, different LANs: [`Reachability::HolePunched`]
+//! - S3 to S5, cellular, symmetric NAT or a restrictive firewall:
+//!   [`Reachability::Relayed`]
+//! - S6, partition healing: see the reconnect latency in
+//!   [`SyncStats::reconnects`](crate::sync_protocol::SyncStats::reconnects)
+
+use crate::sync_protocol::PeerId;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+/// Longest time a probe waits for a direct path.
+pub const PROBE_DURATION: Duration = Duration::from_secs(5);
+
+/// Interval between path samples during a probe.
+pub(crate) const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Kind of network path to a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathKind {
+    /// UDP to one of the peer's addresses, possibly after hole punching.
+    Direct,
+    /// Through a relay server.
+    Relay,
+}
+
+/// A path to a peer and how it answered.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathProbe {
+    /// Kind of path.
+    pub kind: PathKind,
+    /// Socket address of a direct path, or URL of a relay.
+    pub address: String,
+    /// Round-trip time, if the path answered during the probe.
+    pub rtt: Option<Duration>,
+}
+
+/// How a peer can be reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reachability {
+    /// Directly, over a local network address.
+    Lan,
+    /// Directly, over a public address that was reachable from the start.
+    Direct,
+    /// Directly, once hole punching succeeded over a relayed connection.
+    HolePunched,
+    /// Only through a relay.
+    Relayed,
+    /// Not at all.
+    Unreachable,
+}
+
+/// Result of probing the paths to a peer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectivityReport {
+    /// Peer ID.
+    pub peer_id: PeerId,
+    /// This node's candidate addresses.
+    pub local_candidates: Vec<SocketAddr>,
+    /// Every path to the peer: its candidate addresses and its relay.
+    pub paths: Vec<PathProbe>,
+    /// Path the connection settled on.
+    pub chosen_path: Option<PathProbe>,
+    /// How the peer can be reached.
+    pub reachability: Reachability,
+    /// Time to connect, if the probe had to connect.
+    pub connect_time: Option<Duration>,
+    /// Time until a direct path carried the connection, if one did.
+    pub time_to_direct: Option<Duration>,
+    /// Time the probe took.
+    pub probe_duration: Duration,
+}
+
+/// The endpoint's paths to a peer at one point of a probe.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PathSample {
+    /// Path carrying the connection, with its address.
+    pub(crate) chosen: Option<(PathKind, String)>,
+    /// The peer's direct addresses, with their round-trip time.
+    pub(crate) direct: Vec<(SocketAddr, Option<Duration>)>,
+    /// The peer's relay, with its round-trip time.
+    pub(crate) relay: Option<(String, Option<Duration>)>,
+}
+
+/// Collects path samples into a [`ConnectivityReport`].
+pub(crate) struct ConnectivityProbe {
+    peer_id: PeerId,
+    local_candidates: Vec<SocketAddr>,
+    connect_time: Option<Duration>,
+    started: Instant,
+    /// Latest round-trip time of every path seen.
+    paths: BTreeMap<(PathKind, String), Option<Duration>>,
+    /// Path carrying the connection in the first sample.
+    first: Option<PathKind>,
+    /// Path carrying the connection in the latest sample.
+    chosen: Option<(PathKind, String)>,
+    time_to_direct: Option<Duration>,
+}
+
+impl ConnectivityProbe {
+    pub(crate) fn new(
+        peer_id: PeerId,
+        local_candidates: Vec<SocketAddr>,
+        connect_time: Option<Duration>,
+    ) -> Self {
+        Self {
+            peer_id,
+            local_candidates,
+            connect_time,
+            started: Instant::now(),
+            paths: BTreeMap::new(),
+            first: None,
+            chosen: None,
+            time_to_direct: None,
+        }
+    }
+
+    /// Record a sample of the paths to the peer.
+    pub(crate) fn record(&mut self, sample: PathSample) {
+        let direct = sample
+            .direct
+            .into_iter()
+            .map(|(addr, rtt)| ((PathKind::Direct, addr.to_string()), rtt));
+        let relay = sample.relay.map(|(url, rtt)| ((PathKind::Relay, url), rtt));
+        for (key, rtt) in direct.chain(relay) {
+            let known = self.paths.entry(key).or_default();
+            // Keep the last answer of a path that stopped answering
+            if rtt.is_some() {
+                *known = rtt;
+            }
+        }
+
+        if let Some((kind, _)) = &sample.chosen {
+            self.first.get_or_insert(*kind);
+            if *kind == PathKind::Direct && self.time_to_direct.is_none() {
+                self.time_to_direct = Some(self.elapsed());
+            }
+            self.chosen = sample.chosen;
+        }
+    }
+
+    /// Check whether a direct path carries the connection, so waiting
+    /// longer can't improve it.
+    pub(crate) fn is_settled(&self) -> bool {
+        matches!(self.chosen, Some((PathKind::Direct, _)))
+    }
+
+    /// Finish the probe.
+    pub(crate) fn finish(self) -> ConnectivityReport {
+        let probe_duration = self.elapsed();
+        let paths: Vec<PathProbe> = self
+            .paths
+            .into_iter()
+            .map(|((kind, address), rtt)| PathProbe { kind, address, rtt })
+            .collect();
+        let chosen_path = self.chosen.as_ref().map(|(kind, address)| {
+            let rtt = paths
+                .iter()
+                .find(|path| path.kind == *kind && path.address == *address)
+                .and_then(|path| path.rtt);
+            PathProbe {
+                kind: *kind,
+                address: address.clone(),
+                rtt,
+            }
+        });
+        let reachability = match &self.chosen {
+            Some((PathKind::Direct, address)) if is_local(address) => Reachability::Lan,
+            Some((PathKind::Direct, _)) if self.first == Some(PathKind::Relay) => {
+                Reachability::HolePunched
+            }
+            Some((PathKind::Direct, _)) => Reachability::Direct,
+            Some((PathKind::Relay, _)) => Reachability::Relayed,
+            None => Reachability::Unreachable,
+        };
+
+        ConnectivityReport {
+            peer_id: self.peer_id,
+            local_candidates: self.local_candidates,
+            paths,
+            chosen_path,
+            reachability,
+            connect_time: self.connect_time,
+            time_to_direct: self.time_to_direct,
+            probe_duration,
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+/// Check whether a socket address is on a local network.
+fn is_local(address: &str) -> bool {
+    match address.parse::<SocketAddr>().map(|addr| addr.ip()) {
+        Ok(IpAddr::V4(ip)) => ip.is_private() || ip.is_loopback() || ip.is_link_local(),
+        Ok(IpAddr::V6(ip)) => {
+            let first = ip.segments()[0];
+            // Unique local (fc00::/7) and link-local (fe80::/10) addresses
+            ip.is_loopback() || first & 0xfe00 == 0xfc00 || first & 0xffc0 == 0xfe80
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(chosen: Option<(PathKind, &str)>, direct: &[(&str, Option<u64>)]) -> PathSample {
+        PathSample {
+            chosen: chosen.map(|(kind, address)| (kind, address.to_string())),
+            direct: direct
+                .iter()
+                .map(|(addr, rtt)| (addr.parse().unwrap(), rtt.map(Duration::from_millis)))
+                .collect(),
+            relay: Some((
+                "https://relay.example/".to_string(),
+                Some(Duration::from_millis(80)),
+            )),
+        }
+    }
+
+    #[test]
+    fn test_connectivity_probe() {
+        const RELAY: &str = "https://relay.example/";
+        const PUBLIC: &str = "203.0.113.7:4433";
+
+        // Hole punching upgrades a relayed connection
+        let mut probe = ConnectivityProbe::new("peer".to_string(), Vec::new(), None);
+        probe.record(sample(Some((PathKind::Relay, RELAY)), &[(PUBLIC, None)]));
+        assert!(!probe.is_settled());
+        probe.record(sample(
+            Some((PathKind::Direct, PUBLIC)),
+            &[(PUBLIC, Some(30))],
+        ));
+        assert!(probe.is_settled());
+        let report = probe.finish();
+        assert_eq!(report.reachability, Reachability::HolePunched);
+        assert!(report.time_to_direct.is_some());
+        assert_eq!(report.paths.len(), 2);
+        assert_eq!(report.paths[0].kind, PathKind::Direct);
+        assert_eq!(report.paths[1].rtt, Some(Duration::from_millis(80)));
+        let chosen = report.chosen_path.unwrap();
+        assert_eq!(chosen.address, PUBLIC);
+        assert_eq!(chosen.rtt, Some(Duration::from_millis(30)));
+
+        // Local addresses are reached directly
+        let mut probe = ConnectivityProbe::new("peer".to_string(), Vec::new(), None);
+        let lan = "192.168.1.20:4433";
+        probe.record(sample(Some((PathKind::Direct, lan)), &[(lan, Some(2))]));
+        assert_eq!(probe.finish().reachability, Reachability::Lan);
+
+        // A peer behind a symmetric NAT stays relayed
+        let mut probe = ConnectivityProbe::new("peer".to_string(), Vec::new(), None);
+        probe.record(sample(Some((PathKind::Relay, RELAY)), &[(PUBLIC, None)]));
+        let report = probe.finish();
+        assert_eq!(report.reachability, Reachability::Relayed);
+        assert_eq!(report.paths[0].rtt, None);
+        assert!(report.time_to_direct.is_none());
+
+        let probe = ConnectivityProbe::new("peer".to_string(), Vec::new(), None);
+        assert_eq!(probe.finish().reachability, Reachability::Unreachable);
+    }
+
+    #[test]
+    fn test_is_local() {
+        assert!(is_local("10.0.0.2:1"));
+        assert!(is_local("[fe80::1]:1"));
+        assert!(is_local("[fd12::1]:1"));
+        assert!(!is_local("[2001:db8::1]:1"));
+        assert!(!is_local("8.8.8.8:53"));
+        assert!(!is_local("https://relay.example/"));
+    }
+}
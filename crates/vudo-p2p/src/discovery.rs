@@ -327,6 +327,8 @@ mod tests {
             peer_id: "peer1".to_string(),
             established_at: Instant::now(),
             is_direct: true,
+            relay_url: None,
+            relay: None,
             messages_sent: 10,
             messages_received: 10,
             bytes_sent: 1000,
@@ -32,6 +32,10 @@ pub enum P2PError {
     #[error("Document not found: {0}")]
     DocumentNotFound(String),
 
+    /// Document was deleted (see [`crate::tombstones`]).
+    #[error("Document deleted: {0}")]
+    DocumentDeleted(String),
+
     /// Serialization error.
     #[error("Serialization error: {0}")]
     SerializationError(String),
@@ -87,6 +91,10 @@ pub enum P2PError {
     /// Capability delegation error.
     #[error("Capability delegation error: {0}")]
     CapabilityDelegationError(String),
+
+    /// Identity error.
+    #[error("Identity error: {0}")]
+    IdentityError(#[from] vudo_identity::Error),
 }
 
 impl From<serde_json::Error> for P2PError {
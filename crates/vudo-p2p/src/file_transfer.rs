@@ -0,0 +1,891 @@
+//! Direct device-to-device file transfer.
+//!
+//! Files are content-addressed by their BLAKE3 hash and moved with
+//! iroh-blobs over the same Iroh endpoint, which encrypts connections end to
+//! end. iroh-blobs verifies the data against the hash as it streams, and
+//! keeps partial downloads in its store, so fetching the same hash again
+//! after a dropped connection only requests the missing ranges. A transfer
+//! runs in three steps:
+//!
+//! 1. The sender imports the file into its blob store and offers it (name,
+//!    size, hash and an optional Meadowcap capability).
+//! 2. The receiver evaluates its [`AcceptancePolicy`]. Offers matching a rule
+//!    are accepted straight away, others are either rejected or held for the
+//!    application to decide on via [`FileTransferManager::accept`].
+//! 3. The receiver fetches the blob from the sender and copies it into the
+//!    download directory, under a free name so existing files are never
+//!    overwritten.
+//!
+//! File data is bulk traffic: it counts against the global, per-peer and
+//! [`TRANSFER_PRIORITY`] bandwidth limits, and waits until its whole size
+//! fits them (on the sender before the offer, on the receiver before the
+//! fetch).
+//!
+//! This module keeps the offer and acceptance state; the blob store and the
+//! iroh-blobs protocol live in the Iroh adapter, so browsers (`wasm32`) can't
+//! accept transfers.
+
+use crate::bandwidth::SyncPriority;
+use crate::error::{P2PError, Result};
+use crate::meadowcap::Capability;
+use crate::sync_protocol::{PeerId, SyncMessage};
+use crate::willow_types::{NamespaceId, Path, SubspaceId};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+use tracing::{debug, info};
+
+/// Path component under which offered files are checked against capabilities.
+pub const FILES_PATH_PREFIX: &str = "files";
+
+/// Priority file data is throttled at.
+pub const TRANSFER_PRIORITY: SyncPriority = SyncPriority::Low;
+
+/// Numbered names tried before giving up on saving a download.
+const MAX_NAME_ATTEMPTS: usize = 1000;
+
+/// Transfer ID (hex-encoded BLAKE3 hash of the file content, which is also
+/// its iroh-blobs hash).
+pub type TransferId = String;
+
+/// Offer to send a file to a peer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileOffer {
+    /// Content hash, which also identifies the transfer.
+    pub hash: TransferId,
+    /// File name (without directories).
+    pub name: String,
+    /// File size in bytes.
+    pub size: u64,
+    /// Capability the sender presents for automatic acceptance.
+    pub capability: Option<Capability>,
+}
+
+/// Direction of a transfer, from this node's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferDirection {
+    /// We are sending the file.
+    Outgoing,
+    /// We are receiving the file.
+    Incoming,
+}
+
+/// Transfer progress event.
+#[derive(Debug, Clone)]
+pub enum TransferEvent {
+    /// A peer offered a file that needs an explicit decision.
+    Offered {
+        /// Offering peer.
+        peer_id: PeerId,
+        /// The offer.
+        offer: FileOffer,
+    },
+    /// A transfer was accepted.
+    Accepted {
+        /// Transfer ID.
+        hash: TransferId,
+        /// Remote peer.
+        peer_id: PeerId,
+        /// Direction.
+        direction: TransferDirection,
+    },
+    /// A transfer was rejected.
+    Rejected {
+        /// Transfer ID.
+        hash: TransferId,
+        /// Remote peer.
+        peer_id: PeerId,
+        /// Direction.
+        direction: TransferDirection,
+        /// Rejection reason.
+        reason: String,
+    },
+    /// Bytes were received.
+    Progress {
+        /// Transfer ID.
+        hash: TransferId,
+        /// Remote peer.
+        peer_id: PeerId,
+        /// Direction.
+        direction: TransferDirection,
+        /// Bytes transferred so far.
+        transferred: u64,
+        /// Total size.
+        total: u64,
+    },
+    /// A transfer completed.
+    Completed {
+        /// Transfer ID.
+        hash: TransferId,
+        /// Remote peer.
+        peer_id: PeerId,
+        /// Direction.
+        direction: TransferDirection,
+        /// Local path of the file (sent or received).
+        path: PathBuf,
+    },
+    /// A transfer failed.
+    Failed {
+        /// Transfer ID.
+        hash: TransferId,
+        /// Remote peer.
+        peer_id: PeerId,
+        /// Direction.
+        direction: TransferDirection,
+        /// Error message.
+        error: String,
+    },
+}
+
+/// Rule under which an offer is accepted without asking the application.
+#[derive(Debug, Clone)]
+pub enum AcceptanceRule {
+    /// Accept any offer from this peer.
+    TrustedPeer(PeerId),
+    /// Accept offers carrying a valid capability with write access to
+    /// `files/<name>` in this namespace and subspace.
+    Capability {
+        /// Namespace the capability must be issued for.
+        namespace_id: NamespaceId,
+        /// Subspace the capability must grant access to.
+        subspace_id: SubspaceId,
+    },
+}
+
+impl AcceptanceRule {
+    /// Check whether an offer from a peer matches this rule.
+    pub fn matches(&self, peer_id: &PeerId, offer: &FileOffer) -> bool {
+        match self {
+            AcceptanceRule::TrustedPeer(trusted) => trusted == peer_id,
+            AcceptanceRule::Capability {
+                namespace_id,
+                subspace_id,
+            } => match &offer.capability {
+                Some(cap) => {
+                    let path = Path::from_components([FILES_PATH_PREFIX, offer.name.as_str()]);
+                    cap.namespace_id == *namespace_id
+                        && cap.verify().is_ok()
+                        && cap.can_write(*subspace_id, &path)
+                }
+                None => false,
+            },
+        }
+    }
+}
+
+/// Outcome of evaluating an offer against an acceptance policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AcceptanceDecision {
+    /// Accept the transfer.
+    Accept,
+    /// Reject the transfer.
+    Reject(String),
+    /// Hold the offer until the application decides.
+    Ask,
+}
+
+/// Policy deciding which file offers are accepted.
+#[derive(Debug, Clone)]
+pub struct AcceptancePolicy {
+    /// Rules under which offers are accepted automatically.
+    pub rules: Vec<AcceptanceRule>,
+    /// Largest file accepted (None for no limit).
+    pub max_size: Option<u64>,
+    /// Hold offers matching no rule for the application instead of rejecting them.
+    pub ask_unmatched: bool,
+}
+
+impl Default for AcceptancePolicy {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            max_size: None,
+            ask_unmatched: true,
+        }
+    }
+}
+
+impl AcceptancePolicy {
+    /// Evaluate an offer from a peer.
+    pub fn evaluate(&self, peer_id: &PeerId, offer: &FileOffer) -> AcceptanceDecision {
+        if let Some(max_size) = self.max_size {
+            if offer.size > max_size {
+                return AcceptanceDecision::Reject(format!(
+                    "File size {} exceeds limit of {} bytes",
+                    offer.size, max_size
+                ));
+            }
+        }
+
+        if self.rules.iter().any(|rule| rule.matches(peer_id, offer)) {
+            AcceptanceDecision::Accept
+        } else if self.ask_unmatched {
+            AcceptanceDecision::Ask
+        } else {
+            AcceptanceDecision::Reject("No acceptance rule matched".to_string())
+        }
+    }
+}
+
+/// File transfer configuration.
+#[derive(Debug, Clone)]
+pub struct FileTransferConfig {
+    /// Directory received files are stored in.
+    pub download_dir: PathBuf,
+    /// Directory of the iroh-blobs store holding offered files and partial
+    /// downloads.
+    pub blob_dir: PathBuf,
+    /// Acceptance policy for incoming offers.
+    pub policy: AcceptancePolicy,
+}
+
+impl Default for FileTransferConfig {
+    fn default() -> Self {
+        Self {
+            download_dir: std::env::temp_dir().join("vudo-downloads"),
+            blob_dir: std::env::temp_dir().join("vudo-blobs"),
+            policy: AcceptancePolicy::default(),
+        }
+    }
+}
+
+/// State of a file we are sending.
+#[derive(Debug, Clone)]
+struct OutgoingTransfer {
+    peer_id: PeerId,
+    path: PathBuf,
+}
+
+/// State of a file we are receiving.
+#[derive(Debug, Clone)]
+struct IncomingTransfer {
+    peer_id: PeerId,
+    offer: FileOffer,
+    accepted: bool,
+}
+
+/// Manager for direct file transfers.
+pub struct FileTransferManager {
+    /// Configuration.
+    config: RwLock<FileTransferConfig>,
+    /// Files we offered, keyed by (peer, hash).
+    outgoing: RwLock<HashMap<(PeerId, TransferId), OutgoingTransfer>>,
+    /// Files offered to us, keyed by hash.
+    incoming: RwLock<HashMap<TransferId, IncomingTransfer>>,
+    /// Event subscribers.
+    subscribers: RwLock<Vec<mpsc::UnboundedSender<TransferEvent>>>,
+}
+
+impl FileTransferManager {
+    /// Create a new file transfer manager.
+    pub fn new(config: FileTransferConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            outgoing: RwLock::new(HashMap::new()),
+            incoming: RwLock::new(HashMap::new()),
+            subscribers: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Replace the acceptance policy.
+    pub fn set_policy(&self, policy: AcceptancePolicy) {
+        self.config.write().policy = policy;
+    }
+
+    /// Subscribe to transfer events.
+    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<TransferEvent> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.subscribers.write().push(tx);
+        rx
+    }
+
+    /// Offers waiting for an application decision.
+    pub fn pending_offers(&self) -> Vec<(PeerId, FileOffer)> {
+        self.incoming
+            .read()
+            .values()
+            .filter(|t| !t.accepted)
+            .map(|t| (t.peer_id.clone(), t.offer.clone()))
+            .collect()
+    }
+
+    /// Register a file imported into the blob store for sending to a peer.
+    pub fn offer_file(
+        &self,
+        peer_id: &PeerId,
+        path: impl Into<PathBuf>,
+        hash: TransferId,
+        size: u64,
+        capability: Option<Capability>,
+    ) -> Result<FileOffer> {
+        let path = path.into();
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(str::to_string)
+            .ok_or_else(|| P2PError::InvalidPath(path.display().to_string()))?;
+        if !is_transfer_id(&hash) {
+            return Err(P2PError::Internal(format!(
+                "Invalid transfer id {:?}",
+                hash
+            )));
+        }
+
+        info!(
+            "Offering {} ({} bytes, {}) to peer {}",
+            name, size, hash, peer_id
+        );
+
+        self.outgoing.write().insert(
+            (peer_id.clone(), hash.clone()),
+            OutgoingTransfer {
+                peer_id: peer_id.clone(),
+                path,
+            },
+        );
+
+        Ok(FileOffer {
+            hash,
+            name,
+            size,
+            capability,
+        })
+    }
+
+    /// Handle a file offer from a peer.
+    ///
+    /// Returns the reply to send, or `None` if the offer awaits a decision.
+    pub async fn handle_offer(
+        &self,
+        peer_id: &PeerId,
+        offer: FileOffer,
+    ) -> Result<Option<SyncMessage>> {
+        if !is_transfer_id(&offer.hash) {
+            return Err(P2PError::InvalidMessage(format!(
+                "Offer from peer {} has invalid hash {:?}",
+                peer_id, offer.hash
+            )));
+        }
+
+        let decision = self.config.read().policy.evaluate(peer_id, &offer);
+        debug!(
+            "File offer {} from peer {}: {:?}",
+            offer.hash, peer_id, decision
+        );
+
+        let hash = offer.hash.clone();
+        self.incoming.write().insert(
+            hash.clone(),
+            IncomingTransfer {
+                peer_id: peer_id.clone(),
+                offer: offer.clone(),
+                accepted: false,
+            },
+        );
+
+        match decision {
+            AcceptanceDecision::Accept => self.accept(&hash).await.map(|(_, reply)| Some(reply)),
+            AcceptanceDecision::Reject(reason) => {
+                self.reject(&hash, reason).map(|(_, reply)| Some(reply))
+            }
+            AcceptanceDecision::Ask => {
+                self.emit(TransferEvent::Offered {
+                    peer_id: peer_id.clone(),
+                    offer,
+                });
+                Ok(None)
+            }
+        }
+    }
+
+    /// Accept a pending offer.
+    ///
+    /// Returns the offering peer and the reply to send it. The file is then
+    /// fetched from the peer and handed back via
+    /// [`FileTransferManager::complete_incoming`].
+    pub async fn accept(&self, hash: &TransferId) -> Result<(PeerId, SyncMessage)> {
+        let peer_id = {
+            let mut incoming = self.incoming.write();
+            let transfer = incoming
+                .get_mut(hash)
+                .ok_or_else(|| P2PError::Internal(format!("No offer for transfer {}", hash)))?;
+            transfer.accepted = true;
+            transfer.peer_id.clone()
+        };
+
+        self.prepare_download_dir().await?;
+
+        info!("Accepting transfer {} from peer {}", hash, peer_id);
+        self.emit(TransferEvent::Accepted {
+            hash: hash.clone(),
+            peer_id: peer_id.clone(),
+            direction: TransferDirection::Incoming,
+        });
+
+        Ok((peer_id, SyncMessage::FileAccept { hash: hash.clone() }))
+    }
+
+    /// Create the download directory.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn prepare_download_dir(&self) -> Result<()> {
+        let dir = self.config.read().download_dir.clone();
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .map_err(|e| P2PError::Internal(format!("Failed to create download dir: {}", e)))
+    }
+
+    /// Browsers have nowhere to fetch files to, so they accept none.
+    #[cfg(target_arch = "wasm32")]
+    async fn prepare_download_dir(&self) -> Result<()> {
+        Err(P2PError::Internal(
+            "File transfer is not supported on wasm32".to_string(),
+        ))
+    }
+
+    /// Reject a pending offer.
+    pub fn reject(
+        &self,
+        hash: &TransferId,
+        reason: impl Into<String>,
+    ) -> Result<(PeerId, SyncMessage)> {
+        let reason = reason.into();
+        let transfer = self
+            .incoming
+            .write()
+            .remove(hash)
+            .ok_or_else(|| P2PError::Internal(format!("No offer for transfer {}", hash)))?;
+
+        info!(
+            "Rejecting transfer {} from peer {}: {}",
+            hash, transfer.peer_id, reason
+        );
+        self.emit(TransferEvent::Rejected {
+            hash: hash.clone(),
+            peer_id: transfer.peer_id.clone(),
+            direction: TransferDirection::Incoming,
+            reason: reason.clone(),
+        });
+
+        Ok((
+            transfer.peer_id,
+            SyncMessage::FileReject {
+                hash: hash.clone(),
+                reason,
+            },
+        ))
+    }
+
+    /// Handle the receiver accepting one of our offers.
+    pub fn handle_accept(&self, peer_id: &PeerId, hash: &TransferId) -> Result<()> {
+        self.outgoing_transfer(peer_id, hash)?;
+        self.emit(TransferEvent::Accepted {
+            hash: hash.clone(),
+            peer_id: peer_id.clone(),
+            direction: TransferDirection::Outgoing,
+        });
+        Ok(())
+    }
+
+    /// Reserve the path an accepted incoming transfer from a peer is saved
+    /// to.
+    ///
+    /// The offered name is used unless a file by that name exists, in which
+    /// case a number is appended (`notes (1).txt`). The file is created
+    /// empty, so concurrent downloads don't pick the same name and existing
+    /// files are never overwritten.
+    pub fn download_path(&self, peer_id: &PeerId, hash: &TransferId) -> Result<PathBuf> {
+        let offer = self.accepted_offer(peer_id, hash)?;
+        let dir = self.config.read().download_dir.clone();
+        let name = sanitize_file_name(&offer.name);
+        for n in 0..MAX_NAME_ATTEMPTS {
+            let path = dir.join(numbered_file_name(&name, n));
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(_) => return Ok(path),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+                Err(e) => {
+                    return Err(P2PError::Internal(format!(
+                        "Failed to create {}: {}",
+                        path.display(),
+                        e
+                    )))
+                }
+            }
+        }
+        Err(P2PError::Internal(format!(
+            "No free file name for {} in {}",
+            name,
+            dir.display()
+        )))
+    }
+
+    /// Record the bytes received so far, emitting a progress event.
+    pub fn record_received(&self, peer_id: &PeerId, hash: &TransferId, transferred: u64) {
+        if let Ok(offer) = self.accepted_offer(peer_id, hash) {
+            self.emit(TransferEvent::Progress {
+                hash: hash.clone(),
+                peer_id: peer_id.clone(),
+                direction: TransferDirection::Incoming,
+                transferred,
+                total: offer.size,
+            });
+        }
+    }
+
+    /// Finish an incoming transfer whose verified data was saved to `path`.
+    ///
+    /// Returns the completion message for the sender.
+    pub fn complete_incoming(
+        &self,
+        peer_id: &PeerId,
+        hash: &TransferId,
+        path: PathBuf,
+    ) -> Result<SyncMessage> {
+        self.accepted_offer(peer_id, hash)?;
+        self.incoming.write().remove(hash);
+
+        info!("Received {} from peer {}", path.display(), peer_id);
+        self.emit(TransferEvent::Completed {
+            hash: hash.clone(),
+            peer_id: peer_id.clone(),
+            direction: TransferDirection::Incoming,
+            path,
+        });
+
+        Ok(SyncMessage::FileComplete { hash: hash.clone() })
+    }
+
+    /// Mark an incoming transfer as failed.
+    ///
+    /// The offer is dropped; partial data stays in the blob store, so a new
+    /// offer of the same file resumes the download.
+    pub fn fail_incoming(&self, peer_id: &PeerId, hash: &TransferId, error: String) {
+        self.incoming.write().remove(hash);
+        self.emit(TransferEvent::Failed {
+            hash: hash.clone(),
+            peer_id: peer_id.clone(),
+            direction: TransferDirection::Incoming,
+            error,
+        });
+    }
+
+    /// Handle the receiver confirming a completed transfer.
+    pub fn handle_complete(&self, peer_id: &PeerId, hash: &TransferId) {
+        if let Some(transfer) = self
+            .outgoing
+            .write()
+            .remove(&(peer_id.clone(), hash.clone()))
+        {
+            info!("Sent {} to peer {}", transfer.path.display(), peer_id);
+            self.emit(TransferEvent::Completed {
+                hash: hash.clone(),
+                peer_id: transfer.peer_id,
+                direction: TransferDirection::Outgoing,
+                path: transfer.path,
+            });
+        }
+    }
+
+    /// Handle the receiver rejecting one of our offers.
+    pub fn handle_reject(&self, peer_id: &PeerId, hash: &TransferId, reason: String) {
+        if self
+            .outgoing
+            .write()
+            .remove(&(peer_id.clone(), hash.clone()))
+            .is_some()
+        {
+            info!("Peer {} rejected transfer {}: {}", peer_id, hash, reason);
+            self.emit(TransferEvent::Rejected {
+                hash: hash.clone(),
+                peer_id: peer_id.clone(),
+                direction: TransferDirection::Outgoing,
+                reason,
+            });
+        }
+    }
+
+    /// Look up one of our offers.
+    fn outgoing_transfer(&self, peer_id: &PeerId, hash: &TransferId) -> Result<OutgoingTransfer> {
+        self.outgoing
+            .read()
+            .get(&(peer_id.clone(), hash.clone()))
+            .cloned()
+            .ok_or_else(|| P2PError::InvalidMessage(format!("No offer for transfer {}", hash)))
+    }
+
+    /// Look up an accepted offer from a peer.
+    pub(crate) fn accepted_offer(&self, peer_id: &PeerId, hash: &TransferId) -> Result<FileOffer> {
+        match self.incoming.read().get(hash) {
+            Some(t) if t.accepted && &t.peer_id == peer_id => Ok(t.offer.clone()),
+            _ => Err(P2PError::InvalidMessage(format!(
+                "No accepted transfer {} from peer {}",
+                hash, peer_id
+            ))),
+        }
+    }
+
+    /// Deliver an event to all live subscribers.
+    fn emit(&self, event: TransferEvent) {
+        self.subscribers
+            .write()
+            .retain(|tx| tx.send(event.clone()).is_ok());
+    }
+}
+
+/// Whether `hash` is a hex-encoded BLAKE3 hash.
+///
+/// Peers supply transfer IDs, so anything else is refused before it reaches
+/// the blob store or the file system.
+pub(crate) fn is_transfer_id(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Strip directory components from a peer-supplied file name.
+fn sanitize_file_name(name: &str) -> String {
+    let base = name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches('.');
+    if base.is_empty() {
+        "download".to_string()
+    } else {
+        base.to_string()
+    }
+}
+
+/// The `n`th candidate name for a download: the name itself, then the name
+/// with ` (n)` before its extension.
+fn numbered_file_name(name: &str, n: usize) -> String {
+    if n == 0 {
+        return name.to_string();
+    }
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => format!("{} ({}){}", &name[..dot], n, &name[dot..]),
+        _ => format!("{} ({})", name, n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+
+    fn manager(dir: &std::path::Path, policy: AcceptancePolicy) -> FileTransferManager {
+        FileTransferManager::new(FileTransferConfig {
+            download_dir: dir.to_path_buf(),
+            blob_dir: dir.join("blobs"),
+            policy,
+        })
+    }
+
+    fn offer(capability: Option<Capability>) -> FileOffer {
+        FileOffer {
+            hash: blake3::hash(b"photo").to_hex().to_string(),
+            name: "photo.jpg".to_string(),
+            size: 10,
+            capability,
+        }
+    }
+
+    fn trusting(peer: &str) -> AcceptancePolicy {
+        AcceptancePolicy {
+            rules: vec![AcceptanceRule::TrustedPeer(peer.to_string())],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_policy_max_size() {
+        let policy = AcceptancePolicy {
+            max_size: Some(5),
+            ..trusting("alice")
+        };
+        assert!(matches!(
+            policy.evaluate(&"alice".to_string(), &offer(None)),
+            AcceptanceDecision::Reject(_)
+        ));
+    }
+
+    #[test]
+    fn test_policy_unmatched() {
+        let mut policy = trusting("alice");
+        assert_eq!(
+            policy.evaluate(&"alice".to_string(), &offer(None)),
+            AcceptanceDecision::Accept
+        );
+        assert_eq!(
+            policy.evaluate(&"eve".to_string(), &offer(None)),
+            AcceptanceDecision::Ask
+        );
+
+        policy.ask_unmatched = false;
+        assert!(matches!(
+            policy.evaluate(&"eve".to_string(), &offer(None)),
+            AcceptanceDecision::Reject(_)
+        ));
+    }
+
+    #[test]
+    fn test_policy_capability() {
+        let key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let namespace_id = NamespaceId::from_dol_namespace("myapp.v1");
+        let subspace_id = SubspaceId::from_dol_collection("shared");
+        let root = Capability::new_root(namespace_id, &key);
+
+        let policy = AcceptancePolicy {
+            rules: vec![AcceptanceRule::Capability {
+                namespace_id,
+                subspace_id,
+            }],
+            ask_unmatched: false,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            policy.evaluate(&"eve".to_string(), &offer(Some(root))),
+            AcceptanceDecision::Accept
+        );
+
+        let other = Capability::new_root(NamespaceId::from_dol_namespace("other.v1"), &key);
+        assert!(matches!(
+            policy.evaluate(&"eve".to_string(), &offer(Some(other))),
+            AcceptanceDecision::Reject(_)
+        ));
+    }
+
+    #[test]
+    fn test_sanitize_file_name() {
+        assert_eq!(sanitize_file_name("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_file_name("..\\evil.txt"), "evil.txt");
+        assert_eq!(sanitize_file_name(".."), "download");
+    }
+
+    #[test]
+    fn test_numbered_file_name() {
+        assert_eq!(numbered_file_name("notes.txt", 0), "notes.txt");
+        assert_eq!(numbered_file_name("notes.txt", 2), "notes (2).txt");
+        assert_eq!(
+            numbered_file_name("archive.tar.gz", 1),
+            "archive.tar (1).gz"
+        );
+        assert_eq!(numbered_file_name("README", 1), "README (1)");
+    }
+
+    #[tokio::test]
+    async fn test_transfer_roundtrip() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let (alice, bob) = ("alice".to_string(), "bob".to_string());
+        let hash = blake3::hash(b"hello file transfer").to_hex().to_string();
+
+        let sender = manager(src.path(), AcceptancePolicy::default());
+        let receiver = manager(dst.path(), trusting("alice"));
+        let mut events = sender.subscribe();
+
+        let offer = sender
+            .offer_file(&bob, src.path().join("notes.txt"), hash.clone(), 19, None)
+            .unwrap();
+        let accept = receiver.handle_offer(&alice, offer).await.unwrap().unwrap();
+        assert!(matches!(&accept, SyncMessage::FileAccept { hash: h } if h == &hash));
+        sender.handle_accept(&bob, &hash).unwrap();
+
+        // Only the offering peer's data is saved, under the offered name
+        // unless a file already has it
+        assert!(receiver.download_path(&"eve".to_string(), &hash).is_err());
+        std::fs::write(dst.path().join("notes.txt"), b"mine").unwrap();
+        let path = receiver.download_path(&alice, &hash).unwrap();
+        assert_eq!(path, dst.path().join("notes (1).txt"));
+        assert_eq!(
+            std::fs::read(dst.path().join("notes.txt")).unwrap(),
+            b"mine"
+        );
+        let next = receiver.download_path(&alice, &hash).unwrap();
+        assert_eq!(next, dst.path().join("notes (2).txt"));
+
+        let complete = receiver.complete_incoming(&alice, &hash, path).unwrap();
+        assert!(matches!(complete, SyncMessage::FileComplete { .. }));
+        assert!(receiver.download_path(&alice, &hash).is_err());
+        sender.handle_complete(&bob, &hash);
+
+        let mut completed = false;
+        while let Ok(event) = events.try_recv() {
+            completed |= matches!(event, TransferEvent::Completed { .. });
+        }
+        assert!(completed);
+    }
+
+    #[tokio::test]
+    async fn test_pending_offer() {
+        let dst = tempfile::tempdir().unwrap();
+        let alice = "alice".to_string();
+        let receiver = manager(dst.path(), AcceptancePolicy::default());
+        let mut events = receiver.subscribe();
+
+        let offer = offer(None);
+        let hash = offer.hash.clone();
+        assert!(receiver
+            .handle_offer(&alice, offer.clone())
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(receiver.pending_offers().len(), 1);
+        assert!(receiver.download_path(&alice, &hash).is_err());
+
+        receiver.accept(&hash).await.unwrap();
+        assert!(receiver.pending_offers().is_empty());
+        receiver.record_received(&alice, &hash, 4);
+
+        // A failed download drops the offer until the peer offers again
+        receiver.fail_incoming(&alice, &hash, "connection lost".to_string());
+        assert!(receiver.download_path(&alice, &hash).is_err());
+        receiver.handle_offer(&alice, offer).await.unwrap();
+        receiver.accept(&hash).await.unwrap();
+        assert!(receiver.download_path(&alice, &hash).is_ok());
+
+        let mut progress = false;
+        while let Ok(event) = events.try_recv() {
+            progress |= matches!(
+                event,
+                TransferEvent::Progress {
+                    transferred: 4,
+                    total: 10,
+                    ..
+                }
+            );
+        }
+        assert!(progress);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_hash_rejected() {
+        let root = tempfile::tempdir().unwrap();
+        let dst = root.path().join("downloads");
+        let receiver = manager(&dst, trusting("alice"));
+
+        for hash in [
+            "../../escape",
+            "/tmp/escape",
+            &"A".repeat(64),
+            &"0".repeat(63),
+        ] {
+            let bogus = FileOffer {
+                hash: hash.to_string(),
+                name: "x.txt".to_string(),
+                size: 3,
+                capability: None,
+            };
+            let result = receiver.handle_offer(&"alice".to_string(), bogus).await;
+            assert!(matches!(result, Err(P2PError::InvalidMessage(_))));
+            assert!(receiver.accept(&hash.to_string()).await.is_err());
+        }
+        assert!(!dst.exists());
+        assert!(is_transfer_id(&blake3::hash(b"x").to_hex()));
+    }
+}
@@ -0,0 +1,466 @@
+//! Chunked, compressed framing of sync messages on Iroh streams.
+//!
+//! Every message travels on its own QUIC stream. When a connection opens,
+//! both sides send a [`TransportHello`] announcing the framing and
+//! compression they can read. Messages to a peer that sent one are split
+//! into chunks, each compressed with zstd when that makes it smaller:
+//!
+//! ```text
+//! stream = magic "VUDF" | chunk*
+//! chunk  = codec (u8) | length (u32 LE) | data
+//! ```
+//!
+//! Chunks are paced and throttled one at a time, so a large
+//! [`SyncMessage::FullDocument`](crate::SyncMessage::FullDocument) doesn't
+//! stall a slow link in one burst, and the receiver decodes them as they
+//! arrive, refusing a message once it exceeds the size limit instead of
+//! buffering it first. Peers that haven't sent a hello get the plain bincode
+//! message, which starts with a variant index and never with the magic.
+//!
+//! zstd doesn't build for `wasm32`, so browsers announce and send only
+//! uncompressed chunks.
+
+use crate::error::{P2PError, Result};
+use crate::sync_protocol::PeerId;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// First bytes of a framed stream.
+pub const MAGIC: [u8; 4] = *b"VUDF";
+
+/// Framing version this node reads.
+pub const FRAMING_VERSION: u8 = 1;
+
+/// Largest chunk, before compression, a receiver decodes.
+pub const MAX_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Default limit of [`TransportConfig::max_message_size`].
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Bytes of a chunk header (codec and length).
+const CHUNK_HEADER_LEN: usize = 5;
+
+/// Whether this build compresses and decompresses zstd chunks.
+const ZSTD_SUPPORTED: bool = cfg!(not(target_arch = "wasm32"));
+
+/// Compression codec of a chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Compression {
+    /// Uncompressed.
+    None,
+    /// Zstandard.
+    Zstd,
+}
+
+impl Compression {
+    /// Codec byte on the wire.
+    fn tag(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Zstd => 1,
+        }
+    }
+
+    /// Codec of a codec byte.
+    fn from_tag(tag: u8) -> Result<Self> {
+        match tag {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Zstd),
+            _ => Err(P2PError::InvalidMessage(format!(
+                "Unknown chunk codec {}",
+                tag
+            ))),
+        }
+    }
+}
+
+/// Message framing and compression settings.
+#[derive(Debug, Clone)]
+pub struct TransportConfig {
+    /// Compress messages to peers that support it.
+    pub compression: bool,
+    /// Zstd compression level.
+    pub compression_level: i32,
+    /// Messages smaller than this are sent uncompressed.
+    pub min_compress_size: usize,
+    /// Chunk size before compression (at most [`MAX_CHUNK_SIZE`]).
+    pub chunk_size: usize,
+    /// Largest message accepted from a peer, after decompression.
+    pub max_message_size: usize,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            compression: true,
+            compression_level: 3,
+            min_compress_size: 1024,
+            chunk_size: 64 * 1024,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+        }
+    }
+}
+
+/// Framing and compression a node can read, sent when a connection opens.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransportHello {
+    /// Framing version the sender reads.
+    pub framing: u8,
+    /// Codecs the sender decodes.
+    pub compression: Vec<Compression>,
+}
+
+impl TransportHello {
+    /// Hello of this node.
+    pub fn local() -> Self {
+        let mut compression = vec![Compression::None];
+        if ZSTD_SUPPORTED {
+            compression.push(Compression::Zstd);
+        }
+        Self {
+            framing: FRAMING_VERSION,
+            compression,
+        }
+    }
+}
+
+/// Framing negotiated with each connected peer.
+pub struct PeerTransports {
+    /// Local settings.
+    config: TransportConfig,
+    /// Codec used for framed messages, by peers that sent a hello.
+    peers: RwLock<HashMap<PeerId, Compression>>,
+}
+
+impl PeerTransports {
+    /// Create the negotiation state.
+    pub fn new(config: TransportConfig) -> Self {
+        Self {
+            config,
+            peers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Get the local settings.
+    pub fn config(&self) -> &TransportConfig {
+        &self.config
+    }
+
+    /// Record a peer's hello, returning the codec used for messages to it
+    /// (`None` if the peer can't read the framing).
+    pub fn negotiate(&self, peer: &PeerId, hello: &TransportHello) -> Option<Compression> {
+        if hello.framing < FRAMING_VERSION {
+            self.peers.write().remove(peer);
+            return None;
+        }
+        let compression = if ZSTD_SUPPORTED
+            && self.config.compression
+            && hello.compression.contains(&Compression::Zstd)
+        {
+            Compression::Zstd
+        } else {
+            Compression::None
+        };
+        self.peers.write().insert(peer.clone(), compression);
+        Some(compression)
+    }
+
+    /// Get the codec for framed messages to a peer, or `None` to send it
+    /// plain messages.
+    pub fn framing(&self, peer: &PeerId) -> Option<Compression> {
+        self.peers.read().get(peer).copied()
+    }
+
+    /// Forget a peer's hello.
+    pub fn remove(&self, peer: &PeerId) {
+        self.peers.write().remove(peer);
+    }
+
+    /// Split a serialized message into encoded chunks for a peer using
+    /// `compression`. The first chunk is preceded by [`MAGIC`].
+    pub fn chunks<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        compression: Compression,
+    ) -> impl Iterator<Item = Result<Vec<u8>>> + 'a {
+        let compression = if bytes.len() < self.config.min_compress_size {
+            Compression::None
+        } else {
+            compression
+        };
+        let chunk_size = self.config.chunk_size.clamp(1, MAX_CHUNK_SIZE);
+        bytes.chunks(chunk_size).enumerate().map(move |(i, raw)| {
+            let mut chunk = if i == 0 { MAGIC.to_vec() } else { Vec::new() };
+            encode_chunk(&mut chunk, raw, compression, self.config.compression_level)?;
+            Ok(chunk)
+        })
+    }
+}
+
+/// Append an encoded chunk, falling back to no compression when it doesn't
+/// make the chunk smaller.
+fn encode_chunk(out: &mut Vec<u8>, raw: &[u8], compression: Compression, level: i32) -> Result<()> {
+    let compressed = match compression {
+        Compression::Zstd => Some(
+            zstd_compress(raw, level).map_err(|e| P2PError::SerializationError(e.to_string()))?,
+        ),
+        Compression::None => None,
+    };
+    let (codec, data) = match &compressed {
+        Some(data) if data.len() < raw.len() => (Compression::Zstd, data.as_slice()),
+        _ => (Compression::None, raw),
+    };
+    out.push(codec.tag());
+    out.extend((data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    Ok(())
+}
+
+/// A message read from a stream.
+#[derive(Debug)]
+pub struct ReceivedMessage {
+    /// Serialized message.
+    pub bytes: Vec<u8>,
+    /// Bytes read from the stream.
+    pub wire_len: usize,
+}
+
+/// Read a framed or plain message from a stream.
+///
+/// Fails with [`P2PError::ConnectionFailed`] if the stream breaks, and with
+/// [`P2PError::ResourceLimitExceeded`] once the message exceeds
+/// `max_message_size`.
+pub async fn read_message<R>(reader: &mut R, max_message_size: usize) -> Result<ReceivedMessage>
+where
+    R: AsyncRead + Unpin,
+{
+    let too_large =
+        || P2PError::ResourceLimitExceeded(format!("Message exceeds {} bytes", max_message_size));
+
+    let mut prefix = [0u8; 4];
+    reader.read_exact(&mut prefix).await.map_err(read_error)?;
+    if prefix != MAGIC {
+        let mut bytes = prefix.to_vec();
+        reader
+            .take(max_message_size.saturating_sub(bytes.len()) as u64 + 1)
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(read_error)?;
+        if bytes.len() > max_message_size {
+            return Err(too_large());
+        }
+        return Ok(ReceivedMessage {
+            wire_len: bytes.len(),
+            bytes,
+        });
+    }
+
+    let mut bytes = Vec::new();
+    let mut wire_len = MAGIC.len();
+    loop {
+        let mut header = [0u8; CHUNK_HEADER_LEN];
+        if reader.read(&mut header[..1]).await.map_err(read_error)? == 0 {
+            break;
+        }
+        reader
+            .read_exact(&mut header[1..])
+            .await
+            .map_err(read_error)?;
+        let codec = Compression::from_tag(header[0])?;
+        let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        if len > MAX_CHUNK_SIZE {
+            return Err(P2PError::InvalidMessage(format!(
+                "Chunk of {} bytes exceeds {} bytes",
+                len, MAX_CHUNK_SIZE
+            )));
+        }
+
+        let mut data = vec![0u8; len];
+        reader.read_exact(&mut data).await.map_err(read_error)?;
+        wire_len += CHUNK_HEADER_LEN + len;
+        match codec {
+            Compression::None => bytes.extend_from_slice(&data),
+            Compression::Zstd => {
+                let raw = zstd_decompress(&data)
+                    .map_err(|e| P2PError::InvalidMessage(format!("Invalid chunk: {}", e)))?;
+                bytes.extend_from_slice(&raw);
+            }
+        }
+        if bytes.len() > max_message_size {
+            return Err(too_large());
+        }
+    }
+
+    Ok(ReceivedMessage { bytes, wire_len })
+}
+
+/// Compress a chunk with zstd.
+#[cfg(not(target_arch = "wasm32"))]
+fn zstd_compress(raw: &[u8], level: i32) -> std::io::Result<Vec<u8>> {
+    zstd::bulk::compress(raw, level)
+}
+
+/// Decompress a zstd chunk.
+#[cfg(not(target_arch = "wasm32"))]
+fn zstd_decompress(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    zstd::bulk::decompress(data, MAX_CHUNK_SIZE)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn zstd_compress(_raw: &[u8], _level: i32) -> std::io::Result<Vec<u8>> {
+    Err(zstd_unsupported())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn zstd_decompress(_data: &[u8]) -> std::io::Result<Vec<u8>> {
+    Err(zstd_unsupported())
+}
+
+/// Error for zstd chunks on builds without zstd.
+#[cfg(target_arch = "wasm32")]
+fn zstd_unsupported() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "zstd is not supported on wasm32",
+    )
+}
+
+/// Map a stream read error.
+fn read_error(e: std::io::Error) -> P2PError {
+    P2PError::ConnectionFailed(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync_protocol::SyncMessage;
+
+    fn encode(transports: &PeerTransports, bytes: &[u8], compression: Compression) -> Vec<u8> {
+        transports
+            .chunks(bytes, compression)
+            .collect::<Result<Vec<_>>>()
+            .unwrap()
+            .concat()
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let transports = PeerTransports::new(TransportConfig::default());
+        let peer = "peer1".to_string();
+        assert_eq!(transports.framing(&peer), None);
+
+        let hello = TransportHello::local();
+        assert_eq!(transports.negotiate(&peer, &hello), Some(Compression::Zstd));
+        assert_eq!(transports.framing(&peer), Some(Compression::Zstd));
+
+        let plain = TransportHello {
+            framing: FRAMING_VERSION,
+            compression: vec![Compression::None],
+        };
+        assert_eq!(transports.negotiate(&peer, &plain), Some(Compression::None));
+
+        let old = TransportHello {
+            framing: 0,
+            compression: Vec::new(),
+        };
+        assert_eq!(transports.negotiate(&peer, &old), None);
+        assert_eq!(transports.framing(&peer), None);
+
+        let config = TransportConfig {
+            compression: false,
+            ..Default::default()
+        };
+        let uncompressed = PeerTransports::new(config);
+        assert_eq!(
+            uncompressed.negotiate(&peer, &hello),
+            Some(Compression::None)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_large_document_round_trip() {
+        let transports = PeerTransports::new(TransportConfig::default());
+        let message = SyncMessage::FullDocument {
+            namespace: "users".to_string(),
+            id: "alice".to_string(),
+            document: b"alice@example.com ".repeat(100_000),
+        };
+        let bytes = message.to_bytes().unwrap();
+
+        let chunks: Vec<Vec<u8>> = transports
+            .chunks(&bytes, Compression::Zstd)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(chunks.len(), bytes.len().div_ceil(64 * 1024));
+        assert!(chunks[0].starts_with(&MAGIC));
+
+        let wire = chunks.concat();
+        assert!(wire.len() < bytes.len() / 10);
+        let received = read_message(&mut wire.as_slice(), 64 * 1024 * 1024)
+            .await
+            .unwrap();
+        assert_eq!(received.bytes, bytes);
+        assert_eq!(received.wire_len, wire.len());
+        assert!(matches!(
+            SyncMessage::from_bytes(&received.bytes).unwrap(),
+            SyncMessage::FullDocument { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_plain_and_incompressible() {
+        let transports = PeerTransports::new(TransportConfig::default());
+
+        // Peers without framing send plain bincode
+        let plain = SyncMessage::Heartbeat.to_bytes().unwrap();
+        let received = read_message(&mut plain.as_slice(), 1024).await.unwrap();
+        assert_eq!(received.bytes, plain);
+
+        // Small messages aren't compressed
+        let wire = encode(&transports, &plain, Compression::Zstd);
+        assert_eq!(wire[MAGIC.len()], Compression::None.tag());
+        let received = read_message(&mut wire.as_slice(), 1024).await.unwrap();
+        assert_eq!(received.bytes, plain);
+
+        // Nor is data that compression doesn't shrink
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let noise: Vec<u8> = (0..4096)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 56) as u8
+            })
+            .collect();
+        let wire = encode(&transports, &noise, Compression::Zstd);
+        assert_eq!(wire.len(), MAGIC.len() + CHUNK_HEADER_LEN + noise.len());
+        let received = read_message(&mut wire.as_slice(), 8192).await.unwrap();
+        assert_eq!(received.bytes, noise);
+    }
+
+    #[tokio::test]
+    async fn test_size_limit() {
+        let transports = PeerTransports::new(TransportConfig::default());
+        let bytes = vec![0u8; 200_000];
+
+        let wire = encode(&transports, &bytes, Compression::Zstd);
+        assert!(matches!(
+            read_message(&mut wire.as_slice(), 100_000).await,
+            Err(P2PError::ResourceLimitExceeded(_))
+        ));
+        let mut plain = vec![1u8; 200_000];
+        assert!(matches!(
+            read_message(&mut plain.as_slice(), 100_000).await,
+            Err(P2PError::ResourceLimitExceeded(_))
+        ));
+
+        // Truncated streams are broken connections
+        plain = wire[..wire.len() - 1].to_vec();
+        assert!(matches!(
+            read_message(&mut plain.as_slice(), 1_000_000).await,
+            Err(P2PError::ConnectionFailed(_))
+        ));
+    }
+}
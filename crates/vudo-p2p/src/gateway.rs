@@ -0,0 +1,350 @@
+//! WebSocket gateway for browser peers.
+//!
+//! Accepts browser links (see [`crate::browser`]) and attaches each linked
+//! browser to an [`IrohAdapter`] as a peer, so it syncs with this node like
+//! any Iroh peer. Put the gateway behind a TLS-terminating proxy to serve
+//! pages loaded over HTTPS, which may only open `wss://` links.
+
+use crate::browser::{new_nonce, verify_hello, LinkMessage, LINK_PROTOCOL};
+use crate::connection_pool::DisconnectReason;
+use crate::error::{P2PError, Result};
+use crate::framing::TransportHello;
+use crate::iroh_adapter::IrohAdapter;
+use crate::sync_protocol::PeerId;
+use futures::{SinkExt, StreamExt};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+use tracing::{debug, info, warn};
+
+/// Default port of the gateway.
+pub const DEFAULT_GATEWAY_PORT: u16 = 3341;
+
+/// Browser gateway configuration.
+#[derive(Debug, Clone)]
+pub struct BrowserGatewayConfig {
+    /// Address to accept WebSocket links on.
+    pub listen_addr: SocketAddr,
+    /// How long a browser has to complete the link handshake.
+    pub handshake_timeout: Duration,
+}
+
+impl Default for BrowserGatewayConfig {
+    fn default() -> Self {
+        Self {
+            listen_addr: SocketAddr::from(([0, 0, 0, 0], DEFAULT_GATEWAY_PORT)),
+            handshake_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// WebSocket gateway linking browsers to an [`IrohAdapter`].
+///
+/// Stops accepting links when dropped; open links stay up until they close.
+pub struct BrowserGateway {
+    /// Address the gateway listens on.
+    local_addr: SocketAddr,
+    /// Accept loop.
+    task: JoinHandle<()>,
+}
+
+impl BrowserGateway {
+    /// Start accepting links for `iroh`.
+    pub async fn spawn(config: BrowserGatewayConfig, iroh: Arc<IrohAdapter>) -> Result<Self> {
+        let listener = TcpListener::bind(config.listen_addr)
+            .await
+            .map_err(|e| P2PError::ConnectionFailed(e.to_string()))?;
+        let local_addr = listener
+            .local_addr()
+            .map_err(|e| P2PError::ConnectionFailed(e.to_string()))?;
+        info!("Browser gateway listening on ws://{}", local_addr);
+
+        let task = tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, addr)) => {
+                        let config = config.clone();
+                        let iroh = Arc::clone(&iroh);
+                        tokio::spawn(async move {
+                            if let Err(e) = serve(stream, &config, &iroh).await {
+                                debug!("Browser link from {} failed: {}", addr, e);
+                            }
+                        });
+                    }
+                    Err(e) => warn!("Failed to accept browser link: {}", e),
+                }
+            }
+        });
+
+        Ok(Self { local_addr, task })
+    }
+
+    /// Get the address the gateway listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Get the URL browsers link to.
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.local_addr)
+    }
+}
+
+impl Drop for BrowserGateway {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Serve a browser link until it closes.
+async fn serve(stream: TcpStream, config: &BrowserGatewayConfig, iroh: &IrohAdapter) -> Result<()> {
+    let (mut socket, peer_id, mut frames) =
+        tokio::time::timeout(config.handshake_timeout, handshake(stream, iroh))
+            .await
+            .map_err(|_| P2PError::Timeout)??;
+
+    let welcome = LinkMessage::Welcome {
+        peer_id: peer_id.clone(),
+        transport: TransportHello::local(),
+    };
+    if let Err(e) = socket.send(Message::Text(welcome.to_text()?)).await {
+        iroh.detach_link(&peer_id, DisconnectReason::Lost(e.to_string()));
+        return Err(P2PError::ConnectionFailed(e.to_string()));
+    }
+
+    let reason = loop {
+        tokio::select! {
+            incoming = socket.next() => match incoming {
+                Some(Ok(Message::Binary(frame))) => {
+                    if let Err(e) = iroh.deliver(&peer_id, &frame).await {
+                        warn!("Rejected message from browser peer {}: {}", peer_id, e);
+                    }
+                }
+                Some(Ok(Message::Close(_))) | None => {
+                    break DisconnectReason::Lost("link closed".to_string());
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => break DisconnectReason::Lost(e.to_string()),
+            },
+            outgoing = frames.recv() => match outgoing {
+                Some(frame) => {
+                    if let Err(e) = socket.send(Message::Binary(frame)).await {
+                        break DisconnectReason::Lost(e.to_string());
+                    }
+                }
+                None => {
+                    // Detached by this node
+                    let _ = socket.close(None).await;
+                    return Ok(());
+                }
+            },
+        }
+    };
+
+    iroh.detach_link(&peer_id, reason);
+    Ok(())
+}
+
+/// Accept a WebSocket and run the link handshake.
+///
+/// Returns the socket, the browser's peer ID and the frames to send it.
+async fn handshake(
+    stream: TcpStream,
+    iroh: &IrohAdapter,
+) -> Result<(
+    WebSocketStream<TcpStream>,
+    PeerId,
+    mpsc::UnboundedReceiver<Vec<u8>>,
+)> {
+    let mut socket = tokio_tungstenite::accept_hdr_async(stream, select_protocol)
+        .await
+        .map_err(|e| P2PError::ConnectionFailed(e.to_string()))?;
+
+    let gateway = iroh.node_id().to_string();
+    let nonce = new_nonce();
+    let challenge = LinkMessage::Challenge {
+        gateway: gateway.clone(),
+        nonce: nonce.clone(),
+    };
+    socket
+        .send(Message::Text(challenge.to_text()?))
+        .await
+        .map_err(|e| P2PError::ConnectionFailed(e.to_string()))?;
+
+    let hello = match socket.next().await {
+        Some(Ok(Message::Text(text))) => LinkMessage::from_text(&text),
+        Some(Ok(_)) => Err(P2PError::InvalidMessage(
+            "Expected a link hello".to_string(),
+        )),
+        Some(Err(e)) => return Err(P2PError::ConnectionFailed(e.to_string())),
+        None => {
+            return Err(P2PError::ConnectionFailed(
+                "Link closed during handshake".to_string(),
+            ))
+        }
+    };
+    let linked = hello
+        .and_then(|hello| verify_hello(&gateway, &nonce, &hello))
+        .and_then(|(peer_id, transport)| {
+            let frames = iroh.attach_link(&peer_id, &transport)?;
+            Ok((peer_id, frames))
+        });
+
+    match linked {
+        Ok((peer_id, frames)) => Ok((socket, peer_id, frames)),
+        Err(e) => {
+            let rejected = LinkMessage::Rejected {
+                reason: e.to_string(),
+            };
+            if let Ok(text) = rejected.to_text() {
+                let _ = socket.send(Message::Text(text)).await;
+            }
+            let _ = socket.close(None).await;
+            Err(e)
+        }
+    }
+}
+
+/// Accept the link subprotocol when the browser asks for it.
+#[allow(clippy::result_large_err)]
+fn select_protocol(
+    request: &Request,
+    mut response: Response,
+) -> std::result::Result<Response, ErrorResponse> {
+    let requested = request
+        .headers()
+        .get_all("Sec-WebSocket-Protocol")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|protocol| protocol.trim() == LINK_PROTOCOL);
+    if requested {
+        response.headers_mut().insert(
+            "Sec-WebSocket-Protocol",
+            HeaderValue::from_static(LINK_PROTOCOL),
+        );
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framing;
+    use crate::iroh_adapter::P2PConfig;
+    use crate::sync_protocol::SyncMessage;
+    use ed25519_dalek::SigningKey;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+    type Client = WebSocketStream<tokio_tungstenite::MaybeTlsStream<TcpStream>>;
+
+    async fn open(gateway: &BrowserGateway) -> (Client, PeerId, String) {
+        let mut request = gateway.url().into_client_request().unwrap();
+        request.headers_mut().insert(
+            "Sec-WebSocket-Protocol",
+            HeaderValue::from_static(LINK_PROTOCOL),
+        );
+        let (mut socket, response) = tokio_tungstenite::connect_async(request).await.unwrap();
+        assert_eq!(response.headers()["Sec-WebSocket-Protocol"], LINK_PROTOCOL);
+
+        let Some(Ok(Message::Text(text))) = socket.next().await else {
+            panic!("expected a challenge");
+        };
+        let LinkMessage::Challenge { gateway, nonce } = LinkMessage::from_text(&text).unwrap()
+        else {
+            panic!("expected a challenge");
+        };
+        (socket, gateway, nonce)
+    }
+
+    async fn next_text(socket: &mut Client) -> LinkMessage {
+        let Some(Ok(Message::Text(text))) = socket.next().await else {
+            panic!("expected a text frame");
+        };
+        LinkMessage::from_text(&text).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_browser_link() {
+        let iroh = Arc::new(IrohAdapter::new(P2PConfig::default()).await.unwrap());
+        let config = BrowserGatewayConfig {
+            listen_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+            ..Default::default()
+        };
+        let gateway = BrowserGateway::spawn(config, Arc::clone(&iroh))
+            .await
+            .unwrap();
+        let key = SigningKey::generate(&mut rand::rngs::OsRng);
+
+        // A hello signed for another challenge is rejected
+        let (mut socket, gateway_id, _) = open(&gateway).await;
+        assert_eq!(gateway_id, iroh.node_id().to_string());
+        let hello = LinkMessage::hello(&key, &gateway_id, &new_nonce(), TransportHello::local());
+        socket
+            .send(Message::Text(hello.to_text().unwrap()))
+            .await
+            .unwrap();
+        assert!(matches!(
+            next_text(&mut socket).await,
+            LinkMessage::Rejected { .. }
+        ));
+        assert!(iroh.connected_peers().is_empty());
+
+        let (mut socket, gateway_id, nonce) = open(&gateway).await;
+        let hello = LinkMessage::hello(&key, &gateway_id, &nonce, TransportHello::local());
+        socket
+            .send(Message::Text(hello.to_text().unwrap()))
+            .await
+            .unwrap();
+        let LinkMessage::Welcome { peer_id, .. } = next_text(&mut socket).await else {
+            panic!("expected a welcome");
+        };
+        assert_eq!(iroh.connected_peers(), vec![peer_id.clone()]);
+
+        // Sync messages flow both ways
+        let message = SyncMessage::SyncComplete {
+            namespace: "users".to_string(),
+            id: "alice".to_string(),
+            version: 3,
+        };
+        socket
+            .send(Message::Binary(message.to_bytes().unwrap()))
+            .await
+            .unwrap();
+        let (from, received) = iroh.recv_message().await.unwrap();
+        assert_eq!(from, peer_id);
+        assert!(matches!(
+            received,
+            SyncMessage::SyncComplete { version: 3, .. }
+        ));
+
+        iroh.send_message(&peer_id, &message).await.unwrap();
+        let Some(Ok(Message::Binary(frame))) = socket.next().await else {
+            panic!("expected a binary frame");
+        };
+        let received = framing::read_message(&mut &frame[..], usize::MAX)
+            .await
+            .unwrap();
+        assert!(matches!(
+            SyncMessage::from_bytes(&received.bytes).unwrap(),
+            SyncMessage::SyncComplete { version: 3, .. }
+        ));
+
+        // Closing the socket detaches the browser
+        socket.close(None).await.unwrap();
+        for _ in 0..50 {
+            if iroh.connected_peers().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(iroh.connected_peers().is_empty());
+    }
+}
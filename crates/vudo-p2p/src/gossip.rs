@@ -1,7 +1,22 @@
 //! Gossip overlay for presence and document discovery.
-
+//!
+//! Messages reach local subscribers directly. Topics joined on the network
+//! (backed by iroh-gossip) also carry them to remote peers, as
+//! [`SignedGossipMessage`]s: each message is signed with its sender's node
+//! key, and messages whose signature doesn't match the claimed peer ID are
+//! rejected.
+//!
+//! Application topics ([`Topic::app`]) carry ephemeral signals such as
+//! cursors or typing indicators without writing documents. Publishing to one
+//! can be restricted to holders of a UCAN granting
+//! [`PUBLISH`](crate::auth::PUBLISH) on it; their messages then carry a
+//! publish token, checked by every receiver.
+
+use crate::auth::SyncAuthPolicy;
 use crate::error::{P2PError, Result};
+use crate::presence::PresenceState;
 use crate::sync_protocol::PeerId;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
@@ -24,6 +39,11 @@ impl Topic {
         Self(format!("doc:{}:{}", namespace, id))
     }
 
+    /// Create an application topic, for signals not tied to a document.
+    pub fn app(name: &str) -> Self {
+        Self(format!("app:{}", name))
+    }
+
     /// Create a topic for presence.
     pub fn presence() -> Self {
         Self("presence".to_string())
@@ -33,6 +53,11 @@ impl Topic {
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// Get the 32-byte topic ID used on the network.
+    pub fn id(&self) -> [u8; 32] {
+        *blake3::hash(self.0.as_bytes()).as_bytes()
+    }
 }
 
 impl From<String> for Topic {
@@ -87,9 +112,147 @@ pub enum GossipMessage {
         /// Timestamp.
         timestamp: u64,
     },
+
+    /// Application-defined payload for topics not tied to a document.
+    Application {
+        /// Peer ID.
+        peer_id: PeerId,
+        /// Application topic the payload belongs to.
+        topic: String,
+        /// Opaque payload (encoding chosen by the application).
+        payload: Vec<u8>,
+        /// Publish token, for topics with restricted publishers (see
+        /// [`publish_token`](crate::auth::publish_token)).
+        proof: Option<String>,
+        /// Timestamp.
+        timestamp: u64,
+    },
+
+    /// Periodic presence heartbeat (see [`crate::presence`]).
+    Heartbeat {
+        /// Peer ID.
+        peer_id: PeerId,
+        /// DID of the user or device, as claimed by the peer.
+        did: Option<String>,
+        /// Presence state.
+        state: PresenceState,
+        /// Documents the peer is active on.
+        documents: Vec<(String, String)>, // (namespace, id)
+        /// Timestamp.
+        timestamp: u64,
+    },
 }
 
 impl GossipMessage {
+    /// Create a document update notification.
+    pub fn document_update(peer_id: PeerId, namespace: &str, id: &str, version: u64) -> Self {
+        GossipMessage::DocumentUpdate {
+            peer_id,
+            namespace: namespace.to_string(),
+            id: id.to_string(),
+            version,
+            timestamp: current_timestamp(),
+        }
+    }
+
+    /// Get the peer the message claims to come from.
+    pub fn peer_id(&self) -> &PeerId {
+        match self {
+            GossipMessage::Presence { peer_id, .. }
+            | GossipMessage::DocumentAnnouncement { peer_id, .. }
+            | GossipMessage::DocumentUpdate { peer_id, .. }
+            | GossipMessage::Application { peer_id, .. }
+            | GossipMessage::Heartbeat { peer_id, .. } => peer_id,
+        }
+    }
+
+    /// Sign the message with the sender's node key.
+    pub fn sign(self, signing_key: &SigningKey) -> Result<SignedGossipMessage> {
+        let signature = signing_key.sign(&SignedGossipMessage::signed_bytes(&self)?);
+        Ok(SignedGossipMessage {
+            message: self,
+            signer: signing_key.verifying_key(),
+            signature,
+        })
+    }
+
+    /// Get the topic the message is published to.
+    pub fn topic(&self) -> Topic {
+        match self {
+            GossipMessage::Presence { .. } | GossipMessage::Heartbeat { .. } => Topic::presence(),
+            GossipMessage::DocumentAnnouncement { namespace, id, .. }
+            | GossipMessage::DocumentUpdate { namespace, id, .. } => Topic::document(namespace, id),
+            GossipMessage::Application { topic, .. } => Topic::new(topic.clone()),
+        }
+    }
+
+    /// Serialize message to bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        bincode::serialize(self).map_err(P2PError::from)
+    }
+
+    /// Deserialize message from bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        bincode::deserialize(bytes).map_err(P2PError::from)
+    }
+}
+
+/// Domain tag of gossip message signatures.
+const SIGNATURE_DOMAIN: &[u8] = b"vudo-gossip/1";
+
+/// Get the peer ID of the node owning a key.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn peer_id_of(key: &VerifyingKey) -> PeerId {
+    iroh::net::key::PublicKey::from(*key).to_string()
+}
+
+/// Get the peer ID of the node owning a key.
+///
+/// Formatted like an Iroh node ID: the key in lowercase, unpadded base32.
+#[cfg(target_arch = "wasm32")]
+pub fn peer_id_of(key: &VerifyingKey) -> PeerId {
+    data_encoding::BASE32_NOPAD
+        .encode(key.as_bytes())
+        .to_ascii_lowercase()
+}
+
+/// Gossip message signed by its sender's node key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedGossipMessage {
+    /// The message.
+    pub message: GossipMessage,
+    /// Node key of the sender.
+    pub signer: VerifyingKey,
+    /// Signature over the message.
+    pub signature: Signature,
+}
+
+impl SignedGossipMessage {
+    /// Bytes covered by the signature.
+    fn signed_bytes(message: &GossipMessage) -> Result<Vec<u8>> {
+        let mut bytes = SIGNATURE_DOMAIN.to_vec();
+        bytes.extend(message.to_bytes()?);
+        Ok(bytes)
+    }
+
+    /// Check the signature, and that the signer is the peer the message
+    /// claims to come from.
+    pub fn verify(&self) -> Result<()> {
+        self.signer
+            .verify(&Self::signed_bytes(&self.message)?, &self.signature)
+            .map_err(|_| P2PError::PermissionDenied("Invalid gossip signature".to_string()))?;
+
+        let signer = peer_id_of(&self.signer);
+        if *self.message.peer_id() != signer {
+            return Err(P2PError::PermissionDenied(format!(
+                "Gossip from {} signed by {}",
+                self.message.peer_id(),
+                signer
+            )));
+        }
+        Ok(())
+    }
+
     /// Serialize message to bytes.
     pub fn to_bytes(&self) -> Result<Vec<u8>> {
         bincode::serialize(self).map_err(P2PError::from)
@@ -101,6 +264,9 @@ impl GossipMessage {
     }
 }
 
+/// Sends signed messages to the network peers of a joined topic.
+pub type TopicSender = mpsc::UnboundedSender<SignedGossipMessage>;
+
 /// Subscription handle.
 pub struct Subscription {
     /// Subscription ID.
@@ -124,6 +290,47 @@ impl Subscription {
 /// Subscription ID.
 pub type SubscriptionId = u64;
 
+/// Payload received on an application topic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicMessage {
+    /// Peer that published it.
+    pub peer_id: PeerId,
+    /// Opaque payload.
+    pub payload: Vec<u8>,
+    /// When it was published (milliseconds since epoch).
+    pub timestamp: u64,
+}
+
+/// Subscription to an application topic.
+pub struct TopicSubscription(Subscription);
+
+impl TopicSubscription {
+    /// Receive the next payload.
+    pub async fn recv(&mut self) -> Option<TopicMessage> {
+        while let Some(message) = self.0.recv().await {
+            if let GossipMessage::Application {
+                peer_id,
+                payload,
+                timestamp,
+                ..
+            } = message
+            {
+                return Some(TopicMessage {
+                    peer_id,
+                    payload,
+                    timestamp,
+                });
+            }
+        }
+        None
+    }
+
+    /// Get subscription ID.
+    pub fn id(&self) -> SubscriptionId {
+        self.0.id()
+    }
+}
+
 /// Gossip overlay manager.
 pub struct GossipOverlay {
     /// Topic subscriptions.
@@ -132,6 +339,14 @@ pub struct GossipOverlay {
     next_sub_id: Arc<RwLock<SubscriptionId>>,
     /// Peer interests (which peers are interested in which topics).
     peer_interests: Arc<RwLock<HashMap<PeerId, HashSet<Topic>>>>,
+    /// Topics joined on the network.
+    joined: Arc<RwLock<HashMap<Topic, TopicSender>>>,
+    /// Key signing outgoing network messages.
+    signing_key: Option<SigningKey>,
+    /// Who may publish to restricted application topics.
+    publishers: Arc<RwLock<HashMap<Topic, SyncAuthPolicy>>>,
+    /// Publish tokens attached to this node's application messages.
+    publish_tokens: Arc<RwLock<HashMap<Topic, String>>>,
 }
 
 impl GossipOverlay {
@@ -141,7 +356,165 @@ impl GossipOverlay {
             subscriptions: Arc::new(RwLock::new(HashMap::new())),
             next_sub_id: Arc::new(RwLock::new(0)),
             peer_interests: Arc::new(RwLock::new(HashMap::new())),
+            joined: Arc::new(RwLock::new(HashMap::new())),
+            signing_key: None,
+            publishers: Arc::new(RwLock::new(HashMap::new())),
+            publish_tokens: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Sign messages sent to the network with a node key.
+    pub fn with_signing_key(mut self, signing_key: SigningKey) -> Self {
+        self.signing_key = Some(signing_key);
+        self
+    }
+
+    /// Get the peer ID messages are signed as.
+    pub fn local_peer_id(&self) -> Option<PeerId> {
+        self.signing_key
+            .as_ref()
+            .map(|key| peer_id_of(&key.verifying_key()))
+    }
+
+    /// Join a topic on the network.
+    ///
+    /// Announcements to the topic are signed and passed to `sender`, which
+    /// delivers them to the topic's network peers.
+    pub fn join(&self, topic: Topic, sender: TopicSender) -> Result<()> {
+        if self.signing_key.is_none() {
+            return Err(P2PError::Internal(
+                "Gossip overlay has no signing key".to_string(),
+            ));
         }
+        info!("Joined topic: {}", topic.as_str());
+        self.joined.write().insert(topic, sender);
+        Ok(())
+    }
+
+    /// Leave a topic on the network.
+    ///
+    /// Returns whether the topic was joined. Its sender is dropped, and
+    /// network peers are no longer tracked as interested in it.
+    pub fn leave(&self, topic: &Topic) -> bool {
+        if self.joined.write().remove(topic).is_none() {
+            return false;
+        }
+        for interests in self.peer_interests.write().values_mut() {
+            interests.remove(topic);
+        }
+        info!("Left topic: {}", topic.as_str());
+        true
+    }
+
+    /// Check whether a topic is joined on the network.
+    pub fn is_joined(&self, topic: &Topic) -> bool {
+        self.joined
+            .read()
+            .get(topic)
+            .is_some_and(|sender| !sender.is_closed())
+    }
+
+    /// Get the topics joined on the network.
+    pub fn joined_topics(&self) -> Vec<Topic> {
+        self.joined
+            .read()
+            .iter()
+            .filter(|(_, sender)| !sender.is_closed())
+            .map(|(topic, _)| topic.clone())
+            .collect()
+    }
+
+    /// Handle a signed message received on a topic.
+    ///
+    /// The message is delivered to local subscribers if its signature is
+    /// valid, it belongs to the topic, and its sender may publish to the
+    /// topic.
+    pub async fn receive(
+        &self,
+        topic: &Topic,
+        signed: SignedGossipMessage,
+    ) -> Result<GossipMessage> {
+        signed.verify()?;
+        let message = signed.message;
+        if message.topic() != *topic {
+            return Err(P2PError::InvalidMessage(format!(
+                "Gossip for {} received on {}",
+                message.topic().as_str(),
+                topic.as_str()
+            )));
+        }
+        self.check_publisher(topic, &message)?;
+
+        self.publish(topic.clone(), message.clone()).await?;
+        Ok(message)
+    }
+
+    /// Check that the sender of an application message may publish to the
+    /// topic, if its publishers are restricted.
+    fn check_publisher(&self, topic: &Topic, message: &GossipMessage) -> Result<()> {
+        let GossipMessage::Application { peer_id, proof, .. } = message else {
+            return Ok(());
+        };
+        let Some(policy) = self.publishers.read().get(topic).cloned() else {
+            return Ok(());
+        };
+
+        let proof = proof.as_deref().ok_or_else(|| {
+            P2PError::PermissionDenied(format!(
+                "Publishing to {} needs a publish token",
+                topic.as_str()
+            ))
+        })?;
+        policy.verify_publish(proof, peer_id, topic.as_str())?;
+        Ok(())
+    }
+
+    /// Only accept messages on an application topic from publishers
+    /// authorized by `policy`.
+    ///
+    /// Local publishing is not restricted.
+    pub fn restrict_publishers(&self, topic: Topic, policy: SyncAuthPolicy) {
+        info!("Restricted publishers of topic: {}", topic.as_str());
+        self.publishers.write().insert(topic, policy);
+    }
+
+    /// Let anyone publish to an application topic again.
+    ///
+    /// Returns whether its publishers were restricted.
+    pub fn unrestrict_publishers(&self, topic: &Topic) -> bool {
+        self.publishers.write().remove(topic).is_some()
+    }
+
+    /// Attach a publish token to this node's messages on an application
+    /// topic (see [`publish_token`](crate::auth::publish_token)).
+    pub fn set_publish_token(&self, topic: Topic, token: String) {
+        self.publish_tokens.write().insert(topic, token);
+    }
+
+    /// Sign a message with the node key.
+    pub fn sign(&self, message: GossipMessage) -> Result<SignedGossipMessage> {
+        let signing_key = self
+            .signing_key
+            .as_ref()
+            .ok_or_else(|| P2PError::Internal("Gossip overlay has no signing key".to_string()))?;
+        message.sign(signing_key)
+    }
+
+    /// Publish a message locally and to the network, if its topic is joined.
+    async fn announce(&self, message: GossipMessage) -> Result<()> {
+        let topic = message.topic();
+        self.publish(topic.clone(), message.clone()).await?;
+
+        let sender = self.joined.read().get(&topic).cloned();
+        if let Some(sender) = sender {
+            if sender.send(self.sign(message)?).is_err() {
+                warn!(
+                    "Topic {} is no longer connected to the network",
+                    topic.as_str()
+                );
+            }
+        }
+        Ok(())
     }
 
     /// Subscribe to a topic.
@@ -203,8 +576,6 @@ impl GossipOverlay {
 
     /// Announce document presence.
     pub async fn announce_document(&self, peer_id: PeerId, namespace: &str, id: &str, version: u64) -> Result<()> {
-        let topic = Topic::document(namespace, id);
-
         let message = GossipMessage::DocumentAnnouncement {
             peer_id,
             namespace: namespace.to_string(),
@@ -213,35 +584,58 @@ impl GossipOverlay {
             timestamp: current_timestamp(),
         };
 
-        self.publish(topic, message).await
+        self.announce(message).await
     }
 
     /// Announce document update.
     pub async fn announce_update(&self, peer_id: PeerId, namespace: &str, id: &str, version: u64) -> Result<()> {
-        let topic = Topic::document(namespace, id);
+        let message = GossipMessage::document_update(peer_id, namespace, id, version);
+        self.announce(message).await
+    }
 
-        let message = GossipMessage::DocumentUpdate {
+    /// Announce presence with available documents.
+    pub async fn announce_presence(&self, peer_id: PeerId, documents: Vec<(String, String)>) -> Result<()> {
+        let message = GossipMessage::Presence {
             peer_id,
-            namespace: namespace.to_string(),
-            id: id.to_string(),
-            version,
+            documents,
             timestamp: current_timestamp(),
         };
 
-        self.publish(topic, message).await
+        self.announce(message).await
     }
 
-    /// Announce presence with available documents.
-    pub async fn announce_presence(&self, peer_id: PeerId, documents: Vec<(String, String)>) -> Result<()> {
-        let topic = Topic::presence();
-
-        let message = GossipMessage::Presence {
+    /// Announce a presence heartbeat.
+    pub async fn announce_heartbeat(
+        &self,
+        peer_id: PeerId,
+        did: Option<String>,
+        state: PresenceState,
+        documents: Vec<(String, String)>,
+    ) -> Result<()> {
+        let message = GossipMessage::Heartbeat {
             peer_id,
+            did,
+            state,
             documents,
             timestamp: current_timestamp(),
         };
 
-        self.publish(topic, message).await
+        self.announce(message).await
+    }
+
+    /// Publish an application-defined payload to an application topic (see
+    /// [`Topic::app`]), received with [`GossipOverlay::subscribe_application`].
+    pub async fn publish_application(&self, peer_id: PeerId, topic: Topic, payload: Vec<u8>) -> Result<()> {
+        let proof = self.publish_tokens.read().get(&topic).cloned();
+        let message = GossipMessage::Application {
+            peer_id,
+            topic: topic.as_str().to_string(),
+            payload,
+            proof,
+            timestamp: current_timestamp(),
+        };
+
+        self.announce(message).await
     }
 
     /// Subscribe to document updates.
@@ -250,12 +644,25 @@ impl GossipOverlay {
         self.subscribe(topic).await
     }
 
+    /// Subscribe to the payloads of an application topic.
+    pub async fn subscribe_application(&self, topic: Topic) -> Result<TopicSubscription> {
+        self.subscribe(topic).await.map(TopicSubscription)
+    }
+
     /// Subscribe to presence announcements.
     pub async fn subscribe_presence(&self) -> Result<Subscription> {
         let topic = Topic::presence();
         self.subscribe(topic).await
     }
 
+    /// Check whether a topic has live local subscribers.
+    pub fn is_subscribed(&self, topic: &Topic) -> bool {
+        self.subscriptions
+            .read()
+            .get(topic)
+            .is_some_and(|subs| subs.iter().any(|(_, tx)| !tx.is_closed()))
+    }
+
     /// Track peer interest in a topic.
     pub fn add_peer_interest(&self, peer_id: &PeerId, topic: Topic) {
         self.peer_interests
@@ -310,6 +717,7 @@ fn current_timestamp() -> u64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::auth;
 
     #[test]
     fn test_topic_creation() {
@@ -388,6 +796,198 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_publish_application() {
+        let overlay = GossipOverlay::new();
+        let topic = Topic::new("app:signals");
+        let mut sub = overlay.subscribe(topic.clone()).await.unwrap();
+
+        overlay
+            .publish_application("peer1".to_string(), topic, b"hello".to_vec())
+            .await
+            .unwrap();
+
+        match sub.recv().await.unwrap() {
+            GossipMessage::Application { peer_id, topic, payload, .. } => {
+                assert_eq!(peer_id, "peer1");
+                assert_eq!(topic, "app:signals");
+                assert_eq!(payload, b"hello");
+            }
+            _ => panic!("Wrong message type"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_message_topic_and_subscription() {
+        let overlay = GossipOverlay::new();
+        let message = GossipMessage::document_update("peer1".to_string(), "users", "alice", 3);
+        assert_eq!(message.topic(), Topic::document("users", "alice"));
+        assert!(!overlay.is_subscribed(&message.topic()));
+
+        let sub = overlay.subscribe_document("users", "alice").await.unwrap();
+        assert!(overlay.is_subscribed(&message.topic()));
+
+        // Dropped subscriptions no longer count
+        drop(sub);
+        assert!(!overlay.is_subscribed(&message.topic()));
+    }
+
+    fn signing_key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    #[test]
+    fn test_signed_message() {
+        let key = signing_key(1);
+        let peer_id = peer_id_of(&key.verifying_key());
+        let message = GossipMessage::document_update(peer_id.clone(), "users", "alice", 1);
+
+        let signed = message.clone().sign(&key).unwrap();
+        signed.verify().unwrap();
+        let decoded = SignedGossipMessage::from_bytes(&signed.to_bytes().unwrap()).unwrap();
+        decoded.verify().unwrap();
+        assert_eq!(decoded.message.peer_id(), &peer_id);
+
+        // Tampered messages fail verification
+        let mut tampered = signed.clone();
+        tampered.message = GossipMessage::document_update(peer_id, "users", "alice", 2);
+        assert!(tampered.verify().is_err());
+
+        // Signing as someone else is rejected
+        let spoofed = GossipMessage::Presence {
+            peer_id: peer_id_of(&signing_key(2).verifying_key()),
+            documents: vec![],
+            timestamp: 12345,
+        };
+        let signed = spoofed.sign(&key).unwrap();
+        assert!(matches!(
+            signed.verify(),
+            Err(P2PError::PermissionDenied(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_join_and_receive() {
+        let key = signing_key(1);
+        let peer_id = peer_id_of(&key.verifying_key());
+        let sender = GossipOverlay::new().with_signing_key(key);
+        let receiver = GossipOverlay::new();
+        assert_eq!(sender.local_peer_id(), Some(peer_id.clone()));
+
+        // Joining needs a key to sign with
+        let (tx, mut network) = mpsc::unbounded_channel();
+        assert!(receiver.join(Topic::presence(), tx.clone()).is_err());
+        sender.join(Topic::presence(), tx).unwrap();
+        assert!(sender.is_joined(&Topic::presence()));
+
+        // Announcements are signed and handed to the network
+        sender
+            .announce_presence(
+                peer_id.clone(),
+                vec![("users".to_string(), "alice".to_string())],
+            )
+            .await
+            .unwrap();
+        let signed = network.recv().await.unwrap();
+
+        let mut sub = receiver.subscribe_presence().await.unwrap();
+        let received = receiver
+            .receive(&Topic::presence(), signed.clone())
+            .await
+            .unwrap();
+        assert_eq!(received.peer_id(), &peer_id);
+        assert_eq!(sub.recv().await.unwrap().peer_id(), &peer_id);
+
+        // Messages must belong to the topic they arrive on
+        let other = Topic::document("users", "alice");
+        assert!(receiver.receive(&other, signed).await.is_err());
+
+        // Topics not joined stay local
+        sender
+            .announce_update(peer_id.clone(), "users", "alice", 1)
+            .await
+            .unwrap();
+        assert!(network.try_recv().is_err());
+
+        sender.add_peer_interest(&"peer2".to_string(), Topic::presence());
+        assert!(sender.leave(&Topic::presence()));
+        assert!(!sender.is_joined(&Topic::presence()));
+        assert!(sender.get_interested_peers(&Topic::presence()).is_empty());
+        assert!(!sender.leave(&Topic::presence()));
+    }
+
+    #[tokio::test]
+    async fn test_receive_rejects_spoofed() {
+        let overlay = GossipOverlay::new();
+        let mut sub = overlay.subscribe_presence().await.unwrap();
+
+        let spoofed = GossipMessage::Presence {
+            peer_id: peer_id_of(&signing_key(2).verifying_key()),
+            documents: vec![],
+            timestamp: 12345,
+        };
+        let signed = spoofed.sign(&signing_key(1)).unwrap();
+        assert!(overlay.receive(&Topic::presence(), signed).await.is_err());
+        assert!(sub.rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_restricted_publishers() {
+        let overlay = GossipOverlay::new();
+        let topic = Topic::app("cursors");
+        let mut sub = overlay.subscribe_application(topic.clone()).await.unwrap();
+        let key = signing_key(1);
+        let peer_id = peer_id_of(&key.verifying_key());
+        let device = vudo_identity::DeviceIdentity::generate("Phone")
+            .await
+            .unwrap();
+        let policy = SyncAuthPolicy::new().trust(device.did().clone());
+        overlay.restrict_publishers(topic.clone(), policy);
+
+        let message = |proof| {
+            GossipMessage::Application {
+                peer_id: peer_id.clone(),
+                topic: topic.as_str().to_string(),
+                payload: b"x".to_vec(),
+                proof,
+                timestamp: 1,
+            }
+            .sign(&key)
+            .unwrap()
+        };
+
+        // Rejected without a token, or with one issued to another node
+        assert!(overlay.receive(&topic, message(None)).await.is_err());
+        let other = auth::publish_token(&device, &"other".to_string(), topic.as_str()).unwrap();
+        assert!(overlay.receive(&topic, message(Some(other))).await.is_err());
+
+        let token = auth::publish_token(&device, &peer_id, topic.as_str()).unwrap();
+        overlay
+            .receive(&topic, message(Some(token.clone())))
+            .await
+            .unwrap();
+        let received = sub.recv().await.unwrap();
+        assert_eq!(received.peer_id, peer_id);
+        assert_eq!(received.payload, b"x");
+
+        // Anyone may publish once unrestricted
+        assert!(overlay.unrestrict_publishers(&topic));
+        overlay.receive(&topic, message(None)).await.unwrap();
+        assert!(sub.recv().await.is_some());
+
+        // The token is attached to this node's messages
+        let mut raw = overlay.subscribe(topic.clone()).await.unwrap();
+        overlay.set_publish_token(topic.clone(), token.clone());
+        overlay
+            .publish_application(peer_id, topic, b"y".to_vec())
+            .await
+            .unwrap();
+        assert!(matches!(
+            raw.recv().await.unwrap(),
+            GossipMessage::Application { proof: Some(proof), .. } if proof == token
+        ));
+    }
+
     #[test]
     fn test_peer_interests() {
         let overlay = GossipOverlay::new();
@@ -1,15 +1,50 @@
 //! Iroh node management and connection handling.
 
+use crate::auth::SyncAuthPolicy;
+use crate::bandwidth::{
+    BandwidthLimit, BandwidthManager, LimitScope, LinkEstimate, LinkSample, TrafficDirection,
+};
+use crate::connection_pool::{
+    ConnectionEvent, ConnectionPool, ConnectionPoolConfig, DisconnectReason,
+};
+use crate::diagnostics::{PathKind, PathSample};
 use crate::error::{P2PError, Result};
+use crate::file_transfer::{is_transfer_id, FileTransferConfig, TransferId, TRANSFER_PRIORITY};
+use crate::framing::{self, PeerTransports, TransportConfig, TransportHello};
+use crate::mailbox::{MailboxConfig, DEFAULT_MAIL_INTERVAL};
+use crate::peer_access::PeerAccessConfig;
+use crate::presence::PresenceConfig;
+use crate::recording::{Direction, SessionRecorder};
+use crate::relay::{RelayHealth, RelaySelector, DEFAULT_PROBE_INTERVAL};
+use crate::replication::DEFAULT_REPLICATION_INTERVAL;
+use crate::revocations::DEFAULT_REVOCATION_INTERVAL;
+use crate::session_cache::SessionCache;
+use crate::supervisor::{Heartbeat, SupervisorConfig};
+use crate::sync_policy::SyncPolicy;
 use crate::sync_protocol::{PeerId, SyncMessage};
-use iroh::net::endpoint::{Connection, Incoming};
-use iroh::net::{Endpoint, NodeAddr, NodeId};
+use ed25519_dalek::SigningKey;
+use iroh::net::defaults::DEFAULT_STUN_PORT;
+use iroh::net::endpoint::{get_remote_node_id, Connection, ConnectionType, Incoming};
+use iroh::net::relay::{RelayMap, RelayMode, RelayNode};
+use iroh::net::{Endpoint, NodeAddr, NodeId, RelayUrl};
+use iroh_blobs::get::db::DownloadProgress;
+use iroh_blobs::store::{ExportMode, ImportMode, Store as _};
+use iroh_blobs::util::local_pool::{LocalPool, LocalPoolHandle};
+use iroh_blobs::util::progress::{AsyncChannelProgressSender, IgnoreProgressSender};
+use iroh_blobs::{BlobFormat, Hash, HashAndFormat};
+use iroh_gossip::net::{Gossip, GossipTopic, GOSSIP_ALPN};
+use iroh_gossip::proto::TopicId;
 use parking_lot::RwLock;
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
 use std::time::Duration;
 use tokio::sync::mpsc;
 use tracing::{debug, info, warn};
+use vudo_identity::GuestPolicy;
 
 /// ALPN protocol identifier for VUDO P2P.
 const ALPN: &[u8] = b"vudo-p2p/1";
@@ -21,6 +56,14 @@ pub struct P2PConfig {
     pub node_name: String,
     /// Enable relay mode.
     pub enable_relay: bool,
+    /// Relay servers to choose from, e.g. one per region (Iroh's default
+    /// relays when empty).
+    pub relays: Vec<String>,
+    /// Interval between latency probes of the configured relays.
+    pub relay_probe_interval: Duration,
+    /// Reach peers through relays only: direct addresses are neither dialed
+    /// nor advertised (requires `enable_relay`).
+    pub relay_only: bool,
     /// Enable mDNS discovery.
     pub enable_mdns: bool,
     /// Enable DHT discovery.
@@ -29,6 +72,47 @@ pub struct P2PConfig {
     pub connection_timeout: Duration,
     /// Maximum concurrent connections.
     pub max_connections: usize,
+    /// Reconnects, idle expiry and connection events.
+    pub connection_pool: ConnectionPoolConfig,
+    /// Upload and download limits, globally and per peer, namespace or
+    /// priority (changeable at runtime through the bandwidth manager).
+    pub bandwidth_limits: HashMap<LimitScope, BandwidthLimit>,
+    /// How long address hints for recently-seen peers are kept.
+    pub session_cache_ttl: Duration,
+    /// Local file keeping address hints across restarts (kept in memory only
+    /// when `None`).
+    pub session_cache_path: Option<PathBuf>,
+    /// Direct file transfer settings.
+    pub file_transfer: FileTransferConfig,
+    /// Address of the local control API (disabled when `None`).
+    pub control_addr: Option<SocketAddr>,
+    /// File to record sync sessions to (disabled when `None`).
+    pub record_path: Option<PathBuf>,
+    /// Run as a guest with an ephemeral identity (disabled when `None`).
+    pub guest: Option<GuestPolicy>,
+    /// Watchdog restarting failed subsystems.
+    pub supervisor: SupervisorConfig,
+    /// Require peers to authenticate with a UCAN before syncing (anyone may
+    /// sync when `None`).
+    pub sync_auth: Option<SyncAuthPolicy>,
+    /// Allowed and blocked peers, and whether unknown peers need pairing
+    /// approval.
+    pub peer_access: PeerAccessConfig,
+    /// Presence heartbeats and timeouts.
+    pub presence: PresenceConfig,
+    /// Which documents are pulled from peers (changeable at runtime through
+    /// [`VudoP2P::set_sync_policy`](crate::VudoP2P::set_sync_policy)).
+    pub sync_policy: SyncPolicy,
+    /// Compression and chunking of messages.
+    pub transport: TransportConfig,
+    /// Interval between syncs of replication groups with their members.
+    pub replication_interval: Duration,
+    /// Hold mail for offline peers (not a mailbox when `None`).
+    pub mailbox: Option<MailboxConfig>,
+    /// Interval between mail fetches from connected mailboxes.
+    pub mail_interval: Duration,
+    /// Interval between pruning revocations and syncing them with peers.
+    pub revocation_interval: Duration,
 }
 
 impl Default for P2PConfig {
@@ -36,10 +120,31 @@ impl Default for P2PConfig {
         Self {
             node_name: "vudo-node".to_string(),
             enable_relay: true,
+            relays: Vec::new(),
+            relay_probe_interval: DEFAULT_PROBE_INTERVAL,
+            relay_only: false,
             enable_mdns: true,
             enable_dht: true,
             connection_timeout: Duration::from_secs(10),
             max_connections: 100,
+            connection_pool: ConnectionPoolConfig::default(),
+            bandwidth_limits: HashMap::new(),
+            session_cache_ttl: Duration::from_secs(7 * 24 * 60 * 60),
+            session_cache_path: None,
+            file_transfer: FileTransferConfig::default(),
+            control_addr: None,
+            record_path: None,
+            guest: None,
+            supervisor: SupervisorConfig::default(),
+            sync_auth: None,
+            peer_access: PeerAccessConfig::default(),
+            presence: PresenceConfig::default(),
+            sync_policy: SyncPolicy::default(),
+            transport: TransportConfig::default(),
+            replication_interval: DEFAULT_REPLICATION_INTERVAL,
+            mailbox: None,
+            mail_interval: DEFAULT_MAIL_INTERVAL,
+            revocation_interval: DEFAULT_REVOCATION_INTERVAL,
         }
     }
 }
@@ -53,6 +158,10 @@ pub struct ConnectionMetadata {
     pub established_at: std::time::Instant,
     /// Is this a direct connection (vs relay)?
     pub is_direct: bool,
+    /// Relay carrying the connection, if relayed.
+    pub relay_url: Option<String>,
+    /// Health of that relay, if it is one of the configured relays.
+    pub relay: Option<RelayHealth>,
     /// Number of messages sent.
     pub messages_sent: u64,
     /// Number of messages received.
@@ -67,28 +176,160 @@ pub struct ConnectionMetadata {
 pub struct IrohAdapter {
     /// Iroh endpoint.
     endpoint: Endpoint,
+    /// Gossip swarms of joined topics.
+    gossip: Gossip,
     /// Configuration.
     config: P2PConfig,
     /// Active connections.
     connections: Arc<RwLock<HashMap<PeerId, Connection>>>,
     /// Connection metadata.
     metadata: Arc<RwLock<HashMap<PeerId, ConnectionMetadata>>>,
+    /// Connection limits, reconnects and events.
+    pool: Arc<ConnectionPool>,
     /// Incoming message channel.
     message_tx: mpsc::UnboundedSender<(PeerId, SyncMessage)>,
     /// Incoming message receiver.
     message_rx: Arc<tokio::sync::Mutex<mpsc::UnboundedReceiver<(PeerId, SyncMessage)>>>,
+    /// Address hints for recently-seen peers.
+    session_cache: Arc<SessionCache>,
+    /// Per-peer relay selection across the configured relays.
+    relays: Arc<RelaySelector>,
+    /// Bandwidth manager fed with per-connection link estimates.
+    bandwidth: Arc<BandwidthManager>,
+    /// Recorder capturing sent and received messages.
+    recorder: RwLock<Option<Arc<SessionRecorder>>>,
+    /// Framing negotiated with each peer.
+    transports: Arc<PeerTransports>,
+    /// Browser links attached by a gateway, with their outgoing frames.
+    links: Arc<RwLock<HashMap<PeerId, mpsc::UnboundedSender<Vec<u8>>>>>,
+    /// ID of the next browser link.
+    next_link_id: AtomicUsize,
+    /// iroh-blobs store and the peers allowed to fetch from it.
+    blobs: BlobProvider,
+    /// Thread pool the iroh-blobs provider runs on.
+    _blob_pool: LocalPool,
+}
+
+/// Blob store serving offered files over iroh-blobs.
+#[derive(Clone)]
+struct BlobProvider {
+    /// Store holding offered files and partial downloads.
+    store: iroh_blobs::store::fs::Store,
+    /// Handle of the pool the provider runs on.
+    rt: LocalPoolHandle,
+    /// Blobs offered to each peer.
+    offered: Arc<RwLock<HashMap<PeerId, HashSet<TransferId>>>>,
+}
+
+impl BlobProvider {
+    /// Whether a peer has any blob offered to it.
+    fn serves(&self, peer_id: &PeerId) -> bool {
+        self.offered
+            .read()
+            .get(peer_id)
+            .is_some_and(|hashes| !hashes.is_empty())
+    }
+}
+
+/// Configured relay URLs, or none when relaying is disabled.
+fn relay_urls(config: &P2PConfig) -> Result<Vec<RelayUrl>> {
+    if config.relay_only && !config.enable_relay {
+        return Err(P2PError::InvalidMessage(
+            "Relay-only mode requires relays to be enabled".to_string(),
+        ));
+    }
+    if !config.enable_relay {
+        return Ok(Vec::new());
+    }
+    config
+        .relays
+        .iter()
+        .map(|url| parse_relay_url(url))
+        .collect()
+}
+
+/// Relay mode of the endpoint.
+fn relay_mode(config: &P2PConfig, relay_urls: &[RelayUrl]) -> Result<RelayMode> {
+    if !config.enable_relay {
+        return Ok(RelayMode::Disabled);
+    }
+    if relay_urls.is_empty() {
+        return Ok(RelayMode::Default);
+    }
+
+    let nodes = relay_urls.iter().map(|url| RelayNode {
+        url: url.clone(),
+        stun_only: false,
+        stun_port: DEFAULT_STUN_PORT,
+    });
+    Ok(RelayMode::Custom(RelayMap::from_nodes(nodes)?))
+}
+
+/// Address of a peer without its direct addresses, for relay-only mode.
+fn relay_addr(node_addr: &NodeAddr) -> NodeAddr {
+    let addr = NodeAddr::new(node_addr.node_id);
+    match node_addr.relay_url() {
+        Some(url) => addr.with_relay_url(url.clone()),
+        None => addr,
+    }
+}
+
+pub(crate) fn parse_relay_url(url: &str) -> Result<RelayUrl> {
+    url.parse()
+        .map_err(|e| P2PError::InvalidMessage(format!("Invalid relay URL {}: {}", url, e)))
+}
+
+/// Sample the transport statistics of a connection.
+fn link_sample(conn: &Connection) -> LinkSample {
+    let stats = conn.stats();
+    LinkSample {
+        rtt: conn.rtt(),
+        cwnd: stats.path.cwnd,
+        congestion_events: stats.path.congestion_events,
+        lost_packets: stats.path.lost_packets,
+        sent_packets: stats.path.sent_packets,
+        tx_bytes: stats.udp_tx.bytes,
+        rx_bytes: stats.udp_rx.bytes,
+    }
 }
 
 impl IrohAdapter {
     /// Create a new Iroh adapter.
     pub async fn new(config: P2PConfig) -> Result<Self> {
+        Self::with_bandwidth(config, Arc::new(BandwidthManager::new())).await
+    }
+
+    /// Create a new Iroh adapter that paces sends through a shared bandwidth manager.
+    pub async fn with_bandwidth(
+        config: P2PConfig,
+        bandwidth: Arc<BandwidthManager>,
+    ) -> Result<Self> {
         info!("[{}] Initializing Iroh endpoint", config.node_name);
 
         // Create endpoint
+        let relay_urls = relay_urls(&config)?;
         let endpoint = Endpoint::builder()
+            .relay_mode(relay_mode(&config, &relay_urls)?)
+            .alpns(vec![
+                ALPN.to_vec(),
+                GOSSIP_ALPN.to_vec(),
+                iroh_blobs::protocol::ALPN.to_vec(),
+            ])
             .bind()
             .await
             .map_err(|e| P2PError::IrohError(e.into()))?;
+        let addr = endpoint.node_addr().await.map_err(P2PError::IrohError)?;
+        let gossip = Gossip::from_endpoint(endpoint.clone(), Default::default(), &addr.info);
+
+        let blob_store = iroh_blobs::store::fs::Store::load(&config.file_transfer.blob_dir)
+            .await
+            .map_err(P2PError::IrohError)?;
+        let blob_pool = LocalPool::default();
+        let blobs = BlobProvider {
+            store: blob_store,
+            rt: blob_pool.handle().clone(),
+            offered: Arc::new(RwLock::new(HashMap::new())),
+        };
 
         info!(
             "[{}] Endpoint created with node ID: {}",
@@ -99,13 +340,34 @@ impl IrohAdapter {
         // Create message channel
         let (message_tx, message_rx) = mpsc::unbounded_channel();
 
+        let session_cache = Arc::new(SessionCache::new(config.session_cache_ttl));
+        let relays = Arc::new(RelaySelector::new(
+            relay_urls.iter().map(|url| url.to_string()),
+        ));
+        let pool = Arc::new(ConnectionPool::new(
+            config.connection_pool.clone(),
+            config.max_connections,
+        ));
+
+        let transports = Arc::new(PeerTransports::new(config.transport.clone()));
         let adapter = Self {
             endpoint,
+            gossip,
             config,
             connections: Arc::new(RwLock::new(HashMap::new())),
             metadata: Arc::new(RwLock::new(HashMap::new())),
+            pool,
             message_tx,
             message_rx: Arc::new(tokio::sync::Mutex::new(message_rx)),
+            session_cache,
+            relays,
+            bandwidth,
+            recorder: RwLock::new(None),
+            transports,
+            links: Arc::new(RwLock::new(HashMap::new())),
+            next_link_id: AtomicUsize::new(0),
+            blobs,
+            _blob_pool: blob_pool,
         };
 
         // Start connection listener
@@ -119,15 +381,222 @@ impl IrohAdapter {
         self.endpoint.node_id()
     }
 
+    /// Get the key of this node's ID, signing its gossip messages.
+    pub fn signing_key(&self) -> SigningKey {
+        self.endpoint.secret_key().secret().clone()
+    }
+
+    /// Join the gossip swarm of a topic.
+    ///
+    /// The swarm is bootstrapped from the given peers, whose addresses must
+    /// be known to the endpoint (e.g. through an earlier connection or
+    /// discovery). The topic is left once both halves of the returned handle
+    /// are dropped.
+    pub fn join_gossip(&self, topic: [u8; 32], bootstrap: Vec<NodeId>) -> Result<GossipTopic> {
+        self.gossip
+            .subscribe(TopicId::from_bytes(topic), bootstrap)
+            .map_err(P2PError::IrohError)
+    }
+
+    /// Import a file into the blob store, returning its hash and size.
+    ///
+    /// The store references the file in place rather than copying it.
+    pub async fn import_blob(&self, path: &Path) -> Result<(TransferId, u64)> {
+        let (tag, size) = self
+            .blobs
+            .store
+            .import_file(
+                path.to_path_buf(),
+                ImportMode::TryReference,
+                BlobFormat::Raw,
+                IgnoreProgressSender::default(),
+            )
+            .await
+            .map_err(|e| {
+                P2PError::Internal(format!("Failed to import {}: {}", path.display(), e))
+            })?;
+        Ok((tag.hash().to_hex(), size))
+    }
+
+    /// Let a peer fetch a blob from us over iroh-blobs.
+    ///
+    /// Peers are admitted while any blob is offered to them. iroh-blobs
+    /// serves whatever hash an admitted peer asks for, so hashes are only
+    /// ever disclosed to the peers they are offered to.
+    pub fn offer_blob(&self, peer_id: &PeerId, hash: &TransferId) {
+        self.blobs
+            .offered
+            .write()
+            .entry(peer_id.clone())
+            .or_default()
+            .insert(hash.clone());
+    }
+
+    /// Stop offering a blob to a peer.
+    pub fn withdraw_blob(&self, peer_id: &PeerId, hash: &TransferId) {
+        let mut offered = self.blobs.offered.write();
+        if let Some(hashes) = offered.get_mut(peer_id) {
+            hashes.remove(hash);
+            if hashes.is_empty() {
+                offered.remove(peer_id);
+            }
+        }
+    }
+
+    /// Fetch a blob of `size` bytes from a peer and save a copy to `target`.
+    ///
+    /// iroh-blobs verifies the data against the hash while it streams and
+    /// only requests ranges missing from the store, so fetching again after
+    /// an interruption resumes the download. The fetch waits until `size`
+    /// fits the download limits for the peer at [`TRANSFER_PRIORITY`].
+    /// `progress` is called with the bytes received so far.
+    pub async fn fetch_blob(
+        &self,
+        peer_id: &PeerId,
+        hash: &TransferId,
+        size: u64,
+        target: &Path,
+        progress: impl Fn(u64) + Send + 'static,
+    ) -> Result<()> {
+        let node_id = peer_id
+            .parse::<NodeId>()
+            .map_err(|e| P2PError::PeerNotFound(format!("{}: {}", peer_id, e)))?;
+        if !is_transfer_id(hash) {
+            return Err(P2PError::InvalidMessage(format!(
+                "Invalid transfer id {:?}",
+                hash
+            )));
+        }
+        let blob: Hash = hash
+            .parse()
+            .map_err(|e| P2PError::InvalidMessage(format!("Invalid transfer id: {}", e)))?;
+
+        // iroh-blobs reports progress without backpressure, so the whole
+        // blob is reserved up front rather than paced while it streams
+        self.bandwidth
+            .throttle(
+                TrafficDirection::Download,
+                peer_id,
+                None,
+                TRANSFER_PRIORITY,
+                usize::try_from(size).unwrap_or(usize::MAX),
+            )
+            .await;
+
+        let conn = tokio::time::timeout(
+            self.config.connection_timeout,
+            self.endpoint
+                .connect(NodeAddr::new(node_id), iroh_blobs::protocol::ALPN),
+        )
+        .await
+        .map_err(|_| P2PError::Timeout)?
+        .map_err(|e| P2PError::ConnectionFailed(e.to_string()))?;
+
+        let (progress_tx, progress_rx) = async_channel::bounded(32);
+        let bandwidth = self.bandwidth.clone();
+        let reporter = tokio::spawn(async move {
+            let mut received = 0;
+            while let Ok(event) = progress_rx.recv().await {
+                if let DownloadProgress::Progress { offset, .. } = event {
+                    bandwidth.record_received(offset.saturating_sub(received) as usize);
+                    received = received.max(offset);
+                    progress(offset);
+                }
+            }
+        });
+
+        let result = iroh_blobs::get::db::get_to_db(
+            &self.blobs.store,
+            move || {
+                let conn = conn.clone();
+                async move { Ok(conn) }
+            },
+            &HashAndFormat::raw(blob),
+            AsyncChannelProgressSender::new(progress_tx),
+        )
+        .await;
+        reporter.abort();
+        result.map_err(|e| P2PError::Internal(format!("Failed to fetch {}: {}", hash, e)))?;
+
+        // The target was reserved by the file transfer manager, so this only
+        // replaces the empty placeholder. Copying keeps the store's data
+        // independent of a file the user may edit or delete.
+        self.blobs
+            .store
+            .export(
+                blob,
+                target.to_path_buf(),
+                ExportMode::Copy,
+                Box::new(|_| Ok(())),
+            )
+            .await
+            .map_err(|e| P2PError::Internal(format!("Failed to save {}: {}", target.display(), e)))
+    }
+
     /// Get this node's address (for sharing with peers).
+    ///
+    /// In relay-only mode the address only names the home relay.
     pub async fn node_addr(&self) -> Result<NodeAddr> {
-        self.endpoint
+        let addr = self
+            .endpoint
             .node_addr()
             .await
-            .map_err(|e| P2PError::IrohError(e.into()))
+            .map_err(|e| P2PError::IrohError(e.into()))?;
+        if self.config.relay_only {
+            return Ok(relay_addr(&addr));
+        }
+        Ok(addr)
+    }
+
+    /// Set the recorder capturing sent and received messages, returning the
+    /// previous one.
+    pub fn set_recorder(
+        &self,
+        recorder: Option<Arc<SessionRecorder>>,
+    ) -> Option<Arc<SessionRecorder>> {
+        std::mem::replace(&mut *self.recorder.write(), recorder)
+    }
+
+    /// Record a message if a recorder is set.
+    fn record(&self, peer_id: &PeerId, direction: Direction, message: &SyncMessage) {
+        let recorder = self.recorder.read().clone();
+        if let Some(recorder) = recorder {
+            if let Err(e) = recorder.record(peer_id, direction, message) {
+                warn!(
+                    "[{}] Failed to record message for peer {}: {}",
+                    self.config.node_name, peer_id, e
+                );
+            }
+        }
+    }
+
+    /// Get the session cache of recently-seen peers.
+    pub fn session_cache(&self) -> Arc<SessionCache> {
+        Arc::clone(&self.session_cache)
+    }
+
+    /// Get the relay selector.
+    pub fn relay_selector(&self) -> Arc<RelaySelector> {
+        Arc::clone(&self.relays)
+    }
+
+    /// Get the bandwidth manager.
+    pub fn bandwidth(&self) -> Arc<BandwidthManager> {
+        Arc::clone(&self.bandwidth)
+    }
+
+    /// Get the current link estimate for a peer.
+    pub fn link_estimate(&self, peer_id: &PeerId) -> Option<LinkEstimate> {
+        self.bandwidth.link_estimate(peer_id)
     }
 
     /// Connect to a peer.
+    ///
+    /// If `node_addr` carries no addresses and the peer was seen recently, the
+    /// cached address hint is dialed directly instead of waiting on discovery.
+    /// Without a known relay, the peer is dialed through the best configured
+    /// relay, failing over once to the next-best relay if that dial fails.
+    /// In relay-only mode, direct addresses are dropped before dialing.
     pub async fn connect(&self, node_addr: NodeAddr) -> Result<PeerId> {
         let peer_id = node_addr.node_id;
         let peer_id_str = peer_id.to_string();
@@ -144,23 +613,47 @@ impl IrohAdapter {
         }
 
         // Check connection limit
-        if self.connections.read().len() >= self.config.max_connections {
+        if self.pool.is_full() {
             return Err(P2PError::ConnectionFailed(
                 "Maximum connections reached".to_string(),
             ));
         }
 
-        // Connect with timeout
-        let conn = tokio::time::timeout(
-            self.config.connection_timeout,
-            self.endpoint.connect(node_addr, ALPN),
-        )
-        .await
-        .map_err(|_| P2PError::Timeout)?
-        .map_err(|e| P2PError::ConnectionFailed(e.to_string()))?;
+        // Use cached addresses for known peers, and the best relay if none is known
+        let mut node_addr = self.session_cache.resolve(node_addr);
+        if self.config.relay_only {
+            node_addr = relay_addr(&node_addr);
+        }
+        let mut relay_selected = false;
+        if node_addr.relay_url().is_none() {
+            if let Some(url) = self.relays.select(&peer_id_str) {
+                node_addr = node_addr.with_relay_url(parse_relay_url(&url)?);
+                relay_selected = true;
+            }
+        }
+
+        let conn = match self.dial(node_addr.clone()).await {
+            Ok(conn) => conn,
+            Err(e) if relay_selected => {
+                let url = self.relays.fail_over(&peer_id_str).ok_or(e)?;
+                warn!(
+                    "[{}] Dial to peer {} failed, retrying through relay {}",
+                    self.config.node_name, peer_id_str, url
+                );
+                node_addr = node_addr.with_relay_url(parse_relay_url(&url)?);
+                self.dial(node_addr.clone()).await.inspect_err(|_| {
+                    self.relays.record_failure(&url);
+                    self.relays.release(&peer_id_str);
+                })?
+            }
+            Err(e) => return Err(e),
+        };
 
         info!("[{}] Connected to peer {}", self.config.node_name, peer_id_str);
 
+        // Remember the working path for fast reconnects
+        self.session_cache.record(&node_addr);
+
         // Store connection
         self.connections.write().insert(peer_id_str.clone(), conn.clone());
 
@@ -168,13 +661,16 @@ impl IrohAdapter {
         let metadata = ConnectionMetadata {
             peer_id: peer_id_str.clone(),
             established_at: std::time::Instant::now(),
-            is_direct: true, // TODO: Detect if using relay
+            is_direct: !self.config.relay_only,
+            relay_url: node_addr.relay_url().map(|url| url.to_string()),
+            relay: None,
             messages_sent: 0,
             messages_received: 0,
             bytes_sent: 0,
             bytes_received: 0,
         };
         self.metadata.write().insert(peer_id_str.clone(), metadata);
+        self.pool.add(&peer_id_str, conn.stable_id(), true);
 
         // Start receiver for this connection
         self.start_receiver(peer_id_str.clone(), conn);
@@ -182,29 +678,68 @@ impl IrohAdapter {
         Ok(peer_id_str)
     }
 
+    /// Dial a peer with the connection timeout.
+    async fn dial(&self, node_addr: NodeAddr) -> Result<Connection> {
+        tokio::time::timeout(
+            self.config.connection_timeout,
+            self.endpoint.connect(node_addr, ALPN),
+        )
+        .await
+        .map_err(|_| P2PError::Timeout)?
+        .map_err(|e| P2PError::ConnectionFailed(e.to_string()))
+    }
+
     /// Disconnect from a peer.
+    ///
+    /// The peer is not redialed.
     pub async fn disconnect(&self, peer_id: &PeerId) -> Result<()> {
+        self.close_connection(peer_id, DisconnectReason::Closed)
+    }
+
+    /// Close a connection on purpose.
+    fn close_connection(&self, peer_id: &PeerId, reason: DisconnectReason) -> Result<()> {
         info!(
-            "[{}] Disconnecting from peer {}",
-            self.config.node_name, peer_id
+            "[{}] Disconnecting from peer {} ({:?})",
+            self.config.node_name, peer_id, reason
         );
 
+        if self.detach_link(peer_id, reason.clone()) {
+            return Ok(());
+        }
+        self.pool.remove(peer_id, reason);
         let conn = self
-            .connections
-            .write()
-            .remove(peer_id)
+            .forget(peer_id)
             .ok_or_else(|| P2PError::PeerNotFound(peer_id.clone()))?;
 
-        self.metadata.write().remove(peer_id);
-
         // Close connection
         conn.close(0u32.into(), b"disconnect");
 
         Ok(())
     }
 
+    /// Drop the state kept for a peer's connection.
+    fn forget(&self, peer_id: &PeerId) -> Option<Connection> {
+        let conn = self.connections.write().remove(peer_id)?;
+        self.metadata.write().remove(peer_id);
+        self.bandwidth.remove_link(peer_id);
+        self.relays.release(peer_id);
+        self.transports.remove(peer_id);
+        Some(conn)
+    }
+
     /// Send a message to a peer.
+    ///
+    /// Messages to peers that negotiated framing are sent in compressed
+    /// chunks (see [`crate::framing`]). Each send, or chunk, is paced to the
+    /// estimated capacity of the link, so a fast peer does not overwhelm a
+    /// slow one, and waits for the configured upload limits of the peer,
+    /// namespace and priority of the message.
     pub async fn send_message(&self, peer_id: &PeerId, message: &SyncMessage) -> Result<()> {
+        let link = self.links.read().get(peer_id).cloned();
+        if let Some(link) = link {
+            return self.send_link(peer_id, &link, message).await;
+        }
+
         let conn = self
             .connections
             .read()
@@ -228,9 +763,27 @@ impl IrohAdapter {
             .map_err(|e| P2PError::ConnectionFailed(e.to_string()))?;
 
         // Send message
-        send.write_all(&bytes)
-            .await
-            .map_err(|e| P2PError::ConnectionFailed(e.to_string()))?;
+        let wire_len = match self.transports.framing(peer_id) {
+            Some(compression) => {
+                let mut wire_len = 0;
+                for chunk in self.transports.chunks(&bytes, compression) {
+                    let chunk = chunk?;
+                    self.pace(peer_id, message, chunk.len()).await;
+                    send.write_all(&chunk)
+                        .await
+                        .map_err(|e| P2PError::ConnectionFailed(e.to_string()))?;
+                    wire_len += chunk.len();
+                }
+                wire_len
+            }
+            None => {
+                self.pace(peer_id, message, bytes.len()).await;
+                send.write_all(&bytes)
+                    .await
+                    .map_err(|e| P2PError::ConnectionFailed(e.to_string()))?;
+                bytes.len()
+            }
+        };
 
         send.finish()
             .map_err(|e| P2PError::ConnectionFailed(e.to_string()))?;
@@ -238,15 +791,197 @@ impl IrohAdapter {
         // Update metadata
         if let Some(metadata) = self.metadata.write().get_mut(peer_id) {
             metadata.messages_sent += 1;
-            metadata.bytes_sent += bytes.len() as u64;
+            metadata.bytes_sent += wire_len as u64;
+        }
+
+        self.bandwidth.record_sent(wire_len);
+        self.bandwidth
+            .record_compression(TrafficDirection::Upload, bytes.len(), wire_len);
+        self.bandwidth.record_link_sample(peer_id, link_sample(&conn));
+        self.pool.touch(peer_id);
+        self.record(peer_id, Direction::Outbound, message);
+
+        Ok(())
+    }
+
+    /// Send a message over a browser link, as a single binary frame.
+    async fn send_link(
+        &self,
+        peer_id: &PeerId,
+        link: &mpsc::UnboundedSender<Vec<u8>>,
+        message: &SyncMessage,
+    ) -> Result<()> {
+        let bytes = message.to_bytes()?;
+        let frame = match self.transports.framing(peer_id) {
+            Some(compression) => {
+                let mut frame = Vec::new();
+                for chunk in self.transports.chunks(&bytes, compression) {
+                    let chunk = chunk?;
+                    self.pace(peer_id, message, chunk.len()).await;
+                    frame.extend_from_slice(&chunk);
+                }
+                frame
+            }
+            None => {
+                self.pace(peer_id, message, bytes.len()).await;
+                bytes.clone()
+            }
+        };
+        let wire_len = frame.len();
+
+        debug!(
+            "[{}] Sending {} bytes to browser peer {}",
+            self.config.node_name, wire_len, peer_id
+        );
+        link.send(frame)
+            .map_err(|_| P2PError::ConnectionFailed("Browser link closed".to_string()))?;
+
+        if let Some(metadata) = self.metadata.write().get_mut(peer_id) {
+            metadata.messages_sent += 1;
+            metadata.bytes_sent += wire_len as u64;
         }
+        self.bandwidth.record_sent(wire_len);
+        self.bandwidth
+            .record_compression(TrafficDirection::Upload, bytes.len(), wire_len);
+        self.pool.touch(peer_id);
+        self.record(peer_id, Direction::Outbound, message);
 
         Ok(())
     }
 
+    /// Attach a browser linked through a gateway (see [`crate::browser`]) as
+    /// a peer.
+    ///
+    /// `hello` is the framing the browser reads. Returns the frames to send
+    /// to the browser, which end once the link is detached.
+    pub fn attach_link(
+        &self,
+        peer_id: &PeerId,
+        hello: &TransportHello,
+    ) -> Result<mpsc::UnboundedReceiver<Vec<u8>>> {
+        if self.connections.read().contains_key(peer_id) || self.links.read().contains_key(peer_id)
+        {
+            return Err(P2PError::ConnectionFailed(format!(
+                "Peer {} is already connected",
+                peer_id
+            )));
+        }
+        if self.pool.is_full() {
+            return Err(P2PError::ConnectionFailed(
+                "Maximum connections reached".to_string(),
+            ));
+        }
+
+        let (link_tx, link_rx) = mpsc::unbounded_channel();
+        self.links.write().insert(peer_id.clone(), link_tx);
+        let compression = self.transports.negotiate(peer_id, hello);
+        info!(
+            "[{}] Linked browser peer {} (framing {:?})",
+            self.config.node_name, peer_id, compression
+        );
+
+        let metadata = ConnectionMetadata {
+            peer_id: peer_id.clone(),
+            established_at: std::time::Instant::now(),
+            is_direct: true,
+            relay_url: None,
+            relay: None,
+            messages_sent: 0,
+            messages_received: 0,
+            bytes_sent: 0,
+            bytes_received: 0,
+        };
+        self.metadata.write().insert(peer_id.clone(), metadata);
+        let link_id = self.next_link_id.fetch_add(1, Ordering::Relaxed);
+        self.pool.add(peer_id, link_id, false);
+
+        Ok(link_rx)
+    }
+
+    /// Handle a binary frame received on a browser link.
+    pub async fn deliver(&self, peer_id: &PeerId, frame: &[u8]) -> Result<()> {
+        if !self.links.read().contains_key(peer_id) {
+            return Err(P2PError::PeerNotFound(peer_id.clone()));
+        }
+
+        let max_message_size = self.transports.config().max_message_size;
+        let received = framing::read_message(&mut &frame[..], max_message_size).await?;
+        debug!(
+            "[{}] Received {} bytes from browser peer {}",
+            self.config.node_name, received.wire_len, peer_id
+        );
+
+        if let Some(metadata) = self.metadata.write().get_mut(peer_id) {
+            metadata.messages_received += 1;
+            metadata.bytes_received += received.wire_len as u64;
+        }
+        self.bandwidth.record_compression(
+            TrafficDirection::Download,
+            received.bytes.len(),
+            received.wire_len,
+        );
+        self.pool.touch(peer_id);
+
+        match SyncMessage::from_bytes(&received.bytes)? {
+            SyncMessage::Hello(hello) => {
+                let compression = self.transports.negotiate(peer_id, &hello);
+                debug!(
+                    "[{}] Negotiated framing {:?} with browser peer {}",
+                    self.config.node_name, compression, peer_id
+                );
+            }
+            message => self
+                .message_tx
+                .send((peer_id.clone(), message))
+                .map_err(|_| P2PError::Internal("Message channel closed".to_string()))?,
+        }
+
+        Ok(())
+    }
+
+    /// Detach a browser link, e.g. once its WebSocket closed.
+    ///
+    /// Returns whether the link was attached.
+    pub fn detach_link(&self, peer_id: &PeerId, reason: DisconnectReason) -> bool {
+        if self.links.write().remove(peer_id).is_none() {
+            return false;
+        }
+
+        info!(
+            "[{}] Browser peer {} unlinked ({:?})",
+            self.config.node_name, peer_id, reason
+        );
+        self.pool.remove(peer_id, reason);
+        self.metadata.write().remove(peer_id);
+        self.bandwidth.remove_link(peer_id);
+        self.transports.remove(peer_id);
+        true
+    }
+
+    /// Wait until `len` bytes of a message may be sent to a peer.
+    async fn pace(&self, peer_id: &PeerId, message: &SyncMessage, len: usize) {
+        let delay = self.bandwidth.pacing_delay(peer_id, len);
+        if !delay.is_zero() {
+            debug!(
+                "[{}] Pacing send to peer {} by {:?}",
+                self.config.node_name, peer_id, delay
+            );
+            tokio::time::sleep(delay).await;
+        }
+        self.bandwidth
+            .throttle(
+                TrafficDirection::Upload,
+                peer_id,
+                message.namespace(),
+                message.priority(),
+                len,
+            )
+            .await;
+    }
+
     /// Broadcast a message to all connected peers.
     pub async fn broadcast(&self, message: &SyncMessage) -> Result<()> {
-        let peer_ids: Vec<PeerId> = self.connections.read().keys().cloned().collect();
+        let peer_ids = self.connected_peers();
 
         debug!(
             "[{}] Broadcasting message to {} peers",
@@ -265,37 +1000,194 @@ impl IrohAdapter {
 
     /// Receive the next message.
     pub async fn recv_message(&self) -> Result<(PeerId, SyncMessage)> {
-        self.message_rx
+        let (peer_id, message) = self
+            .message_rx
             .lock()
             .await
             .recv()
             .await
-            .ok_or_else(|| P2PError::Internal("Message channel closed".to_string()))
+            .ok_or_else(|| P2PError::Internal("Message channel closed".to_string()))?;
+        self.record(&peer_id, Direction::Inbound, &message);
+        Ok((peer_id, message))
     }
 
-    /// Get list of connected peers.
+    /// Get list of connected peers, including linked browsers.
     pub fn connected_peers(&self) -> Vec<PeerId> {
-        self.connections.read().keys().cloned().collect()
+        let mut peers: Vec<PeerId> = self.connections.read().keys().cloned().collect();
+        peers.extend(self.links.read().keys().cloned());
+        peers
     }
 
     /// Get connection metadata for a peer.
+    ///
+    /// Whether the connection is direct, and which relay carries it otherwise,
+    /// is read from the endpoint's current path to the peer.
     pub fn get_metadata(&self, peer_id: &PeerId) -> Option<ConnectionMetadata> {
-        self.metadata.read().get(peer_id).cloned()
+        let mut metadata = self.metadata.read().get(peer_id).cloned()?;
+
+        let remote = peer_id
+            .parse::<NodeId>()
+            .ok()
+            .and_then(|node_id| self.endpoint.remote_info(node_id));
+        if let Some(remote) = remote {
+            match remote.conn_type {
+                ConnectionType::Direct(_) => {
+                    metadata.is_direct = true;
+                    metadata.relay_url = None;
+                }
+                ConnectionType::Relay(url) | ConnectionType::Mixed(_, url) => {
+                    metadata.is_direct = false;
+                    metadata.relay_url = Some(url.to_string());
+                }
+                ConnectionType::None => {}
+            }
+        }
+        metadata.relay = metadata
+            .relay_url
+            .as_deref()
+            .and_then(|url| self.relays.relay_health(url));
+
+        Some(metadata)
+    }
+
+    /// Sample the endpoint's paths to a peer (see [`crate::diagnostics`]).
+    pub(crate) fn path_sample(&self, peer_id: &PeerId) -> Option<PathSample> {
+        let node_id = peer_id.parse::<NodeId>().ok()?;
+        let remote = self.endpoint.remote_info(node_id)?;
+        let chosen = match remote.conn_type {
+            ConnectionType::Direct(addr) => Some((PathKind::Direct, addr.to_string())),
+            // A mixed connection still carries data over the relay
+            ConnectionType::Relay(url) | ConnectionType::Mixed(_, url) => {
+                Some((PathKind::Relay, url.to_string()))
+            }
+            ConnectionType::None => None,
+        };
+
+        Some(PathSample {
+            chosen,
+            direct: remote
+                .addrs
+                .iter()
+                .map(|info| (info.addr, info.latency))
+                .collect(),
+            relay: remote
+                .relay_url
+                .map(|info| (info.relay_url.to_string(), info.latency)),
+        })
+    }
+
+    /// Check whether a peer is a browser linked through a gateway.
+    pub fn is_link(&self, peer_id: &PeerId) -> bool {
+        self.links.read().contains_key(peer_id)
     }
 
     /// Get connection count.
     pub fn connection_count(&self) -> usize {
-        self.connections.read().len()
+        self.connections.read().len() + self.links.read().len()
+    }
+
+    /// Get the connection pool.
+    pub fn connection_pool(&self) -> Arc<ConnectionPool> {
+        Arc::clone(&self.pool)
+    }
+
+    /// Subscribe to connection events.
+    pub fn subscribe_connections(&self) -> mpsc::UnboundedReceiver<ConnectionEvent> {
+        self.pool.subscribe()
+    }
+
+    /// Maintenance loop of the connection pool, for running under a
+    /// [`Supervisor`](crate::supervisor::Supervisor).
+    ///
+    /// Dropped connections are cleaned up and, if this node dialed them,
+    /// redialed with backoff. Idle connections are closed. The loop ends
+    /// once the adapter is dropped.
+    pub fn pool_maintainer(
+        self: &Arc<Self>,
+        heartbeat: Heartbeat,
+    ) -> impl Future<Output = ()> + Send + 'static {
+        let adapter: Weak<Self> = Arc::downgrade(self);
+        let pool = Arc::clone(&self.pool);
+        async move {
+            let mut ticker = tokio::time::interval(pool.config().maintenance_interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    dropped = pool.next_dropped() => {
+                        let (Some((peer_id, redial)), Some(adapter)) = (dropped, adapter.upgrade())
+                        else {
+                            break;
+                        };
+                        let _busy = heartbeat.busy();
+                        adapter.forget(&peer_id);
+                        if redial {
+                            adapter.spawn_reconnect(peer_id);
+                        }
+                    }
+                    _ = ticker.tick() => {
+                        let Some(adapter) = adapter.upgrade() else {
+                            break;
+                        };
+                        let _busy = heartbeat.busy();
+                        for peer_id in pool.idle_peers() {
+                            let _ = adapter.close_connection(&peer_id, DisconnectReason::Idle);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Redial a peer whose connection dropped, with backoff.
+    fn spawn_reconnect(self: &Arc<Self>, peer_id: PeerId) {
+        let node_id = match peer_id.parse::<NodeId>() {
+            Ok(node_id) => node_id,
+            Err(e) => {
+                warn!("Can't reconnect to peer {}: {}", peer_id, e);
+                return;
+            }
+        };
+        let adapter = Arc::downgrade(self);
+        self.pool.start_reconnect(&peer_id);
+
+        tokio::spawn(async move {
+            loop {
+                let delay = match adapter.upgrade() {
+                    Some(adapter) => adapter.pool.next_attempt(&peer_id),
+                    None => None,
+                };
+                let Some(delay) = delay else {
+                    break;
+                };
+                tokio::time::sleep(delay).await;
+
+                // Closing the peer during the backoff cancels the redial
+                let Some(adapter) = adapter.upgrade() else {
+                    break;
+                };
+                if !adapter.pool.is_reconnecting(&peer_id) {
+                    break;
+                }
+                match adapter.connect(NodeAddr::new(node_id)).await {
+                    Ok(_) => break,
+                    Err(e) => debug!("Reconnecting to peer {} failed: {}", peer_id, e),
+                }
+            }
+        });
     }
 
     /// Start listening for incoming connections.
     fn start_listener(&self) {
         let endpoint = self.endpoint.clone();
+        let gossip = self.gossip.clone();
         let node_name = self.config.node_name.clone();
         let connections = self.connections.clone();
         let metadata = self.metadata.clone();
         let message_tx = self.message_tx.clone();
-        let max_connections = self.config.max_connections;
+        let bandwidth = self.bandwidth.clone();
+        let pool = self.pool.clone();
+        let transports = self.transports.clone();
+        let blobs = self.blobs.clone();
 
         tokio::spawn(async move {
             info!("[{}] Listening for incoming connections", node_name);
@@ -303,19 +1195,28 @@ impl IrohAdapter {
             loop {
                 match endpoint.accept().await {
                     Some(incoming) => {
+                        let gossip = gossip.clone();
                         let node_name = node_name.clone();
                         let connections = connections.clone();
                         let metadata = metadata.clone();
                         let message_tx = message_tx.clone();
+                        let bandwidth = bandwidth.clone();
+                        let pool = pool.clone();
+                        let transports = transports.clone();
+                        let blobs = blobs.clone();
 
                         tokio::spawn(async move {
                             if let Err(e) = Self::handle_incoming(
                                 incoming,
+                                &gossip,
+                                &blobs,
                                 &node_name,
                                 connections,
                                 metadata,
                                 message_tx,
-                                max_connections,
+                                bandwidth,
+                                pool,
+                                transports,
                             )
                             .await
                             {
@@ -333,26 +1234,69 @@ impl IrohAdapter {
     }
 
     /// Handle an incoming connection.
+    ///
+    /// Gossip connections are handed to the gossip swarms, and iroh-blobs
+    /// connections from peers we offered a file to are served from the blob
+    /// store.
+    #[allow(clippy::too_many_arguments)]
     async fn handle_incoming(
         incoming: Incoming,
+        gossip: &Gossip,
+        blobs: &BlobProvider,
         node_name: &str,
         connections: Arc<RwLock<HashMap<PeerId, Connection>>>,
         metadata: Arc<RwLock<HashMap<PeerId, ConnectionMetadata>>>,
         message_tx: mpsc::UnboundedSender<(PeerId, SyncMessage)>,
-        max_connections: usize,
+        bandwidth: Arc<BandwidthManager>,
+        pool: Arc<ConnectionPool>,
+        transports: Arc<PeerTransports>,
     ) -> Result<()> {
-        let conn = incoming
+        let mut connecting = incoming
+            .accept()
+            .map_err(|e| P2PError::ConnectionFailed(e.to_string()))?;
+        let alpn = connecting
+            .alpn()
+            .await
+            .map_err(|e| P2PError::ConnectionFailed(e.to_string()))?;
+        let conn = connecting
             .await
             .map_err(|e| P2PError::ConnectionFailed(e.to_string()))?;
 
-        // Get peer ID from connection - in Iroh 0.28 this might be different
-        // For now, generate a temporary peer ID based on connection
-        let peer_id = format!("peer-{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos());
+        if alpn == GOSSIP_ALPN {
+            return gossip
+                .handle_connection(conn)
+                .await
+                .map_err(P2PError::IrohError);
+        }
+
+        // Peers are identified by their node ID, which signs their gossip
+        let peer_id = get_remote_node_id(&conn)
+            .map_err(|e| P2PError::ConnectionFailed(e.to_string()))?
+            .to_string();
+
+        if alpn == iroh_blobs::protocol::ALPN {
+            if !blobs.serves(&peer_id) {
+                debug!(
+                    "[{}] Refusing blob connection from peer {}: nothing offered",
+                    node_name, peer_id
+                );
+                conn.close(0u32.into(), b"nothing offered");
+                return Ok(());
+            }
+            iroh_blobs::provider::handle_connection(
+                conn,
+                blobs.store.clone(),
+                Default::default(),
+                blobs.rt.clone(),
+            )
+            .await;
+            return Ok(());
+        }
 
         info!("[{}] Accepted connection from peer {}", node_name, peer_id);
 
         // Check connection limit
-        if connections.read().len() >= max_connections {
+        if pool.is_full() {
             warn!(
                 "[{}] Rejecting connection from {}: max connections reached",
                 node_name, peer_id
@@ -369,15 +1313,27 @@ impl IrohAdapter {
             peer_id: peer_id.clone(),
             established_at: std::time::Instant::now(),
             is_direct: true,
+            relay_url: None,
+            relay: None,
             messages_sent: 0,
             messages_received: 0,
             bytes_sent: 0,
             bytes_received: 0,
         };
         metadata.write().insert(peer_id.clone(), conn_metadata);
+        pool.add(&peer_id, conn.stable_id(), false);
 
         // Start receiver
-        Self::spawn_receiver(peer_id, conn, node_name.to_string(), metadata, message_tx);
+        Self::spawn_receiver(
+            peer_id,
+            conn,
+            node_name.to_string(),
+            metadata,
+            message_tx,
+            bandwidth,
+            pool,
+            transports,
+        );
 
         Ok(())
     }
@@ -390,48 +1346,77 @@ impl IrohAdapter {
             self.config.node_name.clone(),
             self.metadata.clone(),
             self.message_tx.clone(),
+            self.bandwidth.clone(),
+            self.pool.clone(),
+            self.transports.clone(),
         );
     }
 
     /// Spawn receiver task.
+    ///
+    /// Sends our [`TransportHello`] first, and negotiates framing with the
+    /// peer's hello instead of forwarding it.
+    #[allow(clippy::too_many_arguments)]
     fn spawn_receiver(
         peer_id: PeerId,
         conn: Connection,
         node_name: String,
         metadata: Arc<RwLock<HashMap<PeerId, ConnectionMetadata>>>,
         message_tx: mpsc::UnboundedSender<(PeerId, SyncMessage)>,
+        bandwidth: Arc<BandwidthManager>,
+        pool: Arc<ConnectionPool>,
+        transports: Arc<PeerTransports>,
     ) {
         tokio::spawn(async move {
             debug!("[{}] Starting receiver for peer {}", node_name, peer_id);
 
-            loop {
+            if let Err(e) = Self::send_hello(&conn).await {
+                warn!(
+                    "[{}] Failed to send hello to peer {}: {}",
+                    node_name, peer_id, e
+                );
+            }
+
+            let max_message_size = transports.config().max_message_size;
+            let error = loop {
                 match conn.accept_uni().await {
                     Ok(mut recv) => {
-                        match recv.read_to_end(10 * 1024 * 1024).await {
-                            // 10 MB max
-                            Ok(bytes) => {
+                        match framing::read_message(&mut recv, max_message_size).await {
+                            Ok(received) => {
                                 debug!(
                                     "[{}] Received {} bytes from peer {}",
-                                    node_name,
-                                    bytes.len(),
-                                    peer_id
+                                    node_name, received.wire_len, peer_id
                                 );
 
                                 // Update metadata
                                 if let Some(meta) = metadata.write().get_mut(&peer_id) {
                                     meta.messages_received += 1;
-                                    meta.bytes_received += bytes.len() as u64;
+                                    meta.bytes_received += received.wire_len as u64;
                                 }
+                                bandwidth.record_compression(
+                                    TrafficDirection::Download,
+                                    received.bytes.len(),
+                                    received.wire_len,
+                                );
+                                bandwidth.record_link_sample(&peer_id, link_sample(&conn));
+                                pool.touch(&peer_id);
 
                                 // Deserialize message
-                                match SyncMessage::from_bytes(&bytes) {
+                                match SyncMessage::from_bytes(&received.bytes) {
+                                    Ok(SyncMessage::Hello(hello)) => {
+                                        let compression = transports.negotiate(&peer_id, &hello);
+                                        debug!(
+                                            "[{}] Negotiated framing {:?} with peer {}",
+                                            node_name, compression, peer_id
+                                        );
+                                    }
                                     Ok(message) => {
                                         if message_tx.send((peer_id.clone(), message)).is_err() {
                                             warn!(
                                                 "[{}] Failed to forward message from peer {}",
                                                 node_name, peer_id
                                             );
-                                            break;
+                                            break "message handler stopped".to_string();
                                         }
                                     }
                                     Err(e) => {
@@ -442,32 +1427,56 @@ impl IrohAdapter {
                                     }
                                 }
                             }
-                            Err(e) => {
+                            Err(P2PError::ConnectionFailed(e)) => {
                                 warn!(
                                     "[{}] Failed to read from peer {}: {}",
                                     node_name, peer_id, e
                                 );
-                                break;
+                                break e;
+                            }
+                            Err(e) => {
+                                // Oversized or malformed message: drop the stream only
+                                warn!(
+                                    "[{}] Rejected message from peer {}: {}",
+                                    node_name, peer_id, e
+                                );
+                                let _ = recv.stop(0u32.into());
                             }
                         }
                     }
                     Err(e) => {
                         debug!("[{}] Connection closed from peer {}: {}", node_name, peer_id, e);
-                        break;
+                        break e.to_string();
                     }
                 }
-            }
+            };
 
             info!("[{}] Receiver stopped for peer {}", node_name, peer_id);
+            pool.report_closed(&peer_id, conn.stable_id(), error);
         });
     }
 
+    /// Announce our framing to a peer, as a plain message any peer can read.
+    async fn send_hello(conn: &Connection) -> Result<()> {
+        let bytes = SyncMessage::Hello(TransportHello::local()).to_bytes()?;
+        let mut send = conn
+            .open_uni()
+            .await
+            .map_err(|e| P2PError::ConnectionFailed(e.to_string()))?;
+        send.write_all(&bytes)
+            .await
+            .map_err(|e| P2PError::ConnectionFailed(e.to_string()))?;
+        send.finish()
+            .map_err(|e| P2PError::ConnectionFailed(e.to_string()))?;
+        Ok(())
+    }
+
     /// Close the endpoint.
     pub async fn close(&self) -> Result<()> {
         info!("[{}] Closing endpoint", self.config.node_name);
 
         // Close all connections
-        let peer_ids: Vec<PeerId> = self.connections.read().keys().cloned().collect();
+        let peer_ids = self.connected_peers();
         for peer_id in peer_ids {
             let _ = self.disconnect(&peer_id).await;
         }
@@ -498,6 +1507,10 @@ mod tests {
 
         let node_id = adapter.node_id();
         assert!(!node_id.to_string().is_empty());
+
+        // Gossip is signed with the key of the node ID
+        let key = adapter.signing_key().verifying_key();
+        assert_eq!(crate::gossip::peer_id_of(&key), node_id.to_string());
     }
 
     #[tokio::test]
@@ -508,4 +1521,66 @@ mod tests {
         let peers = adapter.connected_peers();
         assert_eq!(peers.len(), 0);
     }
+
+    #[tokio::test]
+    async fn test_configured_relays() {
+        let config = P2PConfig {
+            relays: vec![
+                "https://eu.relay.example".to_string(),
+                "https://us.relay.example".to_string(),
+            ],
+            ..Default::default()
+        };
+        let adapter = IrohAdapter::new(config.clone()).await.unwrap();
+        assert_eq!(adapter.relay_selector().health().len(), 2);
+        assert!(adapter
+            .relay_selector()
+            .health()
+            .iter()
+            .all(|relay| relay.healthy && relay.peers == 0));
+
+        let disabled = P2PConfig {
+            enable_relay: false,
+            ..config
+        };
+        assert!(relay_urls(&disabled).unwrap().is_empty());
+
+        let invalid = P2PConfig {
+            relays: vec!["not a url".to_string()],
+            ..Default::default()
+        };
+        assert!(relay_urls(&invalid).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_relay_only() {
+        let config = P2PConfig {
+            relays: vec!["https://eu.relay.example".to_string()],
+            relay_only: true,
+            ..Default::default()
+        };
+        let adapter = IrohAdapter::new(config.clone()).await.unwrap();
+        assert!(adapter
+            .node_addr()
+            .await
+            .unwrap()
+            .direct_addresses()
+            .next()
+            .is_none());
+
+        let addr = NodeAddr::new(adapter.node_id())
+            .with_relay_url(parse_relay_url("https://eu.relay.example").unwrap())
+            .with_direct_addresses(["127.0.0.1:4433".parse().unwrap()]);
+        let relayed = relay_addr(&addr);
+        assert_eq!(relayed.relay_url(), addr.relay_url());
+        assert!(relayed.direct_addresses().next().is_none());
+
+        // Relay-only needs relays
+        let disabled = P2PConfig {
+            enable_relay: false,
+            ..config
+        };
+        assert!(relay_urls(&disabled).is_err());
+        assert!(IrohAdapter::new(disabled).await.is_err());
+    }
 }
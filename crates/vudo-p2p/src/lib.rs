@@ -2,14 +2,41 @@
 //!
 //! Iroh-based peer-to-peer networking for VUDO Runtime with:
 //! - Peer discovery (DHT + mDNS) via Iroh
-//! - Connection management (direct + relay)
-//! - Automerge sync protocol over Iroh streams
+//! - Connection management (direct + relay) with auto-reconnect and idle expiry
+//! - Automerge sync protocol over Iroh streams, with negotiated zstd
+//!   compression and chunked framing of large messages
+//! - Sync conflict events, with optional suggested resolutions
 //! - Willow Protocol adapter for structured data sync
 //! - Meadowcap capabilities for fine-grained permissions
-//! - Gossip overlay for presence
+//! - Gossip overlay for presence, signed and spread through iroh-gossip swarms
+//! - Application pub/sub topics for ephemeral signals, with optional
+//!   UCAN-restricted publishers
+//! - Presence tracking with online, idle and offline events per DID
 //! - Bandwidth-aware sync
+//! - Selective sync policies per namespace and document, with size caps and
+//!   Wi-Fi-only sync
+//! - Cached addresses of recently-seen peers (no TLS session resumption)
+//! - Latency-based relay selection across multiple relays
+//! - NAT traversal diagnostics reporting the paths to a peer
+//! - Encrypted direct file transfer between devices
+//! - Local control API for operator tools (`vudo top`)
+//! - Record-and-replay of sync sessions for debugging
+//! - Per-document sync progress and tracing spans for each sync round
+//! - UCAN-authenticated sync sessions scoped by capability
+//! - Peer allowlists and blocklists, with pairing approval of unknown peers
+//! - Replication groups syncing a namespace between members in a full mesh
+//!   or star, reporting under-replicated documents
+//! - Store-and-forward mail for offline peers, encrypted to their DID and
+//!   held by a mailbox peer until they fetch it
+//! - UCAN revocations synced with every peer as an OR-Set document, refusing
+//!   revoked tokens within one sync cycle
+//! - Guest mode with ephemeral, read-only identities for demos and kiosks
+//! - Supervisor restarting panicked or stalled subsystems with backoff
 //! - Background sync in Web Workers/tokio
-//! - GDPR-compliant deletion with tombstones
+//! - Browser peers linked over WebSocket through a gateway node
+//! - A `wasm32` build without Iroh for browsers
+//! - GDPR-compliant deletion with tombstones, propagated to peers with
+//!   signed deletion proofs, and audit log checkpoints anchored over gossip
 //!
 //! # Architecture
 //!
@@ -21,8 +48,16 @@
 //! - DHT for internet-wide discovery
 //! - Relay servers for NAT traversal
 //!
+//! ## Browsers
+//! - Iroh, the full tokio runtime and zstd don't build for `wasm32`, so the
+//!   `wasm32` build leaves out `VudoP2P` and the modules built on Iroh
+//!   (adapter, connection pool, discovery, relays, diagnostics, control API)
+//! - Browsers link to a gateway node over WebSocket with `BrowserTransport`
+//!   and exchange uncompressed framed sync messages with it
+//!
 //! ## Willow Protocol Data Sync
 //! - 3D namespace structure (namespace, subspace, path)
+//! - Range-based reconciliation exchanging only differing entries
 //! - Fine-grained capabilities (Meadowcap)
 //! - GDPR-compliant deletion (tombstones)
 //! - Resource-aware sync
@@ -77,52 +112,187 @@
 //! let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
 //! let namespace_id = adapter.map_namespace("myapp.v1");
 //! let root_cap = Capability::new_root(namespace_id, &signing_key);
+//! adapter.capabilities().add(root_cap.clone())?;
 //!
 //! // Write data with capability
 //! let data = Bytes::from("test data");
-//! adapter.write_entry("myapp.v1", "users", "alice", data, &root_cap).await?;
+//! adapter.write_entry("myapp.v1", "users", "alice", data, &root_cap, &signing_key).await?;
 //! # Ok(())
 //! # }
 //! ```
 
 // Iroh P2P modules
+pub mod audit_anchor;
+pub mod auth;
 pub mod background_sync;
 pub mod bandwidth;
+pub mod browser;
+pub mod conflicts;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod connection_pool;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod control;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod diagnostics;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod discovery;
+pub mod file_transfer;
+pub mod framing;
+#[cfg(all(feature = "browser-gateway", not(target_arch = "wasm32")))]
+pub mod gateway;
 pub mod gossip;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod iroh_adapter;
+pub mod mailbox;
+pub mod peer_access;
+pub mod presence;
+pub mod recording;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod relay;
+#[cfg(all(feature = "relay-server", not(target_arch = "wasm32")))]
+pub mod relay_server;
+pub mod replication;
+pub mod revocations;
+pub mod session_cache;
+pub mod supervisor;
+pub mod sync_policy;
 pub mod sync_protocol;
+pub mod tombstones;
 
 // Willow Protocol modules
 pub mod error;
 pub mod meadowcap;
 pub mod willow_adapter;
+pub mod willow_sync;
 pub mod willow_types;
 
 // Iroh P2P exports
+pub use audit_anchor::{GossipAuditAnchor, AUDIT_ANCHOR_TOPIC};
+pub use auth::{PeerAuth, SyncAuthPolicy};
 pub use background_sync::{BackgroundSync, BackgroundSyncConfig};
-pub use bandwidth::{BandwidthManager, BandwidthStats, SyncTask};
+pub use bandwidth::{
+    BandwidthLimit, BandwidthManager, BandwidthStats, LimitScope, LinkEstimate, LinkSample,
+    SyncTask, TrafficDirection,
+};
+#[cfg(target_arch = "wasm32")]
+pub use browser::BrowserTransport;
+pub use browser::{LinkMessage, LINK_PROTOCOL};
+pub use conflicts::{ConflictMonitor, ConflictSuggestion, SyncConflict};
+#[cfg(not(target_arch = "wasm32"))]
+pub use connection_pool::{
+    ConnectionEvent, ConnectionPool, ConnectionPoolConfig, DisconnectReason,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use control::{
+    BandwidthStatus, ControlServer, DocumentStatus, ErrorEntry, ErrorLog, NodeStatus, PeerStatus,
+    QueueStatus, StatusProvider, DEFAULT_CONTROL_ADDR,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use diagnostics::{ConnectivityReport, PathKind, PathProbe, Reachability, PROBE_DURATION};
+#[cfg(not(target_arch = "wasm32"))]
 pub use discovery::{DiscoveredPeer, DiscoveryMethod, PeerDiscovery, PeerPrioritizer};
-pub use gossip::{GossipMessage, GossipOverlay, Subscription, Topic};
+pub use file_transfer::{
+    AcceptanceDecision, AcceptancePolicy, AcceptanceRule, FileOffer, FileTransferConfig,
+    FileTransferManager, TransferDirection, TransferEvent, TransferId,
+};
+pub use framing::{Compression, PeerTransports, TransportConfig, TransportHello};
+#[cfg(all(feature = "browser-gateway", not(target_arch = "wasm32")))]
+pub use gateway::{BrowserGateway, BrowserGatewayConfig};
+pub use gossip::{
+    GossipMessage, GossipOverlay, SignedGossipMessage, Subscription, Topic, TopicMessage,
+    TopicSubscription,
+};
+#[cfg(not(target_arch = "wasm32"))]
 pub use iroh_adapter::{ConnectionMetadata, IrohAdapter, P2PConfig};
-pub use sync_protocol::{PeerId, SyncMessage, SyncProtocol, SyncStats};
+pub use mailbox::{
+    MailFetch, MailPayload, MailSettings, Mailbox, MailboxConfig, SealedMail,
+    DEFAULT_MAIL_INTERVAL, DEFAULT_MAIL_TTL,
+};
+pub use peer_access::{
+    pairing_code, AccessDecision, AccessMode, PairingEvent, PairingRequest, PeerAccess,
+    PeerAccessConfig,
+};
+pub use presence::{PeerPresence, PresenceConfig, PresenceState, PresenceTracker};
+pub use recording::{
+    Direction, RecordedMessage, Recording, RecordingHeader, ReplayFailure, ReplayOutcome,
+    ReplayReport, SessionRecorder, SessionReplayer,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use relay::{RelayHealth, RelaySelector};
+#[cfg(all(feature = "relay-server", not(target_arch = "wasm32")))]
+pub use relay_server::{RelayServer, RelayServerConfig};
+pub use replication::{
+    DocumentReplication, GroupTopology, ReplicationGroup, ReplicationTracker,
+    DEFAULT_REPLICATION_INTERVAL,
+};
+pub use revocations::{
+    RevocationRegistry, DEFAULT_REVOCATION_INTERVAL, REVOCATIONS_KEY, REVOCATIONS_NAMESPACE,
+};
+pub use session_cache::{PeerHint, SessionCache};
+pub use supervisor::{Heartbeat, Incident, IncidentKind, Subsystem, Supervisor, SupervisorConfig};
+pub use sync_policy::{NetworkType, SkipReason, SyncPolicy, SyncScope};
+pub use sync_protocol::{
+    PeerId, ReconnectStats, SyncMessage, SyncPhase, SyncProgress, SyncProtocol, SyncSession,
+    SyncStats, PARTITION_HEAL_TARGET,
+};
+pub use tombstones::{DocumentDeletion, SignedDeletion, TombstoneStore};
+#[cfg(not(target_arch = "wasm32"))]
+pub use vudo_identity::Keystore;
+pub use vudo_identity::{
+    AuthChallenge, AuthResponse, DeviceIdentity, DeviceUnlink, Did, GuestGrant, GuestIdentity,
+    GuestPolicy, MasterIdentity, RevocationStore, UcanRevocation, WipeInstruction,
+};
+pub use vudo_privacy::crypto::DeletionReceipt;
 
 // Willow Protocol exports
 pub use error::{P2PError, Result};
-pub use meadowcap::{Capability, CapabilityStore, Permission};
+pub use meadowcap::{
+    Area, AuthorisationToken, Capability, CapabilityGrant, CapabilityStore, Permission, Share,
+    GRANT_URL_PREFIX,
+};
 pub use willow_adapter::{ResourceConstraints, WillowAdapter, WillowStats};
+pub use willow_sync::{Fingerprint, Range3d, SyncItem, WillowSync, WillowSyncMessage};
 pub use willow_types::{Entry, NamespaceId, Path, SubspaceId, Tombstone};
 
 // Re-export SyncPriority from bandwidth (more general than Willow's)
 pub use bandwidth::SyncPriority;
 
-use iroh::net::NodeAddr;
+#[cfg(not(target_arch = "wasm32"))]
+use control::NodeProbe;
+#[cfg(not(target_arch = "wasm32"))]
+use diagnostics::ConnectivityProbe;
+#[cfg(not(target_arch = "wasm32"))]
+use ed25519_dalek::SigningKey;
+#[cfg(not(target_arch = "wasm32"))]
+use futures::StreamExt;
+#[cfg(not(target_arch = "wasm32"))]
+use iroh::net::{NodeAddr, NodeId};
+#[cfg(not(target_arch = "wasm32"))]
+use iroh_gossip::net::{Event, GossipEvent, GossipTopic};
+#[cfg(not(target_arch = "wasm32"))]
 use parking_lot::RwLock;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::SocketAddr;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+#[cfg(not(target_arch = "wasm32"))]
 use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Duration, Instant};
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::sync::mpsc;
+#[cfg(not(target_arch = "wasm32"))]
 use tracing::{debug, info, warn};
-use vudo_state::StateEngine;
+#[cfg(not(target_arch = "wasm32"))]
+use vudo_state::{ConflictHandler, StateEngine};
+#[cfg(not(target_arch = "wasm32"))]
+use vudo_storage::StorageAdapter;
 
 /// Main P2P coordinator integrating Iroh and Willow.
+///
+/// Not available on `wasm32`, where browsers link to a gateway node with
+/// `BrowserTransport` instead.
+#[cfg(not(target_arch = "wasm32"))]
 pub struct VudoP2P {
     /// State engine.
     state_engine: Arc<StateEngine>,
@@ -136,33 +306,100 @@ pub struct VudoP2P {
     discovery: Arc<PeerDiscovery>,
     /// Bandwidth manager.
     bandwidth: Arc<BandwidthManager>,
+    /// Direct file transfers.
+    file_transfers: Arc<FileTransferManager>,
+    /// Recent errors, for the control API.
+    errors: Arc<ErrorLog>,
+    /// Control API server task.
+    control: RwLock<Option<tokio::task::JoinHandle<()>>>,
+    /// Watchdog restarting failed subsystems.
+    supervisor: Arc<Supervisor>,
+    /// Creation time.
+    started_at: Instant,
     /// Background sync.
     background_sync: Arc<RwLock<Option<BackgroundSync>>>,
+    /// Which documents are pulled from peers.
+    sync_policy: Arc<RwLock<SyncPolicy>>,
+    /// Allowed, blocked and pairing peers.
+    peer_access: Arc<PeerAccess>,
+    /// Presence of peers.
+    presence: Arc<PresenceTracker>,
+    /// Replication groups this node is a member of.
+    replication: Arc<ReplicationTracker>,
+    /// Mail held for others, and how this node receives its own.
+    mailbox: Arc<Mailbox>,
+    /// UCAN revocations, synced with every peer.
+    revocations: Arc<RevocationRegistry>,
     /// Willow adapter (optional, for structured sync).
     willow: Option<Arc<WillowAdapter>>,
+    /// Ephemeral identity when running as a guest.
+    guest: Option<GuestIdentity>,
     /// Configuration.
     config: P2PConfig,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl VudoP2P {
     /// Create a new P2P instance with Iroh networking.
     pub async fn new(state_engine: Arc<StateEngine>, config: P2PConfig) -> Result<Self> {
         info!("Initializing VUDO P2P layer");
 
+        // Create bandwidth manager, fed with link estimates by the Iroh adapter
+        let bandwidth = Arc::new(BandwidthManager::new());
+        for (scope, limit) in &config.bandwidth_limits {
+            bandwidth.set_limit(scope.clone(), *limit);
+        }
+
         // Create Iroh adapter
-        let iroh = Arc::new(IrohAdapter::new(config.clone()).await?);
+        let iroh = Arc::new(
+            IrohAdapter::with_bandwidth(config.clone(), Arc::clone(&bandwidth)).await?,
+        );
 
-        // Create sync protocol
-        let sync_protocol = Arc::new(SyncProtocol::new(Arc::clone(&state_engine)));
+        // Create sync protocol, authenticating peers if configured
+        let revocations = Arc::new(RevocationRegistry::new(Arc::clone(&state_engine)));
+        let mut sync_protocol = SyncProtocol::new(Arc::clone(&state_engine));
+        if let Some(policy) = config.sync_auth.clone() {
+            let policy = policy.with_revocations(Arc::clone(revocations.store()));
+            sync_protocol = sync_protocol.with_auth(policy);
+        }
+        let sync_protocol = Arc::new(sync_protocol);
 
-        // Create gossip overlay
-        let gossip = Arc::new(GossipOverlay::new());
+        // Create gossip overlay, signing with the node key
+        let gossip = Arc::new(GossipOverlay::new().with_signing_key(iroh.signing_key()));
 
         // Create peer discovery
         let discovery = Arc::new(PeerDiscovery::new(config.enable_mdns, config.enable_dht));
 
-        // Create bandwidth manager
-        let bandwidth = Arc::new(BandwidthManager::new());
+        // Create file transfer manager
+        let file_transfers = Arc::new(FileTransferManager::new(config.file_transfer.clone()));
+
+        // Guests get a throwaway identity and a capped send rate
+        let guest = match &config.guest {
+            Some(policy) => {
+                let guest = GuestIdentity::generate(policy.clone()).await?;
+                info!("Running as guest {}", guest.did());
+                bandwidth.set_rate_limit(policy.max_bandwidth);
+                Some(guest)
+            }
+            None => None,
+        };
+
+        let peer_access = Arc::new(PeerAccess::new(
+            iroh.node_id().to_string(),
+            config.peer_access.clone(),
+        ));
+
+        // Heartbeats name the guest DID, if any
+        let presence = Arc::new(PresenceTracker::new(
+            config.presence.clone(),
+            Some(iroh.node_id().to_string()),
+        ));
+        presence.set_did(guest.as_ref().map(|guest| guest.did().to_string()));
+
+        let errors = Arc::new(ErrorLog::default());
+        let supervisor = Arc::new(
+            Supervisor::new(config.supervisor.clone()).with_error_log(Arc::clone(&errors)),
+        );
 
         Ok(Self {
             state_engine,
@@ -171,8 +408,20 @@ impl VudoP2P {
             gossip,
             discovery,
             bandwidth,
+            file_transfers,
+            errors,
+            control: RwLock::new(None),
+            supervisor,
+            started_at: Instant::now(),
             background_sync: Arc::new(RwLock::new(None)),
+            sync_policy: Arc::new(RwLock::new(config.sync_policy.clone())),
+            peer_access,
+            presence,
+            replication: Arc::new(ReplicationTracker::new()),
+            mailbox: Arc::new(Mailbox::new(config.mailbox.clone())),
+            revocations,
             willow: None,
+            guest,
             config,
         })
     }
@@ -197,6 +446,9 @@ impl VudoP2P {
     pub async fn start(&self) -> Result<()> {
         info!("Starting VUDO P2P services");
 
+        // Restart subsystems that panic or stall
+        self.supervisor.start();
+
         // Start peer discovery
         self.discovery.start();
 
@@ -204,13 +456,152 @@ impl VudoP2P {
         let bg_sync = BackgroundSync::new(
             BackgroundSyncConfig::default(),
             Arc::clone(&self.bandwidth),
-        );
-        bg_sync.start();
+        )
+        .with_policy(Arc::clone(&self.sync_policy));
+        bg_sync.start_supervised(&self.supervisor);
         *self.background_sync.write() = Some(bg_sync);
 
+        // Restore address hints for recently-seen peers
+        match SessionCache::remove_legacy(&self.state_engine).await {
+            Ok(true) => debug!("Removed session hints from the synced state"),
+            Ok(false) => {}
+            Err(e) => warn!("Failed to remove legacy session hints: {}", e),
+        }
+        if let Some(path) = &self.config.session_cache_path {
+            match self.iroh.session_cache().load(path) {
+                Ok(count) => debug!("Restored {} session hints", count),
+                Err(e) => warn!("Failed to restore session hints: {}", e),
+            }
+        }
+
+        // Resume document syncs where the last sessions ended
+        match self.sync_protocol.load_sync_states().await {
+            Ok(count) => debug!("Restored {} sync states", count),
+            Err(e) => warn!("Failed to restore sync states: {}", e),
+        }
+
+        // Keep deleted documents deleted
+        match self.sync_protocol.load_tombstones().await {
+            Ok(count) => debug!("Restored {} tombstones", count),
+            Err(e) => warn!("Failed to restore tombstones: {}", e),
+        }
+
+        // Restore paired and blocked peers
+        match self.peer_access.load(&self.state_engine).await {
+            Ok(count) => debug!("Restored {} peer access entries", count),
+            Err(e) => warn!("Failed to restore peer access lists: {}", e),
+        }
+
+        // Refuse tokens revoked before the restart
+        match self.revocations.load().await {
+            Ok(count) => debug!("Restored {} UCAN revocations", count),
+            Err(e) => warn!("Failed to restore UCAN revocations: {}", e),
+        }
+
+        // Start probing relays, so peers are assigned by measured latency
+        let relays = self.iroh.relay_selector();
+        if !relays.is_empty() {
+            let interval = self.config.relay_probe_interval;
+            self.supervisor
+                .supervise(Subsystem::RelayProber, move |heartbeat| {
+                    relays.prober(interval, heartbeat)
+                });
+        }
+
+        // Redial dropped peers and close idle connections
+        let iroh = Arc::clone(&self.iroh);
+        self.supervisor
+            .supervise(Subsystem::ConnectionPool, move |heartbeat| {
+                iroh.pool_maintainer(heartbeat)
+            });
+
+        // Turn away blocked peers and ask to pair with unknown ones
+        let iroh = Arc::clone(&self.iroh);
+        let peer_access = Arc::clone(&self.peer_access);
+        self.supervisor
+            .supervise(Subsystem::PeerAccess, move |heartbeat| {
+                Self::watch_connections(Arc::clone(&iroh), Arc::clone(&peer_access), heartbeat)
+            });
+
+        // Announce heartbeats, time out silent peers and challenge connected
+        // peers for the DIDs they announce
+        let iroh = Arc::clone(&self.iroh);
+        let sync_protocol = Arc::clone(&self.sync_protocol);
+        let gossip = Arc::clone(&self.gossip);
+        let presence = Arc::clone(&self.presence);
+        let node_id = self.node_id();
+        self.supervisor
+            .supervise(Subsystem::Presence, move |heartbeat| {
+                Self::run_presence(
+                    Arc::clone(&iroh),
+                    Arc::clone(&sync_protocol),
+                    Arc::clone(&gossip),
+                    Arc::clone(&presence),
+                    node_id.clone(),
+                    heartbeat,
+                )
+            });
+
+        // Keep replication groups in sync with their members
+        let iroh = Arc::clone(&self.iroh);
+        let sync_protocol = Arc::clone(&self.sync_protocol);
+        let state_engine = Arc::clone(&self.state_engine);
+        let peer_access = Arc::clone(&self.peer_access);
+        let replication = Arc::clone(&self.replication);
+        let interval = self.config.replication_interval;
+        self.supervisor
+            .supervise(Subsystem::Replication, move |heartbeat| {
+                Self::run_replication(
+                    Arc::clone(&iroh),
+                    Arc::clone(&sync_protocol),
+                    Arc::clone(&state_engine),
+                    Arc::clone(&peer_access),
+                    Arc::clone(&replication),
+                    interval,
+                    heartbeat,
+                )
+            });
+
+        // Fetch mail from mailboxes and expire held mail
+        let iroh = Arc::clone(&self.iroh);
+        let mailbox = Arc::clone(&self.mailbox);
+        let interval = self.config.mail_interval;
+        self.supervisor
+            .supervise(Subsystem::Mailbox, move |heartbeat| {
+                Self::run_mailbox(Arc::clone(&iroh), Arc::clone(&mailbox), interval, heartbeat)
+            });
+
+        // Sync revocations with peers as they connect, and prune expired ones
+        let iroh = Arc::clone(&self.iroh);
+        let sync_protocol = Arc::clone(&self.sync_protocol);
+        let peer_access = Arc::clone(&self.peer_access);
+        let revocations = Arc::clone(&self.revocations);
+        let interval = self.config.revocation_interval;
+        self.supervisor
+            .supervise(Subsystem::Revocations, move |heartbeat| {
+                Self::run_revocations(
+                    Arc::clone(&iroh),
+                    Arc::clone(&sync_protocol),
+                    Arc::clone(&peer_access),
+                    Arc::clone(&revocations),
+                    interval,
+                    heartbeat,
+                )
+            });
+
         // Start message handler
         self.start_message_handler();
 
+        // Start control API
+        if let Some(addr) = self.config.control_addr {
+            self.serve_control(addr).await?;
+        }
+
+        // Start recording sync sessions
+        if let Some(path) = self.config.record_path.clone() {
+            self.start_recording(path)?;
+        }
+
         // Announce presence
         let node_addr = self.iroh.node_addr().await?;
         self.discovery.announce_presence(node_addr)?;
@@ -228,6 +619,34 @@ impl VudoP2P {
             bg_sync.stop();
         }
 
+        // Tell peers we're going
+        self.presence.set_state(PresenceState::Offline);
+        if let Err(e) = self.presence.announce(&self.gossip, self.node_id()).await {
+            warn!("Failed to announce going offline: {}", e);
+        }
+
+        // Persist address hints so reconnects after restart skip discovery
+        if let Some(path) = &self.config.session_cache_path {
+            if let Err(e) = self.iroh.session_cache().persist(path) {
+                warn!("Failed to persist session hints: {}", e);
+            }
+        }
+        if let Err(e) = self.sync_protocol.persist_sync_states().await {
+            warn!("Failed to persist sync states: {}", e);
+        }
+
+        // Stop control API
+        if let Some(control) = self.control.write().take() {
+            control.abort();
+        }
+
+        // Stop supervised subsystems: message handler, relay probes,
+        // connection pool and background sync
+        self.supervisor.stop();
+
+        // Stop recording
+        self.stop_recording();
+
         // Close Iroh endpoint
         self.iroh.close().await?;
 
@@ -254,12 +673,27 @@ impl VudoP2P {
     pub async fn connect(&self, node_addr: NodeAddr) -> Result<PeerId> {
         info!("Connecting to peer: {}", node_addr.node_id);
 
+        // Blocked peers aren't dialed
+        let peer_id = node_addr.node_id.to_string();
+        if self.peer_access.decide(&peer_id, None) == AccessDecision::Blocked {
+            AccessDecision::Blocked.check(&peer_id)?;
+        }
+
         // Add to discovery
-        let peer_id = self.discovery.add_peer(node_addr.clone())?;
+        self.discovery.add_peer(node_addr.clone())?;
+
+        // Reconnects to recently-seen peers count towards partition healing
+        let reconnect = self.iroh.session_cache().is_known(&peer_id)
+            && self.iroh.get_metadata(&peer_id).is_none();
+        let started = Instant::now();
 
         // Connect via Iroh
         self.iroh.connect(node_addr).await?;
 
+        if reconnect {
+            self.sync_protocol.record_reconnect(&peer_id, started.elapsed());
+        }
+
         Ok(peer_id)
     }
 
@@ -274,38 +708,606 @@ impl VudoP2P {
         Ok(())
     }
 
+    /// Probe the network paths to a peer.
+    ///
+    /// Connects to the peer if needed, then keeps the connection busy while
+    /// the endpoint tries the peer's direct addresses and punches through
+    /// NATs, for up to [`PROBE_DURATION`] or until a direct path carries the
+    /// connection. The report lists the candidate addresses, the round-trip
+    /// time of every path and the path chosen (see [`diagnostics`]).
+    ///
+    /// A peer connected a while ago reports the path it settled on, so it
+    /// can't tell hole punching from a direct path; disconnect first to
+    /// probe from scratch.
+    pub async fn diagnose(&self, peer_id: &PeerId) -> Result<ConnectivityReport> {
+        self.check_peer(peer_id)?;
+        if self.iroh.is_link(peer_id) {
+            return Err(P2PError::ConnectionFailed(format!(
+                "Peer {} is linked through the browser gateway",
+                peer_id
+            )));
+        }
+        let node_id = peer_id
+            .parse::<NodeId>()
+            .map_err(|e| P2PError::InvalidMessage(format!("Invalid peer ID {}: {}", peer_id, e)))?;
+
+        let connect_time = match self.iroh.get_metadata(peer_id) {
+            Some(_) => None,
+            None => {
+                let started = Instant::now();
+                self.connect(NodeAddr::new(node_id)).await?;
+                Some(started.elapsed())
+            }
+        };
+        let node_addr = self.node_addr().await?;
+        let local_candidates = node_addr.direct_addresses().copied().collect();
+        let mut probe = ConnectivityProbe::new(peer_id.clone(), local_candidates, connect_time);
+
+        let deadline = Instant::now() + PROBE_DURATION;
+        loop {
+            // Traffic makes the endpoint ping the candidate paths
+            self.iroh
+                .send_message(peer_id, &SyncMessage::Heartbeat)
+                .await?;
+            if let Some(sample) = self.iroh.path_sample(peer_id) {
+                probe.record(sample);
+            }
+            if probe.is_settled() || Instant::now() >= deadline {
+                break;
+            }
+            tokio::time::sleep(diagnostics::SAMPLE_INTERVAL).await;
+        }
+
+        let report = probe.finish();
+        info!(
+            "Peer {} is reachable: {:?} in {:?}",
+            peer_id, report.reachability, report.probe_duration
+        );
+        Ok(report)
+    }
+
+    /// Get the ephemeral identity when running as a guest.
+    ///
+    /// Present its [`authorization`](GuestIdentity::authorization) to peers,
+    /// which admit it with their own [`GuestPolicy`].
+    pub fn guest_identity(&self) -> Option<&GuestIdentity> {
+        self.guest.as_ref()
+    }
+
+    /// Authenticate to a peer as a device.
+    ///
+    /// Sends a session token proving the device's capabilities, see
+    /// [`auth`]. Peers requiring authentication only serve and accept
+    /// documents within those capabilities; the peer acknowledges with
+    /// [`SyncMessage::Authenticated`].
+    pub async fn authenticate(&self, peer_id: &PeerId, device: &DeviceIdentity) -> Result<()> {
+        let token = auth::session_token(device, &self.node_id(), peer_id)?;
+        self.iroh
+            .send_message(peer_id, &SyncMessage::Authenticate { token })
+            .await
+    }
+
+    /// Get the identity a peer authenticated as.
+    pub fn peer_auth(&self, peer_id: &PeerId) -> Option<PeerAuth> {
+        self.sync_protocol.peer_auth(peer_id)
+    }
+
+    /// Set the device this node runs as.
+    ///
+    /// Its DID is named in presence heartbeats, and the node proves it
+    /// holds the DID when peers challenge it.
+    pub fn set_device(&self, device: &DeviceIdentity) {
+        self.sync_protocol.set_device(Some(device.clone()));
+        self.presence.set_did(Some(device.did().to_string()));
+    }
+
+    /// Challenge a connected peer to prove it holds a DID.
+    ///
+    /// Peers are challenged for the DID their heartbeats name as they
+    /// connect; this challenges for any other. Once the peer answers, the
+    /// DID is returned by [`proven_did`](Self::proven_did).
+    pub async fn challenge_peer(&self, peer_id: &PeerId, did: &Did) -> Result<()> {
+        let message = self
+            .sync_protocol
+            .challenge(peer_id, &self.node_id(), did.clone());
+        self.iroh.send_message(peer_id, &message).await
+    }
+
+    /// Get the DID a peer proved it holds on its current connection.
+    ///
+    /// Unlike the DID a peer's heartbeats name, this one was proven with a
+    /// challenge signed by the DID's key.
+    pub fn proven_did(&self, peer_id: &PeerId) -> Option<Did> {
+        self.sync_protocol.proven_did(peer_id)
+    }
+
     /// Sync a document with a peer.
+    ///
+    /// Runs the Automerge sync protocol: only changes missing on either
+    /// side are exchanged, also after reconnecting or restarting.
+    ///
+    /// Fails if the sync policy skips the document, or the peer may not
+    /// sync (see [`peer_access`]).
     pub async fn sync_document(&self, peer_id: &PeerId, namespace: &str, id: &str) -> Result<()> {
+        self.check_guest_read(namespace)?;
+        self.check_peer(peer_id)?;
+        let policy = check_sync_policy(&self.sync_policy, &self.gossip, namespace, id, None);
+        if let Err(reason) = policy {
+            return Err(P2PError::PermissionDenied(format!(
+                "Sync policy skips {}/{}: {}",
+                namespace, id, reason
+            )));
+        }
         info!("Syncing document {}/{} with peer {}", namespace, id, peer_id);
 
-        // Create sync request
-        let request = self
-            .sync_protocol
-            .create_sync_request(peer_id, namespace, id)?;
-
-        // Send request
-        self.iroh.send_message(peer_id, &request).await?;
+        let message = self.sync_protocol.start_sync(peer_id, namespace, id).await?;
+        self.iroh.send_message(peer_id, &message).await?;
 
         Ok(())
     }
 
+    /// Subscribe to sync conflicts.
+    ///
+    /// Reports every merge of a peer's changes that left fields with
+    /// concurrent values, including those resolved by a conflict policy.
+    pub fn subscribe_conflicts(&self) -> mpsc::UnboundedReceiver<SyncConflict> {
+        self.sync_protocol.conflicts().subscribe()
+    }
+
+    /// Suggest winners for unresolved sync conflicts with `advisor`, e.g.
+    /// vudo-ai's `AiConflictHandler`.
+    ///
+    /// Suggestions are reported in [`SyncConflict::suggestions`]; apply
+    /// them with [`vudo_state::DocumentHandle::resolve_conflict`].
+    pub fn set_conflict_advisor(&self, advisor: Arc<dyn ConflictHandler>) {
+        self.sync_protocol.conflicts().set_advisor(advisor);
+    }
+
+    /// Reconcile a Willow namespace with a peer.
+    ///
+    /// Both sides end up with the union of their entries and tombstones in
+    /// the area `capability` may read, exchanging only the ranges that
+    /// differ (see [`willow_sync`]). `signing_key` must be the capability's
+    /// receiver's key. Requires [`VudoP2P::with_willow`] on both nodes, each
+    /// trusting the namespace's root capability.
+    pub async fn reconcile_willow(
+        &self,
+        peer_id: &PeerId,
+        namespace: &str,
+        capability: &Capability,
+        signing_key: &SigningKey,
+    ) -> Result<()> {
+        self.check_guest_read(namespace)?;
+        self.check_peer(peer_id)?;
+        let willow = self
+            .willow()
+            .ok_or_else(|| P2PError::WillowError("Willow sync is not enabled".to_string()))?;
+        info!(
+            "Reconciling Willow namespace {} with peer {}",
+            namespace, peer_id
+        );
+
+        let message = WillowSync::new(willow, self.node_id(), peer_id.clone()).start(
+            namespace,
+            capability,
+            signing_key,
+        )?;
+        self.iroh
+            .send_message(peer_id, &SyncMessage::WillowSync(Box::new(message)))
+            .await
+    }
+
     /// Subscribe to document updates.
     pub async fn subscribe_document(&self, namespace: &str, id: &str) -> Result<Subscription> {
+        self.check_guest_read(namespace)?;
         self.gossip.subscribe_document(namespace, id).await
     }
 
+    /// Join a gossip topic on the network.
+    ///
+    /// The topic's swarm is bootstrapped from `bootstrap`, e.g. connected
+    /// peers. Announcements to the topic then reach every peer in the swarm,
+    /// and theirs are delivered to local subscribers once their signature is
+    /// verified. Neighbors in the swarm are tracked as interested peers.
+    pub async fn join_topic(&self, topic: Topic, bootstrap: Vec<PeerId>) -> Result<()> {
+        let bootstrap = bootstrap
+            .iter()
+            .map(|peer| {
+                peer.parse::<NodeId>().map_err(|e| {
+                    P2PError::InvalidMessage(format!("Invalid peer ID {}: {}", peer, e))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let swarm = self.iroh.join_gossip(topic.id(), bootstrap)?;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.gossip.join(topic.clone(), tx)?;
+        Self::spawn_gossip_topic(
+            topic,
+            swarm,
+            rx,
+            Arc::clone(&self.sync_protocol),
+            Arc::clone(&self.iroh),
+            Arc::clone(&self.gossip),
+            Arc::clone(&self.sync_policy),
+        );
+        Ok(())
+    }
+
+    /// Leave a gossip topic on the network.
+    ///
+    /// Returns whether the topic was joined. Local subscriptions are kept.
+    pub fn leave_topic(&self, topic: &Topic) -> bool {
+        self.gossip.leave(topic)
+    }
+
+    /// Get the gossip topics joined on the network.
+    pub fn joined_topics(&self) -> Vec<Topic> {
+        self.gossip.joined_topics()
+    }
+
+    /// Publish an ephemeral payload, e.g. a cursor position, to an
+    /// application topic (see [`Topic::app`]).
+    ///
+    /// The payload reaches local subscribers, and the network if the topic
+    /// is joined with [`VudoP2P::join_topic`]. Nothing is stored.
+    pub async fn publish(&self, topic: Topic, payload: Vec<u8>) -> Result<()> {
+        self.gossip
+            .publish_application(self.node_id(), topic, payload)
+            .await
+    }
+
+    /// Subscribe to the payloads published to an application topic.
+    pub async fn subscribe(&self, topic: Topic) -> Result<TopicSubscription> {
+        self.gossip.subscribe_application(topic).await
+    }
+
+    /// Only accept payloads on an application topic from publishers whose
+    /// capability to publish there is delegated by one of `policy`'s
+    /// trusted issuers.
+    pub fn restrict_publishers(&self, topic: Topic, policy: SyncAuthPolicy) {
+        let policy = policy.with_revocations(Arc::clone(self.revocations.store()));
+        self.gossip.restrict_publishers(topic, policy);
+    }
+
+    /// Publish to an application topic as a device, proving its capability
+    /// to peers restricting the topic's publishers.
+    ///
+    /// The proof expires with the device's authorization, and after at most
+    /// [`auth::SESSION_TTL`]; authorize again to renew it.
+    pub fn authorize_publish(&self, topic: Topic, device: &DeviceIdentity) -> Result<()> {
+        let token = auth::publish_token(device, &self.node_id(), topic.as_str())?;
+        self.gossip.set_publish_token(topic, token);
+        Ok(())
+    }
+
     /// Announce presence with available documents.
+    ///
+    /// The announcement reaches local subscribers, and the network if the
+    /// presence topic is joined.
     pub async fn announce_presence(&self, documents: Vec<(String, String)>) -> Result<()> {
         let peer_id = self.node_id();
         self.gossip.announce_presence(peer_id, documents).await
     }
 
+    /// Watch a peer's presence by DID, or by node ID for peers announcing
+    /// no DID.
+    ///
+    /// Receives the peer's current presence, if known, then every
+    /// transition between online, idle and offline with its last-seen time.
+    /// Peers' heartbeats arrive on the presence topic, so join it with
+    /// [`VudoP2P::join_topic`] to see peers beyond local subscribers.
+    pub fn watch_peer(&self, did: &str) -> mpsc::UnboundedReceiver<PeerPresence> {
+        self.presence.watch_peer(did)
+    }
+
+    /// Get the online peers active on a document.
+    pub fn active_on(&self, namespace: &str, id: &str) -> Vec<PeerPresence> {
+        self.presence.active_on(namespace, id)
+    }
+
+    /// Get the presence tracker, e.g. to set the DID, state and documents
+    /// this node announces.
+    pub fn presence(&self) -> &Arc<PresenceTracker> {
+        &self.presence
+    }
+
+    /// Join a replication group as the member `did`.
+    ///
+    /// The group's documents are synced every
+    /// [`replication_interval`](P2PConfig::replication_interval) with the
+    /// connected members this node links to, once they authenticated (see
+    /// [`replication`]).
+    pub fn join_replication_group(&self, group: ReplicationGroup, did: &str) -> Result<()> {
+        self.replication.join(group, did)
+    }
+
+    /// Leave a replication group. Returns whether this node was a member.
+    pub fn leave_replication_group(&self, name: &str) -> bool {
+        self.replication.leave(name)
+    }
+
+    /// Get the replication of a group's documents held by this node.
+    pub async fn replication_status(&self, group: &str) -> Result<Vec<DocumentReplication>> {
+        let namespace = self
+            .replication
+            .group(group)
+            .map(|group| group.namespace)
+            .ok_or_else(|| {
+                P2PError::InvalidNamespace(format!("Not a member of replication group {}", group))
+            })?;
+        let documents = self.state_engine.list_documents(&namespace).await?;
+        let progress = self.sync_protocol.all_progress();
+        self.replication
+            .replication(group, &documents, &progress)
+            .ok_or_else(|| {
+                P2PError::InvalidNamespace(format!("Not a member of replication group {}", group))
+            })
+    }
+
+    /// List the documents of every joined group held by fewer members than
+    /// the group requires.
+    pub async fn under_replicated(&self) -> Result<Vec<DocumentReplication>> {
+        let mut under_replicated = Vec::new();
+        for group in self.replication.groups() {
+            let replication = self.replication_status(&group.name).await?;
+            under_replicated.extend(
+                replication
+                    .into_iter()
+                    .filter(DocumentReplication::is_under_replicated),
+            );
+        }
+        Ok(under_replicated)
+    }
+
+    /// Leave the changes of a document for an offline peer with a mailbox.
+    ///
+    /// The changes are sealed to `recipient` and signed by `device`, so the
+    /// mailbox can neither read nor alter them (see [`mailbox`]). A mailbox
+    /// refusing the mail replies with [`SyncMessage::Error`].
+    pub async fn send_mail(
+        &self,
+        mailbox: &PeerId,
+        recipient: &Did,
+        namespace: &str,
+        id: &str,
+        device: &DeviceIdentity,
+    ) -> Result<()> {
+        self.check_peer(mailbox)?;
+        self.check_guest_write()?;
+        if self.sync_protocol.is_deleted(namespace, id) {
+            return Err(P2PError::DocumentDeleted(format!("{}/{}", namespace, id)));
+        }
+
+        let doc_id = vudo_state::DocumentId::new(namespace, id);
+        let handle = self
+            .state_engine
+            .get_document(&doc_id)
+            .await
+            .map_err(|_| P2PError::DocumentNotFound(doc_id.to_string()))?;
+        let payload = MailPayload {
+            namespace: namespace.to_string(),
+            id: id.to_string(),
+            changes: vec![handle.save()],
+        };
+        let mail = SealedMail::seal(device, recipient, &payload, DEFAULT_MAIL_TTL)?;
+        info!(
+            "Leaving {}/{} for {} with mailbox {}",
+            namespace, id, recipient, mailbox
+        );
+        self.iroh
+            .send_message(mailbox, &SyncMessage::MailDeposit(mail))
+            .await
+    }
+
+    /// Receive mail as set out by `settings`.
+    ///
+    /// Mail is fetched from the designated mailboxes as they connect, and
+    /// every [`mail_interval`](P2PConfig::mail_interval) while connected.
+    pub fn receive_mail(&self, settings: MailSettings) {
+        self.mailbox.receive(settings);
+    }
+
+    /// Stop receiving mail.
+    pub fn stop_receiving_mail(&self) {
+        self.mailbox.stop_receiving();
+    }
+
+    /// Fetch mail from a designated mailbox now.
+    pub async fn fetch_mail(&self, mailbox: &PeerId) -> Result<()> {
+        self.check_peer(mailbox)?;
+        let fetch = self.mailbox.fetch_from(mailbox)?.ok_or_else(|| {
+            P2PError::PermissionDenied(format!("{} is not a designated mailbox", mailbox))
+        })?;
+        self.iroh
+            .send_message(mailbox, &SyncMessage::MailFetch(fetch))
+            .await
+    }
+
+    /// Get the mailbox, e.g. to see how much mail it holds.
+    pub fn mailbox(&self) -> &Arc<Mailbox> {
+        &self.mailbox
+    }
+
+    /// Revoke a UCAN issued by `device`, or delegated from one it issued.
+    ///
+    /// The revocation is signed by the device and synced with every
+    /// connected peer, which pass it on; peers connecting later get it when
+    /// they sync revocations. From then on, session and publish tokens
+    /// carrying the UCAN are refused (see [`revocations`]).
+    pub async fn revoke_ucan(
+        &self,
+        ucan: &vudo_identity::Ucan,
+        device: &DeviceIdentity,
+        reason: Option<String>,
+    ) -> Result<()> {
+        let revocation =
+            UcanRevocation::new(ucan, device.did().clone(), reason, &device.signing_key())?;
+        if self.revocations.publish(revocation).await? {
+            info!("Revoked UCAN {}", ucan.cid()?);
+            Self::spread_revocations(&self.iroh, &self.sync_protocol, &self.peer_access, None)
+                .await;
+        }
+        Ok(())
+    }
+
+    /// Create an anchor publishing GDPR audit log checkpoints as this node.
+    ///
+    /// Join the [`GossipAuditAnchor::topic`] with [`VudoP2P::join_topic`] for
+    /// checkpoints to reach the network.
+    pub fn audit_anchor(&self) -> Result<GossipAuditAnchor> {
+        GossipAuditAnchor::new(Arc::clone(&self.gossip), self.node_id())
+    }
+
+    /// Get the revocations this node knows of.
+    pub fn revocations(&self) -> &Arc<RevocationStore> {
+        self.revocations.store()
+    }
+
+    /// Unlink a lost or stolen device of `master`.
+    ///
+    /// Revokes the device's UCAN and publishes a wipe instruction for it,
+    /// both synced with every connected peer like other revocations. The
+    /// device deletes its identity when the instruction reaches it (see
+    /// [`honor_wipes`](Self::honor_wipes)). The unlink is recorded in the
+    /// master's audit log; save the master afterwards.
+    pub async fn unlink_device(
+        &self,
+        master: &mut MasterIdentity,
+        device: &Did,
+        reason: Option<String>,
+    ) -> Result<DeviceUnlink> {
+        let unlink = master.unlink_device(device, reason)?;
+        if let Some(revocation) = &unlink.revocation {
+            self.revocations.publish(revocation.clone()).await?;
+        }
+        self.revocations.publish_wipe(unlink.wipe.clone()).await?;
+        info!("Unlinked device {}", device);
+        Self::spread_revocations(&self.iroh, &self.sync_protocol, &self.peer_access, None).await;
+        Ok(unlink)
+    }
+
+    /// Delete this node's device identity when its master unlinks it.
+    ///
+    /// `label` is where `keystore` holds `device`. Wipe instructions are
+    /// checked against the master the device is linked to, following the
+    /// master's key rotations. Once the identity is deleted, the
+    /// instruction is sent on the returned channel so the application can
+    /// clear its data and stop the node.
+    pub async fn honor_wipes(
+        &self,
+        keystore: Arc<Keystore>,
+        label: &str,
+        device: &DeviceIdentity,
+    ) -> mpsc::UnboundedReceiver<WipeInstruction> {
+        self.revocations.honor_wipes(keystore, label, device).await
+    }
+
     /// Announce document update.
+    ///
+    /// The announcement reaches local subscribers, every connected peer and
+    /// the document's topic on the network, if joined. Peers subscribed to
+    /// the document request a sync from this node when the version is newer
+    /// than the last one it announced to them.
     pub async fn announce_update(&self, namespace: &str, id: &str, version: u64) -> Result<()> {
+        self.check_guest_write()?;
         let peer_id = self.node_id();
         self.gossip
-            .announce_update(peer_id, namespace, id, version)
-            .await
+            .announce_update(peer_id.clone(), namespace, id, version)
+            .await?;
+
+        let message = SyncMessage::Gossip(self.gossip.sign(GossipMessage::document_update(
+            peer_id, namespace, id, version,
+        ))?);
+        for peer in self.iroh.connected_peers() {
+            if let Err(e) = self.iroh.send_message(&peer, &message).await {
+                warn!("Failed to announce update to peer {}: {}", peer, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete a document everywhere under GDPR Article 17.
+    ///
+    /// `receipt` proves the erasure of the personal data of `owner`, e.g.
+    /// the `crypto_proof` of a vudo-privacy deletion report for the owner's
+    /// DID. The owner signs it, along with a deletion token proving it may
+    /// write the document (see [`auth::deletion_token`]). The document is
+    /// deleted locally and the signed deletion is sent to every connected
+    /// peer, which deletes its copy and passes it on. Peers syncing the
+    /// document later are sent the deletion instead.
+    pub async fn delete_document(
+        &self,
+        namespace: &str,
+        id: &str,
+        mut receipt: DeletionReceipt,
+        owner: &DeviceIdentity,
+    ) -> Result<()> {
+        self.check_guest_write()?;
+        receipt
+            .sign(&owner.signing_key())
+            .map_err(|e| P2PError::SerializationError(e.to_string()))?;
+        let token = auth::deletion_token(owner, namespace, id)?;
+        let signed = DocumentDeletion::new(namespace, id, receipt)
+            .with_authorization(token)
+            .sign(&self.iroh.signing_key())?;
+        if !self.sync_protocol.apply_deletion(signed.clone()).await? {
+            debug!("{}/{} was already deleted", namespace, id);
+        }
+        Self::broadcast_deletion(&self.iroh, signed, None).await;
+        Ok(())
+    }
+
+    /// Check whether a document was deleted.
+    pub fn is_deleted(&self, namespace: &str, id: &str) -> bool {
+        self.sync_protocol.is_deleted(namespace, id)
+    }
+
+    /// Get the allowed, blocked and pairing peers.
+    pub fn peer_access(&self) -> &Arc<PeerAccess> {
+        &self.peer_access
+    }
+
+    /// Allow a node ID or DID to sync, lifting a block.
+    pub async fn allow_peer(&self, id: &str) -> Result<()> {
+        self.peer_access.allow(id);
+        self.peer_access.persist(&self.state_engine).await
+    }
+
+    /// Block a node ID or DID, disconnecting the peer if it is connected.
+    pub async fn block_peer(&self, id: &str) -> Result<()> {
+        self.peer_access.block(id);
+        let blocked = self
+            .connected_peers()
+            .into_iter()
+            .filter(|peer_id| self.peer_decision(peer_id) == AccessDecision::Blocked);
+        for peer_id in blocked.collect::<Vec<_>>() {
+            info!("Disconnecting blocked peer {}", peer_id);
+            let _ = self.iroh.disconnect(&peer_id).await;
+        }
+        self.peer_access.persist(&self.state_engine).await
+    }
+
+    /// Subscribe to pairing events.
+    ///
+    /// In [`AccessMode::Pairing`], every unknown peer that connects raises
+    /// [`PairingEvent::Requested`] with a code to show the user, who checks
+    /// that the other device shows the same code before approving it.
+    pub fn subscribe_pairing(&self) -> mpsc::UnboundedReceiver<PairingEvent> {
+        self.peer_access.subscribe()
+    }
+
+    /// Approve pairing with a peer, allowing it to sync from now on.
+    pub async fn approve_pairing(&self, peer_id: &PeerId) -> Result<()> {
+        info!("Paired with peer {}", peer_id);
+        self.peer_access.approve(peer_id);
+        self.peer_access.persist(&self.state_engine).await
+    }
+
+    /// Reject pairing with a peer. Returns `false` if the peer had no
+    /// pending request.
+    pub fn reject_pairing(&self, peer_id: &PeerId) -> bool {
+        self.peer_access.reject(peer_id)
     }
 
     /// Get connected peers.
@@ -313,6 +1315,14 @@ impl VudoP2P {
         self.iroh.connected_peers()
     }
 
+    /// Subscribe to connection events.
+    ///
+    /// Reports connections opening and closing, and redials of peers whose
+    /// connection dropped.
+    pub fn subscribe_connections(&self) -> mpsc::UnboundedReceiver<ConnectionEvent> {
+        self.iroh.subscribe_connections()
+    }
+
     /// Get connection metadata.
     pub fn get_connection_metadata(&self, peer_id: &PeerId) -> Option<ConnectionMetadata> {
         self.iroh.get_metadata(peer_id)
@@ -323,11 +1333,205 @@ impl VudoP2P {
         self.bandwidth.stats()
     }
 
+    /// Get the bandwidth manager, e.g. to change limits at runtime.
+    pub fn bandwidth(&self) -> Arc<BandwidthManager> {
+        Arc::clone(&self.bandwidth)
+    }
+
+    /// Get the estimated link quality to a peer.
+    pub fn link_estimate(&self, peer_id: &PeerId) -> Option<LinkEstimate> {
+        self.iroh.link_estimate(peer_id)
+    }
+
+    /// Get the progress of a document's sync with a peer.
+    ///
+    /// Each sync round also runs in a `sync_round` tracing span carrying
+    /// the peer and document, to follow a document that isn't converging.
+    pub fn sync_progress(
+        &self,
+        peer_id: &PeerId,
+        namespace: &str,
+        id: &str,
+    ) -> Option<SyncProgress> {
+        self.sync_protocol.progress(peer_id, namespace, id)
+    }
+
+    /// List the progress of every document sync, most recently updated
+    /// first.
+    pub fn all_sync_progress(&self) -> Vec<SyncProgress> {
+        self.sync_protocol.all_progress()
+    }
+
     /// Get sync statistics.
     pub fn sync_stats(&self) -> SyncStats {
         self.sync_protocol.get_stats()
     }
 
+    /// Take a snapshot of documents, sync sessions, peers, bandwidth, queues
+    /// and recent errors.
+    pub fn status(&self) -> NodeStatus {
+        self.probe().status()
+    }
+
+    /// Get the supervisor restarting failed subsystems.
+    ///
+    /// Subscribe to it for [`Incident`]s, or supervise application loops.
+    pub fn supervisor(&self) -> &Arc<Supervisor> {
+        &self.supervisor
+    }
+
+    /// Check the health of a storage adapter periodically.
+    ///
+    /// A failed check is recorded as an incident and the adapter is
+    /// re-initialized, retrying with backoff until it is healthy again.
+    pub fn supervise_storage(&self, storage: Arc<dyn StorageAdapter>) {
+        let recover = Arc::clone(&storage);
+        self.supervisor.add_health_check(
+            Subsystem::Storage,
+            move || {
+                let storage = Arc::clone(&storage);
+                async move {
+                    storage
+                        .stats()
+                        .await
+                        .map(|_| ())
+                        .map_err(|e| P2PError::Internal(format!("Storage: {}", e)))
+                }
+            },
+            move || {
+                let storage = Arc::clone(&recover);
+                async move {
+                    storage
+                        .init()
+                        .await
+                        .map_err(|e| P2PError::Internal(format!("Storage: {}", e)))
+                }
+            },
+        );
+    }
+
+    /// Serve the control API on an address, replacing any running server.
+    ///
+    /// Returns the bound address, which differs from `addr` when port 0 is
+    /// requested.
+    pub async fn serve_control(&self, addr: SocketAddr) -> Result<SocketAddr> {
+        let server = ControlServer::bind(addr).await?;
+        let local_addr = server.local_addr()?;
+        let handle = server.spawn(Arc::new(self.probe()));
+        if let Some(previous) = self.control.write().replace(handle) {
+            previous.abort();
+        }
+        Ok(local_addr)
+    }
+
+    /// Accept browser peers over WebSocket (see [`browser`]).
+    ///
+    /// Linked browsers sync with this node like any other peer. The gateway
+    /// stops accepting links when dropped.
+    #[cfg(feature = "browser-gateway")]
+    pub async fn start_browser_gateway(
+        &self,
+        config: BrowserGatewayConfig,
+    ) -> Result<BrowserGateway> {
+        BrowserGateway::spawn(config, Arc::clone(&self.iroh)).await
+    }
+
+    /// Record every sync message sent or received to a file, replacing any
+    /// running recording.
+    ///
+    /// Load the file with [`Recording::load`] and feed it to a
+    /// [`SessionReplayer`] to reproduce the session.
+    pub fn start_recording(&self, path: impl Into<PathBuf>) -> Result<()> {
+        let recorder = SessionRecorder::create(path, &self.node_id())?;
+        info!("Recording sync sessions to {}", recorder.path().display());
+        self.iroh.set_recorder(Some(Arc::new(recorder)));
+        Ok(())
+    }
+
+    /// Stop recording, returning the recording file path.
+    pub fn stop_recording(&self) -> Option<PathBuf> {
+        let recorder = self.iroh.set_recorder(None)?;
+        info!(
+            "Recorded {} sync messages to {}",
+            recorder.message_count(),
+            recorder.path().display()
+        );
+        Some(recorder.path().to_path_buf())
+    }
+
+    /// Offer a file to a peer.
+    ///
+    /// The file is imported into the node's iroh-blobs store and fetched by
+    /// the peer once it accepts; progress is reported via
+    /// [`VudoP2P::subscribe_transfers`]. Calling this again for the same file
+    /// after an interrupted transfer resumes it.
+    ///
+    /// The offer waits until the file fits the upload limits for the peer
+    /// at [`file_transfer::TRANSFER_PRIORITY`].
+    pub async fn send_file(
+        &self,
+        peer_id: &PeerId,
+        path: impl Into<PathBuf>,
+        capability: Option<Capability>,
+    ) -> Result<TransferId> {
+        self.check_guest_write()?;
+        self.check_peer(peer_id)?;
+        let path = path.into();
+        let (hash, size) = self.iroh.import_blob(&path).await?;
+        // The peer fetches through iroh-blobs, so the upload is reserved
+        // before it is offered
+        self.iroh
+            .bandwidth()
+            .throttle(
+                TrafficDirection::Upload,
+                peer_id,
+                None,
+                file_transfer::TRANSFER_PRIORITY,
+                usize::try_from(size).unwrap_or(usize::MAX),
+            )
+            .await;
+        let offer = self
+            .file_transfers
+            .offer_file(peer_id, path, hash.clone(), size, capability)?;
+
+        // Offered before the peer can accept, so its fetch finds it
+        self.iroh.offer_blob(peer_id, &hash);
+        self.iroh
+            .send_message(peer_id, &SyncMessage::FileOffer(offer))
+            .await
+            .inspect_err(|_| self.iroh.withdraw_blob(peer_id, &hash))?;
+        Ok(hash)
+    }
+
+    /// Accept a file offer held for an application decision.
+    pub async fn accept_file(&self, hash: &TransferId) -> Result<()> {
+        let (peer_id, reply) = self.file_transfers.accept(hash).await?;
+        self.iroh.send_message(&peer_id, &reply).await?;
+        Self::spawn_file_receiver(
+            peer_id,
+            hash.clone(),
+            Arc::clone(&self.iroh),
+            Arc::clone(&self.file_transfers),
+        );
+        Ok(())
+    }
+
+    /// Reject a file offer held for an application decision.
+    pub async fn reject_file(&self, hash: &TransferId, reason: &str) -> Result<()> {
+        let (peer_id, reply) = self.file_transfers.reject(hash, reason)?;
+        self.iroh.send_message(&peer_id, &reply).await
+    }
+
+    /// Replace the acceptance policy for incoming file offers.
+    pub fn set_file_acceptance_policy(&self, policy: AcceptancePolicy) {
+        self.file_transfers.set_policy(policy);
+    }
+
+    /// Subscribe to file transfer events.
+    pub fn subscribe_transfers(&self) -> mpsc::UnboundedReceiver<TransferEvent> {
+        self.file_transfers.subscribe()
+    }
+
     /// Add document to background sync.
     pub fn add_to_background_sync(&self, peer_id: PeerId, namespace: String, doc_id: String) {
         if let Some(bg_sync) = self.background_sync.read().as_ref() {
@@ -335,54 +1539,336 @@ impl VudoP2P {
         }
     }
 
+    /// Replace the sync policy.
+    ///
+    /// Takes effect for the next message, announcement and background sync
+    /// pass; documents already synced are kept.
+    pub fn set_sync_policy(&self, policy: SyncPolicy) {
+        *self.sync_policy.write() = policy;
+    }
+
+    /// Get the sync policy.
+    pub fn sync_policy(&self) -> SyncPolicy {
+        self.sync_policy.read().clone()
+    }
+
+    /// Fail if running as a guest that may not read a namespace.
+    fn check_guest_read(&self, namespace: &str) -> Result<()> {
+        match &self.guest {
+            Some(guest) => guest_can_read(guest.policy(), guest.is_expired(), namespace),
+            None => Ok(()),
+        }
+    }
+
+    /// Decide whether a peer may sync, by node ID and authenticated DID.
+    fn peer_decision(&self, peer_id: &PeerId) -> AccessDecision {
+        peer_decision(&self.peer_access, &self.sync_protocol, peer_id)
+    }
+
+    /// Fail unless a peer may sync, asking the user to pair with unknown
+    /// peers in pairing mode.
+    fn check_peer(&self, peer_id: &PeerId) -> Result<()> {
+        let decision = self.peer_decision(peer_id);
+        if decision == AccessDecision::PendingApproval {
+            self.peer_access.request_pairing(peer_id);
+        }
+        decision.check(peer_id)
+    }
+
+    /// Fail if running as a guest, which is read-only.
+    fn check_guest_write(&self) -> Result<()> {
+        match &self.guest {
+            Some(_) => Err(P2PError::PermissionDenied(
+                "Guest nodes are read-only".to_string(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Build a status reader over the shared components.
+    fn probe(&self) -> NodeProbe {
+        NodeProbe {
+            node_name: self.config.node_name.clone(),
+            started_at: self.started_at,
+            state_engine: Arc::clone(&self.state_engine),
+            iroh: Arc::clone(&self.iroh),
+            sync_protocol: Arc::clone(&self.sync_protocol),
+            bandwidth: Arc::clone(&self.bandwidth),
+            file_transfers: Arc::clone(&self.file_transfers),
+            errors: Arc::clone(&self.errors),
+        }
+    }
+
     /// Start message handler.
     fn start_message_handler(&self) {
         let iroh = Arc::clone(&self.iroh);
         let sync_protocol = Arc::clone(&self.sync_protocol);
         let bandwidth = Arc::clone(&self.bandwidth);
         let discovery = Arc::clone(&self.discovery);
-
-        tokio::spawn(async move {
-            info!("Starting message handler");
-
-            loop {
-                match iroh.recv_message().await {
-                    Ok((peer_id, message)) => {
-                        debug!("Received message from peer {}", peer_id);
-
-                        // Update peer last seen
-                        discovery.update_last_seen(&peer_id);
-
-                        // Handle message
-                        if let Err(e) = Self::handle_message(
-                            &peer_id,
-                            message,
-                            &sync_protocol,
-                            &iroh,
-                            &bandwidth,
-                        )
-                        .await
-                        {
-                            warn!("Failed to handle message from peer {}: {}", peer_id, e);
-                        }
-                    }
-                    Err(e) => {
-                        warn!("Error receiving message: {}", e);
-                        // Don't break on error, keep listening
-                    }
-                }
-            }
-        });
-    }
+        let gossip = Arc::clone(&self.gossip);
+        let file_transfers = Arc::clone(&self.file_transfers);
+        let errors = Arc::clone(&self.errors);
+        let guest = self.guest.clone();
+        let willow = self.willow.clone();
+        let sync_policy = Arc::clone(&self.sync_policy);
+        let peer_access = Arc::clone(&self.peer_access);
+        let mailbox = Arc::clone(&self.mailbox);
+        let revocations = Arc::clone(&self.revocations);
+
+        self.supervisor
+            .supervise(Subsystem::MessageHandler, move |heartbeat| {
+                let iroh = Arc::clone(&iroh);
+                let sync_protocol = Arc::clone(&sync_protocol);
+                let bandwidth = Arc::clone(&bandwidth);
+                let discovery = Arc::clone(&discovery);
+                let gossip = Arc::clone(&gossip);
+                let file_transfers = Arc::clone(&file_transfers);
+                let errors = Arc::clone(&errors);
+                let guest = guest.clone();
+                let willow = willow.clone();
+                let sync_policy = Arc::clone(&sync_policy);
+                let peer_access = Arc::clone(&peer_access);
+                let mailbox = Arc::clone(&mailbox);
+                let revocations = Arc::clone(&revocations);
+
+                async move {
+                    info!("Starting message handler");
+
+                    loop {
+                        match iroh.recv_message().await {
+                            Ok((peer_id, message)) => {
+                                debug!("Received message from peer {}", peer_id);
+
+                                // Waiting on download limits doesn't count as a stall
+                                bandwidth
+                                    .throttle(
+                                        TrafficDirection::Download,
+                                        &peer_id,
+                                        message.namespace(),
+                                        message.priority(),
+                                        message.encoded_len(),
+                                    )
+                                    .await;
+                                let _busy = heartbeat.busy();
+
+                                // Update peer last seen
+                                discovery.update_last_seen(&peer_id);
+
+                                // Handle message
+                                if let Err(e) = Self::handle_message(
+                                    &peer_id,
+                                    message,
+                                    &sync_protocol,
+                                    &iroh,
+                                    &bandwidth,
+                                    &gossip,
+                                    &file_transfers,
+                                    willow.as_ref(),
+                                    guest.as_ref(),
+                                    &sync_policy,
+                                    &peer_access,
+                                    &mailbox,
+                                    &revocations,
+                                )
+                                .await
+                                {
+                                    warn!("Failed to handle message from peer {}: {}", peer_id, e);
+                                    errors.record(format!("Message from peer {}: {}", peer_id, e));
+                                }
+                            }
+                            Err(e) => {
+                                warn!("Error receiving message: {}", e);
+                                errors.record(format!("Receive failed: {}", e));
+                                // Don't break on error, keep listening
+                            }
+                        }
+                    }
+                }
+            });
+    }
 
     /// Handle an incoming message.
+    #[allow(clippy::too_many_arguments)]
     async fn handle_message(
         peer_id: &PeerId,
         message: SyncMessage,
         sync_protocol: &Arc<SyncProtocol>,
         iroh: &Arc<IrohAdapter>,
         bandwidth: &Arc<BandwidthManager>,
+        gossip: &Arc<GossipOverlay>,
+        file_transfers: &Arc<FileTransferManager>,
+        willow: Option<&Arc<WillowAdapter>>,
+        guest: Option<&GuestIdentity>,
+        sync_policy: &Arc<RwLock<SyncPolicy>>,
+        peer_access: &Arc<PeerAccess>,
+        mailbox: &Arc<Mailbox>,
+        revocations: &Arc<RevocationRegistry>,
     ) -> Result<()> {
+        // Blocked peers are dropped, and unknown ones may only authenticate
+        // until they are allowed or paired
+        let decision = peer_decision(peer_access, sync_protocol, peer_id);
+        match decision {
+            AccessDecision::Blocked => {
+                let _ = iroh.disconnect(peer_id).await;
+                return decision.check(peer_id);
+            }
+            AccessDecision::PendingApproval => {
+                peer_access.request_pairing(peer_id);
+            }
+            AccessDecision::Allowed | AccessDecision::Denied => {}
+        }
+        let control = matches!(
+            message,
+            SyncMessage::Authenticate { .. }
+                | SyncMessage::Authenticated { .. }
+                | SyncMessage::Challenge(_)
+                | SyncMessage::ChallengeResponse(_)
+                | SyncMessage::Heartbeat
+                | SyncMessage::Error { .. }
+        );
+        if !control {
+            if let Err(e) = decision.check(peer_id) {
+                let reply = SyncMessage::Error {
+                    message: e.to_string(),
+                };
+                iroh.send_message(peer_id, &reply).await?;
+                return Err(e);
+            }
+        }
+
+        // Peers may only read and write documents their session covers, but
+        // any peer may sync the signed revocations
+        let access = match &message {
+            SyncMessage::SyncRequest { namespace, id, .. }
+            | SyncMessage::FullSync { namespace, id }
+            | SyncMessage::AutomergeSync { namespace, id, .. }
+            | SyncMessage::SyncChanges { namespace, id, .. }
+            | SyncMessage::FullDocument { namespace, id, .. }
+                if revocations::is_revocations_document(namespace, id) =>
+            {
+                None
+            }
+            SyncMessage::SyncRequest { namespace, id, .. }
+            | SyncMessage::FullSync { namespace, id }
+            | SyncMessage::AutomergeSync { namespace, id, .. } => Some((namespace, id, auth::READ)),
+            SyncMessage::SyncChanges { namespace, id, .. }
+            | SyncMessage::FullDocument { namespace, id, .. } => Some((namespace, id, auth::WRITE)),
+            SyncMessage::Delete(signed) => {
+                Some((&signed.deletion.namespace, &signed.deletion.id, auth::WRITE))
+            }
+            _ => None,
+        };
+        if let Some((namespace, id, action)) = access {
+            if let Err(e) = sync_protocol.authorize(peer_id, namespace, id, action) {
+                let reply = SyncMessage::Error {
+                    message: e.to_string(),
+                };
+                iroh.send_message(peer_id, &reply).await?;
+                return Err(e);
+            }
+        }
+        // Willow reconciliation covers whole namespaces
+        if let SyncMessage::WillowSync(message) = &message {
+            let actions = [
+                (message.reads(), auth::READ),
+                (message.writes(), auth::WRITE),
+            ];
+            for (_, action) in actions.into_iter().filter(|(needed, _)| *needed) {
+                let namespace = message.namespace();
+                if let Err(e) = sync_protocol.authorize(peer_id, namespace, "*", action) {
+                    let reply = SyncMessage::Error {
+                        message: e.to_string(),
+                    };
+                    iroh.send_message(peer_id, &reply).await?;
+                    return Err(e);
+                }
+            }
+        }
+
+        if let Some(guest) = guest {
+            match &message {
+                // Serving our copy would let peers pull guest changes
+                SyncMessage::SyncRequest { .. } | SyncMessage::FullSync { .. } => {
+                    let reply = SyncMessage::Error {
+                        message: "Guest nodes are read-only".to_string(),
+                    };
+                    return iroh.send_message(peer_id, &reply).await;
+                }
+                // Guests don't write, so their replies carry no changes
+                SyncMessage::SyncChanges { namespace, .. }
+                | SyncMessage::FullDocument { namespace, .. }
+                | SyncMessage::AutomergeSync { namespace, .. } => {
+                    guest_can_read(guest.policy(), guest.is_expired(), namespace)?;
+                }
+                SyncMessage::WillowSync(message) => {
+                    guest_can_read(guest.policy(), guest.is_expired(), message.namespace())?;
+                }
+                _ => {}
+            }
+        }
+
+        // Peers still syncing a deleted document are sent its deletion
+        let synced = match &message {
+            SyncMessage::SyncRequest { namespace, id, .. }
+            | SyncMessage::FullSync { namespace, id }
+            | SyncMessage::AutomergeSync { namespace, id, .. }
+            | SyncMessage::SyncChanges { namespace, id, .. }
+            | SyncMessage::FullDocument { namespace, id, .. } => Some((namespace, id)),
+            _ => None,
+        };
+        if let Some(signed) =
+            synced.and_then(|(namespace, id)| sync_protocol.deletion(namespace, id))
+        {
+            debug!(
+                "Peer {} synced deleted document {}/{}",
+                peer_id, signed.deletion.namespace, signed.deletion.id
+            );
+            return iroh
+                .send_message(peer_id, &SyncMessage::Delete(signed))
+                .await;
+        }
+
+        // Documents the policy skips aren't pulled, whoever starts the sync
+        let pulled = match &message {
+            SyncMessage::SyncChanges {
+                namespace,
+                id,
+                changes,
+            } => Some((namespace, id, changes.iter().map(|c| c.len()).sum())),
+            SyncMessage::FullDocument {
+                namespace,
+                id,
+                document,
+            } => Some((namespace, id, document.len())),
+            SyncMessage::AutomergeSync {
+                namespace,
+                id,
+                message,
+            } => Some((namespace, id, message.len())),
+            _ => None,
+        };
+        let pulled = pulled
+            .filter(|(namespace, id, _)| !revocations::is_revocations_document(namespace, id));
+        if let Some((namespace, id, size)) = pulled {
+            if let Err(reason) = check_sync_policy(sync_policy, gossip, namespace, id, Some(size)) {
+                debug!(
+                    "Sync policy skips {}/{} from peer {}: {}",
+                    namespace, id, peer_id, reason
+                );
+                return Ok(());
+            }
+        }
+
+        // Revocations merged from the peer are loaded and passed on
+        let merges_revocations = match &message {
+            SyncMessage::SyncChanges { namespace, id, .. }
+            | SyncMessage::FullDocument { namespace, id, .. }
+            | SyncMessage::AutomergeSync { namespace, id, .. } => {
+                revocations::is_revocations_document(namespace, id)
+            }
+            _ => false,
+        };
+
         match message {
             SyncMessage::SyncRequest {
                 namespace,
@@ -442,6 +1928,34 @@ impl VudoP2P {
                 iroh.send_message(peer_id, &response).await?;
             }
 
+            SyncMessage::FileOffer(offer) => {
+                if let Some(reply) = file_transfers.handle_offer(peer_id, offer).await? {
+                    iroh.send_message(peer_id, &reply).await?;
+                    if let SyncMessage::FileAccept { hash } = reply {
+                        Self::spawn_file_receiver(
+                            peer_id.clone(),
+                            hash,
+                            Arc::clone(iroh),
+                            Arc::clone(file_transfers),
+                        );
+                    }
+                }
+            }
+
+            SyncMessage::FileAccept { hash } => {
+                file_transfers.handle_accept(peer_id, &hash)?;
+            }
+
+            SyncMessage::FileReject { hash, reason } => {
+                iroh.withdraw_blob(peer_id, &hash);
+                file_transfers.handle_reject(peer_id, &hash, reason);
+            }
+
+            SyncMessage::FileComplete { hash } => {
+                iroh.withdraw_blob(peer_id, &hash);
+                file_transfers.handle_complete(peer_id, &hash);
+            }
+
             SyncMessage::Heartbeat => {
                 debug!("Received heartbeat from peer {}", peer_id);
             }
@@ -449,13 +1963,762 @@ impl VudoP2P {
             SyncMessage::Error { message } => {
                 warn!("Received error from peer {}: {}", peer_id, message);
             }
+
+            SyncMessage::Gossip(message) => {
+                Self::handle_gossip(peer_id, message, sync_protocol, iroh, gossip, sync_policy)
+                    .await?;
+            }
+
+            SyncMessage::Authenticate { token } => {
+                let local = iroh.node_id().to_string();
+                match sync_protocol.authenticate(peer_id, &local, &token) {
+                    Ok(reply) => iroh.send_message(peer_id, &reply).await?,
+                    Err(e) => {
+                        let reply = SyncMessage::Error {
+                            message: e.to_string(),
+                        };
+                        iroh.send_message(peer_id, &reply).await?;
+                        return Err(e);
+                    }
+                }
+            }
+
+            SyncMessage::Authenticated { did } => {
+                info!("Authenticated to peer {} as {}", peer_id, did);
+            }
+
+            SyncMessage::Challenge(challenge) => {
+                let local = iroh.node_id().to_string();
+                match sync_protocol.answer_challenge(peer_id, &local, &challenge) {
+                    Ok(reply) => iroh.send_message(peer_id, &reply).await?,
+                    Err(e) => {
+                        let reply = SyncMessage::Error {
+                            message: e.to_string(),
+                        };
+                        iroh.send_message(peer_id, &reply).await?;
+                        return Err(e);
+                    }
+                }
+            }
+
+            SyncMessage::ChallengeResponse(response) => {
+                sync_protocol.verify_challenge(peer_id, &response)?;
+            }
+
+            SyncMessage::Hello(_) => {
+                // Negotiated by the Iroh adapter
+            }
+
+            SyncMessage::AutomergeSync {
+                namespace,
+                id,
+                message,
+            } => {
+                bandwidth.record_received(message.len());
+
+                if let Some(reply) = sync_protocol
+                    .receive_sync(peer_id, namespace, id, message)
+                    .await?
+                {
+                    iroh.send_message(peer_id, &reply).await?;
+                }
+            }
+
+            SyncMessage::WillowSync(message) => {
+                let willow = willow.ok_or_else(|| {
+                    P2PError::WillowError("Willow sync is not enabled".to_string())
+                })?;
+                let sync = WillowSync::new(
+                    Arc::clone(willow),
+                    iroh.node_id().to_string(),
+                    peer_id.clone(),
+                );
+                let replies = sync.receive(*message)?;
+                for reply in replies {
+                    iroh.send_message(peer_id, &SyncMessage::WillowSync(Box::new(reply)))
+                        .await?;
+                }
+            }
+
+            SyncMessage::MailDeposit(mail) => {
+                bandwidth.record_received(mail.size());
+                if let Err(e) = mailbox.deposit(mail) {
+                    let reply = SyncMessage::Error {
+                        message: e.to_string(),
+                    };
+                    iroh.send_message(peer_id, &reply).await?;
+                    return Err(e);
+                }
+            }
+
+            SyncMessage::MailFetch(fetch) => {
+                let local = iroh.node_id().to_string();
+                let mail = match mailbox.take(&fetch, &local) {
+                    Ok(mail) => mail,
+                    Err(e) => {
+                        let reply = SyncMessage::Error {
+                            message: e.to_string(),
+                        };
+                        iroh.send_message(peer_id, &reply).await?;
+                        return Err(e);
+                    }
+                };
+                let delivery = SyncMessage::MailDelivery(mail.clone());
+                if let Err(e) = iroh.send_message(peer_id, &delivery).await {
+                    // Keep the mail for the next fetch
+                    for mail in mail {
+                        let _ = mailbox.deposit(mail);
+                    }
+                    return Err(e);
+                }
+            }
+
+            SyncMessage::MailDelivery(mail) => {
+                bandwidth.record_received(mail.iter().map(SealedMail::size).sum());
+                let (opened, errors) = mailbox.open(mail);
+                for e in errors {
+                    warn!("Dropped mail from mailbox {}: {}", peer_id, e);
+                }
+                for (sender, payload) in opened {
+                    let (namespace, id) = (payload.namespace, payload.id);
+                    if let Some(guest) = guest {
+                        let expired = guest.is_expired();
+                        if let Err(e) = guest_can_read(guest.policy(), expired, &namespace) {
+                            warn!("Dropped mail for {}/{}: {}", namespace, id, e);
+                            continue;
+                        }
+                    }
+                    let size = payload.changes.iter().map(|c| c.len()).sum();
+                    if let Err(reason) =
+                        check_sync_policy(sync_policy, gossip, &namespace, &id, Some(size))
+                    {
+                        debug!(
+                            "Sync policy skips mail for {}/{} from {}: {}",
+                            namespace, id, sender, reason
+                        );
+                        continue;
+                    }
+                    info!("Applying mail for {}/{} from {}", namespace, id, sender);
+                    let doc = format!("{}/{}", namespace, id);
+                    if let Err(e) = sync_protocol
+                        .apply_sync_changes(&sender, namespace, id, payload.changes)
+                        .await
+                    {
+                        warn!("Failed to apply mail for {} from {}: {}", doc, sender, e);
+                    }
+                }
+            }
+
+            SyncMessage::Delete(signed) => {
+                if sync_protocol.apply_deletion(signed.clone()).await? {
+                    info!(
+                        "Peer {} deleted {}/{}",
+                        peer_id, signed.deletion.namespace, signed.deletion.id
+                    );
+                    Self::broadcast_deletion(iroh, signed, Some(peer_id)).await;
+                }
+            }
+        }
+
+        if merges_revocations {
+            match revocations.load().await {
+                Ok(0) => {}
+                Ok(count) => {
+                    info!("Learned {} revocations from peer {}", count, peer_id);
+                    Self::spread_revocations(iroh, sync_protocol, peer_access, Some(peer_id)).await;
+                }
+                Err(e) => warn!("Failed to load revocations from peer {}: {}", peer_id, e),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Announce this node's heartbeats, follow peers' heartbeats and turn
+    /// silent peers idle or offline.
+    ///
+    /// Connected peers are challenged to prove they hold the DID their
+    /// heartbeats name, when they connect or start naming it.
+    async fn run_presence(
+        iroh: Arc<IrohAdapter>,
+        sync_protocol: Arc<SyncProtocol>,
+        gossip: Arc<GossipOverlay>,
+        presence: Arc<PresenceTracker>,
+        node_id: PeerId,
+        heartbeat: Heartbeat,
+    ) {
+        let mut sub = match gossip.subscribe_presence().await {
+            Ok(sub) => sub,
+            Err(e) => {
+                warn!("Failed to subscribe to presence: {}", e);
+                return;
+            }
+        };
+        let mut events = iroh.subscribe_connections();
+        let mut ticker = tokio::time::interval(presence.config().heartbeat_interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                message = sub.recv() => {
+                    let Some(message) = message else {
+                        break;
+                    };
+                    let _busy = heartbeat.busy();
+                    presence.observe(&message);
+                    if let GossipMessage::Heartbeat { peer_id, did: Some(did), .. } = &message {
+                        if iroh.connected_peers().contains(peer_id) {
+                            Self::challenge_announced(&iroh, &sync_protocol, &node_id, peer_id, did)
+                                .await;
+                        }
+                    }
+                }
+                event = events.recv() => {
+                    let Some(event) = event else {
+                        break;
+                    };
+                    let ConnectionEvent::Connected { peer_id, .. } = event else {
+                        continue;
+                    };
+                    let _busy = heartbeat.busy();
+                    let announced = presence
+                        .peers()
+                        .into_iter()
+                        .find(|p| p.peer_id == peer_id && p.id != peer_id);
+                    if let Some(announced) = announced {
+                        Self::challenge_announced(
+                            &iroh,
+                            &sync_protocol,
+                            &node_id,
+                            &peer_id,
+                            &announced.id,
+                        )
+                        .await;
+                    }
+                }
+                _ = ticker.tick() => {
+                    let _busy = heartbeat.busy();
+                    presence.expire();
+                    if let Err(e) = presence.announce(&gossip, node_id.clone()).await {
+                        warn!("Failed to announce heartbeat: {}", e);
+                    }
+                }
+            }
+        }
+        let _ = gossip.unsubscribe(sub.id()).await;
+    }
+
+    /// Challenge a connected peer for the DID it announces, unless it
+    /// proved it already or a challenge is awaiting an answer.
+    async fn challenge_announced(
+        iroh: &Arc<IrohAdapter>,
+        sync_protocol: &Arc<SyncProtocol>,
+        node_id: &PeerId,
+        peer_id: &PeerId,
+        did: &str,
+    ) {
+        if !sync_protocol.needs_challenge(peer_id, did) {
+            return;
+        }
+        let did = match Did::parse(did) {
+            Ok(did) => did,
+            Err(e) => {
+                debug!("Peer {} announces an invalid DID: {}", peer_id, e);
+                return;
+            }
+        };
+        let message = sync_protocol.challenge(peer_id, node_id, did);
+        if let Err(e) = iroh.send_message(peer_id, &message).await {
+            warn!("Failed to challenge peer {}: {}", peer_id, e);
+        }
+    }
+
+    /// Periodically sync the documents of replication groups with the
+    /// connected members this node links to.
+    ///
+    /// Documents a member already holds the current version of are skipped.
+    async fn run_replication(
+        iroh: Arc<IrohAdapter>,
+        sync_protocol: Arc<SyncProtocol>,
+        state_engine: Arc<StateEngine>,
+        peer_access: Arc<PeerAccess>,
+        replication: Arc<ReplicationTracker>,
+        interval: Duration,
+        heartbeat: Heartbeat,
+    ) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            let _busy = heartbeat.busy();
+            for peer_id in iroh.connected_peers() {
+                let Some(auth) = sync_protocol.peer_auth(&peer_id) else {
+                    continue;
+                };
+                replication.observe(&peer_id, auth.did.as_str());
+                if peer_decision(&peer_access, &sync_protocol, &peer_id) != AccessDecision::Allowed
+                {
+                    continue;
+                }
+
+                for namespace in replication.namespaces_for(&peer_id) {
+                    let documents = match state_engine.list_documents(&namespace).await {
+                        Ok(documents) => documents,
+                        Err(e) => {
+                            warn!("Failed to list namespace {}: {}", namespace, e);
+                            continue;
+                        }
+                    };
+                    for metadata in documents {
+                        let (namespace, id) = (&metadata.id.namespace, &metadata.id.key);
+                        let progress = sync_protocol.progress(&peer_id, namespace, id);
+                        let current =
+                            progress.is_some_and(|p| crate::replication::is_current(&p, &metadata));
+                        if current || sync_protocol.is_deleted(namespace, id) {
+                            continue;
+                        }
+                        heartbeat.beat();
+                        let sent = match sync_protocol.start_sync(&peer_id, namespace, id).await {
+                            Ok(message) => iroh.send_message(&peer_id, &message).await,
+                            Err(e) => Err(e),
+                        };
+                        if let Err(e) = sent {
+                            warn!(
+                                "Failed to replicate {}/{} to peer {}: {}",
+                                namespace, id, peer_id, e
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Disconnect blocked peers as they connect, and ask the user to pair
+    /// with unknown ones.
+    async fn watch_connections(
+        iroh: Arc<IrohAdapter>,
+        peer_access: Arc<PeerAccess>,
+        heartbeat: Heartbeat,
+    ) {
+        let mut events = iroh.subscribe_connections();
+        while let Some(event) = events.recv().await {
+            let ConnectionEvent::Connected { peer_id, .. } = event else {
+                continue;
+            };
+            let _busy = heartbeat.busy();
+            match peer_access.decide(&peer_id, None) {
+                AccessDecision::Blocked => {
+                    info!("Disconnecting blocked peer {}", peer_id);
+                    let _ = iroh.disconnect(&peer_id).await;
+                }
+                AccessDecision::PendingApproval => {
+                    let request = peer_access.request_pairing(&peer_id);
+                    info!("Peer {} asks to pair (code {})", peer_id, request.code);
+                }
+                AccessDecision::Allowed | AccessDecision::Denied => {}
+            }
+        }
+    }
+
+    /// Fetch mail from designated mailboxes as they connect and while
+    /// connected, and drop held mail once it expires.
+    async fn run_mailbox(
+        iroh: Arc<IrohAdapter>,
+        mailbox: Arc<Mailbox>,
+        interval: Duration,
+        heartbeat: Heartbeat,
+    ) {
+        let mut events = iroh.subscribe_connections();
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                event = events.recv() => {
+                    let Some(event) = event else {
+                        break;
+                    };
+                    let ConnectionEvent::Connected { peer_id, .. } = event else {
+                        continue;
+                    };
+                    let _busy = heartbeat.busy();
+                    Self::request_mail(&iroh, &mailbox, &peer_id).await;
+                }
+                _ = ticker.tick() => {
+                    let _busy = heartbeat.busy();
+                    let expired = mailbox.prune_expired();
+                    if expired > 0 {
+                        debug!("Dropped {} expired mail", expired);
+                    }
+                    for peer_id in iroh.connected_peers() {
+                        Self::request_mail(&iroh, &mailbox, &peer_id).await;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sync revocations with peers as they connect and while connected, and
+    /// drop those of UCANs that have expired.
+    async fn run_revocations(
+        iroh: Arc<IrohAdapter>,
+        sync_protocol: Arc<SyncProtocol>,
+        peer_access: Arc<PeerAccess>,
+        revocations: Arc<RevocationRegistry>,
+        interval: Duration,
+        heartbeat: Heartbeat,
+    ) {
+        let mut events = iroh.subscribe_connections();
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                event = events.recv() => {
+                    let Some(event) = event else {
+                        break;
+                    };
+                    let ConnectionEvent::Connected { peer_id, .. } = event else {
+                        continue;
+                    };
+                    let _busy = heartbeat.busy();
+                    Self::sync_revocations(&iroh, &sync_protocol, &peer_access, &peer_id).await;
+                }
+                _ = ticker.tick() => {
+                    let _busy = heartbeat.busy();
+                    match revocations.prune_expired().await {
+                        Ok(0) => {}
+                        Ok(pruned) => debug!("Pruned {} revocations of expired UCANs", pruned),
+                        Err(e) => warn!("Failed to prune revocations: {}", e),
+                    }
+                    Self::spread_revocations(&iroh, &sync_protocol, &peer_access, None).await;
+                }
+            }
+        }
+    }
+
+    /// Sync revocations with every connected peer but `from`.
+    async fn spread_revocations(
+        iroh: &Arc<IrohAdapter>,
+        sync_protocol: &Arc<SyncProtocol>,
+        peer_access: &Arc<PeerAccess>,
+        from: Option<&PeerId>,
+    ) {
+        for peer_id in iroh.connected_peers() {
+            if Some(&peer_id) == from {
+                continue;
+            }
+            Self::sync_revocations(iroh, sync_protocol, peer_access, &peer_id).await;
+        }
+    }
+
+    /// Start a sync of the revocations document with an allowed peer.
+    async fn sync_revocations(
+        iroh: &Arc<IrohAdapter>,
+        sync_protocol: &Arc<SyncProtocol>,
+        peer_access: &Arc<PeerAccess>,
+        peer_id: &PeerId,
+    ) {
+        if peer_decision(peer_access, sync_protocol, peer_id) != AccessDecision::Allowed {
+            return;
+        }
+        let sent = match sync_protocol
+            .start_sync(peer_id, REVOCATIONS_NAMESPACE, REVOCATIONS_KEY)
+            .await
+        {
+            Ok(message) => iroh.send_message(peer_id, &message).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = sent {
+            warn!("Failed to sync revocations with peer {}: {}", peer_id, e);
+        }
+    }
+
+    /// Ask a peer for this node's mail, if it is a designated mailbox.
+    async fn request_mail(iroh: &Arc<IrohAdapter>, mailbox: &Arc<Mailbox>, peer_id: &PeerId) {
+        let fetch = match mailbox.fetch_from(peer_id) {
+            Ok(Some(fetch)) => fetch,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("Failed to sign mail fetch: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = iroh
+            .send_message(peer_id, &SyncMessage::MailFetch(fetch))
+            .await
+        {
+            warn!("Failed to fetch mail from peer {}: {}", peer_id, e);
+        }
+    }
+
+    /// Send a deletion to every connected peer but the one it came from.
+    async fn broadcast_deletion(
+        iroh: &Arc<IrohAdapter>,
+        signed: SignedDeletion,
+        from: Option<&PeerId>,
+    ) {
+        let message = SyncMessage::Delete(signed);
+        for peer in iroh.connected_peers() {
+            if Some(&peer) == from {
+                continue;
+            }
+            if let Err(e) = iroh.send_message(&peer, &message).await {
+                warn!("Failed to send deletion to peer {}: {}", peer, e);
+            }
+        }
+    }
+
+    /// Handle a gossip message sent directly by a peer.
+    ///
+    /// Peers only send their own announcements directly, so messages signed
+    /// by anyone else are rejected.
+    async fn handle_gossip(
+        peer_id: &PeerId,
+        signed: SignedGossipMessage,
+        sync_protocol: &Arc<SyncProtocol>,
+        iroh: &Arc<IrohAdapter>,
+        gossip: &Arc<GossipOverlay>,
+        sync_policy: &Arc<RwLock<SyncPolicy>>,
+    ) -> Result<()> {
+        if signed.message.peer_id() != peer_id {
+            return Err(P2PError::PermissionDenied(format!(
+                "Peer {} sent gossip from {}",
+                peer_id,
+                signed.message.peer_id()
+            )));
+        }
+        let topic = signed.message.topic();
+        Self::receive_gossip(&topic, signed, sync_protocol, iroh, gossip, sync_policy).await
+    }
+
+    /// Deliver a signed gossip message received on a topic.
+    ///
+    /// The message is delivered to local subscribers once its signature is
+    /// verified. Updates of documents this node subscribes to are synced
+    /// from the announcing peer when connected to it, unless the peer
+    /// already announced that version or the sync policy skips the document.
+    async fn receive_gossip(
+        topic: &Topic,
+        signed: SignedGossipMessage,
+        sync_protocol: &Arc<SyncProtocol>,
+        iroh: &Arc<IrohAdapter>,
+        gossip: &Arc<GossipOverlay>,
+        sync_policy: &Arc<RwLock<SyncPolicy>>,
+    ) -> Result<()> {
+        let message = gossip.receive(topic, signed).await?;
+        let update = match &message {
+            GossipMessage::DocumentAnnouncement {
+                namespace,
+                id,
+                version,
+                ..
+            }
+            | GossipMessage::DocumentUpdate {
+                namespace,
+                id,
+                version,
+                ..
+            } => Some((namespace, id, *version)),
+            _ => None,
+        };
+
+        // The signature identifies the sender
+        let peer_id = message.peer_id();
+        if let Some((namespace, id, version)) = update {
+            if gossip.is_subscribed(topic)
+                && iroh.connected_peers().contains(peer_id)
+                && !sync_protocol.is_deleted(namespace, id)
+                && check_sync_policy(sync_policy, gossip, namespace, id, None).is_ok()
+                && sync_protocol.track_announcement(peer_id, namespace, id, version)
+            {
+                debug!(
+                    "Peer {} announced {}/{} version {}, requesting sync",
+                    peer_id, namespace, id, version
+                );
+                let message = sync_protocol.start_sync(peer_id, namespace, id).await?;
+                iroh.send_message(peer_id, &message).await?;
+            }
         }
 
         Ok(())
     }
+
+    /// Bridge a joined topic between the gossip overlay and its swarm.
+    ///
+    /// Announcements the overlay signs are broadcast to the swarm, and
+    /// messages from the swarm are delivered like those sent directly. The
+    /// task ends, leaving the swarm, once the overlay leaves the topic or
+    /// the swarm fails.
+    fn spawn_gossip_topic(
+        topic: Topic,
+        swarm: GossipTopic,
+        mut outgoing: mpsc::UnboundedReceiver<SignedGossipMessage>,
+        sync_protocol: Arc<SyncProtocol>,
+        iroh: Arc<IrohAdapter>,
+        gossip: Arc<GossipOverlay>,
+        sync_policy: Arc<RwLock<SyncPolicy>>,
+    ) {
+        let (sender, mut receiver) = swarm.split();
+
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    message = outgoing.recv() => {
+                        let Some(message) = message else {
+                            break;
+                        };
+                        let result = match message.to_bytes() {
+                            Ok(bytes) => sender
+                                .broadcast(bytes.into())
+                                .await
+                                .map_err(P2PError::IrohError),
+                            Err(e) => Err(e),
+                        };
+                        if let Err(e) = result {
+                            warn!("Failed to broadcast to topic {}: {}", topic.as_str(), e);
+                        }
+                    }
+                    event = receiver.next() => match event {
+                        Some(Ok(Event::Gossip(GossipEvent::Received(message)))) => {
+                            let result = match SignedGossipMessage::from_bytes(&message.content) {
+                                Ok(signed) => {
+                                    Self::receive_gossip(
+                                        &topic,
+                                        signed,
+                                        &sync_protocol,
+                                        &iroh,
+                                        &gossip,
+                                        &sync_policy,
+                                    )
+                                    .await
+                                }
+                                Err(e) => Err(e),
+                            };
+                            if let Err(e) = result {
+                                warn!(
+                                    "Rejected gossip on topic {} delivered by {}: {}",
+                                    topic.as_str(),
+                                    message.delivered_from,
+                                    e
+                                );
+                            }
+                        }
+                        Some(Ok(Event::Gossip(GossipEvent::NeighborUp(node_id)))) => {
+                            gossip.add_peer_interest(&node_id.to_string(), topic.clone());
+                        }
+                        Some(Ok(Event::Gossip(GossipEvent::NeighborDown(node_id)))) => {
+                            gossip.remove_peer_interest(&node_id.to_string(), &topic);
+                        }
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => {
+                            warn!("Gossip swarm of topic {} failed: {}", topic.as_str(), e);
+                            break;
+                        }
+                        None => break,
+                    },
+                }
+            }
+
+            // Dropping the queue marks the topic as no longer joined
+            debug!("Left gossip swarm of topic {}", topic.as_str());
+        });
+    }
+
+    /// Fetch the file of an accepted transfer from the offering peer.
+    fn spawn_file_receiver(
+        peer_id: PeerId,
+        hash: TransferId,
+        iroh: Arc<IrohAdapter>,
+        file_transfers: Arc<FileTransferManager>,
+    ) {
+        tokio::spawn(async move {
+            let result = async {
+                let size = file_transfers.accepted_offer(&peer_id, &hash)?.size;
+                let target = file_transfers.download_path(&peer_id, &hash)?;
+                let progress = {
+                    let (peer_id, hash) = (peer_id.clone(), hash.clone());
+                    let file_transfers = Arc::clone(&file_transfers);
+                    move |received| file_transfers.record_received(&peer_id, &hash, received)
+                };
+                if let Err(e) = iroh
+                    .fetch_blob(&peer_id, &hash, size, &target, progress)
+                    .await
+                {
+                    // Drop the placeholder reserved for the download
+                    let _ = tokio::fs::remove_file(&target).await;
+                    return Err(e);
+                }
+                file_transfers.complete_incoming(&peer_id, &hash, target)
+            }
+            .await;
+
+            match result {
+                Ok(complete) => {
+                    if let Err(e) = iroh.send_message(&peer_id, &complete).await {
+                        warn!(
+                            "Failed to confirm transfer {} to peer {}: {}",
+                            hash, peer_id, e
+                        );
+                    }
+                }
+                Err(e) => {
+                    warn!("File transfer {} from peer {} failed: {}", hash, peer_id, e);
+                    file_transfers.fail_incoming(&peer_id, &hash, e.to_string());
+                }
+            }
+        });
+    }
+}
+
+/// Check a guest's access to a namespace.
+#[cfg(not(target_arch = "wasm32"))]
+fn guest_can_read(policy: &GuestPolicy, expired: bool, namespace: &str) -> Result<()> {
+    if expired {
+        return Err(P2PError::PermissionDenied(
+            "Guest credential has expired".to_string(),
+        ));
+    }
+    if !policy.can_read(namespace) {
+        return Err(P2PError::PermissionDenied(format!(
+            "Guests may not read namespace {}",
+            namespace
+        )));
+    }
+    Ok(())
+}
+
+/// Decide whether a peer may sync, by node ID and authenticated DID.
+#[cfg(not(target_arch = "wasm32"))]
+fn peer_decision(
+    peer_access: &PeerAccess,
+    sync_protocol: &SyncProtocol,
+    peer_id: &PeerId,
+) -> AccessDecision {
+    let did = sync_protocol
+        .peer_auth(peer_id)
+        .map(|auth| auth.did.as_str().to_string());
+    peer_access.decide(peer_id, did.as_deref())
+}
+
+/// Check whether the sync policy lets a document be pulled from peers.
+#[cfg(not(target_arch = "wasm32"))]
+fn check_sync_policy(
+    policy: &RwLock<SyncPolicy>,
+    gossip: &GossipOverlay,
+    namespace: &str,
+    id: &str,
+    size: Option<usize>,
+) -> std::result::Result<(), SkipReason> {
+    let subscribed = gossip.is_subscribed(&Topic::document(namespace, id));
+    policy
+        .read()
+        .check(namespace, id, subscribed, size.map(|size| size as u64))
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(target_arch = "wasm32")))]
 mod tests {
     use super::*;
 
@@ -490,6 +2753,61 @@ mod tests {
         p2p.stop().await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_recording() {
+        let state_engine = Arc::new(StateEngine::new().await.unwrap());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.vrec");
+
+        let p2p = VudoP2P::new(state_engine, P2PConfig::default())
+            .await
+            .unwrap();
+        assert!(p2p.stop_recording().is_none());
+
+        p2p.start_recording(&path).unwrap();
+        assert_eq!(p2p.stop_recording(), Some(path.clone()));
+
+        let recording = Recording::load(&path).unwrap();
+        assert_eq!(recording.header.node_id, p2p.node_id());
+        assert!(recording.messages.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_guest_mode() {
+        let state_engine = Arc::new(StateEngine::new().await.unwrap());
+        let config = P2PConfig {
+            guest: Some(
+                GuestPolicy::new(600)
+                    .read_namespace("demo")
+                    .max_bandwidth(16 * 1024),
+            ),
+            ..Default::default()
+        };
+
+        let p2p = VudoP2P::new(state_engine, config).await.unwrap();
+        let guest = p2p.guest_identity().unwrap();
+        assert!(guest.authorization().verify().is_ok());
+        assert_eq!(p2p.bandwidth_stats().rate_limit, 16 * 1024);
+
+        assert!(p2p.subscribe_document("demo", "welcome").await.is_ok());
+        assert!(matches!(
+            p2p.subscribe_document("private", "notes").await,
+            Err(P2PError::PermissionDenied(_))
+        ));
+        assert!(matches!(
+            p2p.announce_update("demo", "welcome", 2).await,
+            Err(P2PError::PermissionDenied(_))
+        ));
+
+        let host = VudoP2P::new(
+            Arc::new(StateEngine::new().await.unwrap()),
+            P2PConfig::default(),
+        )
+        .await
+        .unwrap();
+        assert!(host.guest_identity().is_none());
+    }
+
     #[tokio::test]
     async fn test_node_addr() {
         let state_engine = Arc::new(StateEngine::new().await.unwrap());
@@ -500,4 +2818,321 @@ mod tests {
         let addr = p2p.node_addr().await.unwrap();
         assert!(!addr.node_id.to_string().is_empty());
     }
+
+    #[tokio::test]
+    async fn test_gossip_topics() {
+        let state_engine = Arc::new(StateEngine::new().await.unwrap());
+        let config = P2PConfig::default();
+        let p2p = VudoP2P::new(state_engine, config).await.unwrap();
+
+        let topic = Topic::presence();
+        let invalid = vec!["not a node".to_string()];
+        assert!(p2p.join_topic(topic.clone(), invalid).await.is_err());
+
+        p2p.join_topic(topic.clone(), vec![]).await.unwrap();
+        assert_eq!(p2p.joined_topics(), vec![topic.clone()]);
+
+        // Presence is signed with the node key
+        let mut sub = p2p.gossip.subscribe_presence().await.unwrap();
+        p2p.announce_presence(vec![]).await.unwrap();
+        assert_eq!(sub.recv().await.unwrap().peer_id(), &p2p.node_id());
+        let update = GossipMessage::document_update(p2p.node_id(), "users", "alice", 1);
+        let signed = p2p.gossip.sign(update).unwrap();
+        signed.verify().unwrap();
+
+        assert!(p2p.leave_topic(&topic));
+        assert!(p2p.joined_topics().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_app_topics() {
+        let state_engine = Arc::new(StateEngine::new().await.unwrap());
+        let p2p = VudoP2P::new(state_engine, P2PConfig::default())
+            .await
+            .unwrap();
+        let topic = Topic::app("cursors");
+
+        let mut sub = p2p.subscribe(topic.clone()).await.unwrap();
+        p2p.publish(topic.clone(), b"10,20".to_vec()).await.unwrap();
+        let message = sub.recv().await.unwrap();
+        assert_eq!(message.peer_id, p2p.node_id());
+        assert_eq!(message.payload, b"10,20");
+
+        // Peers restricting the topic accept payloads published as a device
+        let device = DeviceIdentity::generate("Laptop").await.unwrap();
+        let peer = GossipOverlay::new();
+        let policy = SyncAuthPolicy::new().trust(device.did().clone());
+        peer.restrict_publishers(topic.clone(), policy);
+
+        let mut raw = p2p.gossip.subscribe(topic.clone()).await.unwrap();
+        p2p.publish(topic.clone(), b"11,20".to_vec()).await.unwrap();
+        let signed = p2p.gossip.sign(raw.recv().await.unwrap()).unwrap();
+        assert!(peer.receive(&topic, signed).await.is_err());
+
+        p2p.authorize_publish(topic.clone(), &device).unwrap();
+        p2p.publish(topic.clone(), b"12,20".to_vec()).await.unwrap();
+        let signed = p2p.gossip.sign(raw.recv().await.unwrap()).unwrap();
+        peer.receive(&topic, signed).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_replication_groups() {
+        let state_engine = Arc::new(StateEngine::new().await.unwrap());
+        state_engine
+            .create_document(vudo_state::DocumentId::new("notes", "todo"))
+            .await
+            .unwrap();
+        let p2p = VudoP2P::new(state_engine, P2PConfig::default())
+            .await
+            .unwrap();
+        let group = ReplicationGroup::new("family", "notes")
+            .member("did:key:laptop")
+            .member("did:key:phone");
+        p2p.join_replication_group(group, "did:key:laptop").unwrap();
+
+        // Only this node holds the document so far
+        let status = p2p.replication_status("family").await.unwrap();
+        assert_eq!(status.len(), 1);
+        assert_eq!(status[0].replicas, vec!["did:key:laptop".to_string()]);
+        assert_eq!(p2p.under_replicated().await.unwrap(), status);
+
+        assert!(p2p.leave_replication_group("family"));
+        assert!(p2p.replication_status("family").await.is_err());
+        assert!(p2p.under_replicated().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_mail() {
+        let state_engine = Arc::new(StateEngine::new().await.unwrap());
+        let config = P2PConfig {
+            mailbox: Some(MailboxConfig::default()),
+            ..P2PConfig::default()
+        };
+        let p2p = VudoP2P::new(state_engine, config).await.unwrap();
+        assert!(p2p.mailbox().is_hosting());
+
+        let device = DeviceIdentity::generate("Laptop").await.unwrap();
+        let recipient = DeviceIdentity::generate("Phone").await.unwrap();
+        let mailbox = "mailbox-node".to_string();
+        let missing = p2p
+            .send_mail(&mailbox, recipient.did(), "notes", "todo", &device)
+            .await;
+        assert!(matches!(missing, Err(P2PError::DocumentNotFound(_))));
+
+        // Mail is only fetched from designated mailboxes
+        assert!(matches!(
+            p2p.fetch_mail(&mailbox).await,
+            Err(P2PError::PermissionDenied(_))
+        ));
+        p2p.receive_mail(MailSettings::new(recipient).mailbox(mailbox.clone()));
+        assert!(p2p.mailbox().fetch_from(&mailbox).unwrap().is_some());
+        p2p.stop_receiving_mail();
+        assert!(p2p.mailbox().fetch_from(&mailbox).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_revoke_ucan() {
+        let state_engine = Arc::new(StateEngine::new().await.unwrap());
+        let p2p = VudoP2P::new(state_engine, P2PConfig::default())
+            .await
+            .unwrap();
+
+        let device = DeviceIdentity::generate("Laptop").await.unwrap();
+        let app = DeviceIdentity::generate("Notes").await.unwrap();
+        let ucan = vudo_identity::Ucan::new(
+            device.did().clone(),
+            app.did().clone(),
+            vec![vudo_identity::Capability::wildcard("vudo://notes/")],
+            chrono::Utc::now().timestamp() as u64 + 3600,
+            None,
+            None,
+            vec![],
+        )
+        .sign(&device.signing_key())
+        .unwrap();
+
+        // Only issuers in the UCAN's chain may revoke it
+        assert!(p2p.revoke_ucan(&ucan, &app, None).await.is_err());
+        p2p.revocations().verify(&ucan).unwrap();
+
+        p2p.revoke_ucan(&ucan, &device, Some("Uninstalled".to_string()))
+            .await
+            .unwrap();
+        assert!(p2p.revocations().verify(&ucan).is_err());
+        assert_eq!(p2p.revocations.load().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_unlink_device() {
+        let state_engine = Arc::new(StateEngine::new().await.unwrap());
+        let p2p = VudoP2P::new(state_engine, P2PConfig::default())
+            .await
+            .unwrap();
+
+        let mut master = MasterIdentity::generate("Alice").await.unwrap();
+        let phone = DeviceIdentity::generate("Phone").await.unwrap();
+        let master_key = master.signing_key();
+        let link = master
+            .link_device("Phone".to_string(), phone.did().clone(), &master_key)
+            .await
+            .unwrap();
+
+        let unlink = p2p
+            .unlink_device(&mut master, phone.did(), Some("Stolen".to_string()))
+            .await
+            .unwrap();
+        assert!(p2p.revocations().verify(&link.authorization).is_err());
+        assert_eq!(p2p.revocations.wipes_for(phone.did()).len(), 1);
+        assert!(unlink.wipe.verify().is_ok());
+        assert_eq!(p2p.revocations.load().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sync_policy() {
+        let state_engine = Arc::new(StateEngine::new().await.unwrap());
+        let config = P2PConfig {
+            sync_policy: SyncPolicy::sync_subscribed_only(),
+            ..Default::default()
+        };
+        let p2p = VudoP2P::new(state_engine, config).await.unwrap();
+        assert_eq!(p2p.sync_policy().scope(), SyncScope::SubscribedOnly);
+
+        let peer = "peer1".to_string();
+        let skipped = check_sync_policy(&p2p.sync_policy, &p2p.gossip, "users", "alice", None);
+        assert_eq!(skipped, Err(SkipReason::NotSubscribed));
+        assert!(p2p.sync_document(&peer, "users", "alice").await.is_err());
+
+        let _sub = p2p.subscribe_document("users", "alice").await.unwrap();
+        assert!(check_sync_policy(&p2p.sync_policy, &p2p.gossip, "users", "alice", None).is_ok());
+
+        p2p.set_sync_policy(SyncPolicy::sync_all().exclude("media"));
+        assert!(check_sync_policy(&p2p.sync_policy, &p2p.gossip, "users", "bob", None).is_ok());
+        let skipped = check_sync_policy(&p2p.sync_policy, &p2p.gossip, "media", "photo1", None);
+        assert_eq!(skipped, Err(SkipReason::Excluded("media".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_peer_access() {
+        let state_engine = Arc::new(StateEngine::new().await.unwrap());
+        let config = P2PConfig {
+            peer_access: PeerAccessConfig {
+                mode: AccessMode::Pairing,
+                blocked: vec!["mallory".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let p2p = VudoP2P::new(Arc::clone(&state_engine), config)
+            .await
+            .unwrap();
+        let mut events = p2p.subscribe_pairing();
+
+        let mallory = "mallory".to_string();
+        assert_eq!(p2p.peer_decision(&mallory), AccessDecision::Blocked);
+        assert!(p2p.sync_document(&mallory, "users", "alice").await.is_err());
+
+        // Unknown peers wait for approval
+        let peer = "peer1".to_string();
+        assert!(p2p.sync_document(&peer, "users", "alice").await.is_err());
+        let Some(PairingEvent::Requested(request)) = events.recv().await else {
+            panic!("expected a pairing request");
+        };
+        assert_eq!(request.peer_id, peer);
+        assert_eq!(request.code, pairing_code(&p2p.node_id(), &peer));
+
+        p2p.approve_pairing(&peer).await.unwrap();
+        assert_eq!(p2p.peer_decision(&peer), AccessDecision::Allowed);
+
+        p2p.block_peer(&peer).await.unwrap();
+        assert_eq!(p2p.peer_decision(&peer), AccessDecision::Blocked);
+
+        // The lists survive restarts
+        let restored = PeerAccess::new(p2p.node_id(), PeerAccessConfig::default());
+        restored.load(&state_engine).await.unwrap();
+        assert_eq!(restored.blocked(), vec!["mallory".to_string(), peer]);
+    }
+
+    #[tokio::test]
+    async fn test_presence() {
+        let state_engine = Arc::new(StateEngine::new().await.unwrap());
+        let p2p = VudoP2P::new(state_engine, P2PConfig::default())
+            .await
+            .unwrap();
+        let mut watch = p2p.watch_peer("did:key:bob");
+
+        let heartbeat = GossipMessage::Heartbeat {
+            peer_id: "node-b".to_string(),
+            did: Some("did:key:bob".to_string()),
+            state: PresenceState::Online,
+            documents: vec![("users".to_string(), "alice".to_string())],
+            timestamp: 0,
+        };
+        p2p.presence().observe(&heartbeat);
+        assert_eq!(watch.recv().await.unwrap().state, PresenceState::Online);
+        assert_eq!(p2p.active_on("users", "alice").len(), 1);
+
+        // Our own heartbeats aren't tracked
+        let own = GossipMessage::Heartbeat {
+            peer_id: p2p.node_id(),
+            did: None,
+            state: PresenceState::Online,
+            documents: Vec::new(),
+            timestamp: 0,
+        };
+        p2p.presence().observe(&own);
+        assert_eq!(p2p.presence().peers().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sync_conflicts() {
+        use automerge::transaction::Transactable;
+        use automerge::ROOT;
+        use vudo_state::{ConcurrentValue, DocumentId, FieldConflict};
+
+        struct KeepLast;
+
+        impl ConflictHandler for KeepLast {
+            fn choose(&self, _: &DocumentId, conflict: &FieldConflict) -> Option<ConcurrentValue> {
+                conflict.winner().cloned()
+            }
+        }
+
+        let (local, remote) = (
+            Arc::new(StateEngine::new().await.unwrap()),
+            Arc::new(StateEngine::new().await.unwrap()),
+        );
+        let p2p = VudoP2P::new(Arc::clone(&local), P2PConfig::default())
+            .await
+            .unwrap();
+        let mut conflicts = p2p.subscribe_conflicts();
+        p2p.set_conflict_advisor(Arc::new(KeepLast));
+
+        let doc_id = DocumentId::new("tasks", "groceries");
+        let theirs = remote.create_document(doc_id.clone()).await.unwrap();
+        let ours = local.create_document(doc_id).await.unwrap();
+        for (handle, status) in [(&theirs, "done"), (&ours, "open")] {
+            handle
+                .update(|doc| {
+                    doc.put(ROOT, "status", status)?;
+                    Ok(())
+                })
+                .unwrap();
+        }
+
+        let peer = "peer1".to_string();
+        p2p.sync_protocol
+            .apply_sync_changes(
+                &peer,
+                "tasks".to_string(),
+                "groceries".to_string(),
+                vec![theirs.save()],
+            )
+            .await
+            .unwrap();
+        let conflict = conflicts.recv().await.unwrap();
+        assert_eq!(conflict.id, "groceries");
+        assert_eq!(conflict.keys, vec!["status"]);
+        assert_eq!(conflict.suggestions.len(), 1);
+        assert_eq!(conflict.suggestions[0].key, "status");
+    }
 }
@@ -0,0 +1,520 @@
+//! Store-and-forward mail for offline peers.
+//!
+//! Changes for a peer that is offline can be left with a mailbox: a peer (or
+//! relay) both sides reach that hosts mail, i.e. runs with
+//! [`P2PConfig::mailbox`](crate::P2PConfig::mailbox) set. The sender seals
+//! them into a [`SealedMail`], encrypted to the recipient DID's encryption
+//! key so the mailbox can't read them, and signed with the sender DID's key
+//! so nobody can forge or alter them. The mailbox checks the signature and
+//! holds the mail until the recipient fetches it with a [`MailFetch`] signed
+//! by its DID key, or until it expires.
+//!
+//! Receiving mail is opt-in too (see [`MailSettings`]): once a designated
+//! mailbox connects, the recipient fetches its mail, verifies and opens it,
+//! and applies the changes of the senders it accepts mail from.
+
+use crate::error::{P2PError, Result};
+use crate::sync_protocol::PeerId;
+use ed25519_dalek::{Signature, Signer, Verifier};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+use tracing::{debug, info};
+use vudo_identity::{DeviceIdentity, Did};
+
+/// Default time mail is kept before it expires.
+pub const DEFAULT_MAIL_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Default interval between mail fetches from connected mailboxes.
+pub const DEFAULT_MAIL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How far a fetch's timestamp may be from the mailbox's clock.
+pub const FETCH_VALIDITY: Duration = Duration::from_secs(5 * 60);
+
+/// Domain tag of mail signatures.
+const MAIL_DOMAIN: &[u8] = b"vudo-mail/1";
+
+/// Domain tag of fetch signatures.
+const FETCH_DOMAIN: &[u8] = b"vudo-mail-fetch/1";
+
+/// Limits of a mailbox hosting mail for other peers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailboxConfig {
+    /// Longest time mail is held, whatever its sender asked for.
+    pub max_ttl: Duration,
+    /// Most mail held for one recipient.
+    pub max_mail_per_recipient: usize,
+    /// Most bytes held for one recipient.
+    pub max_bytes_per_recipient: usize,
+}
+
+impl Default for MailboxConfig {
+    fn default() -> Self {
+        Self {
+            max_ttl: DEFAULT_MAIL_TTL,
+            max_mail_per_recipient: 256,
+            max_bytes_per_recipient: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// Changes to a document, as carried by mail.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MailPayload {
+    /// Document namespace.
+    pub namespace: String,
+    /// Document key.
+    pub id: String,
+    /// Serialized Automerge changes.
+    pub changes: Vec<Vec<u8>>,
+}
+
+/// Mail encrypted to its recipient and signed by its sender.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SealedMail {
+    /// DID of the sender.
+    pub sender: String,
+    /// DID of the recipient.
+    pub recipient: String,
+    /// [`MailPayload`] sealed to the recipient DID (see [`Did::encrypt_to`]).
+    pub ciphertext: Vec<u8>,
+    /// Creation timestamp (milliseconds since epoch).
+    pub created_at: u64,
+    /// Expiration timestamp (milliseconds since epoch).
+    pub expires_at: u64,
+    /// Sender signature over the mail.
+    pub signature: Signature,
+}
+
+impl SealedMail {
+    /// Seal changes for `recipient`, expiring after `ttl`.
+    pub fn seal(
+        sender: &DeviceIdentity,
+        recipient: &Did,
+        payload: &MailPayload,
+        ttl: Duration,
+    ) -> Result<Self> {
+        let ciphertext = recipient
+            .encrypt_to(&bincode::serialize(payload)?)
+            .map_err(|e| P2PError::Internal(format!("Failed to encrypt mail: {}", e)))?;
+
+        let created_at = current_timestamp();
+        let mut mail = Self {
+            sender: sender.did().as_str().to_string(),
+            recipient: recipient.as_str().to_string(),
+            ciphertext,
+            created_at,
+            expires_at: created_at.saturating_add(ttl.as_millis() as u64),
+            signature: Signature::from_bytes(&[0; 64]),
+        };
+        mail.signature = sender.signing_key().sign(&mail.signed_bytes()?);
+        Ok(mail)
+    }
+
+    /// Bytes covered by the signature.
+    fn signed_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = MAIL_DOMAIN.to_vec();
+        bytes.extend(bincode::serialize(&(
+            &self.sender,
+            &self.recipient,
+            &self.ciphertext,
+            self.created_at,
+            self.expires_at,
+        ))?);
+        Ok(bytes)
+    }
+
+    /// Check the sender's signature.
+    pub fn verify(&self) -> Result<()> {
+        let sender = Did::parse(&self.sender)?;
+        sender
+            .verification_key
+            .verify(&self.signed_bytes()?, &self.signature)
+            .map_err(|_| P2PError::PermissionDenied("Invalid mail signature".to_string()))
+    }
+
+    /// Verify and decrypt mail addressed to `device`.
+    pub fn open(&self, device: &DeviceIdentity) -> Result<MailPayload> {
+        if self.recipient != device.did().as_str() {
+            return Err(P2PError::PermissionDenied(format!(
+                "Mail is addressed to {}",
+                self.recipient
+            )));
+        }
+        self.verify()?;
+
+        let plaintext = device
+            .decrypt(&self.ciphertext)
+            .map_err(|_| P2PError::InvalidMessage("Failed to decrypt mail".to_string()))?;
+        Ok(bincode::deserialize(&plaintext)?)
+    }
+
+    /// Get the mail ID, derived from its signature.
+    pub fn id(&self) -> String {
+        hex::encode(&blake3::hash(&self.signature.to_bytes()).as_bytes()[..16])
+    }
+
+    /// Size of the encrypted payload in bytes.
+    pub fn size(&self) -> usize {
+        self.ciphertext.len()
+    }
+
+    /// Check if the mail has expired.
+    pub fn is_expired(&self) -> bool {
+        current_timestamp() > self.expires_at
+    }
+}
+
+/// Request for the mail held for a recipient, signed by the recipient.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MailFetch {
+    /// DID of the recipient.
+    pub recipient: String,
+    /// Peer ID of the mailbox.
+    pub mailbox: PeerId,
+    /// Request timestamp (milliseconds since epoch).
+    pub timestamp: u64,
+    /// Recipient signature over the request.
+    pub signature: Signature,
+}
+
+impl MailFetch {
+    /// Sign a fetch of the mail `mailbox` holds for `device`.
+    pub fn sign(device: &DeviceIdentity, mailbox: &PeerId) -> Result<Self> {
+        let mut fetch = Self {
+            recipient: device.did().as_str().to_string(),
+            mailbox: mailbox.clone(),
+            timestamp: current_timestamp(),
+            signature: Signature::from_bytes(&[0; 64]),
+        };
+        fetch.signature = device.signing_key().sign(&fetch.signed_bytes()?);
+        Ok(fetch)
+    }
+
+    /// Bytes covered by the signature.
+    fn signed_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = FETCH_DOMAIN.to_vec();
+        bytes.extend(bincode::serialize(&(
+            &self.recipient,
+            &self.mailbox,
+            self.timestamp,
+        ))?);
+        Ok(bytes)
+    }
+
+    /// Check the request was signed by the recipient for mailbox `local`,
+    /// recently.
+    pub fn verify(&self, local: &PeerId) -> Result<()> {
+        if &self.mailbox != local {
+            return Err(P2PError::PermissionDenied(format!(
+                "Mail fetch is for mailbox {}",
+                self.mailbox
+            )));
+        }
+        if current_timestamp().abs_diff(self.timestamp) > FETCH_VALIDITY.as_millis() as u64 {
+            return Err(P2PError::PermissionDenied(
+                "Mail fetch is stale".to_string(),
+            ));
+        }
+        let recipient = Did::parse(&self.recipient)?;
+        recipient
+            .verification_key
+            .verify(&self.signed_bytes()?, &self.signature)
+            .map_err(|_| P2PError::PermissionDenied("Invalid mail fetch signature".to_string()))
+    }
+}
+
+/// How this node receives mail.
+#[derive(Debug, Clone)]
+pub struct MailSettings {
+    /// Identity mail is addressed to.
+    pub device: DeviceIdentity,
+    /// Mailboxes mail is fetched from.
+    pub mailboxes: BTreeSet<PeerId>,
+    /// DIDs whose mail is applied.
+    pub senders: BTreeSet<String>,
+}
+
+impl MailSettings {
+    /// Receive mail addressed to `device`, from no mailbox and no sender.
+    pub fn new(device: DeviceIdentity) -> Self {
+        Self {
+            device,
+            mailboxes: BTreeSet::new(),
+            senders: BTreeSet::new(),
+        }
+    }
+
+    /// Fetch mail from a mailbox.
+    pub fn mailbox(mut self, peer_id: impl Into<PeerId>) -> Self {
+        self.mailboxes.insert(peer_id.into());
+        self
+    }
+
+    /// Accept mail from a sender.
+    pub fn sender(mut self, did: impl Into<String>) -> Self {
+        self.senders.insert(did.into());
+        self
+    }
+}
+
+/// Mail held for a recipient.
+struct HeldMail {
+    mail: SealedMail,
+    /// Time the mailbox drops the mail, at most its configured TTL.
+    held_until: u64,
+}
+
+/// Mail this node holds for others, and how it receives its own.
+pub struct Mailbox {
+    /// Limits of hosted mail (not a mailbox when `None`).
+    config: Option<MailboxConfig>,
+    /// Held mail by recipient DID.
+    held: RwLock<HashMap<String, Vec<HeldMail>>>,
+    /// How mail is received (not received when `None`).
+    settings: RwLock<Option<MailSettings>>,
+}
+
+impl Mailbox {
+    /// Create a mailbox, hosting mail for others when `config` is set.
+    pub fn new(config: Option<MailboxConfig>) -> Self {
+        Self {
+            config,
+            held: RwLock::new(HashMap::new()),
+            settings: RwLock::new(None),
+        }
+    }
+
+    /// Check whether this node hosts mail for others.
+    pub fn is_hosting(&self) -> bool {
+        self.config.is_some()
+    }
+
+    /// Hold mail deposited by a peer.
+    ///
+    /// Mail already held is accepted again without being duplicated.
+    pub fn deposit(&self, mail: SealedMail) -> Result<()> {
+        let Some(config) = &self.config else {
+            return Err(P2PError::PermissionDenied(
+                "This node is not a mailbox".to_string(),
+            ));
+        };
+        mail.verify()?;
+        if mail.is_expired() {
+            return Err(P2PError::InvalidMessage("Mail has expired".to_string()));
+        }
+
+        let mut held = self.held.write();
+        let queue = held.entry(mail.recipient.clone()).or_default();
+        let id = mail.id();
+        if queue.iter().any(|held| held.mail.id() == id) {
+            return Ok(());
+        }
+        let bytes: usize = queue.iter().map(|held| held.mail.size()).sum();
+        if queue.len() >= config.max_mail_per_recipient
+            || bytes + mail.size() > config.max_bytes_per_recipient
+        {
+            return Err(P2PError::ResourceLimitExceeded(format!(
+                "Mailbox of {} is full",
+                mail.recipient
+            )));
+        }
+
+        let max_ttl = config.max_ttl.as_millis() as u64;
+        let held_until = mail
+            .expires_at
+            .min(current_timestamp().saturating_add(max_ttl));
+        debug!("Holding mail {} for {}", id, mail.recipient);
+        queue.push(HeldMail { mail, held_until });
+        Ok(())
+    }
+
+    /// Hand over the unexpired mail held for the recipient of a verified
+    /// fetch to mailbox `local`.
+    pub fn take(&self, fetch: &MailFetch, local: &PeerId) -> Result<Vec<SealedMail>> {
+        fetch.verify(local)?;
+        let now = current_timestamp();
+        let mail: Vec<SealedMail> = self
+            .held
+            .write()
+            .remove(&fetch.recipient)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|held| held.held_until >= now)
+            .map(|held| held.mail)
+            .collect();
+        if !mail.is_empty() {
+            info!("Delivering {} mail to {}", mail.len(), fetch.recipient);
+        }
+        Ok(mail)
+    }
+
+    /// Get the number of mail held for a recipient.
+    pub fn pending(&self, recipient: &str) -> usize {
+        self.held.read().get(recipient).map_or(0, Vec::len)
+    }
+
+    /// Drop expired mail. Returns the number of mail dropped.
+    pub fn prune_expired(&self) -> usize {
+        let now = current_timestamp();
+        let mut held = self.held.write();
+        let before: usize = held.values().map(Vec::len).sum();
+        held.retain(|_, queue| {
+            queue.retain(|held| held.held_until >= now);
+            !queue.is_empty()
+        });
+        before - held.values().map(Vec::len).sum::<usize>()
+    }
+
+    /// Receive mail as set out by `settings`.
+    pub fn receive(&self, settings: MailSettings) {
+        *self.settings.write() = Some(settings);
+    }
+
+    /// Stop receiving mail.
+    pub fn stop_receiving(&self) {
+        *self.settings.write() = None;
+    }
+
+    /// Sign a fetch from a peer, if it is a designated mailbox.
+    pub fn fetch_from(&self, peer_id: &PeerId) -> Result<Option<MailFetch>> {
+        match &*self.settings.read() {
+            Some(settings) if settings.mailboxes.contains(peer_id) => {
+                MailFetch::sign(&settings.device, peer_id).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Open delivered mail from accepted senders.
+    ///
+    /// Returns the payloads with their sender, and the errors of mail that
+    /// couldn't be opened.
+    pub fn open(&self, mail: Vec<SealedMail>) -> (Vec<(String, MailPayload)>, Vec<P2PError>) {
+        let settings = self.settings.read();
+        let Some(settings) = settings.as_ref() else {
+            return (
+                Vec::new(),
+                vec![P2PError::PermissionDenied(
+                    "This node does not receive mail".to_string(),
+                )],
+            );
+        };
+
+        let mut opened = Vec::new();
+        let mut errors = Vec::new();
+        for mail in mail {
+            if !settings.senders.contains(&mail.sender) {
+                errors.push(P2PError::PermissionDenied(format!(
+                    "Mail from {} is not accepted",
+                    mail.sender
+                )));
+                continue;
+            }
+            match mail.open(&settings.device) {
+                Ok(payload) => opened.push((mail.sender, payload)),
+                Err(e) => errors.push(e),
+            }
+        }
+        (opened, errors)
+    }
+}
+
+/// Get current timestamp in milliseconds.
+fn current_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload() -> MailPayload {
+        MailPayload {
+            namespace: "notes".to_string(),
+            id: "todo".to_string(),
+            changes: vec![b"changes".to_vec()],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_seal_and_open() {
+        let alice = DeviceIdentity::generate("Alice").await.unwrap();
+        let bob = DeviceIdentity::generate("Bob").await.unwrap();
+        let eve = DeviceIdentity::generate("Eve").await.unwrap();
+
+        let mail = SealedMail::seal(&alice, bob.did(), &payload(), DEFAULT_MAIL_TTL).unwrap();
+        mail.verify().unwrap();
+        assert!(!mail.is_expired());
+        assert_eq!(mail.open(&bob).unwrap(), payload());
+        assert!(mail.open(&eve).is_err());
+
+        // Altered mail fails to verify
+        let mut altered = mail.clone();
+        altered.ciphertext[0] ^= 1;
+        assert!(altered.verify().is_err());
+        let mut forged = mail;
+        forged.sender = eve.did().as_str().to_string();
+        assert!(forged.open(&bob).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mailbox() {
+        let alice = DeviceIdentity::generate("Alice").await.unwrap();
+        let bob = DeviceIdentity::generate("Bob").await.unwrap();
+        let local = "mailbox-node".to_string();
+
+        let config = MailboxConfig {
+            max_mail_per_recipient: 2,
+            ..MailboxConfig::default()
+        };
+        let mailbox = Mailbox::new(Some(config));
+        let seal = || SealedMail::seal(&alice, bob.did(), &payload(), DEFAULT_MAIL_TTL).unwrap();
+        let first = seal();
+        mailbox.deposit(first.clone()).unwrap();
+        mailbox.deposit(first).unwrap();
+        mailbox.deposit(seal()).unwrap();
+        assert_eq!(mailbox.pending(bob.did().as_str()), 2);
+        assert!(matches!(
+            mailbox.deposit(seal()),
+            Err(P2PError::ResourceLimitExceeded(_))
+        ));
+
+        // Only the recipient can fetch its mail, from this mailbox
+        let fetch = MailFetch::sign(&alice, &local).unwrap();
+        assert!(mailbox.take(&fetch, &local).unwrap().is_empty());
+        let fetch = MailFetch::sign(&bob, &local).unwrap();
+        assert!(mailbox.take(&fetch, &"other-node".to_string()).is_err());
+        let mut forged = fetch.clone();
+        forged.recipient = alice.did().as_str().to_string();
+        assert!(mailbox.take(&forged, &local).is_err());
+        let mail = mailbox.take(&fetch, &local).unwrap();
+        assert_eq!(mail.len(), 2);
+        assert_eq!(mailbox.pending(bob.did().as_str()), 0);
+
+        // Expired mail is refused
+        let expired = SealedMail::seal(&alice, bob.did(), &payload(), Duration::ZERO).unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(mailbox.deposit(expired).is_err());
+        assert_eq!(mailbox.prune_expired(), 0);
+
+        // The recipient opens mail from accepted senders only
+        let inbox = Mailbox::new(None);
+        assert!(inbox.deposit(seal()).is_err());
+        assert!(inbox.fetch_from(&local).unwrap().is_none());
+        inbox.receive(MailSettings::new(bob.clone()).mailbox(local.clone()));
+        assert!(inbox.fetch_from(&local).unwrap().is_some());
+        let (opened, errors) = inbox.open(mail.clone());
+        assert!(opened.is_empty());
+        assert_eq!(errors.len(), 2);
+
+        inbox.receive(MailSettings::new(bob).sender(alice.did().as_str()));
+        let (opened, errors) = inbox.open(mail);
+        assert!(errors.is_empty());
+        assert_eq!(opened[0], (alice.did().as_str().to_string(), payload()));
+    }
+}
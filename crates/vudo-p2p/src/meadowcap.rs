@@ -3,14 +3,41 @@
 //! This module implements the Meadowcap capability system as specified in the
 //! Willow Protocol. Capabilities provide cryptographically-secured permissions
 //! for read, write, and delegation operations on path prefixes within a namespace.
+//!
+//! # Sharing
+//!
+//! [`Share`] covers the common sharing flows without assembling capabilities
+//! by hand: it delegates a capability scoped to a collection, binds it to the
+//! recipient's DID with an expiry, and signs the result as a
+//! [`CapabilityGrant`]. The grant travels as a URL-safe token (or a
+//! `vudo://grant/` URL for links and QR codes), and the receiving side checks
+//! it with [`CapabilityGrant::validate_for`].
+//!
+//! ```ignore
+//! let grant = Share::read("photos")
+//!     .to("did:peer:bob")
+//!     .for_days(30)
+//!     .grant(&root, &alice_key)?;
+//! let url = grant.to_url()?;
+//!
+//! // On Bob's device
+//! let grant = CapabilityGrant::from_url(&url)?;
+//! grant.validate_for("did:peer:bob")?;
+//! ```
 
 use crate::error::{P2PError, Result};
 use crate::willow_types::{NamespaceId, Path, SubspaceId};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// URL prefix of capability grant links.
+pub const GRANT_URL_PREFIX: &str = "vudo://grant/";
 
 /// Permission level for a capability.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -19,7 +46,7 @@ pub enum Permission {
     Read,
     /// Write access (includes read).
     Write,
-    /// Full access including delegation.
+    /// Full access, including delegating admin capabilities.
     Admin,
 }
 
@@ -36,7 +63,58 @@ impl Permission {
     }
 }
 
+/// Part of a namespace a capability covers: a subspace (or all of them) and
+/// the paths under a prefix.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Area {
+    /// Subspace ID (None for every subspace).
+    pub subspace_id: Option<SubspaceId>,
+    /// Path prefix.
+    pub path_prefix: Path,
+}
+
+impl Area {
+    /// Create an area.
+    pub fn new(subspace_id: Option<SubspaceId>, path_prefix: Path) -> Self {
+        Self {
+            subspace_id,
+            path_prefix,
+        }
+    }
+
+    /// The whole namespace.
+    pub fn full() -> Self {
+        Self::new(None, Path::empty())
+    }
+
+    /// A whole subspace.
+    pub fn subspace(subspace_id: SubspaceId) -> Self {
+        Self::new(Some(subspace_id), Path::empty())
+    }
+
+    /// Check if a path of a subspace is in the area.
+    pub fn contains(&self, subspace_id: SubspaceId, path: &Path) -> bool {
+        (self.subspace_id.is_none() || self.subspace_id == Some(subspace_id))
+            && self.path_prefix.is_prefix_of(path)
+    }
+
+    /// Check if another area lies entirely within this one.
+    pub fn includes(&self, other: &Area) -> bool {
+        let subspace_ok = match (self.subspace_id, other.subspace_id) {
+            (None, _) => true,
+            (Some(sub), Some(other_sub)) => sub == other_sub,
+            (Some(_), None) => false,
+        };
+        subspace_ok && self.path_prefix.is_prefix_of(&other.path_prefix)
+    }
+}
+
 /// A Meadowcap capability for accessing resources within a namespace.
+///
+/// A root capability is issued by the namespace owner to itself. Each
+/// delegation is signed by the receiver of the parent capability and may
+/// only narrow its area and permission, so a chain can be checked link by
+/// link back to the root (see [`Capability::verify`]).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Capability {
     /// Namespace ID this capability grants access to.
@@ -49,6 +127,8 @@ pub struct Capability {
     pub permission: Permission,
     /// Issuer public key (who created this capability).
     pub issuer: VerifyingKey,
+    /// Receiver public key (who may use and further delegate it).
+    pub receiver: VerifyingKey,
     /// Signature over the capability.
     pub signature: Signature,
     /// Delegation chain (parent capabilities).
@@ -58,43 +138,41 @@ pub struct Capability {
 impl Capability {
     /// Create a new root capability with admin permission.
     pub fn new_root(namespace_id: NamespaceId, signing_key: &SigningKey) -> Self {
-        let path_prefix = Path::empty();
+        let area = Area::full();
         let permission = Permission::Admin;
         let issuer = signing_key.verifying_key();
 
-        let message = Self::create_signing_message(
-            namespace_id,
-            None,
-            &path_prefix,
-            permission,
-            &issuer,
-            &[],
-        );
+        let message =
+            Self::create_signing_message(namespace_id, &area, permission, &issuer, &issuer, None);
         let signature = signing_key.sign(&message);
 
         Self {
             namespace_id,
-            subspace_id: None,
-            path_prefix,
+            subspace_id: area.subspace_id,
+            path_prefix: area.path_prefix,
             permission,
             issuer,
+            receiver: issuer,
             signature,
             delegation_chain: Vec::new(),
         }
     }
 
-    /// Delegate a capability to a more restricted scope.
+    /// Delegate a capability over a narrower area to another key.
+    ///
+    /// `signing_key` must be the receiver's key, and the delegated
+    /// permission can't exceed this capability's.
     pub fn delegate(
         &self,
-        subspace_id: Option<SubspaceId>,
-        path_prefix: Path,
+        to: VerifyingKey,
+        area: Area,
         permission: Permission,
         signing_key: &SigningKey,
     ) -> Result<Self> {
-        // Verify this capability can delegate
-        if self.permission != Permission::Admin {
+        let issuer = signing_key.verifying_key();
+        if issuer != self.receiver {
             return Err(P2PError::CapabilityDelegationError(
-                "Only admin capabilities can delegate".to_string(),
+                "Only the receiver of a capability can delegate it".to_string(),
             ));
         }
 
@@ -105,49 +183,48 @@ impl Capability {
             ));
         }
 
-        // Verify path prefix is more restrictive (or equal)
-        if !self.path_prefix.is_prefix_of(&path_prefix) {
+        // Verify area is within the parent's
+        if !self.area().includes(&area) {
             return Err(P2PError::CapabilityDelegationError(
-                "Delegated path must be under parent path".to_string(),
+                "Delegated area must be within the parent's".to_string(),
             ));
         }
 
-        // Verify subspace ID matches if parent has one
-        if let Some(parent_sub) = self.subspace_id {
-            if let Some(new_sub) = subspace_id {
-                if parent_sub != new_sub {
-                    return Err(P2PError::CapabilityDelegationError(
-                        "Delegated subspace must match parent".to_string(),
-                    ));
-                }
-            }
-        }
-
-        let issuer = signing_key.verifying_key();
         let mut delegation_chain = self.delegation_chain.clone();
         delegation_chain.push(self.clone());
 
         let message = Self::create_signing_message(
             self.namespace_id,
-            subspace_id,
-            &path_prefix,
+            &area,
             permission,
             &issuer,
-            &delegation_chain,
+            &to,
+            Some(&self.signature),
         );
         let signature = signing_key.sign(&message);
 
         Ok(Self {
             namespace_id: self.namespace_id,
-            subspace_id,
-            path_prefix,
+            subspace_id: area.subspace_id,
+            path_prefix: area.path_prefix,
             permission,
             issuer,
+            receiver: to,
             signature,
             delegation_chain,
         })
     }
 
+    /// Area the capability covers.
+    pub fn area(&self) -> Area {
+        Area::new(self.subspace_id, self.path_prefix.clone())
+    }
+
+    /// Root of the delegation chain (the capability itself if not delegated).
+    pub fn root(&self) -> &Capability {
+        self.delegation_chain.first().unwrap_or(self)
+    }
+
     /// Check if this capability grants read permission for a path.
     pub fn can_read(&self, subspace_id: SubspaceId, path: &Path) -> bool {
         self.check_permission(subspace_id, path, Permission::Read)
@@ -159,68 +236,397 @@ impl Capability {
     }
 
     /// Check if this capability grants permission for a path.
-    fn check_permission(&self, subspace_id: SubspaceId, path: &Path, required: Permission) -> bool {
-        // Check permission level
-        if !self.permission.includes(required) {
-            return false;
+    pub(crate) fn check_permission(&self, subspace_id: SubspaceId, path: &Path, required: Permission) -> bool {
+        self.permission.includes(required) && self.area().contains(subspace_id, path)
+    }
+
+    /// Verify the signatures of the capability and its delegation chain.
+    ///
+    /// The chain must start at a self-issued root, and each link must be
+    /// issued by the previous link's receiver without widening its area or
+    /// permission. Whether the root is trusted is up to the caller (see
+    /// [`CapabilityStore::verify_chain`]).
+    pub fn verify(&self) -> Result<()> {
+        let mut parent: Option<&Capability> = None;
+        for link in self.delegation_chain.iter().chain(std::iter::once(self)) {
+            link.verify_link(parent)?;
+            parent = Some(link);
         }
+        Ok(())
+    }
 
-        // Check subspace
-        if let Some(cap_subspace) = self.subspace_id {
-            if cap_subspace != subspace_id {
-                return false;
+    /// Verify a single link of a delegation chain against its parent.
+    fn verify_link(&self, parent: Option<&Capability>) -> Result<()> {
+        let invalid = |reason: &str| Err(P2PError::CapabilityDelegationError(reason.to_string()));
+        match parent {
+            None if self.issuer != self.receiver => {
+                return invalid("Root capability must be issued to its issuer");
+            }
+            None => {}
+            Some(parent) => {
+                if self.namespace_id != parent.namespace_id {
+                    return invalid("Delegation changes the namespace");
+                }
+                if self.issuer != parent.receiver {
+                    return invalid("Delegation not issued by the parent's receiver");
+                }
+                if !parent.permission.includes(self.permission) {
+                    return invalid("Delegation widens the parent's permission");
+                }
+                if !parent.area().includes(&self.area()) {
+                    return invalid("Delegation widens the parent's area");
+                }
             }
         }
 
-        // Check path prefix
-        self.path_prefix.is_prefix_of(path)
-    }
-
-    /// Verify the cryptographic signature on this capability.
-    pub fn verify(&self) -> Result<()> {
         let message = Self::create_signing_message(
             self.namespace_id,
-            self.subspace_id,
-            &self.path_prefix,
+            &self.area(),
             self.permission,
             &self.issuer,
-            &self.delegation_chain,
+            &self.receiver,
+            parent.map(|p| &p.signature),
         );
-
         self.issuer
             .verify(&message, &self.signature)
-            .map_err(|e| P2PError::CapabilityDelegationError(format!("Invalid signature: {}", e)))?;
-
-        // Verify delegation chain
-        for parent in &self.delegation_chain {
-            parent.verify()?;
-        }
-
-        Ok(())
+            .map_err(|e| P2PError::CapabilityDelegationError(format!("Invalid signature: {}", e)))
     }
 
     /// Create a message to sign for a capability.
+    ///
+    /// Delegations include the parent's signature, binding them to it.
     fn create_signing_message(
         namespace_id: NamespaceId,
-        subspace_id: Option<SubspaceId>,
-        path_prefix: &Path,
+        area: &Area,
         permission: Permission,
         issuer: &VerifyingKey,
-        delegation_chain: &[Capability],
+        receiver: &VerifyingKey,
+        parent: Option<&Signature>,
     ) -> Vec<u8> {
         let mut hasher = Sha256::new();
+        hasher.update(b"meadowcap-capability");
         hasher.update(namespace_id.as_bytes());
-        if let Some(sub) = subspace_id {
-            hasher.update(sub.as_bytes());
+        match area.subspace_id {
+            Some(sub) => {
+                hasher.update([1]);
+                hasher.update(sub.as_bytes());
+            }
+            None => hasher.update([0]),
+        }
+        hasher.update((area.path_prefix.len() as u64).to_le_bytes());
+        for component in area.path_prefix.components() {
+            hasher.update((component.len() as u64).to_le_bytes());
+            hasher.update(component.as_bytes());
         }
-        hasher.update(path_prefix.to_string().as_bytes());
         hasher.update([permission as u8]);
         hasher.update(issuer.as_bytes());
-        hasher.update(&(delegation_chain.len() as u64).to_le_bytes());
+        hasher.update(receiver.as_bytes());
+        if let Some(parent) = parent {
+            hasher.update(parent.to_bytes());
+        }
+        hasher.finalize().to_vec()
+    }
+}
+
+/// A capability used by its receiver: the capability and the receiver's
+/// signature over what it is used for.
+///
+/// Willow entries and tombstones carry one signed over the item, so peers
+/// can check every write they are sent. Reconciliation requests carry a read
+/// capability signed over the connection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorisationToken {
+    /// The capability, with its delegation chain.
+    pub capability: Capability,
+    /// Signature of the capability's receiver.
+    pub signature: Signature,
+}
+
+impl AuthorisationToken {
+    /// Sign `message` with a capability.
+    ///
+    /// `signing_key` must be the capability's receiver's key.
+    pub fn sign(capability: &Capability, message: &[u8], signing_key: &SigningKey) -> Result<Self> {
+        if signing_key.verifying_key() != capability.receiver {
+            return Err(P2PError::CapabilityDelegationError(
+                "Only the receiver of a capability can use it".to_string(),
+            ));
+        }
+        Ok(Self {
+            capability: capability.clone(),
+            signature: signing_key.sign(message),
+        })
+    }
+
+    /// Check that the capability's receiver signed `message`, and the
+    /// signatures of the delegation chain.
+    ///
+    /// Whether the chain's root is trusted is checked with
+    /// [`CapabilityStore::verify_chain`].
+    pub fn verify(&self, message: &[u8]) -> Result<()> {
+        self.capability
+            .receiver
+            .verify(message, &self.signature)
+            .map_err(|e| {
+                P2PError::CapabilityDelegationError(format!("Invalid token signature: {}", e))
+            })?;
+        self.capability.verify()
+    }
+}
+
+/// A sharing flow: what to share, with whom and for how long.
+///
+/// Turned into a signed [`CapabilityGrant`] with [`Share::grant`].
+#[derive(Debug, Clone)]
+pub struct Share {
+    /// Collection (subspace) to share, or None for every collection.
+    collection: Option<String>,
+    /// Path prefix within the collection.
+    path_prefix: Path,
+    /// Permission to grant.
+    permission: Permission,
+    /// Recipient DID.
+    grantee: Option<String>,
+    /// How long the grant is valid.
+    duration: Option<Duration>,
+}
+
+impl Share {
+    /// Share read access to a collection.
+    pub fn read(collection: impl Into<String>) -> Self {
+        Self::new(Some(collection.into()), Permission::Read)
+    }
+
+    /// Share write access (including read) to a collection.
+    pub fn write(collection: impl Into<String>) -> Self {
+        Self::new(Some(collection.into()), Permission::Write)
+    }
+
+    /// Share access to every collection of the namespace.
+    pub fn all_collections(permission: Permission) -> Self {
+        Self::new(None, permission)
+    }
+
+    fn new(collection: Option<String>, permission: Permission) -> Self {
+        Self {
+            collection,
+            path_prefix: Path::empty(),
+            permission,
+            grantee: None,
+            duration: None,
+        }
+    }
+
+    /// Restrict the share to paths under `path_prefix`.
+    pub fn under(mut self, path_prefix: Path) -> Self {
+        self.path_prefix = path_prefix;
+        self
+    }
+
+    /// Share with the holder of a DID.
+    pub fn to(mut self, did: impl Into<String>) -> Self {
+        self.grantee = Some(did.into());
+        self
+    }
+
+    /// Expire the grant after `duration`.
+    pub fn for_duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Expire the grant after `days` days.
+    pub fn for_days(self, days: u64) -> Self {
+        self.for_duration(Duration::from_secs(days * 24 * 60 * 60))
+    }
+
+    /// Delegate `parent` to the recipient and sign the grant.
+    ///
+    /// `parent` must cover the shared collection, path and permission, and
+    /// `signing_key` must be its receiver's key. Fails if no recipient or
+    /// duration was given.
+    pub fn grant(self, parent: &Capability, signing_key: &SigningKey) -> Result<CapabilityGrant> {
+        let grantee = self.grantee.ok_or_else(|| {
+            P2PError::CapabilityDelegationError("A share needs a recipient DID".to_string())
+        })?;
+        let duration = self.duration.ok_or_else(|| {
+            P2PError::CapabilityDelegationError("A share needs an expiry".to_string())
+        })?;
+
+        let subspace_id = self
+            .collection
+            .as_deref()
+            .map(SubspaceId::from_dol_collection);
+        // The grant, not the capability, names the recipient
+        let area = Area::new(subspace_id, self.path_prefix);
+        let capability = parent.delegate(
+            signing_key.verifying_key(),
+            area,
+            self.permission,
+            signing_key,
+        )?;
+
+        let granted_at = unix_now();
+        let expires_at = granted_at.saturating_add(duration.as_secs());
+        let message =
+            CapabilityGrant::signing_message(&capability, &grantee, granted_at, expires_at);
+        let signature = signing_key.sign(&message);
+
+        Ok(CapabilityGrant {
+            capability,
+            collection: self.collection,
+            grantee,
+            granted_at,
+            expires_at,
+            signature,
+        })
+    }
+}
+
+/// A delegated capability bound to a recipient DID and an expiry.
+///
+/// Signed by the key that issued the delegated capability, so neither the
+/// recipient nor the expiry can be changed in transit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapabilityGrant {
+    /// The delegated capability, with its delegation chain.
+    pub capability: Capability,
+    /// Shared collection, or None for every collection.
+    pub collection: Option<String>,
+    /// Recipient DID.
+    pub grantee: String,
+    /// When the grant was issued (Unix seconds).
+    pub granted_at: u64,
+    /// When the grant expires (Unix seconds).
+    pub expires_at: u64,
+    /// Signature of the capability's issuer over the grant.
+    pub signature: Signature,
+}
+
+impl CapabilityGrant {
+    /// Encode the grant as a URL-safe token.
+    pub fn to_token(&self) -> Result<String> {
+        let bytes =
+            bincode::serialize(self).map_err(|e| P2PError::SerializationError(e.to_string()))?;
+        Ok(URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    /// Decode a token made by [`CapabilityGrant::to_token`].
+    ///
+    /// The grant is not validated; call [`CapabilityGrant::validate_for`].
+    pub fn from_token(token: &str) -> Result<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(token.trim()).map_err(|e| {
+            P2PError::CapabilityDelegationError(format!("Invalid grant token: {}", e))
+        })?;
+        bincode::deserialize(&bytes).map_err(|e| P2PError::SerializationError(e.to_string()))
+    }
+
+    /// Encode the grant as a `vudo://grant/` link, e.g. for a QR code.
+    pub fn to_url(&self) -> Result<String> {
+        Ok(format!("{}{}", GRANT_URL_PREFIX, self.to_token()?))
+    }
+
+    /// Decode a link made by [`CapabilityGrant::to_url`] or a bare token.
+    pub fn from_url(url: &str) -> Result<Self> {
+        let url = url.trim();
+        Self::from_token(url.strip_prefix(GRANT_URL_PREFIX).unwrap_or(url))
+    }
+
+    /// Check the grant's signatures, delegation chain and expiry.
+    pub fn validate(&self) -> Result<()> {
+        self.validate_at(unix_now())
+    }
+
+    /// Check the grant as [`CapabilityGrant::validate`] does and that it was
+    /// issued to `did`.
+    pub fn validate_for(&self, did: &str) -> Result<()> {
+        if self.grantee != did {
+            return Err(P2PError::CapabilityDelegationError(format!(
+                "Grant is for {}, not {}",
+                self.grantee, did
+            )));
+        }
+        self.validate()
+    }
+
+    /// Whether the grant is valid for `did` and allows `required` on `path`
+    /// of a subspace.
+    pub fn allows(
+        &self,
+        did: &str,
+        subspace_id: SubspaceId,
+        path: &Path,
+        required: Permission,
+    ) -> bool {
+        self.validate_for(did).is_ok()
+            && self
+                .capability
+                .check_permission(subspace_id, path, required)
+    }
+
+    /// Whether the grant has expired.
+    pub fn is_expired(&self) -> bool {
+        unix_now() > self.expires_at
+    }
+
+    fn validate_at(&self, now: u64) -> Result<()> {
+        if now > self.expires_at {
+            return Err(P2PError::CapabilityDelegationError(format!(
+                "Grant expired at {}",
+                self.expires_at
+            )));
+        }
+        if self.capability.subspace_id
+            != self
+                .collection
+                .as_deref()
+                .map(SubspaceId::from_dol_collection)
+        {
+            return Err(P2PError::CapabilityDelegationError(
+                "Grant collection doesn't match its capability".to_string(),
+            ));
+        }
+
+        self.capability.verify()?;
+        let message = Self::signing_message(
+            &self.capability,
+            &self.grantee,
+            self.granted_at,
+            self.expires_at,
+        );
+        self.capability
+            .issuer
+            .verify(&message, &self.signature)
+            .map_err(|e| {
+                P2PError::CapabilityDelegationError(format!("Invalid grant signature: {}", e))
+            })
+    }
+
+    fn signing_message(
+        capability: &Capability,
+        grantee: &str,
+        granted_at: u64,
+        expires_at: u64,
+    ) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.update(b"meadowcap-grant");
+        hasher.update(capability.signature.to_bytes());
+        hasher.update((grantee.len() as u64).to_le_bytes());
+        hasher.update(grantee.as_bytes());
+        hasher.update(granted_at.to_le_bytes());
+        hasher.update(expires_at.to_le_bytes());
         hasher.finalize().to_vec()
     }
 }
 
+/// Current Unix time in seconds.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
 /// Store for managing capabilities.
 pub struct CapabilityStore {
     /// Capabilities indexed by namespace ID.
@@ -247,6 +653,27 @@ impl CapabilityStore {
         Ok(())
     }
 
+    /// Verify a capability received from elsewhere.
+    ///
+    /// Besides the signatures of the delegation chain, its root must have
+    /// been issued by a key the store trusts for the namespace: the root
+    /// issuer of a capability added with [`CapabilityStore::add`].
+    pub fn verify_chain(&self, capability: &Capability) -> Result<()> {
+        capability.verify()?;
+
+        let owner = capability.root().issuer;
+        let caps = self.capabilities.read();
+        let trusted = caps
+            .get(&capability.namespace_id)
+            .is_some_and(|caps| caps.iter().any(|cap| cap.root().issuer == owner));
+        if !trusted {
+            return Err(P2PError::CapabilityDelegationError(
+                "Capability chain doesn't start at a trusted root".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
     /// Find a capability that grants the required permission for a path.
     pub fn find_capability(
         &self,
@@ -331,18 +758,110 @@ mod tests {
         let delegated_key = SigningKey::generate(&mut rand::rngs::OsRng);
         let delegated = root
             .delegate(
-                Some(subspace_id),
-                Path::from_components(["alice"]),
+                delegated_key.verifying_key(),
+                Area::new(Some(subspace_id), Path::from_components(["alice"])),
                 Permission::Write,
-                &delegated_key,
+                &signing_key,
             )
             .unwrap();
 
         assert_eq!(delegated.namespace_id, namespace_id);
         assert_eq!(delegated.subspace_id, Some(subspace_id));
         assert_eq!(delegated.permission, Permission::Write);
+        assert_eq!(delegated.issuer, signing_key.verifying_key());
+        assert_eq!(delegated.receiver, delegated_key.verifying_key());
         assert_eq!(delegated.delegation_chain.len(), 1);
         assert!(delegated.verify().is_ok());
+
+        // The receiver narrows it further, even without admin permission
+        let bob_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let posts = Area::new(Some(subspace_id), Path::from_components(["alice", "posts"]));
+        let bob = delegated
+            .delegate(
+                bob_key.verifying_key(),
+                posts.clone(),
+                Permission::Read,
+                &delegated_key,
+            )
+            .unwrap();
+        assert_eq!(bob.root().issuer, signing_key.verifying_key());
+        assert!(bob.verify().is_ok());
+
+        // Only the receiver can delegate
+        assert!(delegated
+            .delegate(bob_key.verifying_key(), posts, Permission::Read, &bob_key)
+            .is_err());
+    }
+
+    #[test]
+    fn test_delegation_chain_tampering() {
+        let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let namespace_id = NamespaceId::from_dol_namespace("myapp.v1");
+        let subspace_id = SubspaceId::from_dol_collection("users");
+        let root = Capability::new_root(namespace_id, &signing_key);
+
+        let alice_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let alice_area = Area::new(Some(subspace_id), Path::from_components(["alice"]));
+        let alice = root
+            .delegate(
+                alice_key.verifying_key(),
+                alice_area,
+                Permission::Write,
+                &signing_key,
+            )
+            .unwrap();
+
+        // Widening the area breaks the signature
+        let mut widened = alice.clone();
+        widened.path_prefix = Path::empty();
+        assert!(widened.verify().is_err());
+
+        // Re-signing a widened copy doesn't link to the parent
+        let mut forged = alice.clone();
+        forged.path_prefix = Path::empty();
+        forged.issuer = alice_key.verifying_key();
+        let message = Capability::create_signing_message(
+            namespace_id,
+            &forged.area(),
+            forged.permission,
+            &forged.issuer,
+            &forged.receiver,
+            Some(&root.signature),
+        );
+        forged.signature = alice_key.sign(&message);
+        assert!(forged.verify().is_err());
+
+        // Swapping in another owner's root breaks the link
+        let mallory_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let mut rerooted = alice.clone();
+        rerooted.delegation_chain = vec![Capability::new_root(namespace_id, &mallory_key)];
+        assert!(rerooted.verify().is_err());
+    }
+
+    #[test]
+    fn test_verify_chain_trusted_root() {
+        let owner_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let namespace_id = NamespaceId::from_dol_namespace("myapp.v1");
+        let root = Capability::new_root(namespace_id, &owner_key);
+        let store = CapabilityStore::new();
+        store.add(root.clone()).unwrap();
+
+        let alice_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let alice = root
+            .delegate(
+                alice_key.verifying_key(),
+                Area::subspace(SubspaceId::from_dol_collection("users")),
+                Permission::Write,
+                &owner_key,
+            )
+            .unwrap();
+        assert!(store.verify_chain(&alice).is_ok());
+
+        // A self-made root for the same namespace isn't trusted
+        let mallory_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let mallory = Capability::new_root(namespace_id, &mallory_key);
+        assert!(mallory.verify().is_ok());
+        assert!(store.verify_chain(&mallory).is_err());
     }
 
     #[test]
@@ -356,10 +875,10 @@ mod tests {
         let alice_key = SigningKey::generate(&mut rand::rngs::OsRng);
         let alice_cap = root
             .delegate(
-                Some(subspace_id),
-                Path::from_components(["alice"]),
+                alice_key.verifying_key(),
+                Area::new(Some(subspace_id), Path::from_components(["alice"])),
                 Permission::Write,
-                &alice_key,
+                &signing_key,
             )
             .unwrap();
 
@@ -393,6 +912,75 @@ mod tests {
         assert!(found.is_some());
     }
 
+    #[test]
+    fn test_share_grant_round_trip() {
+        let alice_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let namespace_id = NamespaceId::from_dol_namespace("myapp.v1");
+        let root = Capability::new_root(namespace_id, &alice_key);
+
+        let grant = Share::read("photos")
+            .to("did:peer:bob")
+            .for_days(30)
+            .grant(&root, &alice_key)
+            .unwrap();
+        assert_eq!(grant.expires_at - grant.granted_at, 30 * 24 * 60 * 60);
+        assert_eq!(grant.capability.delegation_chain.len(), 1);
+
+        let url = grant.to_url().unwrap();
+        assert!(url.starts_with(GRANT_URL_PREFIX));
+        assert!(url[GRANT_URL_PREFIX.len()..]
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'));
+
+        let received = CapabilityGrant::from_url(&url).unwrap();
+        assert!(received.validate_for("did:peer:bob").is_ok());
+        assert!(received.validate_for("did:peer:carol").is_err());
+
+        let photos = SubspaceId::from_dol_collection("photos");
+        let path = Path::from_components(["2024", "beach.jpg"]);
+        assert!(received.allows("did:peer:bob", photos, &path, Permission::Read));
+        assert!(!received.allows("did:peer:bob", photos, &path, Permission::Write));
+        assert!(!received.allows(
+            "did:peer:bob",
+            SubspaceId::from_dol_collection("notes"),
+            &path,
+            Permission::Read
+        ));
+    }
+
+    #[test]
+    fn test_share_grant_rejects_tampering_and_expiry() {
+        let alice_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let root = Capability::new_root(NamespaceId::from_dol_namespace("myapp.v1"), &alice_key);
+        let grant = Share::write("notes")
+            .to("did:peer:bob")
+            .for_duration(Duration::from_secs(60))
+            .grant(&root, &alice_key)
+            .unwrap();
+        assert!(grant.validate().is_ok());
+
+        let mut extended = grant.clone();
+        extended.expires_at += 365 * 24 * 60 * 60;
+        assert!(extended.validate().is_err());
+
+        let mut forwarded = grant.clone();
+        forwarded.grantee = "did:peer:mallory".to_string();
+        assert!(forwarded.validate_for("did:peer:mallory").is_err());
+
+        assert!(grant.validate_at(grant.expires_at + 1).is_err());
+
+        // Recipient and expiry are required
+        assert!(Share::read("notes")
+            .for_days(1)
+            .grant(&root, &alice_key)
+            .is_err());
+        assert!(Share::read("notes")
+            .to("did:peer:bob")
+            .grant(&root, &alice_key)
+            .is_err());
+        assert!(CapabilityGrant::from_token("not a token").is_err());
+    }
+
     #[test]
     fn test_delegation_path_restriction() {
         let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
@@ -402,16 +990,25 @@ mod tests {
 
         let delegated_key = SigningKey::generate(&mut rand::rngs::OsRng);
 
-        // Try to delegate to a path not under parent - should fail
-        let result = root.delegate(
-            None,
-            Path::from_components(["different", "path"]),
-            Permission::Write,
+        // Root has an empty path prefix, so any path is under it
+        let different = root
+            .delegate(
+                delegated_key.verifying_key(),
+                Area::new(None, Path::from_components(["different", "path"])),
+                Permission::Write,
+                &signing_key,
+            )
+            .unwrap();
+
+        // Delegating outside the parent's path fails
+        let other_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let result = different.delegate(
+            other_key.verifying_key(),
+            Area::new(None, Path::from_components(["other"])),
+            Permission::Read,
             &delegated_key,
         );
-
-        // This should succeed because root has empty path prefix (matches all)
-        assert!(result.is_ok());
+        assert!(result.is_err());
     }
 
     #[test]
@@ -425,20 +1022,20 @@ mod tests {
         let delegated_key = SigningKey::generate(&mut rand::rngs::OsRng);
         let write_cap = root
             .delegate(
-                None,
-                Path::from_components(["alice"]),
+                delegated_key.verifying_key(),
+                Area::new(None, Path::from_components(["alice"])),
                 Permission::Write,
-                &delegated_key,
+                &signing_key,
             )
             .unwrap();
 
         // Try to delegate admin from write capability - should fail
         let admin_key = SigningKey::generate(&mut rand::rngs::OsRng);
         let result = write_cap.delegate(
-            None,
-            Path::from_components(["alice", "posts"]),
+            admin_key.verifying_key(),
+            Area::new(None, Path::from_components(["alice", "posts"])),
             Permission::Admin,
-            &admin_key,
+            &delegated_key,
         );
 
         assert!(result.is_err());
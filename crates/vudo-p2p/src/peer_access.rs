@@ -0,0 +1,440 @@
+//! Peer allowlists, blocklists and pairing.
+//!
+//! [`PeerAccess`] decides which peers may sync with this node. Peers are
+//! listed by node ID or by the DID they authenticate as (see
+//! [`crate::auth`]). Blocked peers are disconnected whatever the mode; other
+//! peers are admitted according to the [`AccessMode`]:
+//!
+//! - [`AccessMode::Open`]: any peer that isn't blocked;
+//! - [`AccessMode::AllowlistOnly`]: only allowed peers;
+//! - [`AccessMode::Pairing`]: allowed peers, and unknown peers once the user
+//!   approves them. A connecting unknown peer raises
+//!   [`PairingEvent::Requested`] with a short code derived from both node
+//!   IDs, which both users compare before approving; no document is synced
+//!   with the peer until then.
+//!
+//! Approved peers join the allowlist, which is persisted in the state engine
+//! together with the blocklist.
+
+use crate::error::{P2PError, Result};
+use crate::session_cache::SESSION_CACHE_NAMESPACE;
+use crate::sync_protocol::PeerId;
+use automerge::{transaction::Transactable, ReadDoc, ROOT};
+use parking_lot::RwLock;
+use std::collections::{BTreeSet, HashMap};
+use std::time::Instant;
+use tokio::sync::mpsc;
+use vudo_state::{DocumentId, StateEngine};
+
+/// Key of the document holding the persisted allowlist and blocklist (in
+/// the session cache namespace).
+pub const PEER_ACCESS_KEY: &str = "peer_access";
+
+/// Domain tag of pairing codes.
+const PAIRING_DOMAIN: &[u8] = b"vudo-pairing/1";
+
+/// Which peers that are neither allowed nor blocked may sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AccessMode {
+    /// Any peer.
+    #[default]
+    Open,
+    /// No peer.
+    AllowlistOnly,
+    /// Peers the user approves after comparing pairing codes.
+    Pairing,
+}
+
+/// Peer access configuration.
+#[derive(Debug, Clone, Default)]
+pub struct PeerAccessConfig {
+    /// How unknown peers are treated.
+    pub mode: AccessMode,
+    /// Allowed node IDs and DIDs.
+    pub allowed: Vec<String>,
+    /// Blocked node IDs and DIDs.
+    pub blocked: Vec<String>,
+}
+
+/// Whether a peer may sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessDecision {
+    /// The peer may sync.
+    Allowed,
+    /// The peer is blocked.
+    Blocked,
+    /// The peer is unknown and the node only syncs with allowed peers.
+    Denied,
+    /// The peer is unknown and waits for pairing approval.
+    PendingApproval,
+}
+
+impl AccessDecision {
+    /// Fail unless the peer may sync.
+    pub fn check(self, peer_id: &PeerId) -> Result<()> {
+        let reason = match self {
+            Self::Allowed => return Ok(()),
+            Self::Blocked => "is blocked",
+            Self::Denied => "is not on the allowlist",
+            Self::PendingApproval => "awaits pairing approval",
+        };
+        Err(P2PError::PermissionDenied(format!(
+            "Peer {} {}",
+            peer_id, reason
+        )))
+    }
+}
+
+/// Request to pair with an unknown peer.
+#[derive(Debug, Clone)]
+pub struct PairingRequest {
+    /// Peer asking to pair.
+    pub peer_id: PeerId,
+    /// Code shown on both devices (see [`pairing_code`]).
+    pub code: String,
+    /// When the peer first connected.
+    pub requested_at: Instant,
+}
+
+/// Pairing event.
+#[derive(Debug, Clone)]
+pub enum PairingEvent {
+    /// An unknown peer connected and waits for approval.
+    Requested(PairingRequest),
+    /// A peer was approved and added to the allowlist.
+    Approved {
+        /// Peer ID.
+        peer_id: PeerId,
+    },
+    /// A pairing request was rejected.
+    Rejected {
+        /// Peer ID.
+        peer_id: PeerId,
+    },
+}
+
+/// Six-digit pairing code of two nodes.
+///
+/// Both nodes compute the same code from their node IDs, so users can check
+/// they're pairing with the device they think they are.
+pub fn pairing_code(a: &str, b: &str) -> String {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(PAIRING_DOMAIN);
+    hasher.update(first.as_bytes());
+    hasher.update(&[0]);
+    hasher.update(second.as_bytes());
+    let hash = hasher.finalize();
+    let bytes = hash.as_bytes();
+    let value = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    format!("{:06}", value % 1_000_000)
+}
+
+/// Peer allowlist, blocklist and pending pairings.
+pub struct PeerAccess {
+    /// Node ID of this node.
+    local: PeerId,
+    /// How unknown peers are treated.
+    mode: RwLock<AccessMode>,
+    /// Allowed node IDs and DIDs.
+    allowed: RwLock<BTreeSet<String>>,
+    /// Blocked node IDs and DIDs.
+    blocked: RwLock<BTreeSet<String>>,
+    /// Pairing requests waiting for approval.
+    pending: RwLock<HashMap<PeerId, PairingRequest>>,
+    /// Pairing event subscribers.
+    subscribers: RwLock<Vec<mpsc::UnboundedSender<PairingEvent>>>,
+}
+
+impl PeerAccess {
+    /// Create peer access control for the node `local`.
+    pub fn new(local: impl Into<PeerId>, config: PeerAccessConfig) -> Self {
+        Self {
+            local: local.into(),
+            mode: RwLock::new(config.mode),
+            allowed: RwLock::new(config.allowed.into_iter().collect()),
+            blocked: RwLock::new(config.blocked.into_iter().collect()),
+            pending: RwLock::new(HashMap::new()),
+            subscribers: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Get the mode.
+    pub fn mode(&self) -> AccessMode {
+        *self.mode.read()
+    }
+
+    /// Change the mode.
+    pub fn set_mode(&self, mode: AccessMode) {
+        *self.mode.write() = mode;
+    }
+
+    /// Decide whether a peer may sync. `did` is the DID the peer
+    /// authenticated as, if any.
+    pub fn decide(&self, peer_id: &PeerId, did: Option<&str>) -> AccessDecision {
+        let listed = |list: &BTreeSet<String>| {
+            list.contains(peer_id) || did.is_some_and(|did| list.contains(did))
+        };
+        if listed(&self.blocked.read()) {
+            return AccessDecision::Blocked;
+        }
+        if listed(&self.allowed.read()) {
+            return AccessDecision::Allowed;
+        }
+        match self.mode() {
+            AccessMode::Open => AccessDecision::Allowed,
+            AccessMode::AllowlistOnly => AccessDecision::Denied,
+            AccessMode::Pairing => AccessDecision::PendingApproval,
+        }
+    }
+
+    /// Allow a node ID or DID, lifting a block.
+    pub fn allow(&self, id: impl Into<String>) {
+        let id = id.into();
+        self.blocked.write().remove(&id);
+        self.allowed.write().insert(id);
+    }
+
+    /// Block a node ID or DID, dropping it from the allowlist and rejecting
+    /// its pairing request.
+    pub fn block(&self, id: impl Into<String>) {
+        let id = id.into();
+        self.allowed.write().remove(&id);
+        self.pending.write().remove(&id);
+        self.blocked.write().insert(id);
+    }
+
+    /// Remove a node ID or DID from both lists. Returns `false` if it was in
+    /// neither.
+    pub fn remove(&self, id: &str) -> bool {
+        let allowed = self.allowed.write().remove(id);
+        let blocked = self.blocked.write().remove(id);
+        allowed || blocked
+    }
+
+    /// Get the allowed node IDs and DIDs.
+    pub fn allowed(&self) -> Vec<String> {
+        self.allowed.read().iter().cloned().collect()
+    }
+
+    /// Get the blocked node IDs and DIDs.
+    pub fn blocked(&self) -> Vec<String> {
+        self.blocked.read().iter().cloned().collect()
+    }
+
+    /// Get the pairing code shared with a peer.
+    pub fn pairing_code(&self, peer_id: &PeerId) -> String {
+        pairing_code(&self.local, peer_id)
+    }
+
+    /// Ask the user to approve a peer, raising [`PairingEvent::Requested`]
+    /// the first time. Returns the pending request.
+    pub fn request_pairing(&self, peer_id: &PeerId) -> PairingRequest {
+        let request = {
+            let mut pending = self.pending.write();
+            if let Some(request) = pending.get(peer_id) {
+                return request.clone();
+            }
+            let request = PairingRequest {
+                peer_id: peer_id.clone(),
+                code: self.pairing_code(peer_id),
+                requested_at: Instant::now(),
+            };
+            pending.insert(peer_id.clone(), request.clone());
+            request
+        };
+        self.emit(PairingEvent::Requested(request.clone()));
+        request
+    }
+
+    /// Pairing requests waiting for approval.
+    pub fn pending(&self) -> Vec<PairingRequest> {
+        self.pending.read().values().cloned().collect()
+    }
+
+    /// Approve a peer, adding it to the allowlist.
+    pub fn approve(&self, peer_id: &PeerId) {
+        self.pending.write().remove(peer_id);
+        self.allow(peer_id.clone());
+        self.emit(PairingEvent::Approved {
+            peer_id: peer_id.clone(),
+        });
+    }
+
+    /// Reject a pairing request. The peer stays unknown and may ask again
+    /// when it reconnects; block it to keep it out. Returns `false` if the
+    /// peer had no pending request.
+    pub fn reject(&self, peer_id: &PeerId) -> bool {
+        if self.pending.write().remove(peer_id).is_none() {
+            return false;
+        }
+        self.emit(PairingEvent::Rejected {
+            peer_id: peer_id.clone(),
+        });
+        true
+    }
+
+    /// Subscribe to pairing events.
+    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<PairingEvent> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.subscribers.write().push(tx);
+        rx
+    }
+
+    /// Send an event to subscribers, dropping closed ones.
+    fn emit(&self, event: PairingEvent) {
+        self.subscribers
+            .write()
+            .retain(|tx| tx.send(event.clone()).is_ok());
+    }
+
+    /// Persist the allowlist and blocklist in the state engine.
+    pub async fn persist(&self, engine: &StateEngine) -> Result<()> {
+        let doc_id = DocumentId::new(SESSION_CACHE_NAMESPACE, PEER_ACCESS_KEY);
+        let handle = match engine.get_document(&doc_id).await {
+            Ok(h) => h,
+            Err(_) => engine.create_document(doc_id).await?,
+        };
+
+        let allowed = serde_json::to_string(&self.allowed())?;
+        let blocked = serde_json::to_string(&self.blocked())?;
+        handle.update(|doc| {
+            doc.put(ROOT, "allowed", allowed)?;
+            doc.put(ROOT, "blocked", blocked)?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// Load lists persisted by [`PeerAccess::persist`], adding them to the
+    /// configured ones. Returns the number of entries loaded.
+    pub async fn load(&self, engine: &StateEngine) -> Result<usize> {
+        let doc_id = DocumentId::new(SESSION_CACHE_NAMESPACE, PEER_ACCESS_KEY);
+        let handle = match engine.get_document(&doc_id).await {
+            Ok(h) => h,
+            Err(_) => return Ok(0),
+        };
+
+        let stored: Vec<Option<String>> = handle.read(|doc| {
+            let mut stored = Vec::new();
+            for key in ["allowed", "blocked"] {
+                let value = doc.get(ROOT, key)?;
+                stored.push(value.and_then(|(value, _)| value.to_str().map(str::to_string)));
+            }
+            Ok(stored)
+        })?;
+        let mut lists = stored.into_iter().map(|json| {
+            json.and_then(|json| serde_json::from_str::<Vec<String>>(&json).ok())
+                .unwrap_or_default()
+        });
+        let (allowed, blocked) = (
+            lists.next().unwrap_or_default(),
+            lists.next().unwrap_or_default(),
+        );
+
+        let loaded = allowed.len() + blocked.len();
+        self.allowed.write().extend(allowed);
+        self.blocked.write().extend(blocked);
+        Ok(loaded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairing_code() {
+        let code = pairing_code("node-a", "node-b");
+        assert_eq!(code.len(), 6);
+        assert!(code.chars().all(|c| c.is_ascii_digit()));
+        assert_eq!(code, pairing_code("node-b", "node-a"));
+        assert_ne!(code, pairing_code("node-a", "node-c"));
+    }
+
+    #[test]
+    fn test_decide() {
+        let config = PeerAccessConfig {
+            allowed: vec!["did:key:alice".to_string()],
+            blocked: vec!["mallory".to_string()],
+            ..Default::default()
+        };
+        let access = PeerAccess::new("local", config);
+        let bob = "bob".to_string();
+
+        assert_eq!(access.decide(&bob, None), AccessDecision::Allowed);
+        assert_eq!(
+            access.decide(&"mallory".to_string(), None),
+            AccessDecision::Blocked
+        );
+
+        access.set_mode(AccessMode::AllowlistOnly);
+        assert_eq!(access.decide(&bob, None), AccessDecision::Denied);
+        assert!(AccessDecision::Denied.check(&bob).is_err());
+        assert_eq!(
+            access.decide(&bob, Some("did:key:alice")),
+            AccessDecision::Allowed
+        );
+
+        access.set_mode(AccessMode::Pairing);
+        assert_eq!(access.decide(&bob, None), AccessDecision::PendingApproval);
+        access.block("did:key:alice");
+        assert_eq!(
+            access.decide(&bob, Some("did:key:alice")),
+            AccessDecision::Blocked
+        );
+        assert!(access.remove("did:key:alice"));
+        assert!(!access.remove("did:key:alice"));
+    }
+
+    #[test]
+    fn test_pairing_flow() {
+        let access = PeerAccess::new(
+            "local",
+            PeerAccessConfig {
+                mode: AccessMode::Pairing,
+                ..Default::default()
+            },
+        );
+        let mut events = access.subscribe();
+        let peer = "peer1".to_string();
+
+        let request = access.request_pairing(&peer);
+        assert_eq!(request.code, pairing_code("local", "peer1"));
+        access.request_pairing(&peer);
+        assert_eq!(access.pending().len(), 1);
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            PairingEvent::Requested(PairingRequest { peer_id, .. }) if peer_id == peer
+        ));
+        assert!(events.try_recv().is_err());
+
+        assert!(access.reject(&peer));
+        assert!(!access.reject(&peer));
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            PairingEvent::Rejected { .. }
+        ));
+        assert_eq!(access.decide(&peer, None), AccessDecision::PendingApproval);
+
+        access.request_pairing(&peer);
+        access.approve(&peer);
+        assert!(access.pending().is_empty());
+        assert_eq!(access.decide(&peer, None), AccessDecision::Allowed);
+        assert_eq!(access.allowed(), vec![peer]);
+    }
+
+    #[tokio::test]
+    async fn test_persist_and_load() {
+        let engine = StateEngine::new().await.unwrap();
+        let access = PeerAccess::new("local", PeerAccessConfig::default());
+        access.allow("peer1");
+        access.block("did:key:mallory");
+        access.persist(&engine).await.unwrap();
+
+        let restored = PeerAccess::new("local", PeerAccessConfig::default());
+        assert_eq!(restored.load(&engine).await.unwrap(), 2);
+        assert_eq!(restored.allowed(), vec!["peer1".to_string()]);
+        assert_eq!(restored.blocked(), vec!["did:key:mallory".to_string()]);
+    }
+}
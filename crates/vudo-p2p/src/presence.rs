@@ -0,0 +1,418 @@
+//! Presence of peers, built on the gossip overlay.
+//!
+//! Every node periodically announces a [`GossipMessage::Heartbeat`] on the
+//! presence topic, naming its DID (if set), whether the user is active or
+//! idle, and the documents it is active on. The [`PresenceTracker`] follows
+//! these heartbeats and reports transitions to watchers:
+//!
+//! - a heartbeat brings a peer [`PresenceState::Online`] or
+//!   [`PresenceState::Idle`], as the peer announces;
+//! - a peer not heard from for [`PresenceConfig::idle_timeout`] turns idle;
+//! - a peer not heard from for [`PresenceConfig::offline_timeout`], or one
+//!   announcing it is going offline, turns [`PresenceState::Offline`] and is
+//!   forgotten.
+//!
+//! Peers are tracked by the DID their heartbeats name, or by their node ID
+//! if they name none. Heartbeats are signed by the node key, but the DID is
+//! only claimed by the node; use authenticated sessions or DIDs proven with
+//! a challenge (see [`crate::auth`]) where it matters. Plain
+//! [`GossipMessage::Presence`] announcements count as heartbeats of online
+//! peers.
+
+use crate::error::Result;
+use crate::gossip::{GossipMessage, GossipOverlay};
+use crate::sync_protocol::PeerId;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::debug;
+
+/// Presence state of a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PresenceState {
+    /// Connected and in use.
+    Online,
+    /// Connected, but the user is away or the peer stopped sending
+    /// heartbeats.
+    Idle,
+    /// Gone.
+    Offline,
+}
+
+/// Presence tracking settings.
+#[derive(Debug, Clone)]
+pub struct PresenceConfig {
+    /// Interval between heartbeats of this node.
+    pub heartbeat_interval: Duration,
+    /// Silence after which a peer turns idle.
+    pub idle_timeout: Duration,
+    /// Silence after which a peer turns offline.
+    pub offline_timeout: Duration,
+}
+
+impl Default for PresenceConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval: Duration::from_secs(15),
+            idle_timeout: Duration::from_secs(45),
+            offline_timeout: Duration::from_secs(120),
+        }
+    }
+}
+
+/// Presence of a peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerPresence {
+    /// DID the peer announces, or its node ID.
+    pub id: String,
+    /// Node ID of the peer.
+    pub peer_id: PeerId,
+    /// Presence state.
+    pub state: PresenceState,
+    /// When the peer was last heard from (milliseconds since epoch).
+    pub last_seen: u64,
+    /// Documents the peer is active on.
+    pub documents: Vec<(String, String)>,
+}
+
+/// Tracked peer.
+struct Tracked {
+    /// Last reported presence.
+    presence: PeerPresence,
+    /// When the last heartbeat arrived.
+    heard_at: Instant,
+}
+
+/// What this node announces in its heartbeats.
+#[derive(Clone)]
+struct LocalPresence {
+    /// DID of the user or device.
+    did: Option<String>,
+    /// Presence state.
+    state: PresenceState,
+    /// Documents this node is active on.
+    documents: Vec<(String, String)>,
+}
+
+/// Presence of DID-identified peers.
+pub struct PresenceTracker {
+    /// Settings.
+    config: PresenceConfig,
+    /// Node ID of this node, whose heartbeats are ignored.
+    local: Option<PeerId>,
+    /// Tracked peers by DID or node ID.
+    peers: RwLock<HashMap<String, Tracked>>,
+    /// Watchers by DID or node ID.
+    watchers: RwLock<HashMap<String, Vec<mpsc::UnboundedSender<PeerPresence>>>>,
+    /// What this node announces.
+    local_presence: RwLock<LocalPresence>,
+}
+
+impl PresenceTracker {
+    /// Create a tracker ignoring heartbeats of the node `local`.
+    pub fn new(config: PresenceConfig, local: Option<PeerId>) -> Self {
+        Self {
+            config,
+            local,
+            peers: RwLock::new(HashMap::new()),
+            watchers: RwLock::new(HashMap::new()),
+            local_presence: RwLock::new(LocalPresence {
+                did: None,
+                state: PresenceState::Online,
+                documents: Vec::new(),
+            }),
+        }
+    }
+
+    /// Get the settings.
+    pub fn config(&self) -> &PresenceConfig {
+        &self.config
+    }
+
+    /// Set the DID this node's heartbeats name.
+    pub fn set_did(&self, did: Option<String>) {
+        self.local_presence.write().did = did;
+    }
+
+    /// Set the state this node announces, e.g. [`PresenceState::Idle`] when
+    /// the user is away.
+    pub fn set_state(&self, state: PresenceState) {
+        self.local_presence.write().state = state;
+    }
+
+    /// Set the documents this node announces it is active on.
+    pub fn set_documents(&self, documents: Vec<(String, String)>) {
+        self.local_presence.write().documents = documents;
+    }
+
+    /// Announce this node's heartbeat as `peer_id`.
+    pub async fn announce(&self, gossip: &GossipOverlay, peer_id: PeerId) -> Result<()> {
+        let local = self.local_presence.read().clone();
+        gossip
+            .announce_heartbeat(peer_id, local.did, local.state, local.documents)
+            .await
+    }
+
+    /// Follow a message received on the presence topic.
+    pub fn observe(&self, message: &GossipMessage) {
+        let (peer_id, did, state, documents) = match message {
+            GossipMessage::Heartbeat {
+                peer_id,
+                did,
+                state,
+                documents,
+                ..
+            } => (peer_id, did.as_ref(), *state, documents),
+            GossipMessage::Presence {
+                peer_id, documents, ..
+            } => (peer_id, None, PresenceState::Online, documents),
+            _ => return,
+        };
+        if self.local.as_ref() == Some(peer_id) {
+            return;
+        }
+
+        let id = did.unwrap_or(peer_id).clone();
+        let presence = PeerPresence {
+            id: id.clone(),
+            peer_id: peer_id.clone(),
+            state,
+            last_seen: current_timestamp(),
+            documents: documents.clone(),
+        };
+
+        if state == PresenceState::Offline {
+            if self.peers.write().remove(&id).is_some() {
+                self.emit(presence);
+            }
+            return;
+        }
+
+        let previous = self.peers.write().insert(
+            id,
+            Tracked {
+                presence: presence.clone(),
+                heard_at: Instant::now(),
+            },
+        );
+        if previous.map(|tracked| tracked.presence.state) != Some(state) {
+            self.emit(presence);
+        }
+    }
+
+    /// Turn silent peers idle or offline. Returns the transitions.
+    pub fn expire(&self) -> Vec<PeerPresence> {
+        self.expire_at(Instant::now())
+    }
+
+    /// Turn peers silent at `now` idle or offline.
+    fn expire_at(&self, now: Instant) -> Vec<PeerPresence> {
+        let mut transitions = Vec::new();
+        self.peers.write().retain(|_, tracked| {
+            let silence = now.saturating_duration_since(tracked.heard_at);
+            if silence >= self.config.offline_timeout {
+                tracked.presence.state = PresenceState::Offline;
+                transitions.push(tracked.presence.clone());
+                return false;
+            }
+            if silence >= self.config.idle_timeout
+                && tracked.presence.state == PresenceState::Online
+            {
+                tracked.presence.state = PresenceState::Idle;
+                transitions.push(tracked.presence.clone());
+            }
+            true
+        });
+
+        for presence in &transitions {
+            debug!("Peer {} is {:?}", presence.id, presence.state);
+            self.emit(presence.clone());
+        }
+        transitions
+    }
+
+    /// Watch a peer by DID (or node ID), receiving its presence
+    /// transitions. The current presence is sent first, if the peer is
+    /// known.
+    pub fn watch_peer(&self, id: &str) -> mpsc::UnboundedReceiver<PeerPresence> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        if let Some(presence) = self.get(id) {
+            let _ = tx.send(presence);
+        }
+        self.watchers
+            .write()
+            .entry(id.to_string())
+            .or_default()
+            .push(tx);
+        rx
+    }
+
+    /// Get the presence of a peer by DID (or node ID).
+    pub fn get(&self, id: &str) -> Option<PeerPresence> {
+        self.peers
+            .read()
+            .get(id)
+            .map(|tracked| tracked.presence.clone())
+    }
+
+    /// Get the peers that are online or idle.
+    pub fn peers(&self) -> Vec<PeerPresence> {
+        self.peers
+            .read()
+            .values()
+            .map(|tracked| tracked.presence.clone())
+            .collect()
+    }
+
+    /// Get the online peers active on a document.
+    pub fn active_on(&self, namespace: &str, id: &str) -> Vec<PeerPresence> {
+        self.peers()
+            .into_iter()
+            .filter(|presence| {
+                presence.state == PresenceState::Online
+                    && presence
+                        .documents
+                        .iter()
+                        .any(|(ns, doc)| ns == namespace && doc == id)
+            })
+            .collect()
+    }
+
+    /// Send a transition to the peer's watchers, dropping closed ones.
+    fn emit(&self, presence: PeerPresence) {
+        let mut watchers = self.watchers.write();
+        if let Some(senders) = watchers.get_mut(&presence.id) {
+            senders.retain(|tx| tx.send(presence.clone()).is_ok());
+            if senders.is_empty() {
+                watchers.remove(&presence.id);
+            }
+        }
+    }
+}
+
+/// Get current timestamp in milliseconds.
+fn current_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heartbeat(peer_id: &str, did: Option<&str>, state: PresenceState) -> GossipMessage {
+        GossipMessage::Heartbeat {
+            peer_id: peer_id.to_string(),
+            did: did.map(str::to_string),
+            state,
+            documents: vec![("users".to_string(), "alice".to_string())],
+            timestamp: current_timestamp(),
+        }
+    }
+
+    #[test]
+    fn test_transitions() {
+        let tracker = PresenceTracker::new(PresenceConfig::default(), Some("local".to_string()));
+        let mut watch = tracker.watch_peer("did:key:bob");
+
+        tracker.observe(&heartbeat(
+            "node-b",
+            Some("did:key:bob"),
+            PresenceState::Online,
+        ));
+        let online = watch.try_recv().unwrap();
+        assert_eq!(online.state, PresenceState::Online);
+        assert_eq!(online.peer_id, "node-b");
+        assert!(online.last_seen > 0);
+
+        // Repeated heartbeats aren't transitions
+        tracker.observe(&heartbeat(
+            "node-b",
+            Some("did:key:bob"),
+            PresenceState::Online,
+        ));
+        assert!(watch.try_recv().is_err());
+        assert_eq!(tracker.active_on("users", "alice").len(), 1);
+        assert!(tracker.active_on("users", "carol").is_empty());
+
+        tracker.observe(&heartbeat(
+            "node-b",
+            Some("did:key:bob"),
+            PresenceState::Idle,
+        ));
+        assert_eq!(watch.try_recv().unwrap().state, PresenceState::Idle);
+        assert!(tracker.active_on("users", "alice").is_empty());
+
+        tracker.observe(&heartbeat(
+            "node-b",
+            Some("did:key:bob"),
+            PresenceState::Offline,
+        ));
+        assert_eq!(watch.try_recv().unwrap().state, PresenceState::Offline);
+        assert!(tracker.get("did:key:bob").is_none());
+
+        // Our own heartbeats and unrelated messages are ignored
+        tracker.observe(&heartbeat("local", None, PresenceState::Online));
+        tracker.observe(&GossipMessage::document_update(
+            "node-c".to_string(),
+            "users",
+            "alice",
+            1,
+        ));
+        assert!(tracker.peers().is_empty());
+    }
+
+    #[test]
+    fn test_heartbeat_timeouts() {
+        let tracker = PresenceTracker::new(PresenceConfig::default(), None);
+        tracker.observe(&heartbeat("node-b", None, PresenceState::Online));
+        let mut watch = tracker.watch_peer("node-b");
+        assert_eq!(watch.try_recv().unwrap().state, PresenceState::Online);
+
+        let now = Instant::now();
+        assert!(tracker.expire_at(now + Duration::from_secs(10)).is_empty());
+
+        let idle = tracker.expire_at(now + Duration::from_secs(60));
+        assert_eq!(idle.len(), 1);
+        assert_eq!(watch.try_recv().unwrap().state, PresenceState::Idle);
+        assert!(tracker.expire_at(now + Duration::from_secs(90)).is_empty());
+
+        tracker.expire_at(now + Duration::from_secs(180));
+        assert_eq!(watch.try_recv().unwrap().state, PresenceState::Offline);
+        assert!(tracker.peers().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_announce() {
+        let gossip = GossipOverlay::new();
+        let mut sub = gossip.subscribe_presence().await.unwrap();
+        let tracker = PresenceTracker::new(PresenceConfig::default(), None);
+        tracker.set_did(Some("did:key:alice".to_string()));
+        tracker.set_state(PresenceState::Idle);
+        tracker.set_documents(vec![("notes".to_string(), "todo".to_string())]);
+
+        tracker
+            .announce(&gossip, "node-a".to_string())
+            .await
+            .unwrap();
+        match sub.recv().await.unwrap() {
+            GossipMessage::Heartbeat {
+                peer_id,
+                did,
+                state,
+                documents,
+                ..
+            } => {
+                assert_eq!(peer_id, "node-a");
+                assert_eq!(did.as_deref(), Some("did:key:alice"));
+                assert_eq!(state, PresenceState::Idle);
+                assert_eq!(documents.len(), 1);
+            }
+            _ => panic!("Wrong message type"),
+        }
+    }
+}
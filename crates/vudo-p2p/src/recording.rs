@@ -0,0 +1,614 @@
+//! Record-and-replay of sync sessions.
+//!
+//! A [`SessionRecorder`] captures every [`SyncMessage`] a node sends or
+//! receives, with timestamps and peer IDs, into a file. A [`SessionReplayer`]
+//! feeds the received messages of a [`Recording`] back into a fresh
+//! [`StateEngine`] in recorded order, so convergence bugs reported from the
+//! field can be reproduced locally.
+//!
+//! # File Format
+//!
+//! The file starts with [`RECORDING_MAGIC`] and a little-endian `u32` format
+//! version, followed by length-prefixed (`u32` LE) bincode frames: one
+//! [`RecordingHeader`], then one [`RecordedMessage`] per message. Every frame
+//! is flushed as it is written, so a recording cut short by a crash loses at
+//! most its last frame. Frames are at most [`MAX_FRAME_SIZE`] bytes.
+//!
+//! Messages are encoded with bincode, which numbers enum variants by
+//! position, so the format version is bumped whenever [`SyncMessage`]
+//! variants are added, removed or reordered.
+
+use crate::error::{P2PError, Result};
+use crate::framing::DEFAULT_MAX_MESSAGE_SIZE;
+use crate::sync_protocol::{PeerId, SyncMessage, SyncProtocol};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+use vudo_state::StateEngine;
+
+/// Magic bytes at the start of a recording file.
+pub const RECORDING_MAGIC: &[u8; 8] = b"VUDOSYNC";
+
+/// Current recording format version.
+pub const RECORDING_VERSION: u32 = 2;
+
+/// Largest frame written to or read from a recording: a message of the
+/// default size limit plus its recording metadata.
+pub const MAX_FRAME_SIZE: usize = DEFAULT_MAX_MESSAGE_SIZE + 64 * 1024;
+
+/// Direction of a recorded message, relative to the recording node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    /// Received from the peer.
+    Inbound,
+    /// Sent to the peer.
+    Outbound,
+}
+
+/// Metadata written at the start of a recording.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordingHeader {
+    /// ID of the recording node.
+    pub node_id: String,
+    /// Recording start (Unix epoch milliseconds).
+    pub started_at: u64,
+}
+
+/// A message captured by a [`SessionRecorder`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedMessage {
+    /// Capture time (Unix epoch milliseconds).
+    pub timestamp: u64,
+    /// Remote peer.
+    pub peer_id: PeerId,
+    /// Whether the message was received or sent.
+    pub direction: Direction,
+    /// The message.
+    pub message: SyncMessage,
+}
+
+/// Borrowed form of [`RecordedMessage`], encoded identically by bincode.
+#[derive(Serialize)]
+struct RecordedMessageRef<'a> {
+    timestamp: u64,
+    peer_id: &'a str,
+    direction: Direction,
+    message: &'a SyncMessage,
+}
+
+/// Writes the sync messages of a node to a recording file.
+pub struct SessionRecorder {
+    /// Recording file path.
+    path: PathBuf,
+    /// Buffered file writer.
+    writer: Mutex<BufWriter<File>>,
+    /// Number of messages recorded.
+    message_count: AtomicU64,
+}
+
+impl SessionRecorder {
+    /// Create a recording file, replacing any existing file at `path`.
+    pub fn create(path: impl Into<PathBuf>, node_id: &str) -> Result<Self> {
+        let path = path.into();
+        let file = File::create(&path).map_err(|e| {
+            P2PError::Internal(format!("Failed to create {}: {}", path.display(), e))
+        })?;
+
+        let mut writer = BufWriter::new(file);
+        writer
+            .write_all(RECORDING_MAGIC)
+            .and_then(|_| writer.write_all(&RECORDING_VERSION.to_le_bytes()))
+            .map_err(|e| P2PError::Internal(e.to_string()))?;
+
+        let header = RecordingHeader {
+            node_id: node_id.to_string(),
+            started_at: current_timestamp(),
+        };
+        write_frame(&mut writer, &bincode::serialize(&header)?)?;
+
+        debug!("Recording sync session to {}", path.display());
+        Ok(Self {
+            path,
+            writer: Mutex::new(writer),
+            message_count: AtomicU64::new(0),
+        })
+    }
+
+    /// Get the recording file path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the number of messages recorded so far.
+    pub fn message_count(&self) -> u64 {
+        self.message_count.load(Ordering::Relaxed)
+    }
+
+    /// Record a message sent to or received from a peer.
+    pub fn record(&self, peer_id: &str, direction: Direction, message: &SyncMessage) -> Result<()> {
+        let frame = bincode::serialize(&RecordedMessageRef {
+            timestamp: current_timestamp(),
+            peer_id,
+            direction,
+            message,
+        })?;
+
+        write_frame(&mut self.writer.lock(), &frame)?;
+        self.message_count.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Write a length-prefixed frame and flush it.
+fn write_frame(writer: &mut BufWriter<File>, frame: &[u8]) -> Result<()> {
+    let len = u32::try_from(frame.len())
+        .ok()
+        .filter(|_| frame.len() <= MAX_FRAME_SIZE)
+        .ok_or_else(|| P2PError::SerializationError("Recorded frame too large".to_string()))?;
+    writer
+        .write_all(&len.to_le_bytes())
+        .and_then(|_| writer.write_all(frame))
+        .and_then(|_| writer.flush())
+        .map_err(|e| P2PError::Internal(e.to_string()))
+}
+
+/// Read a length-prefixed frame, or `None` at a clean or truncated end.
+fn read_frame(reader: &mut impl Read) -> Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(P2PError::Internal(e.to_string())),
+    }
+
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(P2PError::DeserializationError(format!(
+            "Recorded frame of {} bytes exceeds {} bytes",
+            len, MAX_FRAME_SIZE
+        )));
+    }
+
+    // Grown as data arrives, so a corrupt length can't reserve the maximum
+    let mut frame = Vec::new();
+    reader
+        .take(len as u64)
+        .read_to_end(&mut frame)
+        .map_err(|e| P2PError::Internal(e.to_string()))?;
+    if frame.len() < len {
+        warn!("Recording ends with a truncated frame");
+        return Ok(None);
+    }
+    Ok(Some(frame))
+}
+
+/// A recorded sync session.
+#[derive(Debug, Clone)]
+pub struct Recording {
+    /// Recording metadata.
+    pub header: RecordingHeader,
+    /// Messages in capture order.
+    pub messages: Vec<RecordedMessage>,
+}
+
+impl Recording {
+    /// Load a recording file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|e| P2PError::Internal(format!("Failed to open {}: {}", path.display(), e)))?;
+        Self::from_reader(BufReader::new(file))
+    }
+
+    /// Read a recording from a byte stream.
+    pub fn from_reader(mut reader: impl Read) -> Result<Self> {
+        let mut preamble = [0u8; 12];
+        reader.read_exact(&mut preamble).map_err(|_| {
+            P2PError::DeserializationError("Not a sync session recording".to_string())
+        })?;
+        if &preamble[..8] != RECORDING_MAGIC {
+            return Err(P2PError::DeserializationError(
+                "Not a sync session recording".to_string(),
+            ));
+        }
+        let version = u32::from_le_bytes([preamble[8], preamble[9], preamble[10], preamble[11]]);
+        if version != RECORDING_VERSION {
+            return Err(P2PError::DeserializationError(format!(
+                "Unsupported recording version {}",
+                version
+            )));
+        }
+
+        let header = read_frame(&mut reader)?.ok_or_else(|| {
+            P2PError::DeserializationError("Missing recording header".to_string())
+        })?;
+        let header: RecordingHeader = bincode::deserialize(&header)?;
+
+        let mut messages = Vec::new();
+        while let Some(frame) = read_frame(&mut reader)? {
+            messages.push(bincode::deserialize(&frame)?);
+        }
+
+        Ok(Self { header, messages })
+    }
+
+    /// Iterate over the received messages.
+    pub fn inbound(&self) -> impl Iterator<Item = &RecordedMessage> {
+        self.messages
+            .iter()
+            .filter(|m| m.direction == Direction::Inbound)
+    }
+
+    /// List the peers that appear in the recording, in order of first appearance.
+    pub fn peers(&self) -> Vec<PeerId> {
+        let mut peers: Vec<PeerId> = Vec::new();
+        for message in &self.messages {
+            if !peers.contains(&message.peer_id) {
+                peers.push(message.peer_id.clone());
+            }
+        }
+        peers
+    }
+}
+
+/// Outcome of replaying one message.
+#[derive(Debug, Clone)]
+pub enum ReplayOutcome {
+    /// The message was applied to the state engine.
+    Applied,
+    /// The message was a request; the response the node would have sent.
+    Responded(Box<SyncMessage>),
+    /// The message doesn't affect document state, or was sent by the node.
+    Skipped,
+}
+
+/// A message whose replay failed.
+#[derive(Debug, Clone)]
+pub struct ReplayFailure {
+    /// Index of the message in [`Recording::messages`].
+    pub index: usize,
+    /// Error message.
+    pub error: String,
+}
+
+/// Summary of a replay.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayReport {
+    /// Number of messages applied to the state engine.
+    pub applied: usize,
+    /// Number of requests answered.
+    pub responded: usize,
+    /// Number of messages skipped.
+    pub skipped: usize,
+    /// Messages whose replay failed.
+    pub failures: Vec<ReplayFailure>,
+}
+
+impl ReplayReport {
+    /// Check whether every message replayed without error.
+    pub fn is_clean(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Replays recorded sync sessions into a state engine.
+///
+/// Received messages are applied in recorded order through the same
+/// [`SyncProtocol`] handlers the node uses, with no network or timing
+/// involved, so a replay is deterministic. Messages the node sent are
+/// skipped: they are kept in the recording for inspection, but local edits
+/// that produced them aren't captured.
+pub struct SessionReplayer {
+    /// State engine receiving the replayed changes.
+    state_engine: Arc<StateEngine>,
+    /// Sync protocol handler.
+    sync_protocol: SyncProtocol,
+}
+
+impl SessionReplayer {
+    /// Create a replayer over a state engine, normally a fresh one.
+    pub fn new(state_engine: Arc<StateEngine>) -> Self {
+        Self {
+            sync_protocol: SyncProtocol::new(Arc::clone(&state_engine)),
+            state_engine,
+        }
+    }
+
+    /// Get the state engine receiving the replayed changes.
+    pub fn state_engine(&self) -> &Arc<StateEngine> {
+        &self.state_engine
+    }
+
+    /// Replay every message of a recording, continuing past failures.
+    pub async fn replay(&self, recording: &Recording) -> ReplayReport {
+        self.replay_until(recording, recording.messages.len()).await
+    }
+
+    /// Replay the first `count` messages of a recording, e.g. to bisect a
+    /// divergence.
+    pub async fn replay_until(&self, recording: &Recording, count: usize) -> ReplayReport {
+        let mut report = ReplayReport::default();
+        for (index, message) in recording.messages.iter().take(count).enumerate() {
+            match self.replay_message(message).await {
+                Ok(ReplayOutcome::Applied) => report.applied += 1,
+                Ok(ReplayOutcome::Responded(_)) => report.responded += 1,
+                Ok(ReplayOutcome::Skipped) => report.skipped += 1,
+                Err(e) => {
+                    debug!("Replay of message {} failed: {}", index, e);
+                    report.failures.push(ReplayFailure {
+                        index,
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+        report
+    }
+
+    /// Replay a single recorded message.
+    pub async fn replay_message(&self, recorded: &RecordedMessage) -> Result<ReplayOutcome> {
+        if recorded.direction == Direction::Outbound {
+            return Ok(ReplayOutcome::Skipped);
+        }
+
+        let peer_id = &recorded.peer_id;
+        match recorded.message.clone() {
+            SyncMessage::SyncRequest {
+                namespace,
+                id,
+                last_sync,
+            } => self
+                .sync_protocol
+                .handle_sync_request(peer_id, namespace, id, last_sync)
+                .await
+                .map(|reply| ReplayOutcome::Responded(Box::new(reply))),
+            SyncMessage::FullSync { namespace, id } => self
+                .sync_protocol
+                .handle_sync_request(peer_id, namespace, id, None)
+                .await
+                .map(|reply| ReplayOutcome::Responded(Box::new(reply))),
+            SyncMessage::SyncChanges {
+                namespace,
+                id,
+                changes,
+            } => {
+                self.sync_protocol
+                    .apply_sync_changes(peer_id, namespace, id, changes)
+                    .await?;
+                Ok(ReplayOutcome::Applied)
+            }
+            SyncMessage::FullDocument {
+                namespace,
+                id,
+                document,
+            } => {
+                self.sync_protocol
+                    .apply_full_document(peer_id, namespace, id, document)
+                    .await?;
+                Ok(ReplayOutcome::Applied)
+            }
+            SyncMessage::AutomergeSync {
+                namespace,
+                id,
+                message,
+            } => match self
+                .sync_protocol
+                .receive_sync(peer_id, namespace, id, message)
+                .await?
+            {
+                Some(reply) => Ok(ReplayOutcome::Responded(Box::new(reply))),
+                None => Ok(ReplayOutcome::Applied),
+            },
+            _ => Ok(ReplayOutcome::Skipped),
+        }
+    }
+}
+
+/// Get current timestamp in milliseconds.
+fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use automerge::{transaction::Transactable, ReadDoc, ROOT};
+    use vudo_state::DocumentId;
+
+    /// Build a document in a fresh engine and return its saved bytes.
+    async fn document_bytes(name: &str) -> Vec<u8> {
+        let engine = StateEngine::new().await.unwrap();
+        let handle = engine
+            .create_document(DocumentId::new("users", "alice"))
+            .await
+            .unwrap();
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "name", name)?;
+                Ok(())
+            })
+            .unwrap();
+        handle.save()
+    }
+
+    fn full_document(document: Vec<u8>) -> SyncMessage {
+        SyncMessage::FullDocument {
+            namespace: "users".to_string(),
+            id: "alice".to_string(),
+            document,
+        }
+    }
+
+    #[test]
+    fn test_record_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.vrec");
+
+        let recorder = SessionRecorder::create(&path, "node-a").unwrap();
+        recorder
+            .record("peer1", Direction::Outbound, &SyncMessage::Heartbeat)
+            .unwrap();
+        recorder
+            .record(
+                "peer2",
+                Direction::Inbound,
+                &SyncMessage::FullSync {
+                    namespace: "users".to_string(),
+                    id: "alice".to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(recorder.message_count(), 2);
+
+        let recording = Recording::load(&path).unwrap();
+        assert_eq!(recording.header.node_id, "node-a");
+        assert_eq!(recording.messages.len(), 2);
+        assert_eq!(recording.messages[0].direction, Direction::Outbound);
+        assert!(matches!(
+            recording.messages[1].message,
+            SyncMessage::FullSync { .. }
+        ));
+        assert_eq!(recording.inbound().count(), 1);
+        assert_eq!(recording.peers(), vec!["peer1", "peer2"]);
+    }
+
+    #[test]
+    fn test_load_truncated_recording() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.vrec");
+
+        let recorder = SessionRecorder::create(&path, "node-a").unwrap();
+        for _ in 0..3 {
+            recorder
+                .record("peer1", Direction::Inbound, &SyncMessage::Heartbeat)
+                .unwrap();
+        }
+        drop(recorder);
+
+        // Simulate a crash part-way through the last frame
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 2]).unwrap();
+
+        let recording = Recording::load(&path).unwrap();
+        assert_eq!(recording.messages.len(), 2);
+    }
+
+    #[test]
+    fn test_load_rejects_other_files() {
+        assert!(Recording::from_reader(&b"not a recording"[..]).is_err());
+
+        let mut bytes = RECORDING_MAGIC.to_vec();
+        bytes.extend_from_slice(&99u32.to_le_bytes());
+        assert!(Recording::from_reader(&bytes[..]).is_err());
+    }
+
+    #[test]
+    fn test_load_rejects_oversized_frame() {
+        let mut bytes = RECORDING_MAGIC.to_vec();
+        bytes.extend_from_slice(&RECORDING_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            Recording::from_reader(&bytes[..]),
+            Err(P2PError::DeserializationError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_replay_reproduces_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.vrec");
+
+        let recorder = SessionRecorder::create(&path, "node-a").unwrap();
+        recorder
+            .record(
+                "peer1",
+                Direction::Inbound,
+                &full_document(document_bytes("Alice").await),
+            )
+            .unwrap();
+        recorder
+            .record(
+                "peer1",
+                Direction::Outbound,
+                &SyncMessage::SyncComplete {
+                    namespace: "users".to_string(),
+                    id: "alice".to_string(),
+                    version: 1,
+                },
+            )
+            .unwrap();
+        recorder
+            .record(
+                "peer2",
+                Direction::Inbound,
+                &SyncMessage::SyncRequest {
+                    namespace: "users".to_string(),
+                    id: "alice".to_string(),
+                    last_sync: None,
+                },
+            )
+            .unwrap();
+        recorder
+            .record(
+                "peer2",
+                Direction::Inbound,
+                &SyncMessage::SyncRequest {
+                    namespace: "users".to_string(),
+                    id: "bob".to_string(),
+                    last_sync: None,
+                },
+            )
+            .unwrap();
+        let recording = Recording::load(&path).unwrap();
+
+        let engine = Arc::new(StateEngine::new().await.unwrap());
+        let replayer = SessionReplayer::new(Arc::clone(&engine));
+        let report = replayer.replay(&recording).await;
+        assert_eq!(report.applied, 1);
+        assert_eq!(report.responded, 1);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].index, 3);
+        assert!(!report.is_clean());
+
+        let handle = engine
+            .get_document(&DocumentId::new("users", "alice"))
+            .await
+            .unwrap();
+        let name = handle
+            .read(|doc| {
+                Ok(doc
+                    .get(ROOT, "name")?
+                    .and_then(|(value, _)| value.to_str().map(str::to_string)))
+            })
+            .unwrap();
+        assert_eq!(name.as_deref(), Some("Alice"));
+
+        // A second replay converges to the same heads
+        let other = Arc::new(StateEngine::new().await.unwrap());
+        SessionReplayer::new(Arc::clone(&other))
+            .replay(&recording)
+            .await;
+        let heads = |bytes: Vec<u8>| automerge::Automerge::load(&bytes).unwrap().get_heads();
+        let replayed = other
+            .get_document(&DocumentId::new("users", "alice"))
+            .await
+            .unwrap();
+        assert_eq!(heads(handle.save()), heads(replayed.save()));
+
+        // Replaying a prefix stops before the failing request
+        let replayer = SessionReplayer::new(Arc::new(StateEngine::new().await.unwrap()));
+        let report = replayer.replay_until(&recording, 3).await;
+        assert!(report.is_clean());
+        assert_eq!(report.applied + report.responded + report.skipped, 3);
+    }
+}
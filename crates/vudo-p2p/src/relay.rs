@@ -0,0 +1,557 @@
+//! Relay selection across multiple relay servers.
+//!
+//! When several relays are configured (for example one per region), a
+//! [`RelaySelector`] keeps a health record for each of them, fed by periodic
+//! latency probes, and assigns every peer the relay with the best score:
+//! smoothed probe latency, weighted by how many peers the relay already
+//! carries. Assignments are sticky - a peer only moves when its relay turns
+//! unhealthy, a dial through it fails, or another relay is clearly better -
+//! so probe jitter doesn't churn connections between relays.
+//!
+//! Probes measure the TCP connect time to the relay's HTTPS port, which is
+//! the round trip a relayed connection pays on its first hop.
+
+use crate::error::{P2PError, Result};
+use crate::supervisor::Heartbeat;
+use crate::sync_protocol::PeerId;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpStream;
+use tracing::{debug, info, warn};
+
+/// Default interval between relay probes.
+pub const DEFAULT_PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Timeout of a single relay probe.
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Consecutive probe failures after which a relay is considered unhealthy.
+pub const MAX_CONSECUTIVE_FAILURES: u32 = 3;
+
+/// Weight of a new latency sample in the moving average.
+const LATENCY_SMOOTHING: f64 = 0.3;
+
+/// Latency assumed for relays that have not been probed yet (milliseconds).
+const UNPROBED_LATENCY_MS: f64 = 1000.0;
+
+/// Number of assigned peers that doubles a relay's effective latency.
+const LOAD_PEERS: f64 = 50.0;
+
+/// A peer stays on its relay unless another one scores this much better.
+const STICKY_MARGIN: f64 = 0.25;
+
+/// Health of a relay server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelayHealth {
+    /// Relay URL.
+    pub url: String,
+    /// Smoothed probe latency in milliseconds, once probed.
+    pub latency_ms: Option<f64>,
+    /// Whether the relay is currently used for new assignments.
+    pub healthy: bool,
+    /// Consecutive failed probes or dials.
+    pub consecutive_failures: u32,
+    /// Number of peers assigned to the relay.
+    pub peers: usize,
+    /// Last probe timestamp (Unix epoch milliseconds).
+    pub last_probe: Option<u64>,
+}
+
+impl RelayHealth {
+    fn new(url: String) -> Self {
+        Self {
+            url,
+            latency_ms: None,
+            healthy: true,
+            consecutive_failures: 0,
+            peers: 0,
+            last_probe: None,
+        }
+    }
+
+    /// Selection score (lower is better), or `None` if unhealthy.
+    fn score(&self) -> Option<f64> {
+        if !self.healthy {
+            return None;
+        }
+        let latency = self.latency_ms.unwrap_or(UNPROBED_LATENCY_MS);
+        Some(latency * (1.0 + self.peers as f64 / LOAD_PEERS))
+    }
+}
+
+/// Relay health and peer assignments.
+#[derive(Debug, Default)]
+struct SelectorState {
+    /// Relays in configuration order.
+    relays: Vec<RelayHealth>,
+    /// Relay URL assigned to each peer.
+    assignments: HashMap<PeerId, String>,
+}
+
+impl SelectorState {
+    fn relay_mut(&mut self, url: &str) -> Option<&mut RelayHealth> {
+        self.relays.iter_mut().find(|relay| relay.url == url)
+    }
+
+    /// Best healthy relay other than `exclude`, with its score.
+    fn best(&self, exclude: Option<&str>) -> Option<(String, f64)> {
+        self.relays
+            .iter()
+            .filter(|relay| Some(relay.url.as_str()) != exclude)
+            .filter_map(|relay| relay.score().map(|score| (relay.url.clone(), score)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    fn assign(&mut self, peer_id: &PeerId, url: String) {
+        if let Some(relay) = self.relay_mut(&url) {
+            relay.peers += 1;
+        }
+        self.assignments.insert(peer_id.clone(), url);
+    }
+
+    fn unassign(&mut self, peer_id: &PeerId) -> Option<String> {
+        let url = self.assignments.remove(peer_id)?;
+        if let Some(relay) = self.relay_mut(&url) {
+            relay.peers = relay.peers.saturating_sub(1);
+        }
+        Some(url)
+    }
+
+    fn record_failure(&mut self, url: &str) {
+        if let Some(relay) = self.relay_mut(url) {
+            relay.consecutive_failures += 1;
+            if relay.healthy && relay.consecutive_failures >= MAX_CONSECUTIVE_FAILURES {
+                warn!("Relay {} marked unhealthy", relay.url);
+                relay.healthy = false;
+            }
+        }
+    }
+}
+
+/// Picks a relay per peer from probed relay health.
+#[derive(Debug, Default)]
+pub struct RelaySelector {
+    state: RwLock<SelectorState>,
+}
+
+impl RelaySelector {
+    /// Create a selector over a set of relay URLs.
+    pub fn new<I, S>(urls: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut relays: Vec<RelayHealth> = Vec::new();
+        for url in urls {
+            let url = url.into();
+            if !relays.iter().any(|relay| relay.url == url) {
+                relays.push(RelayHealth::new(url));
+            }
+        }
+
+        Self {
+            state: RwLock::new(SelectorState {
+                relays,
+                assignments: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Check whether no relays are configured.
+    pub fn is_empty(&self) -> bool {
+        self.state.read().relays.is_empty()
+    }
+
+    /// Configured relay URLs.
+    pub fn urls(&self) -> Vec<String> {
+        self.state
+            .read()
+            .relays
+            .iter()
+            .map(|relay| relay.url.clone())
+            .collect()
+    }
+
+    /// Health of all relays, in configuration order.
+    pub fn health(&self) -> Vec<RelayHealth> {
+        self.state.read().relays.clone()
+    }
+
+    /// Health of a single relay.
+    pub fn relay_health(&self, url: &str) -> Option<RelayHealth> {
+        self.state
+            .read()
+            .relays
+            .iter()
+            .find(|relay| relay.url == url)
+            .cloned()
+    }
+
+    /// Relay currently assigned to a peer.
+    pub fn assigned(&self, peer_id: &PeerId) -> Option<String> {
+        self.state.read().assignments.get(peer_id).cloned()
+    }
+
+    /// Record a successful probe of a relay.
+    ///
+    /// A single success makes an unhealthy relay eligible again.
+    pub fn record_latency(&self, url: &str, latency: Duration) {
+        let mut state = self.state.write();
+        if let Some(relay) = state.relay_mut(url) {
+            let sample = latency.as_secs_f64() * 1000.0;
+            relay.latency_ms = Some(match relay.latency_ms {
+                Some(average) => average + LATENCY_SMOOTHING * (sample - average),
+                None => sample,
+            });
+            relay.consecutive_failures = 0;
+            relay.last_probe = Some(now_millis());
+            if !relay.healthy {
+                info!("Relay {} is healthy again", relay.url);
+                relay.healthy = true;
+            }
+        }
+    }
+
+    /// Record a failed probe of a relay.
+    pub fn record_failure(&self, url: &str) {
+        let mut state = self.state.write();
+        state.record_failure(url);
+        if let Some(relay) = state.relay_mut(url) {
+            relay.last_probe = Some(now_millis());
+        }
+    }
+
+    /// Pick the relay for a peer.
+    ///
+    /// Keeps the peer's current relay while it is healthy and no other relay
+    /// scores more than [`STICKY_MARGIN`] better. Returns `None` when no relay
+    /// is healthy.
+    pub fn select(&self, peer_id: &PeerId) -> Option<String> {
+        let mut state = self.state.write();
+
+        // Score every relay as if the peer were unassigned
+        let current = state.unassign(peer_id);
+        let (best, best_score) = match state.best(None) {
+            Some(best) => best,
+            None => {
+                debug!("No healthy relay for peer {}", peer_id);
+                return None;
+            }
+        };
+
+        let current_score = current.as_deref().and_then(|url| {
+            state
+                .relays
+                .iter()
+                .find(|relay| relay.url == url)
+                .and_then(RelayHealth::score)
+        });
+        let chosen = match (current, current_score) {
+            (Some(current), Some(score)) if best_score >= score * (1.0 - STICKY_MARGIN) => current,
+            (current, _) => {
+                if let Some(current) = current {
+                    debug!("Moving peer {} from relay {} to {}", peer_id, current, best);
+                }
+                best
+            }
+        };
+
+        state.assign(peer_id, chosen.clone());
+        Some(chosen)
+    }
+
+    /// Move a peer off its relay after a failed dial.
+    ///
+    /// Counts the failure against the relay and assigns the best other healthy
+    /// relay, if any.
+    pub fn fail_over(&self, peer_id: &PeerId) -> Option<String> {
+        let mut state = self.state.write();
+        let failed = state.unassign(peer_id)?;
+        state.record_failure(&failed);
+
+        let (next, _) = state.best(Some(&failed))?;
+        debug!(
+            "Failing over peer {} from relay {} to {}",
+            peer_id, failed, next
+        );
+        state.assign(peer_id, next.clone());
+        Some(next)
+    }
+
+    /// Release a peer's relay assignment.
+    pub fn release(&self, peer_id: &PeerId) {
+        self.state.write().unassign(peer_id);
+    }
+
+    /// Probe every relay once and record the results.
+    pub async fn probe_all(&self) {
+        let urls = self.urls();
+        let results =
+            futures::future::join_all(urls.iter().map(|url| probe_latency(url, PROBE_TIMEOUT)))
+                .await;
+
+        for (url, result) in urls.iter().zip(results) {
+            match result {
+                Ok(latency) => {
+                    debug!("Relay {} probed in {:?}", url, latency);
+                    self.record_latency(url, latency);
+                }
+                Err(e) => {
+                    debug!("Relay {} probe failed: {}", url, e);
+                    self.record_failure(url);
+                }
+            }
+        }
+    }
+
+    /// Probe all relays every `interval` in the background.
+    ///
+    /// The task ends once the selector is dropped.
+    pub fn spawn_prober(self: &Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(self.prober(interval, Heartbeat::default()))
+    }
+
+    /// Probe loop of [`RelaySelector::spawn_prober`], for running under a
+    /// [`Supervisor`](crate::supervisor::Supervisor).
+    pub fn prober(
+        self: &Arc<Self>,
+        interval: Duration,
+        heartbeat: Heartbeat,
+    ) -> impl Future<Output = ()> + Send + 'static {
+        let selector: Weak<Self> = Arc::downgrade(self);
+        async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                match selector.upgrade() {
+                    Some(selector) => {
+                        let _busy = heartbeat.busy();
+                        selector.probe_all().await
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+}
+
+/// Measure the TCP connect time to a relay.
+pub async fn probe_latency(url: &str, timeout: Duration) -> Result<Duration> {
+    let authority = relay_authority(url)?;
+
+    // Resolve first so DNS lookups don't count towards the latency
+    let addr = tokio::time::timeout(timeout, tokio::net::lookup_host(authority.as_str()))
+        .await
+        .map_err(|_| P2PError::Timeout)?
+        .map_err(|e| P2PError::ConnectionFailed(format!("Relay {}: {}", url, e)))?
+        .next()
+        .ok_or_else(|| P2PError::ConnectionFailed(format!("Relay {} did not resolve", url)))?;
+
+    let start = Instant::now();
+    tokio::time::timeout(timeout, TcpStream::connect(addr))
+        .await
+        .map_err(|_| P2PError::Timeout)?
+        .map_err(|e| P2PError::ConnectionFailed(format!("Relay {}: {}", url, e)))?;
+    Ok(start.elapsed())
+}
+
+/// `host:port` of a relay URL, defaulting to the scheme's port.
+fn relay_authority(url: &str) -> Result<String> {
+    let (default_port, rest) = if let Some(rest) = url.strip_prefix("https://") {
+        (443, rest)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        (80, rest)
+    } else {
+        return Err(P2PError::InvalidMessage(format!(
+            "Invalid relay URL: {}",
+            url
+        )));
+    };
+
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    if authority.is_empty() {
+        return Err(P2PError::InvalidMessage(format!(
+            "Invalid relay URL: {}",
+            url
+        )));
+    }
+
+    let has_port = match authority.rfind(']') {
+        // IPv6 literal: a port follows the closing bracket
+        Some(end) => authority[end..].contains(':'),
+        None => authority.contains(':'),
+    };
+    if has_port {
+        Ok(authority.to_string())
+    } else {
+        Ok(format!("{}:{}", authority, default_port))
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EU: &str = "https://eu.relay.example";
+    const US: &str = "https://us.relay.example";
+
+    fn peer(n: usize) -> PeerId {
+        format!("peer-{}", n)
+    }
+
+    fn selector(latencies: &[(&str, u64)]) -> RelaySelector {
+        let selector = RelaySelector::new(latencies.iter().map(|(url, _)| *url));
+        for (url, ms) in latencies {
+            selector.record_latency(url, Duration::from_millis(*ms));
+        }
+        selector
+    }
+
+    #[test]
+    fn test_selects_lowest_latency() {
+        let selector = selector(&[(EU, 80), (US, 20)]);
+        assert_eq!(selector.select(&peer(1)).as_deref(), Some(US));
+        assert_eq!(selector.assigned(&peer(1)).as_deref(), Some(US));
+        assert_eq!(selector.relay_health(US).unwrap().peers, 1);
+    }
+
+    #[test]
+    fn test_load_spreads_peers() {
+        let selector = selector(&[(EU, 30), (US, 20)]);
+        let counts = (0..100).fold(HashMap::new(), |mut counts, n| {
+            *counts
+                .entry(selector.select(&peer(n)).unwrap())
+                .or_insert(0) += 1;
+            counts
+        });
+        assert!(counts[EU] > 0);
+        assert!(counts[US] > counts[EU]);
+    }
+
+    #[test]
+    fn test_selection_is_sticky() {
+        let selector = selector(&[(EU, 20), (US, 25)]);
+        assert_eq!(selector.select(&peer(1)).as_deref(), Some(EU));
+
+        // US becomes slightly faster: stay on EU
+        selector.record_latency(US, Duration::from_millis(5));
+        let us = selector.relay_health(US).unwrap().latency_ms.unwrap();
+        assert!(us < 20.0 && us > 20.0 * (1.0 - STICKY_MARGIN));
+        assert_eq!(selector.select(&peer(1)).as_deref(), Some(EU));
+
+        // US becomes much faster: move
+        for _ in 0..10 {
+            selector.record_latency(US, Duration::from_millis(1));
+        }
+        assert_eq!(selector.select(&peer(1)).as_deref(), Some(US));
+        assert_eq!(selector.relay_health(EU).unwrap().peers, 0);
+        assert_eq!(selector.relay_health(US).unwrap().peers, 1);
+    }
+
+    #[test]
+    fn test_unhealthy_relay_is_avoided() {
+        let selector = selector(&[(EU, 10), (US, 50)]);
+        assert_eq!(selector.select(&peer(1)).as_deref(), Some(EU));
+
+        for _ in 0..MAX_CONSECUTIVE_FAILURES {
+            selector.record_failure(EU);
+        }
+        assert!(!selector.relay_health(EU).unwrap().healthy);
+        assert_eq!(selector.select(&peer(1)).as_deref(), Some(US));
+
+        // Recovers after a successful probe
+        selector.record_latency(EU, Duration::from_millis(10));
+        assert!(selector.relay_health(EU).unwrap().healthy);
+        assert_eq!(selector.select(&peer(2)).as_deref(), Some(EU));
+
+        for _ in 0..MAX_CONSECUTIVE_FAILURES {
+            selector.record_failure(EU);
+            selector.record_failure(US);
+        }
+        assert_eq!(selector.select(&peer(3)), None);
+    }
+
+    #[test]
+    fn test_fail_over() {
+        let selector = selector(&[(EU, 10), (US, 50)]);
+        assert_eq!(selector.select(&peer(1)).as_deref(), Some(EU));
+
+        assert_eq!(selector.fail_over(&peer(1)).as_deref(), Some(US));
+        let eu = selector.relay_health(EU).unwrap();
+        assert_eq!(eu.consecutive_failures, 1);
+        assert_eq!(eu.peers, 0);
+        assert_eq!(selector.assigned(&peer(1)).as_deref(), Some(US));
+        assert_eq!(selector.fail_over(&peer(1)).as_deref(), Some(EU));
+
+        // Nothing left to fail over to
+        let single = self::selector(&[(EU, 10)]);
+        single.select(&peer(1));
+        assert_eq!(single.fail_over(&peer(1)), None);
+        assert_eq!(single.assigned(&peer(1)), None);
+    }
+
+    #[test]
+    fn test_release() {
+        let selector = selector(&[(EU, 10)]);
+        selector.select(&peer(1));
+        selector.select(&peer(1));
+        assert_eq!(selector.relay_health(EU).unwrap().peers, 1);
+
+        selector.release(&peer(1));
+        assert_eq!(selector.relay_health(EU).unwrap().peers, 0);
+        assert_eq!(selector.assigned(&peer(1)), None);
+    }
+
+    #[test]
+    fn test_relay_authority() {
+        assert_eq!(relay_authority(EU).unwrap(), "eu.relay.example:443");
+        assert_eq!(
+            relay_authority("https://eu.relay.example./").unwrap(),
+            "eu.relay.example.:443"
+        );
+        assert_eq!(
+            relay_authority("http://10.0.0.1:3340/relay").unwrap(),
+            "10.0.0.1:3340"
+        );
+        assert_eq!(relay_authority("https://[::1]").unwrap(), "[::1]:443");
+        assert_eq!(relay_authority("https://[::1]:8443").unwrap(), "[::1]:8443");
+        assert!(relay_authority("eu.relay.example").is_err());
+        assert!(relay_authority("https://").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_probe_all() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let up = format!("http://{}", listener.local_addr().unwrap());
+
+        // Bind and drop to get a port nothing listens on
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let down = format!("http://{}", closed.local_addr().unwrap());
+        drop(closed);
+
+        let selector = RelaySelector::new([up.clone(), down.clone()]);
+        selector.probe_all().await;
+
+        let up = selector.relay_health(&up).unwrap();
+        assert!(up.latency_ms.is_some());
+        assert!(up.last_probe.is_some());
+        assert_eq!(up.consecutive_failures, 0);
+
+        let down = selector.relay_health(&down).unwrap();
+        assert_eq!(down.latency_ms, None);
+        assert_eq!(down.consecutive_failures, 1);
+        assert!(down.last_probe.is_some());
+    }
+}
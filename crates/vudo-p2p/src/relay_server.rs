@@ -0,0 +1,168 @@
+//! Self-hosted relay server.
+//!
+//! A [`RelayServer`] runs an Iroh relay (plus an optional STUN server) so a
+//! deployment doesn't depend on the public Iroh relays. Nodes use it by
+//! listing its URL in [`P2PConfig::relays`](crate::P2PConfig::relays), and can
+//! be restricted to it with [`P2PConfig::relay_only`](crate::P2PConfig::relay_only).
+//!
+//! The server speaks plain HTTP; put it behind a TLS-terminating reverse
+//! proxy and advertise the proxy's `https://` URL as
+//! [`RelayServerConfig::public_url`]. The `vudo-relay` binary wraps this
+//! module (feature `relay-server`).
+
+use crate::error::{P2PError, Result};
+use crate::iroh_adapter::parse_relay_url;
+use iroh_relay::server::{Limits, RelayConfig, Server, ServerConfig, StunConfig};
+use std::net::SocketAddr;
+use tracing::info;
+
+/// Default HTTP port of the relay.
+pub const DEFAULT_RELAY_PORT: u16 = 3340;
+
+/// Default STUN port.
+pub const DEFAULT_STUN_PORT: u16 = 3478;
+
+/// Relay server configuration.
+#[derive(Debug, Clone)]
+pub struct RelayServerConfig {
+    /// Address the relay listens on.
+    pub http_addr: SocketAddr,
+    /// Address of the STUN server (disabled when `None`).
+    pub stun_addr: Option<SocketAddr>,
+    /// URL nodes reach the relay at, e.g. behind a reverse proxy (the
+    /// listening address when `None`).
+    pub public_url: Option<String>,
+    /// Maximum new connections accepted per second (unlimited when `None`).
+    pub accept_conn_limit: Option<f64>,
+}
+
+impl Default for RelayServerConfig {
+    fn default() -> Self {
+        Self {
+            http_addr: SocketAddr::from(([0, 0, 0, 0], DEFAULT_RELAY_PORT)),
+            stun_addr: Some(SocketAddr::from(([0, 0, 0, 0], DEFAULT_STUN_PORT))),
+            public_url: None,
+            accept_conn_limit: None,
+        }
+    }
+}
+
+/// A running relay server.
+pub struct RelayServer {
+    /// Iroh relay server.
+    server: Server,
+    /// URL nodes reach the relay at.
+    url: String,
+}
+
+impl RelayServer {
+    /// Start a relay server.
+    pub async fn spawn(config: RelayServerConfig) -> Result<Self> {
+        // Fail before binding anything
+        let public_url = config
+            .public_url
+            .as_deref()
+            .map(parse_relay_url)
+            .transpose()?;
+
+        let server_config: ServerConfig<std::io::Error> = ServerConfig {
+            relay: Some(RelayConfig {
+                http_bind_addr: config.http_addr,
+                tls: None,
+                limits: Limits {
+                    accept_conn_limit: config.accept_conn_limit,
+                    accept_conn_burst: None,
+                },
+            }),
+            stun: config.stun_addr.map(|bind_addr| StunConfig { bind_addr }),
+            metrics_addr: None,
+        };
+        let server = Server::spawn(server_config).await?;
+
+        let http_addr = server
+            .http_addr()
+            .ok_or_else(|| P2PError::Internal("Relay server has no HTTP address".to_string()))?;
+        // Normalized the way nodes print it, so it matches their relay health
+        let url = match public_url {
+            Some(url) => url.to_string(),
+            None => parse_relay_url(&format!("http://{}", http_addr))?.to_string(),
+        };
+        info!("Relay server listening on {} ({})", http_addr, url);
+
+        Ok(Self { server, url })
+    }
+
+    /// URL to list in [`P2PConfig::relays`](crate::P2PConfig::relays).
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Address the relay listens on.
+    pub fn http_addr(&self) -> Option<SocketAddr> {
+        self.server.http_addr()
+    }
+
+    /// Address of the STUN server, if enabled.
+    pub fn stun_addr(&self) -> Option<SocketAddr> {
+        self.server.stun_addr()
+    }
+
+    /// Stop the server.
+    pub async fn shutdown(self) -> Result<()> {
+        info!("Shutting down relay server {}", self.url);
+        Ok(self.server.shutdown().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iroh_adapter::{IrohAdapter, P2PConfig};
+
+    fn local_config() -> RelayServerConfig {
+        RelayServerConfig {
+            http_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+            stun_addr: None,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_relay_server() {
+        let server = RelayServer::spawn(local_config()).await.unwrap();
+        let addr = server.http_addr().unwrap();
+        assert_eq!(server.url(), format!("http://{}/", addr));
+        assert_eq!(server.stun_addr(), None);
+
+        // Nodes can be pointed at it, and see it as healthy
+        let config = P2PConfig {
+            relays: vec![server.url().to_string()],
+            relay_only: true,
+            ..Default::default()
+        };
+        let adapter = IrohAdapter::new(config).await.unwrap();
+        let relays = adapter.relay_selector();
+        relays.probe_all().await;
+        let health = relays.relay_health(server.url()).unwrap();
+        assert!(health.healthy && health.latency_ms.is_some());
+
+        server.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_public_url() {
+        let config = RelayServerConfig {
+            public_url: Some("https://relay.example".to_string()),
+            ..local_config()
+        };
+        let server = RelayServer::spawn(config).await.unwrap();
+        assert_eq!(server.url(), "https://relay.example/");
+        server.shutdown().await.unwrap();
+
+        let invalid = RelayServerConfig {
+            public_url: Some("not a url".to_string()),
+            ..local_config()
+        };
+        assert!(RelayServer::spawn(invalid).await.is_err());
+    }
+}
@@ -0,0 +1,380 @@
+//! Replication groups: sets of peers that should all hold a namespace.
+//!
+//! A [`ReplicationGroup`] names the members, by DID, that replicate a
+//! namespace, and how they sync it:
+//!
+//! - [`GroupTopology::FullMesh`]: every member syncs with every other one;
+//! - [`GroupTopology::Star`]: members sync with a hub only, which passes
+//!   changes on to the others.
+//!
+//! Peers are matched to members by the DID they authenticated as (see
+//! [`crate::auth`]), so members should require authentication and
+//! authenticate to each other. [`VudoP2P`](crate::VudoP2P) periodically
+//! syncs the group's documents with the connected members this node links
+//! to, and counts a member as a replica of a document once a sync with it
+//! converged after the document last changed. Replicas are counted from this
+//! node's syncs: in a star, only the hub sees every replica.
+
+use crate::error::{P2PError, Result};
+use crate::sync_protocol::{PeerId, SyncPhase, SyncProgress};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+use tracing::info;
+use vudo_state::DocumentMetadata;
+
+/// Default interval between replication passes.
+pub const DEFAULT_REPLICATION_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How the members of a group sync with each other.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum GroupTopology {
+    /// Every member syncs with every other member.
+    #[default]
+    FullMesh,
+    /// Members sync with the hub only.
+    Star {
+        /// DID of the hub.
+        hub: String,
+    },
+}
+
+/// Peers that should all hold a namespace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicationGroup {
+    /// Group name.
+    pub name: String,
+    /// Replicated namespace.
+    pub namespace: String,
+    /// DIDs of the members.
+    pub members: BTreeSet<String>,
+    /// How members sync with each other.
+    pub topology: GroupTopology,
+    /// Replicas each document needs, this node included (every member when
+    /// `None`).
+    pub min_replicas: Option<usize>,
+}
+
+impl ReplicationGroup {
+    /// Create a full-mesh group replicating `namespace`, with no members.
+    pub fn new(name: impl Into<String>, namespace: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            namespace: namespace.into(),
+            members: BTreeSet::new(),
+            topology: GroupTopology::FullMesh,
+            min_replicas: None,
+        }
+    }
+
+    /// Add a member.
+    pub fn member(mut self, did: impl Into<String>) -> Self {
+        self.members.insert(did.into());
+        self
+    }
+
+    /// Sync through a hub, which becomes a member.
+    pub fn star(mut self, hub: impl Into<String>) -> Self {
+        let hub = hub.into();
+        self.members.insert(hub.clone());
+        self.topology = GroupTopology::Star { hub };
+        self
+    }
+
+    /// Require fewer replicas than there are members.
+    pub fn with_min_replicas(mut self, replicas: usize) -> Self {
+        self.min_replicas = Some(replicas);
+        self
+    }
+
+    /// Number of replicas each document needs.
+    pub fn required_replicas(&self) -> usize {
+        let members = self.members.len();
+        self.min_replicas.map_or(members, |min| min.min(members))
+    }
+
+    /// Get the members a member syncs with.
+    pub fn partners(&self, did: &str) -> Vec<&str> {
+        if !self.members.contains(did) {
+            return Vec::new();
+        }
+        match &self.topology {
+            GroupTopology::Star { hub } if hub != did => vec![hub.as_str()],
+            _ => self
+                .members
+                .iter()
+                .map(String::as_str)
+                .filter(|member| *member != did)
+                .collect(),
+        }
+    }
+}
+
+/// Replication of a document within a group.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentReplication {
+    /// Group name.
+    pub group: String,
+    /// Document namespace.
+    pub namespace: String,
+    /// Document key.
+    pub document_id: String,
+    /// Members known to hold the document's current version, this node
+    /// included.
+    pub replicas: Vec<String>,
+    /// Replicas the group requires.
+    pub required: usize,
+}
+
+impl DocumentReplication {
+    /// Number of members holding the current version.
+    pub fn replication_factor(&self) -> usize {
+        self.replicas.len()
+    }
+
+    /// Check whether fewer members than required hold the current version.
+    pub fn is_under_replicated(&self) -> bool {
+        self.replicas.len() < self.required
+    }
+}
+
+/// A group this node is a member of.
+struct JoinedGroup {
+    /// The group.
+    group: ReplicationGroup,
+    /// DID this node is a member as.
+    local: String,
+}
+
+/// Replication groups of this node.
+pub struct ReplicationTracker {
+    /// Joined groups by name.
+    groups: RwLock<HashMap<String, JoinedGroup>>,
+    /// DIDs peers authenticated as, remembered after they disconnect.
+    dids: RwLock<HashMap<PeerId, String>>,
+}
+
+impl ReplicationTracker {
+    /// Create a tracker with no groups.
+    pub fn new() -> Self {
+        Self {
+            groups: RwLock::new(HashMap::new()),
+            dids: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Join a group as the member `local`, replacing a group of the same
+    /// name.
+    pub fn join(&self, group: ReplicationGroup, local: &str) -> Result<()> {
+        if !group.members.contains(local) {
+            return Err(P2PError::PermissionDenied(format!(
+                "{} is not a member of replication group {}",
+                local, group.name
+            )));
+        }
+        info!(
+            "Joined replication group {} for namespace {} ({} members)",
+            group.name,
+            group.namespace,
+            group.members.len()
+        );
+        let local = local.to_string();
+        self.groups
+            .write()
+            .insert(group.name.clone(), JoinedGroup { group, local });
+        Ok(())
+    }
+
+    /// Leave a group. Returns whether this node was a member.
+    pub fn leave(&self, name: &str) -> bool {
+        self.groups.write().remove(name).is_some()
+    }
+
+    /// Get a joined group.
+    pub fn group(&self, name: &str) -> Option<ReplicationGroup> {
+        self.groups
+            .read()
+            .get(name)
+            .map(|joined| joined.group.clone())
+    }
+
+    /// List the joined groups.
+    pub fn groups(&self) -> Vec<ReplicationGroup> {
+        self.groups
+            .read()
+            .values()
+            .map(|joined| joined.group.clone())
+            .collect()
+    }
+
+    /// Record the DID a peer authenticated as.
+    pub fn observe(&self, peer_id: &PeerId, did: &str) {
+        self.dids.write().insert(peer_id.clone(), did.to_string());
+    }
+
+    /// Get the namespaces this node replicates with a peer: those of the
+    /// groups in which it links to the peer's DID.
+    pub fn namespaces_for(&self, peer_id: &PeerId) -> Vec<String> {
+        let Some(did) = self.dids.read().get(peer_id).cloned() else {
+            return Vec::new();
+        };
+        let namespaces: BTreeSet<String> = self
+            .groups
+            .read()
+            .values()
+            .filter(|joined| joined.group.partners(&joined.local).contains(&did.as_str()))
+            .map(|joined| joined.group.namespace.clone())
+            .collect();
+        namespaces.into_iter().collect()
+    }
+
+    /// Count the replicas of a group's documents, or `None` if this node
+    /// isn't a member of the group.
+    ///
+    /// `documents` are the documents of the group's namespace held by this
+    /// node, and `progress` the sync progress with peers.
+    pub fn replication(
+        &self,
+        name: &str,
+        documents: &[DocumentMetadata],
+        progress: &[SyncProgress],
+    ) -> Option<Vec<DocumentReplication>> {
+        let groups = self.groups.read();
+        let joined = groups.get(name)?;
+        let group = &joined.group;
+        let dids = self.dids.read();
+
+        let replication = documents
+            .iter()
+            .filter(|metadata| metadata.id.namespace == group.namespace)
+            .map(|metadata| {
+                let mut replicas = BTreeSet::from([joined.local.clone()]);
+                replicas.extend(
+                    progress
+                        .iter()
+                        .filter(|p| {
+                            p.namespace == metadata.id.namespace
+                                && p.document_id == metadata.id.key
+                                && is_current(p, metadata)
+                        })
+                        .filter_map(|p| dids.get(&p.peer_id))
+                        .filter(|did| group.members.contains(*did))
+                        .cloned(),
+                );
+                DocumentReplication {
+                    group: group.name.clone(),
+                    namespace: metadata.id.namespace.clone(),
+                    document_id: metadata.id.key.clone(),
+                    replicas: replicas.into_iter().collect(),
+                    required: group.required_replicas(),
+                }
+            })
+            .collect();
+        Some(replication)
+    }
+}
+
+impl Default for ReplicationTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Check whether a sync converged after the document last changed, i.e.
+/// the peer holds the current version.
+pub(crate) fn is_current(progress: &SyncProgress, metadata: &DocumentMetadata) -> bool {
+    progress.phase == SyncPhase::InSync && progress.updated_at >= metadata.last_modified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vudo_state::DocumentId;
+
+    fn document(key: &str, last_modified: u64) -> DocumentMetadata {
+        DocumentMetadata {
+            id: DocumentId::new("notes", key),
+            created_at: 0,
+            last_modified,
+            size: 0,
+            version: 1,
+        }
+    }
+
+    fn progress(peer_id: &str, key: &str, phase: SyncPhase, updated_at: u64) -> SyncProgress {
+        SyncProgress {
+            peer_id: peer_id.to_string(),
+            namespace: "notes".to_string(),
+            document_id: key.to_string(),
+            phase,
+            rounds: 1,
+            bytes_sent: 0,
+            bytes_received: 0,
+            changes_applied: 0,
+            last_round_trip: None,
+            last_error: None,
+            updated_at,
+        }
+    }
+
+    #[test]
+    fn test_topology() {
+        let mesh = ReplicationGroup::new("family", "notes")
+            .member("did:key:laptop")
+            .member("did:key:phone")
+            .member("did:key:tablet");
+        assert_eq!(
+            mesh.partners("did:key:phone"),
+            vec!["did:key:laptop", "did:key:tablet"]
+        );
+        assert!(mesh.partners("did:key:stranger").is_empty());
+        assert_eq!(mesh.required_replicas(), 3);
+
+        let star = mesh.star("did:key:server").with_min_replicas(2);
+        assert_eq!(star.partners("did:key:phone"), vec!["did:key:server"]);
+        assert_eq!(star.partners("did:key:server").len(), 3);
+        assert_eq!(star.required_replicas(), 2);
+    }
+
+    #[test]
+    fn test_replication_factor() {
+        let tracker = ReplicationTracker::new();
+        let group = ReplicationGroup::new("family", "notes")
+            .member("did:key:laptop")
+            .member("did:key:phone")
+            .member("did:key:tablet");
+        assert!(tracker.join(group.clone(), "did:key:stranger").is_err());
+        tracker.join(group, "did:key:laptop").unwrap();
+
+        // Peers are matched to members once they authenticate
+        let (phone, tablet) = ("phone-node".to_string(), "tablet-node".to_string());
+        assert!(tracker.namespaces_for(&phone).is_empty());
+        tracker.observe(&phone, "did:key:phone");
+        tracker.observe(&tablet, "did:key:tablet");
+        assert_eq!(tracker.namespaces_for(&phone), vec!["notes".to_string()]);
+
+        // The tablet synced before the last change, so it's behind
+        let documents = [document("todo", 100), document("ideas", 100)];
+        let progress = [
+            progress(&phone, "todo", SyncPhase::InSync, 150),
+            progress(&tablet, "todo", SyncPhase::InSync, 50),
+            progress(&phone, "ideas", SyncPhase::Exchanging, 150),
+        ];
+        let replication = tracker
+            .replication("family", &documents, &progress)
+            .unwrap();
+        assert_eq!(
+            replication[0].replicas,
+            vec!["did:key:laptop".to_string(), "did:key:phone".to_string()]
+        );
+        assert_eq!(replication[0].replication_factor(), 2);
+        assert!(replication[0].is_under_replicated());
+        assert_eq!(replication[1].replication_factor(), 1);
+
+        assert!(tracker.leave("family"));
+        assert!(tracker
+            .replication("family", &documents, &progress)
+            .is_none());
+    }
+}
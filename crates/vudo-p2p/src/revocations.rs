@@ -0,0 +1,477 @@
+//! UCAN revocations synced between peers.
+//!
+//! Revocations published on any node are kept in one Automerge document,
+//! [`REVOCATIONS_NAMESPACE`]/[`REVOCATIONS_KEY`], holding a map key per
+//! revocation. Merging the document merges the underlying [`RevocationSet`],
+//! an OR-Set, so replicas converge whatever order changes arrive in.
+//!
+//! Only expiry removes a revocation, and each node prunes expired ones on
+//! its own. Peers deleting a revocation's key from the document withdraw
+//! nothing: the store keeps it and the next load writes it back.
+//!
+//! Nodes sync the document with peers as they connect, push it to every
+//! connected peer when a revocation is published or learned, and load it into
+//! their [`RevocationStore`] after each merge, so a revocation reaches the
+//! mesh within one sync cycle. [`SyncAuthPolicy`](crate::SyncAuthPolicy)
+//! consults the store when verifying session and publish tokens.
+//!
+//! The document also carries [`WipeInstruction`]s for devices their master
+//! unlinked. A node told which device identity it holds
+//! ([`RevocationRegistry::honor_wipes`]) deletes it from its keystore when an
+//! instruction for it arrives. There is no keystore on `wasm32`, so browsers
+//! only relay wipe instructions.
+//!
+//! Revocations and wipe instructions are signed, so the document is synced
+//! with every allowed peer, whatever its capabilities and the sync policy.
+//! An unlinked device thus still learns it was wiped.
+
+use crate::error::Result;
+use automerge::{transaction::Transactable, ReadDoc, ROOT};
+use parking_lot::RwLock;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::sync::mpsc;
+#[cfg(not(target_arch = "wasm32"))]
+use tracing::info;
+use tracing::warn;
+#[cfg(not(target_arch = "wasm32"))]
+use vudo_identity::{DeviceIdentity, Keystore};
+use vudo_identity::{Did, RevocationSet, RevocationStore, UcanRevocation, WipeInstruction};
+use vudo_state::{DocumentId, StateEngine};
+
+/// Namespace of the revocations document.
+pub const REVOCATIONS_NAMESPACE: &str = "_revocations";
+
+/// Key of the revocations document.
+pub const REVOCATIONS_KEY: &str = "ucan";
+
+/// Default interval between pruning and syncing revocations.
+pub const DEFAULT_REVOCATION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Map key prefix of a revocation.
+const REVOKED_PREFIX: &str = "revoked/";
+
+/// Map key prefix of a wipe instruction.
+const WIPE_PREFIX: &str = "wipe/";
+
+/// Check whether a document is the revocations document.
+pub fn is_revocations_document(namespace: &str, id: &str) -> bool {
+    namespace == REVOCATIONS_NAMESPACE && id == REVOCATIONS_KEY
+}
+
+/// UCAN revocations of a node, kept in the synced revocations document.
+pub struct RevocationRegistry {
+    /// Verified revocations.
+    store: Arc<RevocationStore>,
+    /// Wipe instructions with valid signatures, by tag.
+    wipes: RwLock<BTreeMap<String, WipeInstruction>>,
+    /// Device identity to delete when its master says so.
+    #[cfg(not(target_arch = "wasm32"))]
+    target: RwLock<Option<WipeTarget>>,
+    /// State engine holding the document.
+    state_engine: Arc<StateEngine>,
+}
+
+/// Device identity of this node, held in a keystore.
+#[cfg(not(target_arch = "wasm32"))]
+struct WipeTarget {
+    /// Keystore holding the identity.
+    keystore: Arc<Keystore>,
+    /// Label of the identity.
+    label: String,
+    /// The device, to check instructions against.
+    device: DeviceIdentity,
+    /// Notified when the identity is wiped.
+    events: mpsc::UnboundedSender<WipeInstruction>,
+}
+
+impl RevocationRegistry {
+    /// Create a registry backed by a state engine.
+    pub fn new(state_engine: Arc<StateEngine>) -> Self {
+        Self {
+            store: Arc::new(RevocationStore::new()),
+            wipes: RwLock::new(BTreeMap::new()),
+            #[cfg(not(target_arch = "wasm32"))]
+            target: RwLock::new(None),
+            state_engine,
+        }
+    }
+
+    /// Store consulted when verifying UCANs.
+    pub fn store(&self) -> &Arc<RevocationStore> {
+        &self.store
+    }
+
+    /// Publish a revocation.
+    ///
+    /// Returns false if it was already known.
+    pub async fn publish(&self, revocation: UcanRevocation) -> Result<bool> {
+        if !self.store.publish(revocation)? {
+            return Ok(false);
+        }
+        self.persist().await?;
+        Ok(true)
+    }
+
+    /// Publish a wipe instruction.
+    ///
+    /// Returns false if it was already known.
+    pub async fn publish_wipe(&self, wipe: WipeInstruction) -> Result<bool> {
+        wipe.verify()?;
+        if self.wipes.write().insert(wipe.tag(), wipe).is_some() {
+            return Ok(false);
+        }
+        self.persist().await?;
+        Ok(true)
+    }
+
+    /// Wipe instructions addressed to a device.
+    ///
+    /// Their signatures are valid, but whether the signer is the device's
+    /// master is only known to the device.
+    pub fn wipes_for(&self, device: &Did) -> Vec<WipeInstruction> {
+        self.wipes
+            .read()
+            .values()
+            .filter(|wipe| &wipe.device == device)
+            .cloned()
+            .collect()
+    }
+
+    /// Delete `device` from `keystore` once its master unlinks it.
+    ///
+    /// `label` is where the keystore holds the device. Instructions already
+    /// known are honored right away. The instruction is sent on the returned
+    /// channel when the device is wiped, so the application can clear its
+    /// data and stop the node.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn honor_wipes(
+        &self,
+        keystore: Arc<Keystore>,
+        label: &str,
+        device: &DeviceIdentity,
+    ) -> mpsc::UnboundedReceiver<WipeInstruction> {
+        let (events, rx) = mpsc::unbounded_channel();
+        *self.target.write() = Some(WipeTarget {
+            keystore,
+            label: label.to_string(),
+            device: device.clone(),
+            events,
+        });
+        self.apply_wipes().await;
+        rx
+    }
+
+    /// Wipe the target device if an instruction from its master is known.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn apply_wipes(&self) {
+        let (keystore, label, device) = match &*self.target.read() {
+            Some(target) => (
+                target.keystore.clone(),
+                target.label.clone(),
+                target.device.clone(),
+            ),
+            None => return,
+        };
+
+        for wipe in self.wipes_for(device.did()) {
+            if let Err(e) = wipe.verify_for(&device) {
+                warn!("Ignoring wipe instruction for {}: {}", device.did(), e);
+                continue;
+            }
+            match keystore.apply_wipe(&label, &wipe).await {
+                Ok(_) => {
+                    info!(
+                        "Wiped device {} on instruction of {}",
+                        device.did(),
+                        wipe.master
+                    );
+                    if let Some(target) = self.target.write().take() {
+                        let _ = target.events.send(wipe);
+                    }
+                    return;
+                }
+                Err(e) => warn!("Failed to wipe device {}: {}", device.did(), e),
+            }
+        }
+    }
+
+    /// Remove revocations of UCANs that have expired anyway.
+    pub async fn prune_expired(&self) -> Result<usize> {
+        let pruned = self.store.prune_expired();
+        if pruned > 0 {
+            self.persist().await?;
+        }
+        Ok(pruned)
+    }
+
+    /// Write the store's revocations to the document.
+    ///
+    /// Only adds what the document is missing, so unchanged revocations
+    /// produce no Automerge changes.
+    pub async fn persist(&self) -> Result<()> {
+        let doc_id = DocumentId::new(REVOCATIONS_NAMESPACE, REVOCATIONS_KEY);
+        let handle = match self.state_engine.get_document(&doc_id).await {
+            Ok(h) => h,
+            Err(_) => self.state_engine.create_document(doc_id).await?,
+        };
+
+        let set = self.store.snapshot();
+        let revoked = set
+            .iter()
+            .map(|revocation| {
+                let key = format!("{}{}", REVOKED_PREFIX, revocation.tag());
+                Ok((key, serde_json::to_string(revocation)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let pruned: Vec<String> = set
+            .removed()
+            .map(|tag| format!("{}{}", REVOKED_PREFIX, tag))
+            .collect();
+        let wipes = self
+            .wipes
+            .read()
+            .iter()
+            .map(|(tag, wipe)| {
+                let key = format!("{}{}", WIPE_PREFIX, tag);
+                Ok((key, serde_json::to_string(wipe)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        handle.update(|doc| {
+            let keys: HashSet<String> = doc.keys(ROOT).collect();
+            for key in &pruned {
+                if keys.contains(key) {
+                    doc.delete(ROOT, key.as_str())?;
+                }
+            }
+            for (key, json) in revoked.iter().chain(&wipes) {
+                if !keys.contains(key) {
+                    doc.put(ROOT, key.as_str(), json.as_str())?;
+                }
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// Load the document into the store.
+    ///
+    /// Unreadable and invalid entries are skipped, and so are removals by
+    /// peers: known revocations missing from the document are written back.
+    /// Returns the number of revocations and wipe instructions the registry
+    /// didn't know, after honoring any wipe instruction for this node's
+    /// device.
+    pub async fn load(&self) -> Result<usize> {
+        let doc_id = DocumentId::new(REVOCATIONS_NAMESPACE, REVOCATIONS_KEY);
+        let handle = match self.state_engine.get_document(&doc_id).await {
+            Ok(h) => h,
+            Err(_) => return Ok(0),
+        };
+
+        let (keys, revoked, wipes) = handle.read(|doc| {
+            let keys: HashSet<String> = doc.keys(ROOT).collect();
+            let mut revoked = Vec::new();
+            let mut wipes = Vec::new();
+            for key in &keys {
+                if key.starts_with(REVOKED_PREFIX) || key.starts_with(WIPE_PREFIX) {
+                    if let Some((value, _)) = doc.get(ROOT, key.as_str())? {
+                        if let Some(json) = value.to_str() {
+                            if key.starts_with(WIPE_PREFIX) {
+                                wipes.push(json.to_string());
+                            } else {
+                                revoked.push(json.to_string());
+                            }
+                        }
+                    }
+                }
+            }
+            Ok((keys, revoked, wipes))
+        })?;
+
+        let mut set = RevocationSet::new();
+        for json in revoked {
+            match serde_json::from_str::<UcanRevocation>(&json) {
+                Ok(revocation) => {
+                    set.insert(revocation);
+                }
+                Err(e) => warn!("Skipping unreadable revocation: {}", e),
+            }
+        }
+        let mut learned = self.store.merge(&set);
+
+        for json in wipes {
+            let wipe = match serde_json::from_str::<WipeInstruction>(&json) {
+                Ok(wipe) => wipe,
+                Err(e) => {
+                    warn!("Skipping unreadable wipe instruction: {}", e);
+                    continue;
+                }
+            };
+            if self.wipes.read().contains_key(&wipe.tag()) {
+                continue;
+            }
+            if let Err(e) = wipe.verify() {
+                warn!("Skipping invalid wipe instruction: {}", e);
+                continue;
+            }
+            self.wipes.write().insert(wipe.tag(), wipe);
+            learned += 1;
+        }
+
+        let missing = |prefix: &str, tag: &str| !keys.contains(&format!("{}{}", prefix, tag));
+        let withdrawn = self
+            .store
+            .snapshot()
+            .iter()
+            .any(|revocation| missing(REVOKED_PREFIX, &revocation.tag()))
+            || self
+                .wipes
+                .read()
+                .keys()
+                .any(|tag| missing(WIPE_PREFIX, tag));
+        if withdrawn {
+            self.persist().await?;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if learned > 0 {
+            self.apply_wipes().await;
+        }
+        Ok(learned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use vudo_identity::{Capability, KdfParams, MasterIdentity, Ucan};
+
+    #[tokio::test]
+    async fn test_revocations_document_merge() {
+        let device = DeviceIdentity::generate("Phone").await.unwrap();
+        let app = DeviceIdentity::generate("Notes").await.unwrap();
+        let ucan = Ucan::new(
+            device.did().clone(),
+            app.did().clone(),
+            vec![Capability::wildcard("vudo://notes/")],
+            Utc::now().timestamp() as u64 + 3600,
+            None,
+            None,
+            vec![],
+        )
+        .sign(&device.signing_key())
+        .unwrap();
+        let revocation =
+            UcanRevocation::new(&ucan, device.did().clone(), None, &device.signing_key()).unwrap();
+
+        let alice = RevocationRegistry::new(Arc::new(StateEngine::new().await.unwrap()));
+        let bob = RevocationRegistry::new(Arc::new(StateEngine::new().await.unwrap()));
+        assert!(alice.publish(revocation.clone()).await.unwrap());
+        assert!(!alice.publish(revocation).await.unwrap());
+        assert!(alice.store().verify(&ucan).is_err());
+
+        // Syncing the document carries the revocation to Bob
+        let doc_id = DocumentId::new(REVOCATIONS_NAMESPACE, REVOCATIONS_KEY);
+        let bytes = alice
+            .state_engine
+            .get_document(&doc_id)
+            .await
+            .unwrap()
+            .save();
+        let handle = bob
+            .state_engine
+            .create_document(doc_id.clone())
+            .await
+            .unwrap();
+        handle.load_incremental(&bytes).unwrap();
+        assert_eq!(bob.load().await.unwrap(), 1);
+        assert_eq!(bob.load().await.unwrap(), 0);
+        assert!(bob.store().verify(&ucan).is_err());
+
+        // A peer's unsigned removal withdraws nothing
+        let tag = alice.store().snapshot().iter().next().unwrap().tag();
+        let mallory = StateEngine::new().await.unwrap();
+        let forged = mallory.create_document(doc_id.clone()).await.unwrap();
+        forged.load_incremental(&handle.save()).unwrap();
+        forged
+            .update(|doc| {
+                doc.put(ROOT, format!("removed/{}", tag).as_str(), true)?;
+                doc.delete(ROOT, format!("{}{}", REVOKED_PREFIX, tag).as_str())?;
+                Ok(())
+            })
+            .unwrap();
+        handle.load_incremental(&forged.save()).unwrap();
+        assert_eq!(bob.load().await.unwrap(), 0);
+        assert!(bob.store().verify(&ucan).is_err());
+
+        // And the revocation is written back for peers that took it
+        let carol = RevocationRegistry::new(Arc::new(StateEngine::new().await.unwrap()));
+        let copy = carol.state_engine.create_document(doc_id).await.unwrap();
+        copy.load_incremental(&forged.save()).unwrap();
+        copy.load_incremental(&handle.save()).unwrap();
+        assert_eq!(carol.load().await.unwrap(), 1);
+        assert!(carol.store().verify(&ucan).is_err());
+        assert!(is_revocations_document(
+            REVOCATIONS_NAMESPACE,
+            REVOCATIONS_KEY
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_wipe_unlinked_device() {
+        let mut master = MasterIdentity::generate("Alice").await.unwrap();
+        let mut phone = DeviceIdentity::generate("Phone").await.unwrap();
+        let master_key = master.signing_key();
+        let link = master
+            .link_device("Phone".to_string(), phone.did().clone(), &master_key)
+            .await
+            .unwrap();
+        phone.link_to_master(master.did.clone(), link.authorization);
+
+        let dir = tempfile::tempdir().unwrap();
+        let keystore = Arc::new(
+            Keystore::with_passphrase(dir.path(), "hunter2")
+                .with_kdf_params(KdfParams::INTERACTIVE),
+        );
+        keystore.save_device("device", &phone).await.unwrap();
+
+        let laptop = RevocationRegistry::new(Arc::new(StateEngine::new().await.unwrap()));
+        let stolen = RevocationRegistry::new(Arc::new(StateEngine::new().await.unwrap()));
+        let mut wiped = stolen.honor_wipes(keystore.clone(), "device", &phone).await;
+
+        // Instructions from anyone but the phone's master are ignored
+        let mallory = MasterIdentity::generate("Mallory").await.unwrap();
+        let forged = WipeInstruction::new(
+            mallory.did.clone(),
+            phone.did().clone(),
+            None,
+            vec![],
+            &mallory.signing_key(),
+        )
+        .unwrap();
+        assert!(laptop.publish_wipe(forged).await.unwrap());
+
+        let unlink = master.unlink_device(phone.did(), None).unwrap();
+        assert!(laptop.publish(unlink.revocation.unwrap()).await.unwrap());
+        assert!(laptop.publish_wipe(unlink.wipe.clone()).await.unwrap());
+        assert!(!laptop.publish_wipe(unlink.wipe).await.unwrap());
+        assert_eq!(laptop.wipes_for(phone.did()).len(), 2);
+
+        // The phone is wiped on its next sync
+        let doc_id = DocumentId::new(REVOCATIONS_NAMESPACE, REVOCATIONS_KEY);
+        let bytes = laptop
+            .state_engine
+            .get_document(&doc_id)
+            .await
+            .unwrap()
+            .save();
+        let handle = stolen.state_engine.create_document(doc_id).await.unwrap();
+        handle.load_incremental(&bytes).unwrap();
+        assert_eq!(stolen.load().await.unwrap(), 3);
+        assert_eq!(wiped.try_recv().unwrap().master, master.did);
+        assert!(!keystore.contains("device").await.unwrap());
+    }
+}
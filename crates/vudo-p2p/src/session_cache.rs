@@ -0,0 +1,356 @@
+//! Address hints for recently-seen peers.
+//!
+//! Reconnecting to a peer normally costs a discovery lookup (DHT, mDNS or
+//! relay) before the QUIC handshake can start. The session cache remembers
+//! the last working relay URL and direct addresses of every peer we connected
+//! to, so a reconnect - including one after a restart, when
+//! [`P2PConfig::session_cache_path`] is set - dials the last known path
+//! straight away instead of waiting for discovery.
+//!
+//! Session resumption is not supported: no QUIC/TLS session tickets are
+//! cached and reconnects never use 0-RTT, so every reconnect runs a full
+//! handshake. Iroh 0.28 does not expose tickets for export.
+//!
+//! Hints reveal which peers we talk to and where they were reachable, so they
+//! are kept in a local file and never in the state engine, whose documents
+//! sync to peers.
+//!
+//! Hints convert to and from Iroh node addresses on native builds only.
+//!
+//! [`P2PConfig::session_cache_path`]: crate::P2PConfig::session_cache_path
+
+use crate::error::{P2PError, Result};
+use crate::sync_protocol::PeerId;
+#[cfg(not(target_arch = "wasm32"))]
+use iroh::net::{NodeAddr, NodeId, RelayUrl};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+#[cfg(not(target_arch = "wasm32"))]
+use tracing::debug;
+use tracing::warn;
+use vudo_state::{DocumentId, StateEngine};
+
+/// Namespace of the P2P layer's own documents (sync states, peer access,
+/// tombstones).
+///
+/// Session hints are not stored here: they stay in a local file.
+pub const SESSION_CACHE_NAMESPACE: &str = "_p2p";
+
+/// Key of the synced document older versions kept session hints in.
+const LEGACY_SESSION_CACHE_KEY: &str = "sessions";
+
+/// Address hint for a recently-seen peer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerHint {
+    /// Peer ID (Iroh node ID).
+    pub peer_id: PeerId,
+    /// Relay URL the peer was reachable through.
+    pub relay_url: Option<String>,
+    /// Direct addresses the peer was reachable at.
+    pub direct_addresses: Vec<SocketAddr>,
+    /// Last successful connection (milliseconds since epoch).
+    pub last_connected: u64,
+}
+
+impl PeerHint {
+    /// Create a hint from a node address.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_node_addr(node_addr: &NodeAddr) -> Self {
+        Self {
+            peer_id: node_addr.node_id.to_string(),
+            relay_url: node_addr.relay_url().map(|url| url.to_string()),
+            direct_addresses: node_addr.direct_addresses().copied().collect(),
+            last_connected: current_timestamp(),
+        }
+    }
+
+    /// Check whether the hint carries any address to dial.
+    pub fn has_addresses(&self) -> bool {
+        self.relay_url.is_some() || !self.direct_addresses.is_empty()
+    }
+
+    /// Convert the hint back into a dialable node address.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn to_node_addr(&self) -> Result<NodeAddr> {
+        let node_id: NodeId = self
+            .peer_id
+            .parse()
+            .map_err(|e| P2PError::InvalidMessage(format!("Invalid node ID in hint: {}", e)))?;
+
+        let mut node_addr =
+            NodeAddr::new(node_id).with_direct_addresses(self.direct_addresses.iter().copied());
+        if let Some(url) = &self.relay_url {
+            let url: RelayUrl = url.parse().map_err(|e| {
+                P2PError::InvalidMessage(format!("Invalid relay URL in hint: {}", e))
+            })?;
+            node_addr = node_addr.with_relay_url(url);
+        }
+
+        Ok(node_addr)
+    }
+}
+
+/// Cache of address hints for recently-seen peers.
+pub struct SessionCache {
+    /// Hints keyed by peer ID.
+    hints: RwLock<HashMap<PeerId, PeerHint>>,
+    /// How long a hint stays valid after the last connection.
+    ttl: Duration,
+}
+
+impl SessionCache {
+    /// Create a new session cache.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            hints: RwLock::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    /// Record a successful connection to a peer.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn record(&self, node_addr: &NodeAddr) {
+        let hint = PeerHint::from_node_addr(node_addr);
+        if !hint.has_addresses() {
+            // Keep the previous path rather than overwriting it with nothing
+            if let Some(existing) = self.hints.write().get_mut(&hint.peer_id) {
+                existing.last_connected = hint.last_connected;
+            }
+            return;
+        }
+
+        debug!("Caching session hint for peer {}", hint.peer_id);
+        self.hints.write().insert(hint.peer_id.clone(), hint);
+    }
+
+    /// Get the hint for a peer, if it has not expired.
+    pub fn hint(&self, peer_id: &PeerId) -> Option<PeerHint> {
+        self.hints
+            .read()
+            .get(peer_id)
+            .filter(|hint| !self.is_expired(hint))
+            .cloned()
+    }
+
+    /// Check whether a peer was seen within the cache TTL.
+    pub fn is_known(&self, peer_id: &PeerId) -> bool {
+        self.hint(peer_id).is_some()
+    }
+
+    /// Fill in cached addresses for a node address without any.
+    ///
+    /// Addresses supplied by the caller always take precedence over the cache.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn resolve(&self, node_addr: NodeAddr) -> NodeAddr {
+        if node_addr.relay_url().is_some() || node_addr.direct_addresses().next().is_some() {
+            return node_addr;
+        }
+
+        match self
+            .hint(&node_addr.node_id.to_string())
+            .map(|hint| hint.to_node_addr())
+        {
+            Some(Ok(cached)) => cached,
+            Some(Err(e)) => {
+                warn!("Ignoring invalid session hint: {}", e);
+                node_addr
+            }
+            None => node_addr,
+        }
+    }
+
+    /// Get all unexpired hints as dialable node addresses.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn known_addrs(&self) -> Vec<NodeAddr> {
+        self.hints
+            .read()
+            .values()
+            .filter(|hint| !self.is_expired(hint))
+            .filter_map(|hint| hint.to_node_addr().ok())
+            .collect()
+    }
+
+    /// Remove expired hints.
+    pub fn prune(&self) -> usize {
+        let mut hints = self.hints.write();
+        let before = hints.len();
+        hints.retain(|_, hint| !self.is_expired(hint));
+        before - hints.len()
+    }
+
+    /// Remove the hint for a peer.
+    pub fn forget(&self, peer_id: &PeerId) {
+        self.hints.write().remove(peer_id);
+    }
+
+    /// Get number of cached hints.
+    pub fn len(&self) -> usize {
+        self.hints.read().len()
+    }
+
+    /// Check whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.hints.read().is_empty()
+    }
+
+    /// Persist unexpired hints to a local file.
+    ///
+    /// The file is replaced atomically. Returns the number of hints written.
+    pub fn persist(&self, path: &Path) -> Result<usize> {
+        self.prune();
+        let hints: Vec<PeerHint> = self.hints.read().values().cloned().collect();
+        let json = serde_json::to_vec(&hints)?;
+
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                P2PError::Internal(format!("Failed to create {}: {}", dir.display(), e))
+            })?;
+        }
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, json)
+            .and_then(|_| std::fs::rename(&tmp, path))
+            .map_err(|e| {
+                P2PError::Internal(format!("Failed to write {}: {}", path.display(), e))
+            })?;
+
+        Ok(hints.len())
+    }
+
+    /// Load persisted hints from a local file.
+    ///
+    /// A missing file loads nothing. Expired or unreadable hints are skipped.
+    /// Returns the number of hints loaded.
+    pub fn load(&self, path: &Path) -> Result<usize> {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => {
+                return Err(P2PError::Internal(format!(
+                    "Failed to read {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+        let stored: Vec<serde_json::Value> = serde_json::from_slice(&bytes)
+            .map_err(|e| P2PError::DeserializationError(e.to_string()))?;
+
+        let mut hints = self.hints.write();
+        let mut loaded = 0;
+        for value in stored {
+            match serde_json::from_value::<PeerHint>(value) {
+                Ok(hint) if !self.is_expired(&hint) => {
+                    hints.insert(hint.peer_id.clone(), hint);
+                    loaded += 1;
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Skipping unreadable session hint: {}", e),
+            }
+        }
+
+        Ok(loaded)
+    }
+
+    /// Delete session hints older versions stored in the state engine.
+    ///
+    /// Returns whether a legacy document was found.
+    pub async fn remove_legacy(state_engine: &StateEngine) -> Result<bool> {
+        let doc_id = DocumentId::new(SESSION_CACHE_NAMESPACE, LEGACY_SESSION_CACHE_KEY);
+        if state_engine.get_document(&doc_id).await.is_err() {
+            return Ok(false);
+        }
+        state_engine.delete_document(&doc_id).await?;
+        Ok(true)
+    }
+
+    fn is_expired(&self, hint: &PeerHint) -> bool {
+        current_timestamp().saturating_sub(hint.last_connected) > self.ttl.as_millis() as u64
+    }
+}
+
+/// Get current timestamp in milliseconds.
+fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hint(peer_id: &str, last_connected: u64) -> PeerHint {
+        PeerHint {
+            peer_id: peer_id.to_string(),
+            relay_url: Some("https://relay.example.org".to_string()),
+            direct_addresses: vec!["192.168.1.20:4433".parse().unwrap()],
+            last_connected,
+        }
+    }
+
+    #[test]
+    fn test_hint_expiry() {
+        let cache = SessionCache::new(Duration::from_secs(60));
+        cache
+            .hints
+            .write()
+            .insert("fresh".to_string(), hint("fresh", current_timestamp()));
+        cache
+            .hints
+            .write()
+            .insert("stale".to_string(), hint("stale", 0));
+
+        assert!(cache.is_known(&"fresh".to_string()));
+        assert!(!cache.is_known(&"stale".to_string()));
+        assert_eq!(cache.prune(), 1);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_persist_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("p2p").join("sessions.json");
+        let cache = SessionCache::new(Duration::from_secs(60));
+        let fresh = hint("fresh", current_timestamp());
+        cache
+            .hints
+            .write()
+            .insert("fresh".to_string(), fresh.clone());
+        cache
+            .hints
+            .write()
+            .insert("stale".to_string(), hint("stale", 0));
+
+        assert_eq!(cache.persist(&path).unwrap(), 1);
+
+        let restored = SessionCache::new(Duration::from_secs(60));
+        assert_eq!(restored.load(&path).unwrap(), 1);
+        assert_eq!(restored.hint(&"fresh".to_string()), Some(fresh));
+
+        // Missing files load nothing
+        let empty = SessionCache::new(Duration::from_secs(60));
+        assert_eq!(empty.load(&dir.path().join("missing.json")).unwrap(), 0);
+
+        // Forgotten peers are dropped from storage on the next persist
+        cache.forget(&"fresh".to_string());
+        assert_eq!(cache.persist(&path).unwrap(), 0);
+        let restored = SessionCache::new(Duration::from_secs(60));
+        assert_eq!(restored.load(&path).unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_remove_legacy() {
+        let engine = StateEngine::new().await.unwrap();
+        let doc_id = DocumentId::new(SESSION_CACHE_NAMESPACE, LEGACY_SESSION_CACHE_KEY);
+        engine.create_document(doc_id.clone()).await.unwrap();
+
+        assert!(SessionCache::remove_legacy(&engine).await.unwrap());
+        assert!(engine.get_document(&doc_id).await.is_err());
+        assert!(!SessionCache::remove_legacy(&engine).await.unwrap());
+    }
+}
@@ -0,0 +1,834 @@
+//! Watchdog and restart supervision for node subsystems.
+//!
+//! A long-running node runs several background loops: the message handler,
+//! background sync, peer discovery, relay probing and connection pool
+//! maintenance. A [`Supervisor`] watches
+//! them and restarts a loop that panics or stalls, with exponential backoff,
+//! recording an [`Incident`] each time, so a node recovers without a manual
+//! restart. Health checks, e.g. of storage, run on an interval and retry
+//! their recovery action with the same backoff.
+//!
+//! Supervised loops report progress through a [`Heartbeat`]. A loop holds a
+//! [`Heartbeat::busy`] guard while it works on something, and is stalled when
+//! it stays busy longer than the stall timeout; a loop waiting for messages
+//! is never restarted for being idle. Loops that return normally are not
+//! restarted.
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::control::ErrorLog;
+use crate::error::Result;
+use futures::future::BoxFuture;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+/// Number of recent incidents kept.
+pub const INCIDENT_CAPACITY: usize = 128;
+
+/// A supervised part of the node.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Subsystem {
+    /// Loop handling incoming sync messages.
+    MessageHandler,
+    /// Background sync scheduling.
+    BackgroundSync,
+    /// Peer discovery and stale peer cleanup.
+    Discovery,
+    /// Relay latency probing.
+    RelayProber,
+    /// Connection pool maintenance: reconnects and idle expiry.
+    ConnectionPool,
+    /// Blocking and pairing of connecting peers.
+    PeerAccess,
+    /// Presence heartbeats and timeouts.
+    Presence,
+    /// Syncing replication groups.
+    Replication,
+    /// Fetching and expiring store-and-forward mail.
+    Mailbox,
+    /// Pruning and syncing UCAN revocations.
+    Revocations,
+    /// Document storage.
+    Storage,
+    /// An application-defined subsystem.
+    Other(String),
+}
+
+impl fmt::Display for Subsystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MessageHandler => write!(f, "message-handler"),
+            Self::BackgroundSync => write!(f, "background-sync"),
+            Self::Discovery => write!(f, "discovery"),
+            Self::RelayProber => write!(f, "relay-prober"),
+            Self::ConnectionPool => write!(f, "connection-pool"),
+            Self::PeerAccess => write!(f, "peer-access"),
+            Self::Presence => write!(f, "presence"),
+            Self::Replication => write!(f, "replication"),
+            Self::Mailbox => write!(f, "mailbox"),
+            Self::Revocations => write!(f, "revocations"),
+            Self::Storage => write!(f, "storage"),
+            Self::Other(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Supervisor configuration.
+#[derive(Debug, Clone)]
+pub struct SupervisorConfig {
+    /// Interval between checks of the supervised loops.
+    pub check_interval: Duration,
+    /// How long a loop may stay busy before it's restarted.
+    pub stall_timeout: Duration,
+    /// Delay before the first restart after a failure.
+    pub initial_backoff: Duration,
+    /// Maximum delay between restarts.
+    pub max_backoff: Duration,
+    /// Running this long without a failure resets the backoff.
+    pub stable_after: Duration,
+    /// Interval between health checks.
+    pub health_check_interval: Duration,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            check_interval: Duration::from_secs(1),
+            stall_timeout: Duration::from_secs(120),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            stable_after: Duration::from_secs(300),
+            health_check_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+impl SupervisorConfig {
+    /// Backoff before the restart following `failures` consecutive failures.
+    pub fn backoff(&self, failures: u32) -> Duration {
+        let factor = 2u32.saturating_pow(failures.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// What went wrong with a subsystem.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IncidentKind {
+    /// The loop panicked.
+    Panicked {
+        /// Panic message.
+        message: String,
+    },
+    /// The loop stayed busy longer than the stall timeout.
+    Stalled {
+        /// How long it had been busy, in milliseconds.
+        busy_ms: u64,
+    },
+    /// A health check failed.
+    Unhealthy {
+        /// Error reported by the check.
+        error: String,
+    },
+    /// The recovery action of a failed health check failed.
+    RecoveryFailed {
+        /// Error reported by the recovery action.
+        error: String,
+    },
+}
+
+impl fmt::Display for IncidentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Panicked { message } => write!(f, "panicked: {}", message),
+            Self::Stalled { busy_ms } => write!(f, "stalled for {} ms", busy_ms),
+            Self::Unhealthy { error } => write!(f, "health check failed: {}", error),
+            Self::RecoveryFailed { error } => write!(f, "recovery failed: {}", error),
+        }
+    }
+}
+
+/// A failure of a subsystem and the supervisor's response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Incident {
+    /// Affected subsystem.
+    pub subsystem: Subsystem,
+    /// What went wrong.
+    #[serde(flatten)]
+    pub kind: IncidentKind,
+    /// Timestamp (Unix epoch milliseconds).
+    pub timestamp: u64,
+    /// Consecutive failures of the subsystem, including this one.
+    pub failures: u32,
+    /// Delay before the restart or next check, in milliseconds.
+    pub retry_in_ms: u64,
+}
+
+impl fmt::Display for Incident {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} (failure {}, retrying in {} ms)",
+            self.subsystem, self.kind, self.failures, self.retry_in_ms
+        )
+    }
+}
+
+/// Progress reporting handle of a supervised loop.
+#[derive(Debug, Clone, Default)]
+pub struct Heartbeat {
+    /// When the loop last became busy or reported progress, while busy.
+    busy_since: Arc<Mutex<Option<Instant>>>,
+}
+
+impl Heartbeat {
+    /// Mark the loop busy until the guard is dropped.
+    pub fn busy(&self) -> BusyGuard<'_> {
+        *self.busy_since.lock() = Some(Instant::now());
+        BusyGuard { heartbeat: self }
+    }
+
+    /// Report progress of long-running work, restarting the stall timer.
+    pub fn beat(&self) {
+        let mut busy_since = self.busy_since.lock();
+        if busy_since.is_some() {
+            *busy_since = Some(Instant::now());
+        }
+    }
+
+    /// How long the loop has been busy without progress, or None when idle.
+    pub fn busy_for(&self) -> Option<Duration> {
+        self.busy_since.lock().map(|since| since.elapsed())
+    }
+}
+
+/// Marks a loop busy while alive; see [`Heartbeat::busy`].
+pub struct BusyGuard<'a> {
+    heartbeat: &'a Heartbeat,
+}
+
+impl Drop for BusyGuard<'_> {
+    fn drop(&mut self) {
+        *self.heartbeat.busy_since.lock() = None;
+    }
+}
+
+/// Creates a fresh instance of a supervised loop.
+type LoopFactory = Arc<dyn Fn(Heartbeat) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// Async health check or recovery action.
+type Check = Arc<dyn Fn() -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+/// A supervised loop.
+struct SupervisedLoop {
+    factory: LoopFactory,
+    /// Running task, or None while waiting to restart.
+    handle: Option<JoinHandle<()>>,
+    heartbeat: Heartbeat,
+    /// When the current run started.
+    started_at: Instant,
+    /// When to restart after a failure.
+    restart_at: Option<Instant>,
+    /// Consecutive failures.
+    failures: u32,
+    /// Total restarts.
+    restarts: u32,
+}
+
+/// A periodic health check.
+struct HealthCheck {
+    check: Check,
+    recover: Check,
+    /// When to run next.
+    next_at: Instant,
+    /// Consecutive failures.
+    failures: u32,
+    /// Whether a check is running.
+    running: bool,
+}
+
+/// Watches subsystems and restarts them when they fail.
+pub struct Supervisor {
+    config: SupervisorConfig,
+    loops: Mutex<HashMap<Subsystem, SupervisedLoop>>,
+    checks: Mutex<HashMap<Subsystem, HealthCheck>>,
+    /// Recent incidents, oldest first.
+    incidents: Mutex<VecDeque<Incident>>,
+    subscribers: Mutex<Vec<mpsc::UnboundedSender<Incident>>>,
+    /// Also records incidents here, for the control API.
+    #[cfg(not(target_arch = "wasm32"))]
+    error_log: Option<Arc<ErrorLog>>,
+    /// Watchdog task.
+    watchdog: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Supervisor {
+    /// Create a supervisor. Nothing is watched until [`Supervisor::start`].
+    pub fn new(config: SupervisorConfig) -> Self {
+        Self {
+            config,
+            loops: Mutex::new(HashMap::new()),
+            checks: Mutex::new(HashMap::new()),
+            incidents: Mutex::new(VecDeque::new()),
+            subscribers: Mutex::new(Vec::new()),
+            #[cfg(not(target_arch = "wasm32"))]
+            error_log: None,
+            watchdog: Mutex::new(None),
+        }
+    }
+
+    /// Also record incidents in `errors`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_error_log(mut self, errors: Arc<ErrorLog>) -> Self {
+        self.error_log = Some(errors);
+        self
+    }
+
+    /// Get the configuration.
+    pub fn config(&self) -> &SupervisorConfig {
+        &self.config
+    }
+
+    /// Start the watchdog, replacing any running one.
+    ///
+    /// The watchdog ends once the supervisor is dropped.
+    pub fn start(self: &Arc<Self>) {
+        let supervisor: Weak<Self> = Arc::downgrade(self);
+        let interval = self.config.check_interval;
+        let watchdog = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                match supervisor.upgrade() {
+                    Some(supervisor) => supervisor.check().await,
+                    None => break,
+                }
+            }
+        });
+        if let Some(previous) = self.watchdog.lock().replace(watchdog) {
+            previous.abort();
+        }
+    }
+
+    /// Stop the watchdog and every supervised loop.
+    pub fn stop(&self) {
+        if let Some(watchdog) = self.watchdog.lock().take() {
+            watchdog.abort();
+        }
+        for (_, supervised) in self.loops.lock().drain() {
+            if let Some(handle) = supervised.handle {
+                handle.abort();
+            }
+        }
+        self.checks.lock().clear();
+    }
+
+    /// Run a loop under supervision, replacing any loop of the subsystem.
+    ///
+    /// `factory` creates the loop; it's called again for every restart.
+    pub fn supervise<F, Fut>(&self, subsystem: Subsystem, factory: F)
+    where
+        F: Fn(Heartbeat) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let factory: LoopFactory = Arc::new(move |heartbeat| Box::pin(factory(heartbeat)));
+        let heartbeat = Heartbeat::default();
+        let handle = tokio::spawn(factory(heartbeat.clone()));
+        let supervised = SupervisedLoop {
+            factory,
+            handle: Some(handle),
+            heartbeat,
+            started_at: Instant::now(),
+            restart_at: None,
+            failures: 0,
+            restarts: 0,
+        };
+        if let Some(previous) = self.loops.lock().insert(subsystem, supervised) {
+            if let Some(handle) = previous.handle {
+                handle.abort();
+            }
+        }
+    }
+
+    /// Stop supervising a subsystem, aborting its loop.
+    pub fn unsupervise(&self, subsystem: &Subsystem) {
+        if let Some(handle) = self
+            .loops
+            .lock()
+            .remove(subsystem)
+            .and_then(|supervised| supervised.handle)
+        {
+            handle.abort();
+        }
+        self.checks.lock().remove(subsystem);
+    }
+
+    /// Run `check` every health check interval, replacing any check of the
+    /// subsystem.
+    ///
+    /// When the check fails, `recover` runs and the check is retried after
+    /// a backoff instead of the interval.
+    pub fn add_health_check<C, CFut, R, RFut>(&self, subsystem: Subsystem, check: C, recover: R)
+    where
+        C: Fn() -> CFut + Send + Sync + 'static,
+        CFut: Future<Output = Result<()>> + Send + 'static,
+        R: Fn() -> RFut + Send + Sync + 'static,
+        RFut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let check: Check = Arc::new(move || Box::pin(check()));
+        let recover: Check = Arc::new(move || Box::pin(recover()));
+        self.checks.lock().insert(
+            subsystem,
+            HealthCheck {
+                check,
+                recover,
+                next_at: Instant::now(),
+                failures: 0,
+                running: false,
+            },
+        );
+    }
+
+    /// Whether a subsystem's loop is running.
+    pub fn is_running(&self, subsystem: &Subsystem) -> bool {
+        self.loops
+            .lock()
+            .get(subsystem)
+            .and_then(|supervised| supervised.handle.as_ref())
+            .is_some_and(|handle| !handle.is_finished())
+    }
+
+    /// Number of times a subsystem's loop was restarted.
+    pub fn restart_count(&self, subsystem: &Subsystem) -> u32 {
+        self.loops
+            .lock()
+            .get(subsystem)
+            .map_or(0, |supervised| supervised.restarts)
+    }
+
+    /// Recent incidents, newest first.
+    pub fn incidents(&self) -> Vec<Incident> {
+        self.incidents.lock().iter().rev().cloned().collect()
+    }
+
+    /// Subscribe to incidents.
+    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<Incident> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.subscribers.lock().push(tx);
+        rx
+    }
+
+    /// Check every supervised loop and run due health checks.
+    async fn check(self: &Arc<Self>) {
+        self.check_loops().await;
+        self.run_health_checks();
+    }
+
+    /// Restart loops that panicked or stalled, once their backoff passed.
+    async fn check_loops(&self) {
+        let now = Instant::now();
+        let mut finished = Vec::new();
+        {
+            let mut loops = self.loops.lock();
+            for (subsystem, supervised) in loops.iter_mut() {
+                if let Some(restart_at) = supervised.restart_at {
+                    if now >= restart_at {
+                        info!("Restarting {}", subsystem);
+                        supervised.heartbeat = Heartbeat::default();
+                        supervised.handle = Some(tokio::spawn((supervised.factory)(
+                            supervised.heartbeat.clone(),
+                        )));
+                        supervised.started_at = now;
+                        supervised.restart_at = None;
+                        supervised.restarts += 1;
+                    }
+                    continue;
+                }
+                let Some(handle) = &supervised.handle else {
+                    continue;
+                };
+
+                if handle.is_finished() {
+                    finished.push((subsystem.clone(), supervised.handle.take()));
+                } else if let Some(busy) = supervised.heartbeat.busy_for() {
+                    if busy > self.config.stall_timeout {
+                        handle.abort();
+                        supervised.handle = None;
+                        let kind = IncidentKind::Stalled {
+                            busy_ms: busy.as_millis() as u64,
+                        };
+                        let incident = self.fail(subsystem, supervised, kind, now);
+                        self.record(incident);
+                    }
+                } else if supervised.failures > 0
+                    && now.duration_since(supervised.started_at) > self.config.stable_after
+                {
+                    supervised.failures = 0;
+                }
+            }
+        }
+
+        // Collect results outside the lock; finished tasks resolve at once
+        for (subsystem, handle) in finished {
+            let Some(handle) = handle else {
+                continue;
+            };
+            let message = match handle.await {
+                Err(e) if e.is_panic() => panic_message(e.into_panic()),
+                _ => {
+                    // Loops that return are done, e.g. after a stop
+                    self.loops.lock().remove(&subsystem);
+                    continue;
+                }
+            };
+            let incident = {
+                let mut loops = self.loops.lock();
+                let Some(supervised) = loops.get_mut(&subsystem) else {
+                    continue;
+                };
+                self.fail(
+                    &subsystem,
+                    supervised,
+                    IncidentKind::Panicked { message },
+                    Instant::now(),
+                )
+            };
+            self.record(incident);
+        }
+    }
+
+    /// Schedule the restart of a failed loop.
+    fn fail(
+        &self,
+        subsystem: &Subsystem,
+        supervised: &mut SupervisedLoop,
+        kind: IncidentKind,
+        now: Instant,
+    ) -> Incident {
+        supervised.failures += 1;
+        let backoff = self.config.backoff(supervised.failures);
+        supervised.restart_at = Some(now + backoff);
+        Incident {
+            subsystem: subsystem.clone(),
+            kind,
+            timestamp: now_millis(),
+            failures: supervised.failures,
+            retry_in_ms: backoff.as_millis() as u64,
+        }
+    }
+
+    /// Run the health checks that are due, each in its own task.
+    fn run_health_checks(self: &Arc<Self>) {
+        let now = Instant::now();
+        let mut checks = self.checks.lock();
+        for (subsystem, health) in checks.iter_mut() {
+            if health.running || now < health.next_at {
+                continue;
+            }
+            health.running = true;
+            let supervisor = Arc::downgrade(self);
+            let subsystem = subsystem.clone();
+            let (check, recover) = (Arc::clone(&health.check), Arc::clone(&health.recover));
+            tokio::spawn(async move {
+                let result = check().await;
+                let recovery = match &result {
+                    Ok(()) => None,
+                    Err(_) => Some(recover().await),
+                };
+                if let Some(supervisor) = supervisor.upgrade() {
+                    supervisor.finish_health_check(&subsystem, result, recovery);
+                }
+            });
+        }
+    }
+
+    /// Record the outcome of a health check and schedule the next one.
+    fn finish_health_check(
+        &self,
+        subsystem: &Subsystem,
+        result: Result<()>,
+        recovery: Option<Result<()>>,
+    ) {
+        let now = Instant::now();
+        let incidents = {
+            let mut checks = self.checks.lock();
+            let Some(health) = checks.get_mut(subsystem) else {
+                return;
+            };
+            health.running = false;
+
+            let Err(error) = result else {
+                health.failures = 0;
+                health.next_at = now + self.config.health_check_interval;
+                return;
+            };
+            health.failures += 1;
+            let backoff = self.config.backoff(health.failures);
+            health.next_at = now + backoff;
+
+            let incident = |kind| Incident {
+                subsystem: subsystem.clone(),
+                kind,
+                timestamp: now_millis(),
+                failures: health.failures,
+                retry_in_ms: backoff.as_millis() as u64,
+            };
+            let mut incidents = vec![incident(IncidentKind::Unhealthy {
+                error: error.to_string(),
+            })];
+            if let Some(Err(e)) = recovery {
+                incidents.push(incident(IncidentKind::RecoveryFailed {
+                    error: e.to_string(),
+                }));
+            }
+            incidents
+        };
+        for incident in incidents {
+            self.record(incident);
+        }
+    }
+
+    /// Log an incident and notify subscribers.
+    fn record(&self, incident: Incident) {
+        match &incident.kind {
+            IncidentKind::Panicked { .. } | IncidentKind::RecoveryFailed { .. } => {
+                error!("Subsystem {}", incident)
+            }
+            _ => warn!("Subsystem {}", incident),
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(errors) = &self.error_log {
+            errors.record(format!("Subsystem {}", incident));
+        }
+
+        {
+            let mut incidents = self.incidents.lock();
+            if incidents.len() == INCIDENT_CAPACITY {
+                incidents.pop_front();
+            }
+            incidents.push_back(incident.clone());
+        }
+        self.subscribers
+            .lock()
+            .retain(|tx| tx.send(incident.clone()).is_ok());
+    }
+}
+
+impl Drop for Supervisor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Message of a panic payload.
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Current time in Unix epoch milliseconds.
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::P2PError;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+    fn fast_config() -> SupervisorConfig {
+        SupervisorConfig {
+            check_interval: Duration::from_millis(10),
+            stall_timeout: Duration::from_millis(50),
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(40),
+            stable_after: Duration::from_secs(60),
+            health_check_interval: Duration::from_millis(10),
+        }
+    }
+
+    #[test]
+    fn test_backoff() {
+        let config = SupervisorConfig::default();
+        assert_eq!(config.backoff(1), Duration::from_secs(1));
+        assert_eq!(config.backoff(2), Duration::from_secs(2));
+        assert_eq!(config.backoff(4), Duration::from_secs(8));
+        assert_eq!(config.backoff(40), Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn test_restarts_panicked_loop() {
+        let supervisor = Arc::new(Supervisor::new(fast_config()));
+        let mut incidents = supervisor.subscribe();
+        let runs = Arc::new(AtomicU32::new(0));
+
+        let counter = Arc::clone(&runs);
+        supervisor.supervise(Subsystem::MessageHandler, move |_heartbeat| {
+            let counter = Arc::clone(&counter);
+            async move {
+                if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                    panic!("handler crashed");
+                }
+                std::future::pending::<()>().await;
+            }
+        });
+        supervisor.start();
+
+        for failures in 1..=2 {
+            let incident = tokio::time::timeout(Duration::from_secs(5), incidents.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(incident.subsystem, Subsystem::MessageHandler);
+            assert_eq!(
+                incident.kind,
+                IncidentKind::Panicked {
+                    message: "handler crashed".to_string()
+                }
+            );
+            assert_eq!(incident.failures, failures);
+        }
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while runs.load(Ordering::SeqCst) < 3 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(supervisor.is_running(&Subsystem::MessageHandler));
+        assert_eq!(supervisor.restart_count(&Subsystem::MessageHandler), 2);
+        assert_eq!(supervisor.incidents().len(), 2);
+
+        supervisor.stop();
+        assert!(!supervisor.is_running(&Subsystem::MessageHandler));
+    }
+
+    #[tokio::test]
+    async fn test_restarts_stalled_loop() {
+        let supervisor = Arc::new(Supervisor::new(fast_config()));
+        let mut incidents = supervisor.subscribe();
+        let stalled = Arc::new(AtomicBool::new(true));
+
+        let stall = Arc::clone(&stalled);
+        supervisor.supervise(Subsystem::BackgroundSync, move |heartbeat| {
+            let stall = Arc::clone(&stall);
+            async move {
+                loop {
+                    if stall.swap(false, Ordering::SeqCst) {
+                        let _busy = heartbeat.busy();
+                        std::future::pending::<()>().await;
+                    }
+                    // Idle loops are left alone
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+            }
+        });
+        supervisor.start();
+
+        let incident = tokio::time::timeout(Duration::from_secs(5), incidents.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(incident.subsystem, Subsystem::BackgroundSync);
+        assert!(matches!(incident.kind, IncidentKind::Stalled { busy_ms } if busy_ms >= 50));
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(supervisor.is_running(&Subsystem::BackgroundSync));
+        assert_eq!(supervisor.restart_count(&Subsystem::BackgroundSync), 1);
+        assert_eq!(supervisor.incidents().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_finished_loop_is_not_restarted() {
+        let supervisor = Arc::new(Supervisor::new(fast_config()));
+        supervisor.supervise(Subsystem::Discovery, |_heartbeat| async {});
+        supervisor.start();
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!supervisor.is_running(&Subsystem::Discovery));
+        assert_eq!(supervisor.restart_count(&Subsystem::Discovery), 0);
+        assert!(supervisor.incidents().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_health_check_recovery() {
+        let supervisor = Arc::new(Supervisor::new(fast_config()));
+        let mut incidents = supervisor.subscribe();
+        let healthy = Arc::new(AtomicBool::new(false));
+        let recoveries = Arc::new(AtomicU32::new(0));
+
+        let (check_healthy, recover_healthy) = (Arc::clone(&healthy), Arc::clone(&healthy));
+        let counter = Arc::clone(&recoveries);
+        supervisor.add_health_check(
+            Subsystem::Storage,
+            move || {
+                let healthy = check_healthy.load(Ordering::SeqCst);
+                async move {
+                    match healthy {
+                        true => Ok(()),
+                        false => Err(P2PError::Internal("database locked".to_string())),
+                    }
+                }
+            },
+            move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                recover_healthy.store(true, Ordering::SeqCst);
+                async { Ok(()) }
+            },
+        );
+        supervisor.start();
+
+        let incident = tokio::time::timeout(Duration::from_secs(5), incidents.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(incident.subsystem, Subsystem::Storage);
+        assert!(matches!(incident.kind, IncidentKind::Unhealthy { .. }));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(recoveries.load(Ordering::SeqCst), 1);
+        assert_eq!(supervisor.incidents().len(), 1);
+    }
+
+    #[test]
+    fn test_incident_serialization() {
+        let incident = Incident {
+            subsystem: Subsystem::RelayProber,
+            kind: IncidentKind::Stalled { busy_ms: 1500 },
+            timestamp: 1,
+            failures: 2,
+            retry_in_ms: 2000,
+        };
+        let json = serde_json::to_value(&incident).unwrap();
+        assert_eq!(json["subsystem"], "relay-prober");
+        assert_eq!(json["kind"], "stalled");
+        assert_eq!(json["busy_ms"], 1500);
+        assert_eq!(serde_json::from_value::<Incident>(json).unwrap(), incident);
+        assert_eq!(
+            incident.to_string(),
+            "relay-prober stalled for 1500 ms (failure 2, retrying in 2000 ms)"
+        );
+    }
+}
@@ -0,0 +1,302 @@
+//! Selective sync policies.
+//!
+//! A [`SyncPolicy`] decides which documents this node pulls from peers, so a
+//! phone doesn't download every document a peer offers. The policy is
+//! consulted by the message handler before applying changes, before syncing
+//! a document announced by a peer, and by [`BackgroundSync`] before
+//! scheduling a task. A policy can:
+//!
+//! - sync every document, or only those this node subscribes to;
+//! - exclude namespaces or documents by glob, matched against both
+//!   `namespace` and `namespace/id` (`*` matches any run of characters);
+//! - skip documents larger than a size cap;
+//! - sync only on Wi-Fi or Ethernet, asking a hook for the current network
+//!   type.
+//!
+//! [`BackgroundSync`]: crate::background_sync::BackgroundSync
+
+use std::fmt;
+use std::sync::Arc;
+
+/// Type of network the device is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkType {
+    /// Wi-Fi.
+    Wifi,
+    /// Wired Ethernet.
+    Ethernet,
+    /// Cellular (usually metered).
+    Cellular,
+    /// The platform couldn't tell.
+    Unknown,
+}
+
+impl NetworkType {
+    /// Check whether this is Wi-Fi or Ethernet.
+    pub fn is_wifi_or_ethernet(&self) -> bool {
+        matches!(self, NetworkType::Wifi | NetworkType::Ethernet)
+    }
+}
+
+/// Reports the current network type, e.g. from the platform's connectivity
+/// API.
+pub type NetworkTypeHook = Arc<dyn Fn() -> NetworkType + Send + Sync>;
+
+/// Which documents are synced at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncScope {
+    /// Every document a peer offers.
+    All,
+    /// Only documents this node subscribes to.
+    SubscribedOnly,
+}
+
+/// Why a policy skips a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The policy only syncs subscribed documents.
+    NotSubscribed,
+    /// The document matches an excluded glob.
+    Excluded(String),
+    /// The document exceeds the size cap.
+    TooLarge {
+        /// Document (or change) size in bytes.
+        size: u64,
+        /// Size cap in bytes.
+        max: u64,
+    },
+    /// The policy only syncs on Wi-Fi or Ethernet.
+    Network(NetworkType),
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotSubscribed => write!(f, "not subscribed"),
+            Self::Excluded(glob) => write!(f, "excluded by {}", glob),
+            Self::TooLarge { size, max } => {
+                write!(f, "{} bytes exceed the {} byte limit", size, max)
+            }
+            Self::Network(network) => write!(f, "on {:?} network", network),
+        }
+    }
+}
+
+/// Selective sync policy.
+#[derive(Clone)]
+pub struct SyncPolicy {
+    /// Which documents are synced at all.
+    scope: SyncScope,
+    /// Excluded namespace and document globs.
+    excluded: Vec<String>,
+    /// Size cap in bytes.
+    max_document_size: Option<u64>,
+    /// Network type hook, when syncing on Wi-Fi or Ethernet only.
+    network_type: Option<NetworkTypeHook>,
+}
+
+impl SyncPolicy {
+    /// Sync every document a peer offers.
+    pub fn sync_all() -> Self {
+        Self {
+            scope: SyncScope::All,
+            excluded: Vec::new(),
+            max_document_size: None,
+            network_type: None,
+        }
+    }
+
+    /// Only sync documents this node subscribes to.
+    pub fn sync_subscribed_only() -> Self {
+        Self {
+            scope: SyncScope::SubscribedOnly,
+            ..Self::sync_all()
+        }
+    }
+
+    /// Exclude namespaces or documents matching a glob, e.g. `media`,
+    /// `media/*` or `cache.*`.
+    pub fn exclude(mut self, glob: impl Into<String>) -> Self {
+        self.excluded.push(glob.into());
+        self
+    }
+
+    /// Skip documents (and change sets) larger than `bytes`.
+    pub fn max_document_size(mut self, bytes: u64) -> Self {
+        self.max_document_size = Some(bytes);
+        self
+    }
+
+    /// Only sync while `network_type` reports Wi-Fi or Ethernet.
+    pub fn wifi_only(
+        mut self,
+        network_type: impl Fn() -> NetworkType + Send + Sync + 'static,
+    ) -> Self {
+        self.network_type = Some(Arc::new(network_type));
+        self
+    }
+
+    /// Get the scope.
+    pub fn scope(&self) -> SyncScope {
+        self.scope
+    }
+
+    /// Check whether a document may be synced.
+    ///
+    /// `size` is the size of the document or of the changes received, if
+    /// known.
+    pub fn check(
+        &self,
+        namespace: &str,
+        id: &str,
+        subscribed: bool,
+        size: Option<u64>,
+    ) -> Result<(), SkipReason> {
+        if self.scope == SyncScope::SubscribedOnly && !subscribed {
+            return Err(SkipReason::NotSubscribed);
+        }
+
+        let document = format!("{}/{}", namespace, id);
+        if let Some(glob) = self
+            .excluded
+            .iter()
+            .find(|glob| glob_matches(glob, namespace) || glob_matches(glob, &document))
+        {
+            return Err(SkipReason::Excluded(glob.clone()));
+        }
+
+        if let (Some(size), Some(max)) = (size, self.max_document_size) {
+            if size > max {
+                return Err(SkipReason::TooLarge { size, max });
+            }
+        }
+
+        if let Some(network_type) = &self.network_type {
+            let network = network_type();
+            if !network.is_wifi_or_ethernet() {
+                return Err(SkipReason::Network(network));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for SyncPolicy {
+    fn default() -> Self {
+        Self::sync_all()
+    }
+}
+
+impl fmt::Debug for SyncPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncPolicy")
+            .field("scope", &self.scope)
+            .field("excluded", &self.excluded)
+            .field("max_document_size", &self.max_document_size)
+            .field("wifi_only", &self.network_type.is_some())
+            .finish()
+    }
+}
+
+/// Match text against a glob where `*` matches any run of characters.
+fn glob_matches(glob: &str, text: &str) -> bool {
+    let mut parts = glob.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No wildcard
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("media", "media"));
+        assert!(!glob_matches("media", "media2"));
+        assert!(glob_matches("media/*", "media/photo1"));
+        assert!(!glob_matches("media/*", "media"));
+        assert!(glob_matches("cache.*", "cache.thumbnails"));
+        assert!(glob_matches("*.tmp", "drafts.tmp"));
+        assert!(glob_matches("a*b*c", "a-b-b-c"));
+        assert!(!glob_matches("a*b*c", "a-c"));
+        assert!(!glob_matches("ab*ba", "aba"));
+        assert!(glob_matches("*", "anything"));
+    }
+
+    #[test]
+    fn test_scope() {
+        let all = SyncPolicy::default();
+        assert_eq!(all.scope(), SyncScope::All);
+        assert!(all.check("users", "alice", false, None).is_ok());
+
+        let subscribed = SyncPolicy::sync_subscribed_only();
+        assert!(subscribed.check("users", "alice", true, None).is_ok());
+        assert_eq!(
+            subscribed.check("users", "alice", false, None),
+            Err(SkipReason::NotSubscribed)
+        );
+    }
+
+    #[test]
+    fn test_exclude_and_size() {
+        let policy = SyncPolicy::sync_all()
+            .exclude("media")
+            .exclude("users/archive-*")
+            .max_document_size(1024);
+
+        assert_eq!(
+            policy.check("media", "photo1", true, None),
+            Err(SkipReason::Excluded("media".to_string()))
+        );
+        assert!(policy.check("users", "archive-2019", true, None).is_err());
+        assert!(policy.check("users", "alice", true, Some(1024)).is_ok());
+        assert_eq!(
+            policy.check("users", "alice", true, Some(4096)),
+            Err(SkipReason::TooLarge {
+                size: 4096,
+                max: 1024
+            })
+        );
+    }
+
+    #[test]
+    fn test_wifi_only() {
+        let on_wifi = Arc::new(AtomicBool::new(true));
+        let hook = Arc::clone(&on_wifi);
+        let policy = SyncPolicy::sync_all().wifi_only(move || {
+            if hook.load(Ordering::SeqCst) {
+                NetworkType::Wifi
+            } else {
+                NetworkType::Cellular
+            }
+        });
+
+        assert!(policy.check("users", "alice", true, None).is_ok());
+        on_wifi.store(false, Ordering::SeqCst);
+        assert_eq!(
+            policy.check("users", "alice", true, None),
+            Err(SkipReason::Network(NetworkType::Cellular))
+        );
+        assert_eq!(
+            format!("{:?}", policy),
+            "SyncPolicy { scope: All, excluded: [], max_document_size: None, wifi_only: true }"
+        );
+    }
+}
@@ -1,7 +1,32 @@
 //! Automerge sync protocol over Iroh connections.
+//!
+//! Documents are synced with Automerge's sync protocol
+//! ([`SyncMessage::AutomergeSync`]): peers exchange their heads and a bloom
+//! filter of the changes they have, then send each other only the changes
+//! the other side is missing. The per-peer sync state is kept across
+//! disconnects and persisted in the state engine, so a reconnecting peer
+//! picks up where the last session ended instead of receiving the whole
+//! document again. [`SyncMessage::SyncRequest`] and
+//! [`SyncMessage::FullSync`] remain for peers without sync state support.
+//!
+//! Deleted documents are never synced again: [`SyncMessage::Delete`] carries
+//! a signed deletion, recorded as a tombstone (see [`crate::tombstones`]).
 
+use crate::auth::{connection_binding, document_resource, PeerAuth, SyncAuthPolicy, WRITE};
+use crate::bandwidth::SyncPriority;
+use crate::conflicts::ConflictMonitor;
 use crate::error::{P2PError, Result};
-use automerge::{AutoCommit, Change};
+use crate::file_transfer::{FileOffer, TransferId};
+use crate::framing::TransportHello;
+use crate::gossip::SignedGossipMessage;
+use crate::mailbox::{MailFetch, SealedMail};
+use crate::revocations::is_revocations_document;
+use crate::session_cache::SESSION_CACHE_NAMESPACE;
+use crate::tombstones::{DocumentDeletion, SignedDeletion, TombstoneStore};
+use crate::willow_sync::WillowSyncMessage;
+use automerge::sync::{self, SyncDoc};
+use automerge::transaction::Transactable;
+use automerge::{AutoCommit, Change, ReadDoc, ROOT};
 use bytes::Bytes;
 use lru::LruCache;
 use parking_lot::RwLock;
@@ -9,9 +34,17 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::num::NonZeroUsize;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
-use tracing::{debug, info, warn};
-use vudo_state::{DocumentHandle, DocumentId, StateEngine};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, debug_span, info, warn, Instrument};
+use vudo_identity::{AuthChallenge, AuthResponse, DeviceIdentity, Did};
+use vudo_state::{DocumentHandle, DocumentId, FieldConflict, StateEngine};
+
+/// Target time for a partition to heal after connectivity returns.
+pub const PARTITION_HEAL_TARGET: Duration = Duration::from_secs(5);
+
+/// Key of the document holding persisted Automerge sync states (in the
+/// session cache namespace).
+pub const SYNC_STATES_KEY: &str = "sync_states";
 
 /// Peer ID (Iroh node ID).
 pub type PeerId = String;
@@ -67,6 +100,30 @@ pub enum SyncMessage {
         document: Vec<u8>,
     },
 
+    /// Offer to send a file.
+    FileOffer(FileOffer),
+
+    /// Accept a file offer; the receiver then fetches the file over
+    /// iroh-blobs.
+    FileAccept {
+        /// Transfer ID.
+        hash: TransferId,
+    },
+
+    /// Reject a file offer.
+    FileReject {
+        /// Transfer ID.
+        hash: TransferId,
+        /// Rejection reason.
+        reason: String,
+    },
+
+    /// Confirm a file was received and verified.
+    FileComplete {
+        /// Transfer ID.
+        hash: TransferId,
+    },
+
     /// Heartbeat to keep connection alive.
     Heartbeat,
 
@@ -75,6 +132,57 @@ pub enum SyncMessage {
         /// Error message.
         message: String,
     },
+
+    /// Signed gossip message sent directly to a connected peer.
+    Gossip(SignedGossipMessage),
+
+    /// Present a session token (see [`crate::auth`]).
+    Authenticate {
+        /// Encoded session token.
+        token: String,
+    },
+
+    /// Accept a session token.
+    Authenticated {
+        /// DID the session is authenticated as.
+        did: String,
+    },
+
+    /// Ask the peer to prove it holds a DID (see [`crate::auth`]).
+    Challenge(AuthChallenge),
+
+    /// Answer a challenge.
+    ChallengeResponse(AuthResponse),
+
+    /// Automerge sync protocol message.
+    AutomergeSync {
+        /// Document namespace.
+        namespace: String,
+        /// Document key.
+        id: String,
+        /// Encoded `automerge::sync::Message` (heads, have/need and changes).
+        message: Vec<u8>,
+    },
+
+    /// Willow namespace reconciliation message (see [`crate::willow_sync`]).
+    WillowSync(Box<WillowSyncMessage>),
+
+    /// Signed deletion of a document (see [`crate::tombstones`]).
+    Delete(SignedDeletion),
+
+    /// Framing and compression the sender reads, sent when a connection
+    /// opens (see [`crate::framing`]).
+    Hello(TransportHello),
+
+    /// Leave mail for an offline peer with a mailbox (see
+    /// [`crate::mailbox`]).
+    MailDeposit(SealedMail),
+
+    /// Ask a mailbox for the mail held for a recipient.
+    MailFetch(MailFetch),
+
+    /// Mail handed over by a mailbox.
+    MailDelivery(Vec<SealedMail>),
 }
 
 impl SyncMessage {
@@ -87,6 +195,59 @@ impl SyncMessage {
     pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
         bincode::deserialize(bytes).map_err(P2PError::from)
     }
+
+    /// Size of the serialized message in bytes.
+    pub fn encoded_len(&self) -> usize {
+        bincode::serialized_size(self).unwrap_or(0) as usize
+    }
+
+    /// Namespace of the document the message is about, if any.
+    pub fn namespace(&self) -> Option<&str> {
+        match self {
+            Self::SyncRequest { namespace, .. }
+            | Self::SyncChanges { namespace, .. }
+            | Self::SyncComplete { namespace, .. }
+            | Self::FullSync { namespace, .. }
+            | Self::FullDocument { namespace, .. }
+            | Self::AutomergeSync { namespace, .. } => Some(namespace),
+            Self::WillowSync(message) => Some(message.namespace()),
+            Self::Delete(signed) => Some(&signed.deletion.namespace),
+            _ => None,
+        }
+    }
+
+    /// Priority the message's traffic is limited at (see
+    /// [`BandwidthManager::set_limit`](crate::bandwidth::BandwidthManager::set_limit)).
+    ///
+    /// Control messages are urgent and document sync is normal. File data
+    /// does not pass through here; it is fetched over iroh-blobs.
+    pub fn priority(&self) -> SyncPriority {
+        match self {
+            Self::SyncRequest { .. }
+            | Self::SyncChanges { .. }
+            | Self::FullSync { .. }
+            | Self::FullDocument { .. }
+            | Self::AutomergeSync { .. }
+            | Self::FileOffer(_)
+            | Self::Gossip(_)
+            | Self::WillowSync(_)
+            | Self::Delete(_)
+            | Self::MailDeposit(_)
+            | Self::MailDelivery(_) => SyncPriority::Normal,
+            Self::SyncComplete { .. }
+            | Self::FileAccept { .. }
+            | Self::FileReject { .. }
+            | Self::FileComplete { .. }
+            | Self::Heartbeat
+            | Self::Error { .. }
+            | Self::Authenticate { .. }
+            | Self::Authenticated { .. }
+            | Self::Challenge(_)
+            | Self::ChallengeResponse(_)
+            | Self::Hello(_)
+            | Self::MailFetch(_) => SyncPriority::Urgent,
+        }
+    }
 }
 
 /// Sync metadata for a document.
@@ -107,6 +268,9 @@ struct SyncState {
     state: HashMap<(PeerId, String, String), SyncMetadata>,
     /// LRU cache to bound memory usage.
     cache: LruCache<(PeerId, String, String), SyncMetadata>,
+    /// Highest document version each peer announced.
+    /// Key: (peer_id, namespace, document_id)
+    announced: LruCache<(PeerId, String, String), u64>,
 }
 
 impl SyncState {
@@ -114,6 +278,7 @@ impl SyncState {
         Self {
             state: HashMap::new(),
             cache: LruCache::new(NonZeroUsize::new(capacity).unwrap()),
+            announced: LruCache::new(NonZeroUsize::new(capacity).unwrap()),
         }
     }
 
@@ -141,6 +306,28 @@ pub struct SyncProtocol {
     state_engine: Arc<StateEngine>,
     /// Sync state tracker.
     sync_state: Arc<RwLock<SyncState>>,
+    /// Reconnect latency tracker.
+    reconnects: RwLock<ReconnectStats>,
+    /// Who may sync with this node (anyone when `None`).
+    auth: Option<SyncAuthPolicy>,
+    /// Authenticated peers.
+    peers: RwLock<HashMap<PeerId, PeerAuth>>,
+    /// Device this node proves it holds when challenged.
+    device: RwLock<Option<DeviceIdentity>>,
+    /// Challenges awaiting an answer, by peer.
+    challenges: RwLock<HashMap<PeerId, AuthChallenge>>,
+    /// DIDs peers proved they hold on their current connection.
+    proven: RwLock<HashMap<PeerId, Did>>,
+    /// Automerge sync states.
+    /// Key: (peer_id, namespace, document_id)
+    doc_states: RwLock<HashMap<(PeerId, String, String), sync::State>>,
+    /// Tombstones of deleted documents.
+    tombstones: TombstoneStore,
+    /// Reports conflicts left by merges.
+    conflicts: ConflictMonitor,
+    /// Per-document sync progress.
+    /// Key: (peer_id, namespace, document_id)
+    progress: RwLock<HashMap<(PeerId, String, String), ProgressEntry>>,
 }
 
 impl SyncProtocol {
@@ -149,7 +336,530 @@ impl SyncProtocol {
         Self {
             state_engine,
             sync_state: Arc::new(RwLock::new(SyncState::new(10_000))),
+            reconnects: RwLock::new(ReconnectStats::default()),
+            auth: None,
+            peers: RwLock::new(HashMap::new()),
+            device: RwLock::new(None),
+            challenges: RwLock::new(HashMap::new()),
+            proven: RwLock::new(HashMap::new()),
+            doc_states: RwLock::new(HashMap::new()),
+            tombstones: TombstoneStore::new(),
+            conflicts: ConflictMonitor::new(),
+            progress: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Require peers to authenticate before exchanging documents.
+    pub fn with_auth(mut self, policy: SyncAuthPolicy) -> Self {
+        self.auth = Some(policy);
+        self
+    }
+
+    /// Check whether peers must authenticate.
+    pub fn requires_auth(&self) -> bool {
+        self.auth.is_some()
+    }
+
+    /// Verify a session token presented by a peer.
+    ///
+    /// On success the peer is authenticated until the token expires, and
+    /// the returned message acknowledges it.
+    pub fn authenticate(&self, peer: &PeerId, local: &PeerId, token: &str) -> Result<SyncMessage> {
+        let policy = self.auth.as_ref().ok_or_else(|| {
+            P2PError::SyncProtocolError("Sync authentication is not enabled".to_string())
+        })?;
+        let auth = policy.verify(token, peer, local)?;
+
+        info!("Peer {} authenticated as {}", peer, auth.did);
+        let did = auth.did.to_string();
+        self.peers.write().insert(peer.clone(), auth);
+        Ok(SyncMessage::Authenticated { did })
+    }
+
+    /// Set the device identity this node proves when challenged.
+    pub fn set_device(&self, device: Option<DeviceIdentity>) {
+        *self.device.write() = device;
+    }
+
+    /// Challenge a peer to prove it holds `did`.
+    ///
+    /// Replaces any challenge of the peer still awaiting an answer.
+    pub fn challenge(&self, peer: &PeerId, local: &PeerId, did: Did) -> SyncMessage {
+        let challenge = AuthChallenge::new(did, connection_binding(local, peer));
+        self.challenges
+            .write()
+            .insert(peer.clone(), challenge.clone());
+        SyncMessage::Challenge(challenge)
+    }
+
+    /// Check whether a peer still has to prove it holds `did`: it hasn't
+    /// on this connection, and no challenge is awaiting an answer.
+    pub fn needs_challenge(&self, peer: &PeerId, did: &str) -> bool {
+        let proven = self
+            .proven
+            .read()
+            .get(peer)
+            .is_some_and(|proven| proven.as_str() == did);
+        let pending = self
+            .challenges
+            .read()
+            .get(peer)
+            .is_some_and(|challenge| challenge.did.as_str() == did && !challenge.is_expired());
+        !proven && !pending
+    }
+
+    /// Answer a peer's challenge with this node's device.
+    pub fn answer_challenge(
+        &self,
+        peer: &PeerId,
+        local: &PeerId,
+        challenge: &AuthChallenge,
+    ) -> Result<SyncMessage> {
+        let Some(device) = self.device.read().clone() else {
+            return Err(P2PError::PermissionDenied(
+                "No device identity to prove".to_string(),
+            ));
+        };
+        let response = challenge.respond(&device, &connection_binding(peer, local))?;
+        Ok(SyncMessage::ChallengeResponse(response))
+    }
+
+    /// Verify a peer's answer to this node's challenge.
+    ///
+    /// On success the peer is known to hold the DID until it disconnects.
+    pub fn verify_challenge(&self, peer: &PeerId, response: &AuthResponse) -> Result<Did> {
+        let challenge = self.challenges.write().remove(peer).ok_or_else(|| {
+            P2PError::PermissionDenied(format!("No challenge awaits an answer from {}", peer))
+        })?;
+        challenge.verify(response)?;
+
+        info!("Peer {} proved it holds {}", peer, challenge.did);
+        self.proven
+            .write()
+            .insert(peer.clone(), challenge.did.clone());
+        Ok(challenge.did)
+    }
+
+    /// Get the DID a peer proved it holds on its current connection.
+    pub fn proven_did(&self, peer: &PeerId) -> Option<Did> {
+        self.proven.read().get(peer).cloned()
+    }
+
+    /// Get the identity a peer authenticated as.
+    pub fn peer_auth(&self, peer: &PeerId) -> Option<PeerAuth> {
+        self.peers.read().get(peer).cloned()
+    }
+
+    /// Check whether a peer may perform `action` on a document.
+    ///
+    /// Always succeeds when authentication isn't required.
+    pub fn authorize(&self, peer: &PeerId, namespace: &str, id: &str, action: &str) -> Result<()> {
+        if self.auth.is_none() {
+            return Ok(());
+        }
+
+        let mut peers = self.peers.write();
+        let auth = match peers.get(peer) {
+            Some(auth) if auth.is_expired() => {
+                peers.remove(peer);
+                return Err(P2PError::PermissionDenied(format!(
+                    "Session of peer {} has expired",
+                    peer
+                )));
+            }
+            Some(auth) => auth,
+            None => {
+                return Err(P2PError::PermissionDenied(format!(
+                    "Peer {} has not authenticated",
+                    peer
+                )));
+            }
+        };
+        if !auth.allows(namespace, id, action) {
+            return Err(P2PError::PermissionDenied(format!(
+                "{} may not {} {}",
+                auth.did,
+                action,
+                document_resource(namespace, id)
+            )));
+        }
+        Ok(())
+    }
+
+    /// Start syncing a document with a peer.
+    ///
+    /// The message carries this node's heads and a summary of the changes
+    /// it has since the last sync with the peer, so the peer replies with
+    /// just the missing changes. A document that doesn't exist yet is
+    /// synced from scratch.
+    pub async fn start_sync(
+        &self,
+        peer: &PeerId,
+        namespace: &str,
+        id: &str,
+    ) -> Result<SyncMessage> {
+        let span = debug_span!("sync_round", peer = %peer, namespace, id);
+        async {
+            let result = self.open_round(peer, namespace, id).await;
+            self.update_progress(peer, namespace, id, |entry| match &result {
+                Ok(message) => {
+                    entry.progress.rounds += 1;
+                    entry.sent(message.len(), SyncPhase::Requested);
+                }
+                Err(e) => entry.fail(e),
+            });
+            let message = result?;
+            debug!("Sent {} byte sync message", message.len());
+
+            Ok(SyncMessage::AutomergeSync {
+                namespace: namespace.to_string(),
+                id: id.to_string(),
+                message,
+            })
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// Generate the first message of a sync round.
+    async fn open_round(&self, peer: &PeerId, namespace: &str, id: &str) -> Result<Vec<u8>> {
+        self.check_not_deleted(namespace, id)?;
+        let doc_id = DocumentId::new(namespace, id);
+        let handle = self.state_engine.get_document(&doc_id).await.ok();
+
+        let mut states = self.doc_states.write();
+        let key = (peer.clone(), namespace.to_string(), id.to_string());
+        // Start a new round, keeping only what both sides are known to share
+        let state = states.entry(key).or_default();
+        *state = reset(state);
+
+        let message = match &handle {
+            Some(handle) => handle.generate_sync_message(state),
+            None => AutoCommit::new().sync().generate_sync_message(state),
+        }
+        .ok_or_else(|| {
+            P2PError::SyncProtocolError(format!("No sync message for {}/{}", namespace, id))
+        })?;
+        Ok(message.encode())
+    }
+
+    /// Handle an Automerge sync message from a peer.
+    ///
+    /// Changes in the message are applied, resolving conflicts with the
+    /// registered policies; applying them needs write access when
+    /// authentication is required. Returns the reply, or `None` once both
+    /// sides are in sync.
+    pub async fn receive_sync(
+        &self,
+        peer: &PeerId,
+        namespace: String,
+        id: String,
+        message: Vec<u8>,
+    ) -> Result<Option<SyncMessage>> {
+        let span = debug_span!("sync_round", peer = %peer, namespace, id);
+        async {
+            self.update_progress(peer, &namespace, &id, |entry| entry.received(message.len()));
+            let result = self.receive_round(peer, &namespace, &id, &message).await;
+            let progress = self.update_progress(peer, &namespace, &id, |entry| match &result {
+                Ok(round) => {
+                    entry.progress.changes_applied += round.applied as u64;
+                    let phase = if round.converged || round.reply.is_none() {
+                        SyncPhase::InSync
+                    } else {
+                        SyncPhase::Exchanging
+                    };
+                    let sent = round.reply.as_ref().map_or(0, Vec::len);
+                    entry.sent(sent, phase);
+                }
+                Err(e) => entry.fail(e),
+            });
+            let round = result?;
+            debug!(
+                "Received {} byte sync message, {} changes applied, {:?}",
+                message.len(),
+                round.applied,
+                progress.phase
+            );
+
+            Ok(round.reply.map(|message| SyncMessage::AutomergeSync {
+                namespace,
+                id,
+                message,
+            }))
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// Apply an Automerge sync message and generate the reply.
+    async fn receive_round(
+        &self,
+        peer: &PeerId,
+        namespace: &str,
+        id: &str,
+        message: &[u8],
+    ) -> Result<SyncRound> {
+        self.check_not_deleted(namespace, id)?;
+        let message = sync::Message::decode(message)
+            .map_err(|e| P2PError::SyncProtocolError(format!("Invalid sync message: {}", e)))?;
+        let change_count = message.changes.len();
+        let has_changes = change_count > 0;
+        // Any peer may sync the signed revocations
+        if has_changes && !is_revocations_document(namespace, id) {
+            self.authorize(peer, namespace, id, WRITE)?;
+        }
+
+        let doc_id = DocumentId::new(namespace, id);
+        let handle = match self.state_engine.get_document(&doc_id).await {
+            Ok(handle) => Some(handle),
+            Err(_) if has_changes => Some(self.state_engine.create_document(doc_id).await?),
+            Err(_) => None,
+        };
+
+        let before = match &handle {
+            Some(handle) if has_changes => handle.conflicts()?,
+            _ => Vec::new(),
+        };
+
+        let key = (peer.clone(), namespace.to_string(), id.to_string());
+        let (changed, converged, reply) = {
+            let mut states = self.doc_states.write();
+            let state = states.entry(key).or_default();
+            let (changed, heads, reply) = match &handle {
+                Some(handle) => {
+                    let changed = handle.receive_sync_message(state, message)?;
+                    let reply = handle.generate_sync_message(state);
+                    (changed, handle.heads(), reply)
+                }
+                None => {
+                    let mut empty = AutoCommit::new();
+                    empty
+                        .sync()
+                        .receive_sync_message(state, message)
+                        .map_err(vudo_state::StateError::from)?;
+                    let reply = empty.sync().generate_sync_message(state);
+                    (false, Vec::new(), reply)
+                }
+            };
+            // The peer announced the same heads: any reply only tells it so
+            let converged = state.their_heads.as_deref() == Some(&heads[..]);
+            (changed, converged, reply)
+        };
+
+        if let (true, Some(handle)) = (changed, &handle) {
+            self.resolve_conflicts(peer, handle, &before)?;
+
+            let mut sync_state = self.sync_state.write();
+            let sync_count = sync_state
+                .state
+                .get(&(peer.clone(), namespace.to_string(), id.to_string()))
+                .map(|m| m.sync_count + 1)
+                .unwrap_or(1);
+            let metadata = SyncMetadata {
+                last_sync: current_timestamp(),
+                version: handle.metadata().version,
+                sync_count,
+            };
+            sync_state.update(peer, namespace, id, metadata);
+            info!(
+                "Applied sync message from peer {} for {}/{}",
+                peer, namespace, id
+            );
+        }
+
+        Ok(SyncRound {
+            applied: if changed { change_count } else { 0 },
+            converged,
+            reply: reply.map(|message| message.encode()),
+        })
+    }
+
+    /// Persist the Automerge sync states in the state engine.
+    ///
+    /// Only what is needed to resume is stored: the heads shared with each
+    /// peer. Returns the number of states persisted.
+    pub async fn persist_sync_states(&self) -> Result<usize> {
+        let doc_id = DocumentId::new(SESSION_CACHE_NAMESPACE, SYNC_STATES_KEY);
+        let handle = match self.state_engine.get_document(&doc_id).await {
+            Ok(h) => h,
+            Err(_) => self.state_engine.create_document(doc_id).await?,
+        };
+
+        let encoded = self
+            .doc_states
+            .read()
+            .iter()
+            .map(|(key, state)| Ok((serde_json::to_string(key)?, state.encode())))
+            .collect::<Result<Vec<_>>>()?;
+
+        handle.update(|doc| {
+            for (key, state) in &encoded {
+                doc.put(ROOT, key.as_str(), state.clone())?;
+            }
+            Ok(())
+        })?;
+
+        Ok(encoded.len())
+    }
+
+    /// Load Automerge sync states persisted by
+    /// [`SyncProtocol::persist_sync_states`].
+    ///
+    /// Unreadable states are skipped. Returns the number of states loaded.
+    pub async fn load_sync_states(&self) -> Result<usize> {
+        let doc_id = DocumentId::new(SESSION_CACHE_NAMESPACE, SYNC_STATES_KEY);
+        let handle = match self.state_engine.get_document(&doc_id).await {
+            Ok(h) => h,
+            Err(_) => return Ok(0),
+        };
+
+        let stored: Vec<(String, Vec<u8>)> = handle.read(|doc| {
+            let mut stored = Vec::new();
+            for key in doc.keys(ROOT) {
+                if let Some((value, _)) = doc.get(ROOT, key.as_str())? {
+                    if let Some(bytes) = value.to_bytes() {
+                        stored.push((key, bytes.to_vec()));
+                    }
+                }
+            }
+            Ok(stored)
+        })?;
+
+        let mut states = self.doc_states.write();
+        let mut loaded = 0;
+        for (key, bytes) in stored {
+            let key = serde_json::from_str::<(PeerId, String, String)>(&key);
+            match (key, sync::State::decode(&bytes)) {
+                (Ok(key), Ok(state)) => {
+                    states.insert(key, state);
+                    loaded += 1;
+                }
+                _ => warn!("Skipping unreadable sync state"),
+            }
+        }
+
+        Ok(loaded)
+    }
+
+    /// Check whether a document was deleted.
+    pub fn is_deleted(&self, namespace: &str, id: &str) -> bool {
+        self.tombstones.contains(namespace, id)
+    }
+
+    /// Get the deletion of a document, to send to peers still syncing it.
+    pub fn deletion(&self, namespace: &str, id: &str) -> Option<SignedDeletion> {
+        self.tombstones.get(namespace, id)
+    }
+
+    /// Get the deletions of all deleted documents.
+    pub fn deletions(&self) -> Vec<SignedDeletion> {
+        self.tombstones.list()
+    }
+
+    /// Apply a signed deletion.
+    ///
+    /// Once the deletion is verified, and the owner's deletion token shows
+    /// the owner may write the document, the local copy of the document and
+    /// its sync states are removed, and a tombstone is persisted so later
+    /// merges can't bring the document back. Returns `false` if the document
+    /// was already deleted.
+    pub async fn apply_deletion(&self, signed: SignedDeletion) -> Result<bool> {
+        signed.verify()?;
+        self.authorize_deletion(&signed.deletion)?;
+        if !self.tombstones.insert(signed.clone()) {
+            return Ok(false);
+        }
+
+        let (namespace, id) = (&signed.deletion.namespace, &signed.deletion.id);
+        let doc_id = DocumentId::new(namespace, id);
+        if self.state_engine.get_document(&doc_id).await.is_ok() {
+            self.state_engine.delete_document(&doc_id).await?;
+        }
+        self.doc_states
+            .write()
+            .retain(|(_, ns, doc), _| ns != namespace || doc != id);
+        {
+            let mut sync_state = self.sync_state.write();
+            let peers: Vec<PeerId> = sync_state
+                .state
+                .keys()
+                .filter(|(_, ns, doc)| ns == namespace && doc == id)
+                .map(|(peer, _, _)| peer.clone())
+                .collect();
+            for peer in peers {
+                sync_state.remove(&peer, namespace, id);
+            }
+        }
+        self.tombstones.persist(&self.state_engine, &signed).await?;
+
+        info!(
+            "Deleted {}/{} as erased by {}",
+            namespace, id, signed.deletion.receipt.owner
+        );
+        Ok(true)
+    }
+
+    /// Check that the owner of a deleted document may write it.
+    ///
+    /// Always succeeds when authentication isn't required, as any peer may
+    /// then write any document.
+    fn authorize_deletion(&self, deletion: &DocumentDeletion) -> Result<()> {
+        let Some(policy) = &self.auth else {
+            return Ok(());
+        };
+        let (namespace, id) = (&deletion.namespace, &deletion.id);
+        let token = deletion.authorization.as_deref().ok_or_else(|| {
+            P2PError::PermissionDenied(format!(
+                "Deletion of {} carries no deletion token",
+                document_resource(namespace, id)
+            ))
+        })?;
+        policy.verify_deletion(token, &deletion.receipt.owner, namespace, id)?;
+        Ok(())
+    }
+
+    /// Load tombstones persisted by [`SyncProtocol::apply_deletion`].
+    ///
+    /// Returns the number of tombstones loaded.
+    pub async fn load_tombstones(&self) -> Result<usize> {
+        self.tombstones.load(&self.state_engine).await
+    }
+
+    /// Get the monitor reporting conflicts left by merges.
+    pub fn conflicts(&self) -> &ConflictMonitor {
+        &self.conflicts
+    }
+
+    /// Resolve the conflicts a merge from `peer` left in a document with
+    /// the registered policies, and report those it introduced.
+    ///
+    /// `before` holds the document's conflicts before the merge.
+    fn resolve_conflicts(
+        &self,
+        peer: &PeerId,
+        handle: &DocumentHandle,
+        before: &[FieldConflict],
+    ) -> Result<()> {
+        let after = handle.conflicts()?;
+        let report = self.state_engine.conflict_policies.resolve(handle)?;
+        if !report.unresolved.is_empty() {
+            debug!(
+                "{} conflicting fields left unresolved in {}",
+                report.unresolved.len(),
+                handle.id
+            );
+        }
+        self.conflicts
+            .record(peer, &handle.id, before, after, &report);
+        Ok(())
+    }
+
+    /// Fail if a document was deleted.
+    fn check_not_deleted(&self, namespace: &str, id: &str) -> Result<()> {
+        if self.tombstones.contains(namespace, id) {
+            return Err(P2PError::DocumentDeleted(format!("{}/{}", namespace, id)));
         }
+        Ok(())
     }
 
     /// Handle incoming sync request.
@@ -164,6 +874,7 @@ impl SyncProtocol {
             "Handling sync request from peer {} for {}/{}",
             peer, namespace, id
         );
+        self.check_not_deleted(&namespace, &id)?;
 
         let doc_id = DocumentId::new(&namespace, &id);
 
@@ -233,23 +944,19 @@ impl SyncProtocol {
             namespace,
             id
         );
+        self.check_not_deleted(&namespace, &id)?;
 
         let doc_id = DocumentId::new(&namespace, &id);
 
-        // Get or create document
         let handle = match self.state_engine.get_document(&doc_id).await {
-            Ok(h) => h,
-            Err(_) => self.state_engine.create_document(doc_id.clone()).await?,
+            Ok(handle) => handle,
+            Err(_) => self.state_engine.create_document(doc_id).await?,
         };
 
-        // Apply changes
-        handle.update(|doc| {
-            for change_bytes in &changes {
-                doc.load_incremental(change_bytes)
-                    .map_err(|e| vudo_state::StateError::Internal(e.to_string()))?;
-            }
-            Ok(())
-        })?;
+        // Apply changes, resolving conflicts with the registered policies
+        let before = handle.conflicts()?;
+        handle.load_incremental(&changes.concat())?;
+        self.resolve_conflicts(peer, &handle, &before)?;
 
         // Update sync state
         let metadata = SyncMetadata {
@@ -287,6 +994,7 @@ impl SyncProtocol {
             id,
             document_bytes.len()
         );
+        self.check_not_deleted(&namespace, &id)?;
 
         let doc_id = DocumentId::new(&namespace, &id);
 
@@ -354,12 +1062,137 @@ impl SyncProtocol {
         })
     }
 
+    /// Record a document version announced by a peer.
+    ///
+    /// Returns whether the version is newer than any the peer announced
+    /// before, i.e. whether the document should be synced with the peer.
+    /// Versions are per-peer counters, so only announcements from the same
+    /// peer are compared.
+    pub fn track_announcement(
+        &self,
+        peer: &PeerId,
+        namespace: &str,
+        id: &str,
+        version: u64,
+    ) -> bool {
+        let key = (peer.clone(), namespace.to_string(), id.to_string());
+        let mut state = self.sync_state.write();
+        match state.announced.get(&key) {
+            Some(&known) if known >= version => {
+                debug!(
+                    "Skipping announcement of {}/{} version {} from peer {} (have {})",
+                    namespace, id, version, peer, known
+                );
+                false
+            }
+            _ => {
+                state.announced.put(key, version);
+                true
+            }
+        }
+    }
+
     /// Clear sync state for a peer.
     pub fn clear_peer_state(&self, peer: &PeerId) {
         let mut state = self.sync_state.write();
         state
             .state
             .retain(|(p, _, _), _| p != peer);
+        let announced: Vec<_> = state
+            .announced
+            .iter()
+            .filter(|((p, _, _), _)| p == peer)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in announced {
+            state.announced.pop(&key);
+        }
+        drop(state);
+        self.peers.write().remove(peer);
+        self.challenges.write().remove(peer);
+        self.proven.write().remove(peer);
+
+        // Messages in flight are lost with the connection; keep what both
+        // sides share so the next session only exchanges missing changes
+        for ((p, _, _), state) in self.doc_states.write().iter_mut() {
+            if p == peer {
+                *state = reset(state);
+            }
+        }
+        for ((p, _, _), entry) in self.progress.write().iter_mut() {
+            if p == peer && entry.awaiting_since.take().is_some() {
+                entry.progress.phase = SyncPhase::Interrupted;
+                entry.progress.updated_at = current_timestamp();
+            }
+        }
+    }
+
+    /// Record the latency of a reconnect to a recently-seen peer.
+    pub fn record_reconnect(&self, peer: &PeerId, latency: Duration) {
+        if latency > PARTITION_HEAL_TARGET {
+            warn!(
+                "Reconnect to peer {} took {:?}, exceeding the {:?} target",
+                peer, latency, PARTITION_HEAL_TARGET
+            );
+        }
+        self.reconnects.write().record(latency);
+    }
+
+    /// List per-peer document sync sessions, most recently synced first.
+    pub fn sessions(&self) -> Vec<SyncSession> {
+        let state = self.sync_state.read();
+        let mut sessions: Vec<SyncSession> = state
+            .state
+            .iter()
+            .map(|((peer_id, namespace, id), metadata)| SyncSession {
+                peer_id: peer_id.clone(),
+                namespace: namespace.clone(),
+                document_id: id.clone(),
+                last_sync: metadata.last_sync,
+                version: metadata.version,
+                sync_count: metadata.sync_count,
+            })
+            .collect();
+        sessions.sort_by(|a, b| b.last_sync.cmp(&a.last_sync));
+        sessions
+    }
+
+    /// Get the progress of a document's sync with a peer.
+    pub fn progress(&self, peer: &PeerId, namespace: &str, id: &str) -> Option<SyncProgress> {
+        let key = (peer.clone(), namespace.to_string(), id.to_string());
+        let progress = self.progress.read();
+        progress.get(&key).map(|entry| entry.progress.clone())
+    }
+
+    /// List the progress of every document sync, most recently updated
+    /// first.
+    pub fn all_progress(&self) -> Vec<SyncProgress> {
+        let mut progress: Vec<SyncProgress> = self
+            .progress
+            .read()
+            .values()
+            .map(|entry| entry.progress.clone())
+            .collect();
+        progress.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        progress
+    }
+
+    /// Update the progress of a document's sync with a peer.
+    fn update_progress(
+        &self,
+        peer: &PeerId,
+        namespace: &str,
+        id: &str,
+        update: impl FnOnce(&mut ProgressEntry),
+    ) -> SyncProgress {
+        let key = (peer.clone(), namespace.to_string(), id.to_string());
+        let mut progress = self.progress.write();
+        let entry = progress
+            .entry(key)
+            .or_insert_with(|| ProgressEntry::new(peer, namespace, id));
+        update(entry);
+        entry.progress.updated_at = current_timestamp();
+        entry.progress.clone()
     }
 
     /// Get sync statistics.
@@ -368,8 +1201,137 @@ impl SyncProtocol {
         SyncStats {
             tracked_documents: state.state.len(),
             total_sync_count: state.state.values().map(|m| m.sync_count).sum(),
+            reconnects: self.reconnects.read().clone(),
+        }
+    }
+}
+
+/// Sync state of one document with one peer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncSession {
+    /// Peer ID.
+    pub peer_id: PeerId,
+    /// Document namespace.
+    pub namespace: String,
+    /// Document key.
+    pub document_id: String,
+    /// Last sync timestamp (Unix epoch milliseconds).
+    pub last_sync: u64,
+    /// Document version at last sync.
+    pub version: u64,
+    /// Number of sync operations.
+    pub sync_count: u64,
+}
+
+/// Phase of a document's sync with a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncPhase {
+    /// This node started a round and awaits the peer's reply.
+    Requested,
+    /// Changes are being exchanged.
+    Exchanging,
+    /// Both sides have the same changes.
+    InSync,
+    /// The connection closed before the round ended.
+    Interrupted,
+    /// The last message failed (see [`SyncProgress::last_error`]).
+    Failed,
+}
+
+/// Progress of a document's sync with a peer.
+///
+/// Counters accumulate over every round since the node started. A document
+/// stuck in [`SyncPhase::Exchanging`] over many rounds, or repeatedly
+/// [`SyncPhase::Failed`], isn't converging.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncProgress {
+    /// Peer ID.
+    pub peer_id: PeerId,
+    /// Document namespace.
+    pub namespace: String,
+    /// Document key.
+    pub document_id: String,
+    /// Current phase.
+    pub phase: SyncPhase,
+    /// Number of sync rounds, started by either side.
+    pub rounds: u64,
+    /// Sync message bytes sent to the peer.
+    pub bytes_sent: u64,
+    /// Sync message bytes received from the peer.
+    pub bytes_received: u64,
+    /// Number of the peer's changes applied.
+    pub changes_applied: u64,
+    /// Time the peer took to answer the last message that expected a reply.
+    pub last_round_trip: Option<Duration>,
+    /// Error of the last failed message.
+    pub last_error: Option<String>,
+    /// Last update timestamp (Unix epoch milliseconds).
+    pub updated_at: u64,
+}
+
+/// Sync progress tracker entry.
+struct ProgressEntry {
+    /// Reported progress.
+    progress: SyncProgress,
+    /// When the message the peer should answer was sent.
+    awaiting_since: Option<Instant>,
+}
+
+impl ProgressEntry {
+    fn new(peer: &PeerId, namespace: &str, id: &str) -> Self {
+        Self {
+            progress: SyncProgress {
+                peer_id: peer.clone(),
+                namespace: namespace.to_string(),
+                document_id: id.to_string(),
+                phase: SyncPhase::Requested,
+                rounds: 0,
+                bytes_sent: 0,
+                bytes_received: 0,
+                changes_applied: 0,
+                last_round_trip: None,
+                last_error: None,
+                updated_at: 0,
+            },
+            awaiting_since: None,
+        }
+    }
+
+    /// Record a message from the peer; one that doesn't answer this node
+    /// starts a round.
+    fn received(&mut self, bytes: usize) {
+        self.progress.bytes_received += bytes as u64;
+        match self.awaiting_since.take() {
+            Some(sent) => self.progress.last_round_trip = Some(sent.elapsed()),
+            None => self.progress.rounds += 1,
         }
     }
+
+    /// Record a message sent to the peer (if `bytes` isn't 0) and the phase
+    /// the sync is in.
+    fn sent(&mut self, bytes: usize, phase: SyncPhase) {
+        self.progress.bytes_sent += bytes as u64;
+        self.progress.phase = phase;
+        self.progress.last_error = None;
+        self.awaiting_since = (bytes > 0 && phase != SyncPhase::InSync).then(Instant::now);
+    }
+
+    fn fail(&mut self, error: &P2PError) {
+        self.progress.phase = SyncPhase::Failed;
+        self.progress.last_error = Some(error.to_string());
+        self.awaiting_since = None;
+    }
+}
+
+/// Outcome of handling an Automerge sync message.
+struct SyncRound {
+    /// Number of the peer's changes applied.
+    applied: usize,
+    /// Whether both sides have the same heads.
+    converged: bool,
+    /// Encoded reply, if any.
+    reply: Option<Vec<u8>>,
 }
 
 /// Sync statistics.
@@ -379,6 +1341,51 @@ pub struct SyncStats {
     pub tracked_documents: usize,
     /// Total number of sync operations.
     pub total_sync_count: u64,
+    /// Reconnect latency to recently-seen peers.
+    pub reconnects: ReconnectStats,
+}
+
+/// Reconnect latency statistics, measured against [`PARTITION_HEAL_TARGET`].
+#[derive(Debug, Clone, Default)]
+pub struct ReconnectStats {
+    /// Number of reconnects to recently-seen peers.
+    pub count: u64,
+    /// Number of reconnects that exceeded the partition-healing target.
+    pub over_target: u64,
+    /// Latency of the most recent reconnect.
+    pub last_latency: Option<Duration>,
+    /// Slowest reconnect.
+    pub max_latency: Option<Duration>,
+    /// Sum of all reconnect latencies.
+    total_latency: Duration,
+}
+
+impl ReconnectStats {
+    fn record(&mut self, latency: Duration) {
+        self.count += 1;
+        if latency > PARTITION_HEAL_TARGET {
+            self.over_target += 1;
+        }
+        self.last_latency = Some(latency);
+        self.max_latency = Some(self.max_latency.map_or(latency, |max| max.max(latency)));
+        self.total_latency += latency;
+    }
+
+    /// Average reconnect latency.
+    pub fn average_latency(&self) -> Option<Duration> {
+        (self.count > 0).then(|| self.total_latency / self.count as u32)
+    }
+
+    /// Check whether every reconnect met the partition-healing target.
+    pub fn within_target(&self) -> bool {
+        self.over_target == 0
+    }
+}
+
+/// Reset an Automerge sync state to what survives a reconnect (the heads
+/// shared with the peer).
+fn reset(state: &sync::State) -> sync::State {
+    sync::State::decode(&state.encode()).unwrap_or_default()
 }
 
 /// Get current timestamp in milliseconds.
@@ -392,6 +1399,8 @@ fn current_timestamp() -> u64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::auth::{session_token, READ, WRITE};
+    use vudo_identity::DeviceIdentity;
 
     #[test]
     fn test_sync_message_serialization() {
@@ -443,4 +1452,503 @@ mod tests {
         let stats = protocol.get_stats();
         assert_eq!(stats.tracked_documents, 0);
     }
+
+    #[tokio::test]
+    async fn test_track_announcement() {
+        let engine = Arc::new(StateEngine::new().await.unwrap());
+        let protocol = SyncProtocol::new(engine);
+        let peer1 = "peer1".to_string();
+        let peer2 = "peer2".to_string();
+
+        assert!(protocol.track_announcement(&peer1, "users", "alice", 2));
+        // Repeated and stale announcements are redundant
+        assert!(!protocol.track_announcement(&peer1, "users", "alice", 2));
+        assert!(!protocol.track_announcement(&peer1, "users", "alice", 1));
+        assert!(protocol.track_announcement(&peer1, "users", "alice", 3));
+
+        // Versions are tracked per peer and per document
+        assert!(protocol.track_announcement(&peer2, "users", "alice", 1));
+        assert!(protocol.track_announcement(&peer1, "users", "bob", 1));
+
+        protocol.clear_peer_state(&peer1);
+        assert!(protocol.track_announcement(&peer1, "users", "alice", 3));
+        assert!(!protocol.track_announcement(&peer2, "users", "alice", 1));
+    }
+
+    #[tokio::test]
+    async fn test_authenticated_session() {
+        let device = DeviceIdentity::generate("Phone").await.unwrap();
+        let policy = SyncAuthPolicy::new().trust(device.did().clone());
+        let engine = Arc::new(StateEngine::new().await.unwrap());
+        let protocol = SyncProtocol::new(engine).with_auth(policy);
+        let (peer, local) = ("phone".to_string(), "laptop".to_string());
+
+        assert!(protocol.requires_auth());
+        assert!(matches!(
+            protocol.authorize(&peer, "users", "alice", READ),
+            Err(P2PError::PermissionDenied(_))
+        ));
+
+        let token = session_token(&device, &peer, &local).unwrap();
+        assert!(protocol.authenticate(&peer, &peer, &token).is_err());
+        let reply = protocol.authenticate(&peer, &local, &token).unwrap();
+        assert!(
+            matches!(reply, SyncMessage::Authenticated { did } if did == device.did().as_str())
+        );
+        assert!(protocol.authorize(&peer, "users", "alice", WRITE).is_ok());
+        assert_eq!(protocol.peer_auth(&peer).unwrap().did, *device.did());
+
+        protocol.clear_peer_state(&peer);
+        assert!(protocol.peer_auth(&peer).is_none());
+        assert!(protocol.authorize(&peer, "users", "alice", READ).is_err());
+
+        // Without a policy everyone may sync
+        let open = SyncProtocol::new(Arc::new(StateEngine::new().await.unwrap()));
+        assert!(open.authorize(&peer, "users", "alice", WRITE).is_ok());
+        assert!(open.authenticate(&peer, &local, &token).is_err());
+    }
+
+    /// Exchange Automerge sync messages between two nodes until both are
+    /// in sync. Returns the bytes sent from `a` to `b` and back.
+    async fn run_sync(
+        (a, a_id): (&SyncProtocol, &PeerId),
+        (b, b_id): (&SyncProtocol, &PeerId),
+    ) -> (usize, usize) {
+        let (mut sent, mut received) = (0, 0);
+        let mut next = Some(a.start_sync(b_id, "users", "alice").await.unwrap());
+        let mut from_a = true;
+        while let Some(SyncMessage::AutomergeSync {
+            namespace,
+            id,
+            message,
+        }) = next
+        {
+            let (size, (to, from)) = (message.len(), if from_a { (b, a_id) } else { (a, b_id) });
+            if from_a {
+                sent += size;
+            } else {
+                received += size;
+            }
+            next = to.receive_sync(from, namespace, id, message).await.unwrap();
+            from_a = !from_a;
+        }
+        (sent, received)
+    }
+
+    #[tokio::test]
+    async fn test_did_challenge() {
+        let device = DeviceIdentity::generate("Phone").await.unwrap();
+        let laptop = SyncProtocol::new(Arc::new(StateEngine::new().await.unwrap()));
+        let phone = SyncProtocol::new(Arc::new(StateEngine::new().await.unwrap()));
+        let (laptop_id, phone_id) = ("laptop".to_string(), "phone".to_string());
+        let did = device.did().as_str();
+
+        // Without a device there is nothing to prove
+        let SyncMessage::Challenge(challenge) =
+            laptop.challenge(&phone_id, &laptop_id, device.did().clone())
+        else {
+            panic!("expected a challenge");
+        };
+        assert!(!laptop.needs_challenge(&phone_id, did));
+        assert!(phone
+            .answer_challenge(&laptop_id, &phone_id, &challenge)
+            .is_err());
+
+        // Answers only count on the connection they were asked on
+        phone.set_device(Some(device.clone()));
+        assert!(phone
+            .answer_challenge(&"mallory".to_string(), &phone_id, &challenge)
+            .is_err());
+        let SyncMessage::ChallengeResponse(response) = phone
+            .answer_challenge(&laptop_id, &phone_id, &challenge)
+            .unwrap()
+        else {
+            panic!("expected a challenge response");
+        };
+        assert!(laptop
+            .verify_challenge(&"mallory".to_string(), &response)
+            .is_err());
+        assert_eq!(
+            laptop.verify_challenge(&phone_id, &response).unwrap(),
+            *device.did()
+        );
+        assert_eq!(laptop.proven_did(&phone_id).unwrap(), *device.did());
+        assert!(!laptop.needs_challenge(&phone_id, did));
+
+        // Each challenge is answered once, and proofs end with the connection
+        assert!(laptop.verify_challenge(&phone_id, &response).is_err());
+        laptop.clear_peer_state(&phone_id);
+        assert!(laptop.proven_did(&phone_id).is_none());
+        assert!(laptop.needs_challenge(&phone_id, did));
+    }
+
+    #[tokio::test]
+    async fn test_incremental_sync() {
+        let (laptop_engine, phone_engine) = (
+            Arc::new(StateEngine::new().await.unwrap()),
+            Arc::new(StateEngine::new().await.unwrap()),
+        );
+        let laptop = SyncProtocol::new(Arc::clone(&laptop_engine));
+        let phone = SyncProtocol::new(Arc::clone(&phone_engine));
+        let (laptop_id, phone_id) = ("laptop".to_string(), "phone".to_string());
+
+        let doc_id = DocumentId::new("users", "alice");
+        let handle = laptop_engine.create_document(doc_id.clone()).await.unwrap();
+        handle
+            .update(|doc| {
+                for i in 0..500 {
+                    doc.put(ROOT, format!("field{}", i), i as i64)?;
+                }
+                Ok(())
+            })
+            .unwrap();
+
+        // Initial sync transfers the whole document
+        let (_, initial) = run_sync((&phone, &phone_id), (&laptop, &laptop_id)).await;
+
+        let synced = phone_engine.get_document(&doc_id).await.unwrap();
+        assert_eq!(synced.heads(), handle.heads());
+        assert_eq!(phone.sessions().len(), 1);
+
+        // After a reconnect only the new change is sent
+        phone.clear_peer_state(&laptop_id);
+        laptop.clear_peer_state(&phone_id);
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "name", "Alice")?;
+                Ok(())
+            })
+            .unwrap();
+        let (sent, received) = run_sync((&phone, &phone_id), (&laptop, &laptop_id)).await;
+        assert!(sent + received < initial / 4);
+        assert_eq!(synced.heads(), handle.heads());
+
+        // Sync states survive a restart
+        assert_eq!(phone.persist_sync_states().await.unwrap(), 1);
+        let restarted = SyncProtocol::new(Arc::clone(&phone_engine));
+        assert_eq!(restarted.load_sync_states().await.unwrap(), 1);
+        let (sent, received) = run_sync((&restarted, &phone_id), (&laptop, &laptop_id)).await;
+        assert!(sent + received < initial / 4);
+
+        // Changes are pushed as well, and need write access
+        synced
+            .update(|doc| {
+                doc.put(ROOT, "city", "Lisbon")?;
+                Ok(())
+            })
+            .unwrap();
+        run_sync((&restarted, &phone_id), (&laptop, &laptop_id)).await;
+        assert_eq!(handle.heads(), synced.heads());
+
+        let payload = |message: Option<SyncMessage>| match message {
+            Some(SyncMessage::AutomergeSync { message, .. }) => message,
+            other => panic!("Unexpected message: {:?}", other),
+        };
+        let device = DeviceIdentity::generate("Phone").await.unwrap();
+        let policy = SyncAuthPolicy::new().trust(device.did().clone());
+        let guarded =
+            SyncProtocol::new(Arc::new(StateEngine::new().await.unwrap())).with_auth(policy);
+        let (users, alice) = ("users".to_string(), "alice".to_string());
+        let hello = restarted
+            .start_sync(&laptop_id, "users", "alice")
+            .await
+            .unwrap();
+        let reply = guarded
+            .receive_sync(
+                &phone_id,
+                users.clone(),
+                alice.clone(),
+                payload(Some(hello)),
+            )
+            .await
+            .unwrap();
+        let push = restarted
+            .receive_sync(&laptop_id, users.clone(), alice.clone(), payload(reply))
+            .await
+            .unwrap();
+        assert!(matches!(
+            guarded
+                .receive_sync(&phone_id, users, alice, payload(push))
+                .await,
+            Err(P2PError::PermissionDenied(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sync_progress() {
+        let laptop_engine = Arc::new(StateEngine::new().await.unwrap());
+        let laptop = SyncProtocol::new(Arc::clone(&laptop_engine));
+        let phone = SyncProtocol::new(Arc::new(StateEngine::new().await.unwrap()));
+        let (laptop_id, phone_id) = ("laptop".to_string(), "phone".to_string());
+
+        let handle = laptop_engine
+            .create_document(DocumentId::new("users", "alice"))
+            .await
+            .unwrap();
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "name", "Alice")?;
+                doc.put(ROOT, "city", "Lisbon")?;
+                Ok(())
+            })
+            .unwrap();
+
+        // Both sides report the round once it converged
+        let (sent, received) = run_sync((&phone, &phone_id), (&laptop, &laptop_id)).await;
+        let progress = phone.progress(&laptop_id, "users", "alice").unwrap();
+        assert_eq!(progress.phase, SyncPhase::InSync);
+        assert_eq!(progress.rounds, 1);
+        assert_eq!(progress.bytes_sent, sent as u64);
+        assert_eq!(progress.bytes_received, received as u64);
+        assert_eq!(progress.changes_applied, 1);
+        assert!(progress.last_round_trip.is_some());
+        let progress = laptop.progress(&phone_id, "users", "alice").unwrap();
+        assert_eq!(progress.phase, SyncPhase::InSync);
+        assert_eq!(progress.bytes_received, sent as u64);
+        assert_eq!(laptop.all_progress(), vec![progress]);
+
+        // A round cut off by a disconnect is reported as interrupted
+        let request = phone.start_sync(&laptop_id, "users", "alice").await;
+        assert!(request.is_ok());
+        phone.clear_peer_state(&laptop_id);
+        let progress = phone.progress(&laptop_id, "users", "alice").unwrap();
+        assert_eq!(progress.phase, SyncPhase::Interrupted);
+        assert_eq!(progress.rounds, 2);
+
+        // Failures keep their reason
+        let result = phone
+            .receive_sync(&laptop_id, "users".into(), "alice".into(), vec![0xff])
+            .await;
+        assert!(result.is_err());
+        let progress = phone.progress(&laptop_id, "users", "alice").unwrap();
+        assert_eq!(progress.phase, SyncPhase::Failed);
+        let error = progress.last_error.unwrap();
+        assert!(error.contains("Invalid sync message"));
+        assert!(phone.progress(&laptop_id, "users", "bob").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_deletion() {
+        use crate::tombstones::DocumentDeletion;
+        use ed25519_dalek::SigningKey;
+        use vudo_privacy::crypto::PersonalDataCrypto;
+
+        let (laptop_engine, phone_engine) = (
+            Arc::new(StateEngine::new().await.unwrap()),
+            Arc::new(StateEngine::new().await.unwrap()),
+        );
+        let laptop = SyncProtocol::new(Arc::clone(&laptop_engine));
+        let phone = SyncProtocol::new(Arc::clone(&phone_engine));
+        let (laptop_id, phone_id) = ("laptop".to_string(), "phone".to_string());
+
+        let doc_id = DocumentId::new("users", "alice");
+        let handle = laptop_engine.create_document(doc_id.clone()).await.unwrap();
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "email", "alice@example.com")?;
+                Ok(())
+            })
+            .unwrap();
+        run_sync((&phone, &phone_id), (&laptop, &laptop_id)).await;
+        assert!(phone_engine.get_document(&doc_id).await.is_ok());
+
+        // Alice's key is erased and the deletion reaches the phone
+        let alice = DeviceIdentity::generate("Alice").await.unwrap();
+        let crypto = PersonalDataCrypto::new();
+        crypto.generate_dek(alice.did().as_str()).unwrap();
+        let mut receipt = crypto.delete_dek(alice.did().as_str()).unwrap();
+        receipt.sign(&alice.signing_key()).unwrap();
+        let signed = DocumentDeletion::new("users", "alice", receipt)
+            .sign(&SigningKey::from_bytes(&[7; 32]))
+            .unwrap();
+        let message = SyncMessage::Delete(signed.clone());
+        let signed = match SyncMessage::from_bytes(&message.to_bytes().unwrap()).unwrap() {
+            SyncMessage::Delete(signed) => signed,
+            other => panic!("Unexpected message: {:?}", other),
+        };
+        assert!(phone.apply_deletion(signed.clone()).await.unwrap());
+        assert!(!phone.apply_deletion(signed).await.unwrap());
+        assert!(phone.is_deleted("users", "alice"));
+        assert!(phone_engine.get_document(&doc_id).await.is_err());
+        assert!(phone.sessions().is_empty());
+
+        // The laptop's copy doesn't come back, also after a restart
+        let hello = laptop
+            .start_sync(&phone_id, "users", "alice")
+            .await
+            .unwrap();
+        let SyncMessage::AutomergeSync { message, .. } = hello else {
+            panic!("Unexpected message");
+        };
+        let (users, alice) = ("users".to_string(), "alice".to_string());
+        assert!(matches!(
+            phone
+                .receive_sync(&laptop_id, users.clone(), alice.clone(), message)
+                .await,
+            Err(P2PError::DocumentDeleted(_))
+        ));
+        let restarted = SyncProtocol::new(Arc::clone(&phone_engine));
+        assert_eq!(restarted.load_tombstones().await.unwrap(), 1);
+        let result = restarted
+            .apply_full_document(&laptop_id, users, alice, handle.save())
+            .await;
+        assert!(matches!(result, Err(P2PError::DocumentDeleted(_))));
+        assert!(phone_engine.get_document(&doc_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_deletion_needs_owner_write_access() {
+        use crate::auth::deletion_token;
+        use crate::tombstones::DocumentDeletion;
+        use ed25519_dalek::SigningKey;
+        use vudo_identity::{Capability, MasterIdentity, Ucan};
+        use vudo_privacy::crypto::PersonalDataCrypto;
+
+        let master = MasterIdentity::generate("Alice").await.unwrap();
+        let policy = SyncAuthPolicy::new().trust(master.did.clone());
+        let phone =
+            SyncProtocol::new(Arc::new(StateEngine::new().await.unwrap())).with_auth(policy);
+        let node_key = SigningKey::from_bytes(&[7; 32]);
+
+        async fn linked(master: &MasterIdentity, action: &str) -> DeviceIdentity {
+            let mut device = DeviceIdentity::generate("Laptop").await.unwrap();
+            let authorization = Ucan::new(
+                master.did.clone(),
+                device.did().clone(),
+                vec![Capability::new("vudo://users/alice", action)],
+                current_timestamp() / 1000 + 600,
+                None,
+                None,
+                vec![],
+            )
+            .sign(&master.signing_key())
+            .unwrap();
+            device.link_to_master(master.did.clone(), authorization);
+            device
+        }
+        let deletion = |owner: &DeviceIdentity| {
+            let crypto = PersonalDataCrypto::new();
+            crypto.generate_dek(owner.did().as_str()).unwrap();
+            let mut receipt = crypto.delete_dek(owner.did().as_str()).unwrap();
+            receipt.sign(&owner.signing_key()).unwrap();
+            DocumentDeletion::new("users", "alice", receipt)
+        };
+
+        // A stranger's own receipt and token don't reach a trusted issuer
+        let mallory = DeviceIdentity::generate("Mallory").await.unwrap();
+        let token = deletion_token(&mallory, "users", "alice").unwrap();
+        let signed = deletion(&mallory)
+            .with_authorization(token)
+            .sign(&node_key)
+            .unwrap();
+        assert!(matches!(
+            phone.apply_deletion(signed).await,
+            Err(P2PError::PermissionDenied(_))
+        ));
+
+        // Nor may a device that can only read the document delete it
+        let reader = linked(&master, READ).await;
+        let token = deletion_token(&reader, "users", "alice").unwrap();
+        let signed = deletion(&reader)
+            .with_authorization(token)
+            .sign(&node_key)
+            .unwrap();
+        assert!(phone.apply_deletion(signed).await.is_err());
+
+        // A writer needs a token for this document, issued by the owner
+        let writer = linked(&master, WRITE).await;
+        let signed = deletion(&writer).sign(&node_key).unwrap();
+        assert!(phone.apply_deletion(signed).await.is_err());
+        let token = deletion_token(&writer, "users", "bob").unwrap();
+        let signed = deletion(&writer)
+            .with_authorization(token)
+            .sign(&node_key)
+            .unwrap();
+        assert!(phone.apply_deletion(signed).await.is_err());
+        let token = deletion_token(&writer, "users", "alice").unwrap();
+        let signed = deletion(&reader)
+            .with_authorization(token.clone())
+            .sign(&node_key)
+            .unwrap();
+        assert!(phone.apply_deletion(signed).await.is_err());
+        assert!(!phone.is_deleted("users", "alice"));
+
+        let signed = deletion(&writer)
+            .with_authorization(token)
+            .sign(&node_key)
+            .unwrap();
+        assert!(phone.apply_deletion(signed).await.unwrap());
+        assert!(phone.is_deleted("users", "alice"));
+    }
+
+    #[tokio::test]
+    async fn test_sync_conflicts() {
+        let (laptop_engine, phone_engine) = (
+            Arc::new(StateEngine::new().await.unwrap()),
+            Arc::new(StateEngine::new().await.unwrap()),
+        );
+        let phone = SyncProtocol::new(Arc::clone(&phone_engine));
+        let mut conflicts = phone.conflicts().subscribe();
+        let laptop_id = "laptop".to_string();
+
+        // Both devices write the name while apart
+        let doc_id = DocumentId::new("users", "alice");
+        let laptop_doc = laptop_engine.create_document(doc_id.clone()).await.unwrap();
+        let phone_doc = phone_engine.create_document(doc_id).await.unwrap();
+        for (handle, name) in [(&laptop_doc, "Alice"), (&phone_doc, "Alicia")] {
+            handle
+                .update(|doc| {
+                    doc.put(ROOT, "name", name)?;
+                    Ok(())
+                })
+                .unwrap();
+        }
+
+        let (users, alice) = ("users".to_string(), "alice".to_string());
+        phone
+            .apply_sync_changes(
+                &laptop_id,
+                users.clone(),
+                alice.clone(),
+                vec![laptop_doc.save()],
+            )
+            .await
+            .unwrap();
+        let conflict = conflicts.try_recv().unwrap();
+        assert_eq!(conflict.peer_id, laptop_id);
+        assert_eq!(conflict.keys, vec!["name"]);
+        assert_eq!(conflict.actors.len(), 2);
+        assert!(conflict.actors.contains(&phone_doc.actor().to_string()));
+        assert_eq!(conflict.unresolved.len(), 1);
+
+        // The same conflict isn't reported twice
+        phone
+            .apply_sync_changes(&laptop_id, users, alice, vec![laptop_doc.save()])
+            .await
+            .unwrap();
+        assert!(conflicts.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_stats() {
+        let engine = Arc::new(StateEngine::new().await.unwrap());
+        let protocol = SyncProtocol::new(engine);
+        let peer = "peer1".to_string();
+
+        assert!(protocol.get_stats().reconnects.average_latency().is_none());
+
+        protocol.record_reconnect(&peer, Duration::from_millis(200));
+        protocol.record_reconnect(&peer, Duration::from_millis(400));
+        let stats = protocol.get_stats().reconnects;
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.average_latency(), Some(Duration::from_millis(300)));
+        assert_eq!(stats.max_latency, Some(Duration::from_millis(400)));
+        assert!(stats.within_target());
+
+        protocol.record_reconnect(&peer, Duration::from_secs(6));
+        let stats = protocol.get_stats().reconnects;
+        assert_eq!(stats.over_target, 1);
+        assert_eq!(stats.last_latency, Some(Duration::from_secs(6)));
+        assert!(!stats.within_target());
+    }
 }
@@ -0,0 +1,375 @@
+//! Propagation of GDPR deletions through the sync protocol.
+//!
+//! Willow entries carry their own tombstones, but Automerge sync would merge
+//! a deleted document straight back from any peer still holding a copy.
+//! Deleting a document therefore produces a [`SignedDeletion`]: the
+//! document's namespace and ID with a vudo-privacy [`DeletionReceipt`]
+//! signed by the owner, signed with the node key. Peers receiving it as
+//! [`SyncMessage::Delete`](crate::SyncMessage::Delete) verify it, delete
+//! their copy, pass it on and keep it in the [`TombstoneStore`]. From then on
+//! changes to the document are refused, and peers trying to sync it are sent
+//! the deletion instead, so it also reaches peers that were offline.
+//!
+//! Peers requiring authentication also need the owner's
+//! [`deletion_token`](crate::auth::deletion_token), proving the owner may
+//! write the document.
+
+use crate::error::{P2PError, Result};
+use crate::gossip::peer_id_of;
+use crate::session_cache::SESSION_CACHE_NAMESPACE;
+use crate::sync_protocol::PeerId;
+use automerge::{transaction::Transactable, ReadDoc, ROOT};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::warn;
+use vudo_identity::Did;
+use vudo_privacy::crypto::DeletionReceipt;
+use vudo_state::{DocumentId, StateEngine};
+
+/// Key of the document holding persisted tombstones (in the session cache
+/// namespace).
+pub const TOMBSTONES_KEY: &str = "tombstones";
+
+/// Domain tag of deletion signatures.
+const SIGNATURE_DOMAIN: &[u8] = b"vudo-deletion/1";
+
+/// Deletion of a document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentDeletion {
+    /// Document namespace.
+    pub namespace: String,
+    /// Document key.
+    pub id: String,
+    /// Proof of the erasure of the owner's personal data.
+    #[serde(with = "receipt_json")]
+    pub receipt: DeletionReceipt,
+    /// Deletion token of the owner (see [`crate::auth::deletion_token`]).
+    pub authorization: Option<String>,
+}
+
+impl DocumentDeletion {
+    /// Create a deletion of a document.
+    pub fn new(namespace: &str, id: &str, receipt: DeletionReceipt) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            id: id.to_string(),
+            receipt,
+            authorization: None,
+        }
+    }
+
+    /// Attach the owner's deletion token.
+    pub fn with_authorization(mut self, token: String) -> Self {
+        self.authorization = Some(token);
+        self
+    }
+
+    /// Sign the deletion with a node key.
+    pub fn sign(self, signing_key: &SigningKey) -> Result<SignedDeletion> {
+        let signature = signing_key.sign(&SignedDeletion::signed_bytes(&self)?);
+        Ok(SignedDeletion {
+            deletion: self,
+            signer: signing_key.verifying_key(),
+            signature,
+        })
+    }
+}
+
+/// Receipts skip their committee timestamp when it is absent, which bincode
+/// can't read back, so they travel as JSON.
+mod receipt_json {
+    use super::DeletionReceipt;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S>(receipt: &DeletionReceipt, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let json = serde_json::to_string(receipt).map_err(serde::ser::Error::custom)?;
+        json.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<DeletionReceipt, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let json = String::deserialize(deserializer)?;
+        serde_json::from_str(&json).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Document deletion signed by the node that deleted the document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedDeletion {
+    /// The deletion.
+    pub deletion: DocumentDeletion,
+    /// Node key of the deleting node.
+    pub signer: VerifyingKey,
+    /// Signature over the deletion.
+    pub signature: Signature,
+}
+
+impl SignedDeletion {
+    /// Bytes covered by the signature.
+    fn signed_bytes(deletion: &DocumentDeletion) -> Result<Vec<u8>> {
+        let mut bytes = SIGNATURE_DOMAIN.to_vec();
+        bytes.extend(bincode::serialize(deletion)?);
+        Ok(bytes)
+    }
+
+    /// Check the signature, and that the receipt proves an irreversible
+    /// erasure and was signed by its owner.
+    ///
+    /// Whether the owner may write the document is checked against the
+    /// sync auth policy, see [`SyncProtocol::apply_deletion`](crate::SyncProtocol::apply_deletion).
+    pub fn verify(&self) -> Result<()> {
+        self.signer
+            .verify(&Self::signed_bytes(&self.deletion)?, &self.signature)
+            .map_err(|_| P2PError::PermissionDenied("Invalid deletion signature".to_string()))?;
+
+        let (namespace, id) = (&self.deletion.namespace, &self.deletion.id);
+        let receipt = &self.deletion.receipt;
+        if !receipt.irreversible {
+            return Err(P2PError::InvalidMessage(format!(
+                "Deletion of {}/{} is not irreversible",
+                namespace, id
+            )));
+        }
+        let owner = Did::parse(&receipt.owner)?;
+        receipt.verify(&owner.verification_key).map_err(|e| {
+            P2PError::PermissionDenied(format!(
+                "Receipt for {}/{} was not signed by {}: {}",
+                namespace, id, receipt.owner, e
+            ))
+        })?;
+        Ok(())
+    }
+
+    /// Get the peer ID of the deleting node.
+    pub fn signer_id(&self) -> PeerId {
+        peer_id_of(&self.signer)
+    }
+}
+
+/// Tombstones of deleted documents.
+#[derive(Default)]
+pub struct TombstoneStore {
+    /// Deletions by (namespace, document ID).
+    tombstones: RwLock<HashMap<(String, String), SignedDeletion>>,
+}
+
+impl TombstoneStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check whether a document was deleted.
+    pub fn contains(&self, namespace: &str, id: &str) -> bool {
+        self.tombstones
+            .read()
+            .contains_key(&(namespace.to_string(), id.to_string()))
+    }
+
+    /// Get the deletion of a document.
+    pub fn get(&self, namespace: &str, id: &str) -> Option<SignedDeletion> {
+        self.tombstones
+            .read()
+            .get(&(namespace.to_string(), id.to_string()))
+            .cloned()
+    }
+
+    /// Record a deletion. Returns `false` if the document already had a
+    /// tombstone.
+    pub fn insert(&self, deletion: SignedDeletion) -> bool {
+        let key = (
+            deletion.deletion.namespace.clone(),
+            deletion.deletion.id.clone(),
+        );
+        let mut tombstones = self.tombstones.write();
+        if tombstones.contains_key(&key) {
+            return false;
+        }
+        tombstones.insert(key, deletion);
+        true
+    }
+
+    /// Get all deletions.
+    pub fn list(&self) -> Vec<SignedDeletion> {
+        self.tombstones.read().values().cloned().collect()
+    }
+
+    /// Get the number of tombstones.
+    pub fn len(&self) -> usize {
+        self.tombstones.read().len()
+    }
+
+    /// Check whether there are no tombstones.
+    pub fn is_empty(&self) -> bool {
+        self.tombstones.read().is_empty()
+    }
+
+    /// Persist a tombstone in the state engine.
+    pub async fn persist(&self, engine: &StateEngine, deletion: &SignedDeletion) -> Result<()> {
+        let doc_id = DocumentId::new(SESSION_CACHE_NAMESPACE, TOMBSTONES_KEY);
+        let handle = match engine.get_document(&doc_id).await {
+            Ok(h) => h,
+            Err(_) => engine.create_document(doc_id).await?,
+        };
+
+        let key = serde_json::to_string(&(&deletion.deletion.namespace, &deletion.deletion.id))?;
+        let bytes = bincode::serialize(deletion)?;
+        handle.update(|doc| {
+            doc.put(ROOT, key.as_str(), bytes)?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// Load tombstones persisted by [`TombstoneStore::persist`].
+    ///
+    /// Unreadable or invalid tombstones are skipped. Returns the number of
+    /// tombstones loaded.
+    pub async fn load(&self, engine: &StateEngine) -> Result<usize> {
+        let doc_id = DocumentId::new(SESSION_CACHE_NAMESPACE, TOMBSTONES_KEY);
+        let handle = match engine.get_document(&doc_id).await {
+            Ok(h) => h,
+            Err(_) => return Ok(0),
+        };
+
+        let stored: Vec<Vec<u8>> = handle.read(|doc| {
+            let mut stored = Vec::new();
+            for key in doc.keys(ROOT) {
+                if let Some((value, _)) = doc.get(ROOT, key.as_str())? {
+                    if let Some(bytes) = value.to_bytes() {
+                        stored.push(bytes.to_vec());
+                    }
+                }
+            }
+            Ok(stored)
+        })?;
+
+        let mut loaded = 0;
+        for bytes in stored {
+            match bincode::deserialize::<SignedDeletion>(&bytes) {
+                Ok(deletion) if deletion.verify().is_ok() => {
+                    self.insert(deletion);
+                    loaded += 1;
+                }
+                _ => warn!("Skipping unreadable tombstone"),
+            }
+        }
+        Ok(loaded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Receipt of the erasure of the data of `owner`, signed by the owner.
+    fn receipt(owner: &SigningKey) -> DeletionReceipt {
+        let mut receipt = DeletionReceipt {
+            owner: Did::from_key(owner.verifying_key()).to_string(),
+            deleted_at: 1_700_000_000,
+            irreversible: true,
+            scope: vec![vudo_privacy::DataCategory::PersonalData],
+            key_commitment: "dek-1".to_string(),
+            timestamp: None,
+            signer: None,
+            signature: None,
+        };
+        receipt.sign(owner).unwrap();
+        receipt
+    }
+
+    #[test]
+    fn test_signed_deletion() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let alice = SigningKey::from_bytes(&[1; 32]);
+        let signed = DocumentDeletion::new("users", "alice", receipt(&alice))
+            .sign(&key)
+            .unwrap();
+        signed.verify().unwrap();
+        assert_eq!(signed.signer_id(), peer_id_of(&key.verifying_key()));
+
+        // Survives the wire
+        let bytes = bincode::serialize(&signed).unwrap();
+        let decoded: SignedDeletion = bincode::deserialize(&bytes).unwrap();
+        decoded.verify().unwrap();
+        assert_eq!(
+            decoded.deletion.receipt.owner,
+            signed.deletion.receipt.owner
+        );
+
+        let mut tampered = signed.clone();
+        tampered.deletion.id = "bob".to_string();
+        assert!(tampered.verify().is_err());
+
+        let mut reversible = receipt(&alice);
+        reversible.irreversible = false;
+        reversible.sign(&alice).unwrap();
+        let signed = DocumentDeletion::new("users", "alice", reversible)
+            .sign(&key)
+            .unwrap();
+        assert!(signed.verify().is_err());
+    }
+
+    #[test]
+    fn test_forged_receipt() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let alice = SigningKey::from_bytes(&[1; 32]);
+        let mallory = SigningKey::from_bytes(&[2; 32]);
+
+        // Mallory signs a receipt naming Alice as the owner
+        let mut forged = receipt(&alice);
+        forged.sign(&mallory).unwrap();
+        let signed = DocumentDeletion::new("users", "alice", forged)
+            .sign(&key)
+            .unwrap();
+        assert!(matches!(
+            signed.verify(),
+            Err(P2PError::PermissionDenied(_))
+        ));
+
+        // Or leaves it unsigned
+        let mut unsigned = receipt(&alice);
+        unsigned.signer = None;
+        unsigned.signature = None;
+        let signed = DocumentDeletion::new("users", "alice", unsigned)
+            .sign(&key)
+            .unwrap();
+        assert!(signed.verify().is_err());
+
+        // Or changes what Alice signed
+        let mut altered = receipt(&alice);
+        altered.deleted_at += 1;
+        let signed = DocumentDeletion::new("users", "alice", altered)
+            .sign(&key)
+            .unwrap();
+        assert!(signed.verify().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_persist_and_load() {
+        let engine = StateEngine::new().await.unwrap();
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let store = TombstoneStore::new();
+        let signed = DocumentDeletion::new("users", "alice", receipt(&key))
+            .sign(&key)
+            .unwrap();
+
+        assert!(store.insert(signed.clone()));
+        assert!(!store.insert(signed.clone()));
+        assert!(store.contains("users", "alice"));
+        assert!(!store.contains("users", "bob"));
+        store.persist(&engine, &signed).await.unwrap();
+
+        let restored = TombstoneStore::new();
+        assert_eq!(restored.load(&engine).await.unwrap(), 1);
+        assert!(restored.get("users", "alice").is_some());
+        assert_eq!(restored.len(), 1);
+    }
+}
@@ -3,12 +3,18 @@
 //! This module provides the integration between DOL's document model and
 //! Willow's 3D namespace structure, enabling structured sync with fine-grained
 //! permissions and GDPR-compliant deletion.
+//!
+//! Entries and tombstones are stored with the [`AuthorisationToken`] their
+//! writer signed them with, so peers they are reconciled with can check
+//! the writer's capability.
 
 use crate::error::{P2PError, Result};
-use crate::meadowcap::{Capability, CapabilityStore, Permission};
+use crate::meadowcap::{AuthorisationToken, Capability, CapabilityStore, Permission};
+use crate::willow_sync::{sort_items, Range3d, SyncItem};
 use crate::willow_types::{Entry, NamespaceId, Path, SubspaceId, Tombstone};
 use bytes::Bytes;
 use dashmap::DashMap;
+use ed25519_dalek::SigningKey;
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -47,6 +53,9 @@ pub enum SyncPriority {
     Low,
 }
 
+/// Location of a stored entry or tombstone.
+type ItemKey = (NamespaceId, SubspaceId, Path);
+
 /// Willow Protocol adapter.
 pub struct WillowAdapter {
     /// State engine integration.
@@ -55,10 +64,11 @@ pub struct WillowAdapter {
     namespaces: Arc<RwLock<HashMap<String, NamespaceId>>>,
     /// Capability store for permissions.
     capabilities: Arc<CapabilityStore>,
-    /// Local entry storage (simulated Willow store).
-    entries: Arc<DashMap<(NamespaceId, SubspaceId, Path), Entry>>,
-    /// Tombstone storage for deletions.
-    tombstones: Arc<DashMap<(NamespaceId, SubspaceId, Path), Tombstone>>,
+    /// Local entry storage (simulated Willow store), with the tokens
+    /// authorising the entries.
+    entries: Arc<DashMap<ItemKey, (Entry, AuthorisationToken)>>,
+    /// Tombstone storage for deletions, with the tokens authorising them.
+    tombstones: Arc<DashMap<ItemKey, (Tombstone, AuthorisationToken)>>,
 }
 
 impl WillowAdapter {
@@ -100,6 +110,9 @@ impl WillowAdapter {
     }
 
     /// Write an entry to Willow.
+    ///
+    /// `signing_key` must be the capability's receiver's key; it signs the
+    /// entry's authorisation token.
     pub async fn write_entry(
         &self,
         namespace: &str,
@@ -107,24 +120,25 @@ impl WillowAdapter {
         id: &str,
         data: Bytes,
         capability: &Capability,
+        signing_key: &SigningKey,
     ) -> Result<()> {
         let (ns, subspace, path) = self.map_path(namespace, collection, id);
 
-        // Check write permission
-        if !capability.can_write(subspace, &path) {
-            return Err(P2PError::PermissionDenied(format!(
-                "No write permission for {}/{}",
-                collection, id
-            )));
-        }
-
-        // Verify capability signature
-        capability.verify()?;
+        self.authorize(
+            ns,
+            subspace,
+            collection,
+            &path,
+            capability,
+            Permission::Write,
+        )?;
 
         let timestamp = current_timestamp();
         let entry = Entry::new(ns, subspace, path.clone(), data, timestamp);
+        let token =
+            AuthorisationToken::sign(capability, &SyncItem::entry_message(&entry), signing_key)?;
 
-        self.entries.insert((ns, subspace, path), entry);
+        self.entries.insert((ns, subspace, path), (entry, token));
 
         Ok(())
     }
@@ -139,16 +153,14 @@ impl WillowAdapter {
     ) -> Result<Option<Bytes>> {
         let (ns, subspace, path) = self.map_path(namespace, collection, id);
 
-        // Check read permission
-        if !capability.can_read(subspace, &path) {
-            return Err(P2PError::PermissionDenied(format!(
-                "No read permission for {}/{}",
-                collection, id
-            )));
-        }
-
-        // Verify capability signature
-        capability.verify()?;
+        self.authorize(
+            ns,
+            subspace,
+            collection,
+            &path,
+            capability,
+            Permission::Read,
+        )?;
 
         // Check if tombstone exists (document was deleted)
         if self.tombstones.contains_key(&(ns, subspace, path.clone())) {
@@ -159,43 +171,94 @@ impl WillowAdapter {
         Ok(self
             .entries
             .get(&(ns, subspace, path))
-            .map(|entry| entry.payload.clone()))
+            .map(|entry| entry.0.payload.clone()))
     }
 
     /// Delete an entry (GDPR-compliant deletion with tombstone).
+    ///
+    /// `signing_key` must be the capability's receiver's key.
     pub async fn delete_entry(
         &self,
         namespace: &str,
         collection: &str,
         id: &str,
         capability: &Capability,
+        signing_key: &SigningKey,
         reason: Option<String>,
     ) -> Result<()> {
         let (ns, subspace, path) = self.map_path(namespace, collection, id);
 
-        // Check write permission (delete requires write)
-        if !capability.can_write(subspace, &path) {
-            return Err(P2PError::PermissionDenied(format!(
-                "No write permission for {}/{}",
-                collection, id
-            )));
-        }
-
-        // Verify capability signature
-        capability.verify()?;
+        // Delete requires write
+        self.authorize(
+            ns,
+            subspace,
+            collection,
+            &path,
+            capability,
+            Permission::Write,
+        )?;
 
         let timestamp = current_timestamp();
+        let tombstone = Tombstone::new(ns, subspace, path.clone(), timestamp, reason);
+        let token = AuthorisationToken::sign(
+            capability,
+            &SyncItem::tombstone_message(&tombstone),
+            signing_key,
+        )?;
 
         // Remove entry
         self.entries.remove(&(ns, subspace, path.clone()));
 
-        // Create tombstone
-        let tombstone = Tombstone::new(ns, subspace, path.clone(), timestamp, reason);
-        self.tombstones.insert((ns, subspace, path), tombstone);
+        // Store tombstone
+        self.tombstones
+            .insert((ns, subspace, path), (tombstone, token));
 
         Ok(())
     }
 
+    /// Check that a capability allows an operation on a path.
+    ///
+    /// The capability must cover the path with the required permission, and
+    /// its delegation chain must verify back to a root trusted by the
+    /// capability store.
+    fn authorize(
+        &self,
+        namespace_id: NamespaceId,
+        subspace_id: SubspaceId,
+        collection: &str,
+        path: &Path,
+        capability: &Capability,
+        required: Permission,
+    ) -> Result<()> {
+        if capability.namespace_id != namespace_id
+            || !capability.check_permission(subspace_id, path, required)
+        {
+            return Err(P2PError::PermissionDenied(format!(
+                "No {} permission for {}/{}",
+                format!("{:?}", required).to_lowercase(),
+                collection,
+                path
+            )));
+        }
+
+        self.capabilities.verify_chain(capability)
+    }
+
+    /// Check that an item received from a peer was signed by its writer
+    /// with a capability that may write its path.
+    fn authorize_item(&self, namespace_id: NamespaceId, item: &SyncItem) -> Result<()> {
+        let token = item.token();
+        token.verify(&item.signing_message())?;
+        self.authorize(
+            namespace_id,
+            item.subspace_id(),
+            &item.subspace_id().to_string(),
+            item.path(),
+            &token.capability,
+            Permission::Write,
+        )
+    }
+
     /// Sync document from state engine to Willow.
     pub async fn sync_from_state_engine(
         &self,
@@ -203,6 +266,7 @@ impl WillowAdapter {
         collection: &str,
         id: &str,
         capability: &Capability,
+        signing_key: &SigningKey,
     ) -> Result<()> {
         // Load document from state engine
         let doc_id = DocumentId::new(collection, id);
@@ -216,7 +280,7 @@ impl WillowAdapter {
         let data = Bytes::from(handle.save());
 
         // Write to Willow
-        self.write_entry(namespace, collection, id, data, capability)
+        self.write_entry(namespace, collection, id, data, capability, signing_key)
             .await?;
 
         Ok(())
@@ -260,6 +324,7 @@ impl WillowAdapter {
         namespace: &str,
         collection: &str,
         capability: &Capability,
+        signing_key: &SigningKey,
         constraints: ResourceConstraints,
     ) -> Result<SyncStats> {
         let (ns, subspace, _) = self.map_path(namespace, collection, "");
@@ -282,7 +347,7 @@ impl WillowAdapter {
 
             // Sync document
             match self
-                .sync_from_state_engine(namespace, collection, &doc_id.key, capability)
+                .sync_from_state_engine(namespace, collection, &doc_id.key, capability, signing_key)
                 .await
             {
                 Ok(()) => {
@@ -291,7 +356,7 @@ impl WillowAdapter {
                         .entries
                         .get(&(ns, subspace, Path::from_dol_id(&doc_id.key)))
                     {
-                        total_bytes += entry.size();
+                        total_bytes += entry.0.size();
                     }
                 }
                 Err(_) => {
@@ -314,11 +379,19 @@ impl WillowAdapter {
         collection: &str,
         id: &str,
         capability: &Capability,
+        signing_key: &SigningKey,
         reason: &str,
     ) -> Result<()> {
         // Delete from Willow with tombstone
-        self.delete_entry(namespace, collection, id, capability, Some(reason.to_string()))
-            .await?;
+        self.delete_entry(
+            namespace,
+            collection,
+            id,
+            capability,
+            signing_key,
+            Some(reason.to_string()),
+        )
+        .await?;
 
         // Delete from state engine
         let doc_id = DocumentId::new(collection, id);
@@ -346,16 +419,97 @@ impl WillowAdapter {
                     && entry.key().1 == subspace_id
                     && prefix.is_prefix_of(&entry.key().2)
             })
-            .map(|entry| entry.value().clone())
+            .map(|entry| entry.value().0.clone())
             .collect()
     }
 
+    /// Entries and tombstones of a namespace in a 3D range, sorted by
+    /// subspace, path and timestamp.
+    pub fn range_items(&self, namespace_id: NamespaceId, range: &Range3d) -> Vec<SyncItem> {
+        let entries = self
+            .entries
+            .iter()
+            .filter(|entry| entry.key().0 == namespace_id)
+            .map(|entry| {
+                let (entry, token) = entry.value().clone();
+                SyncItem::Entry(entry, token)
+            });
+        let tombstones = self
+            .tombstones
+            .iter()
+            .filter(|tombstone| tombstone.key().0 == namespace_id)
+            .map(|tombstone| {
+                let (tombstone, token) = tombstone.value().clone();
+                SyncItem::Tombstone(tombstone, token)
+            });
+
+        let mut items: Vec<SyncItem> = entries
+            .chain(tombstones)
+            .filter(|item| range.contains(item))
+            .collect();
+        sort_items(&mut items);
+        items
+    }
+
+    /// Merge an entry or tombstone received from a peer.
+    ///
+    /// The item's token must be signed by its writer with a capability that
+    /// may write the item's path and verifies back to a trusted root. The
+    /// newest version of a path wins (ties broken by payload), and a
+    /// tombstone removes entries no newer than itself. Returns whether the
+    /// store changed.
+    pub fn apply_item(&self, namespace_id: NamespaceId, item: SyncItem) -> Result<bool> {
+        self.authorize_item(namespace_id, &item)?;
+        match item {
+            SyncItem::Entry(entry, token) => {
+                if entry.namespace_id != namespace_id {
+                    return Err(P2PError::WillowError(format!(
+                        "Entry {} belongs to another namespace",
+                        entry.path
+                    )));
+                }
+                let key = (namespace_id, entry.subspace_id, entry.path.clone());
+                if let Some(tombstone) = self.tombstones.get(&key) {
+                    if tombstone.0.timestamp >= entry.timestamp {
+                        return Ok(false);
+                    }
+                }
+                if let Some(existing) = self.entries.get(&key) {
+                    let incoming = (entry.timestamp, &entry.payload);
+                    if (existing.0.timestamp, &existing.0.payload) >= incoming {
+                        return Ok(false);
+                    }
+                }
+                self.entries.insert(key, (entry, token));
+                Ok(true)
+            }
+            SyncItem::Tombstone(tombstone, token) => {
+                if tombstone.namespace_id != namespace_id {
+                    return Err(P2PError::WillowError(format!(
+                        "Tombstone {} belongs to another namespace",
+                        tombstone.path
+                    )));
+                }
+                let key = (namespace_id, tombstone.subspace_id, tombstone.path.clone());
+                if let Some(existing) = self.tombstones.get(&key) {
+                    if existing.0.timestamp >= tombstone.timestamp {
+                        return Ok(false);
+                    }
+                }
+                self.entries
+                    .remove_if(&key, |_, (entry, _)| entry.timestamp <= tombstone.timestamp);
+                self.tombstones.insert(key, (tombstone, token));
+                Ok(true)
+            }
+        }
+    }
+
     /// Get sync statistics.
     pub fn stats(&self) -> WillowStats {
         WillowStats {
             entry_count: self.entries.len(),
             tombstone_count: self.tombstones.len(),
-            total_size: self.entries.iter().map(|e| e.value().size()).sum(),
+            total_size: self.entries.iter().map(|e| e.value().0.size()).sum(),
         }
     }
 }
@@ -396,8 +550,8 @@ fn current_timestamp() -> u64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::meadowcap::Area;
     use automerge::{transaction::Transactable, ROOT};
-    use ed25519_dalek::SigningKey;
 
     #[tokio::test]
     async fn test_willow_adapter_new() {
@@ -448,7 +602,14 @@ mod tests {
 
         let data = Bytes::from("test data");
         adapter
-            .write_entry("myapp.v1", "users", "alice", data.clone(), &capability)
+            .write_entry(
+                "myapp.v1",
+                "users",
+                "alice",
+                data.clone(),
+                &capability,
+                &signing_key,
+            )
             .await
             .unwrap();
 
@@ -468,15 +629,30 @@ mod tests {
         let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
         let namespace_id = adapter.map_namespace("myapp.v1");
         let capability = Capability::new_root(namespace_id, &signing_key);
+        adapter.capabilities().add(capability.clone()).unwrap();
 
         let data = Bytes::from("test data");
         adapter
-            .write_entry("myapp.v1", "users", "alice", data, &capability)
+            .write_entry(
+                "myapp.v1",
+                "users",
+                "alice",
+                data,
+                &capability,
+                &signing_key,
+            )
             .await
             .unwrap();
 
         adapter
-            .delete_entry("myapp.v1", "users", "alice", &capability, Some("test deletion".to_string()))
+            .delete_entry(
+                "myapp.v1",
+                "users",
+                "alice",
+                &capability,
+                &signing_key,
+                Some("test deletion".to_string()),
+            )
             .await
             .unwrap();
 
@@ -497,23 +673,24 @@ mod tests {
         let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
         let namespace_id = adapter.map_namespace("myapp.v1");
         let root_cap = Capability::new_root(namespace_id, &signing_key);
+        adapter.capabilities().add(root_cap.clone()).unwrap();
 
         // Create a write-only capability for Alice's data
         let alice_key = SigningKey::generate(&mut rand::rngs::OsRng);
         let subspace_id = adapter.map_subspace("users");
         let alice_cap = root_cap
             .delegate(
-                Some(subspace_id),
-                Path::from_components(["alice"]),
+                alice_key.verifying_key(),
+                Area::new(Some(subspace_id), Path::from_components(["alice"])),
                 Permission::Write,
-                &alice_key,
+                &signing_key,
             )
             .unwrap();
 
         // Write some data with root capability
         let data = Bytes::from("bob's data");
         adapter
-            .write_entry("myapp.v1", "users", "bob", data, &root_cap)
+            .write_entry("myapp.v1", "users", "bob", data, &root_cap, &signing_key)
             .await
             .unwrap();
 
@@ -525,6 +702,92 @@ mod tests {
         assert!(matches!(result, Err(P2PError::PermissionDenied(_))));
     }
 
+    #[tokio::test]
+    async fn test_delegated_write_rights() {
+        let engine = StateEngine::new().await.unwrap();
+        let adapter = WillowAdapter::new(Arc::new(engine)).await.unwrap();
+
+        let owner_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let namespace_id = adapter.map_namespace("myapp.v1");
+        let root_cap = Capability::new_root(namespace_id, &owner_key);
+        adapter.capabilities().add(root_cap.clone()).unwrap();
+
+        // Alice may write under users/alice only
+        let alice_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let users = adapter.map_subspace("users");
+        let alice_cap = root_cap
+            .delegate(
+                alice_key.verifying_key(),
+                Area::new(Some(users), Path::from_components(["alice"])),
+                Permission::Write,
+                &owner_key,
+            )
+            .unwrap();
+
+        let data = Bytes::from("alice's post");
+        adapter
+            .write_entry(
+                "myapp.v1",
+                "users",
+                "alice/post",
+                data.clone(),
+                &alice_cap,
+                &alice_key,
+            )
+            .await
+            .unwrap();
+        let result = adapter
+            .write_entry(
+                "myapp.v1",
+                "users",
+                "bob/post",
+                data.clone(),
+                &alice_cap,
+                &alice_key,
+            )
+            .await;
+        assert!(matches!(result, Err(P2PError::PermissionDenied(_))));
+
+        // Only Alice can sign entries with her capability
+        let result = adapter
+            .write_entry(
+                "myapp.v1",
+                "users",
+                "alice/post",
+                data.clone(),
+                &alice_cap,
+                &owner_key,
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(P2PError::CapabilityDelegationError(_))
+        ));
+
+        // Capabilities from another "owner" of the namespace are rejected
+        let mallory_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let mallory = Capability::new_root(namespace_id, &mallory_key);
+        let result = adapter
+            .write_entry(
+                "myapp.v1",
+                "users",
+                "alice/post",
+                data,
+                &mallory,
+                &mallory_key,
+            )
+            .await;
+        let untrusted = matches!(result, Err(P2PError::CapabilityDelegationError(_)));
+        assert!(untrusted);
+
+        // So are capabilities for another namespace
+        let other_cap = Capability::new_root(adapter.map_namespace("other.v1"), &owner_key);
+        let result = adapter
+            .read_entry("myapp.v1", "users", "alice/post", &other_cap)
+            .await;
+        assert!(matches!(result, Err(P2PError::PermissionDenied(_))));
+    }
+
     #[tokio::test]
     async fn test_sync_from_state_engine() {
         let engine = Arc::new(StateEngine::new().await.unwrap());
@@ -543,10 +806,11 @@ mod tests {
         let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
         let namespace_id = adapter.map_namespace("myapp.v1");
         let capability = Capability::new_root(namespace_id, &signing_key);
+        adapter.capabilities().add(capability.clone()).unwrap();
 
         // Sync to Willow
         adapter
-            .sync_from_state_engine("myapp.v1", "users", "alice", &capability)
+            .sync_from_state_engine("myapp.v1", "users", "alice", &capability, &signing_key)
             .await
             .unwrap();
 
@@ -567,6 +831,7 @@ mod tests {
         let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
         let namespace_id = adapter.map_namespace("myapp.v1");
         let capability = Capability::new_root(namespace_id, &signing_key);
+        adapter.capabilities().add(capability.clone()).unwrap();
 
         // Create and save a document
         let doc_id = DocumentId::new("users", "alice");
@@ -582,7 +847,14 @@ mod tests {
 
         // Write to Willow
         adapter
-            .write_entry("myapp.v1", "users", "alice", bytes, &capability)
+            .write_entry(
+                "myapp.v1",
+                "users",
+                "alice",
+                bytes,
+                &capability,
+                &signing_key,
+            )
             .await
             .unwrap();
 
@@ -618,16 +890,24 @@ mod tests {
         let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
         let namespace_id = adapter.map_namespace("myapp.v1");
         let capability = Capability::new_root(namespace_id, &signing_key);
+        adapter.capabilities().add(capability.clone()).unwrap();
 
         // Sync to Willow
         adapter
-            .sync_from_state_engine("myapp.v1", "users", "alice", &capability)
+            .sync_from_state_engine("myapp.v1", "users", "alice", &capability, &signing_key)
             .await
             .unwrap();
 
         // GDPR delete
         adapter
-            .gdpr_delete("myapp.v1", "users", "alice", &capability, "User requested deletion")
+            .gdpr_delete(
+                "myapp.v1",
+                "users",
+                "alice",
+                &capability,
+                &signing_key,
+                "User requested deletion",
+            )
             .await
             .unwrap();
 
@@ -656,6 +936,7 @@ mod tests {
         let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
         let namespace_id = adapter.map_namespace("myapp.v1");
         let capability = Capability::new_root(namespace_id, &signing_key);
+        adapter.capabilities().add(capability.clone()).unwrap();
 
         // Sync with tight constraints
         let constraints = ResourceConstraints {
@@ -665,7 +946,7 @@ mod tests {
         };
 
         let stats = adapter
-            .sync_with_constraints("myapp.v1", "users", &capability, constraints)
+            .sync_with_constraints("myapp.v1", "users", &capability, &signing_key, constraints)
             .await
             .unwrap();
 
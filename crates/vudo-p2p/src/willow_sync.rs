@@ -0,0 +1,820 @@
+//! Range-based reconciliation of Willow namespaces.
+//!
+//! Two peers reconcile a namespace by comparing fingerprints of 3D ranges
+//! (subspaces × paths × timestamps) instead of exchanging every entry:
+//!
+//! 1. The initiator sends the fingerprint of the whole namespace.
+//! 2. A peer whose fingerprint for a range matches is done with it. If it
+//!    doesn't match, a small range is answered with its items, a large one
+//!    is split and the fingerprints of the parts are sent back.
+//! 3. Items received are merged (the newest entry or tombstone of a path
+//!    wins) and answered with the items the sender lacks.
+//!
+//! Only ranges containing differences are split further, so the traffic
+//! grows with the number of differing entries (times the log of the
+//! namespace size) rather than with the namespace size.
+//!
+//! Every item carries an [`AuthorisationToken`] signed by its writer and is
+//! only merged if the token's capability lets it write the item's path.
+//! The initiator presents a read capability signed for the connection, and
+//! both peers only reconcile the items in its area.
+//!
+//! Messages travel as [`SyncMessage::WillowSync`](crate::SyncMessage::WillowSync)
+//! over the peers' Iroh connection; see [`VudoP2P::reconcile_willow`](crate::VudoP2P::reconcile_willow).
+
+use crate::auth::connection_binding;
+use crate::error::{P2PError, Result};
+use crate::meadowcap::{Area, AuthorisationToken, Capability, Permission};
+use crate::sync_protocol::PeerId;
+use crate::willow_adapter::WillowAdapter;
+use crate::willow_types::{Entry, NamespaceId, Path, SubspaceId, Tombstone};
+use ed25519_dalek::SigningKey;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Ranges with at most this many local items are answered with the items
+/// rather than split.
+pub const MAX_RANGE_ITEMS: usize = 16;
+
+/// Number of parts a mismatching range is split into.
+pub const SPLIT_PARTS: usize = 8;
+
+/// Range along one dimension: `start` inclusive, `end` exclusive (open when
+/// `None`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Range<T> {
+    /// First value in the range.
+    pub start: T,
+    /// First value past the range (unbounded when `None`).
+    pub end: Option<T>,
+}
+
+impl<T: Ord> Range<T> {
+    /// Check if a value is in the range.
+    pub fn contains(&self, value: &T) -> bool {
+        let before_end = match &self.end {
+            Some(end) => value < end,
+            None => true,
+        };
+        *value >= self.start && before_end
+    }
+}
+
+impl<T: Clone> Range<T> {
+    /// Split the range at the given (ascending) bounds.
+    fn split_at(&self, bounds: &[T]) -> Vec<Self> {
+        let starts = std::iter::once(self.start.clone()).chain(bounds.iter().cloned());
+        let ends = bounds
+            .iter()
+            .cloned()
+            .map(Some)
+            .chain(std::iter::once(self.end.clone()));
+        starts
+            .zip(ends)
+            .map(|(start, end)| Self { start, end })
+            .collect()
+    }
+}
+
+/// A 3D range of a namespace: subspaces × paths × timestamps.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Range3d {
+    /// Subspace range.
+    pub subspaces: Range<SubspaceId>,
+    /// Path range (paths ordered component by component).
+    pub paths: Range<Path>,
+    /// Timestamp range (Unix epoch milliseconds).
+    pub times: Range<u64>,
+}
+
+impl Range3d {
+    /// The range covering a whole namespace.
+    pub fn full() -> Self {
+        Self {
+            subspaces: Range {
+                start: SubspaceId::from_bytes(&[0; 32]),
+                end: None,
+            },
+            paths: Range {
+                start: Path::empty(),
+                end: None,
+            },
+            times: Range {
+                start: 0,
+                end: None,
+            },
+        }
+    }
+
+    /// Check if an item is in the range.
+    pub fn contains(&self, item: &SyncItem) -> bool {
+        self.subspaces.contains(&item.subspace_id())
+            && self.paths.contains(item.path())
+            && self.times.contains(&item.timestamp())
+    }
+
+    /// Split the range into up to `parts` ranges holding similar shares of
+    /// `items` (sorted, all in the range).
+    ///
+    /// Splits along subspaces if the items span several, then along paths,
+    /// then along timestamps. Returns nothing if the items can't be told
+    /// apart along any dimension.
+    fn split(&self, items: &[SyncItem], parts: usize) -> Vec<Self> {
+        let subspaces = distinct(items.iter().map(SyncItem::subspace_id));
+        if subspaces.len() > 1 {
+            return self
+                .subspaces
+                .split_at(&bounds(&subspaces, parts))
+                .into_iter()
+                .map(|subspaces| Self {
+                    subspaces,
+                    ..self.clone()
+                })
+                .collect();
+        }
+        let paths = distinct(items.iter().map(|item| item.path().clone()));
+        if paths.len() > 1 {
+            return self
+                .paths
+                .split_at(&bounds(&paths, parts))
+                .into_iter()
+                .map(|paths| Self {
+                    paths,
+                    ..self.clone()
+                })
+                .collect();
+        }
+        let times = distinct(items.iter().map(SyncItem::timestamp));
+        if times.len() > 1 {
+            return self
+                .times
+                .split_at(&bounds(&times, parts))
+                .into_iter()
+                .map(|times| Self {
+                    times,
+                    ..self.clone()
+                })
+                .collect();
+        }
+        Vec::new()
+    }
+}
+
+/// Sorted distinct values.
+fn distinct<T: Ord>(values: impl Iterator<Item = T>) -> Vec<T> {
+    let mut values: Vec<T> = values.collect();
+    values.sort();
+    values.dedup();
+    values
+}
+
+/// Bounds splitting sorted distinct `values` (at least two) into up to
+/// `parts` similar groups. The first value is never a bound, so no part is
+/// empty.
+fn bounds<T: Clone>(values: &[T], parts: usize) -> Vec<T> {
+    let parts = parts.clamp(2, values.len());
+    (1..parts)
+        .map(|i| values[i * values.len() / parts].clone())
+        .collect()
+}
+
+/// An entry or tombstone being reconciled, with the token authorising its
+/// write.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SyncItem {
+    /// Live entry.
+    Entry(Entry, AuthorisationToken),
+    /// Deletion tombstone.
+    Tombstone(Tombstone, AuthorisationToken),
+}
+
+impl SyncItem {
+    /// Subspace of the item.
+    pub fn subspace_id(&self) -> SubspaceId {
+        match self {
+            Self::Entry(entry, _) => entry.subspace_id,
+            Self::Tombstone(tombstone, _) => tombstone.subspace_id,
+        }
+    }
+
+    /// Path of the item.
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Entry(entry, _) => &entry.path,
+            Self::Tombstone(tombstone, _) => &tombstone.path,
+        }
+    }
+
+    /// Timestamp of the item.
+    pub fn timestamp(&self) -> u64 {
+        match self {
+            Self::Entry(entry, _) => entry.timestamp,
+            Self::Tombstone(tombstone, _) => tombstone.timestamp,
+        }
+    }
+
+    /// Token authorising the item.
+    pub fn token(&self) -> &AuthorisationToken {
+        match self {
+            Self::Entry(_, token) | Self::Tombstone(_, token) => token,
+        }
+    }
+
+    /// Bytes the item's token signs.
+    pub fn signing_message(&self) -> Vec<u8> {
+        match self {
+            Self::Entry(entry, _) => Self::entry_message(entry),
+            Self::Tombstone(tombstone, _) => Self::tombstone_message(tombstone),
+        }
+    }
+
+    /// Bytes the token of an entry signs.
+    pub(crate) fn entry_message(entry: &Entry) -> Vec<u8> {
+        signing_message(b"willow-entry", entry)
+    }
+
+    /// Bytes the token of a tombstone signs.
+    pub(crate) fn tombstone_message(tombstone: &Tombstone) -> Vec<u8> {
+        signing_message(b"willow-tombstone", tombstone)
+    }
+
+    /// BLAKE3 hash of the item.
+    ///
+    /// Covers the entry or tombstone but not its token, so peers holding
+    /// the same data agree on fingerprints.
+    pub fn hash(&self) -> [u8; 32] {
+        *blake3::hash(&self.signing_message()).as_bytes()
+    }
+
+    /// Sort key: subspace, path, timestamp.
+    fn key(&self) -> (SubspaceId, &Path, u64) {
+        (self.subspace_id(), self.path(), self.timestamp())
+    }
+}
+
+/// Domain-separated bytes of a value for a token to sign.
+fn signing_message(domain: &[u8], value: &impl Serialize) -> Vec<u8> {
+    let mut message = domain.to_vec();
+    message.extend(bincode::serialize(value).unwrap_or_default());
+    message
+}
+
+/// Bytes the read token of a reconciliation signs: the namespace and the
+/// connection it was presented on.
+fn read_message(namespace_id: NamespaceId, binding: &str) -> Vec<u8> {
+    let mut message = b"willow-read".to_vec();
+    message.extend_from_slice(namespace_id.as_bytes());
+    message.extend_from_slice(binding.as_bytes());
+    message
+}
+
+/// Sort items by subspace, path and timestamp.
+pub(crate) fn sort_items(items: &mut [SyncItem]) {
+    items.sort_by(|a, b| a.key().cmp(&b.key()));
+}
+
+/// Fingerprint of a set of items: the XOR of their hashes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Fingerprint([u8; 32]);
+
+impl Fingerprint {
+    /// Fingerprint of a set of items.
+    pub fn of<'a>(items: impl IntoIterator<Item = &'a SyncItem>) -> Self {
+        let mut fingerprint = [0u8; 32];
+        for item in items {
+            for (byte, other) in fingerprint.iter_mut().zip(item.hash()) {
+                *byte ^= other;
+            }
+        }
+        Self(fingerprint)
+    }
+}
+
+/// Willow reconciliation message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WillowSyncMessage {
+    /// Fingerprint of the sender's items in a range.
+    Fingerprint {
+        /// DOL namespace.
+        namespace: String,
+        /// Range the fingerprint covers.
+        range: Range3d,
+        /// Fingerprint of the items in the range.
+        fingerprint: Fingerprint,
+        /// The initiator's read capability, signed for the connection.
+        read: AuthorisationToken,
+    },
+
+    /// The sender's items in a range.
+    Items {
+        /// DOL namespace.
+        namespace: String,
+        /// Range the items were taken from.
+        range: Range3d,
+        /// Entries and tombstones in the range.
+        items: Vec<SyncItem>,
+        /// Whether the receiver should answer with the items the sender lacks.
+        reply: bool,
+        /// The initiator's read capability, signed for the connection.
+        read: AuthorisationToken,
+    },
+}
+
+impl WillowSyncMessage {
+    /// DOL namespace the message is about.
+    pub fn namespace(&self) -> &str {
+        match self {
+            Self::Fingerprint { namespace, .. } | Self::Items { namespace, .. } => namespace,
+        }
+    }
+
+    /// The initiator's read capability.
+    pub fn read_token(&self) -> &AuthorisationToken {
+        match self {
+            Self::Fingerprint { read, .. } | Self::Items { read, .. } => read,
+        }
+    }
+
+    /// Whether handling the message reveals local items to the sender.
+    pub fn reads(&self) -> bool {
+        !matches!(self, Self::Items { reply: false, .. })
+    }
+
+    /// Whether handling the message may store items from the sender.
+    pub fn writes(&self) -> bool {
+        matches!(self, Self::Items { items, .. } if !items.is_empty())
+    }
+}
+
+/// Range-based reconciliation of the namespaces of a [`WillowAdapter`]
+/// with a peer.
+pub struct WillowSync {
+    /// Local Willow store.
+    adapter: Arc<WillowAdapter>,
+    /// Local node.
+    local: PeerId,
+    /// Peer reconciled with.
+    peer: PeerId,
+    /// Ranges with at most this many local items are sent whole.
+    max_range_items: usize,
+}
+
+impl WillowSync {
+    /// Create a reconciler for the connection from `local` to `peer`.
+    pub fn new(adapter: Arc<WillowAdapter>, local: PeerId, peer: PeerId) -> Self {
+        Self {
+            adapter,
+            local,
+            peer,
+            max_range_items: MAX_RANGE_ITEMS,
+        }
+    }
+
+    /// Set the number of local items under which ranges are sent whole.
+    pub fn with_max_range_items(mut self, max_range_items: usize) -> Self {
+        self.max_range_items = max_range_items;
+        self
+    }
+
+    /// First message of a reconciliation of `namespace`.
+    ///
+    /// Only the area `capability` may read is reconciled. `signing_key`
+    /// must be the capability's receiver's key.
+    pub fn start(
+        &self,
+        namespace: &str,
+        capability: &Capability,
+        signing_key: &SigningKey,
+    ) -> Result<WillowSyncMessage> {
+        let namespace_id = self.adapter.map_namespace(namespace);
+        let binding = connection_binding(&self.peer, &self.local);
+        let read = AuthorisationToken::sign(
+            capability,
+            &read_message(namespace_id, &binding),
+            signing_key,
+        )?;
+        let area = self.read_area(namespace, &read)?;
+
+        let range = Range3d::full();
+        let items = self.items(namespace, &range, &area);
+        Ok(WillowSyncMessage::Fingerprint {
+            namespace: namespace.to_string(),
+            fingerprint: Fingerprint::of(&items),
+            range,
+            read,
+        })
+    }
+
+    /// Handle a message from the peer, returning the replies to send back
+    /// (none once the range is reconciled).
+    pub fn receive(&self, message: WillowSyncMessage) -> Result<Vec<WillowSyncMessage>> {
+        let area = self.read_area(message.namespace(), message.read_token())?;
+        match message {
+            WillowSyncMessage::Fingerprint {
+                namespace,
+                range,
+                fingerprint,
+                read,
+            } => {
+                let items = self.items(&namespace, &range, &area);
+                if Fingerprint::of(&items) == fingerprint {
+                    return Ok(Vec::new());
+                }
+
+                let parts = if items.len() > self.max_range_items {
+                    range.split(&items, SPLIT_PARTS)
+                } else {
+                    Vec::new()
+                };
+                if parts.is_empty() {
+                    return Ok(vec![WillowSyncMessage::Items {
+                        namespace,
+                        range,
+                        items,
+                        reply: true,
+                        read,
+                    }]);
+                }
+
+                Ok(parts
+                    .into_iter()
+                    .map(|part| WillowSyncMessage::Fingerprint {
+                        namespace: namespace.clone(),
+                        fingerprint: Fingerprint::of(items.iter().filter(|i| part.contains(i))),
+                        range: part,
+                        read: read.clone(),
+                    })
+                    .collect())
+            }
+            WillowSyncMessage::Items {
+                namespace,
+                range,
+                items,
+                reply,
+                read,
+            } => {
+                let namespace_id = self.adapter.map_namespace(&namespace);
+                let received: HashSet<[u8; 32]> = items.iter().map(SyncItem::hash).collect();
+                for item in items {
+                    if !range.contains(&item) || !area.contains(item.subspace_id(), item.path()) {
+                        return Err(P2PError::InvalidMessage(format!(
+                            "Willow item {} outside the reconciled range",
+                            item.path()
+                        )));
+                    }
+                    self.adapter.apply_item(namespace_id, item)?;
+                }
+                if !reply {
+                    return Ok(Vec::new());
+                }
+
+                // Whatever we hold that the peer didn't send (after merging)
+                let missing: Vec<SyncItem> = self
+                    .items(&namespace, &range, &area)
+                    .into_iter()
+                    .filter(|item| !received.contains(&item.hash()))
+                    .collect();
+                if missing.is_empty() {
+                    return Ok(Vec::new());
+                }
+                Ok(vec![WillowSyncMessage::Items {
+                    namespace,
+                    range,
+                    items: missing,
+                    reply: false,
+                    read,
+                }])
+            }
+        }
+    }
+
+    /// Check the read token of a reconciliation, returning the area it
+    /// covers.
+    ///
+    /// The token must be signed for this connection, either by the peer
+    /// (which started the reconciliation) or by us (the peer echoing our
+    /// own), and its capability must verify back to a trusted root.
+    fn read_area(&self, namespace: &str, read: &AuthorisationToken) -> Result<Area> {
+        let namespace_id = self.adapter.map_namespace(namespace);
+        let presented = connection_binding(&self.local, &self.peer);
+        let echoed = connection_binding(&self.peer, &self.local);
+        if read
+            .verify(&read_message(namespace_id, &presented))
+            .is_err()
+        {
+            read.verify(&read_message(namespace_id, &echoed))?;
+        }
+
+        let capability = &read.capability;
+        if capability.namespace_id != namespace_id
+            || !capability.permission.includes(Permission::Read)
+        {
+            return Err(P2PError::PermissionDenied(format!(
+                "No read permission for Willow namespace {}",
+                namespace
+            )));
+        }
+        self.adapter.capabilities().verify_chain(capability)?;
+        Ok(capability.area())
+    }
+
+    /// Local items of a namespace in a range and an area.
+    fn items(&self, namespace: &str, range: &Range3d, area: &Area) -> Vec<SyncItem> {
+        let namespace_id = self.adapter.map_namespace(namespace);
+        let mut items = self.adapter.range_items(namespace_id, range);
+        items.retain(|item| area.contains(item.subspace_id(), item.path()));
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use std::collections::VecDeque;
+    use vudo_state::StateEngine;
+
+    const NAMESPACE: &str = "myapp.v1";
+
+    /// Key of the namespace's owner, trusted by every adapter.
+    fn owner_key() -> SigningKey {
+        SigningKey::from_bytes(&[7; 32])
+    }
+
+    fn root() -> Capability {
+        Capability::new_root(NamespaceId::from_dol_namespace(NAMESPACE), &owner_key())
+    }
+
+    async fn adapter() -> Arc<WillowAdapter> {
+        let engine = StateEngine::new().await.unwrap();
+        let adapter = WillowAdapter::new(Arc::new(engine)).await.unwrap();
+        adapter.capabilities().add(root()).unwrap();
+        Arc::new(adapter)
+    }
+
+    fn signed_entry(
+        collection: &str,
+        id: &str,
+        data: &str,
+        timestamp: u64,
+        capability: &Capability,
+        key: &SigningKey,
+    ) -> SyncItem {
+        let entry = Entry::new(
+            NamespaceId::from_dol_namespace(NAMESPACE),
+            SubspaceId::from_dol_collection(collection),
+            Path::from_dol_id(id),
+            Bytes::from(data.to_string()),
+            timestamp,
+        );
+        let message = SyncItem::entry_message(&entry);
+        let token = AuthorisationToken::sign(capability, &message, key).unwrap();
+        SyncItem::Entry(entry, token)
+    }
+
+    fn entry(collection: &str, id: &str, data: &str, timestamp: u64) -> SyncItem {
+        signed_entry(collection, id, data, timestamp, &root(), &owner_key())
+    }
+
+    fn insert(adapter: &WillowAdapter, item: SyncItem) {
+        let namespace_id = adapter.map_namespace(NAMESPACE);
+        assert!(adapter.apply_item(namespace_id, item).unwrap());
+    }
+
+    fn fingerprint(adapter: &Arc<WillowAdapter>) -> Fingerprint {
+        let namespace_id = adapter.map_namespace(NAMESPACE);
+        Fingerprint::of(&adapter.range_items(namespace_id, &Range3d::full()))
+    }
+
+    fn peers(a: &Arc<WillowAdapter>, b: &Arc<WillowAdapter>) -> [WillowSync; 2] {
+        [
+            WillowSync::new(Arc::clone(a), "a".to_string(), "b".to_string()),
+            WillowSync::new(Arc::clone(b), "b".to_string(), "a".to_string()),
+        ]
+    }
+
+    /// Run a reconciliation between two adapters, returning the number of
+    /// messages and of items exchanged.
+    fn reconcile(a: &Arc<WillowAdapter>, b: &Arc<WillowAdapter>) -> (usize, usize) {
+        reconcile_with(a, b, &root(), &owner_key())
+    }
+
+    /// Run a reconciliation started by `a` with a read capability.
+    fn reconcile_with(
+        a: &Arc<WillowAdapter>,
+        b: &Arc<WillowAdapter>,
+        capability: &Capability,
+        key: &SigningKey,
+    ) -> (usize, usize) {
+        let peers = peers(a, b);
+        let start = peers[0].start(NAMESPACE, capability, key).unwrap();
+        let mut queue = VecDeque::from([(1, start)]);
+        let (mut messages, mut items) = (0, 0);
+        while let Some((to, message)) = queue.pop_front() {
+            messages += 1;
+            if let WillowSyncMessage::Items { items: sent, .. } = &message {
+                items += sent.len();
+            }
+            for reply in peers[to].receive(message).unwrap() {
+                queue.push_back((1 - to, reply));
+            }
+        }
+        (messages, items)
+    }
+
+    #[test]
+    fn test_range_split() {
+        let items: Vec<SyncItem> = (0..10)
+            .map(|i| entry("users", &format!("user{}", i), "x", 1))
+            .collect();
+        let range = Range3d::full();
+        let parts = range.split(&items, 4);
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts[0].paths.start, Path::empty());
+        assert!(parts[3].paths.end.is_none());
+        // Every item lands in exactly one part
+        for item in &items {
+            assert_eq!(parts.iter().filter(|p| p.contains(item)).count(), 1);
+        }
+
+        // Same subspace and path: split along time
+        let versions = [
+            entry("users", "alice", "a", 1),
+            entry("users", "alice", "b", 2),
+        ];
+        let parts = range.split(&versions, 4);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[1].times.start, 2);
+        assert!(range.split(&versions[..1], 4).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_identical() {
+        let (a, b) = (adapter().await, adapter().await);
+        for i in 0..100 {
+            let item = entry("users", &format!("user{}", i), "x", 1);
+            insert(&a, item.clone());
+            insert(&b, item);
+        }
+
+        assert_eq!(reconcile(&a, &b), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_large_namespace() {
+        let (a, b) = (adapter().await, adapter().await);
+        for i in 0..5000 {
+            let collection = ["users", "posts", "comments"][i % 3];
+            let item = entry(collection, &format!("doc{}", i), "shared", 1);
+            insert(&a, item.clone());
+            insert(&b, item);
+        }
+        // A few differences on each side
+        insert(&a, entry("users", "only-a", "a", 2));
+        insert(&b, entry("posts", "only-b", "b", 2));
+        insert(&a, entry("comments", "doc2", "edited", 3));
+
+        let (messages, items) = reconcile(&a, &b);
+        assert_eq!(fingerprint(&a), fingerprint(&b));
+        assert_eq!(a.stats().entry_count, 5002);
+        assert_eq!(b.stats().entry_count, 5002);
+
+        // Traffic follows the differences, not the 5000 shared entries
+        assert!(messages < 200, "{} messages", messages);
+        assert!(items < 200, "{} items", items);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_empty_peer() {
+        let (a, b) = (adapter().await, adapter().await);
+        for i in 0..100 {
+            insert(&a, entry("users", &format!("user{}", i), "x", 1));
+        }
+
+        reconcile(&b, &a);
+        assert_eq!(b.stats().entry_count, 100);
+        assert_eq!(fingerprint(&a), fingerprint(&b));
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_tombstones() {
+        let (a, b) = (adapter().await, adapter().await);
+        let namespace_id = NamespaceId::from_dol_namespace(NAMESPACE);
+        let subspace = SubspaceId::from_dol_collection("users");
+        insert(&a, entry("users", "alice", "old", 1));
+        insert(&a, entry("users", "bob", "bob", 1));
+        insert(&b, entry("users", "bob", "bob", 1));
+        let tombstone = Tombstone::new(namespace_id, subspace, Path::from_dol_id("alice"), 2, None);
+        let message = SyncItem::tombstone_message(&tombstone);
+        let token = AuthorisationToken::sign(&root(), &message, &owner_key()).unwrap();
+        insert(&b, SyncItem::Tombstone(tombstone, token));
+
+        reconcile(&a, &b);
+        assert_eq!(fingerprint(&a), fingerprint(&b));
+        // The newer deletion wins on both sides
+        assert_eq!(a.stats().entry_count, 1);
+        assert_eq!(a.stats().tombstone_count, 1);
+        assert_eq!(b.stats().entry_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_items_outside_range_rejected() {
+        let (a, b) = (adapter().await, adapter().await);
+        let [a, b] = peers(&a, &b);
+        let read = a.start(NAMESPACE, &root(), &owner_key()).unwrap();
+        let mut range = Range3d::full();
+        range.times.end = Some(5);
+        let message = WillowSyncMessage::Items {
+            namespace: NAMESPACE.to_string(),
+            range,
+            items: vec![entry("users", "alice", "x", 10)],
+            reply: false,
+            read: read.read_token().clone(),
+        };
+        assert!(b.receive(message).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unauthorised_items_rejected() {
+        let adapter = adapter().await;
+        let namespace_id = adapter.map_namespace(NAMESPACE);
+
+        // Alice may write under users/alice only
+        let alice_key = SigningKey::from_bytes(&[1; 32]);
+        let alice_area = Area::new(
+            Some(SubspaceId::from_dol_collection("users")),
+            Path::from_dol_id("alice"),
+        );
+        let alice = root()
+            .delegate(
+                alice_key.verifying_key(),
+                alice_area,
+                Permission::Write,
+                &owner_key(),
+            )
+            .unwrap();
+        let item = signed_entry("users", "alice", "hi", 1, &alice, &alice_key);
+        assert!(adapter.apply_item(namespace_id, item).unwrap());
+        let item = signed_entry("users", "bob", "hi", 1, &alice, &alice_key);
+        let result = adapter.apply_item(namespace_id, item);
+        assert!(matches!(result, Err(P2PError::PermissionDenied(_))));
+
+        // An item changed after signing
+        let SyncItem::Entry(mut entry, token) = entry("users", "bob", "hi", 1) else {
+            unreachable!()
+        };
+        entry.payload = Bytes::from("forged");
+        let result = adapter.apply_item(namespace_id, SyncItem::Entry(entry, token));
+        assert!(matches!(
+            result,
+            Err(P2PError::CapabilityDelegationError(_))
+        ));
+
+        // A capability from an untrusted root
+        let mallory_key = SigningKey::from_bytes(&[2; 32]);
+        let mallory = Capability::new_root(namespace_id, &mallory_key);
+        let item = signed_entry("users", "bob", "hi", 1, &mallory, &mallory_key);
+        assert!(adapter.apply_item(namespace_id, item).is_err());
+        assert_eq!(adapter.stats().entry_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_read_area() {
+        let (a, b) = (adapter().await, adapter().await);
+        insert(&a, entry("users", "alice", "a", 1));
+        insert(&a, entry("users", "bob", "b", 1));
+        insert(&a, entry("posts", "alice", "p", 1));
+
+        // B may only read users/alice
+        let b_key = SigningKey::from_bytes(&[3; 32]);
+        let area = Area::new(
+            Some(SubspaceId::from_dol_collection("users")),
+            Path::from_dol_id("alice"),
+        );
+        let read = root()
+            .delegate(b_key.verifying_key(), area, Permission::Read, &owner_key())
+            .unwrap();
+        reconcile_with(&b, &a, &read, &b_key);
+        assert_eq!(b.stats().entry_count, 1);
+        assert_eq!(
+            b.list_entries(
+                b.map_namespace(NAMESPACE),
+                SubspaceId::from_dol_collection("users"),
+                &Path::from_dol_id("alice"),
+            )
+            .len(),
+            1
+        );
+
+        // A read token presented on another connection is rejected
+        let [_, b_sync] = peers(&a, &b);
+        let message = b_sync.start(NAMESPACE, &read, &b_key).unwrap();
+        let c = WillowSync::new(Arc::clone(&a), "c".to_string(), "b".to_string());
+        let result = c.receive(message);
+        assert!(matches!(
+            result,
+            Err(P2PError::CapabilityDelegationError(_))
+        ));
+    }
+}
@@ -44,7 +44,7 @@ impl fmt::Display for NamespaceId {
 /// A 32-byte subspace identifier.
 ///
 /// Subspaces are derived from DOL collection names using BLAKE3 hashing.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct SubspaceId([u8; 32]);
 
 impl SubspaceId {
@@ -76,7 +76,7 @@ impl fmt::Display for SubspaceId {
 /// A path component in the Willow 3D namespace.
 ///
 /// Paths are hierarchical and can have multiple components (e.g., ["users", "alice", "posts", "1"]).
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Path {
     /// Path components.
     components: Vec<String>,
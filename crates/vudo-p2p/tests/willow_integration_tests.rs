@@ -5,7 +5,7 @@ use bytes::Bytes;
 use ed25519_dalek::SigningKey;
 use std::sync::Arc;
 use vudo_p2p::{
-    meadowcap::{Capability, Permission},
+    meadowcap::{Area, Capability, Permission},
     ResourceConstraints, SyncPriority, WillowAdapter,
 };
 use vudo_state::{DocumentId, StateEngine};
@@ -33,6 +33,7 @@ async fn test_complete_sync_workflow() {
     let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let namespace_id = adapter.map_namespace("myapp.v1");
     let root_cap = Capability::new_root(namespace_id, &signing_key);
+    adapter.capabilities().add(root_cap.clone()).unwrap();
 
     // Create document in state engine
     let doc_id = DocumentId::new("users", "alice");
@@ -48,7 +49,7 @@ async fn test_complete_sync_workflow() {
 
     // Sync to Willow
     adapter
-        .sync_from_state_engine("myapp.v1", "users", "alice", &root_cap)
+        .sync_from_state_engine("myapp.v1", "users", "alice", &root_cap, &signing_key)
         .await
         .unwrap();
 
@@ -80,16 +81,17 @@ async fn test_capability_delegation_hierarchy() {
     let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let namespace_id = adapter.map_namespace("myapp.v1");
     let root_cap = Capability::new_root(namespace_id, &signing_key);
+    adapter.capabilities().add(root_cap.clone()).unwrap();
 
     // Admin delegates write capability for users collection
     let users_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let subspace_id = adapter.map_subspace("users");
     let users_cap = root_cap
         .delegate(
-            Some(subspace_id),
-            vudo_p2p::Path::empty(),
+            users_key.verifying_key(),
+            Area::new(Some(subspace_id), vudo_p2p::Path::empty()),
             Permission::Write,
-            &users_key,
+            &signing_key,
         )
         .unwrap();
 
@@ -97,17 +99,27 @@ async fn test_capability_delegation_hierarchy() {
     let alice_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let alice_cap = users_cap
         .delegate(
-            Some(subspace_id),
-            vudo_p2p::Path::from_components(["alice"]),
+            alice_key.verifying_key(),
+            Area::new(
+                Some(subspace_id),
+                vudo_p2p::Path::from_components(["alice"]),
+            ),
             Permission::Read,
-            &alice_key,
+            &users_key,
         )
         .unwrap();
 
     // Alice can read her own data
     let data = Bytes::from("alice's data");
     adapter
-        .write_entry("myapp.v1", "users", "alice", data.clone(), &users_cap)
+        .write_entry(
+            "myapp.v1",
+            "users",
+            "alice",
+            data.clone(),
+            &users_cap,
+            &users_key,
+        )
         .await
         .unwrap();
 
@@ -120,7 +132,14 @@ async fn test_capability_delegation_hierarchy() {
 
     // Alice cannot write
     let result = adapter
-        .write_entry("myapp.v1", "users", "alice", Bytes::from("new data"), &alice_cap)
+        .write_entry(
+            "myapp.v1",
+            "users",
+            "alice",
+            Bytes::from("new data"),
+            &alice_cap,
+            &alice_key,
+        )
         .await;
 
     assert!(result.is_err());
@@ -134,6 +153,7 @@ async fn test_multi_collection_sync() {
     let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let namespace_id = adapter.map_namespace("myapp.v1");
     let root_cap = Capability::new_root(namespace_id, &signing_key);
+    adapter.capabilities().add(root_cap.clone()).unwrap();
 
     // Create documents in different collections
     let collections = ["users", "posts", "comments"];
@@ -154,7 +174,13 @@ async fn test_multi_collection_sync() {
     for collection in &collections {
         for i in 0..3 {
             adapter
-                .sync_from_state_engine("myapp.v1", collection, &format!("doc{}", i), &root_cap)
+                .sync_from_state_engine(
+                    "myapp.v1",
+                    collection,
+                    &format!("doc{}", i),
+                    &root_cap,
+                    &signing_key,
+                )
                 .await
                 .unwrap();
         }
@@ -172,6 +198,7 @@ async fn test_gdpr_compliant_deletion() {
     let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let namespace_id = adapter.map_namespace("myapp.v1");
     let root_cap = Capability::new_root(namespace_id, &signing_key);
+    adapter.capabilities().add(root_cap.clone()).unwrap();
 
     // Create user document
     let doc_id = DocumentId::new("users", "alice");
@@ -186,7 +213,7 @@ async fn test_gdpr_compliant_deletion() {
 
     // Sync to Willow
     adapter
-        .sync_from_state_engine("myapp.v1", "users", "alice", &root_cap)
+        .sync_from_state_engine("myapp.v1", "users", "alice", &root_cap, &signing_key)
         .await
         .unwrap();
 
@@ -197,6 +224,7 @@ async fn test_gdpr_compliant_deletion() {
             "users",
             "alice",
             &root_cap,
+            &signing_key,
             "User requested data deletion under GDPR Article 17",
         )
         .await
@@ -226,6 +254,7 @@ async fn test_resource_constrained_sync_high_priority() {
     let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let namespace_id = adapter.map_namespace("myapp.v1");
     let root_cap = Capability::new_root(namespace_id, &signing_key);
+    adapter.capabilities().add(root_cap.clone()).unwrap();
 
     // Create many documents
     for i in 0..20 {
@@ -247,7 +276,7 @@ async fn test_resource_constrained_sync_high_priority() {
     };
 
     let stats = adapter
-        .sync_with_constraints("myapp.v1", "users", &root_cap, constraints)
+        .sync_with_constraints("myapp.v1", "users", &root_cap, &signing_key, constraints)
         .await
         .unwrap();
 
@@ -264,6 +293,7 @@ async fn test_hierarchical_path_permissions() {
     let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let namespace_id = adapter.map_namespace("myapp.v1");
     let root_cap = Capability::new_root(namespace_id, &signing_key);
+    adapter.capabilities().add(root_cap.clone()).unwrap();
 
     let subspace_id = adapter.map_subspace("users");
 
@@ -271,10 +301,13 @@ async fn test_hierarchical_path_permissions() {
     let alice_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let alice_cap = root_cap
         .delegate(
-            Some(subspace_id),
-            vudo_p2p::Path::from_components(["alice"]),
+            alice_key.verifying_key(),
+            Area::new(
+                Some(subspace_id),
+                vudo_p2p::Path::from_components(["alice"]),
+            ),
             Permission::Read,
-            &alice_key,
+            &signing_key,
         )
         .unwrap();
 
@@ -286,6 +319,7 @@ async fn test_hierarchical_path_permissions() {
             "alice/profile",
             Bytes::from("alice profile"),
             &root_cap,
+            &signing_key,
         )
         .await
         .unwrap();
@@ -297,12 +331,20 @@ async fn test_hierarchical_path_permissions() {
             "alice/posts/1",
             Bytes::from("alice post 1"),
             &root_cap,
+            &signing_key,
         )
         .await
         .unwrap();
 
     adapter
-        .write_entry("myapp.v1", "users", "bob/profile", Bytes::from("bob profile"), &root_cap)
+        .write_entry(
+            "myapp.v1",
+            "users",
+            "bob/profile",
+            Bytes::from("bob profile"),
+            &root_cap,
+            &signing_key,
+        )
         .await
         .unwrap();
 
@@ -335,20 +377,36 @@ async fn test_namespace_isolation() {
     let key1 = SigningKey::generate(&mut rand::rngs::OsRng);
     let ns1 = adapter.map_namespace("app1.v1");
     let cap1 = Capability::new_root(ns1, &key1);
+    adapter.capabilities().add(cap1.clone()).unwrap();
 
     let key2 = SigningKey::generate(&mut rand::rngs::OsRng);
     let ns2 = adapter.map_namespace("app2.v1");
     let cap2 = Capability::new_root(ns2, &key2);
+    adapter.capabilities().add(cap2.clone()).unwrap();
 
     // Write to app1
     adapter
-        .write_entry("app1.v1", "users", "alice", Bytes::from("app1 data"), &cap1)
+        .write_entry(
+            "app1.v1",
+            "users",
+            "alice",
+            Bytes::from("app1 data"),
+            &cap1,
+            &key1,
+        )
         .await
         .unwrap();
 
     // Write to app2
     adapter
-        .write_entry("app2.v1", "users", "alice", Bytes::from("app2 data"), &cap2)
+        .write_entry(
+            "app2.v1",
+            "users",
+            "alice",
+            Bytes::from("app2 data"),
+            &cap2,
+            &key2,
+        )
         .await
         .unwrap();
 
@@ -375,6 +433,7 @@ async fn test_concurrent_sync_operations() {
     let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let namespace_id = adapter.map_namespace("myapp.v1");
     let root_cap = Capability::new_root(namespace_id, &signing_key);
+    adapter.capabilities().add(root_cap.clone()).unwrap();
 
     // Create documents
     for i in 0..10 {
@@ -393,9 +452,16 @@ async fn test_concurrent_sync_operations() {
     for i in 0..10 {
         let adapter_clone = Arc::clone(&adapter);
         let cap_clone = root_cap.clone();
+        let key_clone = signing_key.clone();
         let handle = tokio::spawn(async move {
             adapter_clone
-                .sync_from_state_engine("myapp.v1", "users", &format!("user{}", i), &cap_clone)
+                .sync_from_state_engine(
+                    "myapp.v1",
+                    "users",
+                    &format!("user{}", i),
+                    &cap_clone,
+                    &key_clone,
+                )
                 .await
         });
         handles.push(handle);
@@ -418,10 +484,18 @@ async fn test_tombstone_propagation() {
     let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let namespace_id = adapter.map_namespace("myapp.v1");
     let root_cap = Capability::new_root(namespace_id, &signing_key);
+    adapter.capabilities().add(root_cap.clone()).unwrap();
 
     // Write data
     adapter
-        .write_entry("myapp.v1", "users", "alice", Bytes::from("data"), &root_cap)
+        .write_entry(
+            "myapp.v1",
+            "users",
+            "alice",
+            Bytes::from("data"),
+            &root_cap,
+            &signing_key,
+        )
         .await
         .unwrap();
 
@@ -429,7 +503,14 @@ async fn test_tombstone_propagation() {
 
     // Delete with tombstone
     adapter
-        .delete_entry("myapp.v1", "users", "alice", &root_cap, Some("test".to_string()))
+        .delete_entry(
+            "myapp.v1",
+            "users",
+            "alice",
+            &root_cap,
+            &signing_key,
+            Some("test".to_string()),
+        )
         .await
         .unwrap();
 
@@ -4,7 +4,7 @@
 
 use ed25519_dalek::SigningKey;
 use vudo_p2p::willow_types::{NamespaceId, Path, SubspaceId};
-use vudo_p2p::meadowcap::{Capability, CapabilityStore, Permission};
+use vudo_p2p::meadowcap::{Area, Capability, CapabilityStore, Permission};
 
 #[test]
 fn test_namespace_id_deterministic() {
@@ -119,10 +119,10 @@ fn test_capability_delegation_basic() {
     let delegated_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let delegated_cap = root_cap
         .delegate(
-            Some(subspace_id),
-            Path::from_components(["alice"]),
+            delegated_key.verifying_key(),
+            Area::new(Some(subspace_id), Path::from_components(["alice"])),
             Permission::Write,
-            &delegated_key,
+            &signing_key,
         )
         .unwrap();
 
@@ -143,10 +143,10 @@ fn test_delegated_capability_verification() {
     let delegated_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let delegated_cap = root_cap
         .delegate(
-            Some(subspace_id),
-            Path::from_components(["alice"]),
+            delegated_key.verifying_key(),
+            Area::new(Some(subspace_id), Path::from_components(["alice"])),
             Permission::Read,
-            &delegated_key,
+            &signing_key,
         )
         .unwrap();
 
@@ -165,10 +165,10 @@ fn test_capability_read_permission() {
     let alice_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let alice_cap = root_cap
         .delegate(
-            Some(subspace_id),
-            Path::from_components(["alice"]),
+            alice_key.verifying_key(),
+            Area::new(Some(subspace_id), Path::from_components(["alice"])),
             Permission::Read,
-            &alice_key,
+            &signing_key,
         )
         .unwrap();
 
@@ -191,10 +191,10 @@ fn test_capability_write_permission() {
     let alice_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let alice_cap = root_cap
         .delegate(
-            Some(subspace_id),
-            Path::from_components(["alice"]),
+            alice_key.verifying_key(),
+            Area::new(Some(subspace_id), Path::from_components(["alice"])),
             Permission::Write,
-            &alice_key,
+            &signing_key,
         )
         .unwrap();
 
@@ -219,10 +219,10 @@ fn test_capability_delegation_path_restriction() {
     let users_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let users_cap = root_cap
         .delegate(
-            None,
-            Path::from_components(["users"]),
+            users_key.verifying_key(),
+            Area::new(None, Path::from_components(["users"])),
             Permission::Write,
-            &users_key,
+            &signing_key,
         )
         .unwrap();
 
@@ -230,10 +230,10 @@ fn test_capability_delegation_path_restriction() {
     let alice_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let alice_cap = users_cap
         .delegate(
-            None,
-            Path::from_components(["users", "alice"]),
+            alice_key.verifying_key(),
+            Area::new(None, Path::from_components(["users", "alice"])),
             Permission::Read,
-            &alice_key,
+            &users_key,
         )
         .unwrap();
 
@@ -242,10 +242,10 @@ fn test_capability_delegation_path_restriction() {
     // Try to delegate to a path NOT under users - should fail
     let admin_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let result = users_cap.delegate(
-        None,
-        Path::from_components(["admin", "settings"]),
+        admin_key.verifying_key(),
+        Area::new(None, Path::from_components(["admin", "settings"])),
         Permission::Read,
-        &admin_key,
+        &users_key,
     );
 
     assert!(result.is_err());
@@ -262,20 +262,20 @@ fn test_capability_delegation_permission_restriction() {
     let write_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let write_cap = root_cap
         .delegate(
-            None,
-            Path::from_components(["users"]),
+            write_key.verifying_key(),
+            Area::new(None, Path::from_components(["users"])),
             Permission::Write,
-            &write_key,
+            &signing_key,
         )
         .unwrap();
 
     // Try to delegate admin permission from write capability - should fail
     let admin_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let result = write_cap.delegate(
-        None,
-        Path::from_components(["users", "alice"]),
+        admin_key.verifying_key(),
+        Area::new(None, Path::from_components(["users", "alice"])),
         Permission::Admin,
-        &admin_key,
+        &write_key,
     );
 
     assert!(result.is_err());
@@ -284,10 +284,10 @@ fn test_capability_delegation_permission_restriction() {
     let read_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let read_cap = write_cap
         .delegate(
-            None,
-            Path::from_components(["users", "alice"]),
+            read_key.verifying_key(),
+            Area::new(None, Path::from_components(["users", "alice"])),
             Permission::Read,
-            &read_key,
+            &write_key,
         )
         .unwrap();
 
@@ -336,10 +336,10 @@ fn test_capability_store_multiple_capabilities() {
     let alice_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let alice_cap = root_cap
         .delegate(
-            Some(subspace_id),
-            Path::from_components(["alice"]),
+            alice_key.verifying_key(),
+            Area::new(Some(subspace_id), Path::from_components(["alice"])),
             Permission::Read,
-            &alice_key,
+            &signing_key,
         )
         .unwrap();
 
@@ -363,10 +363,10 @@ fn test_multi_level_delegation_chain() {
     let users_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let users_cap = root_cap
         .delegate(
-            Some(subspace_id),
-            Path::empty(),
+            users_key.verifying_key(),
+            Area::new(Some(subspace_id), Path::empty()),
             Permission::Write,
-            &users_key,
+            &signing_key,
         )
         .unwrap();
 
@@ -374,10 +374,10 @@ fn test_multi_level_delegation_chain() {
     let alice_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let alice_cap = users_cap
         .delegate(
-            Some(subspace_id),
-            Path::from_components(["alice"]),
+            alice_key.verifying_key(),
+            Area::new(Some(subspace_id), Path::from_components(["alice"])),
             Permission::Read,
-            &alice_key,
+            &users_key,
         )
         .unwrap();
 
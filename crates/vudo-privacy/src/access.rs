@@ -0,0 +1,204 @@
+//! Audit of reads of `@personal` fields.
+//!
+//! Deletion proofs show that personal data is gone; compliance teams also
+//! need evidence of who read it while it existed. With a [`ReadAudit`]
+//! enabled on a [`PersonalDataCrypto`], every decryption of a personal field
+//! is recorded in the audit log as an [`AccessLogEntry`]: who read it (the
+//! device DID), what (owner, document and field) and why (the capability the
+//! reader used), as given by the reader's [`AccessContext`].
+//!
+//! Busy applications can sample reads: a sample rate below 1.0 records that
+//! fraction of reads, except for fields marked as always audited.
+//!
+//! [`PersonalDataCrypto`]: crate::crypto::PersonalDataCrypto
+//! [`AccessLogEntry`]: crate::audit::AccessLogEntry
+//!
+//! # Example
+//!
+//! ```rust
+//! use vudo_privacy::access::{AccessContext, ReadAudit};
+//! use vudo_privacy::audit::DeletionAuditLog;
+//! use vudo_privacy::crypto::PersonalDataCrypto;
+//!
+//! # fn example() -> vudo_privacy::error::Result<()> {
+//! let audit_log = DeletionAuditLog::new();
+//! let crypto = PersonalDataCrypto::new();
+//! crypto.enable_read_audit(ReadAudit::new(audit_log.clone()).always_audit(["ssn"]));
+//!
+//! let dek = crypto.generate_dek("did:peer:alice")?;
+//! let encrypted = crypto.encrypt_field(&dek, b"alice@example.com")?;
+//!
+//! let context = AccessContext::device("did:key:z6MkSupport").with_capability("support/read");
+//! crypto.decrypt_field_as(&dek, &encrypted, "email", &context)?;
+//!
+//! let accesses = audit_log.get_accesses_for_owner("did:peer:alice");
+//! assert_eq!(accesses[0].accessor.as_deref(), Some("did:key:z6MkSupport"));
+//! assert_eq!(accesses[0].capability.as_deref(), Some("support/read"));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::audit::{AccessLogEntry, DeletionAuditLog};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Who reads personal data, and why.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessContext {
+    /// DID of the reading device (None if unattributed).
+    pub accessor: Option<String>,
+
+    /// Capability the reader used (e.g. a UCAN ability or its CID).
+    pub capability: Option<String>,
+}
+
+impl AccessContext {
+    /// Create the context of a read by a device.
+    pub fn device(did: impl Into<String>) -> Self {
+        Self {
+            accessor: Some(did.into()),
+            capability: None,
+        }
+    }
+
+    /// Create the context of a read no reader was given for.
+    pub fn unattributed() -> Self {
+        Self::default()
+    }
+
+    /// Set the capability the reader used.
+    pub fn with_capability(mut self, capability: impl Into<String>) -> Self {
+        self.capability = Some(capability.into());
+        self
+    }
+}
+
+/// Counts of audited and skipped reads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadAuditStats {
+    /// Reads recorded in the audit log.
+    pub audited: u64,
+
+    /// Reads left out by sampling.
+    pub skipped: u64,
+}
+
+/// Read-audit mode of a [`PersonalDataCrypto`](crate::crypto::PersonalDataCrypto).
+#[derive(Clone)]
+pub struct ReadAudit {
+    /// Audit log receiving access entries.
+    log: DeletionAuditLog,
+
+    /// Fraction of reads recorded.
+    sample_rate: f64,
+
+    /// Fields whose reads are always recorded.
+    always: Arc<BTreeSet<String>>,
+
+    /// Reads recorded.
+    audited: Arc<AtomicU64>,
+
+    /// Reads left out by sampling.
+    skipped: Arc<AtomicU64>,
+}
+
+impl ReadAudit {
+    /// Create a read audit recording every read into `log`.
+    pub fn new(log: DeletionAuditLog) -> Self {
+        Self {
+            log,
+            sample_rate: 1.0,
+            always: Arc::new(BTreeSet::new()),
+            audited: Arc::new(AtomicU64::new(0)),
+            skipped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Record only this fraction of reads (0.0 to 1.0), chosen at random.
+    pub fn with_sample_rate(mut self, rate: f64) -> Self {
+        self.sample_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Record every read of these fields, whatever the sample rate.
+    pub fn always_audit<I, S>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut always = (*self.always).clone();
+        always.extend(fields.into_iter().map(Into::into));
+        self.always = Arc::new(always);
+        self
+    }
+
+    /// Get the audit log receiving access entries.
+    pub fn log(&self) -> &DeletionAuditLog {
+        &self.log
+    }
+
+    /// Get the counts of audited and skipped reads.
+    pub fn stats(&self) -> ReadAuditStats {
+        ReadAuditStats {
+            audited: self.audited.load(Ordering::Relaxed),
+            skipped: self.skipped.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Record a read of a personal field, if sampled.
+    pub(crate) fn record(
+        &self,
+        owner: &str,
+        document: Option<&str>,
+        field: Option<&str>,
+        context: &AccessContext,
+    ) {
+        let sampled = field.is_some_and(|field| self.always.contains(field))
+            || self.sample_rate >= 1.0
+            || rand::random::<f64>() < self.sample_rate;
+        if !sampled {
+            self.skipped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        self.log.record_access(AccessLogEntry::new(
+            owner.to_string(),
+            document.map(str::to_string),
+            field.map(str::to_string),
+            context,
+        ));
+        self.audited.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampling() {
+        let log = DeletionAuditLog::new();
+        let audit = ReadAudit::new(log.clone())
+            .with_sample_rate(0.0)
+            .always_audit(["ssn"]);
+        let context = AccessContext::device("did:key:z6MkSupport");
+
+        for _ in 0..10 {
+            audit.record("did:peer:alice", None, Some("email"), &context);
+        }
+        audit.record("did:peer:alice", None, Some("ssn"), &context);
+
+        assert_eq!(
+            audit.stats(),
+            ReadAuditStats {
+                audited: 1,
+                skipped: 10
+            }
+        );
+        let accesses = log.get_access_entries();
+        assert_eq!(accesses.len(), 1);
+        assert_eq!(accesses[0].field.as_deref(), Some("ssn"));
+    }
+}
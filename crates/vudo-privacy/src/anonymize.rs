@@ -0,0 +1,476 @@
+//! Anonymization of documents for analytics exports.
+//!
+//! An [`Anonymizer`] turns documents into privacy-safe rows that operators
+//! can export to analytics pipelines:
+//!
+//! - `@personal` fields are suppressed.
+//! - Direct identifiers (user IDs, device IDs) are replaced by keyed BLAKE3
+//!   hashes under a per-dataset salt: rows of one export stay linkable, but
+//!   two exports can't be joined with each other or with a dictionary of
+//!   known IDs.
+//! - Quasi-identifiers (age, ZIP code, ...) are generalized until every
+//!   combination of them is shared by at least `k` rows (k-anonymity). Rows
+//!   that remain in smaller groups are suppressed, once they are few enough
+//!   (see [`AnonymizationPolicy::max_suppression`]) or the generalizations
+//!   are exhausted.
+//!
+//! # Example
+//!
+//! ```rust
+//! use serde_json::json;
+//! use vudo_privacy::anonymize::{AnonymizationPolicy, Anonymizer, Generalization};
+//!
+//! # fn example() -> vudo_privacy::error::Result<()> {
+//! let policy = AnonymizationPolicy::new()
+//!     .personal(["email"])
+//!     .hash("user_id")
+//!     .quasi_identifier("age", vec![Generalization::Range(10), Generalization::Range(50)])
+//!     .k(2);
+//!
+//! let rows = vec![
+//!     json!({"user_id": "u1", "email": "a@example.com", "age": 31, "plan": "pro"}),
+//!     json!({"user_id": "u2", "email": "b@example.com", "age": 37, "plan": "free"}),
+//! ];
+//! let rows = rows.into_iter().filter_map(|r| r.as_object().cloned()).collect();
+//!
+//! let dataset = Anonymizer::new(policy).anonymize_rows(rows)?;
+//! assert_eq!(dataset.rows[0]["age"], "30-39");
+//! assert!(dataset.rows[0].get("email").is_none());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{PrivacyError, Result};
+use crate::gdpr::document_json;
+use crate::personal::PersonalFieldInterceptor;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashMap};
+use vudo_state::DocumentHandle;
+
+/// A row of an analytics dataset.
+pub type Row = Map<String, Value>;
+
+/// Generalization of a quasi-identifier value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Generalization {
+    /// Keep the value.
+    Exact,
+    /// Bucket numbers into ranges of this width (e.g. "30-39").
+    Range(u64),
+    /// Keep this many leading characters of strings, masking the rest
+    /// (e.g. "941**").
+    Prefix(usize),
+    /// Replace the value with "*".
+    Suppress,
+}
+
+impl Generalization {
+    /// Generalize a value.
+    pub fn apply(&self, value: &Value) -> Value {
+        match (self, value) {
+            (Generalization::Exact, value) => value.clone(),
+            (Generalization::Range(width), Value::Number(n)) if *width > 0 => match n.as_f64() {
+                Some(v) if v >= 0.0 => {
+                    let lower = (v as u64 / width) * width;
+                    Value::String(format!("{}-{}", lower, lower + width - 1))
+                }
+                _ => Value::String("*".to_string()),
+            },
+            (Generalization::Prefix(len), Value::String(s)) => {
+                let masked: String = s
+                    .chars()
+                    .enumerate()
+                    .map(|(i, c)| if i < *len { c } else { '*' })
+                    .collect();
+                Value::String(masked)
+            }
+            _ => Value::String("*".to_string()),
+        }
+    }
+}
+
+/// Quasi-identifier and its generalizations, finest first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuasiIdentifier {
+    /// Field name.
+    pub field: String,
+
+    /// Generalizations, from finest to coarsest.
+    pub levels: Vec<Generalization>,
+}
+
+/// What to do with each field of an analytics export.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnonymizationPolicy {
+    /// Fields to suppress (`@personal` fields).
+    pub suppressed: BTreeSet<String>,
+
+    /// Direct identifiers to hash.
+    pub hashed: BTreeSet<String>,
+
+    /// Quasi-identifiers to generalize.
+    pub quasi_identifiers: Vec<QuasiIdentifier>,
+
+    /// Minimum size of groups sharing quasi-identifiers (0 or 1 disables
+    /// k-anonymity).
+    pub k: usize,
+
+    /// Fraction of rows that may be suppressed before generalizing further.
+    pub max_suppression: f64,
+}
+
+impl AnonymizationPolicy {
+    /// Create a policy that keeps every field.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Suppress the named `@personal` fields.
+    pub fn personal<I, S>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.suppressed.extend(fields.into_iter().map(Into::into));
+        self
+    }
+
+    /// Suppress the `@personal` fields of an interceptor.
+    pub fn personal_from(self, interceptor: &PersonalFieldInterceptor) -> Self {
+        self.personal(interceptor.fields())
+    }
+
+    /// Suppress the `@personal` fields of a Gen.
+    #[cfg(feature = "reflect")]
+    pub fn personal_from_gen(self, gen: &dol_reflect::GenReflection) -> Self {
+        self.personal(
+            gen.personal_fields()
+                .into_iter()
+                .map(|f| f.name().to_string()),
+        )
+    }
+
+    /// Replace a direct identifier with its salted hash.
+    pub fn hash(mut self, field: impl Into<String>) -> Self {
+        self.hashed.insert(field.into());
+        self
+    }
+
+    /// Generalize a quasi-identifier, trying `levels` from finest to
+    /// coarsest.
+    pub fn quasi_identifier(
+        mut self,
+        field: impl Into<String>,
+        levels: Vec<Generalization>,
+    ) -> Self {
+        self.quasi_identifiers.push(QuasiIdentifier {
+            field: field.into(),
+            levels,
+        });
+        self
+    }
+
+    /// Require k-anonymity over the quasi-identifiers.
+    pub fn k(mut self, k: usize) -> Self {
+        self.k = k;
+        self
+    }
+
+    /// Allow suppressing up to this fraction of rows (0.0 to 1.0) instead of
+    /// generalizing further.
+    pub fn max_suppression(mut self, fraction: f64) -> Self {
+        self.max_suppression = fraction.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Number of generalization levels to try.
+    fn level_count(&self) -> usize {
+        self.quasi_identifiers
+            .iter()
+            .map(|qi| qi.levels.len())
+            .max()
+            .unwrap_or(0)
+            .max(1)
+    }
+}
+
+/// Anonymized analytics dataset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnonymizedDataset {
+    /// Anonymized rows.
+    pub rows: Vec<Row>,
+
+    /// Number of rows suppressed to reach k-anonymity.
+    pub suppressed_rows: usize,
+
+    /// Generalization level used (index into the quasi-identifier levels).
+    pub level: usize,
+
+    /// k the dataset satisfies.
+    pub k: usize,
+}
+
+/// Produces privacy-safe copies of documents under a policy.
+pub struct Anonymizer {
+    /// Field policy.
+    policy: AnonymizationPolicy,
+
+    /// Per-dataset hashing salt.
+    salt: [u8; 32],
+}
+
+impl Anonymizer {
+    /// Create an anonymizer with a fresh random salt.
+    pub fn new(policy: AnonymizationPolicy) -> Self {
+        let mut salt = [0u8; 32];
+        OsRng.fill_bytes(&mut salt);
+        Self::with_salt(policy, salt)
+    }
+
+    /// Create an anonymizer with a given salt.
+    ///
+    /// Reuse a salt only for exports that must be joinable with each other.
+    pub fn with_salt(policy: AnonymizationPolicy, salt: [u8; 32]) -> Self {
+        Self { policy, salt }
+    }
+
+    /// Get the policy.
+    pub fn policy(&self) -> &AnonymizationPolicy {
+        &self.policy
+    }
+
+    /// Anonymize the top-level fields of documents.
+    pub fn anonymize(&self, documents: &[DocumentHandle]) -> Result<AnonymizedDataset> {
+        let rows = documents
+            .iter()
+            .map(|handle| handle.read(document_json))
+            .collect::<vudo_state::Result<Vec<Row>>>()?;
+        self.anonymize_rows(rows)
+    }
+
+    /// Anonymize rows.
+    pub fn anonymize_rows(&self, rows: Vec<Row>) -> Result<AnonymizedDataset> {
+        if self
+            .policy
+            .quasi_identifiers
+            .iter()
+            .any(|qi| qi.levels.is_empty())
+        {
+            return Err(PrivacyError::AnonymizationError(
+                "Quasi-identifiers need at least one generalization".to_string(),
+            ));
+        }
+
+        let rows: Vec<Row> = rows.into_iter().map(|row| self.transform(row)).collect();
+        let k = self.policy.k.max(1);
+
+        // Coarsen until few enough rows are in small groups, or the coarsest
+        // level
+        let levels = self.policy.level_count();
+        let allowed = (rows.len() as f64 * self.policy.max_suppression).floor() as usize;
+        let mut level = 0;
+        let (mut generalized, mut small) = self.generalize(&rows, level, k);
+        while small.len() > allowed && level + 1 < levels {
+            level += 1;
+            (generalized, small) = self.generalize(&rows, level, k);
+        }
+
+        let suppressed_rows = small.len();
+        let rows = generalized
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| !small.contains(i))
+            .map(|(_, row)| row)
+            .collect();
+        Ok(AnonymizedDataset {
+            rows,
+            suppressed_rows,
+            level,
+            k,
+        })
+    }
+
+    /// Suppress personal fields and hash direct identifiers.
+    fn transform(&self, mut row: Row) -> Row {
+        row.retain(|field, _| !self.policy.suppressed.contains(field));
+        for field in &self.policy.hashed {
+            if let Some(value) = row.get_mut(field) {
+                *value = Value::String(self.hash_value(value));
+            }
+        }
+        row
+    }
+
+    /// Hash a value under the dataset salt.
+    fn hash_value(&self, value: &Value) -> String {
+        let canonical = match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        blake3::keyed_hash(&self.salt, canonical.as_bytes())
+            .to_hex()
+            .to_string()
+    }
+
+    /// Generalize quasi-identifiers at `level`, returning the rows and the
+    /// indices of rows in groups smaller than `k`.
+    fn generalize(&self, rows: &[Row], level: usize, k: usize) -> (Vec<Row>, BTreeSet<usize>) {
+        let mut generalized = Vec::with_capacity(rows.len());
+        let mut groups: HashMap<Vec<Value>, Vec<usize>> = HashMap::new();
+        for (i, row) in rows.iter().enumerate() {
+            let mut row = row.clone();
+            let mut key = Vec::with_capacity(self.policy.quasi_identifiers.len());
+            for qi in &self.policy.quasi_identifiers {
+                let generalization = &qi.levels[level.min(qi.levels.len() - 1)];
+                let value = row
+                    .get(&qi.field)
+                    .map(|value| generalization.apply(value))
+                    .unwrap_or(Value::Null);
+                if row.contains_key(&qi.field) {
+                    row.insert(qi.field.clone(), value.clone());
+                }
+                key.push(value);
+            }
+            groups.entry(key).or_default().push(i);
+            generalized.push(row);
+        }
+
+        let small = groups
+            .into_values()
+            .filter(|members| members.len() < k)
+            .flatten()
+            .collect();
+        (generalized, small)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rows(values: Vec<Value>) -> Vec<Row> {
+        values
+            .into_iter()
+            .map(|v| v.as_object().unwrap().clone())
+            .collect()
+    }
+
+    fn policy() -> AnonymizationPolicy {
+        AnonymizationPolicy::new()
+            .personal(["email"])
+            .hash("user_id")
+            .quasi_identifier(
+                "age",
+                vec![
+                    Generalization::Range(10),
+                    Generalization::Range(30),
+                    Generalization::Suppress,
+                ],
+            )
+            .quasi_identifier(
+                "zip",
+                vec![Generalization::Prefix(4), Generalization::Prefix(2)],
+            )
+            .k(2)
+            .max_suppression(0.2)
+    }
+
+    #[test]
+    fn test_generalizations() {
+        assert_eq!(Generalization::Range(10).apply(&json!(37)), json!("30-39"));
+        assert_eq!(
+            Generalization::Range(10).apply(&json!(37.9)),
+            json!("30-39")
+        );
+        assert_eq!(Generalization::Range(10).apply(&json!("old")), json!("*"));
+        assert_eq!(
+            Generalization::Prefix(3).apply(&json!("94107")),
+            json!("941**")
+        );
+        assert_eq!(Generalization::Exact.apply(&json!(37)), json!(37));
+    }
+
+    #[test]
+    fn test_k_anonymity() {
+        let anonymizer = Anonymizer::new(policy());
+        let dataset = anonymizer
+            .anonymize_rows(rows(vec![
+                json!({"user_id": "u1", "email": "a@x.org", "age": 31, "zip": "94107", "plan": "pro"}),
+                json!({"user_id": "u2", "email": "b@x.org", "age": 35, "zip": "94109", "plan": "free"}),
+                json!({"user_id": "u3", "email": "c@x.org", "age": 52, "zip": "94110", "plan": "pro"}),
+                json!({"user_id": "u4", "email": "d@x.org", "age": 48, "zip": "94118", "plan": "pro"}),
+                json!({"user_id": "u5", "email": "e@x.org", "age": 44, "zip": "10001", "plan": "free"}),
+            ]))
+            .unwrap();
+
+        // Ages 52 and 48 only group at the second level; the New York row never does
+        assert_eq!(dataset.level, 1);
+        assert_eq!(dataset.suppressed_rows, 1);
+        assert_eq!(dataset.rows.len(), 4);
+        let mut groups: HashMap<(Value, Value), usize> = HashMap::new();
+        for row in &dataset.rows {
+            assert!(row.get("email").is_none());
+            assert!(row["plan"].as_str().is_some());
+            *groups
+                .entry((row["age"].clone(), row["zip"].clone()))
+                .or_default() += 1;
+        }
+        assert!(groups.values().all(|&n| n >= 2));
+        assert_eq!(dataset.rows[0]["age"], json!("30-59"));
+        assert_eq!(dataset.rows[0]["zip"], json!("94***"));
+    }
+
+    #[test]
+    fn test_salted_hashes() {
+        let input = rows(vec![
+            json!({"user_id": "u1", "age": 31}),
+            json!({"user_id": "u1", "age": 33}),
+        ]);
+        let first = Anonymizer::new(policy())
+            .anonymize_rows(input.clone())
+            .unwrap();
+        let second = Anonymizer::new(policy())
+            .anonymize_rows(input.clone())
+            .unwrap();
+
+        // Linkable within a dataset, not across datasets
+        assert_eq!(first.rows[0]["user_id"], first.rows[1]["user_id"]);
+        assert_ne!(first.rows[0]["user_id"], json!("u1"));
+        assert_ne!(first.rows[0]["user_id"], second.rows[0]["user_id"]);
+
+        let salt = [7u8; 32];
+        let a = Anonymizer::with_salt(policy(), salt).anonymize_rows(input.clone());
+        let b = Anonymizer::with_salt(policy(), salt).anonymize_rows(input);
+        assert_eq!(a.unwrap().rows, b.unwrap().rows);
+    }
+
+    #[test]
+    fn test_anonymize_documents() {
+        use crate::at_rest::DekOwner;
+        use crate::crypto::PersonalDataCrypto;
+        use vudo_state::{DocumentId, DocumentStore};
+
+        let crypto = PersonalDataCrypto::new();
+        crypto.generate_dek("did:peer:alice").unwrap();
+        let interceptor = PersonalFieldInterceptor::new(crypto, DekOwner::DocumentKey, ["email"]);
+        let handle = DocumentStore::new()
+            .create(DocumentId::new("users", "did:peer:alice"))
+            .unwrap();
+        interceptor
+            .update(&handle, |doc| {
+                doc.put("email", "alice@example.com")?;
+                doc.put("plan", "pro")
+            })
+            .unwrap();
+
+        let policy = AnonymizationPolicy::new().personal_from(&interceptor);
+        let dataset = Anonymizer::new(policy).anonymize(&[handle]).unwrap();
+        assert_eq!(dataset.rows.len(), 1);
+        assert!(dataset.rows[0].get("email").is_none());
+        assert_eq!(dataset.rows[0]["plan"], json!("pro"));
+    }
+}
@@ -18,6 +18,13 @@
 //! The audit log is append-only and includes cryptographic proofs (deletion
 //! receipts) when cryptographic deletion is used.
 //!
+//! Entries are hash-chained: each entry includes the hash of the previous
+//! one, so editing, removing or reordering an entry breaks the chain. With a
+//! signing key, the log also signs an [`AuditCheckpoint`] of the chain head
+//! every `interval` entries, and passes it to an optional [`AuditAnchor`]
+//! (e.g. one publishing it over P2P gossip). Once a checkpoint is out of the
+//! controller's hands, rewriting the log up to it is detectable too.
+//!
 //! # Example
 //!
 //! ```rust
@@ -41,12 +48,26 @@
 //! # }
 //! ```
 
+use crate::access::AccessContext;
 use crate::crypto::DeletionReceipt;
+use crate::error::{PrivacyError, Result};
 use chrono::Utc;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use parking_lot::RwLock;
+use tracing::warn;
 use uuid::Uuid;
+use vudo_identity::Did;
+
+/// Domain separator of entry hashes.
+const ENTRY_DOMAIN: &[u8] = b"vudo-privacy audit entry v1";
+
+/// Domain separator of checkpoint signatures.
+const CHECKPOINT_DOMAIN: &[u8] = b"vudo-privacy audit checkpoint v1";
+
+/// Hash preceding the first entry of a log (hex).
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
 
 /// Category of data being deleted.
 ///
@@ -83,6 +104,9 @@ pub enum DeletionMethod {
 
     /// Physical deletion (not recommended for CRDTs).
     PhysicalDeletion,
+
+    /// DEK rotation (the old key is destroyed after re-encryption).
+    KeyRotation,
 }
 
 /// Audit log entry for a deletion request.
@@ -108,6 +132,18 @@ pub struct DeletionLogEntry {
 
     /// Optional notes or rationale.
     pub notes: Option<String>,
+
+    /// Name of the compliance profile that governed the operation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+
+    /// Hash of the previous entry (hex, [`GENESIS_HASH`] for the first).
+    #[serde(default)]
+    pub prev_hash: String,
+
+    /// Hash of this entry, covering `prev_hash` (hex).
+    #[serde(default)]
+    pub hash: String,
 }
 
 impl DeletionLogEntry {
@@ -126,6 +162,9 @@ impl DeletionLogEntry {
             method,
             proof,
             notes: None,
+            profile: None,
+            prev_hash: String::new(),
+            hash: String::new(),
         }
     }
 
@@ -135,6 +174,12 @@ impl DeletionLogEntry {
         self
     }
 
+    /// Name the compliance profile that governed this entry.
+    pub fn with_profile(mut self, profile: String) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
     /// Check if this entry has cryptographic proof.
     pub fn has_proof(&self) -> bool {
         self.proof.is_some()
@@ -145,6 +190,146 @@ impl DeletionLogEntry {
         let now = Utc::now().timestamp() as u64;
         now.saturating_sub(self.deleted_at)
     }
+
+    /// Compute the chain hash of this entry (hex).
+    ///
+    /// Covers every field but `hash` itself, `prev_hash` included.
+    pub fn compute_hash(&self) -> Result<String> {
+        let unhashed = Self {
+            hash: String::new(),
+            ..self.clone()
+        };
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(ENTRY_DOMAIN);
+        hasher.update(&[0]);
+        hasher.update(&serde_json::to_vec(&unhashed)?);
+        Ok(hasher.finalize().to_hex().to_string())
+    }
+}
+
+/// Audit log entry for a read of personal data.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessLogEntry {
+    /// Unique access ID.
+    pub access_id: String,
+
+    /// DID of the reading device (None if unattributed).
+    pub accessor: Option<String>,
+
+    /// Capability the reader used.
+    pub capability: Option<String>,
+
+    /// DID of the data owner.
+    pub owner: String,
+
+    /// Document read (`namespace/key`), if known.
+    pub document: Option<String>,
+
+    /// Field read, if known.
+    pub field: Option<String>,
+
+    /// Access timestamp (Unix seconds).
+    pub accessed_at: u64,
+}
+
+impl AccessLogEntry {
+    /// Create an access log entry.
+    pub fn new(
+        owner: String,
+        document: Option<String>,
+        field: Option<String>,
+        context: &AccessContext,
+    ) -> Self {
+        Self {
+            access_id: Uuid::new_v4().to_string(),
+            accessor: context.accessor.clone(),
+            capability: context.capability.clone(),
+            owner,
+            document,
+            field,
+            accessed_at: Utc::now().timestamp() as u64,
+        }
+    }
+}
+
+/// Signed checkpoint of the head of an audit log's hash chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditCheckpoint {
+    /// Number of entries covered.
+    pub sequence: usize,
+
+    /// Hash of the last covered entry (hex).
+    pub head_hash: String,
+
+    /// Checkpoint timestamp (Unix seconds).
+    pub created_at: u64,
+
+    /// DID of the signing controller (did:key).
+    pub signer: String,
+
+    /// Ed25519 signature of the checkpoint (hex).
+    pub signature: String,
+}
+
+impl AuditCheckpoint {
+    /// Create a checkpoint of `sequence` entries ending in `head_hash`.
+    fn sign(sequence: usize, head_hash: String, key: &SigningKey) -> Result<Self> {
+        let mut checkpoint = Self {
+            sequence,
+            head_hash,
+            created_at: Utc::now().timestamp() as u64,
+            signer: Did::from_key(key.verifying_key()).to_string(),
+            signature: String::new(),
+        };
+        checkpoint.signature = hex::encode(key.sign(&checkpoint.signing_bytes()?).to_bytes());
+        Ok(checkpoint)
+    }
+
+    /// Get the digest of the checkpoint, as published by anchors (hex).
+    pub fn digest(&self) -> Result<String> {
+        Ok(blake3::hash(&serde_json::to_vec(self)?)
+            .to_hex()
+            .to_string())
+    }
+
+    /// Verify the checkpoint signature against the controller's public key.
+    pub fn verify(&self, public_key: &VerifyingKey) -> Result<()> {
+        if self.signer != Did::from_key(*public_key).to_string() {
+            return Err(PrivacyError::InvalidProof(format!(
+                "Checkpoint is signed by {}",
+                self.signer
+            )));
+        }
+        let signature = hex::decode(&self.signature)
+            .ok()
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+            .ok_or_else(|| {
+                PrivacyError::InvalidProof("Malformed checkpoint signature".to_string())
+            })?;
+        public_key
+            .verify(&self.signing_bytes()?, &signature)
+            .map_err(|_| PrivacyError::InvalidProof("Invalid checkpoint signature".to_string()))
+    }
+
+    /// Bytes covered by the signature.
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        let unsigned = Self {
+            signature: String::new(),
+            ..self.clone()
+        };
+        let mut bytes = CHECKPOINT_DOMAIN.to_vec();
+        bytes.push(0);
+        bytes.extend_from_slice(&serde_json::to_vec(&unsigned)?);
+        Ok(bytes)
+    }
+}
+
+/// Hook publishing audit checkpoints outside the controller's reach.
+///
+/// Anchoring failures are logged and don't fail the recording of entries.
+pub trait AuditAnchor: Send + Sync {
+    /// Publish a checkpoint.
+    fn anchor(&self, checkpoint: &AuditCheckpoint) -> Result<()>;
 }
 
 /// Audit log for GDPR deletion requests.
@@ -154,6 +339,18 @@ impl DeletionLogEntry {
 pub struct DeletionAuditLog {
     /// Log entries (append-only).
     logs: Arc<RwLock<Vec<DeletionLogEntry>>>,
+
+    /// Reads of personal data (append-only).
+    accesses: Arc<RwLock<Vec<AccessLogEntry>>>,
+
+    /// Signed checkpoints, oldest first.
+    checkpoints: Arc<RwLock<Vec<AuditCheckpoint>>>,
+
+    /// Key signing checkpoints, and the number of entries between them.
+    checkpointing: Option<(Arc<SigningKey>, usize)>,
+
+    /// Hook publishing checkpoints (optional).
+    anchor: Option<Arc<dyn AuditAnchor>>,
 }
 
 impl DeletionAuditLog {
@@ -161,9 +358,26 @@ impl DeletionAuditLog {
     pub fn new() -> Self {
         Self {
             logs: Arc::new(RwLock::new(Vec::new())),
+            accesses: Arc::new(RwLock::new(Vec::new())),
+            checkpoints: Arc::new(RwLock::new(Vec::new())),
+            checkpointing: None,
+            anchor: None,
         }
     }
 
+    /// Sign a checkpoint of the chain head with `key` every `interval`
+    /// entries.
+    pub fn with_checkpoints(mut self, key: SigningKey, interval: usize) -> Self {
+        self.checkpointing = Some((Arc::new(key), interval.max(1)));
+        self
+    }
+
+    /// Publish checkpoints through an anchor.
+    pub fn with_anchor(mut self, anchor: Arc<dyn AuditAnchor>) -> Self {
+        self.anchor = Some(anchor);
+        self
+    }
+
     /// Record a deletion operation.
     ///
     /// # Arguments
@@ -190,11 +404,104 @@ impl DeletionAuditLog {
             proof,
         );
 
+        self.record_entry(entry)
+    }
+
+    /// Record a prepared log entry (e.g. one with notes).
+    ///
+    /// The entry is chained to the previous one, and a checkpoint is signed
+    /// if one is due.
+    ///
+    /// # Returns
+    ///
+    /// The request ID of the entry.
+    pub fn record_entry(&self, mut entry: DeletionLogEntry) -> String {
         let request_id = entry.request_id.clone();
-        self.logs.write().push(entry);
+        let checkpoint = {
+            let mut logs = self.logs.write();
+            entry.prev_hash = logs
+                .last()
+                .map_or_else(|| GENESIS_HASH.to_string(), |last| last.hash.clone());
+            entry.hash = entry.compute_hash().unwrap_or_default();
+            logs.push(entry);
+
+            match &self.checkpointing {
+                Some((key, interval)) if logs.len() % interval == 0 => {
+                    self.sign_checkpoint(&logs, key)
+                }
+                _ => None,
+            }
+        };
+
+        if let Some(checkpoint) = checkpoint {
+            self.publish(&checkpoint);
+        }
         request_id
     }
 
+    /// Sign a checkpoint of the current chain head, and anchor it.
+    ///
+    /// Fails if the log has no signing key or no entries.
+    pub fn checkpoint(&self) -> Result<AuditCheckpoint> {
+        let (key, _) = self.checkpointing.as_ref().ok_or_else(|| {
+            PrivacyError::AuditLogError("Audit log has no checkpoint signing key".to_string())
+        })?;
+        let checkpoint = self
+            .sign_checkpoint(&self.logs.read(), key)
+            .ok_or_else(|| PrivacyError::AuditLogError("Audit log is empty".to_string()))?;
+        self.publish(&checkpoint);
+        Ok(checkpoint)
+    }
+
+    /// Get the signed checkpoints, oldest first.
+    pub fn checkpoints(&self) -> Vec<AuditCheckpoint> {
+        self.checkpoints.read().clone()
+    }
+
+    /// Get the hash of the last entry ([`GENESIS_HASH`] if empty).
+    pub fn head_hash(&self) -> String {
+        self.logs
+            .read()
+            .last()
+            .map_or_else(|| GENESIS_HASH.to_string(), |last| last.hash.clone())
+    }
+
+    /// Verify the hash chain of the log.
+    pub fn verify_chain(&self) -> Result<()> {
+        verify_chain(&self.logs.read())
+    }
+
+    /// Sign and keep a checkpoint of `logs`.
+    fn sign_checkpoint(
+        &self,
+        logs: &[DeletionLogEntry],
+        key: &SigningKey,
+    ) -> Option<AuditCheckpoint> {
+        let head = logs.last()?;
+        match AuditCheckpoint::sign(logs.len(), head.hash.clone(), key) {
+            Ok(checkpoint) => {
+                self.checkpoints.write().push(checkpoint.clone());
+                Some(checkpoint)
+            }
+            Err(e) => {
+                warn!("Failed to sign audit checkpoint: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Pass a checkpoint to the anchor.
+    fn publish(&self, checkpoint: &AuditCheckpoint) {
+        if let Some(anchor) = &self.anchor {
+            if let Err(e) = anchor.anchor(checkpoint) {
+                warn!(
+                    "Failed to anchor audit checkpoint {}: {}",
+                    checkpoint.sequence, e
+                );
+            }
+        }
+    }
+
     /// Get all log entries.
     pub fn get_all_entries(&self) -> Vec<DeletionLogEntry> {
         self.logs.read().clone()
@@ -247,6 +554,47 @@ impl DeletionAuditLog {
             .collect()
     }
 
+    /// Record a read of personal data.
+    ///
+    /// # Returns
+    ///
+    /// The access ID of the entry.
+    pub fn record_access(&self, entry: AccessLogEntry) -> String {
+        let access_id = entry.access_id.clone();
+        self.accesses.write().push(entry);
+        access_id
+    }
+
+    /// Get all access entries.
+    pub fn get_access_entries(&self) -> Vec<AccessLogEntry> {
+        self.accesses.read().clone()
+    }
+
+    /// Get the reads of a data owner's personal data.
+    pub fn get_accesses_for_owner(&self, owner_did: &str) -> Vec<AccessLogEntry> {
+        self.accesses
+            .read()
+            .iter()
+            .filter(|entry| entry.owner == owner_did)
+            .cloned()
+            .collect()
+    }
+
+    /// Get the reads by a device.
+    pub fn get_accesses_by(&self, accessor_did: &str) -> Vec<AccessLogEntry> {
+        self.accesses
+            .read()
+            .iter()
+            .filter(|entry| entry.accessor.as_deref() == Some(accessor_did))
+            .cloned()
+            .collect()
+    }
+
+    /// Export the access entries to JSON.
+    pub fn export_access_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&*self.accesses.read())
+    }
+
     /// Get total number of deletions.
     pub fn total_deletions(&self) -> usize {
         self.logs.read().len()
@@ -258,10 +606,13 @@ impl DeletionAuditLog {
     }
 
     /// Import audit log from JSON.
+    ///
+    /// The hash chain is not checked; see [`DeletionAuditLog::verify_chain`].
     pub fn import_json(json: &str) -> serde_json::Result<Self> {
         let entries: Vec<DeletionLogEntry> = serde_json::from_str(json)?;
         Ok(Self {
             logs: Arc::new(RwLock::new(entries)),
+            ..Self::new()
         })
     }
 
@@ -272,6 +623,125 @@ impl DeletionAuditLog {
     }
 }
 
+/// Result of verifying the deletion proofs of an audit log.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditVerification {
+    /// Number of log entries.
+    pub entries: usize,
+
+    /// Request IDs of entries with a verified proof.
+    pub verified: Vec<String>,
+
+    /// Request IDs of entries without a proof (e.g. tombstones).
+    pub unproven: Vec<String>,
+
+    /// Request IDs of entries whose proof failed, with the reason.
+    pub failed: Vec<(String, String)>,
+
+    /// Why the hash chain is broken, if it is.
+    #[serde(default)]
+    pub chain_error: Option<String>,
+}
+
+impl AuditVerification {
+    /// Check that the hash chain is intact and no proof failed.
+    pub fn is_valid(&self) -> bool {
+        self.failed.is_empty() && self.chain_error.is_none()
+    }
+}
+
+/// Verify the hash chain of log entries.
+fn verify_chain(entries: &[DeletionLogEntry]) -> Result<()> {
+    let mut prev_hash = GENESIS_HASH.to_string();
+    for entry in entries {
+        if entry.prev_hash != prev_hash {
+            return Err(PrivacyError::AuditLogError(format!(
+                "Entry {} does not follow the previous entry",
+                entry.request_id
+            )));
+        }
+        if entry.compute_hash()? != entry.hash {
+            return Err(PrivacyError::AuditLogError(format!(
+                "Entry {} was modified",
+                entry.request_id
+            )));
+        }
+        prev_hash = entry.hash.clone();
+    }
+    Ok(())
+}
+
+/// Verify an exported audit log against a signed checkpoint.
+///
+/// `json` is the output of [`DeletionAuditLog::export_json`], and
+/// `checkpoint` one obtained independently of it, e.g. from an anchor. The
+/// checkpoint must be signed with `public_key`, and the log's hash chain must
+/// be intact and pass through the checkpoint's head.
+pub fn verify_checkpoint(
+    json: &str,
+    checkpoint: &AuditCheckpoint,
+    public_key: &VerifyingKey,
+) -> Result<()> {
+    checkpoint.verify(public_key)?;
+
+    let entries: Vec<DeletionLogEntry> = serde_json::from_str(json)?;
+    verify_chain(&entries)?;
+    let head = checkpoint
+        .sequence
+        .checked_sub(1)
+        .and_then(|i| entries.get(i))
+        .ok_or_else(|| {
+            PrivacyError::AuditLogError(format!(
+                "Log has {} entries, checkpoint covers {}",
+                entries.len(),
+                checkpoint.sequence
+            ))
+        })?;
+    if head.hash != checkpoint.head_hash {
+        return Err(PrivacyError::AuditLogError(format!(
+            "Log was rewritten before entry {}",
+            checkpoint.sequence
+        )));
+    }
+    Ok(())
+}
+
+/// Verify the deletion proofs of an exported audit log.
+///
+/// For auditors and other third parties: `json` is the output of
+/// [`DeletionAuditLog::export_json`] and `public_key` the controller's
+/// signing key. Every deletion receipt in the log must be signed with it and
+/// name the user of its entry. Fails only if the log can't be parsed.
+pub fn verify_audit_log(json: &str, public_key: &VerifyingKey) -> Result<AuditVerification> {
+    let entries: Vec<DeletionLogEntry> = serde_json::from_str(json)?;
+
+    let mut verification = AuditVerification {
+        entries: entries.len(),
+        chain_error: verify_chain(&entries).err().map(|e| e.to_string()),
+        ..Default::default()
+    };
+    for entry in entries {
+        let receipt = match &entry.proof {
+            Some(receipt) => receipt,
+            None => {
+                verification.unproven.push(entry.request_id);
+                continue;
+            }
+        };
+
+        let result = if receipt.owner != entry.user_did {
+            Err(format!("Receipt is for {}", receipt.owner))
+        } else {
+            receipt.verify(public_key).map_err(|e| e.to_string())
+        };
+        match result {
+            Ok(()) => verification.verified.push(entry.request_id),
+            Err(reason) => verification.failed.push((entry.request_id, reason)),
+        }
+    }
+    Ok(verification)
+}
+
 impl Default for DeletionAuditLog {
     fn default() -> Self {
         Self::new()
@@ -282,6 +752,10 @@ impl Clone for DeletionAuditLog {
     fn clone(&self) -> Self {
         Self {
             logs: Arc::clone(&self.logs),
+            accesses: Arc::clone(&self.accesses),
+            checkpoints: Arc::clone(&self.checkpoints),
+            checkpointing: self.checkpointing.clone(),
+            anchor: self.anchor.clone(),
         }
     }
 }
@@ -305,6 +779,158 @@ mod tests {
         assert_eq!(audit_log.total_deletions(), 1);
     }
 
+    #[test]
+    fn test_verify_audit_log() {
+        use crate::crypto::PersonalDataCrypto;
+        use ed25519_dalek::SigningKey;
+        use rand::rngs::OsRng;
+
+        let controller = SigningKey::generate(&mut OsRng);
+        let crypto = PersonalDataCrypto::new();
+        crypto.generate_dek("did:peer:alice").unwrap();
+        crypto.generate_dek("did:peer:bob").unwrap();
+        let mut alice = crypto.delete_dek("did:peer:alice").unwrap();
+        alice.sign(&controller).unwrap();
+        let bob = crypto.delete_dek("did:peer:bob").unwrap();
+
+        let audit_log = DeletionAuditLog::new();
+        let verified = audit_log.record_deletion(
+            "did:peer:alice",
+            vec![DataCategory::PersonalData],
+            DeletionMethod::CryptographicErasure,
+            Some(alice.clone()),
+        );
+        let unsigned = audit_log.record_deletion(
+            "did:peer:bob",
+            vec![DataCategory::PersonalData],
+            DeletionMethod::CryptographicErasure,
+            Some(bob),
+        );
+        let unproven = audit_log.record_deletion(
+            "did:peer:alice",
+            vec![DataCategory::PublicData],
+            DeletionMethod::Tombstone,
+            None,
+        );
+        // Alice's receipt passed off as Carol's
+        let misattributed = audit_log.record_deletion(
+            "did:peer:carol",
+            vec![DataCategory::PersonalData],
+            DeletionMethod::CryptographicErasure,
+            Some(alice),
+        );
+
+        let json = audit_log.export_json().unwrap();
+        let verification = verify_audit_log(&json, &controller.verifying_key()).unwrap();
+        assert_eq!(verification.entries, 4);
+        assert_eq!(verification.verified, vec![verified]);
+        assert_eq!(verification.unproven, vec![unproven]);
+        let failed: Vec<&str> = verification
+            .failed
+            .iter()
+            .map(|(id, _)| id.as_str())
+            .collect();
+        assert_eq!(failed, vec![unsigned.as_str(), misattributed.as_str()]);
+        assert!(!verification.is_valid());
+
+        assert!(verify_audit_log("not json", &controller.verifying_key()).is_err());
+    }
+
+    #[test]
+    fn test_hash_chain() {
+        let audit_log = DeletionAuditLog::new();
+        for user in ["did:peer:alice", "did:peer:bob", "did:peer:carol"] {
+            audit_log.record_deletion(
+                user,
+                vec![DataCategory::PersonalData],
+                DeletionMethod::CryptographicErasure,
+                None,
+            );
+        }
+
+        let entries = audit_log.get_all_entries();
+        assert_eq!(entries[0].prev_hash, GENESIS_HASH);
+        assert_eq!(entries[1].prev_hash, entries[0].hash);
+        assert_eq!(audit_log.head_hash(), entries[2].hash);
+        audit_log.verify_chain().unwrap();
+
+        // Edited entry
+        let mut edited = entries.clone();
+        edited[1].user_did = "did:peer:mallory".to_string();
+        let json = serde_json::to_string(&edited).unwrap();
+        assert!(DeletionAuditLog::import_json(&json)
+            .unwrap()
+            .verify_chain()
+            .is_err());
+
+        // Removed entry
+        let mut removed = entries;
+        removed.remove(1);
+        let json = serde_json::to_string(&removed).unwrap();
+        let key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let verification = verify_audit_log(&json, &key.verifying_key()).unwrap();
+        assert!(verification.chain_error.is_some());
+        assert!(!verification.is_valid());
+    }
+
+    #[test]
+    fn test_checkpoints_and_anchor() {
+        use ed25519_dalek::SigningKey;
+        use rand::rngs::OsRng;
+
+        struct Anchored(RwLock<Vec<String>>);
+        impl AuditAnchor for Anchored {
+            fn anchor(&self, checkpoint: &AuditCheckpoint) -> Result<()> {
+                self.0.write().push(checkpoint.digest()?);
+                Ok(())
+            }
+        }
+
+        let controller = SigningKey::generate(&mut OsRng);
+        let anchored = Arc::new(Anchored(RwLock::new(Vec::new())));
+        let audit_log = DeletionAuditLog::new()
+            .with_checkpoints(controller.clone(), 2)
+            .with_anchor(anchored.clone());
+        for _ in 0..5 {
+            audit_log.record_deletion(
+                "did:peer:alice",
+                vec![DataCategory::PersonalData],
+                DeletionMethod::CryptographicErasure,
+                None,
+            );
+        }
+
+        let checkpoints = audit_log.checkpoints();
+        assert_eq!(checkpoints.len(), 2);
+        assert_eq!(checkpoints[1].sequence, 4);
+        assert_eq!(anchored.0.read().len(), 2);
+        assert_eq!(anchored.0.read()[1], checkpoints[1].digest().unwrap());
+
+        let json = audit_log.export_json().unwrap();
+        let public_key = controller.verifying_key();
+        verify_checkpoint(&json, &checkpoints[1], &public_key).unwrap();
+
+        // Rewritten log: a consistent chain, but not through the checkpoint
+        let rewritten = DeletionAuditLog::new();
+        for entry in audit_log.get_all_entries().into_iter().skip(1) {
+            rewritten.record_entry(entry);
+        }
+        let json = rewritten.export_json().unwrap();
+        rewritten.verify_chain().unwrap();
+        assert!(verify_checkpoint(&json, &checkpoints[1], &public_key).is_err());
+
+        // Forged checkpoint
+        let mut forged = checkpoints[1].clone();
+        forged.head_hash = rewritten.get_all_entries()[3].hash.clone();
+        assert!(verify_checkpoint(&json, &forged, &public_key).is_err());
+
+        // Forced checkpoint of the chain head
+        let checkpoint = audit_log.checkpoint().unwrap();
+        assert_eq!(checkpoint.sequence, 5);
+        assert_eq!(anchored.0.read().len(), 3);
+        assert!(DeletionAuditLog::new().checkpoint().is_err());
+    }
+
     #[test]
     fn test_get_entries_for_user() {
         let audit_log = DeletionAuditLog::new();
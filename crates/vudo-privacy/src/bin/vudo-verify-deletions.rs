@@ -0,0 +1,92 @@
+//! vudo-verify-deletions - Verify the deletion proofs of an exported audit log
+//!
+//! Checks that the hash chain of an audit log exported with
+//! `GdprComplianceEngine::export_audit_log` is intact, and that every deletion
+//! receipt in it is signed by the controller and names the user of its entry.
+//!
+//! # Usage
+//!
+//! ```bash
+//! # Controller key as a did:key
+//! vudo-verify-deletions --signer did:key:z6Mk... audit-log.json
+//!
+//! # Controller key as hex, with a JSON report
+//! vudo-verify-deletions --signer 3b6a27bc... --format json audit-log.json
+//! ```
+//!
+//! Exits with status 1 if the chain is broken or a proof fails to verify.
+
+use clap::{Parser, ValueEnum};
+use ed25519_dalek::VerifyingKey;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use vudo_identity::Did;
+use vudo_privacy::audit::verify_audit_log;
+
+/// Verify the deletion proofs of an exported audit log
+#[derive(Parser, Debug)]
+#[command(name = "vudo-verify-deletions", version, about)]
+struct Args {
+    /// Exported audit log (JSON)
+    audit_log: PathBuf,
+
+    /// Controller signing key (did:key DID or hex Ed25519 public key)
+    #[arg(long)]
+    signer: String,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<bool, Box<dyn std::error::Error>> {
+    let public_key = parse_signer(&args.signer)?;
+    let json = std::fs::read_to_string(&args.audit_log)?;
+    let verification = verify_audit_log(&json, &public_key)?;
+
+    match args.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&verification)?),
+        Format::Text => {
+            println!("Entries:    {}", verification.entries);
+            println!("Verified:   {}", verification.verified.len());
+            println!("Unproven:   {}", verification.unproven.len());
+            println!("Failed:     {}", verification.failed.len());
+            if let Some(error) = &verification.chain_error {
+                println!("Chain:      broken ({})", error);
+            }
+            for (request_id, reason) in &verification.failed {
+                println!("  {}: {}", request_id, reason);
+            }
+        }
+    }
+    Ok(verification.is_valid())
+}
+
+/// Parse a did:key DID or a hex Ed25519 public key.
+fn parse_signer(signer: &str) -> Result<VerifyingKey, Box<dyn std::error::Error>> {
+    if signer.starts_with("did:") {
+        return Ok(Did::parse(signer)?.verification_key);
+    }
+    let bytes: [u8; 32] = hex::decode(signer)?
+        .try_into()
+        .map_err(|_| "Public key must be 32 bytes")?;
+    Ok(VerifyingKey::from_bytes(&bytes)?)
+}
@@ -0,0 +1,455 @@
+//! Consent management ledger.
+//!
+//! GDPR Article 7 requires controllers to demonstrate consent and to make
+//! withdrawing it as easy as giving it. [`ConsentManager`] records consents
+//! and withdrawals in an append-only ledger kept in a `vudo-state` document,
+//! so it syncs between peers like any other CRDT document. Processing code
+//! asks [`ConsentManager::check_consent`] (or `require_consent`) before it
+//! touches a subject's data.
+//!
+//! Withdrawing consent stops processing: subscribers (see
+//! [`ConsentManager::subscribe_withdrawals`]) are notified so they can halt
+//! running jobs, and purposes configured with
+//! [`ConsentManager::erase_on_withdrawal`] trigger a GDPR deletion.
+//!
+//! # Ledger Format
+//!
+//! Each entry is an immutable JSON-encoded [`ConsentRecord`] stored under its
+//! record ID at the document root. Entries are only ever added, so ledgers
+//! from different peers merge without conflicts. The state of a consent is
+//! that of its latest record; a withdrawal wins over a grant made in the same
+//! second.
+//!
+//! # Example
+//!
+//! ```rust
+//! use vudo_privacy::audit::DataCategory;
+//! use vudo_privacy::consent::ConsentManager;
+//! use vudo_state::{DocumentId, DocumentStore};
+//!
+//! # async fn example() -> vudo_privacy::error::Result<()> {
+//! let store = DocumentStore::new();
+//! let ledger = store.create(DocumentId::new("consent", "ledger"))?;
+//! let consents = ConsentManager::new(ledger);
+//!
+//! consents.grant(
+//!     "did:peer:alice",
+//!     "analytics",
+//!     vec![DataCategory::PersonalData],
+//!     None,
+//! )?;
+//! assert!(consents.check_consent("did:peer:alice", "analytics")?);
+//!
+//! consents.withdraw("did:peer:alice", "analytics").await?;
+//! assert!(consents.require_consent("did:peer:alice", "analytics").is_err());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::audit::DataCategory;
+use crate::error::{PrivacyError, Result};
+use crate::gdpr::{DeletionReport, DeletionRequest, GdprComplianceEngine};
+use automerge::transaction::Transactable;
+use automerge::{ReadDoc, ROOT};
+use chrono::Utc;
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::info;
+use vudo_state::{DocumentHandle, StateError};
+
+/// Kind of consent record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsentAction {
+    /// Consent given.
+    Grant,
+    /// Consent withdrawn.
+    Withdraw,
+}
+
+/// Entry of the consent ledger.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsentRecord {
+    /// Record ID.
+    pub id: String,
+
+    /// Subject DID.
+    pub subject: String,
+
+    /// Processing purpose (e.g. "analytics").
+    pub purpose: String,
+
+    /// Data categories the consent covers.
+    pub scope: Vec<DataCategory>,
+
+    /// Grant or withdrawal.
+    pub action: ConsentAction,
+
+    /// Record timestamp (Unix seconds).
+    pub recorded_at: u64,
+
+    /// Expiry of a grant (Unix seconds, if any).
+    pub expires_at: Option<u64>,
+}
+
+impl ConsentRecord {
+    /// Check if this record is a grant in force at `now`.
+    pub fn is_active_at(&self, now: u64) -> bool {
+        self.action == ConsentAction::Grant && self.expires_at.map_or(true, |exp| now < exp)
+    }
+}
+
+/// Outcome of a consent withdrawal.
+#[derive(Debug, Clone)]
+pub struct WithdrawalOutcome {
+    /// Withdrawal record.
+    pub record: ConsentRecord,
+
+    /// Number of subscribers notified to stop processing.
+    pub notified: usize,
+
+    /// Report of the deletion triggered by the withdrawal (if any).
+    pub deletion: Option<DeletionReport>,
+}
+
+/// Append-only consent ledger.
+#[derive(Clone)]
+pub struct ConsentManager {
+    /// Ledger document.
+    ledger: DocumentHandle,
+
+    /// Engine executing deletions on withdrawal (optional).
+    engine: Option<Arc<GdprComplianceEngine>>,
+
+    /// Deletions to execute on withdrawal, by purpose.
+    erasures: Arc<DashMap<String, DeletionRequest>>,
+
+    /// Withdrawal subscribers.
+    subscribers: Arc<Mutex<Vec<mpsc::UnboundedSender<ConsentRecord>>>>,
+}
+
+impl ConsentManager {
+    /// Create a consent manager over a ledger document.
+    pub fn new(ledger: DocumentHandle) -> Self {
+        Self {
+            ledger,
+            engine: None,
+            erasures: Arc::new(DashMap::new()),
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Set the engine executing deletions on withdrawal.
+    pub fn with_engine(mut self, engine: Arc<GdprComplianceEngine>) -> Self {
+        self.engine = Some(engine);
+        self
+    }
+
+    /// Get the ledger document.
+    pub fn ledger(&self) -> &DocumentHandle {
+        &self.ledger
+    }
+
+    /// Execute `request` when consent for `purpose` is withdrawn.
+    ///
+    /// Use this for purposes that are the only basis for holding the data,
+    /// so withdrawal erases it. Requires an engine.
+    pub fn erase_on_withdrawal(&self, purpose: impl Into<String>, request: DeletionRequest) {
+        self.erasures.insert(purpose.into(), request);
+    }
+
+    /// Subscribe to consent withdrawals.
+    ///
+    /// Processing code stops work for a subject and purpose when it receives
+    /// their withdrawal.
+    pub fn subscribe_withdrawals(&self) -> mpsc::UnboundedReceiver<ConsentRecord> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.subscribers.lock().push(sender);
+        receiver
+    }
+
+    /// Record a subject's consent to a purpose.
+    ///
+    /// # Arguments
+    ///
+    /// * `subject` - The DID of the data subject
+    /// * `purpose` - The processing purpose
+    /// * `scope` - The data categories covered
+    /// * `expires_at` - When the consent lapses (Unix seconds), if ever
+    pub fn grant(
+        &self,
+        subject: &str,
+        purpose: &str,
+        scope: Vec<DataCategory>,
+        expires_at: Option<u64>,
+    ) -> Result<ConsentRecord> {
+        let record = self.append(subject, purpose, scope, ConsentAction::Grant, expires_at)?;
+        info!("Consent granted by {} for {}", subject, purpose);
+        Ok(record)
+    }
+
+    /// Withdraw a subject's consent to a purpose.
+    ///
+    /// Records the withdrawal, notifies subscribers, and executes the
+    /// deletion configured for the purpose.
+    pub async fn withdraw(&self, subject: &str, purpose: &str) -> Result<WithdrawalOutcome> {
+        let scope = self
+            .latest(subject, purpose)?
+            .map(|record| record.scope)
+            .unwrap_or_default();
+        let record = self.append(subject, purpose, scope, ConsentAction::Withdraw, None)?;
+        info!("Consent withdrawn by {} for {}", subject, purpose);
+
+        let notified = {
+            let mut subscribers = self.subscribers.lock();
+            subscribers.retain(|sender| sender.send(record.clone()).is_ok());
+            subscribers.len()
+        };
+
+        let request = self.erasures.get(purpose).map(|r| r.value().clone());
+        let deletion = match request {
+            Some(request) => {
+                let engine = self.engine.as_ref().ok_or_else(|| {
+                    PrivacyError::GdprDeletionFailed(format!(
+                        "No engine to erase data of {} on withdrawal",
+                        purpose
+                    ))
+                })?;
+                Some(engine.execute_deletion(subject, request).await?)
+            }
+            None => None,
+        };
+
+        Ok(WithdrawalOutcome {
+            record,
+            notified,
+            deletion,
+        })
+    }
+
+    /// Check if a subject currently consents to a purpose.
+    pub fn check_consent(&self, subject: &str, purpose: &str) -> Result<bool> {
+        self.check_consent_at(subject, purpose, Utc::now().timestamp() as u64)
+    }
+
+    /// Check if a subject consents to a purpose at `now` (Unix seconds).
+    pub fn check_consent_at(&self, subject: &str, purpose: &str, now: u64) -> Result<bool> {
+        Ok(self
+            .latest(subject, purpose)?
+            .is_some_and(|record| record.is_active_at(now)))
+    }
+
+    /// Fail unless a subject currently consents to a purpose.
+    pub fn require_consent(&self, subject: &str, purpose: &str) -> Result<()> {
+        if self.check_consent(subject, purpose)? {
+            Ok(())
+        } else {
+            Err(PrivacyError::ConsentRequired(format!(
+                "{} has not consented to {}",
+                subject, purpose
+            )))
+        }
+    }
+
+    /// Get the consents a subject currently gives, by purpose.
+    pub fn active_consents(&self, subject: &str) -> Result<Vec<ConsentRecord>> {
+        let now = Utc::now().timestamp() as u64;
+        let mut latest: Vec<ConsentRecord> = Vec::new();
+        for record in self.records()? {
+            if record.subject != subject {
+                continue;
+            }
+            latest.retain(|r| r.purpose != record.purpose);
+            latest.push(record);
+        }
+        latest.retain(|r| r.is_active_at(now));
+        Ok(latest)
+    }
+
+    /// Get the history of a subject's consents, oldest first.
+    pub fn history(&self, subject: &str) -> Result<Vec<ConsentRecord>> {
+        Ok(self
+            .records()?
+            .into_iter()
+            .filter(|r| r.subject == subject)
+            .collect())
+    }
+
+    /// Get every ledger record, oldest first.
+    pub fn records(&self) -> Result<Vec<ConsentRecord>> {
+        let mut records = self.ledger.read(|doc| {
+            let mut records = Vec::new();
+            for key in doc.keys(ROOT) {
+                let json = doc
+                    .get(ROOT, &key)?
+                    .and_then(|(value, _)| value.to_str().map(str::to_string))
+                    .ok_or_else(|| {
+                        StateError::SerializationError(format!("Malformed consent record {}", key))
+                    })?;
+                let record: ConsentRecord = serde_json::from_str(&json)
+                    .map_err(|e| StateError::SerializationError(e.to_string()))?;
+                records.push(record);
+            }
+            Ok(records)
+        })?;
+        records.sort_by(|a, b| {
+            (a.recorded_at, a.action, &a.id).cmp(&(b.recorded_at, b.action, &b.id))
+        });
+        Ok(records)
+    }
+
+    /// Get the latest record of a subject's consent to a purpose.
+    fn latest(&self, subject: &str, purpose: &str) -> Result<Option<ConsentRecord>> {
+        Ok(self
+            .records()?
+            .into_iter()
+            .rev()
+            .find(|r| r.subject == subject && r.purpose == purpose))
+    }
+
+    /// Append a record to the ledger.
+    fn append(
+        &self,
+        subject: &str,
+        purpose: &str,
+        scope: Vec<DataCategory>,
+        action: ConsentAction,
+        expires_at: Option<u64>,
+    ) -> Result<ConsentRecord> {
+        let record = ConsentRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            subject: subject.to_string(),
+            purpose: purpose.to_string(),
+            scope,
+            action,
+            recorded_at: Utc::now().timestamp() as u64,
+            expires_at,
+        };
+        let json = serde_json::to_string(&record)?;
+        self.ledger.update(|doc| {
+            doc.put(ROOT, record.id.as_str(), json)?;
+            Ok(())
+        })?;
+        Ok(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vudo_state::{DocumentId, DocumentStore};
+
+    fn manager() -> ConsentManager {
+        let ledger = DocumentStore::new()
+            .create(DocumentId::new("consent", "ledger"))
+            .unwrap();
+        ConsentManager::new(ledger)
+    }
+
+    #[tokio::test]
+    async fn test_grant_and_withdraw() {
+        let consents = manager();
+        let scope = vec![DataCategory::PersonalData];
+        assert!(!consents
+            .check_consent("did:peer:alice", "analytics")
+            .unwrap());
+
+        consents
+            .grant("did:peer:alice", "analytics", scope.clone(), None)
+            .unwrap();
+        consents
+            .grant("did:peer:alice", "marketing", scope, None)
+            .unwrap();
+        assert!(consents
+            .check_consent("did:peer:alice", "analytics")
+            .unwrap());
+        assert!(!consents.check_consent("did:peer:bob", "analytics").unwrap());
+
+        let mut withdrawals = consents.subscribe_withdrawals();
+        let outcome = consents
+            .withdraw("did:peer:alice", "analytics")
+            .await
+            .unwrap();
+        assert_eq!(outcome.notified, 1);
+        assert!(outcome.deletion.is_none());
+        assert_eq!(outcome.record.scope, vec![DataCategory::PersonalData]);
+        assert_eq!(withdrawals.try_recv().unwrap(), outcome.record);
+
+        assert!(matches!(
+            consents.require_consent("did:peer:alice", "analytics"),
+            Err(PrivacyError::ConsentRequired(_))
+        ));
+        let active = consents.active_consents("did:peer:alice").unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].purpose, "marketing");
+        assert_eq!(consents.history("did:peer:alice").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_expiry() {
+        let consents = manager();
+        let record = consents
+            .grant("did:peer:alice", "analytics", vec![], Some(2_000_000_000))
+            .unwrap();
+        assert!(consents
+            .check_consent_at("did:peer:alice", "analytics", 1_999_999_999)
+            .unwrap());
+        assert!(!consents
+            .check_consent_at("did:peer:alice", "analytics", 2_000_000_000)
+            .unwrap());
+        assert!(!record.is_active_at(2_000_000_001));
+    }
+
+    #[test]
+    fn test_ledgers_merge() {
+        let consents = manager();
+        let peer = ConsentManager::new(
+            DocumentStore::new()
+                .load(consents.ledger().id.clone(), &consents.ledger().save())
+                .unwrap(),
+        );
+
+        consents
+            .grant("did:peer:alice", "analytics", vec![], None)
+            .unwrap();
+        peer.grant("did:peer:bob", "analytics", vec![], None)
+            .unwrap();
+
+        consents
+            .ledger()
+            .load_incremental(&peer.ledger().save())
+            .unwrap();
+        assert_eq!(consents.records().unwrap().len(), 2);
+        assert!(consents.check_consent("did:peer:bob", "analytics").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_withdrawal_erases_data() {
+        let engine = Arc::new(GdprComplianceEngine::new().unwrap());
+        engine.crypto().generate_dek("did:peer:alice").unwrap();
+
+        let consents = manager().with_engine(Arc::clone(&engine));
+        consents.erase_on_withdrawal(
+            "profile",
+            DeletionRequest::personal_only("app.example".to_string()),
+        );
+        consents
+            .grant(
+                "did:peer:alice",
+                "profile",
+                vec![DataCategory::PersonalData],
+                None,
+            )
+            .unwrap();
+
+        let outcome = consents
+            .withdraw("did:peer:alice", "profile")
+            .await
+            .unwrap();
+        assert!(outcome.deletion.unwrap().crypto_proof.is_some());
+        assert!(engine.is_deleted("did:peer:alice"));
+    }
+}
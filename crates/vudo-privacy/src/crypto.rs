@@ -22,6 +22,10 @@
 //! - 256-bit keys for strong security
 //! - Nonces are generated randomly per encryption
 //! - Key deletion uses secure memory zeroing (zeroize crate)
+//! - DEKs can be rotated: reachable ciphertext is re-encrypted with a new key
+//!   and the old key is destroyed
+//! - With key escrow enabled, DEKs are also wrapped to a recovery DID for
+//!   legal hold (see [`crate::escrow`])
 //!
 //! # Example
 //!
@@ -54,17 +58,29 @@
 //! # }
 //! ```
 
+use crate::access::{AccessContext, ReadAudit};
+use crate::audit::DataCategory;
 use crate::error::{PrivacyError, Result};
+use crate::escrow::KeyEscrow;
 use chacha20poly1305::{
     aead::{Aead, AeadCore, KeyInit, OsRng},
     ChaCha20Poly1305, Key, Nonce,
 };
 use chrono::Utc;
 use dashmap::DashMap;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use vudo_identity::{Did, TimestampCommittee, TimestampRequest, TimestampToken};
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
+/// Key derivation context of DEK key commitments and IDs.
+const KEY_ID_CONTEXT: &str = "vudo-privacy 2026-10 DEK key id";
+
+/// Domain separator of deletion receipt signatures.
+const RECEIPT_DOMAIN: &[u8] = b"vudo-privacy deletion receipt v1";
+
 /// Data Encryption Key (DEK) for personal data.
 ///
 /// Each user has a unique DEK that encrypts their personal data fields.
@@ -86,6 +102,10 @@ pub struct DataEncryptionKey {
 
     /// Deletion timestamp (Unix seconds, if deleted).
     pub deleted_at: Option<u64>,
+
+    /// Commitment to the destroyed key material (set on deletion).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<String>,
 }
 
 /// Helper module for serializing byte arrays.
@@ -122,9 +142,28 @@ impl DataEncryptionKey {
             created_at: Utc::now().timestamp() as u64,
             deleted: false,
             deleted_at: None,
+            commitment: None,
+        }
+    }
+
+    /// Get the commitment to the key material (BLAKE3, hex).
+    ///
+    /// It identifies the key without revealing anything about it. A deleted
+    /// DEK keeps the commitment to its destroyed key.
+    pub fn key_commitment(&self) -> String {
+        match &self.commitment {
+            Some(commitment) => commitment.clone(),
+            None => hex::encode(blake3::derive_key(KEY_ID_CONTEXT, &self.key)),
         }
     }
 
+    /// Get the key ID of this DEK.
+    ///
+    /// A short prefix of the key commitment, for audit records.
+    pub fn key_id(&self) -> String {
+        self.key_commitment()[..16].to_string()
+    }
+
     /// Check if this DEK has been deleted.
     pub fn is_deleted(&self) -> bool {
         self.deleted
@@ -132,6 +171,7 @@ impl DataEncryptionKey {
 
     /// Mark this DEK as deleted (cryptographic erasure).
     pub fn mark_deleted(&mut self) {
+        self.commitment = Some(self.key_commitment());
         self.deleted = true;
         self.deleted_at = Some(Utc::now().timestamp() as u64);
         // Securely zero out key material
@@ -179,6 +219,10 @@ mod serde_bytes_nonce {
 }
 
 /// Deletion receipt proving cryptographic erasure.
+///
+/// Signed by the controller, it is a statement that third parties can
+/// verify later: which subject's data, of what scope, was erased when, by
+/// destroying which key.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeletionReceipt {
     /// Owner DID.
@@ -187,8 +231,161 @@ pub struct DeletionReceipt {
     /// Deletion timestamp (Unix seconds).
     pub deleted_at: u64,
 
-    /// Irreversibility flag (always true for cryptographic deletion).
+    /// Irreversibility flag (false while an escrowed copy of the DEK is kept
+    /// under a legal hold).
     pub irreversible: bool,
+
+    /// Data categories erased.
+    #[serde(default = "personal_data_scope")]
+    pub scope: Vec<DataCategory>,
+
+    /// Commitment to the destroyed DEK (see
+    /// [`DataEncryptionKey::key_commitment`]).
+    #[serde(default)]
+    pub key_commitment: String,
+
+    /// Committee timestamp of the deletion (optional).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<TimestampToken>,
+
+    /// DID of the signing controller (did:key).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+
+    /// Ed25519 signature of the deletion statement (hex).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// Scope of receipts issued before receipts recorded one.
+fn personal_data_scope() -> Vec<DataCategory> {
+    vec![DataCategory::PersonalData]
+}
+
+impl DeletionReceipt {
+    /// Create a receipt for the deletion of a DEK.
+    fn new(dek: &DataEncryptionKey, deleted_at: u64, irreversible: bool) -> Self {
+        Self {
+            owner: dek.owner.clone(),
+            deleted_at,
+            irreversible,
+            scope: personal_data_scope(),
+            key_commitment: dek.key_commitment(),
+            timestamp: None,
+            signer: None,
+            signature: None,
+        }
+    }
+
+    /// Sign the deletion statement with the controller's key.
+    ///
+    /// The receipt names the key as a did:key.
+    pub fn sign(&mut self, key: &SigningKey) -> Result<()> {
+        self.signer = Some(Did::from_key(key.verifying_key()).to_string());
+        let signature = key.sign(&self.signing_bytes()?);
+        self.signature = Some(hex::encode(signature.to_bytes()));
+        Ok(())
+    }
+
+    /// Verify that `public_key` signed the deletion statement.
+    ///
+    /// The statement covers the subject, scope, deletion time,
+    /// irreversibility and key commitment; the committee timestamp is
+    /// checked separately with [`Self::attested_deleted_at`].
+    pub fn verify(&self, public_key: &VerifyingKey) -> Result<()> {
+        let invalid = |reason: &str| PrivacyError::InvalidProof(reason.to_string());
+
+        let signer = self
+            .signer
+            .as_deref()
+            .ok_or_else(|| invalid("Receipt is not signed"))?;
+        if Did::parse(signer)?.verification_key != *public_key {
+            return Err(invalid("Receipt was signed by another key"));
+        }
+        if self.key_commitment.is_empty() {
+            return Err(invalid("Receipt has no key commitment"));
+        }
+
+        let signature = self
+            .signature
+            .as_ref()
+            .and_then(|signature| hex::decode(signature).ok())
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+            .ok_or_else(|| invalid("Malformed receipt signature"))?;
+        public_key
+            .verify(&self.signing_bytes()?, &signature)
+            .map_err(|_| invalid("Invalid receipt signature"))
+    }
+
+    /// Check that the receipt commits to the DEK with `key_id`.
+    pub fn commits_to(&self, key_id: &str) -> bool {
+        !key_id.is_empty() && self.key_commitment.starts_with(key_id)
+    }
+
+    /// Bytes covered by the signature.
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        let statement = serde_json::json!({
+            "subject": self.owner,
+            "scope": self.scope,
+            "deleted_at": self.deleted_at,
+            "irreversible": self.irreversible,
+            "key_commitment": self.key_commitment,
+            "signer": self.signer,
+        });
+        let mut bytes = RECEIPT_DOMAIN.to_vec();
+        bytes.push(0);
+        bytes.extend_from_slice(&serde_json::to_vec(&statement)?);
+        Ok(bytes)
+    }
+
+    /// Request for a committee timestamp of this deletion.
+    pub fn timestamp_request(&self) -> TimestampRequest {
+        let message = format!("{}|{}|{}", self.owner, self.deleted_at, self.irreversible);
+        TimestampRequest::new("deletion-certificate", message.as_bytes(), self.deleted_at)
+    }
+
+    /// Attach a committee timestamp after verifying it.
+    pub fn attach_timestamp(
+        &mut self,
+        token: TimestampToken,
+        committee: &TimestampCommittee,
+    ) -> Result<()> {
+        token.verify(committee, &self.timestamp_request())?;
+        self.timestamp = Some(token);
+        Ok(())
+    }
+
+    /// Committee-agreed deletion time.
+    ///
+    /// Use this instead of `deleted_at` when the receipt comes from another
+    /// party: it can't be back-dated past the committee's clock skew. Fails if
+    /// the receipt has no timestamp or it doesn't verify against `committee`.
+    pub fn attested_deleted_at(&self, committee: &TimestampCommittee) -> Result<u64> {
+        let token = self.timestamp.as_ref().ok_or_else(|| {
+            vudo_identity::Error::Timestamp("Deletion receipt has no timestamp".to_string())
+        })?;
+        token.verify(committee, &self.timestamp_request())?;
+        Ok(token.timestamp)
+    }
+}
+
+/// Record of a DEK rotation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DekRotation {
+    /// Owner DID.
+    pub owner: String,
+
+    /// Key ID of the retired DEK.
+    pub old_key_id: String,
+
+    /// Key ID of the new DEK.
+    pub new_key_id: String,
+
+    /// Rotation timestamp (Unix seconds).
+    pub rotated_at: u64,
+
+    /// Number of values re-encrypted with the new DEK.
+    pub reencrypted: usize,
 }
 
 /// Personal data cryptography manager.
@@ -199,6 +396,12 @@ pub struct DeletionReceipt {
 pub struct PersonalDataCrypto {
     /// Key storage (DID → DEK).
     key_store: Arc<DashMap<String, DataEncryptionKey>>,
+
+    /// Key escrow (if enabled).
+    escrow: Arc<RwLock<Option<KeyEscrow>>>,
+
+    /// Read audit (if enabled).
+    read_audit: Arc<RwLock<Option<ReadAudit>>>,
 }
 
 impl PersonalDataCrypto {
@@ -206,6 +409,8 @@ impl PersonalDataCrypto {
     pub fn new() -> Self {
         Self {
             key_store: Arc::new(DashMap::new()),
+            escrow: Arc::new(RwLock::new(None)),
+            read_audit: Arc::new(RwLock::new(None)),
         }
     }
 
@@ -220,6 +425,7 @@ impl PersonalDataCrypto {
     /// A new `DataEncryptionKey` that can be used to encrypt personal data.
     pub fn generate_dek(&self, owner_did: &str) -> Result<DataEncryptionKey> {
         let dek = DataEncryptionKey::generate(owner_did.to_string());
+        self.escrow_dek(&dek)?;
         self.key_store.insert(owner_did.to_string(), dek.clone());
         Ok(dek)
     }
@@ -240,6 +446,24 @@ impl PersonalDataCrypto {
             .ok_or_else(|| PrivacyError::DekNotFound(owner_did.to_string()))
     }
 
+    /// Restore a previously generated DEK (e.g., from a local key file).
+    ///
+    /// # Arguments
+    ///
+    /// * `dek` - The data encryption key to restore
+    ///
+    /// # Returns
+    ///
+    /// An error if the DEK was deleted, since erased data must stay erased.
+    pub fn restore_dek(&self, dek: DataEncryptionKey) -> Result<()> {
+        if dek.is_deleted() {
+            return Err(PrivacyError::KeyDeleted);
+        }
+        self.escrow_dek(&dek)?;
+        self.key_store.insert(dek.owner.clone(), dek);
+        Ok(())
+    }
+
     /// Encrypt personal data.
     ///
     /// # Arguments
@@ -284,10 +508,41 @@ impl PersonalDataCrypto {
     /// # Returns
     ///
     /// The decrypted plaintext, or an error if the key was deleted or decryption failed.
+    ///
+    /// With read audit enabled, the read is recorded as unattributed; use
+    /// [`Self::decrypt_field_as`] to name the reader.
     pub fn decrypt_field(
         &self,
         dek: &DataEncryptionKey,
         encrypted: &EncryptedField,
+    ) -> Result<Vec<u8>> {
+        let plaintext = self.decrypt_unaudited(dek, encrypted)?;
+        self.audit_read(&dek.owner, None, None, &AccessContext::unattributed());
+        Ok(plaintext)
+    }
+
+    /// Decrypt a personal field on behalf of a reader.
+    ///
+    /// With read audit enabled, the read of `field` is recorded with the
+    /// reader's device DID and capability.
+    pub fn decrypt_field_as(
+        &self,
+        dek: &DataEncryptionKey,
+        encrypted: &EncryptedField,
+        field: &str,
+        context: &AccessContext,
+    ) -> Result<Vec<u8>> {
+        let plaintext = self.decrypt_unaudited(dek, encrypted)?;
+        self.audit_read(&dek.owner, None, Some(field), context);
+        Ok(plaintext)
+    }
+
+    /// Decrypt personal data without recording the read (e.g. to re-encrypt
+    /// it).
+    pub(crate) fn decrypt_unaudited(
+        &self,
+        dek: &DataEncryptionKey,
+        encrypted: &EncryptedField,
     ) -> Result<Vec<u8>> {
         if dek.deleted {
             return Err(PrivacyError::DataPermanentlyErased);
@@ -323,16 +578,219 @@ impl PersonalDataCrypto {
             // Mark as deleted and zero out key material
             dek.mark_deleted();
 
-            Ok(DeletionReceipt {
-                owner: owner_did.to_string(),
-                deleted_at: dek.deleted_at.unwrap(),
-                irreversible: true,
-            })
+            // Escrowed copies go too, unless held
+            let held = match self.escrow() {
+                Some(escrow) if escrow.is_held(owner_did) => true,
+                Some(escrow) => {
+                    escrow.destroy(owner_did);
+                    false
+                }
+                None => false,
+            };
+
+            Ok(DeletionReceipt::new(dek, dek.deleted_at.unwrap(), !held))
         } else {
             Err(PrivacyError::DekNotFound(owner_did.to_string()))
         }
     }
 
+    /// Rotate a user's DEK, re-encrypting `fields` with the new key.
+    ///
+    /// The fields are only updated if they all re-encrypt. The old DEK is
+    /// then destroyed: ciphertext that wasn't re-encrypted (e.g. in CRDT
+    /// history) is erased with it.
+    pub fn rotate_dek(
+        &self,
+        owner_did: &str,
+        fields: &mut [EncryptedField],
+    ) -> Result<DekRotation> {
+        self.rotate_dek_with(owner_did, |old, new| {
+            let reencrypted = fields
+                .iter()
+                .map(|field| self.reencrypt_field(old, new, field))
+                .collect::<Result<Vec<_>>>()?;
+            let count = reencrypted.len();
+            for (field, reencrypted) in fields.iter_mut().zip(reencrypted) {
+                *field = reencrypted;
+            }
+            Ok(count)
+        })
+    }
+
+    /// Rotate a user's DEK, re-encrypting data with `reencrypt`.
+    ///
+    /// `reencrypt` is given the old and new DEK and returns the number of
+    /// values it re-encrypted. If it fails, the old DEK stays in place. Stop
+    /// writes of the user's data while it runs: values encrypted with the
+    /// old DEK in the meantime are erased with it.
+    pub fn rotate_dek_with<F>(&self, owner_did: &str, reencrypt: F) -> Result<DekRotation>
+    where
+        F: FnOnce(&DataEncryptionKey, &DataEncryptionKey) -> Result<usize>,
+    {
+        let old = self.get_dek(owner_did)?;
+        if old.is_deleted() {
+            return Err(PrivacyError::KeyDeleted);
+        }
+
+        // Escrow the new DEK before any data depends on it
+        let new = DataEncryptionKey::generate(owner_did.to_string());
+        let escrow = self.escrow();
+        if let Some(escrow) = &escrow {
+            escrow.escrow(&new)?;
+        }
+        let reencrypted = match reencrypt(&old, &new) {
+            Ok(reencrypted) => reencrypted,
+            Err(e) => {
+                if let Some(escrow) = &escrow {
+                    escrow.discard(owner_did, &new.key_id());
+                }
+                return Err(e);
+            }
+        };
+        if let Some(escrow) = &escrow {
+            escrow.prune(owner_did, &new.key_id());
+        }
+
+        let rotation = DekRotation {
+            owner: owner_did.to_string(),
+            old_key_id: old.key_id(),
+            new_key_id: new.key_id(),
+            rotated_at: new.created_at,
+            reencrypted,
+        };
+        // Dropping the old DEK zeroes it
+        self.key_store.insert(owner_did.to_string(), new);
+        Ok(rotation)
+    }
+
+    /// Re-encrypt a field from one DEK to another.
+    pub fn reencrypt_field(
+        &self,
+        old: &DataEncryptionKey,
+        new: &DataEncryptionKey,
+        field: &EncryptedField,
+    ) -> Result<EncryptedField> {
+        let plaintext = zeroize::Zeroizing::new(self.decrypt_unaudited(old, field)?);
+        self.encrypt_field(new, &plaintext)
+    }
+
+    /// Enable key escrow to a recovery DID.
+    ///
+    /// Existing DEKs are escrowed right away, and new ones as they are
+    /// generated, restored or rotated.
+    pub fn enable_escrow(&self, recovery: Did) -> Result<KeyEscrow> {
+        let mut slot = self.escrow.write();
+        if slot.is_some() {
+            return Err(PrivacyError::EscrowError(
+                "Key escrow is already enabled".to_string(),
+            ));
+        }
+
+        let escrow = KeyEscrow::new(recovery);
+        for entry in self.key_store.iter() {
+            if !entry.value().is_deleted() {
+                escrow.escrow(entry.value())?;
+            }
+        }
+        *slot = Some(escrow.clone());
+        Ok(escrow)
+    }
+
+    /// Get the key escrow, if enabled.
+    pub fn escrow(&self) -> Option<KeyEscrow> {
+        self.escrow.read().clone()
+    }
+
+    /// Place a legal hold on a user's escrowed DEKs.
+    ///
+    /// While the hold lasts, deleting or rotating the DEK keeps its escrowed
+    /// copies.
+    pub fn place_legal_hold(&self, owner_did: &str, reason: impl Into<String>) -> Result<()> {
+        let escrow = self.require_escrow()?;
+        if escrow.escrowed(owner_did).is_empty() {
+            return Err(PrivacyError::EscrowError(format!(
+                "No escrowed DEK for {}",
+                owner_did
+            )));
+        }
+        escrow.place_hold(owner_did, reason);
+        Ok(())
+    }
+
+    /// Release the legal hold on a user's escrowed DEKs.
+    ///
+    /// Escrowed copies of retired DEKs are destroyed. If the DEK itself was
+    /// deleted under the hold, its copy is destroyed too and the returned
+    /// receipt records the now irreversible erasure.
+    pub fn release_legal_hold(&self, owner_did: &str) -> Result<Option<DeletionReceipt>> {
+        let escrow = self.require_escrow()?;
+        if !escrow.is_held(owner_did) {
+            return Err(PrivacyError::EscrowError(format!(
+                "No legal hold on {}",
+                owner_did
+            )));
+        }
+        let dek = self.get_dek(owner_did)?;
+        escrow.release_hold(owner_did);
+
+        if !dek.is_deleted() {
+            escrow.prune(owner_did, &dek.key_id());
+            return Ok(None);
+        }
+        escrow.destroy(owner_did);
+        let deleted_at = Utc::now().timestamp() as u64;
+        Ok(Some(DeletionReceipt::new(&dek, deleted_at, true)))
+    }
+
+    /// Check if a user's escrowed DEKs are under a legal hold.
+    pub fn is_under_legal_hold(&self, owner_did: &str) -> bool {
+        self.escrow()
+            .is_some_and(|escrow| escrow.is_held(owner_did))
+    }
+
+    /// Escrow a DEK if escrow is enabled, replacing the owner's earlier one.
+    fn escrow_dek(&self, dek: &DataEncryptionKey) -> Result<()> {
+        if let Some(escrow) = self.escrow() {
+            escrow.escrow(dek)?;
+            escrow.prune(&dek.owner, &dek.key_id());
+        }
+        Ok(())
+    }
+
+    /// Get the key escrow, failing if it isn't enabled.
+    fn require_escrow(&self) -> Result<KeyEscrow> {
+        self.escrow()
+            .ok_or_else(|| PrivacyError::EscrowError("Key escrow is not enabled".to_string()))
+    }
+
+    /// Enable read audit, replacing any enabled before.
+    pub fn enable_read_audit(&self, audit: ReadAudit) {
+        *self.read_audit.write() = Some(audit);
+    }
+
+    /// Disable read audit, returning it.
+    pub fn disable_read_audit(&self) -> Option<ReadAudit> {
+        self.read_audit.write().take()
+    }
+
+    /// Get the read audit, if enabled.
+    pub fn read_audit(&self) -> Option<ReadAudit> {
+        self.read_audit.read().clone()
+    }
+
+    /// Record a read of personal data, if read audit is enabled.
+    pub(crate) fn audit_read(
+        &self,
+        owner: &str,
+        document: Option<&str>,
+        field: Option<&str>,
+        context: &AccessContext,
+    ) {
+        if let Some(audit) = self.read_audit.read().as_ref() {
+            audit.record(owner, document, field, context);
+        }
+    }
+
     /// Check if a DEK exists for a user.
     pub fn has_dek(&self, owner_did: &str) -> bool {
         self.key_store.contains_key(owner_did)
@@ -353,6 +811,7 @@ impl Default for PersonalDataCrypto {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use vudo_identity::{DeviceIdentity, TimestampSigner};
 
     #[test]
     fn test_dek_generation() {
@@ -396,6 +855,195 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_signed_receipt() {
+        use rand::rngs::OsRng;
+
+        let controller = SigningKey::generate(&mut OsRng);
+        let crypto = PersonalDataCrypto::new();
+        let dek = crypto.generate_dek("did:peer:alice").unwrap();
+        let mut receipt = crypto.delete_dek("did:peer:alice").unwrap();
+        assert!(receipt.commits_to(&dek.key_id()));
+        assert_eq!(receipt.scope, vec![DataCategory::PersonalData]);
+        assert!(receipt.verify(&controller.verifying_key()).is_err());
+
+        receipt.sign(&controller).unwrap();
+        receipt.verify(&controller.verifying_key()).unwrap();
+
+        // Survives export, and is bound to the signer and the statement
+        let receipt: DeletionReceipt =
+            serde_json::from_str(&serde_json::to_string(&receipt).unwrap()).unwrap();
+        receipt.verify(&controller.verifying_key()).unwrap();
+        let other = SigningKey::generate(&mut OsRng);
+        assert!(receipt.verify(&other.verifying_key()).is_err());
+
+        let mut backdated = receipt.clone();
+        backdated.deleted_at -= 24 * 60 * 60;
+        assert!(backdated.verify(&controller.verifying_key()).is_err());
+        let mut other_key = receipt.clone();
+        other_key.key_commitment = crypto
+            .generate_dek("did:peer:bob")
+            .unwrap()
+            .key_commitment();
+        assert!(other_key.verify(&controller.verifying_key()).is_err());
+    }
+
+    #[test]
+    fn test_restore_dek() {
+        let crypto = PersonalDataCrypto::new();
+        let dek = crypto.generate_dek("did:peer:alice").unwrap();
+        let encrypted = crypto.encrypt_field(&dek, b"alice@example.com").unwrap();
+
+        let restored = PersonalDataCrypto::new();
+        restored.restore_dek(dek).unwrap();
+        let dek = restored.get_dek("did:peer:alice").unwrap();
+        assert_eq!(
+            restored.decrypt_field(&dek, &encrypted).unwrap(),
+            b"alice@example.com"
+        );
+
+        // Erased keys can't be brought back
+        crypto.delete_dek("did:peer:alice").unwrap();
+        let deleted = crypto.get_dek("did:peer:alice").unwrap();
+        assert!(PersonalDataCrypto::new().restore_dek(deleted).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_deletion_receipt_timestamp() {
+        let mut signers = Vec::new();
+        for i in 0..4 {
+            let device = DeviceIdentity::generate(format!("notary-{}", i))
+                .await
+                .unwrap();
+            signers.push(TimestampSigner::new(device.did().clone(), device.signing_key()).unwrap());
+        }
+        let committee =
+            TimestampCommittee::new(signers.iter().map(|s| s.did().clone()).collect()).unwrap();
+
+        let crypto = PersonalDataCrypto::new();
+        crypto.generate_dek("did:peer:alice").unwrap();
+        let mut receipt = crypto.delete_dek("did:peer:alice").unwrap();
+        assert!(receipt.attested_deleted_at(&committee).is_err());
+
+        let request = receipt.timestamp_request();
+        let signatures = signers
+            .iter()
+            .map(|s| s.co_sign(&request).unwrap())
+            .collect();
+        let token = committee.assemble(&request, signatures).unwrap();
+
+        // A token for the receipt doesn't fit a back-dated copy of it
+        let mut backdated = receipt.clone();
+        backdated.deleted_at -= 24 * 60 * 60;
+        assert!(backdated
+            .attach_timestamp(token.clone(), &committee)
+            .is_err());
+
+        receipt.attach_timestamp(token.clone(), &committee).unwrap();
+        assert_eq!(
+            receipt.attested_deleted_at(&committee).unwrap(),
+            token.timestamp
+        );
+    }
+
+    #[test]
+    fn test_rotate_dek() {
+        let crypto = PersonalDataCrypto::new();
+        let old = crypto.generate_dek("did:peer:alice").unwrap();
+        let mut fields = vec![
+            crypto.encrypt_field(&old, b"alice@example.com").unwrap(),
+            crypto.encrypt_field(&old, b"Alice").unwrap(),
+        ];
+        let stale = fields[0].clone();
+
+        let rotation = crypto.rotate_dek("did:peer:alice", &mut fields).unwrap();
+        assert_eq!(rotation.old_key_id, old.key_id());
+        assert_eq!(rotation.reencrypted, 2);
+
+        let new = crypto.get_dek("did:peer:alice").unwrap();
+        assert_eq!(new.key_id(), rotation.new_key_id);
+        assert_eq!(
+            crypto.decrypt_field(&new, &fields[0]).unwrap(),
+            b"alice@example.com"
+        );
+        assert!(crypto.decrypt_field(&new, &stale).is_err());
+    }
+
+    #[test]
+    fn test_failed_rotation_keeps_dek() {
+        let crypto = PersonalDataCrypto::new();
+        let old = crypto.generate_dek("did:peer:alice").unwrap();
+        let other = DataEncryptionKey::generate("did:peer:bob".to_string());
+        let mut fields = vec![
+            crypto.encrypt_field(&old, b"alice@example.com").unwrap(),
+            crypto.encrypt_field(&other, b"bob@example.com").unwrap(),
+        ];
+        let before = fields[0].ciphertext.clone();
+
+        assert!(crypto.rotate_dek("did:peer:alice", &mut fields).is_err());
+        assert_eq!(fields[0].ciphertext, before);
+        assert_eq!(crypto.get_dek("did:peer:alice").unwrap().key, old.key);
+
+        crypto.delete_dek("did:peer:alice").unwrap();
+        assert!(matches!(
+            crypto.rotate_dek("did:peer:alice", &mut []),
+            Err(PrivacyError::KeyDeleted)
+        ));
+    }
+
+    #[test]
+    fn test_escrow_follows_rotation_and_deletion() {
+        use rand::rngs::OsRng;
+        use x25519_dalek::{PublicKey, StaticSecret};
+
+        let secret = StaticSecret::random_from_rng(OsRng);
+        let signing = ed25519_dalek::SigningKey::generate(&mut OsRng);
+        let recovery = Did::from_keys(signing.verifying_key(), &PublicKey::from(&secret)).unwrap();
+
+        let crypto = PersonalDataCrypto::new();
+        crypto.generate_dek("did:peer:alice").unwrap();
+        crypto.generate_dek("did:peer:bob").unwrap();
+        let escrow = crypto.enable_escrow(recovery.clone()).unwrap();
+        assert!(crypto.enable_escrow(recovery).is_err());
+        assert!(crypto.place_legal_hold("did:peer:carol", "Case 1").is_err());
+
+        // Without a hold, only the current DEK is escrowed, and deletion
+        // destroys it
+        let rotation = crypto.rotate_dek("did:peer:bob", &mut []).unwrap();
+        let escrowed = escrow.escrowed("did:peer:bob");
+        assert_eq!(escrowed.len(), 1);
+        assert_eq!(escrowed[0].key_id, rotation.new_key_id);
+        assert!(crypto.delete_dek("did:peer:bob").unwrap().irreversible);
+        assert!(escrow.escrowed("did:peer:bob").is_empty());
+
+        // Under a hold, retired and deleted DEKs stay recoverable
+        let old = crypto.get_dek("did:peer:alice").unwrap();
+        let mut fields = vec![crypto.encrypt_field(&old, b"alice@example.com").unwrap()];
+        let stale = fields[0].clone();
+        crypto.place_legal_hold("did:peer:alice", "Case 1").unwrap();
+        crypto.rotate_dek("did:peer:alice", &mut fields).unwrap();
+        assert!(!crypto.delete_dek("did:peer:alice").unwrap().irreversible);
+
+        let recovered = escrow.recover("did:peer:alice", &secret).unwrap();
+        assert_eq!(recovered.len(), 2);
+        assert_eq!(
+            crypto.decrypt_field(&recovered[0], &stale).unwrap(),
+            b"alice@example.com"
+        );
+        assert_eq!(
+            crypto.decrypt_field(&recovered[1], &fields[0]).unwrap(),
+            b"alice@example.com"
+        );
+
+        let receipt = crypto
+            .release_legal_hold("did:peer:alice")
+            .unwrap()
+            .unwrap();
+        assert!(receipt.irreversible);
+        assert!(escrow.escrowed("did:peer:alice").is_empty());
+        assert!(crypto.release_legal_hold("did:peer:alice").is_err());
+    }
+
     #[test]
     fn test_encrypt_with_deleted_key() {
         let crypto = PersonalDataCrypto::new();
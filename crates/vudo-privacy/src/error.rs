@@ -44,6 +44,30 @@ pub enum PrivacyError {
     #[error("GDPR deletion request failed: {0}")]
     GdprDeletionFailed(String),
 
+    /// Data export failed or its manifest is invalid.
+    #[error("Data export error: {0}")]
+    ExportError(String),
+
+    /// Key escrow operation failed.
+    #[error("Key escrow error: {0}")]
+    EscrowError(String),
+
+    /// Compliance profile is not registered.
+    #[error("Unknown compliance profile: {0}")]
+    UnknownProfile(String),
+
+    /// Anonymization policy is invalid.
+    #[error("Anonymization error: {0}")]
+    AnonymizationError(String),
+
+    /// Processing lacks the subject's consent.
+    #[error("Consent required: {0}")]
+    ConsentRequired(String),
+
+    /// Deletion proof is missing or invalid.
+    #[error("Invalid deletion proof: {0}")]
+    InvalidProof(String),
+
     /// Willow adapter error.
     #[error("Willow adapter error: {0}")]
     WillowError(String),
@@ -52,6 +76,14 @@ pub enum PrivacyError {
     #[error("Serialization error: {0}")]
     SerializationError(String),
 
+    /// State engine error.
+    #[error("State error: {0}")]
+    StateError(#[from] vudo_state::StateError),
+
+    /// Identity error.
+    #[error("Identity error: {0}")]
+    IdentityError(#[from] vudo_identity::Error),
+
     /// UTF-8 conversion error.
     #[error("UTF-8 conversion error: {0}")]
     Utf8Error(#[from] std::string::FromUtf8Error),
@@ -0,0 +1,317 @@
+//! Key escrow of data encryption keys for legal hold.
+//!
+//! Enterprises may have to preserve personal data under a legal hold (GDPR
+//! Article 17(3)(e)) while still honouring erasure requests everywhere else.
+//! With escrow enabled on a [`PersonalDataCrypto`], every DEK is also wrapped
+//! to a recovery DID (a sealed box only its X25519 secret opens) and kept as
+//! an [`EscrowedDek`].
+//!
+//! Escrow does not weaken cryptographic deletion:
+//!
+//! - Deleting a DEK also destroys its escrowed copies, unless the owner is
+//!   under a legal hold.
+//! - Under a hold, the live DEK is still deleted (peers and the application
+//!   can no longer read the data), but the escrowed copies survive and the
+//!   deletion receipt is marked reversible.
+//! - Releasing the hold destroys the escrowed copies, completing the erasure.
+//!
+//! [`PersonalDataCrypto`]: crate::crypto::PersonalDataCrypto
+//!
+//! # Example
+//!
+//! ```rust
+//! use rand::rngs::OsRng;
+//! use vudo_identity::Did;
+//! use vudo_privacy::crypto::PersonalDataCrypto;
+//! use x25519_dalek::{PublicKey, StaticSecret};
+//!
+//! # fn example() -> vudo_privacy::error::Result<()> {
+//! // Recovery identity held by the enterprise
+//! let signing = ed25519_dalek::SigningKey::generate(&mut OsRng);
+//! let secret = StaticSecret::random_from_rng(OsRng);
+//! let recovery = Did::from_keys(signing.verifying_key(), &PublicKey::from(&secret))?;
+//!
+//! let crypto = PersonalDataCrypto::new();
+//! let escrow = crypto.enable_escrow(recovery)?;
+//! let dek = crypto.generate_dek("did:peer:alice")?;
+//! let encrypted = crypto.encrypt_field(&dek, b"alice@example.com")?;
+//!
+//! // Legal hold, then an erasure request
+//! crypto.place_legal_hold("did:peer:alice", "Case 2026-17")?;
+//! let receipt = crypto.delete_dek("did:peer:alice")?;
+//! assert!(!receipt.irreversible);
+//!
+//! // The recovery key still opens the data
+//! let recovered = escrow.recover("did:peer:alice", &secret)?;
+//! let dek = recovered.last().unwrap();
+//! assert_eq!(crypto.decrypt_field(dek, &encrypted)?, b"alice@example.com");
+//!
+//! // Releasing the hold completes the erasure
+//! let receipt = crypto.release_legal_hold("did:peer:alice")?.unwrap();
+//! assert!(receipt.irreversible);
+//! assert!(escrow.recover("did:peer:alice", &secret).is_err());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::crypto::DataEncryptionKey;
+use crate::error::{PrivacyError, Result};
+use chrono::Utc;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use vudo_identity::Did;
+use x25519_dalek::StaticSecret;
+use zeroize::Zeroizing;
+
+/// DEK wrapped to a recovery DID.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscrowedDek {
+    /// Owner DID.
+    pub owner: String,
+
+    /// Key ID of the wrapped DEK.
+    pub key_id: String,
+
+    /// Recovery DID the DEK is wrapped to.
+    pub recovery: String,
+
+    /// Sealed box of the DEK.
+    pub sealed: Vec<u8>,
+
+    /// Escrow timestamp (Unix seconds).
+    pub escrowed_at: u64,
+}
+
+/// Legal hold on a data owner's escrowed DEKs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LegalHold {
+    /// Owner DID.
+    pub owner: String,
+
+    /// Reason for the hold (e.g. a case reference).
+    pub reason: String,
+
+    /// Hold timestamp (Unix seconds).
+    pub placed_at: u64,
+}
+
+/// Escrow of DEKs wrapped to a recovery DID.
+#[derive(Clone)]
+pub struct KeyEscrow {
+    /// Recovery DID.
+    recovery: Did,
+
+    /// Escrowed DEKs (owner DID → DEKs, oldest first).
+    wrapped: Arc<DashMap<String, Vec<EscrowedDek>>>,
+
+    /// Legal holds (owner DID → hold).
+    holds: Arc<DashMap<String, LegalHold>>,
+}
+
+impl KeyEscrow {
+    /// Create an escrow wrapping DEKs to `recovery`.
+    pub fn new(recovery: Did) -> Self {
+        Self {
+            recovery,
+            wrapped: Arc::new(DashMap::new()),
+            holds: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Get the recovery DID.
+    pub fn recovery(&self) -> &Did {
+        &self.recovery
+    }
+
+    /// Wrap a DEK to the recovery DID and keep it.
+    ///
+    /// Escrowing a DEK twice is a no-op.
+    pub fn escrow(&self, dek: &DataEncryptionKey) -> Result<EscrowedDek> {
+        if dek.is_deleted() {
+            return Err(PrivacyError::KeyDeleted);
+        }
+
+        let key_id = dek.key_id();
+        if let Some(existing) = self
+            .wrapped
+            .get(&dek.owner)
+            .and_then(|deks| deks.iter().find(|e| e.key_id == key_id).cloned())
+        {
+            return Ok(existing);
+        }
+
+        let plaintext = Zeroizing::new(serde_json::to_vec(dek)?);
+        let escrowed = EscrowedDek {
+            owner: dek.owner.clone(),
+            key_id,
+            recovery: self.recovery.to_string(),
+            sealed: self.recovery.encrypt_to(&plaintext)?,
+            escrowed_at: Utc::now().timestamp() as u64,
+        };
+
+        self.wrapped
+            .entry(dek.owner.clone())
+            .or_default()
+            .push(escrowed.clone());
+        Ok(escrowed)
+    }
+
+    /// Get the escrowed DEKs of an owner, oldest first.
+    pub fn escrowed(&self, owner_did: &str) -> Vec<EscrowedDek> {
+        self.wrapped
+            .get(owner_did)
+            .map(|deks| deks.clone())
+            .unwrap_or_default()
+    }
+
+    /// Unwrap the escrowed DEKs of an owner with the recovery secret.
+    ///
+    /// The DEKs are returned oldest first; the last one is the current DEK
+    /// unless it was rotated or deleted since. They are meant for reading
+    /// held data, not for restoring into a live key store.
+    pub fn recover(
+        &self,
+        owner_did: &str,
+        secret: &StaticSecret,
+    ) -> Result<Vec<DataEncryptionKey>> {
+        let escrowed = self.escrowed(owner_did);
+        if escrowed.is_empty() {
+            return Err(PrivacyError::EscrowError(format!(
+                "No escrowed DEK for {}",
+                owner_did
+            )));
+        }
+
+        escrowed
+            .iter()
+            .map(|escrowed| {
+                let plaintext =
+                    Zeroizing::new(self.recovery.decrypt_from(&escrowed.sealed, secret)?);
+                let dek: DataEncryptionKey = serde_json::from_slice(&plaintext)?;
+                if dek.owner != owner_did || dek.key_id() != escrowed.key_id {
+                    return Err(PrivacyError::EscrowError(format!(
+                        "Escrowed DEK {} does not match its record",
+                        escrowed.key_id
+                    )));
+                }
+                Ok(dek)
+            })
+            .collect()
+    }
+
+    /// Place a legal hold on an owner's escrowed DEKs.
+    pub fn place_hold(&self, owner_did: &str, reason: impl Into<String>) {
+        self.holds.insert(
+            owner_did.to_string(),
+            LegalHold {
+                owner: owner_did.to_string(),
+                reason: reason.into(),
+                placed_at: Utc::now().timestamp() as u64,
+            },
+        );
+    }
+
+    /// Release the legal hold on an owner, returning it.
+    pub fn release_hold(&self, owner_did: &str) -> Option<LegalHold> {
+        self.holds.remove(owner_did).map(|(_, hold)| hold)
+    }
+
+    /// Get the legal hold on an owner.
+    pub fn hold(&self, owner_did: &str) -> Option<LegalHold> {
+        self.holds.get(owner_did).map(|hold| hold.clone())
+    }
+
+    /// Check if an owner is under a legal hold.
+    pub fn is_held(&self, owner_did: &str) -> bool {
+        self.holds.contains_key(owner_did)
+    }
+
+    /// Destroy the escrowed DEKs of an owner, returning how many there were.
+    pub(crate) fn destroy(&self, owner_did: &str) -> usize {
+        self.wrapped
+            .remove(owner_did)
+            .map_or(0, |(_, deks)| deks.len())
+    }
+
+    /// Destroy the escrowed DEK of an owner with `key_id`.
+    pub(crate) fn discard(&self, owner_did: &str, key_id: &str) {
+        if let Some(mut deks) = self.wrapped.get_mut(owner_did) {
+            deks.retain(|e| e.key_id != key_id);
+        }
+    }
+
+    /// Destroy the escrowed DEKs of an owner other than `key_id`, unless
+    /// the owner is under a legal hold.
+    pub(crate) fn prune(&self, owner_did: &str, key_id: &str) {
+        if self.is_held(owner_did) {
+            return;
+        }
+        if let Some(mut deks) = self.wrapped.get_mut(owner_did) {
+            deks.retain(|e| e.key_id == key_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+    use x25519_dalek::PublicKey;
+
+    fn recovery() -> (Did, StaticSecret) {
+        let signing = ed25519_dalek::SigningKey::generate(&mut OsRng);
+        let secret = StaticSecret::random_from_rng(OsRng);
+        let did = Did::from_keys(signing.verifying_key(), &PublicKey::from(&secret)).unwrap();
+        (did, secret)
+    }
+
+    #[test]
+    fn test_escrow_and_recover() {
+        let (did, secret) = recovery();
+        let escrow = KeyEscrow::new(did);
+        let dek = DataEncryptionKey::generate("did:peer:alice".to_string());
+
+        let escrowed = escrow.escrow(&dek).unwrap();
+        assert_eq!(escrowed.key_id, dek.key_id());
+        assert_eq!(escrow.escrow(&dek).unwrap(), escrowed);
+        assert!(!escrowed.sealed.windows(32).any(|w| w == dek.key));
+
+        let recovered = escrow.recover("did:peer:alice", &secret).unwrap();
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].key, dek.key);
+
+        // Only the recovery secret opens it
+        let (_, other) = recovery();
+        assert!(escrow.recover("did:peer:alice", &other).is_err());
+    }
+
+    #[test]
+    fn test_hold_keeps_earlier_deks() {
+        let (did, secret) = recovery();
+        let escrow = KeyEscrow::new(did);
+        let first = DataEncryptionKey::generate("did:peer:alice".to_string());
+        let second = DataEncryptionKey::generate("did:peer:alice".to_string());
+        let third = DataEncryptionKey::generate("did:peer:alice".to_string());
+
+        escrow.escrow(&first).unwrap();
+        escrow.escrow(&second).unwrap();
+        escrow.prune("did:peer:alice", &second.key_id());
+        assert_eq!(escrow.escrowed("did:peer:alice").len(), 1);
+
+        escrow.place_hold("did:peer:alice", "Case 1");
+        escrow.escrow(&third).unwrap();
+        escrow.prune("did:peer:alice", &third.key_id());
+        let recovered = escrow.recover("did:peer:alice", &secret).unwrap();
+        assert_eq!(recovered.len(), 2);
+        assert_eq!(recovered[0].key, second.key);
+        assert_eq!(recovered[1].key, third.key);
+
+        assert_eq!(
+            escrow.release_hold("did:peer:alice").unwrap().reason,
+            "Case 1"
+        );
+        assert_eq!(escrow.destroy("did:peer:alice"), 2);
+        assert!(escrow.recover("did:peer:alice", &secret).is_err());
+    }
+}
@@ -0,0 +1,334 @@
+//! Data-subject access request (DSAR) exports.
+//!
+//! GDPR Article 15 gives data subjects the right to a copy of the personal
+//! data held about them. [`GdprComplianceEngine::export_user_data`] collects
+//! every field attributable to a subject into a [`DataExport`]: all fields of
+//! documents the subject owns (with `@personal` fields decrypted with their
+//! DEK), and fields the subject wrote elsewhere, found through their
+//! pseudonymous actor ID.
+//!
+//! The export is rendered as JSON or CSV, and an [`ExportManifest`] signed by
+//! the controller lets the subject check that the files they received are the
+//! ones that were produced.
+//!
+//! [`GdprComplianceEngine::export_user_data`]: crate::gdpr::GdprComplianceEngine::export_user_data
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use ed25519_dalek::SigningKey;
+//! use rand::rngs::OsRng;
+//! use vudo_privacy::{at_rest::DekOwner, GdprComplianceEngine, PersonalFieldInterceptor};
+//! use vudo_state::{DocumentId, DocumentStore};
+//!
+//! # fn example() -> vudo_privacy::error::Result<()> {
+//! let store = Arc::new(DocumentStore::new());
+//! let engine = GdprComplianceEngine::new()?.with_store(Arc::clone(&store));
+//! engine.crypto().generate_dek("did:peer:alice")?;
+//!
+//! let interceptor = PersonalFieldInterceptor::new(
+//!     (*engine.crypto()).clone(),
+//!     DekOwner::DocumentKey,
+//!     ["email"],
+//! );
+//! engine.register_interceptor("users", interceptor.clone());
+//!
+//! let handle = store.create(DocumentId::new("users", "did:peer:alice"))?;
+//! interceptor.update(&handle, |doc| doc.put("email", "alice@example.com"))?;
+//!
+//! let export = engine.export_user_data("did:peer:alice")?;
+//! let (json, csv) = (export.to_json()?, export.to_csv());
+//! assert!(json.contains("alice@example.com"));
+//!
+//! let manifest = export.sign(&SigningKey::generate(&mut OsRng))?;
+//! manifest.verify(&json, &csv)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{PrivacyError, Result};
+use crate::profiles::ExportFormat;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
+use serde::{Deserialize, Serialize};
+use vudo_identity::Did;
+
+/// Domain separator of manifest signatures.
+const MANIFEST_DOMAIN: &[u8] = b"vudo-privacy data export manifest v1";
+
+/// Why a field is attributed to the data subject.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Attribution {
+    /// The field is in a document the subject owns.
+    Owner,
+    /// The subject last wrote the field.
+    Author,
+}
+
+impl Attribution {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Attribution::Owner => "owner",
+            Attribution::Author => "author",
+        }
+    }
+}
+
+/// A field attributable to the data subject.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportedField {
+    /// Document ID (`namespace/key`).
+    pub document: String,
+
+    /// Top-level field name.
+    pub field: String,
+
+    /// Field is marked `@personal`.
+    pub personal: bool,
+
+    /// Why the field is attributed to the subject.
+    pub attribution: Attribution,
+
+    /// Field value (None if encrypted for another owner or erased).
+    pub value: Option<serde_json::Value>,
+}
+
+/// Export of the data held about a data subject.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataExport {
+    /// Subject DID.
+    pub subject: String,
+
+    /// Export timestamp (Unix seconds).
+    pub generated_at: u64,
+
+    /// Name of the compliance profile that governed the export.
+    #[serde(default)]
+    pub profile: String,
+
+    /// Renderings required by the profile.
+    #[serde(default)]
+    pub formats: Vec<ExportFormat>,
+
+    /// Fields attributable to the subject, by document and field.
+    pub fields: Vec<ExportedField>,
+}
+
+impl DataExport {
+    /// Get the IDs of the documents holding exported fields.
+    pub fn documents(&self) -> Vec<String> {
+        let mut documents: Vec<String> = self.fields.iter().map(|f| f.document.clone()).collect();
+        documents.sort();
+        documents.dedup();
+        documents
+    }
+
+    /// Render the export as JSON.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Render the export as CSV, one row per field.
+    ///
+    /// Values are written as JSON.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("document,field,personal,attribution,value\n");
+        for field in &self.fields {
+            let value = field
+                .value
+                .as_ref()
+                .map(|v| v.to_string())
+                .unwrap_or_default();
+            let row = [
+                csv_escape(&field.document),
+                csv_escape(&field.field),
+                field.personal.to_string(),
+                field.attribution.as_str().to_string(),
+                csv_escape(&value),
+            ];
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Render the export in a format.
+    pub fn render(&self, format: ExportFormat) -> serde_json::Result<String> {
+        match format {
+            ExportFormat::Json => self.to_json(),
+            ExportFormat::Csv => Ok(self.to_csv()),
+        }
+    }
+
+    /// Render the export in every format required by its profile.
+    pub fn renderings(&self) -> serde_json::Result<Vec<(ExportFormat, String)>> {
+        self.formats
+            .iter()
+            .map(|&format| Ok((format, self.render(format)?)))
+            .collect()
+    }
+
+    /// Sign a manifest of the JSON and CSV renderings.
+    ///
+    /// `key` is the controller's signing key; the manifest names it as a
+    /// did:key.
+    pub fn sign(&self, key: &SigningKey) -> Result<ExportManifest> {
+        let mut manifest = ExportManifest {
+            export_id: uuid::Uuid::new_v4().to_string(),
+            subject: self.subject.clone(),
+            generated_at: self.generated_at,
+            documents: self.documents(),
+            field_count: self.fields.len(),
+            json_hash: blake3::hash(self.to_json()?.as_bytes())
+                .to_hex()
+                .to_string(),
+            csv_hash: blake3::hash(self.to_csv().as_bytes()).to_hex().to_string(),
+            signer: Did::from_key(key.verifying_key()).to_string(),
+            signature: String::new(),
+        };
+        manifest.signature = hex::encode(key.sign(&manifest.signing_bytes()?).to_bytes());
+        Ok(manifest)
+    }
+}
+
+/// Signed manifest of a data export.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportManifest {
+    /// Export ID.
+    pub export_id: String,
+
+    /// Subject DID.
+    pub subject: String,
+
+    /// Export timestamp (Unix seconds).
+    pub generated_at: u64,
+
+    /// IDs of the documents holding exported fields.
+    pub documents: Vec<String>,
+
+    /// Number of exported fields.
+    pub field_count: usize,
+
+    /// BLAKE3 hash of the JSON export (hex).
+    pub json_hash: String,
+
+    /// BLAKE3 hash of the CSV export (hex).
+    pub csv_hash: String,
+
+    /// DID of the signing controller (did:key).
+    pub signer: String,
+
+    /// Ed25519 signature of the manifest (hex).
+    pub signature: String,
+}
+
+impl ExportManifest {
+    /// Verify the signature and that `json` and `csv` are the signed exports.
+    pub fn verify(&self, json: &str, csv: &str) -> Result<()> {
+        let signer = Did::parse(&self.signer)?;
+        let signature = hex::decode(&self.signature)
+            .ok()
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+            .ok_or_else(|| PrivacyError::ExportError("Malformed manifest signature".to_string()))?;
+        signer
+            .verification_key
+            .verify(&self.signing_bytes()?, &signature)
+            .map_err(|_| PrivacyError::ExportError("Invalid manifest signature".to_string()))?;
+
+        if blake3::hash(json.as_bytes()).to_hex().as_str() != self.json_hash {
+            return Err(PrivacyError::ExportError(
+                "JSON export does not match the manifest".to_string(),
+            ));
+        }
+        if blake3::hash(csv.as_bytes()).to_hex().as_str() != self.csv_hash {
+            return Err(PrivacyError::ExportError(
+                "CSV export does not match the manifest".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Bytes covered by the signature.
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        let unsigned = Self {
+            signature: String::new(),
+            ..self.clone()
+        };
+        let mut bytes = MANIFEST_DOMAIN.to_vec();
+        bytes.push(0);
+        bytes.extend_from_slice(&serde_json::to_vec(&unsigned)?);
+        Ok(bytes)
+    }
+}
+
+/// Quote a CSV cell if needed.
+fn csv_escape(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    fn export() -> DataExport {
+        DataExport {
+            subject: "did:peer:alice".to_string(),
+            generated_at: 1_700_000_000,
+            profile: "GDPR".to_string(),
+            formats: vec![ExportFormat::Json, ExportFormat::Csv],
+            fields: vec![
+                ExportedField {
+                    document: "users/did:peer:alice".to_string(),
+                    field: "bio".to_string(),
+                    personal: false,
+                    attribution: Attribution::Owner,
+                    value: Some(serde_json::json!("Hi, all")),
+                },
+                ExportedField {
+                    document: "users/did:peer:alice".to_string(),
+                    field: "email".to_string(),
+                    personal: true,
+                    attribution: Attribution::Owner,
+                    value: None,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_csv() {
+        let csv = export().to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "document,field,personal,attribution,value");
+        assert_eq!(
+            lines[1],
+            r#"users/did:peer:alice,bio,false,owner,"""Hi, all""""#
+        );
+        assert_eq!(lines[2], "users/did:peer:alice,email,true,owner,");
+
+        let renderings = export().renderings().unwrap();
+        assert_eq!(renderings.len(), 2);
+        assert_eq!(renderings[1], (ExportFormat::Csv, csv));
+    }
+
+    #[test]
+    fn test_manifest() {
+        let export = export();
+        let (json, csv) = (export.to_json().unwrap(), export.to_csv());
+        let manifest = export.sign(&SigningKey::generate(&mut OsRng)).unwrap();
+        assert_eq!(manifest.documents, vec!["users/did:peer:alice"]);
+        manifest.verify(&json, &csv).unwrap();
+
+        assert!(manifest.verify(&json, "document\n").is_err());
+
+        let mut forged = manifest.clone();
+        forged.field_count = 1;
+        assert!(forged.verify(&json, &csv).is_err());
+    }
+}
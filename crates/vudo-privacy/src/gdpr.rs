@@ -48,13 +48,24 @@
 //! # }
 //! ```
 
-use crate::audit::{DataCategory, DeletionAuditLog, DeletionMethod};
-use crate::crypto::{DeletionReceipt, PersonalDataCrypto};
+use crate::access::AccessContext;
+use crate::audit::{DataCategory, DeletionAuditLog, DeletionLogEntry, DeletionMethod};
+use crate::crypto::{DekRotation, DeletionReceipt, PersonalDataCrypto};
 use crate::error::{PrivacyError, Result};
+use crate::export::{Attribution, DataExport, ExportedField};
+use crate::personal::PersonalFieldInterceptor;
+use crate::profiles::{ComplianceProfile, ComplianceProfiles};
+use crate::pseudonymous::PseudonymousActorId;
+use automerge::{AutoSerde, ReadDoc, ROOT};
+use ed25519_dalek::SigningKey;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use parking_lot::RwLock;
 use tracing::{info, warn};
+use vudo_state::{DocumentStore, StateError};
+
+/// Capability recorded for reads of personal data by access requests.
+pub const ACCESS_REQUEST_CAPABILITY: &str = "gdpr/access-request";
 
 /// GDPR deletion request.
 ///
@@ -124,6 +135,18 @@ pub struct DeletionReport {
     /// Compliance note for legal records.
     pub compliance_note: String,
 
+    /// Name of the compliance profile that governed the deletion.
+    #[serde(default)]
+    pub profile: String,
+
+    /// Request timestamp (Unix seconds).
+    #[serde(default)]
+    pub requested_at: u64,
+
+    /// Deadline of the deletion under the profile (Unix seconds).
+    #[serde(default)]
+    pub due_by: u64,
+
     /// Categories of data deleted.
     pub categories_deleted: Vec<DataCategory>,
 
@@ -151,6 +174,18 @@ pub struct GdprComplianceEngine {
 
     /// Deletion history (for idempotency).
     deletion_history: Arc<dashmap::DashMap<String, DeletionReport>>,
+
+    /// Document store walked by data exports (optional).
+    store: Option<Arc<DocumentStore>>,
+
+    /// `@personal` field interceptors, by namespace.
+    interceptors: Arc<dashmap::DashMap<String, PersonalFieldInterceptor>>,
+
+    /// Controller key signing deletion receipts (optional).
+    signing_key: Option<SigningKey>,
+
+    /// Compliance profiles, by jurisdiction.
+    profiles: ComplianceProfiles,
 }
 
 impl GdprComplianceEngine {
@@ -160,9 +195,47 @@ impl GdprComplianceEngine {
             crypto: Arc::new(PersonalDataCrypto::new()),
             audit_log: Arc::new(RwLock::new(DeletionAuditLog::new())),
             deletion_history: Arc::new(dashmap::DashMap::new()),
+            store: None,
+            interceptors: Arc::new(dashmap::DashMap::new()),
+            signing_key: None,
+            profiles: ComplianceProfiles::new(),
         })
     }
 
+    /// Set the controller key signing deletion receipts.
+    ///
+    /// Third parties verify the receipts in exported audit logs against its
+    /// public key (see [`crate::audit::verify_audit_log`]).
+    pub fn with_signing_key(mut self, key: SigningKey) -> Self {
+        self.signing_key = Some(key);
+        self
+    }
+
+    /// Set the audit log, e.g. one signing checkpoints (see
+    /// [`DeletionAuditLog::with_checkpoints`]).
+    pub fn with_audit_log(mut self, audit_log: DeletionAuditLog) -> Self {
+        self.audit_log = Arc::new(RwLock::new(audit_log));
+        self
+    }
+
+    /// Set the document store walked by data exports.
+    pub fn with_store(mut self, store: Arc<DocumentStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Register the `@personal` field interceptor of a namespace.
+    ///
+    /// Data exports use it to find document owners and decrypt their
+    /// personal fields.
+    pub fn register_interceptor(
+        &self,
+        namespace: impl Into<String>,
+        interceptor: PersonalFieldInterceptor,
+    ) {
+        self.interceptors.insert(namespace.into(), interceptor);
+    }
+
     /// Get the crypto manager.
     pub fn crypto(&self) -> Arc<PersonalDataCrypto> {
         Arc::clone(&self.crypto)
@@ -173,8 +246,18 @@ impl GdprComplianceEngine {
         self.audit_log.read().clone()
     }
 
+    /// Get the compliance profiles, to register profiles and assign them to
+    /// users and namespaces.
+    pub fn profiles(&self) -> &ComplianceProfiles {
+        &self.profiles
+    }
+
     /// Execute a GDPR deletion request (Article 17).
     ///
+    /// The compliance profile of the user, or else of the request's
+    /// namespace, sets the deadline and legal basis of the deletion and the
+    /// detail of its audit entries.
+    ///
     /// # Arguments
     ///
     /// * `user_did` - The DID of the user requesting deletion
@@ -196,6 +279,10 @@ impl GdprComplianceEngine {
             return Ok(existing_report.value().clone());
         }
 
+        let profile = self.profiles.resolve(user_did, Some(&request.namespace));
+        let requested_at = chrono::Utc::now().timestamp() as u64;
+        let due_by = profile.erasure_due_by(requested_at);
+
         let mut deleted_categories = Vec::new();
         let mut crypto_proof = None;
 
@@ -204,17 +291,25 @@ impl GdprComplianceEngine {
             info!("Deleting personal data for user: {}", user_did);
 
             match self.crypto.delete_dek(user_did) {
-                Ok(receipt) => {
+                Ok(mut receipt) => {
+                    self.sign_receipt(&mut receipt)?;
                     deleted_categories.push(DataCategory::PersonalData);
                     crypto_proof = Some(receipt.clone());
 
                     // Record in audit log
-                    self.audit_log.write().record_deletion(
-                        user_did,
+                    let mut entry = DeletionLogEntry::new(
+                        user_did.to_string(),
                         vec![DataCategory::PersonalData],
                         DeletionMethod::CryptographicErasure,
-                        Some(receipt),
+                        Some(receipt.clone()),
                     );
+                    if let Some(hold) = self.crypto.escrow().and_then(|e| e.hold(user_did)) {
+                        entry = entry.with_notes(format!(
+                            "Escrowed DEK retained under legal hold: {}",
+                            hold.reason
+                        ));
+                    }
+                    self.record_governed(entry, &profile, Some(due_by));
 
                     info!("Personal data deleted for user: {}", user_did);
                 }
@@ -233,12 +328,13 @@ impl GdprComplianceEngine {
             // For now, we just record it in the audit log
             deleted_categories.push(DataCategory::PublicData);
 
-            self.audit_log.write().record_deletion(
-                user_did,
+            let entry = DeletionLogEntry::new(
+                user_did.to_string(),
                 vec![DataCategory::PublicData],
                 DeletionMethod::Tombstone,
                 None,
             );
+            self.record_governed(entry, &profile, Some(due_by));
 
             info!("Public data deleted for user: {}", user_did);
         }
@@ -251,12 +347,13 @@ impl GdprComplianceEngine {
             // in transaction records while retaining the transactions for legal/tax reasons
             deleted_categories.push(DataCategory::TransactionHistory);
 
-            self.audit_log.write().record_deletion(
-                user_did,
+            let entry = DeletionLogEntry::new(
+                user_did.to_string(),
                 vec![DataCategory::TransactionHistory],
                 DeletionMethod::Anonymization,
                 None,
             );
+            self.record_governed(entry, &profile, Some(due_by));
 
             info!("Transaction history anonymized for user: {}", user_did);
         }
@@ -265,11 +362,14 @@ impl GdprComplianceEngine {
         let report = DeletionReport {
             request_id: uuid::Uuid::new_v4().to_string(),
             completed_at: chrono::Utc::now().timestamp() as u64,
-            irreversible: true,
+            irreversible: crypto_proof.as_ref().map_or(true, |r| r.irreversible),
             compliance_note: format!(
-                "Data deletion completed per GDPR Article 17 for user {}",
-                user_did
+                "Data deletion completed per {} for user {}",
+                profile.erasure_basis, user_did
             ),
+            profile: profile.name,
+            requested_at,
+            due_by,
             categories_deleted: deleted_categories,
             crypto_proof,
         };
@@ -281,6 +381,189 @@ impl GdprComplianceEngine {
         Ok(report)
     }
 
+    /// Export the data held about a user (Article 15 access request).
+    ///
+    /// Walks every document in the store. A document belongs to the user if
+    /// the interceptor of its namespace names them as its DEK owner; all its
+    /// fields are exported, with `@personal` fields decrypted. Elsewhere,
+    /// fields last written by the user's pseudonymous actor are exported,
+    /// without the values of personal fields encrypted for someone else.
+    ///
+    /// The export names the user's compliance profile and the renderings it
+    /// requires (see [`DataExport::renderings`]). With read audit enabled,
+    /// the decrypted reads are recorded with [`ACCESS_REQUEST_CAPABILITY`].
+    pub fn export_user_data(&self, user_did: &str) -> Result<DataExport> {
+        info!("Exporting data for user: {}", user_did);
+
+        let store = self
+            .store
+            .as_ref()
+            .ok_or_else(|| PrivacyError::ExportError("No document store configured".to_string()))?;
+        let actor = PseudonymousActorId::from_did(user_did)?.actor_id();
+        let context = AccessContext::unattributed().with_capability(ACCESS_REQUEST_CAPABILITY);
+
+        let mut ids = store.list_all();
+        ids.sort_by_key(|id| id.to_string());
+
+        let mut fields = Vec::new();
+        for id in ids {
+            let handle = store.get(&id)?;
+            let interceptor = self
+                .interceptors
+                .get(&id.namespace)
+                .map(|i| i.value().clone());
+            let owned = interceptor
+                .as_ref()
+                .is_some_and(|i| i.owner_of(&id) == user_did);
+
+            let values = match (&interceptor, owned) {
+                (Some(interceptor), true) => {
+                    match interceptor.read_as(&handle, &context, document_json) {
+                        Err(PrivacyError::DekNotFound(_)) => handle.read(document_json)?,
+                        result => result?,
+                    }
+                }
+                _ => handle.read(document_json)?,
+            };
+            let keys: Vec<String> = handle.read(|doc| Ok(doc.keys(ROOT).collect()))?;
+
+            for key in keys {
+                let attribution = if owned {
+                    Attribution::Owner
+                } else if handle.blame(&key)?.as_ref() == Some(&actor) {
+                    Attribution::Author
+                } else {
+                    continue;
+                };
+                let personal = interceptor.as_ref().is_some_and(|i| i.is_personal(&key));
+                let value = if personal && !owned {
+                    None
+                } else {
+                    values.get(&key).cloned()
+                };
+
+                fields.push(ExportedField {
+                    document: id.to_string(),
+                    field: key,
+                    personal,
+                    attribution,
+                    value,
+                });
+            }
+        }
+
+        info!("Exported {} fields for user: {}", fields.len(), user_did);
+        let profile = self.profiles.resolve(user_did, None);
+        Ok(DataExport {
+            subject: user_did.to_string(),
+            generated_at: chrono::Utc::now().timestamp() as u64,
+            profile: profile.name,
+            formats: profile.export_formats,
+            fields,
+        })
+    }
+
+    /// Rotate a user's DEK (e.g. after a suspected key compromise).
+    ///
+    /// The `@personal` fields of the documents the user owns in the store
+    /// are re-encrypted with a new DEK through the registered interceptors;
+    /// every document is re-encrypted before any is written. The old DEK is
+    /// then destroyed, and the rotation recorded in the audit log. Documents
+    /// encrypted at rest with a [`DekCodec`] must be saved again afterwards.
+    ///
+    /// [`DekCodec`]: crate::at_rest::DekCodec
+    pub fn rotate_dek(&self, user_did: &str) -> Result<DekRotation> {
+        info!("Rotating DEK for user: {}", user_did);
+
+        let rotation = self.crypto.rotate_dek_with(user_did, |old, new| {
+            let mut pending = Vec::new();
+            if let Some(store) = &self.store {
+                for id in store.list_all() {
+                    let interceptor = match self.interceptors.get(&id.namespace) {
+                        Some(interceptor) => interceptor.value().clone(),
+                        None => continue,
+                    };
+                    if interceptor.owner_of(&id) != user_did {
+                        continue;
+                    }
+                    let handle = store.get(&id)?;
+                    let values = interceptor.reencrypt_fields(&handle, old, new)?;
+                    pending.push((handle, values));
+                }
+            }
+
+            let mut reencrypted = 0;
+            for (handle, values) in pending {
+                reencrypted += values.len();
+                PersonalFieldInterceptor::write_fields(&handle, values)?;
+            }
+            Ok(reencrypted)
+        })?;
+
+        let entry = DeletionLogEntry::new(
+            user_did.to_string(),
+            vec![DataCategory::PersonalData],
+            DeletionMethod::KeyRotation,
+            None,
+        )
+        .with_notes(format!(
+            "Retired DEK {} for DEK {}; re-encrypted {} fields",
+            rotation.old_key_id, rotation.new_key_id, rotation.reencrypted
+        ));
+        self.record_governed(entry, &self.profiles.resolve(user_did, None), None);
+
+        info!("DEK rotated for user: {}", user_did);
+        Ok(rotation)
+    }
+
+    /// Release the legal hold on a user's escrowed DEKs.
+    ///
+    /// If the user's personal data was erased under the hold, the escrowed
+    /// copies of their DEK are destroyed now. The completed erasure is
+    /// recorded in the audit log and the user's deletion report becomes
+    /// irreversible.
+    pub fn release_legal_hold(&self, user_did: &str) -> Result<Option<DeletionReceipt>> {
+        let mut receipt = self.crypto.release_legal_hold(user_did)?;
+
+        if let Some(receipt) = &mut receipt {
+            self.sign_receipt(receipt)?;
+            let entry = DeletionLogEntry::new(
+                user_did.to_string(),
+                vec![DataCategory::PersonalData],
+                DeletionMethod::CryptographicErasure,
+                Some(receipt.clone()),
+            )
+            .with_notes("Escrowed DEK destroyed on release of legal hold".to_string());
+            self.record_governed(entry, &self.profiles.resolve(user_did, None), None);
+
+            if let Some(mut report) = self.deletion_history.get_mut(user_did) {
+                report.irreversible = true;
+                report.crypto_proof = Some(receipt.clone());
+            }
+        }
+        Ok(receipt)
+    }
+
+    /// Record an audit entry with the detail of a compliance profile.
+    fn record_governed(
+        &self,
+        mut entry: DeletionLogEntry,
+        profile: &ComplianceProfile,
+        erasure_due_by: Option<u64>,
+    ) -> String {
+        entry.notes = profile.audit_notes(entry.notes.take(), erasure_due_by);
+        let entry = entry.with_profile(profile.name.clone());
+        self.audit_log.write().record_entry(entry)
+    }
+
+    /// Sign a deletion receipt with the controller key, if set.
+    fn sign_receipt(&self, receipt: &mut DeletionReceipt) -> Result<()> {
+        match &self.signing_key {
+            Some(key) => receipt.sign(key),
+            None => Ok(()),
+        }
+    }
+
     /// Check if a user's data has been deleted.
     pub fn is_deleted(&self, user_did: &str) -> bool {
         self.deletion_history.contains_key(user_did)
@@ -311,6 +594,9 @@ impl GdprComplianceEngine {
             anonymizations: audit
                 .get_entries_by_method(DeletionMethod::Anonymization)
                 .len(),
+            key_rotations: audit
+                .get_entries_by_method(DeletionMethod::KeyRotation)
+                .len(),
         }
     }
 }
@@ -321,6 +607,17 @@ impl Default for GdprComplianceEngine {
     }
 }
 
+/// Render the top level of a document as a JSON object.
+pub(crate) fn document_json(
+    doc: &automerge::AutoCommit,
+) -> vudo_state::Result<serde_json::Map<String, serde_json::Value>> {
+    match serde_json::to_value(AutoSerde::from(doc)) {
+        Ok(serde_json::Value::Object(map)) => Ok(map),
+        Ok(_) => Ok(serde_json::Map::new()),
+        Err(e) => Err(StateError::SerializationError(e.to_string())),
+    }
+}
+
 /// Statistics about GDPR deletions.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeletionStats {
@@ -335,6 +632,9 @@ pub struct DeletionStats {
 
     /// Number of anonymizations.
     pub anonymizations: usize,
+
+    /// Number of DEK rotations.
+    pub key_rotations: usize,
 }
 
 #[cfg(test)]
@@ -420,4 +720,249 @@ mod tests {
         assert!(!json.is_empty());
         assert!(json.contains("did:peer:alice"));
     }
+
+    #[tokio::test]
+    async fn test_compliance_profiles() {
+        use crate::profiles::AuditDetail;
+
+        let engine = GdprComplianceEngine::new().unwrap();
+        let profiles = engine.profiles();
+        profiles.register(ComplianceProfile::lgpd());
+        profiles.register(ComplianceProfile::ccpa().with_audit_detail(AuditDetail::Minimal));
+        profiles.assign_namespace("app.br", "LGPD").unwrap();
+        profiles.assign_user("did:peer:bob", "CCPA").unwrap();
+        engine.crypto().generate_dek("did:peer:alice").unwrap();
+        engine.crypto().generate_dek("did:peer:bob").unwrap();
+
+        let request = DeletionRequest::personal_only("app.br".to_string());
+        let alice = engine
+            .execute_deletion("did:peer:alice", request)
+            .await
+            .unwrap();
+        assert_eq!(alice.profile, "LGPD");
+        assert_eq!(alice.due_by, alice.requested_at + 15 * 86_400);
+        assert!(alice.compliance_note.contains("LGPD Article 18"));
+
+        // The user's profile wins over the namespace's
+        let request = DeletionRequest::personal_only("app.br".to_string());
+        let bob = engine
+            .execute_deletion("did:peer:bob", request)
+            .await
+            .unwrap();
+        assert_eq!(bob.profile, "CCPA");
+
+        let audit_log = engine.audit_log();
+        let entry = &audit_log.get_entries_for_user("did:peer:alice")[0];
+        assert_eq!(entry.profile.as_deref(), Some("LGPD"));
+        assert!(entry.notes.as_ref().unwrap().contains("Legal basis: LGPD"));
+        let entry = &audit_log.get_entries_for_user("did:peer:bob")[0];
+        assert_eq!(entry.profile.as_deref(), Some("CCPA"));
+        assert_eq!(entry.notes, None);
+        audit_log.verify_chain().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_export_user_data() {
+        use crate::at_rest::DekOwner;
+        use automerge::transaction::Transactable;
+        use vudo_state::DocumentId;
+
+        let store = Arc::new(DocumentStore::new());
+        let engine = GdprComplianceEngine::new()
+            .unwrap()
+            .with_store(Arc::clone(&store));
+        let crypto = (*engine.crypto()).clone();
+        crypto.generate_dek("did:peer:alice").unwrap();
+        crypto.generate_dek("did:peer:bob").unwrap();
+        let interceptor = PersonalFieldInterceptor::new(crypto, DekOwner::DocumentKey, ["email"]);
+        engine.register_interceptor("users", interceptor.clone());
+
+        let alice = PseudonymousActorId::from_did("did:peer:alice").unwrap();
+        let bob = PseudonymousActorId::from_did("did:peer:bob").unwrap();
+
+        // Alice's profile, and a post and Bob's profile she wrote to
+        alice.bind_store(&store);
+        let profile = store
+            .create(DocumentId::new("users", "did:peer:alice"))
+            .unwrap();
+        interceptor
+            .update(&profile, |doc| {
+                doc.put("email", "alice@example.com")?;
+                doc.put("username", "alice")
+            })
+            .unwrap();
+        let post = store.create(DocumentId::new("posts", "1")).unwrap();
+        post.update(|doc| {
+            doc.put(ROOT, "title", "Hello")?;
+            Ok(())
+        })
+        .unwrap();
+        let bob_profile = store
+            .create(DocumentId::new("users", "did:peer:bob"))
+            .unwrap();
+        interceptor
+            .update(&bob_profile, |doc| doc.put("email", "bob@example.com"))
+            .unwrap();
+
+        // Bob's own writes
+        bob.bind_store(&store);
+        post.update(|doc| {
+            doc.put(ROOT, "body", "Hi")?;
+            Ok(())
+        })
+        .unwrap();
+        interceptor
+            .update(&bob_profile, |doc| doc.put("username", "bob"))
+            .unwrap();
+
+        let export = engine.export_user_data("did:peer:alice").unwrap();
+        assert_eq!(export.profile, "GDPR");
+        assert_eq!(export.renderings().unwrap().len(), 2);
+        let rows: Vec<(&str, &str, Attribution, Option<serde_json::Value>)> = export
+            .fields
+            .iter()
+            .map(|f| {
+                (
+                    f.document.as_str(),
+                    f.field.as_str(),
+                    f.attribution,
+                    f.value.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (
+                    "posts/1",
+                    "title",
+                    Attribution::Author,
+                    Some("Hello".into())
+                ),
+                (
+                    "users/did:peer:alice",
+                    "email",
+                    Attribution::Owner,
+                    Some("alice@example.com".into())
+                ),
+                (
+                    "users/did:peer:alice",
+                    "username",
+                    Attribution::Owner,
+                    Some("alice".into())
+                ),
+                // Encrypted with Bob's DEK
+                ("users/did:peer:bob", "email", Attribution::Author, None),
+            ]
+        );
+
+        // After erasure, personal values are gone from the export
+        engine
+            .execute_deletion(
+                "did:peer:alice",
+                DeletionRequest::personal_only("app".to_string()),
+            )
+            .await
+            .unwrap();
+        let export = engine.export_user_data("did:peer:alice").unwrap();
+        let email = export.fields.iter().find(|f| f.field == "email").unwrap();
+        assert_eq!(email.value, None);
+    }
+
+    #[test]
+    fn test_rotate_dek() {
+        use crate::at_rest::DekOwner;
+        use vudo_state::DocumentId;
+
+        let store = Arc::new(DocumentStore::new());
+        let engine = GdprComplianceEngine::new()
+            .unwrap()
+            .with_store(Arc::clone(&store));
+        let crypto = (*engine.crypto()).clone();
+        crypto.generate_dek("did:peer:alice").unwrap();
+        crypto.generate_dek("did:peer:bob").unwrap();
+        let interceptor = PersonalFieldInterceptor::new(crypto, DekOwner::DocumentKey, ["email"]);
+        engine.register_interceptor("users", interceptor.clone());
+
+        let alice = store
+            .create(DocumentId::new("users", "did:peer:alice"))
+            .unwrap();
+        interceptor
+            .update(&alice, |doc| doc.put("email", "alice@example.com"))
+            .unwrap();
+        let bob = store
+            .create(DocumentId::new("users", "did:peer:bob"))
+            .unwrap();
+        interceptor
+            .update(&bob, |doc| doc.put("email", "bob@example.com"))
+            .unwrap();
+
+        let rotation = engine.rotate_dek("did:peer:alice").unwrap();
+        assert_eq!(rotation.reencrypted, 1);
+
+        let email = |handle| {
+            interceptor
+                .update(handle, |doc| doc.get("email"))
+                .unwrap()
+                .and_then(|v| v.to_str().map(str::to_string))
+        };
+        assert_eq!(email(&alice).as_deref(), Some("alice@example.com"));
+        assert_eq!(email(&bob).as_deref(), Some("bob@example.com"));
+
+        let entries = engine
+            .audit_log()
+            .get_entries_by_method(DeletionMethod::KeyRotation);
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0]
+            .notes
+            .as_ref()
+            .unwrap()
+            .contains(&rotation.old_key_id));
+        assert_eq!(engine.get_stats().key_rotations, 1);
+    }
+
+    #[tokio::test]
+    async fn test_deletion_under_legal_hold() {
+        use rand::rngs::OsRng;
+        use x25519_dalek::{PublicKey, StaticSecret};
+
+        let secret = StaticSecret::random_from_rng(OsRng);
+        let signing = ed25519_dalek::SigningKey::generate(&mut OsRng);
+        let recovery =
+            vudo_identity::Did::from_keys(signing.verifying_key(), &PublicKey::from(&secret))
+                .unwrap();
+
+        let engine = GdprComplianceEngine::new().unwrap();
+        let escrow = engine.crypto().enable_escrow(recovery).unwrap();
+        engine.crypto().generate_dek("did:peer:alice").unwrap();
+        engine
+            .crypto()
+            .place_legal_hold("did:peer:alice", "Case 1")
+            .unwrap();
+
+        let request = DeletionRequest::personal_only("app.example".to_string());
+        let report = engine
+            .execute_deletion("did:peer:alice", request)
+            .await
+            .unwrap();
+        assert!(!report.irreversible);
+        assert!(escrow.recover("did:peer:alice", &secret).is_ok());
+
+        let receipt = engine
+            .release_legal_hold("did:peer:alice")
+            .unwrap()
+            .unwrap();
+        assert!(receipt.irreversible);
+        assert!(escrow.recover("did:peer:alice", &secret).is_err());
+        assert!(
+            engine
+                .get_deletion_report("did:peer:alice")
+                .unwrap()
+                .irreversible
+        );
+
+        let entries = engine.audit_log().get_entries_for_user("did:peer:alice");
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].notes.as_ref().unwrap().contains("Case 1"));
+    }
 }
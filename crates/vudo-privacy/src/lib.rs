@@ -12,6 +12,16 @@
 //! - **Pseudonymous Actor IDs**: Privacy-preserving CRDT metadata
 //! - **Audit Trail**: Comprehensive logging for compliance
 //! - **Willow Integration**: True-deletion for non-personal data
+//! - **Encryption at Rest**: Whole-document encryption with DEKs via the document codec hook
+//! - **Field-Level Encryption**: `@personal` fields encrypted on update and decrypted on read
+//! - **Data Subject Access Requests**: JSON/CSV exports of a subject's data with a signed manifest
+//! - **Verifiable Deletion Proofs**: Signed deletion receipts that auditors verify against exported audit logs
+//! - **Tamper-Evident Audit Log**: Hash-chained entries with signed checkpoints, optionally anchored over P2P gossip
+//! - **Key Rotation and Escrow**: DEK rotation with re-encryption, and DEKs wrapped to a recovery key for legal hold
+//! - **Read Audit**: Optional, sampled record of who decrypted which `@personal` field, and with what capability
+//! - **Consent Management**: Append-only CRDT consent ledger; withdrawal stops processing and can trigger erasure
+//! - **Compliance Profiles**: GDPR, CCPA and LGPD deadlines, audit detail and export formats per user or namespace
+//! - **Analytics Anonymization**: k-anonymity generalization, salted hashing and `@personal` suppression for exports
 //!
 //! # Architecture
 //!
@@ -71,7 +81,8 @@
 //! }
 //! ```
 //!
-//! Generated Rust code will automatically encrypt `@personal` fields with DEKs.
+//! Generated Rust code will automatically encrypt `@personal` fields with DEKs,
+//! through a [`PersonalFieldInterceptor`] built from the schema.
 //!
 //! # GDPR Compliance
 //!
@@ -95,17 +106,38 @@
 //! - [Cryptographic Deletion in CRDTs](https://arxiv.org/abs/2103.13108)
 //! - [VUDO Privacy Design](docs/compliance/gdpr-local-first.md)
 
+pub mod access;
+pub mod anonymize;
+pub mod at_rest;
 pub mod audit;
+pub mod consent;
 pub mod crypto;
 pub mod error;
+pub mod escrow;
+pub mod export;
 pub mod gdpr;
+pub mod personal;
+pub mod profiles;
 pub mod pseudonymous;
 
 // Re-export main types
-pub use audit::{DataCategory, DeletionAuditLog, DeletionLogEntry, DeletionMethod};
-pub use crypto::{DataEncryptionKey, DeletionReceipt, EncryptedField, PersonalDataCrypto};
+pub use access::{AccessContext, ReadAudit, ReadAuditStats};
+pub use anonymize::{AnonymizationPolicy, AnonymizedDataset, Anonymizer, Generalization};
+pub use at_rest::{DekCodec, DekOwner};
+pub use audit::{
+    verify_audit_log, verify_checkpoint, AccessLogEntry, AuditAnchor, AuditCheckpoint,
+    AuditVerification, DataCategory, DeletionAuditLog, DeletionLogEntry, DeletionMethod,
+};
+pub use consent::{ConsentAction, ConsentManager, ConsentRecord, WithdrawalOutcome};
+pub use crypto::{
+    DataEncryptionKey, DekRotation, DeletionReceipt, EncryptedField, PersonalDataCrypto,
+};
 pub use error::{PrivacyError, Result};
+pub use escrow::{EscrowedDek, KeyEscrow, LegalHold};
+pub use export::{Attribution, DataExport, ExportManifest, ExportedField};
 pub use gdpr::{DeletionReport, DeletionRequest, DeletionStats, GdprComplianceEngine};
+pub use personal::{PersonalDoc, PersonalFieldInterceptor};
+pub use profiles::{AuditDetail, ComplianceProfile, ComplianceProfiles, ExportFormat};
 pub use pseudonymous::{ActorIdMapper, PseudonymousActorId};
 
 /// Library version
@@ -0,0 +1,579 @@
+//! Transparent encryption of `@personal` fields in state documents.
+//!
+//! [`PersonalFieldInterceptor`] sits between application code and a
+//! `vudo-state` document. Given the `@personal` fields of a Gen (from a
+//! `dol-reflect` schema with the `reflect` feature, or by name), it encrypts
+//! them with the owner's DEK as they are written and decrypts them on read,
+//! so generated code doesn't have to hand-roll crypto.
+//!
+//! Personal fields are encrypted before they reach the document, so their
+//! plaintext never enters the CRDT history. Updates that write a personal
+//! field around the interceptor (in plaintext) are rolled back. Once the
+//! owner's DEK is deleted, personal fields read as absent.
+//!
+//! Fields are top-level scalar properties of the document, as generated
+//! code stores them.
+//!
+//! # Example
+//!
+//! ```rust
+//! use automerge::ReadDoc;
+//! use vudo_privacy::at_rest::DekOwner;
+//! use vudo_privacy::crypto::PersonalDataCrypto;
+//! use vudo_privacy::personal::PersonalFieldInterceptor;
+//! use vudo_state::{DocumentId, DocumentStore};
+//!
+//! # fn example() -> vudo_privacy::error::Result<()> {
+//! let crypto = PersonalDataCrypto::new();
+//! crypto.generate_dek("did:peer:alice")?;
+//!
+//! let interceptor =
+//!     PersonalFieldInterceptor::new(crypto, DekOwner::DocumentKey, ["email"]);
+//!
+//! let store = DocumentStore::new();
+//! let handle = store.create(DocumentId::new("users", "did:peer:alice"))?;
+//! interceptor.update(&handle, |doc| {
+//!     doc.put("email", "alice@example.com")?;
+//!     doc.put("username", "alice")
+//! })?;
+//!
+//! // Readers see the plaintext, storage and peers only the ciphertext
+//! let email = interceptor.read(&handle, |doc| {
+//!     Ok(doc
+//!         .get(automerge::ROOT, "email")?
+//!         .and_then(|(v, _)| v.to_str().map(str::to_string)))
+//! })?;
+//! assert_eq!(email.as_deref(), Some("alice@example.com"));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::access::AccessContext;
+use crate::at_rest::DekOwner;
+use crate::crypto::{DataEncryptionKey, EncryptedField, PersonalDataCrypto};
+use crate::error::{PrivacyError, Result};
+use automerge::transaction::Transactable;
+use automerge::{AutoCommit, ReadDoc, ScalarValue, Value, ROOT};
+use std::collections::BTreeSet;
+use vudo_state::{DocumentHandle, DocumentId, StateError};
+
+/// Magic bytes prefixed to encrypted field values.
+const FIELD_MAGIC: &[u8; 4] = b"VPF1";
+
+/// Length of the ChaCha20-Poly1305 nonce in encrypted field values.
+const NONCE_LEN: usize = 12;
+
+/// Type tags of encrypted scalar values.
+const TAG_BYTES: u8 = 0;
+const TAG_STR: u8 = 1;
+const TAG_INT: u8 = 2;
+const TAG_UINT: u8 = 3;
+const TAG_F64: u8 = 4;
+const TAG_BOOLEAN: u8 = 5;
+const TAG_TIMESTAMP: u8 = 6;
+const TAG_NULL: u8 = 7;
+
+/// Encrypts `@personal` fields on update and decrypts them on read.
+#[derive(Clone)]
+pub struct PersonalFieldInterceptor {
+    /// DEK manager.
+    crypto: PersonalDataCrypto,
+    /// Owner resolution.
+    owner: DekOwner,
+    /// Names of the personal fields.
+    fields: BTreeSet<String>,
+}
+
+impl PersonalFieldInterceptor {
+    /// Create an interceptor for the named personal fields.
+    pub fn new<I, S>(crypto: PersonalDataCrypto, owner: DekOwner, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            crypto,
+            owner,
+            fields: fields.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Create an interceptor for the `@personal` fields of a Gen.
+    #[cfg(feature = "reflect")]
+    pub fn from_gen(
+        crypto: PersonalDataCrypto,
+        owner: DekOwner,
+        gen: &dol_reflect::GenReflection,
+    ) -> Self {
+        Self::new(
+            crypto,
+            owner,
+            gen.personal_fields()
+                .into_iter()
+                .map(|f| f.name().to_string()),
+        )
+    }
+
+    /// Check if a field is personal.
+    pub fn is_personal(&self, field: &str) -> bool {
+        self.fields.contains(field)
+    }
+
+    /// Get the DEK owner of a document.
+    pub fn owner_of<'a>(&'a self, id: &'a DocumentId) -> &'a str {
+        self.owner.owner_of(id)
+    }
+
+    /// Get the names of the personal fields.
+    pub fn fields(&self) -> impl Iterator<Item = &str> {
+        self.fields.iter().map(String::as_str)
+    }
+
+    /// Update a document, encrypting the personal fields written.
+    ///
+    /// Writing a personal field in plaintext through [`PersonalDoc::doc`]
+    /// fails the update, and none of its changes are applied.
+    pub fn update<F, T>(&self, handle: &DocumentHandle, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut PersonalDoc<'_>) -> vudo_state::Result<T>,
+    {
+        let dek = self.dek(&handle.id)?;
+        let result = handle.update(|doc| {
+            let mut personal = PersonalDoc {
+                doc,
+                interceptor: self,
+                dek: &dek,
+            };
+            let result = f(&mut personal).and_then(|result| {
+                personal.check_encrypted()?;
+                Ok(result)
+            });
+            if result.is_err() {
+                doc.rollback();
+            }
+            result
+        })?;
+        Ok(result)
+    }
+
+    /// Read a document with its personal fields decrypted.
+    ///
+    /// `f` reads a decrypted copy of the document. Fields whose DEK was
+    /// deleted are absent from it. With read audit enabled, the reads are
+    /// recorded as unattributed; use [`Self::read_as`] to name the reader.
+    pub fn read<F, T>(&self, handle: &DocumentHandle, f: F) -> Result<T>
+    where
+        F: FnOnce(&AutoCommit) -> vudo_state::Result<T>,
+    {
+        self.read_as(handle, &AccessContext::unattributed(), f)
+    }
+
+    /// Read a document with its personal fields decrypted, on behalf of a
+    /// reader.
+    ///
+    /// With read audit enabled, the read of each personal field that is set
+    /// is recorded with the reader's device DID and capability.
+    pub fn read_as<F, T>(&self, handle: &DocumentHandle, context: &AccessContext, f: F) -> Result<T>
+    where
+        F: FnOnce(&AutoCommit) -> vudo_state::Result<T>,
+    {
+        let dek = self.dek(&handle.id)?;
+        let document = handle.id.to_string();
+        let result = handle.read(|doc| {
+            let mut view = doc.clone();
+            for field in &self.fields {
+                match self.decrypt_value(&dek, doc, field) {
+                    Ok(Some(value)) => {
+                        self.crypto
+                            .audit_read(&dek.owner, Some(&document), Some(field), context);
+                        view.put(ROOT, field.as_str(), value)?
+                    }
+                    Ok(None) => {}
+                    Err(PrivacyError::DataPermanentlyErased) => {
+                        view.delete(ROOT, field.as_str())?
+                    }
+                    Err(e) => return Err(codec_error(e)),
+                }
+            }
+            f(&view)
+        })?;
+        Ok(result)
+    }
+
+    /// Re-encrypt the personal fields of a document from one DEK to another.
+    ///
+    /// The new field values are returned rather than written, so that a DEK
+    /// rotation can re-encrypt every document before it changes any.
+    pub(crate) fn reencrypt_fields(
+        &self,
+        handle: &DocumentHandle,
+        old: &DataEncryptionKey,
+        new: &DataEncryptionKey,
+    ) -> Result<Vec<(String, Vec<u8>)>> {
+        let doc = handle.read(|doc| Ok(doc.clone()))?;
+        let mut values = Vec::new();
+        for field in &self.fields {
+            if let Some(value) = self.decrypt_value(old, &doc, field)? {
+                values.push((field.clone(), self.encrypt_value(new, &value)?));
+            }
+        }
+        Ok(values)
+    }
+
+    /// Write field values returned by [`Self::reencrypt_fields`].
+    pub(crate) fn write_fields(
+        handle: &DocumentHandle,
+        values: Vec<(String, Vec<u8>)>,
+    ) -> Result<()> {
+        handle.update(|doc| {
+            for (field, value) in values {
+                doc.put(ROOT, field.as_str(), value)?;
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// Get the DEK of a document's owner.
+    fn dek(&self, id: &DocumentId) -> Result<DataEncryptionKey> {
+        self.crypto.get_dek(self.owner.owner_of(id))
+    }
+
+    /// Encrypt a scalar value into a field value.
+    fn encrypt_value(&self, dek: &DataEncryptionKey, value: &ScalarValue) -> Result<Vec<u8>> {
+        let encrypted = self.crypto.encrypt_field(dek, &encode_scalar(value)?)?;
+
+        let mut out =
+            Vec::with_capacity(FIELD_MAGIC.len() + NONCE_LEN + encrypted.ciphertext.len());
+        out.extend_from_slice(FIELD_MAGIC);
+        out.extend_from_slice(&encrypted.nonce);
+        out.extend_from_slice(&encrypted.ciphertext);
+        Ok(out)
+    }
+
+    /// Decrypt the value of a personal field, if it is set.
+    fn decrypt_value(
+        &self,
+        dek: &DataEncryptionKey,
+        doc: &AutoCommit,
+        field: &str,
+    ) -> Result<Option<ScalarValue>> {
+        let bytes = match doc.get(ROOT, field).map_err(StateError::from)? {
+            Some((Value::Scalar(value), _)) => match value.as_ref() {
+                ScalarValue::Bytes(bytes) if bytes.starts_with(FIELD_MAGIC) => bytes.clone(),
+                _ => return Err(plaintext_error(field)),
+            },
+            Some(_) => return Err(plaintext_error(field)),
+            None => return Ok(None),
+        };
+
+        let rest = &bytes[FIELD_MAGIC.len()..];
+        if rest.len() < NONCE_LEN {
+            return Err(PrivacyError::DecryptionFailed);
+        }
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let encrypted = EncryptedField {
+            ciphertext: ciphertext.to_vec(),
+            nonce: nonce.try_into().expect("nonce length checked"),
+            owner: dek.owner.clone(),
+        };
+
+        decode_scalar(&self.crypto.decrypt_unaudited(dek, &encrypted)?).map(Some)
+    }
+}
+
+/// Document being updated through a [`PersonalFieldInterceptor`].
+pub struct PersonalDoc<'a> {
+    doc: &'a mut AutoCommit,
+    interceptor: &'a PersonalFieldInterceptor,
+    dek: &'a DataEncryptionKey,
+}
+
+impl PersonalDoc<'_> {
+    /// Set a top-level field, encrypting it if personal.
+    pub fn put(&mut self, field: &str, value: impl Into<ScalarValue>) -> vudo_state::Result<()> {
+        let value = value.into();
+        if self.interceptor.is_personal(field) {
+            let encrypted = self
+                .interceptor
+                .encrypt_value(self.dek, &value)
+                .map_err(codec_error)?;
+            self.doc.put(ROOT, field, encrypted)?;
+        } else {
+            self.doc.put(ROOT, field, value)?;
+        }
+        Ok(())
+    }
+
+    /// Get a top-level scalar field, decrypting it if personal.
+    ///
+    /// A personal field whose DEK was deleted reads as absent.
+    pub fn get(&self, field: &str) -> vudo_state::Result<Option<ScalarValue>> {
+        if self.interceptor.is_personal(field) {
+            return match self.interceptor.decrypt_value(self.dek, self.doc, field) {
+                Err(PrivacyError::DataPermanentlyErased) => Ok(None),
+                result => result.map_err(codec_error),
+            };
+        }
+        match self.doc.get(ROOT, field)? {
+            Some((Value::Scalar(value), _)) => Ok(Some(value.into_owned())),
+            _ => Ok(None),
+        }
+    }
+
+    /// Remove a top-level field.
+    pub fn delete(&mut self, field: &str) -> vudo_state::Result<()> {
+        self.doc.delete(ROOT, field)?;
+        Ok(())
+    }
+
+    /// Get the underlying document, for non-personal data.
+    pub fn doc(&mut self) -> &mut AutoCommit {
+        self.doc
+    }
+
+    /// Check that every personal field is encrypted.
+    fn check_encrypted(&self) -> vudo_state::Result<()> {
+        for field in self.interceptor.fields() {
+            if let Some((value, _)) = self.doc.get(ROOT, field)? {
+                let encrypted = matches!(
+                    value,
+                    Value::Scalar(ref scalar)
+                        if matches!(scalar.as_ref(), ScalarValue::Bytes(b) if b.starts_with(FIELD_MAGIC))
+                );
+                if !encrypted {
+                    return Err(codec_error(plaintext_error(field)));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Serialize a scalar value with a type tag.
+fn encode_scalar(value: &ScalarValue) -> Result<Vec<u8>> {
+    let (tag, payload) = match value {
+        ScalarValue::Bytes(bytes) => (TAG_BYTES, bytes.clone()),
+        ScalarValue::Str(s) => (TAG_STR, s.as_bytes().to_vec()),
+        ScalarValue::Int(i) => (TAG_INT, i.to_be_bytes().to_vec()),
+        ScalarValue::Uint(u) => (TAG_UINT, u.to_be_bytes().to_vec()),
+        ScalarValue::F64(f) => (TAG_F64, f.to_be_bytes().to_vec()),
+        ScalarValue::Boolean(b) => (TAG_BOOLEAN, vec![*b as u8]),
+        ScalarValue::Timestamp(t) => (TAG_TIMESTAMP, t.to_be_bytes().to_vec()),
+        ScalarValue::Null => (TAG_NULL, Vec::new()),
+        other => {
+            return Err(PrivacyError::EncryptionFailed(format!(
+                "Personal fields cannot hold {:?}",
+                other
+            )))
+        }
+    };
+
+    let mut out = Vec::with_capacity(1 + payload.len());
+    out.push(tag);
+    out.extend_from_slice(&payload);
+    Ok(out)
+}
+
+/// Deserialize a scalar value serialized by [`encode_scalar`].
+fn decode_scalar(bytes: &[u8]) -> Result<ScalarValue> {
+    let (&tag, payload) = bytes.split_first().ok_or(PrivacyError::DecryptionFailed)?;
+    let fixed = |payload: &[u8]| -> Result<[u8; 8]> {
+        payload
+            .try_into()
+            .map_err(|_| PrivacyError::DecryptionFailed)
+    };
+
+    Ok(match tag {
+        TAG_BYTES => ScalarValue::Bytes(payload.to_vec()),
+        TAG_STR => ScalarValue::Str(String::from_utf8(payload.to_vec())?.into()),
+        TAG_INT => ScalarValue::Int(i64::from_be_bytes(fixed(payload)?)),
+        TAG_UINT => ScalarValue::Uint(u64::from_be_bytes(fixed(payload)?)),
+        TAG_F64 => ScalarValue::F64(f64::from_be_bytes(fixed(payload)?)),
+        TAG_BOOLEAN => ScalarValue::Boolean(payload == [1]),
+        TAG_TIMESTAMP => ScalarValue::Timestamp(i64::from_be_bytes(fixed(payload)?)),
+        TAG_NULL => ScalarValue::Null,
+        _ => return Err(PrivacyError::DecryptionFailed),
+    })
+}
+
+fn plaintext_error(field: &str) -> PrivacyError {
+    PrivacyError::EncryptionFailed(format!("Personal field {} is not encrypted", field))
+}
+
+fn codec_error(e: PrivacyError) -> StateError {
+    StateError::CodecError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vudo_state::DocumentStore;
+
+    fn setup() -> (PersonalDataCrypto, PersonalFieldInterceptor, DocumentHandle) {
+        let crypto = PersonalDataCrypto::new();
+        crypto.generate_dek("did:peer:alice").unwrap();
+        let interceptor =
+            PersonalFieldInterceptor::new(crypto.clone(), DekOwner::DocumentKey, ["email", "age"]);
+        let handle = DocumentStore::new()
+            .create(DocumentId::new("users", "did:peer:alice"))
+            .unwrap();
+        (crypto, interceptor, handle)
+    }
+
+    fn read_str(
+        interceptor: &PersonalFieldInterceptor,
+        handle: &DocumentHandle,
+        field: &str,
+    ) -> Option<String> {
+        interceptor
+            .read(handle, |doc| {
+                Ok(doc
+                    .get(ROOT, field)?
+                    .and_then(|(v, _)| v.to_str().map(str::to_string)))
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_personal_fields_encrypted() {
+        let (_, interceptor, handle) = setup();
+        interceptor
+            .update(&handle, |doc| {
+                doc.put("email", "alice@example.com")?;
+                doc.put("age", 42i64)?;
+                doc.put("username", "alice")
+            })
+            .unwrap();
+
+        // Plaintext is neither in the document nor in its history
+        let saved = handle.save();
+        let needle = b"alice@example.com";
+        assert!(!saved.windows(needle.len()).any(|w| w == needle));
+        assert!(saved.windows(5).any(|w| w == b"alice"));
+
+        assert_eq!(
+            read_str(&interceptor, &handle, "email").as_deref(),
+            Some("alice@example.com")
+        );
+        assert_eq!(
+            read_str(&interceptor, &handle, "username").as_deref(),
+            Some("alice")
+        );
+        interceptor
+            .update(&handle, |doc| {
+                assert_eq!(doc.get("age")?, Some(ScalarValue::Int(42)));
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_plaintext_write_rolled_back() {
+        let (_, interceptor, handle) = setup();
+        let result = interceptor.update(&handle, |doc| {
+            doc.put("username", "alice")?;
+            doc.doc().put(ROOT, "email", "alice@example.com")?;
+            Ok(())
+        });
+        assert!(matches!(
+            result,
+            Err(PrivacyError::StateError(StateError::CodecError(_)))
+        ));
+
+        let saved = handle.save();
+        assert!(!saved.windows(5).any(|w| w == b"alice"));
+        assert_eq!(read_str(&interceptor, &handle, "username"), None);
+    }
+
+    #[test]
+    fn test_deleted_dek_erases_fields() {
+        let (crypto, interceptor, handle) = setup();
+        interceptor
+            .update(&handle, |doc| {
+                doc.put("email", "alice@example.com")?;
+                doc.put("username", "alice")
+            })
+            .unwrap();
+
+        crypto.delete_dek("did:peer:alice").unwrap();
+
+        assert_eq!(read_str(&interceptor, &handle, "email"), None);
+        assert_eq!(
+            read_str(&interceptor, &handle, "username").as_deref(),
+            Some("alice")
+        );
+        assert!(interceptor
+            .update(&handle, |doc| doc.put("email", "new@example.com"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_read_audit() {
+        use crate::access::ReadAudit;
+        use crate::audit::DeletionAuditLog;
+
+        let (crypto, interceptor, handle) = setup();
+        interceptor
+            .update(&handle, |doc| {
+                doc.put("email", "alice@example.com")?;
+                doc.put("username", "alice")
+            })
+            .unwrap();
+
+        let audit_log = DeletionAuditLog::new();
+        crypto.enable_read_audit(ReadAudit::new(audit_log.clone()));
+        let context = AccessContext::device("did:key:z6MkSupport").with_capability("support/read");
+        interceptor.read_as(&handle, &context, |_| Ok(())).unwrap();
+
+        // Only the personal field that is set was read
+        let accesses = audit_log.get_accesses_by("did:key:z6MkSupport");
+        assert_eq!(accesses.len(), 1);
+        assert_eq!(accesses[0].owner, "did:peer:alice");
+        assert_eq!(
+            accesses[0].document.as_deref(),
+            Some("users/did:peer:alice")
+        );
+        assert_eq!(accesses[0].field.as_deref(), Some("email"));
+        assert_eq!(accesses[0].capability.as_deref(), Some("support/read"));
+
+        // Re-encryption is not a read
+        let old = crypto.get_dek("did:peer:alice").unwrap();
+        let new = DataEncryptionKey::generate("did:peer:alice".to_string());
+        interceptor.reencrypt_fields(&handle, &old, &new).unwrap();
+        assert_eq!(audit_log.get_access_entries().len(), 1);
+
+        read_str(&interceptor, &handle, "email");
+        let accesses = audit_log.get_accesses_for_owner("did:peer:alice");
+        assert_eq!(accesses.len(), 2);
+        assert_eq!(accesses[1].accessor, None);
+    }
+
+    #[cfg(feature = "reflect")]
+    #[test]
+    fn test_from_gen() {
+        let mut registry = dol_reflect::SchemaRegistry::new();
+        registry
+            .load_schema(
+                r#"
+gen user.profile {
+  user has id: String
+
+  @personal
+  user has email: String
+}
+
+exegesis { User profile }
+"#,
+            )
+            .unwrap();
+
+        let interceptor = PersonalFieldInterceptor::from_gen(
+            PersonalDataCrypto::new(),
+            DekOwner::DocumentKey,
+            registry.get_gen("user.profile").unwrap(),
+        );
+        assert!(interceptor.is_personal("email"));
+        assert!(!interceptor.is_personal("id"));
+    }
+}
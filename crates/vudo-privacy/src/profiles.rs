@@ -0,0 +1,334 @@
+//! Per-jurisdiction compliance profiles.
+//!
+//! Data protection laws agree on the rights of data subjects but not on the
+//! details. A [`ComplianceProfile`] captures what differs between them:
+//!
+//! | Profile | Erasure basis | Deadline | Audit detail | Export formats |
+//! |---------|---------------|----------|--------------|----------------|
+//! | GDPR    | Article 17    | 30 days  | Full         | JSON, CSV      |
+//! | CCPA    | §1798.105     | 45 days  | Standard     | JSON           |
+//! | LGPD    | Article 18    | 15 days  | Full         | JSON           |
+//!
+//! [`ComplianceProfiles`] holds the profiles of a
+//! [`GdprComplianceEngine`](crate::gdpr::GdprComplianceEngine) and selects
+//! one per operation: the profile assigned to the user, else the one assigned
+//! to the namespace, else the default (GDPR). Deletion reports, data exports
+//! and audit entries name the profile that governed them.
+//!
+//! # Example
+//!
+//! ```rust
+//! use vudo_privacy::gdpr::{DeletionRequest, GdprComplianceEngine};
+//! use vudo_privacy::profiles::ComplianceProfile;
+//!
+//! # async fn example() -> vudo_privacy::error::Result<()> {
+//! let engine = GdprComplianceEngine::new()?;
+//! engine.profiles().register(ComplianceProfile::ccpa());
+//! engine.profiles().assign_user("did:peer:alice", "CCPA")?;
+//!
+//! let request = DeletionRequest::personal_only("app.example".to_string());
+//! let report = engine.execute_deletion("did:peer:alice", request).await?;
+//! assert_eq!(report.profile, "CCPA");
+//! assert_eq!(report.due_by - report.requested_at, 45 * 86_400);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{PrivacyError, Result};
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Seconds per day.
+const DAY: u64 = 86_400;
+
+/// Name of the default profile.
+pub const DEFAULT_PROFILE: &str = "GDPR";
+
+/// Detail recorded in the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditDetail {
+    /// Categories, method and proof only.
+    Minimal,
+    /// Also the notes of each operation (legal holds, rotations).
+    Standard,
+    /// Also the legal basis and deadline of each erasure.
+    Full,
+}
+
+/// Rendering of a data export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// JSON document.
+    Json,
+    /// CSV, one row per field.
+    Csv,
+}
+
+/// Compliance rules of a jurisdiction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComplianceProfile {
+    /// Profile name (e.g. "GDPR").
+    pub name: String,
+
+    /// Legal basis of erasure, cited in deletion reports.
+    pub erasure_basis: String,
+
+    /// Days to complete an erasure request.
+    pub erasure_deadline_days: u64,
+
+    /// Days to answer an access request.
+    pub access_deadline_days: u64,
+
+    /// Detail recorded in the audit log.
+    pub audit_detail: AuditDetail,
+
+    /// Renderings of data exports.
+    pub export_formats: Vec<ExportFormat>,
+}
+
+impl ComplianceProfile {
+    /// EU General Data Protection Regulation.
+    ///
+    /// Requests are answered within one month (Article 12(3)), and exports
+    /// must be machine-readable (Article 20).
+    pub fn gdpr() -> Self {
+        Self {
+            name: DEFAULT_PROFILE.to_string(),
+            erasure_basis: "GDPR Article 17".to_string(),
+            erasure_deadline_days: 30,
+            access_deadline_days: 30,
+            audit_detail: AuditDetail::Full,
+            export_formats: vec![ExportFormat::Json, ExportFormat::Csv],
+        }
+    }
+
+    /// California Consumer Privacy Act.
+    ///
+    /// Requests are answered within 45 days (§1798.130).
+    pub fn ccpa() -> Self {
+        Self {
+            name: "CCPA".to_string(),
+            erasure_basis: "CCPA §1798.105".to_string(),
+            erasure_deadline_days: 45,
+            access_deadline_days: 45,
+            audit_detail: AuditDetail::Standard,
+            export_formats: vec![ExportFormat::Json],
+        }
+    }
+
+    /// Brazilian Lei Geral de Proteção de Dados.
+    ///
+    /// Complete access requests are answered within 15 days (Article 19).
+    pub fn lgpd() -> Self {
+        Self {
+            name: "LGPD".to_string(),
+            erasure_basis: "LGPD Article 18".to_string(),
+            erasure_deadline_days: 15,
+            access_deadline_days: 15,
+            audit_detail: AuditDetail::Full,
+            export_formats: vec![ExportFormat::Json],
+        }
+    }
+
+    /// Create a custom profile, starting from the GDPR rules.
+    pub fn custom(name: impl Into<String>, erasure_basis: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            erasure_basis: erasure_basis.into(),
+            ..Self::gdpr()
+        }
+    }
+
+    /// Set the deadlines of erasure and access requests.
+    pub fn with_deadlines(mut self, erasure_days: u64, access_days: u64) -> Self {
+        self.erasure_deadline_days = erasure_days;
+        self.access_deadline_days = access_days;
+        self
+    }
+
+    /// Set the detail recorded in the audit log.
+    pub fn with_audit_detail(mut self, detail: AuditDetail) -> Self {
+        self.audit_detail = detail;
+        self
+    }
+
+    /// Set the renderings of data exports.
+    pub fn with_export_formats(mut self, formats: Vec<ExportFormat>) -> Self {
+        self.export_formats = formats;
+        self
+    }
+
+    /// Get the deadline of an erasure requested at `requested_at` (Unix
+    /// seconds).
+    pub fn erasure_due_by(&self, requested_at: u64) -> u64 {
+        requested_at + self.erasure_deadline_days * DAY
+    }
+
+    /// Get the deadline of an access request made at `requested_at` (Unix
+    /// seconds).
+    pub fn access_due_by(&self, requested_at: u64) -> u64 {
+        requested_at + self.access_deadline_days * DAY
+    }
+
+    /// Filter the notes of an audit entry by the audit detail.
+    ///
+    /// `erasure_due_by` is the deadline of the erasure the entry records, if
+    /// it records one.
+    pub fn audit_notes(
+        &self,
+        notes: Option<String>,
+        erasure_due_by: Option<u64>,
+    ) -> Option<String> {
+        let mut lines: Vec<String> = match self.audit_detail {
+            AuditDetail::Minimal => Vec::new(),
+            AuditDetail::Standard | AuditDetail::Full => notes.into_iter().collect(),
+        };
+        if let (AuditDetail::Full, Some(due_by)) = (self.audit_detail, erasure_due_by) {
+            lines.push(format!("Legal basis: {}", self.erasure_basis));
+            lines.push(format!("Due by: {}", due_by));
+        }
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+}
+
+/// Compliance profiles and their assignments to users and namespaces.
+#[derive(Clone)]
+pub struct ComplianceProfiles {
+    /// Profiles, by name.
+    profiles: Arc<DashMap<String, ComplianceProfile>>,
+
+    /// Profile names, by user DID.
+    users: Arc<DashMap<String, String>>,
+
+    /// Profile names, by namespace.
+    namespaces: Arc<DashMap<String, String>>,
+
+    /// Name of the default profile.
+    default: Arc<RwLock<String>>,
+}
+
+impl ComplianceProfiles {
+    /// Create a set holding the GDPR profile, as the default.
+    pub fn new() -> Self {
+        let profiles = Self {
+            profiles: Arc::new(DashMap::new()),
+            users: Arc::new(DashMap::new()),
+            namespaces: Arc::new(DashMap::new()),
+            default: Arc::new(RwLock::new(DEFAULT_PROFILE.to_string())),
+        };
+        profiles.register(ComplianceProfile::gdpr());
+        profiles
+    }
+
+    /// Register a profile, replacing any with the same name.
+    pub fn register(&self, profile: ComplianceProfile) {
+        self.profiles.insert(profile.name.clone(), profile);
+    }
+
+    /// Get a profile by name.
+    pub fn get(&self, name: &str) -> Option<ComplianceProfile> {
+        self.profiles.get(name).map(|p| p.value().clone())
+    }
+
+    /// Set the profile used when none is assigned.
+    pub fn set_default(&self, name: &str) -> Result<()> {
+        self.check(name)?;
+        *self.default.write() = name.to_string();
+        Ok(())
+    }
+
+    /// Assign a profile to a user, e.g. from their residence.
+    pub fn assign_user(&self, user_did: &str, name: &str) -> Result<()> {
+        self.check(name)?;
+        self.users.insert(user_did.to_string(), name.to_string());
+        Ok(())
+    }
+
+    /// Assign a profile to a namespace, e.g. an app serving one market.
+    pub fn assign_namespace(&self, namespace: &str, name: &str) -> Result<()> {
+        self.check(name)?;
+        self.namespaces
+            .insert(namespace.to_string(), name.to_string());
+        Ok(())
+    }
+
+    /// Select the profile governing an operation on a user's data.
+    ///
+    /// The user's profile wins over the namespace's, which wins over the
+    /// default.
+    pub fn resolve(&self, user_did: &str, namespace: Option<&str>) -> ComplianceProfile {
+        let name = self
+            .users
+            .get(user_did)
+            .map(|name| name.value().clone())
+            .or_else(|| namespace.and_then(|ns| self.namespaces.get(ns).map(|n| n.value().clone())))
+            .unwrap_or_else(|| self.default.read().clone());
+        self.get(&name).unwrap_or_else(ComplianceProfile::gdpr)
+    }
+
+    /// Check that a profile is registered.
+    fn check(&self, name: &str) -> Result<()> {
+        if self.profiles.contains_key(name) {
+            Ok(())
+        } else {
+            Err(PrivacyError::UnknownProfile(name.to_string()))
+        }
+    }
+}
+
+impl Default for ComplianceProfiles {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let profiles = ComplianceProfiles::new();
+        profiles.register(ComplianceProfile::ccpa());
+        profiles.register(ComplianceProfile::lgpd());
+        profiles.assign_namespace("app.br", "LGPD").unwrap();
+        profiles.assign_user("did:peer:alice", "CCPA").unwrap();
+
+        assert_eq!(profiles.resolve("did:peer:bob", None).name, "GDPR");
+        assert_eq!(
+            profiles.resolve("did:peer:bob", Some("app.br")).name,
+            "LGPD"
+        );
+        assert_eq!(
+            profiles.resolve("did:peer:alice", Some("app.br")).name,
+            "CCPA"
+        );
+
+        profiles.set_default("LGPD").unwrap();
+        assert_eq!(profiles.resolve("did:peer:bob", None).name, "LGPD");
+        assert!(profiles.assign_user("did:peer:bob", "PIPL").is_err());
+    }
+
+    #[test]
+    fn test_audit_notes() {
+        let note = Some("Retired DEK".to_string());
+        let minimal = ComplianceProfile::gdpr().with_audit_detail(AuditDetail::Minimal);
+        assert_eq!(minimal.audit_notes(note.clone(), Some(100)), None);
+        assert_eq!(
+            ComplianceProfile::ccpa().audit_notes(note.clone(), Some(100)),
+            note
+        );
+        assert_eq!(
+            ComplianceProfile::lgpd()
+                .audit_notes(note, Some(100))
+                .unwrap(),
+            "Retired DEK\nLegal basis: LGPD Article 18\nDue by: 100"
+        );
+        assert_eq!(ComplianceProfile::lgpd().audit_notes(None, None), None);
+        assert_eq!(ComplianceProfile::lgpd().erasure_due_by(0), 15 * DAY);
+    }
+}
@@ -47,6 +47,7 @@ use crate::error::{PrivacyError, Result};
 use automerge::ActorId;
 use blake3;
 use serde::{Deserialize, Serialize};
+use vudo_state::{DocumentHandle, DocumentStore};
 
 /// Pseudonymous actor ID for CRDT operations.
 ///
@@ -138,6 +139,14 @@ impl PseudonymousActorId {
     pub fn same_user(&self, other: &Self) -> bool {
         self.real_did == other.real_did
     }
+
+    /// Bind this pseudonym as the device actor for a document store.
+    ///
+    /// All local writes through the store are then attributed to the
+    /// pseudonym rather than a random Automerge actor.
+    pub fn bind_store(&self, store: &DocumentStore) {
+        store.set_actor(self.actor_id());
+    }
 }
 
 impl PartialEq for PseudonymousActorId {
@@ -200,6 +209,16 @@ impl ActorIdMapper {
     pub fn count(&self) -> usize {
         self.pseudonym_to_did.len()
     }
+
+    /// Resolve the DID of the actor that last wrote a root-level field.
+    ///
+    /// Returns None if the field does not exist or its writer is not known
+    /// locally.
+    pub fn blame(&self, handle: &DocumentHandle, key: &str) -> Result<Option<String>> {
+        Ok(handle
+            .blame(key)?
+            .and_then(|actor_id| self.resolve(&actor_id)))
+    }
 }
 
 impl Default for ActorIdMapper {
@@ -252,6 +271,47 @@ mod tests {
         assert_eq!(resolved, Some("did:peer:alice".to_string()));
     }
 
+    #[test]
+    fn test_bind_store_and_blame() {
+        use automerge::transaction::Transactable;
+        use vudo_state::DocumentId;
+
+        let store = DocumentStore::new();
+        let mapper = ActorIdMapper::new();
+        let alice = PseudonymousActorId::from_did("did:peer:alice").unwrap();
+        let bob = PseudonymousActorId::from_did("did:peer:bob").unwrap();
+        mapper.register(&alice);
+        mapper.register(&bob);
+
+        alice.bind_store(&store);
+        let handle = store.create(DocumentId::new("users", "profile")).unwrap();
+        assert_eq!(handle.actor(), alice.actor_id());
+        handle
+            .update(|doc| {
+                doc.put(automerge::ROOT, "name", "Alice")?;
+                Ok(())
+            })
+            .unwrap();
+
+        bob.bind_store(&store);
+        handle
+            .update(|doc| {
+                doc.put(automerge::ROOT, "bio", "Hi")?;
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(
+            mapper.blame(&handle, "name").unwrap(),
+            Some("did:peer:alice".to_string())
+        );
+        assert_eq!(
+            mapper.blame(&handle, "bio").unwrap(),
+            Some("did:peer:bob".to_string())
+        );
+        assert_eq!(mapper.blame(&handle, "missing").unwrap(), None);
+    }
+
     #[test]
     fn test_same_user() {
         let pseudo1 = PseudonymousActorId::from_did("did:peer:alice").unwrap();
@@ -158,6 +158,42 @@ async fn test_audit_log_completeness() {
     assert_eq!(bob_entries.len(), 1);
 }
 
+#[tokio::test]
+async fn test_third_party_verification() {
+    use ed25519_dalek::SigningKey;
+    use rand::rngs::OsRng;
+    use vudo_privacy::audit::verify_audit_log;
+
+    let controller = SigningKey::generate(&mut OsRng);
+    let engine = GdprComplianceEngine::new()
+        .unwrap()
+        .with_signing_key(controller.clone());
+    engine.crypto().generate_dek("did:peer:alice").unwrap();
+
+    let request = DeletionRequest::all_data("app.example".to_string())
+        .add_public_path("/profile".to_string());
+    let report = engine
+        .execute_deletion("did:peer:alice", request)
+        .await
+        .unwrap();
+    report
+        .crypto_proof
+        .unwrap()
+        .verify(&controller.verifying_key())
+        .unwrap();
+
+    // An auditor holding only the exported log and the controller's key
+    let json = engine.export_audit_log().unwrap();
+    let verification = verify_audit_log(&json, &controller.verifying_key()).unwrap();
+    assert!(verification.is_valid());
+    assert_eq!(verification.verified.len(), 1);
+    assert_eq!(verification.unproven.len(), 2);
+
+    let tampered = json.replacen("did:peer:alice", "did:peer:mallory", 2);
+    let verification = verify_audit_log(&tampered, &controller.verifying_key()).unwrap();
+    assert!(!verification.is_valid());
+}
+
 #[tokio::test]
 async fn test_idempotent_deletion() {
     let engine = GdprComplianceEngine::new().unwrap();
@@ -284,6 +320,9 @@ fn test_deletion_report_serialization() {
         completed_at: 1234567890,
         irreversible: true,
         compliance_note: "Test deletion".to_string(),
+        profile: "GDPR".to_string(),
+        requested_at: 1234567890,
+        due_by: 1234567890 + 30 * 86_400,
         categories_deleted: vec![DataCategory::PersonalData],
         crypto_proof: None,
     };
@@ -86,6 +86,10 @@ async fn main() -> Result<()> {
                 println!("  ✓ Would delete: {}", document_id);
                 success_count += 1;
             }
+            OperationType::Snapshot { document_id, version } => {
+                println!("  ✓ Would snapshot: {} (v{})", document_id, version);
+                success_count += 1;
+            }
         }
     }
 
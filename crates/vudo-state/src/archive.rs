@@ -0,0 +1,412 @@
+//! Portable archive format for backing up and moving a state engine.
+//!
+//! An archive is a single gzip-compressed file holding every document,
+//! every stored snapshot, and the pending operation queue. It lets a node
+//! be restored or migrated to another device without going through P2P sync.
+//!
+//! # Layout
+//!
+//! ```text
+//! gzip(
+//!   magic    "VUDOARC" + format version (1 byte)
+//!   manifest u64 LE length + JSON ArchiveManifest
+//!   blobs    u64 LE length + bytes, in manifest order
+//! )
+//! ```
+//!
+//! Document and snapshot bytes are stored as raw blobs rather than inside the
+//! JSON manifest to keep the archive compact. Blobs of namespaces with a
+//! codec (see [`DocumentStore::codecs`]) are stored encoded, so encrypted
+//! namespaces stay encrypted in the archive; importing needs the same codecs.
+
+use crate::codec::CodecRegistry;
+use crate::document_store::{DocumentId, DocumentMetadata, DocumentStore};
+use crate::error::{Result, StateError};
+use crate::operation_queue::{Operation, OperationQueue};
+use crate::snapshot::{Snapshot, SnapshotMetadata, SnapshotStorage};
+use automerge::AutoCommit;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+/// Magic bytes at the start of every archive.
+const ARCHIVE_MAGIC: &[u8; 7] = b"VUDOARC";
+
+/// Current archive format version.
+pub const ARCHIVE_FORMAT_VERSION: u8 = 1;
+
+/// Archive manifest describing the blobs that follow it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    /// Creation timestamp (Unix epoch milliseconds).
+    pub created_at: u64,
+    /// Document metadata, one entry per document blob.
+    pub documents: Vec<DocumentMetadata>,
+    /// Snapshot metadata, one entry per snapshot blob (after documents).
+    pub snapshots: Vec<SnapshotMetadata>,
+    /// Pending operations.
+    pub operations: Vec<Operation>,
+}
+
+/// Summary of an archive export or import.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveSummary {
+    /// Number of documents written or restored.
+    pub documents: usize,
+    /// Number of existing documents merged with archived state (import only).
+    pub merged_documents: usize,
+    /// Number of snapshots written or restored.
+    pub snapshots: usize,
+    /// Number of operations written or restored.
+    pub operations: usize,
+}
+
+/// Write an archive of the given components to `writer`.
+pub fn write_archive<W: Write>(
+    writer: W,
+    store: &DocumentStore,
+    snapshots: &SnapshotStorage,
+    queue: &OperationQueue,
+) -> Result<ArchiveSummary> {
+    let mut ids = store.list_all();
+    ids.sort_by_key(|id| id.to_string());
+
+    let mut documents = Vec::with_capacity(ids.len());
+    let mut blobs = Vec::with_capacity(ids.len());
+    for id in &ids {
+        let handle = store.get(id)?;
+        blobs.push(store.save_encoded(id)?);
+        documents.push(handle.metadata());
+    }
+
+    // Snapshots taken without the store's codecs are encoded here
+    let mut snapshot_meta = Vec::new();
+    for snapshot in snapshots.all() {
+        let mut metadata = snapshot.metadata;
+        let data = if CodecRegistry::is_encoded(&snapshot.data) {
+            snapshot.data
+        } else {
+            store
+                .codecs()
+                .encode(&metadata.document_id, snapshot.data)?
+        };
+        metadata.size = data.len();
+        snapshot_meta.push(metadata);
+        blobs.push(data);
+    }
+
+    let manifest = ArchiveManifest {
+        created_at: now_millis(),
+        documents,
+        snapshots: snapshot_meta,
+        operations: queue.list(),
+    };
+
+    let summary = ArchiveSummary {
+        documents: manifest.documents.len(),
+        merged_documents: 0,
+        snapshots: manifest.snapshots.len(),
+        operations: manifest.operations.len(),
+    };
+
+    let mut encoder = GzEncoder::new(writer, Compression::default());
+    encoder.write_all(ARCHIVE_MAGIC)?;
+    encoder.write_all(&[ARCHIVE_FORMAT_VERSION])?;
+    write_frame(&mut encoder, &serde_json::to_vec(&manifest)?)?;
+    for blob in &blobs {
+        write_frame(&mut encoder, blob)?;
+    }
+    encoder.finish()?;
+
+    Ok(summary)
+}
+
+/// Read an archive from `reader` into the given components.
+///
+/// Documents that do not exist yet are loaded as-is; documents that already
+/// exist are merged with the archived state, so importing is safe to repeat.
+/// Snapshots already stored for the same document and version, and
+/// operations whose idempotency key is already queued, are skipped.
+pub fn read_archive<R: Read>(
+    reader: R,
+    store: &DocumentStore,
+    snapshots: &SnapshotStorage,
+    queue: &OperationQueue,
+) -> Result<ArchiveSummary> {
+    let mut decoder = GzDecoder::new(reader);
+
+    let mut magic = [0u8; 7];
+    decoder.read_exact(&mut magic)?;
+    if &magic != ARCHIVE_MAGIC {
+        return Err(StateError::ArchiveError("Not a VUDO archive".to_string()));
+    }
+
+    let mut version = [0u8; 1];
+    decoder.read_exact(&mut version)?;
+    if version[0] != ARCHIVE_FORMAT_VERSION {
+        return Err(StateError::ArchiveError(format!(
+            "Unsupported archive format version: {}",
+            version[0]
+        )));
+    }
+
+    let manifest: ArchiveManifest = serde_json::from_slice(&read_frame(&mut decoder)?)
+        .map_err(|e| StateError::DeserializationError(e.to_string()))?;
+
+    let mut summary = ArchiveSummary::default();
+
+    for meta in &manifest.documents {
+        let bytes = store
+            .codecs()
+            .decode(&meta.id, &read_frame(&mut decoder)?)?;
+        restore_document(store, &meta.id, &bytes, &mut summary)?;
+    }
+
+    for meta in manifest.snapshots {
+        let data = read_frame(&mut decoder)?;
+        if snapshots
+            .get_version(&meta.document_id, meta.version)
+            .is_some()
+        {
+            continue;
+        }
+        snapshots.store(Snapshot {
+            metadata: meta,
+            data,
+        })?;
+        summary.snapshots += 1;
+    }
+
+    let existing: Vec<_> = queue.list().into_iter().map(|op| op.id).collect();
+    for op in manifest.operations {
+        if existing.contains(&op.id) {
+            continue;
+        }
+        queue.enqueue(op)?;
+        summary.operations += 1;
+    }
+
+    Ok(summary)
+}
+
+/// Load or merge a single archived document.
+pub(crate) fn restore_document(
+    store: &DocumentStore,
+    id: &DocumentId,
+    bytes: &[u8],
+    summary: &mut ArchiveSummary,
+) -> Result<()> {
+    if store.exists(id) {
+        let mut archived = AutoCommit::load(bytes)?;
+        store.get(id)?.update(|doc| {
+            doc.merge(&mut archived)?;
+            Ok(())
+        })?;
+        summary.merged_documents += 1;
+    } else {
+        store.load(id.clone(), bytes)?;
+    }
+    summary.documents += 1;
+    Ok(())
+}
+
+pub(crate) fn write_frame<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<()> {
+    writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    writer.write_all(bytes)?;
+    Ok(())
+}
+
+pub(crate) fn read_frame<R: Read>(reader: &mut R) -> Result<Vec<u8>> {
+    let mut len = [0u8; 8];
+    reader.read_exact(&mut len)?;
+    let len = u64::from_le_bytes(len) as usize;
+
+    let mut bytes = Vec::new();
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(StateError::ArchiveError("Truncated archive".to_string()));
+    }
+    Ok(bytes)
+}
+
+pub(crate) fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::DocumentCodec;
+    use crate::operation_queue::OperationType;
+    use crate::snapshot::SnapshotManager;
+    use automerge::{transaction::Transactable, ReadDoc, ROOT};
+    use std::sync::Arc;
+
+    /// Test codec standing in for encryption.
+    struct XorCodec(u8);
+
+    impl DocumentCodec for XorCodec {
+        fn name(&self) -> &str {
+            "xor"
+        }
+
+        fn encode(&self, _id: &DocumentId, bytes: &[u8]) -> Result<Vec<u8>> {
+            Ok(bytes.iter().map(|b| b ^ self.0).collect())
+        }
+
+        fn decode(&self, id: &DocumentId, bytes: &[u8]) -> Result<Vec<u8>> {
+            self.encode(id, bytes)
+        }
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    fn populated() -> (DocumentStore, SnapshotStorage, OperationQueue) {
+        let store = DocumentStore::new();
+        let snapshots = SnapshotStorage::new();
+        let queue = OperationQueue::new();
+
+        let alice = store.create(DocumentId::new("users", "alice")).unwrap();
+        alice
+            .update(|doc| {
+                doc.put(ROOT, "name", "Alice")?;
+                Ok(())
+            })
+            .unwrap();
+        store.create(DocumentId::new("posts", "1")).unwrap();
+
+        snapshots.store(Snapshot::from_document(&alice, 1)).unwrap();
+        queue
+            .enqueue(Operation::new(OperationType::Create {
+                document_id: DocumentId::new("users", "alice"),
+            }))
+            .unwrap();
+
+        (store, snapshots, queue)
+    }
+
+    #[test]
+    fn test_archive_roundtrip() {
+        let (store, snapshots, queue) = populated();
+
+        let mut bytes = Vec::new();
+        let written = write_archive(&mut bytes, &store, &snapshots, &queue).unwrap();
+        assert_eq!(written.documents, 2);
+        assert_eq!(written.snapshots, 1);
+        assert_eq!(written.operations, 1);
+
+        let (store2, snapshots2, queue2) =
+            (DocumentStore::new(), SnapshotStorage::new(), OperationQueue::new());
+        let read = read_archive(&bytes[..], &store2, &snapshots2, &queue2).unwrap();
+        assert_eq!(read.documents, 2);
+        assert_eq!(read.merged_documents, 0);
+        assert_eq!(store2.count(), 2);
+        assert_eq!(snapshots2.total_count(), 1);
+        assert_eq!(queue2.len(), 1);
+
+        store2
+            .get(&DocumentId::new("users", "alice"))
+            .unwrap()
+            .read(|doc| {
+                assert!(doc.get(ROOT, "name")?.is_some());
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_archive_import_merges_existing() {
+        let (store, snapshots, queue) = populated();
+        let mut bytes = Vec::new();
+        write_archive(&mut bytes, &store, &snapshots, &queue).unwrap();
+
+        // Importing into the source merges rather than failing.
+        let read = read_archive(&bytes[..], &store, &snapshots, &queue).unwrap();
+        assert_eq!(read.merged_documents, 2);
+        assert_eq!(read.snapshots, 0);
+        assert_eq!(read.operations, 0);
+        assert_eq!(store.count(), 2);
+        assert_eq!(snapshots.total_count(), 1);
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn test_archive_keeps_namespaces_encoded() {
+        let (store, snapshots, queue) = populated();
+        store.codecs().set_codec("users", Arc::new(XorCodec(0x5a)));
+        let alice = store.get(&DocumentId::new("users", "alice")).unwrap();
+        let needle = b"Alice";
+        assert!(contains(&alice.save(), needle));
+
+        // Snapshots taken with the store's codecs are encoded
+        let snapshots = Arc::new(snapshots);
+        let manager =
+            SnapshotManager::new(Arc::clone(&snapshots)).with_codecs(store.codecs().clone());
+        let snapshot = manager.create_snapshot(&alice).unwrap();
+        assert!(!contains(&snapshot.data, needle));
+        assert!(snapshot.decode(store.codecs()).is_ok());
+        manager.compact(&alice).unwrap();
+        let compacted = snapshots.get_latest(&alice.id).unwrap();
+        assert!(!contains(&compacted.data, needle));
+
+        // Documents and the plain snapshot taken without codecs are encoded
+        // in the archive
+        let mut bytes = Vec::new();
+        write_archive(&mut bytes, &store, &snapshots, &queue).unwrap();
+        let mut contents = Vec::new();
+        GzDecoder::new(&bytes[..])
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert!(!contains(&contents, needle));
+
+        let (store2, snapshots2, queue2) = (
+            DocumentStore::new(),
+            SnapshotStorage::new(),
+            OperationQueue::new(),
+        );
+        store2.codecs().set_codec("users", Arc::new(XorCodec(0x5a)));
+        read_archive(&bytes[..], &store2, &snapshots2, &queue2).unwrap();
+        assert_eq!(snapshots2.total_count(), 3);
+        for snapshot in snapshots2.all() {
+            assert!(!contains(&snapshot.data, needle));
+            assert!(snapshot.decode(store2.codecs()).is_ok());
+        }
+        store2
+            .get(&DocumentId::new("users", "alice"))
+            .unwrap()
+            .read(|doc| {
+                assert!(doc.get(ROOT, "name")?.is_some());
+                Ok(())
+            })
+            .unwrap();
+
+        // Without the codec the archive can't be imported
+        let result = read_archive(
+            &bytes[..],
+            &DocumentStore::new(),
+            &SnapshotStorage::new(),
+            &OperationQueue::new(),
+        );
+        assert!(matches!(result, Err(StateError::CodecError(_))));
+    }
+
+    #[test]
+    fn test_archive_rejects_garbage() {
+        let (store, snapshots, queue) =
+            (DocumentStore::new(), SnapshotStorage::new(), OperationQueue::new());
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"NOTANARCHIVE").unwrap();
+        let bytes = encoder.finish().unwrap();
+
+        let result = read_archive(&bytes[..], &store, &snapshots, &queue);
+        assert!(matches!(result, Err(StateError::ArchiveError(_))));
+    }
+}
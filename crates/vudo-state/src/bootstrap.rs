@@ -0,0 +1,370 @@
+//! Cold-start bootstrap bundles for newly linked devices.
+//!
+//! A bootstrap bundle carries everything a new device needs to work offline
+//! right away: a compacted snapshot of every document, the schema version and
+//! access control list of each namespace, and hints for reaching peers. The
+//! device imports the bundle instead of replaying full histories over P2P and
+//! afterwards syncs only the changes made since the recorded heads.
+//!
+//! Unlike an [archive](crate::archive), a bundle holds no operation queue (the
+//! exporting device's pending operations are not the new device's to send)
+//! and is always encoded with a [`DocumentCodec`], normally an encrypting one
+//! keyed to the identity both devices share.
+//!
+//! # Layout
+//!
+//! ```text
+//! magic    "VUDOBST" + format version (1 byte)
+//! codec    u64 LE length + codec name
+//! payload  codec(gzip(
+//!            manifest u64 LE length + JSON BootstrapManifest
+//!            blobs    u64 LE length + bytes, in manifest order
+//!          ))
+//! ```
+//!
+//! The codec encodes the payload as the document [`BOOTSTRAP_NAMESPACE`]`/bundle`.
+
+use crate::archive::{now_millis, read_frame, restore_document, write_frame, ArchiveSummary};
+use crate::codec::DocumentCodec;
+use crate::document_store::{DocumentId, DocumentMetadata, DocumentStore, VersionToken};
+use crate::error::{Result, StateError};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+/// Magic bytes at the start of every bootstrap bundle.
+const BOOTSTRAP_MAGIC: &[u8; 7] = b"VUDOBST";
+
+/// Current bootstrap bundle format version.
+pub const BOOTSTRAP_FORMAT_VERSION: u8 = 1;
+
+/// Namespace of the document ID the bundle payload is encoded as.
+pub const BOOTSTRAP_NAMESPACE: &str = "_bootstrap";
+
+/// A peer the new device can sync with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerHint {
+    /// Peer identifier (node ID or DID).
+    pub peer_id: String,
+    /// Addresses the peer was last reachable at.
+    pub addresses: Vec<String>,
+}
+
+impl PeerHint {
+    /// Create a hint for a peer.
+    pub fn new(peer_id: impl Into<String>, addresses: Vec<String>) -> Self {
+        Self {
+            peer_id: peer_id.into(),
+            addresses,
+        }
+    }
+}
+
+/// A document in a bootstrap bundle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootstrapDocument {
+    /// Document metadata at export time.
+    pub metadata: DocumentMetadata,
+    /// Document heads at export time; sync only needs changes after these.
+    pub heads: VersionToken,
+}
+
+/// Manifest of a bootstrap bundle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootstrapManifest {
+    /// Creation timestamp (Unix epoch milliseconds).
+    pub created_at: u64,
+    /// Documents, one entry per blob.
+    pub documents: Vec<BootstrapDocument>,
+    /// Schema version per namespace.
+    pub schema_versions: BTreeMap<String, Version>,
+    /// Access control list per namespace, in the format of the sync layer.
+    pub acls: BTreeMap<String, serde_json::Value>,
+    /// Peers to sync with after import.
+    pub peer_hints: Vec<PeerHint>,
+}
+
+/// What goes into a bootstrap bundle besides the documents.
+#[derive(Debug, Clone, Default)]
+pub struct BootstrapOptions {
+    namespaces: Option<Vec<String>>,
+    schema_versions: BTreeMap<String, Version>,
+    acls: BTreeMap<String, serde_json::Value>,
+    peer_hints: Vec<PeerHint>,
+}
+
+impl BootstrapOptions {
+    /// Create options exporting every namespace.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Export only the given namespaces (may be called repeatedly).
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespaces
+            .get_or_insert_with(Vec::new)
+            .push(namespace.into());
+        self
+    }
+
+    /// Record the schema version of a namespace.
+    pub fn schema_version(mut self, namespace: impl Into<String>, version: Version) -> Self {
+        self.schema_versions.insert(namespace.into(), version);
+        self
+    }
+
+    /// Record the access control list of a namespace.
+    pub fn acl(mut self, namespace: impl Into<String>, acl: serde_json::Value) -> Self {
+        self.acls.insert(namespace.into(), acl);
+        self
+    }
+
+    /// Add a peer the new device can sync with.
+    pub fn peer_hint(mut self, hint: PeerHint) -> Self {
+        self.peer_hints.push(hint);
+        self
+    }
+
+    fn includes(&self, namespace: &str) -> bool {
+        self.namespaces
+            .as_ref()
+            .map_or(true, |namespaces| namespaces.iter().any(|n| n == namespace))
+    }
+}
+
+/// Document ID the bundle payload is encoded as.
+fn bundle_id() -> DocumentId {
+    DocumentId::new(BOOTSTRAP_NAMESPACE, "bundle")
+}
+
+/// Write a bootstrap bundle of `store` to `writer`.
+pub fn write_bootstrap<W: Write>(
+    mut writer: W,
+    store: &DocumentStore,
+    options: &BootstrapOptions,
+    codec: &dyn DocumentCodec,
+) -> Result<BootstrapManifest> {
+    let mut ids: Vec<DocumentId> = store
+        .list_all()
+        .into_iter()
+        .filter(|id| options.includes(&id.namespace))
+        .collect();
+    ids.sort_by_key(|id| id.to_string());
+
+    let mut documents = Vec::with_capacity(ids.len());
+    let mut blobs = Vec::with_capacity(ids.len());
+    for id in &ids {
+        let handle = store.get(id)?;
+        blobs.push(handle.save());
+        documents.push(BootstrapDocument {
+            metadata: handle.metadata(),
+            heads: handle.version_token(),
+        });
+    }
+
+    let manifest = BootstrapManifest {
+        created_at: now_millis(),
+        documents,
+        schema_versions: options.schema_versions.clone(),
+        acls: options.acls.clone(),
+        peer_hints: options.peer_hints.clone(),
+    };
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    write_frame(&mut encoder, &serde_json::to_vec(&manifest)?)?;
+    for blob in &blobs {
+        write_frame(&mut encoder, blob)?;
+    }
+    let payload = codec.encode(&bundle_id(), &encoder.finish()?)?;
+
+    writer.write_all(BOOTSTRAP_MAGIC)?;
+    writer.write_all(&[BOOTSTRAP_FORMAT_VERSION])?;
+    write_frame(&mut writer, codec.name().as_bytes())?;
+    writer.write_all(&payload)?;
+
+    Ok(manifest)
+}
+
+/// Read a bootstrap bundle from `reader` into `store`.
+///
+/// Documents that already exist are merged with the bundled state, so
+/// importing into a device that has started working is safe.
+pub fn read_bootstrap<R: Read>(
+    mut reader: R,
+    store: &DocumentStore,
+    codec: &dyn DocumentCodec,
+) -> Result<BootstrapManifest> {
+    let mut magic = [0u8; 7];
+    reader.read_exact(&mut magic)?;
+    if &magic != BOOTSTRAP_MAGIC {
+        return Err(StateError::ArchiveError(
+            "Not a VUDO bootstrap bundle".to_string(),
+        ));
+    }
+
+    let mut version = [0u8; 1];
+    reader.read_exact(&mut version)?;
+    if version[0] != BOOTSTRAP_FORMAT_VERSION {
+        return Err(StateError::ArchiveError(format!(
+            "Unsupported bootstrap format version: {}",
+            version[0]
+        )));
+    }
+
+    let codec_name = String::from_utf8_lossy(&read_frame(&mut reader)?).into_owned();
+    if codec_name != codec.name() {
+        return Err(StateError::CodecError(format!(
+            "Bootstrap bundle was encoded with codec '{}', not '{}'",
+            codec_name,
+            codec.name()
+        )));
+    }
+
+    let mut payload = Vec::new();
+    reader.read_to_end(&mut payload)?;
+    let payload = codec.decode(&bundle_id(), &payload)?;
+    let mut decoder = GzDecoder::new(&payload[..]);
+
+    let manifest: BootstrapManifest = serde_json::from_slice(&read_frame(&mut decoder)?)
+        .map_err(|e| StateError::DeserializationError(e.to_string()))?;
+
+    // Read every blob before touching the store so a corrupt bundle imports nothing
+    let blobs = manifest
+        .documents
+        .iter()
+        .map(|_| read_frame(&mut decoder))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut summary = ArchiveSummary::default();
+    for (doc, bytes) in manifest.documents.iter().zip(&blobs) {
+        restore_document(store, &doc.metadata.id, bytes, &mut summary)?;
+    }
+
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::GzipCodec;
+    use automerge::{transaction::Transactable, ReadDoc, ROOT};
+
+    /// Test codec standing in for encryption.
+    struct XorCodec(u8);
+
+    impl DocumentCodec for XorCodec {
+        fn name(&self) -> &str {
+            "xor"
+        }
+
+        fn encode(&self, _id: &DocumentId, bytes: &[u8]) -> Result<Vec<u8>> {
+            Ok(bytes.iter().map(|b| b ^ self.0).collect())
+        }
+
+        fn decode(&self, id: &DocumentId, bytes: &[u8]) -> Result<Vec<u8>> {
+            self.encode(id, bytes)
+        }
+    }
+
+    fn populated() -> DocumentStore {
+        let store = DocumentStore::new();
+        let alice = store.create(DocumentId::new("users", "alice")).unwrap();
+        alice
+            .update(|doc| {
+                doc.put(ROOT, "name", "Alice")?;
+                Ok(())
+            })
+            .unwrap();
+        store.create(DocumentId::new("posts", "1")).unwrap();
+        store
+    }
+
+    #[test]
+    fn test_bootstrap_roundtrip() {
+        let store = populated();
+        let options = BootstrapOptions::new()
+            .schema_version("users", Version::new(2, 1, 0))
+            .acl("users", serde_json::json!({ "read": ["did:peer:alice"] }))
+            .peer_hint(PeerHint::new("node-1", vec!["10.0.0.1:4433".to_string()]));
+
+        let mut bytes = Vec::new();
+        let written = write_bootstrap(&mut bytes, &store, &options, &XorCodec(0x5a)).unwrap();
+        assert_eq!(written.documents.len(), 2);
+
+        let device = DocumentStore::new();
+        let manifest = read_bootstrap(&bytes[..], &device, &XorCodec(0x5a)).unwrap();
+        assert_eq!(manifest.schema_versions["users"], Version::new(2, 1, 0));
+        assert_eq!(manifest.acls["users"]["read"][0], "did:peer:alice");
+        assert_eq!(manifest.peer_hints[0].peer_id, "node-1");
+
+        let alice = device.get(&DocumentId::new("users", "alice")).unwrap();
+        let name = alice
+            .read(|doc| Ok(doc.get(ROOT, "name")?.unwrap().0.to_string()))
+            .unwrap();
+        assert_eq!(name, "\"Alice\"");
+
+        // Heads match, so sync can continue from them with deltas only
+        let doc = manifest
+            .documents
+            .iter()
+            .find(|d| d.metadata.id.namespace == "users")
+            .unwrap();
+        assert_eq!(alice.version_token(), doc.heads);
+    }
+
+    #[test]
+    fn test_bootstrap_namespaces() {
+        let store = populated();
+        let options = BootstrapOptions::new().namespace("posts");
+
+        let mut bytes = Vec::new();
+        write_bootstrap(&mut bytes, &store, &options, &GzipCodec).unwrap();
+
+        let device = DocumentStore::new();
+        read_bootstrap(&bytes[..], &device, &GzipCodec).unwrap();
+        assert_eq!(device.list_all(), vec![DocumentId::new("posts", "1")]);
+    }
+
+    #[test]
+    fn test_bootstrap_wrong_codec() {
+        let store = populated();
+        let mut bytes = Vec::new();
+        write_bootstrap(&mut bytes, &store, &BootstrapOptions::new(), &XorCodec(1)).unwrap();
+
+        let device = DocumentStore::new();
+        assert!(read_bootstrap(&bytes[..], &device, &GzipCodec).is_err());
+        // Same codec, wrong key
+        assert!(read_bootstrap(&bytes[..], &device, &XorCodec(2)).is_err());
+        assert_eq!(device.count(), 0);
+    }
+
+    #[test]
+    fn test_bootstrap_merges_existing() {
+        let store = populated();
+        let mut bytes = Vec::new();
+        write_bootstrap(&mut bytes, &store, &BootstrapOptions::new(), &GzipCodec).unwrap();
+
+        let device = DocumentStore::new();
+        let alice = device.create(DocumentId::new("users", "alice")).unwrap();
+        alice
+            .update(|doc| {
+                doc.put(ROOT, "device", "tablet")?;
+                Ok(())
+            })
+            .unwrap();
+
+        read_bootstrap(&bytes[..], &device, &GzipCodec).unwrap();
+        let alice = device.get(&DocumentId::new("users", "alice")).unwrap();
+        alice
+            .read(|doc| {
+                assert!(doc.get(ROOT, "name")?.is_some());
+                assert!(doc.get(ROOT, "device")?.is_some());
+                Ok(())
+            })
+            .unwrap();
+    }
+}
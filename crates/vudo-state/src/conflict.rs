@@ -0,0 +1,295 @@
+//! Conflict surface API for inspecting and resolving concurrent writes.
+//!
+//! Automerge keeps every value written concurrently to the same map key and
+//! deterministically picks one as the visible "winner". The losing values are
+//! not lost: they remain in the document until a later write supersedes them.
+//! This module exposes those concurrent values with actor and timestamp
+//! metadata so applications (or an AI resolver) can choose a winner explicitly.
+
+use crate::document_store::DocumentHandle;
+use crate::error::{Result, StateError};
+use automerge::{ActorId, AutoCommit, ObjId, ReadDoc, Value, ROOT};
+use std::collections::HashMap;
+
+/// One of several values written concurrently to the same field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConcurrentValue {
+    /// The value.
+    pub value: Value<'static>,
+    /// Actor that wrote the value.
+    pub actor: ActorId,
+    /// Operation counter of the write (Lamport clock).
+    pub counter: u64,
+    /// Timestamp of the change containing the write (Unix epoch milliseconds).
+    pub timestamp: i64,
+}
+
+/// A root-level field with more than one concurrent value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldConflict {
+    /// Field key.
+    pub key: String,
+    /// Concurrent values, ordered so that the last one is the current winner.
+    pub values: Vec<ConcurrentValue>,
+}
+
+impl FieldConflict {
+    /// Get the value Automerge currently resolves the field to.
+    pub fn winner(&self) -> Option<&ConcurrentValue> {
+        self.values.last()
+    }
+
+    /// Get the value written by a specific actor, if any.
+    pub fn by_actor(&self, actor: &ActorId) -> Option<&ConcurrentValue> {
+        self.values.iter().find(|v| &v.actor == actor)
+    }
+}
+
+impl DocumentHandle {
+    /// List all root-level fields that currently have concurrent values.
+    pub fn conflicts(&self) -> Result<Vec<FieldConflict>> {
+        let mut doc = self.doc.write();
+        let timestamps = change_timestamps(&mut doc);
+
+        let keys: Vec<String> = doc.keys(ROOT).collect();
+        let mut conflicts = Vec::new();
+        for key in keys {
+            if let Some(conflict) = field_conflict(&doc, &key, &timestamps)? {
+                conflicts.push(conflict);
+            }
+        }
+        Ok(conflicts)
+    }
+
+    /// Get the concurrent values of a single root-level field.
+    ///
+    /// Returns `None` if the field has at most one value.
+    pub fn conflict(&self, key: &str) -> Result<Option<FieldConflict>> {
+        let mut doc = self.doc.write();
+        let timestamps = change_timestamps(&mut doc);
+        field_conflict(&doc, key, &timestamps)
+    }
+
+    /// Resolve a conflict by picking one of its concurrent values.
+    ///
+    /// The chosen value is written again so that it supersedes every
+    /// concurrent value; peers converge on it after the next sync. Only
+    /// scalar values can be chosen.
+    pub fn resolve_conflict(&self, key: &str, winner: &ConcurrentValue) -> Result<()> {
+        let scalar = match &winner.value {
+            Value::Scalar(s) => s.as_ref().clone(),
+            Value::Object(_) => {
+                return Err(StateError::ConflictResolutionFailed(format!(
+                    "Cannot pick an object value as winner for '{}'",
+                    key
+                )))
+            }
+        };
+
+        let current = self.conflict(key)?;
+        let known = current
+            .as_ref()
+            .map(|c| {
+                c.values
+                    .iter()
+                    .any(|v| v.actor == winner.actor && v.counter == winner.counter)
+            })
+            .unwrap_or(false);
+        if !known {
+            return Err(StateError::ConflictResolutionFailed(format!(
+                "Value is not a concurrent value of '{}'",
+                key
+            )));
+        }
+
+        self.update(|doc| {
+            use automerge::transaction::Transactable;
+            doc.put(ROOT, key, scalar)?;
+            Ok(())
+        })
+    }
+}
+
+/// Map (actor, counter) ranges of every change to its timestamp.
+fn change_timestamps(doc: &mut AutoCommit) -> HashMap<ActorId, Vec<(u64, u64, i64)>> {
+    let mut map: HashMap<ActorId, Vec<(u64, u64, i64)>> = HashMap::new();
+    for change in doc.get_changes(&[]) {
+        map.entry(change.actor_id().clone()).or_default().push((
+            change.start_op().get(),
+            change.max_op(),
+            change.timestamp(),
+        ));
+    }
+    map
+}
+
+fn field_conflict(
+    doc: &AutoCommit,
+    key: &str,
+    timestamps: &HashMap<ActorId, Vec<(u64, u64, i64)>>,
+) -> Result<Option<FieldConflict>> {
+    let values = doc.get_all(ROOT, key)?;
+    if values.len() < 2 {
+        return Ok(None);
+    }
+
+    let values = values
+        .into_iter()
+        .filter_map(|(value, id)| match id {
+            ObjId::Id(counter, actor, _) => {
+                let timestamp = timestamps
+                    .get(&actor)
+                    .and_then(|ranges| {
+                        ranges
+                            .iter()
+                            .find(|(start, max, _)| counter >= *start && counter <= *max)
+                    })
+                    .map(|(_, _, ts)| *ts)
+                    .unwrap_or_default();
+                Some(ConcurrentValue {
+                    value: value.into_owned(),
+                    actor,
+                    counter,
+                    timestamp,
+                })
+            }
+            ObjId::Root => None,
+        })
+        .collect();
+
+    Ok(Some(FieldConflict {
+        key: key.to_string(),
+        values,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::document_store::{DocumentId, DocumentStore};
+    use crate::error::StateError;
+    use automerge::{
+        transaction::{CommitOptions, Transactable},
+        ActorId, AutoCommit, ReadDoc, ScalarValue, Value, ROOT,
+    };
+
+    /// Create a document with a concurrent write to "title" from two actors.
+    fn conflicted() -> (DocumentStore, crate::DocumentHandle, ActorId, ActorId) {
+        let store = DocumentStore::new();
+        let handle = store.create(DocumentId::new("notes", "1")).unwrap();
+        let alice = ActorId::from(vec![0xa1u8; 16]);
+        let bob = ActorId::from(vec![0xb0u8; 16]);
+
+        handle.set_actor(alice.clone());
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "title", "base")?;
+                doc.put(ROOT, "body", "text")?;
+                Ok(())
+            })
+            .unwrap();
+
+        let mut remote = AutoCommit::load(&handle.save())
+            .unwrap()
+            .with_actor(bob.clone());
+        remote.put(ROOT, "title", "from bob").unwrap();
+        remote.commit_with(CommitOptions::default().with_time(1_700_000_000_000));
+
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "title", "from alice")?;
+                Ok(())
+            })
+            .unwrap();
+        handle
+            .update(|doc| {
+                doc.merge(&mut remote)?;
+                Ok(())
+            })
+            .unwrap();
+
+        (store, handle, alice, bob)
+    }
+
+    #[test]
+    fn test_no_conflicts() {
+        let store = DocumentStore::new();
+        let handle = store.create(DocumentId::new("notes", "1")).unwrap();
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "title", "only")?;
+                Ok(())
+            })
+            .unwrap();
+
+        assert!(handle.conflicts().unwrap().is_empty());
+        assert!(handle.conflict("title").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_list_conflicts() {
+        let (_store, handle, alice, bob) = conflicted();
+
+        let conflicts = handle.conflicts().unwrap();
+        assert_eq!(conflicts.len(), 1);
+
+        let conflict = &conflicts[0];
+        assert_eq!(conflict.key, "title");
+        assert_eq!(conflict.values.len(), 2);
+        assert!(conflict.by_actor(&alice).is_some());
+        assert!(conflict.by_actor(&bob).is_some());
+        assert!(conflict.values.iter().all(|v| v.timestamp > 0));
+
+        // The reported winner is what a plain read returns.
+        let winner = conflict.winner().unwrap();
+        handle
+            .read(|doc| {
+                let (value, _) = doc.get(ROOT, "title")?.unwrap();
+                assert_eq!(value.into_owned(), winner.value);
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_resolve_conflict() {
+        let (_store, handle, alice, _bob) = conflicted();
+
+        let conflict = handle.conflict("title").unwrap().unwrap();
+        let alice_value = conflict.by_actor(&alice).unwrap().clone();
+        handle.resolve_conflict("title", &alice_value).unwrap();
+
+        assert!(handle.conflict("title").unwrap().is_none());
+        handle
+            .read(|doc| {
+                let (value, _) = doc.get(ROOT, "title")?.unwrap();
+                assert_eq!(
+                    value,
+                    Value::Scalar(std::borrow::Cow::Owned(ScalarValue::Str(
+                        "from alice".into()
+                    )))
+                );
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_resolve_conflict_rejects_unknown_value() {
+        let (_store, handle, alice, _bob) = conflicted();
+
+        let mut bogus = handle
+            .conflict("title")
+            .unwrap()
+            .unwrap()
+            .by_actor(&alice)
+            .unwrap()
+            .clone();
+        bogus.counter += 1000;
+
+        let result = handle.resolve_conflict("title", &bogus);
+        assert!(matches!(
+            result,
+            Err(StateError::ConflictResolutionFailed(_))
+        ));
+    }
+}
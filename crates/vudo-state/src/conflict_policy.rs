@@ -0,0 +1,373 @@
+//! Runtime conflict-resolution policies.
+//!
+//! The `@crdt` strategy compiled into a gen decides how concurrent writes
+//! merge, and Automerge breaks remaining ties deterministically. Applications
+//! can override that tie-breaking per field at runtime by registering a
+//! [`ConflictPolicy`] keyed by `(gen, field)`. The registry is consulted after
+//! remote changes are applied (see [`crate::StateEngine::apply_remote_changes`]).
+//!
+//! The gen name is the namespace of the documents that hold its instances.
+
+use crate::conflict::{ConcurrentValue, FieldConflict};
+use crate::document_store::{DocumentHandle, DocumentId};
+use crate::error::Result;
+use automerge::{ActorId, ScalarValue, Value};
+use dashmap::DashMap;
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::Arc;
+
+/// Field name matching every field of a gen.
+pub const ANY_FIELD: &str = "*";
+
+/// External decision-maker for conflicts (e.g., an AI resolver).
+pub trait ConflictHandler: Send + Sync {
+    /// Choose a winner among the concurrent values.
+    ///
+    /// Returning `None` leaves the conflict unresolved.
+    fn choose(&self, document_id: &DocumentId, conflict: &FieldConflict)
+        -> Option<ConcurrentValue>;
+}
+
+/// How a conflicting field is resolved.
+#[derive(Clone, Default)]
+pub enum ConflictPolicy {
+    /// Keep the compiled strategy's deterministic winner.
+    #[default]
+    Default,
+    /// Prefer the value written by the local actor.
+    PreferLocal,
+    /// Prefer a value written by a remote actor.
+    PreferRemote,
+    /// Prefer the highest value (numbers numerically, strings lexically).
+    PreferHighest,
+    /// Prefer the lowest value (numbers numerically, strings lexically).
+    PreferLowest,
+    /// Leave the conflict for the user to resolve.
+    AskUser,
+    /// Delegate the choice to a handler.
+    Delegate(Arc<dyn ConflictHandler>),
+}
+
+impl fmt::Debug for ConflictPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictPolicy::Default => write!(f, "Default"),
+            ConflictPolicy::PreferLocal => write!(f, "PreferLocal"),
+            ConflictPolicy::PreferRemote => write!(f, "PreferRemote"),
+            ConflictPolicy::PreferHighest => write!(f, "PreferHighest"),
+            ConflictPolicy::PreferLowest => write!(f, "PreferLowest"),
+            ConflictPolicy::AskUser => write!(f, "AskUser"),
+            ConflictPolicy::Delegate(_) => write!(f, "Delegate(..)"),
+        }
+    }
+}
+
+/// A conflict awaiting a user decision.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingConflict {
+    /// Document containing the conflict.
+    pub document_id: DocumentId,
+    /// The conflicting field.
+    pub conflict: FieldConflict,
+}
+
+/// Outcome of applying policies to a document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConflictReport {
+    /// Fields resolved by a policy.
+    pub resolved: Vec<String>,
+    /// Fields left unresolved (default policy, no applicable winner, or awaiting the user).
+    pub unresolved: Vec<String>,
+}
+
+/// Registry of conflict policies keyed by `(gen, field)`.
+#[derive(Debug, Default)]
+pub struct ConflictPolicyRegistry {
+    /// Policies by (gen, field).
+    policies: DashMap<(String, String), ConflictPolicy>,
+    /// Conflicts awaiting a user decision, by (document, field).
+    pending: DashMap<(DocumentId, String), FieldConflict>,
+}
+
+impl ConflictPolicyRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the policy for a field of a gen.
+    ///
+    /// Use [`ANY_FIELD`] to set the fallback for all fields of the gen.
+    pub fn set_policy(&self, gen: &str, field: &str, policy: ConflictPolicy) {
+        self.policies
+            .insert((gen.to_string(), field.to_string()), policy);
+    }
+
+    /// Remove the policy for a field of a gen.
+    pub fn remove_policy(&self, gen: &str, field: &str) -> Option<ConflictPolicy> {
+        self.policies
+            .remove(&(gen.to_string(), field.to_string()))
+            .map(|(_, policy)| policy)
+    }
+
+    /// Get the effective policy for a field of a gen.
+    pub fn policy_for(&self, gen: &str, field: &str) -> ConflictPolicy {
+        self.policies
+            .get(&(gen.to_string(), field.to_string()))
+            .or_else(|| self.policies.get(&(gen.to_string(), ANY_FIELD.to_string())))
+            .map(|entry| entry.value().clone())
+            .unwrap_or_default()
+    }
+
+    /// Apply the registered policies to every conflict in a document.
+    pub fn resolve(&self, handle: &DocumentHandle) -> Result<ConflictReport> {
+        let local = handle.actor();
+        let mut report = ConflictReport::default();
+
+        for conflict in handle.conflicts()? {
+            let policy = self.policy_for(&handle.id.namespace, &conflict.key);
+            let winner = match &policy {
+                ConflictPolicy::AskUser => {
+                    self.pending
+                        .insert((handle.id.clone(), conflict.key.clone()), conflict.clone());
+                    None
+                }
+                ConflictPolicy::Delegate(handler) => handler.choose(&handle.id, &conflict),
+                other => choose(other, &conflict, &local),
+            };
+
+            match winner {
+                Some(value) => {
+                    handle.resolve_conflict(&conflict.key, &value)?;
+                    report.resolved.push(conflict.key);
+                }
+                None => report.unresolved.push(conflict.key),
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// List conflicts awaiting a user decision.
+    pub fn pending(&self) -> Vec<PendingConflict> {
+        self.pending
+            .iter()
+            .map(|entry| PendingConflict {
+                document_id: entry.key().0.clone(),
+                conflict: entry.value().clone(),
+            })
+            .collect()
+    }
+
+    /// Resolve a pending conflict with the user's choice.
+    pub fn resolve_pending(
+        &self,
+        handle: &DocumentHandle,
+        key: &str,
+        winner: &ConcurrentValue,
+    ) -> Result<()> {
+        handle.resolve_conflict(key, winner)?;
+        self.pending.remove(&(handle.id.clone(), key.to_string()));
+        Ok(())
+    }
+}
+
+/// Pick a winner for the built-in policies.
+fn choose(
+    policy: &ConflictPolicy,
+    conflict: &FieldConflict,
+    local: &ActorId,
+) -> Option<ConcurrentValue> {
+    match policy {
+        ConflictPolicy::PreferLocal => conflict.by_actor(local).cloned(),
+        ConflictPolicy::PreferRemote => conflict
+            .values
+            .iter()
+            .rev()
+            .find(|v| &v.actor != local)
+            .cloned(),
+        ConflictPolicy::PreferHighest => extreme(conflict, Ordering::Greater),
+        ConflictPolicy::PreferLowest => extreme(conflict, Ordering::Less),
+        _ => None,
+    }
+}
+
+/// Find the value that compares `target` against all others.
+///
+/// Returns `None` if any pair of values cannot be compared.
+fn extreme(conflict: &FieldConflict, target: Ordering) -> Option<ConcurrentValue> {
+    let mut best = conflict.values.first()?;
+    for value in &conflict.values[1..] {
+        if compare(&value.value, &best.value)? == target {
+            best = value;
+        }
+    }
+    Some(best.clone())
+}
+
+fn compare(a: &Value<'_>, b: &Value<'_>) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Scalar(a), Value::Scalar(b)) => match (a.as_ref(), b.as_ref()) {
+            (ScalarValue::Str(a), ScalarValue::Str(b)) => Some(a.cmp(b)),
+            (a, b) => a.to_f64()?.partial_cmp(&b.to_f64()?),
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document_store::DocumentStore;
+    use automerge::transaction::{CommitOptions, Transactable};
+    use automerge::{AutoCommit, ReadDoc, ROOT};
+
+    /// Create a document where "score" and "title" conflict between a local
+    /// and a remote actor.
+    fn conflicted(store: &DocumentStore) -> (DocumentHandle, ActorId) {
+        let handle = store.create(DocumentId::new("players", "1")).unwrap();
+        let local = ActorId::from(vec![0x01u8; 16]);
+        handle.set_actor(local.clone());
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "score", 0i64)?;
+                Ok(())
+            })
+            .unwrap();
+
+        let mut remote = AutoCommit::load(&handle.save())
+            .unwrap()
+            .with_actor(ActorId::from(vec![0x02u8; 16]));
+        remote.put(ROOT, "score", 5i64).unwrap();
+        remote.put(ROOT, "title", "remote").unwrap();
+        remote.commit_with(CommitOptions::default().with_time(1));
+
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "score", 9i64)?;
+                doc.put(ROOT, "title", "local")?;
+                Ok(())
+            })
+            .unwrap();
+        handle.load_incremental(&remote.save()).unwrap();
+
+        (handle, local)
+    }
+
+    fn get(handle: &DocumentHandle, key: &str) -> ScalarValue {
+        handle
+            .read(|doc| match doc.get(ROOT, key)? {
+                Some((Value::Scalar(s), _)) => Ok(s.into_owned()),
+                _ => panic!("missing {}", key),
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_policy_lookup_falls_back_to_gen_default() {
+        let registry = ConflictPolicyRegistry::new();
+        assert!(matches!(
+            registry.policy_for("players", "score"),
+            ConflictPolicy::Default
+        ));
+
+        registry.set_policy("players", ANY_FIELD, ConflictPolicy::PreferLocal);
+        registry.set_policy("players", "score", ConflictPolicy::PreferHighest);
+        assert!(matches!(
+            registry.policy_for("players", "score"),
+            ConflictPolicy::PreferHighest
+        ));
+        assert!(matches!(
+            registry.policy_for("players", "title"),
+            ConflictPolicy::PreferLocal
+        ));
+
+        registry.remove_policy("players", "score");
+        assert!(matches!(
+            registry.policy_for("players", "score"),
+            ConflictPolicy::PreferLocal
+        ));
+    }
+
+    #[test]
+    fn test_prefer_highest_and_remote() {
+        let store = DocumentStore::new();
+        let (handle, _) = conflicted(&store);
+
+        let registry = ConflictPolicyRegistry::new();
+        registry.set_policy("players", "score", ConflictPolicy::PreferLowest);
+        registry.set_policy("players", "title", ConflictPolicy::PreferRemote);
+
+        let report = registry.resolve(&handle).unwrap();
+        assert_eq!(report.resolved.len(), 2);
+        assert!(report.unresolved.is_empty());
+        assert_eq!(get(&handle, "score"), ScalarValue::Int(5));
+        assert_eq!(get(&handle, "title"), ScalarValue::Str("remote".into()));
+        assert!(handle.conflicts().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_prefer_local() {
+        let store = DocumentStore::new();
+        let (handle, _) = conflicted(&store);
+
+        let registry = ConflictPolicyRegistry::new();
+        registry.set_policy("players", ANY_FIELD, ConflictPolicy::PreferLocal);
+        registry.resolve(&handle).unwrap();
+
+        assert_eq!(get(&handle, "score"), ScalarValue::Int(9));
+        assert_eq!(get(&handle, "title"), ScalarValue::Str("local".into()));
+    }
+
+    #[test]
+    fn test_ask_user_defers() {
+        let store = DocumentStore::new();
+        let (handle, local) = conflicted(&store);
+
+        let registry = ConflictPolicyRegistry::new();
+        registry.set_policy("players", "title", ConflictPolicy::AskUser);
+
+        let report = registry.resolve(&handle).unwrap();
+        assert!(report.resolved.is_empty());
+        assert_eq!(report.unresolved.len(), 2);
+
+        let pending = registry.pending();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].conflict.key, "title");
+
+        let choice = pending[0].conflict.by_actor(&local).unwrap().clone();
+        registry.resolve_pending(&handle, "title", &choice).unwrap();
+        assert!(registry.pending().is_empty());
+        assert_eq!(get(&handle, "title"), ScalarValue::Str("local".into()));
+    }
+
+    struct LongestString;
+
+    impl ConflictHandler for LongestString {
+        fn choose(&self, _: &DocumentId, conflict: &FieldConflict) -> Option<ConcurrentValue> {
+            conflict
+                .values
+                .iter()
+                .max_by_key(|v| v.value.to_str().map(str::len).unwrap_or(0))
+                .cloned()
+        }
+    }
+
+    #[test]
+    fn test_delegate_handler() {
+        let store = DocumentStore::new();
+        let (handle, _) = conflicted(&store);
+
+        let registry = ConflictPolicyRegistry::new();
+        registry.set_policy(
+            "players",
+            "title",
+            ConflictPolicy::Delegate(Arc::new(LongestString)),
+        );
+
+        let report = registry.resolve(&handle).unwrap();
+        assert_eq!(report.resolved, vec!["title".to_string()]);
+        assert_eq!(get(&handle, "title"), ScalarValue::Str("remote".into()));
+    }
+}
@@ -1,13 +1,26 @@
 //! Document store for managing Automerge documents.
 
+use crate::codec::CodecRegistry;
 use crate::error::{Result, StateError};
-use automerge::AutoCommit;
+use automerge::sync::{self, SyncDoc};
+use automerge::{transaction::CommitOptions, ActorId, AutoCommit, ChangeHash, ReadDoc, ROOT};
 use dashmap::DashMap;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Chunk size of document reads and writes.
+///
+/// Automerge encodes and decodes whole documents, so documents are still
+/// fully buffered in memory; chunking only bounds the size of each I/O call.
+pub const IO_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Separator between an application and its namespace in virtualized
+/// namespaces (`<app>:<namespace>`).
+pub const APP_SEPARATOR: char = ':';
+
 /// Document identifier.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct DocumentId {
@@ -42,6 +55,116 @@ impl DocumentId {
     pub fn to_string(&self) -> String {
         format!("{}/{}", self.namespace, self.key)
     }
+
+    /// Derive a document ID whose key is a hash of `parts`.
+    ///
+    /// Parts are hashed with their lengths, so `["ab", "c"]` and
+    /// `["a", "bc"]` derive different keys. Uses the default
+    /// [`KeyDerivation`].
+    pub fn derive<P: AsRef<[u8]>>(namespace: impl Into<String>, parts: &[P]) -> Self {
+        Self::derive_with(namespace, parts, &KeyDerivation::default())
+    }
+
+    /// Derive a document ID whose key is a hash of `parts`, with a custom
+    /// derivation.
+    pub fn derive_with<P: AsRef<[u8]>>(
+        namespace: impl Into<String>,
+        parts: &[P],
+        derivation: &KeyDerivation,
+    ) -> Self {
+        Self::new(namespace, derivation.derive_key(parts))
+    }
+
+    /// Get the ID of this document in an application's virtual namespace.
+    pub fn virtualize(&self, app: &str) -> Self {
+        Self::new(
+            format!("{}{}{}", app, APP_SEPARATOR, self.namespace),
+            self.key.clone(),
+        )
+    }
+
+    /// Get the application owning a virtualized ID.
+    pub fn app(&self) -> Option<&str> {
+        self.namespace.split_once(APP_SEPARATOR).map(|(app, _)| app)
+    }
+
+    /// Get the ID as seen by an application, or `None` if the document
+    /// belongs to another application.
+    pub fn devirtualize(&self, app: &str) -> Option<Self> {
+        match self.namespace.split_once(APP_SEPARATOR) {
+            Some((owner, namespace)) if owner == app => {
+                Some(Self::new(namespace, self.key.clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Hash function used to derive document keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyHash {
+    /// BLAKE3.
+    #[default]
+    Blake3,
+    /// SHA-256.
+    Sha256,
+}
+
+/// How document keys are derived from arbitrary parts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyDerivation {
+    /// Hash function.
+    pub hash: KeyHash,
+    /// Key length in bytes, at most 32. Keys are hex-encoded, so twice as
+    /// many characters long.
+    pub key_bytes: usize,
+    /// Domain separation context. Deployments using different contexts
+    /// derive unrelated keys from the same parts.
+    pub context: String,
+}
+
+impl Default for KeyDerivation {
+    fn default() -> Self {
+        Self {
+            hash: KeyHash::Blake3,
+            key_bytes: 16,
+            context: "vudo-state document id v1".to_string(),
+        }
+    }
+}
+
+impl KeyDerivation {
+    /// Derive a document key from `parts`.
+    pub fn derive_key<P: AsRef<[u8]>>(&self, parts: &[P]) -> String {
+        let digest: [u8; 32] = match self.hash {
+            KeyHash::Blake3 => {
+                let mut hasher = blake3::Hasher::new_derive_key(&self.context);
+                for part in parts {
+                    let part = part.as_ref();
+                    hasher.update(&(part.len() as u64).to_le_bytes());
+                    hasher.update(part);
+                }
+                *hasher.finalize().as_bytes()
+            }
+            KeyHash::Sha256 => {
+                use sha2::{Digest, Sha256};
+                let mut hasher = Sha256::new();
+                hasher.update((self.context.len() as u64).to_le_bytes());
+                hasher.update(self.context.as_bytes());
+                for part in parts {
+                    let part = part.as_ref();
+                    hasher.update((part.len() as u64).to_le_bytes());
+                    hasher.update(part);
+                }
+                hasher.finalize().into()
+            }
+        };
+        digest[..self.key_bytes.min(32)]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
 }
 
 impl std::fmt::Display for DocumentId {
@@ -60,11 +183,60 @@ pub struct DocumentMetadata {
     /// Last modification timestamp (Unix epoch milliseconds).
     pub last_modified: u64,
     /// Document size in bytes.
+    ///
+    /// Exact after a load or save; between saves it grows by the encoded
+    /// size of each change, so it may overestimate the compacted size.
     pub size: usize,
     /// Document version (number of changes).
     pub version: u64,
 }
 
+/// Token identifying a document version for optimistic concurrency.
+///
+/// The token is the sorted set of document heads, so it changes whenever a
+/// change is applied, whether made locally or merged from a peer. Its string
+/// form (dot-separated hex hashes) is suitable for use as an HTTP ETag.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct VersionToken(Vec<ChangeHash>);
+
+impl VersionToken {
+    fn from_heads(mut heads: Vec<ChangeHash>) -> Self {
+        heads.sort();
+        Self(heads)
+    }
+
+    /// Get the document heads this token represents.
+    pub fn heads(&self) -> &[ChangeHash] {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for VersionToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let hashes: Vec<String> = self.0.iter().map(|h| h.to_string()).collect();
+        write!(f, "{}", hashes.join("."))
+    }
+}
+
+impl std::str::FromStr for VersionToken {
+    type Err = StateError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.is_empty() {
+            return Ok(Self(Vec::new()));
+        }
+        let heads = s
+            .split('.')
+            .map(|h| {
+                h.parse::<ChangeHash>().map_err(|e| {
+                    StateError::DeserializationError(format!("Invalid version token: {}", e))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::from_heads(heads))
+    }
+}
+
 /// A handle to an Automerge document.
 #[derive(Clone)]
 pub struct DocumentHandle {
@@ -113,20 +285,83 @@ impl DocumentHandle {
         F: FnOnce(&mut AutoCommit) -> Result<T>,
     {
         let mut doc = self.doc.write();
-        let result = f(&mut *doc)?;
+        self.apply_update(&mut doc, f)
+    }
 
-        // Update metadata
-        let mut meta = self.metadata.write();
-        meta.last_modified = SystemTime::now()
+    /// Get the current version token of the document.
+    pub fn version_token(&self) -> VersionToken {
+        VersionToken::from_heads(self.doc.write().get_heads())
+    }
+
+    /// Read from the document and return the version token of the state read.
+    ///
+    /// Pass the token to [`DocumentHandle::update_if`] for compare-and-swap
+    /// semantics.
+    pub fn read_versioned<F, T>(&self, f: F) -> Result<(T, VersionToken)>
+    where
+        F: FnOnce(&AutoCommit) -> Result<T>,
+    {
+        let mut doc = self.doc.write();
+        let token = VersionToken::from_heads(doc.get_heads());
+        Ok((f(&doc)?, token))
+    }
+
+    /// Update the document only if it is still at version `expected`.
+    ///
+    /// Fails with [`StateError::VersionConflict`] if any change, local or
+    /// merged from a peer, was applied since the token was obtained. This
+    /// layers compare-and-swap on top of the CRDT without affecting merges.
+    /// Returns the result of `f` and the new version token.
+    pub fn update_if<F, T>(&self, expected: &VersionToken, f: F) -> Result<(T, VersionToken)>
+    where
+        F: FnOnce(&mut AutoCommit) -> Result<T>,
+    {
+        let mut doc = self.doc.write();
+        let actual = VersionToken::from_heads(doc.get_heads());
+        if &actual != expected {
+            return Err(StateError::VersionConflict {
+                expected: expected.to_string(),
+                actual: actual.to_string(),
+            });
+        }
+
+        let result = self.apply_update(&mut doc, f)?;
+        Ok((result, VersionToken::from_heads(doc.get_heads())))
+    }
+
+    /// Run a transaction function on a locked document and commit it.
+    fn apply_update<F, T>(&self, doc: &mut AutoCommit, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut AutoCommit) -> Result<T>,
+    {
+        let heads_before = doc.get_heads();
+        let result = f(doc)?;
+
+        // Commit with a timestamp so change history carries wall-clock time
+        let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_millis() as u64;
-        meta.size = doc.save().len();
+        doc.commit_with(CommitOptions::default().with_time(now as i64));
+
+        // Update metadata
+        let mut meta = self.metadata.write();
+        meta.last_modified = now;
+        // Account for the new changes only, rather than re-encoding the
+        // whole document on every update
+        meta.size += doc.save_after(&heads_before).len();
         meta.version += 1;
 
         Ok(result)
     }
 
+    /// Get the approximate encoded size of the document in bytes.
+    ///
+    /// Unlike `save().len()` this does not serialize the document.
+    pub fn size_hint(&self) -> usize {
+        self.metadata.read().size
+    }
+
     /// Read from the document.
     pub fn read<F, T>(&self, f: F) -> Result<T>
     where
@@ -138,19 +373,134 @@ impl DocumentHandle {
 
     /// Save the document to bytes.
     pub fn save(&self) -> Vec<u8> {
-        self.doc.write().save()
+        let bytes = self.doc.write().save();
+        self.metadata.write().size = bytes.len();
+        bytes
+    }
+
+    /// Save the document to a writer in chunks, returning the bytes written.
+    ///
+    /// The whole document is encoded into memory first (Automerge has no
+    /// incremental encoder), then written in [`IO_CHUNK_SIZE`] chunks.
+    pub fn save_to<W: Write>(&self, mut writer: W) -> Result<usize> {
+        let bytes = self.save();
+        for chunk in bytes.chunks(IO_CHUNK_SIZE) {
+            writer.write_all(chunk)?;
+        }
+        writer.flush()?;
+        Ok(bytes.len())
+    }
+
+    /// Save only the changes made after `heads`.
+    ///
+    /// Together with a snapshot taken at `heads`, this is the change suffix
+    /// accepted by [`DocumentHandle::load_incremental`].
+    pub fn save_after(&self, heads: &[ChangeHash]) -> Vec<u8> {
+        self.doc.write().save_after(heads)
+    }
+
+    /// Apply a change suffix to the document.
+    ///
+    /// Changes the document already has are skipped. Returns the number of
+    /// operations applied.
+    pub fn load_incremental(&self, bytes: &[u8]) -> Result<usize> {
+        let applied = self.doc.write().load_incremental(bytes)?;
+        if applied > 0 {
+            let mut meta = self.metadata.write();
+            meta.last_modified = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64;
+            meta.size += bytes.len();
+            meta.version += 1;
+        }
+        Ok(applied)
+    }
+
+    /// Generate the next Automerge sync message for a peer.
+    ///
+    /// `state` is the sync state of this document with the peer. Returns
+    /// `None` when the peer is up to date or a message is still in flight.
+    pub fn generate_sync_message(&self, state: &mut sync::State) -> Option<sync::Message> {
+        self.doc.write().sync().generate_sync_message(state)
+    }
+
+    /// Apply an Automerge sync message from a peer.
+    ///
+    /// Returns whether the message changed the document.
+    pub fn receive_sync_message(
+        &self,
+        state: &mut sync::State,
+        message: sync::Message,
+    ) -> Result<bool> {
+        let mut doc = self.doc.write();
+        let heads_before = doc.get_heads();
+        doc.sync().receive_sync_message(state, message)?;
+        if doc.get_heads() == heads_before {
+            return Ok(false);
+        }
+
+        let mut meta = self.metadata.write();
+        meta.last_modified = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        meta.size += doc.save_after(&heads_before).len();
+        meta.version += 1;
+        Ok(true)
     }
 
     /// Get the number of changes in the document.
     pub fn change_count(&self) -> usize {
         self.doc.write().get_changes(&[]).len()
     }
+
+    /// Get the current heads (latest change hashes) of the document.
+    pub fn heads(&self) -> Vec<ChangeHash> {
+        self.doc.write().get_heads()
+    }
+
+    /// Check whether the document contains every change in `heads`.
+    pub fn contains_heads(&self, heads: &[ChangeHash]) -> bool {
+        let doc = self.doc.write();
+        heads
+            .iter()
+            .all(|hash| doc.get_change_by_hash(hash).is_some())
+    }
+
+    /// Get the actor ID used for local writes to this document.
+    pub fn actor(&self) -> ActorId {
+        self.doc.read().get_actor().clone()
+    }
+
+    /// Set the actor ID used for subsequent local writes to this document.
+    ///
+    /// Changes already made under a previous actor keep their attribution.
+    pub fn set_actor(&self, actor: ActorId) {
+        self.doc.write().set_actor(actor);
+    }
+
+    /// Get the actor that last wrote a root-level field.
+    ///
+    /// Returns `None` if the field does not exist. When concurrent writes
+    /// are unresolved, the actor of the winning value is returned.
+    pub fn blame(&self, key: &str) -> Result<Option<ActorId>> {
+        let doc = self.doc.read();
+        Ok(doc.get(ROOT, key)?.and_then(|(_, id)| match id {
+            automerge::ObjId::Id(_, actor, _) => Some(actor),
+            automerge::ObjId::Root => None,
+        }))
+    }
 }
 
 /// Document store for managing multiple Automerge documents.
 pub struct DocumentStore {
     /// Map of document ID to document handle.
     documents: DashMap<DocumentId, DocumentHandle>,
+    /// Actor ID bound to this device (applied to created and loaded documents).
+    actor: RwLock<Option<ActorId>>,
+    /// Per-namespace codecs applied to encoded saves and loads.
+    codecs: CodecRegistry,
 }
 
 impl DocumentStore {
@@ -158,6 +508,41 @@ impl DocumentStore {
     pub fn new() -> Self {
         Self {
             documents: DashMap::new(),
+            actor: RwLock::new(None),
+            codecs: CodecRegistry::new(),
+        }
+    }
+
+    /// Create a new document store bound to a device actor ID.
+    pub fn with_actor(actor: ActorId) -> Self {
+        Self {
+            documents: DashMap::new(),
+            actor: RwLock::new(Some(actor)),
+            codecs: CodecRegistry::new(),
+        }
+    }
+
+    /// Get the actor ID bound to this store, if any.
+    pub fn actor(&self) -> Option<ActorId> {
+        self.actor.read().clone()
+    }
+
+    /// Bind this store to a device actor ID.
+    ///
+    /// The actor is applied to every document already in the store and to
+    /// all documents created or loaded afterwards, so that local writes are
+    /// attributed to the same device.
+    pub fn set_actor(&self, actor: ActorId) {
+        for entry in self.documents.iter() {
+            entry.value().set_actor(actor.clone());
+        }
+        *self.actor.write() = Some(actor);
+    }
+
+    /// Apply the bound actor ID (if any) to a document.
+    fn bind_actor(&self, doc: &mut AutoCommit) {
+        if let Some(actor) = self.actor.read().as_ref() {
+            doc.set_actor(actor.clone());
         }
     }
 
@@ -167,7 +552,8 @@ impl DocumentStore {
             return Err(StateError::DocumentAlreadyExists(id.to_string()));
         }
 
-        let doc = AutoCommit::new();
+        let mut doc = AutoCommit::new();
+        self.bind_actor(&mut doc);
         let handle = DocumentHandle::new(id.clone(), doc);
         self.documents.insert(id, handle.clone());
         Ok(handle)
@@ -179,12 +565,69 @@ impl DocumentStore {
             return Err(StateError::DocumentAlreadyExists(id.to_string()));
         }
 
-        let doc = AutoCommit::load(bytes)?;
+        let mut doc = AutoCommit::load(bytes)?;
+        self.bind_actor(&mut doc);
         let handle = DocumentHandle::new(id.clone(), doc);
         self.documents.insert(id, handle.clone());
         Ok(handle)
     }
 
+    /// Load a document from a reader.
+    ///
+    /// The snapshot is read in [`IO_CHUNK_SIZE`] chunks into a buffer sized
+    /// from `size_hint`, avoiding repeated reallocation for large documents.
+    /// The whole snapshot is buffered before it is decoded.
+    pub fn load_from_reader<R: Read>(
+        &self,
+        id: DocumentId,
+        reader: R,
+        size_hint: Option<usize>,
+    ) -> Result<DocumentHandle> {
+        if self.documents.contains_key(&id) {
+            return Err(StateError::DocumentAlreadyExists(id.to_string()));
+        }
+
+        let bytes = read_chunked(reader, size_hint.unwrap_or(0))?;
+        self.load(id, &bytes)
+    }
+
+    /// Load a document from a snapshot plus the changes made since it.
+    ///
+    /// Only the suffix is applied incrementally on top of the snapshot, so a
+    /// large document can be restored from an old snapshot without
+    /// re-encoding it.
+    pub fn load_with_suffix<R: Read>(
+        &self,
+        id: DocumentId,
+        snapshot: R,
+        suffix: &[u8],
+    ) -> Result<DocumentHandle> {
+        let handle = self.load_from_reader(id, snapshot, None)?;
+        if !suffix.is_empty() {
+            handle.load_incremental(suffix)?;
+        }
+        Ok(handle)
+    }
+
+    /// Get the per-namespace codec registry.
+    pub fn codecs(&self) -> &CodecRegistry {
+        &self.codecs
+    }
+
+    /// Save a document, encoded with its namespace codec, for a storage adapter.
+    pub fn save_encoded(&self, id: &DocumentId) -> Result<Vec<u8>> {
+        let bytes = self.get(id)?.save();
+        self.codecs.encode(id, bytes)
+    }
+
+    /// Load a document from bytes produced by [`DocumentStore::save_encoded`].
+    ///
+    /// Bytes saved without a codec are loaded as-is.
+    pub fn load_encoded(&self, id: DocumentId, bytes: &[u8]) -> Result<DocumentHandle> {
+        let decoded = self.codecs.decode(&id, bytes)?;
+        self.load(id, &decoded)
+    }
+
     /// Get a document by ID.
     pub fn get(&self, id: &DocumentId) -> Result<DocumentHandle> {
         self.documents
@@ -242,6 +685,21 @@ impl DocumentStore {
     }
 }
 
+/// Read all bytes from a reader into one buffer, in fixed-size chunks.
+fn read_chunked<R: Read>(mut reader: R, capacity: usize) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(capacity);
+    let mut chunk = vec![0u8; IO_CHUNK_SIZE];
+    loop {
+        match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => bytes.extend_from_slice(&chunk[..n]),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(bytes)
+}
+
 impl Default for DocumentStore {
     fn default() -> Self {
         Self::new()
@@ -305,6 +763,39 @@ mod tests {
         assert_eq!(id.to_string(), "users/alice");
     }
 
+    #[test]
+    fn test_document_id_derive() {
+        let id = DocumentId::derive("users", &["alice@example.com"]);
+        assert_eq!(id.namespace, "users");
+        assert_eq!(id.key.len(), 32);
+        assert_eq!(id, DocumentId::derive("users", &["alice@example.com"]));
+
+        // Part boundaries and the derivation are part of the key
+        assert_ne!(
+            DocumentId::derive("users", &["ab", "c"]),
+            DocumentId::derive("users", &["a", "bc"])
+        );
+        let sha = KeyDerivation {
+            hash: KeyHash::Sha256,
+            key_bytes: 32,
+            ..Default::default()
+        };
+        let derived = DocumentId::derive_with("users", &["alice@example.com"], &sha);
+        assert_eq!(derived.key.len(), 64);
+        assert_ne!(derived.key[..32], id.key);
+    }
+
+    #[test]
+    fn test_document_id_virtualize() {
+        let id = DocumentId::new("users", "alice");
+        let virtualized = id.virtualize("chat");
+        assert_eq!(virtualized.to_string(), "chat:users/alice");
+        assert_eq!(virtualized.app(), Some("chat"));
+        assert_eq!(virtualized.devirtualize("chat"), Some(id.clone()));
+        assert_eq!(virtualized.devirtualize("photos"), None);
+        assert_eq!(id.app(), None);
+    }
+
     #[test]
     fn test_document_store_create() {
         let store = DocumentStore::new();
@@ -481,6 +972,214 @@ mod tests {
         assert!(meta2.version > meta.version);
     }
 
+    #[test]
+    fn test_document_store_actor_binding() {
+        let actor = ActorId::from(vec![1u8; 16]);
+        let store = DocumentStore::with_actor(actor.clone());
+        let handle = store.create(DocumentId::new("users", "alice")).unwrap();
+        assert_eq!(handle.actor(), actor);
+
+        let other = ActorId::from(vec![2u8; 16]);
+        store.set_actor(other.clone());
+        assert_eq!(handle.actor(), other);
+        assert_eq!(store.actor(), Some(other.clone()));
+
+        let bytes = handle.save();
+        let loaded = store.load(DocumentId::new("users", "copy"), &bytes).unwrap();
+        assert_eq!(loaded.actor(), other);
+    }
+
+    #[test]
+    fn test_document_handle_blame() {
+        let store = DocumentStore::new();
+        let handle = store.create(DocumentId::new("users", "alice")).unwrap();
+        let alice = ActorId::from(vec![0xa1u8; 16]);
+        let bob = ActorId::from(vec![0xb0u8; 16]);
+
+        handle.set_actor(alice.clone());
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "name", "Alice")?;
+                doc.put(ROOT, "age", 30i64)?;
+                Ok(())
+            })
+            .unwrap();
+
+        handle.set_actor(bob.clone());
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "age", 31i64)?;
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(handle.blame("name").unwrap(), Some(alice));
+        assert_eq!(handle.blame("age").unwrap(), Some(bob));
+        assert_eq!(handle.blame("missing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_document_size_hint_and_chunked_save() {
+        let store = DocumentStore::new();
+        let handle = store.create(DocumentId::new("docs", "big")).unwrap();
+        let initial = handle.size_hint();
+
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "body", "x".repeat(4096))?;
+                Ok(())
+            })
+            .unwrap();
+        assert!(handle.size_hint() > initial);
+
+        let mut out = Vec::new();
+        let written = handle.save_to(&mut out).unwrap();
+        assert_eq!(written, out.len());
+        assert_eq!(out, handle.save());
+        assert_eq!(handle.size_hint(), written);
+    }
+
+    #[test]
+    fn test_document_load_with_suffix() {
+        let store = DocumentStore::new();
+        let handle = store.create(DocumentId::new("docs", "1")).unwrap();
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "title", "Draft")?;
+                Ok(())
+            })
+            .unwrap();
+
+        let mut snapshot = Vec::new();
+        handle.save_to(&mut snapshot).unwrap();
+        let snapshot_heads = handle.heads();
+
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "title", "Final")?;
+                doc.put(ROOT, "pages", 12i64)?;
+                Ok(())
+            })
+            .unwrap();
+        let suffix = handle.save_after(&snapshot_heads);
+        assert!(suffix.len() < handle.save().len());
+
+        let other = DocumentStore::new();
+        let loaded = other
+            .load_with_suffix(DocumentId::new("docs", "1"), &snapshot[..], &suffix)
+            .unwrap();
+        assert_eq!(loaded.heads(), handle.heads());
+        loaded
+            .read(|doc| {
+                assert_eq!(get_string(doc, ROOT, "title")?, "Final");
+                Ok(())
+            })
+            .unwrap();
+
+        // Re-applying the suffix is a no-op
+        assert_eq!(loaded.load_incremental(&suffix).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_document_sync_messages() {
+        let id = DocumentId::new("users", "alice");
+        let local = DocumentStore::new().create(id.clone()).unwrap();
+        let remote = DocumentStore::new().create(id).unwrap();
+        local
+            .update(|doc| {
+                doc.put(ROOT, "name", "Alice")?;
+                Ok(())
+            })
+            .unwrap();
+
+        let (mut local_state, mut remote_state) = (sync::State::new(), sync::State::new());
+        let mut changed = false;
+        loop {
+            let to_remote = local.generate_sync_message(&mut local_state);
+            let to_local = remote.generate_sync_message(&mut remote_state);
+            if to_remote.is_none() && to_local.is_none() {
+                break;
+            }
+            if let Some(message) = to_remote {
+                let applied = remote.receive_sync_message(&mut remote_state, message);
+                changed |= applied.unwrap();
+            }
+            if let Some(message) = to_local {
+                let applied = local.receive_sync_message(&mut local_state, message);
+                assert!(!applied.unwrap());
+            }
+        }
+
+        assert!(changed);
+        assert_eq!(remote.heads(), local.heads());
+        assert_eq!(remote.metadata().version, 1);
+    }
+
+    #[test]
+    fn test_save_and_load_encoded() {
+        let store = DocumentStore::new();
+        store
+            .codecs()
+            .set_codec("users", Arc::new(crate::codec::GzipCodec));
+        let id = DocumentId::new("users", "alice");
+        let handle = store.create(id.clone()).unwrap();
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "name", "Alice")?;
+                Ok(())
+            })
+            .unwrap();
+
+        let encoded = store.save_encoded(&id).unwrap();
+        assert_ne!(encoded, handle.save());
+
+        let other = DocumentStore::new();
+        other
+            .codecs()
+            .set_codec("users", Arc::new(crate::codec::GzipCodec));
+        let loaded = other.load_encoded(id.clone(), &encoded).unwrap();
+        let name = loaded.read(|doc| get_string(doc, ROOT, "name")).unwrap();
+        assert_eq!(name, "Alice");
+
+        // Without the codec the document cannot be decoded
+        let plain = DocumentStore::new();
+        assert!(matches!(
+            plain.load_encoded(id, &encoded),
+            Err(StateError::CodecError(_))
+        ));
+    }
+
+    #[test]
+    fn test_document_update_if() {
+        let store = DocumentStore::new();
+        let handle = store.create(DocumentId::new("users", "alice")).unwrap();
+
+        let (_, token) = handle.read_versioned(|_| Ok(())).unwrap();
+        assert_eq!(token, handle.version_token());
+
+        let (_, next) = handle
+            .update_if(&token, |doc| {
+                doc.put(ROOT, "name", "Alice")?;
+                Ok(())
+            })
+            .unwrap();
+        assert_ne!(next, token);
+
+        // A stale token is rejected and the document is left untouched
+        let result = handle.update_if(&token, |doc| {
+            doc.put(ROOT, "name", "Mallory")?;
+            Ok(())
+        });
+        assert!(matches!(result, Err(StateError::VersionConflict { .. })));
+        let name = handle.read(|doc| get_string(doc, ROOT, "name")).unwrap();
+        assert_eq!(name, "Alice");
+
+        // Tokens round-trip through their string form
+        let parsed: VersionToken = next.to_string().parse().unwrap();
+        assert_eq!(parsed, next);
+        assert!("not-a-hash".parse::<VersionToken>().is_err());
+    }
+
     #[test]
     fn test_concurrent_access() {
         use std::sync::Arc;
@@ -28,6 +28,19 @@ pub enum StateError {
     #[error("Transaction conflict: {0}")]
     TransactionConflict(String),
 
+    /// Optimistic concurrency check failed because the document advanced.
+    #[error("Version conflict: expected {expected}, found {actual}")]
+    VersionConflict {
+        /// Version token the caller expected.
+        expected: String,
+        /// Current version token.
+        actual: String,
+    },
+
+    /// Conflict resolution failed.
+    #[error("Conflict resolution failed: {0}")]
+    ConflictResolutionFailed(String),
+
     /// Automerge error.
     #[error("Automerge error: {0}")]
     AutomergeError(String),
@@ -56,6 +69,30 @@ pub enum StateError {
     #[error("Snapshot error: {0}")]
     SnapshotError(String),
 
+    /// Archive format error.
+    #[error("Archive error: {0}")]
+    ArchiveError(String),
+
+    /// Document codec error.
+    #[error("Codec error: {0}")]
+    CodecError(String),
+
+    /// Webhook configuration or delivery error.
+    #[error("Webhook error: {0}")]
+    WebhookFailed(String),
+
+    /// Access outside an application's namespaces.
+    #[error("Access denied: {0}")]
+    AccessDenied(String),
+
+    /// Namespace quota exceeded.
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+
+    /// Persistent storage error.
+    #[error("Storage error: {0}")]
+    StorageError(String),
+
     /// I/O error.
     #[error("I/O error: {0}")]
     IoError(String),
@@ -5,9 +5,19 @@
 //! This crate provides the core state management layer for the VUDO Runtime, including:
 //! - Automerge document store with in-memory caching
 //! - Reactive subscriptions for change notifications
-//! - Operation queue for offline mutations
-//! - Snapshot management for compaction
+//! - Operation queue for offline mutations, with priorities and coalescing
+//! - Snapshot management for compaction, preserving verifiable audit heads
 //! - Multi-document transactions with atomic commit/rollback
+//! - Portable archives for backup and device migration
+//! - Optional storage integrity pass at startup, repairing corrupt documents
+//! - Encrypted bootstrap bundles for cold-starting newly linked devices
+//! - Conflict inspection and explicit resolution of concurrent writes
+//! - Runtime conflict-resolution policies per gen field
+//! - Per-namespace document codecs for encryption or compression at rest
+//! - Namespace virtualization for apps sharing one engine, with scopes and quotas
+//! - Collision-resistant, configurable derivation of document IDs
+//! - Live metrics with Prometheus text export (`metrics` crate facade with the `metrics` feature)
+//! - Signed change webhooks for server-side integrations (`webhooks` feature)
 //!
 //! # Examples
 //!
@@ -46,26 +56,57 @@
 //! }
 //! ```
 
+pub mod archive;
+pub mod bootstrap;
+pub mod codec;
+pub mod conflict;
+pub mod conflict_policy;
 pub mod document_store;
 pub mod error;
+pub mod metrics;
+pub mod namespace;
 pub mod operation_queue;
 pub mod reactive;
 // pub mod schema_evolution; // Disabled - task t2.5
 pub mod snapshot;
 pub mod transaction;
-
-pub use document_store::{DocumentHandle, DocumentId, DocumentMetadata, DocumentStore};
+#[cfg(feature = "webhooks")]
+pub mod webhook;
+
+pub use archive::{ArchiveManifest, ArchiveSummary};
+pub use bootstrap::{BootstrapDocument, BootstrapManifest, BootstrapOptions, PeerHint};
+pub use codec::{CodecChain, CodecRegistry, DocumentCodec, GzipCodec};
+pub use conflict::{ConcurrentValue, FieldConflict};
+pub use conflict_policy::{
+    ConflictHandler, ConflictPolicy, ConflictPolicyRegistry, ConflictReport, PendingConflict,
+};
+pub use document_store::{
+    DocumentHandle, DocumentId, DocumentMetadata, DocumentStore, KeyDerivation, KeyHash,
+    VersionToken, APP_SEPARATOR,
+};
 pub use error::{Result, StateError};
-pub use operation_queue::{Operation, OperationId, OperationQueue, OperationType};
+pub use metrics::{HistogramSnapshot, LatencyHistogram, MetricsHandle};
+pub use namespace::{AppNamespace, AppScope, NamespaceQuota, NamespaceRegistry, NamespaceUsage};
+pub use operation_queue::{Operation, OperationId, OperationQueue, OperationType, Priority};
 pub use reactive::{ChangeEvent, ChangeObservable, ReactiveDocument, Subscription, SubscriptionFilter, SubscriptionId};
 // pub use schema_evolution::{
 //     EvolutionEngine, ForwardCompatibleReader, Migration, MigrationConflictResolver,
 //     MigrationMetadata, SchemaMetadata, SchemaVersion,
 // };
-pub use snapshot::{CompactionResult, Snapshot, SnapshotManager, SnapshotMetadata, SnapshotStorage};
+pub use snapshot::{
+    AuditChainReport, AuditFailure, AuditHead, AuditHeadKind, CompactionResult, Snapshot,
+    SnapshotManager, SnapshotMetadata, SnapshotStorage,
+};
 pub use transaction::{Transaction, TransactionBuilder, TransactionId, TransactionManager, TransactionState};
+#[cfg(feature = "webhooks")]
+pub use webhook::{
+    verify_signature, DeliveryResult, WebhookDispatcher, WebhookEndpoint, WebhookId,
+    WebhookPayload, WebhookStats,
+};
 
+use std::path::Path;
 use std::sync::Arc;
+use vudo_storage::{IntegrityReport, StorageAdapter};
 
 /// Main state engine that coordinates all components.
 pub struct StateEngine {
@@ -81,6 +122,10 @@ pub struct StateEngine {
     pub snapshot_manager: Arc<SnapshotManager>,
     /// Transaction manager.
     pub transaction_manager: Arc<TransactionManager>,
+    /// Runtime conflict-resolution policies.
+    pub conflict_policies: Arc<ConflictPolicyRegistry>,
+    /// Applications sharing the engine.
+    pub namespaces: Arc<NamespaceRegistry>,
 }
 
 impl StateEngine {
@@ -90,7 +135,9 @@ impl StateEngine {
         let observable = Arc::new(ChangeObservable::new());
         let queue = Arc::new(OperationQueue::new());
         let snapshot_storage = Arc::new(SnapshotStorage::new());
-        let snapshot_manager = Arc::new(SnapshotManager::new(Arc::clone(&snapshot_storage)));
+        let snapshot_manager = Arc::new(
+            SnapshotManager::new(Arc::clone(&snapshot_storage)).with_codecs(store.codecs().clone()),
+        );
         let transaction_manager = Arc::new(TransactionManager::new(Arc::clone(&store)));
 
         Ok(Self {
@@ -100,6 +147,8 @@ impl StateEngine {
             snapshot_storage,
             snapshot_manager,
             transaction_manager,
+            conflict_policies: Arc::new(ConflictPolicyRegistry::new()),
+            namespaces: Arc::new(NamespaceRegistry::new()),
         })
     }
 
@@ -107,15 +156,21 @@ impl StateEngine {
     pub async fn with_config(config: StateEngineConfig) -> Result<Self> {
         let store = Arc::new(DocumentStore::new());
         let observable = Arc::new(ChangeObservable::new());
-        let queue = Arc::new(OperationQueue::with_max_size(config.max_queue_size));
+        let queue = Arc::new(
+            OperationQueue::with_max_size(config.max_queue_size)
+                .with_coalescing(config.coalesce_operations),
+        );
         let snapshot_storage = Arc::new(SnapshotStorage::with_max_snapshots(
             config.max_snapshots_per_doc,
         ));
-        let snapshot_manager = Arc::new(SnapshotManager::with_settings(
-            Arc::clone(&snapshot_storage),
-            config.snapshot_interval,
-            config.min_changes_threshold,
-        ));
+        let snapshot_manager = Arc::new(
+            SnapshotManager::with_settings(
+                Arc::clone(&snapshot_storage),
+                config.snapshot_interval,
+                config.min_changes_threshold,
+            )
+            .with_codecs(store.codecs().clone()),
+        );
         let transaction_manager = Arc::new(TransactionManager::new(Arc::clone(&store)));
 
         Ok(Self {
@@ -125,9 +180,39 @@ impl StateEngine {
             snapshot_storage,
             snapshot_manager,
             transaction_manager,
+            conflict_policies: Arc::new(ConflictPolicyRegistry::new()),
+            namespaces: Arc::new(NamespaceRegistry::with_key_derivation(
+                config.key_derivation,
+            )),
         })
     }
 
+    /// Create a state engine after an integrity pass over `storage`.
+    ///
+    /// Runs [`StorageAdapter::verify`] before the engine starts: corrupt
+    /// documents are rebuilt from their latest good snapshot and the queued
+    /// operations, and items that can't be recovered are logged and listed
+    /// in the returned report. Fails if the adapter doesn't support
+    /// integrity checks.
+    pub async fn with_integrity_check(
+        config: StateEngineConfig,
+        storage: &dyn StorageAdapter,
+    ) -> Result<(Self, IntegrityReport)> {
+        let report = storage
+            .verify()
+            .await
+            .map_err(|e| StateError::StorageError(e.to_string()))?;
+        for item in &report.repaired {
+            tracing::warn!("Repaired corrupt {} from its latest snapshot", item);
+        }
+        for item in &report.unrecoverable {
+            tracing::error!("Corrupt {} could not be recovered", item);
+        }
+
+        let engine = Self::with_config(config).await?;
+        Ok((engine, report))
+    }
+
     /// Create a new document.
     pub async fn create_document(&self, id: DocumentId) -> Result<DocumentHandle> {
         let handle = self.store.create(id.clone())?;
@@ -157,6 +242,98 @@ impl StateEngine {
         Ok(())
     }
 
+    /// List metadata of all documents in a namespace, sorted by key.
+    pub async fn list_documents(&self, namespace: &str) -> Result<Vec<DocumentMetadata>> {
+        let mut documents = self
+            .store
+            .list_namespace(namespace)
+            .iter()
+            .map(|id| self.store.get(id).map(|handle| handle.metadata()))
+            .collect::<Result<Vec<_>>>()?;
+        documents.sort_by(|a, b| a.id.key.cmp(&b.id.key));
+        Ok(documents)
+    }
+
+    /// Delete every document in a namespace.
+    ///
+    /// A delete operation is enqueued for each document, exactly as if it had
+    /// been deleted individually. `progress` is called after each document.
+    /// Returns the number of documents deleted.
+    pub async fn delete_namespace<F>(&self, namespace: &str, mut progress: F) -> Result<usize>
+    where
+        F: FnMut(BulkProgress<'_>),
+    {
+        let ids = self.sorted_namespace(namespace);
+        let total = ids.len();
+
+        for (i, id) in ids.iter().enumerate() {
+            self.delete_document(id).await?;
+            progress(BulkProgress {
+                document_id: id,
+                completed: i + 1,
+                total,
+            });
+        }
+
+        Ok(total)
+    }
+
+    /// Snapshot every document in a namespace.
+    ///
+    /// A snapshot operation is enqueued for each document so storage and
+    /// sync layers can persist the new snapshots. `progress` is called after
+    /// each document.
+    pub async fn snapshot_namespace<F>(
+        &self,
+        namespace: &str,
+        mut progress: F,
+    ) -> Result<Vec<SnapshotMetadata>>
+    where
+        F: FnMut(BulkProgress<'_>),
+    {
+        let ids = self.sorted_namespace(namespace);
+        let total = ids.len();
+        let mut snapshots = Vec::with_capacity(total);
+
+        for (i, id) in ids.iter().enumerate() {
+            let handle = self.store.get(id)?;
+            let snapshot = self.snapshot_manager.create_snapshot(&handle)?;
+            self.queue.enqueue(Operation::new(OperationType::Snapshot {
+                document_id: id.clone(),
+                version: snapshot.metadata.version,
+            }))?;
+            snapshots.push(snapshot.metadata);
+            progress(BulkProgress {
+                document_id: id,
+                completed: i + 1,
+                total,
+            });
+        }
+
+        Ok(snapshots)
+    }
+
+    /// Document IDs in a namespace, sorted by key for deterministic bulk order.
+    fn sorted_namespace(&self, namespace: &str) -> Vec<DocumentId> {
+        let mut ids = self.store.list_namespace(namespace);
+        ids.sort_by(|a, b| a.key.cmp(&b.key));
+        ids
+    }
+
+    /// Register an application sharing the engine, replacing any with the
+    /// same name.
+    pub fn register_app(&self, app: AppNamespace) -> Result<()> {
+        self.namespaces.register(app)
+    }
+
+    /// Get a registered application's view of the engine.
+    pub fn app(&self, name: &str) -> Result<AppScope<'_>> {
+        let app = self.namespaces.get(name).ok_or_else(|| {
+            StateError::AccessDenied(format!("App {} is not registered", name))
+        })?;
+        Ok(AppScope::new(self, app))
+    }
+
     /// Subscribe to document changes.
     pub async fn subscribe(&self, filter: SubscriptionFilter) -> Subscription {
         self.observable.subscribe(filter)
@@ -182,6 +359,23 @@ impl StateEngine {
         self.transaction_manager.rollback(tx)
     }
 
+    /// Apply changes received from a remote peer.
+    ///
+    /// The document is created if it does not exist. Any conflicts left by
+    /// the merge are then resolved using the registered conflict policies.
+    pub async fn apply_remote_changes(
+        &self,
+        id: &DocumentId,
+        changes: &[u8],
+    ) -> Result<ConflictReport> {
+        let handle = match self.store.get(id) {
+            Ok(handle) => handle,
+            Err(_) => self.create_document(id.clone()).await?,
+        };
+        handle.load_incremental(changes)?;
+        self.conflict_policies.resolve(&handle)
+    }
+
     /// Create a snapshot of a document.
     pub async fn snapshot(&self, handle: &DocumentHandle) -> Result<Snapshot> {
         self.snapshot_manager.create_snapshot(handle)
@@ -192,20 +386,108 @@ impl StateEngine {
         self.snapshot_manager.compact(handle)
     }
 
+    /// Preserve the current state of a document as an audit head that
+    /// survives compaction.
+    pub async fn mark_audit_head(
+        &self,
+        handle: &DocumentHandle,
+        kind: AuditHeadKind,
+        label: impl Into<String>,
+    ) -> Result<AuditHead> {
+        self.snapshot_manager.mark_audit_head(handle, kind, label)
+    }
+
+    /// Verify a document's audit heads and their chain to the current state.
+    pub async fn verify_audit_chain(&self, handle: &DocumentHandle) -> AuditChainReport {
+        self.snapshot_manager.verify_audit_chain(handle)
+    }
+
+    /// Export all documents, snapshots, and queued operations to an archive file.
+    pub async fn export_archive(&self, path: impl AsRef<Path>) -> Result<ArchiveSummary> {
+        let file = std::fs::File::create(path)?;
+        archive::write_archive(
+            std::io::BufWriter::new(file),
+            &self.store,
+            &self.snapshot_storage,
+            &self.queue,
+        )
+    }
+
+    /// Import an archive file produced by [`StateEngine::export_archive`].
+    ///
+    /// Documents already present are merged with the archived state.
+    pub async fn import_archive(&self, path: impl AsRef<Path>) -> Result<ArchiveSummary> {
+        let file = std::fs::File::open(path)?;
+        archive::read_archive(
+            std::io::BufReader::new(file),
+            &self.store,
+            &self.snapshot_storage,
+            &self.queue,
+        )
+    }
+
+    /// Export a bootstrap bundle for a newly linked device.
+    ///
+    /// The bundle holds a compacted snapshot of every document (or of the
+    /// namespaces selected in `options`) plus the schema versions, ACLs and
+    /// peer hints from `options`, encoded with `codec`. Use an encrypting
+    /// codec keyed to the identity the devices share.
+    pub async fn export_bootstrap(
+        &self,
+        options: &BootstrapOptions,
+        codec: &dyn DocumentCodec,
+    ) -> Result<Vec<u8>> {
+        let mut bundle = Vec::new();
+        bootstrap::write_bootstrap(&mut bundle, &self.store, options, codec)?;
+        Ok(bundle)
+    }
+
+    /// Import a bundle produced by [`StateEngine::export_bootstrap`].
+    ///
+    /// Documents already present are merged with the bundled state. The
+    /// returned manifest holds the schema versions, ACLs and peer hints to
+    /// apply, and the heads each document was exported at, after which only
+    /// deltas need to be synced.
+    pub async fn import_bootstrap(
+        &self,
+        bundle: &[u8],
+        codec: &dyn DocumentCodec,
+    ) -> Result<BootstrapManifest> {
+        bootstrap::read_bootstrap(bundle, &self.store, codec)
+    }
+
     /// Get statistics about the state engine.
     pub fn stats(&self) -> StateEngineStats {
-        StateEngineStats {
-            document_count: self.store.count(),
-            total_document_size: self.store.total_size(),
-            subscription_count: self.observable.subscription_count(),
-            queue_length: self.queue.len(),
-            snapshot_count: self.snapshot_storage.total_count(),
-            total_snapshot_size: self.snapshot_storage.total_size(),
-            active_transaction_count: self.transaction_manager.active_count(),
-        }
+        self.metrics().stats()
+    }
+
+    /// Get a handle to the live metrics of the state engine.
+    ///
+    /// The handle stays valid as long as it is held and can be moved to a
+    /// metrics endpoint or exporter task.
+    pub fn metrics(&self) -> MetricsHandle {
+        MetricsHandle::new(
+            Arc::clone(&self.store),
+            Arc::clone(&self.observable),
+            Arc::clone(&self.queue),
+            Arc::clone(&self.snapshot_storage),
+            Arc::clone(&self.snapshot_manager),
+            Arc::clone(&self.transaction_manager),
+        )
     }
 }
 
+/// Progress of a namespace-level bulk operation.
+#[derive(Debug, Clone, Copy)]
+pub struct BulkProgress<'a> {
+    /// Document just processed.
+    pub document_id: &'a DocumentId,
+    /// Number of documents processed so far.
+    pub completed: usize,
+    /// Total number of documents in the operation.
+    pub total: usize,
+}
+
 /// Configuration for the state engine.
 #[derive(Debug, Clone)]
 pub struct StateEngineConfig {
@@ -217,6 +499,10 @@ pub struct StateEngineConfig {
     pub snapshot_interval: tokio::time::Duration,
     /// Minimum number of changes before creating a snapshot.
     pub min_changes_threshold: usize,
+    /// Coalesce redundant operations in the queue.
+    pub coalesce_operations: bool,
+    /// Derivation of hashed document keys.
+    pub key_derivation: KeyDerivation,
 }
 
 impl Default for StateEngineConfig {
@@ -226,6 +512,8 @@ impl Default for StateEngineConfig {
             max_snapshots_per_doc: 10,
             snapshot_interval: tokio::time::Duration::from_secs(60),
             min_changes_threshold: 10,
+            coalesce_operations: false,
+            key_derivation: KeyDerivation::default(),
         }
     }
 }
@@ -389,6 +677,97 @@ mod tests {
         assert!(stats.total_snapshot_size > 0);
     }
 
+    #[tokio::test]
+    async fn test_state_engine_metrics() {
+        let engine = StateEngine::new().await.unwrap();
+        let metrics = engine.metrics();
+        let doc_id = DocumentId::new("users", "alice");
+        let handle = engine.create_document(doc_id.clone()).await.unwrap();
+        let mut subscription = engine
+            .subscribe(SubscriptionFilter::Document(doc_id.clone()))
+            .await;
+
+        engine.observable.notify(ChangeEvent {
+            document_id: doc_id,
+            timestamp: 0,
+            change_hash: Vec::new(),
+            path: None,
+        });
+        engine.observable.flush_batch();
+        assert!(subscription.recv().await.is_some());
+        engine.snapshot(&handle).await.unwrap();
+
+        assert_eq!(metrics.stats().document_count, 1);
+        assert_eq!(metrics.stats().queue_length, 1);
+        assert_eq!(metrics.events_delivered(), 1);
+        assert_eq!(metrics.fanout_latency().count, 1);
+        assert_eq!(metrics.snapshot_durations().count, 1);
+
+        let text = metrics.render_prometheus();
+        assert!(text.contains("# TYPE vudo_state_documents gauge\nvudo_state_documents 1\n"));
+        assert!(text.contains("vudo_state_queue_depth 1\n"));
+        assert!(text.contains("vudo_state_events_delivered_total 1\n"));
+        assert!(text.contains("vudo_state_fanout_latency_seconds_count 1\n"));
+        assert!(text.contains("vudo_state_snapshot_duration_seconds_bucket{le=\"+Inf\"} 1\n"));
+    }
+
+    #[tokio::test]
+    async fn test_state_engine_integrity_check() {
+        use automerge::{transaction::Transactable, Automerge, ReadDoc, ROOT};
+        use vudo_storage::operation::{Operation, OperationType};
+        use vudo_storage_native::SqliteAdapter;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("state.db");
+        let storage = SqliteAdapter::new(&db_path).await.unwrap();
+        storage.init().await.unwrap();
+
+        // Snapshot, then a queued change on top of it
+        let mut doc = Automerge::new();
+        let mut tx = doc.transaction();
+        tx.put(ROOT, "name", "Alice").unwrap();
+        tx.commit();
+        storage
+            .save_snapshot("users", "alice", 1, doc.save().into())
+            .await
+            .unwrap();
+        let mut tx = doc.transaction();
+        tx.put(ROOT, "age", 30i64).unwrap();
+        let (hash, _) = tx.commit();
+        let change = doc.get_change_by_hash(&hash.unwrap()).unwrap();
+        let op = Operation::new(
+            1,
+            "users",
+            "alice",
+            OperationType::Update {
+                change_bytes: change.raw_bytes().to_vec(),
+            },
+        );
+        storage.save_operations(&[op]).await.unwrap();
+        storage
+            .save("users", "alice", doc.save().into())
+            .await
+            .unwrap();
+
+        rusqlite::Connection::open(&db_path)
+            .unwrap()
+            .execute("UPDATE documents SET data = x'00'", [])
+            .unwrap();
+
+        let (engine, report) =
+            StateEngine::with_integrity_check(StateEngineConfig::default(), &storage)
+                .await
+                .unwrap();
+        assert_eq!(report.repaired.len(), 1);
+        assert!(report.unrecoverable.is_empty());
+        assert_eq!(engine.stats().document_count, 0);
+
+        let repaired = storage.load("users", "alice").await.unwrap().unwrap();
+        let repaired = Automerge::load(&repaired).unwrap();
+        assert!(repaired.get(ROOT, "name").unwrap().is_some());
+        assert!(repaired.get(ROOT, "age").unwrap().is_some());
+    }
+
     #[tokio::test]
     async fn test_state_engine_with_config() {
         let config = StateEngineConfig {
@@ -396,10 +775,201 @@ mod tests {
             max_snapshots_per_doc: 5,
             snapshot_interval: tokio::time::Duration::from_secs(30),
             min_changes_threshold: 5,
+            coalesce_operations: true,
+            key_derivation: KeyDerivation::default(),
         };
 
         let engine = StateEngine::with_config(config).await.unwrap();
         assert_eq!(engine.stats().document_count, 0);
+        assert!(engine.queue.is_coalescing());
+    }
+
+    #[tokio::test]
+    async fn test_state_engine_apply_remote_changes_with_policy() {
+        let engine = StateEngine::new().await.unwrap();
+        let doc_id = DocumentId::new("accounts", "alice");
+        let handle = engine.create_document(doc_id.clone()).await.unwrap();
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "balance", 10i64)?;
+                Ok(())
+            })
+            .unwrap();
+
+        let mut remote = automerge::AutoCommit::load(&handle.save()).unwrap();
+        remote.put(ROOT, "balance", 50i64).unwrap();
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "balance", 20i64)?;
+                Ok(())
+            })
+            .unwrap();
+
+        engine
+            .conflict_policies
+            .set_policy("accounts", "balance", ConflictPolicy::PreferHighest);
+        let report = engine
+            .apply_remote_changes(&doc_id, &remote.save())
+            .await
+            .unwrap();
+        assert_eq!(report.resolved, vec!["balance".to_string()]);
+
+        handle
+            .read(|doc| {
+                assert_eq!(get_i64(doc, ROOT, "balance")?, 50);
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_state_engine_namespace_bulk_operations() {
+        let engine = StateEngine::new().await.unwrap();
+        for key in ["c", "a", "b"] {
+            engine
+                .create_document(DocumentId::new("posts", key))
+                .await
+                .unwrap();
+        }
+        engine
+            .create_document(DocumentId::new("users", "alice"))
+            .await
+            .unwrap();
+
+        let listed = engine.list_documents("posts").await.unwrap();
+        let keys: Vec<_> = listed.iter().map(|m| m.id.key.as_str()).collect();
+        assert_eq!(keys, vec!["a", "b", "c"]);
+
+        let mut seen = Vec::new();
+        let snapshots = engine
+            .snapshot_namespace("posts", |p| seen.push((p.completed, p.total)))
+            .await
+            .unwrap();
+        assert_eq!(snapshots.len(), 3);
+        assert_eq!(seen, vec![(1, 3), (2, 3), (3, 3)]);
+        assert_eq!(engine.stats().snapshot_count, 3);
+
+        let queued = engine.queue.len();
+        let mut deleted_ids = Vec::new();
+        let deleted = engine
+            .delete_namespace("posts", |p| deleted_ids.push(p.document_id.key.clone()))
+            .await
+            .unwrap();
+        assert_eq!(deleted, 3);
+        assert_eq!(deleted_ids, vec!["a", "b", "c"]);
+        assert!(engine.list_documents("posts").await.unwrap().is_empty());
+        assert_eq!(engine.stats().document_count, 1);
+
+        let ops = engine.queue.list();
+        assert_eq!(ops.len(), queued + 3);
+        assert!(ops[queued..]
+            .iter()
+            .all(|op| matches!(op.op_type, OperationType::Delete { .. })));
+        assert_eq!(
+            ops.iter()
+                .filter(|op| matches!(op.op_type, OperationType::Snapshot { .. }))
+                .count(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn test_state_engine_archive_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("node.vudoarc");
+
+        let engine = StateEngine::new().await.unwrap();
+        let handle = engine
+            .create_document(DocumentId::new("users", "alice"))
+            .await
+            .unwrap();
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "balance", 42i64)?;
+                Ok(())
+            })
+            .unwrap();
+        engine.snapshot(&handle).await.unwrap();
+
+        let exported = engine.export_archive(&path).await.unwrap();
+        assert_eq!(exported.documents, 1);
+
+        let restored = StateEngine::new().await.unwrap();
+        let imported = restored.import_archive(&path).await.unwrap();
+        assert_eq!(imported, exported);
+
+        let stats = restored.stats();
+        assert_eq!(stats.document_count, 1);
+        assert_eq!(stats.snapshot_count, 1);
+        assert_eq!(stats.queue_length, 1);
+
+        // A second import leaves the restored engine unchanged
+        restored.import_archive(&path).await.unwrap();
+        let stats = restored.stats();
+        assert_eq!(stats.document_count, 1);
+        assert_eq!(stats.snapshot_count, 1);
+        assert_eq!(stats.queue_length, 1);
+
+        let handle = restored
+            .get_document(&DocumentId::new("users", "alice"))
+            .await
+            .unwrap();
+        handle
+            .read(|doc| {
+                assert_eq!(get_i64(doc, ROOT, "balance")?, 42);
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_state_engine_bootstrap() {
+        let engine = StateEngine::new().await.unwrap();
+        let handle = engine
+            .create_document(DocumentId::new("users", "alice"))
+            .await
+            .unwrap();
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "balance", 42i64)?;
+                Ok(())
+            })
+            .unwrap();
+
+        let options =
+            BootstrapOptions::new().peer_hint(PeerHint::new("node-1", vec!["relay".to_string()]));
+        let bundle = engine.export_bootstrap(&options, &GzipCodec).await.unwrap();
+
+        let device = StateEngine::new().await.unwrap();
+        let manifest = device.import_bootstrap(&bundle, &GzipCodec).await.unwrap();
+        assert_eq!(manifest.peer_hints.len(), 1);
+
+        // Usable offline without replaying the exporting device's queue
+        let stats = device.stats();
+        assert_eq!(stats.document_count, 1);
+        assert_eq!(stats.queue_length, 0);
+
+        // Later changes sync as deltas after the bundled heads
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "balance", 50i64)?;
+                Ok(())
+            })
+            .unwrap();
+        let delta = handle.save_after(manifest.documents[0].heads.heads());
+        device
+            .apply_remote_changes(&DocumentId::new("users", "alice"), &delta)
+            .await
+            .unwrap();
+        device
+            .get_document(&DocumentId::new("users", "alice"))
+            .await
+            .unwrap()
+            .read(|doc| {
+                assert_eq!(get_i64(doc, ROOT, "balance")?, 50);
+                Ok(())
+            })
+            .unwrap();
     }
 
     #[tokio::test]
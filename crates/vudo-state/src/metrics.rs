@@ -0,0 +1,293 @@
+//! Live metrics for the state engine.
+//!
+//! [`StateEngine::metrics`](crate::StateEngine::metrics) returns a
+//! [`MetricsHandle`] that reads gauges (document count, bytes, queue depth,
+//! ...) from the engine components on demand, together with latency
+//! histograms the components record as they run. The handle renders the
+//! Prometheus text exposition format and, with the `metrics` feature,
+//! publishes everything through the [`metrics`](https://docs.rs/metrics)
+//! crate facade.
+
+use crate::reactive::ChangeObservable;
+use crate::snapshot::{SnapshotManager, SnapshotStorage};
+use crate::transaction::TransactionManager;
+use crate::{DocumentStore, OperationQueue, StateEngineStats};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Upper bounds of the latency histogram buckets, in seconds.
+pub const LATENCY_BUCKETS: [f64; 10] = [
+    0.0001, 0.0005, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.5, 1.0,
+];
+
+/// Lock-free latency histogram with fixed buckets.
+#[derive(Debug)]
+pub struct LatencyHistogram {
+    /// Metric name.
+    name: &'static str,
+    /// Observations per bucket (non-cumulative), plus the overflow bucket.
+    buckets: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+    /// Number of observations.
+    count: AtomicU64,
+    /// Sum of observations in microseconds.
+    sum_micros: AtomicU64,
+}
+
+impl LatencyHistogram {
+    /// Create an empty histogram.
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            buckets: Default::default(),
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    /// Get the metric name.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Record an observation.
+    pub fn observe(&self, latency: Duration) {
+        let seconds = latency.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+
+        #[cfg(feature = "metrics")]
+        metrics::histogram!(self.name).record(seconds);
+    }
+
+    /// Get a point-in-time copy of the histogram.
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+        let buckets = LATENCY_BUCKETS
+            .iter()
+            .zip(&self.buckets)
+            .map(|(bound, count)| {
+                cumulative += count.load(Ordering::Relaxed);
+                (*bound, cumulative)
+            })
+            .collect();
+
+        HistogramSnapshot {
+            buckets,
+            count: self.count.load(Ordering::Relaxed),
+            sum: Duration::from_micros(self.sum_micros.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Point-in-time copy of a [`LatencyHistogram`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramSnapshot {
+    /// Cumulative observation counts by bucket upper bound in seconds.
+    pub buckets: Vec<(f64, u64)>,
+    /// Number of observations.
+    pub count: u64,
+    /// Sum of observations.
+    pub sum: Duration,
+}
+
+impl HistogramSnapshot {
+    /// Get the mean observation, if any were recorded.
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0)
+            .then(|| Duration::from_secs_f64(self.sum.as_secs_f64() / self.count as f64))
+    }
+}
+
+/// Handle to the live metrics of a state engine.
+#[derive(Clone)]
+pub struct MetricsHandle {
+    store: Arc<DocumentStore>,
+    observable: Arc<ChangeObservable>,
+    queue: Arc<OperationQueue>,
+    snapshot_storage: Arc<SnapshotStorage>,
+    snapshot_manager: Arc<SnapshotManager>,
+    transaction_manager: Arc<TransactionManager>,
+}
+
+impl MetricsHandle {
+    /// Create a handle over the engine components.
+    pub(crate) fn new(
+        store: Arc<DocumentStore>,
+        observable: Arc<ChangeObservable>,
+        queue: Arc<OperationQueue>,
+        snapshot_storage: Arc<SnapshotStorage>,
+        snapshot_manager: Arc<SnapshotManager>,
+        transaction_manager: Arc<TransactionManager>,
+    ) -> Self {
+        Self {
+            store,
+            observable,
+            queue,
+            snapshot_storage,
+            snapshot_manager,
+            transaction_manager,
+        }
+    }
+
+    /// Get the current gauge values.
+    pub fn stats(&self) -> StateEngineStats {
+        StateEngineStats {
+            document_count: self.store.count(),
+            total_document_size: self.store.total_size(),
+            subscription_count: self.observable.subscription_count(),
+            queue_length: self.queue.len(),
+            snapshot_count: self.snapshot_storage.total_count(),
+            total_snapshot_size: self.snapshot_storage.total_size(),
+            active_transaction_count: self.transaction_manager.active_count(),
+        }
+    }
+
+    /// Get the number of change events delivered to subscribers.
+    pub fn events_delivered(&self) -> u64 {
+        self.observable.events_delivered()
+    }
+
+    /// Get the latency from change notification to subscriber delivery.
+    pub fn fanout_latency(&self) -> HistogramSnapshot {
+        self.observable.fanout_latency().snapshot()
+    }
+
+    /// Get the durations of snapshot creation.
+    pub fn snapshot_durations(&self) -> HistogramSnapshot {
+        self.snapshot_manager.durations().snapshot()
+    }
+
+    /// Render all metrics in the Prometheus text exposition format.
+    pub fn render_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        for (name, help, value) in self.gauges() {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} gauge", name);
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP vudo_state_events_delivered_total Change events delivered to subscribers."
+        );
+        let _ = writeln!(out, "# TYPE vudo_state_events_delivered_total counter");
+        let _ = writeln!(
+            out,
+            "vudo_state_events_delivered_total {}",
+            self.events_delivered()
+        );
+
+        render_histogram(
+            &mut out,
+            self.observable.fanout_latency(),
+            "Latency from change notification to subscriber delivery.",
+        );
+        render_histogram(
+            &mut out,
+            self.snapshot_manager.durations(),
+            "Duration of snapshot creation.",
+        );
+
+        out
+    }
+
+    /// Publish the current gauge and counter values through the `metrics` facade.
+    ///
+    /// Histograms are recorded as observations happen; call this periodically
+    /// (e.g. before each scrape) to refresh the gauges.
+    #[cfg(feature = "metrics")]
+    pub fn publish(&self) {
+        for (name, _, value) in self.gauges() {
+            metrics::gauge!(name).set(value as f64);
+        }
+        metrics::counter!("vudo_state_events_delivered_total").absolute(self.events_delivered());
+    }
+
+    /// Gauge names, help texts and current values.
+    fn gauges(&self) -> [(&'static str, &'static str, usize); 7] {
+        let stats = self.stats();
+        [
+            (
+                "vudo_state_documents",
+                "Number of documents in the store.",
+                stats.document_count,
+            ),
+            (
+                "vudo_state_document_bytes",
+                "Total size of all documents in bytes.",
+                stats.total_document_size,
+            ),
+            (
+                "vudo_state_subscriptions",
+                "Number of active subscriptions.",
+                stats.subscription_count,
+            ),
+            (
+                "vudo_state_queue_depth",
+                "Number of operations in the queue.",
+                stats.queue_length,
+            ),
+            (
+                "vudo_state_snapshots",
+                "Number of snapshots stored.",
+                stats.snapshot_count,
+            ),
+            (
+                "vudo_state_snapshot_bytes",
+                "Total size of all snapshots in bytes.",
+                stats.total_snapshot_size,
+            ),
+            (
+                "vudo_state_active_transactions",
+                "Number of active transactions.",
+                stats.active_transaction_count,
+            ),
+        ]
+    }
+}
+
+/// Append a histogram in the Prometheus text format.
+fn render_histogram(out: &mut String, histogram: &LatencyHistogram, help: &str) {
+    let name = histogram.name();
+    let snapshot = histogram.snapshot();
+
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} histogram", name);
+    for (bound, count) in &snapshot.buckets {
+        let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, count);
+    }
+    let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, snapshot.count);
+    let _ = writeln!(out, "{}_sum {}", name, snapshot.sum.as_secs_f64());
+    let _ = writeln!(out, "{}_count {}", name, snapshot.count);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_histogram() {
+        let histogram = LatencyHistogram::new("test_latency_seconds");
+        histogram.observe(Duration::from_micros(50));
+        histogram.observe(Duration::from_millis(3));
+        histogram.observe(Duration::from_secs(2));
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count, 3);
+        assert_eq!(snapshot.buckets[0], (0.0001, 1));
+        assert_eq!(snapshot.buckets[3], (0.005, 2));
+        assert_eq!(snapshot.buckets.last().unwrap().1, 2);
+        assert_eq!(snapshot.sum, Duration::from_micros(2_003_050));
+        assert!(snapshot.mean().is_some());
+    }
+}
@@ -0,0 +1,454 @@
+//! Namespace virtualization for applications sharing one state engine.
+//!
+//! Each application registered with an [`AppNamespace`] sees its own set of
+//! namespaces through an [`AppScope`]. Its documents are stored under
+//! namespaces prefixed with the application name (`<app>:<namespace>`, see
+//! [`DocumentId::virtualize`]), so two applications can both use a `users`
+//! namespace without seeing each other's documents. An application can be
+//! restricted to some namespaces, with the same trailing-`*` globs as UCAN
+//! resources, and given quotas on its number of documents and their size.
+//!
+//! # Examples
+//!
+//! ```
+//! use vudo_state::{AppNamespace, StateEngine};
+//!
+//! # async fn example() -> vudo_state::Result<()> {
+//! let engine = StateEngine::new().await?;
+//! engine.register_app(AppNamespace::new("chat").allow("rooms*").max_documents(1000))?;
+//!
+//! let chat = engine.app("chat")?;
+//! let handle = chat.create_document("rooms", "general").await?;
+//! assert_eq!(handle.id.namespace, "chat:rooms");
+//! assert!(chat.create_document("users", "alice").await.is_err());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::conflict_policy::ConflictReport;
+use crate::document_store::{
+    DocumentHandle, DocumentId, DocumentMetadata, KeyDerivation, APP_SEPARATOR,
+};
+use crate::error::{Result, StateError};
+use crate::StateEngine;
+use automerge::AutoCommit;
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Limits on an application's documents.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamespaceQuota {
+    /// Maximum number of documents (unlimited when `None`).
+    pub max_documents: Option<usize>,
+    /// Maximum total document size in bytes (unlimited when `None`).
+    ///
+    /// Checked before each write, so the last write may overshoot it.
+    pub max_bytes: Option<usize>,
+}
+
+/// Documents an application currently holds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamespaceUsage {
+    /// Number of documents.
+    pub documents: usize,
+    /// Total document size in bytes.
+    pub bytes: usize,
+}
+
+/// An application sharing the state engine.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppNamespace {
+    /// Application name, the prefix of its namespaces.
+    pub name: String,
+    /// Namespaces the application may use, exact or with a trailing `*`
+    /// (any namespace when empty).
+    pub namespaces: Vec<String>,
+    /// Limits on the application's documents.
+    pub quota: NamespaceQuota,
+}
+
+impl AppNamespace {
+    /// Create an application allowed to use any namespace, without quotas.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            namespaces: Vec::new(),
+            quota: NamespaceQuota::default(),
+        }
+    }
+
+    /// Allow a namespace, or namespaces matching a trailing-`*` glob.
+    pub fn allow(mut self, pattern: impl Into<String>) -> Self {
+        self.namespaces.push(pattern.into());
+        self
+    }
+
+    /// Limit the number of documents.
+    pub fn max_documents(mut self, count: usize) -> Self {
+        self.quota.max_documents = Some(count);
+        self
+    }
+
+    /// Limit the total document size.
+    pub fn max_bytes(mut self, bytes: usize) -> Self {
+        self.quota.max_bytes = Some(bytes);
+        self
+    }
+
+    /// Check if the application may use a namespace.
+    pub fn allows(&self, namespace: &str) -> bool {
+        self.namespaces.is_empty()
+            || self
+                .namespaces
+                .iter()
+                .any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => namespace.starts_with(prefix),
+                    None => pattern == namespace,
+                })
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.name.is_empty() || self.name.contains([APP_SEPARATOR, '/']) {
+            return Err(StateError::InvalidDocumentId(format!(
+                "Invalid app name '{}'",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Applications registered with a state engine.
+pub struct NamespaceRegistry {
+    /// Registered applications by name.
+    apps: DashMap<String, AppNamespace>,
+    /// Derivation of hashed document keys.
+    key_derivation: RwLock<KeyDerivation>,
+}
+
+impl NamespaceRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::with_key_derivation(KeyDerivation::default())
+    }
+
+    /// Create an empty registry deriving document keys with `derivation`.
+    pub fn with_key_derivation(derivation: KeyDerivation) -> Self {
+        Self {
+            apps: DashMap::new(),
+            key_derivation: RwLock::new(derivation),
+        }
+    }
+
+    /// Register an application, replacing any with the same name.
+    pub fn register(&self, app: AppNamespace) -> Result<()> {
+        app.validate()?;
+        self.apps.insert(app.name.clone(), app);
+        Ok(())
+    }
+
+    /// Unregister an application. Its documents are kept.
+    pub fn unregister(&self, name: &str) -> Option<AppNamespace> {
+        self.apps.remove(name).map(|(_, app)| app)
+    }
+
+    /// Get a registered application.
+    pub fn get(&self, name: &str) -> Option<AppNamespace> {
+        self.apps.get(name).map(|app| app.clone())
+    }
+
+    /// Names of the registered applications, sorted.
+    pub fn apps(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.apps.iter().map(|app| app.key().clone()).collect();
+        names.sort();
+        names
+    }
+
+    /// Get the derivation of hashed document keys.
+    pub fn key_derivation(&self) -> KeyDerivation {
+        self.key_derivation.read().clone()
+    }
+
+    /// Set the derivation of hashed document keys.
+    ///
+    /// Keys derived before keep their value, so changing the derivation of
+    /// an engine with derived documents makes them unreachable by their
+    /// parts.
+    pub fn set_key_derivation(&self, derivation: KeyDerivation) {
+        *self.key_derivation.write() = derivation;
+    }
+}
+
+impl Default for NamespaceRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An application's view of the state engine.
+///
+/// Namespaces and document IDs passed in and returned are the application's
+/// own; the handles returned carry the virtualized IDs.
+pub struct AppScope<'a> {
+    engine: &'a StateEngine,
+    app: AppNamespace,
+}
+
+impl<'a> AppScope<'a> {
+    pub(crate) fn new(engine: &'a StateEngine, app: AppNamespace) -> Self {
+        Self { engine, app }
+    }
+
+    /// Get the application.
+    pub fn app(&self) -> &AppNamespace {
+        &self.app
+    }
+
+    /// Get the virtualized ID of a document, checking the namespace is
+    /// allowed.
+    pub fn id(&self, namespace: &str, key: &str) -> Result<DocumentId> {
+        if namespace.contains([APP_SEPARATOR, '/']) || key.contains('/') {
+            return Err(StateError::InvalidDocumentId(format!(
+                "{}/{}",
+                namespace, key
+            )));
+        }
+        if !self.app.allows(namespace) {
+            return Err(StateError::AccessDenied(format!(
+                "App {} may not use namespace {}",
+                self.app.name, namespace
+            )));
+        }
+        Ok(DocumentId::new(namespace, key).virtualize(&self.app.name))
+    }
+
+    /// Get the virtualized ID of a document whose key is derived from
+    /// `parts` (see [`DocumentId::derive`]), with the engine's key
+    /// derivation.
+    pub fn derive_id<P: AsRef<[u8]>>(&self, namespace: &str, parts: &[P]) -> Result<DocumentId> {
+        let key = self.engine.namespaces.key_derivation().derive_key(parts);
+        self.id(namespace, &key)
+    }
+
+    /// Create a document.
+    pub async fn create_document(&self, namespace: &str, key: &str) -> Result<DocumentHandle> {
+        let id = self.id(namespace, key)?;
+        self.check_quota(true)?;
+        self.engine.create_document(id).await
+    }
+
+    /// Get a document.
+    pub async fn get_document(&self, namespace: &str, key: &str) -> Result<DocumentHandle> {
+        let id = self.id(namespace, key)?;
+        self.engine.get_document(&id).await
+    }
+
+    /// Update a document, unless the application is over its size quota.
+    pub async fn update_document<F, T>(&self, namespace: &str, key: &str, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut AutoCommit) -> Result<T>,
+    {
+        let handle = self.get_document(namespace, key).await?;
+        self.check_quota(false)?;
+        handle.update(f)
+    }
+
+    /// Delete a document.
+    pub async fn delete_document(&self, namespace: &str, key: &str) -> Result<()> {
+        let id = self.id(namespace, key)?;
+        self.engine.delete_document(&id).await
+    }
+
+    /// Apply changes received from a remote peer, creating the document if
+    /// needed.
+    pub async fn apply_remote_changes(
+        &self,
+        namespace: &str,
+        key: &str,
+        changes: &[u8],
+    ) -> Result<ConflictReport> {
+        let id = self.id(namespace, key)?;
+        self.check_quota(!self.engine.store.exists(&id))?;
+        self.engine.apply_remote_changes(&id, changes).await
+    }
+
+    /// List metadata of all documents in a namespace, sorted by key.
+    ///
+    /// The returned IDs are the application's own.
+    pub async fn list_documents(&self, namespace: &str) -> Result<Vec<DocumentMetadata>> {
+        let id = self.id(namespace, "")?;
+        let mut documents = self.engine.list_documents(&id.namespace).await?;
+        for metadata in &mut documents {
+            metadata.id = DocumentId::new(namespace, metadata.id.key.clone());
+        }
+        Ok(documents)
+    }
+
+    /// Namespaces the application holds documents in, sorted.
+    pub fn namespaces(&self) -> Vec<String> {
+        self.documents()
+            .into_iter()
+            .filter_map(|id| id.devirtualize(&self.app.name))
+            .map(|id| id.namespace)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Get the documents the application currently holds.
+    pub fn usage(&self) -> NamespaceUsage {
+        let store = &self.engine.store;
+        self.documents()
+            .iter()
+            .filter_map(|id| store.get(id).ok())
+            .fold(NamespaceUsage::default(), |usage, handle| NamespaceUsage {
+                documents: usage.documents + 1,
+                bytes: usage.bytes + handle.size_hint(),
+            })
+    }
+
+    /// Virtualized IDs of the application's documents.
+    fn documents(&self) -> Vec<DocumentId> {
+        self.engine
+            .store
+            .list_all()
+            .into_iter()
+            .filter(|id| id.app() == Some(self.app.name.as_str()))
+            .collect()
+    }
+
+    /// Fail if the application is over its size quota, or would go over its
+    /// document quota with a new document.
+    fn check_quota(&self, new_document: bool) -> Result<()> {
+        let quota = &self.app.quota;
+        if quota.max_documents.is_none() && quota.max_bytes.is_none() {
+            return Ok(());
+        }
+
+        let usage = self.usage();
+        if let Some(max) = quota.max_documents {
+            if new_document && usage.documents >= max {
+                return Err(StateError::QuotaExceeded(format!(
+                    "App {} is limited to {} documents",
+                    self.app.name, max
+                )));
+            }
+        }
+        if let Some(max) = quota.max_bytes {
+            if usage.bytes >= max {
+                return Err(StateError::QuotaExceeded(format!(
+                    "App {} is limited to {} bytes",
+                    self.app.name, max
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use automerge::{transaction::Transactable, ROOT};
+
+    #[tokio::test]
+    async fn test_apps_are_isolated() {
+        let engine = StateEngine::new().await.unwrap();
+        engine.register_app(AppNamespace::new("chat")).unwrap();
+        engine.register_app(AppNamespace::new("photos")).unwrap();
+        assert_eq!(engine.namespaces.apps(), vec!["chat", "photos"]);
+        assert!(engine.register_app(AppNamespace::new("a:b")).is_err());
+        assert!(engine.app("notes").is_err());
+
+        let chat = engine.app("chat").unwrap();
+        let photos = engine.app("photos").unwrap();
+        chat.create_document("users", "alice").await.unwrap();
+        photos.create_document("users", "alice").await.unwrap();
+        chat.create_document("rooms", "general").await.unwrap();
+
+        assert!(engine.store.exists(&DocumentId::new("chat:users", "alice")));
+        assert!(engine
+            .store
+            .exists(&DocumentId::new("photos:users", "alice")));
+        assert_eq!(chat.namespaces(), vec!["rooms", "users"]);
+        assert_eq!(photos.namespaces(), vec!["users"]);
+
+        let listed = chat.list_documents("users").await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, DocumentId::new("users", "alice"));
+
+        photos.delete_document("users", "alice").await.unwrap();
+        assert!(chat.get_document("users", "alice").await.is_ok());
+
+        // No escaping into another app's namespaces
+        assert!(chat.id("photos:users", "alice").is_err());
+        assert!(chat.id("users", "../alice").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_scope_and_quotas() {
+        let engine = StateEngine::new().await.unwrap();
+        engine
+            .register_app(
+                AppNamespace::new("chat")
+                    .allow("rooms*")
+                    .allow("profile")
+                    .max_documents(2)
+                    .max_bytes(256),
+            )
+            .unwrap();
+        let chat = engine.app("chat").unwrap();
+
+        assert!(chat.id("rooms", "general").is_ok());
+        assert!(chat.id("rooms-archive", "2024").is_ok());
+        assert!(chat.id("profile", "me").is_ok());
+        assert!(matches!(
+            chat.id("users", "alice"),
+            Err(StateError::AccessDenied(_))
+        ));
+
+        chat.create_document("rooms", "general").await.unwrap();
+        chat.create_document("profile", "me").await.unwrap();
+        assert!(matches!(
+            chat.create_document("rooms", "random").await,
+            Err(StateError::QuotaExceeded(_))
+        ));
+
+        chat.update_document("rooms", "general", |doc| {
+            doc.put(ROOT, "topic", "x".repeat(512))?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+        assert!(chat.usage().bytes >= 256);
+        assert!(matches!(
+            chat.update_document("profile", "me", |doc| {
+                doc.put(ROOT, "name", "Alice")?;
+                Ok(())
+            })
+            .await,
+            Err(StateError::QuotaExceeded(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_derived_ids() {
+        let engine = StateEngine::new().await.unwrap();
+        engine.register_app(AppNamespace::new("chat")).unwrap();
+        let chat = engine.app("chat").unwrap();
+
+        let id = chat.derive_id("users", &["alice@example.com"]).unwrap();
+        assert_eq!(
+            id,
+            DocumentId::derive("users", &["alice@example.com"]).virtualize("chat")
+        );
+
+        engine.namespaces.set_key_derivation(KeyDerivation {
+            context: "other deployment".to_string(),
+            ..Default::default()
+        });
+        assert_ne!(chat.derive_id("users", &["alice@example.com"]).unwrap(), id);
+    }
+}
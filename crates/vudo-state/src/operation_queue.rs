@@ -1,4 +1,14 @@
 //! Operation queue for offline mutation tracking and sync.
+//!
+//! Operations are dequeued by [`Priority`], oldest first within a priority
+//! class, but never ahead of an earlier operation on the same document. With
+//! coalescing enabled, redundant operations on the same document
+//! collapse when they are enqueued:
+//!
+//! - An update following a pending update appends its change bytes to it
+//! - A snapshot following a pending snapshot replaces its version
+//! - A delete drops the document's pending updates and snapshots, and
+//!   cancels a pending create together with itself
 
 use crate::document_store::DocumentId;
 use crate::error::{Result, StateError};
@@ -40,6 +50,27 @@ pub enum OperationType {
         /// Document ID.
         document_id: DocumentId,
     },
+    /// Snapshot a document.
+    Snapshot {
+        /// Document ID.
+        document_id: DocumentId,
+        /// Snapshot version number.
+        version: u64,
+    },
+}
+
+/// Priority class of an operation.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum Priority {
+    /// Background work such as snapshots.
+    Low,
+    /// Regular edits.
+    #[default]
+    Normal,
+    /// Operations that should sync before anything else.
+    High,
 }
 
 /// Operation metadata.
@@ -55,6 +86,9 @@ pub struct Operation {
     pub idempotency_key: Option<String>,
     /// Number of retry attempts.
     pub retry_count: u32,
+    /// Priority class.
+    #[serde(default)]
+    pub priority: Priority,
 }
 
 impl Operation {
@@ -69,6 +103,7 @@ impl Operation {
                 .as_millis() as u64,
             idempotency_key: None,
             retry_count: 0,
+            priority: Priority::Normal,
         }
     }
 
@@ -79,24 +114,33 @@ impl Operation {
         op
     }
 
+    /// Set the priority class.
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
     /// Get the document ID for this operation.
     pub fn document_id(&self) -> &DocumentId {
         match &self.op_type {
             OperationType::Create { document_id } => document_id,
             OperationType::Update { document_id, .. } => document_id,
             OperationType::Delete { document_id } => document_id,
+            OperationType::Snapshot { document_id, .. } => document_id,
         }
     }
 }
 
 /// Operation queue for tracking offline mutations.
 pub struct OperationQueue {
-    /// FIFO queue of pending operations.
+    /// Pending operations in dequeue order.
     queue: Arc<RwLock<VecDeque<Operation>>>,
     /// Map of idempotency keys to operation IDs (for deduplication).
     idempotency_map: Arc<RwLock<HashMap<String, OperationId>>>,
     /// Maximum queue size.
     max_size: usize,
+    /// Whether redundant operations are coalesced on enqueue.
+    coalesce: bool,
 }
 
 impl OperationQueue {
@@ -106,6 +150,7 @@ impl OperationQueue {
             queue: Arc::new(RwLock::new(VecDeque::new())),
             idempotency_map: Arc::new(RwLock::new(HashMap::new())),
             max_size: 10_000,
+            coalesce: false,
         }
     }
 
@@ -115,12 +160,52 @@ impl OperationQueue {
             queue: Arc::new(RwLock::new(VecDeque::new())),
             idempotency_map: Arc::new(RwLock::new(HashMap::new())),
             max_size,
+            coalesce: false,
         }
     }
 
+    /// Enable or disable coalescing of redundant operations.
+    pub fn with_coalescing(mut self, coalesce: bool) -> Self {
+        self.coalesce = coalesce;
+        self
+    }
+
+    /// Whether redundant operations are coalesced on enqueue.
+    pub fn is_coalescing(&self) -> bool {
+        self.coalesce
+    }
+
     /// Enqueue an operation.
+    ///
+    /// Returns the ID of the queued operation. When the operation is
+    /// coalesced into a pending one, that operation's ID is returned; when
+    /// a delete cancels a pending create, neither is queued and the
+    /// delete's own ID is returned.
     pub fn enqueue(&self, operation: Operation) -> Result<OperationId> {
         let mut queue = self.queue.write();
+        let mut idempotency_map = self.idempotency_map.write();
+
+        // Check for duplicate idempotency key
+        if let Some(ref key) = operation.idempotency_key {
+            if let Some(&existing_id) = idempotency_map.get(key) {
+                // Operation with this key already exists
+                return Ok(existing_id);
+            }
+        }
+
+        let operation = if self.coalesce {
+            match coalesce(&mut queue, &mut idempotency_map, operation) {
+                Coalesced::Into(id, key) => {
+                    if let Some(key) = key {
+                        idempotency_map.insert(key, id);
+                    }
+                    return Ok(id);
+                }
+                Coalesced::Pending(operation) => operation,
+            }
+        } else {
+            operation
+        };
 
         // Check queue size limit
         if queue.len() >= self.max_size {
@@ -129,18 +214,12 @@ impl OperationQueue {
             ));
         }
 
-        // Check for duplicate idempotency key
         if let Some(ref key) = operation.idempotency_key {
-            let mut idempotency_map = self.idempotency_map.write();
-            if let Some(&existing_id) = idempotency_map.get(key) {
-                // Operation with this key already exists
-                return Ok(existing_id);
-            }
             idempotency_map.insert(key.clone(), operation.id);
         }
 
         let id = operation.id;
-        queue.push_back(operation);
+        insert_by_priority(&mut queue, operation);
         Ok(id)
     }
 
@@ -150,9 +229,8 @@ impl OperationQueue {
         let op = queue.pop_front()?;
 
         // Remove from idempotency map
-        if let Some(ref key) = op.idempotency_key {
-            self.idempotency_map.write().remove(key);
-        }
+        let mut idempotency_map = self.idempotency_map.write();
+        forget_keys(&mut idempotency_map, &op, self.coalesce);
 
         Some(op)
     }
@@ -210,7 +288,7 @@ impl OperationQueue {
             if let Some(ref key) = op.idempotency_key {
                 idempotency_map.insert(key.clone(), op.id);
             }
-            queue.push_back(op);
+            insert_by_priority(&mut queue, op);
         }
 
         Ok(())
@@ -233,15 +311,150 @@ impl OperationQueue {
 
         queue.retain(|op| {
             if op.document_id() == document_id {
-                if let Some(ref key) = op.idempotency_key {
-                    idempotency_map.remove(key);
-                }
+                forget_keys(&mut idempotency_map, op, self.coalesce);
                 false
             } else {
                 true
             }
         });
     }
+
+    /// Number of pending operations of a priority class.
+    pub fn len_by_priority(&self, priority: Priority) -> usize {
+        self.queue
+            .read()
+            .iter()
+            .filter(|op| op.priority == priority)
+            .count()
+    }
+}
+
+/// Outcome of coalescing an operation into the queue.
+enum Coalesced {
+    /// Absorbed by (or cancelled against) the queue; carries the ID to
+    /// report and the idempotency key to record for it.
+    Into(OperationId, Option<String>),
+    /// Still needs to be queued.
+    Pending(Operation),
+}
+
+/// Insert an operation after every pending operation of the same or higher
+/// priority, and never ahead of a pending operation on the same document.
+fn insert_by_priority(queue: &mut VecDeque<Operation>, operation: Operation) {
+    let floor = queue
+        .iter()
+        .rposition(|op| op.document_id() == operation.document_id())
+        .map_or(0, |index| index + 1);
+    let index = (floor..queue.len())
+        .find(|&index| queue[index].priority < operation.priority)
+        .unwrap_or(queue.len());
+    queue.insert(index, operation);
+}
+
+/// Remove the idempotency keys pointing at an operation that left the queue.
+///
+/// Coalesced operations can be reached through several keys.
+fn forget_keys(idempotency_map: &mut HashMap<String, OperationId>, op: &Operation, all: bool) {
+    if let Some(ref key) = op.idempotency_key {
+        idempotency_map.remove(key);
+    }
+    if all {
+        idempotency_map.retain(|_, id| *id != op.id);
+    }
+}
+
+/// Collapse an operation with the pending operations of its document.
+fn coalesce(
+    queue: &mut VecDeque<Operation>,
+    idempotency_map: &mut HashMap<String, OperationId>,
+    operation: Operation,
+) -> Coalesced {
+    let document_id = operation.document_id().clone();
+    let last = queue
+        .iter()
+        .rposition(|op| op.document_id() == &document_id);
+
+    match operation.op_type {
+        OperationType::Update {
+            ref change_bytes, ..
+        } => {
+            let Some(index) = last else {
+                return Coalesced::Pending(operation);
+            };
+            let OperationType::Update {
+                change_bytes: pending,
+                ..
+            } = &mut queue[index].op_type
+            else {
+                return Coalesced::Pending(operation);
+            };
+
+            // Automerge loads concatenated changes incrementally
+            pending.extend_from_slice(change_bytes);
+            let id = queue[index].id;
+            if operation.priority > queue[index].priority {
+                if let Some(mut pending) = queue.remove(index) {
+                    pending.priority = operation.priority;
+                    insert_by_priority(queue, pending);
+                }
+            }
+            Coalesced::Into(id, operation.idempotency_key)
+        }
+        OperationType::Snapshot { version, .. } => {
+            let Some(index) = last else {
+                return Coalesced::Pending(operation);
+            };
+            let OperationType::Snapshot {
+                version: pending, ..
+            } = &mut queue[index].op_type
+            else {
+                return Coalesced::Pending(operation);
+            };
+
+            *pending = (*pending).max(version);
+            Coalesced::Into(queue[index].id, operation.idempotency_key)
+        }
+        OperationType::Delete { .. } => {
+            // The latest pending create or delete decides what the delete means
+            let lifecycle = queue
+                .iter()
+                .rev()
+                .find(|op| {
+                    op.document_id() == &document_id
+                        && matches!(
+                            op.op_type,
+                            OperationType::Create { .. } | OperationType::Delete { .. }
+                        )
+                })
+                .map(|op| (op.id, matches!(op.op_type, OperationType::Create { .. })));
+            let cancelled_create = match lifecycle {
+                Some((id, true)) => Some(id),
+                _ => None,
+            };
+
+            queue.retain(|op| {
+                let redundant = op.document_id() == &document_id
+                    && match op.op_type {
+                        OperationType::Update { .. } | OperationType::Snapshot { .. } => true,
+                        OperationType::Create { .. } => Some(op.id) == cancelled_create,
+                        OperationType::Delete { .. } => false,
+                    };
+                if redundant {
+                    forget_keys(idempotency_map, op, true);
+                }
+                !redundant
+            });
+
+            match lifecycle {
+                // Never synced, so nothing to delete
+                Some((_, true)) => Coalesced::Into(operation.id, None),
+                // Already being deleted
+                Some((id, false)) => Coalesced::Into(id, operation.idempotency_key),
+                None => Coalesced::Pending(operation),
+            }
+        }
+        OperationType::Create { .. } => Coalesced::Pending(operation),
+    }
 }
 
 impl Default for OperationQueue {
@@ -454,6 +667,191 @@ mod tests {
         assert_eq!(remaining[0].document_id().key, "bob");
     }
 
+    fn update(doc_id: &DocumentId, change_bytes: &[u8]) -> Operation {
+        Operation::new(OperationType::Update {
+            document_id: doc_id.clone(),
+            change_bytes: change_bytes.to_vec(),
+        })
+    }
+
+    #[test]
+    fn test_queue_priority_order() {
+        let queue = OperationQueue::new();
+        let alice = DocumentId::new("users", "alice");
+        let bob = DocumentId::new("users", "bob");
+
+        let low = Operation::new(OperationType::Snapshot {
+            document_id: DocumentId::new("users", "carol"),
+            version: 1,
+        })
+        .with_priority(Priority::Low);
+        let normal = Operation::new(OperationType::Create { document_id: alice.clone() });
+        let high = Operation::new(OperationType::Create { document_id: bob.clone() })
+            .with_priority(Priority::High);
+        // Held back behind the pending create of the same document
+        let high_alice = update(&alice, &[1]).with_priority(Priority::High);
+
+        for op in [low.clone(), normal.clone(), high.clone(), high_alice.clone()] {
+            queue.enqueue(op).unwrap();
+        }
+        assert_eq!(queue.len_by_priority(Priority::High), 2);
+
+        let order: Vec<_> = std::iter::from_fn(|| queue.dequeue()).map(|op| op.id).collect();
+        assert_eq!(order, vec![high.id, normal.id, high_alice.id, low.id]);
+    }
+
+    #[test]
+    fn test_queue_priority_serialization() {
+        let queue1 = OperationQueue::new();
+        let doc_id = DocumentId::new("users", "alice");
+        queue1
+            .enqueue(Operation::new(OperationType::Delete { document_id: doc_id.clone() }))
+            .unwrap();
+        queue1
+            .enqueue(update(&DocumentId::new("users", "bob"), &[]).with_priority(Priority::High))
+            .unwrap();
+
+        let queue2 = OperationQueue::new();
+        queue2.deserialize(&queue1.serialize().unwrap()).unwrap();
+        assert_eq!(queue2.peek().unwrap().priority, Priority::High);
+
+        // Queues serialized before priorities existed default to normal
+        let legacy = br#"[{"id":7,"op_type":{"Create":{"document_id":{"namespace":"users","key":"alice"}}},"timestamp":0,"idempotency_key":null,"retry_count":0}]"#;
+        queue2.deserialize(legacy).unwrap();
+        assert_eq!(queue2.peek().unwrap().priority, Priority::Normal);
+    }
+
+    #[test]
+    fn test_queue_coalesces_updates() {
+        let queue = OperationQueue::new().with_coalescing(true);
+        let doc_id = DocumentId::new("users", "alice");
+
+        let first = queue.enqueue(update(&doc_id, &[1, 2])).unwrap();
+        let second = queue
+            .enqueue(Operation::new_with_key(
+                OperationType::Update {
+                    document_id: doc_id.clone(),
+                    change_bytes: vec![3],
+                },
+                "typing".to_string(),
+            ))
+            .unwrap();
+        assert_eq!(first, second);
+        assert_eq!(queue.len(), 1);
+        assert_eq!(
+            queue.peek().unwrap().op_type,
+            OperationType::Update {
+                document_id: doc_id.clone(),
+                change_bytes: vec![1, 2, 3],
+            }
+        );
+
+        // The absorbed operation's key resolves to the merged one
+        let again = Operation::new_with_key(
+            OperationType::Create { document_id: doc_id.clone() },
+            "typing".to_string(),
+        );
+        assert_eq!(queue.enqueue(again).unwrap(), first);
+
+        // A snapshot in between keeps later updates separate
+        queue
+            .enqueue(Operation::new(OperationType::Snapshot {
+                document_id: doc_id.clone(),
+                version: 1,
+            }))
+            .unwrap();
+        queue.enqueue(update(&doc_id, &[4])).unwrap();
+        assert_eq!(queue.len(), 3);
+
+        // Raising the priority moves the merged update forward
+        queue.enqueue(update(&DocumentId::new("users", "bob"), &[])).unwrap();
+        let merged = queue
+            .enqueue(update(&doc_id, &[5]).with_priority(Priority::High))
+            .unwrap();
+        let ops = queue.list();
+        assert_eq!(ops.len(), 4);
+        assert_eq!(ops[2].id, merged);
+        assert_eq!(ops[2].priority, Priority::High);
+
+        queue.dequeue();
+        queue.dequeue();
+        queue.dequeue();
+        queue.dequeue();
+        assert!(queue.idempotency_map.read().is_empty());
+    }
+
+    #[test]
+    fn test_queue_coalesces_snapshots() {
+        let queue = OperationQueue::new().with_coalescing(true);
+        let doc_id = DocumentId::new("users", "alice");
+
+        for version in [2, 5, 3] {
+            queue
+                .enqueue(Operation::new(OperationType::Snapshot {
+                    document_id: doc_id.clone(),
+                    version,
+                }))
+                .unwrap();
+        }
+        assert_eq!(queue.len(), 1);
+        assert!(matches!(
+            queue.peek().unwrap().op_type,
+            OperationType::Snapshot { version: 5, .. }
+        ));
+    }
+
+    #[test]
+    fn test_queue_delete_cancels_pending_create() {
+        let queue = OperationQueue::new().with_coalescing(true);
+        let alice = DocumentId::new("users", "alice");
+        let bob = DocumentId::new("users", "bob");
+
+        queue
+            .enqueue(Operation::new(OperationType::Create { document_id: alice.clone() }))
+            .unwrap();
+        queue.enqueue(update(&alice, &[1])).unwrap();
+        queue.enqueue(update(&bob, &[1])).unwrap();
+        queue
+            .enqueue(Operation::new(OperationType::Delete { document_id: alice.clone() }))
+            .unwrap();
+
+        // Alice never left the device
+        assert!(queue.filter_by_document(&alice).is_empty());
+        assert_eq!(queue.len(), 1);
+
+        // Deleting a synced document drops its pending edits
+        let delete = queue
+            .enqueue(Operation::new(OperationType::Delete { document_id: bob.clone() }))
+            .unwrap();
+        let again = queue
+            .enqueue(Operation::new(OperationType::Delete { document_id: bob.clone() }))
+            .unwrap();
+        assert_eq!(delete, again);
+        let ops = queue.list();
+        assert_eq!(ops.len(), 1);
+        assert!(matches!(ops[0].op_type, OperationType::Delete { .. }));
+
+        // Recreating after a pending delete keeps both
+        queue
+            .enqueue(Operation::new(OperationType::Create { document_id: bob.clone() }))
+            .unwrap();
+        queue
+            .enqueue(Operation::new(OperationType::Delete { document_id: bob.clone() }))
+            .unwrap();
+        assert_eq!(queue.list().len(), 1);
+    }
+
+    #[test]
+    fn test_queue_without_coalescing() {
+        let queue = OperationQueue::new();
+        let doc_id = DocumentId::new("users", "alice");
+
+        queue.enqueue(update(&doc_id, &[1])).unwrap();
+        queue.enqueue(update(&doc_id, &[2])).unwrap();
+        assert_eq!(queue.len(), 2);
+        assert!(!queue.is_coalescing());
+    }
+
     #[test]
     fn test_operation_type_equality() {
         let doc_id = DocumentId::new("users", "alice");
@@ -2,6 +2,7 @@
 
 use crate::document_store::{DocumentHandle, DocumentId};
 use crate::error::{Result, StateError};
+use crate::metrics::LatencyHistogram;
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicU64, Ordering};
@@ -61,6 +62,8 @@ pub enum SubscriptionFilter {
     Document(DocumentId),
     /// Subscribe to changes on a specific path (e.g., "users/*/name").
     Path(DocumentId, String),
+    /// Subscribe to changes on every document in a namespace.
+    Namespace(String),
 }
 
 impl SubscriptionFilter {
@@ -76,6 +79,7 @@ impl SubscriptionFilter {
                         .map(|p| path_matches(path, p))
                         .unwrap_or(false)
             }
+            SubscriptionFilter::Namespace(namespace) => event.document_id.namespace == *namespace,
         }
     }
 }
@@ -108,8 +112,8 @@ struct SubscriptionData {
 
 /// Change event batcher to coalesce rapid changes.
 struct EventBatcher {
-    /// Pending events.
-    pending: Vec<ChangeEvent>,
+    /// Pending events with the time they were queued.
+    pending: Vec<(ChangeEvent, Instant)>,
     /// Last flush time.
     last_flush: Instant,
     /// Batch duration.
@@ -128,7 +132,7 @@ impl EventBatcher {
 
     /// Add an event to the batch.
     fn add(&mut self, event: ChangeEvent) {
-        self.pending.push(event);
+        self.pending.push((event, Instant::now()));
     }
 
     /// Check if the batch should be flushed.
@@ -137,7 +141,7 @@ impl EventBatcher {
     }
 
     /// Flush pending events.
-    fn flush(&mut self) -> Vec<ChangeEvent> {
+    fn flush(&mut self) -> Vec<(ChangeEvent, Instant)> {
         self.last_flush = Instant::now();
         std::mem::take(&mut self.pending)
     }
@@ -149,6 +153,10 @@ pub struct ChangeObservable {
     subscriptions: Arc<DashMap<SubscriptionId, SubscriptionData>>,
     /// Event batcher.
     batcher: Arc<parking_lot::Mutex<EventBatcher>>,
+    /// Latency from notification to delivery.
+    fanout_latency: LatencyHistogram,
+    /// Number of events delivered to subscribers.
+    events_delivered: AtomicU64,
 }
 
 impl ChangeObservable {
@@ -159,6 +167,8 @@ impl ChangeObservable {
             batcher: Arc::new(parking_lot::Mutex::new(EventBatcher::new(
                 Duration::from_millis(16), // One animation frame
             ))),
+            fanout_latency: LatencyHistogram::new("vudo_state_fanout_latency_seconds"),
+            events_delivered: AtomicU64::new(0),
         }
     }
 
@@ -201,16 +211,34 @@ impl ChangeObservable {
     pub fn flush_batch(&self) {
         let events = self.batcher.lock().flush();
 
-        for event in events {
+        for (event, queued_at) in events {
+            let mut delivered = 0;
             for entry in self.subscriptions.iter() {
                 if entry.value().filter.matches(&event) {
                     // Ignore send errors (subscriber may have dropped)
-                    let _ = entry.value().sender.send(event.clone());
+                    if entry.value().sender.send(event.clone()).is_ok() {
+                        delivered += 1;
+                    }
                 }
             }
+
+            if delivered > 0 {
+                self.events_delivered.fetch_add(delivered, Ordering::Relaxed);
+                self.fanout_latency.observe(queued_at.elapsed());
+            }
         }
     }
 
+    /// Get the notification-to-delivery latency histogram.
+    pub fn fanout_latency(&self) -> &LatencyHistogram {
+        &self.fanout_latency
+    }
+
+    /// Get the number of events delivered to subscribers.
+    pub fn events_delivered(&self) -> u64 {
+        self.events_delivered.load(Ordering::Relaxed)
+    }
+
     /// Get the number of active subscriptions.
     pub fn subscription_count(&self) -> usize {
         self.subscriptions.len()
@@ -339,6 +367,29 @@ mod tests {
         assert!(!filter.matches(&event2));
     }
 
+    #[test]
+    fn test_subscription_filter_namespace() {
+        let filter = SubscriptionFilter::Namespace("users".to_string());
+
+        let event = ChangeEvent {
+            document_id: DocumentId::new("users", "alice"),
+            timestamp: 0,
+            change_hash: vec![],
+            path: None,
+        };
+
+        assert!(filter.matches(&event));
+
+        let event2 = ChangeEvent {
+            document_id: DocumentId::new("posts", "1"),
+            timestamp: 0,
+            change_hash: vec![],
+            path: None,
+        };
+
+        assert!(!filter.matches(&event2));
+    }
+
     #[test]
     fn test_observable_subscribe_unsubscribe() {
         let observable = ChangeObservable::new();
@@ -1,14 +1,31 @@
 //! Snapshot management for document compaction and versioning.
-
+//!
+//! Compaction keeps only the most recent snapshots of a document. Audit heads
+//! (signed checkpoints, deletion certificates, reconciliation points) marked
+//! with [`SnapshotManager::mark_audit_head`] are stored apart from the
+//! snapshots and kept verbatim, so they stay verifiable after the history
+//! around them is compacted. [`SnapshotManager::verify_audit_chain`] checks
+//! that each head is intact and that the latest snapshot and the current
+//! state still descend from it.
+//!
+//! Snapshots taken by a [`SnapshotManager`] with codecs are encoded with the
+//! codec of their namespace, so encrypted namespaces stay encrypted in
+//! snapshot storage and archives.
+
+use crate::codec::CodecRegistry;
 use crate::document_store::{DocumentHandle, DocumentId};
 use crate::error::{Result, StateError};
-use automerge::AutoCommit;
+use crate::metrics::LatencyHistogram;
+use automerge::{AutoCommit, Change, ChangeHash};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
 use tokio::time::Duration;
 
+/// Metric name of the snapshot creation duration histogram.
+const SNAPSHOT_DURATION_METRIC: &str = "vudo_state_snapshot_duration_seconds";
+
 /// Snapshot metadata.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SnapshotMetadata {
@@ -29,14 +46,32 @@ pub struct SnapshotMetadata {
 pub struct Snapshot {
     /// Metadata.
     pub metadata: SnapshotMetadata,
-    /// Serialized Automerge document.
+    /// Serialized Automerge document, possibly encoded with its namespace
+    /// codec.
     pub data: Vec<u8>,
 }
 
 impl Snapshot {
     /// Create a new snapshot from a document handle.
     pub fn from_document(handle: &DocumentHandle, version: u64) -> Self {
-        let data = handle.save();
+        Self::with_data(handle, version, handle.save())
+    }
+
+    /// Create a snapshot holding the canonical encoding of a document.
+    ///
+    /// Replicas holding the same changes produce identical snapshot data.
+    pub fn canonical(handle: &DocumentHandle, version: u64) -> Result<Self> {
+        Ok(Self::with_data(handle, version, canonical_save(handle)?))
+    }
+
+    /// Create a snapshot from a document handle, encoded with its namespace
+    /// codec.
+    pub fn encoded(handle: &DocumentHandle, version: u64, codecs: &CodecRegistry) -> Result<Self> {
+        let data = codecs.encode(&handle.id, handle.save())?;
+        Ok(Self::with_data(handle, version, data))
+    }
+
+    fn with_data(handle: &DocumentHandle, version: u64, data: Vec<u8>) -> Self {
         let timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
@@ -54,10 +89,19 @@ impl Snapshot {
     }
 
     /// Load a document from this snapshot.
+    ///
+    /// Encoded snapshots fail to load; use [`Snapshot::decode`].
     pub fn to_document(&self) -> Result<AutoCommit> {
         AutoCommit::load(&self.data).map_err(StateError::from)
     }
 
+    /// Load a document from this snapshot, decoding it with its namespace
+    /// codec if it was encoded.
+    pub fn decode(&self, codecs: &CodecRegistry) -> Result<AutoCommit> {
+        let bytes = codecs.decode(&self.metadata.document_id, &self.data)?;
+        AutoCommit::load(&bytes).map_err(StateError::from)
+    }
+
     /// Calculate compression ratio compared to original size.
     pub fn compression_ratio(&self, original_size: usize) -> f64 {
         if original_size == 0 {
@@ -67,10 +111,131 @@ impl Snapshot {
     }
 }
 
+/// Kind of an audit head.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditHeadKind {
+    /// A checkpoint signed by its author.
+    SignedCheckpoint,
+    /// Proof that data was deleted.
+    DeletionCertificate,
+    /// A point where replicas were reconciled.
+    ReconciliationPoint,
+    /// An application-defined kind.
+    Custom(String),
+}
+
+/// A document state preserved verbatim across compaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditHead {
+    /// Document ID.
+    pub document_id: DocumentId,
+    /// Position in the document's audit chain, starting at 1.
+    pub sequence: u64,
+    /// Kind of head.
+    pub kind: AuditHeadKind,
+    /// Label, e.g. the checkpoint signature or certificate ID.
+    pub label: String,
+    /// Document heads at this point (sorted).
+    pub heads: Vec<ChangeHash>,
+    /// Version of the latest snapshot when the head was marked (0 if none).
+    pub snapshot_version: u64,
+    /// Timestamp (Unix epoch milliseconds).
+    pub timestamp: u64,
+    /// Serialized Automerge document at this point.
+    pub data: Vec<u8>,
+}
+
+impl AuditHead {
+    /// Capture the current state of a document as an audit head.
+    fn from_document(
+        handle: &DocumentHandle,
+        sequence: u64,
+        kind: AuditHeadKind,
+        label: String,
+        snapshot_version: u64,
+    ) -> Result<Self> {
+        let data = canonical_save(handle)?;
+        let mut heads = handle.heads();
+        heads.sort();
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+
+        Ok(Self {
+            document_id: handle.id.clone(),
+            sequence,
+            kind,
+            label,
+            heads,
+            snapshot_version,
+            timestamp,
+            data,
+        })
+    }
+
+    /// Load the document state at this head.
+    pub fn to_document(&self) -> Result<AutoCommit> {
+        AutoCommit::load(&self.data).map_err(StateError::from)
+    }
+
+    /// Check that the preserved data reproduces the recorded heads.
+    pub fn verify(&self) -> Result<()> {
+        self.load_verified().map(|_| ())
+    }
+
+    /// Load the document state, checking it against the recorded heads.
+    fn load_verified(&self) -> Result<AutoCommit> {
+        let mut doc = self.to_document()?;
+        let mut heads = doc.get_heads();
+        heads.sort();
+        if heads != self.heads {
+            return Err(StateError::SnapshotError(format!(
+                "Audit head {} of {} does not match its recorded heads",
+                self.sequence,
+                self.document_id.to_string()
+            )));
+        }
+        Ok(doc)
+    }
+}
+
+/// A failed check of an audit chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditFailure {
+    /// Sequence of the audit head, or `None` for the snapshot itself.
+    pub sequence: Option<u64>,
+    /// What failed.
+    pub reason: String,
+}
+
+/// Result of verifying a document's audit chain.
+#[derive(Debug, Clone)]
+pub struct AuditChainReport {
+    /// Document ID.
+    pub document_id: DocumentId,
+    /// Version of the latest snapshot, if any.
+    pub snapshot_version: Option<u64>,
+    /// Number of audit heads that passed every check.
+    pub verified_heads: usize,
+    /// Failed checks.
+    pub failures: Vec<AuditFailure>,
+}
+
+impl AuditChainReport {
+    /// Check whether every check passed.
+    pub fn is_valid(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
 /// Snapshot storage (in-memory for now, will be persisted in Phase 2.2).
 pub struct SnapshotStorage {
     /// Map of document ID to snapshots (ordered by version).
     snapshots: Arc<RwLock<HashMap<DocumentId, Vec<Snapshot>>>>,
+    /// Map of document ID to audit heads (ordered by sequence).
+    audit_heads: Arc<RwLock<HashMap<DocumentId, Vec<AuditHead>>>>,
     /// Maximum number of snapshots to keep per document.
     max_snapshots_per_doc: usize,
 }
@@ -80,6 +245,7 @@ impl SnapshotStorage {
     pub fn new() -> Self {
         Self {
             snapshots: Arc::new(RwLock::new(HashMap::new())),
+            audit_heads: Arc::new(RwLock::new(HashMap::new())),
             max_snapshots_per_doc: 10,
         }
     }
@@ -88,6 +254,7 @@ impl SnapshotStorage {
     pub fn with_max_snapshots(max_snapshots_per_doc: usize) -> Self {
         Self {
             snapshots: Arc::new(RwLock::new(HashMap::new())),
+            audit_heads: Arc::new(RwLock::new(HashMap::new())),
             max_snapshots_per_doc,
         }
     }
@@ -137,12 +304,64 @@ impl SnapshotStorage {
             .unwrap_or_default()
     }
 
+    /// Get every stored snapshot across all documents.
+    pub fn all(&self) -> Vec<Snapshot> {
+        self.snapshots
+            .read()
+            .values()
+            .flat_map(|snaps| snaps.iter().cloned())
+            .collect()
+    }
+
     /// Delete all snapshots for a document.
+    ///
+    /// Audit heads are kept; see [`SnapshotStorage::delete_audit_heads`].
     pub fn delete(&self, document_id: &DocumentId) -> Result<()> {
         self.snapshots.write().remove(document_id);
         Ok(())
     }
 
+    /// Store an audit head.
+    pub fn store_audit_head(&self, head: AuditHead) -> Result<()> {
+        let mut audit_heads = self.audit_heads.write();
+        let doc_heads = audit_heads.entry(head.document_id.clone()).or_default();
+
+        if doc_heads.iter().any(|h| h.sequence == head.sequence) {
+            return Err(StateError::SnapshotError(format!(
+                "Audit head {} of {} already exists",
+                head.sequence,
+                head.document_id.to_string()
+            )));
+        }
+        doc_heads.push(head);
+        doc_heads.sort_by_key(|h| h.sequence);
+        Ok(())
+    }
+
+    /// List the audit heads of a document, oldest first.
+    pub fn audit_heads(&self, document_id: &DocumentId) -> Vec<AuditHead> {
+        self.audit_heads
+            .read()
+            .get(document_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Delete all audit heads of a document.
+    pub fn delete_audit_heads(&self, document_id: &DocumentId) -> Result<()> {
+        self.audit_heads.write().remove(document_id);
+        Ok(())
+    }
+
+    /// Get the total number of audit heads.
+    pub fn audit_head_count(&self) -> usize {
+        self.audit_heads
+            .read()
+            .values()
+            .map(|heads| heads.len())
+            .sum()
+    }
+
     /// Delete snapshots older than a specific version.
     pub fn delete_older_than(&self, document_id: &DocumentId, version: u64) -> Result<()> {
         let mut snapshots = self.snapshots.write();
@@ -171,9 +390,10 @@ impl SnapshotStorage {
             .sum()
     }
 
-    /// Clear all snapshots.
+    /// Clear all snapshots and audit heads.
     pub fn clear(&self) {
         self.snapshots.write().clear();
+        self.audit_heads.write().clear();
     }
 }
 
@@ -191,6 +411,10 @@ pub struct SnapshotManager {
     snapshot_interval: Duration,
     /// Minimum changes before creating a snapshot.
     min_changes_threshold: usize,
+    /// Snapshot creation durations.
+    durations: LatencyHistogram,
+    /// Codecs snapshots are encoded with.
+    codecs: CodecRegistry,
 }
 
 impl SnapshotManager {
@@ -200,6 +424,8 @@ impl SnapshotManager {
             storage,
             snapshot_interval: Duration::from_secs(60), // 1 minute
             min_changes_threshold: 10,
+            durations: LatencyHistogram::new(SNAPSHOT_DURATION_METRIC),
+            codecs: CodecRegistry::new(),
         }
     }
 
@@ -213,21 +439,50 @@ impl SnapshotManager {
             storage,
             snapshot_interval,
             min_changes_threshold,
+            durations: LatencyHistogram::new(SNAPSHOT_DURATION_METRIC),
+            codecs: CodecRegistry::new(),
         }
     }
 
+    /// Encode snapshots with per-namespace codecs, usually those of the
+    /// document store (see [`DocumentStore::codecs`]).
+    ///
+    /// [`DocumentStore::codecs`]: crate::document_store::DocumentStore::codecs
+    pub fn with_codecs(mut self, codecs: CodecRegistry) -> Self {
+        self.codecs = codecs;
+        self
+    }
+
     /// Create a snapshot of a document.
     pub fn create_snapshot(&self, handle: &DocumentHandle) -> Result<Snapshot> {
+        self.store_snapshot(handle, |version| {
+            Snapshot::encoded(handle, version, &self.codecs)
+        })
+    }
+
+    /// Create a snapshot with the next version number and store it.
+    fn store_snapshot<F>(&self, handle: &DocumentHandle, create: F) -> Result<Snapshot>
+    where
+        F: FnOnce(u64) -> Result<Snapshot>,
+    {
+        let started = std::time::Instant::now();
+
         // Get the next version number
         let latest = self.storage.get_latest(&handle.id);
         let version = latest.map(|s| s.metadata.version + 1).unwrap_or(1);
 
-        let snapshot = Snapshot::from_document(handle, version);
+        let snapshot = create(version)?;
         self.storage.store(snapshot.clone())?;
+        self.durations.observe(started.elapsed());
 
         Ok(snapshot)
     }
 
+    /// Get the snapshot creation duration histogram.
+    pub fn durations(&self) -> &LatencyHistogram {
+        &self.durations
+    }
+
     /// Check if a document should be snapshotted based on change count.
     pub fn should_snapshot(&self, handle: &DocumentHandle) -> bool {
         let change_count = handle.change_count();
@@ -244,11 +499,22 @@ impl SnapshotManager {
     }
 
     /// Compact a document by loading the latest snapshot and creating a new one.
+    ///
+    /// The new snapshot holds the canonical encoding of the document, so
+    /// replicas with the same changes compact to identical bytes (before
+    /// any namespace codec is applied). Only the most recent snapshots are
+    /// kept; the document's audit heads are preserved as they are.
     pub fn compact(&self, handle: &DocumentHandle) -> Result<CompactionResult> {
         let original_size = handle.save().len();
 
         // Create a new snapshot
-        let snapshot = self.create_snapshot(handle)?;
+        let snapshot = self.store_snapshot(handle, |version| {
+            let mut snapshot = Snapshot::canonical(handle, version)?;
+            snapshot.data = self.codecs.encode(&handle.id, snapshot.data)?;
+            snapshot.metadata.size = snapshot.data.len();
+            Ok(snapshot)
+        })?;
+        let preserved_heads = self.storage.audit_heads(&handle.id).len();
 
         let compacted_size = snapshot.metadata.size;
         let reduction = original_size.saturating_sub(compacted_size);
@@ -263,9 +529,123 @@ impl SnapshotManager {
             compacted_size,
             reduction,
             reduction_percent,
+            preserved_heads,
         })
     }
 
+    /// Preserve the current state of a document as an audit head.
+    ///
+    /// The head is appended to the document's audit chain and survives
+    /// compaction verbatim.
+    pub fn mark_audit_head(
+        &self,
+        handle: &DocumentHandle,
+        kind: AuditHeadKind,
+        label: impl Into<String>,
+    ) -> Result<AuditHead> {
+        let sequence = self
+            .storage
+            .audit_heads(&handle.id)
+            .last()
+            .map(|h| h.sequence + 1)
+            .unwrap_or(1);
+        let snapshot_version = self
+            .storage
+            .get_latest(&handle.id)
+            .map(|s| s.metadata.version)
+            .unwrap_or(0);
+
+        let head =
+            AuditHead::from_document(handle, sequence, kind, label.into(), snapshot_version)?;
+        self.storage.store_audit_head(head.clone())?;
+        Ok(head)
+    }
+
+    /// List the audit heads of a document, oldest first.
+    pub fn audit_heads(&self, document_id: &DocumentId) -> Vec<AuditHead> {
+        self.storage.audit_heads(document_id)
+    }
+
+    /// Verify the audit chain of a document.
+    ///
+    /// Checks that each audit head reproduces its recorded heads, that each
+    /// head descends from the one before it, that snapshots taken after a
+    /// head contain it, and that the current state descends from the latest
+    /// snapshot and from every head.
+    pub fn verify_audit_chain(&self, handle: &DocumentHandle) -> AuditChainReport {
+        let latest = self.storage.get_latest(&handle.id);
+        let mut report = AuditChainReport {
+            document_id: handle.id.clone(),
+            snapshot_version: latest.as_ref().map(|s| s.metadata.version),
+            verified_heads: 0,
+            failures: Vec::new(),
+        };
+        let mut fail = |sequence, reason| report.failures.push(AuditFailure { sequence, reason });
+
+        // Snapshot to current state
+        let mut snapshot_doc = None;
+        if let Some(snapshot) = &latest {
+            let version = snapshot.metadata.version;
+            match snapshot.decode(&self.codecs) {
+                Ok(mut doc) => {
+                    if !handle.contains_heads(&doc.get_heads()) {
+                        fail(
+                            None,
+                            format!("Current state does not descend from snapshot {}", version),
+                        );
+                    }
+                    snapshot_doc = Some((version, doc));
+                }
+                Err(e) => fail(None, format!("Snapshot {} does not load: {}", version, e)),
+            }
+        }
+
+        let mut verified = 0;
+        let mut previous: Option<&AuditHead> = None;
+        for head in &self.storage.audit_heads(&handle.id) {
+            let sequence = Some(head.sequence);
+            let mut valid = true;
+            let mut check = |ok: bool, reason: String| {
+                if !ok {
+                    valid = false;
+                    fail(sequence, reason);
+                }
+            };
+
+            match head.load_verified() {
+                Ok(mut doc) => {
+                    if let Some(previous) = previous {
+                        check(
+                            contains_heads(&mut doc, &previous.heads),
+                            format!("Does not descend from audit head {}", previous.sequence),
+                        );
+                    }
+                }
+                Err(e) => check(false, e.to_string()),
+            }
+            if let Some((version, doc)) = snapshot_doc.as_mut() {
+                if *version > head.snapshot_version {
+                    check(
+                        contains_heads(doc, &head.heads),
+                        format!("Snapshot {} does not contain the audit head", version),
+                    );
+                }
+            }
+            check(
+                handle.contains_heads(&head.heads),
+                "Current state does not contain the audit head".to_string(),
+            );
+
+            if valid {
+                verified += 1;
+            }
+            previous = Some(head);
+        }
+
+        report.verified_heads = verified;
+        report
+    }
+
     /// Start a background task that periodically creates snapshots.
     pub async fn start_background_snapshots(
         self: Arc<Self>,
@@ -304,6 +684,58 @@ pub struct CompactionResult {
     pub reduction: usize,
     /// Size reduction percentage.
     pub reduction_percent: f64,
+    /// Number of audit heads preserved.
+    pub preserved_heads: usize,
+}
+
+/// Encode a document canonically.
+///
+/// The changes are applied to an empty document in dependency order, ties
+/// broken by change hash, so replicas holding the same changes produce the
+/// same bytes whatever order they received them in.
+fn canonical_save(handle: &DocumentHandle) -> Result<Vec<u8>> {
+    let mut doc = AutoCommit::load(&handle.save())?;
+    let changes: HashMap<ChangeHash, Change> = doc
+        .get_changes(&[])
+        .into_iter()
+        .map(|change| (change.hash(), change.clone()))
+        .collect();
+
+    let mut missing_deps: HashMap<ChangeHash, usize> = HashMap::new();
+    let mut dependents: HashMap<ChangeHash, Vec<ChangeHash>> = HashMap::new();
+    let mut ready = BTreeSet::new();
+    for (hash, change) in &changes {
+        missing_deps.insert(*hash, change.deps().len());
+        for dep in change.deps() {
+            dependents.entry(*dep).or_default().push(*hash);
+        }
+        if change.deps().is_empty() {
+            ready.insert(*hash);
+        }
+    }
+
+    let mut ordered = Vec::with_capacity(changes.len());
+    while let Some(hash) = ready.pop_first() {
+        for dependent in dependents.remove(&hash).unwrap_or_default() {
+            let missing = missing_deps.get_mut(&dependent).expect("dependent change");
+            *missing -= 1;
+            if *missing == 0 {
+                ready.insert(dependent);
+            }
+        }
+        ordered.push(changes[&hash].clone());
+    }
+
+    let mut canonical = AutoCommit::new();
+    canonical.apply_changes(ordered)?;
+    Ok(canonical.save())
+}
+
+/// Check whether a document contains every change in `heads`.
+fn contains_heads(doc: &mut AutoCommit, heads: &[ChangeHash]) -> bool {
+    heads
+        .iter()
+        .all(|hash| doc.get_change_by_hash(hash).is_some())
 }
 
 #[cfg(test)]
@@ -631,4 +1063,132 @@ mod tests {
 
         task.abort();
     }
+
+    fn set_name(handle: &DocumentHandle, name: &str) {
+        handle
+            .update(|doc| {
+                doc.put(ROOT, "name", name)?;
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_audit_heads_survive_compaction() {
+        let storage = Arc::new(SnapshotStorage::with_max_snapshots(2));
+        let manager = SnapshotManager::new(Arc::clone(&storage));
+        let store = DocumentStore::new();
+        let handle = store.create(DocumentId::new("users", "alice")).unwrap();
+
+        set_name(&handle, "Alice");
+        let checkpoint = manager
+            .mark_audit_head(&handle, AuditHeadKind::SignedCheckpoint, "sig-1")
+            .unwrap();
+        set_name(&handle, "Alicia");
+        manager
+            .mark_audit_head(&handle, AuditHeadKind::DeletionCertificate, "cert-1")
+            .unwrap();
+
+        for i in 0..5 {
+            set_name(&handle, &format!("Alice {}", i));
+            let result = manager.compact(&handle).unwrap();
+            assert_eq!(result.preserved_heads, 2);
+        }
+        assert_eq!(storage.list(&handle.id).len(), 2);
+
+        let heads = manager.audit_heads(&handle.id);
+        assert_eq!(heads.len(), 2);
+        assert_eq!(heads[0].sequence, 1);
+        assert_eq!(heads[0].kind, AuditHeadKind::SignedCheckpoint);
+        assert_eq!(heads[0].data, checkpoint.data);
+        assert_eq!(heads[1].sequence, 2);
+        assert_eq!(
+            get_string(&heads[0].to_document().unwrap(), ROOT, "name").unwrap(),
+            "Alice"
+        );
+
+        let report = manager.verify_audit_chain(&handle);
+        assert!(report.is_valid(), "{:?}", report.failures);
+        assert_eq!(report.verified_heads, 2);
+        assert_eq!(report.snapshot_version, Some(5));
+
+        // Deleting snapshots keeps the audit heads
+        storage.delete(&handle.id).unwrap();
+        assert_eq!(manager.audit_heads(&handle.id).len(), 2);
+        storage.delete_audit_heads(&handle.id).unwrap();
+        assert_eq!(storage.audit_head_count(), 0);
+    }
+
+    #[test]
+    fn test_verify_audit_chain_detects_tampering() {
+        let storage = Arc::new(SnapshotStorage::new());
+        let manager = SnapshotManager::new(Arc::clone(&storage));
+        let store = DocumentStore::new();
+        let handle = store.create(DocumentId::new("users", "alice")).unwrap();
+        let other = store.create(DocumentId::new("users", "bob")).unwrap();
+
+        set_name(&handle, "Alice");
+        let mut head = manager
+            .mark_audit_head(&handle, AuditHeadKind::ReconciliationPoint, "sync-1")
+            .unwrap();
+        assert!(head.verify().is_ok());
+
+        // Preserved data replaced by another document's state
+        set_name(&other, "Bob");
+        head.data = other.save();
+        assert!(head.verify().is_err());
+        head.sequence = 2;
+        storage.store_audit_head(head).unwrap();
+
+        let report = manager.verify_audit_chain(&handle);
+        assert!(!report.is_valid());
+        assert_eq!(report.verified_heads, 1);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].sequence, Some(2));
+
+        // A state that doesn't descend from the heads fails as well
+        let report = manager.verify_audit_chain(&other);
+        assert_eq!(report.verified_heads, 0);
+    }
+
+    #[test]
+    fn test_compaction_is_deterministic() {
+        let store = DocumentStore::new();
+        let alice = store.create(DocumentId::new("users", "alice")).unwrap();
+        set_name(&alice, "Alice");
+        let replica = store
+            .load(DocumentId::new("users", "replica"), &alice.save())
+            .unwrap();
+
+        // Concurrent changes merged in opposite orders
+        let base = alice.heads();
+        set_name(&alice, "Alicia");
+        replica
+            .update(|doc| {
+                doc.put(ROOT, "age", 30i64)?;
+                Ok(())
+            })
+            .unwrap();
+        let from_alice = alice.save_after(&base);
+        let from_replica = replica.save_after(&base);
+        alice.load_incremental(&from_replica).unwrap();
+        replica.load_incremental(&from_alice).unwrap();
+
+        let a = SnapshotManager::new(Arc::new(SnapshotStorage::new()));
+        let b = SnapshotManager::new(Arc::new(SnapshotStorage::new()));
+        a.compact(&alice).unwrap();
+        b.compact(&replica).unwrap();
+        assert_eq!(
+            a.storage().get_latest(&alice.id).unwrap().data,
+            b.storage().get_latest(&replica.id).unwrap().data
+        );
+        assert_eq!(
+            a.mark_audit_head(&alice, AuditHeadKind::ReconciliationPoint, "merge")
+                .unwrap()
+                .data,
+            b.mark_audit_head(&replica, AuditHeadKind::ReconciliationPoint, "merge")
+                .unwrap()
+                .data
+        );
+    }
 }
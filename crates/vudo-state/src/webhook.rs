@@ -0,0 +1,566 @@
+//! Document change webhooks for server-side integrations.
+//!
+//! The [`WebhookDispatcher`] POSTs a signed JSON notification to every
+//! registered endpoint subscribed to the namespace of a changed document, so
+//! conventional back-ends can react to local-first data changes. Deliveries
+//! are retried with exponential backoff on network errors, `429` and `5xx`
+//! responses.
+//!
+//! Every request carries three headers:
+//! - `X-Vudo-Event-Id`: unique ID of the notification (stable across retries)
+//! - `X-Vudo-Timestamp`: signing time in Unix epoch seconds
+//! - `X-Vudo-Signature`: `sha256=<hex>` HMAC-SHA256 of `"{timestamp}.{body}"`
+//!   keyed with the endpoint secret
+//!
+//! Receivers check the signature with [`verify_signature`].
+//!
+//! Only available with the `webhooks` feature.
+
+use crate::document_store::DocumentId;
+use crate::error::{Result, StateError};
+use crate::reactive::{ChangeEvent, ChangeObservable, SubscriptionFilter};
+use dashmap::DashMap;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Header carrying the notification ID.
+pub const EVENT_ID_HEADER: &str = "X-Vudo-Event-Id";
+
+/// Header carrying the signing timestamp.
+pub const TIMESTAMP_HEADER: &str = "X-Vudo-Timestamp";
+
+/// Header carrying the HMAC signature.
+pub const SIGNATURE_HEADER: &str = "X-Vudo-Signature";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Webhook endpoint ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WebhookId(u64);
+
+impl WebhookId {
+    /// Generate a new webhook ID.
+    fn new() -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        Self(COUNTER.fetch_add(1, Ordering::SeqCst))
+    }
+}
+
+/// Configuration of a webhook endpoint.
+#[derive(Debug, Clone)]
+pub struct WebhookEndpoint {
+    /// URL notifications are POSTed to.
+    pub url: String,
+    /// Shared secret used to sign notifications.
+    pub secret: Vec<u8>,
+    /// Namespaces this endpoint is subscribed to (empty means none).
+    pub namespaces: Vec<String>,
+    /// Maximum number of retries after the first attempt.
+    pub max_retries: u32,
+    /// Delay before the first retry.
+    pub initial_backoff: Duration,
+    /// Upper bound on the retry delay.
+    pub max_backoff: Duration,
+}
+
+impl WebhookEndpoint {
+    /// Create a new endpoint with default retry settings.
+    pub fn new(url: impl Into<String>, secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            url: url.into(),
+            secret: secret.into(),
+            namespaces: Vec::new(),
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+
+    /// Subscribe the endpoint to a namespace.
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespaces.push(namespace.into());
+        self
+    }
+
+    /// Set the retry policy.
+    pub fn with_retries(
+        mut self,
+        max_retries: u32,
+        initial_backoff: Duration,
+        max_backoff: Duration,
+    ) -> Self {
+        self.max_retries = max_retries;
+        self.initial_backoff = initial_backoff;
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Check whether the endpoint is subscribed to a document's namespace.
+    pub fn is_subscribed(&self, document_id: &DocumentId) -> bool {
+        self.namespaces.contains(&document_id.namespace)
+    }
+
+    /// Get the delay before retry number `attempt` (starting at 1).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// JSON body of a change notification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    /// Unique notification ID.
+    pub event_id: String,
+    /// Namespace of the changed document.
+    pub namespace: String,
+    /// Key of the changed document.
+    pub key: String,
+    /// Timestamp of the change (Unix epoch milliseconds).
+    pub timestamp: u64,
+    /// Hex-encoded change hash.
+    pub change_hash: String,
+    /// Path affected, if known.
+    pub path: Option<String>,
+}
+
+impl WebhookPayload {
+    /// Build a payload from a change event.
+    pub fn from_event(event: &ChangeEvent) -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        Self {
+            event_id: format!(
+                "{}-{}",
+                event.timestamp,
+                COUNTER.fetch_add(1, Ordering::SeqCst)
+            ),
+            namespace: event.document_id.namespace.clone(),
+            key: event.document_id.key.clone(),
+            timestamp: event.timestamp,
+            change_hash: hex::encode(&event.change_hash),
+            path: event.path.clone(),
+        }
+    }
+}
+
+/// Outcome of delivering a notification to one endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryResult {
+    /// Endpoint the notification was sent to.
+    pub webhook_id: WebhookId,
+    /// Notification ID.
+    pub event_id: String,
+    /// Number of attempts made.
+    pub attempts: u32,
+    /// Final HTTP status, if a response was received.
+    pub status: Option<u16>,
+    /// Whether the endpoint accepted the notification.
+    pub delivered: bool,
+}
+
+/// Webhook delivery statistics.
+#[derive(Debug, Clone, Default)]
+pub struct WebhookStats {
+    /// Notifications accepted by their endpoint.
+    pub delivered: u64,
+    /// Notifications given up on after exhausting retries.
+    pub failed: u64,
+    /// Retries performed.
+    pub retries: u64,
+}
+
+/// Dispatcher POSTing signed change notifications to webhook endpoints.
+pub struct WebhookDispatcher {
+    /// HTTP client.
+    client: reqwest::Client,
+    /// Registered endpoints.
+    endpoints: DashMap<WebhookId, WebhookEndpoint>,
+    /// Delivery statistics.
+    stats: parking_lot::Mutex<WebhookStats>,
+}
+
+impl WebhookDispatcher {
+    /// Create a new dispatcher with the given per-request timeout.
+    pub fn new(timeout: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| StateError::WebhookFailed(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            endpoints: DashMap::new(),
+            stats: parking_lot::Mutex::new(WebhookStats::default()),
+        })
+    }
+
+    /// Register an endpoint.
+    pub fn register(&self, endpoint: WebhookEndpoint) -> WebhookId {
+        let id = WebhookId::new();
+        self.endpoints.insert(id, endpoint);
+        id
+    }
+
+    /// Unregister an endpoint.
+    pub fn unregister(&self, id: WebhookId) -> Result<()> {
+        self.endpoints
+            .remove(&id)
+            .ok_or(StateError::WebhookFailed(format!(
+                "Unknown webhook {:?}",
+                id
+            )))?;
+        Ok(())
+    }
+
+    /// Get the number of registered endpoints.
+    pub fn endpoint_count(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    /// Get delivery statistics.
+    pub fn stats(&self) -> WebhookStats {
+        self.stats.lock().clone()
+    }
+
+    /// Deliver a change event to every endpoint subscribed to its namespace.
+    pub async fn dispatch(&self, event: &ChangeEvent) -> Result<Vec<DeliveryResult>> {
+        let targets: Vec<(WebhookId, WebhookEndpoint)> = self
+            .endpoints
+            .iter()
+            .filter(|entry| entry.value().is_subscribed(&event.document_id))
+            .map(|entry| (*entry.key(), entry.value().clone()))
+            .collect();
+        if targets.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let payload = WebhookPayload::from_event(event);
+        let body = serde_json::to_vec(&payload)?;
+
+        let deliveries = targets
+            .iter()
+            .map(|(id, endpoint)| self.deliver(*id, endpoint, &payload.event_id, &body));
+        Ok(futures::future::join_all(deliveries).await)
+    }
+
+    /// Forward changes in a namespace to the subscribed endpoints.
+    ///
+    /// Spawns a task that runs until the observable drops the subscription.
+    pub fn subscribe(
+        self: &Arc<Self>,
+        observable: &ChangeObservable,
+        namespace: impl Into<String>,
+    ) -> JoinHandle<()> {
+        let mut subscription =
+            observable.subscribe(SubscriptionFilter::Namespace(namespace.into()));
+        let dispatcher = Arc::clone(self);
+
+        tokio::spawn(async move {
+            while let Some(event) = subscription.recv().await {
+                if let Err(e) = dispatcher.dispatch(&event).await {
+                    warn!(
+                        "Failed to dispatch webhooks for {}: {}",
+                        event.document_id, e
+                    );
+                }
+            }
+        })
+    }
+
+    /// Deliver one notification to one endpoint, retrying with backoff.
+    async fn deliver(
+        &self,
+        webhook_id: WebhookId,
+        endpoint: &WebhookEndpoint,
+        event_id: &str,
+        body: &[u8],
+    ) -> DeliveryResult {
+        let mut attempts = 0;
+        let mut status = None;
+
+        loop {
+            attempts += 1;
+            let timestamp = current_timestamp_secs();
+            let signature = sign_payload(&endpoint.secret, timestamp, body);
+
+            let response = self
+                .client
+                .post(&endpoint.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_ID_HEADER, event_id)
+                .header(TIMESTAMP_HEADER, timestamp.to_string())
+                .header(SIGNATURE_HEADER, signature)
+                .body(body.to_vec())
+                .send()
+                .await;
+
+            let retryable = match response {
+                Ok(resp) => {
+                    let code = resp.status();
+                    status = Some(code.as_u16());
+                    if code.is_success() {
+                        debug!("Webhook {} accepted event {}", endpoint.url, event_id);
+                        self.stats.lock().delivered += 1;
+                        return DeliveryResult {
+                            webhook_id,
+                            event_id: event_id.to_string(),
+                            attempts,
+                            status,
+                            delivered: true,
+                        };
+                    }
+                    code.is_server_error() || code == reqwest::StatusCode::TOO_MANY_REQUESTS
+                }
+                Err(e) => {
+                    debug!("Webhook {} delivery error: {}", endpoint.url, e);
+                    true
+                }
+            };
+
+            if !retryable || attempts > endpoint.max_retries {
+                warn!(
+                    "Giving up on webhook {} for event {} after {} attempts",
+                    endpoint.url, event_id, attempts
+                );
+                self.stats.lock().failed += 1;
+                return DeliveryResult {
+                    webhook_id,
+                    event_id: event_id.to_string(),
+                    attempts,
+                    status,
+                    delivered: false,
+                };
+            }
+
+            self.stats.lock().retries += 1;
+            tokio::time::sleep(endpoint.backoff(attempts)).await;
+        }
+    }
+}
+
+/// Compute the `X-Vudo-Signature` header value for a payload.
+pub fn sign_payload(secret: &[u8], timestamp: u64, body: &[u8]) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Verify the signature headers of a received notification.
+///
+/// Rejects signatures older (or further in the future) than `tolerance` to
+/// limit replay. Comparison is constant-time.
+pub fn verify_signature(
+    secret: &[u8],
+    timestamp: u64,
+    body: &[u8],
+    signature: &str,
+    tolerance: Duration,
+) -> bool {
+    if current_timestamp_secs().abs_diff(timestamp) > tolerance.as_secs() {
+        return false;
+    }
+
+    let Some(expected) = signature
+        .strip_prefix("sha256=")
+        .and_then(|hex_sig| hex::decode(hex_sig).ok())
+    else {
+        return false;
+    };
+
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// Get current timestamp in seconds.
+fn current_timestamp_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn event(namespace: &str) -> ChangeEvent {
+        ChangeEvent {
+            document_id: DocumentId::new(namespace, "alice"),
+            timestamp: 1_700_000_000_000,
+            change_hash: vec![0xab, 0xcd],
+            path: Some("name".to_string()),
+        }
+    }
+
+    /// Serve `statuses` in order, returning each request's headers and body.
+    async fn serve(statuses: Vec<u16>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for status in statuses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                loop {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length = text
+                            .lines()
+                            .find_map(|l| {
+                                l.to_lowercase()
+                                    .strip_prefix("content-length: ")
+                                    .map(|v| v.trim().parse::<usize>().unwrap())
+                            })
+                            .unwrap_or(0);
+                        if request.len() >= end + 4 + length {
+                            break;
+                        }
+                    }
+                }
+                let response = format!(
+                    "HTTP/1.1 {} X\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                    status
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+                requests.push(String::from_utf8_lossy(&request).to_string());
+            }
+            requests
+        });
+
+        (url, server)
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let body = br#"{"namespace":"users"}"#;
+        let now = current_timestamp_secs();
+        let signature = sign_payload(b"secret", now, body);
+        let tolerance = Duration::from_secs(300);
+
+        assert!(signature.starts_with("sha256="));
+        assert!(verify_signature(
+            b"secret", now, body, &signature, tolerance
+        ));
+        assert!(!verify_signature(
+            b"other", now, body, &signature, tolerance
+        ));
+        assert!(!verify_signature(
+            b"secret", now, b"{}", &signature, tolerance
+        ));
+        assert!(!verify_signature(
+            b"secret",
+            now,
+            body,
+            "sha256=zz",
+            tolerance
+        ));
+
+        // Stale signatures are rejected even if valid
+        let old = now - 3600;
+        let stale = sign_payload(b"secret", old, body);
+        assert!(!verify_signature(b"secret", old, body, &stale, tolerance));
+    }
+
+    #[test]
+    fn test_backoff() {
+        let endpoint = WebhookEndpoint::new("http://localhost/hook", "secret").with_retries(
+            5,
+            Duration::from_millis(100),
+            Duration::from_millis(350),
+        );
+
+        assert_eq!(endpoint.backoff(1), Duration::from_millis(100));
+        assert_eq!(endpoint.backoff(2), Duration::from_millis(200));
+        assert_eq!(endpoint.backoff(3), Duration::from_millis(350));
+        assert_eq!(endpoint.backoff(40), Duration::from_millis(350));
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_retries_and_signs() {
+        let (url, server) = serve(vec![503, 200]).await;
+        let dispatcher = WebhookDispatcher::new(Duration::from_secs(5)).unwrap();
+        let webhook_id = dispatcher.register(
+            WebhookEndpoint::new(url, "secret")
+                .with_namespace("users")
+                .with_retries(3, Duration::from_millis(10), Duration::from_millis(50)),
+        );
+
+        // Other namespaces are not delivered
+        assert!(dispatcher
+            .dispatch(&event("posts"))
+            .await
+            .unwrap()
+            .is_empty());
+
+        let results = dispatcher.dispatch(&event("users")).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].webhook_id, webhook_id);
+        assert!(results[0].delivered);
+        assert_eq!(results[0].attempts, 2);
+        assert_eq!(results[0].status, Some(200));
+
+        let stats = dispatcher.stats();
+        assert_eq!(stats.delivered, 1);
+        assert_eq!(stats.retries, 1);
+
+        // The receiver can verify the signature of the final request
+        let requests = server.await.unwrap();
+        let request = &requests[1];
+        let header = |name: &str| {
+            request
+                .lines()
+                .find_map(|l| {
+                    l.to_lowercase()
+                        .starts_with(&format!("{}:", name.to_lowercase()))
+                        .then(|| l[name.len() + 1..].trim().to_string())
+                })
+                .unwrap()
+        };
+        let body = &request[request.find("\r\n\r\n").unwrap() + 4..];
+        let timestamp: u64 = header(TIMESTAMP_HEADER).parse().unwrap();
+        assert!(verify_signature(
+            b"secret",
+            timestamp,
+            body.as_bytes(),
+            &header(SIGNATURE_HEADER),
+            Duration::from_secs(300),
+        ));
+
+        let payload: WebhookPayload = serde_json::from_str(body).unwrap();
+        assert_eq!(payload.key, "alice");
+        assert_eq!(payload.change_hash, "abcd");
+        assert_eq!(header(EVENT_ID_HEADER), payload.event_id);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_gives_up_on_client_error() {
+        let (url, _server) = serve(vec![400]).await;
+        let dispatcher = WebhookDispatcher::new(Duration::from_secs(5)).unwrap();
+        dispatcher.register(WebhookEndpoint::new(url, "secret").with_namespace("users"));
+
+        let results = dispatcher.dispatch(&event("users")).await.unwrap();
+        assert!(!results[0].delivered);
+        assert_eq!(results[0].attempts, 1);
+        assert_eq!(results[0].status, Some(400));
+        assert_eq!(dispatcher.stats().failed, 1);
+    }
+}
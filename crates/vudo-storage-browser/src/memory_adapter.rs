@@ -11,15 +11,59 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use dashmap::DashMap;
 use parking_lot::RwLock;
-use std::collections::BTreeMap;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
-use vudo_storage::{Operation, QueryFilter, Result, StorageAdapter, StorageStats};
+use vudo_storage::{
+    enforce_quota, parse_field_value, unreferenced_blob, BlobGcReport, BlobHash, BlobInfo,
+    BlobStore, Cursor, EvictionCandidate, EvictionHook, JsonPath, MaintenanceOptions,
+    MaintenanceReport, Operation, QueryFilter, QueryOptions, QueryPage, QuotaScope, QuotaUsage,
+    Result, SortDirection, SortField, StorageAdapter, StorageError, StorageQuota, StorageStats,
+};
 
 /// Document entry with metadata.
 #[derive(Debug, Clone)]
 struct DocumentEntry {
     data: Bytes,
+    created_at: u64,
     updated_at: u64,
+    /// Values of the namespace's indexed fields, keyed by canonical path.
+    fields: HashMap<String, Option<Value>>,
+}
+
+impl DocumentEntry {
+    /// Extract the values of indexed fields from the document bytes.
+    fn index_fields(&mut self, paths: &[JsonPath]) {
+        if paths.is_empty() {
+            self.fields.clear();
+            return;
+        }
+        let document: Option<Value> = serde_json::from_slice(&self.data).ok();
+        self.fields = paths
+            .iter()
+            .map(|path| {
+                let value = document.as_ref().and_then(|doc| path.extract(doc)).cloned();
+                (path.to_string(), value)
+            })
+            .collect();
+    }
+
+    /// Value of a field, from the index when the path is indexed.
+    fn field(&self, path: &JsonPath) -> Option<Value> {
+        match self.fields.get(&path.to_string()) {
+            Some(value) => value.clone(),
+            None => path.extract_from_bytes(&self.data),
+        }
+    }
+
+    /// Timestamp used as the sort key for a field (None when sorting by ID).
+    fn sort_timestamp(&self, field: SortField) -> Option<u64> {
+        match field {
+            SortField::Id => None,
+            SortField::CreatedAt => Some(self.created_at),
+            SortField::UpdatedAt => Some(self.updated_at),
+        }
+    }
 }
 
 /// Snapshot entry.
@@ -31,6 +75,13 @@ struct SnapshotEntry {
     created_at: u64,
 }
 
+/// Blob entry with its reference count.
+#[derive(Debug, Clone)]
+struct BlobEntry {
+    data: Bytes,
+    refcount: u64,
+}
+
 /// In-memory storage adapter.
 ///
 /// This adapter stores all data in memory using concurrent data structures.
@@ -43,6 +94,16 @@ pub struct MemoryAdapter {
     /// Snapshots stored by namespace, ID, and version.
     #[allow(clippy::type_complexity)]
     snapshots: Arc<DashMap<String, DashMap<String, BTreeMap<u64, SnapshotEntry>>>>,
+    /// Indexed JSON paths by namespace.
+    indexes: Arc<DashMap<String, Vec<JsonPath>>>,
+    /// Deletion timestamps of deleted documents by namespace and ID.
+    tombstones: Arc<DashMap<(String, String), u64>>,
+    /// Content-addressed blobs.
+    blobs: Arc<DashMap<BlobHash, BlobEntry>>,
+    /// Byte limits on documents.
+    quota: StorageQuota,
+    /// Chooses documents to evict when a save would exceed the quota.
+    eviction_hook: Option<Arc<dyn EvictionHook>>,
 }
 
 impl MemoryAdapter {
@@ -52,9 +113,119 @@ impl MemoryAdapter {
             documents: Arc::new(DashMap::new()),
             operations: Arc::new(RwLock::new(Vec::new())),
             snapshots: Arc::new(DashMap::new()),
+            indexes: Arc::new(DashMap::new()),
+            tombstones: Arc::new(DashMap::new()),
+            blobs: Arc::new(DashMap::new()),
+            quota: StorageQuota::default(),
+            eviction_hook: None,
+        }
+    }
+
+    /// Enforce `quota` on saves.
+    ///
+    /// Saves that would exceed a limit fail with
+    /// [`StorageError::QuotaExceeded`](vudo_storage::StorageError::QuotaExceeded),
+    /// unless the eviction hook frees enough room first. Sizes are the
+    /// lengths of the document data.
+    pub fn with_quota(mut self, quota: StorageQuota) -> Self {
+        self.quota = quota;
+        self
+    }
+
+    /// Let `hook` evict documents when a save would exceed the quota.
+    pub fn with_eviction_hook(mut self, hook: Arc<dyn EvictionHook>) -> Self {
+        self.eviction_hook = Some(hook);
+        self
+    }
+
+    /// The quota enforced on saves.
+    pub fn quota(&self) -> &StorageQuota {
+        &self.quota
+    }
+
+    /// Check the quota before saving `size` bytes as a document, evicting
+    /// other documents if the hook allows it.
+    fn enforce_quota(&self, namespace: &str, id: &str, size: u64) -> Result<()> {
+        enforce_quota(
+            &self.quota,
+            self.eviction_hook.as_deref(),
+            || Ok(self.usage_after_save(namespace, id, size)),
+            |scope| Ok(self.eviction_candidates(scope, namespace, id)),
+            |namespace, id| {
+                self.remove_document(namespace, id);
+                Ok(())
+            },
+        )
+    }
+
+    /// Document bytes after replacing a document with `size` bytes.
+    fn usage_after_save(&self, namespace: &str, id: &str, size: u64) -> QuotaUsage {
+        let mut namespace_bytes = size;
+        let mut total = size;
+        for ns in self.documents.iter() {
+            for entry in ns.value().iter() {
+                if ns.key() == namespace && entry.key() == id {
+                    continue;
+                }
+                let len = entry.value().data.len() as u64;
+                total += len;
+                if ns.key() == namespace {
+                    namespace_bytes += len;
+                }
+            }
+        }
+        QuotaUsage {
+            namespaces: BTreeMap::from([(namespace.to_string(), namespace_bytes)]),
+            total,
+        }
+    }
+
+    /// Documents in `scope` other than the one being saved.
+    fn eviction_candidates(
+        &self,
+        scope: &QuotaScope,
+        namespace: &str,
+        id: &str,
+    ) -> Vec<EvictionCandidate> {
+        self.documents
+            .iter()
+            .filter(|ns| scope.contains(ns.key()))
+            .flat_map(|ns| {
+                ns.value()
+                    .iter()
+                    .filter(|entry| !(ns.key() == namespace && entry.key() == id))
+                    .map(|entry| EvictionCandidate {
+                        namespace: ns.key().clone(),
+                        id: entry.key().clone(),
+                        size: entry.value().data.len() as u64,
+                        updated_at: entry.value().updated_at,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Remove a document, leaving a tombstone if it existed.
+    fn remove_document(&self, namespace: &str, id: &str) {
+        let removed = self
+            .documents
+            .get(namespace)
+            .and_then(|ns| ns.remove(id))
+            .is_some();
+        if removed {
+            self.tombstones
+                .insert((namespace.to_string(), id.to_string()), Self::timestamp());
         }
     }
 
+    /// Get the indexed paths of a namespace.
+    fn indexed_paths(&self, namespace: &str) -> Vec<JsonPath> {
+        self.indexes
+            .get(namespace)
+            .map(|paths| paths.clone())
+            .unwrap_or_default()
+    }
+
     /// Get current timestamp in milliseconds.
     fn timestamp() -> u64 {
         std::time::SystemTime::now()
@@ -100,12 +271,23 @@ impl StorageAdapter for MemoryAdapter {
     }
 
     async fn save(&self, namespace: &str, id: &str, data: Bytes) -> Result<()> {
+        self.enforce_quota(namespace, id, data.len() as u64)?;
         let ns = self.get_namespace(namespace);
-        let entry = DocumentEntry {
+        let updated_at = Self::timestamp();
+        let created_at = ns
+            .get(id)
+            .map(|existing| existing.created_at)
+            .unwrap_or(updated_at);
+        let mut entry = DocumentEntry {
             data,
-            updated_at: Self::timestamp(),
+            created_at,
+            updated_at,
+            fields: HashMap::new(),
         };
+        entry.index_fields(&self.indexed_paths(namespace));
         ns.insert(id.to_string(), entry);
+        self.tombstones
+            .remove(&(namespace.to_string(), id.to_string()));
         Ok(())
     }
 
@@ -118,9 +300,7 @@ impl StorageAdapter for MemoryAdapter {
     }
 
     async fn delete(&self, namespace: &str, id: &str) -> Result<()> {
-        if let Some(ns) = self.documents.get(namespace) {
-            ns.remove(id);
-        }
+        self.remove_document(namespace, id);
         Ok(())
     }
 
@@ -134,6 +314,17 @@ impl StorageAdapter for MemoryAdapter {
         }
     }
 
+    async fn list_namespaces(&self) -> Result<Vec<String>> {
+        let mut namespaces: Vec<String> = self
+            .documents
+            .iter()
+            .filter(|ns| !ns.value().is_empty())
+            .map(|ns| ns.key().clone())
+            .collect();
+        namespaces.sort();
+        Ok(namespaces)
+    }
+
     async fn save_operations(&self, ops: &[Operation]) -> Result<()> {
         let mut operations = self.operations.write();
         operations.clear();
@@ -184,10 +375,11 @@ impl StorageAdapter for MemoryAdapter {
     }
 
     async fn query(&self, namespace: &str, filter: QueryFilter) -> Result<Vec<(String, Bytes)>> {
+        validate_filter(&filter)?;
         if let Some(ns) = self.documents.get(namespace) {
             let mut results: Vec<(String, Bytes)> = ns
                 .iter()
-                .filter(|entry| matches_filter(entry.value(), &filter))
+                .filter(|entry| matches_filter(entry.key(), entry.value(), &filter))
                 .map(|entry| (entry.key().clone(), entry.value().data.clone()))
                 .collect();
 
@@ -198,6 +390,141 @@ impl StorageAdapter for MemoryAdapter {
         }
     }
 
+    async fn query_page(
+        &self,
+        namespace: &str,
+        filter: QueryFilter,
+        options: QueryOptions,
+    ) -> Result<QueryPage> {
+        validate_filter(&filter)?;
+        let field = options.sort.field;
+        let mut matches: Vec<(Option<u64>, String, Bytes)> = match self.documents.get(namespace) {
+            Some(ns) => ns
+                .iter()
+                .filter(|entry| matches_filter(entry.key(), entry.value(), &filter))
+                .map(|entry| {
+                    (
+                        entry.value().sort_timestamp(field),
+                        entry.key().clone(),
+                        entry.value().data.clone(),
+                    )
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        matches.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+        if options.sort.direction == SortDirection::Descending {
+            matches.reverse();
+        }
+
+        if let Some(cursor) = &options.cursor {
+            let after = (cursor.timestamp, &cursor.id);
+            matches.retain(|(timestamp, id, _)| match options.sort.direction {
+                SortDirection::Ascending => (*timestamp, id) > after,
+                SortDirection::Descending => (*timestamp, id) < after,
+            });
+        }
+
+        let page: Vec<_> = matches
+            .into_iter()
+            .skip(options.offset)
+            .take(options.limit.unwrap_or(usize::MAX))
+            .collect();
+
+        let next_cursor = match (options.limit, page.last()) {
+            (Some(limit), Some((timestamp, id, _))) if page.len() == limit => Some(Cursor {
+                timestamp: *timestamp,
+                id: id.clone(),
+            }),
+            _ => None,
+        };
+
+        Ok(QueryPage {
+            items: page.into_iter().map(|(_, id, data)| (id, data)).collect(),
+            next_cursor,
+        })
+    }
+
+    async fn ensure_index(&self, namespace: &str, json_path: &str) -> Result<()> {
+        let path = JsonPath::parse(json_path)?;
+
+        let paths = {
+            let mut paths = self.indexes.entry(namespace.to_string()).or_default();
+            if paths.contains(&path) {
+                return Ok(());
+            }
+            paths.push(path);
+            paths.clone()
+        };
+
+        // Backfill existing documents
+        if let Some(ns) = self.documents.get(namespace) {
+            for mut entry in ns.iter_mut() {
+                entry.index_fields(&paths);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn list_indexes(&self, namespace: &str) -> Result<Vec<String>> {
+        let mut paths: Vec<String> = self
+            .indexed_paths(namespace)
+            .iter()
+            .map(|path| path.to_string())
+            .collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    async fn maintenance(&self, options: MaintenanceOptions) -> Result<MaintenanceReport> {
+        let mut report = MaintenanceReport::default();
+
+        if let Some(max) = options.max_snapshots_per_doc {
+            for ns in self.snapshots.iter() {
+                for mut doc_snapshots in ns.value().iter_mut() {
+                    while doc_snapshots.len() > max {
+                        if let Some((_, snapshot)) = doc_snapshots.pop_first() {
+                            report.snapshots_pruned += 1;
+                            report.bytes_freed += snapshot.data.len() as u64;
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(retention) = options.tombstone_retention {
+            let cutoff = Self::timestamp().saturating_sub(retention.as_millis() as u64);
+            let expired: Vec<(String, String)> = self
+                .tombstones
+                .iter()
+                .filter(|tombstone| *tombstone.value() <= cutoff)
+                .map(|tombstone| tombstone.key().clone())
+                .collect();
+
+            for key in expired {
+                self.tombstones.remove(&key);
+                report.tombstones_purged += 1;
+
+                let (namespace, id) = key;
+                if let Some((_, snapshots)) =
+                    self.snapshots.get(&namespace).and_then(|ns| ns.remove(&id))
+                {
+                    report.tombstone_snapshots_purged += snapshots.len();
+                    report.bytes_freed += snapshots
+                        .values()
+                        .map(|snapshot| snapshot.data.len() as u64)
+                        .sum::<u64>();
+                }
+            }
+        }
+
+        // Memory is released as soon as the values are dropped
+        report.bytes_reclaimed = report.bytes_freed;
+        Ok(report)
+    }
+
     async fn stats(&self) -> Result<StorageStats> {
         let document_count: usize = self
             .documents
@@ -252,6 +579,10 @@ impl StorageAdapter for MemoryAdapter {
             operation_count,
             snapshot_count,
             total_snapshot_size,
+            tombstone_count: self.tombstones.len(),
+            // Values are kept uncompressed
+            uncompressed_document_size: total_document_size,
+            uncompressed_snapshot_size: total_snapshot_size,
         })
     }
 
@@ -259,12 +590,73 @@ impl StorageAdapter for MemoryAdapter {
         self.documents.clear();
         self.operations.write().clear();
         self.snapshots.clear();
+        self.indexes.clear();
+        self.tombstones.clear();
+        self.blobs.clear();
         Ok(())
     }
 }
 
+#[async_trait]
+impl BlobStore for MemoryAdapter {
+    async fn put_blob(&self, data: Bytes) -> Result<BlobHash> {
+        let hash = BlobHash::of(&data);
+        self.blobs
+            .entry(hash)
+            .and_modify(|entry| entry.refcount += 1)
+            .or_insert(BlobEntry { data, refcount: 1 });
+        Ok(hash)
+    }
+
+    async fn get_blob(&self, hash: &BlobHash) -> Result<Option<Bytes>> {
+        Ok(self.blobs.get(hash).map(|entry| entry.data.clone()))
+    }
+
+    async fn blob_info(&self, hash: &BlobHash) -> Result<Option<BlobInfo>> {
+        Ok(self.blobs.get(hash).map(|entry| BlobInfo {
+            hash: *hash,
+            size: entry.data.len() as u64,
+            refcount: entry.refcount,
+        }))
+    }
+
+    async fn retain_blob(&self, hash: &BlobHash) -> Result<u64> {
+        let mut entry = self
+            .blobs
+            .get_mut(hash)
+            .ok_or(StorageError::BlobNotFound(*hash))?;
+        entry.refcount += 1;
+        Ok(entry.refcount)
+    }
+
+    async fn release_blob(&self, hash: &BlobHash) -> Result<u64> {
+        let mut entry = self
+            .blobs
+            .get_mut(hash)
+            .ok_or(StorageError::BlobNotFound(*hash))?;
+        if entry.refcount == 0 {
+            return Err(unreferenced_blob(hash));
+        }
+        entry.refcount -= 1;
+        Ok(entry.refcount)
+    }
+
+    async fn gc_blobs(&self) -> Result<BlobGcReport> {
+        let mut report = BlobGcReport::default();
+        self.blobs.retain(|_, entry| {
+            if entry.refcount > 0 {
+                return true;
+            }
+            report.blobs_removed += 1;
+            report.bytes_freed += entry.data.len() as u64;
+            false
+        });
+        Ok(report)
+    }
+}
+
 /// Check if a document entry matches a filter.
-fn matches_filter(entry: &DocumentEntry, filter: &QueryFilter) -> bool {
+fn matches_filter(id: &str, entry: &DocumentEntry, filter: &QueryFilter) -> bool {
     match filter {
         QueryFilter::All => true,
         QueryFilter::UpdatedAfter(timestamp) => entry.updated_at > *timestamp,
@@ -272,19 +664,39 @@ fn matches_filter(entry: &DocumentEntry, filter: &QueryFilter) -> bool {
         QueryFilter::UpdatedBetween { start, end } => {
             entry.updated_at >= *start && entry.updated_at <= *end
         }
-        QueryFilter::And(filters) => filters.iter().all(|f| matches_filter(entry, f)),
-        QueryFilter::Or(filters) => filters.iter().any(|f| matches_filter(entry, f)),
-        QueryFilter::Not(f) => !matches_filter(entry, f),
-        QueryFilter::Field { .. } => {
-            // Field filtering not supported in in-memory adapter
-            false
+        QueryFilter::CreatedAfter(timestamp) => entry.created_at > *timestamp,
+        QueryFilter::CreatedBefore(timestamp) => entry.created_at < *timestamp,
+        QueryFilter::CreatedBetween { start, end } => {
+            entry.created_at >= *start && entry.created_at <= *end
+        }
+        QueryFilter::IdPrefix(prefix) => id.starts_with(prefix.as_str()),
+        QueryFilter::And(filters) => filters.iter().all(|f| matches_filter(id, entry, f)),
+        QueryFilter::Or(filters) => filters.iter().any(|f| matches_filter(id, entry, f)),
+        QueryFilter::Not(f) => !matches_filter(id, entry, f),
+        QueryFilter::Field { field, value } => match JsonPath::parse(field) {
+            Ok(path) => entry.field(&path) == Some(parse_field_value(value)),
+            Err(_) => false,
+        },
+    }
+}
+
+/// Reject filters with malformed field paths.
+fn validate_filter(filter: &QueryFilter) -> Result<()> {
+    match filter {
+        QueryFilter::Field { field, .. } => JsonPath::parse(field).map(|_| ()),
+        QueryFilter::And(filters) | QueryFilter::Or(filters) => {
+            filters.iter().try_for_each(validate_filter)
         }
+        QueryFilter::Not(f) => validate_filter(f),
+        _ => Ok(()),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
+    use vudo_storage::{LeastRecentlyUpdated, MemoryBackupTarget, SortOrder};
 
     #[tokio::test]
     async fn test_memory_adapter_new() {
@@ -359,6 +771,129 @@ mod tests {
         assert_eq!(posts, vec!["post1"]);
     }
 
+    #[tokio::test]
+    async fn test_memory_adapter_quota() {
+        let adapter = MemoryAdapter::new().with_quota(
+            StorageQuota::new()
+                .max_total_bytes(20)
+                .namespace_limit("users", 10),
+        );
+        adapter.init().await.unwrap();
+
+        adapter
+            .save("users", "alice", Bytes::from("0123456789"))
+            .await
+            .unwrap();
+        let err = adapter
+            .save("users", "bob", Bytes::from("x"))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            StorageError::QuotaExceeded {
+                scope: QuotaScope::Namespace(ref ns),
+                limit: 10,
+                required: 11,
+            } if ns == "users"
+        ));
+        assert_eq!(adapter.load("users", "bob").await.unwrap(), None);
+
+        // Replacing a document only counts the new size
+        adapter
+            .save("users", "alice", Bytes::from("abcdefghij"))
+            .await
+            .unwrap();
+
+        adapter
+            .save("posts", "p1", Bytes::from("0123456789"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            adapter.save("posts", "p2", Bytes::from("x")).await,
+            Err(StorageError::QuotaExceeded {
+                scope: QuotaScope::Total,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_memory_adapter_quota_eviction() {
+        let adapter = MemoryAdapter::new()
+            .with_quota(StorageQuota::new().max_total_bytes(10))
+            .with_eviction_hook(Arc::new(LeastRecentlyUpdated::in_namespaces(["cache"])));
+        adapter.init().await.unwrap();
+
+        adapter
+            .save("cache", "a", Bytes::from("aaaa"))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        adapter
+            .save("cache", "b", Bytes::from("bbbb"))
+            .await
+            .unwrap();
+        adapter
+            .save("users", "alice", Bytes::from("al"))
+            .await
+            .unwrap();
+
+        // Evicts the oldest cache entry to make room
+        adapter
+            .save("users", "bob", Bytes::from("bob"))
+            .await
+            .unwrap();
+        assert_eq!(adapter.list("cache").await.unwrap(), vec!["b"]);
+        assert_eq!(adapter.stats().await.unwrap().tombstone_count, 1);
+
+        // Cache entries alone can't make room for this
+        assert!(matches!(
+            adapter
+                .save("users", "carol", Bytes::from("carolcarol"))
+                .await,
+            Err(StorageError::QuotaExceeded { .. })
+        ));
+        assert_eq!(adapter.list("users").await.unwrap(), vec!["alice", "bob"]);
+        assert_eq!(adapter.list("cache").await.unwrap(), vec!["b"]);
+    }
+
+    #[tokio::test]
+    async fn test_memory_adapter_backup_and_restore() {
+        let adapter = MemoryAdapter::new();
+        adapter.init().await.unwrap();
+
+        adapter
+            .save("users", "alice", Bytes::from("data1"))
+            .await
+            .unwrap();
+        adapter
+            .save("posts", "post1", Bytes::from("data2"))
+            .await
+            .unwrap();
+        adapter
+            .save("drafts", "d1", Bytes::from("data3"))
+            .await
+            .unwrap();
+        adapter.delete("drafts", "d1").await.unwrap();
+        assert_eq!(
+            adapter.list_namespaces().await.unwrap(),
+            vec!["posts", "users"]
+        );
+
+        let target = MemoryBackupTarget::new();
+        let manifest = adapter.backup_to(&target).await.unwrap();
+        assert_eq!(manifest.documents.len(), 2);
+
+        let restored = MemoryAdapter::new();
+        restored.init().await.unwrap();
+        restored.restore_from(&target).await.unwrap();
+        assert_eq!(
+            restored.load("users", "alice").await.unwrap(),
+            Some(Bytes::from("data1"))
+        );
+        assert_eq!(restored.stats().await.unwrap().document_count, 2);
+    }
+
     #[tokio::test]
     async fn test_memory_adapter_operations() {
         let adapter = MemoryAdapter::new();
@@ -471,6 +1006,242 @@ mod tests {
         assert_eq!(results[0].0, "alice");
     }
 
+    #[tokio::test]
+    async fn test_memory_adapter_query_compound() {
+        let adapter = MemoryAdapter::new();
+        adapter.init().await.unwrap();
+
+        for id in ["user:alice", "user:bob", "post:1"] {
+            adapter.save("docs", id, Bytes::from(id)).await.unwrap();
+        }
+
+        let filter = QueryFilter::id_prefix("user:").or(QueryFilter::id_prefix("post:"));
+        let results = adapter.query("docs", filter).await.unwrap();
+        assert_eq!(results.len(), 3);
+
+        let filter = QueryFilter::id_prefix("user:").and(QueryFilter::id_prefix("user:b").not());
+        let results = adapter.query("docs", filter).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, "user:alice");
+    }
+
+    #[tokio::test]
+    async fn test_memory_adapter_query_page() {
+        let adapter = MemoryAdapter::new();
+        adapter.init().await.unwrap();
+
+        for id in ["a", "b", "c", "d", "e"] {
+            adapter.save("docs", id, Bytes::from(id)).await.unwrap();
+            tokio::time::sleep(tokio::time::Duration::from_millis(2)).await;
+        }
+
+        // Updating keeps the creation time
+        adapter.save("docs", "a", Bytes::from("a2")).await.unwrap();
+
+        let options = QueryOptions::default()
+            .sort_by(SortOrder::desc(SortField::CreatedAt))
+            .limit(2);
+        let page = adapter
+            .query_page("docs", QueryFilter::All, options.clone())
+            .await
+            .unwrap();
+        let ids: Vec<_> = page.items.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["e", "d"]);
+
+        let page = adapter
+            .query_page(
+                "docs",
+                QueryFilter::All,
+                options.clone().after(page.next_cursor.unwrap()),
+            )
+            .await
+            .unwrap();
+        let ids: Vec<_> = page.items.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "b"]);
+
+        let page = adapter
+            .query_page("docs", QueryFilter::All, options.offset(4))
+            .await
+            .unwrap();
+        let ids: Vec<_> = page.items.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["a"]);
+        assert!(page.next_cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_memory_adapter_streams() {
+        let adapter = MemoryAdapter::new();
+        adapter.init().await.unwrap();
+
+        let saved = adapter
+            .save_stream("wasm", "module", &mut &b"\0asm"[..])
+            .await
+            .unwrap();
+        assert_eq!(saved, 4);
+
+        let mut out = Vec::new();
+        let loaded = adapter
+            .load_stream("wasm", "module", &mut out)
+            .await
+            .unwrap();
+        assert_eq!(loaded, Some(4));
+        assert_eq!(out, b"\0asm");
+    }
+
+    #[tokio::test]
+    async fn test_memory_adapter_field_query() {
+        let adapter = MemoryAdapter::new();
+        adapter.init().await.unwrap();
+
+        adapter
+            .save(
+                "users",
+                "alice",
+                Bytes::from(r#"{"status":"active","age":30}"#),
+            )
+            .await
+            .unwrap();
+        adapter
+            .save("users", "bob", Bytes::from(r#"{"status":"away","age":41}"#))
+            .await
+            .unwrap();
+        adapter
+            .save("users", "carol", Bytes::from("not json"))
+            .await
+            .unwrap();
+
+        // Unindexed lookups scan the documents
+        let results = adapter
+            .query("users", QueryFilter::field("status", "active"))
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, "alice");
+
+        // Indexing backfills existing documents and covers new ones
+        adapter.ensure_index("users", "$.age").await.unwrap();
+        adapter.ensure_index("users", "age").await.unwrap();
+        adapter
+            .save(
+                "users",
+                "dave",
+                Bytes::from(r#"{"status":"active","age":41}"#),
+            )
+            .await
+            .unwrap();
+
+        let mut ids: Vec<_> = adapter
+            .query("users", QueryFilter::field("$.age", "41"))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec!["bob", "dave"]);
+        assert_eq!(adapter.list_indexes("users").await.unwrap(), vec!["$.age"]);
+
+        assert!(adapter.ensure_index("users", "$.a'b").await.is_err());
+        assert!(adapter
+            .query("users", QueryFilter::field("$.", "1"))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_memory_adapter_maintenance_prunes_snapshots() {
+        let adapter = MemoryAdapter::new();
+        adapter.init().await.unwrap();
+
+        for version in 1..=5 {
+            adapter
+                .save_snapshot("users", "alice", version, Bytes::from("snap"))
+                .await
+                .unwrap();
+        }
+        adapter
+            .save_snapshot("users", "bob", 1, Bytes::from("snap"))
+            .await
+            .unwrap();
+
+        let report = adapter
+            .maintenance(MaintenanceOptions::new().max_snapshots_per_doc(2))
+            .await
+            .unwrap();
+        assert_eq!(report.snapshots_pruned, 3);
+        assert_eq!(report.bytes_freed, 12);
+        assert_eq!(report.bytes_reclaimed, 12);
+
+        let stats = adapter.stats().await.unwrap();
+        assert_eq!(stats.snapshot_count, 3);
+        let (version, _) = adapter
+            .load_snapshot("users", "alice")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(version, 5);
+
+        // Nothing left to prune
+        let report = adapter
+            .maintenance(MaintenanceOptions::new().max_snapshots_per_doc(2))
+            .await
+            .unwrap();
+        assert_eq!(report, MaintenanceReport::default());
+    }
+
+    #[tokio::test]
+    async fn test_memory_adapter_maintenance_purges_tombstones() {
+        let adapter = MemoryAdapter::new();
+        adapter.init().await.unwrap();
+
+        for id in ["alice", "bob", "carol"] {
+            adapter
+                .save("users", id, Bytes::from("data"))
+                .await
+                .unwrap();
+            adapter
+                .save_snapshot("users", id, 1, Bytes::from("snapshot"))
+                .await
+                .unwrap();
+        }
+        adapter.delete("users", "alice").await.unwrap();
+        adapter.delete("users", "bob").await.unwrap();
+        adapter.delete("users", "nobody").await.unwrap();
+        assert_eq!(adapter.stats().await.unwrap().tombstone_count, 2);
+
+        // Saving again revives the document
+        adapter
+            .save("users", "bob", Bytes::from("back"))
+            .await
+            .unwrap();
+        assert_eq!(adapter.stats().await.unwrap().tombstone_count, 1);
+
+        // Still within retention
+        let report = adapter
+            .maintenance(MaintenanceOptions::new().tombstone_retention(Duration::from_secs(3600)))
+            .await
+            .unwrap();
+        assert_eq!(report.tombstones_purged, 0);
+
+        let report = adapter
+            .maintenance(MaintenanceOptions::new().tombstone_retention(Duration::ZERO))
+            .await
+            .unwrap();
+        assert_eq!(report.tombstones_purged, 1);
+        assert_eq!(report.tombstone_snapshots_purged, 1);
+        assert_eq!(report.bytes_freed, 8);
+
+        assert_eq!(adapter.load_snapshot("users", "alice").await.unwrap(), None);
+        assert!(adapter
+            .load_snapshot("users", "bob")
+            .await
+            .unwrap()
+            .is_some());
+        let stats = adapter.stats().await.unwrap();
+        assert_eq!(stats.tombstone_count, 0);
+        assert_eq!(stats.snapshot_count, 2);
+    }
+
     #[tokio::test]
     async fn test_memory_adapter_stats() {
         let adapter = MemoryAdapter::new();
@@ -502,6 +1273,44 @@ mod tests {
         assert_eq!(loaded, None);
     }
 
+    #[tokio::test]
+    async fn test_memory_adapter_blobs() {
+        let adapter = MemoryAdapter::new();
+        let wasm = Bytes::from_static(b"\0asm module");
+
+        let hash = adapter.put_blob(wasm.clone()).await.unwrap();
+        assert_eq!(adapter.put_blob(wasm.clone()).await.unwrap(), hash);
+        assert_eq!(adapter.get_blob(&hash).await.unwrap(), Some(wasm.clone()));
+        assert_eq!(
+            adapter.blob_info(&hash).await.unwrap(),
+            Some(BlobInfo {
+                hash,
+                size: wasm.len() as u64,
+                refcount: 2
+            })
+        );
+
+        assert_eq!(adapter.retain_blob(&hash).await.unwrap(), 3);
+        for expected in [2, 1, 0] {
+            assert_eq!(adapter.release_blob(&hash).await.unwrap(), expected);
+        }
+        assert!(adapter.release_blob(&hash).await.is_err());
+
+        // Unreferenced blobs stay readable until collected
+        assert!(adapter.has_blob(&hash).await.unwrap());
+        let kept = adapter.put_blob(Bytes::from("weights")).await.unwrap();
+        let report = adapter.gc_blobs().await.unwrap();
+        assert_eq!(report.blobs_removed, 1);
+        assert_eq!(report.bytes_freed, wasm.len() as u64);
+        assert!(!adapter.has_blob(&hash).await.unwrap());
+        assert!(adapter.has_blob(&kept).await.unwrap());
+
+        assert!(matches!(
+            adapter.retain_blob(&hash).await,
+            Err(StorageError::BlobNotFound(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_memory_adapter_concurrent_access() {
         let adapter = Arc::new(MemoryAdapter::new());
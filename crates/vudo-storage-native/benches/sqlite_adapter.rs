@@ -47,6 +47,26 @@ fn bench_bulk_save(c: &mut Criterion) {
     }
 }
 
+fn bench_batch_save(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    for size in [100, 1000, 10000].iter() {
+        c.bench_with_input(BenchmarkId::new("batch_save", size), size, |b, &size| {
+            let adapter = runtime.block_on(setup_adapter());
+            let ids: Vec<String> = (0..size).map(|i| format!("user{}", i)).collect();
+            let data = Bytes::from("test document data");
+            let batch: Vec<(&str, &str, Bytes)> = ids
+                .iter()
+                .map(|id| ("users", id.as_str(), data.clone()))
+                .collect();
+
+            b.to_async(&runtime).iter(|| async {
+                adapter.save_batch(black_box(&batch)).await.unwrap();
+            });
+        });
+    }
+}
+
 fn bench_load(c: &mut Criterion) {
     let runtime = tokio::runtime::Runtime::new().unwrap();
 
@@ -92,5 +112,12 @@ fn bench_query(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bench_save, bench_bulk_save, bench_load, bench_query);
+criterion_group!(
+    benches,
+    bench_save,
+    bench_bulk_save,
+    bench_batch_save,
+    bench_load,
+    bench_query
+);
 criterion_main!(benches);
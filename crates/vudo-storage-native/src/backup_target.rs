@@ -0,0 +1,161 @@
+//! Backup target backed by an `object_store` backend.
+//!
+//! [`ObjectStoreTarget`] stores backup blobs under a key prefix of any
+//! [`ObjectStore`]: S3-compatible buckets (feature `s3`), or the local and
+//! in-memory stores that `object_store` always provides.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use vudo_storage::StorageAdapter;
+//! use vudo_storage_native::{ObjectStoreTarget, SqliteAdapter};
+//!
+//! // Credentials, region and endpoint come from the AWS_* environment variables
+//! let target = ObjectStoreTarget::s3("vudo-backups", "node-1/2024-06-01")?;
+//!
+//! let storage = SqliteAdapter::new("./vudo.db").await?;
+//! storage.init().await?;
+//! let manifest = storage.backup_to(&target).await?;
+//! println!("backed up {} documents", manifest.documents.len());
+//! ```
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use object_store::path::Path;
+use object_store::{ObjectStore, PutPayload};
+use std::io;
+use std::sync::Arc;
+use vudo_storage::{BackupTarget, Result, StorageError};
+
+/// Backup target that stores blobs under a prefix of an [`ObjectStore`].
+#[derive(Debug, Clone)]
+pub struct ObjectStoreTarget {
+    store: Arc<dyn ObjectStore>,
+    prefix: Path,
+}
+
+impl ObjectStoreTarget {
+    /// Create a target storing blobs under `prefix` in `store`.
+    ///
+    /// Each backup should get its own prefix (e.g. node name and date);
+    /// backing up into a prefix that holds a backup replaces it.
+    pub fn new(store: Arc<dyn ObjectStore>, prefix: &str) -> Result<Self> {
+        let prefix = Path::parse(prefix).map_err(|e| {
+            StorageError::InvalidOperation(format!("Invalid backup prefix '{}': {}", prefix, e))
+        })?;
+        Ok(Self { store, prefix })
+    }
+
+    /// Create a target in an S3-compatible bucket.
+    ///
+    /// Credentials, region and endpoint (for MinIO, R2 and other
+    /// S3-compatible services) are read from the `AWS_*` environment
+    /// variables.
+    #[cfg(feature = "s3")]
+    pub fn s3(bucket: &str, prefix: &str) -> Result<Self> {
+        let store = object_store::aws::AmazonS3Builder::from_env()
+            .with_bucket_name(bucket)
+            .build()
+            .map_err(object_store_error)?;
+        Self::new(Arc::new(store), prefix)
+    }
+
+    /// Key prefix of the backup in the store.
+    pub fn prefix(&self) -> &str {
+        self.prefix.as_ref()
+    }
+
+    fn path(&self, key: &str) -> Path {
+        key.split('/')
+            .fold(self.prefix.clone(), |path, part| path.child(part))
+    }
+}
+
+#[async_trait]
+impl BackupTarget for ObjectStoreTarget {
+    async fn put(&self, key: &str, data: Bytes) -> Result<()> {
+        self.store
+            .put(&self.path(key), PutPayload::from(data))
+            .await
+            .map_err(object_store_error)?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Bytes>> {
+        let result = match self.store.get(&self.path(key)).await {
+            Ok(result) => result,
+            Err(object_store::Error::NotFound { .. }) => return Ok(None),
+            Err(e) => return Err(object_store_error(e)),
+        };
+        let data = result.bytes().await.map_err(object_store_error)?;
+        Ok(Some(data))
+    }
+}
+
+fn object_store_error(err: object_store::Error) -> StorageError {
+    StorageError::Io(io::Error::other(err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FsAdapter, SqliteAdapter};
+    use object_store::memory::InMemory;
+    use vudo_storage::StorageAdapter;
+
+    #[tokio::test]
+    async fn test_object_store_target() {
+        let store = Arc::new(InMemory::new());
+        let target = ObjectStoreTarget::new(store.clone(), "node-1/2024-06-01").unwrap();
+        assert_eq!(target.prefix(), "node-1/2024-06-01");
+
+        target.put("docs/0", Bytes::from("a")).await.unwrap();
+        assert_eq!(target.get("docs/0").await.unwrap(), Some(Bytes::from("a")));
+        assert_eq!(target.get("docs/1").await.unwrap(), None);
+
+        let stored = store
+            .get(&Path::from("node-1/2024-06-01/docs/0"))
+            .await
+            .unwrap();
+        assert_eq!(stored.bytes().await.unwrap(), Bytes::from("a"));
+
+        // Another prefix in the same store is a separate backup
+        let other = ObjectStoreTarget::new(store, "node-2").unwrap();
+        assert_eq!(other.get("docs/0").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_backup_sqlite_restore_fs() {
+        let source = SqliteAdapter::in_memory().await.unwrap();
+        source.init().await.unwrap();
+        source
+            .save("users", "alice/smith", Bytes::from("alice"))
+            .await
+            .unwrap();
+        source
+            .save_snapshot("users", "alice/smith", 2, Bytes::from("snap"))
+            .await
+            .unwrap();
+
+        let target = ObjectStoreTarget::new(Arc::new(InMemory::new()), "backups/1").unwrap();
+        let manifest = source.backup_to(&target).await.unwrap();
+        assert_eq!(manifest.documents.len(), 1);
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let restored = FsAdapter::new(temp_dir.path());
+        restored.init().await.unwrap();
+        assert_eq!(restored.restore_from(&target).await.unwrap(), manifest);
+
+        assert_eq!(
+            restored.load("users", "alice/smith").await.unwrap(),
+            Some(Bytes::from("alice"))
+        );
+        assert_eq!(
+            restored
+                .load_snapshot("users", "alice/smith")
+                .await
+                .unwrap(),
+            Some((2, Bytes::from("snap")))
+        );
+    }
+}
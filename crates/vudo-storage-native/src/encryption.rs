@@ -0,0 +1,194 @@
+//! Value-level encryption for [`SqliteAdapter`](crate::SqliteAdapter).
+//!
+//! Every stored value (document data, snapshots, operations) is sealed with
+//! ChaCha20-Poly1305 under a random nonce. The row's location (table,
+//! namespace, id and version) is bound as associated data, so a ciphertext
+//! copied into another row fails to open. Namespaces, ids and timestamps stay
+//! in plaintext so that lookups, listing and time-based queries keep working.
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::fmt;
+use vudo_storage::{Result, StorageError};
+
+/// Context string for deriving the database key from a device identity.
+pub const KEY_CONTEXT: &str = "vudo-storage-native sqlite value encryption v1";
+
+/// Format version prefixed to every sealed value.
+const SEAL_VERSION: u8 = 1;
+
+/// Nonce length for ChaCha20-Poly1305.
+const NONCE_LEN: usize = 12;
+
+/// A 256-bit key for encrypting stored values.
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey([u8; 32]);
+
+impl EncryptionKey {
+    /// Create a key from raw bytes.
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// Generate a random key.
+    pub fn generate() -> Self {
+        Self(ChaCha20Poly1305::generate_key(&mut OsRng).into())
+    }
+
+    /// Derive the key from a device's encryption key.
+    ///
+    /// The same device always derives the same key, so the database can be
+    /// reopened without storing key material separately.
+    #[cfg(feature = "identity")]
+    pub fn from_device(device: &vudo_identity::DeviceIdentity) -> Self {
+        Self(device.derive_key(KEY_CONTEXT))
+    }
+
+    /// Get the raw key bytes.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+/// Seals and opens stored values.
+pub(crate) struct Cipher {
+    aead: ChaCha20Poly1305,
+}
+
+impl Cipher {
+    /// Create a cipher for a key.
+    pub(crate) fn new(key: &EncryptionKey) -> Self {
+        Self {
+            aead: ChaCha20Poly1305::new(Key::from_slice(key.as_bytes())),
+        }
+    }
+
+    /// Encrypt a value: `version || nonce || ciphertext || tag`.
+    pub(crate) fn seal(&self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .aead
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .map_err(|_| StorageError::Encryption("Failed to encrypt value".to_string()))?;
+
+        let mut sealed = Vec::with_capacity(1 + NONCE_LEN + ciphertext.len());
+        sealed.push(SEAL_VERSION);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypt a value produced by [`Cipher::seal`] with the same associated data.
+    pub(crate) fn open(&self, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>> {
+        let (version, rest) = sealed
+            .split_first()
+            .ok_or_else(|| StorageError::Encryption("Empty encrypted value".to_string()))?;
+        if *version != SEAL_VERSION {
+            return Err(StorageError::Encryption(format!(
+                "Unsupported encrypted value version: {}",
+                version
+            )));
+        }
+        if rest.len() < NONCE_LEN {
+            return Err(StorageError::Encryption(
+                "Truncated encrypted value".to_string(),
+            ));
+        }
+
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        self.aead
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .map_err(|_| {
+                StorageError::Encryption(
+                    "Failed to decrypt value (wrong key or tampered data)".to_string(),
+                )
+            })
+    }
+}
+
+/// Associated data identifying where a value is stored.
+///
+/// Parts are length-prefixed so that `("a", "bc")` and `("ab", "c")` differ.
+pub(crate) fn aad(parts: &[&[u8]]) -> Vec<u8> {
+    let mut aad = Vec::new();
+    for part in parts {
+        aad.extend_from_slice(&(part.len() as u64).to_le_bytes());
+        aad.extend_from_slice(part);
+    }
+    aad
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let cipher = Cipher::new(&EncryptionKey::generate());
+        let aad = aad(&[b"documents", b"users", b"alice"]);
+
+        let sealed = cipher.seal(&aad, b"secret").unwrap();
+        assert_eq!(sealed[0], SEAL_VERSION);
+        assert!(!sealed.windows(6).any(|w| w == b"secret"));
+        assert_eq!(cipher.open(&aad, &sealed).unwrap(), b"secret");
+
+        // Fresh nonce per seal
+        assert_ne!(cipher.seal(&aad, b"secret").unwrap(), sealed);
+    }
+
+    #[test]
+    fn test_open_rejects_mismatch() {
+        let key = EncryptionKey::generate();
+        let cipher = Cipher::new(&key);
+        let sealed = cipher
+            .seal(&aad(&[b"documents", b"users", b"alice"]), b"secret")
+            .unwrap();
+
+        let other_row = aad(&[b"documents", b"users", b"bob"]);
+        assert!(matches!(
+            cipher.open(&other_row, &sealed),
+            Err(StorageError::Encryption(_))
+        ));
+
+        let other_key = Cipher::new(&EncryptionKey::generate());
+        assert!(other_key
+            .open(&aad(&[b"documents", b"users", b"alice"]), &sealed)
+            .is_err());
+
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(cipher
+            .open(&aad(&[b"documents", b"users", b"alice"]), &tampered)
+            .is_err());
+        assert!(cipher.open(&[], &sealed[..5]).is_err());
+    }
+
+    #[test]
+    fn test_aad_is_unambiguous() {
+        assert_ne!(aad(&[b"a", b"bc"]), aad(&[b"ab", b"c"]));
+    }
+
+    #[test]
+    fn test_key_debug_is_redacted() {
+        let key = EncryptionKey::from_bytes([7u8; 32]);
+        assert_eq!(format!("{:?}", key), "EncryptionKey(..)");
+    }
+}
@@ -0,0 +1,1262 @@
+//! Filesystem storage adapter implementation.
+//!
+//! Stores each document as a plain file so the state can be inspected with
+//! `grep`, backed up with `rsync` and edited by hand:
+//!
+//! ```text
+//! <root>/
+//!   docs/<namespace>/<id>                   document bytes
+//!   meta/<namespace>/<id>                   {"created_at":..,"updated_at":..}
+//!   snapshots/<namespace>/<id>/<version>    snapshot bytes (zero-padded version)
+//!   tombstones/<namespace>/<id>             deletion time (Unix epoch milliseconds)
+//!   blobs/<hash>                            blob bytes (hex BLAKE3 content hash)
+//!   blobs/<hash>.refs                       blob reference count
+//!   ops.log                                 append-only operation log (JSON lines)
+//! ```
+//!
+//! Namespaces and IDs are used as file names as-is when they consist of ASCII
+//! letters, digits, `-`, `_` and `.`; any other byte is percent-encoded, as is
+//! a leading `.`. Files are written to a temporary file and renamed into
+//! place, so readers never see a partial document.
+//!
+//! `save_operations` appends a `{"queue":N}` record followed by the N
+//! operations of the new queue. Loading replays the log and keeps the last
+//! complete queue, so a write torn by a crash falls back to the previous one.
+//! A vacuum rewrites the log with only the current queue.
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task;
+use vudo_storage::{
+    parse_field_value, unreferenced_blob, BlobGcReport, BlobHash, BlobInfo, BlobStore, Cursor,
+    JsonPath, MaintenanceOptions, MaintenanceReport, Operation, QueryFilter, QueryOptions,
+    QueryPage, Result, SortDirection, SortField, StorageAdapter, StorageError, StorageStats,
+};
+
+/// Name of the operation log file.
+const OPS_LOG: &str = "ops.log";
+
+/// Directories holding documents, their metadata, snapshots, tombstones and
+/// blobs.
+const DIRS: [&str; 5] = ["docs", "meta", "snapshots", "tombstones", "blobs"];
+
+/// Extension of blob reference count files.
+const REFS_EXT: &str = "refs";
+
+/// Filesystem storage adapter.
+///
+/// Intended for embedded and CLI use where SQLite is more than needed. Every
+/// call runs in a blocking task and calls are serialized, so the adapter is
+/// safe to share, but it is not meant for concurrent writers from several
+/// processes. Queries scan the namespace directory; secondary indexes and
+/// transactions are not supported.
+pub struct FsAdapter {
+    /// Root directory.
+    root: PathBuf,
+    /// Serializes access to the directory tree.
+    lock: Arc<Mutex<()>>,
+}
+
+/// Creation and update times of a document, stored next to it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct DocumentMeta {
+    created_at: u64,
+    updated_at: u64,
+}
+
+/// A line of the operation log.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum LogEntry {
+    /// Start of a queue of `queue` operations replacing the previous one.
+    Queue { queue: usize },
+    /// An operation of the current queue.
+    Op(Operation),
+}
+
+/// A document read back for a query.
+struct DocumentFile {
+    id: String,
+    data: Bytes,
+    meta: DocumentMeta,
+}
+
+impl DocumentFile {
+    /// Timestamp used as the sort key for a field (None when sorting by ID).
+    fn sort_timestamp(&self, field: SortField) -> Option<u64> {
+        match field {
+            SortField::Id => None,
+            SortField::CreatedAt => Some(self.meta.created_at),
+            SortField::UpdatedAt => Some(self.meta.updated_at),
+        }
+    }
+}
+
+impl FsAdapter {
+    /// Create a filesystem adapter rooted at `root`.
+    ///
+    /// The directory is created by [`init`](StorageAdapter::init).
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Get the root directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Run filesystem work in a blocking task.
+    async fn execute<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&Path) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let root = self.root.clone();
+        let lock = Arc::clone(&self.lock);
+        task::spawn_blocking(move || {
+            let _guard = lock.lock();
+            f(&root)
+        })
+        .await
+        .map_err(|e| StorageError::Internal(format!("Task join error: {}", e)))?
+    }
+}
+
+#[async_trait]
+impl StorageAdapter for FsAdapter {
+    async fn init(&self) -> Result<()> {
+        self.execute(|root| {
+            for dir in DIRS {
+                fs::create_dir_all(root.join(dir))?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    async fn save(&self, namespace: &str, id: &str, data: Bytes) -> Result<()> {
+        let (ns, name) = (encode_name(namespace)?, encode_name(id)?);
+        self.execute(move |root| save_document(root, &ns, &name, &data))
+            .await
+    }
+
+    async fn load(&self, namespace: &str, id: &str) -> Result<Option<Bytes>> {
+        let path = self
+            .root
+            .join("docs")
+            .join(encode_name(namespace)?)
+            .join(encode_name(id)?);
+        self.execute(move |_| Ok(read_optional(&path)?.map(Bytes::from)))
+            .await
+    }
+
+    async fn delete(&self, namespace: &str, id: &str) -> Result<()> {
+        let (ns, name) = (encode_name(namespace)?, encode_name(id)?);
+        self.execute(move |root| {
+            let removed = remove_optional(&root.join("docs").join(&ns).join(&name))?;
+            remove_optional(&root.join("meta").join(&ns).join(&name))?;
+            if removed {
+                let tombstones = root.join("tombstones").join(&ns);
+                fs::create_dir_all(&tombstones)?;
+                write_atomic(&tombstones.join(&name), timestamp().to_string().as_bytes())?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    async fn list(&self, namespace: &str) -> Result<Vec<String>> {
+        let dir = self.root.join("docs").join(encode_name(namespace)?);
+        self.execute(move |_| {
+            let mut ids: Vec<String> = list_names(&dir)?.into_iter().map(|(id, _)| id).collect();
+            ids.sort();
+            Ok(ids)
+        })
+        .await
+    }
+
+    async fn list_namespaces(&self) -> Result<Vec<String>> {
+        let dir = self.root.join("docs");
+        self.execute(move |_| {
+            let mut namespaces = Vec::new();
+            for (namespace, path) in list_names(&dir)? {
+                if !list_names(&path)?.is_empty() {
+                    namespaces.push(namespace);
+                }
+            }
+            namespaces.sort();
+            Ok(namespaces)
+        })
+        .await
+    }
+
+    async fn save_operations(&self, ops: &[Operation]) -> Result<()> {
+        let mut record = serde_json::to_vec(&LogEntry::Queue { queue: ops.len() })?;
+        record.push(b'\n');
+        for op in ops {
+            serde_json::to_writer(&mut record, op)?;
+            record.push(b'\n');
+        }
+
+        self.execute(move |root| {
+            let mut log = OpenOptions::new()
+                .create(true)
+                .read(true)
+                .append(true)
+                .open(root.join(OPS_LOG))?;
+
+            // Start on a fresh line after a torn write
+            if log.seek(SeekFrom::End(-1)).is_ok() {
+                let mut last = [0u8];
+                log.read_exact(&mut last)?;
+                if last[0] != b'\n' {
+                    record.insert(0, b'\n');
+                }
+            }
+
+            // A single append, so a crash leaves at most a torn tail
+            log.write_all(&record)?;
+            log.sync_data()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn load_operations(&self) -> Result<Vec<Operation>> {
+        self.execute(read_operations).await
+    }
+
+    async fn save_snapshot(
+        &self,
+        namespace: &str,
+        id: &str,
+        version: u64,
+        data: Bytes,
+    ) -> Result<()> {
+        let dir = self
+            .root
+            .join("snapshots")
+            .join(encode_name(namespace)?)
+            .join(encode_name(id)?);
+        self.execute(move |_| {
+            fs::create_dir_all(&dir)?;
+            write_atomic(&dir.join(format!("{:020}", version)), &data)
+        })
+        .await
+    }
+
+    async fn load_snapshot(&self, namespace: &str, id: &str) -> Result<Option<(u64, Bytes)>> {
+        let dir = self
+            .root
+            .join("snapshots")
+            .join(encode_name(namespace)?)
+            .join(encode_name(id)?);
+        self.execute(move |_| {
+            let Some(version) = snapshot_versions(&dir)?.pop() else {
+                return Ok(None);
+            };
+            let data = fs::read(dir.join(format!("{:020}", version)))?;
+            Ok(Some((version, Bytes::from(data))))
+        })
+        .await
+    }
+
+    async fn query(&self, namespace: &str, filter: QueryFilter) -> Result<Vec<(String, Bytes)>> {
+        validate_filter(&filter)?;
+        let ns = encode_name(namespace)?;
+        self.execute(move |root| {
+            let mut results: Vec<(String, Bytes)> = read_documents(root, &ns)?
+                .into_iter()
+                .filter(|doc| matches_filter(doc, &filter))
+                .map(|doc| (doc.id, doc.data))
+                .collect();
+            results.sort_by(|a, b| a.0.cmp(&b.0));
+            Ok(results)
+        })
+        .await
+    }
+
+    async fn query_page(
+        &self,
+        namespace: &str,
+        filter: QueryFilter,
+        options: QueryOptions,
+    ) -> Result<QueryPage> {
+        validate_filter(&filter)?;
+        let ns = encode_name(namespace)?;
+        let documents = self.execute(move |root| read_documents(root, &ns)).await?;
+
+        let field = options.sort.field;
+        let mut matches: Vec<(Option<u64>, String, Bytes)> = documents
+            .into_iter()
+            .filter(|doc| matches_filter(doc, &filter))
+            .map(|doc| (doc.sort_timestamp(field), doc.id, doc.data))
+            .collect();
+
+        matches.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+        if options.sort.direction == SortDirection::Descending {
+            matches.reverse();
+        }
+
+        if let Some(cursor) = &options.cursor {
+            let after = (cursor.timestamp, &cursor.id);
+            matches.retain(|(timestamp, id, _)| match options.sort.direction {
+                SortDirection::Ascending => (*timestamp, id) > after,
+                SortDirection::Descending => (*timestamp, id) < after,
+            });
+        }
+
+        let page: Vec<_> = matches
+            .into_iter()
+            .skip(options.offset)
+            .take(options.limit.unwrap_or(usize::MAX))
+            .collect();
+
+        let next_cursor = match (options.limit, page.last()) {
+            (Some(limit), Some((timestamp, id, _))) if page.len() == limit => Some(Cursor {
+                timestamp: *timestamp,
+                id: id.clone(),
+            }),
+            _ => None,
+        };
+
+        Ok(QueryPage {
+            items: page.into_iter().map(|(_, id, data)| (id, data)).collect(),
+            next_cursor,
+        })
+    }
+
+    async fn maintenance(&self, options: MaintenanceOptions) -> Result<MaintenanceReport> {
+        self.execute(move |root| {
+            let mut report = MaintenanceReport::default();
+            let snapshots = root.join("snapshots");
+
+            if let Some(max) = options.max_snapshots_per_doc {
+                for (_, ns_dir) in list_names(&snapshots)? {
+                    for (_, doc_dir) in list_names(&ns_dir)? {
+                        let versions = snapshot_versions(&doc_dir)?;
+                        let excess = versions.len().saturating_sub(max);
+                        for version in &versions[..excess] {
+                            let path = doc_dir.join(format!("{:020}", version));
+                            report.bytes_freed += fs::metadata(&path)?.len();
+                            fs::remove_file(path)?;
+                            report.snapshots_pruned += 1;
+                        }
+                    }
+                }
+            }
+
+            if let Some(retention) = options.tombstone_retention {
+                let cutoff = timestamp().saturating_sub(retention.as_millis() as u64);
+                for (_, ns_dir) in list_names(&root.join("tombstones"))? {
+                    let ns = file_name(&ns_dir);
+                    for (_, path) in list_names(&ns_dir)? {
+                        let deleted_at: u64 =
+                            fs::read_to_string(&path)?.trim().parse().map_err(|_| {
+                                StorageError::Database(format!(
+                                    "Malformed tombstone {}",
+                                    path.display()
+                                ))
+                            })?;
+                        if deleted_at > cutoff {
+                            continue;
+                        }
+
+                        fs::remove_file(&path)?;
+                        report.tombstones_purged += 1;
+
+                        let doc_dir = snapshots.join(&ns).join(file_name(&path));
+                        for version in snapshot_versions(&doc_dir)? {
+                            let path = doc_dir.join(format!("{:020}", version));
+                            report.bytes_freed += fs::metadata(path)?.len();
+                            report.tombstone_snapshots_purged += 1;
+                        }
+                        if doc_dir.exists() {
+                            fs::remove_dir_all(doc_dir)?;
+                        }
+                    }
+                }
+            }
+
+            // Removed files release their space right away
+            report.bytes_reclaimed = report.bytes_freed;
+
+            if options.vacuum {
+                report.bytes_reclaimed += compact_log(root)?;
+                for dir in DIRS {
+                    remove_empty_dirs(&root.join(dir))?;
+                }
+            }
+
+            Ok(report)
+        })
+        .await
+    }
+
+    async fn stats(&self) -> Result<StorageStats> {
+        self.execute(|root| {
+            let mut stats = StorageStats::default();
+
+            for (_, ns_dir) in list_names(&root.join("docs"))? {
+                for (_, path) in list_names(&ns_dir)? {
+                    stats.document_count += 1;
+                    stats.total_document_size += fs::metadata(path)?.len() as usize;
+                }
+            }
+
+            for (_, ns_dir) in list_names(&root.join("snapshots"))? {
+                for (_, doc_dir) in list_names(&ns_dir)? {
+                    for (_, path) in list_names(&doc_dir)? {
+                        stats.snapshot_count += 1;
+                        stats.total_snapshot_size += fs::metadata(path)?.len() as usize;
+                    }
+                }
+            }
+
+            for (_, ns_dir) in list_names(&root.join("tombstones"))? {
+                stats.tombstone_count += list_names(&ns_dir)?.len();
+            }
+
+            stats.operation_count = read_operations(root)?.len();
+            // Files are stored uncompressed
+            stats.uncompressed_document_size = stats.total_document_size;
+            stats.uncompressed_snapshot_size = stats.total_snapshot_size;
+            Ok(stats)
+        })
+        .await
+    }
+
+    async fn clear(&self) -> Result<()> {
+        self.execute(|root| {
+            for dir in DIRS {
+                let dir = root.join(dir);
+                if dir.exists() {
+                    fs::remove_dir_all(&dir)?;
+                }
+                fs::create_dir_all(dir)?;
+            }
+            remove_optional(&root.join(OPS_LOG))?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// Blob contents are verified against their hash when read.
+#[async_trait]
+impl BlobStore for FsAdapter {
+    async fn put_blob(&self, data: Bytes) -> Result<BlobHash> {
+        let hash = BlobHash::of(&data);
+        self.execute(move |root| {
+            let path = blob_path(root, &hash);
+            let refcount = match read_refcount(&path)? {
+                Some(refcount) => refcount + 1,
+                None => {
+                    fs::create_dir_all(root.join("blobs"))?;
+                    write_atomic(&path, &data)?;
+                    1
+                }
+            };
+            write_refcount(&path, refcount)?;
+            Ok(hash)
+        })
+        .await
+    }
+
+    async fn get_blob(&self, hash: &BlobHash) -> Result<Option<Bytes>> {
+        let hash = *hash;
+        self.execute(move |root| {
+            let Some(data) = read_optional(&blob_path(root, &hash))? else {
+                return Ok(None);
+            };
+            hash.verify(&data)?;
+            Ok(Some(Bytes::from(data)))
+        })
+        .await
+    }
+
+    async fn blob_info(&self, hash: &BlobHash) -> Result<Option<BlobInfo>> {
+        let hash = *hash;
+        self.execute(move |root| {
+            let path = blob_path(root, &hash);
+            let Some(refcount) = read_refcount(&path)? else {
+                return Ok(None);
+            };
+            match fs::metadata(&path) {
+                Ok(metadata) => Ok(Some(BlobInfo {
+                    hash,
+                    size: metadata.len(),
+                    refcount,
+                })),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        })
+        .await
+    }
+
+    async fn retain_blob(&self, hash: &BlobHash) -> Result<u64> {
+        let hash = *hash;
+        self.execute(move |root| {
+            let path = blob_path(root, &hash);
+            let refcount = read_refcount(&path)?.ok_or(StorageError::BlobNotFound(hash))? + 1;
+            write_refcount(&path, refcount)?;
+            Ok(refcount)
+        })
+        .await
+    }
+
+    async fn release_blob(&self, hash: &BlobHash) -> Result<u64> {
+        let hash = *hash;
+        self.execute(move |root| {
+            let path = blob_path(root, &hash);
+            let refcount = read_refcount(&path)?.ok_or(StorageError::BlobNotFound(hash))?;
+            if refcount == 0 {
+                return Err(unreferenced_blob(&hash));
+            }
+            write_refcount(&path, refcount - 1)?;
+            Ok(refcount - 1)
+        })
+        .await
+    }
+
+    async fn gc_blobs(&self) -> Result<BlobGcReport> {
+        self.execute(|root| {
+            let mut report = BlobGcReport::default();
+            for (name, path) in list_names(&root.join("blobs"))? {
+                // Skip reference count files
+                if name.parse::<BlobHash>().is_err() {
+                    continue;
+                }
+                if read_refcount(&path)?.is_some_and(|refcount| refcount > 0) {
+                    continue;
+                }
+                let size = fs::metadata(&path)?.len();
+                remove_optional(&path)?;
+                remove_optional(&path.with_extension(REFS_EXT))?;
+                report.blobs_removed += 1;
+                report.bytes_freed += size;
+            }
+            Ok(report)
+        })
+        .await
+    }
+}
+
+/// Get current timestamp in milliseconds.
+fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+/// Encode a namespace or ID as a file name.
+fn encode_name(name: &str) -> Result<String> {
+    if name.is_empty() {
+        return Err(StorageError::InvalidOperation(
+            "Namespaces and document IDs must not be empty".to_string(),
+        ));
+    }
+
+    let mut encoded = String::with_capacity(name.len());
+    for (i, byte) in name.bytes().enumerate() {
+        let plain = byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.');
+        if plain && !(i == 0 && byte == b'.') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    Ok(encoded)
+}
+
+/// Decode a file name written by [`encode_name`].
+fn decode_name(name: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(name.len());
+    let mut rest = name.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Final component of a path as a string.
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Entries of a directory as (decoded name, path), skipping temporary and
+/// foreign files. A missing directory is empty.
+fn list_names(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut names = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let Some(file_name) = file_name.to_str() else {
+            continue;
+        };
+        if file_name.starts_with('.') {
+            continue;
+        }
+        if let Some(name) = decode_name(file_name) {
+            names.push((name, entry.path()));
+        }
+    }
+    Ok(names)
+}
+
+/// Snapshot versions of a document, oldest first.
+fn snapshot_versions(dir: &Path) -> Result<Vec<u64>> {
+    let mut versions: Vec<u64> = list_names(dir)?
+        .into_iter()
+        .filter_map(|(name, _)| name.parse().ok())
+        .collect();
+    versions.sort_unstable();
+    Ok(versions)
+}
+
+/// Read a file, or None if it doesn't exist.
+fn read_optional(path: &Path) -> Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Remove a file, returning whether it existed.
+fn remove_optional(path: &Path) -> Result<bool> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Write a file through a temporary file in the same directory.
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let tmp = path.with_file_name(format!(".{}.tmp", file_name(path)));
+    let mut file = File::create(&tmp)?;
+    file.write_all(data)?;
+    file.sync_data()?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Path of a blob's content file.
+fn blob_path(root: &Path, hash: &BlobHash) -> PathBuf {
+    root.join("blobs").join(hash.to_hex())
+}
+
+/// Reference count of the blob at `path`, or None if it isn't stored.
+///
+/// A blob file without a count file has no references.
+fn read_refcount(path: &Path) -> Result<Option<u64>> {
+    match read_optional(&path.with_extension(REFS_EXT))? {
+        Some(refs) => std::str::from_utf8(&refs)
+            .ok()
+            .and_then(|refs| refs.trim().parse().ok())
+            .map(Some)
+            .ok_or_else(|| {
+                StorageError::Internal(format!("Corrupt blob reference count for {:?}", path))
+            }),
+        None if path.exists() => Ok(Some(0)),
+        None => Ok(None),
+    }
+}
+
+/// Write the reference count of the blob at `path`.
+fn write_refcount(path: &Path, refcount: u64) -> Result<()> {
+    write_atomic(
+        &path.with_extension(REFS_EXT),
+        refcount.to_string().as_bytes(),
+    )
+}
+
+/// Write a document and its metadata, keeping the creation time of an
+/// existing document.
+fn save_document(root: &Path, ns: &str, name: &str, data: &[u8]) -> Result<()> {
+    let docs = root.join("docs").join(ns);
+    let meta_dir = root.join("meta").join(ns);
+    fs::create_dir_all(&docs)?;
+    fs::create_dir_all(&meta_dir)?;
+
+    let meta_path = meta_dir.join(name);
+    let updated_at = timestamp();
+    let created_at = read_meta(&meta_path, &docs.join(name))?
+        .map(|meta| meta.created_at)
+        .unwrap_or(updated_at);
+
+    write_atomic(&docs.join(name), data)?;
+    let meta = DocumentMeta {
+        created_at,
+        updated_at,
+    };
+    write_atomic(&meta_path, &serde_json::to_vec(&meta)?)?;
+    remove_optional(&root.join("tombstones").join(ns).join(name))?;
+    Ok(())
+}
+
+/// Read a document's metadata, falling back to the document file's
+/// modification time for documents written by other tools.
+fn read_meta(meta_path: &Path, doc_path: &Path) -> Result<Option<DocumentMeta>> {
+    if let Some(meta) = read_optional(meta_path)? {
+        return Ok(Some(serde_json::from_slice(&meta)?));
+    }
+
+    match fs::metadata(doc_path) {
+        Ok(metadata) => {
+            let modified = metadata
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or(Duration::ZERO)
+                .as_millis() as u64;
+            Ok(Some(DocumentMeta {
+                created_at: modified,
+                updated_at: modified,
+            }))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Read every document of a namespace.
+fn read_documents(root: &Path, ns: &str) -> Result<Vec<DocumentFile>> {
+    let meta_dir = root.join("meta").join(ns);
+    let mut documents = Vec::new();
+    for (id, path) in list_names(&root.join("docs").join(ns))? {
+        let Some(data) = read_optional(&path)? else {
+            continue;
+        };
+        let Some(meta) = read_meta(&meta_dir.join(file_name(&path)), &path)? else {
+            continue;
+        };
+        documents.push(DocumentFile {
+            id,
+            data: Bytes::from(data),
+            meta,
+        });
+    }
+    Ok(documents)
+}
+
+/// Replay the operation log and return the last complete queue.
+fn read_operations(root: &Path) -> Result<Vec<Operation>> {
+    let Some(log) = read_optional(&root.join(OPS_LOG))? else {
+        return Ok(Vec::new());
+    };
+
+    let mut current = Vec::new();
+    let mut pending: Option<(usize, Vec<Operation>)> = None;
+    for line in log.split(|&byte| byte == b'\n') {
+        if line.is_empty() {
+            continue;
+        }
+        let entry: LogEntry = match serde_json::from_slice(line) {
+            Ok(entry) => entry,
+            Err(e) => {
+                // The torn tail of an interrupted write; drop its queue
+                tracing::warn!("Skipping malformed operation log entry: {}", e);
+                pending = None;
+                continue;
+            }
+        };
+
+        match entry {
+            LogEntry::Queue { queue: 0 } => {
+                current.clear();
+                pending = None;
+            }
+            LogEntry::Queue { queue } => pending = Some((queue, Vec::with_capacity(queue))),
+            LogEntry::Op(op) => {
+                let Some((expected, ops)) = pending.as_mut() else {
+                    tracing::warn!("Skipping operation {} outside a queue record", op.id);
+                    continue;
+                };
+                ops.push(op);
+                if ops.len() == *expected {
+                    current = std::mem::take(ops);
+                    pending = None;
+                }
+            }
+        }
+    }
+    Ok(current)
+}
+
+/// Rewrite the operation log with only the current queue, returning the
+/// number of bytes it shrank by.
+fn compact_log(root: &Path) -> Result<u64> {
+    let path = root.join(OPS_LOG);
+    let before = match fs::metadata(&path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+
+    let ops = read_operations(root)?;
+    let mut record = serde_json::to_vec(&LogEntry::Queue { queue: ops.len() })?;
+    record.push(b'\n');
+    for op in &ops {
+        serde_json::to_writer(&mut record, op)?;
+        record.push(b'\n');
+    }
+    write_atomic(&path, &record)?;
+    Ok(before.saturating_sub(record.len() as u64))
+}
+
+/// Remove empty directories below `dir`, keeping `dir` itself.
+fn remove_empty_dirs(dir: &Path) -> Result<()> {
+    for (_, path) in list_names(dir)? {
+        if path.is_dir() {
+            remove_empty_dirs(&path)?;
+            if fs::read_dir(&path)?.next().is_none() {
+                fs::remove_dir(&path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check if a document matches a filter.
+fn matches_filter(doc: &DocumentFile, filter: &QueryFilter) -> bool {
+    let DocumentMeta {
+        created_at,
+        updated_at,
+    } = doc.meta;
+    match filter {
+        QueryFilter::All => true,
+        QueryFilter::UpdatedAfter(timestamp) => updated_at > *timestamp,
+        QueryFilter::UpdatedBefore(timestamp) => updated_at < *timestamp,
+        QueryFilter::UpdatedBetween { start, end } => updated_at >= *start && updated_at <= *end,
+        QueryFilter::CreatedAfter(timestamp) => created_at > *timestamp,
+        QueryFilter::CreatedBefore(timestamp) => created_at < *timestamp,
+        QueryFilter::CreatedBetween { start, end } => created_at >= *start && created_at <= *end,
+        QueryFilter::IdPrefix(prefix) => doc.id.starts_with(prefix.as_str()),
+        QueryFilter::And(filters) => filters.iter().all(|f| matches_filter(doc, f)),
+        QueryFilter::Or(filters) => filters.iter().any(|f| matches_filter(doc, f)),
+        QueryFilter::Not(f) => !matches_filter(doc, f),
+        QueryFilter::Field { field, value } => match JsonPath::parse(field) {
+            Ok(path) => path.extract_from_bytes(&doc.data) == Some(parse_field_value(value)),
+            Err(_) => false,
+        },
+    }
+}
+
+/// Reject filters with malformed field paths.
+fn validate_filter(filter: &QueryFilter) -> Result<()> {
+    match filter {
+        QueryFilter::Field { field, .. } => JsonPath::parse(field).map(|_| ()),
+        QueryFilter::And(filters) | QueryFilter::Or(filters) => {
+            filters.iter().try_for_each(validate_filter)
+        }
+        QueryFilter::Not(f) => validate_filter(f),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vudo_storage::operation::OperationType;
+    use vudo_storage::{MemoryBackupTarget, SortOrder};
+
+    async fn adapter() -> (tempfile::TempDir, FsAdapter) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let adapter = FsAdapter::new(temp_dir.path().join("state"));
+        adapter.init().await.unwrap();
+        (temp_dir, adapter)
+    }
+
+    #[test]
+    fn test_name_encoding() {
+        assert_eq!(encode_name("alice").unwrap(), "alice");
+        assert_eq!(encode_name("v1.2_x-y").unwrap(), "v1.2_x-y");
+        assert_eq!(encode_name("user:alice").unwrap(), "user%3Aalice");
+        assert_eq!(encode_name("../etc").unwrap(), "%2E.%2Fetc");
+        assert_eq!(encode_name("100%").unwrap(), "100%25");
+        assert_eq!(encode_name("é").unwrap(), "%C3%A9");
+        assert!(encode_name("").is_err());
+
+        for name in ["alice", "user:alice", "../etc", "100%", "é", ".hidden"] {
+            assert_eq!(decode_name(&encode_name(name).unwrap()).unwrap(), name);
+        }
+        assert_eq!(decode_name("bad%2"), None);
+    }
+
+    #[tokio::test]
+    async fn test_fs_adapter_init() {
+        let (_temp_dir, adapter) = adapter().await;
+        for dir in DIRS {
+            assert!(adapter.root().join(dir).is_dir());
+        }
+
+        // Init should be idempotent
+        adapter.init().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fs_adapter_save_and_load() {
+        let (_temp_dir, adapter) = adapter().await;
+
+        let data = Bytes::from("test document");
+        adapter.save("users", "alice", data.clone()).await.unwrap();
+        assert_eq!(adapter.load("users", "alice").await.unwrap(), Some(data));
+        assert_eq!(adapter.load("users", "bob").await.unwrap(), None);
+
+        // Documents are plain files
+        let path = adapter.root().join("docs/users/alice");
+        assert_eq!(fs::read(path).unwrap(), b"test document");
+
+        adapter
+            .save("users", "user:bob/1", Bytes::from("bob"))
+            .await
+            .unwrap();
+        assert!(adapter.root().join("docs/users/user%3Abob%2F1").is_file());
+        assert_eq!(
+            adapter.load("users", "user:bob/1").await.unwrap(),
+            Some(Bytes::from("bob"))
+        );
+        assert!(adapter.save("users", "", Bytes::new()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fs_adapter_delete_and_list() {
+        let (_temp_dir, adapter) = adapter().await;
+
+        for (ns, id) in [("users", "bob"), ("users", "alice"), ("posts", "post1")] {
+            adapter.save(ns, id, Bytes::from(id)).await.unwrap();
+        }
+        assert_eq!(adapter.list("users").await.unwrap(), vec!["alice", "bob"]);
+        assert_eq!(adapter.list("posts").await.unwrap(), vec!["post1"]);
+        assert!(adapter.list("empty").await.unwrap().is_empty());
+
+        adapter.delete("users", "alice").await.unwrap();
+        adapter.delete("users", "nobody").await.unwrap();
+        assert_eq!(adapter.load("users", "alice").await.unwrap(), None);
+        assert_eq!(adapter.list("users").await.unwrap(), vec!["bob"]);
+        assert_eq!(adapter.stats().await.unwrap().tombstone_count, 1);
+
+        // Saving again revives the document
+        adapter
+            .save("users", "alice", Bytes::from("back"))
+            .await
+            .unwrap();
+        assert_eq!(adapter.stats().await.unwrap().tombstone_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_fs_adapter_backup_and_restore() {
+        let (_temp_dir, source) = adapter().await;
+        source
+            .save("users", "alice", Bytes::from("a"))
+            .await
+            .unwrap();
+        source.save("posts", "p1", Bytes::from("p")).await.unwrap();
+        source.save("gone", "x", Bytes::from("x")).await.unwrap();
+        source.delete("gone", "x").await.unwrap();
+        source
+            .save_snapshot("posts", "p1", 7, Bytes::from("snap"))
+            .await
+            .unwrap();
+        assert_eq!(
+            source.list_namespaces().await.unwrap(),
+            vec!["posts", "users"]
+        );
+
+        let target = MemoryBackupTarget::new();
+        let manifest = source.backup_to(&target).await.unwrap();
+        assert_eq!(manifest.documents.len(), 2);
+
+        let (_other_dir, restored) = adapter().await;
+        restored.restore_from(&target).await.unwrap();
+        assert_eq!(
+            restored.list_namespaces().await.unwrap(),
+            vec!["posts", "users"]
+        );
+        assert_eq!(
+            restored.load_snapshot("posts", "p1").await.unwrap(),
+            Some((7, Bytes::from("snap")))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fs_adapter_operations_log() {
+        let (_temp_dir, adapter) = adapter().await;
+        assert!(adapter.load_operations().await.unwrap().is_empty());
+
+        let ops = vec![
+            Operation::new(1, "users", "alice", OperationType::Create),
+            Operation::new(2, "users", "bob", OperationType::Create),
+        ];
+        adapter.save_operations(&ops).await.unwrap();
+        adapter.save_operations(&ops[1..]).await.unwrap();
+        assert_eq!(adapter.load_operations().await.unwrap(), ops[1..].to_vec());
+
+        // The log only grows
+        let log = fs::read_to_string(adapter.root().join(OPS_LOG)).unwrap();
+        assert_eq!(log.lines().count(), 5);
+
+        // A torn write falls back to the last complete queue
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(adapter.root().join(OPS_LOG))
+            .unwrap();
+        file.write_all(b"{\"queue\":2}\n{\"id\":3,\"names").unwrap();
+        assert_eq!(adapter.load_operations().await.unwrap(), ops[1..].to_vec());
+
+        adapter.save_operations(&[]).await.unwrap();
+        assert!(adapter.load_operations().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fs_adapter_snapshots() {
+        let (_temp_dir, adapter) = adapter().await;
+
+        for version in [2, 10, 1] {
+            adapter
+                .save_snapshot(
+                    "users",
+                    "alice",
+                    version,
+                    Bytes::from(format!("v{}", version)),
+                )
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            adapter.load_snapshot("users", "alice").await.unwrap(),
+            Some((10, Bytes::from("v10")))
+        );
+        assert_eq!(adapter.load_snapshot("users", "bob").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_fs_adapter_query() {
+        let (_temp_dir, adapter) = adapter().await;
+
+        adapter
+            .save("users", "user:alice", Bytes::from(r#"{"status":"active"}"#))
+            .await
+            .unwrap();
+        adapter
+            .save("users", "user:bob", Bytes::from(r#"{"status":"away"}"#))
+            .await
+            .unwrap();
+        adapter
+            .save("users", "post:1", Bytes::from("not json"))
+            .await
+            .unwrap();
+
+        let results = adapter.query("users", QueryFilter::All).await.unwrap();
+        assert_eq!(results.len(), 3);
+
+        let filter = QueryFilter::id_prefix("user:").and(QueryFilter::field("status", "active"));
+        let results = adapter.query("users", filter).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, "user:alice");
+
+        assert!(adapter
+            .query("users", QueryFilter::field("$.", "1"))
+            .await
+            .is_err());
+        assert!(adapter.ensure_index("users", "status").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fs_adapter_query_page() {
+        let (_temp_dir, adapter) = adapter().await;
+
+        for id in ["a", "b", "c", "d", "e"] {
+            adapter.save("docs", id, Bytes::from(id)).await.unwrap();
+            tokio::time::sleep(tokio::time::Duration::from_millis(2)).await;
+        }
+
+        // Updating keeps the creation time
+        adapter.save("docs", "a", Bytes::from("a2")).await.unwrap();
+
+        let options = QueryOptions::default()
+            .sort_by(SortOrder::desc(SortField::CreatedAt))
+            .limit(2);
+        let page = adapter
+            .query_page("docs", QueryFilter::All, options.clone())
+            .await
+            .unwrap();
+        let ids: Vec<_> = page.items.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["e", "d"]);
+
+        let page = adapter
+            .query_page(
+                "docs",
+                QueryFilter::All,
+                options.after(page.next_cursor.unwrap()),
+            )
+            .await
+            .unwrap();
+        let ids: Vec<_> = page.items.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "b"]);
+
+        let options = QueryOptions::default().sort_by(SortOrder::desc(SortField::UpdatedAt));
+        let page = adapter
+            .query_page("docs", QueryFilter::All, options)
+            .await
+            .unwrap();
+        assert_eq!(page.items[0].0, "a");
+    }
+
+    #[tokio::test]
+    async fn test_fs_adapter_reads_hand_written_documents() {
+        let (_temp_dir, adapter) = adapter().await;
+
+        let dir = adapter.root().join("docs/notes");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("todo"), "buy milk").unwrap();
+
+        assert_eq!(adapter.list("notes").await.unwrap(), vec!["todo"]);
+        let results = adapter
+            .query("notes", QueryFilter::updated_after(0))
+            .await
+            .unwrap();
+        assert_eq!(results, vec![("todo".to_string(), Bytes::from("buy milk"))]);
+    }
+
+    #[tokio::test]
+    async fn test_fs_adapter_maintenance() {
+        let (_temp_dir, adapter) = adapter().await;
+
+        for version in 1..=5 {
+            adapter
+                .save_snapshot("users", "alice", version, Bytes::from("snap"))
+                .await
+                .unwrap();
+        }
+        for id in ["bob", "carol"] {
+            adapter
+                .save("users", id, Bytes::from("data"))
+                .await
+                .unwrap();
+            adapter
+                .save_snapshot("users", id, 1, Bytes::from("snapshot"))
+                .await
+                .unwrap();
+        }
+        adapter.delete("users", "bob").await.unwrap();
+
+        let ops = vec![Operation::new(1, "users", "carol", OperationType::Create)];
+        for _ in 0..10 {
+            adapter.save_operations(&ops).await.unwrap();
+        }
+
+        let report = adapter
+            .maintenance(
+                MaintenanceOptions::new()
+                    .max_snapshots_per_doc(2)
+                    .tombstone_retention(Duration::ZERO)
+                    .vacuum(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.snapshots_pruned, 3);
+        assert_eq!(report.tombstones_purged, 1);
+        assert_eq!(report.tombstone_snapshots_purged, 1);
+        assert_eq!(report.bytes_freed, 12 + 8);
+        assert!(report.bytes_reclaimed > report.bytes_freed);
+
+        let stats = adapter.stats().await.unwrap();
+        assert_eq!(stats.document_count, 1);
+        assert_eq!(stats.snapshot_count, 3);
+        assert_eq!(stats.tombstone_count, 0);
+        assert_eq!(stats.operation_count, 1);
+        assert_eq!(adapter.load_operations().await.unwrap(), ops);
+        assert!(!adapter.root().join("snapshots/users/bob").exists());
+
+        let log = fs::read_to_string(adapter.root().join(OPS_LOG)).unwrap();
+        assert_eq!(log.lines().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fs_adapter_blobs() {
+        let (_temp_dir, adapter) = adapter().await;
+        let wasm = Bytes::from_static(b"\0asm module");
+
+        let hash = adapter.put_blob(wasm.clone()).await.unwrap();
+        assert_eq!(adapter.put_blob(wasm.clone()).await.unwrap(), hash);
+        assert_eq!(adapter.get_blob(&hash).await.unwrap(), Some(wasm.clone()));
+        let info = adapter.blob_info(&hash).await.unwrap().unwrap();
+        assert_eq!(info.size, wasm.len() as u64);
+        assert_eq!(info.refcount, 2);
+
+        assert_eq!(adapter.retain_blob(&hash).await.unwrap(), 3);
+        for expected in [2, 1, 0] {
+            assert_eq!(adapter.release_blob(&hash).await.unwrap(), expected);
+        }
+        assert!(adapter.release_blob(&hash).await.is_err());
+
+        let kept = adapter.put_blob(Bytes::from("weights")).await.unwrap();
+        let report = adapter.gc_blobs().await.unwrap();
+        assert_eq!(report.blobs_removed, 1);
+        assert_eq!(report.bytes_freed, wasm.len() as u64);
+        assert!(!adapter.has_blob(&hash).await.unwrap());
+        assert!(!adapter.root().join("blobs").join(hash.to_hex()).exists());
+        assert!(adapter.has_blob(&kept).await.unwrap());
+
+        // Tampered blobs are rejected
+        fs::write(adapter.root().join("blobs").join(kept.to_hex()), "tampered").unwrap();
+        assert!(adapter.get_blob(&kept).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fs_adapter_persists_and_clears() {
+        let (_temp_dir, adapter) = adapter().await;
+
+        adapter
+            .save("users", "alice", Bytes::from("data"))
+            .await
+            .unwrap();
+        adapter
+            .save_operations(&[Operation::new(1, "users", "alice", OperationType::Create)])
+            .await
+            .unwrap();
+
+        let reopened = FsAdapter::new(adapter.root());
+        reopened.init().await.unwrap();
+        assert_eq!(
+            reopened.load("users", "alice").await.unwrap(),
+            Some(Bytes::from("data"))
+        );
+        assert_eq!(reopened.load_operations().await.unwrap().len(), 1);
+
+        reopened.clear().await.unwrap();
+        let stats = adapter.stats().await.unwrap();
+        assert_eq!(stats.document_count, 0);
+        assert_eq!(stats.operation_count, 0);
+        assert!(adapter.root().join("docs").is_dir());
+    }
+}
@@ -1,7 +1,8 @@
 //! Native SQLite storage adapter for VUDO Runtime.
 //!
 //! This crate provides high-performance persistent storage for Desktop, Mobile,
-//! and Server platforms using native SQLite with WAL mode.
+//! and Server platforms using native SQLite with WAL mode, and a plain
+//! filesystem adapter ([`FsAdapter`]) for embedded and CLI use.
 //!
 //! # Features
 //!
@@ -9,6 +10,9 @@
 //! - Connection pooling (multiple readers, single writer)
 //! - Optimized bulk inserts
 //! - 100K+ writes/sec performance target
+//! - Optional ChaCha20-Poly1305 encryption of stored values
+//! - Grep-able, rsync-able directory storage with an append-only operation log
+//! - Backups to S3-compatible object stores (features `object-store`, `s3`)
 //!
 //! # Example
 //!
@@ -34,6 +38,14 @@
 //! }
 //! ```
 
+#[cfg(feature = "object-store")]
+pub mod backup_target;
+pub mod encryption;
+pub mod fs_adapter;
 pub mod sqlite_adapter;
 
+#[cfg(feature = "object-store")]
+pub use backup_target::ObjectStoreTarget;
+pub use encryption::EncryptionKey;
+pub use fs_adapter::FsAdapter;
 pub use sqlite_adapter::SqliteAdapter;
@@ -1,13 +1,32 @@
 //! SQLite-based storage adapter implementation.
 
+use crate::encryption::{aad, Cipher, EncryptionKey};
 use async_trait::async_trait;
 use bytes::Bytes;
 use parking_lot::Mutex;
-use rusqlite::{params, Connection, OptionalExtension};
+use rusqlite::blob::ZeroBlob;
+use rusqlite::types::Value;
+use rusqlite::{params, Connection, DatabaseName, OptionalExtension};
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::task;
-use vudo_storage::{Operation, QueryFilter, Result, StorageAdapter, StorageError, StorageStats};
+use vudo_storage::operation::OperationType;
+use vudo_storage::{
+    enforce_quota, parse_field_value, prefix_upper_bound, unreferenced_blob, BlobGcReport,
+    BlobHash, BlobInfo, BlobStore, Codec, CompressionPolicy, Cursor, EvictionCandidate,
+    EvictionHook, IntegrityItem, IntegrityReport, JsonPath, MaintenanceOptions, MaintenanceReport,
+    Operation, QueryFilter, QueryOptions, QueryPage, QuotaScope, QuotaUsage, Result, SortDirection,
+    SortField, SortOrder, StorageAdapter, StorageError, StorageQuota, StorageStats,
+};
+
+/// Chunk size for streaming document data out of SQLite.
+const STREAM_CHUNK_SIZE: usize = 256 * 1024;
+
+/// `storage_meta` key of the value used to verify the encryption key.
+const KEY_CHECK: &str = "key_check";
 
 /// SQLite storage adapter.
 ///
@@ -19,6 +38,14 @@ pub struct SqliteAdapter {
     /// Shared connection for reads and writes (protected by mutex).
     /// We use a single connection with WAL mode which allows concurrent reads.
     connection: Arc<Mutex<Connection>>,
+    /// Cipher for stored values, if encryption is enabled.
+    cipher: Option<Arc<Cipher>>,
+    /// Byte limits on documents.
+    quota: Arc<StorageQuota>,
+    /// Chooses documents to evict when a write would exceed the quota.
+    eviction_hook: Option<Arc<dyn EvictionHook>>,
+    /// Codecs of document and snapshot data.
+    compression: Arc<CompressionPolicy>,
 }
 
 impl SqliteAdapter {
@@ -65,6 +92,10 @@ impl SqliteAdapter {
         Ok(Self {
             path,
             connection: Arc::new(Mutex::new(connection)),
+            cipher: None,
+            quota: Arc::default(),
+            eviction_hook: None,
+            compression: Arc::default(),
         })
     }
 
@@ -85,9 +116,135 @@ impl SqliteAdapter {
         Ok(Self {
             path: PathBuf::from(":memory:"),
             connection: Arc::new(Mutex::new(connection)),
+            cipher: None,
+            quota: Arc::default(),
+            eviction_hook: None,
+            compression: Arc::default(),
         })
     }
 
+    /// Encrypt stored values with `key`.
+    ///
+    /// Document data, snapshots and operations are sealed with
+    /// ChaCha20-Poly1305; namespaces, ids and timestamps are stored in
+    /// plaintext. [`init`](StorageAdapter::init) verifies the key against the
+    /// database and fails if it doesn't match, or if the database already
+    /// holds unencrypted data. Field indexes and field filters are not
+    /// available on an encrypted database.
+    pub fn with_encryption(mut self, key: EncryptionKey) -> Self {
+        self.cipher = Some(Arc::new(Cipher::new(&key)));
+        self
+    }
+
+    /// Whether stored values are encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    /// Enforce `quota` on document writes.
+    ///
+    /// Writes that would exceed a limit fail with
+    /// [`StorageError::QuotaExceeded`] and are rolled back, unless the
+    /// eviction hook frees enough room first; evictions commit with the
+    /// write. Sizes are the stored sizes, including encryption overhead.
+    pub fn with_quota(mut self, quota: StorageQuota) -> Self {
+        self.quota = Arc::new(quota);
+        self
+    }
+
+    /// Let `hook` evict documents when a write would exceed the quota.
+    pub fn with_eviction_hook(mut self, hook: Arc<dyn EvictionHook>) -> Self {
+        self.eviction_hook = Some(hook);
+        self
+    }
+
+    /// The quota enforced on document writes.
+    pub fn quota(&self) -> &StorageQuota {
+        &self.quota
+    }
+
+    /// Compress document and snapshot data with the codecs of `policy`.
+    ///
+    /// Data is compressed before it's encrypted. The codec is recorded with
+    /// each row, so rows written with another codec, or uncompressed, stay
+    /// readable. Streaming saves and loads of compressed documents buffer
+    /// the document, and field indexes and field filters are not available
+    /// on compressed namespaces. [`init`](StorageAdapter::init) fails if the
+    /// policy names a codec that wasn't compiled in.
+    pub fn with_compression(mut self, policy: CompressionPolicy) -> Self {
+        self.compression = Arc::new(policy);
+        self
+    }
+
+    /// The codecs of document and snapshot data.
+    pub fn compression(&self) -> &CompressionPolicy {
+        &self.compression
+    }
+
+    /// Whether field indexes and filters can be used on `namespace`.
+    fn check_fields_available(&self, namespace: &str, what: &str) -> Result<()> {
+        if self.cipher.is_some() {
+            return Err(StorageError::Unsupported(format!(
+                "{} are not available on an encrypted database",
+                what
+            )));
+        }
+        if self.compression.codec_for(namespace) != Codec::None {
+            return Err(StorageError::Unsupported(format!(
+                "{} are not available on compressed namespace '{}'",
+                what, namespace
+            )));
+        }
+        Ok(())
+    }
+
+    /// Compress and seal document or snapshot data for storage.
+    fn encode(&self, namespace: &str, location: &[&[u8]], data: Vec<u8>) -> Result<Encoded> {
+        encode(
+            self.cipher.as_deref(),
+            &self.compression,
+            namespace,
+            location,
+            data,
+        )
+    }
+
+    /// Open and decompress stored document or snapshot data.
+    fn decode(&self, location: &[&[u8]], codec: i64, data: Vec<u8>) -> Result<Vec<u8>> {
+        decode(self.cipher.as_deref(), location, codec, data)
+    }
+
+    /// Seal a value for storage, if encryption is enabled.
+    fn seal(&self, location: &[&[u8]], data: Vec<u8>) -> Result<Vec<u8>> {
+        match &self.cipher {
+            Some(cipher) => cipher.seal(&aad(location), &data),
+            None => Ok(data),
+        }
+    }
+
+    /// Open a stored value, if encryption is enabled.
+    fn open(&self, location: &[&[u8]], data: Vec<u8>) -> Result<Vec<u8>> {
+        match &self.cipher {
+            Some(cipher) => cipher.open(&aad(location), &data),
+            None => Ok(data),
+        }
+    }
+
+    /// Load a document whole and write it to `writer`.
+    async fn load_buffered(
+        &self,
+        namespace: &str,
+        id: &str,
+        writer: &mut (dyn AsyncWrite + Unpin + Send),
+    ) -> Result<Option<u64>> {
+        let Some(data) = self.load(namespace, id).await? else {
+            return Ok(None);
+        };
+        writer.write_all(&data).await?;
+        writer.flush().await?;
+        Ok(Some(data.len() as u64))
+    }
+
     /// Execute a query in a blocking task.
     async fn execute<F, T>(&self, f: F) -> Result<T>
     where
@@ -107,13 +264,20 @@ impl SqliteAdapter {
 #[async_trait]
 impl StorageAdapter for SqliteAdapter {
     async fn init(&self) -> Result<()> {
-        self.execute(|conn| {
+        self.compression.validate()?;
+        let cipher = self.cipher.clone();
+
+        self.execute(move |conn| {
             // Documents table
             conn.execute(
                 "CREATE TABLE IF NOT EXISTS documents (
                     namespace TEXT NOT NULL,
                     id TEXT NOT NULL,
                     data BLOB NOT NULL,
+                    checksum BLOB,
+                    codec INTEGER NOT NULL DEFAULT 0,
+                    size INTEGER,
+                    created_at INTEGER NOT NULL,
                     updated_at INTEGER NOT NULL,
                     PRIMARY KEY (namespace, id)
                 )",
@@ -121,6 +285,23 @@ impl StorageAdapter for SqliteAdapter {
             )
             .map_err(|e| StorageError::Database(e.to_string()))?;
 
+            // Databases created before created_at was tracked
+            let has_created_at: bool = conn
+                .query_row(
+                    "SELECT COUNT(*) > 0 FROM pragma_table_info('documents')
+                     WHERE name = 'created_at'",
+                    [],
+                    |row| row.get(0),
+                )
+                .map_err(|e| StorageError::Database(e.to_string()))?;
+            if !has_created_at {
+                conn.execute_batch(
+                    "ALTER TABLE documents ADD COLUMN created_at INTEGER NOT NULL DEFAULT 0;
+                     UPDATE documents SET created_at = updated_at;",
+                )
+                .map_err(|e| StorageError::Database(e.to_string()))?;
+            }
+
             // Create index on updated_at for time-based queries
             conn.execute(
                 "CREATE INDEX IF NOT EXISTS idx_documents_updated
@@ -129,6 +310,14 @@ impl StorageAdapter for SqliteAdapter {
             )
             .map_err(|e| StorageError::Database(e.to_string()))?;
 
+            // Create index on created_at for creation-time range queries
+            conn.execute(
+                "CREATE INDEX IF NOT EXISTS idx_documents_created
+                 ON documents(namespace, created_at, id)",
+                [],
+            )
+            .map_err(|e| StorageError::Database(e.to_string()))?;
+
             // Operations table
             conn.execute(
                 "CREATE TABLE IF NOT EXISTS operations (
@@ -155,6 +344,9 @@ impl StorageAdapter for SqliteAdapter {
                     id TEXT NOT NULL,
                     version INTEGER NOT NULL,
                     data BLOB NOT NULL,
+                    checksum BLOB,
+                    codec INTEGER NOT NULL DEFAULT 0,
+                    size INTEGER,
                     created_at INTEGER NOT NULL,
                     PRIMARY KEY (namespace, id, version)
                 )",
@@ -162,15 +354,142 @@ impl StorageAdapter for SqliteAdapter {
             )
             .map_err(|e| StorageError::Database(e.to_string()))?;
 
+            // Databases created before checksums were kept (`verify` fills
+            // in the missing checksums) or data was compressed. Rows without
+            // a size are uncompressed
+            let columns = [
+                ("checksum", "BLOB"),
+                ("codec", "INTEGER NOT NULL DEFAULT 0"),
+                ("size", "INTEGER"),
+            ];
+            for table in ["documents", "snapshots"] {
+                for (column, definition) in columns {
+                    let has_column: bool = conn
+                        .query_row(
+                            &format!(
+                                "SELECT COUNT(*) > 0 FROM pragma_table_info('{}')
+                                 WHERE name = '{}'",
+                                table, column
+                            ),
+                            [],
+                            |row| row.get(0),
+                        )
+                        .map_err(|e| StorageError::Database(e.to_string()))?;
+                    if !has_column {
+                        conn.execute(
+                            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+                            [],
+                        )
+                        .map_err(|e| StorageError::Database(e.to_string()))?;
+                    }
+                }
+            }
+
+            // Deletion timestamps of deleted documents
+            conn.execute(
+                "CREATE TABLE IF NOT EXISTS tombstones (
+                    namespace TEXT NOT NULL,
+                    id TEXT NOT NULL,
+                    deleted_at INTEGER NOT NULL,
+                    PRIMARY KEY (namespace, id)
+                )",
+                [],
+            )
+            .map_err(|e| StorageError::Database(e.to_string()))?;
+
+            // Secondary index definitions over JSON document fields
+            conn.execute(
+                "CREATE TABLE IF NOT EXISTS document_indexes (
+                    namespace TEXT NOT NULL,
+                    json_path TEXT NOT NULL,
+                    PRIMARY KEY (namespace, json_path)
+                )",
+                [],
+            )
+            .map_err(|e| StorageError::Database(e.to_string()))?;
+
+            // Content-addressed blobs; size is the unencrypted size
+            conn.execute(
+                "CREATE TABLE IF NOT EXISTS blobs (
+                    hash TEXT PRIMARY KEY,
+                    data BLOB NOT NULL,
+                    size INTEGER NOT NULL,
+                    refcount INTEGER NOT NULL
+                )",
+                [],
+            )
+            .map_err(|e| StorageError::Database(e.to_string()))?;
+
+            // Adapter metadata, e.g. the encryption key check
+            conn.execute(
+                "CREATE TABLE IF NOT EXISTS storage_meta (
+                    key TEXT PRIMARY KEY,
+                    value BLOB NOT NULL
+                )",
+                [],
+            )
+            .map_err(|e| StorageError::Database(e.to_string()))?;
+
+            let key_check: Option<Vec<u8>> = conn
+                .query_row(
+                    "SELECT value FROM storage_meta WHERE key = ?1",
+                    params![KEY_CHECK],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| StorageError::Database(e.to_string()))?;
+            let check_aad = aad(&[b"storage_meta", KEY_CHECK.as_bytes()]);
+
+            match (cipher, key_check) {
+                (Some(cipher), Some(key_check)) => {
+                    cipher.open(&check_aad, &key_check).map_err(|_| {
+                        StorageError::Encryption("Wrong encryption key for database".to_string())
+                    })?;
+                }
+                (Some(cipher), None) => {
+                    let has_data: bool = conn
+                        .query_row(
+                            "SELECT EXISTS (SELECT 1 FROM documents)
+                                 OR EXISTS (SELECT 1 FROM operations)
+                                 OR EXISTS (SELECT 1 FROM snapshots)",
+                            [],
+                            |row| row.get(0),
+                        )
+                        .map_err(|e| StorageError::Database(e.to_string()))?;
+                    if has_data {
+                        return Err(StorageError::Encryption(
+                            "Database already contains unencrypted data".to_string(),
+                        ));
+                    }
+                    conn.execute(
+                        "INSERT INTO storage_meta (key, value) VALUES (?1, ?2)",
+                        params![KEY_CHECK, cipher.seal(&check_aad, &[])?],
+                    )
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+                }
+                (None, Some(_)) => {
+                    return Err(StorageError::Encryption(
+                        "Database is encrypted; an encryption key is required".to_string(),
+                    ))
+                }
+                (None, None) => {}
+            }
+
             Ok(())
         })
         .await
     }
 
     async fn save(&self, namespace: &str, id: &str, data: Bytes) -> Result<()> {
+        let encoded = self.encode(
+            namespace,
+            &[b"documents", namespace.as_bytes(), id.as_bytes()],
+            data.to_vec(),
+        )?;
         let namespace = namespace.to_string();
         let id = id.to_string();
-        let data_vec = data.to_vec();
+        let quota = Arc::clone(&self.quota);
+        let hook = self.eviction_hook.clone();
 
         self.execute(move |conn| {
             let timestamp = std::time::SystemTime::now()
@@ -178,35 +497,314 @@ impl StorageAdapter for SqliteAdapter {
                 .unwrap()
                 .as_millis() as i64;
 
-            conn.execute(
-                "INSERT OR REPLACE INTO documents (namespace, id, data, updated_at)
-                 VALUES (?1, ?2, ?3, ?4)",
-                params![namespace, id, data_vec, timestamp],
+            let tx = Atomic::begin(conn)?;
+            check_quota(
+                &tx,
+                &quota,
+                hook.as_deref(),
+                &[(&namespace, &id, encoded.data.len() as u64)],
+            )?;
+            tx.execute(
+                "INSERT INTO documents
+                     (namespace, id, data, checksum, codec, size, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)
+                 ON CONFLICT (namespace, id)
+                 DO UPDATE SET data = excluded.data, checksum = excluded.checksum,
+                               codec = excluded.codec, size = excluded.size,
+                               updated_at = excluded.updated_at",
+                params![
+                    namespace,
+                    id,
+                    encoded.data,
+                    checksum(&encoded.data),
+                    encoded.codec,
+                    encoded.size,
+                    timestamp
+                ],
+            )
+            .map_err(|e| StorageError::Database(e.to_string()))?;
+            tx.execute(
+                "DELETE FROM tombstones WHERE namespace = ?1 AND id = ?2",
+                params![namespace, id],
             )
             .map_err(|e| StorageError::Database(e.to_string()))?;
 
-            Ok(())
+            tx.commit()
+        })
+        .await
+    }
+
+    async fn save_batch(&self, docs: &[(&str, &str, Bytes)]) -> Result<()> {
+        let rows = docs
+            .iter()
+            .map(|(namespace, id, data)| {
+                let encoded = self.encode(
+                    namespace,
+                    &[b"documents", namespace.as_bytes(), id.as_bytes()],
+                    data.to_vec(),
+                )?;
+                Ok((namespace.to_string(), id.to_string(), encoded))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let quota = Arc::clone(&self.quota);
+        let hook = self.eviction_hook.clone();
+
+        self.execute(move |conn| {
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as i64;
+
+            let tx = Atomic::begin(conn)?;
+            let writes: Vec<_> = rows
+                .iter()
+                .map(|(namespace, id, encoded)| {
+                    (namespace.as_str(), id.as_str(), encoded.data.len() as u64)
+                })
+                .collect();
+            check_quota(&tx, &quota, hook.as_deref(), &writes)?;
+            {
+                let mut upsert = tx
+                    .prepare_cached(
+                        "INSERT INTO documents
+                             (namespace, id, data, checksum, codec, size, created_at, updated_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)
+                         ON CONFLICT (namespace, id)
+                         DO UPDATE SET data = excluded.data, checksum = excluded.checksum,
+                                       codec = excluded.codec, size = excluded.size,
+                                       updated_at = excluded.updated_at",
+                    )
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+                let mut untombstone = tx
+                    .prepare_cached("DELETE FROM tombstones WHERE namespace = ?1 AND id = ?2")
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+
+                for (namespace, id, encoded) in &rows {
+                    upsert
+                        .execute(params![
+                            namespace,
+                            id,
+                            encoded.data,
+                            checksum(&encoded.data),
+                            encoded.codec,
+                            encoded.size,
+                            timestamp
+                        ])
+                        .map_err(|e| StorageError::Database(e.to_string()))?;
+                    untombstone
+                        .execute(params![namespace, id])
+                        .map_err(|e| StorageError::Database(e.to_string()))?;
+                }
+            }
+            tx.commit()
         })
         .await
     }
 
     async fn load(&self, namespace: &str, id: &str) -> Result<Option<Bytes>> {
+        let location = [b"documents".as_slice(), namespace.as_bytes(), id.as_bytes()];
+        let namespace = namespace.to_string();
+        let id = id.to_string();
+
+        let result: Option<(Vec<u8>, i64)> = self
+            .execute(move |conn| {
+                conn.query_row(
+                    "SELECT data, codec FROM documents WHERE namespace = ?1 AND id = ?2",
+                    params![namespace, id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()
+                .map_err(|e| StorageError::Database(e.to_string()))
+            })
+            .await?;
+
+        result
+            .map(|(data, codec)| self.decode(&location, codec, data).map(Bytes::from))
+            .transpose()
+    }
+
+    async fn save_stream(
+        &self,
+        namespace: &str,
+        id: &str,
+        reader: &mut (dyn AsyncRead + Unpin + Send),
+    ) -> Result<u64> {
+        // Values are sealed and compressed whole, so encrypted and
+        // compressed documents are buffered
+        if self.cipher.is_some() || self.compression.codec_for(namespace) != Codec::None {
+            let mut data = Vec::new();
+            let len = reader.read_to_end(&mut data).await? as u64;
+            self.save(namespace, id, Bytes::from(data)).await?;
+            return Ok(len);
+        }
+
+        // Spool to a temporary file: incremental BLOB I/O needs the length
+        // up front, and the document is never buffered whole
+        let mut spool = tokio::fs::File::from_std(tempfile::tempfile()?);
+        let len = tokio::io::copy(reader, &mut spool).await?;
+        spool.flush().await?;
+        let mut spool = spool.into_std().await;
+
+        let size = i32::try_from(len).map_err(|_| {
+            StorageError::InvalidOperation(format!("Document too large for SQLite: {} bytes", len))
+        })?;
         let namespace = namespace.to_string();
         let id = id.to_string();
+        let quota = Arc::clone(&self.quota);
+        let hook = self.eviction_hook.clone();
 
         self.execute(move |conn| {
-            let result: Option<Vec<u8>> = conn
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as i64;
+            spool.seek(SeekFrom::Start(0))?;
+
+            let tx = Atomic::begin(conn)?;
+            check_quota(&tx, &quota, hook.as_deref(), &[(&namespace, &id, len)])?;
+            let upsert = "INSERT INTO documents
+                     (namespace, id, data, checksum, codec, size, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, 0, ?5, ?6, ?6)
+                 ON CONFLICT (namespace, id)
+                 DO UPDATE SET data = excluded.data, checksum = excluded.checksum,
+                               codec = excluded.codec, size = excluded.size,
+                               updated_at = excluded.updated_at";
+
+            // SQLite refuses incremental writes to a column covered by an
+            // index, which field indexes are
+            let indexed: bool = tx
                 .query_row(
-                    "SELECT data FROM documents WHERE namespace = ?1 AND id = ?2",
-                    params![namespace, id],
+                    "SELECT EXISTS (SELECT 1 FROM document_indexes)",
+                    [],
                     |row| row.get(0),
                 )
-                .optional()
                 .map_err(|e| StorageError::Database(e.to_string()))?;
 
-            Ok(result.map(Bytes::from))
+            if indexed {
+                let mut data = Vec::with_capacity(len as usize);
+                spool.read_to_end(&mut data)?;
+                tx.execute(
+                    upsert,
+                    params![namespace, id, data, checksum(&data), len as i64, timestamp],
+                )
+                .map_err(|e| StorageError::Database(e.to_string()))?;
+            } else {
+                let sum: Option<Vec<u8>> = None;
+                tx.execute(
+                    upsert,
+                    params![namespace, id, ZeroBlob(size), sum, len as i64, timestamp],
+                )
+                .map_err(|e| StorageError::Database(e.to_string()))?;
+                let rowid: i64 = tx
+                    .query_row(
+                        "SELECT rowid FROM documents WHERE namespace = ?1 AND id = ?2",
+                        params![namespace, id],
+                        |row| row.get(0),
+                    )
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+                let mut blob = tx
+                    .blob_open(DatabaseName::Main, "documents", "data", rowid, false)
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+                let mut hasher = blake3::Hasher::new();
+                let mut chunk = vec![0u8; STREAM_CHUNK_SIZE];
+                loop {
+                    let n = spool.read(&mut chunk)?;
+                    if n == 0 {
+                        break;
+                    }
+                    hasher.update(&chunk[..n]);
+                    blob.write_all(&chunk[..n])?;
+                }
+                drop(blob);
+                tx.execute(
+                    "UPDATE documents SET checksum = ?1 WHERE rowid = ?2",
+                    params![hasher.finalize().as_bytes().to_vec(), rowid],
+                )
+                .map_err(|e| StorageError::Database(e.to_string()))?;
+            }
+            tx.execute(
+                "DELETE FROM tombstones WHERE namespace = ?1 AND id = ?2",
+                params![namespace, id],
+            )
+            .map_err(|e| StorageError::Database(e.to_string()))?;
+
+            tx.commit()?;
+            Ok(())
         })
-        .await
+        .await?;
+
+        Ok(len)
+    }
+
+    async fn load_stream(
+        &self,
+        namespace: &str,
+        id: &str,
+        writer: &mut (dyn AsyncWrite + Unpin + Send),
+    ) -> Result<Option<u64>> {
+        if self.cipher.is_some() {
+            return self.load_buffered(namespace, id, writer).await;
+        }
+
+        let key = (namespace.to_string(), id.to_string());
+        let row: Option<(i64, i64, i64, i64)> = self
+            .execute(move |conn| {
+                conn.query_row(
+                    "SELECT rowid, updated_at, length(data), codec FROM documents
+                     WHERE namespace = ?1 AND id = ?2",
+                    params![key.0, key.1],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+                )
+                .optional()
+                .map_err(|e| StorageError::Database(e.to_string()))
+            })
+            .await?;
+        let Some((rowid, updated_at, len, codec)) = row else {
+            return Ok(None);
+        };
+        // Compressed documents are decompressed whole
+        if codec != i64::from(Codec::None.id()) {
+            return self.load_buffered(namespace, id, writer).await;
+        }
+        let namespace = namespace.to_string();
+        let id = id.to_string();
+
+        // Read a chunk at a time, releasing the connection in between
+        let len = len as usize;
+        let mut offset = 0;
+        while offset < len {
+            let (namespace, id) = (namespace.clone(), id.clone());
+            let chunk = self
+                .execute(move |conn| {
+                    let current: Option<(i64, i64)> = conn
+                        .query_row(
+                            "SELECT updated_at, length(data) FROM documents
+                             WHERE rowid = ?1 AND namespace = ?2 AND id = ?3",
+                            params![rowid, namespace, id],
+                            |row| Ok((row.get(0)?, row.get(1)?)),
+                        )
+                        .optional()
+                        .map_err(|e| StorageError::Database(e.to_string()))?;
+                    if current != Some((updated_at, len as i64)) {
+                        return Err(StorageError::ConcurrentModification);
+                    }
+
+                    let blob = conn
+                        .blob_open(DatabaseName::Main, "documents", "data", rowid, true)
+                        .map_err(|e| StorageError::Database(e.to_string()))?;
+                    let mut chunk = vec![0u8; STREAM_CHUNK_SIZE.min(len - offset)];
+                    blob.read_at_exact(&mut chunk, offset)
+                        .map_err(|e| StorageError::Database(e.to_string()))?;
+                    Ok(chunk)
+                })
+                .await?;
+
+            writer.write_all(&chunk).await?;
+            offset += chunk.len();
+        }
+        writer.flush().await?;
+
+        Ok(Some(len as u64))
     }
 
     async fn delete(&self, namespace: &str, id: &str) -> Result<()> {
@@ -214,11 +812,14 @@ impl StorageAdapter for SqliteAdapter {
         let id = id.to_string();
 
         self.execute(move |conn| {
-            conn.execute(
-                "DELETE FROM documents WHERE namespace = ?1 AND id = ?2",
-                params![namespace, id],
-            )
-            .map_err(|e| StorageError::Database(e.to_string()))?;
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as i64;
+
+            let tx = Atomic::begin(conn)?;
+            delete_document(&tx, &namespace, &id, timestamp)?;
+            tx.commit()?;
 
             Ok(())
         })
@@ -244,61 +845,87 @@ impl StorageAdapter for SqliteAdapter {
         .await
     }
 
-    async fn save_operations(&self, ops: &[Operation]) -> Result<()> {
-        let ops_json = serde_json::to_vec(ops)
-            .map_err(|e| StorageError::Serialization(e.to_string()))?;
+    async fn list_namespaces(&self) -> Result<Vec<String>> {
+        self.execute(|conn| {
+            let mut stmt = conn
+                .prepare("SELECT DISTINCT namespace FROM documents ORDER BY namespace")
+                .map_err(|e| StorageError::Database(e.to_string()))?;
 
-        self.execute(move |conn| {
-            // Clear existing operations
-            conn.execute("DELETE FROM operations", [])
+            let namespaces = stmt
+                .query_map([], |row| row.get::<_, String>(0))
+                .map_err(|e| StorageError::Database(e.to_string()))?
+                .collect::<std::result::Result<Vec<_>, _>>()
                 .map_err(|e| StorageError::Database(e.to_string()))?;
 
-            // Deserialize and insert each operation
-            let operations: Vec<Operation> = serde_json::from_slice(&ops_json)
-                .map_err(|e| StorageError::Serialization(e.to_string()))?;
+            Ok(namespaces)
+        })
+        .await
+    }
 
-            for op in operations {
-                let op_json = serde_json::to_vec(&op)
+    async fn save_operations(&self, ops: &[Operation]) -> Result<()> {
+        let rows = ops
+            .iter()
+            .map(|op| {
+                let op_json = serde_json::to_vec(op)
                     .map_err(|e| StorageError::Serialization(e.to_string()))?;
+                let data = self.seal(&[b"operations", &op.id.to_le_bytes()], op_json)?;
+                Ok((op.id as i64, data, op.timestamp as i64))
+            })
+            .collect::<Result<Vec<_>>>()?;
 
-                conn.execute(
-                    "INSERT INTO operations (id, data, timestamp) VALUES (?1, ?2, ?3)",
-                    params![op.id as i64, op_json, op.timestamp as i64],
-                )
+        self.execute(move |conn| {
+            let tx = Atomic::begin(conn)?;
+
+            // Clear existing operations
+            tx.execute("DELETE FROM operations", [])
                 .map_err(|e| StorageError::Database(e.to_string()))?;
+
+            {
+                let mut insert = tx
+                    .prepare_cached(
+                        "INSERT INTO operations (id, data, timestamp) VALUES (?1, ?2, ?3)",
+                    )
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+                for (id, data, timestamp) in rows {
+                    insert
+                        .execute(params![id, data, timestamp])
+                        .map_err(|e| StorageError::Database(e.to_string()))?;
+                }
             }
 
-            Ok(())
+            tx.commit()
         })
         .await
     }
 
     async fn load_operations(&self) -> Result<Vec<Operation>> {
-        self.execute(|conn| {
-            let mut stmt = conn
-                .prepare("SELECT data FROM operations ORDER BY timestamp, id")
-                .map_err(|e| StorageError::Database(e.to_string()))?;
-
-            let ops = stmt
-                .query_map([], |row| {
-                    let data: Vec<u8> = row.get(0)?;
-                    Ok(data)
-                })
-                .map_err(|e| StorageError::Database(e.to_string()))?
-                .collect::<std::result::Result<Vec<_>, _>>()
-                .map_err(|e| StorageError::Database(e.to_string()))?;
-
-            let operations = ops
-                .into_iter()
-                .map(|data| {
-                    serde_json::from_slice(&data)
-                        .map_err(|e| StorageError::Serialization(e.to_string()))
-                })
-                .collect::<Result<Vec<_>>>()?;
+        let ops = self
+            .execute(|conn| {
+                let mut stmt = conn
+                    .prepare("SELECT id, data FROM operations ORDER BY timestamp, id")
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+
+                let ops = stmt
+                    .query_map([], |row| {
+                        let id: i64 = row.get(0)?;
+                        let data: Vec<u8> = row.get(1)?;
+                        Ok((id, data))
+                    })
+                    .map_err(|e| StorageError::Database(e.to_string()))?
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+
+                Ok(ops)
+            })
+            .await?;
 
-            Ok(operations)
-        })
-        .await
+        ops.into_iter()
+            .map(|(id, data)| {
+                let data = self.open(&[b"operations", &(id as u64).to_le_bytes()], data)?;
+                serde_json::from_slice(&data)
+                    .map_err(|e| StorageError::Serialization(e.to_string()))
+            })
+            .collect()
     }
 
     async fn save_snapshot(
@@ -308,9 +935,18 @@ impl StorageAdapter for SqliteAdapter {
         version: u64,
         data: Bytes,
     ) -> Result<()> {
+        let encoded = self.encode(
+            namespace,
+            &[
+                b"snapshots",
+                namespace.as_bytes(),
+                id.as_bytes(),
+                &version.to_le_bytes(),
+            ],
+            data.to_vec(),
+        )?;
         let namespace = namespace.to_string();
         let id = id.to_string();
-        let data_vec = data.to_vec();
 
         self.execute(move |conn| {
             let timestamp = std::time::SystemTime::now()
@@ -319,9 +955,19 @@ impl StorageAdapter for SqliteAdapter {
                 .as_millis() as i64;
 
             conn.execute(
-                "INSERT OR REPLACE INTO snapshots (namespace, id, version, data, created_at)
-                 VALUES (?1, ?2, ?3, ?4, ?5)",
-                params![namespace, id, version as i64, data_vec, timestamp],
+                "INSERT OR REPLACE INTO snapshots
+                     (namespace, id, version, data, checksum, codec, size, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    namespace,
+                    id,
+                    version as i64,
+                    encoded.data,
+                    checksum(&encoded.data),
+                    encoded.codec,
+                    encoded.size,
+                    timestamp
+                ],
             )
             .map_err(|e| StorageError::Database(e.to_string()))?;
 
@@ -331,56 +977,373 @@ impl StorageAdapter for SqliteAdapter {
     }
 
     async fn load_snapshot(&self, namespace: &str, id: &str) -> Result<Option<(u64, Bytes)>> {
+        let location = [b"snapshots".as_slice(), namespace.as_bytes(), id.as_bytes()];
         let namespace = namespace.to_string();
         let id = id.to_string();
 
-        self.execute(move |conn| {
-            let result: Option<(i64, Vec<u8>)> = conn
-                .query_row(
-                    "SELECT version, data FROM snapshots
+        let result: Option<(i64, Vec<u8>, i64)> = self
+            .execute(move |conn| {
+                conn.query_row(
+                    "SELECT version, data, codec FROM snapshots
                      WHERE namespace = ?1 AND id = ?2
                      ORDER BY version DESC LIMIT 1",
                     params![namespace, id],
-                    |row| Ok((row.get(0)?, row.get(1)?)),
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
                 )
                 .optional()
-                .map_err(|e| StorageError::Database(e.to_string()))?;
+                .map_err(|e| StorageError::Database(e.to_string()))
+            })
+            .await?;
+
+        result
+            .map(|(v, d, codec)| {
+                let version = (v as u64).to_le_bytes();
+                let location = [location[0], location[1], location[2], &version];
+                let data = self.decode(&location, codec, d)?;
+                Ok((v as u64, Bytes::from(data)))
+            })
+            .transpose()
+    }
+
+    async fn query(&self, namespace: &str, filter: QueryFilter) -> Result<Vec<(String, Bytes)>> {
+        let options = QueryOptions::default().sort_by(default_sort(&filter));
+        Ok(self.query_page(namespace, filter, options).await?.items)
+    }
+
+    async fn query_page(
+        &self,
+        namespace: &str,
+        filter: QueryFilter,
+        options: QueryOptions,
+    ) -> Result<QueryPage> {
+        if has_field_filter(&filter) {
+            self.check_fields_available(namespace, "Field filters")?;
+        }
+        let ns = namespace.to_string();
+
+        let (results, next_cursor) = self
+            .execute(move |conn| {
+                let (sql, params) = build_query_sql(&ns, &filter, &options)?;
+                let mut stmt = conn
+                    .prepare(&sql)
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+
+                let results = stmt
+                    .query_map(rusqlite::params_from_iter(params.iter()), |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, Vec<u8>>(1)?,
+                            row.get::<_, Option<i64>>(2)?,
+                            row.get::<_, i64>(3)?,
+                        ))
+                    })
+                    .map_err(|e| StorageError::Database(e.to_string()))?
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+
+                let next_cursor = match (options.limit, results.last()) {
+                    (Some(limit), Some((id, _, timestamp, _))) if results.len() == limit => {
+                        Some(Cursor {
+                            timestamp: timestamp.map(|t| t as u64),
+                            id: id.clone(),
+                        })
+                    }
+                    _ => None,
+                };
+
+                Ok((results, next_cursor))
+            })
+            .await?;
+
+        let items = results
+            .into_iter()
+            .map(|(id, data, _, codec)| {
+                let location = [b"documents".as_slice(), namespace.as_bytes(), id.as_bytes()];
+                let data = self.decode(&location, codec, data)?;
+                Ok((id, Bytes::from(data)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(QueryPage { items, next_cursor })
+    }
+
+    async fn ensure_index(&self, namespace: &str, json_path: &str) -> Result<()> {
+        let namespace = namespace.to_string();
+        let path = JsonPath::parse(json_path)?;
+        self.check_fields_available(&namespace, "Field indexes")?;
+
+        self.execute(move |conn| {
+            // The expression index is shared by every namespace indexing the
+            // path; the trailing id keeps the default id order index-only
+            conn.execute(
+                &format!(
+                    "CREATE INDEX IF NOT EXISTS {} ON documents(namespace, {}, id)",
+                    field_index_name(&path),
+                    field_expr(&path)
+                ),
+                [],
+            )
+            .map_err(|e| StorageError::Database(e.to_string()))?;
+
+            conn.execute(
+                "INSERT OR IGNORE INTO document_indexes (namespace, json_path) VALUES (?1, ?2)",
+                params![namespace, path.to_string()],
+            )
+            .map_err(|e| StorageError::Database(e.to_string()))?;
 
-            Ok(result.map(|(v, d)| (v as u64, Bytes::from(d))))
+            Ok(())
         })
         .await
     }
 
-    async fn query(&self, namespace: &str, filter: QueryFilter) -> Result<Vec<(String, Bytes)>> {
+    async fn list_indexes(&self, namespace: &str) -> Result<Vec<String>> {
         let namespace = namespace.to_string();
 
         self.execute(move |conn| {
-            let (sql, params) = build_query_sql(&namespace, &filter)?;
             let mut stmt = conn
-                .prepare(&sql)
+                .prepare(
+                    "SELECT json_path FROM document_indexes
+                     WHERE namespace = ?1 ORDER BY json_path",
+                )
                 .map_err(|e| StorageError::Database(e.to_string()))?;
 
-            let results = stmt
-                .query_map(rusqlite::params_from_iter(params.iter()), |row| {
-                    Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?))
-                })
+            let paths = stmt
+                .query_map(params![namespace], |row| row.get::<_, String>(0))
                 .map_err(|e| StorageError::Database(e.to_string()))?
                 .collect::<std::result::Result<Vec<_>, _>>()
                 .map_err(|e| StorageError::Database(e.to_string()))?;
 
-            Ok(results
-                .into_iter()
-                .map(|(id, data)| (id, Bytes::from(data)))
-                .collect())
+            Ok(paths)
         })
         .await
     }
 
-    async fn stats(&self) -> Result<StorageStats> {
+    async fn begin(&self) -> Result<()> {
         self.execute(|conn| {
-            let document_count: i64 = conn
-                .query_row("SELECT COUNT(*) FROM documents", [], |row| row.get(0))
-                .map_err(|e| StorageError::Database(e.to_string()))?;
+            if !conn.is_autocommit() {
+                return Err(StorageError::InvalidOperation(
+                    "Transaction already in progress".to_string(),
+                ));
+            }
+            // Take the write lock up front so writes in the transaction
+            // can't fail with SQLITE_BUSY
+            conn.execute_batch("BEGIN IMMEDIATE")
+                .map_err(|e| StorageError::Database(e.to_string()))
+        })
+        .await
+    }
+
+    async fn commit(&self) -> Result<()> {
+        self.execute(|conn| {
+            if conn.is_autocommit() {
+                return Err(StorageError::InvalidOperation(
+                    "No transaction in progress".to_string(),
+                ));
+            }
+            conn.execute_batch("COMMIT")
+                .map_err(|e| StorageError::Database(e.to_string()))
+        })
+        .await
+    }
+
+    async fn rollback(&self) -> Result<()> {
+        self.execute(|conn| {
+            if conn.is_autocommit() {
+                return Err(StorageError::InvalidOperation(
+                    "No transaction in progress".to_string(),
+                ));
+            }
+            conn.execute_batch("ROLLBACK")
+                .map_err(|e| StorageError::Database(e.to_string()))
+        })
+        .await
+    }
+
+    async fn maintenance(&self, options: MaintenanceOptions) -> Result<MaintenanceReport> {
+        let path = self.path.clone();
+
+        self.execute(move |conn| {
+            if (options.vacuum || options.checkpoint) && !conn.is_autocommit() {
+                return Err(StorageError::InvalidOperation(
+                    "Vacuum and checkpoint can't run inside a transaction".to_string(),
+                ));
+            }
+
+            let size_before = database_size(conn, &path)?;
+            let mut report = MaintenanceReport::default();
+
+            let tx = Atomic::begin(conn)?;
+
+            if let Some(max) = options.max_snapshots_per_doc {
+                // Snapshots beyond the newest `max` versions of each document
+                let excess = "SELECT rowid FROM (
+                        SELECT rowid, ROW_NUMBER() OVER (
+                            PARTITION BY namespace, id ORDER BY version DESC
+                        ) AS rank
+                        FROM snapshots
+                    ) WHERE rank > ?1";
+                report.bytes_freed +=
+                    tx.query_row(
+                        &format!(
+                            "SELECT COALESCE(SUM(LENGTH(data)), 0) FROM snapshots
+                             WHERE rowid IN ({})",
+                            excess
+                        ),
+                        params![max as i64],
+                        |row| row.get::<_, i64>(0),
+                    )
+                    .map_err(|e| StorageError::Database(e.to_string()))? as u64;
+                report.snapshots_pruned = tx
+                    .execute(
+                        &format!("DELETE FROM snapshots WHERE rowid IN ({})", excess),
+                        params![max as i64],
+                    )
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+            }
+
+            if let Some(retention) = options.tombstone_retention {
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap()
+                    .as_millis() as i64;
+                let cutoff = now.saturating_sub(retention.as_millis() as i64);
+
+                // Snapshots left behind by documents deleted before the cutoff
+                let expired = "EXISTS (
+                        SELECT 1 FROM tombstones t
+                        WHERE t.namespace = snapshots.namespace AND t.id = snapshots.id
+                          AND t.deleted_at <= ?1
+                    )";
+                report.bytes_freed +=
+                    tx.query_row(
+                        &format!(
+                            "SELECT COALESCE(SUM(LENGTH(data)), 0) FROM snapshots WHERE {}",
+                            expired
+                        ),
+                        params![cutoff],
+                        |row| row.get::<_, i64>(0),
+                    )
+                    .map_err(|e| StorageError::Database(e.to_string()))? as u64;
+                report.tombstone_snapshots_purged = tx
+                    .execute(
+                        &format!("DELETE FROM snapshots WHERE {}", expired),
+                        params![cutoff],
+                    )
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+                report.tombstones_purged = tx
+                    .execute(
+                        "DELETE FROM tombstones WHERE deleted_at <= ?1",
+                        params![cutoff],
+                    )
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+            }
+
+            tx.commit()?;
+
+            // VACUUM can't run inside a transaction
+            if options.vacuum {
+                conn.execute_batch("VACUUM")
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+            }
+            if options.checkpoint {
+                conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+            }
+
+            report.bytes_reclaimed = size_before.saturating_sub(database_size(conn, &path)?);
+            Ok(report)
+        })
+        .await
+    }
+
+    /// Every document and snapshot row carries a BLAKE3 checksum of its
+    /// stored bytes. Corrupt snapshots are removed, so that documents are
+    /// only rebuilt from good ones and `load_snapshot` falls back to the
+    /// previous version; they are reported as unrecoverable. A corrupt
+    /// document is rebuilt from its latest snapshot with the change bytes of
+    /// the queued `Update` operations recorded since appended, which
+    /// Automerge applies when it loads the document (changes the snapshot
+    /// already has are ignored). Documents without a snapshot are left as
+    /// they are and reported as unrecoverable.
+    async fn verify(&self) -> Result<IntegrityReport> {
+        let cipher = self.cipher.clone();
+        let compression = Arc::clone(&self.compression);
+
+        self.execute(move |conn| {
+            let tx = Atomic::begin(conn)?;
+            let mut report = IntegrityReport::default();
+
+            let snapshots = check_rows(
+                &tx,
+                "SELECT rowid, namespace, id, version, data, checksum FROM snapshots",
+                |row| {
+                    Ok(IntegrityItem::Snapshot {
+                        namespace: row.get(1)?,
+                        id: row.get(2)?,
+                        version: row.get::<_, i64>(3)? as u64,
+                    })
+                },
+            )?;
+            report.snapshots_checked = snapshots.checked;
+            report.checksums_added += add_checksums(&tx, "snapshots", &snapshots.unchecked)?;
+            for (rowid, item) in snapshots.corrupt {
+                tx.execute("DELETE FROM snapshots WHERE rowid = ?1", params![rowid])
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+                report.unrecoverable.push(item);
+            }
+
+            let documents = check_rows(
+                &tx,
+                "SELECT rowid, namespace, id, NULL, data, checksum FROM documents",
+                |row| {
+                    Ok(IntegrityItem::Document {
+                        namespace: row.get(1)?,
+                        id: row.get(2)?,
+                    })
+                },
+            )?;
+            report.documents_checked = documents.checked;
+            report.checksums_added += add_checksums(&tx, "documents", &documents.unchecked)?;
+            if !documents.corrupt.is_empty() {
+                let ops = queued_operations(&tx, cipher.as_deref())?;
+                for (rowid, item) in documents.corrupt {
+                    let IntegrityItem::Document { namespace, id } = &item else {
+                        continue;
+                    };
+                    let rebuilt =
+                        rebuild_document(&tx, cipher.as_deref(), &compression, namespace, id, &ops)?;
+                    match rebuilt {
+                        Some(encoded) => {
+                            tx.execute(
+                                "UPDATE documents SET data = ?1, checksum = ?2, codec = ?3, size = ?4
+                                 WHERE rowid = ?5",
+                                params![
+                                    encoded.data,
+                                    checksum(&encoded.data),
+                                    encoded.codec,
+                                    encoded.size,
+                                    rowid
+                                ],
+                            )
+                            .map_err(|e| StorageError::Database(e.to_string()))?;
+                            report.repaired.push(item);
+                        }
+                        None => report.unrecoverable.push(item),
+                    }
+                }
+            }
+
+            tx.commit()?;
+            Ok(report)
+        })
+        .await
+    }
+
+    async fn stats(&self) -> Result<StorageStats> {
+        self.execute(|conn| {
+            let document_count: i64 = conn
+                .query_row("SELECT COUNT(*) FROM documents", [], |row| row.get(0))
+                .map_err(|e| StorageError::Database(e.to_string()))?;
 
             let total_document_size: i64 = conn
                 .query_row(
@@ -406,12 +1369,36 @@ impl StorageAdapter for SqliteAdapter {
                 )
                 .map_err(|e| StorageError::Database(e.to_string()))?;
 
+            let tombstone_count: i64 = conn
+                .query_row("SELECT COUNT(*) FROM tombstones", [], |row| row.get(0))
+                .map_err(|e| StorageError::Database(e.to_string()))?;
+
+            // Rows written before sizes were kept are uncompressed
+            let uncompressed_document_size: i64 = conn
+                .query_row(
+                    "SELECT COALESCE(SUM(COALESCE(size, LENGTH(data))), 0) FROM documents",
+                    [],
+                    |row| row.get(0),
+                )
+                .map_err(|e| StorageError::Database(e.to_string()))?;
+
+            let uncompressed_snapshot_size: i64 = conn
+                .query_row(
+                    "SELECT COALESCE(SUM(COALESCE(size, LENGTH(data))), 0) FROM snapshots",
+                    [],
+                    |row| row.get(0),
+                )
+                .map_err(|e| StorageError::Database(e.to_string()))?;
+
             Ok(StorageStats {
                 document_count: document_count as usize,
                 total_document_size: total_document_size as usize,
                 operation_count: operation_count as usize,
                 snapshot_count: snapshot_count as usize,
                 total_snapshot_size: total_snapshot_size as usize,
+                tombstone_count: tombstone_count as usize,
+                uncompressed_document_size: uncompressed_document_size as usize,
+                uncompressed_snapshot_size: uncompressed_snapshot_size as usize,
             })
         })
         .await
@@ -425,50 +1412,757 @@ impl StorageAdapter for SqliteAdapter {
                 .map_err(|e| StorageError::Database(e.to_string()))?;
             conn.execute("DELETE FROM snapshots", [])
                 .map_err(|e| StorageError::Database(e.to_string()))?;
+            conn.execute("DELETE FROM tombstones", [])
+                .map_err(|e| StorageError::Database(e.to_string()))?;
+            conn.execute("DELETE FROM blobs", [])
+                .map_err(|e| StorageError::Database(e.to_string()))?;
+
+            let mut stmt = conn
+                .prepare("SELECT DISTINCT json_path FROM document_indexes")
+                .map_err(|e| StorageError::Database(e.to_string()))?;
+            let paths = stmt
+                .query_map([], |row| row.get::<_, String>(0))
+                .map_err(|e| StorageError::Database(e.to_string()))?
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|e| StorageError::Database(e.to_string()))?;
+            for path in paths {
+                let path = JsonPath::parse(&path)?;
+                conn.execute(
+                    &format!("DROP INDEX IF EXISTS {}", field_index_name(&path)),
+                    [],
+                )
+                .map_err(|e| StorageError::Database(e.to_string()))?;
+            }
+            conn.execute("DELETE FROM document_indexes", [])
+                .map_err(|e| StorageError::Database(e.to_string()))?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// Blobs are sealed like documents when encryption is enabled; the content
+/// hash is computed over the unencrypted data.
+#[async_trait]
+impl BlobStore for SqliteAdapter {
+    async fn put_blob(&self, data: Bytes) -> Result<BlobHash> {
+        let hash = BlobHash::of(&data);
+        let key = hash.to_hex();
+        let size = data.len() as i64;
+        let sealed = self.seal(&[b"blobs", hash.as_bytes()], data.to_vec())?;
+
+        self.execute(move |conn| {
+            conn.execute(
+                "INSERT INTO blobs (hash, data, size, refcount) VALUES (?1, ?2, ?3, 1)
+                 ON CONFLICT (hash) DO UPDATE SET refcount = refcount + 1",
+                params![key, sealed, size],
+            )
+            .map_err(|e| StorageError::Database(e.to_string()))?;
             Ok(())
         })
+        .await?;
+        Ok(hash)
+    }
+
+    async fn get_blob(&self, hash: &BlobHash) -> Result<Option<Bytes>> {
+        let key = hash.to_hex();
+        let result: Option<Vec<u8>> = self
+            .execute(move |conn| {
+                conn.query_row(
+                    "SELECT data FROM blobs WHERE hash = ?1",
+                    params![key],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| StorageError::Database(e.to_string()))
+            })
+            .await?;
+
+        result
+            .map(|data| {
+                let data = self.open(&[b"blobs", hash.as_bytes()], data)?;
+                hash.verify(&data)?;
+                Ok(Bytes::from(data))
+            })
+            .transpose()
+    }
+
+    async fn blob_info(&self, hash: &BlobHash) -> Result<Option<BlobInfo>> {
+        let hash = *hash;
+        let key = hash.to_hex();
+        self.execute(move |conn| {
+            conn.query_row(
+                "SELECT size, refcount FROM blobs WHERE hash = ?1",
+                params![key],
+                |row| {
+                    Ok(BlobInfo {
+                        hash,
+                        size: row.get::<_, i64>(0)? as u64,
+                        refcount: row.get::<_, i64>(1)? as u64,
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| StorageError::Database(e.to_string()))
+        })
+        .await
+    }
+
+    async fn retain_blob(&self, hash: &BlobHash) -> Result<u64> {
+        let hash = *hash;
+        self.execute(move |conn| {
+            conn.query_row(
+                "UPDATE blobs SET refcount = refcount + 1 WHERE hash = ?1 RETURNING refcount",
+                params![hash.to_hex()],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()
+            .map_err(|e| StorageError::Database(e.to_string()))?
+            .map(|refcount| refcount as u64)
+            .ok_or(StorageError::BlobNotFound(hash))
+        })
+        .await
+    }
+
+    async fn release_blob(&self, hash: &BlobHash) -> Result<u64> {
+        let hash = *hash;
+        self.execute(move |conn| {
+            let tx = Atomic::begin(conn)?;
+            let refcount: i64 = tx
+                .query_row(
+                    "SELECT refcount FROM blobs WHERE hash = ?1",
+                    params![hash.to_hex()],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| StorageError::Database(e.to_string()))?
+                .ok_or(StorageError::BlobNotFound(hash))?;
+            if refcount == 0 {
+                return Err(unreferenced_blob(&hash));
+            }
+            tx.execute(
+                "UPDATE blobs SET refcount = refcount - 1 WHERE hash = ?1",
+                params![hash.to_hex()],
+            )
+            .map_err(|e| StorageError::Database(e.to_string()))?;
+            tx.commit()?;
+            Ok(refcount as u64 - 1)
+        })
+        .await
+    }
+
+    async fn gc_blobs(&self) -> Result<BlobGcReport> {
+        self.execute(|conn| {
+            let tx = Atomic::begin(conn)?;
+            let (blobs_removed, bytes_freed): (i64, i64) = tx
+                .query_row(
+                    "SELECT COUNT(*), COALESCE(SUM(size), 0) FROM blobs WHERE refcount = 0",
+                    [],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .map_err(|e| StorageError::Database(e.to_string()))?;
+            tx.execute("DELETE FROM blobs WHERE refcount = 0", [])
+                .map_err(|e| StorageError::Database(e.to_string()))?;
+            tx.commit()?;
+            Ok(BlobGcReport {
+                blobs_removed: blobs_removed as usize,
+                bytes_freed: bytes_freed as u64,
+            })
+        })
         .await
     }
 }
 
-/// Build SQL query from filter.
-fn build_query_sql(namespace: &str, filter: &QueryFilter) -> Result<(String, Vec<String>)> {
+/// Atomic section that nests inside an explicit transaction.
+///
+/// A savepoint outside a transaction behaves like `BEGIN DEFERRED`; inside
+/// one started by [`StorageAdapter::begin`] it only undoes its own statements
+/// on failure, and its writes commit with the enclosing transaction. Dropping
+/// it without [`Atomic::commit`] rolls back.
+struct Atomic<'c> {
+    conn: &'c Connection,
+    released: bool,
+}
+
+impl<'c> Atomic<'c> {
+    fn begin(conn: &'c Connection) -> Result<Self> {
+        conn.execute_batch("SAVEPOINT vudo_atomic")
+            .map_err(|e| StorageError::Database(e.to_string()))?;
+        Ok(Self {
+            conn,
+            released: false,
+        })
+    }
+
+    fn commit(mut self) -> Result<()> {
+        self.released = true;
+        self.conn
+            .execute_batch("RELEASE vudo_atomic")
+            .map_err(|e| StorageError::Database(e.to_string()))
+    }
+}
+
+impl std::ops::Deref for Atomic<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn
+    }
+}
+
+impl Drop for Atomic<'_> {
+    fn drop(&mut self) {
+        if !self.released {
+            let _ = self
+                .conn
+                .execute_batch("ROLLBACK TO vudo_atomic; RELEASE vudo_atomic");
+        }
+    }
+}
+
+/// Delete a document and record its tombstone. Returns whether it existed.
+fn delete_document(conn: &Connection, namespace: &str, id: &str, timestamp: i64) -> Result<bool> {
+    let deleted = conn
+        .execute(
+            "DELETE FROM documents WHERE namespace = ?1 AND id = ?2",
+            params![namespace, id],
+        )
+        .map_err(|e| StorageError::Database(e.to_string()))?;
+    if deleted > 0 {
+        conn.execute(
+            "INSERT OR REPLACE INTO tombstones (namespace, id, deleted_at)
+             VALUES (?1, ?2, ?3)",
+            params![namespace, id, timestamp],
+        )
+        .map_err(|e| StorageError::Database(e.to_string()))?;
+    }
+    Ok(deleted > 0)
+}
+
+/// BLAKE3 checksum of a stored value.
+fn checksum(data: &[u8]) -> Vec<u8> {
+    blake3::hash(data).as_bytes().to_vec()
+}
+
+/// Document or snapshot data prepared for storage.
+struct Encoded {
+    /// Compressed and sealed data.
+    data: Vec<u8>,
+    /// ID of the codec the data was compressed with.
+    codec: i64,
+    /// Size of the data before compression.
+    size: i64,
+}
+
+/// Compress document or snapshot data with the codec of its namespace, then
+/// seal it if encryption is enabled.
+fn encode(
+    cipher: Option<&Cipher>,
+    policy: &CompressionPolicy,
+    namespace: &str,
+    location: &[&[u8]],
+    data: Vec<u8>,
+) -> Result<Encoded> {
+    let size = data.len() as i64;
+    let (codec, data) = policy.compress(namespace, data)?;
+    let data = match cipher {
+        Some(cipher) => cipher.seal(&aad(location), &data)?,
+        None => data,
+    };
+    Ok(Encoded {
+        data,
+        codec: codec.id() as i64,
+        size,
+    })
+}
+
+/// Open stored document or snapshot data and decompress it with the codec
+/// it was written with.
+fn decode(
+    cipher: Option<&Cipher>,
+    location: &[&[u8]],
+    codec: i64,
+    data: Vec<u8>,
+) -> Result<Vec<u8>> {
+    let data = match cipher {
+        Some(cipher) => cipher.open(&aad(location), &data)?,
+        None => data,
+    };
+    let codec = u8::try_from(codec)
+        .map_err(|_| StorageError::Compression(format!("Unknown codec ID {}", codec)))
+        .and_then(Codec::from_id)?;
+    match codec {
+        Codec::None => Ok(data),
+        codec => codec.decompress(&data),
+    }
+}
+
+/// Rows of a table sorted by their checksum state.
+struct CheckedRows {
+    /// Number of rows scanned.
+    checked: usize,
+    /// Rows without a checksum, by rowid.
+    unchecked: Vec<i64>,
+    /// Rows whose data doesn't match their checksum.
+    corrupt: Vec<(i64, IntegrityItem)>,
+}
+
+/// Scan rows of `(rowid, namespace, id, version, data, checksum)`, comparing
+/// each row's data with its checksum.
+fn check_rows(
+    conn: &Connection,
+    sql: &str,
+    item: impl Fn(&rusqlite::Row<'_>) -> rusqlite::Result<IntegrityItem>,
+) -> Result<CheckedRows> {
+    let mut stmt = conn
+        .prepare(sql)
+        .map_err(|e| StorageError::Database(e.to_string()))?;
+    let mut rows = stmt
+        .query([])
+        .map_err(|e| StorageError::Database(e.to_string()))?;
+
+    let mut checked = CheckedRows {
+        checked: 0,
+        unchecked: Vec::new(),
+        corrupt: Vec::new(),
+    };
+    while let Some(row) = rows
+        .next()
+        .map_err(|e| StorageError::Database(e.to_string()))?
+    {
+        checked.checked += 1;
+        let rowid: i64 = row
+            .get(0)
+            .map_err(|e| StorageError::Database(e.to_string()))?;
+        let stored: Option<Vec<u8>> = row
+            .get(5)
+            .map_err(|e| StorageError::Database(e.to_string()))?;
+        let data = row
+            .get_ref(4)
+            .and_then(|value| value.as_bytes().map_err(Into::into))
+            .map_err(|e| StorageError::Database(e.to_string()))?;
+        match stored {
+            None => checked.unchecked.push(rowid),
+            Some(sum) if sum == checksum(data) => {}
+            Some(_) => {
+                let item = item(row).map_err(|e| StorageError::Database(e.to_string()))?;
+                checked.corrupt.push((rowid, item));
+            }
+        }
+    }
+    Ok(checked)
+}
+
+/// Store the checksums of rows written before checksums were kept.
+fn add_checksums(conn: &Connection, table: &str, rowids: &[i64]) -> Result<usize> {
+    for rowid in rowids {
+        let data: Vec<u8> = conn
+            .query_row(
+                &format!("SELECT data FROM {} WHERE rowid = ?1", table),
+                params![rowid],
+                |row| row.get(0),
+            )
+            .map_err(|e| StorageError::Database(e.to_string()))?;
+        conn.execute(
+            &format!("UPDATE {} SET checksum = ?1 WHERE rowid = ?2", table),
+            params![checksum(&data), rowid],
+        )
+        .map_err(|e| StorageError::Database(e.to_string()))?;
+    }
+    Ok(rowids.len())
+}
+
+/// The operation queue, skipping operations that can't be read.
+fn queued_operations(conn: &Connection, cipher: Option<&Cipher>) -> Result<Vec<Operation>> {
+    let mut stmt = conn
+        .prepare("SELECT id, data FROM operations ORDER BY timestamp, id")
+        .map_err(|e| StorageError::Database(e.to_string()))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?))
+        })
+        .map_err(|e| StorageError::Database(e.to_string()))?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| StorageError::Database(e.to_string()))?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(id, data)| {
+            let data = match cipher {
+                Some(cipher) => cipher
+                    .open(&aad(&[b"operations", &(id as u64).to_le_bytes()]), &data)
+                    .ok()?,
+                None => data,
+            };
+            serde_json::from_slice(&data).ok()
+        })
+        .collect())
+}
+
+/// Rebuild a document's stored value from its latest snapshot and the
+/// changes of the queued operations recorded since, or None without a
+/// readable snapshot.
+fn rebuild_document(
+    conn: &Connection,
+    cipher: Option<&Cipher>,
+    compression: &CompressionPolicy,
+    namespace: &str,
+    id: &str,
+    ops: &[Operation],
+) -> Result<Option<Encoded>> {
+    let snapshot: Option<(i64, Vec<u8>, i64, i64)> = conn
+        .query_row(
+            "SELECT version, data, codec, created_at FROM snapshots
+             WHERE namespace = ?1 AND id = ?2
+             ORDER BY version DESC LIMIT 1",
+            params![namespace, id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .optional()
+        .map_err(|e| StorageError::Database(e.to_string()))?;
+    let Some((version, data, codec, created_at)) = snapshot else {
+        return Ok(None);
+    };
+
+    let location = [
+        b"snapshots".as_slice(),
+        namespace.as_bytes(),
+        id.as_bytes(),
+        &(version as u64).to_le_bytes(),
+    ];
+    let Ok(mut data) = decode(cipher, &location, codec, data) else {
+        return Ok(None);
+    };
+    for op in ops {
+        if op.namespace != namespace || op.document_id != id || op.timestamp < created_at as u64 {
+            continue;
+        }
+        if let OperationType::Update { change_bytes } = &op.op_type {
+            data.extend_from_slice(change_bytes);
+        }
+    }
+
+    let location = [b"documents".as_slice(), namespace.as_bytes(), id.as_bytes()];
+    encode(cipher, compression, namespace, &location, data).map(Some)
+}
+
+/// Stored bytes of the documents of a namespace, or of all namespaces.
+fn stored_bytes(conn: &Connection, namespace: Option<&str>) -> Result<u64> {
+    let bytes: i64 = conn
+        .query_row(
+            "SELECT COALESCE(SUM(length(data)), 0) FROM documents
+             WHERE ?1 IS NULL OR namespace = ?1",
+            params![namespace],
+            |row| row.get(0),
+        )
+        .map_err(|e| StorageError::Database(e.to_string()))?;
+    Ok(bytes as u64)
+}
+
+/// Enforce the quota on writes of (namespace, id, stored size), evicting
+/// documents through the hook if needed.
+///
+/// Runs in the write's atomic section, before the write, so evictions are
+/// rolled back with a failed write.
+fn check_quota(
+    conn: &Connection,
+    quota: &StorageQuota,
+    hook: Option<&dyn EvictionHook>,
+    writes: &[(&str, &str, u64)],
+) -> Result<()> {
+    if quota.is_unlimited() {
+        return Ok(());
+    }
+
+    // A later write of the same document replaces an earlier one
+    let sizes: BTreeMap<(&str, &str), u64> = writes
+        .iter()
+        .map(|&(namespace, id, size)| ((namespace, id), size))
+        .collect();
+
+    let usage = || {
+        let mut usage = QuotaUsage {
+            namespaces: BTreeMap::new(),
+            total: stored_bytes(conn, None)?,
+        };
+        for (&(namespace, id), &size) in &sizes {
+            if !usage.namespaces.contains_key(namespace) {
+                let bytes = stored_bytes(conn, Some(namespace))?;
+                usage.namespaces.insert(namespace.to_string(), bytes);
+            }
+            let old: i64 = conn
+                .query_row(
+                    "SELECT length(data) FROM documents WHERE namespace = ?1 AND id = ?2",
+                    params![namespace, id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| StorageError::Database(e.to_string()))?
+                .unwrap_or(0);
+            let old = old as u64;
+            if let Some(bytes) = usage.namespaces.get_mut(namespace) {
+                *bytes = *bytes - old + size;
+            }
+            usage.total = usage.total - old + size;
+        }
+        Ok(usage)
+    };
+
+    let candidates = |scope: &QuotaScope| {
+        let namespace = match scope {
+            QuotaScope::Namespace(namespace) => Some(namespace.as_str()),
+            QuotaScope::Total => None,
+        };
+        let mut stmt = conn
+            .prepare(
+                "SELECT namespace, id, length(data), updated_at FROM documents
+                 WHERE ?1 IS NULL OR namespace = ?1",
+            )
+            .map_err(|e| StorageError::Database(e.to_string()))?;
+        let rows = stmt
+            .query_map(params![namespace], |row| {
+                Ok(EvictionCandidate {
+                    namespace: row.get(0)?,
+                    id: row.get(1)?,
+                    size: row.get::<_, i64>(2)? as u64,
+                    updated_at: row.get::<_, i64>(3)? as u64,
+                })
+            })
+            .map_err(|e| StorageError::Database(e.to_string()))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| StorageError::Database(e.to_string()))?;
+        Ok(rows
+            .into_iter()
+            .filter(|c| !sizes.contains_key(&(c.namespace.as_str(), c.id.as_str())))
+            .collect())
+    };
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64;
+    enforce_quota(quota, hook, usage, candidates, |namespace, id| {
+        delete_document(conn, namespace, id, timestamp).map(|_| ())
+    })
+}
+
+/// Size of the database in bytes, including its write-ahead log.
+fn database_size(conn: &Connection, path: &Path) -> Result<u64> {
+    if path == Path::new(":memory:") {
+        let pages: i64 = conn
+            .query_row(
+                "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| StorageError::Database(e.to_string()))?;
+        return Ok(pages as u64);
+    }
+
+    let file_size = |path: &Path| std::fs::metadata(path).map_or(0, |metadata| metadata.len());
+    let mut wal = path.as_os_str().to_owned();
+    wal.push("-wal");
+    Ok(file_size(path) + file_size(Path::new(&wal)))
+}
+
+/// Sort order used by [`StorageAdapter::query`], following the filter's time axis.
+fn default_sort(filter: &QueryFilter) -> SortOrder {
+    match filter {
+        QueryFilter::UpdatedAfter(_)
+        | QueryFilter::UpdatedBefore(_)
+        | QueryFilter::UpdatedBetween { .. } => SortOrder::asc(SortField::UpdatedAt),
+        QueryFilter::CreatedAfter(_)
+        | QueryFilter::CreatedBefore(_)
+        | QueryFilter::CreatedBetween { .. } => SortOrder::asc(SortField::CreatedAt),
+        _ => SortOrder::asc(SortField::Id),
+    }
+}
+
+/// Whether a filter matches on document fields, which needs plaintext data.
+fn has_field_filter(filter: &QueryFilter) -> bool {
     match filter {
-        QueryFilter::All => {
-            let sql = "SELECT id, data FROM documents WHERE namespace = ?1 ORDER BY id"
-                .to_string();
-            Ok((sql, vec![namespace.to_string()]))
+        QueryFilter::Field { .. } => true,
+        QueryFilter::And(filters) | QueryFilter::Or(filters) => {
+            filters.iter().any(has_field_filter)
+        }
+        QueryFilter::Not(filter) => has_field_filter(filter),
+        _ => false,
+    }
+}
+
+/// Name of the expression index over a JSON path.
+fn field_index_name(path: &JsonPath) -> String {
+    let hex: String = path
+        .to_string()
+        .bytes()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("idx_field_{}", hex)
+}
+
+/// SQL expression extracting a JSON field from the document data.
+///
+/// Queries must use the exact text of the indexed expression for SQLite to
+/// pick the index. Documents that aren't JSON yield NULL. `data` is cast to
+/// TEXT because SQLite reads BLOB arguments of the JSON functions as JSONB.
+/// The path is safe to embed because [`JsonPath`] only admits `[A-Za-z0-9_]`
+/// member names.
+fn field_expr(path: &JsonPath) -> String {
+    format!(
+        "(CASE WHEN json_valid(CAST(data AS TEXT)) \
+         THEN json_extract(CAST(data AS TEXT), '{path}') END)"
+    )
+}
+
+/// Build SQL query from filter and options.
+fn build_query_sql(
+    namespace: &str,
+    filter: &QueryFilter,
+    options: &QueryOptions,
+) -> Result<(String, Vec<Value>)> {
+    let mut params = vec![Value::Text(namespace.to_string())];
+    let condition = build_condition(filter, &mut params)?;
+
+    let sort_column = match options.sort.field {
+        SortField::Id => None,
+        SortField::CreatedAt => Some("created_at"),
+        SortField::UpdatedAt => Some("updated_at"),
+    };
+    let (op, dir) = match options.sort.direction {
+        SortDirection::Ascending => (">", "ASC"),
+        SortDirection::Descending => ("<", "DESC"),
+    };
+
+    let mut sql = format!(
+        "SELECT id, data, {}, codec FROM documents WHERE namespace = ?1 AND ({})",
+        sort_column.unwrap_or("NULL"),
+        condition
+    );
+
+    // Keyset pagination: continue strictly after the cursor in sort order
+    if let Some(cursor) = &options.cursor {
+        match (sort_column, cursor.timestamp) {
+            (None, _) => {
+                params.push(Value::Text(cursor.id.clone()));
+                sql.push_str(&format!(" AND id {} ?{}", op, params.len()));
+            }
+            (Some(column), Some(timestamp)) => {
+                params.push(Value::Integer(timestamp as i64));
+                let t = params.len();
+                params.push(Value::Text(cursor.id.clone()));
+                let i = params.len();
+                sql.push_str(&format!(
+                    " AND ({column} {op} ?{t} OR ({column} = ?{t} AND id {op} ?{i}))"
+                ));
+            }
+            (Some(_), None) => {
+                return Err(StorageError::InvalidOperation(
+                    "Cursor has no timestamp for a timestamp sort order".to_string(),
+                ))
+            }
         }
+    }
+
+    match sort_column {
+        Some(column) => sql.push_str(&format!(" ORDER BY {column} {dir}, id {dir}")),
+        None => sql.push_str(&format!(" ORDER BY id {dir}")),
+    }
+
+    params.push(Value::Integer(
+        options.limit.map(|limit| limit as i64).unwrap_or(-1),
+    ));
+    sql.push_str(&format!(" LIMIT ?{}", params.len()));
+    params.push(Value::Integer(options.offset as i64));
+    sql.push_str(&format!(" OFFSET ?{}", params.len()));
+
+    Ok((sql, params))
+}
+
+/// Build the SQL condition for a filter, appending its parameters.
+fn build_condition(filter: &QueryFilter, params: &mut Vec<Value>) -> Result<String> {
+    let mut bind = |value: Value| {
+        params.push(value);
+        format!("?{}", params.len())
+    };
+
+    let condition = match filter {
+        QueryFilter::All => "1".to_string(),
         QueryFilter::UpdatedAfter(timestamp) => {
-            let sql = "SELECT id, data FROM documents WHERE namespace = ?1 AND updated_at > ?2 ORDER BY updated_at".to_string();
-            Ok((
-                sql,
-                vec![namespace.to_string(), timestamp.to_string()],
-            ))
+            format!("updated_at > {}", bind(Value::Integer(*timestamp as i64)))
         }
         QueryFilter::UpdatedBefore(timestamp) => {
-            let sql = "SELECT id, data FROM documents WHERE namespace = ?1 AND updated_at < ?2 ORDER BY updated_at".to_string();
-            Ok((
-                sql,
-                vec![namespace.to_string(), timestamp.to_string()],
-            ))
-        }
-        QueryFilter::UpdatedBetween { start, end } => {
-            let sql = "SELECT id, data FROM documents WHERE namespace = ?1 AND updated_at >= ?2 AND updated_at <= ?3 ORDER BY updated_at".to_string();
-            Ok((
-                sql,
-                vec![namespace.to_string(), start.to_string(), end.to_string()],
-            ))
-        }
-        _ => Err(StorageError::Unsupported(
-            "Complex query filters not yet implemented".to_string(),
-        )),
-    }
+            format!("updated_at < {}", bind(Value::Integer(*timestamp as i64)))
+        }
+        QueryFilter::UpdatedBetween { start, end } => format!(
+            "updated_at >= {} AND updated_at <= {}",
+            bind(Value::Integer(*start as i64)),
+            bind(Value::Integer(*end as i64))
+        ),
+        QueryFilter::CreatedAfter(timestamp) => {
+            format!("created_at > {}", bind(Value::Integer(*timestamp as i64)))
+        }
+        QueryFilter::CreatedBefore(timestamp) => {
+            format!("created_at < {}", bind(Value::Integer(*timestamp as i64)))
+        }
+        QueryFilter::CreatedBetween { start, end } => format!(
+            "created_at >= {} AND created_at <= {}",
+            bind(Value::Integer(*start as i64)),
+            bind(Value::Integer(*end as i64))
+        ),
+        // A range scan on the primary key instead of LIKE, which can't use it
+        QueryFilter::IdPrefix(prefix) => {
+            let lower = format!("id >= {}", bind(Value::Text(prefix.clone())));
+            match prefix_upper_bound(prefix) {
+                Some(upper) => format!("{} AND id < {}", lower, bind(Value::Text(upper))),
+                None => lower,
+            }
+        }
+        QueryFilter::And(filters) if filters.is_empty() => "1".to_string(),
+        QueryFilter::Or(filters) if filters.is_empty() => "0".to_string(),
+        QueryFilter::And(filters) => filters
+            .iter()
+            .map(|f| build_condition(f, params).map(|c| format!("({})", c)))
+            .collect::<Result<Vec<_>>>()?
+            .join(" AND "),
+        QueryFilter::Or(filters) => filters
+            .iter()
+            .map(|f| build_condition(f, params).map(|c| format!("({})", c)))
+            .collect::<Result<Vec<_>>>()?
+            .join(" OR "),
+        QueryFilter::Not(f) => format!("NOT ({})", build_condition(f, params)?),
+        QueryFilter::Field { field, value } => {
+            let path = JsonPath::parse(field)?;
+            // json_extract returns SQL values: booleans as 0/1, objects and
+            // arrays as minified JSON text
+            let expected = match parse_field_value(value) {
+                serde_json::Value::Null => {
+                    return Ok(format!(
+                        "(CASE WHEN json_valid(CAST(data AS TEXT)) \
+                         THEN json_type(CAST(data AS TEXT), '{path}') END) = 'null'"
+                    ))
+                }
+                serde_json::Value::Bool(b) => Value::Integer(b as i64),
+                serde_json::Value::Number(n) => match n.as_i64() {
+                    Some(i) => Value::Integer(i),
+                    None => Value::Real(n.as_f64().unwrap_or(f64::NAN)),
+                },
+                serde_json::Value::String(s) => Value::Text(s),
+                other => Value::Text(other.to_string()),
+            };
+            format!("{} = {}", field_expr(&path), bind(expected))
+        }
+    };
+
+    Ok(condition)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
+    use vudo_storage::{
+        BackupTarget, LeastRecentlyUpdated, MemoryBackupTarget, QuotaScope, StorageQuota,
+    };
 
     #[tokio::test]
     async fn test_sqlite_adapter_new() {
@@ -506,21 +2200,95 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_sqlite_adapter_load_nonexistent() {
+    async fn test_sqlite_adapter_streams() {
         let adapter = SqliteAdapter::in_memory().await.unwrap();
         adapter.init().await.unwrap();
 
-        let loaded = adapter.load("users", "bob").await.unwrap();
-        assert_eq!(loaded, None);
-    }
-
-    #[tokio::test]
-    async fn test_sqlite_adapter_delete() {
-        let adapter = SqliteAdapter::in_memory().await.unwrap();
-        adapter.init().await.unwrap();
+        // Spans several read chunks
+        let data: Vec<u8> = (0..STREAM_CHUNK_SIZE * 3 + 17)
+            .map(|i| (i % 251) as u8)
+            .collect();
 
-        let data = Bytes::from("test document");
-        adapter.save("users", "alice", data).await.unwrap();
+        let saved = adapter
+            .save_stream("models", "big", &mut data.as_slice())
+            .await
+            .unwrap();
+        assert_eq!(saved, data.len() as u64);
+        assert_eq!(
+            adapter.load("models", "big").await.unwrap().unwrap(),
+            data.as_slice()
+        );
+
+        let mut out = Vec::new();
+        let loaded = adapter
+            .load_stream("models", "big", &mut out)
+            .await
+            .unwrap();
+        assert_eq!(loaded, Some(data.len() as u64));
+        assert_eq!(out, data);
+
+        // Overwrite with a smaller document
+        adapter
+            .save_stream("models", "big", &mut &b"small"[..])
+            .await
+            .unwrap();
+        let mut out = Vec::new();
+        adapter
+            .load_stream("models", "big", &mut out)
+            .await
+            .unwrap();
+        assert_eq!(out, b"small");
+
+        let mut out = Vec::new();
+        let missing = adapter
+            .load_stream("models", "none", &mut out)
+            .await
+            .unwrap();
+        assert_eq!(missing, None);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_streams_with_field_index() {
+        let adapter = SqliteAdapter::in_memory().await.unwrap();
+        adapter.init().await.unwrap();
+        adapter.ensure_index("users", "$.status").await.unwrap();
+
+        let data = br#"{"status":"active"}"#;
+        adapter
+            .save_stream("users", "alice", &mut &data[..])
+            .await
+            .unwrap();
+
+        let results = adapter
+            .query("users", QueryFilter::field("$.status", "active"))
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+
+        let mut out = Vec::new();
+        adapter
+            .load_stream("users", "alice", &mut out)
+            .await
+            .unwrap();
+        assert_eq!(out, data);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_load_nonexistent() {
+        let adapter = SqliteAdapter::in_memory().await.unwrap();
+        adapter.init().await.unwrap();
+
+        let loaded = adapter.load("users", "bob").await.unwrap();
+        assert_eq!(loaded, None);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_delete() {
+        let adapter = SqliteAdapter::in_memory().await.unwrap();
+        adapter.init().await.unwrap();
+
+        let data = Bytes::from("test document");
+        adapter.save("users", "alice", data).await.unwrap();
 
         adapter.delete("users", "alice").await.unwrap();
 
@@ -528,6 +2296,542 @@ mod tests {
         assert_eq!(loaded, None);
     }
 
+    #[tokio::test]
+    async fn test_sqlite_adapter_save_batch() {
+        let adapter = SqliteAdapter::in_memory().await.unwrap();
+        adapter.init().await.unwrap();
+
+        adapter
+            .save("users", "alice", Bytes::from("old"))
+            .await
+            .unwrap();
+        adapter.delete("users", "alice").await.unwrap();
+
+        let docs: Vec<(String, Bytes)> = (0..100)
+            .map(|n| (format!("user{}", n), Bytes::from(format!("data{}", n))))
+            .collect();
+        let mut batch: Vec<(&str, &str, Bytes)> = docs
+            .iter()
+            .map(|(id, data)| ("users", id.as_str(), data.clone()))
+            .collect();
+        batch.push(("users", "alice", Bytes::from("new")));
+        adapter.save_batch(&batch).await.unwrap();
+
+        assert_eq!(adapter.list("users").await.unwrap().len(), 101);
+        assert_eq!(
+            adapter.load("users", "user42").await.unwrap(),
+            Some(Bytes::from("data42"))
+        );
+        assert_eq!(
+            adapter.load("users", "alice").await.unwrap(),
+            Some(Bytes::from("new"))
+        );
+        // Saving the deleted document again removed its tombstone
+        assert_eq!(adapter.stats().await.unwrap().tombstone_count, 0);
+
+        adapter.save_batch(&[]).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_backup_and_restore() {
+        let adapter = SqliteAdapter::in_memory().await.unwrap();
+        adapter.init().await.unwrap();
+        adapter
+            .save("users", "alice", Bytes::from("alice"))
+            .await
+            .unwrap();
+        adapter
+            .save("posts", "p1", Bytes::from("post"))
+            .await
+            .unwrap();
+        adapter
+            .save_snapshot("users", "alice", 1, Bytes::from("old"))
+            .await
+            .unwrap();
+        adapter
+            .save_snapshot("users", "alice", 3, Bytes::from("new"))
+            .await
+            .unwrap();
+        let ops = vec![Operation::new(
+            1,
+            "users",
+            "alice",
+            vudo_storage::operation::OperationType::Create,
+        )];
+        adapter.save_operations(&ops).await.unwrap();
+        assert_eq!(
+            adapter.list_namespaces().await.unwrap(),
+            vec!["posts", "users"]
+        );
+
+        let target = MemoryBackupTarget::new();
+        let manifest = adapter.backup_to(&target).await.unwrap();
+        assert_eq!(manifest.documents.len(), 2);
+        assert_eq!(manifest.operation_count, 1);
+        assert_eq!(manifest.total_bytes(), 12);
+
+        // Restore replaces backed-up documents and the queue, keeps others
+        let restored = SqliteAdapter::in_memory().await.unwrap();
+        restored.init().await.unwrap();
+        restored
+            .save("users", "alice", Bytes::from("stale"))
+            .await
+            .unwrap();
+        restored
+            .save("users", "bob", Bytes::from("bob"))
+            .await
+            .unwrap();
+        assert_eq!(restored.restore_from(&target).await.unwrap(), manifest);
+
+        assert_eq!(
+            restored.load("users", "alice").await.unwrap(),
+            Some(Bytes::from("alice"))
+        );
+        assert_eq!(
+            restored.load("posts", "p1").await.unwrap(),
+            Some(Bytes::from("post"))
+        );
+        assert!(restored.load("users", "bob").await.unwrap().is_some());
+        assert_eq!(
+            restored.load_snapshot("users", "alice").await.unwrap(),
+            Some((3, Bytes::from("new")))
+        );
+        assert_eq!(restored.load_operations().await.unwrap(), ops);
+
+        // A truncated blob fails the restore and rolls it back
+        let broken = MemoryBackupTarget::new();
+        for key in target.keys() {
+            let data = target.get(&key).await.unwrap().unwrap();
+            let data = if key == "docs/1" {
+                data.slice(..1)
+            } else {
+                data
+            };
+            broken.put(&key, data).await.unwrap();
+        }
+        let fresh = SqliteAdapter::in_memory().await.unwrap();
+        fresh.init().await.unwrap();
+        assert!(matches!(
+            fresh.restore_from(&broken).await,
+            Err(StorageError::InvalidOperation(_))
+        ));
+        assert!(fresh.list_namespaces().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_quota() {
+        let adapter = SqliteAdapter::in_memory().await.unwrap().with_quota(
+            StorageQuota::new()
+                .max_total_bytes(20)
+                .namespace_limit("users", 10),
+        );
+        adapter.init().await.unwrap();
+
+        adapter
+            .save("users", "alice", Bytes::from("0123456789"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            adapter.save("users", "bob", Bytes::from("x")).await,
+            Err(StorageError::QuotaExceeded {
+                scope: QuotaScope::Namespace(ref ns),
+                limit: 10,
+                required: 11,
+            }) if ns == "users"
+        ));
+        // Replacing a document only counts the new size
+        adapter
+            .save("users", "alice", Bytes::from("abcdefghij"))
+            .await
+            .unwrap();
+
+        // A batch over the limit is rolled back whole
+        assert!(matches!(
+            adapter
+                .save_batch(&[
+                    ("posts", "p1", Bytes::from("0123456789")),
+                    ("posts", "p2", Bytes::from("x")),
+                ])
+                .await,
+            Err(StorageError::QuotaExceeded {
+                scope: QuotaScope::Total,
+                limit: 20,
+                required: 21,
+            })
+        ));
+        assert!(adapter.list("posts").await.unwrap().is_empty());
+
+        let mut reader: &[u8] = b"0123456789x";
+        assert!(matches!(
+            adapter.save_stream("posts", "big", &mut reader).await,
+            Err(StorageError::QuotaExceeded { .. })
+        ));
+        assert!(adapter.list("posts").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_quota_eviction() {
+        let adapter = SqliteAdapter::in_memory()
+            .await
+            .unwrap()
+            .with_quota(StorageQuota::new().max_total_bytes(10))
+            .with_eviction_hook(Arc::new(LeastRecentlyUpdated::in_namespaces(["cache"])));
+        adapter.init().await.unwrap();
+
+        adapter
+            .save("cache", "a", Bytes::from("aaaa"))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        adapter
+            .save("cache", "b", Bytes::from("bbbb"))
+            .await
+            .unwrap();
+        adapter
+            .save("users", "alice", Bytes::from("al"))
+            .await
+            .unwrap();
+
+        adapter
+            .save("users", "bob", Bytes::from("bob"))
+            .await
+            .unwrap();
+        assert_eq!(adapter.list("cache").await.unwrap(), vec!["b"]);
+        assert_eq!(adapter.stats().await.unwrap().tombstone_count, 1);
+
+        // Evictions are rolled back with a transaction
+        adapter.begin().await.unwrap();
+        adapter
+            .save("users", "carol", Bytes::from("cc"))
+            .await
+            .unwrap();
+        assert!(adapter.list("cache").await.unwrap().is_empty());
+        adapter.rollback().await.unwrap();
+        assert_eq!(adapter.list("cache").await.unwrap(), vec!["b"]);
+
+        // Nothing is evicted when eviction can't make room
+        assert!(matches!(
+            adapter.save("users", "dave", Bytes::from("davedave")).await,
+            Err(StorageError::QuotaExceeded { .. })
+        ));
+        assert_eq!(adapter.list("cache").await.unwrap(), vec!["b"]);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_transactions() {
+        let adapter = SqliteAdapter::in_memory().await.unwrap();
+        adapter.init().await.unwrap();
+
+        assert!(matches!(
+            adapter.commit().await,
+            Err(StorageError::InvalidOperation(_))
+        ));
+        assert!(matches!(
+            adapter.rollback().await,
+            Err(StorageError::InvalidOperation(_))
+        ));
+
+        // Rolled back writes, including ones that use savepoints internally
+        adapter
+            .save("users", "alice", Bytes::from("kept"))
+            .await
+            .unwrap();
+        adapter.begin().await.unwrap();
+        assert!(matches!(
+            adapter.begin().await,
+            Err(StorageError::InvalidOperation(_))
+        ));
+        adapter
+            .save("users", "bob", Bytes::from("discarded"))
+            .await
+            .unwrap();
+        adapter.delete("users", "alice").await.unwrap();
+        adapter
+            .save_batch(&[("users", "carol", Bytes::from("discarded"))])
+            .await
+            .unwrap();
+        assert_eq!(adapter.list("users").await.unwrap(), vec!["bob", "carol"]);
+        assert!(matches!(
+            adapter
+                .maintenance(MaintenanceOptions::new().vacuum())
+                .await,
+            Err(StorageError::InvalidOperation(_))
+        ));
+        adapter.rollback().await.unwrap();
+
+        assert_eq!(adapter.list("users").await.unwrap(), vec!["alice"]);
+        assert_eq!(adapter.stats().await.unwrap().tombstone_count, 0);
+
+        // Committed writes
+        adapter.begin().await.unwrap();
+        adapter
+            .save("users", "bob", Bytes::from("committed"))
+            .await
+            .unwrap();
+        adapter.delete("users", "alice").await.unwrap();
+        adapter.commit().await.unwrap();
+
+        assert_eq!(adapter.list("users").await.unwrap(), vec!["bob"]);
+        assert_eq!(adapter.stats().await.unwrap().tombstone_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_blobs() {
+        let adapter = SqliteAdapter::in_memory().await.unwrap();
+        adapter.init().await.unwrap();
+        let wasm = Bytes::from_static(b"\0asm module");
+
+        let hash = adapter.put_blob(wasm.clone()).await.unwrap();
+        assert_eq!(adapter.put_blob(wasm.clone()).await.unwrap(), hash);
+        assert_eq!(adapter.get_blob(&hash).await.unwrap(), Some(wasm.clone()));
+        let info = adapter.blob_info(&hash).await.unwrap().unwrap();
+        assert_eq!(info.size, wasm.len() as u64);
+        assert_eq!(info.refcount, 2);
+
+        assert_eq!(adapter.retain_blob(&hash).await.unwrap(), 3);
+        for expected in [2, 1, 0] {
+            assert_eq!(adapter.release_blob(&hash).await.unwrap(), expected);
+        }
+        assert!(adapter.release_blob(&hash).await.is_err());
+
+        let kept = adapter.put_blob(Bytes::from("weights")).await.unwrap();
+        let report = adapter.gc_blobs().await.unwrap();
+        assert_eq!(report.blobs_removed, 1);
+        assert_eq!(report.bytes_freed, wasm.len() as u64);
+        assert!(!adapter.has_blob(&hash).await.unwrap());
+        assert!(adapter.has_blob(&kept).await.unwrap());
+        assert!(matches!(
+            adapter.retain_blob(&hash).await,
+            Err(StorageError::BlobNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_encrypted_blobs() {
+        let adapter = SqliteAdapter::in_memory()
+            .await
+            .unwrap()
+            .with_encryption(EncryptionKey::generate());
+        adapter.init().await.unwrap();
+
+        let hash = adapter
+            .put_blob(Bytes::from("model weights"))
+            .await
+            .unwrap();
+        assert_eq!(
+            adapter.get_blob(&hash).await.unwrap(),
+            Some(Bytes::from("model weights"))
+        );
+
+        let stored: Vec<u8> = adapter
+            .execute(move |conn| {
+                conn.query_row(
+                    "SELECT data FROM blobs WHERE hash = ?1",
+                    params![hash.to_hex()],
+                    |row| row.get(0),
+                )
+                .map_err(|e| StorageError::Database(e.to_string()))
+            })
+            .await
+            .unwrap();
+        assert!(!stored.windows(7).any(|w| w == b"weights"));
+    }
+
+    /// Overwrite a stored value without updating its checksum.
+    async fn corrupt(adapter: &SqliteAdapter, table: &'static str, id: &'static str) {
+        adapter
+            .execute(move |conn| {
+                conn.execute(
+                    &format!("UPDATE {} SET data = x'deadbeef' WHERE id = ?1", table),
+                    params![id],
+                )
+                .map_err(|e| StorageError::Database(e.to_string()))?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_verify_repairs_documents() {
+        let adapter = SqliteAdapter::in_memory().await.unwrap();
+        adapter.init().await.unwrap();
+
+        adapter
+            .save_snapshot("users", "alice", 1, Bytes::from("base"))
+            .await
+            .unwrap();
+        let ops = vec![
+            Operation::new(
+                1,
+                "users",
+                "alice",
+                OperationType::Update {
+                    change_bytes: b"+change".to_vec(),
+                },
+            ),
+            Operation::new(2, "users", "bob", OperationType::Create),
+        ];
+        adapter.save_operations(&ops).await.unwrap();
+        adapter
+            .save("users", "alice", Bytes::from("base+change"))
+            .await
+            .unwrap();
+        adapter
+            .save("users", "bob", Bytes::from("bob data"))
+            .await
+            .unwrap();
+        adapter
+            .save("users", "carol", Bytes::from("carol data"))
+            .await
+            .unwrap();
+
+        let report = adapter.verify().await.unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.documents_checked, 3);
+        assert_eq!(report.snapshots_checked, 1);
+
+        corrupt(&adapter, "documents", "alice").await;
+        corrupt(&adapter, "documents", "bob").await;
+
+        let report = adapter.verify().await.unwrap();
+        assert_eq!(
+            report.repaired,
+            vec![IntegrityItem::Document {
+                namespace: "users".to_string(),
+                id: "alice".to_string(),
+            }]
+        );
+        assert_eq!(
+            report.unrecoverable,
+            vec![IntegrityItem::Document {
+                namespace: "users".to_string(),
+                id: "bob".to_string(),
+            }]
+        );
+        assert_eq!(
+            adapter.load("users", "alice").await.unwrap(),
+            Some(Bytes::from("base+change"))
+        );
+
+        // Repaired documents pass the next check
+        let report = adapter.verify().await.unwrap();
+        assert!(report.repaired.is_empty());
+        assert_eq!(report.unrecoverable.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_verify_removes_corrupt_snapshots() {
+        let adapter = SqliteAdapter::in_memory().await.unwrap();
+        adapter.init().await.unwrap();
+
+        for version in 1..=2 {
+            adapter
+                .save_snapshot(
+                    "users",
+                    "alice",
+                    version,
+                    Bytes::from(format!("v{}", version)),
+                )
+                .await
+                .unwrap();
+        }
+        adapter
+            .save("users", "alice", Bytes::from("v2"))
+            .await
+            .unwrap();
+        adapter
+            .execute(|conn| {
+                conn.execute("UPDATE snapshots SET data = x'00' WHERE version = 2", [])
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+        corrupt(&adapter, "documents", "alice").await;
+
+        let report = adapter.verify().await.unwrap();
+        assert_eq!(
+            report.unrecoverable,
+            vec![IntegrityItem::Snapshot {
+                namespace: "users".to_string(),
+                id: "alice".to_string(),
+                version: 2,
+            }]
+        );
+        assert_eq!(report.repaired.len(), 1);
+
+        // Rebuilt from the previous good snapshot
+        assert_eq!(
+            adapter.load("users", "alice").await.unwrap(),
+            Some(Bytes::from("v1"))
+        );
+        assert_eq!(
+            adapter.load_snapshot("users", "alice").await.unwrap(),
+            Some((1, Bytes::from("v1")))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_verify_adds_missing_checksums() {
+        let adapter = SqliteAdapter::in_memory().await.unwrap();
+        adapter.init().await.unwrap();
+        adapter
+            .save("users", "alice", Bytes::from("alice data"))
+            .await
+            .unwrap();
+        let mut reader: &[u8] = b"streamed data";
+        adapter
+            .save_stream("users", "bob", &mut reader)
+            .await
+            .unwrap();
+
+        // Streamed documents are checksummed too
+        assert!(adapter.verify().await.unwrap().is_clean());
+
+        adapter
+            .execute(|conn| {
+                conn.execute("UPDATE documents SET checksum = NULL", [])
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        let report = adapter.verify().await.unwrap();
+        assert_eq!(report.checksums_added, 2);
+        assert!(report.is_clean());
+
+        corrupt(&adapter, "documents", "alice").await;
+        assert_eq!(adapter.verify().await.unwrap().unrecoverable.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_verify_encrypted() {
+        let adapter = SqliteAdapter::in_memory()
+            .await
+            .unwrap()
+            .with_encryption(EncryptionKey::generate());
+        adapter.init().await.unwrap();
+
+        adapter
+            .save_snapshot("users", "alice", 1, Bytes::from("base"))
+            .await
+            .unwrap();
+        adapter
+            .save("users", "alice", Bytes::from("base"))
+            .await
+            .unwrap();
+        corrupt(&adapter, "documents", "alice").await;
+
+        let report = adapter.verify().await.unwrap();
+        assert_eq!(report.repaired.len(), 1);
+        assert_eq!(
+            adapter.load("users", "alice").await.unwrap(),
+            Some(Bytes::from("base"))
+        );
+    }
+
     #[tokio::test]
     async fn test_sqlite_adapter_list() {
         let adapter = SqliteAdapter::in_memory().await.unwrap();
@@ -635,6 +2939,705 @@ mod tests {
         assert_eq!(results[0].0, "alice");
     }
 
+    #[tokio::test]
+    async fn test_sqlite_adapter_query_compound() {
+        let adapter = SqliteAdapter::in_memory().await.unwrap();
+        adapter.init().await.unwrap();
+
+        for id in ["user:alice", "user:bob", "userx", "post:1"] {
+            adapter.save("docs", id, Bytes::from(id)).await.unwrap();
+        }
+
+        let results = adapter
+            .query("docs", QueryFilter::id_prefix("user:"))
+            .await
+            .unwrap();
+        let ids: Vec<_> = results.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["user:alice", "user:bob"]);
+
+        let filter = QueryFilter::id_prefix("user:")
+            .and(QueryFilter::id_prefix("user:b").not())
+            .or(QueryFilter::id_prefix("post:"));
+        let results = adapter.query("docs", filter).await.unwrap();
+        let ids: Vec<_> = results.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["post:1", "user:alice"]);
+
+        let result = adapter
+            .query("docs", QueryFilter::field("status", "active"))
+            .await
+            .unwrap();
+        assert!(result.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_query_created_between() {
+        let adapter = SqliteAdapter::in_memory().await.unwrap();
+        adapter.init().await.unwrap();
+
+        let now = || {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64
+        };
+
+        adapter
+            .save("users", "alice", Bytes::from("v1"))
+            .await
+            .unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        let start = now();
+        tokio::time::sleep(tokio::time::Duration::from_millis(5)).await;
+        adapter
+            .save("users", "bob", Bytes::from("v1"))
+            .await
+            .unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(5)).await;
+        let end = now();
+
+        // Updating alice must not move her creation time into the range
+        adapter
+            .save("users", "alice", Bytes::from("v2"))
+            .await
+            .unwrap();
+
+        let results = adapter
+            .query("users", QueryFilter::created_between(start, end))
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, "bob");
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_query_page() {
+        let adapter = SqliteAdapter::in_memory().await.unwrap();
+        adapter.init().await.unwrap();
+
+        for id in ["a", "b", "c", "d", "e"] {
+            adapter.save("docs", id, Bytes::from(id)).await.unwrap();
+            tokio::time::sleep(tokio::time::Duration::from_millis(2)).await;
+        }
+
+        let options = QueryOptions::default()
+            .sort_by(SortOrder::desc(SortField::UpdatedAt))
+            .limit(2);
+        let page = adapter
+            .query_page("docs", QueryFilter::All, options.clone())
+            .await
+            .unwrap();
+        let ids: Vec<_> = page.items.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["e", "d"]);
+
+        let page = adapter
+            .query_page(
+                "docs",
+                QueryFilter::All,
+                options.clone().after(page.next_cursor.unwrap()),
+            )
+            .await
+            .unwrap();
+        let ids: Vec<_> = page.items.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "b"]);
+
+        let page = adapter
+            .query_page("docs", QueryFilter::All, options.offset(4))
+            .await
+            .unwrap();
+        let ids: Vec<_> = page.items.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["a"]);
+        assert!(page.next_cursor.is_none());
+
+        let options = QueryOptions::default().limit(3);
+        let page = adapter
+            .query_page("docs", QueryFilter::All, options.clone())
+            .await
+            .unwrap();
+        let page = adapter
+            .query_page(
+                "docs",
+                QueryFilter::All,
+                options.after(page.next_cursor.unwrap()),
+            )
+            .await
+            .unwrap();
+        let ids: Vec<_> = page.items.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["d", "e"]);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_field_query() {
+        let adapter = SqliteAdapter::in_memory().await.unwrap();
+        adapter.init().await.unwrap();
+
+        let docs = [
+            ("alice", r#"{"status":"active","age":30,"admin":true}"#),
+            ("bob", r#"{"status":"away","age":41,"admin":false}"#),
+            ("carol", r#"{"status":null,"tags":["ops"]}"#),
+            ("dave", "not json"),
+        ];
+        for (id, data) in docs {
+            adapter.save("users", id, Bytes::from(data)).await.unwrap();
+        }
+
+        let ids = |results: Vec<(String, Bytes)>| -> Vec<String> {
+            results.into_iter().map(|(id, _)| id).collect()
+        };
+
+        // Unindexed lookups still work, as a scan
+        let results = adapter
+            .query("users", QueryFilter::field("status", "active"))
+            .await
+            .unwrap();
+        assert_eq!(ids(results), vec!["alice"]);
+
+        adapter.ensure_index("users", "$.status").await.unwrap();
+        adapter.ensure_index("users", "age").await.unwrap();
+        adapter.ensure_index("users", "$.age").await.unwrap();
+        assert_eq!(
+            adapter.list_indexes("users").await.unwrap(),
+            vec!["$.age", "$.status"]
+        );
+        assert!(adapter.list_indexes("posts").await.unwrap().is_empty());
+
+        let cases = [
+            (QueryFilter::field("$.status", "\"active\""), vec!["alice"]),
+            (QueryFilter::field("$.age", "41"), vec!["bob"]),
+            (QueryFilter::field("admin", "true"), vec!["alice"]),
+            (QueryFilter::field("status", "null"), vec!["carol"]),
+            (QueryFilter::field("$.tags", r#"["ops"]"#), vec!["carol"]),
+            (QueryFilter::field("$.tags[0]", "ops"), vec!["carol"]),
+        ];
+        for (filter, expected) in cases {
+            let results = adapter.query("users", filter).await.unwrap();
+            assert_eq!(ids(results), expected);
+        }
+
+        assert!(adapter.ensure_index("users", "$.a'b").await.is_err());
+        assert!(adapter
+            .query("users", QueryFilter::field("$.", "1"))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_field_query_uses_index() {
+        let adapter = SqliteAdapter::in_memory().await.unwrap();
+        adapter.init().await.unwrap();
+        adapter.ensure_index("users", "$.status").await.unwrap();
+
+        let (sql, params) = build_query_sql(
+            "users",
+            &QueryFilter::field("$.status", "active"),
+            &QueryOptions::default(),
+        )
+        .unwrap();
+        let index = field_index_name(&JsonPath::parse("$.status").unwrap());
+
+        let plan: Vec<String> = adapter
+            .execute(move |conn| {
+                let mut stmt = conn
+                    .prepare(&format!("EXPLAIN QUERY PLAN {}", sql))
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+                let rows = stmt
+                    .query_map(rusqlite::params_from_iter(params.iter()), |row| {
+                        row.get::<_, String>(3)
+                    })
+                    .map_err(|e| StorageError::Database(e.to_string()))?
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(|e| StorageError::Database(e.to_string()))?;
+                Ok(rows)
+            })
+            .await
+            .unwrap();
+
+        assert!(
+            plan.iter().any(|step| step.contains(&index)),
+            "query plan doesn't use {}: {:?}",
+            index,
+            plan
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_migrates_created_at() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("legacy.db");
+        {
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE documents (
+                    namespace TEXT NOT NULL,
+                    id TEXT NOT NULL,
+                    data BLOB NOT NULL,
+                    updated_at INTEGER NOT NULL,
+                    PRIMARY KEY (namespace, id)
+                );
+                INSERT INTO documents VALUES ('users', 'alice', x'00', 1000);",
+            )
+            .unwrap();
+        }
+
+        let adapter = SqliteAdapter::new(&db_path).await.unwrap();
+        adapter.init().await.unwrap();
+
+        let results = adapter
+            .query("users", QueryFilter::created_between(1000, 1000))
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_encrypted_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("encrypted.db");
+        let adapter = SqliteAdapter::new(&db_path)
+            .await
+            .unwrap()
+            .with_encryption(EncryptionKey::generate());
+        assert!(adapter.is_encrypted());
+        adapter.init().await.unwrap();
+
+        let data = Bytes::from("top secret document");
+        adapter.save("users", "alice", data.clone()).await.unwrap();
+        assert_eq!(adapter.load("users", "alice").await.unwrap(), Some(data));
+
+        adapter
+            .save_snapshot("users", "alice", 3, Bytes::from("top secret snapshot"))
+            .await
+            .unwrap();
+        assert_eq!(
+            adapter.load_snapshot("users", "alice").await.unwrap(),
+            Some((3, Bytes::from("top secret snapshot")))
+        );
+
+        let ops = vec![Operation::new(
+            1,
+            "users",
+            "alice",
+            vudo_storage::operation::OperationType::Create,
+        )];
+        adapter.save_operations(&ops).await.unwrap();
+        assert_eq!(adapter.load_operations().await.unwrap()[0].id, 1);
+
+        let results = adapter.query("users", QueryFilter::All).await.unwrap();
+        assert_eq!(
+            results,
+            vec![("alice".to_string(), Bytes::from("top secret document"))]
+        );
+
+        let mut out = Vec::new();
+        adapter
+            .save_stream("users", "bob", &mut &b"top secret stream"[..])
+            .await
+            .unwrap();
+        adapter.load_stream("users", "bob", &mut out).await.unwrap();
+        assert_eq!(out, b"top secret stream");
+
+        // Nothing readable reaches the database file
+        drop(adapter);
+        let mut raw = std::fs::read(&db_path).unwrap();
+        raw.extend(std::fs::read(temp_dir.path().join("encrypted.db-wal")).unwrap_or_default());
+        assert!(!raw.windows(10).any(|w| w == b"top secret"));
+        assert!(raw.windows(5).any(|w| w == b"alice"));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_encryption_key_check() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("encrypted.db");
+        let key = EncryptionKey::generate();
+
+        let adapter = SqliteAdapter::new(&db_path)
+            .await
+            .unwrap()
+            .with_encryption(key.clone());
+        adapter.init().await.unwrap();
+        adapter
+            .save("users", "alice", Bytes::from("data"))
+            .await
+            .unwrap();
+        drop(adapter);
+
+        let wrong_key = SqliteAdapter::new(&db_path)
+            .await
+            .unwrap()
+            .with_encryption(EncryptionKey::generate());
+        assert!(matches!(
+            wrong_key.init().await,
+            Err(StorageError::Encryption(_))
+        ));
+
+        let no_key = SqliteAdapter::new(&db_path).await.unwrap();
+        assert!(matches!(
+            no_key.init().await,
+            Err(StorageError::Encryption(_))
+        ));
+
+        let reopened = SqliteAdapter::new(&db_path)
+            .await
+            .unwrap()
+            .with_encryption(key);
+        reopened.init().await.unwrap();
+        assert_eq!(
+            reopened.load("users", "alice").await.unwrap(),
+            Some(Bytes::from("data"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_encryption_rejects_plaintext_database() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("plain.db");
+
+        let plain = SqliteAdapter::new(&db_path).await.unwrap();
+        plain.init().await.unwrap();
+        plain
+            .save("users", "alice", Bytes::from("data"))
+            .await
+            .unwrap();
+        drop(plain);
+
+        let encrypted = SqliteAdapter::new(&db_path)
+            .await
+            .unwrap()
+            .with_encryption(EncryptionKey::generate());
+        assert!(matches!(
+            encrypted.init().await,
+            Err(StorageError::Encryption(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_encryption_binds_row() {
+        let adapter = SqliteAdapter::in_memory()
+            .await
+            .unwrap()
+            .with_encryption(EncryptionKey::generate());
+        adapter.init().await.unwrap();
+
+        adapter
+            .save("users", "alice", Bytes::from("alice data"))
+            .await
+            .unwrap();
+        adapter
+            .save("users", "bob", Bytes::from("bob data"))
+            .await
+            .unwrap();
+
+        // Copy alice's ciphertext over bob's
+        adapter
+            .execute(|conn| {
+                conn.execute(
+                    "UPDATE documents SET data = (SELECT data FROM documents WHERE id = 'alice')
+                     WHERE id = 'bob'",
+                    [],
+                )
+                .map_err(|e| StorageError::Database(e.to_string()))?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            adapter.load("users", "bob").await,
+            Err(StorageError::Encryption(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_encryption_disables_field_indexes() {
+        let adapter = SqliteAdapter::in_memory()
+            .await
+            .unwrap()
+            .with_encryption(EncryptionKey::generate());
+        adapter.init().await.unwrap();
+
+        assert!(matches!(
+            adapter.ensure_index("users", "$.status").await,
+            Err(StorageError::Unsupported(_))
+        ));
+        let filter = QueryFilter::And(vec![
+            QueryFilter::All,
+            QueryFilter::field("$.status", "active"),
+        ]);
+        assert!(matches!(
+            adapter.query("users", filter).await,
+            Err(StorageError::Unsupported(_))
+        ));
+    }
+
+    /// A JSON document that compresses well.
+    fn compressible_document() -> Bytes {
+        let items: Vec<String> = (0..200)
+            .map(|i| format!("{{\"id\":{},\"status\":\"active\"}}", i))
+            .collect();
+        Bytes::from(format!("{{\"items\":[{}]}}", items.join(",")))
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_compression() {
+        let policy = CompressionPolicy::new()
+            .codec(Codec::Zstd)
+            .namespace_codec("cache", Codec::Lz4)
+            .namespace_codec("raw", Codec::None);
+        let adapter = SqliteAdapter::in_memory()
+            .await
+            .unwrap()
+            .with_compression(policy);
+        adapter.init().await.unwrap();
+
+        let doc = compressible_document();
+        for namespace in ["users", "cache", "raw"] {
+            adapter.save(namespace, "alice", doc.clone()).await.unwrap();
+            adapter
+                .save_snapshot(namespace, "alice", 1, doc.clone())
+                .await
+                .unwrap();
+            assert_eq!(
+                adapter.load(namespace, "alice").await.unwrap(),
+                Some(doc.clone())
+            );
+            assert_eq!(
+                adapter.load_snapshot(namespace, "alice").await.unwrap(),
+                Some((1, doc.clone()))
+            );
+        }
+        let codecs: Vec<(String, i64)> = adapter
+            .execute(|conn| {
+                let mut stmt = conn
+                    .prepare("SELECT namespace, codec FROM documents ORDER BY namespace")
+                    .unwrap();
+                let rows = stmt
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                    .unwrap()
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .unwrap();
+                Ok(rows)
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            codecs,
+            vec![
+                ("cache".to_string(), 1),
+                ("raw".to_string(), 0),
+                ("users".to_string(), 2)
+            ]
+        );
+
+        let results = adapter.query("users", QueryFilter::All).await.unwrap();
+        assert_eq!(results, vec![("alice".to_string(), doc.clone())]);
+
+        let mut out = Vec::new();
+        adapter
+            .save_stream("users", "bob", &mut &doc[..])
+            .await
+            .unwrap();
+        adapter.load_stream("users", "bob", &mut out).await.unwrap();
+        assert_eq!(out, doc);
+
+        let stats = adapter.stats().await.unwrap();
+        assert_eq!(stats.uncompressed_document_size, 4 * doc.len());
+        assert_eq!(stats.uncompressed_snapshot_size, 3 * doc.len());
+        assert!(stats.total_document_size < stats.uncompressed_document_size / 2);
+        assert!(stats.compression_ratio() > 2.0);
+
+        // Field indexes need plaintext JSON
+        assert!(matches!(
+            adapter.ensure_index("users", "$.status").await,
+            Err(StorageError::Unsupported(_))
+        ));
+        adapter.ensure_index("raw", "$.status").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_compression_codec_change() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("compressed.db");
+        let key = EncryptionKey::generate();
+        let doc = compressible_document();
+
+        let adapter = SqliteAdapter::new(&db_path)
+            .await
+            .unwrap()
+            .with_encryption(key.clone())
+            .with_compression(CompressionPolicy::new().codec(Codec::Lz4));
+        adapter.init().await.unwrap();
+        adapter.save("users", "alice", doc.clone()).await.unwrap();
+        drop(adapter);
+
+        // Rows keep the codec they were written with
+        let adapter = SqliteAdapter::new(&db_path)
+            .await
+            .unwrap()
+            .with_encryption(key)
+            .with_compression(CompressionPolicy::new());
+        adapter.init().await.unwrap();
+        adapter.save("users", "bob", doc.clone()).await.unwrap();
+        assert_eq!(
+            adapter.load("users", "alice").await.unwrap(),
+            Some(doc.clone())
+        );
+        assert_eq!(adapter.load("users", "bob").await.unwrap(), Some(doc));
+
+        let stats = adapter.stats().await.unwrap();
+        assert!(stats.total_document_size < stats.uncompressed_document_size);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_verify_compressed() {
+        let adapter = SqliteAdapter::in_memory()
+            .await
+            .unwrap()
+            .with_compression(CompressionPolicy::new().codec(Codec::Zstd));
+        adapter.init().await.unwrap();
+
+        let doc = compressible_document();
+        adapter
+            .save_snapshot("users", "alice", 1, doc.clone())
+            .await
+            .unwrap();
+        adapter.save("users", "alice", doc.clone()).await.unwrap();
+        corrupt(&adapter, "documents", "alice").await;
+
+        let report = adapter.verify().await.unwrap();
+        assert_eq!(report.repaired.len(), 1);
+        assert_eq!(adapter.load("users", "alice").await.unwrap(), Some(doc));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_maintenance_prunes_snapshots() {
+        let adapter = SqliteAdapter::in_memory().await.unwrap();
+        adapter.init().await.unwrap();
+
+        for version in 1..=5 {
+            adapter
+                .save_snapshot("users", "alice", version, Bytes::from("snap"))
+                .await
+                .unwrap();
+        }
+        adapter
+            .save_snapshot("users", "bob", 1, Bytes::from("snap"))
+            .await
+            .unwrap();
+
+        let report = adapter
+            .maintenance(MaintenanceOptions::new().max_snapshots_per_doc(2))
+            .await
+            .unwrap();
+        assert_eq!(report.snapshots_pruned, 3);
+        assert_eq!(report.bytes_freed, 12);
+
+        let stats = adapter.stats().await.unwrap();
+        assert_eq!(stats.snapshot_count, 3);
+        let (version, _) = adapter
+            .load_snapshot("users", "alice")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(version, 5);
+
+        let report = adapter
+            .maintenance(MaintenanceOptions::new().max_snapshots_per_doc(2))
+            .await
+            .unwrap();
+        assert_eq!(report.snapshots_pruned, 0);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_maintenance_purges_tombstones() {
+        let adapter = SqliteAdapter::in_memory().await.unwrap();
+        adapter.init().await.unwrap();
+
+        for id in ["alice", "bob", "carol"] {
+            adapter
+                .save("users", id, Bytes::from("data"))
+                .await
+                .unwrap();
+            adapter
+                .save_snapshot("users", id, 1, Bytes::from("snapshot"))
+                .await
+                .unwrap();
+        }
+        adapter.delete("users", "alice").await.unwrap();
+        adapter.delete("users", "bob").await.unwrap();
+        adapter.delete("users", "nobody").await.unwrap();
+        assert_eq!(adapter.stats().await.unwrap().tombstone_count, 2);
+
+        // Saving again revives the document
+        let mut reader: &[u8] = b"back";
+        adapter
+            .save_stream("users", "bob", &mut reader)
+            .await
+            .unwrap();
+        assert_eq!(adapter.stats().await.unwrap().tombstone_count, 1);
+
+        // Still within retention
+        let report = adapter
+            .maintenance(MaintenanceOptions::new().tombstone_retention(Duration::from_secs(3600)))
+            .await
+            .unwrap();
+        assert_eq!(report.tombstones_purged, 0);
+
+        let report = adapter
+            .maintenance(MaintenanceOptions::new().tombstone_retention(Duration::ZERO))
+            .await
+            .unwrap();
+        assert_eq!(report.tombstones_purged, 1);
+        assert_eq!(report.tombstone_snapshots_purged, 1);
+        assert_eq!(report.bytes_freed, 8);
+
+        assert_eq!(adapter.load_snapshot("users", "alice").await.unwrap(), None);
+        assert!(adapter
+            .load_snapshot("users", "bob")
+            .await
+            .unwrap()
+            .is_some());
+        let stats = adapter.stats().await.unwrap();
+        assert_eq!(stats.tombstone_count, 0);
+        assert_eq!(stats.snapshot_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_adapter_maintenance_vacuum() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let adapter = SqliteAdapter::new(temp_dir.path().join("test.db"))
+            .await
+            .unwrap();
+        adapter.init().await.unwrap();
+
+        for n in 0..64 {
+            adapter
+                .save(
+                    "blobs",
+                    &n.to_string(),
+                    Bytes::from(vec![n as u8; 16 * 1024]),
+                )
+                .await
+                .unwrap();
+        }
+        for n in 1..64 {
+            adapter.delete("blobs", &n.to_string()).await.unwrap();
+        }
+
+        let report = adapter
+            .maintenance(
+                MaintenanceOptions::new()
+                    .tombstone_retention(Duration::ZERO)
+                    .vacuum()
+                    .checkpoint(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.tombstones_purged, 63);
+        assert!(report.bytes_reclaimed > 63 * 16 * 1024);
+
+        let loaded = adapter.load("blobs", "0").await.unwrap().unwrap();
+        assert_eq!(loaded.len(), 16 * 1024);
+    }
+
     #[tokio::test]
     async fn test_sqlite_adapter_stats() {
         let adapter = SqliteAdapter::in_memory().await.unwrap();
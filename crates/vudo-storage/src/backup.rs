@@ -0,0 +1,218 @@
+//! Off-device backups of a node's storage.
+//!
+//! A [`BackupTarget`] is a flat key/value blob store, such as an S3 bucket
+//! prefix or a directory. [`StorageAdapter::backup_to`](crate::StorageAdapter::backup_to)
+//! copies every document, the latest snapshot of each document and the
+//! operation queue into it, and
+//! [`StorageAdapter::restore_from`](crate::StorageAdapter::restore_from) loads
+//! them back into an adapter.
+//!
+//! # Layout
+//!
+//! Namespaces and IDs are not used as object keys, so they may contain any
+//! character. Blobs are numbered instead and described by the manifest:
+//!
+//! - `docs/<n>`: document data
+//! - `snapshots/<n>`: latest snapshot of document `n`
+//! - `operations.json`: the operation queue
+//! - `manifest.json`: a [`BackupManifest`], written last
+//!
+//! A backup without a manifest is incomplete. Backing up into a target that
+//! already holds a backup replaces it; leftover blobs of the older backup are
+//! ignored because the manifest no longer references them.
+
+use crate::{Result, StorageError};
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Version of the backup layout written by this crate.
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
+/// Key of the backup manifest.
+pub const MANIFEST_KEY: &str = "manifest.json";
+
+/// Key of the serialized operation queue.
+pub const OPERATIONS_KEY: &str = "operations.json";
+
+/// Destination of a backup: a flat store of named blobs.
+///
+/// Keys are relative, `/`-separated paths. Implementations scope them to
+/// their own location (bucket prefix, directory, ...).
+#[async_trait]
+pub trait BackupTarget: Send + Sync {
+    /// Store a blob, replacing any existing blob with the same key.
+    async fn put(&self, key: &str, data: Bytes) -> Result<()>;
+
+    /// Fetch a blob, or `None` if the key doesn't exist.
+    async fn get(&self, key: &str) -> Result<Option<Bytes>>;
+}
+
+/// Contents of a backup.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackupManifest {
+    /// Layout version ([`BACKUP_FORMAT_VERSION`]).
+    pub format_version: u32,
+    /// When the backup was taken (Unix epoch milliseconds).
+    pub created_at: u64,
+    /// Backed-up documents.
+    pub documents: Vec<BackupEntry>,
+    /// Number of operations in the backed-up queue.
+    pub operation_count: usize,
+}
+
+impl BackupManifest {
+    /// Total size of the backed-up documents and snapshots in bytes.
+    pub fn total_bytes(&self) -> u64 {
+        self.documents
+            .iter()
+            .map(|doc| doc.size + doc.snapshot.as_ref().map_or(0, |s| s.size))
+            .sum()
+    }
+}
+
+/// A backed-up document.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackupEntry {
+    /// Document namespace.
+    pub namespace: String,
+    /// Document ID.
+    pub id: String,
+    /// Key of the document data.
+    pub key: String,
+    /// Size of the document data in bytes.
+    pub size: u64,
+    /// Latest snapshot of the document, if it had one.
+    pub snapshot: Option<BackupSnapshot>,
+}
+
+/// A backed-up snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackupSnapshot {
+    /// Snapshot version.
+    pub version: u64,
+    /// Key of the snapshot data.
+    pub key: String,
+    /// Size of the snapshot data in bytes.
+    pub size: u64,
+}
+
+/// Fetch a blob referenced by a manifest, checking that it is complete.
+pub(crate) async fn fetch(target: &dyn BackupTarget, key: &str, size: u64) -> Result<Bytes> {
+    let data = target.get(key).await?.ok_or_else(|| {
+        StorageError::InvalidOperation(format!("Backup is missing object {}", key))
+    })?;
+    if data.len() as u64 != size {
+        return Err(StorageError::InvalidOperation(format!(
+            "Backup object {} has {} bytes, expected {}",
+            key,
+            data.len(),
+            size
+        )));
+    }
+    Ok(data)
+}
+
+/// Load and check the manifest of a backup.
+pub(crate) async fn read_manifest(target: &dyn BackupTarget) -> Result<BackupManifest> {
+    let data = target.get(MANIFEST_KEY).await?.ok_or_else(|| {
+        StorageError::InvalidOperation("No backup manifest found in target".to_string())
+    })?;
+    let manifest: BackupManifest = serde_json::from_slice(&data)?;
+    if manifest.format_version > BACKUP_FORMAT_VERSION {
+        return Err(StorageError::Unsupported(format!(
+            "Backup format version {} is newer than supported version {}",
+            manifest.format_version, BACKUP_FORMAT_VERSION
+        )));
+    }
+    Ok(manifest)
+}
+
+/// In-memory backup target.
+///
+/// Useful for tests and for handing a backup to code that uploads it
+/// somewhere else.
+#[derive(Debug, Default)]
+pub struct MemoryBackupTarget {
+    objects: Mutex<BTreeMap<String, Bytes>>,
+}
+
+impl MemoryBackupTarget {
+    /// Create an empty target.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keys of the stored blobs, sorted.
+    pub fn keys(&self) -> Vec<String> {
+        self.objects.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+#[async_trait]
+impl BackupTarget for MemoryBackupTarget {
+    async fn put(&self, key: &str, data: Bytes) -> Result<()> {
+        self.objects.lock().unwrap().insert(key.to_string(), data);
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Bytes>> {
+        Ok(self.objects.lock().unwrap().get(key).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_target() {
+        let target = MemoryBackupTarget::new();
+        target.put("docs/1", Bytes::from("b")).await.unwrap();
+        target.put("docs/0", Bytes::from("a")).await.unwrap();
+        target.put("docs/0", Bytes::from("a2")).await.unwrap();
+
+        assert_eq!(target.get("docs/0").await.unwrap(), Some(Bytes::from("a2")));
+        assert_eq!(target.get("docs/2").await.unwrap(), None);
+        assert_eq!(target.keys(), vec!["docs/0", "docs/1"]);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_checks_size() {
+        let target = MemoryBackupTarget::new();
+        target.put("docs/0", Bytes::from("abc")).await.unwrap();
+
+        assert_eq!(
+            fetch(&target, "docs/0", 3).await.unwrap(),
+            Bytes::from("abc")
+        );
+        assert!(fetch(&target, "docs/0", 4).await.is_err());
+        assert!(fetch(&target, "docs/1", 3).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_manifest() {
+        let target = MemoryBackupTarget::new();
+        assert!(read_manifest(&target).await.is_err());
+
+        let manifest = BackupManifest {
+            format_version: BACKUP_FORMAT_VERSION + 1,
+            created_at: 0,
+            documents: vec![],
+            operation_count: 0,
+        };
+        target
+            .put(
+                MANIFEST_KEY,
+                Bytes::from(serde_json::to_vec(&manifest).unwrap()),
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            read_manifest(&target).await,
+            Err(StorageError::Unsupported(_))
+        ));
+    }
+}
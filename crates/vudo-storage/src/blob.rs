@@ -0,0 +1,189 @@
+//! Content-addressed blob storage.
+//!
+//! Large artifacts such as WASM modules and model weights are often
+//! identical across documents and versions. A [`BlobStore`] keeps each
+//! distinct content once, addressed by its BLAKE3 [`BlobHash`], and counts
+//! the references to it. Documents store the hash instead of the bytes.
+//!
+//! # Reference counting
+//!
+//! [`BlobStore::put_blob`] and [`BlobStore::retain_blob`] add a reference;
+//! [`BlobStore::release_blob`] drops one. Blobs without references stay
+//! readable until [`BlobStore::gc_blobs`] removes them, so putting the same
+//! content again before a collection doesn't rewrite it.
+//!
+//! Blobs don't count towards document [quotas](crate::quota).
+
+use crate::{Result, StorageError};
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// BLAKE3 hash of a blob's content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct BlobHash([u8; 32]);
+
+impl BlobHash {
+    /// Hash of `data`.
+    pub fn of(data: &[u8]) -> Self {
+        Self(*blake3::hash(data).as_bytes())
+    }
+
+    /// Create a hash from its raw bytes.
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// Raw bytes of the hash.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Lowercase hex form of the hash.
+    pub fn to_hex(&self) -> String {
+        self.0.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Check that `data` has this hash.
+    pub fn verify(&self, data: &[u8]) -> Result<()> {
+        let actual = Self::of(data);
+        if actual != *self {
+            return Err(StorageError::Internal(format!(
+                "Blob {} is corrupt (content hashes to {})",
+                self, actual
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for BlobHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl FromStr for BlobHash {
+    type Err = StorageError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || StorageError::InvalidOperation(format!("Invalid blob hash '{}'", s));
+        if s.len() != 64 || !s.is_ascii() {
+            return Err(invalid());
+        }
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).map_err(|_| invalid())?;
+        }
+        Ok(Self(bytes))
+    }
+}
+
+impl From<BlobHash> for String {
+    fn from(hash: BlobHash) -> Self {
+        hash.to_hex()
+    }
+}
+
+impl TryFrom<String> for BlobHash {
+    type Error = StorageError;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+/// Size and reference count of a stored blob.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobInfo {
+    /// Content hash.
+    pub hash: BlobHash,
+    /// Size in bytes.
+    pub size: u64,
+    /// Number of references.
+    pub refcount: u64,
+}
+
+/// Outcome of a blob garbage collection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobGcReport {
+    /// Number of unreferenced blobs removed.
+    pub blobs_removed: usize,
+    /// Bytes freed.
+    pub bytes_freed: u64,
+}
+
+/// Content-addressed, reference-counted blob storage.
+///
+/// An extension of [`StorageAdapter`](crate::StorageAdapter) for adapters
+/// that can store large opaque artifacts next to documents.
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    /// Store `data` if it isn't stored yet and add a reference to it.
+    ///
+    /// Returns the content hash.
+    async fn put_blob(&self, data: Bytes) -> Result<BlobHash>;
+
+    /// Load a blob, or `None` if it isn't stored.
+    async fn get_blob(&self, hash: &BlobHash) -> Result<Option<Bytes>>;
+
+    /// Size and reference count of a blob, or `None` if it isn't stored.
+    async fn blob_info(&self, hash: &BlobHash) -> Result<Option<BlobInfo>>;
+
+    /// Add a reference to a stored blob without uploading it again.
+    ///
+    /// Returns the new reference count, or [`StorageError::BlobNotFound`]
+    /// if the blob isn't stored.
+    async fn retain_blob(&self, hash: &BlobHash) -> Result<u64>;
+
+    /// Drop a reference to a blob.
+    ///
+    /// Returns the new reference count. The blob stays stored until
+    /// [`BlobStore::gc_blobs`] runs. Fails if the blob isn't stored or has
+    /// no references.
+    async fn release_blob(&self, hash: &BlobHash) -> Result<u64>;
+
+    /// Remove every blob without references.
+    async fn gc_blobs(&self) -> Result<BlobGcReport>;
+
+    /// Check if a blob is stored.
+    async fn has_blob(&self, hash: &BlobHash) -> Result<bool> {
+        Ok(self.blob_info(hash).await?.is_some())
+    }
+}
+
+/// Error for releasing a blob without references.
+pub fn unreferenced_blob(hash: &BlobHash) -> StorageError {
+    StorageError::InvalidOperation(format!("Blob {} has no references to release", hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blob_hash() {
+        let hash = BlobHash::of(b"wasm");
+        assert_eq!(hash, BlobHash::of(b"wasm"));
+        assert_ne!(hash, BlobHash::of(b"onnx"));
+        assert_eq!(hash.to_hex(), blake3::hash(b"wasm").to_hex().as_str());
+
+        assert!(hash.verify(b"wasm").is_ok());
+        assert!(hash.verify(b"wasm!").is_err());
+    }
+
+    #[test]
+    fn test_blob_hash_parse() {
+        let hash = BlobHash::of(b"wasm");
+        assert_eq!(hash.to_string().parse::<BlobHash>().unwrap(), hash);
+        assert!("abc".parse::<BlobHash>().is_err());
+        assert!("zz".repeat(32).parse::<BlobHash>().is_err());
+
+        let json = serde_json::to_string(&hash).unwrap();
+        assert_eq!(json, format!("\"{}\"", hash));
+        assert_eq!(serde_json::from_str::<BlobHash>(&json).unwrap(), hash);
+    }
+}
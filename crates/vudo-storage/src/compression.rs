@@ -0,0 +1,301 @@
+//! Compression codecs for stored values.
+//!
+//! Automerge documents and snapshots compress well. An adapter configured
+//! with a [`CompressionPolicy`] compresses document and snapshot data with the
+//! [`Codec`] chosen for its namespace before storing it (and before encrypting
+//! it), and records the codec next to the value. Loads decompress with the
+//! recorded codec, so changing a namespace's codec only affects new writes
+//! and values written before stay readable.
+//!
+//! [`StorageStats`](crate::StorageStats) reports the sizes before and after
+//! compression.
+//!
+//! # Features
+//!
+//! The codecs are behind the `lz4` and `zstd` features, both enabled by
+//! default. Compressing or decompressing with a codec that wasn't compiled in
+//! fails with [`StorageError::Unsupported`].
+
+use crate::{Result, StorageError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Zstandard compression level.
+pub const ZSTD_LEVEL: i32 = 3;
+
+/// Values smaller than this are stored uncompressed by default.
+pub const DEFAULT_MIN_COMPRESS_SIZE: usize = 64;
+
+/// A compression codec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    /// Stored as is.
+    #[default]
+    None,
+    /// LZ4: fast, moderate ratio.
+    Lz4,
+    /// Zstandard: slower, better ratio.
+    Zstd,
+}
+
+impl Codec {
+    /// Stable numeric ID, as recorded next to stored values.
+    pub fn id(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Lz4 => 1,
+            Self::Zstd => 2,
+        }
+    }
+
+    /// The codec with a numeric ID.
+    pub fn from_id(id: u8) -> Result<Self> {
+        match id {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Lz4),
+            2 => Ok(Self::Zstd),
+            _ => Err(StorageError::Compression(format!(
+                "Unknown codec ID {}",
+                id
+            ))),
+        }
+    }
+
+    /// Whether the codec was compiled in.
+    pub fn is_available(self) -> bool {
+        match self {
+            Self::None => true,
+            Self::Lz4 => cfg!(feature = "lz4"),
+            Self::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    /// Compress `data`.
+    pub fn compress(self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(data.to_vec()),
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => Ok(lz4_flex::compress_prepend_size(data)),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => zstd::bulk::compress(data, ZSTD_LEVEL)
+                .map_err(|e| StorageError::Compression(e.to_string())),
+            #[allow(unreachable_patterns)]
+            codec => Err(codec.unavailable()),
+        }
+    }
+
+    /// Decompress data compressed with this codec.
+    pub fn decompress(self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(data.to_vec()),
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => lz4_flex::decompress_size_prepended(data)
+                .map_err(|e| StorageError::Compression(e.to_string())),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => {
+                zstd::decode_all(data).map_err(|e| StorageError::Compression(e.to_string()))
+            }
+            #[allow(unreachable_patterns)]
+            codec => Err(codec.unavailable()),
+        }
+    }
+
+    fn unavailable(self) -> StorageError {
+        StorageError::Unsupported(format!(
+            "The {} codec is not compiled in (enable the `{}` feature)",
+            self, self
+        ))
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::None => "none",
+            Self::Lz4 => "lz4",
+            Self::Zstd => "zstd",
+        })
+    }
+}
+
+impl FromStr for Codec {
+    type Err = StorageError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "none" => Ok(Self::None),
+            "lz4" => Ok(Self::Lz4),
+            "zstd" => Ok(Self::Zstd),
+            _ => Err(StorageError::InvalidOperation(format!(
+                "Unknown codec '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/// The codec used for each namespace.
+///
+/// The default stores everything uncompressed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CompressionPolicy {
+    /// Codec of namespaces without their own codec.
+    pub default_codec: Codec,
+    /// Codecs of individual namespaces, overriding `default_codec`.
+    pub namespace_codecs: BTreeMap<String, Codec>,
+    /// Values smaller than this many bytes are stored uncompressed.
+    pub min_size: usize,
+}
+
+impl Default for CompressionPolicy {
+    fn default() -> Self {
+        Self {
+            default_codec: Codec::None,
+            namespace_codecs: BTreeMap::new(),
+            min_size: DEFAULT_MIN_COMPRESS_SIZE,
+        }
+    }
+}
+
+impl CompressionPolicy {
+    /// Create a policy that stores everything uncompressed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compress every namespace without its own codec with `codec`.
+    pub fn codec(mut self, codec: Codec) -> Self {
+        self.default_codec = codec;
+        self
+    }
+
+    /// Compress `namespace` with `codec`.
+    pub fn namespace_codec(mut self, namespace: impl Into<String>, codec: Codec) -> Self {
+        self.namespace_codecs.insert(namespace.into(), codec);
+        self
+    }
+
+    /// Store values smaller than `bytes` uncompressed.
+    pub fn min_size(mut self, bytes: usize) -> Self {
+        self.min_size = bytes;
+        self
+    }
+
+    /// The codec that applies to `namespace`.
+    pub fn codec_for(&self, namespace: &str) -> Codec {
+        self.namespace_codecs
+            .get(namespace)
+            .copied()
+            .unwrap_or(self.default_codec)
+    }
+
+    /// Whether no namespace is compressed.
+    pub fn is_disabled(&self) -> bool {
+        self.default_codec == Codec::None
+            && self
+                .namespace_codecs
+                .values()
+                .all(|&codec| codec == Codec::None)
+    }
+
+    /// Check that every codec of the policy was compiled in.
+    pub fn validate(&self) -> Result<()> {
+        std::iter::once(self.default_codec)
+            .chain(self.namespace_codecs.values().copied())
+            .find(|codec| !codec.is_available())
+            .map_or(Ok(()), |codec| Err(codec.unavailable()))
+    }
+
+    /// Compress a value of `namespace` for storage.
+    ///
+    /// Returns the codec used, which is [`Codec::None`] for values below
+    /// the minimum size and for values the codec doesn't shrink.
+    pub fn compress(&self, namespace: &str, data: Vec<u8>) -> Result<(Codec, Vec<u8>)> {
+        let codec = self.codec_for(namespace);
+        if codec == Codec::None || data.len() < self.min_size {
+            return Ok((Codec::None, data));
+        }
+        let compressed = codec.compress(&data)?;
+        if compressed.len() >= data.len() {
+            return Ok((Codec::None, data));
+        }
+        Ok((codec, compressed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(not(feature = "zstd"))]
+    fn test_unavailable_codec() {
+        assert!(!Codec::Zstd.is_available());
+        assert!(matches!(
+            Codec::Zstd.compress(b"data"),
+            Err(StorageError::Unsupported(_))
+        ));
+        assert!(CompressionPolicy::new()
+            .namespace_codec("users", Codec::Zstd)
+            .validate()
+            .is_err());
+    }
+
+    #[cfg(all(feature = "lz4", feature = "zstd"))]
+    fn compressible() -> Vec<u8> {
+        b"{\"name\":\"alice\",\"tags\":[\"a\",\"b\"]}".repeat(100)
+    }
+
+    #[test]
+    #[cfg(all(feature = "lz4", feature = "zstd"))]
+    fn test_codec_round_trip() {
+        let data = compressible();
+        for codec in [Codec::None, Codec::Lz4, Codec::Zstd] {
+            let compressed = codec.compress(&data).unwrap();
+            if codec != Codec::None {
+                assert!(
+                    compressed.len() < data.len() / 4,
+                    "{} didn't compress",
+                    codec
+                );
+            }
+            assert_eq!(codec.decompress(&compressed).unwrap(), data);
+            assert_eq!(Codec::from_id(codec.id()).unwrap(), codec);
+            assert_eq!(codec.to_string().parse::<Codec>().unwrap(), codec);
+        }
+        assert!(Codec::from_id(9).is_err());
+        assert!(Codec::Zstd.decompress(b"not zstd").is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "lz4", feature = "zstd"))]
+    fn test_policy_per_namespace() {
+        let policy = CompressionPolicy::new()
+            .codec(Codec::Lz4)
+            .namespace_codec("models", Codec::Zstd)
+            .namespace_codec("media", Codec::None);
+        assert_eq!(policy.codec_for("users"), Codec::Lz4);
+        assert_eq!(policy.codec_for("models"), Codec::Zstd);
+        assert_eq!(policy.codec_for("media"), Codec::None);
+        assert!(!policy.is_disabled());
+        assert!(CompressionPolicy::new().is_disabled());
+        assert!(policy.validate().is_ok());
+
+        let (codec, data) = policy.compress("models", compressible()).unwrap();
+        assert_eq!(codec, Codec::Zstd);
+        assert_eq!(codec.decompress(&data).unwrap(), compressible());
+
+        // Small and incompressible values are stored as is
+        let (codec, _) = policy.compress("users", b"tiny".to_vec()).unwrap();
+        assert_eq!(codec, Codec::None);
+        let noise: Vec<u8> = (0..32u32)
+            .flat_map(|i| *blake3::hash(&i.to_le_bytes()).as_bytes())
+            .collect();
+        let (codec, data) = policy.compress("users", noise.clone()).unwrap();
+        assert_eq!(codec, Codec::None);
+        assert_eq!(data, noise);
+    }
+}
@@ -1,5 +1,7 @@
 //! Error types for storage operations.
 
+use crate::blob::BlobHash;
+use crate::quota::QuotaScope;
 use thiserror::Error;
 
 /// Result type for storage operations.
@@ -29,18 +31,37 @@ pub enum StorageError {
         id: String,
     },
 
+    /// Blob not found.
+    #[error("Blob not found: {0}")]
+    BlobNotFound(BlobHash),
+
     /// Invalid operation.
     #[error("Invalid operation: {0}")]
     InvalidOperation(String),
 
-    /// Storage is full or quota exceeded.
-    #[error("Storage quota exceeded")]
-    QuotaExceeded,
+    /// A write would exceed a storage quota.
+    #[error("Storage quota exceeded: {scope} would use {required} of {limit} bytes")]
+    QuotaExceeded {
+        /// The namespace or store whose limit was hit.
+        scope: QuotaScope,
+        /// The limit in bytes.
+        limit: u64,
+        /// The bytes the scope would hold after the write.
+        required: u64,
+    },
 
     /// Concurrent modification error.
     #[error("Concurrent modification detected")]
     ConcurrentModification,
 
+    /// Encryption or decryption failed.
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+
+    /// Compression or decompression failed.
+    #[error("Compression error: {0}")]
+    Compression(String),
+
     /// Unsupported feature.
     #[error("Unsupported feature: {0}")]
     Unsupported(String),
@@ -0,0 +1,204 @@
+//! Secondary index definitions over JSON document fields.
+//!
+//! Adapters that store JSON documents can index fields extracted from the
+//! document bytes (see [`StorageAdapter::ensure_index`](crate::StorageAdapter::ensure_index)),
+//! so that [`QueryFilter::Field`](crate::QueryFilter::Field) lookups don't need
+//! a full scan of the namespace.
+
+use crate::error::{Result, StorageError};
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+/// A segment of a [`JsonPath`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    /// Object member.
+    Key(String),
+    /// Array element.
+    Index(usize),
+}
+
+/// A JSON path selecting a single field of a document.
+///
+/// Supports the subset shared by SQLite's JSON1 functions and simple
+/// in-memory evaluation: `$.user.name`, `$.tags[0]`. A path without the
+/// leading `$` (e.g. `user.name`) is treated as relative to the document
+/// root. Member names are limited to ASCII letters, digits and `_`, so paths
+/// can be embedded in index definitions safely.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct JsonPath {
+    /// Segments from the document root.
+    segments: Vec<PathSegment>,
+}
+
+impl JsonPath {
+    /// Parse a JSON path.
+    pub fn parse(path: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            StorageError::InvalidOperation(format!("Invalid JSON path '{}': {}", path, reason))
+        };
+
+        let rest = match path.strip_prefix('$') {
+            Some(rest) => rest,
+            None if path.starts_with('[') => path,
+            None => return Self::parse(&format!("$.{}", path)),
+        };
+
+        let mut segments = Vec::new();
+        let mut chars = rest.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '.' => {
+                    let mut key = String::new();
+                    while let Some(&c) = chars.peek() {
+                        if !(c.is_ascii_alphanumeric() || c == '_') {
+                            break;
+                        }
+                        key.push(c);
+                        chars.next();
+                    }
+                    if key.is_empty() {
+                        return Err(invalid("expected a member name after '.'"));
+                    }
+                    segments.push(PathSegment::Key(key));
+                }
+                '[' => {
+                    let mut index = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == ']' {
+                            closed = true;
+                            break;
+                        }
+                        index.push(c);
+                    }
+                    if !closed {
+                        return Err(invalid("unterminated '['"));
+                    }
+                    let index = index
+                        .parse()
+                        .map_err(|_| invalid("expected an array index inside '[]'"))?;
+                    segments.push(PathSegment::Index(index));
+                }
+                _ => return Err(invalid(&format!("unexpected character '{}'", c))),
+            }
+        }
+
+        if segments.is_empty() {
+            return Err(invalid("path selects the whole document"));
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Get the path segments.
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    /// Select the field from a parsed document.
+    pub fn extract<'a>(&self, document: &'a Value) -> Option<&'a Value> {
+        self.segments
+            .iter()
+            .try_fold(document, |value, segment| match segment {
+                PathSegment::Key(key) => value.get(key),
+                PathSegment::Index(index) => value.get(index),
+            })
+    }
+
+    /// Select the field from document bytes.
+    ///
+    /// Returns `None` if the bytes are not JSON or the field is missing.
+    pub fn extract_from_bytes(&self, data: &[u8]) -> Option<Value> {
+        let document: Value = serde_json::from_slice(data).ok()?;
+        self.extract(&document).cloned()
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        for segment in &self.segments {
+            match segment {
+                PathSegment::Key(key) => write!(f, ".{}", key)?,
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = StorageError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+/// Parse the expected value of a [`QueryFilter::Field`](crate::QueryFilter::Field).
+///
+/// The value is JSON; anything that doesn't parse is taken as a plain string,
+/// so `field("status", "active")` and `field("status", "\"active\"")` match
+/// the same documents.
+pub fn parse_field_value(value: &str) -> Value {
+    serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(
+            JsonPath::parse("$.user.name").unwrap().to_string(),
+            "$.user.name"
+        );
+        assert_eq!(
+            JsonPath::parse("user.name").unwrap().to_string(),
+            "$.user.name"
+        );
+        assert_eq!(JsonPath::parse("status").unwrap().to_string(), "$.status");
+        assert_eq!(
+            JsonPath::parse("$.tags[2]").unwrap().to_string(),
+            "$.tags[2]"
+        );
+        assert_eq!(JsonPath::parse("$[0].id").unwrap().to_string(), "$[0].id");
+    }
+
+    #[test]
+    fn test_parse_rejects_unsafe_paths() {
+        assert!(JsonPath::parse("$").is_err());
+        assert!(JsonPath::parse("$.").is_err());
+        assert!(JsonPath::parse("$.a'b").is_err());
+        assert!(JsonPath::parse("$.a[x]").is_err());
+        assert!(JsonPath::parse("$.a[1").is_err());
+        assert!(JsonPath::parse("$.a b").is_err());
+    }
+
+    #[test]
+    fn test_extract() {
+        let doc = json!({"user": {"name": "alice", "tags": ["admin", "ops"]}});
+
+        let name = JsonPath::parse("user.name").unwrap();
+        assert_eq!(name.extract(&doc), Some(&json!("alice")));
+
+        let tag = JsonPath::parse("$.user.tags[1]").unwrap();
+        assert_eq!(tag.extract(&doc), Some(&json!("ops")));
+
+        let missing = JsonPath::parse("$.user.email").unwrap();
+        assert_eq!(missing.extract(&doc), None);
+        assert_eq!(missing.extract_from_bytes(b"not json"), None);
+    }
+
+    #[test]
+    fn test_parse_field_value() {
+        assert_eq!(parse_field_value("\"active\""), json!("active"));
+        assert_eq!(parse_field_value("active"), json!("active"));
+        assert_eq!(parse_field_value("42"), json!(42));
+        assert_eq!(parse_field_value("true"), json!(true));
+    }
+}
@@ -3,14 +3,24 @@
 //! This crate provides the core [`StorageAdapter`] trait that all platform-specific
 //! storage implementations must implement. It supports:
 //! - Document persistence (save/load/delete)
+//! - Batched saves and transactions
+//! - Streaming save/load for large documents and blobs
 //! - Operation queue persistence
 //! - Snapshot management
 //! - Query capabilities
+//! - Secondary indexes over JSON document fields
+//! - Maintenance (snapshot pruning, tombstone purging, compaction)
+//! - Backup to and restore from off-device targets
+//! - Storage quotas with eviction hooks
+//! - Content-addressed blobs shared across documents ([`BlobStore`])
+//! - Corruption detection and recovery ([`StorageAdapter::verify`])
+//! - Per-namespace compression of documents and snapshots ([`CompressionPolicy`])
 //!
 //! # Platform Implementations
 //!
 //! - **vudo-storage-browser**: Browser storage using OPFS + SQLite WASM
-//! - **vudo-storage-native**: Desktop/Mobile/Server storage using native SQLite
+//! - **vudo-storage-native**: Desktop/Mobile/Server storage using native SQLite,
+//!   or plain files for embedded and CLI use
 //!
 //! # Example
 //!
@@ -32,16 +42,40 @@
 //! }
 //! ```
 
+pub mod backup;
+pub mod blob;
+pub mod compression;
 pub mod error;
+pub mod index;
+pub mod integrity;
+pub mod maintenance;
 pub mod operation;
 pub mod query;
-
+pub mod quota;
+
+pub use backup::{
+    BackupEntry, BackupManifest, BackupSnapshot, BackupTarget, MemoryBackupTarget,
+    BACKUP_FORMAT_VERSION,
+};
+pub use blob::{unreferenced_blob, BlobGcReport, BlobHash, BlobInfo, BlobStore};
+pub use compression::{Codec, CompressionPolicy};
 pub use error::{Result, StorageError};
+pub use index::{parse_field_value, JsonPath, PathSegment};
+pub use integrity::{IntegrityItem, IntegrityReport};
+pub use maintenance::{MaintenanceOptions, MaintenanceReport};
 pub use operation::Operation;
-pub use query::QueryFilter;
+pub use query::{
+    prefix_upper_bound, Cursor, QueryFilter, QueryOptions, QueryPage, SortDirection, SortField,
+    SortOrder,
+};
+pub use quota::{
+    enforce_quota, EvictionCandidate, EvictionHook, LeastRecentlyUpdated, QuotaScope, QuotaUsage,
+    StorageQuota,
+};
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
 /// Platform-agnostic storage adapter trait.
 ///
@@ -74,8 +108,86 @@ pub trait StorageAdapter: Send + Sync {
     /// * `id` - Document ID within the namespace
     async fn load(&self, namespace: &str, id: &str) -> Result<Option<Bytes>>;
 
+    /// Save several documents at once.
+    ///
+    /// Adapters with transactions write the whole batch atomically, which is
+    /// much faster than saving the documents one by one. The default
+    /// implementation calls [`StorageAdapter::save`] for each document and is
+    /// not atomic.
+    ///
+    /// # Arguments
+    ///
+    /// * `docs` - (namespace, id, data) of each document, saved in order
+    async fn save_batch(&self, docs: &[(&str, &str, Bytes)]) -> Result<()> {
+        for (namespace, id, data) in docs {
+            self.save(namespace, id, data.clone()).await?;
+        }
+        Ok(())
+    }
+
+    /// Save a document from a byte stream.
+    ///
+    /// Meant for large documents, model files and WASM modules. The default
+    /// implementation buffers the stream and calls [`StorageAdapter::save`];
+    /// adapters that can write incrementally should override it.
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - Document namespace
+    /// * `id` - Document ID within the namespace
+    /// * `reader` - Document data, read to the end
+    ///
+    /// # Returns
+    ///
+    /// The number of bytes saved.
+    async fn save_stream(
+        &self,
+        namespace: &str,
+        id: &str,
+        reader: &mut (dyn AsyncRead + Unpin + Send),
+    ) -> Result<u64> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).await?;
+        let len = data.len() as u64;
+        self.save(namespace, id, Bytes::from(data)).await?;
+        Ok(len)
+    }
+
+    /// Load a document into a byte stream.
+    ///
+    /// The default implementation calls [`StorageAdapter::load`] and writes
+    /// the whole document; adapters that can read incrementally should
+    /// override it.
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - Document namespace
+    /// * `id` - Document ID within the namespace
+    /// * `writer` - Destination of the document data
+    ///
+    /// # Returns
+    ///
+    /// The number of bytes written, or `None` if the document doesn't exist.
+    async fn load_stream(
+        &self,
+        namespace: &str,
+        id: &str,
+        writer: &mut (dyn AsyncWrite + Unpin + Send),
+    ) -> Result<Option<u64>> {
+        let Some(data) = self.load(namespace, id).await? else {
+            return Ok(None);
+        };
+        writer.write_all(&data).await?;
+        writer.flush().await?;
+        Ok(Some(data.len() as u64))
+    }
+
     /// Delete a document.
     ///
+    /// Adapters keep a tombstone recording when the document was deleted, and
+    /// keep its snapshots, until [`StorageAdapter::maintenance`] purges them.
+    /// Saving the document again removes the tombstone.
+    ///
     /// # Arguments
     ///
     /// * `namespace` - Document namespace
@@ -89,6 +201,15 @@ pub trait StorageAdapter: Send + Sync {
     /// * `namespace` - Document namespace to list
     async fn list(&self, namespace: &str) -> Result<Vec<String>>;
 
+    /// List the namespaces that hold at least one document, sorted.
+    ///
+    /// The default implementation returns [`StorageError::Unsupported`].
+    async fn list_namespaces(&self) -> Result<Vec<String>> {
+        Err(StorageError::Unsupported(
+            "Listing namespaces is not supported by this adapter".to_string(),
+        ))
+    }
+
     /// Save operation queue.
     ///
     /// Persists the operation queue for offline mutation tracking. This
@@ -149,6 +270,262 @@ pub trait StorageAdapter: Send + Sync {
     /// A vector of (id, data) tuples matching the filter.
     async fn query(&self, namespace: &str, filter: QueryFilter) -> Result<Vec<(String, Bytes)>>;
 
+    /// Query documents with a filter, sort order and pagination.
+    ///
+    /// The default implementation runs [`StorageAdapter::query`] and pages
+    /// through the results in memory, which only supports sorting by ID.
+    /// Adapters with timestamp indexes should override it.
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - Document namespace to query
+    /// * `filter` - Query filter
+    /// * `options` - Sort order, limit, offset and cursor
+    async fn query_page(
+        &self,
+        namespace: &str,
+        filter: QueryFilter,
+        options: QueryOptions,
+    ) -> Result<QueryPage> {
+        if options.sort.field != SortField::Id {
+            return Err(StorageError::Unsupported(format!(
+                "Sorting by {:?} is not supported by this adapter",
+                options.sort.field
+            )));
+        }
+
+        let mut items = self.query(namespace, filter).await?;
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        if options.sort.direction == SortDirection::Descending {
+            items.reverse();
+        }
+
+        if let Some(cursor) = &options.cursor {
+            items.retain(|(id, _)| match options.sort.direction {
+                SortDirection::Ascending => id > &cursor.id,
+                SortDirection::Descending => id < &cursor.id,
+            });
+        }
+
+        let items: Vec<_> = items
+            .into_iter()
+            .skip(options.offset)
+            .take(options.limit.unwrap_or(usize::MAX))
+            .collect();
+
+        let next_cursor = match (options.limit, items.last()) {
+            (Some(limit), Some((id, _))) if items.len() == limit => Some(Cursor {
+                timestamp: None,
+                id: id.clone(),
+            }),
+            _ => None,
+        };
+
+        Ok(QueryPage { items, next_cursor })
+    }
+
+    /// Ensure a secondary index exists over a JSON field of a namespace.
+    ///
+    /// Documents in the namespace are expected to be JSON; the field is
+    /// extracted from the document bytes on every save, and
+    /// [`QueryFilter::Field`] lookups on the same path use the index instead
+    /// of scanning. Documents that aren't JSON or lack the field are indexed
+    /// as missing. This is idempotent.
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - Document namespace to index
+    /// * `json_path` - Field to index (see [`JsonPath`])
+    async fn ensure_index(&self, namespace: &str, json_path: &str) -> Result<()> {
+        JsonPath::parse(json_path)?;
+        Err(StorageError::Unsupported(format!(
+            "Secondary indexes are not supported by this adapter ({}: {})",
+            namespace, json_path
+        )))
+    }
+
+    /// List the indexed JSON paths of a namespace, in canonical form.
+    async fn list_indexes(&self, _namespace: &str) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    /// Begin a transaction.
+    ///
+    /// Every write made through this adapter until [`StorageAdapter::commit`]
+    /// or [`StorageAdapter::rollback`] joins the transaction and is applied
+    /// atomically; reads through the adapter see the uncommitted writes.
+    /// Transactions don't nest.
+    ///
+    /// The default implementation returns [`StorageError::Unsupported`].
+    async fn begin(&self) -> Result<()> {
+        Err(StorageError::Unsupported(
+            "Transactions are not supported by this adapter".to_string(),
+        ))
+    }
+
+    /// Commit the transaction started by [`StorageAdapter::begin`].
+    async fn commit(&self) -> Result<()> {
+        Err(StorageError::Unsupported(
+            "Transactions are not supported by this adapter".to_string(),
+        ))
+    }
+
+    /// Roll back the transaction started by [`StorageAdapter::begin`],
+    /// discarding its writes.
+    async fn rollback(&self) -> Result<()> {
+        Err(StorageError::Unsupported(
+            "Transactions are not supported by this adapter".to_string(),
+        ))
+    }
+
+    /// Run maintenance: prune old snapshots, purge expired tombstones and
+    /// compact the storage.
+    ///
+    /// The default implementation returns [`StorageError::Unsupported`].
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Steps to run (see [`MaintenanceOptions`])
+    async fn maintenance(&self, _options: MaintenanceOptions) -> Result<MaintenanceReport> {
+        Err(StorageError::Unsupported(
+            "Maintenance is not supported by this adapter".to_string(),
+        ))
+    }
+
+    /// Check stored documents and snapshots for corruption.
+    ///
+    /// Corrupt documents are rebuilt from their latest good snapshot plus
+    /// the queued operations recorded since; what can't be rebuilt is
+    /// reported as unrecoverable. See [`integrity`].
+    ///
+    /// The default implementation returns [`StorageError::Unsupported`].
+    async fn verify(&self) -> Result<IntegrityReport> {
+        Err(StorageError::Unsupported(
+            "Integrity checks are not supported by this adapter".to_string(),
+        ))
+    }
+
+    /// Back up every document, the latest snapshot of each document and the
+    /// operation queue to `target`.
+    ///
+    /// Tombstones, older snapshots and snapshots of deleted documents are not
+    /// backed up. Needs [`StorageAdapter::list_namespaces`]. See
+    /// [`backup`] for the layout.
+    ///
+    /// # Returns
+    ///
+    /// The manifest written to the target.
+    async fn backup_to(&self, target: &dyn BackupTarget) -> Result<BackupManifest> {
+        let mut documents = Vec::new();
+        for namespace in self.list_namespaces().await? {
+            for id in self.list(&namespace).await? {
+                // Deleted since it was listed
+                let Some(data) = self.load(&namespace, &id).await? else {
+                    continue;
+                };
+                let n = documents.len();
+                let key = format!("docs/{}", n);
+                let size = data.len() as u64;
+                target.put(&key, data).await?;
+
+                let snapshot = match self.load_snapshot(&namespace, &id).await? {
+                    Some((version, data)) => {
+                        let key = format!("snapshots/{}", n);
+                        let size = data.len() as u64;
+                        target.put(&key, data).await?;
+                        Some(BackupSnapshot { version, key, size })
+                    }
+                    None => None,
+                };
+
+                documents.push(BackupEntry {
+                    namespace: namespace.clone(),
+                    id,
+                    key,
+                    size,
+                    snapshot,
+                });
+            }
+        }
+
+        let ops = self.load_operations().await?;
+        target
+            .put(
+                backup::OPERATIONS_KEY,
+                Bytes::from(serde_json::to_vec(&ops)?),
+            )
+            .await?;
+
+        let manifest = BackupManifest {
+            format_version: BACKUP_FORMAT_VERSION,
+            created_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            documents,
+            operation_count: ops.len(),
+        };
+        target
+            .put(
+                backup::MANIFEST_KEY,
+                Bytes::from(serde_json::to_vec(&manifest)?),
+            )
+            .await?;
+        Ok(manifest)
+    }
+
+    /// Restore a backup written by [`StorageAdapter::backup_to`].
+    ///
+    /// Documents and snapshots in the backup overwrite existing ones and the
+    /// operation queue is replaced; other documents are left alone, so call
+    /// [`StorageAdapter::clear`] first for an exact copy. On adapters with
+    /// transactions the restore is atomic.
+    ///
+    /// # Returns
+    ///
+    /// The manifest of the restored backup.
+    async fn restore_from(&self, target: &dyn BackupTarget) -> Result<BackupManifest> {
+        let manifest = backup::read_manifest(target).await?;
+        let ops_data = target.get(backup::OPERATIONS_KEY).await?.ok_or_else(|| {
+            StorageError::InvalidOperation(format!(
+                "Backup is missing object {}",
+                backup::OPERATIONS_KEY
+            ))
+        })?;
+        let ops: Vec<Operation> = serde_json::from_slice(&ops_data)?;
+
+        let transactional = match self.begin().await {
+            Ok(()) => true,
+            Err(StorageError::Unsupported(_)) => false,
+            Err(e) => return Err(e),
+        };
+
+        let restored = async {
+            for doc in &manifest.documents {
+                let data = backup::fetch(target, &doc.key, doc.size).await?;
+                self.save(&doc.namespace, &doc.id, data).await?;
+                if let Some(snapshot) = &doc.snapshot {
+                    let data = backup::fetch(target, &snapshot.key, snapshot.size).await?;
+                    self.save_snapshot(&doc.namespace, &doc.id, snapshot.version, data)
+                        .await?;
+                }
+            }
+            self.save_operations(&ops).await
+        }
+        .await;
+
+        match (restored, transactional) {
+            (Ok(()), true) => self.commit().await?,
+            (Ok(()), false) => {}
+            (Err(e), true) => {
+                self.rollback().await?;
+                return Err(e);
+            }
+            (Err(e), false) => return Err(e),
+        }
+        Ok(manifest)
+    }
+
     /// Get storage statistics.
     ///
     /// Returns statistics about the storage (sizes, counts, etc.).
@@ -177,6 +554,26 @@ pub struct StorageStats {
     pub snapshot_count: usize,
     /// Total size of all snapshots in bytes.
     pub total_snapshot_size: usize,
+    /// Number of tombstones of deleted documents.
+    pub tombstone_count: usize,
+    /// Total size of all documents before compression in bytes.
+    pub uncompressed_document_size: usize,
+    /// Total size of all snapshots before compression in bytes.
+    pub uncompressed_snapshot_size: usize,
+}
+
+impl StorageStats {
+    /// Ratio of the size of documents and snapshots before compression to
+    /// their stored size, e.g. 4.0 when they take a quarter of the space.
+    ///
+    /// 1.0 when nothing is stored.
+    pub fn compression_ratio(&self) -> f64 {
+        let stored = self.total_document_size + self.total_snapshot_size;
+        if stored == 0 {
+            return 1.0;
+        }
+        (self.uncompressed_document_size + self.uncompressed_snapshot_size) as f64 / stored as f64
+    }
 }
 
 #[cfg(test)]
@@ -230,7 +627,11 @@ mod tests {
             Ok(None)
         }
 
-        async fn query(&self, _namespace: &str, _filter: QueryFilter) -> Result<Vec<(String, Bytes)>> {
+        async fn query(
+            &self,
+            _namespace: &str,
+            _filter: QueryFilter,
+        ) -> Result<Vec<(String, Bytes)>> {
             Ok(vec![])
         }
 
@@ -251,6 +652,105 @@ mod tests {
         assert!(result.is_none()); // Mock always returns None
     }
 
+    #[tokio::test]
+    async fn test_default_streams() {
+        let adapter = MockAdapter;
+
+        let mut reader: &[u8] = b"streamed";
+        let saved = adapter
+            .save_stream("test", "id1", &mut reader)
+            .await
+            .unwrap();
+        assert_eq!(saved, 8);
+
+        let mut out = Vec::new();
+        let loaded = adapter.load_stream("test", "id1", &mut out).await.unwrap();
+        assert_eq!(loaded, None);
+        assert!(out.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_default_query_page_rejects_timestamp_sort() {
+        let adapter = MockAdapter;
+        let options = QueryOptions::default().sort_by(SortOrder::desc(SortField::UpdatedAt));
+
+        let result = adapter.query_page("test", QueryFilter::All, options).await;
+        assert!(matches!(result, Err(StorageError::Unsupported(_))));
+    }
+
+    #[tokio::test]
+    async fn test_default_ensure_index_unsupported() {
+        let adapter = MockAdapter;
+
+        let result = adapter.ensure_index("users", "$.email").await;
+        assert!(matches!(result, Err(StorageError::Unsupported(_))));
+
+        let result = adapter.ensure_index("users", "$.bad path").await;
+        assert!(matches!(result, Err(StorageError::InvalidOperation(_))));
+
+        assert!(adapter.list_indexes("users").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_default_maintenance_unsupported() {
+        let adapter = MockAdapter;
+
+        let result = adapter
+            .maintenance(MaintenanceOptions::new().vacuum())
+            .await;
+        assert!(matches!(result, Err(StorageError::Unsupported(_))));
+    }
+
+    #[tokio::test]
+    async fn test_default_verify_unsupported() {
+        let adapter = MockAdapter;
+
+        assert!(matches!(
+            adapter.verify().await,
+            Err(StorageError::Unsupported(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_default_transactions_unsupported() {
+        let adapter = MockAdapter;
+
+        let docs = [
+            ("users", "alice", Bytes::from("a")),
+            ("users", "bob", Bytes::from("b")),
+        ];
+        adapter.save_batch(&docs).await.unwrap();
+
+        assert!(matches!(
+            adapter.begin().await,
+            Err(StorageError::Unsupported(_))
+        ));
+        assert!(matches!(
+            adapter.commit().await,
+            Err(StorageError::Unsupported(_))
+        ));
+        assert!(matches!(
+            adapter.rollback().await,
+            Err(StorageError::Unsupported(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_default_backup_needs_namespaces() {
+        let adapter = MockAdapter;
+        let target = MemoryBackupTarget::new();
+
+        assert!(matches!(
+            adapter.backup_to(&target).await,
+            Err(StorageError::Unsupported(_))
+        ));
+        assert!(target.keys().is_empty());
+        assert!(matches!(
+            adapter.restore_from(&target).await,
+            Err(StorageError::InvalidOperation(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_storage_stats_default() {
         let stats = StorageStats::default();
@@ -0,0 +1,101 @@
+//! Storage maintenance: snapshot pruning, tombstone purging and compaction.
+//!
+//! Deleting a document leaves a tombstone recording when it was deleted, and
+//! the document's snapshots stay around so that a deletion can still be
+//! inspected or undone for a while. [`StorageAdapter::maintenance`](crate::StorageAdapter::maintenance)
+//! removes what is no longer needed and reports how much space it freed.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// What a maintenance run should do.
+///
+/// The default does nothing; enable each step explicitly.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct MaintenanceOptions {
+    /// Keep at most this many snapshots per document, newest versions first
+    /// (None keeps all).
+    pub max_snapshots_per_doc: Option<usize>,
+    /// Purge tombstones older than this, along with the deleted documents'
+    /// remaining snapshots (None keeps them).
+    pub tombstone_retention: Option<Duration>,
+    /// Rebuild the storage to release free space (SQLite: `VACUUM`).
+    pub vacuum: bool,
+    /// Fold the write-ahead log back into the database and truncate it.
+    pub checkpoint: bool,
+}
+
+impl MaintenanceOptions {
+    /// Create options that do nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep at most `max` snapshots per document.
+    pub fn max_snapshots_per_doc(mut self, max: usize) -> Self {
+        self.max_snapshots_per_doc = Some(max);
+        self
+    }
+
+    /// Purge tombstones older than `retention`.
+    pub fn tombstone_retention(mut self, retention: Duration) -> Self {
+        self.tombstone_retention = Some(retention);
+        self
+    }
+
+    /// Rebuild the storage to release free space.
+    pub fn vacuum(mut self) -> Self {
+        self.vacuum = true;
+        self
+    }
+
+    /// Checkpoint and truncate the write-ahead log.
+    pub fn checkpoint(mut self) -> Self {
+        self.checkpoint = true;
+        self
+    }
+}
+
+/// Outcome of a maintenance run.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct MaintenanceReport {
+    /// Snapshots removed for exceeding the per-document limit.
+    pub snapshots_pruned: usize,
+    /// Tombstones purged after their retention period.
+    pub tombstones_purged: usize,
+    /// Snapshots of purged documents that were removed with their tombstones.
+    pub tombstone_snapshots_purged: usize,
+    /// Size of the removed values in bytes.
+    pub bytes_freed: u64,
+    /// Bytes by which the storage shrank, e.g. through `VACUUM` or WAL
+    /// truncation.
+    pub bytes_reclaimed: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options_builder() {
+        assert_eq!(
+            MaintenanceOptions::new(),
+            MaintenanceOptions {
+                max_snapshots_per_doc: None,
+                tombstone_retention: None,
+                vacuum: false,
+                checkpoint: false,
+            }
+        );
+
+        let options = MaintenanceOptions::new()
+            .max_snapshots_per_doc(3)
+            .tombstone_retention(Duration::from_secs(60))
+            .vacuum()
+            .checkpoint();
+        assert_eq!(options.max_snapshots_per_doc, Some(3));
+        assert_eq!(options.tombstone_retention, Some(Duration::from_secs(60)));
+        assert!(options.vacuum);
+        assert!(options.checkpoint);
+    }
+}
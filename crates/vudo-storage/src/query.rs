@@ -1,5 +1,6 @@
 //! Query filter types for indexed lookups.
 
+use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 
 /// Query filter for document lookups.
@@ -17,12 +18,27 @@ pub enum QueryFilter {
     /// Match documents within a time range.
     UpdatedBetween { start: u64, end: u64 },
 
-    /// Match documents by custom field (if supported by adapter).
+    /// Match documents created after a timestamp.
+    CreatedAfter(u64),
+
+    /// Match documents created before a timestamp.
+    CreatedBefore(u64),
+
+    /// Match documents created within a time range (inclusive).
+    CreatedBetween { start: u64, end: u64 },
+
+    /// Match documents whose ID starts with a prefix.
+    IdPrefix(String),
+
+    /// Match JSON documents by the value of a field (if supported by adapter).
     ///
-    /// This requires the adapter to maintain indexes on custom fields.
-    /// Not all adapters may support this.
+    /// The field is a [`JsonPath`](crate::JsonPath) such as `status` or
+    /// `$.user.name`. Adapters answer this from an index created with
+    /// [`StorageAdapter::ensure_index`](crate::StorageAdapter::ensure_index)
+    /// when one exists, and may fall back to a scan otherwise. Not all
+    /// adapters may support this.
     Field {
-        /// Field name.
+        /// Field path.
         field: String,
         /// Expected value (JSON string).
         value: String,
@@ -54,6 +70,26 @@ impl QueryFilter {
         Self::UpdatedBetween { start, end }
     }
 
+    /// Create a filter for documents created after a timestamp.
+    pub fn created_after(timestamp: u64) -> Self {
+        Self::CreatedAfter(timestamp)
+    }
+
+    /// Create a filter for documents created before a timestamp.
+    pub fn created_before(timestamp: u64) -> Self {
+        Self::CreatedBefore(timestamp)
+    }
+
+    /// Create a filter for documents created within a time range.
+    pub fn created_between(start: u64, end: u64) -> Self {
+        Self::CreatedBetween { start, end }
+    }
+
+    /// Create a filter for document IDs starting with a prefix.
+    pub fn id_prefix(prefix: impl Into<String>) -> Self {
+        Self::IdPrefix(prefix.into())
+    }
+
     /// Create a filter for a custom field.
     pub fn field(field: impl Into<String>, value: impl Into<String>) -> Self {
         Self::Field {
@@ -91,6 +127,134 @@ impl QueryFilter {
     }
 }
 
+/// Document attribute results can be sorted by.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum SortField {
+    /// Document ID.
+    #[default]
+    Id,
+    /// Creation timestamp.
+    CreatedAt,
+    /// Last update timestamp.
+    UpdatedAt,
+}
+
+/// Sort direction.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum SortDirection {
+    /// Smallest first.
+    #[default]
+    Ascending,
+    /// Largest first.
+    Descending,
+}
+
+/// Sort order for query results.
+///
+/// Ties on timestamps are broken by document ID in the same direction, so
+/// the order is total and cursors are stable.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct SortOrder {
+    /// Field to sort by.
+    pub field: SortField,
+    /// Sort direction.
+    pub direction: SortDirection,
+}
+
+impl SortOrder {
+    /// Sort ascending by a field.
+    pub fn asc(field: SortField) -> Self {
+        Self {
+            field,
+            direction: SortDirection::Ascending,
+        }
+    }
+
+    /// Sort descending by a field.
+    pub fn desc(field: SortField) -> Self {
+        Self {
+            field,
+            direction: SortDirection::Descending,
+        }
+    }
+}
+
+/// Position after the last document of a page, for keyset pagination.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Cursor {
+    /// Sort timestamp of the last document (None when sorting by ID).
+    pub timestamp: Option<u64>,
+    /// ID of the last document.
+    pub id: String,
+}
+
+/// Sort and pagination options for a query.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct QueryOptions {
+    /// Sort order.
+    pub sort: SortOrder,
+    /// Maximum number of results (None for all).
+    pub limit: Option<usize>,
+    /// Number of results to skip (applied after the cursor).
+    pub offset: usize,
+    /// Continue after this cursor.
+    pub cursor: Option<Cursor>,
+}
+
+impl QueryOptions {
+    /// Set the sort order.
+    pub fn sort_by(mut self, sort: SortOrder) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Set the maximum number of results.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Set the number of results to skip.
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Continue after a cursor from a previous page.
+    pub fn after(mut self, cursor: Cursor) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+}
+
+/// A page of query results.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct QueryPage {
+    /// Matching (id, data) tuples in sort order.
+    pub items: Vec<(String, Bytes)>,
+    /// Cursor for the next page, if the limit was reached.
+    pub next_cursor: Option<Cursor>,
+}
+
+/// Smallest string greater than every string starting with `prefix`.
+///
+/// Returns `None` when no such bound exists (empty prefix or only
+/// `char::MAX`). Useful for turning a prefix match into an indexed range scan.
+pub fn prefix_upper_bound(prefix: &str) -> Option<String> {
+    let mut chars: Vec<char> = prefix.chars().collect();
+    while let Some(last) = chars.pop() {
+        let next = match last as u32 + 1 {
+            0xD800 => Some('\u{E000}'),
+            code => char::from_u32(code),
+        };
+        if let Some(next) = next {
+            chars.push(next);
+            return Some(chars.into_iter().collect());
+        }
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -125,6 +289,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_query_filter_created_between() {
+        let filter = QueryFilter::created_between(1000, 2000);
+        assert_eq!(
+            filter,
+            QueryFilter::CreatedBetween {
+                start: 1000,
+                end: 2000
+            }
+        );
+    }
+
+    #[test]
+    fn test_query_filter_id_prefix() {
+        let filter = QueryFilter::id_prefix("user:");
+        assert_eq!(filter, QueryFilter::IdPrefix("user:".to_string()));
+    }
+
+    #[test]
+    fn test_prefix_upper_bound() {
+        assert_eq!(prefix_upper_bound("abc"), Some("abd".to_string()));
+        assert_eq!(prefix_upper_bound(""), None);
+        assert_eq!(prefix_upper_bound("a\u{10FFFF}"), Some("b".to_string()));
+        assert_eq!(prefix_upper_bound("\u{D7FF}"), Some("\u{E000}".to_string()));
+    }
+
+    #[test]
+    fn test_query_options_builder() {
+        let options = QueryOptions::default()
+            .sort_by(SortOrder::desc(SortField::CreatedAt))
+            .limit(10)
+            .offset(5);
+        assert_eq!(options.sort.field, SortField::CreatedAt);
+        assert_eq!(options.sort.direction, SortDirection::Descending);
+        assert_eq!(options.limit, Some(10));
+        assert_eq!(options.offset, 5);
+        assert!(options.cursor.is_none());
+    }
+
     #[test]
     fn test_query_filter_field() {
         let filter = QueryFilter::field("status", "active");
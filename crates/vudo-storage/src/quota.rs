@@ -0,0 +1,473 @@
+//! Storage quotas: byte limits per namespace and for the whole store.
+//!
+//! An adapter configured with a [`StorageQuota`] refuses document writes that
+//! would take a namespace, or the whole store, over its limit and fails them
+//! with [`StorageError::QuotaExceeded`]. Before failing, it asks its
+//! [`EvictionHook`], if any, which documents it may delete to make room, so a
+//! browser deployment can stay within its origin's storage budget by dropping
+//! cached data instead of refusing user writes.
+//!
+//! Quotas count the stored bytes of documents. Snapshots, tombstones and the
+//! operation queue are not counted.
+
+use crate::{Result, StorageError};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// Byte limits enforced on document writes.
+///
+/// The default has no limits.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct StorageQuota {
+    /// Limit on the documents of all namespaces together.
+    pub max_total_bytes: Option<u64>,
+    /// Limit on the documents of each namespace without its own limit.
+    pub max_namespace_bytes: Option<u64>,
+    /// Limits of individual namespaces, overriding `max_namespace_bytes`.
+    pub namespace_limits: BTreeMap<String, u64>,
+}
+
+impl StorageQuota {
+    /// Create a quota without limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the documents of all namespaces together to `bytes`.
+    pub fn max_total_bytes(mut self, bytes: u64) -> Self {
+        self.max_total_bytes = Some(bytes);
+        self
+    }
+
+    /// Limit each namespace to `bytes`, unless it has its own limit.
+    pub fn max_namespace_bytes(mut self, bytes: u64) -> Self {
+        self.max_namespace_bytes = Some(bytes);
+        self
+    }
+
+    /// Limit `namespace` to `bytes`.
+    pub fn namespace_limit(mut self, namespace: impl Into<String>, bytes: u64) -> Self {
+        self.namespace_limits.insert(namespace.into(), bytes);
+        self
+    }
+
+    /// The limit that applies to `namespace`, if any.
+    pub fn limit_for(&self, namespace: &str) -> Option<u64> {
+        self.namespace_limits
+            .get(namespace)
+            .copied()
+            .or(self.max_namespace_bytes)
+    }
+
+    /// Whether the quota has no limits at all.
+    pub fn is_unlimited(&self) -> bool {
+        self.max_total_bytes.is_none()
+            && self.max_namespace_bytes.is_none()
+            && self.namespace_limits.is_empty()
+    }
+
+    /// Check the usage a write would leave behind.
+    ///
+    /// Namespace limits are checked before the total limit.
+    pub fn check(&self, usage: &QuotaUsage) -> Result<()> {
+        for (namespace, &required) in &usage.namespaces {
+            if let Some(limit) = self.limit_for(namespace) {
+                if required > limit {
+                    return Err(StorageError::QuotaExceeded {
+                        scope: QuotaScope::Namespace(namespace.clone()),
+                        limit,
+                        required,
+                    });
+                }
+            }
+        }
+        match self.max_total_bytes {
+            Some(limit) if usage.total > limit => Err(StorageError::QuotaExceeded {
+                scope: QuotaScope::Total,
+                limit,
+                required: usage.total,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// What a quota limit applies to.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum QuotaScope {
+    /// A single namespace.
+    Namespace(String),
+    /// The whole store.
+    Total,
+}
+
+impl QuotaScope {
+    /// Whether a document in `namespace` counts towards this scope.
+    pub fn contains(&self, namespace: &str) -> bool {
+        match self {
+            QuotaScope::Namespace(scope) => scope == namespace,
+            QuotaScope::Total => true,
+        }
+    }
+}
+
+impl fmt::Display for QuotaScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuotaScope::Namespace(namespace) => write!(f, "namespace '{}'", namespace),
+            QuotaScope::Total => write!(f, "storage"),
+        }
+    }
+}
+
+/// Document bytes that a write would leave behind.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct QuotaUsage {
+    /// Bytes of each namespace the write touches.
+    pub namespaces: BTreeMap<String, u64>,
+    /// Bytes of all namespaces together.
+    pub total: u64,
+}
+
+/// A document that an [`EvictionHook`] may delete.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvictionCandidate {
+    /// Document namespace.
+    pub namespace: String,
+    /// Document ID.
+    pub id: String,
+    /// Stored size in bytes.
+    pub size: u64,
+    /// Last update (Unix epoch milliseconds).
+    pub updated_at: u64,
+}
+
+/// Chooses documents to delete when a write would exceed a quota.
+///
+/// Evicted documents are deleted like [`StorageAdapter::delete`](crate::StorageAdapter::delete)
+/// deletes them, leaving a tombstone.
+pub trait EvictionHook: Send + Sync {
+    /// Choose documents to free at least `needed` bytes in `scope`.
+    ///
+    /// `candidates` are the documents in the scope, except the ones being
+    /// written. Returns the (namespace, id) of the documents to delete;
+    /// anything that isn't a candidate is ignored. If the chosen documents
+    /// don't free `needed` bytes, nothing is evicted and the write fails.
+    fn select(
+        &self,
+        scope: &QuotaScope,
+        needed: u64,
+        candidates: &[EvictionCandidate],
+    ) -> Vec<(String, String)>;
+}
+
+/// Evicts the least recently updated documents first.
+#[derive(Debug, Clone, Default)]
+pub struct LeastRecentlyUpdated {
+    namespaces: Option<BTreeSet<String>>,
+}
+
+impl LeastRecentlyUpdated {
+    /// Evict from any namespace.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only evict documents of these namespaces, such as caches.
+    pub fn in_namespaces<I, S>(namespaces: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            namespaces: Some(namespaces.into_iter().map(Into::into).collect()),
+        }
+    }
+}
+
+impl EvictionHook for LeastRecentlyUpdated {
+    fn select(
+        &self,
+        _scope: &QuotaScope,
+        needed: u64,
+        candidates: &[EvictionCandidate],
+    ) -> Vec<(String, String)> {
+        let mut evictable: Vec<&EvictionCandidate> = candidates
+            .iter()
+            .filter(|c| {
+                self.namespaces
+                    .as_ref()
+                    .map_or(true, |namespaces| namespaces.contains(&c.namespace))
+            })
+            .collect();
+        evictable.sort_by(|a, b| {
+            (a.updated_at, &a.namespace, &a.id).cmp(&(b.updated_at, &b.namespace, &b.id))
+        });
+
+        let mut freed = 0;
+        let mut selected = Vec::new();
+        for candidate in evictable {
+            if freed >= needed {
+                break;
+            }
+            freed += candidate.size;
+            selected.push((candidate.namespace.clone(), candidate.id.clone()));
+        }
+        selected
+    }
+}
+
+/// Enforce `quota` on a write, evicting documents through `hook` if needed.
+///
+/// Shared by the adapters; each supplies its own accounting:
+///
+/// * `usage` - the usage the write would leave behind
+/// * `candidates` - the documents in a scope that may be evicted
+/// * `evict` - delete a document, leaving a tombstone
+///
+/// Adapters should call this in the same transaction as the write.
+pub fn enforce_quota(
+    quota: &StorageQuota,
+    hook: Option<&dyn EvictionHook>,
+    mut usage: impl FnMut() -> Result<QuotaUsage>,
+    mut candidates: impl FnMut(&QuotaScope) -> Result<Vec<EvictionCandidate>>,
+    mut evict: impl FnMut(&str, &str) -> Result<()>,
+) -> Result<()> {
+    if quota.is_unlimited() {
+        return Ok(());
+    }
+    loop {
+        let err = match quota.check(&usage()?) {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+        let Some(hook) = hook else {
+            return Err(err);
+        };
+        let StorageError::QuotaExceeded {
+            scope,
+            limit,
+            required,
+        } = &err
+        else {
+            return Err(err);
+        };
+
+        let candidates = candidates(scope)?;
+        let needed = required - limit;
+        let mut selected = Vec::new();
+        let mut freed = 0;
+        for key in hook.select(scope, needed, &candidates) {
+            let candidate = candidates
+                .iter()
+                .find(|c| c.namespace == key.0 && c.id == key.1);
+            if let Some(candidate) = candidate {
+                if !selected.contains(&key) {
+                    freed += candidate.size;
+                    selected.push(key);
+                }
+            }
+        }
+        // Evict nothing unless it makes room, so a failed write leaves the
+        // store as it was
+        if freed < needed {
+            return Err(err);
+        }
+        for (namespace, id) in &selected {
+            evict(namespace, id)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn candidate(namespace: &str, id: &str, size: u64, updated_at: u64) -> EvictionCandidate {
+        EvictionCandidate {
+            namespace: namespace.to_string(),
+            id: id.to_string(),
+            size,
+            updated_at,
+        }
+    }
+
+    #[test]
+    fn test_quota_builder() {
+        let quota = StorageQuota::new()
+            .max_total_bytes(1000)
+            .max_namespace_bytes(100)
+            .namespace_limit("media", 500);
+        assert_eq!(quota.limit_for("media"), Some(500));
+        assert_eq!(quota.limit_for("users"), Some(100));
+        assert!(!quota.is_unlimited());
+        assert!(StorageQuota::new().is_unlimited());
+        assert_eq!(StorageQuota::new().limit_for("users"), None);
+    }
+
+    #[test]
+    fn test_quota_check() {
+        let quota = StorageQuota::new()
+            .max_total_bytes(1000)
+            .namespace_limit("media", 500);
+        let usage = |namespace: &str, bytes, total| QuotaUsage {
+            namespaces: BTreeMap::from([(namespace.to_string(), bytes)]),
+            total,
+        };
+
+        assert!(quota.check(&usage("media", 500, 1000)).is_ok());
+        assert!(quota.check(&usage("users", 900, 900)).is_ok());
+
+        let err = quota.check(&usage("media", 501, 1200)).unwrap_err();
+        assert!(matches!(
+            &err,
+            StorageError::QuotaExceeded {
+                scope: QuotaScope::Namespace(namespace),
+                limit: 500,
+                required: 501,
+            } if namespace == "media"
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Storage quota exceeded: namespace 'media' would use 501 of 500 bytes"
+        );
+
+        assert!(matches!(
+            quota.check(&usage("users", 10, 1001)),
+            Err(StorageError::QuotaExceeded {
+                scope: QuotaScope::Total,
+                limit: 1000,
+                required: 1001,
+            })
+        ));
+    }
+
+    #[test]
+    fn test_least_recently_updated() {
+        let candidates = vec![
+            candidate("cache", "new", 10, 300),
+            candidate("cache", "old", 10, 100),
+            candidate("users", "alice", 10, 50),
+            candidate("cache", "mid", 10, 200),
+        ];
+
+        let selected = LeastRecentlyUpdated::new().select(&QuotaScope::Total, 15, &candidates);
+        assert_eq!(
+            selected,
+            vec![
+                ("users".to_string(), "alice".to_string()),
+                ("cache".to_string(), "old".to_string())
+            ]
+        );
+
+        let selected = LeastRecentlyUpdated::in_namespaces(["cache"]).select(
+            &QuotaScope::Total,
+            15,
+            &candidates,
+        );
+        assert_eq!(
+            selected,
+            vec![
+                ("cache".to_string(), "old".to_string()),
+                ("cache".to_string(), "mid".to_string())
+            ]
+        );
+
+        assert!(LeastRecentlyUpdated::in_namespaces(["media"])
+            .select(&QuotaScope::Total, 15, &candidates)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_enforce_quota_evicts() {
+        let quota = StorageQuota::new().max_total_bytes(100);
+        let docs = RefCell::new(vec![
+            candidate("cache", "a", 40, 1),
+            candidate("cache", "b", 40, 2),
+        ]);
+        let hook = LeastRecentlyUpdated::new();
+
+        // Writing 50 bytes needs 30 more: evicting the oldest document is enough
+        enforce_quota(
+            &quota,
+            Some(&hook),
+            || {
+                Ok(QuotaUsage {
+                    namespaces: BTreeMap::new(),
+                    total: docs.borrow().iter().map(|d| d.size).sum::<u64>() + 50,
+                })
+            },
+            |_| Ok(docs.borrow().clone()),
+            |namespace, id| {
+                docs.borrow_mut()
+                    .retain(|d| (d.namespace.as_str(), d.id.as_str()) != (namespace, id));
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(docs.borrow().len(), 1);
+        assert_eq!(docs.borrow()[0].id, "b");
+    }
+
+    #[test]
+    fn test_enforce_quota_without_room() {
+        let quota = StorageQuota::new().max_total_bytes(100);
+        let usage = || {
+            Ok(QuotaUsage {
+                namespaces: BTreeMap::new(),
+                total: 150,
+            })
+        };
+        let mut evictions = 0;
+
+        // No hook
+        assert!(matches!(
+            enforce_quota(&quota, None, usage, |_| Ok(vec![]), |_, _| Ok(())),
+            Err(StorageError::QuotaExceeded { .. })
+        ));
+
+        // Hook choosing too little to make room
+        assert!(matches!(
+            enforce_quota(
+                &quota,
+                Some(&LeastRecentlyUpdated::new()),
+                usage,
+                |_| Ok(vec![candidate("cache", "a", 10, 1)]),
+                |_, _| {
+                    evictions += 1;
+                    Ok(())
+                },
+            ),
+            Err(StorageError::QuotaExceeded { .. })
+        ));
+
+        // Hook choosing documents that aren't candidates
+        struct Rogue;
+        impl EvictionHook for Rogue {
+            fn select(
+                &self,
+                _scope: &QuotaScope,
+                _needed: u64,
+                _candidates: &[EvictionCandidate],
+            ) -> Vec<(String, String)> {
+                vec![("users".to_string(), "alice".to_string())]
+            }
+        }
+        assert!(matches!(
+            enforce_quota(
+                &quota,
+                Some(&Rogue),
+                usage,
+                |_| Ok(vec![candidate("cache", "a", 10, 1)]),
+                |_, _| {
+                    evictions += 1;
+                    Ok(())
+                },
+            ),
+            Err(StorageError::QuotaExceeded { .. })
+        ));
+        assert_eq!(evictions, 0);
+    }
+}
@@ -0,0 +1,433 @@
+//! The tasks app: state, storage and privacy wired together.
+//!
+//! [`TaskApp`] owns a [`StateEngine`] holding the task documents and a
+//! SQLite database persisting them. Local writes are attributed to a
+//! pseudonym of the device DID, and documents are encrypted with the
+//! device's data encryption key before they reach the database, so
+//! [`TaskApp::forget`] erases every stored task at once.
+
+use crate::error::{Result, TasksError};
+use crate::profile::Profile;
+use crate::task::{self, Task, ASSIGNEE, BOARD, COMPLETED_AT, NAMESPACE, TITLE};
+use automerge::transaction::Transactable;
+use automerge::{ActorId, ROOT};
+use bytes::Bytes;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use vudo_privacy::{DekCodec, DekOwner, DeletionReceipt, PersonalDataCrypto, PseudonymousActorId};
+use vudo_state::{ConflictHandler, ConflictPolicy, DocumentHandle, DocumentId, StateEngine};
+use vudo_storage::StorageAdapter;
+use vudo_storage_native::SqliteAdapter;
+
+/// File name of the task database in the data directory.
+pub const DATABASE_FILE: &str = "tasks.db";
+
+/// A device's task list.
+pub struct TaskApp {
+    /// Data directory.
+    dir: PathBuf,
+    /// Identity and keys of the device.
+    profile: Profile,
+    /// Task documents.
+    engine: Arc<StateEngine>,
+    /// Persistent storage.
+    storage: Arc<SqliteAdapter>,
+    /// DEK manager for encryption at rest.
+    crypto: PersonalDataCrypto,
+    /// Actor of local writes.
+    actor: PseudonymousActorId,
+}
+
+impl TaskApp {
+    /// Create a new profile in `dir` and open its (empty) task list.
+    pub async fn init(dir: impl AsRef<Path>, name: &str) -> Result<Self> {
+        let dir = dir.as_ref();
+        if Profile::path(dir).exists() {
+            return Err(TasksError::AlreadyInitialized(dir.display().to_string()));
+        }
+        let profile = Profile::generate(name).await?;
+        profile.save(dir)?;
+        Self::with_profile(dir, profile).await
+    }
+
+    /// Open the task list in `dir`.
+    pub async fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let profile = Profile::load(dir)?;
+        Self::with_profile(dir, profile).await
+    }
+
+    async fn with_profile(dir: &Path, profile: Profile) -> Result<Self> {
+        let engine = Arc::new(StateEngine::new().await?);
+
+        let actor = PseudonymousActorId::from_did(profile.device.did().as_str())?;
+        actor.bind_store(&engine.store);
+
+        let crypto = PersonalDataCrypto::new();
+        crypto.restore_dek(profile.dek.clone())?;
+        let owner = DekOwner::Fixed(profile.dek.owner.clone());
+        engine
+            .store
+            .codecs()
+            .set_codec(NAMESPACE, Arc::new(DekCodec::new(crypto.clone(), owner)));
+
+        // Completing a task wins over reopening it, and the latest
+        // completion time is kept
+        engine
+            .conflict_policies
+            .set_policy(NAMESPACE, COMPLETED_AT, ConflictPolicy::PreferHighest);
+
+        let storage = SqliteAdapter::new(dir.join(DATABASE_FILE)).await?;
+        storage.init().await?;
+
+        let app = Self {
+            dir: dir.to_path_buf(),
+            profile,
+            engine,
+            storage: Arc::new(storage),
+            crypto,
+            actor,
+        };
+        app.reload().await?;
+        if !app.engine.store.exists(&board_id()) {
+            app.engine.create_document(board_id()).await?;
+        }
+        Ok(app)
+    }
+
+    /// Data directory of the app.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Identity and keys of the device.
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// State engine holding the task documents.
+    pub fn engine(&self) -> &Arc<StateEngine> {
+        &self.engine
+    }
+
+    /// Storage adapter persisting the task documents.
+    pub fn storage(&self) -> &Arc<SqliteAdapter> {
+        &self.storage
+    }
+
+    /// Automerge actor of local writes.
+    pub fn actor(&self) -> ActorId {
+        self.actor.actor_id()
+    }
+
+    /// Let `handler` settle conflicting titles and assignees.
+    pub fn set_conflict_handler(&self, handler: Arc<dyn ConflictHandler>) {
+        let policies = &self.engine.conflict_policies;
+        policies.set_policy(NAMESPACE, TITLE, ConflictPolicy::Delegate(handler.clone()));
+        policies.set_policy(NAMESPACE, ASSIGNEE, ConflictPolicy::Delegate(handler));
+    }
+
+    /// Allow a collaborator's devices to sync with this device.
+    pub fn trust(&mut self, did: vudo_identity::Did) -> Result<bool> {
+        let added = self.profile.trust(did);
+        if added {
+            self.profile.save(&self.dir)?;
+        }
+        Ok(added)
+    }
+
+    /// Add a task.
+    pub async fn add(&self, title: &str) -> Result<Task> {
+        let created_at = now();
+        let nonce = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+            .to_string();
+        let id = DocumentId::derive(
+            NAMESPACE,
+            &[self.profile.device.did().as_str(), nonce.as_str()],
+        );
+
+        let task = Task {
+            id: id.key.clone(),
+            title: title.to_string(),
+            completed_at: None,
+            assignee: None,
+            created_at,
+        };
+        let handle = self.engine.create_document(id).await?;
+        handle.update(|doc| task.write(doc))?;
+        self.board()?.update(|doc| {
+            doc.put(ROOT, task.id.as_str(), true)?;
+            Ok(())
+        })?;
+
+        self.save().await?;
+        Ok(task)
+    }
+
+    /// List the tasks on the board, oldest first.
+    pub async fn list(&self) -> Result<Vec<Task>> {
+        let mut tasks = Vec::new();
+        for id in self.task_ids()? {
+            // Listed tasks may not have been synced yet
+            if let Ok(handle) = self
+                .engine
+                .get_document(&DocumentId::new(NAMESPACE, &id))
+                .await
+            {
+                tasks.push(handle.read(|doc| Ok(Task::read(&id, doc)))??);
+            }
+        }
+        tasks.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+        Ok(tasks)
+    }
+
+    /// Get a task by ID or unique ID prefix.
+    pub async fn get(&self, id: &str) -> Result<Task> {
+        let id = self.resolve(id)?;
+        let handle = self.task_handle(&id).await?;
+        handle.read(|doc| Ok(Task::read(&id, doc)))?
+    }
+
+    /// Mark a task as done.
+    pub async fn complete(&self, id: &str) -> Result<Task> {
+        let completed_at = now();
+        self.edit(id, |doc| {
+            doc.put(ROOT, COMPLETED_AT, completed_at)?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Reopen a done task.
+    pub async fn reopen(&self, id: &str) -> Result<Task> {
+        self.edit(id, |doc| {
+            doc.put(ROOT, COMPLETED_AT, 0u64)?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Rename a task.
+    pub async fn rename(&self, id: &str, title: &str) -> Result<Task> {
+        self.edit(id, |doc| {
+            doc.put(ROOT, TITLE, title)?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Assign a task (`None` to unassign).
+    pub async fn assign(&self, id: &str, assignee: Option<&str>) -> Result<Task> {
+        self.edit(id, |doc| {
+            doc.put(ROOT, ASSIGNEE, assignee.unwrap_or(""))?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Remove a task from the board.
+    pub async fn remove(&self, id: &str) -> Result<()> {
+        let id = self.resolve(id)?;
+        self.board()?.update(|doc| {
+            doc.delete(ROOT, id.as_str())?;
+            Ok(())
+        })?;
+
+        let doc_id = DocumentId::new(NAMESPACE, &id);
+        if self.engine.store.exists(&doc_id) {
+            self.engine.delete_document(&doc_id).await?;
+        }
+        self.storage.delete(NAMESPACE, &id).await?;
+        self.save().await
+    }
+
+    /// Merge the stored documents into the engine.
+    ///
+    /// Documents changed by another process (e.g. a running `serve`) are
+    /// merged like remote changes, so conflicts go through the policies.
+    /// Returns the number of documents loaded.
+    pub async fn reload(&self) -> Result<usize> {
+        let keys = self.storage.list(NAMESPACE).await?;
+        for key in &keys {
+            let id = DocumentId::new(NAMESPACE, key);
+            if let Some(bytes) = self.storage.load(NAMESPACE, key).await? {
+                let decoded = self.engine.store.codecs().decode(&id, &bytes)?;
+                self.engine.apply_remote_changes(&id, &decoded).await?;
+            }
+        }
+        Ok(keys.len())
+    }
+
+    /// Persist every task document, encrypted with the device DEK.
+    pub async fn save(&self) -> Result<()> {
+        for id in self.engine.store.list_namespace(NAMESPACE) {
+            let encoded = self.engine.store.save_encoded(&id)?;
+            self.storage
+                .save(NAMESPACE, &id.key, Bytes::from(encoded))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Erase this device's tasks by deleting the data encryption key.
+    ///
+    /// The stored documents stay in the database but can no longer be
+    /// decrypted; the profile keeps the erased key so it isn't reused.
+    pub async fn forget(mut self) -> Result<DeletionReceipt> {
+        let receipt = self.crypto.delete_dek(&self.profile.dek.owner)?;
+        self.profile.dek = self.crypto.get_dek(&self.profile.dek.owner)?;
+        self.profile.save(&self.dir)?;
+        Ok(receipt)
+    }
+
+    /// IDs of the tasks on the board.
+    pub fn task_ids(&self) -> Result<Vec<String>> {
+        Ok(self.board()?.read(|doc| Ok(task::board_tasks(doc)))?)
+    }
+
+    /// Resolve a task ID prefix to the full ID.
+    pub fn resolve(&self, prefix: &str) -> Result<String> {
+        let mut matches = self
+            .task_ids()?
+            .into_iter()
+            .filter(|id| id.starts_with(prefix));
+        match (matches.next(), matches.next()) {
+            (Some(id), None) => Ok(id),
+            (Some(_), Some(_)) => Err(TasksError::AmbiguousTask(prefix.to_string())),
+            (None, _) => Err(TasksError::TaskNotFound(prefix.to_string())),
+        }
+    }
+
+    /// Apply an edit to a task and persist it.
+    async fn edit<F>(&self, id: &str, f: F) -> Result<Task>
+    where
+        F: FnOnce(&mut automerge::AutoCommit) -> vudo_state::Result<()>,
+    {
+        let id = self.resolve(id)?;
+        let handle = self.task_handle(&id).await?;
+        handle.update(f)?;
+        self.save().await?;
+        handle.read(|doc| Ok(Task::read(&id, doc)))?
+    }
+
+    async fn task_handle(&self, id: &str) -> Result<DocumentHandle> {
+        self.engine
+            .get_document(&DocumentId::new(NAMESPACE, id))
+            .await
+            .map_err(|_| TasksError::TaskNotFound(id.to_string()))
+    }
+
+    fn board(&self) -> Result<DocumentHandle> {
+        Ok(self.engine.store.get(&board_id())?)
+    }
+}
+
+/// Document ID of the board.
+pub fn board_id() -> DocumentId {
+    DocumentId::new(NAMESPACE, BOARD)
+}
+
+/// Get current time in seconds.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Send every task document of `from` to `to`, as a sync would.
+    async fn exchange(from: &TaskApp, to: &TaskApp) {
+        for id in from.engine.store.list_namespace(NAMESPACE) {
+            let bytes = from.engine.store.get(&id).unwrap().save();
+            to.engine.apply_remote_changes(&id, &bytes).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tasks_persist() {
+        let dir = TempDir::new().unwrap();
+        let app = TaskApp::init(dir.path(), "Alice").await.unwrap();
+        assert!(TaskApp::init(dir.path(), "Alice").await.is_err());
+
+        let milk = app.add("Buy milk").await.unwrap();
+        let taxes = app.add("File taxes").await.unwrap();
+        app.complete(&milk.id[..8]).await.unwrap();
+        app.assign(&taxes.id, Some("alice")).await.unwrap();
+        drop(app);
+
+        let app = TaskApp::open(dir.path()).await.unwrap();
+        let tasks = app.list().await.unwrap();
+        assert_eq!(tasks.len(), 2);
+        let milk = app.get(&milk.id).await.unwrap();
+        assert!(milk.is_done());
+        assert_eq!(
+            app.get(&taxes.id).await.unwrap().assignee.as_deref(),
+            Some("alice")
+        );
+
+        app.remove(&milk.id).await.unwrap();
+        assert!(app.get(&milk.id).await.is_err());
+        assert_eq!(
+            app.list().await.unwrap(),
+            vec![app.get(&taxes.id).await.unwrap()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forget_erases_stored_tasks() {
+        let dir = TempDir::new().unwrap();
+        let app = TaskApp::init(dir.path(), "Alice").await.unwrap();
+        app.add("Secret plans").await.unwrap();
+
+        // Stored encrypted
+        let board = app.storage.load(NAMESPACE, BOARD).await.unwrap().unwrap();
+        assert!(automerge::AutoCommit::load(&board).is_err());
+
+        app.forget().await.unwrap();
+        assert!(TaskApp::open(dir.path()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_edits_merge() {
+        let (alice_dir, bob_dir) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        let alice = TaskApp::init(alice_dir.path(), "Alice").await.unwrap();
+        let bob = TaskApp::init(bob_dir.path(), "Bob").await.unwrap();
+
+        let task = alice.add("Plan the party").await.unwrap();
+        exchange(&alice, &bob).await;
+        bob.add("Send invites").await.unwrap();
+        assert_eq!(bob.list().await.unwrap().len(), 2);
+
+        // Different fields merge, concurrent completions keep the latest
+        alice
+            .rename(&task.id, "Plan the summer party")
+            .await
+            .unwrap();
+        alice.complete(&task.id).await.unwrap();
+        bob.assign(&task.id, Some("bob")).await.unwrap();
+        bob.edit(&task.id, |doc| {
+            doc.put(ROOT, COMPLETED_AT, u64::MAX)?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+        exchange(&bob, &alice).await;
+        exchange(&alice, &bob).await;
+
+        let merged = alice.get(&task.id).await.unwrap();
+        assert_eq!(merged, bob.get(&task.id).await.unwrap());
+        assert_eq!(merged.title, "Plan the summer party");
+        assert_eq!(merged.assignee.as_deref(), Some("bob"));
+        assert_eq!(merged.completed_at, Some(u64::MAX));
+        assert_eq!(alice.list().await.unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,66 @@
+//! Error types for the tasks app.
+
+use thiserror::Error;
+
+/// Result type for tasks operations.
+pub type Result<T> = std::result::Result<T, TasksError>;
+
+/// Errors raised by the tasks app.
+#[derive(Debug, Error)]
+pub enum TasksError {
+    /// No profile in the data directory.
+    #[error("No tasks profile in {0} (run `vudo-tasks init` first)")]
+    NotInitialized(String),
+
+    /// A profile already exists in the data directory.
+    #[error("A tasks profile already exists in {0}")]
+    AlreadyInitialized(String),
+
+    /// No task matches the given ID.
+    #[error("Task not found: {0}")]
+    TaskNotFound(String),
+
+    /// More than one task matches the given ID prefix.
+    #[error("Task ID prefix is ambiguous: {0}")]
+    AmbiguousTask(String),
+
+    /// A task document is malformed.
+    #[error("Invalid task {0}: {1}")]
+    InvalidTask(String, String),
+
+    /// Sync with a peer failed.
+    #[error("Sync failed: {0}")]
+    SyncFailed(String),
+
+    /// State engine error.
+    #[error("State error: {0}")]
+    State(#[from] vudo_state::StateError),
+
+    /// Storage error.
+    #[error("Storage error: {0}")]
+    Storage(#[from] vudo_storage::StorageError),
+
+    /// Identity error.
+    #[error("Identity error: {0}")]
+    Identity(#[from] vudo_identity::Error),
+
+    /// Privacy error.
+    #[error("Privacy error: {0}")]
+    Privacy(#[from] vudo_privacy::PrivacyError),
+
+    /// P2P error.
+    #[error("P2P error: {0}")]
+    P2P(#[from] vudo_p2p::P2PError),
+
+    /// AI error.
+    #[error("AI error: {0}")]
+    Ai(#[from] vudo_ai::AIError),
+
+    /// I/O error.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Serialization error.
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
@@ -0,0 +1,48 @@
+//! # VUDO Tasks
+//!
+//! A collaborative to-do list built on the VUDO runtime, wiring every layer
+//! of the stack together:
+//!
+//! - **Identity** ([`profile`]): a master identity with a linked device
+//! - **State** ([`task`], [`app`]): one Automerge document per task plus a
+//!   board listing them, with conflict policies per field
+//! - **Storage**: a native SQLite database in the data directory
+//! - **Privacy**: documents encrypted at rest with the device's data
+//!   encryption key, local writes attributed to a pseudonym of the device
+//! - **P2P** ([`sync`]): UCAN-authenticated sync with trusted collaborators
+//! - **AI** ([`resolver`]): an embedding model settling conflicting titles
+//!   and assignees
+//!
+//! It doubles as the template for new VUDO apps: copy the crate, replace
+//! [`task`] with your own documents and keep the wiring.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use vudo_tasks::TaskApp;
+//!
+//! # async fn example() -> vudo_tasks::Result<()> {
+//! let app = TaskApp::init("./.vudo-tasks", "Alice").await?;
+//! let task = app.add("Buy milk").await?;
+//! app.complete(&task.id).await?;
+//!
+//! for task in app.list().await? {
+//!     println!("{} {}", task.id, task.title);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod app;
+pub mod error;
+pub mod profile;
+pub mod resolver;
+pub mod sync;
+pub mod task;
+
+pub use app::TaskApp;
+pub use error::{Result, TasksError};
+pub use profile::Profile;
+pub use resolver::enable_ai_resolution;
+pub use sync::TaskNode;
+pub use task::Task;
@@ -0,0 +1,207 @@
+//! Command-line interface of VUDO Tasks.
+//!
+//! ```bash
+//! vudo-tasks init Alice
+//! vudo-tasks add "Buy milk"
+//! vudo-tasks list
+//! vudo-tasks done 3f2a
+//!
+//! # Share a list: print the ticket on one device...
+//! vudo-tasks serve
+//! # ...and sync with it from another (after trusting each other's DIDs)
+//! vudo-tasks sync '<ticket>'
+//! ```
+
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use std::time::Duration;
+use vudo_identity::Did;
+use vudo_tasks::resolver::resolver_from_file;
+use vudo_tasks::{enable_ai_resolution, Result, Task, TaskApp, TaskNode};
+
+#[derive(Parser)]
+#[command(
+    name = "vudo-tasks",
+    version,
+    about = "Collaborative to-do list on VUDO"
+)]
+struct Cli {
+    /// Data directory.
+    #[arg(long, default_value = ".vudo-tasks")]
+    dir: PathBuf,
+
+    /// ONNX embedding model for AI conflict resolution.
+    #[arg(long)]
+    model: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a profile.
+    Init {
+        /// Your name.
+        name: String,
+    },
+    /// Show the DIDs of this device.
+    Whoami,
+    /// Allow a collaborator (master DID) to sync with this device.
+    Trust {
+        /// Master DID of the collaborator.
+        did: String,
+    },
+    /// Add a task.
+    Add {
+        /// Task title.
+        #[arg(required = true)]
+        title: Vec<String>,
+    },
+    /// List tasks.
+    List,
+    /// Mark a task as done.
+    Done {
+        /// Task ID (or unique prefix).
+        id: String,
+    },
+    /// Reopen a done task.
+    Reopen {
+        /// Task ID (or unique prefix).
+        id: String,
+    },
+    /// Rename a task.
+    Rename {
+        /// Task ID (or unique prefix).
+        id: String,
+        /// New title.
+        #[arg(required = true)]
+        title: Vec<String>,
+    },
+    /// Assign a task (unassign without a name).
+    Assign {
+        /// Task ID (or unique prefix).
+        id: String,
+        /// Assignee.
+        who: Option<String>,
+    },
+    /// Remove a task.
+    Remove {
+        /// Task ID (or unique prefix).
+        id: String,
+    },
+    /// Serve the list to peers until interrupted.
+    Serve {
+        /// Seconds between pulls from connected peers.
+        #[arg(long, default_value_t = 5)]
+        interval: u64,
+    },
+    /// Sync with a serving peer.
+    Sync {
+        /// Ticket printed by `serve`.
+        ticket: String,
+        /// Seconds to stay connected so the peer can pull our changes.
+        #[arg(long, default_value_t = 10)]
+        linger: u64,
+    },
+    /// Erase every stored task by deleting the encryption key.
+    Forget,
+}
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    if let Err(e) = run(Cli::parse()).await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    if let Command::Init { name } = &cli.command {
+        let app = TaskApp::init(&cli.dir, name).await?;
+        println!("Created profile in {}", cli.dir.display());
+        print_identity(&app);
+        return Ok(());
+    }
+
+    let mut app = TaskApp::open(&cli.dir).await?;
+    if let Some(model) = &cli.model {
+        enable_ai_resolution(&app, resolver_from_file(model)?);
+    }
+
+    match cli.command {
+        Command::Init { .. } => unreachable!("handled above"),
+        Command::Whoami => print_identity(&app),
+        Command::Trust { did } => {
+            if app.trust(Did::parse(&did)?)? {
+                println!("Trusting {}", did);
+            } else {
+                println!("{} is already trusted", did);
+            }
+        }
+        Command::Add { title } => print_task(&app.add(&title.join(" ")).await?),
+        Command::List => {
+            let tasks = app.list().await?;
+            if tasks.is_empty() {
+                println!("No tasks");
+            }
+            tasks.iter().for_each(print_task);
+        }
+        Command::Done { id } => print_task(&app.complete(&id).await?),
+        Command::Reopen { id } => print_task(&app.reopen(&id).await?),
+        Command::Rename { id, title } => print_task(&app.rename(&id, &title.join(" ")).await?),
+        Command::Assign { id, who } => print_task(&app.assign(&id, who.as_deref()).await?),
+        Command::Remove { id } => {
+            app.remove(&id).await?;
+            println!("Removed {}", id);
+        }
+        Command::Serve { interval } => {
+            let node = TaskNode::start(&app).await?;
+            println!("Serving tasks, sync from another device with:");
+            println!("  vudo-tasks sync '{}'", node.ticket().await?);
+            node.serve(&app, Duration::from_secs(interval)).await?;
+            node.stop().await?;
+        }
+        Command::Sync { ticket, linger } => {
+            let node = TaskNode::start(&app).await?;
+            let peer = node.connect(&app, &ticket).await?;
+            if let Some(auth) = node.p2p().peer_auth(&peer) {
+                println!("Connected to {}", auth.did);
+            }
+            node.pull(&app, &peer).await?;
+            app.list().await?.iter().for_each(print_task);
+
+            tokio::time::sleep(Duration::from_secs(linger)).await;
+            app.save().await?;
+            node.stop().await?;
+        }
+        Command::Forget => {
+            let receipt = app.forget().await?;
+            println!("Erased all tasks (key deleted at {})", receipt.deleted_at);
+        }
+    }
+    Ok(())
+}
+
+fn print_identity(app: &TaskApp) {
+    let profile = app.profile();
+    println!("Master: {}", profile.master.did);
+    println!("Device: {}", profile.device.did());
+    for did in &profile.collaborators {
+        println!("Trusts: {}", did);
+    }
+}
+
+fn print_task(task: &Task) {
+    let check = if task.is_done() { "x" } else { " " };
+    let assignee = task
+        .assignee
+        .as_ref()
+        .map(|a| format!(" (@{})", a))
+        .unwrap_or_default();
+    println!("[{}] {}  {}{}", check, &task.id[..8], task.title, assignee);
+}
@@ -0,0 +1,105 @@
+//! Local identity of a tasks device.
+//!
+//! The profile lives in `profile.json` in the data directory. It holds the
+//! user's master identity and the device linked to it, the data encryption
+//! key protecting the stored tasks, and the master DIDs of collaborators
+//! this device syncs with.
+//!
+//! A real app keeps the master key offline and links devices from there;
+//! the example keeps both on the device so a single `init` gets you going.
+
+use crate::error::{Result, TasksError};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use vudo_identity::{DeviceIdentity, Did, MasterIdentity};
+use vudo_privacy::DataEncryptionKey;
+
+/// File name of the profile in the data directory.
+pub const PROFILE_FILE: &str = "profile.json";
+
+/// Identity, keys and collaborators of a device.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Profile {
+    /// The user's master identity.
+    pub master: MasterIdentity,
+    /// This device, linked to the master identity.
+    pub device: DeviceIdentity,
+    /// Key encrypting the stored tasks (owned by the device DID).
+    pub dek: DataEncryptionKey,
+    /// Master DIDs of collaborators allowed to sync.
+    pub collaborators: Vec<Did>,
+}
+
+impl Profile {
+    /// Create a profile with a new master identity and a linked device.
+    pub async fn generate(name: &str) -> Result<Self> {
+        let mut master = MasterIdentity::generate(name).await?;
+        let mut device = DeviceIdentity::generate(format!("{}'s device", name)).await?;
+        let link = master
+            .link_device(
+                device.device_name().to_string(),
+                device.did().clone(),
+                &master.signing_key(),
+            )
+            .await?;
+        device.link_to_master(master.did.clone(), link.authorization);
+
+        let dek = DataEncryptionKey::generate(device.did().to_string());
+        Ok(Self {
+            master,
+            device,
+            dek,
+            collaborators: Vec::new(),
+        })
+    }
+
+    /// Path of the profile in a data directory.
+    pub fn path(dir: &Path) -> PathBuf {
+        dir.join(PROFILE_FILE)
+    }
+
+    /// Load the profile from a data directory.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = Self::path(dir);
+        if !path.exists() {
+            return Err(TasksError::NotInitialized(dir.display().to_string()));
+        }
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    /// Save the profile to a data directory.
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(Self::path(dir), serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Allow a collaborator to sync with this device.
+    ///
+    /// Returns `false` if the collaborator was already trusted.
+    pub fn trust(&mut self, did: Did) -> bool {
+        if self.collaborators.contains(&did) || did == self.master.did {
+            return false;
+        }
+        self.collaborators.push(did);
+        true
+    }
+
+    /// Masters whose devices may sync with this device (the user's own
+    /// master and every collaborator).
+    pub fn trusted_masters(&self) -> Vec<Did> {
+        std::iter::once(self.master.did.clone())
+            .chain(self.collaborators.iter().cloned())
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Profile")
+            .field("master", &self.master.did)
+            .field("device", &self.device.did)
+            .field("collaborators", &self.collaborators)
+            .finish_non_exhaustive()
+    }
+}
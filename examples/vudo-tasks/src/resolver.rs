@@ -0,0 +1,102 @@
+//! AI conflict resolution for task fields.
+//!
+//! Loads an ONNX embedding model into a [`ConflictResolver`] and registers
+//! it, through [`AiConflictHandler`], as the policy for conflicting titles
+//! and assignees. Without a model, conflicts on those fields keep
+//! Automerge's deterministic winner.
+
+use crate::app::TaskApp;
+use crate::error::Result;
+use std::path::Path;
+use std::sync::Arc;
+use vudo_ai::{
+    AiConflictHandler, ConflictResolver, EmbeddingService, InferenceEngine, ModelId, ModelManager,
+    ModelMetadata, ModelType,
+};
+
+/// ID under which the embedding model is registered.
+pub const EMBEDDING_MODEL: &str = "tasks-embedding";
+
+/// Minimum confidence for the AI to settle a conflict.
+pub const MIN_CONFIDENCE: f32 = 0.6;
+
+/// Create a conflict resolver from ONNX embedding model bytes.
+pub fn resolver_from_bytes(model_bytes: Vec<u8>) -> Result<Arc<ConflictResolver>> {
+    let manager = Arc::new(ModelManager::new());
+    let id = ModelId::new(EMBEDDING_MODEL);
+    manager.register(ModelMetadata {
+        id: id.clone(),
+        name: "Task embedding".to_string(),
+        description: "Embeds task fields for conflict resolution".to_string(),
+        version: "1.0.0".to_string(),
+        input_dims: vec![1, 512],
+        output_dims: vec![1, 384],
+        size_bytes: model_bytes.len(),
+        model_type: ModelType::Embedding,
+        wasm_compatible: true,
+    })?;
+    manager.load(&id, model_bytes)?;
+
+    let embeddings = Arc::new(EmbeddingService::new(Arc::clone(&manager), id));
+    let inference = Arc::new(InferenceEngine::new(manager));
+    Ok(Arc::new(ConflictResolver::new(embeddings, inference)))
+}
+
+/// Create a conflict resolver from an ONNX embedding model file.
+pub fn resolver_from_file(path: impl AsRef<Path>) -> Result<Arc<ConflictResolver>> {
+    resolver_from_bytes(std::fs::read(path)?)
+}
+
+/// Let the AI settle conflicting titles and assignees of an app.
+pub fn enable_ai_resolution(app: &TaskApp, resolver: Arc<ConflictResolver>) {
+    let handler = AiConflictHandler::new(resolver, app.actor()).with_min_confidence(MIN_CONFIDENCE);
+    app.set_conflict_handler(Arc::new(handler));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_ai_settles_title_conflict() {
+        let (alice_dir, bob_dir) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        let alice = TaskApp::init(alice_dir.path(), "Alice").await.unwrap();
+        let bob = TaskApp::init(bob_dir.path(), "Bob").await.unwrap();
+        enable_ai_resolution(&alice, resolver_from_bytes(vec![0u8; 1000]).unwrap());
+
+        let task = alice.add("Groceries").await.unwrap();
+        let id = task.document_id();
+        let bytes = alice.engine().store.get(&id).unwrap().save();
+        bob.engine()
+            .apply_remote_changes(&id, &bytes)
+            .await
+            .unwrap();
+
+        alice.rename(&task.id, "Buy bread").await.unwrap();
+        bob.rename(&task.id, "Get eggs and flour for the cake")
+            .await
+            .unwrap();
+
+        // The more detailed title wins
+        let bytes = bob.engine().store.get(&id).unwrap().save();
+        let report = alice
+            .engine()
+            .apply_remote_changes(&id, &bytes)
+            .await
+            .unwrap();
+        assert_eq!(report.resolved, vec!["title".to_string()]);
+        assert_eq!(
+            alice.get(&task.id).await.unwrap().title,
+            "Get eggs and flour for the cake"
+        );
+        assert!(alice
+            .engine()
+            .store
+            .get(&id)
+            .unwrap()
+            .conflicts()
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+//! Peer-to-peer sync of the task list.
+//!
+//! A [`TaskNode`] runs the P2P layer over the app's state engine. Peers
+//! authenticate in both directions with their device DIDs before any
+//! document is exchanged, and only devices of the user's own master
+//! identity or of a trusted collaborator are accepted (see
+//! [`TaskApp::sync_policy`]).
+//!
+//! Sync is pull-based: a node requests the board from a peer, then every
+//! task listed on it. Changes arriving from the peer go through the
+//! engine's conflict policies before they are saved. Both sides pull from
+//! each other, so a `sync` against a running `serve` converges both lists.
+
+use crate::app::{board_id, TaskApp};
+use crate::error::{Result, TasksError};
+use crate::task::NAMESPACE;
+use iroh::net::NodeAddr;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use vudo_p2p::{P2PConfig, PeerId, SyncAuthPolicy, VudoP2P};
+
+/// Time given to a peer to answer sync requests.
+pub const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+/// How long to wait for a peer to authenticate.
+pub const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl TaskApp {
+    /// Sync policy accepting the user's own devices and those of trusted
+    /// collaborators.
+    pub fn sync_policy(&self) -> SyncAuthPolicy {
+        self.profile()
+            .trusted_masters()
+            .into_iter()
+            .fold(SyncAuthPolicy::new(), SyncAuthPolicy::trust)
+    }
+}
+
+/// A running P2P node serving an app's tasks.
+pub struct TaskNode {
+    /// P2P layer.
+    p2p: VudoP2P,
+    /// Peers this node has authenticated to.
+    introduced: Mutex<HashSet<PeerId>>,
+}
+
+impl TaskNode {
+    /// Start a node for an app.
+    pub async fn start(app: &TaskApp) -> Result<Self> {
+        let config = P2PConfig {
+            node_name: app.profile().device.device_name().to_string(),
+            enable_mdns: true,
+            sync_auth: Some(app.sync_policy()),
+            ..Default::default()
+        };
+        let p2p = VudoP2P::new(app.engine().clone(), config).await?;
+        p2p.start().await?;
+
+        Ok(Self {
+            p2p,
+            introduced: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// The P2P layer of the node.
+    pub fn p2p(&self) -> &VudoP2P {
+        &self.p2p
+    }
+
+    /// Ticket other devices connect with (the node address as JSON).
+    pub async fn ticket(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self.p2p.node_addr().await?)?)
+    }
+
+    /// Connect to the node of a ticket and authenticate in both directions.
+    pub async fn connect(&self, app: &TaskApp, ticket: &str) -> Result<PeerId> {
+        let addr: NodeAddr = serde_json::from_str(ticket)
+            .map_err(|e| TasksError::SyncFailed(format!("Invalid ticket: {}", e)))?;
+        let peer = self.p2p.connect(addr).await?;
+        self.introduce(app, &peer).await?;
+
+        // The peer authenticates back once it sees our token
+        let started = Instant::now();
+        while self.p2p.peer_auth(&peer).is_none() {
+            if started.elapsed() > AUTH_TIMEOUT {
+                return Err(TasksError::SyncFailed(format!(
+                    "Peer {} did not authenticate",
+                    peer
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        Ok(peer)
+    }
+
+    /// Authenticate to a peer as this device (once per peer).
+    pub async fn introduce(&self, app: &TaskApp, peer: &PeerId) -> Result<()> {
+        if self.introduced.lock().unwrap().contains(peer) {
+            return Ok(());
+        }
+        self.p2p.authenticate(peer, &app.profile().device).await?;
+        self.introduced.lock().unwrap().insert(peer.clone());
+        Ok(())
+    }
+
+    /// Pull the board and every listed task from a peer, then save.
+    pub async fn pull(&self, app: &TaskApp, peer: &PeerId) -> Result<()> {
+        let board = board_id();
+        self.p2p
+            .sync_document(peer, &board.namespace, &board.key)
+            .await?;
+        tokio::time::sleep(SETTLE_TIME).await;
+
+        for id in app.task_ids()? {
+            self.p2p.sync_document(peer, NAMESPACE, &id).await?;
+        }
+        tokio::time::sleep(SETTLE_TIME).await;
+
+        app.save().await
+    }
+
+    /// Serve until interrupted, pulling from authenticated peers every
+    /// `interval` and merging local edits made by other processes.
+    pub async fn serve(&self, app: &TaskApp, interval: Duration) -> Result<()> {
+        let mut ticks = tokio::time::interval(interval);
+        loop {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => break,
+                _ = ticks.tick() => {}
+            }
+
+            app.reload().await?;
+            for peer in self.p2p.connected_peers() {
+                if let Err(e) = self.introduce(app, &peer).await {
+                    warn!("Failed to authenticate to {}: {}", peer, e);
+                    continue;
+                }
+                if self.p2p.peer_auth(&peer).is_some() {
+                    if let Err(e) = self.pull(app, &peer).await {
+                        warn!("Failed to pull tasks from {}: {}", peer, e);
+                    }
+                }
+            }
+            app.save().await?;
+        }
+
+        info!("Shutting down");
+        Ok(())
+    }
+
+    /// Stop the node.
+    pub async fn stop(&self) -> Result<()> {
+        Ok(self.p2p.stop().await?)
+    }
+}
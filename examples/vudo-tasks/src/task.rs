@@ -0,0 +1,117 @@
+//! Task documents.
+//!
+//! Every task is its own Automerge document in the [`NAMESPACE`] namespace,
+//! with one root field per attribute. Keeping attributes at the root is what
+//! lets conflict policies act on them: two devices renaming the same task
+//! concurrently leave a conflict on `title` that a policy (or the AI
+//! resolver) settles, while an edit to `title` and one to `assignee` simply
+//! merge.
+//!
+//! The [`BOARD`] document lists the task IDs, so peers can discover tasks
+//! they haven't seen yet by syncing a single document.
+
+use crate::error::{Result, TasksError};
+use automerge::transaction::Transactable;
+use automerge::{AutoCommit, ReadDoc, ScalarValue, Value, ROOT};
+use serde::{Deserialize, Serialize};
+use vudo_state::DocumentId;
+
+/// Namespace of the task and board documents.
+pub const NAMESPACE: &str = "tasks";
+
+/// Key of the board document listing all tasks.
+pub const BOARD: &str = "board";
+
+/// Task title.
+pub const TITLE: &str = "title";
+
+/// Completion time (Unix seconds, `0` while open).
+pub const COMPLETED_AT: &str = "completed_at";
+
+/// Assignee (empty when unassigned).
+pub const ASSIGNEE: &str = "assignee";
+
+/// Creation time (Unix seconds).
+pub const CREATED_AT: &str = "created_at";
+
+/// A to-do item.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Task {
+    /// Task ID (key of the task document).
+    pub id: String,
+    /// Title.
+    pub title: String,
+    /// Completion time (Unix seconds), `None` while open.
+    pub completed_at: Option<u64>,
+    /// Who is working on the task.
+    pub assignee: Option<String>,
+    /// Creation time (Unix seconds).
+    pub created_at: u64,
+}
+
+impl Task {
+    /// Check whether the task is done.
+    pub fn is_done(&self) -> bool {
+        self.completed_at.is_some()
+    }
+
+    /// Document ID of the task.
+    pub fn document_id(&self) -> DocumentId {
+        DocumentId::new(NAMESPACE, &self.id)
+    }
+
+    /// Write every field of the task to its document.
+    pub(crate) fn write(&self, doc: &mut AutoCommit) -> vudo_state::Result<()> {
+        doc.put(ROOT, TITLE, self.title.as_str())?;
+        doc.put(ROOT, COMPLETED_AT, self.completed_at.unwrap_or(0))?;
+        doc.put(ROOT, ASSIGNEE, self.assignee.as_deref().unwrap_or(""))?;
+        doc.put(ROOT, CREATED_AT, self.created_at)?;
+        Ok(())
+    }
+
+    /// Read a task from its document.
+    pub(crate) fn read(id: &str, doc: &AutoCommit) -> Result<Self> {
+        let invalid = |field: &str| TasksError::InvalidTask(id.to_string(), field.to_string());
+
+        let title = str_field(doc, TITLE)?.ok_or_else(|| invalid("missing title"))?;
+        let assignee = str_field(doc, ASSIGNEE)?.filter(|a| !a.is_empty());
+        let completed_at = uint_field(doc, COMPLETED_AT)?.filter(|t| *t > 0);
+        let created_at = uint_field(doc, CREATED_AT)?.unwrap_or(0);
+
+        Ok(Self {
+            id: id.to_string(),
+            title,
+            completed_at,
+            assignee,
+            created_at,
+        })
+    }
+}
+
+/// Read a string field from the document root.
+fn str_field(doc: &AutoCommit, key: &str) -> Result<Option<String>> {
+    match doc.get(ROOT, key).map_err(vudo_state::StateError::from)? {
+        Some((Value::Scalar(s), _)) => match s.as_ref() {
+            ScalarValue::Str(s) => Ok(Some(s.to_string())),
+            _ => Ok(None),
+        },
+        _ => Ok(None),
+    }
+}
+
+/// Read an unsigned integer field from the document root.
+fn uint_field(doc: &AutoCommit, key: &str) -> Result<Option<u64>> {
+    match doc.get(ROOT, key).map_err(vudo_state::StateError::from)? {
+        Some((Value::Scalar(s), _)) => match s.as_ref() {
+            ScalarValue::Uint(n) => Ok(Some(*n)),
+            ScalarValue::Int(n) => Ok(u64::try_from(*n).ok()),
+            _ => Ok(None),
+        },
+        _ => Ok(None),
+    }
+}
+
+/// List the task IDs on a board document.
+pub(crate) fn board_tasks(doc: &AutoCommit) -> Vec<String> {
+    doc.keys(ROOT).collect()
+}
@@ -14,6 +14,12 @@
 //!
 //! # Type-check DOL files
 //! vudo check counter.dol
+//!
+//! # Import records from an existing data source
+//! vudo import users.import.json --archive state.vudo
+//!
+//! # Watch a running node
+//! vudo top --endpoint http://127.0.0.1:7171
 //! ```
 
 use clap::{Parser, Subcommand};
@@ -53,6 +59,12 @@ enum Commands {
 
     /// Start interactive REPL
     Repl(ReplArgs),
+
+    /// Import records from a SQL, REST or CSV source
+    Import(ImportArgs),
+
+    /// Live dashboard of a running node
+    Top(TopArgs),
 }
 
 /// Arguments for the run command
@@ -119,6 +131,38 @@ struct CheckArgs {
     recursive: bool,
 }
 
+/// Arguments for the import command
+#[derive(Parser, Debug)]
+struct ImportArgs {
+    /// Path to the import mapping file
+    #[arg(required = true)]
+    mapping: PathBuf,
+
+    /// State archive to import into (created if missing)
+    #[arg(short, long, default_value = "state.vudo")]
+    archive: PathBuf,
+
+    /// Output the report as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Fail if any conflicts or rejected records are reported
+    #[arg(long)]
+    strict: bool,
+}
+
+/// Arguments for the top command
+#[derive(Parser, Debug)]
+struct TopArgs {
+    /// Control API endpoint of the node
+    #[arg(short, long, default_value = "http://127.0.0.1:7171")]
+    endpoint: String,
+
+    /// Refresh interval in milliseconds
+    #[arg(short, long, default_value = "1000")]
+    interval: u64,
+}
+
 /// Arguments for the repl command
 #[derive(Parser, Debug)]
 struct ReplArgs {
@@ -147,6 +191,8 @@ fn main() -> ExitCode {
         Commands::Compile(args) => cmd_compile(args, cli.verbose, cli.quiet),
         Commands::Check(args) => cmd_check(args, cli.verbose, cli.quiet),
         Commands::Repl(args) => cmd_repl(args, cli.verbose, cli.quiet),
+        Commands::Import(args) => cmd_import(args, cli.verbose, cli.quiet),
+        Commands::Top(args) => cmd_top(args, cli.verbose, cli.quiet),
     };
 
     match result {
@@ -480,6 +526,455 @@ fn check_file(path: &PathBuf) -> Result<(), String> {
     Ok(())
 }
 
+// =============================================================================
+// Import Command
+// =============================================================================
+
+#[cfg(feature = "import")]
+fn cmd_import(args: ImportArgs, verbose: bool, quiet: bool) -> Result<(), String> {
+    use std::sync::Arc;
+    use vudo_import::Importer;
+    use vudo_state::StateEngine;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| format!("Failed to start runtime: {}", e))?;
+
+    let report = runtime.block_on(async {
+        let engine = Arc::new(StateEngine::new().await.map_err(|e| e.to_string())?);
+
+        if args.archive.exists() {
+            let summary = engine
+                .import_archive(&args.archive)
+                .await
+                .map_err(|e| format!("Failed to load archive: {}", e))?;
+            if verbose {
+                eprintln!(
+                    "Loaded {} document(s) from {}",
+                    summary.documents,
+                    args.archive.display()
+                );
+            }
+        }
+
+        let report = Importer::new(Arc::clone(&engine))
+            .import_file(&args.mapping)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        engine
+            .export_archive(&args.archive)
+            .await
+            .map_err(|e| format!("Failed to write archive: {}", e))?;
+
+        Ok::<_, String>(report)
+    })?;
+
+    if args.json {
+        let result = serde_json::json!({
+            "created": report.created,
+            "updated": report.updated,
+            "unchanged": report.unchanged,
+            "conflicts": report.conflicts.iter().map(|c| {
+                serde_json::json!({
+                    "document": c.document_id.to_string(),
+                    "field": c.field,
+                    "local": c.local,
+                    "incoming": c.incoming,
+                    "resolution": c.resolution,
+                })
+            }).collect::<Vec<_>>(),
+            "errors": report.errors.iter().map(|e| {
+                serde_json::json!({
+                    "index": e.index,
+                    "source_id": e.source_id,
+                    "error": e.message,
+                })
+            }).collect::<Vec<_>>()
+        });
+        println!("{}", serde_json::to_string_pretty(&result).unwrap());
+    } else if !quiet {
+        for conflict in &report.conflicts {
+            eprintln!(
+                "{} {}.{}: local {} / source {} ({:?})",
+                "CONFLICT".yellow(),
+                conflict.document_id,
+                conflict.field,
+                conflict.local,
+                conflict.incoming,
+                conflict.resolution
+            );
+        }
+        for error in &report.errors {
+            eprintln!(
+                "{} record {} ({}): {}",
+                "SKIP".red(),
+                error.index,
+                error.source_id.as_deref().unwrap_or("no id"),
+                error.message
+            );
+        }
+        eprintln!(
+            "\n{} created, {} updated, {} unchanged, {} conflict(s), {} error(s)",
+            report.created.to_string().green(),
+            report.updated.to_string().green(),
+            report.unchanged,
+            report.conflicts.len(),
+            report.errors.len()
+        );
+    }
+
+    if args.strict && !report.is_clean() {
+        Err(format!(
+            "{} conflict(s) and {} rejected record(s)",
+            report.conflicts.len(),
+            report.errors.len()
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "import"))]
+fn cmd_import(_args: ImportArgs, _verbose: bool, _quiet: bool) -> Result<(), String> {
+    Err("Import feature not enabled. Rebuild with --features import".to_string())
+}
+
+// =============================================================================
+// Top Command
+// =============================================================================
+
+/// Number of bandwidth samples kept for the graphs.
+#[cfg(feature = "tui")]
+const TOP_HISTORY: usize = 120;
+
+/// Dashboard state between refreshes.
+#[cfg(feature = "tui")]
+#[derive(Default)]
+struct TopState {
+    status: Option<vudo_p2p::NodeStatus>,
+    error: Option<String>,
+    send_history: std::collections::VecDeque<u64>,
+    receive_history: std::collections::VecDeque<u64>,
+}
+
+#[cfg(feature = "tui")]
+impl TopState {
+    fn refresh(&mut self, agent: &ureq::Agent, endpoint: &str) {
+        let url = format!("{}/status", endpoint.trim_end_matches('/'));
+        match agent.get(&url).call() {
+            Ok(response) => match response.into_json::<vudo_p2p::NodeStatus>() {
+                Ok(status) => {
+                    push_sample(&mut self.send_history, status.bandwidth.send_rate);
+                    push_sample(&mut self.receive_history, status.bandwidth.receive_rate);
+                    self.status = Some(status);
+                    self.error = None;
+                }
+                Err(e) => self.error = Some(format!("Invalid status from {}: {}", url, e)),
+            },
+            Err(e) => self.error = Some(format!("{}: {}", url, e)),
+        }
+    }
+}
+
+#[cfg(feature = "tui")]
+fn push_sample(history: &mut std::collections::VecDeque<u64>, sample: u64) {
+    if history.len() == TOP_HISTORY {
+        history.pop_front();
+    }
+    history.push_back(sample);
+}
+
+#[cfg(feature = "tui")]
+fn cmd_top(args: TopArgs, _verbose: bool, _quiet: bool) -> Result<(), String> {
+    use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+    use std::time::{Duration, Instant};
+
+    let interval = Duration::from_millis(args.interval.max(100));
+    let agent = ureq::AgentBuilder::new()
+        .timeout(interval.max(Duration::from_secs(2)))
+        .build();
+
+    let mut state = TopState::default();
+    let mut terminal = ratatui::init();
+    let mut next_refresh = Instant::now();
+
+    let result = loop {
+        if Instant::now() >= next_refresh {
+            state.refresh(&agent, &args.endpoint);
+            next_refresh = Instant::now() + interval;
+        }
+
+        if let Err(e) = terminal.draw(|frame| draw_top(frame, &state, &args.endpoint)) {
+            break Err(format!("Failed to draw: {}", e));
+        }
+
+        let timeout = next_refresh.saturating_duration_since(Instant::now());
+        match event::poll(timeout) {
+            Ok(true) => match event::read() {
+                Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => break Ok(()),
+                    KeyCode::Char('r') => next_refresh = Instant::now(),
+                    _ => {}
+                },
+                Ok(_) => {}
+                Err(e) => break Err(format!("Failed to read input: {}", e)),
+            },
+            Ok(false) => {}
+            Err(e) => break Err(format!("Failed to read input: {}", e)),
+        }
+    };
+
+    ratatui::restore();
+    result
+}
+
+#[cfg(feature = "tui")]
+fn draw_top(frame: &mut ratatui::Frame, state: &TopState, endpoint: &str) {
+    use ratatui::layout::{Constraint, Layout};
+    use ratatui::style::{Color, Modifier, Style};
+    use ratatui::text::{Line, Span};
+    use ratatui::widgets::{Block, List, ListItem, Paragraph, Row, Sparkline, Table};
+
+    let [header, graphs, middle, bottom] = Layout::vertical([
+        Constraint::Length(2),
+        Constraint::Length(7),
+        Constraint::Percentage(50),
+        Constraint::Fill(1),
+    ])
+    .areas(frame.area());
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let default_status = vudo_p2p::NodeStatus::default();
+    let status = state.status.as_ref().unwrap_or(&default_status);
+
+    // Header
+    let mut title = vec![
+        Span::styled("vudo top", bold),
+        Span::raw(format!(
+            "  {} ({})  up {}  {} docs  {} peers",
+            status.node_name,
+            short_id(&status.node_id),
+            format_duration(status.uptime_secs),
+            status.documents.len(),
+            status.peers.len()
+        )),
+    ];
+    let notice = match &state.error {
+        Some(e) => Line::from(Span::styled(e.clone(), Style::default().fg(Color::Red))),
+        None => Line::from(format!("{}  (q quit, r refresh)", endpoint)),
+    };
+    if !status.relays.is_empty() {
+        title.push(Span::raw(format!(
+            "  {}/{} relays healthy",
+            status.relays.iter().filter(|relay| relay.healthy).count(),
+            status.relays.len()
+        )));
+    }
+    if state.status.is_none() && state.error.is_none() {
+        title.push(Span::raw("  connecting..."));
+    }
+    frame.render_widget(Paragraph::new(vec![Line::from(title), notice]), header);
+
+    // Bandwidth graphs and queue depths
+    let [send_area, receive_area, queue_area] = Layout::horizontal([
+        Constraint::Percentage(40),
+        Constraint::Percentage(40),
+        Constraint::Percentage(20),
+    ])
+    .areas(graphs);
+
+    let send: Vec<u64> = state.send_history.iter().copied().collect();
+    frame.render_widget(
+        Sparkline::default()
+            .block(Block::bordered().title(format!(
+                "Send {}/s",
+                format_bytes(status.bandwidth.send_rate)
+            )))
+            .data(&send)
+            .style(Style::default().fg(Color::Green)),
+        send_area,
+    );
+    let receive: Vec<u64> = state.receive_history.iter().copied().collect();
+    frame.render_widget(
+        Sparkline::default()
+            .block(Block::bordered().title(format!(
+                "Receive {}/s",
+                format_bytes(status.bandwidth.receive_rate)
+            )))
+            .data(&receive)
+            .style(Style::default().fg(Color::Cyan)),
+        receive_area,
+    );
+
+    let queues = &status.queues;
+    frame.render_widget(
+        Paragraph::new(vec![
+            Line::from(format!("operations   {}", queues.operations)),
+            Line::from(format!("sync tasks   {}", queues.sync_tasks)),
+            Line::from(format!("file offers  {}", queues.file_offers)),
+            Line::from(format!("transactions {}", queues.transactions)),
+        ])
+        .block(Block::bordered().title("Queues")),
+        queue_area,
+    );
+
+    // Peers and sync sessions
+    let [peer_area, session_area] =
+        Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)]).areas(middle);
+
+    let peer_rows = status.peers.iter().map(|peer| {
+        Row::new(vec![
+            short_id(&peer.peer_id),
+            match (&peer.relay_url, peer.is_direct) {
+                (_, true) => "direct".to_string(),
+                (Some(url), false) => relay_host(url).to_string(),
+                (None, false) => "relay".to_string(),
+            },
+            peer.rtt_ms
+                .map(|rtt| format!("{:.1}ms", rtt))
+                .unwrap_or_else(|| "-".to_string()),
+            format!("{}/s", format_bytes(peer.send_rate)),
+            format!("{}/s", format_bytes(peer.receive_rate)),
+            format!("{:.1}%", peer.loss_rate * 100.0),
+        ])
+    });
+    frame.render_widget(
+        Table::new(
+            peer_rows,
+            [
+                Constraint::Length(12),
+                Constraint::Length(16),
+                Constraint::Length(9),
+                Constraint::Length(11),
+                Constraint::Length(11),
+                Constraint::Length(6),
+            ],
+        )
+        .header(Row::new(vec!["PEER", "LINK", "RTT", "SEND", "RECV", "LOSS"]).style(bold))
+        .block(Block::bordered().title("Peers")),
+        peer_area,
+    );
+
+    let session_rows = status.sessions.iter().map(|session| {
+        Row::new(vec![
+            short_id(&session.peer_id),
+            format!("{}/{}", session.namespace, session.document_id),
+            session.version.to_string(),
+            session.sync_count.to_string(),
+        ])
+    });
+    frame.render_widget(
+        Table::new(
+            session_rows,
+            [
+                Constraint::Length(12),
+                Constraint::Fill(1),
+                Constraint::Length(8),
+                Constraint::Length(6),
+            ],
+        )
+        .header(Row::new(vec!["PEER", "DOCUMENT", "VERSION", "SYNCS"]).style(bold))
+        .block(Block::bordered().title("Sync Sessions")),
+        session_area,
+    );
+
+    // Documents and recent errors
+    let [document_area, error_area] =
+        Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)]).areas(bottom);
+
+    let document_rows = status.documents.iter().map(|doc| {
+        Row::new(vec![
+            format!("{}/{}", doc.namespace, doc.key),
+            format_bytes(doc.size as u64),
+            doc.version.to_string(),
+        ])
+    });
+    frame.render_widget(
+        Table::new(
+            document_rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(10),
+                Constraint::Length(8),
+            ],
+        )
+        .header(Row::new(vec!["DOCUMENT", "SIZE", "VERSION"]).style(bold))
+        .block(Block::bordered().title("Documents")),
+        document_area,
+    );
+
+    let errors: Vec<ListItem> = status
+        .recent_errors
+        .iter()
+        .map(|e| ListItem::new(e.message.clone()).style(Style::default().fg(Color::Red)))
+        .collect();
+    frame.render_widget(
+        List::new(errors).block(Block::bordered().title("Recent Errors")),
+        error_area,
+    );
+}
+
+/// Shorten a node ID for display.
+#[cfg(feature = "tui")]
+fn short_id(id: &str) -> String {
+    if id.chars().count() > 10 {
+        format!("{}…", id.chars().take(9).collect::<String>())
+    } else {
+        id.to_string()
+    }
+}
+
+/// Host of a relay URL, for display.
+#[cfg(feature = "tui")]
+fn relay_host(url: &str) -> &str {
+    let url = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .unwrap_or(url);
+    url.split('/').next().unwrap_or(url)
+}
+
+/// Format a byte count with a binary unit.
+#[cfg(feature = "tui")]
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Format seconds as `1d 02:03:04`.
+#[cfg(feature = "tui")]
+fn format_duration(secs: u64) -> String {
+    let (days, rest) = (secs / 86_400, secs % 86_400);
+    let clock = format!(
+        "{:02}:{:02}:{:02}",
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    );
+    if days > 0 {
+        format!("{}d {}", days, clock)
+    } else {
+        clock
+    }
+}
+
+#[cfg(not(feature = "tui"))]
+fn cmd_top(_args: TopArgs, _verbose: bool, _quiet: bool) -> Result<(), String> {
+    Err("TUI feature not enabled. Rebuild with --features tui".to_string())
+}
+
 // =============================================================================
 // REPL Command
 // =============================================================================
@@ -705,4 +1200,14 @@ mod tests {
         let files = collect_dol_files(&[], false);
         assert!(files.is_empty());
     }
+
+    #[cfg(feature = "tui")]
+    #[test]
+    fn test_top_formatting() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_duration(3_725), "01:02:05");
+        assert_eq!(format_duration(90_000), "1d 01:00:00");
+        assert_eq!(short_id("abcdefghijkl"), "abcdefghi…");
+    }
 }
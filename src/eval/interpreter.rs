@@ -40,6 +40,19 @@ impl Interpreter {
         Self { env }
     }
 
+    /// Binds a value in the global environment.
+    ///
+    /// Later evaluations see the binding, e.g. functions defined in a REPL
+    /// session.
+    pub fn bind(&mut self, name: impl Into<String>, value: Value) {
+        self.env.bind(name, value);
+    }
+
+    /// Returns the global environment (built-ins and bound values).
+    pub fn environment(&self) -> &Environment {
+        &self.env
+    }
+
     /// Evaluates an expression in the current environment.
     pub fn eval(&mut self, expr: &Expr) -> Result<Value, EvalError> {
         self.eval_in_env(expr, &mut self.env.clone())
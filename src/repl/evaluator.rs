@@ -9,7 +9,14 @@
 //! 6. Execute via wasmtime
 //!
 //! For expressions, we wrap them in a temporary function and extract the result.
+//!
+//! Without wasmtime (e.g. in the browser), expressions can instead be run by
+//! the tree-walking [`Interpreter`](crate::eval::Interpreter), see
+//! [`ReplEvaluator::interpret_expression`].
 
+use crate::ast::{Block, Declaration, Expr, Span, Stmt};
+use crate::eval::{Interpreter, Value};
+use crate::parser::Parser;
 use std::collections::HashMap;
 
 /// Result of REPL evaluation.
@@ -329,12 +336,75 @@ pub fun dolReplEval() -> {} {{
         }
     }
 
+    /// Evaluate an expression with the tree-walking interpreter.
+    ///
+    /// Needs neither the WASM toolchain nor wasmtime, so it also runs on
+    /// `wasm32`. Functions and constants from `declarations` are in scope;
+    /// each function sees the declarations defined before it, so recursion
+    /// is not supported.
+    pub fn interpret_expression(
+        &mut self,
+        expr: &str,
+        declarations: &[Declaration],
+    ) -> Result<String, EvalError> {
+        let mut interpreter = Interpreter::new();
+
+        for decl in declarations {
+            match decl {
+                Declaration::Function(func) => {
+                    let value = Value::Function {
+                        params: func.params.iter().map(|p| p.name.clone()).collect(),
+                        body: Box::new(body_block(&func.body, func.span)),
+                        env: interpreter.environment().clone(),
+                    };
+                    interpreter.bind(func.name.clone(), value);
+                }
+                Declaration::Const(constant) => {
+                    let value = interpreter
+                        .eval(&constant.value)
+                        .map_err(|e| EvalError::Runtime(e.to_string()))?;
+                    interpreter.bind(constant.name.clone(), value);
+                }
+                _ => {}
+            }
+        }
+
+        // Parse the expression as the body of a temporary function
+        let wrapper = format!("pub fun dolReplEval() {{\n    {}\n}}\n", expr);
+        let body = match Parser::new(&wrapper).parse() {
+            Ok(Declaration::Function(func)) => body_block(&func.body, func.span),
+            Ok(_) => return Err(EvalError::Parse("expected an expression".to_string())),
+            Err(e) => return Err(EvalError::Parse(e.to_string())),
+        };
+
+        interpreter
+            .eval(&body)
+            .map(|value| value.to_string())
+            .map_err(|e| EvalError::Runtime(e.to_string()))
+    }
+
     /// Clear the WASM cache.
     pub fn clear_cache(&mut self) {
         self.wasm_cache.clear();
     }
 }
 
+/// Turn a function body into a block valued by its trailing expression or
+/// `return`.
+fn body_block(body: &[Stmt], span: Span) -> Expr {
+    let (statements, final_expr) = match body.split_last() {
+        Some((Stmt::Expr(expr) | Stmt::Return(Some(expr)), rest)) => {
+            (rest.to_vec(), Some(Box::new(expr.clone())))
+        }
+        _ => (body.to_vec(), None),
+    };
+    Expr::Block(Block {
+        statements,
+        final_expr,
+        span,
+    })
+}
+
 /// Evaluation error types.
 #[derive(Debug, Clone)]
 pub enum EvalError {
@@ -410,6 +480,36 @@ impl std::fmt::Display for WasmValue {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_interpret_expression() {
+        let decls = crate::parse_file_all(
+            r#"
+const base: i64 = 40
+fun add(a: i64, b: i64) -> i64 { a + b }
+fun answer() -> i64 { return add(base, 2) }
+"#,
+        )
+        .unwrap();
+
+        let mut eval = ReplEvaluator::new();
+        assert_eq!(eval.interpret_expression("1 + 2 * 3", &[]).unwrap(), "7");
+        assert_eq!(eval.interpret_expression("answer()", &decls).unwrap(), "42");
+        assert_eq!(
+            eval.interpret_expression("let x = add(1, 2)\n x * 2", &decls)
+                .unwrap(),
+            "6"
+        );
+
+        assert!(matches!(
+            eval.interpret_expression("1 +", &[]),
+            Err(EvalError::Parse(_))
+        ));
+        assert!(matches!(
+            eval.interpret_expression("missing(1)", &decls),
+            Err(EvalError::Runtime(_))
+        ));
+    }
+
     #[test]
     fn test_evaluator_new() {
         let eval = ReplEvaluator::new();
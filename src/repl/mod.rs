@@ -357,8 +357,37 @@ impl SpiritRepl {
         }
     }
 
+    /// Try to evaluate input as an expression.
+    ///
+    /// Without the WASM runtime, the `playground` feature evaluates the
+    /// expression on the tree-walking interpreter instead, so it also
+    /// works when the REPL itself is compiled to wasm32.
+    #[cfg(all(feature = "playground", not(feature = "wasm")))]
+    fn try_eval_expression(&mut self, input: &str) -> Result<EvalResult, ReplError> {
+        use crate::repl::evaluator::EvalError;
+
+        match self
+            .evaluator
+            .interpret_expression(input, &self.declarations)
+        {
+            Ok(value) => Ok(EvalResult::Expression {
+                input: input.to_string(),
+                value,
+            }),
+            Err(e) => {
+                let repl_err = match e {
+                    EvalError::Parse(msg) => ReplError::Parse(msg),
+                    EvalError::Compile(msg) => ReplError::Wasm(msg),
+                    EvalError::Runtime(msg) => ReplError::Wasm(format!("Runtime: {}", msg)),
+                    EvalError::Feature(msg) => ReplError::Feature(msg),
+                };
+                Err(repl_err)
+            }
+        }
+    }
+
     /// Try to evaluate input as an expression (stub when wasm feature not enabled).
-    #[cfg(not(feature = "wasm"))]
+    #[cfg(not(any(feature = "wasm", feature = "playground")))]
     fn try_eval_expression(&mut self, input: &str) -> Result<EvalResult, ReplError> {
         // Infer the type for display purposes
         let return_type = self.evaluator.infer_expression_type(input);
@@ -1236,7 +1265,18 @@ mod tests {
     }
 
     #[test]
-    #[cfg(not(feature = "wasm"))]
+    #[cfg(all(feature = "playground", not(feature = "wasm")))]
+    fn test_repl_expression_interpreted() {
+        let mut repl = SpiritRepl::new();
+        repl.eval("fun double(x: i64) -> i64 { x * 2 }").unwrap();
+        match repl.eval("double(1 + 2)") {
+            Ok(EvalResult::Expression { value, .. }) => assert_eq!(value, "6"),
+            other => panic!("Expected Expression result with value 6, got {:?}", other),
+        }
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "wasm", feature = "playground")))]
     fn test_repl_expression_requires_wasm() {
         let mut repl = SpiritRepl::new();
         // Without wasm feature, expression evaluation returns Feature error
@@ -112,7 +112,8 @@ pub struct ReplSession {
     /// Session configuration
     config: SessionConfig,
 
-    /// Session start time
+    /// Session start time (`Instant` is unavailable on wasm32-unknown-unknown)
+    #[cfg(not(target_arch = "wasm32"))]
     started_at: std::time::Instant,
 
     /// Total evaluations in this session
@@ -135,6 +136,7 @@ impl ReplSession {
     pub fn with_config(config: SessionConfig) -> Self {
         Self {
             config,
+            #[cfg(not(target_arch = "wasm32"))]
             started_at: std::time::Instant::now(),
             eval_count: 0,
             success_count: 0,
@@ -165,8 +167,17 @@ impl ReplSession {
     }
 
     /// Get session duration.
+    ///
+    /// Always zero on wasm32, where no monotonic clock is available.
     pub fn duration(&self) -> std::time::Duration {
-        self.started_at.elapsed()
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.started_at.elapsed()
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            std::time::Duration::ZERO
+        }
     }
 
     /// Get evaluation count.
@@ -266,6 +277,7 @@ impl ReplSession {
 
         Ok(Self {
             config,
+            #[cfg(not(target_arch = "wasm32"))]
             started_at: std::time::Instant::now(),
             eval_count,
             success_count,
@@ -301,6 +313,7 @@ impl ReplSession {
 
         Ok(Self {
             config,
+            #[cfg(not(target_arch = "wasm32"))]
             started_at: std::time::Instant::now(),
             eval_count,
             success_count,
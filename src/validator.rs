@@ -947,7 +947,26 @@ fn validate_crdt_type_compatibility(
     crdt: &CrdtAnnotation,
     result: &mut ValidationResult,
 ) {
-    let is_compatible = match (&field.type_, &crdt.strategy) {
+    if !is_crdt_compatible(&field.type_, &crdt.strategy) {
+        let type_str = format_type_expr(&field.type_);
+        let suggestion = suggest_valid_strategies(&field.type_);
+
+        result.add_error(ValidationError::IncompatibleCrdtStrategy {
+            field: field.name.clone(),
+            type_: type_str,
+            strategy: format!("{:?}", crdt.strategy),
+            suggestion,
+            span: field.span,
+        });
+    }
+}
+
+/// Returns whether a CRDT strategy can be applied to a field type.
+///
+/// This is the RFC-001 Table 4.1 compatibility matrix used by the validator,
+/// exposed so that tooling can enumerate the valid strategies for a field.
+pub fn is_crdt_compatible(type_: &TypeExpr, strategy: &CrdtStrategy) -> bool {
+    match (type_, strategy) {
         // String strategies (both String and string)
         (TypeExpr::Named(name), CrdtStrategy::Immutable) if is_string_type(name) => true,
         (TypeExpr::Named(name), CrdtStrategy::Lww) if is_string_type(name) => true,
@@ -1014,19 +1033,6 @@ fn validate_crdt_type_compatibility(
 
         // All other combinations are incompatible
         _ => false,
-    };
-
-    if !is_compatible {
-        let type_str = format_type_expr(&field.type_);
-        let suggestion = suggest_valid_strategies(&field.type_);
-
-        result.add_error(ValidationError::IncompatibleCrdtStrategy {
-            field: field.name.clone(),
-            type_: type_str,
-            strategy: format!("{:?}", crdt.strategy),
-            suggestion,
-            span: field.span,
-        });
     }
 }
 
@@ -1077,7 +1083,7 @@ fn is_float_type(name: &str) -> bool {
 }
 
 /// Formats a TypeExpr for display in error messages.
-fn format_type_expr(type_expr: &TypeExpr) -> String {
+pub fn format_type_expr(type_expr: &TypeExpr) -> String {
     match type_expr {
         TypeExpr::Named(name) => name.clone(),
         TypeExpr::Generic { name, args } => {